@@ -0,0 +1,309 @@
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+
+// Every record carries the hash of the one before it, so deleting or
+// editing a record breaks the chain from that point on; `verify_chain`
+// detects it. Stores only ever append.
+
+/// Actions recorded by the services; other actions are free-form strings
+pub mod actions {
+    pub const WEBHOOK_REPLAYED: &str = "webhook.replayed";
+    pub const TENANT_BUDGET_UPDATED: &str = "tenant_budget.updated";
+    pub const KILL_SWITCH_TOGGLED: &str = "kill_switch.toggled";
+    pub const LLM_CALLS_TOGGLED: &str = "llm_calls.toggled";
+    pub const AUDIT_BUNDLE_EXPORTED: &str = "audit_bundle.exported";
+    pub const PROVENANCE_ATTESTED: &str = "provenance.attested";
+    pub const SPEC_DRIFT_DETECTED: &str = "spec_drift.detected";
+    pub const CONFIG_RELOADED: &str = "config.reloaded";
+    pub const PIPELINE_PAUSED: &str = "pipeline.paused";
+    pub const PIPELINE_RESUMED: &str = "pipeline.resumed";
+}
+
+// Hash the first record chains from
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("Audit store I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Audit record serialization failed: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Audit chain broken at record {sequence}: {message}")]
+    BrokenChain { sequence: u64, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, AuditError>;
+
+/// Who did what to which resource, and the resource's state around it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: DateTime<Utc>,
+    pub actor: String,
+    pub action: String,
+    pub resource: String,
+    #[serde(default)]
+    pub before: Option<serde_json::Value>,
+    #[serde(default)]
+    pub after: Option<serde_json::Value>,
+}
+
+impl AuditEvent {
+    pub fn new(actor: &str, action: &str, resource: &str) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            actor: actor.to_string(),
+            action: action.to_string(),
+            resource: resource.to_string(),
+            before: None,
+            after: None,
+        }
+    }
+
+    /// Values that fail to serialize are recorded as their error message
+    pub fn with_before(mut self, before: &impl Serialize) -> Self {
+        self.before = Some(to_value(before));
+        self
+    }
+
+    pub fn with_after(mut self, after: &impl Serialize) -> Self {
+        self.after = Some(to_value(after));
+        self
+    }
+}
+
+fn to_value(value: &impl Serialize) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_else(|e| serde_json::Value::String(format!("<unserializable: {}>", e)))
+}
+
+/// An event as stored, chained to the record before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub sequence: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    pub previous_hash: String,
+    pub hash: String,
+}
+
+fn record_hash(sequence: u64, event: &AuditEvent, previous_hash: &str) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(previous_hash.as_bytes());
+    hasher.update(serde_json::to_vec(&(sequence, event))?);
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Checks that every record follows from the one before it
+pub fn verify_chain(records: &[AuditRecord]) -> Result<()> {
+    let mut previous_hash = GENESIS_HASH.to_string();
+    for (index, record) in records.iter().enumerate() {
+        let broken = |message: &str| AuditError::BrokenChain { sequence: record.sequence, message: message.to_string() };
+        if record.sequence != index as u64 {
+            return Err(broken("records are missing or out of order"));
+        }
+        if record.previous_hash != previous_hash {
+            return Err(broken("previous hash does not match"));
+        }
+        if record.hash != record_hash(record.sequence, &record.event, &record.previous_hash)? {
+            return Err(broken("record was modified"));
+        }
+        previous_hash = record.hash.clone();
+    }
+    Ok(())
+}
+
+#[async_trait]
+pub trait AuditStore: Send + Sync + Debug {
+    async fn append(&self, record: &AuditRecord) -> Result<()>;
+
+    /// All records in the order they were appended
+    async fn records(&self) -> Result<Vec<AuditRecord>>;
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryAuditStore {
+    records: Mutex<Vec<AuditRecord>>,
+}
+
+impl InMemoryAuditStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AuditStore for InMemoryAuditStore {
+    async fn append(&self, record: &AuditRecord) -> Result<()> {
+        self.records.lock().await.push(record.clone());
+        Ok(())
+    }
+
+    async fn records(&self) -> Result<Vec<AuditRecord>> {
+        Ok(self.records.lock().await.clone())
+    }
+}
+
+/// One JSON record per line, opened in append mode. Ship the file to
+/// write-once storage (e.g. S3 Object Lock) for retention.
+#[derive(Debug)]
+pub struct FileAuditStore {
+    path: PathBuf,
+}
+
+impl FileAuditStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+}
+
+#[async_trait]
+impl AuditStore for FileAuditStore {
+    async fn append(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    async fn records(&self) -> Result<Vec<AuditRecord>> {
+        let file = match tokio::fs::File::open(&self.path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut lines = BufReader::new(file).lines();
+        let mut records = Vec::new();
+        while let Some(line) = lines.next_line().await? {
+            if !line.trim().is_empty() {
+                records.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Numbers, chains and appends events; shared by every caller in a process
+#[derive(Debug)]
+pub struct AuditLog {
+    store: Arc<dyn AuditStore>,
+    // Sequence and hash of the last record appended
+    head: Mutex<(u64, String)>,
+}
+
+impl AuditLog {
+    /// Resumes the chain from the last record in the store
+    pub async fn new(store: Arc<dyn AuditStore>) -> Result<Self> {
+        let head = match store.records().await?.last() {
+            Some(last) => (last.sequence + 1, last.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        Ok(Self { store, head: Mutex::new(head) })
+    }
+
+    pub async fn in_memory() -> Result<Self> {
+        Self::new(Arc::new(InMemoryAuditStore::new())).await
+    }
+
+    /// Appends to the file at `path`, or keeps records in memory when None
+    pub async fn open(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::new(Arc::new(FileAuditStore::new(path))).await,
+            None => Self::in_memory().await,
+        }
+    }
+
+    pub async fn record(&self, event: AuditEvent) -> Result<AuditRecord> {
+        let mut head = self.head.lock().await;
+        let (sequence, previous_hash) = head.clone();
+        let hash = record_hash(sequence, &event, &previous_hash)?;
+        let record = AuditRecord { sequence, event, previous_hash, hash };
+
+        self.store.append(&record).await?;
+        *head = (sequence + 1, record.hash.clone());
+        Ok(record)
+    }
+
+    pub async fn records(&self) -> Result<Vec<AuditRecord>> {
+        self.store.records().await
+    }
+
+    pub async fn verify(&self) -> Result<()> {
+        verify_chain(&self.records().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget_event(actor: &str) -> AuditEvent {
+        AuditEvent::new(actor, actions::TENANT_BUDGET_UPDATED, "tenant/acme")
+            .with_before(&serde_json::json!({"daily_usd": 10.0}))
+            .with_after(&serde_json::json!({"daily_usd": 25.0}))
+    }
+
+    #[tokio::test]
+    async fn test_records_are_chained() {
+        let log = AuditLog::in_memory().await.unwrap();
+        let first = log.record(budget_event("admin")).await.unwrap();
+        let second = log.record(AuditEvent::new("ci", actions::WEBHOOK_REPLAYED, "webhook/abc")).await.unwrap();
+
+        assert_eq!(first.sequence, 0);
+        assert_eq!(first.previous_hash, GENESIS_HASH);
+        assert_eq!(second.sequence, 1);
+        assert_eq!(second.previous_hash, first.hash);
+        assert!(log.verify().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tampering_is_detected() {
+        let log = AuditLog::in_memory().await.unwrap();
+        for actor in ["admin", "ci", "admin"] {
+            log.record(budget_event(actor)).await.unwrap();
+        }
+        let records = log.records().await.unwrap();
+
+        let mut edited = records.clone();
+        edited[1].event.actor = "someone-else".to_string();
+        assert!(matches!(verify_chain(&edited), Err(AuditError::BrokenChain { sequence: 1, .. })));
+
+        let mut deleted = records.clone();
+        deleted.remove(1);
+        assert!(matches!(verify_chain(&deleted), Err(AuditError::BrokenChain { sequence: 2, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_file_store_resumes_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+
+        let log = AuditLog::open(Some(&path)).await.unwrap();
+        log.record(budget_event("admin")).await.unwrap();
+        drop(log);
+
+        // A restarted process continues the same chain
+        let log = AuditLog::open(Some(&path)).await.unwrap();
+        let record = log.record(AuditEvent::new("admin", actions::KILL_SWITCH_TOGGLED, "llm")).await.unwrap();
+        assert_eq!(record.sequence, 1);
+
+        let records = log.records().await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].event.after, Some(serde_json::json!({"daily_usd": 25.0})));
+        assert!(verify_chain(&records).is_ok());
+    }
+}
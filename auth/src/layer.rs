@@ -0,0 +1,98 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tonic::body::BoxBody;
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tower::{Layer, Service};
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+use crate::{AuthConfig, Authorizer, Credentials};
+
+/// Tower layer for `tonic::transport::Server::layer` that authenticates and
+/// authorizes every call before it reaches the service
+#[derive(Debug, Clone)]
+pub struct AuthLayer {
+    authorizer: Arc<Authorizer>,
+}
+
+impl AuthLayer {
+    pub fn new(config: AuthConfig) -> Self {
+        Self { authorizer: Arc::new(Authorizer::new(config)) }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService { inner, authorizer: self.authorizer.clone() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    authorizer: Arc<Authorizer>,
+}
+
+impl<S, B> Service<http::Request<B>> for AuthService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let credentials = Credentials {
+            bearer_token: request.headers()
+                .get(http::header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(str::to_string),
+            certificate_uris: peer_spiffe_ids(request.extensions()),
+        };
+
+        match self.authorizer.authorize(request.uri().path(), &credentials) {
+            Ok(principal) => {
+                request.extensions_mut().insert(principal);
+                Box::pin(self.inner.call(request))
+            }
+            Err(e) => {
+                let response = tonic::Status::from(e).to_http();
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+/// URI SANs of the client certificate rustls verified for this connection
+pub fn peer_spiffe_ids(extensions: &http::Extensions) -> Vec<String> {
+    let Some(certs) = extensions
+        .get::<TlsConnectInfo<TcpConnectInfo>>()
+        .and_then(|info| info.peer_certs())
+    else {
+        return Vec::new();
+    };
+
+    // The leaf certificate comes first
+    let Some(Ok((_, cert))) = certs.first().map(|cert| X509Certificate::from_der(cert.get_ref())) else {
+        return Vec::new();
+    };
+    match cert.subject_alternative_name() {
+        Ok(Some(san)) => san.value.general_names.iter()
+            .filter_map(|name| match name {
+                GeneralName::URI(uri) => Some(uri.to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
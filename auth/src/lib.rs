@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+mod layer;
+
+pub use layer::{peer_spiffe_ids, AuthLayer, AuthService};
+
+// Callers authenticate with a client certificate carrying a SPIFFE ID as a
+// URI SAN (mTLS), with a bearer token, or both. The authenticated principal
+// is then checked against per-method rules and attached to the request, so
+// handlers can read it from the request extensions.
+
+/// Env var naming a JSON file holding an `AuthConfig`
+pub const AUTH_CONFIG_ENV: &str = "GRPC_AUTH_CONFIG";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("{0}")]
+    Unauthenticated(String),
+
+    #[error("{principal} may not call {method}")]
+    PermissionDenied { principal: String, method: String },
+
+    #[error("Invalid auth configuration: {0}")]
+    Config(String),
+}
+
+impl From<AuthError> for tonic::Status {
+    fn from(error: AuthError) -> Self {
+        match error {
+            AuthError::Unauthenticated(message) => tonic::Status::unauthenticated(message),
+            AuthError::PermissionDenied { .. } => tonic::Status::permission_denied(error.to_string()),
+            AuthError::Config(message) => tonic::Status::internal(message),
+        }
+    }
+}
+
+/// Server certificate, key and the CA that signs client certificates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TlsSettings {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    pub client_ca_path: PathBuf,
+}
+
+/// Principals allowed to call the methods matching `method`, e.g.
+/// `/proof.v1.ProofService/GenerateProof` or `/proof.v1.ProofService/*`.
+/// Principals are SPIFFE IDs or token names and may end in `*`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MethodRule {
+    pub method: String,
+    pub principals: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+    /// Client certificates must carry a SPIFFE ID in this trust domain
+    #[serde(default)]
+    pub trust_domain: Option<String>,
+    /// Bearer tokens by the principal name they authenticate as
+    #[serde(default)]
+    pub tokens: HashMap<String, String>,
+    /// Authenticated callers may call methods no rule matches
+    #[serde(default)]
+    pub methods: Vec<MethodRule>,
+    /// Methods that skip authentication, such as health checks
+    #[serde(default)]
+    pub public_methods: Vec<String>,
+}
+
+impl AuthConfig {
+    /// Reads `GRPC_AUTH_CONFIG` if set, then applies `GRPC_TLS_CERT`,
+    /// `GRPC_TLS_KEY`, `GRPC_TLS_CLIENT_CA`, `GRPC_SPIFFE_TRUST_DOMAIN` and
+    /// `GRPC_AUTH_TOKENS` (`name=token,...`) on top
+    pub fn from_env() -> Result<Self, AuthError> {
+        let mut config = match std::env::var(AUTH_CONFIG_ENV) {
+            Ok(path) => {
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| AuthError::Config(format!("Failed to read {}: {}", path, e)))?;
+                serde_json::from_str(&contents)
+                    .map_err(|e| AuthError::Config(format!("Failed to parse {}: {}", path, e)))?
+            }
+            Err(_) => Self::default(),
+        };
+
+        let tls_paths = (
+            std::env::var("GRPC_TLS_CERT"),
+            std::env::var("GRPC_TLS_KEY"),
+            std::env::var("GRPC_TLS_CLIENT_CA"),
+        );
+        if let (Ok(cert_path), Ok(key_path), Ok(client_ca_path)) = tls_paths {
+            config.tls = Some(TlsSettings {
+                cert_path: cert_path.into(),
+                key_path: key_path.into(),
+                client_ca_path: client_ca_path.into(),
+            });
+        }
+        if let Ok(trust_domain) = std::env::var("GRPC_SPIFFE_TRUST_DOMAIN") {
+            config.trust_domain = Some(trust_domain);
+        }
+        if let Ok(tokens) = std::env::var("GRPC_AUTH_TOKENS") {
+            config.tokens.extend(parse_tokens(&tokens)?);
+        }
+
+        Ok(config)
+    }
+
+    /// Without TLS or tokens every caller is let through as anonymous
+    pub fn is_enabled(&self) -> bool {
+        self.tls.is_some() || !self.tokens.is_empty()
+    }
+
+    /// TLS requiring client certificates signed by the configured CA;
+    /// certificates are optional when bearer tokens are also accepted
+    pub fn server_tls_config(&self) -> Result<Option<tonic::transport::ServerTlsConfig>, AuthError> {
+        let Some(tls) = &self.tls else {
+            return Ok(None);
+        };
+        let read = |path: &PathBuf| {
+            std::fs::read(path).map_err(|e| AuthError::Config(format!("Failed to read {}: {}", path.display(), e)))
+        };
+
+        let identity = tonic::transport::Identity::from_pem(read(&tls.cert_path)?, read(&tls.key_path)?);
+        Ok(Some(
+            tonic::transport::ServerTlsConfig::new()
+                .identity(identity)
+                .client_ca_root(tonic::transport::Certificate::from_pem(read(&tls.client_ca_path)?))
+                .client_auth_optional(!self.tokens.is_empty()),
+        ))
+    }
+}
+
+fn parse_tokens(value: &str) -> Result<HashMap<String, String>, AuthError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((name, token)) if !name.is_empty() && !token.is_empty() => {
+                Ok((name.to_string(), token.to_string()))
+            }
+            _ => Err(AuthError::Config(format!("Expected name=token in GRPC_AUTH_TOKENS, got {:?}", entry))),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrincipalKind {
+    Certificate,
+    Token,
+    Anonymous,
+}
+
+/// Who made a request; inserted into the request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub id: String,
+    pub kind: PrincipalKind,
+}
+
+impl Principal {
+    pub fn anonymous() -> Self {
+        Self { id: "anonymous".to_string(), kind: PrincipalKind::Anonymous }
+    }
+}
+
+/// What a caller presented
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    pub bearer_token: Option<String>,
+    /// URI SANs of the verified client certificate
+    pub certificate_uris: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Authorizer {
+    config: AuthConfig,
+}
+
+impl Authorizer {
+    pub fn new(config: AuthConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn authorize(&self, method: &str, credentials: &Credentials) -> Result<Principal, AuthError> {
+        if !self.config.is_enabled()
+            || self.config.public_methods.iter().any(|pattern| matches(pattern, method))
+        {
+            return Ok(Principal::anonymous());
+        }
+
+        let principal = self.authenticate(credentials)?;
+        // The most specific rule wins
+        let rule = self.config.methods.iter()
+            .filter(|rule| matches(&rule.method, method))
+            .max_by_key(|rule| specificity(&rule.method));
+        match rule {
+            Some(rule) if !rule.principals.iter().any(|pattern| matches(pattern, &principal.id)) => {
+                Err(AuthError::PermissionDenied { principal: principal.id, method: method.to_string() })
+            }
+            _ => Ok(principal),
+        }
+    }
+
+    fn authenticate(&self, credentials: &Credentials) -> Result<Principal, AuthError> {
+        let spiffe_id = credentials.certificate_uris.iter().find(|uri| self.in_trust_domain(uri));
+        if let Some(spiffe_id) = spiffe_id {
+            return Ok(Principal { id: spiffe_id.clone(), kind: PrincipalKind::Certificate });
+        }
+
+        if let Some(presented) = &credentials.bearer_token {
+            // Compare against every token so timing does not reveal which matched
+            let mut matched = None;
+            for (name, token) in &self.config.tokens {
+                if constant_time_eq(presented.as_bytes(), token.as_bytes()) {
+                    matched = Some(name);
+                }
+            }
+            return match matched {
+                Some(name) => Ok(Principal { id: name.clone(), kind: PrincipalKind::Token }),
+                None => Err(AuthError::Unauthenticated("Invalid bearer token".to_string())),
+            };
+        }
+
+        if !credentials.certificate_uris.is_empty() {
+            return Err(AuthError::Unauthenticated(match &self.config.trust_domain {
+                Some(trust_domain) => format!("Client certificate has no SPIFFE ID in trust domain {}", trust_domain),
+                None => "Client certificate has no SPIFFE ID".to_string(),
+            }));
+        }
+        Err(AuthError::Unauthenticated("A client certificate or bearer token is required".to_string()))
+    }
+
+    fn in_trust_domain(&self, uri: &str) -> bool {
+        let Some(rest) = uri.strip_prefix("spiffe://") else {
+            return false;
+        };
+        match &self.config.trust_domain {
+            Some(trust_domain) => rest.split('/').next() == Some(trust_domain.as_str()),
+            None => true,
+        }
+    }
+}
+
+// `*` matches everything and a trailing `*` matches by prefix
+fn matches(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    }
+}
+
+// Exact patterns beat any wildcard; longer prefixes beat shorter ones
+fn specificity(pattern: &str) -> (bool, usize) {
+    (!pattern.ends_with('*'), pattern.len())
+}
+
+/// Compares secrets without leaking through timing where they first differ
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENERATE: &str = "/proof.v1.ProofService/GenerateProof";
+
+    fn config() -> AuthConfig {
+        AuthConfig {
+            tls: Some(TlsSettings {
+                cert_path: "server.pem".into(),
+                key_path: "server.key".into(),
+                client_ca_path: "ca.pem".into(),
+            }),
+            trust_domain: Some("spec-to-proof.internal".to_string()),
+            tokens: HashMap::from([("ci".to_string(), "ci-token".to_string())]),
+            methods: vec![
+                MethodRule {
+                    method: "/proof.v1.ProofService/*".to_string(),
+                    principals: vec!["spiffe://spec-to-proof.internal/ns/platform/*".to_string(), "ci".to_string()],
+                },
+                MethodRule {
+                    method: GENERATE.to_string(),
+                    principals: vec!["spiffe://spec-to-proof.internal/ns/platform/sa/nlp".to_string()],
+                },
+            ],
+            public_methods: vec!["/grpc.health.v1.Health/*".to_string()],
+        }
+    }
+
+    fn certificate(uri: &str) -> Credentials {
+        Credentials { certificate_uris: vec![uri.to_string()], ..Default::default() }
+    }
+
+    fn token(token: &str) -> Credentials {
+        Credentials { bearer_token: Some(token.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_disabled_lets_everyone_through() {
+        let authorizer = Authorizer::new(AuthConfig::default());
+        assert_eq!(authorizer.authorize(GENERATE, &Credentials::default()).unwrap(), Principal::anonymous());
+    }
+
+    #[test]
+    fn test_certificate_principals() {
+        let authorizer = Authorizer::new(config());
+
+        let nlp = authorizer.authorize(GENERATE, &certificate("spiffe://spec-to-proof.internal/ns/platform/sa/nlp")).unwrap();
+        assert_eq!(nlp.kind, PrincipalKind::Certificate);
+
+        // The service-wide rule admits gh-app, the method rule does not
+        let gh_app = certificate("spiffe://spec-to-proof.internal/ns/platform/sa/gh-app");
+        assert!(authorizer.authorize("/proof.v1.ProofService/GetProof", &gh_app).is_ok());
+        assert!(matches!(
+            authorizer.authorize(GENERATE, &gh_app),
+            Err(AuthError::PermissionDenied { .. })
+        ));
+
+        let foreign = certificate("spiffe://elsewhere.example/ns/platform/sa/nlp");
+        assert!(matches!(authorizer.authorize(GENERATE, &foreign), Err(AuthError::Unauthenticated(_))));
+    }
+
+    #[test]
+    fn test_token_principals() {
+        let authorizer = Authorizer::new(config());
+
+        let ci = authorizer.authorize("/proof.v1.ProofService/GetProof", &token("ci-token")).unwrap();
+        assert_eq!(ci, Principal { id: "ci".to_string(), kind: PrincipalKind::Token });
+        assert!(matches!(authorizer.authorize(GENERATE, &token("ci-token")), Err(AuthError::PermissionDenied { .. })));
+        assert!(matches!(authorizer.authorize(GENERATE, &token("wrong")), Err(AuthError::Unauthenticated(_))));
+
+        // Methods without a rule are open to any authenticated caller
+        assert!(authorizer.authorize("/nlp.v1.NlpService/ExtractInvariants", &token("ci-token")).is_ok());
+        assert!(authorizer.authorize("/nlp.v1.NlpService/ExtractInvariants", &Credentials::default()).is_err());
+        assert!(authorizer.authorize("/grpc.health.v1.Health/Check", &Credentials::default()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_tokens() {
+        let tokens = parse_tokens("ci=abc, gh-app=def=ghi").unwrap();
+        assert_eq!(tokens["ci"], "abc");
+        assert_eq!(tokens["gh-app"], "def=ghi");
+        assert!(parse_tokens("missing-token").is_err());
+    }
+}
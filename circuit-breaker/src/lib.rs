@@ -0,0 +1,444 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+// Closed: calls go through and their outcomes are kept for `window`. Once
+// at least `minimum_calls` outcomes are known and the failure rate reaches
+// the threshold, the breaker opens.
+// Open: calls fail immediately until `cooldown` has passed.
+// Half-open: up to `half_open_max_calls` trial calls go through. If they
+// all succeed the breaker closes; any failure opens it again.
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Fraction of failed calls in `window` that opens the breaker
+    pub failure_rate_threshold: f64,
+    /// Calls in `window` needed before the failure rate is trusted
+    pub minimum_calls: u32,
+    pub window: Duration,
+    pub cooldown: Duration,
+    pub half_open_max_calls: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_rate_threshold: 0.5,
+            minimum_calls: 5,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(30),
+            half_open_max_calls: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Circuit breaker for {name} is open; retry in {}s", retry_after.as_secs())]
+pub struct CircuitOpenError {
+    pub name: String,
+    pub retry_after: Duration,
+}
+
+impl From<CircuitOpenError> for spec_to_proof_error::Error {
+    fn from(error: CircuitOpenError) -> Self {
+        let retry_after = error.retry_after;
+        spec_to_proof_error::Error::transient(error).with_retry_after(retry_after)
+    }
+}
+
+/// A breaker's state at one moment, for metrics and health checks
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitSnapshot {
+    pub name: String,
+    pub state: CircuitState,
+    pub failure_rate: f64,
+    pub calls_in_window: u32,
+    pub rejected_total: u64,
+    pub opened_total: u64,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    // Outcomes of recent calls, true for failures
+    outcomes: VecDeque<(Instant, bool)>,
+    opened_at: Option<Instant>,
+    half_open_since: Option<Instant>,
+    trial_calls: u32,
+    trial_successes: u32,
+    rejected_total: u64,
+    opened_total: u64,
+}
+
+impl Inner {
+    fn prune(&mut self, now: Instant, window: Duration) {
+        while let Some(&(at, _)) = self.outcomes.front() {
+            if now.duration_since(at) > window {
+                self.outcomes.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn failure_rate(&self) -> f64 {
+        if self.outcomes.is_empty() {
+            return 0.0;
+        }
+        self.outcomes.iter().filter(|(_, failed)| *failed).count() as f64 / self.outcomes.len() as f64
+    }
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: String,
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &str, config: CircuitBreakerConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                outcomes: VecDeque::new(),
+                opened_at: None,
+                half_open_since: None,
+                trial_calls: 0,
+                trial_successes: 0,
+                rejected_total: 0,
+                opened_total: 0,
+            }),
+        }
+    }
+
+    /// The process-wide breaker for `name`, created with the default
+    /// config on first use, so every client of one API trips together
+    pub fn shared(name: &str) -> Arc<Self> {
+        registry()
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(Self::new(name, CircuitBreakerConfig::default())))
+            .clone()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.snapshot().state
+    }
+
+    /// Admits a call, or fails fast while the breaker is open. Every admitted
+    /// call must be followed by `record`.
+    pub fn try_acquire(&self) -> Result<(), CircuitOpenError> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+
+        if inner.state == CircuitState::Open {
+            let opened_at = inner.opened_at.unwrap_or(now);
+            if now.duration_since(opened_at) < self.config.cooldown {
+                inner.rejected_total += 1;
+                return Err(CircuitOpenError {
+                    name: self.name.clone(),
+                    retry_after: self.config.cooldown - now.duration_since(opened_at),
+                });
+            }
+            info!("Circuit breaker for {} is half-open, allowing trial calls", self.name);
+            inner.state = CircuitState::HalfOpen;
+            inner.half_open_since = Some(now);
+            inner.trial_calls = 0;
+            inner.trial_successes = 0;
+        }
+
+        if inner.state == CircuitState::HalfOpen {
+            // Trial calls that never reported back would otherwise keep the
+            // breaker half-open forever
+            if inner.half_open_since.is_some_and(|since| now.duration_since(since) >= self.config.cooldown) {
+                inner.half_open_since = Some(now);
+                inner.trial_calls = 0;
+            }
+            if inner.trial_calls >= self.config.half_open_max_calls {
+                inner.rejected_total += 1;
+                return Err(CircuitOpenError { name: self.name.clone(), retry_after: self.config.cooldown });
+            }
+            inner.trial_calls += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn record_success(&self) {
+        self.record(false);
+    }
+
+    pub fn record_failure(&self) {
+        self.record(true);
+    }
+
+    pub fn record(&self, failed: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+
+        match inner.state {
+            CircuitState::HalfOpen if failed => self.open(&mut inner, now),
+            CircuitState::HalfOpen => {
+                inner.trial_successes += 1;
+                if inner.trial_successes >= self.config.half_open_max_calls {
+                    info!("Circuit breaker for {} closed", self.name);
+                    inner.state = CircuitState::Closed;
+                    inner.outcomes.clear();
+                }
+            }
+            CircuitState::Closed => {
+                inner.outcomes.push_back((now, failed));
+                inner.prune(now, self.config.window);
+                if inner.outcomes.len() >= self.config.minimum_calls as usize
+                    && inner.failure_rate() >= self.config.failure_rate_threshold
+                {
+                    self.open(&mut inner, now);
+                }
+            }
+            // A call admitted before the breaker opened
+            CircuitState::Open => {}
+        }
+    }
+
+    fn open(&self, inner: &mut Inner, now: Instant) {
+        warn!(
+            "Circuit breaker for {} opened after a {:.0}% failure rate; failing calls for {:?}",
+            self.name,
+            inner.failure_rate() * 100.0,
+            self.config.cooldown
+        );
+        inner.state = CircuitState::Open;
+        inner.opened_at = Some(now);
+        inner.opened_total += 1;
+        inner.outcomes.clear();
+    }
+
+    /// Runs `call` through the breaker, counting any error as a failure
+    pub async fn call<T, E, F>(&self, call: F) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: From<CircuitOpenError>,
+    {
+        self.try_acquire()?;
+        let result = call.await;
+        self.record(result.is_err());
+        result
+    }
+
+    /// Runs an HTTP request through the breaker, failing fast while it is
+    /// open rather than adding load to a service that is already failing.
+    /// Transport errors and responses whose `status` is a service failure
+    /// count as failures; other error statuses are the caller's to handle.
+    pub async fn call_http<R, E, F>(&self, request: F, status: impl FnOnce(&R) -> u16) -> Result<R, E>
+    where
+        F: Future<Output = Result<R, E>>,
+        E: From<CircuitOpenError>,
+    {
+        self.try_acquire()?;
+        let result = request.await;
+        self.record(result.as_ref().map_or(true, |response| is_failure_status(status(response))));
+        result
+    }
+
+    pub fn snapshot(&self) -> CircuitSnapshot {
+        let mut inner = self.inner.lock().unwrap();
+        inner.prune(Instant::now(), self.config.window);
+        CircuitSnapshot {
+            name: self.name.clone(),
+            state: inner.state,
+            failure_rate: inner.failure_rate(),
+            calls_in_window: inner.outcomes.len() as u32,
+            rejected_total: inner.rejected_total,
+            opened_total: inner.opened_total,
+        }
+    }
+
+    /// Readiness check that fails while the breaker is open
+    pub fn health_check(&self) -> Result<(), String> {
+        match self.state() {
+            CircuitState::Open => Err(format!("Circuit breaker for {} is open", self.name)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn registry() -> &'static Mutex<HashMap<String, Arc<CircuitBreaker>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Arc<CircuitBreaker>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Snapshots of every shared breaker, sorted by name
+pub fn snapshots() -> Vec<CircuitSnapshot> {
+    let mut snapshots: Vec<CircuitSnapshot> = registry()
+        .lock()
+        .unwrap()
+        .values()
+        .map(|breaker| breaker.snapshot())
+        .collect();
+    snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+    snapshots
+}
+
+/// Whether an HTTP status means the service, rather than the request, is
+/// at fault
+pub fn is_failure_status(status: u16) -> bool {
+    status == 429 || status >= 500
+}
+
+/// Prometheus text exposition of `snapshots`; state is 0 closed, 1
+/// half-open, 2 open
+pub fn render_metrics(snapshots: &[CircuitSnapshot]) -> String {
+    let mut output = String::new();
+    for snapshot in snapshots {
+        let state = match snapshot.state {
+            CircuitState::Closed => 0,
+            CircuitState::HalfOpen => 1,
+            CircuitState::Open => 2,
+        };
+        output.push_str(&format!("spec_to_proof_circuit_breaker_state{{name=\"{}\"}} {}\n", snapshot.name, state));
+        output.push_str(&format!(
+            "spec_to_proof_circuit_breaker_failure_rate{{name=\"{}\"}} {}\n",
+            snapshot.name, snapshot.failure_rate
+        ));
+        output.push_str(&format!(
+            "spec_to_proof_circuit_breaker_rejected_total{{name=\"{}\"}} {}\n",
+            snapshot.name, snapshot.rejected_total
+        ));
+        output.push_str(&format!(
+            "spec_to_proof_circuit_breaker_opened_total{{name=\"{}\"}} {}\n",
+            snapshot.name, snapshot.opened_total
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker::new("test", CircuitBreakerConfig {
+            failure_rate_threshold: 0.5,
+            minimum_calls: 4,
+            window: Duration::from_secs(60),
+            cooldown,
+            half_open_max_calls: 1,
+        })
+    }
+
+    #[test]
+    fn test_opens_on_failure_rate() {
+        let breaker = breaker(Duration::from_secs(60));
+        for failed in [false, true, false] {
+            breaker.try_acquire().unwrap();
+            breaker.record(failed);
+        }
+        // Too few calls to judge yet
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        breaker.try_acquire().unwrap();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let err = breaker.try_acquire().unwrap_err();
+        assert_eq!(err.name, "test");
+        assert!(err.retry_after > Duration::from_secs(50));
+        let snapshot = breaker.snapshot();
+        assert_eq!((snapshot.rejected_total, snapshot.opened_total), (1, 1));
+        assert!(breaker.health_check().is_err());
+    }
+
+    #[test]
+    fn test_half_open_trial() {
+        let breaker = breaker(Duration::from_millis(20));
+        for _ in 0..4 {
+            breaker.try_acquire().unwrap();
+            breaker.record_failure();
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(30));
+        breaker.try_acquire().unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        // Only one trial call at a time
+        assert!(breaker.try_acquire().is_err());
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(30));
+        breaker.try_acquire().unwrap();
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.snapshot().opened_total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_call_http_counts_service_failures() {
+        let breaker = breaker(Duration::from_secs(60));
+        for status in [404u16, 503, 429] {
+            let response = breaker
+                .call_http(async { Ok::<_, CircuitOpenError>(status) }, |status| *status)
+                .await;
+            assert_eq!(response.unwrap(), status);
+        }
+        // A client error doesn't count against the service
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        let response = breaker
+            .call_http(async { Ok::<_, CircuitOpenError>(500u16) }, |status| *status)
+            .await;
+        assert!(response.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let rejected = breaker
+            .call_http(async { Ok::<_, CircuitOpenError>(200u16) }, |status| *status)
+            .await;
+        assert!(rejected.is_err());
+    }
+
+    #[test]
+    fn test_shared_breakers_and_metrics() {
+        let a = CircuitBreaker::shared("metrics-test");
+        let b = CircuitBreaker::shared("metrics-test");
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let snapshots: Vec<CircuitSnapshot> = snapshots().into_iter().filter(|s| s.name == "metrics-test").collect();
+        assert_eq!(snapshots.len(), 1);
+        assert!(render_metrics(&snapshots).contains("spec_to_proof_circuit_breaker_state{name=\"metrics-test\"} 0\n"));
+
+        assert!(is_failure_status(503));
+        assert!(is_failure_status(429));
+        assert!(!is_failure_status(404));
+    }
+}
@@ -0,0 +1,185 @@
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use nlp::proto::nlp::v1 as nlp_v1;
+use proof::proto::proof::v1::{PlannedAction, ProofRunPlan};
+use proof::proto::spec_to_proof::v1::{
+    Invariant, InvariantClassification, InvariantSet, InvariantSetStatus, SourceSpan, Variable,
+};
+
+/// The proof service's view of a stored extraction. Status and priority
+/// share their enum values across the two protos, so they carry over as is.
+pub fn to_invariant(stored: &nlp_v1::StoredInvariant) -> Invariant {
+    let extracted = stored.invariant.clone().unwrap_or_default();
+
+    Invariant {
+        id: stored.id.clone(),
+        content_sha256: sha256_hex(&extracted.formal_expression),
+        description: extracted.description,
+        formal_expression: extracted.formal_expression,
+        natural_language: extracted.natural_language,
+        variables: extracted
+            .variables
+            .into_iter()
+            .map(|variable| Variable {
+                name: variable.name,
+                var_type: variable.type_,
+                description: variable.description,
+                unit: variable.unit,
+                constraints: variable.constraints,
+            })
+            .collect(),
+        units: extracted.units,
+        confidence_score: extracted.confidence_score,
+        source_document_id: stored.document_id.clone(),
+        extracted_at: stored.extracted_at.clone(),
+        status: stored.status,
+        tags: extracted.tags,
+        priority: extracted.priority,
+        source_span: extracted.source_span.map(|span| SourceSpan {
+            quote: span.quote,
+            start_offset: span.start_offset,
+            end_offset: span.end_offset,
+            verified: span.verified,
+        }),
+        classification: extracted.classification.map(|classification| InvariantClassification {
+            category: classification.category,
+            secondary_categories: classification.secondary_categories,
+            proof_strategy: classification.proof_strategy,
+            confidence: classification.confidence,
+        }),
+    }
+}
+
+/// One set per document, ordered by invariant ID so the set hash only
+/// changes when its invariants do
+pub fn invariant_set(document_id: &str, stored: &[nlp_v1::StoredInvariant]) -> InvariantSet {
+    let mut invariants: Vec<Invariant> = stored.iter().map(to_invariant).collect();
+    invariants.sort_by(|a, b| a.id.cmp(&b.id));
+    let content = invariants
+        .iter()
+        .map(|invariant| invariant.content_sha256.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let now = prost_types::Timestamp::from(std::time::SystemTime::now());
+
+    InvariantSet {
+        id: format!("set_{}", document_id),
+        content_sha256: sha256_hex(&content),
+        name: document_id.to_string(),
+        description: format!("Invariants extracted from {}", document_id),
+        invariants,
+        source_document_ids: vec![document_id.to_string()],
+        created_at: Some(now.clone()),
+        modified_at: Some(now),
+        status: InvariantSetStatus::Draft as i32,
+    }
+}
+
+/// JSON for `plan`; the proof protos have no serde support
+pub fn proof_plan_json(plan: &ProofRunPlan) -> Value {
+    let tokens = |usage: Option<&proof::proto::proof::v1::TokenUsage>| {
+        let usage = usage.cloned().unwrap_or_default();
+        json!({
+            "input_tokens": usage.input_tokens,
+            "output_tokens": usage.output_tokens,
+            "total_tokens": usage.total_tokens,
+        })
+    };
+    let resources = plan.lean_resources.clone().unwrap_or_default();
+
+    json!({
+        "invariants": plan.invariants.iter().map(|invariant| json!({
+            "invariant_id": invariant.invariant_id,
+            "action": PlannedAction::try_from(invariant.action)
+                .unwrap_or(PlannedAction::Unspecified)
+                .as_str_name(),
+            "reason": invariant.reason,
+            "backend": invariant.backend,
+            "model": invariant.model,
+            "theorem_cached": invariant.theorem_cached,
+            "estimated_token_usage": tokens(invariant.estimated_token_usage.as_ref()),
+            "estimated_cost_usd": invariant.estimated_cost_usd,
+        })).collect::<Vec<_>>(),
+        "estimated_token_usage": tokens(plan.estimated_token_usage.as_ref()),
+        "estimated_cost_usd": plan.estimated_cost_usd,
+        "max_cost_usd": plan.max_cost_usd,
+        "lean_resources": {
+            "lean_toolchain": resources.lean_toolchain,
+            "mathlib_commit": resources.mathlib_commit,
+            "theorem_count": resources.theorem_count,
+            "max_attempts": resources.max_attempts,
+            "max_duration_seconds": resources.max_duration_seconds,
+        },
+    })
+}
+
+pub fn sha256_hex(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(id: &str, expression: &str) -> nlp_v1::StoredInvariant {
+        nlp_v1::StoredInvariant {
+            id: id.to_string(),
+            document_id: "refunds.md".to_string(),
+            invariant: Some(nlp_v1::ExtractedInvariant {
+                description: "Refunds never exceed the charge".to_string(),
+                formal_expression: expression.to_string(),
+                variables: vec![nlp_v1::Variable {
+                    name: "refund".to_string(),
+                    type_: "int".to_string(),
+                    ..Default::default()
+                }],
+                priority: 3,
+                ..Default::default()
+            }),
+            status: 2,
+            extracted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_to_invariant_carries_fields_over() {
+        let invariant = to_invariant(&stored("inv_1", "refund <= charge"));
+
+        assert_eq!(invariant.id, "inv_1");
+        assert_eq!(invariant.source_document_id, "refunds.md");
+        assert_eq!(invariant.content_sha256, sha256_hex("refund <= charge"));
+        assert_eq!(invariant.variables[0].var_type, "int");
+        assert_eq!((invariant.status, invariant.priority), (2, 3));
+    }
+
+    #[test]
+    fn test_proof_plan_json_names_actions() {
+        let plan = ProofRunPlan {
+            invariants: vec![proof::proto::proof::v1::InvariantPlan {
+                invariant_id: "inv_1".to_string(),
+                action: PlannedAction::Skip as i32,
+                theorem_cached: true,
+                ..Default::default()
+            }],
+            max_cost_usd: 0.5,
+            ..Default::default()
+        };
+        let json = proof_plan_json(&plan);
+
+        assert_eq!(json["invariants"][0]["action"], "PLANNED_ACTION_SKIP");
+        assert_eq!(json["invariants"][0]["theorem_cached"], true);
+        assert_eq!(json["max_cost_usd"], 0.5);
+        assert_eq!(json["lean_resources"]["theorem_count"], 0);
+    }
+
+    #[test]
+    fn test_invariant_set_is_ordered_and_hashed_by_content() {
+        let first = invariant_set("refunds.md", &[stored("inv_b", "b > 0"), stored("inv_a", "a > 0")]);
+        let second = invariant_set("refunds.md", &[stored("inv_a", "a > 0"), stored("inv_b", "b > 0")]);
+
+        assert_eq!(first.invariants[0].id, "inv_a");
+        assert_eq!(first.content_sha256, second.content_sha256);
+        assert_ne!(first.content_sha256, invariant_set("refunds.md", &[stored("inv_a", "a > 1")]).content_sha256);
+    }
+}
@@ -0,0 +1,695 @@
+mod convert;
+mod verify;
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use clap::{Parser, Subcommand};
+use tracing::{info, warn};
+
+use nlp::pipeline::ExtractionPipeline;
+use nlp::proto::nlp::v1::{
+    BackfillRequest, BackfillResponse, DocumentBackfill, ExtractInvariantsRequest, ExtractedInvariant, StoredDocument,
+    StoredInvariant,
+};
+use nlp::{backfill as nlp_backfill, drift, invariant_diff, language, persistence as nlp_persistence, prompts, InvariantExtractionConfig};
+use proof::compiler::LeanCompiler;
+use proof::persistence as proof_persistence;
+use proof::testgen;
+use proof::plan::ProofPlanner;
+use proof::proto::proof::v1::{CompilationOptions, ProofOptions, TestFramework};
+use proof::proto::spec_to_proof::v1::{LeanTheorem, ProofArtifact, ProofStatus};
+use proof::workspace::WorkspaceBuilder;
+use proof::ProofConfig;
+use storage::{Entity, EntityQuery, EntityStore, Repository, StorageBackend, StorageSettings};
+
+use crate::verify::Verified;
+
+// spec_to_proof.v1.InvariantStatus values reviewers use to take an
+// invariant out of the proof run
+const INVARIANT_STATUS_REJECTED: i32 = 3;
+
+const QUERY_PAGE_SIZE: u32 = 100;
+
+/// Run spec-to-proof locally: extract invariants from a spec, compile them
+/// to Lean theorems, prove them and verify the resulting artifacts. State is
+/// kept under --data-dir instead of DynamoDB or Postgres.
+#[derive(Parser)]
+#[command(name = "spec2proof", author, version, about, long_about = None)]
+struct Cli {
+    /// Directory holding invariants, theorems and proof artifacts
+    #[arg(long, global = true, env = "SPEC2PROOF_DATA_DIR", default_value = ".spec2proof")]
+    data_dir: PathBuf,
+
+    /// Log level; logs go to stderr so stdout stays machine-readable
+    #[arg(long, global = true, default_value = "warn")]
+    log_level: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Extract invariants from a Markdown spec and store them
+    Extract {
+        /// Spec document to read
+        file: PathBuf,
+
+        /// Document ID the invariants are stored under; defaults to the
+        /// file name
+        #[arg(long)]
+        document_id: Option<String>,
+
+        /// Write the invariants JSON here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Re-extract stored documents with the current prompts and report how
+    /// the invariants would change, without storing them unless --apply
+    Backfill {
+        /// Source system whose documents are re-extracted
+        #[arg(long, default_value = "local")]
+        source: String,
+
+        /// Only these documents; repeatable
+        #[arg(long = "document-id")]
+        document_ids: Vec<String>,
+
+        /// Only documents extracted at or after this time (RFC 3339 or
+        /// YYYY-MM-DD)
+        #[arg(long, value_parser = parse_time)]
+        since: Option<prost_types::Timestamp>,
+
+        /// Only documents extracted before this time
+        #[arg(long, value_parser = parse_time)]
+        until: Option<prost_types::Timestamp>,
+
+        /// Store the new invariants of documents whose invariants changed
+        #[arg(long)]
+        apply: bool,
+
+        #[arg(long, default_value = "0")]
+        max_documents: u32,
+
+        /// Write the report JSON here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Compile a document's stored invariants into Lean theorems
+    Compile {
+        document_id: String,
+
+        /// Directory the .lean files are written to; defaults to
+        /// <data-dir>/lean/<document-id>
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        #[arg(long, default_value = "auto")]
+        proof_strategy: String,
+    },
+
+    /// Generate proofs for a document's theorems and check them with a
+    /// local `lake build`
+    Prove {
+        document_id: String,
+
+        /// Lake project directory; defaults to
+        /// <data-dir>/workspaces/<document-id>
+        #[arg(long)]
+        workspace: Option<PathBuf>,
+
+        /// Store generated proofs without running Lean
+        #[arg(long)]
+        skip_build: bool,
+
+        #[arg(long, default_value = "auto")]
+        proof_strategy: String,
+
+        #[arg(long, default_value = "3")]
+        max_attempts: u32,
+    },
+
+    /// Estimate what extracting a spec and proving its stored invariants
+    /// would cost, without calling the model or running Lean
+    Plan {
+        /// Spec document to read
+        file: PathBuf,
+
+        /// Document ID the invariants are stored under; defaults to the
+        /// file name
+        #[arg(long)]
+        document_id: Option<String>,
+
+        #[arg(long, default_value = "auto")]
+        proof_strategy: String,
+
+        #[arg(long, default_value = "3")]
+        max_attempts: u32,
+    },
+
+    /// Write property-based test scaffolds (Rust proptest, Python
+    /// hypothesis) with one test per stored invariant of a document
+    ScaffoldTests {
+        document_id: String,
+
+        /// Directory the test files are written under, at their suggested
+        /// paths; defaults to <data-dir>/tests/<document-id>
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// Only this framework: proptest or hypothesis
+        #[arg(long)]
+        framework: Option<String>,
+
+        #[arg(long, default_value = "256")]
+        cases: u32,
+
+        /// Print a patch adding the files instead of writing them
+        #[arg(long)]
+        patch: bool,
+    },
+
+    /// Check a proof artifact's hash, or an audit bundle's entry hashes and
+    /// manifest signature
+    VerifyArtifact {
+        /// Proof artifact JSON or audit bundle (.tar.gz)
+        path: PathBuf,
+
+        /// KMS key the bundle manifest was signed with; without it only the
+        /// entry hashes are checked
+        #[arg(long, env = "AUDIT_SIGNING_KEY_ID")]
+        kms_key_id: Option<String>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(&cli.log_level)),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    match cli.command {
+        Command::Extract { file, document_id, output } => {
+            extract(&cli.data_dir, &file, document_id, output.as_deref()).await
+        }
+        Command::Backfill { source, document_ids, since, until, apply, max_documents, output } => {
+            let request = BackfillRequest {
+                source_system: source,
+                document_ids,
+                ingested_after: since,
+                ingested_before: until,
+                apply,
+                max_documents,
+            };
+            backfill(&cli.data_dir, &request, output.as_deref()).await
+        }
+        Command::Compile { document_id, out_dir, proof_strategy } => {
+            let out_dir = out_dir.unwrap_or_else(|| cli.data_dir.join("lean").join(&document_id));
+            compile(&cli.data_dir, &document_id, &out_dir, &proof_strategy).await
+        }
+        Command::Prove { document_id, workspace, skip_build, proof_strategy, max_attempts } => {
+            let workspace = workspace.unwrap_or_else(|| cli.data_dir.join("workspaces").join(&document_id));
+            prove(
+                &cli.data_dir,
+                &document_id,
+                (!skip_build).then_some(workspace.as_path()),
+                &proof_options(proof_strategy, max_attempts),
+            ).await
+        }
+        Command::Plan { file, document_id, proof_strategy, max_attempts } => {
+            plan(&cli.data_dir, &file, document_id, &proof_options(proof_strategy, max_attempts)).await
+        }
+        Command::ScaffoldTests { document_id, out_dir, framework, cases, patch } => {
+            let out_dir = out_dir.unwrap_or_else(|| cli.data_dir.join("tests").join(&document_id));
+            scaffold_tests(
+                &cli.data_dir,
+                &document_id,
+                (!patch).then_some(out_dir.as_path()),
+                framework.as_deref(),
+                cases,
+            ).await
+        }
+        Command::VerifyArtifact { path, kms_key_id } => verify_artifact(&path, kms_key_id.as_deref()).await,
+    }
+}
+
+async fn extract(
+    data_dir: &Path,
+    file: &Path,
+    document_id: Option<String>,
+    output: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let content = tokio::fs::read_to_string(file).await?;
+    let document_id = document_id.unwrap_or_else(|| file_document_id(file));
+
+    let config = extraction_config(data_dir, claude_api_key()?);
+    let request = extraction_request(&config, &document_id, content, false);
+    let language = language::resolve_language(&request);
+    let prompt = prompts::select_extraction_prompt(&prompts::builtin_registry(), language, &document_id)?;
+    let extracted = ExtractionPipeline::new(&config).run(&request, &prompt.template).await?;
+    if extracted.pii_detected {
+        warn!("Redacted {:?} before extraction", extracted.redacted_fields);
+    }
+
+    let store = open_store(data_dir).await?;
+    nlp_persistence::persist_document(store.repository::<StoredDocument>().as_ref(), &request).await?;
+    let repository = store.repository::<StoredInvariant>();
+    let stored = store_invariants(repository.as_ref(), &document_id, &extracted.invariants).await?;
+    eprintln!(
+        "Extracted {} invariant(s) from {} ({} new)",
+        extracted.invariants.len(),
+        document_id,
+        stored
+    );
+
+    let json = serde_json::to_string_pretty(&extracted.invariants)?;
+    match output {
+        Some(path) => tokio::fs::write(path, json).await?,
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Marks drifted proven invariants stale, then stores the new ones and
+/// returns how many were new
+async fn store_invariants(
+    repository: &dyn Repository<StoredInvariant>,
+    document_id: &str,
+    invariants: &[ExtractedInvariant],
+) -> Result<usize, spec_to_proof_error::Error> {
+    if let Some(event) = drift::detect_drift_event(repository, document_id, invariants).await? {
+        eprintln!("{}", event.summary);
+        let marked = drift::mark_stale(repository, &event).await?;
+        eprintln!("Marked {} proven invariant(s) stale", marked);
+    }
+    nlp_persistence::persist_invariants(repository, document_id, invariants).await
+}
+
+async fn backfill(data_dir: &Path, request: &BackfillRequest, output: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let config = extraction_config(data_dir, claude_api_key()?);
+    let store = open_store(data_dir).await?;
+    let documents = nlp_backfill::matching_documents(store.repository::<StoredDocument>().as_ref(), request).await?;
+    if documents.is_empty() {
+        return Err("No stored documents match; documents are stored by `spec2proof extract`".into());
+    }
+
+    let repository = store.repository::<StoredInvariant>();
+    let pipeline = ExtractionPipeline::new(&config);
+    let registry = prompts::builtin_registry();
+    let mut response = BackfillResponse::default();
+    for document in &documents {
+        let extraction = nlp_backfill::extraction_request(document, config.confidence_threshold);
+        let result: Result<DocumentBackfill, spec_to_proof_error::Error> = async {
+            let language = language::resolve_language(&extraction);
+            let prompt = prompts::select_extraction_prompt(&registry, language, &document.id)?;
+            let current = nlp_backfill::current_invariants(repository.as_ref(), &document.id).await?;
+            let extracted = pipeline.run(&extraction, &prompt.template).await?;
+            let diff = invariant_diff::diff_invariant_sets(&current, &extracted.invariants);
+            eprintln!("{}", nlp_backfill::summary(&document.id, &diff));
+
+            let applied = request.apply && !diff.is_empty();
+            if applied {
+                store_invariants(repository.as_ref(), &document.id, &extracted.invariants).await?;
+            }
+            Ok(nlp_backfill::document_report(document, &prompt.template.version, &diff, applied))
+        }
+        .await;
+
+        match result {
+            Ok(report) => response.documents.push(report),
+            Err(e) => {
+                eprintln!("{}: failed: {}", document.id, e);
+                response.documents.push(nlp_backfill::failed_report(document, &e));
+                response.failed_count += 1;
+            }
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&response)?;
+    match output {
+        Some(path) => tokio::fs::write(path, json).await?,
+        None => println!("{}", json),
+    }
+    if response.failed_count > 0 {
+        return Err(format!("{} of {} document(s) failed", response.failed_count, documents.len()).into());
+    }
+    Ok(())
+}
+
+async fn compile(
+    data_dir: &Path,
+    document_id: &str,
+    out_dir: &Path,
+    proof_strategy: &str,
+) -> Result<(), Box<dyn Error>> {
+    let store = open_store(data_dir).await?;
+    let invariants = provable_invariants(store.repository::<StoredInvariant>().as_ref(), document_id).await?;
+    let config = proof_config(data_dir, claude_api_key()?);
+    let compiler = LeanCompiler::new(&config);
+    let options = compilation_options(&config, proof_strategy);
+
+    let mut theorems = Vec::new();
+    for stored in &invariants {
+        let invariant = convert::to_invariant(stored);
+        let theorem = compiler.compile_invariant_to_theorem(&invariant, &options).await?;
+        info!("Compiled invariant {} into {}", invariant.id, theorem.theorem_name);
+        theorems.push(theorem);
+    }
+    proof_persistence::persist_theorems(store.repository::<LeanTheorem>().as_ref(), &theorems).await?;
+
+    tokio::fs::create_dir_all(out_dir).await?;
+    for theorem in &theorems {
+        let path = out_dir.join(format!("{}.lean", theorem.id));
+        tokio::fs::write(&path, &theorem.lean_code).await?;
+        println!("{}", path.display());
+    }
+    eprintln!("Compiled {} theorem(s) for {}", theorems.len(), document_id);
+    Ok(())
+}
+
+async fn prove(
+    data_dir: &Path,
+    document_id: &str,
+    workspace: Option<&Path>,
+    options: &ProofOptions,
+) -> Result<(), Box<dyn Error>> {
+    let store = open_store(data_dir).await?;
+    let theorem_repository = store.repository::<LeanTheorem>();
+    let artifact_repository = store.repository::<ProofArtifact>();
+    let invariants = provable_invariants(store.repository::<StoredInvariant>().as_ref(), document_id).await?;
+    let config = proof_config(data_dir, claude_api_key()?);
+    let compiler = LeanCompiler::new(&config);
+
+    let mut proven = Vec::new();
+    for stored in &invariants {
+        let theorems = proof_persistence::load_for_invariant(theorem_repository.as_ref(), &stored.id).await?;
+        let Some(theorem) = theorems.into_iter().max_by_key(|theorem| {
+            theorem.generated_at.as_ref().map(|t| (t.seconds, t.nanos)).unwrap_or_default()
+        }) else {
+            warn!("Invariant {} has no compiled theorem; run `spec2proof compile` first", stored.id);
+            continue;
+        };
+        proven.push(compiler.generate_proof(&theorem, options).await?);
+    }
+    if proven.is_empty() {
+        return Err(format!("No theorems to prove for {}", document_id).into());
+    }
+
+    // The model's proof only counts once Lean has checked it
+    if let Some(root) = workspace {
+        let set = convert::invariant_set(document_id, &invariants);
+        let lake = WorkspaceBuilder::new(&set, &config)
+            .theorems(proven.iter().map(|(theorem, _)| theorem))
+            .build()?;
+        let build = lake.build_in(root).await?;
+        eprintln!("lake build in {}: {}", root.display(), if build.success { "ok" } else { "failed" });
+        if !build.success {
+            for (_, artifact) in &mut proven {
+                artifact.status = ProofStatus::Failed as i32;
+                artifact.logs.push(build.stdout.clone());
+                artifact.logs.push(build.stderr.clone());
+            }
+        }
+    }
+
+    let artifact_dir = data_dir.join("artifacts");
+    tokio::fs::create_dir_all(&artifact_dir).await?;
+    let mut failed = 0;
+    for (theorem, artifact) in &proven {
+        proof_persistence::persist_proof_result(
+            theorem_repository.as_ref(),
+            artifact_repository.as_ref(),
+            Some(theorem),
+            artifact,
+        ).await?;
+
+        let path = artifact_dir.join(format!("{}.json", artifact.id));
+        let json = serde_json::to_vec_pretty(&proof::audit_bundle::artifact_json(artifact))?;
+        tokio::fs::write(&path, json).await?;
+        if artifact.status != ProofStatus::Success as i32 {
+            failed += 1;
+        }
+        println!("{}", path.display());
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} proof(s) failed for {}", failed, proven.len(), document_id).into());
+    }
+    eprintln!("Proved {} theorem(s) for {}", proven.len(), document_id);
+    Ok(())
+}
+
+async fn plan(
+    data_dir: &Path,
+    file: &Path,
+    document_id: Option<String>,
+    proof_options: &ProofOptions,
+) -> Result<(), Box<dyn Error>> {
+    let content = tokio::fs::read_to_string(file).await?;
+    let document_id = document_id.unwrap_or_else(|| file_document_id(file));
+    // Planning never calls the model, so it runs without an API key
+    let api_key = claude_api_key().unwrap_or_default();
+
+    let config = extraction_config(data_dir, api_key.clone());
+    let request = extraction_request(&config, &document_id, content, true);
+    let language = language::resolve_language(&request);
+    let prompt = prompts::select_extraction_prompt(&prompts::builtin_registry(), language, &document_id)?;
+    let extraction = ExtractionPipeline::new(&config).plan(&request, &prompt.template)?;
+
+    // The proof plan covers what is stored now; invariants the spec would
+    // add only show up after `extract`
+    let store = open_store(data_dir).await?;
+    let stored = provable_invariants(store.repository::<StoredInvariant>().as_ref(), &document_id).await;
+    let proof = match stored {
+        Ok(invariants) => {
+            let config = proof_config(data_dir, api_key);
+            let compiler = LeanCompiler::new(&config);
+            let theorem_repository = store.repository::<LeanTheorem>();
+            let artifact_repository = store.repository::<ProofArtifact>();
+            let planner = ProofPlanner::new(
+                &compiler,
+                &config,
+                theorem_repository.as_ref(),
+                artifact_repository.as_ref(),
+            );
+            let set = convert::invariant_set(&document_id, &invariants);
+            let options = compilation_options(&config, &proof_options.proof_strategy);
+            Some(planner.plan(&set, None, &options, proof_options).await?)
+        }
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    };
+
+    let json = serde_json::json!({
+        "document_id": document_id,
+        "extraction": extraction,
+        "proof": proof.as_ref().map(convert::proof_plan_json),
+    });
+    println!("{}", serde_json::to_string_pretty(&json)?);
+    Ok(())
+}
+
+async fn scaffold_tests(
+    data_dir: &Path,
+    document_id: &str,
+    out_dir: Option<&Path>,
+    framework: Option<&str>,
+    cases: u32,
+) -> Result<(), Box<dyn Error>> {
+    let frameworks = match framework {
+        Some(name) => vec![TestFramework::from_str_name(&format!("TEST_FRAMEWORK_{}", name.to_uppercase()))
+            .filter(|framework| *framework != TestFramework::Unspecified)
+            .ok_or_else(|| format!("Unknown test framework {}; expected proptest or hypothesis", name))?],
+        None => Vec::new(),
+    };
+    let store = open_store(data_dir).await?;
+    let invariants = provable_invariants(store.repository::<StoredInvariant>().as_ref(), document_id).await?;
+    let set = convert::invariant_set(document_id, &invariants);
+    let scaffolds = testgen::generate_scaffolds(&set, &frameworks, cases);
+
+    let Some(out_dir) = out_dir else {
+        print!("{}", testgen::suggestion_patch(&scaffolds));
+        return Ok(());
+    };
+    for scaffold in &scaffolds {
+        let path = out_dir.join(&scaffold.path);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, &scaffold.content).await?;
+        for test in scaffold.tests.iter().filter(|test| !test.skip_reason.is_empty()) {
+            warn!("{} is skipped in {}: {}", test.name, scaffold.path, test.skip_reason);
+        }
+        println!("{}", path.display());
+    }
+    eprintln!("Scaffolded tests for {} invariant(s) of {}", invariants.len(), document_id);
+    Ok(())
+}
+
+async fn verify_artifact(path: &Path, kms_key_id: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let contents = tokio::fs::read(path).await?;
+    let verified = if verify::is_bundle(path) {
+        verify::verify_bundle(&contents, kms_key_id).await?
+    } else {
+        verify::verify_proof_artifact(&contents)?
+    };
+
+    match verified {
+        Verified::ProofArtifact { id, content_sha256 } => {
+            println!("OK proof artifact {} (sha256 {})", id, content_sha256);
+        }
+        Verified::Bundle { manifest, signed } => {
+            println!(
+                "OK bundle {} ({} entries, {})",
+                manifest.bundle_id,
+                manifest.entries.len(),
+                if signed { "signature verified" } else { "hashes only; pass --kms-key-id to check the signature" }
+            );
+        }
+    }
+    Ok(())
+}
+
+fn local_storage(data_dir: &Path) -> StorageSettings {
+    StorageSettings {
+        backend: StorageBackend::Local,
+        data_dir: Some(data_dir.join("store")),
+        ..Default::default()
+    }
+}
+
+async fn open_store(data_dir: &Path) -> Result<EntityStore, Box<dyn Error>> {
+    let store = EntityStore::connect(&local_storage(data_dir)).await?;
+    store.initialize().await?;
+    Ok(store)
+}
+
+fn parse_time(text: &str) -> Result<prost_types::Timestamp, String> {
+    let time = match chrono::DateTime::parse_from_rfc3339(text) {
+        Ok(time) => time.with_timezone(&chrono::Utc),
+        Err(_) => chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map_err(|_| format!("expected an RFC 3339 time or YYYY-MM-DD, got {:?}", text))?
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc(),
+    };
+    Ok(prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    })
+}
+
+fn file_document_id(file: &Path) -> String {
+    file.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn extraction_request(
+    config: &InvariantExtractionConfig,
+    document_id: &str,
+    content: String,
+    dry_run: bool,
+) -> ExtractInvariantsRequest {
+    ExtractInvariantsRequest {
+        document_id: document_id.to_string(),
+        content,
+        title: document_id.to_string(),
+        source_system: "local".to_string(),
+        invariant_types: Vec::new(),
+        confidence_threshold: config.confidence_threshold,
+        dry_run,
+        language: String::new(),
+    }
+}
+
+fn compilation_options(config: &ProofConfig, proof_strategy: &str) -> CompilationOptions {
+    CompilationOptions {
+        temperature: config.temperature,
+        max_tokens: config.max_tokens,
+        seed: 42,
+        proof_strategy: proof_strategy.to_string(),
+        include_dependencies: true,
+        dry_run: false,
+    }
+}
+
+fn proof_options(proof_strategy: String, max_attempts: u32) -> ProofOptions {
+    ProofOptions {
+        temperature: 0.0,
+        max_tokens: 8000,
+        seed: 42,
+        max_attempts,
+        timeout_seconds: 300,
+        proof_strategy,
+        ..Default::default()
+    }
+}
+
+fn claude_api_key() -> Result<String, Box<dyn Error>> {
+    Ok(std::env::var("CLAUDE_API_KEY").map_err(|_| "CLAUDE_API_KEY environment variable is required")?)
+}
+
+fn extraction_config(data_dir: &Path, claude_api_key: String) -> InvariantExtractionConfig {
+    let defaults = InvariantExtractionConfig::default();
+    InvariantExtractionConfig {
+        claude_api_key,
+        claude_model: std::env::var("CLAUDE_MODEL").unwrap_or(defaults.claude_model.clone()),
+        storage: local_storage(data_dir),
+        ..defaults
+    }
+}
+
+fn proof_config(data_dir: &Path, claude_api_key: String) -> ProofConfig {
+    let defaults = ProofConfig::default();
+    ProofConfig {
+        claude_api_key,
+        claude_model: std::env::var("CLAUDE_MODEL").unwrap_or(defaults.claude_model.clone()),
+        lean_toolchain: std::env::var("LEAN_TOOLCHAIN").unwrap_or(defaults.lean_toolchain.clone()),
+        mathlib_commit: std::env::var("MATHLIB_COMMIT").unwrap_or_default(),
+        storage: local_storage(data_dir),
+        ..defaults
+    }
+}
+
+/// The document's invariants, minus those a reviewer rejected or that went
+/// stale when the spec changed
+async fn provable_invariants(
+    repository: &dyn Repository<StoredInvariant>,
+    document_id: &str,
+) -> Result<Vec<StoredInvariant>, Box<dyn Error>> {
+    let invariants: Vec<StoredInvariant> = load_by_source(repository, document_id)
+        .await?
+        .into_iter()
+        .filter(|stored| {
+            stored.status != INVARIANT_STATUS_REJECTED && stored.status != drift::INVARIANT_STATUS_STALE
+        })
+        .collect();
+    if invariants.is_empty() {
+        return Err(format!("No invariants stored for {}; run `spec2proof extract` first", document_id).into());
+    }
+    Ok(invariants)
+}
+
+async fn load_by_source<E: Entity>(repository: &dyn Repository<E>, source_id: &str) -> Result<Vec<E>, Box<dyn Error>> {
+    let query = EntityQuery::BySource(source_id.to_string());
+    let mut items = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let page = repository.query(&query, page_token.as_deref(), QUERY_PAGE_SIZE).await?;
+        items.extend(page.items.into_iter().map(|stored| stored.entity));
+        match page.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => return Ok(items),
+        }
+    }
+}
+
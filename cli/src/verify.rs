@@ -0,0 +1,103 @@
+use std::error::Error;
+use std::path::Path;
+use export::{KmsManifestSigner, Manifest};
+use serde_json::Value;
+
+use crate::convert::sha256_hex;
+
+/// What `verify-artifact` checked
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verified {
+    /// A proof artifact whose output matches its recorded hash
+    ProofArtifact { id: String, content_sha256: String },
+    /// An audit bundle; `signed` is false when only entry hashes were checked
+    Bundle { manifest: Manifest, signed: bool },
+}
+
+pub fn is_bundle(path: &Path) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+/// Checks an audit bundle against its manifest, and the manifest signature
+/// when a KMS key is given
+pub async fn verify_bundle(archive: &[u8], kms_key_id: Option<&str>) -> Result<Verified, Box<dyn Error>> {
+    match kms_key_id {
+        Some(key_id) => {
+            let aws_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+            let signer = KmsManifestSigner::new(aws_sdk_kms::Client::new(&aws_config), key_id);
+            let manifest = export::verify_archive(archive, &signer).await?;
+            Ok(Verified::Bundle { manifest, signed: true })
+        }
+        None => Ok(Verified::Bundle {
+            manifest: export::verify_archive_hashes(archive)?,
+            signed: false,
+        }),
+    }
+}
+
+/// Checks a proof artifact as written by `prove` or found in an audit
+/// bundle: its output must hash to `content_sha256`
+pub fn verify_proof_artifact(contents: &[u8]) -> Result<Verified, Box<dyn Error>> {
+    let artifact: Value = serde_json::from_slice(contents)?;
+    let field = |name: &str| {
+        artifact
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("Proof artifact has no {} field", name))
+    };
+
+    let id = field("id")?;
+    let expected = field("content_sha256")?;
+    let actual = sha256_hex(field("output")?);
+    if actual != expected {
+        return Err(format!(
+            "Proof artifact {} does not match its hash: recorded {}, computed {}",
+            id, expected, actual
+        )
+        .into());
+    }
+
+    Ok(Verified::ProofArtifact {
+        id: id.to_string(),
+        content_sha256: actual,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_verify_proof_artifact() {
+        let output = "theorem refund_bound : True := trivial";
+        let artifact = json!({
+            "id": "proof_thm_1",
+            "content_sha256": sha256_hex(output),
+            "output": output,
+        });
+        let verified = verify_proof_artifact(artifact.to_string().as_bytes()).unwrap();
+        assert_eq!(
+            verified,
+            Verified::ProofArtifact {
+                id: "proof_thm_1".to_string(),
+                content_sha256: sha256_hex(output),
+            }
+        );
+
+        let tampered = json!({
+            "id": "proof_thm_1",
+            "content_sha256": sha256_hex(output),
+            "output": "theorem refund_bound : True := sorry",
+        });
+        assert!(verify_proof_artifact(tampered.to_string().as_bytes()).is_err());
+        assert!(verify_proof_artifact(b"{\"id\": \"proof_thm_1\"}").is_err());
+    }
+
+    #[test]
+    fn test_is_bundle() {
+        assert!(is_bundle(Path::new("out/acme-pr42-abc.tar.gz")));
+        assert!(!is_bundle(Path::new("out/proof_thm_1.json")));
+    }
+}
@@ -0,0 +1,228 @@
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+/// A tenant's spending limits. Crossing the soft threshold of either limit
+/// sends a notification; reaching a limit denies further LLM calls until the
+/// period rolls over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantBudget {
+    #[serde(default)]
+    pub daily_usd: Option<f64>,
+    #[serde(default)]
+    pub monthly_usd: Option<f64>,
+    #[serde(default = "default_soft_threshold_percent")]
+    pub soft_threshold_percent: f64,
+}
+
+fn default_soft_threshold_percent() -> f64 {
+    80.0
+}
+
+impl Default for TenantBudget {
+    fn default() -> Self {
+        Self {
+            daily_usd: None,
+            monthly_usd: None,
+            soft_threshold_percent: default_soft_threshold_percent(),
+        }
+    }
+}
+
+// Ordered by severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetStatus {
+    WithinBudget,
+    SoftLimitExceeded,
+    HardCapReached,
+}
+
+impl BudgetStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetStatus::WithinBudget => "within_budget",
+            BudgetStatus::SoftLimitExceeded => "soft_limit_exceeded",
+            BudgetStatus::HardCapReached => "hard_cap_reached",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantUsage {
+    pub tenant_id: String,
+    /// UTC day and month the spend figures cover
+    pub day: String,
+    pub month: String,
+    pub daily_spent_usd: f64,
+    pub monthly_spent_usd: f64,
+    pub budget: TenantBudget,
+    pub status: BudgetStatus,
+}
+
+/// The most severe status across the daily and monthly limits
+pub fn budget_status(budget: &TenantBudget, daily_spent_usd: f64, monthly_spent_usd: f64) -> BudgetStatus {
+    let soft_fraction = budget.soft_threshold_percent / 100.0;
+    [(budget.daily_usd, daily_spent_usd), (budget.monthly_usd, monthly_spent_usd)]
+        .into_iter()
+        .filter_map(|(limit, spent)| limit.map(|limit| (limit, spent)))
+        .map(|(limit, spent)| {
+            if spent >= limit {
+                BudgetStatus::HardCapReached
+            } else if spent >= limit * soft_fraction {
+                BudgetStatus::SoftLimitExceeded
+            } else {
+                BudgetStatus::WithinBudget
+            }
+        })
+        .max()
+        .unwrap_or(BudgetStatus::WithinBudget)
+}
+
+/// Budgets and per-period spend in Redis. Spend counters are keyed by UTC
+/// day and month and expire after their period, so limits reset on their
+/// own.
+#[derive(Debug, Clone)]
+pub struct BudgetStore {
+    redis_client: redis::Client,
+}
+
+const DAY_TTL_SECONDS: usize = 2 * 24 * 3600;
+const MONTH_TTL_SECONDS: usize = 32 * 24 * 3600;
+
+impl BudgetStore {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self { redis_client }
+    }
+
+    pub fn connect(redis_url: &str) -> Result<Self> {
+        Ok(Self::new(redis::Client::open(redis_url)?))
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        redis::cmd("PING").query_async::<_, String>(&mut conn).await?;
+        Ok(())
+    }
+
+    pub async fn get_budget(&self, tenant_id: &str) -> Result<TenantBudget> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let raw: Option<String> = conn.get(budget_key(tenant_id)).await?;
+        // An unreadable budget is treated as unlimited rather than locking
+        // the tenant out
+        Ok(raw
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default())
+    }
+
+    pub async fn set_budget(&self, tenant_id: &str, budget: &TenantBudget) -> Result<()> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let _: () = conn.set(budget_key(tenant_id), serde_json::to_string(budget)?).await?;
+        Ok(())
+    }
+
+    pub async fn usage(&self, tenant_id: &str, now: DateTime<Utc>) -> Result<TenantUsage> {
+        let (day, month) = periods(now);
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let daily: Option<f64> = conn.get(spend_key(tenant_id, &day)).await?;
+        let monthly: Option<f64> = conn.get(spend_key(tenant_id, &month)).await?;
+        drop(conn);
+
+        self.build_usage(tenant_id, day, month, daily.unwrap_or(0.0), monthly.unwrap_or(0.0)).await
+    }
+
+    /// Adds spend to the current day and month and returns the new usage
+    pub async fn add_spend(&self, tenant_id: &str, cost_usd: f64, now: DateTime<Utc>) -> Result<TenantUsage> {
+        let (day, month) = periods(now);
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let (daily, monthly): (f64, f64) = redis::pipe()
+            .atomic()
+            .incr(spend_key(tenant_id, &day), cost_usd)
+            .expire(spend_key(tenant_id, &day), DAY_TTL_SECONDS).ignore()
+            .incr(spend_key(tenant_id, &month), cost_usd)
+            .expire(spend_key(tenant_id, &month), MONTH_TTL_SECONDS).ignore()
+            .query_async(&mut conn)
+            .await?;
+        drop(conn);
+
+        self.build_usage(tenant_id, day, month, daily, monthly).await
+    }
+
+    /// Records that `status` was announced for the tenant in `period`;
+    /// false if it already had been
+    pub async fn mark_notified(&self, tenant_id: &str, period: &str, status: BudgetStatus) -> Result<bool> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let key = format!("tenant_budget_notified:{}:{}:{}", tenant_id, period, status.as_str());
+        let first: bool = redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(MONTH_TTL_SECONDS)
+            .query_async::<_, Option<String>>(&mut conn)
+            .await?
+            .is_some();
+        Ok(first)
+    }
+
+    async fn build_usage(
+        &self,
+        tenant_id: &str,
+        day: String,
+        month: String,
+        daily_spent_usd: f64,
+        monthly_spent_usd: f64,
+    ) -> Result<TenantUsage> {
+        let budget = self.get_budget(tenant_id).await?;
+        Ok(TenantUsage {
+            tenant_id: tenant_id.to_string(),
+            status: budget_status(&budget, daily_spent_usd, monthly_spent_usd),
+            day,
+            month,
+            daily_spent_usd,
+            monthly_spent_usd,
+            budget,
+        })
+    }
+}
+
+fn periods(now: DateTime<Utc>) -> (String, String) {
+    (now.format("%Y-%m-%d").to_string(), now.format("%Y-%m").to_string())
+}
+
+fn budget_key(tenant_id: &str) -> String {
+    format!("tenant_budget:{}", tenant_id)
+}
+
+fn spend_key(tenant_id: &str, period: &str) -> String {
+    format!("tenant_spend:{}:{}", tenant_id, period)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_status() {
+        let budget = TenantBudget {
+            daily_usd: Some(10.0),
+            monthly_usd: Some(100.0),
+            ..Default::default()
+        };
+
+        assert_eq!(budget_status(&budget, 1.0, 10.0), BudgetStatus::WithinBudget);
+        assert_eq!(budget_status(&budget, 8.0, 10.0), BudgetStatus::SoftLimitExceeded);
+        // The monthly cap applies even on a quiet day
+        assert_eq!(budget_status(&budget, 0.0, 100.0), BudgetStatus::HardCapReached);
+        assert_eq!(budget_status(&TenantBudget::default(), 1e6, 1e6), BudgetStatus::WithinBudget);
+    }
+
+    #[test]
+    fn test_budget_defaults_soft_threshold() {
+        let budget: TenantBudget = serde_json::from_str(r#"{"daily_usd": 5.0}"#).unwrap();
+        assert_eq!(budget.soft_threshold_percent, 80.0);
+        assert_eq!(budget.monthly_usd, None);
+    }
+}
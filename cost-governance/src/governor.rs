@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use crate::pricing::PricingTable;
+use crate::tenant;
+use crate::{CostGovernanceConfig, CostGovernanceError, CostGovernanceManager, LlmCallLimits, Result};
+
+/// The governance checks an LLM client makes around each request: permission
+/// for the current tenant before the call, and the cost of the call after
+pub struct LlmCallGovernor {
+    manager: Arc<CostGovernanceManager>,
+    pricing: PricingTable,
+}
+
+impl LlmCallGovernor {
+    pub fn new(manager: Arc<CostGovernanceManager>, pricing: PricingTable) -> Self {
+        Self { manager, pricing }
+    }
+
+    pub async fn connect(redis_url: &str, config: CostGovernanceConfig, pricing: PricingTable) -> Result<Self> {
+        let manager = CostGovernanceManager::connect(redis_url, config).await?;
+        Ok(Self::new(Arc::new(manager), pricing))
+    }
+
+    /// Asks permission for a call with a prompt of about `prompt_tokens`
+    /// tokens and returns the tenant it is billed to
+    pub async fn authorize(&self, prompt_tokens: u32) -> Result<String> {
+        let tenant_id = tenant::current_tenant();
+        if !self.manager.check_llm_call_permission(&tenant_id, bucket_tokens(prompt_tokens)).await? {
+            return Err(CostGovernanceError::Denied {
+                tenant_id,
+                reason: "rate or budget limit reached".to_string(),
+            });
+        }
+        Ok(tenant_id)
+    }
+
+    pub fn limits(&self) -> LlmCallLimits {
+        self.manager.limits()
+    }
+
+    pub fn set_limits(&self, limits: LlmCallLimits) {
+        self.manager.set_limits(limits)
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        self.manager.ping().await
+    }
+
+    /// Charges the tenant for a call to `model` at that model's prices
+    pub async fn record(&self, tenant_id: &str, model: &str, input_tokens: u32, output_tokens: u32) -> Result<()> {
+        let cost_usd = self.pricing.cost(model, input_tokens, output_tokens);
+        self.manager.record_llm_cost(tenant_id, cost_usd).await
+    }
+}
+
+// Bucket tokens are charged per started thousand prompt tokens, so large
+// prompts drain a tenant's bucket faster than small ones
+fn bucket_tokens(prompt_tokens: u32) -> u32 {
+    prompt_tokens.div_ceil(1000).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_tokens() {
+        assert_eq!(bucket_tokens(0), 1);
+        assert_eq!(bucket_tokens(1000), 1);
+        assert_eq!(bucket_tokens(1001), 2);
+    }
+}
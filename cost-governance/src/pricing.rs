@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+/// Per-1k-token prices for one model. Output tokens cost several times more
+/// than input tokens on every current model, so they are priced separately.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+impl ModelPricing {
+    pub fn flat(per_1k: f64) -> Self {
+        Self {
+            input_per_1k: per_1k,
+            output_per_1k: per_1k,
+        }
+    }
+
+    pub fn cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        input_tokens as f64 / 1000.0 * self.input_per_1k + output_tokens as f64 / 1000.0 * self.output_per_1k
+    }
+}
+
+/// Prices by model name. Models without an entry are charged the fallback
+/// rate, which is the flat `cost_per_1k_tokens` the services were configured
+/// with before per-model pricing.
+#[derive(Debug, Clone)]
+pub struct PricingTable {
+    models: HashMap<String, ModelPricing>,
+    fallback: ModelPricing,
+}
+
+impl PricingTable {
+    pub fn new(fallback: ModelPricing) -> Self {
+        Self {
+            models: HashMap::new(),
+            fallback,
+        }
+    }
+
+    /// Published list prices for the Claude models the services route
+    /// between
+    pub fn builtin(fallback_per_1k: f64) -> Self {
+        let mut table = Self::new(ModelPricing::flat(fallback_per_1k));
+        for (model, input_per_1k, output_per_1k) in [
+            ("claude-3-opus-20240229", 0.015, 0.075),
+            ("claude-3-sonnet-20240229", 0.003, 0.015),
+            ("claude-3-5-sonnet-20240620", 0.003, 0.015),
+            ("claude-3-haiku-20240307", 0.00025, 0.00125),
+        ] {
+            table.set(model, ModelPricing { input_per_1k, output_per_1k });
+        }
+        table
+    }
+
+    pub fn set(&mut self, model: &str, pricing: ModelPricing) {
+        self.models.insert(model.to_string(), pricing);
+    }
+
+    pub fn get(&self, model: &str) -> ModelPricing {
+        self.models.get(model).copied().unwrap_or(self.fallback)
+    }
+
+    pub fn cost(&self, model: &str, input_tokens: u32, output_tokens: u32) -> f64 {
+        self.get(model).cost(input_tokens, output_tokens)
+    }
+}
+
+/// Parses model prices written as `model=input:output` pairs separated by
+/// commas, with prices per 1k tokens, e.g.
+/// `claude-3-haiku-20240307=0.00025:0.00125`
+pub fn parse_model_pricing(spec: &str) -> Result<HashMap<String, ModelPricing>, String> {
+    let mut models = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let invalid = || format!("invalid model pricing '{}': expected model=input:output", entry);
+        let (model, prices) = entry.split_once('=').ok_or_else(invalid)?;
+        let (input, output) = prices.split_once(':').ok_or_else(invalid)?;
+        let price = |value: &str| {
+            value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|price| *price >= 0.0)
+                .ok_or_else(|| format!("invalid price '{}' for model {}", value, model.trim()))
+        };
+        models.insert(
+            model.trim().to_string(),
+            ModelPricing {
+                input_per_1k: price(input)?,
+                output_per_1k: price(output)?,
+            },
+        );
+    }
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_costs_use_model_prices_with_fallback() {
+        let table = PricingTable::builtin(0.01);
+        let opus = table.cost("claude-3-opus-20240229", 1000, 1000);
+        let haiku = table.cost("claude-3-haiku-20240307", 1000, 1000);
+        assert!((opus - 0.09).abs() < 1e-9);
+        assert!(haiku < opus / 50.0);
+        assert!((table.cost("unknown-model", 500, 500) - 0.01).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_model_pricing() {
+        let models = parse_model_pricing("claude-3-haiku-20240307=0.001:0.002, custom=0.5:1").unwrap();
+        assert_eq!(models["custom"], ModelPricing { input_per_1k: 0.5, output_per_1k: 1.0 });
+        assert_eq!(models["claude-3-haiku-20240307"].output_per_1k, 0.002);
+        assert!(parse_model_pricing("").unwrap().is_empty());
+
+        assert!(parse_model_pricing("custom=0.5").is_err());
+        assert!(parse_model_pricing("custom=-1:1").is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use std::future::Future;
+
+// The tenant an LLM call is billed to. Services read it from the
+// `x-tenant-id` request metadata at the RPC boundary and scope the handler
+// with it, so clients deep in the call path can attribute spend without
+// every layer passing it along.
+
+pub const TENANT_METADATA_KEY: &str = "x-tenant-id";
+
+/// Tenant charged when a request does not name one
+pub const DEFAULT_TENANT: &str = "default";
+
+tokio::task_local! {
+    static TENANT_ID: String;
+}
+
+/// Runs `future` with `tenant_id` as the current tenant
+pub async fn scope<F: Future>(tenant_id: impl Into<String>, future: F) -> F::Output {
+    TENANT_ID.scope(tenant_id.into(), future).await
+}
+
+pub fn current_tenant() -> String {
+    TENANT_ID
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| DEFAULT_TENANT.to_string())
+}
+
+/// The tenant named by a metadata value, falling back to the default for
+/// missing or blank values
+pub fn tenant_from_metadata(value: Option<&str>) -> String {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or(DEFAULT_TENANT)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope_sets_current_tenant() {
+        assert_eq!(current_tenant(), DEFAULT_TENANT);
+        let inner = scope("acme", async { current_tenant() }).await;
+        assert_eq!(inner, "acme");
+        assert_eq!(tenant_from_metadata(Some("  ")), DEFAULT_TENANT);
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::DataKeySpec;
+use aws_sdk_kms::Client as KmsClient;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+// Each object is encrypted with its own AES-256-GCM data key from KMS. The
+// data key, wrapped by the KMS key, travels in the object's metadata next
+// to the nonce, so the object store only ever holds ciphertext. The object
+// key is bound in as both the GCM associated data and the KMS encryption
+// context: a ciphertext copied to another key will not decrypt.
+
+/// Metadata naming the algorithm; objects without it are read as plaintext
+pub const ALGORITHM_METADATA_KEY: &str = "envelope-algorithm";
+/// Metadata holding the base64 KMS-wrapped data key
+pub const WRAPPED_KEY_METADATA_KEY: &str = "envelope-key";
+/// Metadata holding the base64 GCM nonce
+pub const NONCE_METADATA_KEY: &str = "envelope-nonce";
+pub const ALGORITHM: &str = "AES256-GCM-KMS";
+
+// KMS encryption context entry carrying the object key
+const CONTEXT_KEY: &str = "object";
+
+#[derive(Debug, thiserror::Error)]
+pub enum EnvelopeError {
+    #[error("KMS request failed: {0}")]
+    Kms(String),
+
+    #[error("Encryption failed: {0}")]
+    Encrypt(String),
+
+    #[error("Decryption of {object} failed: {message}")]
+    Decrypt { object: String, message: String },
+
+    #[error("Object {object} has invalid encryption metadata: {message}")]
+    Metadata { object: String, message: String },
+}
+
+impl From<EnvelopeError> for spec_to_proof_error::Error {
+    fn from(error: EnvelopeError) -> Self {
+        match error {
+            EnvelopeError::Kms(_) => spec_to_proof_error::Error::transient(error),
+            _ => spec_to_proof_error::Error::internal(error),
+        }
+    }
+}
+
+/// Ciphertext and the metadata to store alongside it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SealedObject {
+    pub ciphertext: Vec<u8>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// Whether an object was written by `EnvelopeEncryptor::seal`
+pub fn is_sealed(metadata: &HashMap<String, String>) -> bool {
+    metadata.contains_key(ALGORITHM_METADATA_KEY)
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvelopeEncryptor {
+    kms_client: KmsClient,
+    key_id: String,
+}
+
+impl EnvelopeEncryptor {
+    pub fn new(kms_client: KmsClient, key_id: impl Into<String>) -> Self {
+        Self {
+            kms_client,
+            key_id: key_id.into(),
+        }
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Encrypts `plaintext` for storage at `object_key`
+    pub async fn seal(&self, object_key: &str, plaintext: &[u8]) -> Result<SealedObject, EnvelopeError> {
+        let data_key = self.kms_client
+            .generate_data_key()
+            .key_id(&self.key_id)
+            .key_spec(DataKeySpec::Aes256)
+            .encryption_context(CONTEXT_KEY, object_key)
+            .send()
+            .await
+            .map_err(|e| EnvelopeError::Kms(e.into_service_error().to_string()))?;
+
+        let plaintext_key = data_key.plaintext()
+            .ok_or_else(|| EnvelopeError::Kms("no plaintext data key returned".to_string()))?;
+        let wrapped_key = data_key.ciphertext_blob()
+            .ok_or_else(|| EnvelopeError::Kms("no wrapped data key returned".to_string()))?;
+
+        let (nonce, ciphertext) = encrypt(plaintext_key.as_ref(), object_key, plaintext)?;
+
+        let mut metadata = HashMap::new();
+        metadata.insert(ALGORITHM_METADATA_KEY.to_string(), ALGORITHM.to_string());
+        metadata.insert(WRAPPED_KEY_METADATA_KEY.to_string(), STANDARD.encode(wrapped_key.as_ref()));
+        metadata.insert(NONCE_METADATA_KEY.to_string(), STANDARD.encode(nonce));
+        Ok(SealedObject { ciphertext, metadata })
+    }
+
+    /// Decrypts an object read from `object_key`. Objects stored before
+    /// encryption was turned on carry no envelope metadata and are
+    /// returned unchanged.
+    pub async fn open(
+        &self,
+        object_key: &str,
+        body: Vec<u8>,
+        metadata: &HashMap<String, String>,
+    ) -> Result<Vec<u8>, EnvelopeError> {
+        let Some(algorithm) = metadata.get(ALGORITHM_METADATA_KEY) else {
+            return Ok(body);
+        };
+        if algorithm != ALGORITHM {
+            return Err(metadata_error(object_key, format!("unsupported algorithm {}", algorithm)));
+        }
+        let wrapped_key = decode_metadata(object_key, metadata, WRAPPED_KEY_METADATA_KEY)?;
+        let nonce = decode_metadata(object_key, metadata, NONCE_METADATA_KEY)?;
+
+        let data_key = self.kms_client
+            .decrypt()
+            .key_id(&self.key_id)
+            .ciphertext_blob(Blob::new(wrapped_key))
+            .encryption_context(CONTEXT_KEY, object_key)
+            .send()
+            .await
+            .map_err(|e| EnvelopeError::Kms(e.into_service_error().to_string()))?;
+        let plaintext_key = data_key.plaintext()
+            .ok_or_else(|| EnvelopeError::Kms("no plaintext data key returned".to_string()))?;
+
+        decrypt(plaintext_key.as_ref(), object_key, &nonce, &body)
+    }
+}
+
+fn encrypt(key: &[u8], object_key: &str, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), EnvelopeError> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| EnvelopeError::Encrypt(e.to_string()))?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad: object_key.as_bytes() })
+        .map_err(|e| EnvelopeError::Encrypt(e.to_string()))?;
+    Ok((nonce.to_vec(), ciphertext))
+}
+
+fn decrypt(key: &[u8], object_key: &str, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+    let decrypt_error = |message: String| EnvelopeError::Decrypt {
+        object: object_key.to_string(),
+        message,
+    };
+    if nonce.len() != 12 {
+        return Err(metadata_error(object_key, format!("nonce is {} bytes, expected 12", nonce.len())));
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| decrypt_error(e.to_string()))?;
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: object_key.as_bytes() })
+        .map_err(|e| decrypt_error(e.to_string()))
+}
+
+fn decode_metadata(object_key: &str, metadata: &HashMap<String, String>, name: &str) -> Result<Vec<u8>, EnvelopeError> {
+    let value = metadata.get(name).ok_or_else(|| metadata_error(object_key, format!("missing {}", name)))?;
+    STANDARD
+        .decode(value)
+        .map_err(|e| metadata_error(object_key, format!("{} is not base64: {}", name, e)))
+}
+
+fn metadata_error(object_key: &str, message: String) -> EnvelopeError {
+    EnvelopeError::Metadata {
+        object: object_key.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = [7u8; 32];
+        let (nonce, ciphertext) = encrypt(&key, "theorems/a1b2c3d4/v1/t.lean", b"theorem t : True := trivial").unwrap();
+        assert_ne!(ciphertext.as_slice(), b"theorem t : True := trivial".as_slice());
+        assert_eq!(
+            decrypt(&key, "theorems/a1b2c3d4/v1/t.lean", &nonce, &ciphertext).unwrap(),
+            b"theorem t : True := trivial"
+        );
+
+        // Bound to the object key and the data key
+        assert!(decrypt(&key, "theorems/other.lean", &nonce, &ciphertext).is_err());
+        assert!(decrypt(&[8u8; 32], "theorems/a1b2c3d4/v1/t.lean", &nonce, &ciphertext).is_err());
+        assert!(matches!(
+            decrypt(&key, "theorems/a1b2c3d4/v1/t.lean", &nonce[..8], &ciphertext),
+            Err(EnvelopeError::Metadata { .. })
+        ));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut metadata = HashMap::new();
+        assert!(!is_sealed(&metadata));
+        metadata.insert(ALGORITHM_METADATA_KEY.to_string(), ALGORITHM.to_string());
+        metadata.insert(NONCE_METADATA_KEY.to_string(), "not base64!".to_string());
+        assert!(is_sealed(&metadata));
+        assert!(decode_metadata("k", &metadata, WRAPPED_KEY_METADATA_KEY).is_err());
+        assert!(decode_metadata("k", &metadata, NONCE_METADATA_KEY).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use std::error::Error as StdError;
+use std::time::Duration;
+
+// Every failure a service surfaces falls into one of these kinds, so callers
+// can decide whether to retry and which status to answer with without
+// parsing messages. Messages are kept as strings: the source errors come
+// from many crates and are rarely Clone or Send.
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// The dependency may well succeed if asked again
+    #[error("{message}")]
+    Transient {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("{message}")]
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Authentication failed: {0}")]
+    AuthFailed(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl Error {
+    pub fn transient(message: impl ToString) -> Self {
+        Error::Transient { message: message.to_string(), retry_after: None }
+    }
+
+    pub fn rate_limited(message: impl ToString, retry_after: Option<Duration>) -> Self {
+        Error::RateLimited { message: message.to_string(), retry_after }
+    }
+
+    pub fn invalid_input(message: impl ToString) -> Self {
+        Error::InvalidInput(message.to_string())
+    }
+
+    pub fn auth_failed(message: impl ToString) -> Self {
+        Error::AuthFailed(message.to_string())
+    }
+
+    pub fn timeout(message: impl ToString) -> Self {
+        Error::Timeout(message.to_string())
+    }
+
+    pub fn internal(message: impl ToString) -> Self {
+        Error::Internal(message.to_string())
+    }
+
+    /// Classifies an unsuccessful HTTP response from a dependency
+    pub fn from_status(status: u16, message: impl ToString) -> Self {
+        let message = message.to_string();
+        match status {
+            401 | 403 => Error::AuthFailed(message),
+            408 | 504 => Error::Timeout(message),
+            429 => Error::rate_limited(message, None),
+            400..=499 => Error::InvalidInput(message),
+            500..=599 => Error::transient(message),
+            _ => Error::Internal(message),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Transient { .. } => "transient",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::InvalidInput(_) => "invalid_input",
+            Error::AuthFailed(_) => "auth_failed",
+            Error::Timeout(_) => "timeout",
+            Error::Internal(_) => "internal",
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Error::Transient { .. } | Error::RateLimited { .. } | Error::Timeout(_))
+    }
+
+    /// How long the dependency asked us to wait, when it said
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::Transient { retry_after, .. } | Error::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    pub fn with_retry_after(mut self, delay: Duration) -> Self {
+        if let Error::Transient { retry_after, .. } | Error::RateLimited { retry_after, .. } = &mut self {
+            *retry_after = Some(delay);
+        }
+        self
+    }
+
+    /// Prefixes the message while keeping the kind
+    pub fn context(self, context: impl std::fmt::Display) -> Self {
+        let wrap = |message: String| format!("{}: {}", context, message);
+        match self {
+            Error::Transient { message, retry_after } => Error::Transient { message: wrap(message), retry_after },
+            Error::RateLimited { message, retry_after } => Error::RateLimited { message: wrap(message), retry_after },
+            Error::InvalidInput(message) => Error::InvalidInput(wrap(message)),
+            Error::AuthFailed(message) => Error::AuthFailed(wrap(message)),
+            Error::Timeout(message) => Error::Timeout(wrap(message)),
+            Error::Internal(message) => Error::Internal(wrap(message)),
+        }
+    }
+
+    /// Status to answer an HTTP caller with
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Error::Transient { .. } => 503,
+            Error::RateLimited { .. } => 429,
+            Error::InvalidInput(_) => 400,
+            Error::AuthFailed(_) => 401,
+            Error::Timeout(_) => 504,
+            Error::Internal(_) => 500,
+        }
+    }
+
+    pub fn grpc_code(&self) -> tonic::Code {
+        match self {
+            Error::Transient { .. } => tonic::Code::Unavailable,
+            Error::RateLimited { .. } => tonic::Code::ResourceExhausted,
+            Error::InvalidInput(_) => tonic::Code::InvalidArgument,
+            Error::AuthFailed(_) => tonic::Code::Unauthenticated,
+            Error::Timeout(_) => tonic::Code::DeadlineExceeded,
+            Error::Internal(_) => tonic::Code::Internal,
+        }
+    }
+
+    // Errors that crossed a `Box<dyn Error>` boundary keep their kind when
+    // they were one of ours or one of the types classified below
+    fn from_dyn(error: &(dyn StdError + 'static)) -> Self {
+        if let Some(error) = error.downcast_ref::<Error>() {
+            error.clone()
+        } else if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            classify_reqwest(error)
+        } else if let Some(error) = error.downcast_ref::<std::io::Error>() {
+            classify_io(error)
+        } else if let Some(error) = error.downcast_ref::<serde_json::Error>() {
+            Error::InvalidInput(error.to_string())
+        } else if error.is::<tokio::time::error::Elapsed>() {
+            Error::Timeout(error.to_string())
+        } else {
+            Error::Internal(error.to_string())
+        }
+    }
+}
+
+fn classify_reqwest(error: &reqwest::Error) -> Error {
+    if error.is_timeout() {
+        Error::Timeout(error.to_string())
+    } else if let Some(status) = error.status() {
+        Error::from_status(status.as_u16(), error)
+    } else if error.is_builder() {
+        Error::InvalidInput(error.to_string())
+    } else if error.is_decode() {
+        Error::Internal(error.to_string())
+    } else {
+        Error::transient(error)
+    }
+}
+
+fn classify_io(error: &std::io::Error) -> Error {
+    use std::io::ErrorKind;
+    match error.kind() {
+        ErrorKind::TimedOut => Error::Timeout(error.to_string()),
+        ErrorKind::ConnectionRefused
+        | ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::NotConnected
+        | ErrorKind::BrokenPipe
+        | ErrorKind::Interrupted
+        | ErrorKind::WouldBlock => Error::transient(error),
+        ErrorKind::NotFound | ErrorKind::InvalidInput | ErrorKind::InvalidData => Error::InvalidInput(error.to_string()),
+        ErrorKind::PermissionDenied => Error::AuthFailed(error.to_string()),
+        _ => Error::Internal(error.to_string()),
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        classify_reqwest(&error)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        classify_io(&error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::InvalidInput(error.to_string())
+    }
+}
+
+impl From<tokio::time::error::Elapsed> for Error {
+    fn from(error: tokio::time::error::Elapsed) -> Self {
+        Error::Timeout(error.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Internal(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Internal(message.to_string())
+    }
+}
+
+impl From<Box<dyn StdError>> for Error {
+    fn from(error: Box<dyn StdError>) -> Self {
+        Error::from_dyn(error.as_ref())
+    }
+}
+
+impl From<Box<dyn StdError + Send + Sync>> for Error {
+    fn from(error: Box<dyn StdError + Send + Sync>) -> Self {
+        Error::from_dyn(error.as_ref())
+    }
+}
+
+impl From<Error> for tonic::Status {
+    fn from(error: Error) -> Self {
+        tonic::Status::new(error.grpc_code(), error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_classification() {
+        assert_eq!(Error::from_status(401, "bad token").kind(), "auth_failed");
+        assert_eq!(Error::from_status(429, "slow down").kind(), "rate_limited");
+        assert_eq!(Error::from_status(422, "bad field").kind(), "invalid_input");
+        assert_eq!(Error::from_status(504, "gateway").kind(), "timeout");
+        assert_eq!(Error::from_status(502, "gateway").kind(), "transient");
+
+        assert!(Error::from_status(503, "down").is_retryable());
+        assert!(!Error::from_status(400, "bad").is_retryable());
+        assert_eq!(Error::invalid_input("x").http_status(), 400);
+    }
+
+    #[test]
+    fn test_kind_survives_boxing() {
+        let boxed: Box<dyn StdError> = Box::new(Error::rate_limited("quota", Some(Duration::from_secs(5))));
+        let error = Error::from(boxed);
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(5)));
+
+        let io: Box<dyn StdError + Send + Sync> = Box::new(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        assert_eq!(Error::from(io).kind(), "timeout");
+
+        let other: Box<dyn StdError> = "plain message".into();
+        assert_eq!(Error::from(other), Error::internal("plain message"));
+    }
+
+    #[test]
+    fn test_context_keeps_kind() {
+        let error = Error::transient("connection reset")
+            .with_retry_after(Duration::from_secs(2))
+            .context("Compilation failed");
+        assert_eq!(error.to_string(), "Compilation failed: connection reset");
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,440 @@
+pub mod signing;
+pub mod store;
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub use signing::{KmsManifestSigner, ManifestSigner};
+pub use store::{BundleStore, UploadedBundle};
+
+// A bundle is a tar.gz with a single top-level directory named after the
+// bundle id, holding `manifest.json`, its detached signature and the
+// entries under one directory per kind. The manifest lists every entry's
+// SHA-256, so verifying the signature over the manifest covers the whole
+// bundle. Archives are deterministic: the same entries and creation time
+// always produce the same bytes.
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const SIGNATURE_FILE: &str = "manifest.sig.json";
+pub const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Archive error: {0}")]
+    Archive(#[from] std::io::Error),
+
+    #[error("Entry {0} was added twice")]
+    DuplicateEntry(String),
+
+    #[error("Signing failed: {0}")]
+    Signing(String),
+
+    #[error("Upload failed: {0}")]
+    Upload(String),
+
+    #[error("Bundle failed verification: {0}")]
+    Verification(String),
+}
+
+impl From<ExportError> for spec_to_proof_error::Error {
+    fn from(error: ExportError) -> Self {
+        use spec_to_proof_error::Error;
+        match error {
+            ExportError::Signing(_) | ExportError::Upload(_) => Error::transient(error),
+            ExportError::DuplicateEntry(_) | ExportError::Verification(_) => Error::invalid_input(error),
+            ExportError::Serialization(_) | ExportError::Archive(_) => Error::internal(error),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ExportError>;
+
+/// What an entry holds, which also decides its directory in the bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    SpecSnapshot,
+    Invariants,
+    LeanSource,
+    ProofOutput,
+    SigstoreEntry,
+}
+
+impl EntryKind {
+    pub fn directory(&self) -> &'static str {
+        match self {
+            EntryKind::SpecSnapshot => "specs",
+            EntryKind::Invariants => "invariants",
+            EntryKind::LeanSource => "lean",
+            EntryKind::ProofOutput => "proofs",
+            EntryKind::SigstoreEntry => "sigstore",
+        }
+    }
+}
+
+/// The pull request a bundle documents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PullRequestRef {
+    /// "owner/name"
+    pub repository: String,
+    pub number: u64,
+    pub head_sha: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Relative to the bundle directory, e.g. "lean/refund_bound.lean"
+    pub path: String,
+    pub kind: EntryKind,
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub bundle_id: String,
+    pub pull_request: PullRequestRef,
+    pub created_at: DateTime<Utc>,
+    /// Sorted by path
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Detached signature over the exact bytes of `manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestSignature {
+    pub algorithm: String,
+    pub key_id: String,
+    pub manifest_sha256: String,
+    /// Base64
+    pub signature: String,
+}
+
+/// Collects the files of a bundle before it is signed and archived
+#[derive(Debug, Clone)]
+pub struct BundleBuilder {
+    pull_request: PullRequestRef,
+    entries: BTreeMap<String, (EntryKind, Vec<u8>)>,
+}
+
+impl BundleBuilder {
+    pub fn new(pull_request: PullRequestRef) -> Self {
+        Self {
+            pull_request,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `contents` as `<kind directory>/<name>`; characters other than
+    /// letters, digits, '.', '-' and '_' in `name` become '_'
+    pub fn add_bytes(&mut self, kind: EntryKind, name: &str, contents: impl Into<Vec<u8>>) -> Result<&mut Self> {
+        let path = format!("{}/{}", kind.directory(), entry_name(name));
+        if self.entries.contains_key(&path) {
+            return Err(ExportError::DuplicateEntry(path));
+        }
+        self.entries.insert(path, (kind, contents.into()));
+        Ok(self)
+    }
+
+    /// Adds `value` as pretty-printed JSON
+    pub fn add_json<T: Serialize>(&mut self, kind: EntryKind, name: &str, value: &T) -> Result<&mut Self> {
+        self.add_bytes(kind, name, serde_json::to_vec_pretty(value)?)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn paths(&self) -> Vec<&str> {
+        self.entries.keys().map(String::as_str).collect()
+    }
+
+    /// Builds the manifest, signs it and writes the archive
+    pub async fn finish(self, signer: &dyn ManifestSigner, created_at: DateTime<Utc>) -> Result<ExportBundle> {
+        let entries: Vec<ManifestEntry> = self.entries
+            .iter()
+            .map(|(path, (kind, contents))| ManifestEntry {
+                path: path.clone(),
+                kind: *kind,
+                sha256: sha256_hex(contents),
+                size: contents.len() as u64,
+            })
+            .collect();
+        let manifest = Manifest {
+            format_version: MANIFEST_FORMAT_VERSION,
+            bundle_id: bundle_id(&self.pull_request, &entries),
+            pull_request: self.pull_request,
+            created_at,
+            entries,
+        };
+
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        let signature = ManifestSignature {
+            algorithm: signer.algorithm().to_string(),
+            key_id: signer.key_id().to_string(),
+            manifest_sha256: sha256_hex(&manifest_json),
+            signature: STANDARD.encode(signer.sign(&manifest_json).await?),
+        };
+
+        let mut files = vec![
+            (MANIFEST_FILE.to_string(), manifest_json),
+            (SIGNATURE_FILE.to_string(), serde_json::to_vec_pretty(&signature)?),
+        ];
+        files.extend(self.entries.into_iter().map(|(path, (_, contents))| (path, contents)));
+        let archive = write_archive(&manifest.bundle_id, created_at, &files)?;
+
+        Ok(ExportBundle { manifest, signature, archive })
+    }
+}
+
+/// A signed bundle and its tar.gz bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportBundle {
+    pub manifest: Manifest,
+    pub signature: ManifestSignature,
+    pub archive: Vec<u8>,
+}
+
+impl ExportBundle {
+    pub fn file_name(&self) -> String {
+        format!("{}.tar.gz", self.manifest.bundle_id)
+    }
+
+    pub fn archive_sha256(&self) -> String {
+        sha256_hex(&self.archive)
+    }
+}
+
+/// Checks a downloaded bundle: the manifest signature, and that the
+/// archive holds exactly the manifest's entries with matching hashes
+pub async fn verify_archive(archive: &[u8], signer: &dyn ManifestSigner) -> Result<Manifest> {
+    let mut files = read_archive(archive)?;
+    let fail = |message: String| ExportError::Verification(message);
+
+    let manifest_json = files.remove(MANIFEST_FILE).ok_or_else(|| fail("missing manifest".to_string()))?;
+    let signature: ManifestSignature = serde_json::from_slice(
+        &files.remove(SIGNATURE_FILE).ok_or_else(|| fail("missing manifest signature".to_string()))?,
+    )?;
+    let signature_bytes = STANDARD.decode(&signature.signature)
+        .map_err(|e| fail(format!("signature is not base64: {}", e)))?;
+    if signature.key_id != signer.key_id() {
+        return Err(fail(format!("signed with {}, not {}", signature.key_id, signer.key_id())));
+    }
+    if !signer.verify(&manifest_json, &signature_bytes).await? {
+        return Err(fail("manifest signature does not match".to_string()));
+    }
+
+    verify_entries(&manifest_json, files)
+}
+
+/// Checks the archive against its manifest without verifying the
+/// signature, for offline use where the signing key is unreachable. Only
+/// detects accidental corruption: anyone can rewrite an unsigned manifest.
+pub fn verify_archive_hashes(archive: &[u8]) -> Result<Manifest> {
+    let mut files = read_archive(archive)?;
+    let fail = |message: String| ExportError::Verification(message);
+
+    let manifest_json = files.remove(MANIFEST_FILE).ok_or_else(|| fail("missing manifest".to_string()))?;
+    if let Some(signature) = files.remove(SIGNATURE_FILE) {
+        let signature: ManifestSignature = serde_json::from_slice(&signature)?;
+        if signature.manifest_sha256 != sha256_hex(&manifest_json) {
+            return Err(fail("manifest does not match the signed manifest hash".to_string()));
+        }
+    }
+
+    verify_entries(&manifest_json, files)
+}
+
+// The archive must hold exactly the manifest's entries, with matching hashes
+fn verify_entries(manifest_json: &[u8], mut files: BTreeMap<String, Vec<u8>>) -> Result<Manifest> {
+    let fail = |message: String| ExportError::Verification(message);
+
+    let manifest: Manifest = serde_json::from_slice(manifest_json)?;
+    for entry in &manifest.entries {
+        let contents = files.remove(&entry.path).ok_or_else(|| fail(format!("missing {}", entry.path)))?;
+        if sha256_hex(&contents) != entry.sha256 {
+            return Err(fail(format!("{} does not match its manifest hash", entry.path)));
+        }
+    }
+    if let Some(path) = files.keys().next() {
+        return Err(fail(format!("{} is not listed in the manifest", path)));
+    }
+    Ok(manifest)
+}
+
+fn write_archive(root: &str, created_at: DateTime<Utc>, files: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(created_at.timestamp().max(0) as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, format!("{}/{}", root, path), contents.as_slice())?;
+    }
+    Ok(archive.into_inner()?.finish()?)
+}
+
+// Files keyed by path relative to the bundle directory
+fn read_archive(archive: &[u8]) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut archive = tar::Archive::new(GzDecoder::new(archive));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        let relative = path.split_once('/').map(|(_, relative)| relative.to_string()).unwrap_or(path);
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.insert(relative, contents);
+    }
+    Ok(files)
+}
+
+// Identifies the pull request and the exact contents, so re-exporting
+// unchanged artifacts gives the same id
+fn bundle_id(pull_request: &PullRequestRef, entries: &[ManifestEntry]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(pull_request.head_sha.as_bytes());
+    for entry in entries {
+        hasher.update(entry.path.as_bytes());
+        hasher.update([0]);
+        hasher.update(entry.sha256.as_bytes());
+    }
+    format!(
+        "{}-pr{}-{}",
+        entry_name(&pull_request.repository.replace('/', "-")),
+        pull_request.number,
+        &hex::encode(hasher.finalize())[..12]
+    )
+}
+
+fn entry_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    // No hidden files or parent-directory names
+    match name.trim_start_matches('.') {
+        "" => "_".to_string(),
+        name => name.to_string(),
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    // Keyed hash standing in for an asymmetric signature
+    struct TestSigner(&'static str);
+
+    #[async_trait]
+    impl ManifestSigner for TestSigner {
+        fn key_id(&self) -> &str {
+            self.0
+        }
+
+        fn algorithm(&self) -> &str {
+            "TEST_SHA_256"
+        }
+
+        async fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+            Ok(Sha256::new().chain_update(self.0).chain_update(message).finalize().to_vec())
+        }
+
+        async fn verify(&self, message: &[u8], signature: &[u8]) -> Result<bool> {
+            Ok(self.sign(message).await? == signature)
+        }
+    }
+
+    fn builder() -> BundleBuilder {
+        let mut builder = BundleBuilder::new(PullRequestRef {
+            repository: "acme/payments".to_string(),
+            number: 42,
+            head_sha: "0123abcd".to_string(),
+        });
+        builder
+            .add_bytes(EntryKind::SpecSnapshot, "refunds.md", "Refunds never exceed the charge.")
+            .unwrap()
+            .add_json(EntryKind::Invariants, "refunds.json", &serde_json::json!([{"id": "inv-1"}]))
+            .unwrap()
+            .add_bytes(EntryKind::LeanSource, "refund_bound.lean", "theorem refund_bound : True := trivial")
+            .unwrap();
+        builder
+    }
+
+    fn created_at() -> DateTime<Utc> {
+        "2024-03-01T12:00:00Z".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bundle_round_trip() {
+        let bundle = builder().finish(&TestSigner("key-1"), created_at()).await.unwrap();
+
+        assert!(bundle.manifest.bundle_id.starts_with("acme-payments-pr42-"));
+        let paths: Vec<&str> = bundle.manifest.entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, vec!["invariants/refunds.json", "lean/refund_bound.lean", "specs/refunds.md"]);
+        assert_eq!(bundle.signature.key_id, "key-1");
+
+        let manifest = verify_archive(&bundle.archive, &TestSigner("key-1")).await.unwrap();
+        assert_eq!(manifest, bundle.manifest);
+        assert!(verify_archive(&bundle.archive, &TestSigner("key-2")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_archives_are_deterministic() {
+        let first = builder().finish(&TestSigner("key-1"), created_at()).await.unwrap();
+        let second = builder().finish(&TestSigner("key-1"), created_at()).await.unwrap();
+        assert_eq!(first.archive_sha256(), second.archive_sha256());
+
+        let mut changed = builder();
+        changed.add_bytes(EntryKind::ProofOutput, "refund_bound.json", "{}").unwrap();
+        let changed = changed.finish(&TestSigner("key-1"), created_at()).await.unwrap();
+        assert_ne!(changed.manifest.bundle_id, first.manifest.bundle_id);
+    }
+
+    #[tokio::test]
+    async fn test_tampered_entries_fail_verification() {
+        let bundle = builder().finish(&TestSigner("key-1"), created_at()).await.unwrap();
+        let mut files: Vec<(String, Vec<u8>)> = read_archive(&bundle.archive).unwrap().into_iter().collect();
+        for (path, contents) in &mut files {
+            if path == "lean/refund_bound.lean" {
+                *contents = b"theorem refund_bound : True := sorry".to_vec();
+            }
+        }
+        let tampered = write_archive(&bundle.manifest.bundle_id, created_at(), &files).unwrap();
+        assert!(matches!(
+            verify_archive(&tampered, &TestSigner("key-1")).await,
+            Err(ExportError::Verification(_))
+        ));
+
+        assert_eq!(verify_archive_hashes(&bundle.archive).unwrap(), bundle.manifest);
+        assert!(matches!(verify_archive_hashes(&tampered), Err(ExportError::Verification(_))));
+    }
+
+    #[test]
+    fn test_entry_names_stay_inside_their_directory() {
+        let mut builder = builder();
+        builder.add_bytes(EntryKind::SigstoreEntry, "../../etc/passwd", "x").unwrap();
+        assert!(builder.paths().contains(&"sigstore/_.._etc_passwd"));
+        assert!(matches!(
+            builder.add_bytes(EntryKind::SpecSnapshot, "refunds.md", "again"),
+            Err(ExportError::DuplicateEntry(_))
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::{MessageType, SigningAlgorithmSpec};
+use aws_sdk_kms::Client as KmsClient;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use sha2::{Digest, Sha256};
+
+use crate::{ExportError, Result};
+
+/// Signs bundle manifests and checks those signatures
+#[async_trait]
+pub trait ManifestSigner: Send + Sync {
+    fn key_id(&self) -> &str;
+
+    fn algorithm(&self) -> &str;
+
+    async fn sign(&self, message: &[u8]) -> Result<Vec<u8>>;
+
+    async fn verify(&self, message: &[u8], signature: &[u8]) -> Result<bool>;
+}
+
+/// Signs with an asymmetric KMS key using ECDSA over SHA-256. The private
+/// key never leaves KMS; auditors verify with the key's public half from
+/// `GetPublicKey`.
+#[derive(Debug, Clone)]
+pub struct KmsManifestSigner {
+    kms_client: KmsClient,
+    key_id: String,
+}
+
+impl KmsManifestSigner {
+    pub fn new(kms_client: KmsClient, key_id: impl Into<String>) -> Self {
+        Self {
+            kms_client,
+            key_id: key_id.into(),
+        }
+    }
+
+    /// The key's public half as PEM, for verifiers outside AWS
+    pub async fn public_key_pem(&self) -> Result<String> {
+        let output = self.kms_client
+            .get_public_key()
+            .key_id(&self.key_id)
+            .send()
+            .await
+            .map_err(|e| ExportError::Signing(e.into_service_error().to_string()))?;
+        let der = output
+            .public_key()
+            .ok_or_else(|| ExportError::Signing("no public key returned".to_string()))?;
+        Ok(public_key_pem(der.as_ref()))
+    }
+}
+
+fn public_key_pem(der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let mut pem = String::from("-----BEGIN PUBLIC KEY-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str("-----END PUBLIC KEY-----\n");
+    pem
+}
+
+#[async_trait]
+impl ManifestSigner for KmsManifestSigner {
+    fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    fn algorithm(&self) -> &str {
+        SigningAlgorithmSpec::EcdsaSha256.as_str()
+    }
+
+    // KMS takes raw messages only up to 4 KB, so the digest is sent instead
+    async fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let output = self.kms_client
+            .sign()
+            .key_id(&self.key_id)
+            .message(Blob::new(Sha256::digest(message).to_vec()))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(SigningAlgorithmSpec::EcdsaSha256)
+            .send()
+            .await
+            .map_err(|e| ExportError::Signing(e.into_service_error().to_string()))?;
+        output
+            .signature()
+            .map(|signature| signature.as_ref().to_vec())
+            .ok_or_else(|| ExportError::Signing("no signature returned".to_string()))
+    }
+
+    async fn verify(&self, message: &[u8], signature: &[u8]) -> Result<bool> {
+        let result = self.kms_client
+            .verify()
+            .key_id(&self.key_id)
+            .message(Blob::new(Sha256::digest(message).to_vec()))
+            .message_type(MessageType::Digest)
+            .signature(Blob::new(signature))
+            .signing_algorithm(SigningAlgorithmSpec::EcdsaSha256)
+            .send()
+            .await;
+        match result {
+            Ok(output) => Ok(output.signature_valid()),
+            Err(e) => {
+                let e = e.into_service_error();
+                // KMS reports a mismatch as an error rather than `false`
+                if e.is_kms_invalid_signature_exception() {
+                    Ok(false)
+                } else {
+                    Err(ExportError::Signing(e.to_string()))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_key_pem_wraps_lines() {
+        let pem = public_key_pem(&[0u8; 91]);
+        let lines: Vec<_> = pem.lines().collect();
+        assert_eq!(lines.first(), Some(&"-----BEGIN PUBLIC KEY-----"));
+        assert_eq!(lines.last(), Some(&"-----END PUBLIC KEY-----"));
+        assert_eq!(lines[1].len(), 64);
+        assert_eq!(lines.len(), 4);
+    }
+}
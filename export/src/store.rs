@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::ServerSideEncryption;
+use aws_sdk_s3::Client as S3Client;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{ExportBundle, ExportError, PullRequestRef, Result};
+
+/// SigV4 presigned URLs are valid for at most seven days
+pub const MAX_URL_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Object metadata holding the SHA-256 of the bundle's manifest
+pub const MANIFEST_SHA256_METADATA_KEY: &str = "manifest-sha256";
+
+/// Where an uploaded bundle lives and a temporary link to download it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadedBundle {
+    pub bundle_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub archive_sha256: String,
+    pub download_url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Stores bundles in S3 under `<prefix><owner>/<repo>/pr-<number>/`
+#[derive(Debug, Clone)]
+pub struct BundleStore {
+    s3_client: S3Client,
+    bucket: String,
+    key_prefix: String,
+    kms_key_id: Option<String>,
+    url_expiry: Duration,
+}
+
+impl BundleStore {
+    pub fn new(s3_client: S3Client, bucket: impl Into<String>, key_prefix: impl Into<String>) -> Self {
+        Self {
+            s3_client,
+            bucket: bucket.into(),
+            key_prefix: key_prefix.into(),
+            kms_key_id: None,
+            url_expiry: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// Encrypts stored bundles with this KMS key
+    pub fn with_kms_key(mut self, key_id: impl Into<String>) -> Self {
+        self.kms_key_id = Some(key_id.into());
+        self
+    }
+
+    pub fn with_url_expiry(mut self, expiry: Duration) -> Self {
+        self.url_expiry = expiry.min(MAX_URL_EXPIRY);
+        self
+    }
+
+    pub fn key(&self, pull_request: &PullRequestRef, bundle_id: &str) -> String {
+        format!("{}{}", self.key_prefix, bundle_key(pull_request, bundle_id))
+    }
+
+    pub async fn upload(&self, bundle: &ExportBundle) -> Result<UploadedBundle> {
+        let key = self.key(&bundle.manifest.pull_request, &bundle.manifest.bundle_id);
+        let mut request = self.s3_client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .body(ByteStream::from(bundle.archive.clone()))
+            .content_type("application/gzip")
+            .content_disposition(format!("attachment; filename=\"{}\"", bundle.file_name()))
+            .metadata(MANIFEST_SHA256_METADATA_KEY, &bundle.signature.manifest_sha256);
+        if let Some(key_id) = &self.kms_key_id {
+            request = request
+                .server_side_encryption(ServerSideEncryption::AwsKms)
+                .ssekms_key_id(key_id);
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| ExportError::Upload(e.into_service_error().to_string()))?;
+
+        let (download_url, expires_at) = self.download_url(&key).await?;
+        Ok(UploadedBundle {
+            bundle_id: bundle.manifest.bundle_id.clone(),
+            bucket: self.bucket.clone(),
+            key,
+            archive_sha256: bundle.archive_sha256(),
+            download_url,
+            expires_at,
+        })
+    }
+
+    /// A fresh download link for a bundle uploaded earlier
+    pub async fn download_url(&self, key: &str) -> Result<(String, DateTime<Utc>)> {
+        let presigning = PresigningConfig::expires_in(self.url_expiry).map_err(|e| ExportError::Upload(e.to_string()))?;
+        let presigned = self.s3_client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(presigning)
+            .await
+            .map_err(|e| ExportError::Upload(e.into_service_error().to_string()))?;
+        let expires_at = Utc::now() + chrono::Duration::from_std(self.url_expiry).unwrap_or_default();
+        Ok((presigned.uri().to_string(), expires_at))
+    }
+}
+
+fn bundle_key(pull_request: &PullRequestRef, bundle_id: &str) -> String {
+    format!("{}/pr-{}/{}.tar.gz", pull_request.repository, pull_request.number, bundle_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_key() {
+        let pull_request = PullRequestRef {
+            repository: "acme/payments".to_string(),
+            number: 42,
+            head_sha: "0123abcd".to_string(),
+        };
+        assert_eq!(
+            bundle_key(&pull_request, "acme-payments-pr42-3f1c2a9b4d5e"),
+            "acme/payments/pr-42/acme-payments-pr42-3f1c2a9b4d5e.tar.gz"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+// The gateway compiles its own copy of the service protos so the generated
+// messages can derive serde and utoipa; the services' own generated code
+// stays free of both.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut builder = tonic_build::configure()
+        .build_server(false)
+        .build_client(true)
+        .compile_well_known_types(true)
+        .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]")
+        .type_attribute(".", "#[serde(rename_all = \"camelCase\")]")
+        .message_attribute(".", "#[serde(default)]");
+
+    // nlp.v1 and proof.v1 both define TokenUsage; the proof one is inlined
+    // so the two don't claim the same component name in the OpenAPI spec
+    for field in [
+        ".spec_to_proof.proof.v1.CompilationMetadata.token_usage",
+        ".spec_to_proof.proof.v1.ProofMetadata.token_usage",
+        ".spec_to_proof.proof.v1.ProofRunPlan.estimated_token_usage",
+        ".spec_to_proof.proof.v1.InvariantPlan.estimated_token_usage",
+    ] {
+        builder = builder.field_attribute(field, "#[schema(inline)]");
+    }
+
+    builder.compile(
+        &[
+            "../proto/spec_to_proof.proto",
+            "../nlp/proto/nlp.proto",
+            "../proof/proto/proof.proto",
+        ],
+        &["../proto", "../nlp/proto", "../proof/proto", ".."],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,201 @@
+pub mod nlp;
+pub mod openapi;
+pub mod proof;
+pub mod review;
+
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tonic::transport::{Channel, Endpoint};
+use utoipa::ToSchema;
+
+use crate::proto::nlp::v1::nlp_service_client::NlpServiceClient;
+use crate::proto::spec_to_proof::proof::v1::proof_service_client::ProofServiceClient;
+use crate::proto::spec_to_proof::v1::spec_to_proof_service_client::SpecToProofServiceClient;
+
+// Generated from the same .proto files as the services, with serde and
+// utoipa derives added by build.rs
+pub mod proto {
+    pub mod google {
+        pub mod protobuf {
+            tonic::include_proto!("google.protobuf");
+        }
+    }
+
+    pub mod nlp {
+        pub mod v1 {
+            tonic::include_proto!("nlp.v1");
+        }
+    }
+
+    pub mod spec_to_proof {
+        pub mod v1 {
+            tonic::include_proto!("spec_to_proof.v1");
+        }
+
+        pub mod proof {
+            pub mod v1 {
+                tonic::include_proto!("spec_to_proof.proof.v1");
+            }
+        }
+    }
+}
+
+/// Request headers passed through to the gRPC services as metadata, so
+/// their authentication and tenant scoping apply to REST callers too
+pub const FORWARDED_HEADERS: &[&str] = &["authorization", "x-tenant-id", "x-request-id"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayConfig {
+    pub nlp_endpoint: String,
+    pub proof_endpoint: String,
+    /// SpecToProofService serving invariant review; the review routes
+    /// answer 503 when unset
+    #[serde(default)]
+    pub review_endpoint: Option<String>,
+    /// Origins allowed to call the gateway from a browser; empty allows any
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
+
+/// gRPC clients shared by the handlers. Channels connect lazily, so the
+/// gateway starts even when a service is not up yet.
+#[derive(Debug, Clone)]
+pub struct GatewayState {
+    pub nlp: NlpServiceClient<Channel>,
+    pub proof: ProofServiceClient<Channel>,
+    pub review: Option<SpecToProofServiceClient<Channel>>,
+}
+
+impl GatewayState {
+    pub fn connect(config: &GatewayConfig) -> Result<Self, tonic::transport::Error> {
+        let channel = |endpoint: &str| Endpoint::from_shared(endpoint.to_string()).map(|e| e.connect_lazy());
+        Ok(Self {
+            nlp: NlpServiceClient::new(channel(&config.nlp_endpoint)?),
+            proof: ProofServiceClient::new(channel(&config.proof_endpoint)?),
+            review: config
+                .review_endpoint
+                .as_deref()
+                .map(channel)
+                .transpose()?
+                .map(SpecToProofServiceClient::new),
+        })
+    }
+}
+
+pub fn router(state: GatewayState) -> Router {
+    Router::new()
+        .merge(nlp::routes())
+        .merge(proof::routes())
+        .merge(review::routes())
+        .route("/openapi.json", get(openapi::serve))
+        .route("/healthz", get(|| async { "ok" }))
+        .with_state(state)
+}
+
+/// Wraps a message for a gRPC call, copying the forwarded headers
+pub fn grpc_request<T>(headers: &HeaderMap, message: T) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    for name in FORWARDED_HEADERS {
+        let value = headers.get(*name).and_then(|v| v.to_str().ok()).and_then(|v| v.parse().ok());
+        if let Some(value) = value {
+            request.metadata_mut().insert(*name, value);
+        }
+    }
+    request
+}
+
+/// Error body in the shape grpc-gateway uses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorBody {
+    /// gRPC status code
+    pub code: i32,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct ApiError(pub tonic::Status);
+
+impl From<tonic::Status> for ApiError {
+    fn from(status: tonic::Status) -> Self {
+        ApiError(status)
+    }
+}
+
+/// The HTTP status grpc-gateway answers for a gRPC status code
+pub fn http_status(code: tonic::Code) -> StatusCode {
+    use tonic::Code;
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => StatusCode::BAD_REQUEST,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Cancelled => StatusCode::from_u16(499).unwrap_or(StatusCode::BAD_REQUEST),
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            code: self.0.code() as i32,
+            message: self.0.message().to_string(),
+        };
+        (http_status(self.0.code()), Json(body)).into_response()
+    }
+}
+
+pub type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn state() -> GatewayState {
+        GatewayState::connect(&GatewayConfig {
+            nlp_endpoint: "http://127.0.0.1:1".to_string(),
+            proof_endpoint: "http://127.0.0.1:1".to_string(),
+            review_endpoint: None,
+            allowed_origins: vec![],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_status_mapping() {
+        assert_eq!(http_status(tonic::Code::InvalidArgument), StatusCode::BAD_REQUEST);
+        assert_eq!(http_status(tonic::Code::ResourceExhausted), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(http_status(tonic::Code::Unavailable), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn test_forwards_auth_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer token".parse().unwrap());
+        headers.insert("cookie", "session=1".parse().unwrap());
+
+        let request = grpc_request(&headers, ());
+        assert_eq!(request.metadata().get("authorization").unwrap(), "Bearer token");
+        assert!(request.metadata().get("cookie").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_review_routes_need_a_backend() {
+        let response = router(state())
+            .oneshot(Request::get("/v1/invariants/inv_1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,64 @@
+use std::net::SocketAddr;
+
+use axum::http::HeaderValue;
+use clap::Parser;
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::trace::TraceLayer;
+use tracing::info;
+
+use gateway::{router, GatewayConfig, GatewayState};
+
+/// REST/JSON facade for the UI and third-party tooling; every route is a
+/// unary call to the nlp, proof or review gRPC service
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long, env = "GATEWAY_ADDR", default_value = "0.0.0.0:8090")]
+    addr: SocketAddr,
+
+    #[arg(long, env = "NLP_ENDPOINT", default_value = "http://localhost:50051")]
+    nlp_endpoint: String,
+
+    #[arg(long, env = "PROOF_ENDPOINT", default_value = "http://localhost:50052")]
+    proof_endpoint: String,
+
+    #[arg(long, env = "REVIEW_ENDPOINT")]
+    review_endpoint: Option<String>,
+
+    /// Comma-separated browser origins; any origin when unset
+    #[arg(long, env = "GATEWAY_ALLOWED_ORIGINS", value_delimiter = ',')]
+    allowed_origins: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    let config = GatewayConfig {
+        nlp_endpoint: args.nlp_endpoint,
+        proof_endpoint: args.proof_endpoint,
+        review_endpoint: args.review_endpoint,
+        allowed_origins: args.allowed_origins,
+    };
+    let state = GatewayState::connect(&config)?;
+
+    let cors = if config.allowed_origins.is_empty() {
+        CorsLayer::permissive()
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| origin.parse::<HeaderValue>())
+            .collect::<Result<Vec<_>, _>>()?;
+        CorsLayer::new().allow_origin(origins).allow_methods(Any).allow_headers(Any)
+    };
+
+    let app = router(state).layer(cors).layer(TraceLayer::new_for_http());
+
+    info!("REST gateway listening on {}", args.addr);
+    info!("nlp: {}, proof: {}", config.nlp_endpoint, config.proof_endpoint);
+    let listener = tokio::net::TcpListener::bind(args.addr).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::{Json, Router};
+
+use crate::proto::nlp::v1::{
+    BackfillRequest, BackfillResponse, DiffInvariantSetsRequest, DiffInvariantSetsResponse, ExtractInvariantsRequest,
+    ExtractInvariantsResponse,
+};
+use crate::{grpc_request, ApiResult, ErrorBody, GatewayState};
+
+pub fn routes() -> Router<GatewayState> {
+    Router::new()
+        .route("/v1/nlp/extract", post(extract_invariants))
+        .route("/v1/nlp/diff", post(diff_invariant_sets))
+        .route("/v1/nlp/backfill", post(backfill))
+}
+
+/// Extracts invariants from a spec document
+#[utoipa::path(
+    post,
+    path = "/v1/nlp/extract",
+    tag = "nlp",
+    request_body = ExtractInvariantsRequest,
+    responses(
+        (status = 200, body = ExtractInvariantsResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn extract_invariants(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<ExtractInvariantsRequest>,
+) -> ApiResult<ExtractInvariantsResponse> {
+    let response = state.nlp.clone().extract_invariants(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+/// Classifies the changes between two versions of a document's invariants
+#[utoipa::path(
+    post,
+    path = "/v1/nlp/diff",
+    tag = "nlp",
+    request_body = DiffInvariantSetsRequest,
+    responses(
+        (status = 200, body = DiffInvariantSetsResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn diff_invariant_sets(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<DiffInvariantSetsRequest>,
+) -> ApiResult<DiffInvariantSetsResponse> {
+    let response = state.nlp.clone().diff_invariant_sets(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+/// Re-extracts stored documents with the current prompts and reports how
+/// their invariants would change
+#[utoipa::path(
+    post,
+    path = "/v1/nlp/backfill",
+    tag = "nlp",
+    request_body = BackfillRequest,
+    responses(
+        (status = 200, body = BackfillResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn backfill(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<BackfillRequest>,
+) -> ApiResult<BackfillResponse> {
+    let response = state.nlp.clone().backfill(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
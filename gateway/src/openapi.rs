@@ -0,0 +1,130 @@
+use axum::Json;
+use utoipa::OpenApi;
+
+use crate::proto::google::protobuf::Timestamp;
+use crate::proto::nlp::v1 as nlp;
+use crate::proto::spec_to_proof::proof::v1 as proof;
+use crate::proto::spec_to_proof::v1 as model;
+use crate::ErrorBody;
+
+// nlp.v1 repeats Variable, SourceSpan and InvariantClassification from
+// spec_to_proof.v1 field for field, so one component serves both
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Spec-to-Proof API", description = "REST/JSON facade over the Spec-to-Proof gRPC services"),
+    paths(
+        crate::nlp::extract_invariants,
+        crate::nlp::diff_invariant_sets,
+        crate::nlp::backfill,
+        crate::proof::compile_invariant_set,
+        crate::proof::generate_proof,
+        crate::proof::plan_proof_run,
+        crate::proof::generate_test_scaffolds,
+        crate::proof::get_proof_analytics,
+        crate::proof::export_audit_bundle,
+        crate::proof::get_proof_transcript,
+        crate::review::list_invariants,
+        crate::review::get_invariant,
+        crate::review::update_invariant,
+    ),
+    components(schemas(
+        ErrorBody,
+        Timestamp,
+        nlp::ExtractInvariantsRequest,
+        nlp::ExtractInvariantsResponse,
+        nlp::ExtractedInvariant,
+        nlp::ExtractionPlan,
+        nlp::ExtractionMetadata,
+        nlp::ChunkProvenance,
+        nlp::ProcessingMetadata,
+        nlp::TokenUsage,
+        nlp::DiffInvariantSetsRequest,
+        nlp::DiffInvariantSetsResponse,
+        nlp::InvariantChange,
+        nlp::BackfillRequest,
+        nlp::BackfillResponse,
+        nlp::DocumentBackfill,
+        model::Invariant,
+        model::InvariantSet,
+        model::Variable,
+        model::SourceSpan,
+        model::InvariantClassification,
+        model::LeanTheorem,
+        model::ProofArtifact,
+        model::ResourceUsage,
+        model::ListInvariantsResponse,
+        proof::CompileInvariantSetRequest,
+        proof::CompileInvariantSetResponse,
+        proof::CompilationOptions,
+        proof::CompilationMetadata,
+        proof::GenerateProofRequest,
+        proof::GenerateProofResponse,
+        proof::ProofOptions,
+        proof::ProofMetadata,
+        proof::PlanProofRunRequest,
+        proof::PlanProofRunResponse,
+        proof::ProofRunPlan,
+        proof::InvariantPlan,
+        proof::LeanResources,
+        proof::GenerateTestScaffoldsRequest,
+        proof::GenerateTestScaffoldsResponse,
+        proof::TestScaffold,
+        proof::ScaffoldedTest,
+        proof::GetProofAnalyticsRequest,
+        proof::GetProofAnalyticsResponse,
+        proof::ProofAnalytics,
+        proof::ProofStats,
+        proof::ExportAuditBundleRequest,
+        proof::ExportAuditBundleResponse,
+        proof::PresignedUrl,
+        proof::GetProofTranscriptResponse,
+        proof::get_proof_transcript_response::Transcript,
+        proof::ProofTranscript,
+        proof::AttemptTranscript,
+    )),
+    tags(
+        (name = "nlp", description = "Invariant extraction"),
+        (name = "proof", description = "Lean compilation and proving"),
+        (name = "review", description = "Invariant review"),
+    )
+)]
+pub struct ApiDoc;
+
+pub async fn serve() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn refs<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(reference)) = map.get("$ref") {
+                    found.push(reference);
+                }
+                map.values().for_each(|v| refs(v, found));
+            }
+            Value::Array(items) => items.iter().for_each(|v| refs(v, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_every_reference_resolves() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let schemas = spec["components"]["schemas"].as_object().unwrap();
+
+        let mut found = Vec::new();
+        refs(&spec, &mut found);
+        assert!(!found.is_empty());
+        for reference in found {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            assert!(schemas.contains_key(name), "unresolved {}", reference);
+        }
+
+        assert!(spec["paths"]["/v1/proof/artifacts/{artifact_id}/transcript"]["get"].is_object());
+    }
+}
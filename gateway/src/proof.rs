@@ -0,0 +1,180 @@
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::proto::spec_to_proof::proof::v1::{
+    CompileInvariantSetRequest, CompileInvariantSetResponse, ExportAuditBundleRequest, ExportAuditBundleResponse,
+    GenerateProofRequest, GenerateProofResponse, GenerateTestScaffoldsRequest, GenerateTestScaffoldsResponse,
+    GetProofAnalyticsRequest, GetProofAnalyticsResponse, GetProofTranscriptRequest, GetProofTranscriptResponse,
+    PlanProofRunRequest, PlanProofRunResponse,
+};
+use crate::{grpc_request, ApiResult, ErrorBody, GatewayState};
+
+pub fn routes() -> Router<GatewayState> {
+    Router::new()
+        .route("/v1/proof/compile", post(compile_invariant_set))
+        .route("/v1/proof/prove", post(generate_proof))
+        .route("/v1/proof/plan", post(plan_proof_run))
+        .route("/v1/proof/test-scaffolds", post(generate_test_scaffolds))
+        .route("/v1/proof/audit-bundles", post(export_audit_bundle))
+        .route("/v1/proof/analytics", post(get_proof_analytics))
+        .route("/v1/proof/artifacts/:artifact_id/transcript", get(get_proof_transcript))
+}
+
+/// Compiles an invariant set into Lean theorems
+#[utoipa::path(
+    post,
+    path = "/v1/proof/compile",
+    tag = "proof",
+    request_body = CompileInvariantSetRequest,
+    responses(
+        (status = 200, body = CompileInvariantSetResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn compile_invariant_set(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<CompileInvariantSetRequest>,
+) -> ApiResult<CompileInvariantSetResponse> {
+    let response = state.proof.clone().compile_invariant_set(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+/// Generates and checks a proof of a Lean theorem
+#[utoipa::path(
+    post,
+    path = "/v1/proof/prove",
+    tag = "proof",
+    request_body = GenerateProofRequest,
+    responses(
+        (status = 200, body = GenerateProofResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn generate_proof(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<GenerateProofRequest>,
+) -> ApiResult<GenerateProofResponse> {
+    let response = state.proof.clone().generate_proof(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+/// Estimates a proof run without calling any model or Lean job
+#[utoipa::path(
+    post,
+    path = "/v1/proof/plan",
+    tag = "proof",
+    request_body = PlanProofRunRequest,
+    responses(
+        (status = 200, body = PlanProofRunResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn plan_proof_run(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<PlanProofRunRequest>,
+) -> ApiResult<PlanProofRunResponse> {
+    let response = state.proof.clone().plan_proof_run(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+/// Property-based test scaffolds with one test per invariant
+#[utoipa::path(
+    post,
+    path = "/v1/proof/test-scaffolds",
+    tag = "proof",
+    request_body = GenerateTestScaffoldsRequest,
+    responses(
+        (status = 200, body = GenerateTestScaffoldsResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn generate_test_scaffolds(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<GenerateTestScaffoldsRequest>,
+) -> ApiResult<GenerateTestScaffoldsResponse> {
+    let response = state.proof.clone().generate_test_scaffolds(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+/// Exports a signed audit bundle for a pull request
+#[utoipa::path(
+    post,
+    path = "/v1/proof/audit-bundles",
+    tag = "proof",
+    request_body = ExportAuditBundleRequest,
+    responses(
+        (status = 200, body = ExportAuditBundleResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn export_audit_bundle(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<ExportAuditBundleRequest>,
+) -> ApiResult<ExportAuditBundleResponse> {
+    let response = state.proof.clone().export_audit_bundle(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+/// Success rate, durations, token cost and retries of finished proofs by
+/// strategy, category, priority and model
+#[utoipa::path(
+    post,
+    path = "/v1/proof/analytics",
+    tag = "proof",
+    request_body = GetProofAnalyticsRequest,
+    responses(
+        (status = 200, body = GetProofAnalyticsResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn get_proof_analytics(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Json(request): Json<GetProofAnalyticsRequest>,
+) -> ApiResult<GetProofAnalyticsResponse> {
+    let response = state.proof.clone().get_proof_analytics(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TranscriptQuery {
+    /// Answer with a presigned S3 URL instead of the transcript itself
+    pub presign: bool,
+    pub url_expiry_seconds: u32,
+}
+
+/// Prompts, completions and diagnostics of every attempt at a proof
+#[utoipa::path(
+    get,
+    path = "/v1/proof/artifacts/{artifact_id}/transcript",
+    tag = "proof",
+    params(("artifact_id" = String, Path), TranscriptQuery),
+    responses(
+        (status = 200, body = GetProofTranscriptResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn get_proof_transcript(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(artifact_id): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+) -> ApiResult<GetProofTranscriptResponse> {
+    let request = GetProofTranscriptRequest {
+        artifact_id,
+        presign: query.presign,
+        url_expiry_seconds: query.url_expiry_seconds,
+    };
+    let response = state.proof.clone().get_proof_transcript(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
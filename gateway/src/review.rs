@@ -0,0 +1,105 @@
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use tonic::transport::Channel;
+use utoipa::IntoParams;
+
+use crate::proto::spec_to_proof::v1::spec_to_proof_service_client::SpecToProofServiceClient;
+use crate::proto::spec_to_proof::v1::{
+    GetInvariantRequest, Invariant, ListInvariantsRequest, ListInvariantsResponse, UpdateInvariantRequest,
+};
+use crate::{grpc_request, ApiError, ApiResult, ErrorBody, GatewayState};
+
+pub fn routes() -> Router<GatewayState> {
+    Router::new()
+        .route("/v1/invariants", get(list_invariants))
+        .route("/v1/invariants/:id", get(get_invariant).put(update_invariant))
+}
+
+fn client(state: &GatewayState) -> Result<SpecToProofServiceClient<Channel>, ApiError> {
+    state
+        .review
+        .clone()
+        .ok_or_else(|| ApiError(tonic::Status::unavailable("No review service is configured")))
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ListInvariantsQuery {
+    pub page_size: i32,
+    pub page_token: String,
+    pub filter: String,
+    pub order_by: String,
+}
+
+/// Lists invariants awaiting or past review
+#[utoipa::path(
+    get,
+    path = "/v1/invariants",
+    tag = "review",
+    params(ListInvariantsQuery),
+    responses(
+        (status = 200, body = ListInvariantsResponse),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn list_invariants(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Query(query): Query<ListInvariantsQuery>,
+) -> ApiResult<ListInvariantsResponse> {
+    let request = ListInvariantsRequest {
+        page_size: query.page_size,
+        page_token: query.page_token,
+        filter: query.filter,
+        order_by: query.order_by,
+    };
+    let response = client(&state)?.list_invariants(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/invariants/{id}",
+    tag = "review",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, body = Invariant),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn get_invariant(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> ApiResult<Invariant> {
+    let response = client(&state)?.get_invariant(grpc_request(&headers, GetInvariantRequest { id })).await?;
+    Ok(Json(response.into_inner()))
+}
+
+/// Records a review decision; the ID in the path wins over the body's
+#[utoipa::path(
+    put,
+    path = "/v1/invariants/{id}",
+    tag = "review",
+    params(("id" = String, Path)),
+    request_body = Invariant,
+    responses(
+        (status = 200, body = Invariant),
+        (status = "default", body = ErrorBody),
+    )
+)]
+pub async fn update_invariant(
+    State(state): State<GatewayState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(invariant): Json<Invariant>,
+) -> ApiResult<Invariant> {
+    let request = UpdateInvariantRequest {
+        invariant: Some(Invariant { id, ..invariant }),
+    };
+    let response = client(&state)?.update_invariant(grpc_request(&headers, request)).await?;
+    Ok(Json(response.into_inner()))
+}
@@ -0,0 +1,223 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+// Liveness says the process is up and serving; it never touches a
+// dependency, so an outage elsewhere does not get healthy pods restarted.
+// Readiness runs every registered dependency check concurrently, each with
+// its own timeout, and reports the latency and outcome of each one.
+
+pub type CheckFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+// Ordered by severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: HealthStatus,
+    /// Whether the service can do useful work without this dependency
+    pub critical: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checks: Vec<CheckResult>,
+}
+
+impl HealthReport {
+    /// Degraded services still take traffic; only a failed critical
+    /// dependency takes the instance out of rotation
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+}
+
+struct Check {
+    name: String,
+    critical: bool,
+    run: Box<dyn Fn() -> CheckFuture + Send + Sync>,
+}
+
+pub struct HealthChecker {
+    checks: Vec<Check>,
+    timeout: Duration,
+    cache_ttl: Duration,
+    // Held while checks run, so concurrent probes share one round
+    last_report: Mutex<Option<(Instant, HealthReport)>>,
+}
+
+impl std::fmt::Debug for HealthChecker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HealthChecker")
+            .field("checks", &self.checks.iter().map(|check| check.name.as_str()).collect::<Vec<_>>())
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HealthChecker {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            checks: Vec::new(),
+            timeout,
+            // Probes typically fire every few seconds per pod; reuse a recent
+            // result rather than hitting every dependency each time
+            cache_ttl: Duration::from_secs(10),
+            last_report: Mutex::new(None),
+        }
+    }
+
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// Registers a dependency check. Failing critical checks make the
+    /// service unhealthy; failing optional ones only degrade it.
+    pub fn with_check<F, Fut>(mut self, name: &str, critical: bool, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.checks.push(Check {
+            name: name.to_string(),
+            critical,
+            run: Box::new(move || -> CheckFuture { Box::pin(check()) }),
+        });
+        self
+    }
+
+    pub fn liveness(&self) -> HealthReport {
+        HealthReport {
+            status: HealthStatus::Healthy,
+            checks: Vec::new(),
+        }
+    }
+
+    pub async fn readiness(&self) -> HealthReport {
+        let mut last_report = self.last_report.lock().await;
+        if let Some((checked_at, report)) = last_report.as_ref() {
+            if checked_at.elapsed() < self.cache_ttl {
+                return report.clone();
+            }
+        }
+
+        let checks = futures::future::join_all(self.checks.iter().map(|check| self.run_check(check))).await;
+        let status = checks
+            .iter()
+            .map(|check| match check.status {
+                HealthStatus::Unhealthy if !check.critical => HealthStatus::Degraded,
+                status => status,
+            })
+            .max()
+            .unwrap_or(HealthStatus::Healthy);
+
+        let report = HealthReport { status, checks };
+        *last_report = Some((Instant::now(), report.clone()));
+        report
+    }
+
+    async fn run_check(&self, check: &Check) -> CheckResult {
+        let start = Instant::now();
+        let outcome = tokio::time::timeout(self.timeout, (check.run)()).await;
+        let latency = start.elapsed();
+
+        let (status, message) = match outcome {
+            // Answering, but slowly enough to eat most of a request budget
+            Ok(Ok(())) if latency > self.timeout / 2 => {
+                (HealthStatus::Degraded, Some(format!("slow response: {}ms", latency.as_millis())))
+            }
+            Ok(Ok(())) => (HealthStatus::Healthy, None),
+            Ok(Err(message)) => (HealthStatus::Unhealthy, Some(message)),
+            Err(_) => (
+                HealthStatus::Unhealthy,
+                Some(format!("timed out after {}ms", self.timeout.as_millis())),
+            ),
+        };
+
+        CheckResult {
+            name: check.name.clone(),
+            status,
+            critical: check.critical,
+            latency_ms: latency.as_millis() as u64,
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn checker() -> HealthChecker {
+        HealthChecker::new(Duration::from_millis(200)).with_cache_ttl(Duration::ZERO)
+    }
+
+    #[tokio::test]
+    async fn test_readiness_aggregates_checks() {
+        let report = checker()
+            .with_check("database", true, || async { Ok(()) })
+            .with_check("redis", false, || async { Err("connection refused".to_string()) })
+            .readiness()
+            .await;
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert!(report.is_ready());
+        assert_eq!(report.checks[1].message.as_deref(), Some("connection refused"));
+
+        let report = checker()
+            .with_check("database", true, || async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .readiness()
+            .await;
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert!(!report.is_ready());
+        assert!(report.checks[0].latency_ms < 1000);
+
+        assert_eq!(checker().readiness().await.status, HealthStatus::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_readiness_results_are_cached() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let checker = HealthChecker::new(Duration::from_millis(200)).with_check("api", true, move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Ok(()) }
+        });
+
+        checker.readiness().await;
+        checker.readiness().await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        // Liveness never runs dependency checks
+        assert!(checker.liveness().checks.is_empty());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+}
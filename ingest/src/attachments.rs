@@ -0,0 +1,291 @@
+use crate::{OAuth2Token, backoff::ExponentialBackoff};
+use crate::proto::spec_to_proof::v1::SpecDocument;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spec_to_proof_error::Error;
+
+// Attachments (PDFs, text files) and embedded images referenced by a spec.
+// Connectors enumerate them as `AttachmentRef`s; allowed types under the
+// size limit are downloaded, their text extracted and appended to the
+// document content between BEGIN/END ATTACHMENT markers.
+
+pub const ATTACHMENT_COUNT_METADATA_KEY: &str = "attachment_count";
+pub const ATTACHMENT_SHA256_METADATA_PREFIX: &str = "attachment_sha256.";
+pub const SKIPPED_ATTACHMENTS_METADATA_KEY: &str = "attachments_skipped";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_max_size_bytes")]
+    pub max_size_bytes: u64,
+    /// MIME types whose text is extracted; parameters such as charset are
+    /// ignored when matching
+    #[serde(default = "default_allowed_types")]
+    pub allowed_types: Vec<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_size_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_allowed_types() -> Vec<String> {
+    vec!["application/pdf".to_string(), "text/plain".to_string()]
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            max_size_bytes: default_max_size_bytes(),
+            allowed_types: default_allowed_types(),
+        }
+    }
+}
+
+impl AttachmentConfig {
+    pub fn is_allowed(&self, mime_type: &str) -> bool {
+        let base_type = base_mime_type(mime_type);
+        self.allowed_types.iter().any(|allowed| base_mime_type(allowed) == base_type)
+    }
+}
+
+fn base_mime_type(mime_type: &str) -> String {
+    mime_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentRef {
+    pub filename: String,
+    pub mime_type: String,
+    /// Size reported by the source, when known before downloading
+    pub size: Option<u64>,
+    pub download_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedAttachment {
+    pub filename: String,
+    pub mime_type: String,
+    pub size: u64,
+    pub sha256: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedAttachment {
+    pub filename: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttachmentSet {
+    pub extracted: Vec<ExtractedAttachment>,
+    pub skipped: Vec<SkippedAttachment>,
+}
+
+impl AttachmentSet {
+    fn skip(&mut self, filename: &str, reason: impl Into<String>) {
+        let reason = reason.into();
+        tracing::debug!("Skipping attachment {}: {}", filename, reason);
+        self.skipped.push(SkippedAttachment {
+            filename: filename.to_string(),
+            reason,
+        });
+    }
+
+    /// Appends the extracted text to the document content and records
+    /// checksums in its metadata. The caller recomputes `content_sha256`.
+    pub fn apply_to(&self, document: &mut SpecDocument) {
+        if self.extracted.is_empty() && self.skipped.is_empty() {
+            return;
+        }
+
+        for attachment in &self.extracted {
+            document.content.push_str(&format!(
+                "\n\n--- BEGIN ATTACHMENT {} ({}, sha256 {}) ---\n{}\n--- END ATTACHMENT {} ---",
+                attachment.filename,
+                attachment.mime_type,
+                attachment.sha256,
+                attachment.text.trim(),
+                attachment.filename
+            ));
+            document.metadata.insert(
+                format!("{}{}", ATTACHMENT_SHA256_METADATA_PREFIX, attachment.filename),
+                attachment.sha256.clone(),
+            );
+        }
+
+        document.metadata.insert(ATTACHMENT_COUNT_METADATA_KEY.to_string(), self.extracted.len().to_string());
+        if !self.skipped.is_empty() {
+            let skipped: Vec<String> = self.skipped
+                .iter()
+                .map(|skipped| format!("{} ({})", skipped.filename, skipped.reason))
+                .collect();
+            document.metadata.insert(SKIPPED_ATTACHMENTS_METADATA_KEY.to_string(), skipped.join("; "));
+        }
+    }
+}
+
+/// Downloads and extracts the allowed attachments. A failing attachment is
+/// recorded as skipped rather than failing the whole document.
+pub async fn collect_attachments(
+    http_client: &Client,
+    backoff: &ExponentialBackoff,
+    config: &AttachmentConfig,
+    attachments: Vec<AttachmentRef>,
+    token: &OAuth2Token,
+) -> AttachmentSet {
+    let mut set = AttachmentSet::default();
+    if !config.enabled {
+        return set;
+    }
+
+    for attachment in attachments {
+        if !config.is_allowed(&attachment.mime_type) {
+            set.skip(&attachment.filename, format!("type {} not allowed", attachment.mime_type));
+            continue;
+        }
+        if attachment.size.is_some_and(|size| size > config.max_size_bytes) {
+            set.skip(&attachment.filename, format!("larger than {} bytes", config.max_size_bytes));
+            continue;
+        }
+
+        // Sizes reported by the source are not always reliable, so the
+        // download stops as soon as it passes the limit
+        let bytes = match download(http_client, backoff, &attachment.download_url, token, config.max_size_bytes).await {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => {
+                set.skip(&attachment.filename, format!("larger than {} bytes", config.max_size_bytes));
+                continue;
+            }
+            Err(e) => {
+                set.skip(&attachment.filename, format!("download failed: {}", e));
+                continue;
+            }
+        };
+
+        let size = bytes.len() as u64;
+        let sha256 = format!("{:x}", Sha256::digest(&bytes));
+        // PDF parsing is CPU-bound and panics on some malformed files
+        let mime_type = attachment.mime_type.clone();
+        match tokio::task::spawn_blocking(move || extract_text(&mime_type, &bytes)).await {
+            Ok(Ok(text)) => set.extracted.push(ExtractedAttachment {
+                filename: attachment.filename,
+                mime_type: base_mime_type(&attachment.mime_type),
+                size,
+                sha256,
+                text,
+            }),
+            Ok(Err(e)) => set.skip(&attachment.filename, format!("text extraction failed: {}", e)),
+            Err(e) => set.skip(&attachment.filename, format!("text extraction panicked: {}", e)),
+        }
+    }
+
+    set
+}
+
+// None when the attachment is larger than `max_size_bytes`
+async fn download(
+    http_client: &Client,
+    backoff: &ExponentialBackoff,
+    url: &str,
+    token: &OAuth2Token,
+    max_size_bytes: u64,
+) -> Result<Option<Vec<u8>>, Error> {
+    let bytes = backoff
+        .execute_with_backoff(|| async {
+            let mut response = http_client
+                .get(url)
+                .header("Authorization", format!("Bearer {}", token.access_token))
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(Error::from_status(response.status().as_u16(), format!("Attachment download error: {}", response.status())));
+            }
+
+            if response.content_length().is_some_and(|length| length > max_size_bytes) {
+                return Ok(None);
+            }
+            let mut bytes = Vec::new();
+            while let Some(chunk) = response.chunk().await? {
+                if (bytes.len() + chunk.len()) as u64 > max_size_bytes {
+                    return Ok(None);
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            Ok(Some(bytes))
+        })
+        .await?;
+
+    Ok(bytes)
+}
+
+/// Text of an attachment: the text layer for PDFs (scanned PDFs without
+/// one yield no text), the decoded contents for text types
+pub fn extract_text(mime_type: &str, bytes: &[u8]) -> Result<String, Error> {
+    let base_type = base_mime_type(mime_type);
+    if base_type == "application/pdf" {
+        let text = pdf_extract::extract_text_from_mem(bytes).map_err(Error::invalid_input)?;
+        if text.trim().is_empty() {
+            return Err(Error::invalid_input("PDF has no text layer"));
+        }
+        Ok(text)
+    } else if base_type.starts_with("text/") {
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    } else {
+        Err(Error::invalid_input(format!("no text extractor for {}", base_type)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed_ignores_parameters_and_case() {
+        let config = AttachmentConfig::default();
+        assert!(config.is_allowed("text/plain; charset=utf-8"));
+        assert!(config.is_allowed("Application/PDF"));
+        assert!(!config.is_allowed("image/png"));
+        assert!(extract_text("image/png", b"\x89PNG").is_err());
+    }
+
+    #[test]
+    fn test_apply_to_appends_delimited_sections() {
+        let mut document = SpecDocument {
+            content: "# Payments".to_string(),
+            ..Default::default()
+        };
+        let set = AttachmentSet {
+            extracted: vec![ExtractedAttachment {
+                filename: "limits.txt".to_string(),
+                mime_type: "text/plain".to_string(),
+                size: 18,
+                sha256: "abc123".to_string(),
+                text: "Limit is 100 EUR.\n".to_string(),
+            }],
+            skipped: vec![SkippedAttachment {
+                filename: "diagram.png".to_string(),
+                reason: "type image/png not allowed".to_string(),
+            }],
+        };
+
+        set.apply_to(&mut document);
+
+        assert_eq!(
+            document.content,
+            "# Payments\n\n--- BEGIN ATTACHMENT limits.txt (text/plain, sha256 abc123) ---\n\
+             Limit is 100 EUR.\n--- END ATTACHMENT limits.txt ---"
+        );
+        assert_eq!(document.metadata["attachment_sha256.limits.txt"], "abc123");
+        assert_eq!(document.metadata[ATTACHMENT_COUNT_METADATA_KEY], "1");
+        assert_eq!(document.metadata[SKIPPED_ATTACHMENTS_METADATA_KEY], "diagram.png (type image/png not allowed)");
+    }
+}
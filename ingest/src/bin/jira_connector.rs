@@ -1,13 +1,19 @@
 use ingest::{
     ConnectorConfig, IngestionConnector, OAuth2Token,
-    connectors::JiraConnector,
-    secrets::SecretsManager,
+    connectors::{jira::JiraContentConfig, JiraConnector},
+    bus::TransportConfig,
+    queries::SourceQueries,
+    dedup::PublishOutcome,
+    secrets::{RotatingCredentials, SecretsManager},
 };
+use pipeline_control::PauseGate;
 use aws_sdk_secretsmanager::Client as SecretsClient;
 use aws_sdk_kms::Client as KmsClient;
-use nats::jetstream::Context as JetStreamContext;
+use std::sync::Arc;
 use tokio::signal;
-use tracing::{info, error, warn};
+use tracing::{debug, info, error, warn};
+
+const OAUTH_SECRET_NAME: &str = "jira-oauth-token";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -27,37 +33,69 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let kms_client = KmsClient::new(&aws_config);
 
     // Initialize secrets manager
-    let secrets_manager = SecretsManager::new(
+    let secrets_manager = Arc::new(SecretsManager::new(
         secrets_client.clone(),
         kms_client,
         std::env::var("KMS_KEY_ID").unwrap_or_else(|_| "alias/spec-to-proof".to_string()),
-    );
+    ));
+
+    // Load OAuth2 credentials and follow their rotations
+    let credentials = Arc::new(RotatingCredentials::load(&secrets_manager, OAUTH_SECRET_NAME).await?);
+    let rotation_interval_seconds = std::env::var("SECRETS_ROTATION_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse::<u64>()?;
+    if rotation_interval_seconds > 0 {
+        credentials.clone().spawn_watcher(
+            secrets_manager.clone(),
+            std::time::Duration::from_secs(rotation_interval_seconds),
+        );
+    }
 
-    // Initialize NATS JetStream
-    let nats_url = std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
-    let nc = nats::connect(&nats_url)?;
-    let jetstream = nats::jetstream::new(nc);
+    // Connect to the configured message bus (NATS JetStream or Kafka)
+    let bus = ingest::bus::connect(&config.transport).await?;
 
     // Initialize Jira connector
     let mut jira_connector = JiraConnector::new(config.clone());
 
+    // Published hashes for content dedup and the outbox of documents
+    // awaiting delivery live in the entity store
+    let store = storage::EntityStore::connect(&load_storage_settings()?).await?;
+    store.initialize().await?;
+
     // Initialize ingestion connector
     let ingestion_connector = IngestionConnector::new(
         config,
         secrets_client,
-        jetstream,
-    ).await?;
+        bus,
+    ).await?
+    .with_storage(&store)
+    .with_rotating_credentials(credentials.clone());
+    // Operators pause ingestion per tenant or source system through the
+    // gh-app admin API
+    let ingestion_connector = match std::env::var("PIPELINE_CONTROL_REDIS_URL") {
+        Ok(redis_url) => ingestion_connector.with_pause_gate(Arc::new(PauseGate::connect(&redis_url)?)),
+        Err(_) => ingestion_connector,
+    };
+
+    // Delivers documents left in the outbox when publishing failed or the
+    // connector crashed before the inline relay pass
+    let relay = ingestion_connector.outbox_relay();
+    tokio::spawn(async move { relay.run().await });
 
     info!("Jira connector initialized successfully");
 
     // Main polling loop
     loop {
-        match poll_and_publish(&mut jira_connector, &ingestion_connector, &secrets_manager).await {
-            Ok(_) => {
-                info!("Successfully polled and published Jira documents");
-            }
-            Err(e) => {
-                error!("Error polling Jira documents: {}", e);
+        if let Some(pause) = ingestion_connector.ingestion_pause().await {
+            info!("Ingestion from {} is paused by {}, skipping poll: {}", config.source_system, pause.paused_by, pause.reason);
+        } else {
+            match poll_and_publish(&mut jira_connector, &ingestion_connector, &credentials).await {
+                Ok(_) => {
+                    info!("Successfully polled and published Jira documents");
+                }
+                Err(e) => {
+                    error!("Error polling Jira documents: {}", e);
+                }
             }
         }
 
@@ -92,6 +130,42 @@ fn load_config() -> Result<ConnectorConfig, Box<dyn std::error::Error>> {
         .parse::<u64>()?;
     let secrets_arn = std::env::var("SECRETS_ARN")
         .ok_or("SECRETS_ARN environment variable is required")?;
+    let transport = match std::env::var("MESSAGE_TRANSPORT").unwrap_or_else(|_| "jetstream".to_string()).as_str() {
+        "jetstream" => TransportConfig::JetStream {
+            nats_url: std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string()),
+        },
+        "kafka" => TransportConfig::Kafka {
+            brokers: std::env::var("KAFKA_BROKERS")
+                .map_err(|_| "KAFKA_BROKERS environment variable is required for the kafka transport")?,
+            client_id: std::env::var("KAFKA_CLIENT_ID").unwrap_or_else(|_| format!("{}-connector", source_system)),
+        },
+        other => return Err(format!("Unknown MESSAGE_TRANSPORT: {}", other).into()),
+    };
+    // JIRA_QUERIES is a JSON list of {"name": ..., "jql": ...} objects
+    let queries = match std::env::var("JIRA_QUERIES") {
+        Ok(raw) => SourceQueries {
+            jira: serde_json::from_str(&raw).map_err(|e| format!("Invalid JIRA_QUERIES: {}", e))?,
+            ..Default::default()
+        },
+        Err(_) => SourceQueries::default(),
+    };
+    queries.validate()?;
+    // RATE_LIMITS is a JSON object of burst, per-endpoint and per-token limits
+    let rate_limits = match std::env::var("RATE_LIMITS") {
+        Ok(raw) => serde_json::from_str(&raw).map_err(|e| format!("Invalid RATE_LIMITS: {}", e))?,
+        Err(_) => Default::default(),
+    };
+    // JIRA_CUSTOM_FIELDS is a comma-separated list of field ids, e.g.
+    // customfield_10020 for acceptance criteria
+    let jira = JiraContentConfig {
+        custom_field_ids: std::env::var("JIRA_CUSTOM_FIELDS")
+            .map(|raw| raw.split(',').map(str::trim).filter(|id| !id.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default(),
+        include_comments: std::env::var("JIRA_INCLUDE_COMMENTS")
+            .map(|raw| raw.parse::<bool>())
+            .unwrap_or(Ok(false))
+            .map_err(|e| format!("Invalid JIRA_INCLUDE_COMMENTS: {}", e))?,
+    };
 
     Ok(ConnectorConfig {
         source_system,
@@ -100,22 +174,43 @@ fn load_config() -> Result<ConnectorConfig, Box<dyn std::error::Error>> {
         batch_size,
         poll_interval_seconds,
         secrets_arn,
+        transport,
+        queries,
+        attachments: Default::default(),
+        rate_limits,
+        jira,
+        tenant_id: std::env::var("TENANT_ID").ok(),
+    })
+}
+
+fn load_storage_settings() -> Result<storage::StorageSettings, Box<dyn std::error::Error>> {
+    Ok(storage::StorageSettings {
+        backend: std::env::var("STORAGE_BACKEND")
+            .unwrap_or_else(|_| "dynamodb".to_string())
+            .parse()?,
+        table_name: std::env::var("ENTITY_TABLE_NAME")
+            .unwrap_or_else(|_| "spec-to-proof-entities".to_string()),
+        database_url: std::env::var("DATABASE_URL").ok(),
+        max_connections: std::env::var("DATABASE_MAX_CONNECTIONS")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .unwrap_or(10),
+        data_dir: std::env::var("STORAGE_DATA_DIR").ok().map(Into::into),
     })
 }
 
 async fn poll_and_publish(
     jira_connector: &mut JiraConnector,
     ingestion_connector: &IngestionConnector,
-    secrets_manager: &SecretsManager,
+    credentials: &RotatingCredentials,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Get OAuth2 token from secrets manager
-    let token_key = "jira-oauth-token";
-    let oauth_credentials = secrets_manager.retrieve_oauth2_credentials(token_key).await?;
+    // Current OAuth2 credentials, swapped by the rotation watcher
+    let oauth_credentials = credentials.current();
 
     // For now, we'll use a mock token. In production, you'd implement OAuth2 token refresh
     let token = OAuth2Token {
         access_token: "mock_access_token".to_string(),
-        refresh_token: oauth_credentials.client_secret,
+        refresh_token: oauth_credentials.client_secret.clone(),
         expires_at: std::time::Instant::now() + std::time::Duration::from_secs(3600),
         token_type: "Bearer".to_string(),
     };
@@ -129,18 +224,35 @@ async fn poll_and_publish(
     }
 
     // Publish documents to JetStream
+    let document_count = documents.len();
     for document in documents {
+        let document_id = document.id.clone();
         match ingestion_connector.publish_document(document).await {
-            Ok(_) => {
-                info!("Published document {} to JetStream", document.id);
+            Ok(PublishOutcome::Published) => {
+                info!("Published document {} to JetStream", document_id);
+            }
+            Ok(PublishOutcome::Skipped) => {
+                info!("Document {} unchanged since last publish, skipped", document_id);
             }
             Err(e) => {
-                error!("Failed to publish document {}: {}", document.id, e);
+                error!("Failed to publish document {}: {}", document_id, e);
             }
         }
     }
 
-    info!("Successfully processed {} documents from Jira", documents.len());
+    info!("Successfully processed {} documents from Jira", document_count);
+    for limit in jira_connector.rate_limit_utilization().await {
+        debug!(
+            "Rate limit {}: {}/{} requests, {} burst available, throttle factor {}",
+            limit.scope, limit.current_usage, limit.max_usage, limit.burst_available, limit.throttle_factor
+        );
+    }
+    for circuit in circuit_breaker::snapshots() {
+        debug!(
+            "Circuit {}: {}, failure rate {:.2}, {} rejected",
+            circuit.name, circuit.state.as_str(), circuit.failure_rate, circuit.rejected_total
+        );
+    }
     Ok(())
 }
 
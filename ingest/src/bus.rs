@@ -0,0 +1,473 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use spec_to_proof_error::Error;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use nats::jetstream::Context as JetStreamContext;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::{Header, Headers, Message as KafkaMessage, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{ClientConfig, Offset, TopicPartitionList};
+
+// Transport-neutral message bus. Subjects use NATS syntax ("a.b", with "*"
+// matching one token and ">" the rest); the Kafka transport maps a subject
+// to the topic of the same name. Consumers sharing a group name split the
+// messages between them on either transport.
+
+// Carries the idempotency key. JetStream dedups on it natively; Kafka uses
+// it as the record key so retries of one event land on one partition.
+pub const MESSAGE_ID_HEADER: &str = "Nats-Msg-Id";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum TransportConfig {
+    JetStream {
+        nats_url: String,
+    },
+    Kafka {
+        brokers: String,
+        client_id: String,
+    },
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        TransportConfig::JetStream {
+            nats_url: "nats://localhost:4222".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BusMessage {
+    pub subject: String,
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+impl BusMessage {
+    pub fn new(subject: &str, payload: Vec<u8>) -> Self {
+        Self {
+            subject: subject.to_string(),
+            headers: HashMap::new(),
+            payload,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn message_id(&self) -> Option<&str> {
+        self.headers.get(MESSAGE_ID_HEADER).map(String::as_str)
+    }
+}
+
+// A received message; pass it back to its subscription's ack once processed
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub message: BusMessage,
+    ack_id: u64,
+}
+
+#[async_trait::async_trait]
+pub trait MessageBus: Send + Sync + std::fmt::Debug {
+    async fn publish(&self, message: BusMessage) -> Result<(), Error>;
+
+    async fn subscribe(
+        &self,
+        subject: &str,
+        group: &str,
+    ) -> Result<Box<dyn Subscription>, Error>;
+}
+
+#[async_trait::async_trait]
+pub trait Subscription: Send {
+    // Waits for the next message; None once the subscription is closed
+    async fn next(&mut self) -> Result<Option<Delivery>, Error>;
+
+    async fn ack(&mut self, delivery: &Delivery) -> Result<(), Error>;
+}
+
+pub async fn connect(config: &TransportConfig) -> Result<Arc<dyn MessageBus>, Error> {
+    match config {
+        TransportConfig::JetStream { nats_url } => {
+            let nc = nats::connect(nats_url)?;
+            Ok(Arc::new(JetStreamBus::new(nats::jetstream::new(nc))))
+        }
+        TransportConfig::Kafka { brokers, client_id } => Ok(Arc::new(KafkaBus::new(brokers, client_id)?)),
+    }
+}
+
+pub fn subject_matches(pattern: &str, subject: &str) -> bool {
+    let mut subject_tokens = subject.split('.');
+    for token in pattern.split('.') {
+        match (token, subject_tokens.next()) {
+            (">", Some(_)) => return true,
+            ("*", Some(_)) => {}
+            (literal, Some(actual)) if literal == actual => {}
+            _ => return false,
+        }
+    }
+    subject_tokens.next().is_none()
+}
+
+#[derive(Debug)]
+pub struct JetStreamBus {
+    jetstream: JetStreamContext,
+}
+
+impl JetStreamBus {
+    pub fn new(jetstream: JetStreamContext) -> Self {
+        Self { jetstream }
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageBus for JetStreamBus {
+    async fn publish(&self, message: BusMessage) -> Result<(), Error> {
+        let mut headers = nats::header::HeaderMap::new();
+        for (name, value) in &message.headers {
+            headers.insert(name.as_str(), value.as_str());
+        }
+
+        let options = nats::jetstream::PublishOptions {
+            id: message.message_id().map(str::to_string),
+            ..Default::default()
+        };
+        let nats_message = nats::Message::new(&message.subject, None, &message.payload, Some(headers));
+
+        self.jetstream
+            .publish_message_with_options(&nats_message, &options)
+            .await?;
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        subject: &str,
+        group: &str,
+    ) -> Result<Box<dyn Subscription>, Error> {
+        let subscription = self.jetstream.pull_subscribe(subject, group).await?;
+        Ok(Box::new(JetStreamSubscription {
+            subscription,
+            buffered: VecDeque::new(),
+            unacked: HashMap::new(),
+            next_ack_id: 0,
+        }))
+    }
+}
+
+struct JetStreamSubscription {
+    subscription: nats::jetstream::PullSubscription,
+    buffered: VecDeque<nats::Message>,
+    unacked: HashMap<u64, nats::Message>,
+    next_ack_id: u64,
+}
+
+#[async_trait::async_trait]
+impl Subscription for JetStreamSubscription {
+    async fn next(&mut self) -> Result<Option<Delivery>, Error> {
+        while self.buffered.is_empty() {
+            let batch = self.subscription.fetch(10, Duration::from_secs(5)).await?;
+            self.buffered.extend(batch);
+        }
+
+        let Some(raw) = self.buffered.pop_front() else {
+            return Ok(None);
+        };
+
+        let headers = raw
+            .headers
+            .as_ref()
+            .map(|headers| {
+                headers
+                    .iter()
+                    .filter_map(|(name, values)| Some((name.to_string(), values.iter().next()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let ack_id = self.next_ack_id;
+        self.next_ack_id += 1;
+        let message = BusMessage {
+            subject: raw.subject.clone(),
+            headers,
+            payload: raw.data.clone(),
+        };
+        self.unacked.insert(ack_id, raw);
+
+        Ok(Some(Delivery { message, ack_id }))
+    }
+
+    async fn ack(&mut self, delivery: &Delivery) -> Result<(), Error> {
+        if let Some(raw) = self.unacked.remove(&delivery.ack_id) {
+            raw.ack().await?;
+        }
+        Ok(())
+    }
+}
+
+pub struct KafkaBus {
+    brokers: String,
+    client_id: String,
+    producer: FutureProducer,
+}
+
+impl std::fmt::Debug for KafkaBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KafkaBus")
+            .field("brokers", &self.brokers)
+            .field("client_id", &self.client_id)
+            .finish()
+    }
+}
+
+impl KafkaBus {
+    pub fn new(brokers: &str, client_id: &str) -> Result<Self, Error> {
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("client.id", client_id)
+            .set("enable.idempotence", "true")
+            .set("acks", "all")
+            .create().map_err(Error::internal)?;
+
+        Ok(Self {
+            brokers: brokers.to_string(),
+            client_id: client_id.to_string(),
+            producer,
+        })
+    }
+}
+
+// Kafka has no subject wildcards; patterns become a regex subscription
+pub fn kafka_topic_pattern(subject: &str) -> String {
+    if !subject.split('.').any(|token| token == "*" || token == ">") {
+        return subject.to_string();
+    }
+
+    let tokens: Vec<String> = subject
+        .split('.')
+        .map(|token| match token {
+            "*" => "[^.]+".to_string(),
+            ">" => ".+".to_string(),
+            literal => regex_escape(literal),
+        })
+        .collect();
+    format!("^{}$", tokens.join("\\."))
+}
+
+fn regex_escape(literal: &str) -> String {
+    literal
+        .chars()
+        .flat_map(|c| {
+            let escape = !(c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            escape.then_some('\\').into_iter().chain(std::iter::once(c))
+        })
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl MessageBus for KafkaBus {
+    async fn publish(&self, message: BusMessage) -> Result<(), Error> {
+        let mut headers = OwnedHeaders::new();
+        for (name, value) in &message.headers {
+            headers = headers.insert(Header {
+                key: name,
+                value: Some(value.as_str()),
+            });
+        }
+
+        let key = message.message_id().unwrap_or_default();
+        let record = FutureRecord::to(&message.subject)
+            .key(key)
+            .payload(&message.payload)
+            .headers(headers);
+
+        self.producer
+            .send(record, Duration::from_secs(30))
+            .await
+            .map_err(|(e, _)| Error::transient(format!("Failed to publish to Kafka topic {}: {}", message.subject, e)))?;
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        subject: &str,
+        group: &str,
+    ) -> Result<Box<dyn Subscription>, Error> {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &self.brokers)
+            .set("client.id", &self.client_id)
+            .set("group.id", group)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .create().map_err(Error::internal)?;
+        consumer.subscribe(&[&kafka_topic_pattern(subject)]).map_err(Error::transient)?;
+
+        Ok(Box::new(KafkaSubscription {
+            consumer,
+            unacked: HashMap::new(),
+            next_ack_id: 0,
+        }))
+    }
+}
+
+struct KafkaSubscription {
+    consumer: StreamConsumer,
+    unacked: HashMap<u64, (String, i32, i64)>,
+    next_ack_id: u64,
+}
+
+#[async_trait::async_trait]
+impl Subscription for KafkaSubscription {
+    async fn next(&mut self) -> Result<Option<Delivery>, Error> {
+        let received = self.consumer.recv().await.map_err(Error::transient)?;
+
+        let headers = received
+            .headers()
+            .map(|headers| {
+                headers
+                    .iter()
+                    .filter_map(|header| {
+                        let value = std::str::from_utf8(header.value?).ok()?;
+                        Some((header.key.to_string(), value.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let ack_id = self.next_ack_id;
+        self.next_ack_id += 1;
+        self.unacked.insert(
+            ack_id,
+            (received.topic().to_string(), received.partition(), received.offset()),
+        );
+
+        Ok(Some(Delivery {
+            message: BusMessage {
+                subject: received.topic().to_string(),
+                headers,
+                payload: received.payload().unwrap_or_default().to_vec(),
+            },
+            ack_id,
+        }))
+    }
+
+    async fn ack(&mut self, delivery: &Delivery) -> Result<(), Error> {
+        if let Some((topic, partition, offset)) = self.unacked.remove(&delivery.ack_id) {
+            // The committed offset is the next one to read
+            let mut offsets = TopicPartitionList::new();
+            offsets.add_partition_offset(&topic, partition, Offset::Offset(offset + 1)).map_err(Error::internal)?;
+            self.consumer.commit(&offsets, CommitMode::Async).map_err(Error::transient)?;
+        }
+        Ok(())
+    }
+}
+
+// Process-local bus for tests. Subscriptions see messages published before
+// they were created, like a stream replayed from the start.
+#[derive(Debug, Default)]
+pub struct InMemoryBus {
+    published: Arc<RwLock<Vec<BusMessage>>>,
+}
+
+impl InMemoryBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn published(&self) -> Vec<BusMessage> {
+        self.published.read().await.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageBus for InMemoryBus {
+    async fn publish(&self, message: BusMessage) -> Result<(), Error> {
+        self.published.write().await.push(message);
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        subject: &str,
+        _group: &str,
+    ) -> Result<Box<dyn Subscription>, Error> {
+        let pending = self.published
+            .read()
+            .await
+            .iter()
+            .filter(|message| subject_matches(subject, &message.subject))
+            .cloned()
+            .collect();
+        Ok(Box::new(InMemorySubscription { pending, next_ack_id: 0 }))
+    }
+}
+
+struct InMemorySubscription {
+    pending: VecDeque<BusMessage>,
+    next_ack_id: u64,
+}
+
+#[async_trait::async_trait]
+impl Subscription for InMemorySubscription {
+    async fn next(&mut self) -> Result<Option<Delivery>, Error> {
+        let ack_id = self.next_ack_id;
+        self.next_ack_id += 1;
+        Ok(self.pending.pop_front().map(|message| Delivery { message, ack_id }))
+    }
+
+    async fn ack(&mut self, _delivery: &Delivery) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subject_matches() {
+        assert!(subject_matches("spec-documents.jira", "spec-documents.jira"));
+        assert!(subject_matches("spec-documents.*", "spec-documents.jira"));
+        assert!(subject_matches("spec-documents.>", "spec-documents.jira.retry"));
+        assert!(!subject_matches("spec-documents.*", "spec-documents.jira.retry"));
+        assert!(!subject_matches("spec-documents.jira", "spec-documents.gdocs"));
+        assert!(!subject_matches("spec-documents.jira.retry", "spec-documents.jira"));
+    }
+
+    #[test]
+    fn test_kafka_topic_pattern() {
+        assert_eq!(kafka_topic_pattern("spec-documents.jira"), "spec-documents.jira");
+        assert_eq!(kafka_topic_pattern("spec-documents.*"), "^spec-documents\\.[^.]+$");
+        assert_eq!(kafka_topic_pattern("spec-documents.>"), "^spec-documents\\..+$");
+    }
+
+    #[test]
+    fn test_transport_config_from_json() {
+        let config: TransportConfig =
+            serde_json::from_str(r#"{"kind":"kafka","brokers":"kafka:9092","client_id":"jira-connector"}"#).unwrap();
+        assert!(matches!(config, TransportConfig::Kafka { ref brokers, .. } if brokers == "kafka:9092"));
+        assert!(matches!(TransportConfig::default(), TransportConfig::JetStream { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_subscription_filters_by_subject() {
+        let bus = InMemoryBus::new();
+        bus.publish(BusMessage::new("spec-documents.jira", b"1".to_vec())).await.unwrap();
+        bus.publish(BusMessage::new("spec-documents.gdocs", b"2".to_vec())).await.unwrap();
+        bus.publish(BusMessage::new("other.jira", b"3".to_vec())).await.unwrap();
+
+        let mut subscription = bus.subscribe("spec-documents.*", "nlp").await.unwrap();
+        let first = subscription.next().await.unwrap().unwrap();
+        subscription.ack(&first).await.unwrap();
+        let second = subscription.next().await.unwrap().unwrap();
+
+        assert_eq!(first.message.payload, b"1");
+        assert_eq!(second.message.payload, b"2");
+        assert!(subscription.next().await.unwrap().is_none());
+    }
+}
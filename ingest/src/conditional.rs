@@ -0,0 +1,127 @@
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::RequestBuilder;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// What a connector remembers about the documents it has fetched, so an
+// unchanged document is neither downloaded nor converted again. Sources
+// expose a version (Confluence page version, Jira `updated` timestamp)
+// that is compared before fetching a body, and HTTP validators (ETag,
+// Last-Modified) that are replayed as conditional request headers.
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FetchedVersion {
+    pub version: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct FetchCache {
+    // Keyed by source id
+    fetched: HashMap<String, FetchedVersion>,
+    skipped_fetches: AtomicU64,
+}
+
+impl FetchCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `version` is the one last fetched for `source_id`
+    pub fn is_current(&self, source_id: &str, version: &str) -> bool {
+        self.fetched.get(source_id).is_some_and(|fetched| fetched.version == version)
+    }
+
+    /// Adds If-None-Match / If-Modified-Since from the last response for
+    /// `source_id`, if it carried validators
+    pub fn conditional(&self, source_id: &str, request: RequestBuilder) -> RequestBuilder {
+        let Some(fetched) = self.fetched.get(source_id) else {
+            return request;
+        };
+        let request = match &fetched.etag {
+            Some(etag) => request.header(IF_NONE_MATCH, etag),
+            None => request,
+        };
+        match &fetched.last_modified {
+            Some(last_modified) => request.header(IF_MODIFIED_SINCE, last_modified),
+            None => request,
+        }
+    }
+
+    /// Remembers `version` and the validators in `headers`. A 304 carries
+    /// no new validators, so the previous ones are kept.
+    pub fn record(&mut self, source_id: &str, version: impl Into<String>, headers: Option<&HeaderMap>) {
+        let header = |name| {
+            headers
+                .and_then(|headers| headers.get(name))
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        let fetched = self.fetched.entry(source_id.to_string()).or_default();
+        fetched.version = version.into();
+        if etag.is_some() || last_modified.is_some() {
+            fetched.etag = etag;
+            fetched.last_modified = last_modified;
+        }
+    }
+
+    /// Counts a document that was not fetched because it was unchanged
+    pub fn record_skip(&self, source_system: &str, source_id: &str, reason: &str) {
+        let skipped = self.skipped_fetches.fetch_add(1, Ordering::Relaxed) + 1;
+        tracing::info!(
+            metric = "ingest_fetches_skipped_total",
+            value = skipped,
+            source_system = %source_system,
+            reason = %reason,
+            "Skipping fetch of unchanged document {}",
+            source_id
+        );
+    }
+
+    pub fn skipped_fetches(&self) -> u64 {
+        self.skipped_fetches.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_version_short_circuit() {
+        let mut cache = FetchCache::new();
+        assert!(!cache.is_current("123", "4"));
+
+        cache.record("123", "4", None);
+        assert!(cache.is_current("123", "4"));
+        assert!(!cache.is_current("123", "5"));
+        assert!(!cache.is_current("456", "4"));
+    }
+
+    #[test]
+    fn test_conditional_headers_replay_validators() {
+        let mut cache = FetchCache::new();
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        headers.insert(LAST_MODIFIED, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        cache.record("123", "4", Some(&headers));
+        // A 304 keeps the validators it was answered for
+        cache.record("123", "4", Some(&HeaderMap::new()));
+
+        let client = reqwest::Client::new();
+        let request = cache.conditional("123", client.get("https://example.com/page/123")).build().unwrap();
+        assert_eq!(request.headers().get(IF_NONE_MATCH).unwrap(), "\"abc\"");
+        assert_eq!(request.headers().get(IF_MODIFIED_SINCE).unwrap(), "Wed, 21 Oct 2015 07:28:00 GMT");
+
+        let request = cache.conditional("456", client.get("https://example.com/page/456")).build().unwrap();
+        assert!(request.headers().get(IF_NONE_MATCH).is_none());
+
+        cache.record_skip("confluence", "123", "not_modified");
+        assert_eq!(cache.skipped_fetches(), 1);
+    }
+}
@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Atlassian Document Format, the JSON rich text Jira Cloud returns for
+// descriptions and comments. Nodes are rendered to markdown; node types
+// without a markdown equivalent fall back to their text content, and
+// media nodes are dropped since attachments are extracted separately.
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AdfNode {
+    #[serde(rename = "type")]
+    pub node_type: String,
+    #[serde(default)]
+    pub content: Vec<AdfNode>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub marks: Vec<AdfMark>,
+    #[serde(default)]
+    pub attrs: Value,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AdfMark {
+    #[serde(rename = "type")]
+    pub mark_type: String,
+    #[serde(default)]
+    pub attrs: Value,
+}
+
+impl AdfNode {
+    fn attr_str(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).and_then(Value::as_str)
+    }
+
+    fn attr_u64(&self, name: &str) -> Option<u64> {
+        self.attrs.get(name).and_then(Value::as_u64)
+    }
+
+    fn is_inline(&self) -> bool {
+        matches!(
+            self.node_type.as_str(),
+            "text" | "hardBreak" | "mention" | "emoji" | "inlineCard" | "date" | "status" | "placeholder"
+        )
+    }
+}
+
+/// Markdown for an ADF document or any node within one
+pub fn to_markdown(node: &AdfNode) -> String {
+    render_block(node).trim().to_string()
+}
+
+fn render_blocks(nodes: &[AdfNode], separator: &str) -> String {
+    nodes
+        .iter()
+        .map(render_block)
+        .filter(|block| !block.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn render_block(node: &AdfNode) -> String {
+    match node.node_type.as_str() {
+        "doc" | "mediaSingle" | "mediaGroup" => render_blocks(&node.content, "\n\n"),
+        "paragraph" => render_inline(&node.content),
+        "heading" => {
+            let level = node.attr_u64("level").unwrap_or(1).clamp(1, 6) as usize;
+            format!("{} {}", "#".repeat(level), render_inline(&node.content))
+        }
+        "bulletList" => render_list(&node.content, None),
+        "orderedList" => render_list(&node.content, Some(node.attr_u64("order").unwrap_or(1))),
+        "taskList" => node.content
+            .iter()
+            .map(|item| {
+                let checkbox = if item.attr_str("state") == Some("DONE") { "[x]" } else { "[ ]" };
+                format!("- {} {}", checkbox, render_item_body(item))
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "decisionList" => node.content
+            .iter()
+            .map(|item| format!("- Decision: {}", render_item_body(item)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "codeBlock" => {
+            let code: String = node.content.iter().filter_map(|text| text.text.as_deref()).collect();
+            format!("```{}\n{}\n```", node.attr_str("language").unwrap_or_default(), code.trim_end_matches('\n'))
+        }
+        "blockquote" => quote(&render_blocks(&node.content, "\n\n")),
+        // Panels become a quote led by their type, e.g. "**Warning**"
+        "panel" => {
+            let mut label = node.attr_str("panelType").filter(|panel_type| !panel_type.is_empty()).unwrap_or("info").to_string();
+            label[..1].make_ascii_uppercase();
+            quote(&format!("**{}**\n{}", label, render_blocks(&node.content, "\n\n")))
+        }
+        "expand" | "nestedExpand" => match node.attr_str("title").filter(|title| !title.is_empty()) {
+            Some(title) => format!("**{}**\n\n{}", title, render_blocks(&node.content, "\n\n")),
+            None => render_blocks(&node.content, "\n\n"),
+        },
+        "rule" => "---".to_string(),
+        "table" => render_table(node),
+        "media" => String::new(),
+        _ if node.content.iter().all(AdfNode::is_inline) => render_inline(std::slice::from_ref(node)),
+        _ => render_blocks(&node.content, "\n\n"),
+    }
+}
+
+// Continuation lines are indented under the marker, so nested lists and
+// multi-paragraph items stay inside their item
+fn render_list(items: &[AdfNode], start: Option<u64>) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let marker = match start {
+                Some(start) => format!("{}. ", start + index as u64),
+                None => "- ".to_string(),
+            };
+            let indent = " ".repeat(marker.len());
+            render_item_body(item)
+                .lines()
+                .enumerate()
+                .map(|(line_number, line)| match (line_number, line.is_empty()) {
+                    (0, _) => format!("{}{}", marker, line),
+                    (_, true) => String::new(),
+                    _ => format!("{}{}", indent, line),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// List and task items hold blocks, decision and task items hold inline
+// content directly
+fn render_item_body(item: &AdfNode) -> String {
+    if item.content.iter().all(AdfNode::is_inline) {
+        render_inline(&item.content)
+    } else {
+        render_blocks(&item.content, "\n")
+    }
+}
+
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Markdown tables need a header row, so the first row is used as one even
+// when ADF marks no header cells
+fn render_table(table: &AdfNode) -> String {
+    let rows: Vec<Vec<String>> = table.content
+        .iter()
+        .map(|row| {
+            row.content
+                .iter()
+                .map(|cell| render_blocks(&cell.content, " ").replace('\n', " ").replace('|', "\\|"))
+                .collect()
+        })
+        .collect();
+    let Some(columns) = rows.iter().map(Vec::len).max().filter(|columns| *columns > 0) else {
+        return String::new();
+    };
+
+    let render_row = |cells: &[String]| {
+        let mut padded = cells.to_vec();
+        padded.resize(columns, String::new());
+        format!("| {} |", padded.join(" | "))
+    };
+    let mut lines = vec![render_row(&rows[0]), format!("|{}", " --- |".repeat(columns))];
+    lines.extend(rows[1..].iter().map(|row| render_row(row)));
+    lines.join("\n")
+}
+
+fn render_inline(nodes: &[AdfNode]) -> String {
+    nodes.iter().map(render_inline_node).collect()
+}
+
+fn render_inline_node(node: &AdfNode) -> String {
+    match node.node_type.as_str() {
+        "text" => apply_marks(node.text.as_deref().unwrap_or_default(), &node.marks),
+        "hardBreak" => "\n".to_string(),
+        "mention" => {
+            let name = node.attr_str("text").or_else(|| node.attr_str("id")).unwrap_or("unknown");
+            if name.starts_with('@') { name.to_string() } else { format!("@{}", name) }
+        }
+        "emoji" => node.attr_str("text").or_else(|| node.attr_str("shortName")).unwrap_or_default().to_string(),
+        "inlineCard" => node.attr_str("url").map(|url| format!("<{}>", url)).unwrap_or_default(),
+        // Dates are epoch milliseconds, as a string
+        "date" => node.attr_str("timestamp")
+            .and_then(|timestamp| timestamp.parse::<i64>().ok())
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+        "status" => node.attr_str("text").map(|text| format!("[{}]", text)).unwrap_or_default(),
+        "placeholder" => String::new(),
+        _ => match &node.text {
+            Some(text) => text.clone(),
+            None => render_inline(&node.content),
+        },
+    }
+}
+
+fn apply_marks(text: &str, marks: &[AdfMark]) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let has = |mark_type: &str| marks.iter().any(|mark| mark.mark_type == mark_type);
+    let mut rendered = text.to_string();
+    if has("code") {
+        rendered = format!("`{}`", rendered);
+    }
+    if has("strong") {
+        rendered = format!("**{}**", rendered);
+    }
+    if has("em") {
+        rendered = format!("*{}*", rendered);
+    }
+    if has("strike") {
+        rendered = format!("~~{}~~", rendered);
+    }
+    if let Some(href) = marks
+        .iter()
+        .find(|mark| mark.mark_type == "link")
+        .and_then(|link| link.attrs.get("href"))
+        .and_then(Value::as_str)
+    {
+        rendered = format!("[{}]({})", rendered, href);
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn markdown(doc: Value) -> String {
+        to_markdown(&serde_json::from_value(doc).unwrap())
+    }
+
+    fn text(text: &str) -> Value {
+        json!({ "type": "text", "text": text })
+    }
+
+    fn paragraph(content: Value) -> Value {
+        json!({ "type": "paragraph", "content": content })
+    }
+
+    #[test]
+    fn test_paragraphs_with_marks_and_mentions() {
+        let doc = json!({
+            "type": "doc",
+            "version": 1,
+            "content": [
+                { "type": "heading", "attrs": { "level": 2 }, "content": [text("Refunds")] },
+                paragraph(json!([
+                    text("Refunds "),
+                    { "type": "text", "text": "must not", "marks": [{ "type": "strong" }] },
+                    text(" exceed the "),
+                    { "type": "text", "text": "charge", "marks": [{ "type": "code" }] },
+                    text(", see "),
+                    { "type": "text", "text": "the policy", "marks": [{ "type": "link", "attrs": { "href": "https://example.com/policy" } }] },
+                    text(". Owner: "),
+                    { "type": "mention", "attrs": { "id": "5b10a2844c20165700ede21g", "text": "@Jane Doe" } },
+                    { "type": "hardBreak" },
+                    text("Due "),
+                    { "type": "date", "attrs": { "timestamp": "1700000000000" } }
+                ]))
+            ]
+        });
+
+        assert_eq!(
+            markdown(doc),
+            "## Refunds\n\nRefunds **must not** exceed the `charge`, see [the policy](https://example.com/policy). \
+             Owner: @Jane Doe\nDue 2023-11-14"
+        );
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        let item = |content: Value| json!({ "type": "listItem", "content": content });
+        let doc = json!({
+            "type": "doc",
+            "content": [{
+                "type": "orderedList",
+                "attrs": { "order": 1 },
+                "content": [
+                    item(json!([
+                        paragraph(json!([text("Validate the charge")])),
+                        { "type": "bulletList", "content": [
+                            item(json!([paragraph(json!([text("Amount is positive")]))])),
+                            item(json!([paragraph(json!([text("Currency matches")]))]))
+                        ] }
+                    ])),
+                    item(json!([paragraph(json!([text("Issue the refund")]))]))
+                ]
+            }]
+        });
+
+        assert_eq!(
+            markdown(doc),
+            "1. Validate the charge\n   - Amount is positive\n   - Currency matches\n2. Issue the refund"
+        );
+    }
+
+    #[test]
+    fn test_code_blocks_panels_and_tables() {
+        let cell = |kind: &str, value: &str| json!({ "type": kind, "content": [paragraph(json!([text(value)]))] });
+        let doc = json!({
+            "type": "doc",
+            "content": [
+                { "type": "codeBlock", "attrs": { "language": "rust" }, "content": [text("assert!(refund <= charge);")] },
+                { "type": "panel", "attrs": { "panelType": "warning" }, "content": [paragraph(json!([text("Partial refunds count too.")]))] },
+                { "type": "table", "content": [
+                    { "type": "tableRow", "content": [cell("tableHeader", "Field"), cell("tableHeader", "Rule")] },
+                    { "type": "tableRow", "content": [cell("tableCell", "amount"), cell("tableCell", "> 0 | = charge")] }
+                ] },
+                { "type": "mediaSingle", "content": [{ "type": "media", "attrs": { "id": "abc", "type": "file" } }] }
+            ]
+        });
+
+        assert_eq!(
+            markdown(doc),
+            "```rust\nassert!(refund <= charge);\n```\n\n\
+             > **Warning**\n> Partial refunds count too.\n\n\
+             | Field | Rule |\n| --- | --- |\n| amount | > 0 \\| = charge |"
+        );
+    }
+
+    #[test]
+    fn test_unknown_nodes_fall_back_to_text() {
+        let doc = json!({
+            "type": "doc",
+            "content": [
+                { "type": "futureBlock", "content": [paragraph(json!([text("Still readable")]))] },
+                paragraph(json!([{ "type": "futureInline", "content": [text("inline too")] }]))
+            ]
+        });
+
+        assert_eq!(markdown(doc), "Still readable\n\ninline too");
+    }
+}
@@ -1,12 +1,18 @@
 use crate::{
     ConnectorConfig, OAuth2Token, DocumentMetadata,
-    rate_limiter::RateLimiter, backoff::ExponentialBackoff,
+    rate_limiter::{LimitUtilization, RateLimiter, RequestScope}, backoff::ExponentialBackoff,
+    queries::{self, ConfluenceQuery, SOURCE_QUERY_METADATA_KEY},
+    attachments::{self, AttachmentRef},
+    conditional::FetchCache,
 };
 use crate::proto::spec_to_proof::v1::SpecDocument;
 use crate::proto::google::protobuf::Timestamp;
-use reqwest::Client;
+use circuit_breaker::{is_failure_status, CircuitBreaker};
+use reqwest::{header::HeaderMap, Client, StatusCode};
 use serde::{Deserialize, Serialize};
+use spec_to_proof_error::Error;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConfluencePage {
@@ -57,9 +63,18 @@ pub struct ConfluenceUser {
     pub username: String,
 }
 
+/// A search result listed with its version only; the body is fetched
+/// separately when the version changed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfluencePageSummary {
+    pub id: String,
+    pub version: ConfluenceVersion,
+    pub last_modified_date: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConfluenceSearchResponse {
-    pub results: Vec<ConfluencePage>,
+    pub results: Vec<ConfluencePageSummary>,
     pub start: i32,
     pub limit: i32,
     pub size: i32,
@@ -71,12 +86,44 @@ pub struct ConfluenceSearchLinks {
     pub next: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfluenceAttachmentResponse {
+    pub results: Vec<ConfluenceAttachment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfluenceAttachment {
+    pub id: String,
+    pub title: String,
+    pub extensions: ConfluenceAttachmentExtensions,
+    pub _links: ConfluenceAttachmentLinks,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfluenceAttachmentExtensions {
+    pub mediaType: String,
+    pub fileSize: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfluenceAttachmentLinks {
+    pub download: String,
+}
+
+/// Name of the breaker shared by every ConfluenceConnector in the process
+pub const CIRCUIT: &str = "confluence";
+
+const PAGE_EXPAND: &str = "body.storage,version,space,history.lastUpdated,history.createdBy,history.lastUpdatedBy";
+
 pub struct ConfluenceConnector {
     config: ConnectorConfig,
     http_client: Client,
     rate_limiter: RateLimiter,
+    breaker: Arc<CircuitBreaker>,
     backoff: ExponentialBackoff,
-    last_sync_timestamp: Option<i64>,
+    // Keyed by query name, so each configured query resumes independently
+    last_sync_timestamps: HashMap<String, i64>,
+    fetch_cache: FetchCache,
 }
 
 impl ConfluenceConnector {
@@ -86,29 +133,58 @@ impl ConfluenceConnector {
             .build()
             .expect("Failed to create HTTP client");
 
-        let rate_limiter = RateLimiter::new(
+        let rate_limiter = RateLimiter::from_config(
             (config.rate_limit_per_minute as f64 * 0.7) as u32, // 70% of quota
-            std::time::Duration::from_secs(60),
+            &config.rate_limits,
         );
 
         Self {
             config,
             http_client,
             rate_limiter,
+            breaker: CircuitBreaker::shared(CIRCUIT),
             backoff: ExponentialBackoff::new(),
-            last_sync_timestamp: None,
+            last_sync_timestamps: HashMap::new(),
+            fetch_cache: FetchCache::new(),
         }
     }
 
-    pub async fn poll_documents(&mut self, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Box<dyn std::error::Error>> {
-        self.rate_limiter.acquire().await?;
+    pub async fn rate_limit_utilization(&self) -> Vec<LimitUtilization> {
+        self.rate_limiter.utilization().await
+    }
+
+    /// Pages not downloaded because their version or validators showed
+    /// them unchanged
+    pub fn skipped_fetches(&self) -> u64 {
+        self.fetch_cache.skipped_fetches()
+    }
+
+    pub async fn poll_documents(&mut self, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Error> {
+        let mut documents: Vec<SpecDocument> = Vec::new();
+
+        for query in self.config.queries.confluence_queries() {
+            for document in self.poll_query(&query, token).await? {
+                // A page matched by several queries is attributed to the first
+                if !documents.iter().any(|d| d.source_id == document.source_id) {
+                    documents.push(document);
+                }
+            }
+        }
+
+        Ok(documents)
+    }
+
+    async fn poll_query(&mut self, query: &ConfluenceQuery, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Error> {
+        let scope = RequestScope::endpoint("search").with_token(&token.access_token);
+        self.breaker.try_acquire()?;
+        self.rate_limiter.acquire_for(&scope).await?;
 
         let url = format!("{}/rest/api/content/search", self.config.base_url);
-        let cql = self.build_cql_query();
+        let cql = self.build_cql_query(query);
 
         let response = self.backoff
             .execute_with_backoff(|| async {
-                let response = self.http_client
+                let result = self.http_client
                     .post(&url)
                     .header("Authorization", format!("Bearer {}", token.access_token))
                     .header("Accept", "application/json")
@@ -116,13 +192,17 @@ impl ConfluenceConnector {
                     .json(&serde_json::json!({
                         "cql": cql,
                         "limit": self.config.batch_size,
-                        "expand": "body.storage,version,space,history.lastUpdated,history.createdBy,history.lastUpdatedBy"
+                        // Versions only; bodies of changed pages are fetched one by one
+                        "expand": "version"
                     }))
                     .send()
-                    .await?;
+                    .await;
+                self.breaker.record(result.as_ref().map_or(true, |response| is_failure_status(response.status().as_u16())));
+                let response = result?;
+                self.rate_limiter.observe(&scope, &response).await;
 
                 if !response.status().is_success() {
-                    return Err(format!("Confluence API error: {}", response.status()));
+                    return Err(Error::from_status(response.status().as_u16(), format!("Confluence API error: {}", response.status())));
                 }
 
                 let search_response: ConfluenceSearchResponse = response.json().await?;
@@ -130,58 +210,107 @@ impl ConfluenceConnector {
             })
             .await?;
 
-        let mut documents = Vec::new();
-        for page in response.results {
-            if let Some(document) = self.convert_page_to_document(page).await? {
-                documents.push(document);
-            }
+        // Results are newest first, but the newest is taken explicitly so
+        // the next poll never resumes from an older page
+        if let Some(timestamp) = response.results
+            .iter()
+            .filter_map(|page| self.parse_confluence_timestamp(&page.last_modified_date).ok())
+            .max()
+        {
+            self.last_sync_timestamps.insert(query.name.clone(), timestamp);
         }
 
-        // Update last sync timestamp
-        if let Some(last_page) = response.results.last() {
-            if let Ok(timestamp) = self.parse_confluence_timestamp(&last_page.last_modified_date) {
-                self.last_sync_timestamp = Some(timestamp);
+        let page_count = response.results.len();
+        let mut documents = Vec::new();
+        for summary in response.results {
+            let version = summary.version.number.to_string();
+            // The CQL bound has minute granularity, so pages from the last
+            // sync's minute are listed again
+            if self.fetch_cache.is_current(&summary.id, &version) {
+                self.fetch_cache.record_skip("confluence", &summary.id, "version");
+                continue;
+            }
+            let Some((page, headers)) = self.fetch_page(&summary.id, token).await? else {
+                self.fetch_cache.record_skip("confluence", &summary.id, "not_modified");
+                self.fetch_cache.record(&summary.id, version, None);
+                continue;
+            };
+            let version = page.version.number.to_string();
+            let converted = self.convert_page_to_document(page, token).await?;
+            // Only once converted, so a failed page is fetched again next poll
+            self.fetch_cache.record(&summary.id, version, Some(&headers));
+            if let Some(mut document) = converted {
+                document.metadata.insert(SOURCE_QUERY_METADATA_KEY.to_string(), query.name.clone());
+                documents.push(document);
             }
         }
 
         tracing::info!(
-            "Polled {} Confluence pages, converted {} to documents",
-            response.results.len(),
+            "Polled {} Confluence pages for query {}, converted {} to documents",
+            page_count,
+            query.name,
             documents.len()
         );
 
         Ok(documents)
     }
 
-    fn build_cql_query(&self) -> String {
-        let mut cql_parts = Vec::new();
-        
-        // Only fetch pages updated since last sync
-        if let Some(last_sync) = self.last_sync_timestamp {
-            let last_sync_str = self.format_confluence_timestamp(last_sync);
-            cql_parts.push(format!("lastmodified >= '{}'", last_sync_str));
-        }
+    // None when the server answers 304 to the conditional request
+    async fn fetch_page(&self, page_id: &str, token: &OAuth2Token) -> Result<Option<(ConfluencePage, HeaderMap)>, Error> {
+        let scope = RequestScope::endpoint("content").with_token(&token.access_token);
+        self.breaker.try_acquire()?;
+        self.rate_limiter.acquire_for(&scope).await?;
 
-        // Filter for specification-related pages
-        cql_parts.push("(text ~ 'specification' OR text ~ 'spec' OR title ~ 'specification' OR title ~ 'spec')".to_string());
-        
-        // Only include pages (not comments, attachments, etc.)
-        cql_parts.push("type = page".to_string());
-        
-        // Order by last modified
-        cql_parts.push("ORDER BY lastmodified DESC".to_string());
+        let url = format!("{}/rest/api/content/{}", self.config.base_url, page_id);
 
-        cql_parts.join(" AND ")
+        self.backoff
+            .execute_with_backoff(|| async {
+                let request = self.http_client
+                    .get(&url)
+                    .header("Authorization", format!("Bearer {}", token.access_token))
+                    .header("Accept", "application/json")
+                    .query(&[("expand", PAGE_EXPAND)]);
+                let result = self.fetch_cache.conditional(page_id, request).send().await;
+                self.breaker.record(result.as_ref().map_or(true, |response| is_failure_status(response.status().as_u16())));
+                let response = result?;
+                self.rate_limiter.observe(&scope, &response).await;
+
+                if response.status() == StatusCode::NOT_MODIFIED {
+                    return Ok(None);
+                }
+                if !response.status().is_success() {
+                    return Err(Error::from_status(response.status().as_u16(), format!("Confluence API error: {}", response.status())));
+                }
+
+                let headers = response.headers().clone();
+                let page: ConfluencePage = response.json().await?;
+                Ok(Some((page, headers)))
+            })
+            .await
     }
 
-    async fn convert_page_to_document(&self, page: ConfluencePage) -> Result<Option<SpecDocument>, Box<dyn std::error::Error>> {
+    fn build_cql_query(&self, query: &ConfluenceQuery) -> String {
+        // Only fetch pages updated since this query's last sync
+        let modified_since = self.last_sync_timestamps
+            .get(&query.name)
+            .map(|last_sync| self.format_confluence_timestamp(*last_sync));
+
+        queries::build_cql(query, modified_since.as_deref())
+    }
+
+    async fn convert_page_to_document(&self, page: ConfluencePage, token: &OAuth2Token) -> Result<Option<SpecDocument>, Error> {
         // Skip pages that don't have meaningful content
         if page.body.storage.value.is_empty() {
             return Ok(None);
         }
 
         let content = self.extract_content(&page)?;
-        let content_sha256 = self.compute_content_hash(&content);
+        // Embedded images are page attachments too, so they are listed here
+        let attachment_refs = if self.config.attachments.enabled {
+            self.fetch_attachments(&page.id, token).await?
+        } else {
+            Vec::new()
+        };
 
         let metadata = DocumentMetadata {
             source_id: page.id,
@@ -197,13 +326,55 @@ impl ConfluenceConnector {
 
         let mut document: SpecDocument = metadata.into();
         document.content = content;
-        document.content_sha256 = content_sha256;
+        attachments::collect_attachments(&self.http_client, &self.backoff, &self.config.attachments, attachment_refs, token)
+            .await
+            .apply_to(&mut document);
+        document.content_sha256 = self.compute_content_hash(&document.content);
         document.source_system = "confluence".to_string();
 
         Ok(Some(document))
     }
 
-    fn extract_content(&self, page: &ConfluencePage) -> Result<String, Box<dyn std::error::Error>> {
+    async fn fetch_attachments(&self, page_id: &str, token: &OAuth2Token) -> Result<Vec<AttachmentRef>, Error> {
+        let scope = RequestScope::endpoint("attachments").with_token(&token.access_token);
+        self.breaker.try_acquire()?;
+        self.rate_limiter.acquire_for(&scope).await?;
+
+        let url = format!("{}/rest/api/content/{}/child/attachment", self.config.base_url, page_id);
+
+        let response = self.backoff
+            .execute_with_backoff(|| async {
+                let result = self.http_client
+                    .get(&url)
+                    .header("Authorization", format!("Bearer {}", token.access_token))
+                    .header("Accept", "application/json")
+                    .send()
+                    .await;
+                self.breaker.record(result.as_ref().map_or(true, |response| is_failure_status(response.status().as_u16())));
+                let response = result?;
+                self.rate_limiter.observe(&scope, &response).await;
+
+                if !response.status().is_success() {
+                    return Err(Error::from_status(response.status().as_u16(), format!("Confluence API error: {}", response.status())));
+                }
+
+                let attachments: ConfluenceAttachmentResponse = response.json().await?;
+                Ok(attachments)
+            })
+            .await?;
+
+        Ok(response.results
+            .into_iter()
+            .map(|attachment| AttachmentRef {
+                filename: attachment.title,
+                mime_type: attachment.extensions.mediaType,
+                size: attachment.extensions.fileSize,
+                download_url: format!("{}{}", self.config.base_url, attachment._links.download),
+            })
+            .collect())
+    }
+
+    fn extract_content(&self, page: &ConfluencePage) -> Result<String, Error> {
         let mut content_parts = Vec::new();
 
         // Add title
@@ -224,7 +395,7 @@ impl ConfluenceConnector {
         Ok(content_parts.join("\n\n"))
     }
 
-    fn confluence_storage_to_markdown(&self, storage_content: &str) -> Result<String, Box<dyn std::error::Error>> {
+    fn confluence_storage_to_markdown(&self, storage_content: &str) -> Result<String, Error> {
         // This is a simplified conversion. In production, you'd use a proper
         // Confluence storage format parser or the Confluence REST API's
         // export functionality to get clean markdown.
@@ -275,9 +446,9 @@ impl ConfluenceConnector {
         format!("{:x}", hasher.finalize())
     }
 
-    fn parse_timestamp(&self, timestamp_str: &str) -> Result<Timestamp, Box<dyn std::error::Error>> {
+    fn parse_timestamp(&self, timestamp_str: &str) -> Result<Timestamp, Error> {
         // Confluence timestamps are in format: "2023-01-01T12:00:00.000Z"
-        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str)?;
+        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str).map_err(Error::invalid_input)?;
         let seconds = timestamp.timestamp();
         let nanos = timestamp.timestamp_subsec_nanos() as i32;
         
@@ -287,8 +458,8 @@ impl ConfluenceConnector {
         })
     }
 
-    fn parse_confluence_timestamp(&self, timestamp_str: &str) -> Result<i64, Box<dyn std::error::Error>> {
-        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str)?;
+    fn parse_confluence_timestamp(&self, timestamp_str: &str) -> Result<i64, Error> {
+        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str).map_err(Error::invalid_input)?;
         Ok(timestamp.timestamp())
     }
 
@@ -312,12 +483,19 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:confluence-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = ConfluenceConnector::new(config);
+        let query = connector.config.queries.confluence_queries().remove(0);
         
         // Test without last sync timestamp
-        let cql = connector.build_cql_query();
+        let cql = connector.build_cql_query(&query);
         assert!(cql.contains("specification"));
         assert!(cql.contains("type = page"));
         assert!(cql.contains("ORDER BY lastmodified DESC"));
@@ -332,6 +510,12 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:confluence-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = ConfluenceConnector::new(config);
@@ -350,6 +534,12 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:confluence-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = ConfluenceConnector::new(config);
@@ -368,6 +558,12 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:confluence-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = ConfluenceConnector::new(config);
@@ -377,4 +573,24 @@ mod tests {
         assert!(markdown.contains("# Title"));
         assert!(markdown.contains("**bold**"));
     }
+
+    #[test]
+    fn test_search_lists_versions_without_bodies() {
+        let response: ConfluenceSearchResponse = serde_json::from_value(serde_json::json!({
+            "results": [{
+                "id": "123",
+                "title": "Payments spec",
+                "version": { "number": 7, "message": null },
+                "last_modified_date": "2023-11-14T22:13:20.000Z"
+            }],
+            "start": 0,
+            "limit": 50,
+            "size": 1,
+            "_links": { "next": null }
+        }))
+        .unwrap();
+
+        assert_eq!(response.results[0].id, "123");
+        assert_eq!(response.results[0].version.number, 7);
+    }
 } 
\ No newline at end of file
@@ -1,12 +1,17 @@
 use crate::{
     ConnectorConfig, OAuth2Token, DocumentMetadata,
-    rate_limiter::RateLimiter, backoff::ExponentialBackoff,
+    rate_limiter::{LimitUtilization, RateLimiter, RequestScope}, backoff::ExponentialBackoff,
+    queries::{self, GoogleDocsQuery, SOURCE_QUERY_METADATA_KEY},
+    attachments::{self, AttachmentRef},
 };
 use crate::proto::spec_to_proof::v1::SpecDocument;
 use crate::proto::google::protobuf::Timestamp;
+use circuit_breaker::{is_failure_status, CircuitBreaker};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use spec_to_proof_error::Error;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GoogleDriveFile {
@@ -41,7 +46,67 @@ pub struct GoogleDocsDocument {
     pub title: String,
     pub body: GoogleDocsBody,
     pub revisionId: String,
-    pub documentId: String,
+    #[serde(default)]
+    pub inlineObjects: HashMap<String, GoogleDocsInlineObject>,
+    #[serde(default)]
+    pub lists: HashMap<String, GoogleDocsList>,
+    #[serde(default)]
+    pub footnotes: HashMap<String, GoogleDocsFootnote>,
+    #[serde(default)]
+    pub headers: HashMap<String, GoogleDocsHeaderFooter>,
+    #[serde(default)]
+    pub footers: HashMap<String, GoogleDocsHeaderFooter>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsList {
+    pub listProperties: GoogleDocsListProperties,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsListProperties {
+    pub nestingLevels: Vec<GoogleDocsNestingLevel>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsNestingLevel {
+    /// Set for numbered levels (DECIMAL, ALPHA, ROMAN, ...); bulleted levels
+    /// have a glyphSymbol instead
+    pub glyphType: Option<String>,
+    pub glyphSymbol: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsFootnote {
+    pub footnoteId: String,
+    pub content: Vec<GoogleDocsContent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsHeaderFooter {
+    pub content: Vec<GoogleDocsContent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsInlineObject {
+    pub objectId: String,
+    pub inlineObjectProperties: GoogleDocsInlineObjectProperties,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsInlineObjectProperties {
+    pub embeddedObject: GoogleDocsEmbeddedObject,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsEmbeddedObject {
+    pub title: Option<String>,
+    pub imageProperties: Option<GoogleDocsImageProperties>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsImageProperties {
+    pub contentUri: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -62,6 +127,15 @@ pub struct GoogleDocsContent {
 pub struct GoogleDocsParagraph {
     pub elements: Vec<GoogleDocsElement>,
     pub paragraphStyle: Option<GoogleDocsParagraphStyle>,
+    /// Set when the paragraph is a list item
+    pub bullet: Option<GoogleDocsBullet>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsBullet {
+    pub listId: String,
+    /// Zero for top-level items; omitted by the API when zero
+    pub nestingLevel: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -69,6 +143,13 @@ pub struct GoogleDocsElement {
     pub startIndex: Option<i32>,
     pub endIndex: Option<i32>,
     pub textRun: Option<GoogleDocsTextRun>,
+    pub footnoteReference: Option<GoogleDocsFootnoteReference>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDocsFootnoteReference {
+    pub footnoteId: String,
+    pub footnoteNumber: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -114,12 +195,17 @@ pub struct GoogleDocsSectionStyle {
     pub columnSeparatorStyle: Option<String>,
 }
 
+/// Name of the breaker shared by every GoogleDocsConnector in the process
+pub const CIRCUIT: &str = "google_docs";
+
 pub struct GoogleDocsConnector {
     config: ConnectorConfig,
     http_client: Client,
     rate_limiter: RateLimiter,
+    breaker: Arc<CircuitBreaker>,
     backoff: ExponentialBackoff,
-    last_sync_timestamp: Option<i64>,
+    // Keyed by query name, so each configured query resumes independently
+    last_sync_timestamps: HashMap<String, i64>,
 }
 
 impl GoogleDocsConnector {
@@ -129,44 +215,73 @@ impl GoogleDocsConnector {
             .build()
             .expect("Failed to create HTTP client");
 
-        let rate_limiter = RateLimiter::new(
+        let rate_limiter = RateLimiter::from_config(
             (config.rate_limit_per_minute as f64 * 0.7) as u32, // 70% of quota
-            std::time::Duration::from_secs(60),
+            &config.rate_limits,
         );
 
         Self {
             config,
             http_client,
             rate_limiter,
+            breaker: CircuitBreaker::shared(CIRCUIT),
             backoff: ExponentialBackoff::new(),
-            last_sync_timestamp: None,
+            last_sync_timestamps: HashMap::new(),
         }
     }
 
-    pub async fn poll_documents(&mut self, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Box<dyn std::error::Error>> {
-        self.rate_limiter.acquire().await?;
+    pub async fn rate_limit_utilization(&self) -> Vec<LimitUtilization> {
+        self.rate_limiter.utilization().await
+    }
 
-        // First, search for Google Docs files
-        let files = self.search_docs_files(token).await?;
-        
-        let mut documents = Vec::new();
-        for file in files {
-            if let Some(document) = self.convert_file_to_document(file, token).await? {
-                documents.push(document);
+    pub async fn poll_documents(&mut self, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Error> {
+        let mut documents: Vec<SpecDocument> = Vec::new();
+
+        for query in self.config.queries.google_docs_queries() {
+            self.breaker.try_acquire()?;
+            self.rate_limiter.acquire_for(&RequestScope::endpoint("files").with_token(&token.access_token)).await?;
+
+            // First, search for Google Docs files
+            let files = self.search_docs_files(&query, token).await?;
+            let file_count = files.len();
+            let mut converted = 0;
+
+            for file in files {
+                // A file matched by several queries is attributed to the first
+                if documents.iter().any(|d| d.source_id == file.id) {
+                    continue;
+                }
+                if let Some(mut document) = self.convert_file_to_document(file, &query, token).await? {
+                    document.metadata.insert(SOURCE_QUERY_METADATA_KEY.to_string(), query.name.clone());
+                    documents.push(document);
+                    converted += 1;
+                }
             }
-        }
 
-        tracing::info!(
-            "Polled {} Google Docs files, converted {} to documents",
-            files.len(),
-            documents.len()
-        );
+            tracing::info!(
+                "Polled {} Google Docs files for query {}, converted {} to documents",
+                file_count,
+                query.name,
+                converted
+            );
+        }
 
         Ok(documents)
     }
 
-    async fn search_docs_files(&mut self, token: &OAuth2Token) -> Result<Vec<GoogleDriveFile>, Box<dyn std::error::Error>> {
+    fn build_drive_query(&self, query: &GoogleDocsQuery) -> String {
+        // Only fetch files modified since this query's last sync
+        let modified_since = self.last_sync_timestamps
+            .get(&query.name)
+            .map(|last_sync| self.format_google_timestamp(*last_sync));
+
+        queries::build_drive_query(query, modified_since.as_deref())
+    }
+
+    async fn search_docs_files(&mut self, query: &GoogleDocsQuery, token: &OAuth2Token) -> Result<Vec<GoogleDriveFile>, Error> {
         let url = "https://www.googleapis.com/drive/v3/files";
+        let drive_query = self.build_drive_query(query);
+        let scope = RequestScope::endpoint("files").with_token(&token.access_token);
         
         let mut all_files = Vec::new();
         let mut page_token = None;
@@ -174,29 +289,29 @@ impl GoogleDocsConnector {
         loop {
             let response = self.backoff
                 .execute_with_backoff(|| async {
+                    let page_size = self.config.batch_size.to_string();
                     let mut query_params = vec![
-                        ("q", "mimeType='application/vnd.google-apps.document'"),
-                        ("fields", "files(id,name,mimeType,createdTime,modifiedTime,owners,lastModifyingUser,parents,webViewLink,size,description)"),
-                        ("pageSize", &self.config.batch_size.to_string()),
+                        ("q", drive_query.as_str()),
+                        ("fields", "nextPageToken,files(id,name,mimeType,createdTime,modifiedTime,owners,lastModifyingUser,parents,webViewLink,size,description)"),
+                        ("pageSize", page_size.as_str()),
                     ];
 
                     if let Some(token) = &page_token {
                         query_params.push(("pageToken", token));
                     }
 
-                    // Add search terms for specification-related documents
-                    let search_query = "(name contains 'specification' OR name contains 'spec' OR description contains 'specification' OR description contains 'spec')";
-                    query_params.push(("q", &format!("mimeType='application/vnd.google-apps.document' AND ({})", search_query)));
-
-                    let response = self.http_client
+                    let result = self.http_client
                         .get(url)
                         .header("Authorization", format!("Bearer {}", token.access_token))
                         .query(&query_params)
                         .send()
-                        .await?;
+                        .await;
+                    self.breaker.record(result.as_ref().map_or(true, |response| is_failure_status(response.status().as_u16())));
+                    let response = result?;
+                    self.rate_limiter.observe(&scope, &response).await;
 
                     if !response.status().is_success() {
-                        return Err(format!("Google Drive API error: {}", response.status()));
+                        return Err(Error::from_status(response.status().as_u16(), format!("Google Drive API error: {}", response.status())));
                     }
 
                     let file_list: GoogleDriveFileList = response.json().await?;
@@ -213,18 +328,31 @@ impl GoogleDocsConnector {
             }
         }
 
+        // Update last sync timestamp for this query
+        let latest = all_files
+            .iter()
+            .filter_map(|file| self.parse_google_timestamp(&file.modifiedTime).ok())
+            .max();
+        if let Some(timestamp) = latest {
+            self.last_sync_timestamps.insert(query.name.clone(), timestamp);
+        }
+
         Ok(all_files)
     }
 
-    async fn convert_file_to_document(&self, file: GoogleDriveFile, token: &OAuth2Token) -> Result<Option<SpecDocument>, Box<dyn std::error::Error>> {
+    async fn convert_file_to_document(
+        &self,
+        file: GoogleDriveFile,
+        query: &GoogleDocsQuery,
+        token: &OAuth2Token,
+    ) -> Result<Option<SpecDocument>, Error> {
         // Skip files that don't have meaningful content
         if file.name.is_empty() {
             return Ok(None);
         }
 
         // Fetch the document content
-        let content = self.fetch_document_content(&file.id, token).await?;
-        let content_sha256 = self.compute_content_hash(&content);
+        let (content, attachment_refs) = self.fetch_document_content(&file.id, query.include_headers_footers, token).await?;
 
         let metadata = DocumentMetadata {
             source_id: file.id,
@@ -242,25 +370,36 @@ impl GoogleDocsConnector {
 
         let mut document: SpecDocument = metadata.into();
         document.content = content;
-        document.content_sha256 = content_sha256;
+        attachments::collect_attachments(&self.http_client, &self.backoff, &self.config.attachments, attachment_refs, token)
+            .await
+            .apply_to(&mut document);
+        document.content_sha256 = self.compute_content_hash(&document.content);
         document.source_system = "google_docs".to_string();
 
         Ok(Some(document))
     }
 
-    async fn fetch_document_content(&self, document_id: &str, token: &OAuth2Token) -> Result<String, Box<dyn std::error::Error>> {
+    async fn fetch_document_content(
+        &self,
+        document_id: &str,
+        include_headers_footers: bool,
+        token: &OAuth2Token,
+    ) -> Result<(String, Vec<AttachmentRef>), Error> {
+        self.breaker.try_acquire()?;
         let url = format!("https://docs.googleapis.com/v1/documents/{}", document_id);
 
         let response = self.backoff
             .execute_with_backoff(|| async {
-                let response = self.http_client
+                let result = self.http_client
                     .get(&url)
                     .header("Authorization", format!("Bearer {}", token.access_token))
                     .send()
-                    .await?;
+                    .await;
+                self.breaker.record(result.as_ref().map_or(true, |response| is_failure_status(response.status().as_u16())));
+                let response = result?;
 
                 if !response.status().is_success() {
-                    return Err(format!("Google Docs API error: {}", response.status()));
+                    return Err(Error::from_status(response.status().as_u16(), format!("Google Docs API error: {}", response.status())));
                 }
 
                 let doc: GoogleDocsDocument = response.json().await?;
@@ -268,30 +407,83 @@ impl GoogleDocsConnector {
             })
             .await?;
 
-        self.extract_content_from_document(&response)
+        let content = self.extract_content_from_document(&response, include_headers_footers)?;
+        Ok((content, self.embedded_images(&response)))
+    }
+
+    // Google Docs has no attachments; embedded images are enumerated so
+    // they show up in the document metadata when not extracted
+    fn embedded_images(&self, doc: &GoogleDocsDocument) -> Vec<AttachmentRef> {
+        let mut images: Vec<AttachmentRef> = doc.inlineObjects
+            .values()
+            .filter_map(|object| {
+                let embedded = &object.inlineObjectProperties.embeddedObject;
+                let content_uri = embedded.imageProperties.as_ref()?.contentUri.clone()?;
+                Some(AttachmentRef {
+                    filename: embedded.title.clone().unwrap_or_else(|| object.objectId.clone()),
+                    mime_type: "image/*".to_string(),
+                    size: None,
+                    download_url: content_uri,
+                })
+            })
+            .collect();
+        images.sort_by(|a, b| a.filename.cmp(&b.filename));
+        images
     }
 
-    fn extract_content_from_document(&self, doc: &GoogleDocsDocument) -> Result<String, Box<dyn std::error::Error>> {
+    fn extract_content_from_document(&self, doc: &GoogleDocsDocument, include_headers_footers: bool) -> Result<String, Error> {
         let mut content_parts = Vec::new();
 
         // Add title
         content_parts.push(format!("# {}", doc.title));
 
         // Extract content from body
-        let body_content = self.extract_body_content(&doc.body)?;
+        let body_content = self.extract_body_content(doc, &doc.body.content)?;
         content_parts.push(body_content);
 
+        // Footnote text is listed after the body, in reference order
+        let footnotes = self.extract_footnotes(doc);
+        if !footnotes.is_empty() {
+            content_parts.push(footnotes);
+        }
+
+        if include_headers_footers {
+            for (title, sections) in [("Header", &doc.headers), ("Footer", &doc.footers)] {
+                let section_content = self.extract_header_footer_content(doc, sections)?;
+                if !section_content.is_empty() {
+                    content_parts.push(format!("## {}\n{}", title, section_content));
+                }
+            }
+        }
+
         Ok(content_parts.join("\n\n"))
     }
 
-    fn extract_body_content(&self, body: &GoogleDocsBody) -> Result<String, Box<dyn std::error::Error>> {
+    fn extract_body_content(&self, doc: &GoogleDocsDocument, elements: &[GoogleDocsContent]) -> Result<String, Error> {
         let mut content = String::new();
+        // Item counters per list and nesting level, for numbered lists
+        let mut list_counters: HashMap<&str, Vec<usize>> = HashMap::new();
+        let mut in_list = false;
 
-        for element in &body.content {
+        for element in elements {
             if let Some(paragraph) = &element.paragraph {
+                if let Some(bullet) = &paragraph.bullet {
+                    content.push_str(&self.list_item_marker(doc, bullet, &mut list_counters));
+                    content.push_str(self.extract_paragraph_content(paragraph).trim_end());
+                    content.push('\n');
+                    in_list = true;
+                    continue;
+                }
+                // A blank line ends the list in markdown
+                if std::mem::take(&mut in_list) {
+                    content.push('\n');
+                }
                 content.push_str(&self.extract_paragraph_content(paragraph));
                 content.push('\n');
             } else if let Some(table) = &element.table {
+                if std::mem::take(&mut in_list) {
+                    content.push('\n');
+                }
                 content.push_str(&self.extract_table_content(table));
                 content.push('\n');
             }
@@ -300,6 +492,33 @@ impl GoogleDocsConnector {
         Ok(content)
     }
 
+    // Indented two spaces per nesting level; numbered levels count their
+    // items, restarting whenever a shallower item interrupts them
+    fn list_item_marker<'a>(
+        &self,
+        doc: &GoogleDocsDocument,
+        bullet: &'a GoogleDocsBullet,
+        list_counters: &mut HashMap<&'a str, Vec<usize>>,
+    ) -> String {
+        let level = bullet.nestingLevel.unwrap_or(0);
+        let counters = list_counters.entry(bullet.listId.as_str()).or_default();
+        counters.resize(level + 1, 0);
+        counters[level] += 1;
+
+        let numbered = doc.lists
+            .get(&bullet.listId)
+            .and_then(|list| list.listProperties.nestingLevels.get(level))
+            .and_then(|nesting_level| nesting_level.glyphType.as_deref())
+            .is_some_and(|glyph_type| !matches!(glyph_type, "GLYPH_TYPE_UNSPECIFIED" | "NONE"));
+
+        let indent = "  ".repeat(level);
+        if numbered {
+            format!("{}{}. ", indent, counters[level])
+        } else {
+            format!("{}- ", indent)
+        }
+    }
+
     fn extract_paragraph_content(&self, paragraph: &GoogleDocsParagraph) -> String {
         let mut content = String::new();
 
@@ -331,12 +550,71 @@ impl GoogleDocsConnector {
                 }
 
                 content.push_str(&text);
+            } else if let Some(reference) = &element.footnoteReference {
+                content.push_str(&format!("[^{}]", reference.footnoteNumber));
             }
         }
 
         content
     }
 
+    // Markdown footnote definitions, one line per footnote referenced
+    // from the body
+    fn extract_footnotes(&self, doc: &GoogleDocsDocument) -> String {
+        let mut references = Vec::new();
+        self.collect_footnote_references(&doc.body.content, &mut references);
+
+        let mut definitions = Vec::new();
+        for reference in references {
+            let Some(footnote) = doc.footnotes.get(&reference.footnoteId) else {
+                continue;
+            };
+            let text = footnote.content
+                .iter()
+                .filter_map(|element| element.paragraph.as_ref())
+                .map(|paragraph| self.extract_paragraph_content(paragraph).trim().to_string())
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            definitions.push(format!("[^{}]: {}", reference.footnoteNumber, text));
+        }
+
+        definitions.join("\n")
+    }
+
+    fn collect_footnote_references<'a>(&self, elements: &'a [GoogleDocsContent], references: &mut Vec<&'a GoogleDocsFootnoteReference>) {
+        for element in elements {
+            if let Some(paragraph) = &element.paragraph {
+                references.extend(paragraph.elements.iter().filter_map(|element| element.footnoteReference.as_ref()));
+            } else if let Some(table) = &element.table {
+                for cell in table.tableRows.iter().flat_map(|row| &row.tableCells) {
+                    self.collect_footnote_references(&cell.content, references);
+                }
+            }
+        }
+    }
+
+    // Documents may have separate first-page and default headers, often
+    // with the same text, which is kept once
+    fn extract_header_footer_content(
+        &self,
+        doc: &GoogleDocsDocument,
+        sections: &HashMap<String, GoogleDocsHeaderFooter>,
+    ) -> Result<String, Error> {
+        let mut ids: Vec<&String> = sections.keys().collect();
+        ids.sort();
+
+        let mut rendered: Vec<String> = Vec::new();
+        for id in ids {
+            let text = self.extract_body_content(doc, &sections[id].content)?.trim().to_string();
+            if !text.is_empty() && !rendered.contains(&text) {
+                rendered.push(text);
+            }
+        }
+
+        Ok(rendered.join("\n\n"))
+    }
+
     fn extract_table_content(&self, table: &GoogleDocsTable) -> String {
         let mut content = String::new();
 
@@ -405,9 +683,9 @@ impl GoogleDocsConnector {
         format!("{:x}", hasher.finalize())
     }
 
-    fn parse_timestamp(&self, timestamp_str: &str) -> Result<Timestamp, Box<dyn std::error::Error>> {
+    fn parse_timestamp(&self, timestamp_str: &str) -> Result<Timestamp, Error> {
         // Google API timestamps are in RFC3339 format: "2023-01-01T12:00:00.000Z"
-        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str)?;
+        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str).map_err(Error::invalid_input)?;
         let seconds = timestamp.timestamp();
         let nanos = timestamp.timestamp_subsec_nanos() as i32;
         
@@ -417,15 +695,15 @@ impl GoogleDocsConnector {
         })
     }
 
-    fn parse_google_timestamp(&self, timestamp_str: &str) -> Result<i64, Box<dyn std::error::Error>> {
-        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str)?;
+    fn parse_google_timestamp(&self, timestamp_str: &str) -> Result<i64, Error> {
+        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str).map_err(Error::invalid_input)?;
         Ok(timestamp.timestamp())
     }
 
     fn format_google_timestamp(&self, timestamp: i64) -> String {
         let dt = chrono::DateTime::from_timestamp(timestamp, 0)
             .unwrap_or_else(|| chrono::Utc::now());
-        dt.format("%Y-%m-%d %H:%M").to_string()
+        dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()
     }
 }
 
@@ -442,6 +720,12 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:gdocs-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = GoogleDocsConnector::new(config);
@@ -451,6 +735,37 @@ mod tests {
         assert_eq!(hash.len(), 64);
     }
 
+    #[test]
+    fn test_build_drive_query_resumes_per_query() {
+        let mut config = ConnectorConfig {
+            source_system: "google_docs".to_string(),
+            base_url: "https://docs.googleapis.com".to_string(),
+            rate_limit_per_minute: 100,
+            batch_size: 50,
+            poll_interval_seconds: 300,
+            secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:gdocs-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
+        };
+        config.queries.google_docs = vec![GoogleDocsQuery {
+            name: "product-specs".to_string(),
+            folder_ids: vec!["folder1".to_string()],
+            terms: vec![],
+            include_headers_footers: false,
+        }];
+
+        let mut connector = GoogleDocsConnector::new(config);
+        let query = connector.config.queries.google_docs_queries().remove(0);
+        assert!(connector.build_drive_query(&query).contains("'folder1' in parents"));
+
+        connector.last_sync_timestamps.insert(query.name.clone(), 1_700_000_000);
+        assert!(connector.build_drive_query(&query).contains("modifiedTime > '2023-11-14T22:13:20Z'"));
+    }
+
     #[test]
     fn test_parse_timestamp() {
         let config = ConnectorConfig {
@@ -460,6 +775,12 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:gdocs-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = GoogleDocsConnector::new(config);
@@ -478,6 +799,12 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:gdocs-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = GoogleDocsConnector::new(config);
@@ -495,12 +822,82 @@ mod tests {
                             underline: Some(false),
                         }),
                     }),
+                    footnoteReference: None,
                 },
             ],
             paragraphStyle: None,
+            bullet: None,
         };
 
         let content = connector.extract_paragraph_content(&paragraph);
         assert!(content.contains("**Test**"));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_extract_lists_footnotes_and_headers() {
+        let config = ConnectorConfig {
+            source_system: "google_docs".to_string(),
+            base_url: "https://docs.googleapis.com".to_string(),
+            rate_limit_per_minute: 100,
+            batch_size: 50,
+            poll_interval_seconds: 300,
+            secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:gdocs-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
+        };
+
+        let connector = GoogleDocsConnector::new(config);
+
+        let text = |content: &str| serde_json::json!({ "textRun": { "content": content } });
+        let item = |content: &str, level: usize| serde_json::json!({
+            "paragraph": {
+                "elements": [text(content)],
+                "bullet": { "listId": "steps", "nestingLevel": level }
+            }
+        });
+        let doc: GoogleDocsDocument = serde_json::from_value(serde_json::json!({
+            "documentId": "doc1",
+            "title": "Payments",
+            "revisionId": "rev1",
+            "body": { "content": [
+                { "paragraph": { "elements": [
+                    text("Refunds never exceed the charge"),
+                    { "footnoteReference": { "footnoteId": "fn1", "footnoteNumber": "1" } },
+                    text(".\n")
+                ] } },
+                item("Validate the charge\n", 0),
+                item("Amount is positive\n", 1),
+                item("Currency matches\n", 1),
+                item("Issue the refund\n", 0),
+                { "paragraph": { "elements": [text("Done.\n")] } }
+            ] },
+            "lists": {
+                "steps": { "listProperties": { "nestingLevels": [{ "glyphType": "DECIMAL" }, { "glyphSymbol": "○" }] } }
+            },
+            "footnotes": {
+                "fn1": { "footnoteId": "fn1", "content": [
+                    { "paragraph": { "elements": [text("Including partial refunds.\n")] } }
+                ] }
+            },
+            "headers": {
+                "h1": { "content": [{ "paragraph": { "elements": [text("CONFIDENTIAL\n")] } }] },
+                "h2": { "content": [{ "paragraph": { "elements": [text("CONFIDENTIAL\n")] } }] }
+            }
+        }))
+        .unwrap();
+
+        let content = connector.extract_content_from_document(&doc, false).unwrap();
+        assert!(content.contains("Refunds never exceed the charge[^1]."));
+        assert!(content.contains("1. Validate the charge\n  - Amount is positive\n  - Currency matches\n2. Issue the refund\n\nDone."));
+        assert!(content.contains("[^1]: Including partial refunds."));
+        assert!(!content.contains("CONFIDENTIAL"));
+
+        let content = connector.extract_content_from_document(&doc, true).unwrap();
+        assert_eq!(content.matches("CONFIDENTIAL").count(), 1);
+        assert!(content.contains("## Header\nCONFIDENTIAL"));
+    }
+}
@@ -0,0 +1,400 @@
+use crate::{
+    ConnectorConfig, OAuth2Token, DocumentMetadata,
+    rate_limiter::{LimitUtilization, RateLimiter, RequestScope}, backoff::ExponentialBackoff,
+    queries::{GitRepoQuery, SOURCE_QUERY_METADATA_KEY},
+    openapi::{self, FORMAT_METADATA_KEY, OPENAPI_FORMAT, OPENAPI_VERSION_METADATA_KEY},
+    proto_schema::{self, PROTOBUF_FORMAT, PROTO_MESSAGES_METADATA_KEY, PROTO_PACKAGE_METADATA_KEY},
+};
+use crate::proto::spec_to_proof::v1::SpecDocument;
+use crate::proto::google::protobuf::Timestamp;
+use circuit_breaker::{is_failure_status, CircuitBreaker};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use spec_to_proof_error::Error;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitCommitRef {
+    pub sha: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitTree {
+    pub sha: String,
+    pub tree: Vec<GitTreeEntry>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitTreeEntry {
+    pub path: String,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub sha: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitCommit {
+    pub sha: String,
+    pub html_url: String,
+    pub commit: GitCommitDetails,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitCommitDetails {
+    pub author: GitSignature,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitSignature {
+    pub name: String,
+    pub email: String,
+    pub date: String,
+}
+
+/// Name of the breaker shared by every GitRepoConnector in the process
+pub const CIRCUIT: &str = "github";
+
+/// Reads spec files (Markdown by default) that live in Git repositories,
+/// through the GitHub REST API. `config.base_url` is the API root
+/// (https://api.github.com, or the GHES equivalent) and the token is an
+/// installation token issued for the GitHub App.
+pub struct GitRepoConnector {
+    config: ConnectorConfig,
+    http_client: Client,
+    rate_limiter: RateLimiter,
+    breaker: Arc<CircuitBreaker>,
+    backoff: ExponentialBackoff,
+    // Keyed by query name; the tree SHA last scanned, so unchanged
+    // repositories are skipped without listing them again
+    last_tree_shas: HashMap<String, String>,
+    // Keyed by query name, then path; blob SHAs of files already emitted
+    last_blob_shas: HashMap<String, HashMap<String, String>>,
+}
+
+impl GitRepoConnector {
+    pub fn new(config: ConnectorConfig) -> Self {
+        let http_client = Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .user_agent("spec-to-proof-ingest")
+            .build()
+            .expect("Failed to create HTTP client");
+
+        let rate_limiter = RateLimiter::from_config(
+            (config.rate_limit_per_minute as f64 * 0.7) as u32, // 70% of quota
+            &config.rate_limits,
+        );
+
+        Self {
+            config,
+            http_client,
+            rate_limiter,
+            breaker: CircuitBreaker::shared(CIRCUIT),
+            backoff: ExponentialBackoff::new(),
+            last_tree_shas: HashMap::new(),
+            last_blob_shas: HashMap::new(),
+        }
+    }
+
+    pub async fn rate_limit_utilization(&self) -> Vec<LimitUtilization> {
+        self.rate_limiter.utilization().await
+    }
+
+    pub async fn poll_documents(&mut self, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Error> {
+        let mut documents: Vec<SpecDocument> = Vec::new();
+
+        for query in self.config.queries.git_repo_queries() {
+            for document in self.poll_query(&query, token).await? {
+                // A file matched by several queries is attributed to the first
+                if !documents.iter().any(|d| d.source_id == document.source_id) {
+                    documents.push(document);
+                }
+            }
+        }
+
+        Ok(documents)
+    }
+
+    async fn poll_query(&mut self, query: &GitRepoQuery, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Error> {
+        let git_ref = match &query.git_ref {
+            Some(git_ref) => git_ref.clone(),
+            None => "HEAD".to_string(),
+        };
+
+        let head = self.fetch_commit_ref(&query.repository, &git_ref, token).await?;
+        let tree = self.fetch_tree(&query.repository, &head.sha, token).await?;
+
+        if self.last_tree_shas.get(&query.name) == Some(&tree.sha) {
+            tracing::debug!("Repository {} unchanged at {}, skipping", query.repository, head.sha);
+            return Ok(Vec::new());
+        }
+        if tree.truncated {
+            tracing::warn!(
+                "Tree listing for {} at {} was truncated; some spec files may be missed",
+                query.repository,
+                head.sha
+            );
+        }
+
+        let seen = self.last_blob_shas.get(&query.name).cloned().unwrap_or_default();
+        let mut current = HashMap::new();
+        let mut documents = Vec::new();
+
+        for entry in tree.tree.iter().filter(|entry| entry.entry_type == "blob") {
+            if !query.paths.iter().any(|pattern| glob_matches(pattern, &entry.path)) {
+                continue;
+            }
+            current.insert(entry.path.clone(), entry.sha.clone());
+
+            // Only files whose contents changed since the last scan are emitted
+            if seen.get(&entry.path) == Some(&entry.sha) {
+                continue;
+            }
+
+            let mut document = self.convert_file_to_document(query, &head.sha, &entry.path, token).await?;
+            document.metadata.insert(SOURCE_QUERY_METADATA_KEY.to_string(), query.name.clone());
+            documents.push(document);
+        }
+
+        tracing::info!(
+            "Scanned {} at {} for query {}: {} spec files, {} changed",
+            query.repository,
+            head.sha,
+            query.name,
+            current.len(),
+            documents.len()
+        );
+
+        self.last_tree_shas.insert(query.name.clone(), tree.sha);
+        self.last_blob_shas.insert(query.name.clone(), current);
+
+        Ok(documents)
+    }
+
+    async fn fetch_commit_ref(&self, repository: &str, git_ref: &str, token: &OAuth2Token) -> Result<GitCommitRef, Error> {
+        let url = format!("{}/repos/{}/commits/{}", self.config.base_url, repository, git_ref);
+        self.get_json(&url, &[], token).await
+    }
+
+    async fn fetch_tree(&self, repository: &str, commit_sha: &str, token: &OAuth2Token) -> Result<GitTree, Error> {
+        let url = format!("{}/repos/{}/git/trees/{}", self.config.base_url, repository, commit_sha);
+        self.get_json(&url, &[("recursive", "1")], token).await
+    }
+
+    // The last commit that touched the file is its version: the document ID
+    // only changes when the file itself does
+    async fn fetch_last_commit(&self, repository: &str, commit_sha: &str, path: &str, token: &OAuth2Token) -> Result<Option<GitCommit>, Error> {
+        let url = format!("{}/repos/{}/commits", self.config.base_url, repository);
+        let commits: Vec<GitCommit> = self
+            .get_json(&url, &[("sha", commit_sha), ("path", path), ("per_page", "1")], token)
+            .await?;
+        Ok(commits.into_iter().next())
+    }
+
+    async fn fetch_file_content(&self, repository: &str, commit_sha: &str, path: &str, token: &OAuth2Token) -> Result<String, Error> {
+        let scope = RequestScope::endpoint("contents").with_token(&token.access_token);
+        self.breaker.try_acquire()?;
+        self.rate_limiter.acquire_for(&scope).await?;
+
+        let url = format!("{}/repos/{}/contents/{}", self.config.base_url, repository, path);
+
+        let content = self.backoff
+            .execute_with_backoff(|| async {
+                let result = self.http_client
+                    .get(&url)
+                    .header("Authorization", format!("Bearer {}", token.access_token))
+                    .header("Accept", "application/vnd.github.raw+json")
+                    .query(&[("ref", commit_sha)])
+                    .send()
+                    .await;
+                self.breaker.record(result.as_ref().map_or(true, |response| is_failure_status(response.status().as_u16())));
+                let response = result?;
+                self.rate_limiter.observe(&scope, &response).await;
+
+                if !response.status().is_success() {
+                    return Err(Error::from_status(response.status().as_u16(), format!("GitHub API error: {}", response.status())));
+                }
+
+                let content = response.text().await?;
+                Ok(content)
+            })
+            .await?;
+
+        Ok(content)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str, params: &[(&str, &str)], token: &OAuth2Token) -> Result<T, Error> {
+        let scope = RequestScope::endpoint("api").with_token(&token.access_token);
+        self.breaker.try_acquire()?;
+        self.rate_limiter.acquire_for(&scope).await?;
+
+        let value = self.backoff
+            .execute_with_backoff(|| async {
+                let result = self.http_client
+                    .get(url)
+                    .header("Authorization", format!("Bearer {}", token.access_token))
+                    .header("Accept", "application/vnd.github+json")
+                    .query(params)
+                    .send()
+                    .await;
+                self.breaker.record(result.as_ref().map_or(true, |response| is_failure_status(response.status().as_u16())));
+                let response = result?;
+                self.rate_limiter.observe(&scope, &response).await;
+
+                if !response.status().is_success() {
+                    return Err(Error::from_status(response.status().as_u16(), format!("GitHub API error: {}", response.status())));
+                }
+
+                let value: T = response.json().await?;
+                Ok(value)
+            })
+            .await?;
+
+        Ok(value)
+    }
+
+    async fn convert_file_to_document(&self, query: &GitRepoQuery, head_sha: &str, path: &str, token: &OAuth2Token) -> Result<SpecDocument, Error> {
+        let content = self.fetch_file_content(&query.repository, head_sha, path, token).await?;
+        let content_sha256 = self.compute_content_hash(&content);
+        let last_commit = self.fetch_last_commit(&query.repository, head_sha, path, token).await?;
+
+        let commit_sha = last_commit
+            .as_ref()
+            .map(|commit| commit.sha.clone())
+            .unwrap_or_else(|| head_sha.to_string());
+        let modified_at = match &last_commit {
+            Some(commit) => self.parse_timestamp(&commit.commit.author.date)?,
+            None => Timestamp::default(),
+        };
+
+        let source_id = format!("{}/{}", query.repository, path);
+        let mut metadata = HashMap::new();
+        metadata.insert("repository".to_string(), query.repository.clone());
+        metadata.insert("path".to_string(), path.to_string());
+        metadata.insert("commit_sha".to_string(), commit_sha.clone());
+        metadata.insert("head_sha".to_string(), head_sha.to_string());
+        if let Some(git_ref) = &query.git_ref {
+            metadata.insert("git_ref".to_string(), git_ref.clone());
+        }
+        if let Some(commit) = &last_commit {
+            metadata.insert("commit_url".to_string(), commit.html_url.clone());
+            metadata.insert("author_email".to_string(), commit.commit.author.email.clone());
+        }
+        let openapi = openapi::detect(path, &content);
+        if let Some(openapi) = &openapi {
+            metadata.insert(FORMAT_METADATA_KEY.to_string(), OPENAPI_FORMAT.to_string());
+            metadata.insert(OPENAPI_VERSION_METADATA_KEY.to_string(), openapi.version.clone());
+        }
+        if let Some(schema) = proto_schema::detect(path, &content) {
+            metadata.insert(FORMAT_METADATA_KEY.to_string(), PROTOBUF_FORMAT.to_string());
+            metadata.insert(PROTO_MESSAGES_METADATA_KEY.to_string(), schema.messages.join(","));
+            if let Some(package) = schema.package {
+                metadata.insert(PROTO_PACKAGE_METADATA_KEY.to_string(), package);
+            }
+        }
+
+        let metadata = DocumentMetadata {
+            source_id: source_id.clone(),
+            title: openapi
+                .and_then(|openapi| openapi.title)
+                .unwrap_or_else(|| extract_title(&content, path)),
+            url: format!("https://github.com/{}/blob/{}/{}", query.repository, commit_sha, path),
+            author: last_commit
+                .as_ref()
+                .map(|commit| commit.commit.author.name.clone())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            // The Git history would have to be walked for the creation date
+            created_at: modified_at.clone(),
+            modified_at,
+            version: 1, // Versions are commit SHAs, carried in the document ID
+            status: "published".to_string(),
+            metadata,
+        };
+
+        let mut document: SpecDocument = metadata.into();
+        document.id = format!("{}@{}", source_id, commit_sha);
+        document.content = content;
+        document.content_sha256 = content_sha256;
+        document.source_system = "git".to_string();
+
+        Ok(document)
+    }
+
+    fn compute_content_hash(&self, content: &str) -> String {
+        use sha2::{Sha256, Digest};
+        let mut hasher = Sha256::new();
+        hasher.update(content.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn parse_timestamp(&self, timestamp_str: &str) -> Result<Timestamp, Error> {
+        // GitHub timestamps are RFC 3339: "2023-01-01T12:00:00Z"
+        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp_str).map_err(Error::invalid_input)?;
+        Ok(Timestamp {
+            seconds: timestamp.timestamp(),
+            nanos: timestamp.timestamp_subsec_nanos() as i32,
+        })
+    }
+}
+
+// First Markdown heading, falling back to the file name
+fn extract_title(content: &str, path: &str) -> String {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| path.rsplit('/').next().unwrap_or(path).to_string())
+}
+
+/// Matches a repository path against a glob: `**` spans any number of
+/// directories, `*` and `?` match within a single path segment
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => match_segment(segment.as_bytes(), name.as_bytes()) && match_segments(rest, path_rest),
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("docs/specs/**/*.md", "docs/specs/payments.md"));
+        assert!(glob_matches("docs/specs/**/*.md", "docs/specs/billing/v2/refunds.md"));
+        assert!(!glob_matches("docs/specs/**/*.md", "docs/specs/diagram.png"));
+        assert!(!glob_matches("docs/specs/**/*.md", "docs/other/payments.md"));
+        assert!(glob_matches("**/SPEC?.md", "services/api/SPEC1.md"));
+        assert!(!glob_matches("*.md", "docs/README.md"));
+    }
+
+    #[test]
+    fn test_extract_title() {
+        assert_eq!(extract_title("\n# Payment Limits\n\nBody", "docs/specs/limits.md"), "Payment Limits");
+        assert_eq!(extract_title("No heading here", "docs/specs/limits.md"), "limits.md");
+    }
+}
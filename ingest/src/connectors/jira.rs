@@ -1,12 +1,19 @@
 use crate::{
     ConnectorConfig, IngestionConnector, OAuth2Token, DocumentMetadata,
-    rate_limiter::RateLimiter, backoff::ExponentialBackoff,
+    rate_limiter::{LimitUtilization, RateLimiter, RequestScope}, backoff::ExponentialBackoff,
+    queries::{self, JiraQuery, SOURCE_QUERY_METADATA_KEY},
+    attachments::{self, AttachmentRef},
+    conditional::FetchCache,
+    connectors::adf::{self, AdfNode},
 };
 use crate::proto::spec_to_proof::v1::SpecDocument;
 use crate::proto::google::protobuf::Timestamp;
+use circuit_breaker::{is_failure_status, CircuitBreaker};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use spec_to_proof_error::Error;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,7 +27,7 @@ pub struct JiraIssue {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JiraFields {
     pub summary: String,
-    pub description: Option<String>,
+    pub description: Option<JiraRichText>,
     pub status: JiraStatus,
     pub assignee: Option<JiraUser>,
     pub reporter: Option<JiraUser>,
@@ -31,6 +38,53 @@ pub struct JiraFields {
     pub priority: Option<JiraPriority>,
     pub labels: Vec<String>,
     pub components: Vec<JiraComponent>,
+    #[serde(default)]
+    pub attachment: Vec<JiraAttachment>,
+    #[serde(default)]
+    pub comment: Option<JiraComments>,
+    /// Remaining fields by id, custom fields among them
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JiraComments {
+    pub comments: Vec<JiraComment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JiraComment {
+    pub id: String,
+    pub author: Option<JiraUser>,
+    pub body: JiraRichText,
+    pub created: String,
+}
+
+/// Rich text field: ADF from the v3 API, plain text from v2 and from
+/// some older Server instances
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JiraRichText {
+    Adf(AdfNode),
+    Plain(String),
+}
+
+impl JiraRichText {
+    pub fn to_markdown(&self) -> String {
+        match self {
+            JiraRichText::Adf(node) => adf::to_markdown(node),
+            JiraRichText::Plain(text) => text.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JiraAttachment {
+    pub id: String,
+    pub filename: String,
+    pub mimeType: String,
+    pub size: u64,
+    pub content: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -94,17 +148,44 @@ pub struct JiraChangeItem {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JiraSearchResponse {
     pub issues: Vec<JiraIssue>,
+    /// Display names by field id, for custom field headings
+    #[serde(default)]
+    pub names: HashMap<String, String>,
     pub total: i32,
     pub maxResults: i32,
     pub startAt: i32,
 }
 
+/// Name of the breaker shared by every JiraConnector in the process
+pub const CIRCUIT: &str = "jira";
+
+/// Prefix of the metadata keys holding the character span of each custom
+/// field and comment section, e.g. `jira_section.customfield_10020` or
+/// `jira_section.comment.10001` with value `"120-348"` (end exclusive)
+pub const JIRA_SECTION_METADATA_PREFIX: &str = "jira_section.";
+
+const SEARCH_FIELDS: &str = "summary,description,status,assignee,reporter,created,updated,project,issuetype,priority,labels,components,attachment";
+
+/// Issue content beyond the summary and description
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JiraContentConfig {
+    /// Custom fields appended to the content, e.g. `customfield_10020`
+    /// for acceptance criteria
+    #[serde(default)]
+    pub custom_field_ids: Vec<String>,
+    #[serde(default)]
+    pub include_comments: bool,
+}
+
 pub struct JiraConnector {
     config: ConnectorConfig,
     http_client: Client,
     rate_limiter: RateLimiter,
+    breaker: Arc<CircuitBreaker>,
     backoff: ExponentialBackoff,
-    last_sync_timestamp: Option<i64>,
+    // Keyed by query name, so each configured query resumes independently
+    last_sync_timestamps: HashMap<String, i64>,
+    fetch_cache: FetchCache,
 }
 
 impl JiraConnector {
@@ -114,43 +195,77 @@ impl JiraConnector {
             .build()
             .expect("Failed to create HTTP client");
 
-        let rate_limiter = RateLimiter::new(
+        let rate_limiter = RateLimiter::from_config(
             (config.rate_limit_per_minute as f64 * 0.7) as u32, // 70% of quota
-            std::time::Duration::from_secs(60),
+            &config.rate_limits,
         );
 
         Self {
             config,
             http_client,
             rate_limiter,
+            breaker: CircuitBreaker::shared(CIRCUIT),
             backoff: ExponentialBackoff::new(),
-            last_sync_timestamp: None,
+            last_sync_timestamps: HashMap::new(),
+            fetch_cache: FetchCache::new(),
+        }
+    }
+
+    pub async fn rate_limit_utilization(&self) -> Vec<LimitUtilization> {
+        self.rate_limiter.utilization().await
+    }
+
+    /// Issues not converted, nor their attachments downloaded, because
+    /// their `updated` timestamp was unchanged
+    pub fn skipped_fetches(&self) -> u64 {
+        self.fetch_cache.skipped_fetches()
+    }
+
+    pub async fn poll_documents(&mut self, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Error> {
+        let mut documents: Vec<SpecDocument> = Vec::new();
+
+        for query in self.config.queries.jira_queries() {
+            for document in self.poll_query(&query, token).await? {
+                // An issue matched by several queries is attributed to the first
+                if !documents.iter().any(|d| d.source_id == document.source_id) {
+                    documents.push(document);
+                }
+            }
         }
+
+        Ok(documents)
     }
 
-    pub async fn poll_documents(&mut self, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Box<dyn std::error::Error>> {
-        self.rate_limiter.acquire().await?;
+    async fn poll_query(&mut self, query: &JiraQuery, token: &OAuth2Token) -> Result<Vec<SpecDocument>, Error> {
+        let scope = RequestScope::endpoint("search").with_token(&token.access_token);
+        self.breaker.try_acquire()?;
+        self.rate_limiter.acquire_for(&scope).await?;
 
-        let jql = self.build_jql_query();
+        let jql = self.build_jql_query(query);
         let url = format!("{}/rest/api/3/search", self.config.base_url);
+        let fields = self.search_fields();
 
         let response = self.backoff
             .execute_with_backoff(|| async {
-                let response = self.http_client
+                let result = self.http_client
                     .get(&url)
                     .header("Authorization", format!("Bearer {}", token.access_token))
                     .header("Accept", "application/json")
                     .query(&[
                         ("jql", &jql),
                         ("maxResults", &self.config.batch_size.to_string()),
-                        ("fields", &"summary,description,status,assignee,reporter,created,updated,project,issuetype,priority,labels,components".to_string()),
-                        ("expand", &"changelog".to_string()),
+                        ("fields", &fields),
+                        // Names label the custom field sections
+                        ("expand", &"changelog,names".to_string()),
                     ])
                     .send()
-                    .await?;
+                    .await;
+                self.breaker.record(result.as_ref().map_or(true, |response| is_failure_status(response.status().as_u16())));
+                let response = result?;
+                self.rate_limiter.observe(&scope, &response).await;
 
                 if !response.status().is_success() {
-                    return Err(format!("Jira API error: {}", response.status()));
+                    return Err(Error::from_status(response.status().as_u16(), format!("Jira API error: {}", response.status())));
                 }
 
                 let search_response: JiraSearchResponse = response.json().await?;
@@ -158,55 +273,84 @@ impl JiraConnector {
             })
             .await?;
 
+        // Results are newest first, but the newest is taken explicitly so
+        // the next poll never resumes from an older issue
+        if let Some(timestamp) = response.issues
+            .iter()
+            .filter_map(|issue| self.parse_jira_timestamp(&issue.fields.updated).ok())
+            .max()
+        {
+            self.last_sync_timestamps.insert(query.name.clone(), timestamp);
+        }
+
+        let issue_count = response.issues.len();
         let mut documents = Vec::new();
         for issue in response.issues {
-            if let Some(document) = self.convert_issue_to_document(issue).await? {
-                documents.push(document);
+            // The JQL bound has minute granularity, so issues from the last
+            // sync's minute are returned again
+            if self.fetch_cache.is_current(&issue.key, &issue.fields.updated) {
+                self.fetch_cache.record_skip("jira", &issue.key, "updated");
+                continue;
             }
-        }
-
-        // Update last sync timestamp
-        if let Some(last_issue) = response.issues.last() {
-            if let Ok(timestamp) = self.parse_jira_timestamp(&last_issue.fields.updated) {
-                self.last_sync_timestamp = Some(timestamp);
+            let (key, updated) = (issue.key.clone(), issue.fields.updated.clone());
+            let converted = self.convert_issue_to_document(issue, &response.names, token).await?;
+            // Only once converted, so a failed issue is converted again next poll
+            self.fetch_cache.record(&key, updated, None);
+            if let Some(mut document) = converted {
+                document.metadata.insert(SOURCE_QUERY_METADATA_KEY.to_string(), query.name.clone());
+                documents.push(document);
             }
         }
 
         tracing::info!(
-            "Polled {} Jira issues, converted {} to documents",
-            response.issues.len(),
+            "Polled {} Jira issues for query {}, converted {} to documents",
+            issue_count,
+            query.name,
             documents.len()
         );
 
         Ok(documents)
     }
 
-    fn build_jql_query(&self) -> String {
-        let mut jql_parts = Vec::new();
-        
-        // Only fetch issues updated since last sync
-        if let Some(last_sync) = self.last_sync_timestamp {
-            let last_sync_str = self.format_jira_timestamp(last_sync);
-            jql_parts.push(format!("updated >= '{}'", last_sync_str));
+    fn search_fields(&self) -> String {
+        let mut fields = vec![SEARCH_FIELDS.to_string()];
+        if self.config.jira.include_comments {
+            fields.push("comment".to_string());
         }
+        fields.extend(self.config.jira.custom_field_ids.iter().cloned());
+        fields.join(",")
+    }
 
-        // Filter for specification-related issues
-        jql_parts.push("(summary ~ 'specification' OR summary ~ 'spec' OR description ~ 'specification' OR description ~ 'spec')".to_string());
-        
-        // Order by last updated
-        jql_parts.push("ORDER BY updated DESC".to_string());
+    fn build_jql_query(&self, query: &JiraQuery) -> String {
+        // Only fetch issues updated since this query's last sync
+        let updated_since = self.last_sync_timestamps
+            .get(&query.name)
+            .map(|last_sync| self.format_jira_timestamp(*last_sync));
 
-        jql_parts.join(" AND ")
+        queries::build_jql(query, updated_since.as_deref())
     }
 
-    async fn convert_issue_to_document(&self, issue: JiraIssue) -> Result<Option<SpecDocument>, Box<dyn std::error::Error>> {
+    async fn convert_issue_to_document(
+        &self,
+        issue: JiraIssue,
+        field_names: &HashMap<String, String>,
+        token: &OAuth2Token,
+    ) -> Result<Option<SpecDocument>, Error> {
         // Skip issues that don't have meaningful content
         if issue.fields.description.is_none() && issue.fields.summary.is_empty() {
             return Ok(None);
         }
 
-        let content = self.extract_content(&issue)?;
-        let content_sha256 = self.compute_content_hash(&content);
+        let (content, section_spans) = self.extract_content(&issue, field_names)?;
+        let attachment_refs: Vec<AttachmentRef> = issue.fields.attachment
+            .iter()
+            .map(|attachment| AttachmentRef {
+                filename: attachment.filename.clone(),
+                mime_type: attachment.mimeType.clone(),
+                size: Some(attachment.size),
+                download_url: attachment.content.clone(),
+            })
+            .collect();
 
         let metadata = DocumentMetadata {
             source_id: issue.key,
@@ -224,14 +368,26 @@ impl JiraConnector {
 
         let mut document: SpecDocument = metadata.into();
         document.content = content;
-        document.content_sha256 = content_sha256;
+        document.metadata.extend(section_spans);
+        attachments::collect_attachments(&self.http_client, &self.backoff, &self.config.attachments, attachment_refs, token)
+            .await
+            .apply_to(&mut document);
+        document.content_sha256 = self.compute_content_hash(&document.content);
         document.source_system = "jira".to_string();
 
         Ok(Some(document))
     }
 
-    fn extract_content(&self, issue: &JiraIssue) -> Result<String, Box<dyn std::error::Error>> {
+    // Also returns the span of each custom field and comment section, keyed
+    // by its metadata key
+    fn extract_content(
+        &self,
+        issue: &JiraIssue,
+        field_names: &HashMap<String, String>,
+    ) -> Result<(String, HashMap<String, String>), Error> {
         let mut content_parts = Vec::new();
+        // Index into content_parts of each section, with its section id
+        let mut sections: Vec<(usize, String)> = Vec::new();
 
         // Add summary
         if !issue.fields.summary.is_empty() {
@@ -240,7 +396,39 @@ impl JiraConnector {
 
         // Add description
         if let Some(description) = &issue.fields.description {
-            content_parts.push(description.clone());
+            content_parts.push(description.to_markdown());
+        }
+
+        // Add configured custom fields, e.g. acceptance criteria
+        for field_id in &self.config.jira.custom_field_ids {
+            let Some(text) = issue.fields.other.get(field_id).and_then(custom_field_text) else {
+                continue;
+            };
+            let label = field_names.get(field_id).map(String::as_str).unwrap_or(field_id);
+            sections.push((content_parts.len(), field_id.clone()));
+            content_parts.push(format!(
+                "--- BEGIN FIELD {} ({}) ---\n{}\n--- END FIELD {} ---",
+                label, field_id, text, label
+            ));
+        }
+
+        // Add comments, oldest first
+        if self.config.jira.include_comments {
+            for comment in issue.fields.comment.iter().flat_map(|comments| &comments.comments) {
+                let text = comment.body.to_markdown();
+                if text.is_empty() {
+                    continue;
+                }
+                let author = comment.author
+                    .as_ref()
+                    .map(|author| author.displayName.as_str())
+                    .unwrap_or("Unknown");
+                sections.push((content_parts.len(), format!("comment.{}", comment.id)));
+                content_parts.push(format!(
+                    "--- BEGIN COMMENT {} ({}, {}) ---\n{}\n--- END COMMENT {} ---",
+                    comment.id, author, comment.created, text, comment.id
+                ));
+            }
         }
 
         // Add labels as tags
@@ -265,7 +453,22 @@ impl JiraConnector {
             }
         }
 
-        Ok(content_parts.join("\n\n"))
+        // Character offsets, as used by source spans downstream
+        let mut section_spans = HashMap::new();
+        let mut sections = sections.into_iter().peekable();
+        let mut offset = 0;
+        for (index, part) in content_parts.iter().enumerate() {
+            let length = part.chars().count();
+            if let Some((_, section_id)) = sections.next_if(|(section_index, _)| *section_index == index) {
+                section_spans.insert(
+                    format!("{}{}", JIRA_SECTION_METADATA_PREFIX, section_id),
+                    format!("{}-{}", offset, offset + length),
+                );
+            }
+            offset += length + 2;
+        }
+
+        Ok((content_parts.join("\n\n"), section_spans))
     }
 
     fn extract_recent_changes(&self, changelog: &JiraChangelog) -> String {
@@ -310,9 +513,9 @@ impl JiraConnector {
         format!("{:x}", hasher.finalize())
     }
 
-    fn parse_timestamp(&self, timestamp_str: &str) -> Result<Timestamp, Box<dyn std::error::Error>> {
+    fn parse_timestamp(&self, timestamp_str: &str) -> Result<Timestamp, Error> {
         // Jira timestamps are in format: "2023-01-01T12:00:00.000+0000"
-        let timestamp = chrono::DateTime::parse_from_str(timestamp_str, "%Y-%m-%dT%H:%M:%S.%3f%z")?;
+        let timestamp = chrono::DateTime::parse_from_str(timestamp_str, "%Y-%m-%dT%H:%M:%S.%3f%z").map_err(Error::invalid_input)?;
         let seconds = timestamp.timestamp();
         let nanos = timestamp.timestamp_subsec_nanos() as i32;
         
@@ -322,8 +525,8 @@ impl JiraConnector {
         })
     }
 
-    fn parse_jira_timestamp(&self, timestamp_str: &str) -> Result<i64, Box<dyn std::error::Error>> {
-        let timestamp = chrono::DateTime::parse_from_str(timestamp_str, "%Y-%m-%dT%H:%M:%S.%3f%z")?;
+    fn parse_jira_timestamp(&self, timestamp_str: &str) -> Result<i64, Error> {
+        let timestamp = chrono::DateTime::parse_from_str(timestamp_str, "%Y-%m-%dT%H:%M:%S.%3f%z").map_err(Error::invalid_input)?;
         Ok(timestamp.timestamp())
     }
 
@@ -334,6 +537,29 @@ impl JiraConnector {
     }
 }
 
+// Text of a custom field value: rich text, plain values, select options
+// and lists of them. None when the field is empty.
+fn custom_field_text(value: &serde_json::Value) -> Option<String> {
+    use serde_json::Value;
+
+    let text = match value {
+        Value::Null => return None,
+        Value::String(text) => text.trim().to_string(),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(flag) => flag.to_string(),
+        Value::Array(items) => items.iter().filter_map(custom_field_text).collect::<Vec<_>>().join(", "),
+        Value::Object(object) if object.get("type").and_then(Value::as_str) == Some("doc") => {
+            let node: AdfNode = serde_json::from_value(value.clone()).ok()?;
+            adf::to_markdown(&node)
+        }
+        Value::Object(object) => ["value", "name", "displayName"]
+            .iter()
+            .find_map(|key| object.get(*key).and_then(Value::as_str))?
+            .to_string(),
+    };
+    (!text.is_empty()).then_some(text)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -348,14 +574,25 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:jira-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
-        let connector = JiraConnector::new(config);
+        let mut connector = JiraConnector::new(config);
+        let query = connector.config.queries.jira_queries().remove(0);
         
         // Test without last sync timestamp
-        let jql = connector.build_jql_query();
+        let jql = connector.build_jql_query(&query);
         assert!(jql.contains("specification"));
-        assert!(jql.contains("ORDER BY updated DESC"));
+        assert!(jql.ends_with(" ORDER BY updated DESC"));
+        
+        // Each query resumes from its own cursor
+        connector.last_sync_timestamps.insert(query.name.clone(), 1_700_000_000);
+        assert!(connector.build_jql_query(&query).starts_with("updated >= '2023-11-14 22:13'"));
     }
 
     #[test]
@@ -367,6 +604,12 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:jira-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = JiraConnector::new(config);
@@ -385,6 +628,12 @@ mod tests {
             batch_size: 50,
             poll_interval_seconds: 300,
             secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:jira-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
         };
 
         let connector = JiraConnector::new(config);
@@ -393,4 +642,92 @@ mod tests {
         assert!(timestamp.seconds > 0);
         assert!(timestamp.nanos >= 0);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_rich_text_accepts_adf_and_plain_text() {
+        let adf: JiraRichText = serde_json::from_value(serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "Refunds never exceed the charge" }] }]
+        }))
+        .unwrap();
+        assert_eq!(adf.to_markdown(), "Refunds never exceed the charge");
+
+        let plain: JiraRichText = serde_json::from_value(serde_json::json!("Refunds never exceed the charge")).unwrap();
+        assert_eq!(plain.to_markdown(), "Refunds never exceed the charge");
+    }
+
+    #[test]
+    fn test_extract_custom_fields_and_comments() {
+        let mut config = ConnectorConfig {
+            source_system: "jira".to_string(),
+            base_url: "https://example.atlassian.net".to_string(),
+            rate_limit_per_minute: 100,
+            batch_size: 50,
+            poll_interval_seconds: 300,
+            secrets_arn: "arn:aws:secretsmanager:us-east-1:123456789012:secret:jira-oauth".to_string(),
+            transport: Default::default(),
+            queries: Default::default(),
+            attachments: Default::default(),
+            rate_limits: Default::default(),
+            jira: Default::default(),
+            tenant_id: None,
+        };
+        config.jira.custom_field_ids = vec!["customfield_10020".to_string(), "customfield_10030".to_string()];
+        config.jira.include_comments = true;
+        let connector = JiraConnector::new(config);
+        assert!(connector.search_fields().ends_with(",comment,customfield_10020,customfield_10030"));
+
+        let adf = |text: &str| serde_json::json!({
+            "type": "doc",
+            "version": 1,
+            "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": text }] }]
+        });
+        let issue: JiraIssue = serde_json::from_value(serde_json::json!({
+            "id": "10000",
+            "key": "PAY-1",
+            "fields": {
+                "summary": "Refunds",
+                "description": adf("Customers can request refunds."),
+                "status": { "name": "Open", "statusCategory": { "name": "To Do" } },
+                "assignee": null,
+                "reporter": null,
+                "created": "2023-11-14T22:13:20.000+0000",
+                "updated": "2023-11-14T22:13:20.000+0000",
+                "project": { "key": "PAY", "name": "Payments" },
+                "issuetype": { "name": "Story", "description": null },
+                "priority": null,
+                "labels": [],
+                "components": [],
+                "customfield_10020": adf("Refunds never exceed the charge."),
+                "customfield_10030": null,
+                "comment": { "comments": [{
+                    "id": "10001",
+                    "author": { "displayName": "Jane Doe", "emailAddress": null },
+                    "body": adf("Partial refunds count too."),
+                    "created": "2023-11-15T09:00:00.000+0000"
+                }] }
+            },
+            "changelog": null
+        }))
+        .unwrap();
+        let names = HashMap::from([("customfield_10020".to_string(), "Acceptance Criteria".to_string())]);
+
+        let (content, spans) = connector.extract_content(&issue, &names).unwrap();
+        assert!(content.contains(
+            "--- BEGIN FIELD Acceptance Criteria (customfield_10020) ---\nRefunds never exceed the charge.\n\
+             --- END FIELD Acceptance Criteria ---"
+        ));
+        assert!(content.contains(
+            "--- BEGIN COMMENT 10001 (Jane Doe, 2023-11-15T09:00:00.000+0000) ---\nPartial refunds count too.\n\
+             --- END COMMENT 10001 ---"
+        ));
+        assert_eq!(spans.len(), 2);
+
+        let chars: Vec<char> = content.chars().collect();
+        let (start, end) = spans["jira_section.comment.10001"].split_once('-').unwrap();
+        let section: String = chars[start.parse::<usize>().unwrap()..end.parse::<usize>().unwrap()].iter().collect();
+        assert!(section.starts_with("--- BEGIN COMMENT 10001"));
+        assert!(section.ends_with("--- END COMMENT 10001 ---"));
+    }
+}
@@ -1,7 +1,10 @@
 pub mod jira;
 pub mod confluence;
 pub mod gdocs;
+pub mod git_repo;
+pub mod adf;
 
 pub use jira::JiraConnector;
 pub use confluence::ConfluenceConnector;
-pub use gdocs::GoogleDocsConnector; 
\ No newline at end of file
+pub use gdocs::GoogleDocsConnector;
+pub use git_repo::GitRepoConnector; 
\ No newline at end of file
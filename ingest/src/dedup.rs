@@ -0,0 +1,184 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use spec_to_proof_error::Error;
+use chrono::Utc;
+use sha2::{Sha256, Digest};
+use storage::{Entity, ExpectedVersion, OutboxEvent, Repository};
+use crate::proto::spec_to_proof::v1::SpecDocument;
+
+// Content-hash dedup for polled documents. Connectors re-poll the same
+// documents on every interval; only documents whose content hash differs
+// from the last published hash for their source_id are forwarded, so
+// unchanged documents never trigger downstream LLM calls.
+
+pub fn content_sha256(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishOutcome {
+    Published,
+    Skipped,
+}
+
+/// The content hash of the last published version of a source document,
+/// keyed by "source_system#source_id"
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PublishedHash {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub content_sha256: String,
+    #[prost(int64, tag = "3")]
+    pub published_at_ms: i64,
+}
+
+impl Entity for PublishedHash {
+    const KIND: &'static str = "PUBLISHED_HASH";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        None
+    }
+
+    fn status(&self) -> i32 {
+        0
+    }
+
+    fn set_status(&mut self, _status: i32) {}
+}
+
+fn source_key(source_system: &str, source_id: &str) -> String {
+    format!("{}#{}", source_system, source_id)
+}
+
+pub struct DocumentDeduplicator {
+    repository: Arc<dyn Repository<PublishedHash>>,
+    skipped_documents: AtomicU64,
+}
+
+impl std::fmt::Debug for DocumentDeduplicator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DocumentDeduplicator")
+            .field("skipped_documents", &self.skipped_documents)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DocumentDeduplicator {
+    pub fn new(repository: Arc<dyn Repository<PublishedHash>>) -> Self {
+        Self {
+            repository,
+            skipped_documents: AtomicU64::new(0),
+        }
+    }
+
+    // Fills in content_sha256 when the connector didn't, and reports
+    // whether the document changed since it was last published
+    pub async fn check(&self, document: &mut SpecDocument) -> Result<PublishOutcome, Error> {
+        if document.content_sha256.is_empty() {
+            document.content_sha256 = content_sha256(&document.content);
+        }
+
+        let last = self.repository
+            .get(&source_key(&document.source_system, &document.source_id))
+            .await?;
+
+        if last.is_some_and(|last| last.entity.content_sha256 == document.content_sha256) {
+            let skipped = self.skipped_documents.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::info!(
+                metric = "ingest_documents_skipped_total",
+                value = skipped,
+                source_system = %document.source_system,
+                "Skipping unchanged document {}",
+                document.source_id
+            );
+            return Ok(PublishOutcome::Skipped);
+        }
+
+        Ok(PublishOutcome::Published)
+    }
+
+    /// Records the document's hash and, in the same transaction, the outbox
+    /// events announcing it, so the document is either recorded and queued
+    /// for delivery or neither
+    pub async fn record_published(&self, document: &SpecDocument, events: &[OutboxEvent]) -> Result<(), Error> {
+        let published = PublishedHash {
+            id: source_key(&document.source_system, &document.source_id),
+            content_sha256: document.content_sha256.clone(),
+            published_at_ms: Utc::now().timestamp_millis(),
+        };
+        self.repository.put_with_events(&published, ExpectedVersion::Any, events).await?;
+        Ok(())
+    }
+
+    pub fn skipped_documents(&self) -> u64 {
+        self.skipped_documents.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::{InMemoryOutbox, InMemoryRepository, OutboxStore};
+
+    fn document(source_id: &str, content: &str) -> SpecDocument {
+        SpecDocument {
+            id: format!("{}-1", source_id),
+            source_system: "jira".to_string(),
+            source_id: source_id.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_documents_are_skipped() {
+        let dedup = DocumentDeduplicator::new(Arc::new(InMemoryRepository::new()));
+
+        let mut first = document("PROJ-1", "The balance must never be negative");
+        assert_eq!(dedup.check(&mut first).await.unwrap(), PublishOutcome::Published);
+        assert_eq!(first.content_sha256, content_sha256("The balance must never be negative"));
+        dedup.record_published(&first, &[]).await.unwrap();
+
+        let mut repoll = document("PROJ-1", "The balance must never be negative");
+        assert_eq!(dedup.check(&mut repoll).await.unwrap(), PublishOutcome::Skipped);
+        assert_eq!(dedup.skipped_documents(), 1);
+
+        let mut edited = document("PROJ-1", "The balance must stay above 100");
+        assert_eq!(dedup.check(&mut edited).await.unwrap(), PublishOutcome::Published);
+    }
+
+    #[tokio::test]
+    async fn test_hashes_are_tracked_per_source_id() {
+        let dedup = DocumentDeduplicator::new(Arc::new(InMemoryRepository::new()));
+
+        let mut first = document("PROJ-1", "same text");
+        dedup.check(&mut first).await.unwrap();
+        dedup.record_published(&first, &[]).await.unwrap();
+
+        let mut other = document("PROJ-2", "same text");
+        assert_eq!(dedup.check(&mut other).await.unwrap(), PublishOutcome::Published);
+        assert_eq!(dedup.skipped_documents(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_events_are_recorded_with_the_hash() {
+        let outbox = Arc::new(InMemoryOutbox::new());
+        let dedup = DocumentDeduplicator::new(Arc::new(InMemoryRepository::new().with_outbox(outbox.clone())));
+
+        let mut first = document("PROJ-1", "The balance must never be negative");
+        dedup.check(&mut first).await.unwrap();
+        let event = OutboxEvent::new("spec-documents.jira", "key-1", b"{}".to_vec());
+        dedup.record_published(&first, std::slice::from_ref(&event)).await.unwrap();
+
+        assert_eq!(outbox.fetch_pending(10).await.unwrap(), vec![event]);
+        let mut repoll = document("PROJ-1", "The balance must never be negative");
+        assert_eq!(dedup.check(&mut repoll).await.unwrap(), PublishOutcome::Skipped);
+    }
+}
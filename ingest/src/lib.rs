@@ -1,17 +1,30 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
+use spec_to_proof_error::Error;
 use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
 use aws_sdk_secretsmanager::Client as SecretsClient;
-use nats::jetstream::Context as JetStreamContext;
 use crate::proto::spec_to_proof::v1::SpecDocument;
 use crate::proto::google::protobuf::Timestamp;
+use pipeline_control::{Pause, PauseGate, Stage, DOCUMENT_TENANT_METADATA_KEY};
+use prost::Message;
+use spec_to_proof_proto::{validation, FromProto, SpecDocumentModel};
 
 pub mod proto;
 pub mod connectors;
 pub mod secrets;
 pub mod rate_limiter;
 pub mod backoff;
+pub mod outbox;
+pub mod bus;
+pub mod dedup;
+pub mod queries;
+pub mod attachments;
+pub mod conditional;
+pub mod openapi;
+pub mod proto_schema;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectorConfig {
@@ -21,6 +34,21 @@ pub struct ConnectorConfig {
     pub batch_size: usize,
     pub poll_interval_seconds: u64,
     pub secrets_arn: String,
+    #[serde(default)]
+    pub transport: bus::TransportConfig,
+    #[serde(default)]
+    pub queries: queries::SourceQueries,
+    #[serde(default)]
+    pub attachments: attachments::AttachmentConfig,
+    #[serde(default)]
+    pub rate_limits: rate_limiter::RateLimitConfig,
+    /// Custom fields and comments ingested from Jira issues
+    #[serde(default)]
+    pub jira: connectors::jira::JiraContentConfig,
+    /// Tenant the ingested documents belong to; recorded in their metadata
+    /// so downstream stages can be paused per tenant
+    #[serde(default)]
+    pub tenant_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,38 +63,99 @@ pub struct OAuth2Token {
 pub struct IngestionConnector {
     config: ConnectorConfig,
     secrets_client: SecretsClient,
-    jetstream: JetStreamContext,
+    bus: Arc<dyn bus::MessageBus>,
     token_cache: RwLock<HashMap<String, OAuth2Token>>,
+    credentials: Option<Arc<secrets::RotatingCredentials>>,
+    // Credentials generation the cached tokens were minted under
+    credentials_generation: AtomicU64,
     rate_limiter: rate_limiter::RateLimiter,
+    outbox: Arc<dyn outbox::OutboxStore>,
+    deduplicator: dedup::DocumentDeduplicator,
+    pause_gate: Arc<PauseGate>,
 }
 
 impl IngestionConnector {
     pub async fn new(
         config: ConnectorConfig,
         secrets_client: SecretsClient,
-        jetstream: JetStreamContext,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        let rate_limiter = rate_limiter::RateLimiter::new(
-            config.rate_limit_per_minute,
-            Duration::from_secs(60),
-        );
+        bus: Arc<dyn bus::MessageBus>,
+    ) -> Result<Self, Error> {
+        let rate_limiter = rate_limiter::RateLimiter::from_config(config.rate_limit_per_minute, &config.rate_limits);
+
+        let outbox = Arc::new(outbox::InMemoryOutbox::new());
 
         Ok(Self {
             config,
             secrets_client,
-            jetstream,
+            bus,
             token_cache: RwLock::new(HashMap::new()),
+            credentials: None,
+            credentials_generation: AtomicU64::new(0),
             rate_limiter,
+            deduplicator: dedup::DocumentDeduplicator::new(Arc::new(
+                storage::InMemoryRepository::new().with_outbox(outbox.clone()),
+            )),
+            outbox,
+            pause_gate: Arc::new(PauseGate::disabled()),
         })
     }
 
-    pub async fn start_polling(&self) -> Result<(), Box<dyn std::error::Error>> {
+    // Published hashes and their outbox events must outlive the process
+    // for dedup and delivery to survive restarts; both are kept in the
+    // entity store so they can be written in one transaction
+    pub fn with_storage(mut self, store: &storage::EntityStore) -> Self {
+        self.deduplicator = dedup::DocumentDeduplicator::new(store.repository());
+        self.outbox = store.outbox();
+        self
+    }
+
+    // Cached tokens are dropped whenever these credentials rotate
+    pub fn with_rotating_credentials(mut self, credentials: Arc<secrets::RotatingCredentials>) -> Self {
+        self.credentials_generation = AtomicU64::new(credentials.generation());
+        self.credentials = Some(credentials);
+        self
+    }
+
+    // Polling is skipped while ingestion is paused for this connector's
+    // tenant or source system
+    pub fn with_pause_gate(mut self, pause_gate: Arc<PauseGate>) -> Self {
+        self.pause_gate = pause_gate;
+        self
+    }
+
+    /// The pause holding back this connector, if any
+    pub async fn ingestion_pause(&self) -> Option<Pause> {
+        self.pause_gate
+            .check(Stage::Ingestion, self.config.tenant_id.as_deref(), Some(&self.config.source_system))
+            .await
+    }
+
+    pub fn skipped_documents(&self) -> u64 {
+        self.deduplicator.skipped_documents()
+    }
+
+    pub async fn rate_limit_utilization(&self) -> Vec<rate_limiter::LimitUtilization> {
+        self.rate_limiter.utilization().await
+    }
+
+    // Relay that drains this connector's outbox; run it in the background so
+    // events left behind by a crash are eventually published
+    pub fn outbox_relay(&self) -> outbox::OutboxRelay {
+        outbox::OutboxRelay::new(self.outbox.clone(), Arc::new(outbox::BusSink::new(self.bus.clone())))
+    }
+
+    pub async fn start_polling(&self) -> Result<(), Error> {
         let mut interval = tokio::time::interval(
             Duration::from_secs(self.config.poll_interval_seconds)
         );
 
         loop {
             interval.tick().await;
+
+            if let Some(pause) = self.ingestion_pause().await {
+                tracing::info!("Ingestion from {} is paused by {}, skipping poll: {}", self.config.source_system, pause.paused_by, pause.reason);
+                continue;
+            }
             
             match self.poll_documents().await {
                 Ok(documents) => {
@@ -82,32 +171,61 @@ impl IngestionConnector {
         }
     }
 
-    async fn poll_documents(&self) -> Result<Vec<SpecDocument>, Box<dyn std::error::Error>> {
+    async fn poll_documents(&self) -> Result<Vec<SpecDocument>, Error> {
         // This will be implemented by specific connectors
         todo!("Implement in specific connector")
     }
 
-    async fn publish_document(&self, document: SpecDocument) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn publish_document(&self, mut document: SpecDocument) -> Result<dedup::PublishOutcome, Error> {
+        if document.source_system.is_empty() {
+            document.source_system = self.config.source_system.clone();
+        }
+        if let Some(tenant_id) = &self.config.tenant_id {
+            document.metadata
+                .entry(DOCUMENT_TENANT_METADATA_KEY.to_string())
+                .or_insert_with(|| tenant_id.clone());
+        }
+        if self.deduplicator.check(&mut document).await? == dedup::PublishOutcome::Skipped {
+            return Ok(dedup::PublishOutcome::Skipped);
+        }
+
+        validate_document(&document)?;
+
         let subject = format!("spec-documents.{}", self.config.source_system);
         
         let payload = serde_json::to_vec(&document)?;
+        let key = outbox::idempotency_key(&[&subject, &document.id, &document.content_sha256]);
         
-        self.jetstream
-            .publish(&subject, &payload)
+        // The hash and the event are written in one transaction; the relay
+        // publishes the event with the idempotency key so retries never
+        // produce duplicates
+        self.deduplicator
+            .record_published(&document, &[outbox::OutboxEvent::new(&subject, &key, payload)])
             .await
-            .map_err(|e| format!("Failed to publish to JetStream: {}", e))?;
+            .map_err(|e| e.context("Failed to write document to outbox"))?;
+
+        let published = self.outbox_relay().relay_pending().await?;
 
         tracing::info!(
-            "Published document {} to JetStream subject {}",
+            "Queued document {} for subject {} ({} outbox events published)",
             document.id,
-            subject
+            subject,
+            published
         );
 
-        Ok(())
+        Ok(dedup::PublishOutcome::Published)
     }
 
-    pub async fn refresh_token(&self, token_key: &str) -> Result<OAuth2Token, Box<dyn std::error::Error>> {
+    pub async fn refresh_token(&self, token_key: &str) -> Result<OAuth2Token, Error> {
         let mut cache = self.token_cache.write().await;
+
+        if let Some(credentials) = &self.credentials {
+            let generation = credentials.generation();
+            if self.credentials_generation.swap(generation, Ordering::AcqRel) != generation {
+                tracing::info!("Credentials rotated, dropping {} cached tokens", cache.len());
+                cache.clear();
+            }
+        }
         
         if let Some(token) = cache.get(token_key) {
             if token.expires_at > Instant::now() {
@@ -120,7 +238,7 @@ impl IngestionConnector {
             .get_secret_value()
             .secret_id(token_key)
             .send()
-            .await?;
+            .await.map_err(Error::transient)?;
 
         let token_data: OAuth2Token = serde_json::from_str(
             secret_value.secret_string()
@@ -132,6 +250,17 @@ impl IngestionConnector {
     }
 }
 
+// Checks a document against the shared SpecDocument schema before it is
+// announced, so malformed documents never reach extraction
+fn validate_document(document: &SpecDocument) -> Result<(), Error> {
+    let shared = spec_to_proof_proto::SpecDocument::decode(document.encode_to_vec().as_slice())
+        .map_err(|e| Error::internal(format!("Failed to re-encode document {}: {}", document.id, e)))?;
+    let model = SpecDocumentModel::from_proto(shared)
+        .map_err(|e| Error::invalid_input(format!("Document {} has invalid timestamps: {}", document.id, e)))?;
+    validation::validate_spec_document(&serde_json::to_value(&model)?)
+        .map_err(|e| Error::invalid_input(format!("Document {} rejected: {}", document.id, e)))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DocumentMetadata {
     pub source_id: String,
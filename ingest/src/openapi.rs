@@ -0,0 +1,71 @@
+use serde_json::Value;
+
+// OpenAPI and Swagger documents are ingested like any other spec, tagged so
+// extraction reads their schema constraints directly instead of sending
+// them to the model.
+
+/// Metadata key holding the document format when it is not prose
+pub const FORMAT_METADATA_KEY: &str = "format";
+
+pub const OPENAPI_FORMAT: &str = "openapi";
+
+/// Metadata key holding the `openapi` or `swagger` version field
+pub const OPENAPI_VERSION_METADATA_KEY: &str = "openapi_version";
+
+const OPENAPI_EXTENSIONS: &[&str] = &[".json", ".yaml", ".yml"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApiDocument {
+    pub version: String,
+    /// `info.title`, if the document has one
+    pub title: Option<String>,
+}
+
+/// Recognizes an OpenAPI 3 or Swagger 2 document by its extension and its
+/// top-level `openapi` or `swagger` field. Other JSON and YAML files, such
+/// as CI configuration, are not specs.
+pub fn detect(path: &str, content: &str) -> Option<OpenApiDocument> {
+    let path = path.to_lowercase();
+    if !OPENAPI_EXTENSIONS.iter().any(|extension| path.ends_with(extension)) {
+        return None;
+    }
+
+    let document: Value = serde_json::from_str(content)
+        .ok()
+        .or_else(|| serde_yaml::from_str(content).ok())?;
+    let version = document
+        .get("openapi")
+        .or_else(|| document.get("swagger"))
+        .and_then(Value::as_str)?;
+    let title = document
+        .pointer("/info/title")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(str::to_string);
+
+    Some(OpenApiDocument {
+        version: version.to_string(),
+        title,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let yaml = "openapi: 3.0.3\ninfo:\n  title: Payments API\n  version: 1.0.0\npaths: {}\n";
+        assert_eq!(
+            detect("api/payments.yaml", yaml),
+            Some(OpenApiDocument { version: "3.0.3".to_string(), title: Some("Payments API".to_string()) })
+        );
+        assert_eq!(
+            detect("api/legacy.JSON", r#"{"swagger": "2.0", "paths": {}}"#),
+            Some(OpenApiDocument { version: "2.0".to_string(), title: None })
+        );
+        assert_eq!(detect(".github/workflows/ci.yml", "name: CI\non: push\n"), None);
+        assert_eq!(detect("docs/specs/api.md", yaml), None);
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use spec_to_proof_error::Error;
+use crate::bus::{BusMessage, MessageBus, MESSAGE_ID_HEADER};
+
+// Documents are announced through the transactional outbox in the storage
+// crate: DocumentDeduplicator writes the event in the same transaction as
+// the document's published hash, and the relay delivers it to the message
+// bus through BusSink.
+pub use storage::{idempotency_key, InMemoryOutbox, OutboxEvent, OutboxRelay, OutboxStore};
+
+/// Delivers outbox events to the message bus. The idempotency key is sent
+/// as the message ID header so JetStream drops duplicates when the relay
+/// retries after a crash between publish and mark_published.
+#[derive(Debug)]
+pub struct BusSink {
+    bus: Arc<dyn MessageBus>,
+}
+
+impl BusSink {
+    pub fn new(bus: Arc<dyn MessageBus>) -> Self {
+        Self { bus }
+    }
+}
+
+#[async_trait::async_trait]
+impl storage::OutboxSink for BusSink {
+    async fn deliver(&self, event: &OutboxEvent) -> Result<(), Error> {
+        let message = BusMessage::new(&event.subject, event.payload.clone())
+            .with_header(MESSAGE_ID_HEADER, &event.idempotency_key);
+        self.bus.publish(message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_relay_publishes_with_message_id() {
+        let outbox = Arc::new(InMemoryOutbox::new());
+        let bus = Arc::new(crate::bus::InMemoryBus::new());
+        outbox
+            .append(&[OutboxEvent::new("spec-documents.jira", "key-1", b"{}".to_vec())])
+            .await
+            .unwrap();
+
+        let relay = OutboxRelay::new(outbox.clone(), Arc::new(BusSink::new(bus.clone())));
+        assert_eq!(relay.relay_pending().await.unwrap(), 1);
+        assert_eq!(relay.relay_pending().await.unwrap(), 0);
+
+        let published = bus.published().await;
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].message_id(), Some("key-1"));
+    }
+}
@@ -0,0 +1,57 @@
+// `.proto` files are ingested as message contracts; extraction reads
+// their validation options and field comments directly.
+
+pub const PROTOBUF_FORMAT: &str = "protobuf";
+
+/// Metadata key holding the file's `package`
+pub const PROTO_PACKAGE_METADATA_KEY: &str = "proto_package";
+
+/// Metadata key holding the comma-separated top-level message names
+pub const PROTO_MESSAGES_METADATA_KEY: &str = "proto_messages";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtoSchema {
+    pub package: Option<String>,
+    pub messages: Vec<String>,
+}
+
+/// Recognizes a `.proto` file declaring at least one message
+pub fn detect(path: &str, content: &str) -> Option<ProtoSchema> {
+    if !path.to_lowercase().ends_with(".proto") {
+        return None;
+    }
+
+    let mut package = None;
+    let mut messages = Vec::new();
+    for line in content.lines() {
+        // Nested messages are indented; they belong to their parent
+        if let Some(rest) = line.strip_prefix("package ") {
+            package = Some(rest.trim().trim_end_matches(';').trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("message ") {
+            if let Some(name) = rest.split(|c: char| c.is_whitespace() || c == '{').next().filter(|name| !name.is_empty()) {
+                messages.push(name.to_string());
+            }
+        }
+    }
+
+    (!messages.is_empty()).then_some(ProtoSchema { package, messages })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let content = "syntax = \"proto3\";\npackage payments.v1;\n\nmessage Refund {\n  message Item {}\n}\nmessage Payment{}\n";
+        assert_eq!(
+            detect("proto/payments/v1/refunds.proto", content),
+            Some(ProtoSchema {
+                package: Some("payments.v1".to_string()),
+                messages: vec!["Refund".to_string(), "Payment".to_string()],
+            })
+        );
+        assert_eq!(detect("proto/empty.proto", "syntax = \"proto3\";\nservice Ping {}\n"), None);
+        assert_eq!(detect("docs/refunds.md", content), None);
+    }
+}
@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+
+// Per-connector search configuration. Each connector runs every configured
+// query on each poll and records the query name on the documents it finds.
+// A connector with no queries configured falls back to a keyword search for
+// "spec"/"specification".
+
+/// Metadata key holding the name of the query that found a document
+pub const SOURCE_QUERY_METADATA_KEY: &str = "source_query";
+
+pub const DEFAULT_QUERY_NAME: &str = "default";
+
+const DEFAULT_SEARCH_TERMS: &[&str] = &["specification", "spec"];
+
+const GOOGLE_DOCS_MIME_TYPE: &str = "application/vnd.google-apps.document";
+
+pub const DEFAULT_SPEC_PATH_PATTERN: &str = "docs/specs/**/*.md";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceQueries {
+    #[serde(default)]
+    pub jira: Vec<JiraQuery>,
+    #[serde(default)]
+    pub confluence: Vec<ConfluenceQuery>,
+    #[serde(default)]
+    pub google_docs: Vec<GoogleDocsQuery>,
+    #[serde(default)]
+    pub git_repos: Vec<GitRepoQuery>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JiraQuery {
+    pub name: String,
+    /// JQL filter; may end in its own ORDER BY clause
+    pub jql: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfluenceQuery {
+    pub name: String,
+    /// Extra CQL filter; the keyword search is used when unset
+    #[serde(default)]
+    pub cql: Option<String>,
+    /// Restricts the search to these spaces
+    #[serde(default)]
+    pub space_keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoogleDocsQuery {
+    pub name: String,
+    /// Restricts the search to documents directly inside these folders
+    #[serde(default)]
+    pub folder_ids: Vec<String>,
+    /// Full-text terms, any of which must match; the keyword search is used
+    /// when empty
+    #[serde(default)]
+    pub terms: Vec<String>,
+    /// Appends page header and footer text, which is left out by default
+    #[serde(default)]
+    pub include_headers_footers: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitRepoQuery {
+    pub name: String,
+    /// GitHub repository as "owner/repo"
+    pub repository: String,
+    /// Branch, tag or commit to read; the default branch when unset
+    #[serde(default)]
+    pub git_ref: Option<String>,
+    /// Glob patterns for spec files, relative to the repository root
+    #[serde(default = "default_spec_paths")]
+    pub paths: Vec<String>,
+}
+
+fn default_spec_paths() -> Vec<String> {
+    vec![DEFAULT_SPEC_PATH_PATTERN.to_string()]
+}
+
+impl SourceQueries {
+    pub fn jira_queries(&self) -> Vec<JiraQuery> {
+        if !self.jira.is_empty() {
+            return self.jira.clone();
+        }
+        vec![JiraQuery {
+            name: DEFAULT_QUERY_NAME.to_string(),
+            jql: "summary ~ 'specification' OR summary ~ 'spec' OR description ~ 'specification' OR description ~ 'spec'".to_string(),
+        }]
+    }
+
+    pub fn confluence_queries(&self) -> Vec<ConfluenceQuery> {
+        if !self.confluence.is_empty() {
+            return self.confluence.clone();
+        }
+        vec![ConfluenceQuery {
+            name: DEFAULT_QUERY_NAME.to_string(),
+            cql: None,
+            space_keys: vec![],
+        }]
+    }
+
+    pub fn google_docs_queries(&self) -> Vec<GoogleDocsQuery> {
+        if !self.google_docs.is_empty() {
+            return self.google_docs.clone();
+        }
+        vec![GoogleDocsQuery {
+            name: DEFAULT_QUERY_NAME.to_string(),
+            folder_ids: vec![],
+            terms: vec![],
+            include_headers_footers: false,
+        }]
+    }
+
+    /// Git repositories have no sensible default, so nothing is scanned
+    /// until a repository is configured
+    pub fn git_repo_queries(&self) -> Vec<GitRepoQuery> {
+        self.git_repos.clone()
+    }
+
+    /// Query names identify where a document came from and key the
+    /// per-query sync cursor, so they must be unique per source
+    pub fn validate(&self) -> Result<(), String> {
+        validate_names("jira", self.jira.iter().map(|q| q.name.as_str()))?;
+        validate_names("confluence", self.confluence.iter().map(|q| q.name.as_str()))?;
+        validate_names("google_docs", self.google_docs.iter().map(|q| q.name.as_str()))?;
+        validate_names("git_repos", self.git_repos.iter().map(|q| q.name.as_str()))?;
+
+        if let Some(query) = self.jira.iter().find(|q| q.jql.trim().is_empty()) {
+            return Err(format!("jira query '{}' has an empty jql", query.name));
+        }
+        for query in &self.git_repos {
+            let valid_repository = matches!(
+                query.repository.split('/').collect::<Vec<_>>().as_slice(),
+                [owner, repo] if !owner.is_empty() && !repo.is_empty()
+            );
+            if !valid_repository {
+                return Err(format!("git_repos query '{}' must name a repository as owner/repo", query.name));
+            }
+            if query.paths.is_empty() {
+                return Err(format!("git_repos query '{}' has no path patterns", query.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn validate_names<'a>(source: &str, names: impl Iterator<Item = &'a str>) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for name in names {
+        if name.trim().is_empty() {
+            return Err(format!("{} query names must not be empty", source));
+        }
+        if !seen.insert(name) {
+            return Err(format!("duplicate {} query name '{}'", source, name));
+        }
+    }
+    Ok(())
+}
+
+// Splits a trailing ORDER BY clause off a JQL/CQL filter so the filter can
+// be combined with other conditions
+fn split_order_by(query: &str) -> (&str, Option<&str>) {
+    match query.to_ascii_lowercase().rfind("order by") {
+        Some(index) => (query[..index].trim(), Some(query[index..].trim())),
+        None => (query.trim(), None),
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn keyword_filter(fields: &[&str], operator: &str) -> String {
+    let clauses: Vec<String> = fields
+        .iter()
+        .flat_map(|field| {
+            DEFAULT_SEARCH_TERMS
+                .iter()
+                .map(move |term| format!("{} {} {}", field, operator, quote(term)))
+        })
+        .collect();
+    clauses.join(" OR ")
+}
+
+/// `updated_since` is already formatted as a Jira date ("yyyy-MM-dd HH:mm")
+pub fn build_jql(query: &JiraQuery, updated_since: Option<&str>) -> String {
+    let (filter, order_by) = split_order_by(&query.jql);
+
+    let mut parts = Vec::new();
+    if let Some(since) = updated_since {
+        parts.push(format!("updated >= {}", quote(since)));
+    }
+    if !filter.is_empty() {
+        parts.push(format!("({})", filter));
+    }
+
+    format!("{} {}", parts.join(" AND "), order_by.unwrap_or("ORDER BY updated DESC"))
+}
+
+pub fn build_cql(query: &ConfluenceQuery, modified_since: Option<&str>) -> String {
+    let custom = query.cql.as_deref().map(split_order_by);
+
+    let mut parts = Vec::new();
+    if let Some(since) = modified_since {
+        parts.push(format!("lastmodified >= {}", quote(since)));
+    }
+    match custom {
+        Some((filter, _)) if !filter.is_empty() => parts.push(format!("({})", filter)),
+        Some(_) => {}
+        None => parts.push(format!("({})", keyword_filter(&["text", "title"], "~"))),
+    }
+    if !query.space_keys.is_empty() {
+        let spaces: Vec<String> = query.space_keys.iter().map(|key| format!("\"{}\"", key)).collect();
+        parts.push(format!("space in ({})", spaces.join(", ")));
+    }
+    parts.push("type = page".to_string());
+
+    let order_by = custom.and_then(|(_, order_by)| order_by).unwrap_or("ORDER BY lastmodified DESC");
+    format!("{} {}", parts.join(" AND "), order_by)
+}
+
+/// Drive `files.list` query; `modified_since` is an RFC 3339 timestamp
+pub fn build_drive_query(query: &GoogleDocsQuery, modified_since: Option<&str>) -> String {
+    let mut parts = vec![
+        format!("mimeType = {}", quote(GOOGLE_DOCS_MIME_TYPE)),
+        "trashed = false".to_string(),
+    ];
+
+    if let Some(since) = modified_since {
+        parts.push(format!("modifiedTime > {}", quote(since)));
+    }
+
+    if !query.folder_ids.is_empty() {
+        let folders: Vec<String> = query.folder_ids.iter().map(|id| format!("{} in parents", quote(id))).collect();
+        parts.push(format!("({})", folders.join(" OR ")));
+    }
+
+    if query.terms.is_empty() {
+        parts.push(format!("({})", keyword_filter(&["name", "fullText"], "contains")));
+    } else {
+        let terms: Vec<String> = query.terms.iter().map(|term| format!("fullText contains {}", quote(term))).collect();
+        parts.push(format!("({})", terms.join(" OR ")));
+    }
+
+    parts.join(" AND ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_jql_keeps_custom_order_by() {
+        let query = JiraQuery {
+            name: "payments".to_string(),
+            jql: "project = PAY AND labels = spec ORDER BY created ASC".to_string(),
+        };
+
+        assert_eq!(
+            build_jql(&query, Some("2024-01-02 03:04")),
+            "updated >= '2024-01-02 03:04' AND (project = PAY AND labels = spec) ORDER BY created ASC"
+        );
+
+        let default = &SourceQueries::default().jira_queries()[0];
+        let jql = build_jql(default, None);
+        assert!(jql.starts_with("(summary ~ 'specification'"));
+        assert!(jql.ends_with(") ORDER BY updated DESC"));
+    }
+
+    #[test]
+    fn test_build_cql_with_space_keys() {
+        let query = ConfluenceQuery {
+            name: "eng-specs".to_string(),
+            cql: Some("label = \"spec\"".to_string()),
+            space_keys: vec!["ENG".to_string(), "PAY".to_string()],
+        };
+
+        assert_eq!(
+            build_cql(&query, None),
+            "(label = \"spec\") AND space in (\"ENG\", \"PAY\") AND type = page ORDER BY lastmodified DESC"
+        );
+
+        let default = &SourceQueries::default().confluence_queries()[0];
+        assert!(build_cql(default, None).contains("title ~ 'spec'"));
+    }
+
+    #[test]
+    fn test_build_drive_query() {
+        let query = GoogleDocsQuery {
+            name: "product".to_string(),
+            folder_ids: vec!["folder1".to_string()],
+            terms: vec!["O'Brien rule".to_string()],
+            include_headers_footers: false,
+        };
+
+        assert_eq!(
+            build_drive_query(&query, Some("2024-01-02T03:04:05Z")),
+            "mimeType = 'application/vnd.google-apps.document' AND trashed = false \
+             AND modifiedTime > '2024-01-02T03:04:05Z' AND ('folder1' in parents) \
+             AND (fullText contains 'O\\'Brien rule')"
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicate_names() {
+        let query = JiraQuery {
+            name: "specs".to_string(),
+            jql: "project = PAY".to_string(),
+        };
+        let queries = SourceQueries {
+            jira: vec![query.clone(), query],
+            ..Default::default()
+        };
+
+        assert!(queries.validate().is_err());
+        assert!(SourceQueries::default().validate().is_ok());
+    }
+}
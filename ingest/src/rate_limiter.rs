@@ -1,73 +1,443 @@
-use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime};
+use spec_to_proof_error::Error;
 use tokio::sync::Mutex;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Limits nest: every request counts against the global limit, against its
+// endpoint's limit when one is configured, and against the per-token limit
+// when one is configured. A request waits until all of them have room.
+//
+// Source APIs signal overload with 429 and Retry-After. The most specific
+// limit the request went through pauses for that long and scales down the
+// rate it allows; each success afterwards scales it back up a step
+// (multiplicative decrease, additive increase).
+
+/// Pause after a 429 that came without a usable Retry-After
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+/// Lowest fraction of a limit adaptive throttling will scale it to
+const MIN_THROTTLE: f64 = 0.1;
+const THROTTLE_DECREASE: f64 = 0.5;
+const THROTTLE_RECOVERY_STEP: f64 = 0.05;
+
+/// `max_requests` in any sliding `window`, plus up to `burst` extra
+/// requests drawn from a token bucket refilled at `max_requests / window`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub window: Duration,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        Self { max_requests, window, burst: 0 }
+    }
+
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60))
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+}
+
+/// Limits layered on a connector's `rate_limit_per_minute`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Requests allowed beyond the per-minute limit in a burst
+    #[serde(default)]
+    pub burst: u32,
+    /// Per-minute limit for each OAuth token; unlimited when unset
+    #[serde(default)]
+    pub per_token_per_minute: Option<u32>,
+    /// Keyed by the endpoint names the connectors use, e.g. "search"
+    #[serde(default)]
+    pub endpoints: HashMap<String, EndpointRateLimit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointRateLimit {
+    pub per_minute: u32,
+    #[serde(default)]
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LimitScope {
+    Global,
+    Endpoint(String),
+    /// Keyed by a hash of the token, never the token itself
+    Token(String),
+}
+
+impl fmt::Display for LimitScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitScope::Global => write!(f, "global"),
+            LimitScope::Endpoint(endpoint) => write!(f, "endpoint:{}", endpoint),
+            LimitScope::Token(token) => write!(f, "token:{}", token),
+        }
+    }
+}
+
+/// What a request is counted against besides the global limit
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestScope {
+    endpoint: Option<String>,
+    token: Option<String>,
+}
+
+impl RequestScope {
+    pub fn endpoint(endpoint: &str) -> Self {
+        Self { endpoint: Some(endpoint.to_string()), token: None }
+    }
+
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token_id(token));
+        self
+    }
+}
+
+fn token_id(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())[..12].to_string()
+}
+
+/// A limit's state at one moment, for metrics
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LimitUtilization {
+    pub scope: String,
+    pub current_usage: u32,
+    /// The configured maximum scaled by `throttle_factor`
+    pub max_usage: u32,
+    pub burst_available: u32,
+    /// 1.0 until the source starts answering 429
+    pub throttle_factor: f64,
+    pub throttled_total: u64,
+    pub paused_for_ms: u64,
+}
 
 #[derive(Debug)]
-pub struct RateLimiter {
-    max_requests: u32,
-    window_duration: Duration,
-    requests: Mutex<VecDeque<Instant>>,
+struct LimitState {
+    limit: RateLimit,
+    requests: VecDeque<Instant>,
+    burst_tokens: f64,
+    refilled_at: Instant,
+    throttle: f64,
+    paused_until: Option<Instant>,
+    throttled_total: u64,
 }
 
-impl RateLimiter {
-    pub fn new(max_requests: u32, window_duration: Duration) -> Self {
+impl LimitState {
+    fn new(limit: RateLimit, now: Instant) -> Self {
         Self {
-            max_requests,
-            window_duration,
-            requests: Mutex::new(VecDeque::new()),
+            limit,
+            requests: VecDeque::new(),
+            burst_tokens: limit.burst as f64,
+            refilled_at: now,
+            throttle: 1.0,
+            paused_until: None,
+            throttled_total: 0,
         }
     }
 
-    pub async fn acquire(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut requests = self.requests.lock().await;
-        let now = Instant::now();
+    fn effective_max(&self) -> usize {
+        ((self.limit.max_requests as f64 * self.throttle).floor() as usize).max(1)
+    }
+
+    fn refill_rate(&self) -> f64 {
+        self.limit.max_requests as f64 * self.throttle / self.limit.window.as_secs_f64().max(f64::EPSILON)
+    }
 
-        // Remove expired requests
-        while let Some(&timestamp) = requests.front() {
-            if now.duration_since(timestamp) > self.window_duration {
-                requests.pop_front();
+    fn refresh(&mut self, now: Instant) {
+        while let Some(&timestamp) = self.requests.front() {
+            if now.duration_since(timestamp) > self.limit.window {
+                self.requests.pop_front();
             } else {
                 break;
             }
         }
 
-        // Check if we're at the limit
-        if requests.len() >= self.max_requests as usize {
-            let oldest_request = requests.front().unwrap();
-            let wait_time = self.window_duration - now.duration_since(*oldest_request);
-            
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.burst_tokens = (self.burst_tokens + elapsed * self.refill_rate()).min(self.limit.burst as f64);
+        self.refilled_at = now;
+
+        if self.paused_until.is_some_and(|until| until <= now) {
+            self.paused_until = None;
+        }
+    }
+
+    // How long until a request fits, assuming `refresh` was just called
+    fn wait_time(&self, now: Instant) -> Duration {
+        if let Some(until) = self.paused_until {
+            return until - now;
+        }
+
+        let max = self.effective_max();
+        if self.requests.len() < max || self.burst_tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+
+        // Room opens once enough requests age out of the window
+        let freeing = self.requests[self.requests.len() - max];
+        let window_wait = (freeing + self.limit.window).saturating_duration_since(now);
+        if self.limit.burst == 0 || self.refill_rate() <= 0.0 {
+            return window_wait;
+        }
+        let burst_wait = Duration::from_secs_f64((1.0 - self.burst_tokens) / self.refill_rate());
+        window_wait.min(burst_wait)
+    }
+
+    fn record(&mut self, now: Instant) {
+        if self.requests.len() >= self.effective_max() {
+            self.burst_tokens -= 1.0;
+        }
+        self.requests.push_back(now);
+    }
+
+    fn throttle(&mut self, now: Instant, retry_after: Option<Duration>) {
+        self.throttle = (self.throttle * THROTTLE_DECREASE).max(MIN_THROTTLE);
+        self.paused_until = Some(now + retry_after.unwrap_or(DEFAULT_RETRY_AFTER));
+        self.throttled_total += 1;
+    }
+
+    fn recover(&mut self) {
+        self.throttle = (self.throttle + THROTTLE_RECOVERY_STEP).min(1.0);
+    }
+
+    fn utilization(&self, scope: &LimitScope, now: Instant) -> LimitUtilization {
+        LimitUtilization {
+            scope: scope.to_string(),
+            current_usage: self.requests.len() as u32,
+            max_usage: self.effective_max() as u32,
+            burst_available: self.burst_tokens.max(0.0).floor() as u32,
+            throttle_factor: self.throttle,
+            throttled_total: self.throttled_total,
+            paused_for_ms: self.paused_until
+                .map(|until| until.saturating_duration_since(now).as_millis() as u64)
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    endpoint_limits: HashMap<String, RateLimit>,
+    token_limit: Option<RateLimit>,
+    states: Mutex<HashMap<LimitScope, LimitState>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: u32, window_duration: Duration) -> Self {
+        Self::with_global_limit(RateLimit::new(max_requests, window_duration))
+    }
+
+    pub fn with_global_limit(limit: RateLimit) -> Self {
+        let mut states = HashMap::new();
+        states.insert(LimitScope::Global, LimitState::new(limit, Instant::now()));
+        Self {
+            endpoint_limits: HashMap::new(),
+            token_limit: None,
+            states: Mutex::new(states),
+        }
+    }
+
+    /// `requests_per_minute` as the global limit, with the burst, endpoint
+    /// and per-token limits from `config`
+    pub fn from_config(requests_per_minute: u32, config: &RateLimitConfig) -> Self {
+        let mut limiter = Self::with_global_limit(RateLimit::per_minute(requests_per_minute).with_burst(config.burst));
+        for (endpoint, limit) in &config.endpoints {
+            limiter = limiter.with_endpoint_limit(endpoint, RateLimit::per_minute(limit.per_minute).with_burst(limit.burst));
+        }
+        if let Some(per_minute) = config.per_token_per_minute {
+            limiter = limiter.with_token_limit(RateLimit::per_minute(per_minute));
+        }
+        limiter
+    }
+
+    pub fn with_endpoint_limit(mut self, endpoint: &str, limit: RateLimit) -> Self {
+        self.endpoint_limits.insert(endpoint.to_string(), limit);
+        self
+    }
+
+    /// Applied to each token separately
+    pub fn with_token_limit(mut self, limit: RateLimit) -> Self {
+        self.token_limit = Some(limit);
+        self
+    }
+
+    pub async fn acquire(&self) -> Result<(), Error> {
+        self.acquire_for(&RequestScope::default()).await
+    }
+
+    /// Waits until the request fits every limit in `scope`, then counts it
+    /// against all of them
+    pub async fn acquire_for(&self, scope: &RequestScope) -> Result<(), Error> {
+        loop {
+            let wait_time = {
+                let mut states = self.states.lock().await;
+                let now = Instant::now();
+                let scopes = self.scopes(&mut states, scope, now);
+
+                let mut wait_time = Duration::ZERO;
+                for limit_scope in &scopes {
+                    let state = states.get_mut(limit_scope).expect("limit state exists for scope");
+                    state.refresh(now);
+                    wait_time = wait_time.max(state.wait_time(now));
+                }
+
+                if wait_time.is_zero() {
+                    for limit_scope in &scopes {
+                        states.get_mut(limit_scope).expect("limit state exists for scope").record(now);
+                    }
+                    return Ok(());
+                }
+                wait_time
+            };
+
             // Add jitter to prevent thundering herd
             let jitter = rand::thread_rng().gen_range(0..100);
             let total_wait = wait_time + Duration::from_millis(jitter);
-            
+
             tracing::warn!(
                 "Rate limit exceeded. Waiting {:?} ms (including {}ms jitter)",
                 total_wait.as_millis(),
                 jitter
             );
-            
+
             tokio::time::sleep(total_wait).await;
         }
+    }
+
+    /// Reacts to a 429 from the source: the most specific limit in `scope`
+    /// pauses for `retry_after` and allows fewer requests until it recovers
+    pub async fn record_throttled(&self, scope: &RequestScope, retry_after: Option<Duration>) {
+        let mut states = self.states.lock().await;
+        let now = Instant::now();
+        let limit_scope = self.scopes(&mut states, scope, now).pop().expect("global scope is always present");
+        let state = states.get_mut(&limit_scope).expect("limit state exists for scope");
+        state.throttle(now, retry_after);
+
+        tracing::warn!(
+            "Source throttled requests for {}; pausing {:?} and limiting to {} requests per {:?}",
+            limit_scope,
+            retry_after.unwrap_or(DEFAULT_RETRY_AFTER),
+            state.effective_max(),
+            state.limit.window
+        );
+    }
+
+    /// Feeds a source response into adaptive throttling
+    pub async fn observe(&self, scope: &RequestScope, response: &reqwest::Response) {
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            self.record_throttled(scope, retry_after(response.headers())).await;
+        } else if response.status().is_success() {
+            self.record_success(scope).await;
+        }
+    }
 
-        requests.push_back(now);
-        Ok(())
+    /// Lets limits slowed by `record_throttled` speed back up
+    pub async fn record_success(&self, scope: &RequestScope) {
+        let mut states = self.states.lock().await;
+        let now = Instant::now();
+        for limit_scope in self.scopes(&mut states, scope, now) {
+            states.get_mut(&limit_scope).expect("limit state exists for scope").recover();
+        }
     }
 
     pub async fn get_current_usage(&self) -> (u32, u32) {
-        let requests = self.requests.lock().await;
+        let mut states = self.states.lock().await;
+        let now = Instant::now();
+        let global = states.get_mut(&LimitScope::Global).expect("global scope is always present");
+        global.refresh(now);
+        (global.requests.len() as u32, global.effective_max() as u32)
+    }
+
+    /// Every limit that has seen traffic, global first
+    pub async fn utilization(&self) -> Vec<LimitUtilization> {
+        let mut states = self.states.lock().await;
         let now = Instant::now();
-        
-        // Count active requests in window
-        let active_count = requests
-            .iter()
-            .filter(|&&timestamp| now.duration_since(timestamp) <= self.window_duration)
-            .count() as u32;
+        let mut utilization: Vec<LimitUtilization> = states
+            .iter_mut()
+            .map(|(scope, state)| {
+                state.refresh(now);
+                state.utilization(scope, now)
+            })
+            .collect();
+        utilization.sort_by(|a, b| (a.scope != "global", &a.scope).cmp(&(b.scope != "global", &b.scope)));
+        utilization
+    }
 
-        (active_count, self.max_requests)
+    // Limits `scope` is counted against, least specific first; creates
+    // state for endpoints and tokens on their first request
+    fn scopes(
+        &self,
+        states: &mut HashMap<LimitScope, LimitState>,
+        scope: &RequestScope,
+        now: Instant,
+    ) -> Vec<LimitScope> {
+        let mut scopes = vec![LimitScope::Global];
+        if let Some(endpoint) = &scope.endpoint {
+            if let Some(limit) = self.endpoint_limits.get(endpoint) {
+                let limit_scope = LimitScope::Endpoint(endpoint.clone());
+                states.entry(limit_scope.clone()).or_insert_with(|| LimitState::new(*limit, now));
+                scopes.push(limit_scope);
+            }
+        }
+        if let (Some(token), Some(limit)) = (&scope.token, self.token_limit) {
+            let limit_scope = LimitScope::Token(token.clone());
+            states.entry(limit_scope.clone()).or_insert_with(|| LimitState::new(limit, now));
+            scopes.push(limit_scope);
+        }
+        scopes
     }
 }
 
+/// Prometheus text exposition of `utilization`, using the metric names the
+/// connector dashboard charts
+pub fn render_metrics(source_system: &str, utilization: &[LimitUtilization]) -> String {
+    let metrics: [(&str, fn(&LimitUtilization) -> String); 5] = [
+        ("spec_to_proof_rate_limiter_current_usage", |u| u.current_usage.to_string()),
+        ("spec_to_proof_rate_limiter_max_usage", |u| u.max_usage.to_string()),
+        ("spec_to_proof_rate_limiter_burst_available", |u| u.burst_available.to_string()),
+        ("spec_to_proof_rate_limiter_throttle_factor", |u| u.throttle_factor.to_string()),
+        ("spec_to_proof_rate_limiter_throttled_total", |u| u.throttled_total.to_string()),
+    ];
+
+    let mut output = String::new();
+    for (name, value) in metrics {
+        for limit in utilization {
+            output.push_str(&format!(
+                "{}{{source_system=\"{}\",scope=\"{}\"}} {}\n",
+                name,
+                source_system,
+                limit.scope,
+                value(limit)
+            ));
+        }
+    }
+    output
+}
+
+/// Delay requested by a Retry-After header, given either in seconds or as
+/// an HTTP date
+pub fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let at = SystemTime::UNIX_EPOCH + Duration::from_secs(date.timestamp().max(0) as u64);
+    Some(at.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,17 +446,17 @@ mod tests {
     #[tokio::test]
     async fn test_rate_limiter() {
         let limiter = RateLimiter::new(5, Duration::from_secs(1));
-        
+
         // Should allow 5 requests immediately
         for _ in 0..5 {
             assert!(limiter.acquire().await.is_ok());
         }
-        
+
         // 6th request should be rate limited
         let start = Instant::now();
         assert!(limiter.acquire().await.is_ok());
         let elapsed = start.elapsed();
-        
+
         // Should have waited at least 1 second
         assert!(elapsed >= Duration::from_secs(1));
     }
@@ -94,16 +464,113 @@ mod tests {
     #[tokio::test]
     async fn test_rate_limiter_window() {
         let limiter = RateLimiter::new(3, Duration::from_millis(100));
-        
+
         // Make 3 requests
         for _ in 0..3 {
             assert!(limiter.acquire().await.is_ok());
         }
-        
+
         // Wait for window to expire
         sleep(Duration::from_millis(150)).await;
-        
+
         // Should allow more requests
         assert!(limiter.acquire().await.is_ok());
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_burst_allowance() {
+        let limiter = RateLimiter::with_global_limit(RateLimit::new(2, Duration::from_secs(60)).with_burst(2));
+
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await.unwrap();
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        let utilization = limiter.utilization().await;
+        assert_eq!(utilization[0].current_usage, 4);
+        assert_eq!(utilization[0].burst_available, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hierarchical_limits() {
+        let limiter = RateLimiter::new(100, Duration::from_secs(60))
+            .with_endpoint_limit("search", RateLimit::new(2, Duration::from_millis(200)))
+            .with_token_limit(RateLimit::per_minute(50));
+        let search = RequestScope::endpoint("search").with_token("secret-token");
+
+        limiter.acquire_for(&search).await.unwrap();
+        limiter.acquire_for(&search).await.unwrap();
+
+        // Other endpoints only count against the global and token limits
+        let start = Instant::now();
+        limiter.acquire_for(&RequestScope::endpoint("issue").with_token("secret-token")).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        limiter.acquire_for(&search).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let scopes: Vec<String> = limiter.utilization().await.into_iter().map(|u| u.scope).collect();
+        assert_eq!(scopes[0], "global");
+        assert!(scopes.contains(&"endpoint:search".to_string()));
+        assert!(scopes.iter().all(|scope| !scope.contains("secret-token")));
+        assert_eq!(limiter.get_current_usage().await, (4, 100));
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_throttling() {
+        let limiter = RateLimiter::new(10, Duration::from_secs(60))
+            .with_endpoint_limit("search", RateLimit::per_minute(10));
+        let search = RequestScope::endpoint("search");
+
+        limiter.record_throttled(&search, Some(Duration::from_millis(150))).await;
+        let start = Instant::now();
+        limiter.acquire_for(&search).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(150));
+
+        let utilization = limiter.utilization().await;
+        let endpoint = utilization.iter().find(|u| u.scope == "endpoint:search").unwrap();
+        assert_eq!(endpoint.max_usage, 5);
+        assert_eq!(endpoint.throttled_total, 1);
+        // The global limit was not the one throttled
+        assert_eq!(utilization[0].max_usage, 10);
+
+        limiter.record_success(&search).await;
+        let utilization = limiter.utilization().await;
+        let endpoint = utilization.iter().find(|u| u.scope == "endpoint:search").unwrap();
+        assert!(endpoint.throttle_factor > 0.5);
+
+        let metrics = render_metrics("jira", &utilization);
+        assert!(metrics.contains("spec_to_proof_rate_limiter_max_usage{source_system=\"jira\",scope=\"global\"} 10\n"));
+    }
+
+    #[tokio::test]
+    async fn test_from_config() {
+        let config: RateLimitConfig = serde_json::from_value(serde_json::json!({
+            "burst": 5,
+            "per_token_per_minute": 30,
+            "endpoints": { "search": { "per_minute": 10 } }
+        })).unwrap();
+        let limiter = RateLimiter::from_config(60, &config);
+
+        limiter.acquire_for(&RequestScope::endpoint("search").with_token("token")).await.unwrap();
+        let utilization = limiter.utilization().await;
+        assert_eq!(utilization.len(), 3);
+        assert_eq!((utilization[0].max_usage, utilization[0].burst_available), (60, 5));
+        assert_eq!(utilization[1].scope, "endpoint:search");
+        assert_eq!(utilization[1].max_usage, 10);
+        assert_eq!(utilization[2].max_usage, 30);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(reqwest::header::RETRY_AFTER, "30".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(30)));
+
+        headers.insert(reqwest::header::RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+    }
+}
@@ -2,6 +2,11 @@ use aws_sdk_secretsmanager::Client as SecretsClient;
 use aws_sdk_kms::Client as KmsClient;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use spec_to_proof_error::Error;
+use tokio::task::JoinHandle;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedSecret {
@@ -12,7 +17,7 @@ pub struct EncryptedSecret {
     pub version: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OAuth2Credentials {
     pub client_id: String,
     pub client_secret: String,
@@ -41,14 +46,14 @@ impl SecretsManager {
         &self,
         secret_name: &str,
         credentials: &OAuth2Credentials,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), Error> {
         // Generate a data key from KMS
         let data_key_response = self.kms_client
             .generate_data_key()
             .key_id(&self.key_id)
             .key_spec("AES_256")
             .send()
-            .await?;
+            .await.map_err(Error::transient)?;
 
         let plaintext_key = data_key_response.plaintext()
             .ok_or("No plaintext key returned")?;
@@ -74,7 +79,7 @@ impl SecretsManager {
             .name(secret_name)
             .secret_string(serde_json::to_string(&encrypted_secret)?)
             .send()
-            .await?;
+            .await.map_err(Error::transient)?;
 
         tracing::info!("Stored encrypted OAuth2 credentials for {}", secret_name);
         Ok(())
@@ -83,13 +88,24 @@ impl SecretsManager {
     pub async fn retrieve_oauth2_credentials(
         &self,
         secret_name: &str,
-    ) -> Result<OAuth2Credentials, Box<dyn std::error::Error>> {
+    ) -> Result<OAuth2Credentials, Error> {
+        self.retrieve_oauth2_credentials_version(secret_name, None).await
+    }
+
+    /// Retrieves a specific version of the credentials, or the current one
+    /// when `version_id` is None
+    pub async fn retrieve_oauth2_credentials_version(
+        &self,
+        secret_name: &str,
+        version_id: Option<&str>,
+    ) -> Result<OAuth2Credentials, Error> {
         // Retrieve the encrypted secret
         let secret_response = self.secrets_client
             .get_secret_value()
             .secret_id(secret_name)
+            .set_version_id(version_id.map(str::to_string))
             .send()
-            .await?;
+            .await.map_err(Error::transient)?;
 
         let secret_string = secret_response.secret_string()
             .ok_or("No secret string found")?;
@@ -102,7 +118,7 @@ impl SecretsManager {
             .key_id(&encrypted_secret.key_id)
             .ciphertext_blob(aws_sdk_kms::types::Blob::new(&encrypted_secret.encrypted_key))
             .send()
-            .await?;
+            .await.map_err(Error::transient)?;
 
         let decrypted_key = decrypted_key_response.plaintext()
             .ok_or("No decrypted key returned")?;
@@ -114,7 +130,7 @@ impl SecretsManager {
         Ok(credentials)
     }
 
-    fn encrypt_with_key(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fn encrypt_with_key(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, Error> {
         use aes_gcm::{Aes256Gcm, Key, Nonce};
         use aes_gcm::aead::{Aead, NewAead};
 
@@ -128,7 +144,7 @@ impl SecretsManager {
         Ok(ciphertext)
     }
 
-    fn decrypt_with_key(&self, encrypted_data: &[u8], key: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fn decrypt_with_key(&self, encrypted_data: &[u8], key: &[u8]) -> Result<Vec<u8>, Error> {
         use aes_gcm::{Aes256Gcm, Key, Nonce};
         use aes_gcm::aead::{Aead, NewAead};
 
@@ -142,11 +158,26 @@ impl SecretsManager {
         Ok(plaintext)
     }
 
-    pub async fn list_secrets(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    /// The version id currently staged as `AWSCURRENT`
+    pub async fn current_version(&self, secret_name: &str) -> Result<Option<String>, Error> {
+        let response = self.secrets_client
+            .describe_secret()
+            .secret_id(secret_name)
+            .send()
+            .await.map_err(Error::transient)?;
+
+        Ok(response.version_ids_to_stages().and_then(|versions| {
+            versions.iter()
+                .find(|(_, stages)| stages.iter().any(|stage| stage == "AWSCURRENT"))
+                .map(|(version, _)| version.clone())
+        }))
+    }
+
+    pub async fn list_secrets(&self) -> Result<Vec<String>, Error> {
         let response = self.secrets_client
             .list_secrets()
             .send()
-            .await?;
+            .await.map_err(Error::transient)?;
 
         let secret_names = response
             .secret_list()
@@ -158,19 +189,93 @@ impl SecretsManager {
         Ok(secret_names)
     }
 
-    pub async fn delete_secret(&self, secret_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn delete_secret(&self, secret_name: &str) -> Result<(), Error> {
         self.secrets_client
             .delete_secret()
             .secret_id(secret_name)
             .force_delete_without_recovery(true)
             .send()
-            .await?;
+            .await.map_err(Error::transient)?;
 
         tracing::info!("Deleted secret: {}", secret_name);
         Ok(())
     }
 }
 
+/// OAuth2 credentials that follow rotations of their secret. Connectors
+/// compare `generation` against the one their tokens were minted under and
+/// drop cached tokens when it moves.
+#[derive(Debug)]
+pub struct RotatingCredentials {
+    secret_name: String,
+    current: RwLock<(Option<String>, Arc<OAuth2Credentials>)>,
+    generation: AtomicU64,
+}
+
+impl RotatingCredentials {
+    pub fn new(secret_name: &str, version: Option<String>, credentials: OAuth2Credentials) -> Self {
+        Self {
+            secret_name: secret_name.to_string(),
+            current: RwLock::new((version, Arc::new(credentials))),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn load(manager: &SecretsManager, secret_name: &str) -> Result<Self, Error> {
+        let version = manager.current_version(secret_name).await?;
+        let credentials = manager.retrieve_oauth2_credentials_version(secret_name, version.as_deref()).await?;
+        Ok(Self::new(secret_name, version, credentials))
+    }
+
+    pub fn current(&self) -> Arc<OAuth2Credentials> {
+        self.current.read().unwrap().1.clone()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Re-reads the secret if a new version became current, returning
+    /// whether the credentials were swapped
+    pub async fn refresh(&self, manager: &SecretsManager) -> Result<bool, Error> {
+        let version = manager.current_version(&self.secret_name).await?;
+        if version.is_none() || version == self.current.read().unwrap().0 {
+            return Ok(false);
+        }
+
+        let credentials = manager.retrieve_oauth2_credentials_version(&self.secret_name, version.as_deref()).await?;
+        let swapped = self.swap(version.clone(), credentials);
+        if swapped {
+            tracing::info!("Rotated credentials for {} to version {}", self.secret_name, version.unwrap_or_default());
+        }
+        Ok(swapped)
+    }
+
+    fn swap(&self, version: Option<String>, credentials: OAuth2Credentials) -> bool {
+        let mut current = self.current.write().unwrap();
+        let changed = *current.1 != credentials;
+        *current = (version, Arc::new(credentials));
+        if changed {
+            self.generation.fetch_add(1, Ordering::AcqRel);
+        }
+        changed
+    }
+
+    pub fn spawn_watcher(self: Arc<Self>, manager: Arc<SecretsManager>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            // The first tick fires immediately; the credentials were just loaded
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.refresh(&manager).await {
+                    tracing::error!("Secrets rotation check for {} failed: {}", self.secret_name, e);
+                }
+            }
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,4 +306,26 @@ mod tests {
         // In a real environment, you'd mock the AWS clients
         assert_eq!(credentials_json.len(), 0); // Placeholder assertion
     }
+
+    #[test]
+    fn test_rotating_credentials_swap() {
+        let credentials = OAuth2Credentials {
+            client_id: "client".to_string(),
+            client_secret: "old".to_string(),
+            redirect_uri: "https://example.com/callback".to_string(),
+            scopes: vec!["read".to_string()],
+            token_endpoint: "https://example.com/token".to_string(),
+            auth_endpoint: "https://example.com/auth".to_string(),
+        };
+        let rotating = RotatingCredentials::new("jira-oauth-token", Some("v1".to_string()), credentials.clone());
+
+        // A new version with identical contents does not invalidate tokens
+        assert!(!rotating.swap(Some("v2".to_string()), credentials.clone()));
+        assert_eq!(rotating.generation(), 0);
+
+        let rotated = OAuth2Credentials { client_secret: "new".to_string(), ..credentials };
+        assert!(rotating.swap(Some("v3".to_string()), rotated));
+        assert_eq!(rotating.generation(), 1);
+        assert_eq!(rotating.current().client_secret, "new");
+    }
 } 
\ No newline at end of file
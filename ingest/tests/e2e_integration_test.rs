@@ -1,11 +1,13 @@
 use ingest::{
     ConnectorConfig, IngestionConnector, OAuth2Token,
+    bus::JetStreamBus,
     connectors::{JiraConnector, ConfluenceConnector, GoogleDocsConnector},
     secrets::SecretsManager,
 };
 use aws_sdk_secretsmanager::Client as SecretsClient;
 use aws_sdk_kms::Client as KmsClient;
 use nats::jetstream::Context as JetStreamContext;
+use std::sync::Arc;
 use tokio::time::{sleep, Duration};
 use wiremock::{MockServer, Mock, ResponseTemplate};
 use wiremock::matchers::{method, path, query_param};
@@ -139,6 +141,9 @@ async fn test_jira_connector_e2e() {
         batch_size: 50,
         poll_interval_seconds: 1,
         secrets_arn: "test-jira-oauth".to_string(),
+        transport: Default::default(),
+        queries: Default::default(),
+        attachments: Default::default(),
     };
 
     let mut jira_connector = JiraConnector::new(config);
@@ -147,7 +152,7 @@ async fn test_jira_connector_e2e() {
     let ingestion_connector = IngestionConnector::new(
         config,
         secrets_client,
-        jetstream,
+        Arc::new(JetStreamBus::new(jetstream.clone())),
     ).await.unwrap();
 
     // Create test OAuth2 token
@@ -254,6 +259,9 @@ async fn test_confluence_connector_e2e() {
         batch_size: 50,
         poll_interval_seconds: 1,
         secrets_arn: "test-confluence-oauth".to_string(),
+        transport: Default::default(),
+        queries: Default::default(),
+        attachments: Default::default(),
     };
 
     let mut confluence_connector = ConfluenceConnector::new(config);
@@ -387,6 +395,9 @@ async fn test_gdocs_connector_e2e() {
         batch_size: 50,
         poll_interval_seconds: 1,
         secrets_arn: "test-gdocs-oauth".to_string(),
+        transport: Default::default(),
+        queries: Default::default(),
+        attachments: Default::default(),
     };
 
     let mut gdocs_connector = GoogleDocsConnector::new(config);
@@ -422,6 +433,9 @@ async fn test_rate_limiting_and_backoff() {
         batch_size: 5,
         poll_interval_seconds: 1,
         secrets_arn: "test-jira-oauth".to_string(),
+        transport: Default::default(),
+        queries: Default::default(),
+        attachments: Default::default(),
     };
 
     let jira_connector = JiraConnector::new(config);
@@ -447,6 +461,9 @@ async fn test_secrets_encryption() {
         batch_size: 50,
         poll_interval_seconds: 300,
         secrets_arn: "test-jira-oauth".to_string(),
+        transport: Default::default(),
+        queries: Default::default(),
+        attachments: Default::default(),
     };
 
     // This test would require a real AWS KMS setup or mocking
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+
+use crate::{JobPriority, LeanFarmError, ProofJob};
+use crate::scheduling::{self, SchedulingConfig};
+use crate::proto::proof::v1::ProofOptions;
+use crate::proto::spec_to_proof::v1::LeanTheorem;
+
+/// A job persisted on shutdown so another replica can pick it up. Queue
+/// position and deadlines are kept relative, since `Instant`s do not
+/// survive the process.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobCheckpoint {
+    pub job_id: String,
+    pub priority: i32,
+    /// Base64 protobuf encoding of the theorem
+    pub theorem: String,
+    /// Base64 protobuf encoding of the proof options
+    pub options: String,
+    pub waited_ms: u64,
+    pub deadline_in_ms: Option<u64>,
+    /// Whether the job was running, rather than queued, when the drain
+    /// grace period ran out
+    pub interrupted: bool,
+    pub checkpointed_at: DateTime<Utc>,
+}
+
+impl JobCheckpoint {
+    pub fn from_job(job: &ProofJob, interrupted: bool) -> Self {
+        let now = Instant::now();
+        Self {
+            job_id: job.id.clone(),
+            priority: job.priority.clone() as i32,
+            theorem: STANDARD.encode(job.theorem.encode_to_vec()),
+            options: STANDARD.encode(job.options.encode_to_vec()),
+            waited_ms: now.duration_since(job.created_at).as_millis() as u64,
+            deadline_in_ms: job
+                .deadline
+                .map(|deadline| deadline.saturating_duration_since(now).as_millis() as u64),
+            interrupted,
+            checkpointed_at: Utc::now(),
+        }
+    }
+
+    pub fn into_job(self) -> Result<ProofJob, LeanFarmError> {
+        let decode = |field: &str, encoded: &str| {
+            STANDARD
+                .decode(encoded)
+                .map_err(|e| LeanFarmError::Storage(format!("checkpoint {} has invalid {}: {}", self.job_id, field, e)))
+        };
+        let theorem = LeanTheorem::decode(decode("theorem", &self.theorem)?.as_slice())
+            .map_err(|e| LeanFarmError::Storage(format!("checkpoint {} has invalid theorem: {}", self.job_id, e)))?;
+        let options = ProofOptions::decode(decode("options", &self.options)?.as_slice())
+            .map_err(|e| LeanFarmError::Storage(format!("checkpoint {} has invalid options: {}", self.job_id, e)))?;
+
+        let now = Instant::now();
+        Ok(ProofJob {
+            id: self.job_id,
+            theorem,
+            options,
+            priority: JobPriority::from(self.priority),
+            created_at: now.checked_sub(Duration::from_millis(self.waited_ms)).unwrap_or(now),
+            deadline: self.deadline_in_ms.map(|ms| now + Duration::from_millis(ms)),
+        })
+    }
+}
+
+/// Outcome of draining the runner on shutdown
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DrainReport {
+    /// Jobs still running when the grace period ran out
+    pub interrupted: usize,
+    /// Interrupted and queued jobs checkpointed for another replica
+    pub requeued: usize,
+    /// Jobs that could not be checkpointed and are lost
+    pub lost: usize,
+    pub containers_removed: usize,
+}
+
+#[derive(Debug)]
+struct InFlightJob {
+    job: ProofJob,
+    container_id: Option<String>,
+    started_at: Instant,
+    preempt: Arc<Notify>,
+    preempted: bool,
+}
+
+/// Jobs currently being processed by workers, with the Lean container each
+/// one is running in
+#[derive(Debug, Default)]
+pub struct InFlightJobs {
+    jobs: Mutex<HashMap<String, InFlightJob>>,
+    idle: Notify,
+}
+
+impl InFlightJobs {
+    /// Registers a running job, returning the signal its worker watches
+    /// for preemption
+    pub async fn start(&self, job: &ProofJob) -> Arc<Notify> {
+        let preempt = Arc::new(Notify::new());
+        self.jobs.lock().await.insert(job.id.clone(), InFlightJob {
+            job: job.clone(),
+            container_id: None,
+            started_at: Instant::now(),
+            preempt: preempt.clone(),
+            preempted: false,
+        });
+        preempt
+    }
+
+    pub async fn attach_container(&self, job_id: &str, container_id: &str) {
+        if let Some(in_flight) = self.jobs.lock().await.get_mut(job_id) {
+            in_flight.container_id = Some(container_id.to_string());
+        }
+    }
+
+    /// Removes a finished or preempted job, returning its container
+    pub async fn finish(&self, job_id: &str) -> Option<String> {
+        let mut jobs = self.jobs.lock().await;
+        let container_id = jobs.remove(job_id).and_then(|in_flight| in_flight.container_id);
+        if jobs.is_empty() {
+            self.idle.notify_waiters();
+        }
+        container_id
+    }
+
+    /// Signals the running job chosen by `scheduling::preemption_victim` to
+    /// make way for `incoming`, returning its id
+    pub async fn preempt_for(&self, incoming: &ProofJob, config: &SchedulingConfig) -> Option<String> {
+        let mut jobs = self.jobs.lock().await;
+        let victim_id = scheduling::preemption_victim(
+            jobs.values()
+                .filter(|in_flight| !in_flight.preempted)
+                .map(|in_flight| (&in_flight.job, in_flight.started_at)),
+            incoming,
+            config,
+        )?.id.clone();
+
+        let victim = jobs.get_mut(&victim_id)?;
+        victim.preempted = true;
+        // Stores a permit, so a worker not yet waiting still sees it
+        victim.preempt.notify_one();
+        Some(victim_id)
+    }
+
+    pub async fn len(&self) -> usize {
+        self.jobs.lock().await.len()
+    }
+
+    /// Waits for every running job to finish, returning false if some are
+    /// still running after `grace_period`
+    pub async fn wait_until_idle(&self, grace_period: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + grace_period;
+        loop {
+            // Registered before the check so a finish in between is not missed
+            let idle = self.idle.notified();
+            if self.jobs.lock().await.is_empty() {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline, idle).await.is_err() {
+                return self.jobs.lock().await.is_empty();
+            }
+        }
+    }
+
+    /// Removes every running job, returning each with its container
+    pub async fn take_all(&self) -> Vec<(ProofJob, Option<String>)> {
+        self.jobs
+            .lock()
+            .await
+            .drain()
+            .map(|(_, in_flight)| (in_flight.job, in_flight.container_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str) -> ProofJob {
+        ProofJob {
+            id: id.to_string(),
+            theorem: LeanTheorem {
+                id: id.to_string(),
+                lean_code: "theorem t : 1 + 1 = 2 := by decide".to_string(),
+                ..Default::default()
+            },
+            options: ProofOptions {
+                proof_strategy: "auto".to_string(),
+                ..Default::default()
+            },
+            priority: JobPriority::High,
+            created_at: Instant::now() - Duration::from_secs(30),
+            deadline: Some(Instant::now() + Duration::from_secs(60)),
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let checkpoint = JobCheckpoint::from_job(&job("job-1"), true);
+        assert!(checkpoint.waited_ms >= 30_000);
+        assert!(checkpoint.deadline_in_ms.unwrap() <= 60_000);
+
+        let json = serde_json::to_vec(&checkpoint).unwrap();
+        let restored = serde_json::from_slice::<JobCheckpoint>(&json).unwrap().into_job().unwrap();
+        assert_eq!(restored.id, "job-1");
+        assert_eq!(restored.theorem.lean_code, "theorem t : 1 + 1 = 2 := by decide");
+        assert_eq!(restored.options.proof_strategy, "auto");
+        assert_eq!(restored.priority, JobPriority::High);
+        assert!(restored.created_at.elapsed() >= Duration::from_secs(30));
+        assert!(restored.deadline.unwrap() > Instant::now());
+
+        let corrupt = JobCheckpoint {
+            theorem: "not base64!".to_string(),
+            ..checkpoint
+        };
+        assert!(corrupt.into_job().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_idle() {
+        let in_flight = std::sync::Arc::new(InFlightJobs::default());
+        assert!(in_flight.wait_until_idle(Duration::ZERO).await);
+
+        in_flight.start(&job("job-1")).await;
+        in_flight.start(&job("job-2")).await;
+        in_flight.attach_container("job-2", "container-2").await;
+
+        let finishing = in_flight.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            finishing.finish("job-1").await;
+        });
+        assert!(!in_flight.wait_until_idle(Duration::from_millis(100)).await);
+
+        let remaining = in_flight.take_all().await;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].0.id, "job-2");
+        assert_eq!(remaining[0].1.as_deref(), Some("container-2"));
+        assert_eq!(in_flight.len().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_preempt_for_signals_lowest_priority_job() {
+        let in_flight = InFlightJobs::default();
+        let mut low = job("low");
+        low.priority = JobPriority::Low;
+        let low_signal = in_flight.start(&low).await;
+        in_flight.start(&job("high")).await;
+
+        let mut critical = job("critical");
+        critical.priority = JobPriority::Critical;
+        let config = SchedulingConfig::default();
+        assert_eq!(in_flight.preempt_for(&critical, &config).await.as_deref(), Some("low"));
+        tokio::time::timeout(Duration::from_millis(100), low_signal.notified()).await.unwrap();
+
+        // Already signalled, so the next critical job preempts another one
+        assert_eq!(in_flight.preempt_for(&critical, &config).await.as_deref(), Some("high"));
+        assert_eq!(in_flight.preempt_for(&critical, &config).await, None);
+    }
+}
@@ -0,0 +1,296 @@
+use std::time::Duration;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use crate::security::EgressPolicy;
+use crate::LeanFarmError;
+
+// A sandbox's setup phase runs attached to an internal docker network,
+// which has no route out. The only other container on it is the egress
+// proxy, also attached to the default bridge, which tunnels CONNECT
+// requests to allowed hosts and refuses the rest. Once setup finishes the
+// sandbox is detached again, before any of the job's code is built.
+
+/// Longest request head the proxy reads
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The target of a `CONNECT host:port HTTP/1.x` request head
+pub fn parse_connect(head: &str) -> Option<(String, u16)> {
+    let mut parts = head.lines().next()?.split_whitespace();
+    if parts.next()? != "CONNECT" {
+        return None;
+    }
+    let (host, port) = parts.next()?.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() || !parts.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+/// Tunnels CONNECT requests to the hosts an egress policy allows
+pub struct EgressProxy {
+    policy: EgressPolicy,
+}
+
+impl EgressProxy {
+    pub fn new(policy: EgressPolicy) -> Self {
+        Self { policy }
+    }
+
+    pub async fn serve(self, port: u16) -> Result<(), LeanFarmError> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .await
+            .map_err(|e| LeanFarmError::Network(format!("failed to listen on port {}: {}", port, e)))?;
+        info!("Egress proxy allowing {:?} on port {}", self.policy.allowed_hosts, port);
+
+        let policy = std::sync::Arc::new(self.policy);
+        loop {
+            let (client, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Egress proxy failed to accept: {}", e);
+                    continue;
+                }
+            };
+            let policy = policy.clone();
+            tokio::spawn(async move {
+                if let Err(e) = tunnel(&policy, client).await {
+                    warn!("Egress from {} refused: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+async fn tunnel(policy: &EgressPolicy, mut client: TcpStream) -> Result<(), LeanFarmError> {
+    let io_error = |e: std::io::Error| LeanFarmError::Network(e.to_string());
+
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = client.read(&mut buf).await.map_err(io_error)?;
+        if read == 0 || head.len() + read > MAX_REQUEST_HEAD {
+            return Err(LeanFarmError::Network("incomplete request".to_string()));
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+
+    let Some((host, port)) = parse_connect(&String::from_utf8_lossy(&head)) else {
+        let _ = client.write_all(b"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\n\r\n").await;
+        return Err(LeanFarmError::Network("not a CONNECT request".to_string()));
+    };
+    if !policy.allows(&host, port) {
+        let _ = client.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n").await;
+        return Err(LeanFarmError::Security(format!("{}:{} is not an allowed egress host", host, port)));
+    }
+
+    let mut upstream = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((host.as_str(), port))).await {
+        Ok(Ok(upstream)) => upstream,
+        Ok(Err(e)) => {
+            let _ = client.write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n").await;
+            return Err(LeanFarmError::Network(format!("{}:{}: {}", host, port, e)));
+        }
+        Err(_) => {
+            let _ = client.write_all(b"HTTP/1.1 504 Gateway Timeout\r\nContent-Length: 0\r\n\r\n").await;
+            return Err(LeanFarmError::Timeout(format!("connecting to {}:{}", host, port)));
+        }
+    };
+    client
+        .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+        .await
+        .map_err(io_error)?;
+    tokio::io::copy_bidirectional(&mut client, &mut upstream).await.map_err(io_error)?;
+    Ok(())
+}
+
+/// Gives sandboxes egress through the proxy for their setup phase
+#[derive(Debug)]
+pub struct EgressGateway {
+    policy: EgressPolicy,
+    ready: OnceCell<()>,
+}
+
+impl EgressGateway {
+    pub fn new(policy: EgressPolicy) -> Self {
+        Self {
+            policy,
+            ready: OnceCell::new(),
+        }
+    }
+
+    pub fn policy(&self) -> &EgressPolicy {
+        &self.policy
+    }
+
+    /// Proxy container name. It carries a hash of the allowed hosts, so
+    /// replicas sharing a docker host share a proxy only while their
+    /// policies agree.
+    pub fn proxy_name(&self) -> String {
+        let digest = Sha256::digest(self.policy.allowed_hosts.join(",").as_bytes());
+        format!("lean-farm-egress-proxy-{}", &format!("{:x}", digest)[..12])
+    }
+
+    /// `docker run` arguments starting the proxy on the internal network
+    pub fn proxy_args(&self) -> Vec<String> {
+        vec![
+            "run".to_string(),
+            "--detach".to_string(),
+            "--rm".to_string(),
+            format!("--name={}", self.proxy_name()),
+            format!("--network={}", self.policy.network),
+            "--read-only".to_string(),
+            "--user=1000:1000".to_string(),
+            "--cap-drop=ALL".to_string(),
+            "--security-opt=no-new-privileges".to_string(),
+            "--entrypoint=/usr/local/bin/lean-farm".to_string(),
+            self.policy.proxy_image.clone(),
+            "--egress-proxy-only".to_string(),
+            format!("--egress-proxy-port={}", self.policy.proxy_port),
+            format!("--egress-allowed-hosts={}", self.policy.allowed_hosts.join(",")),
+        ]
+    }
+
+    /// `docker exec` arguments running the setup command in `container_id`
+    /// with the proxy as its HTTP(S) proxy
+    pub fn setup_args(&self, container_id: &str, workdir: &str) -> Vec<String> {
+        let proxy = format!("http://{}:{}", self.proxy_name(), self.policy.proxy_port);
+        let mut args = vec![
+            "exec".to_string(),
+            format!("--workdir={}", workdir),
+            format!("--env=HTTPS_PROXY={}", proxy),
+            format!("--env=https_proxy={}", proxy),
+            format!("--env=HTTP_PROXY={}", proxy),
+            format!("--env=http_proxy={}", proxy),
+            container_id.to_string(),
+        ];
+        args.extend(self.policy.setup_command.iter().cloned());
+        args
+    }
+
+    /// Creates the internal network and starts the proxy, once per farm
+    async fn ensure(&self) -> Result<(), LeanFarmError> {
+        self.ready
+            .get_or_try_init(|| async {
+                let network = &self.policy.network;
+                if docker(&["network", "inspect", network]).await.is_err() {
+                    docker(&["network", "create", "--internal", network]).await?;
+                }
+
+                let name = self.proxy_name();
+                if docker(&["container", "inspect", &name]).await.is_err() {
+                    let args = self.proxy_args();
+                    docker(&args.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+                    docker(&["network", "connect", "bridge", &name]).await?;
+                    info!("Started egress proxy {} on network {}", name, network);
+                }
+                Ok(())
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// Runs the setup command in a sandbox with egress through the proxy.
+    /// The sandbox is detached afterwards even when setup fails; an error
+    /// detaching it means it may still have network, so callers must
+    /// discard it.
+    pub async fn run_setup(&self, container_id: &str, workdir: &str) -> Result<(), LeanFarmError> {
+        self.ensure().await?;
+        docker(&["network", "connect", &self.policy.network, container_id]).await?;
+
+        let args = self.setup_args(container_id, workdir);
+        let result = tokio::time::timeout(
+            Duration::from_secs(self.policy.setup_timeout_seconds),
+            docker(&args.iter().map(String::as_str).collect::<Vec<_>>()),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(LeanFarmError::Timeout(format!(
+                "setup in {} took over {}s",
+                container_id, self.policy.setup_timeout_seconds
+            )))
+        });
+
+        docker(&["network", "disconnect", "--force", &self.policy.network, container_id]).await?;
+        result.map(|_| ())
+    }
+}
+
+async fn docker(args: &[&str]) -> Result<String, LeanFarmError> {
+    let output = tokio::process::Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| LeanFarmError::JobExecution(format!("failed to run docker: {}", e)))?;
+    if !output.status.success() {
+        return Err(LeanFarmError::JobExecution(format!(
+            "docker {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_connect() {
+        assert_eq!(
+            parse_connect("CONNECT lakecache.blob.core.windows.net:443 HTTP/1.1\r\nHost: x\r\n\r\n"),
+            Some(("lakecache.blob.core.windows.net".to_string(), 443))
+        );
+        assert_eq!(parse_connect("CONNECT [::1]:8443 HTTP/1.0\r\n\r\n"), Some(("::1".to_string(), 8443)));
+        assert_eq!(parse_connect("GET http://example.com/ HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(parse_connect("CONNECT example.com HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(parse_connect("CONNECT example.com:https HTTP/1.1\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_setup_runs_through_the_proxy() {
+        let gateway = EgressGateway::new(EgressPolicy {
+            allowed_hosts: vec!["lakecache.blob.core.windows.net".to_string()],
+            proxy_image: "lean-farm:1.0".to_string(),
+            ..Default::default()
+        });
+        let name = gateway.proxy_name();
+        assert!(name.starts_with("lean-farm-egress-proxy-"));
+        assert_eq!(name.len(), "lean-farm-egress-proxy-".len() + 12);
+
+        let proxy = gateway.proxy_args();
+        assert!(proxy.contains(&"--network=lean-farm-egress".to_string()));
+        assert!(proxy.contains(&"--egress-allowed-hosts=lakecache.blob.core.windows.net".to_string()));
+
+        let setup = gateway.setup_args("c1", "/var/lean-farm/code");
+        assert!(setup.contains(&format!("--env=HTTPS_PROXY=http://{}:3128", name)));
+        assert_eq!(setup[setup.len() - 5..], ["c1", "lake", "exe", "cache", "get"]);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_refuses_hosts_outside_the_policy() {
+        let policy = EgressPolicy {
+            allowed_hosts: vec!["lakecache.blob.core.windows.net".to_string()],
+            ..Default::default()
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (client, _) = listener.accept().await.unwrap();
+            tunnel(&policy, client).await
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"CONNECT example.com:443 HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 403"));
+        assert!(matches!(server.await.unwrap(), Err(LeanFarmError::Security(_))));
+    }
+}
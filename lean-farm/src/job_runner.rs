@@ -1,26 +1,62 @@
 use std::error::Error;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 use tracing::{info, warn, error, instrument};
+use prost::Message;
 use serde::{Deserialize, Serialize};
+use envelope::EnvelopeEncryptor;
 
 use crate::{
     Config, ProofJob, ProofResult, JobQueue, JobPriority, ResourceUsage,
-    LeanFarmError, security::SecurityManager, storage::StorageManager, lean::LeanCompiler
+    LeanFarmError, security::SecurityManager, storage::StorageManager, lean::LeanCompiler,
+    scheduling::{self, CoverageTracker, CoverageUpdate, BATCH_ID_METADATA_KEY, PREEMPTION_COUNT_METADATA_KEY},
+    drain::{DrainReport, InFlightJobs, JobCheckpoint},
+    resources::{self, ResourceLimits, ResourcePolicy, ATTEMPT_COUNT_METADATA_KEY},
+    toolchain::Toolchain,
+    worker::WorkerIdentity,
+    provenance::{self, Statement},
+    metrics::{ScalingHints, ScalingMetrics, ScalingPolicy},
+    reverification::{self, ProvenTheorem, Regression, ReverificationConfig, ReverificationMetrics},
+    retention::{self, FailedAttempt, RetentionConfig, RetentionReport, StoredProof},
+    pool::{ContainerPool, JobOutcome, PoolConfig, PoolMetrics},
+    mathlib_cache::{CacheManifest, MathlibCache, MathlibCacheConfig},
+    egress::EgressGateway,
 };
 
-#[derive(Debug, Clone)]
+/// Where a job's code bundle is copied in its sandbox
+const CODE_MOUNT_PATH: &str = "/var/lean-farm/code";
+
+#[derive(Debug)]
 pub struct JobRunner {
     config: Config,
     security_manager: SecurityManager,
     storage_manager: StorageManager,
+    // Set when artifacts and checkpoints are encrypted client-side
+    envelope: Option<EnvelopeEncryptor>,
     lean_compiler: LeanCompiler,
-    job_queue: JobQueue,
+    job_queue: Arc<JobQueue>,
+    coverage: Arc<CoverageTracker>,
+    in_flight: Arc<InFlightJobs>,
+    workers: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    scaling: Arc<ScalingMetrics>,
+    scaling_policy: ScalingPolicy,
     worker_count: usize,
-    max_job_duration: Duration,
-    is_running: RwLock<bool>,
+    resource_policy: Arc<ResourcePolicy>,
+    toolchain: Toolchain,
+    worker: WorkerIdentity,
+    reverification: Option<ReverificationConfig>,
+    reverification_metrics: Arc<ReverificationMetrics>,
+    retention: Option<RetentionConfig>,
+    pool: Arc<ContainerPool>,
+    mathlib_cache: Option<Arc<MathlibCache>>,
+    egress: Option<Arc<EgressGateway>>,
+    // GitHub app endpoint batch coverage is streamed to
+    badge_endpoint: Option<String>,
+    is_running: Arc<RwLock<bool>>,
 }
 
 impl JobRunner {
@@ -30,20 +66,168 @@ impl JobRunner {
     ) -> Result<Self, Box<dyn Error>> {
         let storage_manager = StorageManager::new(&config.storage).await?;
         let lean_compiler = LeanCompiler::new(&config.lean);
-        let job_queue = JobQueue::new(config.job.max_queue_size);
+        let job_queue = Arc::new(JobQueue::new(config.job.max_queue_size));
+        let scaling = ScalingMetrics::new()?;
+        let reverification_metrics = ReverificationMetrics::new(scaling.registry())?;
+        let pool_metrics = PoolMetrics::new(scaling.registry())?;
+        let egress = security_manager.egress_policy().clone();
+        let security_opts = security_manager.sandbox_profiles().security_opts();
+        let toolchain = Toolchain::from_env();
+        let worker = WorkerIdentity::from_env(&toolchain);
         
         Ok(Self {
             config,
             security_manager,
             storage_manager,
+            envelope: None,
             lean_compiler,
             job_queue,
+            coverage: Arc::new(CoverageTracker::default()),
+            in_flight: Arc::new(InFlightJobs::default()),
+            workers: Arc::new(Mutex::new(Vec::new())),
+            scaling: Arc::new(scaling),
+            scaling_policy: ScalingPolicy::default(),
             worker_count: 10,
-            max_job_duration: Duration::from_secs(300), // 5 minutes
-            is_running: RwLock::new(false),
+            resource_policy: Arc::new(ResourcePolicy::default()),
+            toolchain,
+            worker,
+            reverification: None,
+            reverification_metrics: Arc::new(reverification_metrics),
+            retention: None,
+            pool: Arc::new(ContainerPool::new(PoolConfig::default(), pool_metrics).with_security_opts(security_opts)),
+            mathlib_cache: None,
+            egress: egress.enabled().then(|| Arc::new(EgressGateway::new(egress))),
+            badge_endpoint: None,
+            is_running: Arc::new(RwLock::new(false)),
         })
     }
 
+    /// Encrypts proof artifacts and checkpoints with per-object data keys
+    /// before they reach object storage
+    pub fn with_envelope_encryption(mut self, envelope: EnvelopeEncryptor) -> Self {
+        self.envelope = Some(envelope);
+        self
+    }
+
+    /// Sets the timeout, container limits and attempts each job gets by
+    /// priority and taxonomy category
+    pub fn with_resource_policy(mut self, policy: ResourcePolicy) -> Self {
+        self.resource_policy = Arc::new(policy);
+        self
+    }
+
+    /// Periodically re-checks proven theorems against the current toolchain
+    pub fn with_reverification(mut self, config: ReverificationConfig) -> Self {
+        self.reverification = Some(config);
+        self
+    }
+
+    /// Periodically deletes superseded proofs and old failed-attempt
+    /// transcripts
+    pub fn with_retention(mut self, config: RetentionConfig) -> Self {
+        self.retention = Some(config);
+        self
+    }
+
+    /// Keeps warm Lean sandboxes for jobs to lease instead of starting a
+    /// container per job
+    pub fn with_container_pool(mut self, config: PoolConfig) -> Self {
+        self.pool = Arc::new(
+            ContainerPool::new(config, self.pool.metrics().clone())
+                .with_security_opts(self.pool.security_opts().to_vec()),
+        );
+        self
+    }
+
+    /// Mounts a shared, prebuilt Mathlib into every sandbox so jobs don't
+    /// rebuild it
+    pub fn with_mathlib_cache(mut self, config: MathlibCacheConfig) -> Self {
+        let cache = MathlibCache::new(config, &self.toolchain, &self.config.storage.minio.key_prefix);
+        self.mathlib_cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Streams the coverage of submitted batches to the GitHub app's badge
+    /// endpoint
+    pub fn with_badge_endpoint(mut self, endpoint: &str) -> Self {
+        self.badge_endpoint = Some(endpoint.to_string());
+        self
+    }
+    
+    /// Submits a batch of jobs and returns a stream of coverage updates for
+    /// it, one per completed job
+    pub async fn submit_batch(
+        &self,
+        batch_id: &str,
+        mut jobs: Vec<ProofJob>,
+    ) -> Result<broadcast::Receiver<CoverageUpdate>, Box<dyn Error>> {
+        for job in &mut jobs {
+            job.theorem.metadata.insert(BATCH_ID_METADATA_KEY.to_string(), batch_id.to_string());
+        }
+        
+        let critical: Vec<ProofJob> = jobs.iter()
+            .filter(|job| job.priority == JobPriority::Critical)
+            .cloned()
+            .collect();
+        
+        let updates = self.coverage.subscribe();
+        self.coverage.register_batch(batch_id, jobs.len() as u32).await;
+        self.job_queue.enqueue_batch(jobs).await?;
+        for job in &critical {
+            self.preempt_for(job).await;
+        }
+        
+        info!("Submitted batch {}", batch_id);
+        Ok(updates)
+    }
+
+    /// Forwards a batch's coverage updates to the badge until the batch
+    /// completes; a no-op without a badge endpoint or a commit to report on
+    pub fn forward_coverage(
+        &self,
+        updates: broadcast::Receiver<CoverageUpdate>,
+        batch_id: &str,
+        repository_id: &str,
+        commit_sha: &str,
+    ) {
+        let Some(endpoint) = self.badge_endpoint.clone() else {
+            return;
+        };
+        if repository_id.is_empty() || commit_sha.is_empty() {
+            return;
+        }
+        
+        let batch_id = batch_id.to_string();
+        let repository_id = repository_id.to_string();
+        let commit_sha = commit_sha.to_string();
+        tokio::spawn(async move {
+            let forwarded = scheduling::forward_to_badge(updates, &endpoint, &batch_id, &repository_id, &commit_sha).await;
+            if let Err(e) = forwarded {
+                warn!("Stopped forwarding coverage for batch {}: {}", batch_id, e);
+            }
+        });
+    }
+    
+    /// Enqueues a single job. A critical job arriving while every worker is
+    /// busy preempts the lowest-priority running job.
+    pub async fn submit(&self, job: ProofJob) -> Result<(), Box<dyn Error>> {
+        let critical = (job.priority == JobPriority::Critical).then(|| job.clone());
+        self.job_queue.enqueue(job).await?;
+        if let Some(job) = critical {
+            self.preempt_for(&job).await;
+        }
+        Ok(())
+    }
+
+    async fn preempt_for(&self, job: &ProofJob) {
+        if self.in_flight.len().await < self.worker_count {
+            return;
+        }
+        if let Some(victim) = self.in_flight.preempt_for(job, self.job_queue.scheduling()).await {
+            info!("Preempting job {} for critical job {}", victim, job.id);
+        }
+    }
+
     pub async fn start_processing(&self) -> Result<(), Box<dyn Error>> {
         info!("Starting job processing with {} workers", self.worker_count);
         
@@ -54,27 +238,41 @@ impl JobRunner {
         
         let (tx, mut rx) = mpsc::channel(100);
         
-        // Start worker pool
-        let mut worker_handles = Vec::new();
-        for worker_id in 0..self.worker_count {
-            let tx = tx.clone();
+        // Start worker pool; the handles are kept so a drain can abort
+        // workers still busy when the grace period runs out
+        {
+            let mut workers = self.workers.lock().await;
+            for worker_id in 0..self.worker_count {
+                let tx = tx.clone();
+                let job_runner = self.clone();
+                workers.push(tokio::spawn(async move {
+                    job_runner.worker_loop(worker_id, tx).await;
+                }));
+            }
+            
+            // Keeps the scaling gauges current between job completions
             let job_runner = self.clone();
-            let handle = tokio::spawn(async move {
-                job_runner.worker_loop(worker_id, tx).await;
-            });
-            worker_handles.push(handle);
+            workers.push(tokio::spawn(async move {
+                while *job_runner.is_running.read().await {
+                    job_runner.scaling_hints().await;
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }));
+            
+            let job_runner = self.clone();
+            workers.push(tokio::spawn(async move {
+                if let Err(e) = job_runner.run_container_pool().await {
+                    error!("Container pool stopped: {}", e);
+                }
+            }));
         }
+        drop(tx);
         
-        // Process results
+        // Process results until every worker has stopped
         while let Some(result) = rx.recv().await {
             self.handle_job_result(result).await?;
         }
         
-        // Wait for all workers to complete
-        for handle in worker_handles {
-            let _ = handle.await;
-        }
-        
         Ok(())
     }
 
@@ -94,8 +292,25 @@ impl JobRunner {
             
             info!("Worker {} processing job {}", worker_id, job.id);
             
-            // Process the job
-            let result = self.process_job(job).await;
+            // Process the job unless it is preempted first
+            let preempt = self.in_flight.start(&job).await;
+            let result = tokio::select! {
+                result = self.process_job(job.clone()) => Some(result),
+                _ = preempt.notified() => None,
+            };
+            let container_id = self.in_flight.finish(&job.id).await;
+            
+            let result = match result {
+                Some(result) => result,
+                None => {
+                    self.requeue_preempted(job, container_id).await;
+                    continue;
+                }
+            };
+            
+            if !result.success && self.retry_failed(&job).await {
+                continue;
+            }
             
             // Send result back
             if let Err(e) = tx.send(result).await {
@@ -106,12 +321,46 @@ impl JobRunner {
         info!("Worker {} stopped", worker_id);
     }
 
+    // Keeps the job's original creation time, so it retains the priority
+    // it has aged to
+    async fn requeue_preempted(&self, mut job: ProofJob, container_id: Option<String>) {
+        if let Some(container_id) = container_id {
+            let _ = self.cleanup_container(&container_id).await;
+        }
+        
+        let preemptions = scheduling::preemption_count(&job.theorem) + 1;
+        job.theorem.metadata.insert(PREEMPTION_COUNT_METADATA_KEY.to_string(), preemptions.to_string());
+        info!("Job {} preempted ({} times), requeueing", job.id, preemptions);
+        self.job_queue.requeue(job).await;
+    }
+
+    // Requeues a failed job that has attempts left under its resource limits
+    async fn retry_failed(&self, job: &ProofJob) -> bool {
+        let max_attempts = self.resource_policy.limits_for(job).max_attempts;
+        let attempts = resources::attempt_count(&job.theorem) + 1;
+        let deadline_passed = job.deadline.map_or(false, |deadline| Instant::now() > deadline);
+        if attempts >= max_attempts || deadline_passed {
+            return false;
+        }
+        
+        let mut job = job.clone();
+        job.theorem.metadata.insert(ATTEMPT_COUNT_METADATA_KEY.to_string(), attempts.to_string());
+        info!("Job {} failed on attempt {}/{}, retrying", job.id, attempts, max_attempts);
+        self.job_queue.requeue(job).await;
+        true
+    }
+
     #[instrument(skip(self, job))]
     async fn process_job(&self, job: ProofJob) -> ProofResult {
         let start_time = Instant::now();
+        let started_on = chrono::Utc::now();
         let mut resource_usage = ResourceUsage::default();
+        let limits = self.resource_policy.limits_for(&job);
         
-        info!("Processing job {} with theorem {}", job.id, job.theorem.theorem_name);
+        info!(
+            "Processing job {} with theorem {} (timeout {}s, {} CPUs, {}MB)",
+            job.id, job.theorem.theorem_name, limits.timeout_seconds, limits.cpus, limits.memory_mb
+        );
         
         // Check deadline
         if let Some(deadline) = job.deadline {
@@ -128,6 +377,39 @@ impl JobRunner {
             }
         }
         
+        let required = Toolchain::of_theorem(&job.theorem);
+        if !self.toolchain.satisfies(required.as_ref()) {
+            let required = required.unwrap_or_else(|| self.toolchain.clone());
+            return ProofResult {
+                job_id: job.id,
+                theorem: job.theorem,
+                proof_artifact: ProofArtifact::default(),
+                duration_ms: start_time.elapsed().as_millis() as u64,
+                success: false,
+                error_message: Some(format!(
+                    "Theorem requires toolchain {}, this farm runs {}",
+                    required.key(), self.toolchain.key()
+                )),
+                resource_usage,
+            };
+        }
+        
+        // A proof already checked with this toolchain is reused as is,
+        // except when re-verification is the point of the job
+        let reverifying = reverification::reverification_run(&job.theorem).is_some();
+        if let Some(proof_artifact) = self.cached_proof(&job.theorem).await.filter(|_| !reverifying) {
+            info!("Reusing cached proof {} for theorem {}", proof_artifact.id, job.theorem.theorem_name);
+            return ProofResult {
+                job_id: job.id,
+                theorem: job.theorem,
+                proof_artifact,
+                duration_ms: start_time.elapsed().as_millis() as u64,
+                success: true,
+                error_message: None,
+                resource_usage,
+            };
+        }
+        
         // Download code bundle from S3
         let code_bundle_path = match self.download_code_bundle(&job.theorem).await {
             Ok(path) => path,
@@ -145,21 +427,31 @@ impl JobRunner {
         };
         
         // Run Lean compilation and proof generation
-        let result = timeout(self.max_job_duration, async {
-            self.run_lean_proof(&job, &code_bundle_path).await
+        let result = timeout(limits.timeout(), async {
+            self.run_lean_proof(&job, &code_bundle_path, &limits).await
         }).await;
         
-        let (theorem, proof_artifact, success, error_message) = match result {
+        let (theorem, mut proof_artifact, success, error_message) = match result {
             Ok(Ok((theorem, proof_artifact))) => (theorem, proof_artifact, true, None),
             Ok(Err(e)) => (job.theorem.clone(), ProofArtifact::default(), false, Some(e.to_string())),
             Err(_) => (job.theorem.clone(), ProofArtifact::default(), false, Some("Job timeout".to_string())),
         };
+        limits.record(&mut proof_artifact.metadata);
+        self.worker.record(&mut proof_artifact.metadata);
         
         // Upload proof artifact to MinIO
         if success {
-            if let Err(e) = self.upload_proof_artifact(&proof_artifact).await {
+            if let Err(e) = self.upload_provenance(&job, &theorem, &mut proof_artifact, started_on).await {
+                error!("Failed to upload provenance for {}: {}", proof_artifact.id, e);
+            }
+            if let Err(e) = self.upload_proof_artifact(&theorem, &proof_artifact).await {
                 error!("Failed to upload proof artifact: {}", e);
             }
+            if let Err(e) = self.record_proven(&job, &theorem, &proof_artifact).await {
+                error!("Failed to record proven theorem {}: {}", theorem.theorem_name, e);
+            }
+        } else {
+            self.record_failed_attempt(&job, error_message.as_deref().unwrap_or_default()).await;
         }
         
         let duration_ms = start_time.elapsed().as_millis() as u64;
@@ -190,70 +482,51 @@ impl JobRunner {
         &self,
         job: &ProofJob,
         code_bundle_path: &PathBuf,
+        limits: &ResourceLimits,
     ) -> Result<(LeanTheorem, ProofArtifact), Box<dyn Error>> {
         info!("Running Lean proof for theorem {}", job.theorem.theorem_name);
         
-        // Create Docker container with Lean image
-        let container_id = self.create_lean_container(code_bundle_path).await?;
-        
-        // Mount S3 code bundle read-only
-        self.mount_code_bundle(&container_id, code_bundle_path).await?;
-        
-        // Run lake build
-        let build_result = self.run_lake_build(&container_id).await?;
-        if !build_result.success {
-            return Err(LeanFarmError::LeanCompilation(build_result.error_message).into());
-        }
-        
-        // Run proof generation
-        let proof_result = self.run_proof_generation(&container_id, &job.theorem, &job.options).await?;
-        
-        // Clean up container
-        self.cleanup_container(&container_id).await?;
-        
-        Ok((job.theorem.clone(), proof_result))
-    }
-
-    async fn create_lean_container(&self, code_bundle_path: &PathBuf) -> Result<String, Box<dyn Error>> {
-        let lean_version = std::env::var("LEAN_VERSION").unwrap_or_else(|_| "4.7.0".to_string());
-        let image_name = format!("leanprover/lean4:{}", lean_version);
+        // Lease a sandbox with the job's limits; a timeout or preemption
+        // drops the lease and the sandbox is removed
+        let lease = self.pool.lease(limits).await?;
+        self.in_flight.attach_container(&job.id, lease.container_id()).await;
         
-        // Docker run command with security restrictions
-        let output = tokio::process::Command::new("docker")
-            .args(&[
-                "run",
-                "--rm",
-                "--detach",
-                "--security-opt=seccomp=unconfined",
-                "--security-opt=no-new-privileges",
-                "--read-only",
-                "--tmpfs=/tmp:rw,noexec,nosuid,size=1g",
-                "--tmpfs=/var/lean-farm:rw,noexec,nosuid,size=2g",
-                "--user=1000:1000",
-                "--cpus=2",
-                "--memory=4g",
-                "--network=none",
-                "--name", &format!("lean-farm-{}", uuid::Uuid::new_v4()),
-                &image_name,
-                "sleep", "3600"
-            ])
-            .output()
-            .await?;
-        
-        if !output.status.success() {
-            return Err(LeanFarmError::JobExecution(
-                format!("Failed to create container: {}", String::from_utf8_lossy(&output.stderr))
-            ).into());
-        }
+        let result = async {
+            // Mount S3 code bundle read-only
+            self.mount_code_bundle(lease.container_id(), code_bundle_path).await?;
+            
+            // Fetch dependencies from allowed hosts; the sandbox is offline
+            // again before anything is built
+            if let Some(egress) = &self.egress {
+                egress.run_setup(lease.container_id(), CODE_MOUNT_PATH).await?;
+            }
+            
+            // Run lake build
+            let build_result = self.run_lake_build(lease.container_id()).await?;
+            if !build_result.success {
+                return Err(LeanFarmError::LeanCompilation(build_result.error_message.unwrap_or_default()).into());
+            }
+            
+            // Run proof generation
+            self.run_proof_generation(lease.container_id(), &job.theorem, &job.options).await
+        }.await;
         
-        let container_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        info!("Created Lean container: {}", container_id);
+        // A failed build still leaves a clean sandbox; anything else may
+        // have left processes behind
+        let outcome = match &result {
+            Ok(_) => JobOutcome::Completed,
+            Err(e) if matches!(e.downcast_ref::<LeanFarmError>(), Some(LeanFarmError::LeanCompilation(_))) => {
+                JobOutcome::Completed
+            }
+            Err(_) => JobOutcome::Aborted,
+        };
+        lease.release(outcome).await;
         
-        Ok(container_id)
+        Ok((job.theorem.clone(), result?))
     }
 
     async fn mount_code_bundle(&self, container_id: &str, code_bundle_path: &PathBuf) -> Result<(), Box<dyn Error>> {
-        let mount_path = "/var/lean-farm/code";
+        let mount_path = CODE_MOUNT_PATH;
         
         let output = tokio::process::Command::new("docker")
             .args(&[
@@ -352,6 +625,8 @@ impl JobRunner {
             proof_strategy: options.proof_strategy.clone(),
             confidence_score: 0.95,
             metadata: std::collections::HashMap::new(),
+            lean_toolchain: self.toolchain.lean_toolchain.clone(),
+            mathlib_commit: self.toolchain.mathlib_commit.clone(),
         })
     }
 
@@ -370,20 +645,88 @@ impl JobRunner {
         Ok(())
     }
 
-    async fn upload_proof_artifact(&self, proof_artifact: &ProofArtifact) -> Result<(), Box<dyn Error>> {
-        let artifact_key = format!("{}/{}", self.config.storage.minio.key_prefix, proof_artifact.id);
+    // Proofs are stored by theorem content and toolchain, so a theorem is
+    // proven once per toolchain
+    fn proof_key(&self, theorem: &LeanTheorem) -> String {
+        format!("{}/{}", self.toolchain_prefix(&self.toolchain.key()), theorem.content_sha256)
+    }
+
+    fn toolchain_prefix(&self, toolchain_key: &str) -> String {
+        format!("{}/proofs/{}", self.config.storage.minio.key_prefix, toolchain_key)
+    }
+
+    async fn cached_proof(&self, theorem: &LeanTheorem) -> Option<ProofArtifact> {
+        let key = self.proof_key(theorem);
+        match self.storage_manager.list_minio_keys(&key).await {
+            Ok(keys) if keys.contains(&key) => {}
+            Ok(_) => return None,
+            Err(e) => {
+                warn!("Failed to look up cached proof {}: {}", key, e);
+                return None;
+            }
+        }
+        
+        let artifact = self.get_object(&key).await
+            .and_then(|bytes| Ok(ProofArtifact::decode(bytes.as_slice())?));
+        match artifact {
+            Ok(artifact) if artifact.status == ProofStatus::Success as i32 => Some(artifact),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Ignoring unreadable cached proof {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    /// Deletes every proof checked with a deprecated toolchain, identified
+    /// by `Toolchain::key`. Returns how many were removed.
+    pub async fn purge_toolchain(&self, toolchain_key: &str) -> Result<usize, Box<dyn Error>> {
+        if toolchain_key.is_empty() || toolchain_key.contains('/') {
+            return Err(LeanFarmError::Config(format!("Invalid toolchain key: {:?}", toolchain_key)).into());
+        }
+        if toolchain_key == self.toolchain.key() {
+            return Err(LeanFarmError::Config(format!(
+                "Toolchain {} is in use by this farm and cannot be purged", toolchain_key
+            )).into());
+        }
+        
+        let keys = self.storage_manager.list_minio_keys(&format!("{}/", self.toolchain_prefix(toolchain_key))).await?;
+        for key in &keys {
+            self.storage_manager.delete_from_minio(key).await?;
+        }
+        info!("Purged {} proofs for toolchain {}", keys.len(), toolchain_key);
+        Ok(keys.len())
+    }
+
+    async fn upload_proof_artifact(&self, theorem: &LeanTheorem, proof_artifact: &ProofArtifact) -> Result<(), Box<dyn Error>> {
+        let artifact_key = self.proof_key(theorem);
         
         info!("Uploading proof artifact to MinIO: {}", artifact_key);
         
         // Serialize proof artifact to protobuf
         let artifact_bytes = proof_artifact.encode_to_vec();
         
-        self.storage_manager.upload_to_minio(&artifact_key, &artifact_bytes).await?;
+        self.put_object(&artifact_key, artifact_bytes).await?;
         
         info!("Successfully uploaded proof artifact {}", proof_artifact.id);
         Ok(())
     }
 
+    // Stored next to the proof, so purging a toolchain removes both
+    async fn upload_provenance(
+        &self,
+        job: &ProofJob,
+        theorem: &LeanTheorem,
+        proof_artifact: &mut ProofArtifact,
+        started_on: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(), Box<dyn Error>> {
+        let key = format!("{}.intoto.json", self.proof_key(theorem));
+        let statement = Statement::for_proof(job, theorem, proof_artifact, &self.toolchain, &self.worker, started_on, chrono::Utc::now())
+            .to_json()?;
+        provenance::record(&mut proof_artifact.metadata, &key, &statement);
+        self.put_object(&key, statement).await
+    }
+
     async fn handle_job_result(&self, result: ProofResult) -> Result<(), Box<dyn Error>> {
         if result.success {
             info!("Job {} completed successfully in {}ms", result.job_id, result.duration_ms);
@@ -394,6 +737,21 @@ impl JobRunner {
         // Update metrics
         self.update_job_metrics(&result).await;
         
+        // Stream incremental batch coverage
+        if let Some(update) = self.coverage.record(&result).await {
+            info!(
+                "Batch {} coverage: {}/{} proven, {} failed",
+                update.batch_id, update.proven, update.total, update.failed
+            );
+        }
+        
+        if let Some(run_id) = reverification::reverification_run(&result.theorem) {
+            self.reverification_metrics.record_result(result.success);
+            if !result.success {
+                self.record_regression(run_id, &result).await;
+            }
+        }
+        
         // Store result in persistent storage
         self.storage_manager.store_job_result(&result).await?;
         
@@ -401,8 +759,308 @@ impl JobRunner {
     }
 
     async fn update_job_metrics(&self, result: &ProofResult) {
-        // Update Prometheus metrics
-        // This would integrate with the metrics server
+        self.scaling.observe_job_duration(Duration::from_millis(result.duration_ms));
+        self.scaling_hints().await;
+    }
+
+    /// Current backlog and the concurrency needed to clear it, also
+    /// published as Prometheus gauges
+    pub async fn scaling_hints(&self) -> ScalingHints {
+        let hints = ScalingHints::compute(
+            self.job_queue.backlog_by_priority().await,
+            self.in_flight.len().await,
+            self.worker_count,
+            self.scaling.average_job_duration(),
+            &self.scaling_policy,
+        );
+        self.scaling.update(&hints);
+        hints
+    }
+
+    fn proven_prefix(&self) -> String {
+        format!("{}/proven/", self.config.storage.minio.key_prefix)
+    }
+
+    // One record per theorem content, replaced each time it is proven
+    async fn record_proven(
+        &self,
+        job: &ProofJob,
+        theorem: &LeanTheorem,
+        proof_artifact: &ProofArtifact,
+    ) -> Result<(), Box<dyn Error>> {
+        let key = format!("{}{}.json", self.proven_prefix(), theorem.content_sha256);
+        let record = ProvenTheorem::new(job, theorem, &self.toolchain.key(), &proof_artifact.id);
+        self.put_object(&key, serde_json::to_vec(&record)?).await
+    }
+
+    // Kept until retention ages it out
+    async fn record_failed_attempt(&self, job: &ProofJob, error_message: &str) {
+        let attempt = FailedAttempt {
+            job_id: job.id.clone(),
+            attempt: resources::attempt_count(&job.theorem) + 1,
+            theorem_id: job.theorem.id.clone(),
+            theorem_name: job.theorem.theorem_name.clone(),
+            invariant_id: job.theorem.source_invariant_id.clone(),
+            content_sha256: job.theorem.content_sha256.clone(),
+            toolchain: self.toolchain.key(),
+            worker: Some(self.worker.clone()),
+            error_message: error_message.to_string(),
+            failed_at: chrono::Utc::now(),
+        };
+        let key = retention::failed_attempt_key(&self.config.storage.minio.key_prefix, &attempt);
+        let stored = match serde_json::to_vec(&attempt) {
+            Ok(bytes) => self.put_object(&key, bytes).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = stored {
+            warn!("Failed to record failed attempt of job {}: {}", job.id, e);
+        }
+    }
+
+    // Stored under the toolchain that broke the proof, next to its proofs,
+    // and raised through the proof regression alert
+    async fn record_regression(&self, run_id: &str, result: &ProofResult) {
+        let regression = Regression {
+            run_id: run_id.to_string(),
+            theorem_id: result.theorem.id.clone(),
+            theorem_name: result.theorem.theorem_name.clone(),
+            invariant_id: result.theorem.source_invariant_id.clone(),
+            content_sha256: result.theorem.content_sha256.clone(),
+            previous_toolchain: result.theorem.metadata
+                .get(reverification::PREVIOUS_TOOLCHAIN_METADATA_KEY)
+                .cloned()
+                .unwrap_or_default(),
+            toolchain: self.toolchain.key(),
+            error_message: result.error_message.clone().unwrap_or_default(),
+            detected_at: chrono::Utc::now(),
+        };
+        error!(
+            "Proof regression: theorem {} proven with {} fails with {}: {}",
+            regression.theorem_name, regression.previous_toolchain, regression.toolchain, regression.error_message
+        );
+        
+        let key = format!(
+            "{}/regressions/{}/{}.json",
+            self.config.storage.minio.key_prefix, regression.toolchain, regression.content_sha256
+        );
+        let stored = match serde_json::to_vec(&regression) {
+            Ok(bytes) => self.put_object(&key, bytes).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = stored {
+            error!("Failed to record regression of {}: {}", regression.theorem_name, e);
+        }
+    }
+
+    /// Queues re-verification of a sample of proven theorems as low-priority
+    /// jobs and returns how many were queued
+    pub async fn run_reverification(&self) -> Result<usize, Box<dyn Error>> {
+        let Some(config) = &self.reverification else {
+            return Ok(0);
+        };
+        let run_id = chrono::Utc::now().format("%Y%m%dT%H%M").to_string();
+        self.reverification_metrics.record_run();
+        
+        let keys = self.storage_manager.list_minio_keys(&self.proven_prefix()).await?;
+        let sample = reverification::select_sample(&keys, config.sample_rate, config.max_theorems, &run_id);
+        
+        let mut jobs = Vec::new();
+        for key in &sample {
+            let job = self.get_object(key).await
+                .and_then(|bytes| Ok(serde_json::from_slice::<ProvenTheorem>(&bytes)?))
+                .and_then(|proven| Ok(proven.into_job(&run_id)?));
+            match job {
+                Ok(job) => jobs.push(job),
+                Err(e) => warn!("Skipping unreadable proven theorem {}: {}", key, e),
+            }
+        }
+        
+        let queued = jobs.len();
+        if queued > 0 {
+            self.job_queue.enqueue_batch(jobs).await?;
+        }
+        info!(
+            "Re-verification run {} queued {} of {} proven theorems against {}",
+            run_id, queued, keys.len(), self.toolchain.key()
+        );
+        Ok(queued)
+    }
+
+    /// Runs re-verification on its schedule until the runner stops
+    pub async fn start_reverification_scheduler(&self) -> Result<(), Box<dyn Error>> {
+        let Some(config) = &self.reverification else {
+            return Ok(());
+        };
+        info!("Re-verification scheduled at {:?}", config.schedule.expression());
+        
+        loop {
+            let Some(delay) = reverification::delay_until_next(&config.schedule, chrono::Utc::now()) else {
+                return Err(LeanFarmError::Config(format!(
+                    "Re-verification schedule {:?} never fires", config.schedule.expression()
+                )).into());
+            };
+            tokio::time::sleep(delay).await;
+            if !*self.is_running.read().await {
+                return Ok(());
+            }
+            if let Err(e) = self.run_reverification().await {
+                error!("Re-verification run failed: {}", e);
+            }
+        }
+    }
+
+    /// Deletes proofs and failed-attempt transcripts the retention policy
+    /// no longer keeps, or only reports them when `dry_run` is set. The
+    /// report is stored under `<key_prefix>/retention/`.
+    pub async fn run_retention(&self, dry_run: bool) -> Result<RetentionReport, Box<dyn Error>> {
+        let policy = self.retention.as_ref().map(|config| config.policy.clone()).unwrap_or_default();
+        let prefix = &self.config.storage.minio.key_prefix;
+        
+        let mut proofs = Vec::new();
+        for key in self.storage_manager.list_minio_keys(&format!("{}/proofs/", prefix)).await? {
+            if key.ends_with(retention::PROVENANCE_SUFFIX) {
+                continue;
+            }
+            let artifact = self.get_object(&key).await
+                .and_then(|bytes| Ok(ProofArtifact::decode(bytes.as_slice())?));
+            match artifact {
+                Ok(artifact) => proofs.push(StoredProof {
+                    key,
+                    invariant_id: if artifact.invariant_id.is_empty() { artifact.theorem_id } else { artifact.invariant_id },
+                    attempted_at: artifact.attempted_at
+                        .and_then(|at| chrono::DateTime::from_timestamp(at.seconds, at.nanos.max(0) as u32)),
+                    artifact_id: artifact.id,
+                }),
+                // Never delete what cannot be read
+                Err(e) => warn!("Retention skipping unreadable proof {}: {}", key, e),
+            }
+        }
+        
+        // A proof named by a proven record backs a theorem's current status
+        let mut protected = std::collections::HashSet::new();
+        for key in self.storage_manager.list_minio_keys(&self.proven_prefix()).await? {
+            let proven = self.get_object(&key).await
+                .and_then(|bytes| Ok(serde_json::from_slice::<ProvenTheorem>(&bytes)?));
+            match proven {
+                Ok(proven) => {
+                    protected.insert(proven.proof_artifact_id);
+                }
+                Err(e) => {
+                    return Err(LeanFarmError::Storage(format!(
+                        "cannot read proven record {}, so the proofs it protects are unknown: {}", key, e
+                    )).into());
+                }
+            }
+        }
+        
+        let attempts = self.storage_manager.list_minio_keys(&format!("{}/attempts/", prefix)).await?;
+        let mut report = retention::plan(&policy, prefix, &proofs, &protected, &attempts, chrono::Utc::now());
+        report.dry_run = dry_run;
+        
+        if !dry_run {
+            for key in &report.deleted_proofs {
+                self.storage_manager.delete_from_minio(key).await?;
+                self.storage_manager.delete_from_minio(&format!("{}{}", key, retention::PROVENANCE_SUFFIX)).await?;
+            }
+            for key in &report.deleted_failed_attempts {
+                self.storage_manager.delete_from_minio(key).await?;
+            }
+        }
+        
+        info!(
+            "Retention {}: {} proofs deleted, {} kept ({} protected), {} failed attempts deleted, {} kept",
+            if dry_run { "dry run" } else { "run" },
+            report.deleted_proofs.len(),
+            report.proofs_kept,
+            report.proofs_protected,
+            report.deleted_failed_attempts.len(),
+            report.failed_attempts_kept
+        );
+        let key = format!("{}/retention/{}.json", prefix, report.ran_at.format("%Y%m%dT%H%M%S"));
+        self.put_object(&key, serde_json::to_vec_pretty(&report)?).await?;
+        Ok(report)
+    }
+
+    /// Runs retention on its schedule until the runner stops
+    pub async fn start_retention_scheduler(&self) -> Result<(), Box<dyn Error>> {
+        let Some(config) = &self.retention else {
+            return Ok(());
+        };
+        info!(
+            "Retention scheduled at {:?}{}",
+            config.schedule.expression(),
+            if config.dry_run { " (dry run)" } else { "" }
+        );
+        
+        loop {
+            let Some(delay) = reverification::delay_until_next(&config.schedule, chrono::Utc::now()) else {
+                return Err(LeanFarmError::Config(format!(
+                    "Retention schedule {:?} never fires", config.schedule.expression()
+                )).into());
+            };
+            tokio::time::sleep(delay).await;
+            if !*self.is_running.read().await {
+                return Ok(());
+            }
+            if let Err(e) = self.run_retention(config.dry_run).await {
+                error!("Retention run failed: {}", e);
+            }
+        }
+    }
+
+    /// Checks the docker runtime enforces the sandbox seccomp and AppArmor
+    /// profiles; jobs must not run when it doesn't
+    pub async fn validate_sandbox_profiles(&self) -> Result<(), LeanFarmError> {
+        self.security_manager
+            .sandbox_profiles()
+            .validate_runtime(&self.pool.config().image)
+            .await
+    }
+
+    /// Fills the Mathlib cache for the farm's toolchain if it isn't already,
+    /// and mounts it into sandboxes started from then on. Returns None when
+    /// no cache is configured or the toolchain doesn't pin Mathlib.
+    pub async fn warm_mathlib_cache(&self) -> Result<Option<CacheManifest>, LeanFarmError> {
+        let Some(cache) = self.mathlib_cache.as_ref().filter(|cache| cache.applies()) else {
+            return Ok(None);
+        };
+        let manifest = cache.ensure(&self.storage_manager).await?;
+        info!(
+            "Mounting Mathlib cache {} ({}, warmed {})",
+            cache.dir().display(), manifest.source, manifest.warmed_at
+        );
+        self.pool.set_extra_args(manifest.sandbox_args(&cache.dir())).await;
+        Ok(Some(manifest))
+    }
+
+    /// Warms the Mathlib cache and pulls the sandbox image, then keeps the
+    /// container pool topped up with warm sandboxes while jobs are
+    /// processed; returns after warming when pooling is off
+    async fn run_container_pool(&self) -> Result<(), Box<dyn Error>> {
+        // Jobs run without the cache until it is ready, so a failure here
+        // only costs build time
+        if let Err(e) = self.warm_mathlib_cache().await {
+            error!("Failed to warm Mathlib cache: {}", e);
+        }
+
+        let config = self.pool.config();
+        if !config.enabled() {
+            return Ok(());
+        }
+        info!("Keeping {} warm sandboxes from {}", config.size, config.image);
+        self.pool.prepull().await?;
+        
+        while *self.is_running.read().await {
+            if let Err(e) = self.pool.replenish().await {
+                warn!("Failed to start warm sandbox: {}", e);
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+        Ok(())
+    }
+
+    pub fn metrics_registry(&self) -> prometheus::Registry {
+        self.scaling.registry().clone()
     }
 
     pub async fn start_health_server(&self) -> Result<(), Box<dyn Error>> {
@@ -414,15 +1072,21 @@ impl JobRunner {
         };
         use serde_json::json;
         
+        // Not ready before processing starts or once draining begins, so
+        // no new work is routed to this replica
+        let is_running = self.is_running.clone();
+        let job_runner = self.clone();
         let app = Router::new()
             .route("/health", get(|| async { StatusCode::OK }))
-            .route("/ready", get(|| async { StatusCode::OK }))
-            .route("/metrics", get(|| async { 
-                Json(json!({
-                    "queue_size": 0, // Would get from job_queue
-                    "active_workers": 0,
-                    "uptime_seconds": 0,
-                }))
+            .route("/ready", get(move || async move {
+                if *is_running.read().await {
+                    StatusCode::OK
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE
+                }
+            }))
+            .route("/metrics", get(move || async move {
+                Json(job_runner.scaling_hints().await)
             }));
         
         let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
@@ -437,6 +1101,122 @@ impl JobRunner {
         let mut is_running = self.is_running.write().await;
         *is_running = false;
     }
+
+    /// Stops dequeuing, gives running jobs up to `grace_period` to finish,
+    /// then checkpoints whatever is unfinished or still queued for another
+    /// replica and removes the containers of interrupted jobs
+    pub async fn drain(&self, grace_period: Duration) -> DrainReport {
+        info!(
+            "Draining job runner: {} jobs running, {} queued, grace period {}s",
+            self.in_flight.len().await,
+            self.job_queue.size().await,
+            grace_period.as_secs()
+        );
+        self.stop().await;
+        
+        if !self.in_flight.wait_until_idle(grace_period).await {
+            warn!("Drain grace period elapsed with {} jobs still running", self.in_flight.len().await);
+        }
+        
+        // Stop the workers before their containers go away underneath them
+        for worker in self.workers.lock().await.drain(..) {
+            worker.abort();
+        }
+        
+        let interrupted = self.in_flight.take_all().await;
+        let mut report = DrainReport {
+            interrupted: interrupted.len(),
+            ..Default::default()
+        };
+        
+        for job in self.job_queue.drain().await {
+            self.checkpoint(&job, false, &mut report).await;
+        }
+        for (job, container_id) in interrupted {
+            self.checkpoint(&job, true, &mut report).await;
+            if let Some(container_id) = container_id {
+                // cleanup_container ignores docker failures
+                let _ = self.cleanup_container(&container_id).await;
+                report.containers_removed += 1;
+            }
+        }
+        report.containers_removed += self.pool.clear().await;
+        
+        info!(
+            "Drain complete: {} interrupted, {} requeued, {} lost, {} containers removed",
+            report.interrupted, report.requeued, report.lost, report.containers_removed
+        );
+        report
+    }
+
+    async fn checkpoint(&self, job: &ProofJob, interrupted: bool, report: &mut DrainReport) {
+        let checkpoint = JobCheckpoint::from_job(job, interrupted);
+        let key = format!("{}/{}.json", self.checkpoint_prefix(), job.id);
+        let stored = match serde_json::to_vec(&checkpoint) {
+            Ok(bytes) => self.put_object(&key, bytes).await,
+            Err(e) => Err(e.into()),
+        };
+        match stored {
+            Ok(()) => report.requeued += 1,
+            Err(e) => {
+                error!("Failed to checkpoint job {}: {}", job.id, e);
+                report.lost += 1;
+            }
+        }
+    }
+
+    /// Re-enqueues jobs checkpointed by replicas that drained, removing
+    /// each checkpoint once it has been read
+    pub async fn restore_checkpoints(&self) -> Result<usize, Box<dyn Error>> {
+        let mut jobs = Vec::new();
+        for key in self.storage_manager.list_minio_keys(&self.checkpoint_prefix()).await? {
+            let bytes = self.get_object(&key).await?;
+            self.storage_manager.delete_from_minio(&key).await?;
+            
+            let restored = serde_json::from_slice::<JobCheckpoint>(&bytes)
+                .map_err(|e| LeanFarmError::Storage(format!("invalid checkpoint {}: {}", key, e)))
+                .and_then(JobCheckpoint::into_job);
+            match restored {
+                Ok(job) => jobs.push(job),
+                Err(e) => warn!("Discarding checkpoint {}: {}", key, e),
+            }
+        }
+        
+        let restored = jobs.len();
+        if restored > 0 {
+            self.job_queue.enqueue_batch(jobs).await?;
+            info!("Restored {} checkpointed jobs", restored);
+        }
+        Ok(restored)
+    }
+
+    fn checkpoint_prefix(&self) -> String {
+        format!("{}/checkpoints", self.config.storage.minio.key_prefix)
+    }
+
+    async fn put_object(&self, key: &str, bytes: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        match &self.envelope {
+            Some(envelope) => {
+                let sealed = envelope.seal(key, &bytes).await?;
+                self.storage_manager
+                    .upload_to_minio_with_metadata(key, &sealed.ciphertext, &sealed.metadata)
+                    .await
+            }
+            None => self.storage_manager.upload_to_minio(key, &bytes).await,
+        }
+    }
+
+    // Objects written before encryption was enabled are read as they are
+    async fn get_object(&self, key: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let (bytes, metadata) = self.storage_manager.download_from_minio_with_metadata(key).await?;
+        match &self.envelope {
+            Some(envelope) => Ok(envelope.open(key, bytes, &metadata).await?),
+            None if envelope::is_sealed(&metadata) => Err(LeanFarmError::Storage(format!(
+                "{} is encrypted client-side but no KMS key is configured", key
+            )).into()),
+            None => Ok(bytes),
+        }
+    }
 }
 
 impl Clone for JobRunner {
@@ -445,11 +1225,26 @@ impl Clone for JobRunner {
             config: self.config.clone(),
             security_manager: self.security_manager.clone(),
             storage_manager: self.storage_manager.clone(),
+            envelope: self.envelope.clone(),
             lean_compiler: self.lean_compiler.clone(),
-            job_queue: JobQueue::new(self.config.job.max_queue_size),
+            job_queue: self.job_queue.clone(),
+            coverage: self.coverage.clone(),
+            in_flight: self.in_flight.clone(),
+            workers: self.workers.clone(),
+            scaling: self.scaling.clone(),
+            scaling_policy: self.scaling_policy.clone(),
             worker_count: self.worker_count,
-            max_job_duration: self.max_job_duration,
-            is_running: RwLock::new(false),
+            resource_policy: self.resource_policy.clone(),
+            toolchain: self.toolchain.clone(),
+            worker: self.worker.clone(),
+            reverification: self.reverification.clone(),
+            reverification_metrics: self.reverification_metrics.clone(),
+            retention: self.retention.clone(),
+            pool: self.pool.clone(),
+            mathlib_cache: self.mathlib_cache.clone(),
+            egress: self.egress.clone(),
+            badge_endpoint: self.badge_endpoint.clone(),
+            is_running: self.is_running.clone(),
         }
     }
 }
@@ -1,10 +1,23 @@
 pub mod config;
+pub mod drain;
+pub mod egress;
 pub mod job_runner;
+pub mod mathlib_cache;
 pub mod security;
 pub mod metrics;
 pub mod storage;
 pub mod lean;
 pub mod proto;
+pub mod pool;
+pub mod profiles;
+pub mod provenance;
+pub mod scaling;
+pub mod resources;
+pub mod retention;
+pub mod reverification;
+pub mod scheduling;
+pub mod toolchain;
+pub mod worker;
 
 use std::error::Error;
 use std::time::{Duration, Instant};
@@ -31,7 +44,7 @@ impl LeanFarm {
         let metrics_server = metrics::MetricsServer::new(
             config.metrics.port,
             config.metrics.path.clone(),
-        );
+        ).with_registry(job_runner.metrics_registry());
 
         Ok(Self {
             config,
@@ -50,7 +63,7 @@ impl LeanFarm {
         info!("Security validation passed");
         
         // Start metrics server
-        let metrics_handle = tokio::spawn(self.metrics_server.start());
+        let metrics_handle = tokio::spawn(self.metrics_server.clone().start());
         info!("Metrics server started");
         
         // Start health check server
@@ -95,6 +108,24 @@ pub enum JobPriority {
     Critical = 3,
 }
 
+impl JobPriority {
+    pub const ALL: [JobPriority; 4] = [
+        JobPriority::Low,
+        JobPriority::Normal,
+        JobPriority::High,
+        JobPriority::Critical,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobPriority::Low => "low",
+            JobPriority::Normal => "normal",
+            JobPriority::High => "high",
+            JobPriority::Critical => "critical",
+        }
+    }
+}
+
 impl From<i32> for JobPriority {
     fn from(value: i32) -> Self {
         match value {
@@ -143,6 +174,7 @@ impl Default for ResourceUsage {
 pub struct JobQueue {
     jobs: RwLock<Vec<ProofJob>>,
     max_queue_size: usize,
+    scheduling: scheduling::SchedulingConfig,
 }
 
 impl JobQueue {
@@ -150,9 +182,19 @@ impl JobQueue {
         Self {
             jobs: RwLock::new(Vec::new()),
             max_queue_size,
+            scheduling: scheduling::SchedulingConfig::default(),
         }
     }
 
+    pub fn with_scheduling(mut self, scheduling: scheduling::SchedulingConfig) -> Self {
+        self.scheduling = scheduling;
+        self
+    }
+
+    pub fn scheduling(&self) -> &scheduling::SchedulingConfig {
+        &self.scheduling
+    }
+
     pub async fn enqueue(&self, job: ProofJob) -> Result<(), Box<dyn Error>> {
         let mut jobs = self.jobs.write().await;
         
@@ -160,22 +202,73 @@ impl JobQueue {
             return Err("Job queue is full".into());
         }
         
+        info!("Job {} enqueued with priority {:?}", job.id, job.priority);
         jobs.push(job);
-        jobs.sort_by(|a, b| b.priority.cmp(&a.priority));
+        self.reorder(&mut jobs);
+        
+        Ok(())
+    }
+
+    /// Enqueues a whole batch at once so it is ordered by predicted ease
+    /// and priority as a unit, with the hardest proofs deferred to the end
+    pub async fn enqueue_batch(&self, batch: Vec<ProofJob>) -> Result<(), Box<dyn Error>> {
+        let mut jobs = self.jobs.write().await;
+        
+        if jobs.len() + batch.len() > self.max_queue_size {
+            return Err("Job queue is full".into());
+        }
+        
+        info!("Enqueued batch of {} jobs", batch.len());
+        jobs.extend(batch);
+        self.reorder(&mut jobs);
         
-        info!("Job {} enqueued with priority {:?}", job.id, job.priority);
         Ok(())
     }
 
+    /// Puts back a job that was already admitted, such as one preempted
+    /// while running, regardless of the size limit
+    pub async fn requeue(&self, job: ProofJob) {
+        let mut jobs = self.jobs.write().await;
+        info!("Job {} requeued", job.id);
+        jobs.push(job);
+        self.reorder(&mut jobs);
+    }
+
+    // Keeps the next job to run at the end of the vector for dequeue
+    fn reorder(&self, jobs: &mut Vec<ProofJob>) {
+        let now = Instant::now();
+        jobs.sort_by_cached_key(|job| scheduling::schedule_key(job, &self.scheduling, now));
+    }
+
     pub async fn dequeue(&self) -> Option<ProofJob> {
         let mut jobs = self.jobs.write().await;
+        // Aging changes priorities while jobs wait
+        if self.scheduling.aging_interval.is_some() {
+            self.reorder(&mut jobs);
+        }
         jobs.pop()
     }
 
+    /// Removes every queued job, in dequeue order
+    pub async fn drain(&self) -> Vec<ProofJob> {
+        let mut jobs = std::mem::take(&mut *self.jobs.write().await);
+        jobs.reverse();
+        jobs
+    }
+
     pub async fn size(&self) -> usize {
         self.jobs.read().await.len()
     }
 
+    /// Queued jobs by submitted priority, indexed by `JobPriority`
+    pub async fn backlog_by_priority(&self) -> [usize; 4] {
+        let mut backlog = [0; 4];
+        for job in self.jobs.read().await.iter() {
+            backlog[job.priority.clone() as usize] += 1;
+        }
+        backlog
+    }
+
     pub async fn is_empty(&self) -> bool {
         self.jobs.read().await.is_empty()
     }
@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
 use tracing::{info, warn, error};
@@ -9,6 +10,14 @@ use lean_farm::config::Config;
 use lean_farm::job_runner::JobRunner;
 use lean_farm::security::SecurityManager;
 use lean_farm::metrics::MetricsServer;
+use lean_farm::scaling::ScalingService;
+use lean_farm::resources::ResourcePolicy;
+use lean_farm::reverification::{CronSchedule, ReverificationConfig};
+use lean_farm::retention::{RetentionConfig, RetentionPolicy};
+use lean_farm::pool::PoolConfig;
+use lean_farm::mathlib_cache::MathlibCacheConfig;
+use lean_farm::egress::EgressProxy;
+use lean_farm::security::EgressPolicy;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -32,6 +41,122 @@ struct Args {
     /// Metrics path
     #[arg(long, default_value = "/metrics")]
     metrics_path: String,
+    
+    /// Seconds running jobs get to finish on shutdown before they are
+    /// checkpointed for another replica
+    #[arg(long, default_value = "120")]
+    drain_grace_period_seconds: u64,
+    
+    /// Port of the gRPC API serving scaling hints and taking batches
+    #[arg(long, default_value = "50052")]
+    grpc_port: u16,
+    
+    /// KMS key that proof artifacts and checkpoints are envelope-encrypted
+    /// with before upload; stored unencrypted when unset
+    #[arg(long, env = "CLIENT_SIDE_ENCRYPTION_KMS_KEY_ID")]
+    client_side_encryption_kms_key_id: Option<String>,
+    
+    /// JSON file mapping job priority and taxonomy category to timeouts,
+    /// container limits and max attempts
+    #[arg(long)]
+    resource_policy: Option<PathBuf>,
+    
+    /// Cron expression (UTC) for re-running proven theorems against the
+    /// current toolchain, e.g. "0 3 * * 0"; disabled when unset
+    #[arg(long, env = "REVERIFICATION_SCHEDULE")]
+    reverification_schedule: Option<String>,
+    
+    /// Fraction of proven theorems re-verified per run
+    #[arg(long, env = "REVERIFICATION_SAMPLE_RATE", default_value = "1.0")]
+    reverification_sample_rate: f64,
+    
+    /// Most theorems re-verified per run
+    #[arg(long, env = "REVERIFICATION_MAX_THEOREMS")]
+    reverification_max_theorems: Option<usize>,
+    
+    /// Cron expression (UTC) for deleting superseded proofs and old
+    /// failed-attempt transcripts; disabled when unset
+    #[arg(long, env = "RETENTION_SCHEDULE")]
+    retention_schedule: Option<String>,
+    
+    /// Newest proofs kept per invariant
+    #[arg(long, env = "RETENTION_KEEP_LATEST", default_value = "3")]
+    retention_keep_latest: usize,
+    
+    /// Days failed-attempt transcripts are kept
+    #[arg(long, env = "RETENTION_FAILED_ATTEMPT_DAYS", default_value = "30")]
+    retention_failed_attempt_days: u32,
+    
+    /// Only report what scheduled retention runs would delete
+    #[arg(long, env = "RETENTION_DRY_RUN")]
+    retention_dry_run: bool,
+    
+    /// Warm Lean sandboxes kept ready for jobs; 0 starts a container per
+    /// job
+    #[arg(long, env = "CONTAINER_POOL_SIZE", default_value = "0")]
+    container_pool_size: usize,
+    
+    /// Image pooled sandboxes run, with Mathlib built in; defaults to
+    /// leanprover/lean4:$LEAN_VERSION
+    #[arg(long, env = "SANDBOX_IMAGE")]
+    sandbox_image: Option<String>,
+    
+    /// Jobs a pooled sandbox runs before it is replaced
+    #[arg(long, env = "CONTAINER_POOL_MAX_JOBS", default_value = "50")]
+    container_pool_max_jobs: u32,
+    
+    /// Minutes a pooled sandbox is kept before it is replaced
+    #[arg(long, env = "CONTAINER_POOL_MAX_AGE_MINUTES", default_value = "360")]
+    container_pool_max_age_minutes: u64,
+    
+    /// Shared volume holding prebuilt Mathlib per toolchain, mounted
+    /// read-only into every sandbox; must be the same path on the docker
+    /// host
+    #[arg(long, env = "MATHLIB_CACHE_PATH")]
+    mathlib_cache_path: Option<PathBuf>,
+    
+    /// Image that fetches Mathlib oleans with `lake exe cache get`;
+    /// defaults to leanprover/lean4:$LEAN_VERSION
+    #[arg(long, env = "MATHLIB_CACHE_IMAGE")]
+    mathlib_cache_image: Option<String>,
+    
+    /// Share warmed Mathlib caches between replicas through MinIO
+    #[arg(long, env = "MATHLIB_CACHE_SYNC")]
+    mathlib_cache_sync: bool,
+    
+    /// Warm the Mathlib cache for the pinned version and exit
+    #[arg(long)]
+    warm_mathlib_cache_only: bool,
+    
+    /// Hosts sandboxes may reach while a job's setup command runs, e.g.
+    /// the Mathlib cache CDN; overrides `security.egress.allowed_hosts`
+    #[arg(long, env = "EGRESS_ALLOWED_HOSTS", value_delimiter = ',')]
+    egress_allowed_hosts: Vec<String>,
+    
+    /// Image the egress proxy runs in, normally this farm's own
+    #[arg(long, env = "EGRESS_PROXY_IMAGE")]
+    egress_proxy_image: Option<String>,
+    
+    /// Port the egress proxy listens on; defaults to 3128
+    #[arg(long, env = "EGRESS_PROXY_PORT")]
+    egress_proxy_port: Option<u16>,
+    
+    /// Seccomp profile JSON for sandboxes, replacing the built-in one
+    #[arg(long, env = "SANDBOX_SECCOMP_PROFILE")]
+    sandbox_seccomp_profile: Option<PathBuf>,
+    
+    /// AppArmor profile, loaded on the docker host, for sandboxes
+    #[arg(long, env = "SANDBOX_APPARMOR_PROFILE")]
+    sandbox_apparmor_profile: Option<String>,
+    
+    /// GitHub app endpoint that submitted batches stream their coverage
+    /// to, e.g. http://platform:8080/badge/coverage; not reported when unset
+    #[arg(long, env = "BADGE_COVERAGE_ENDPOINT")]
+    badge_coverage_endpoint: Option<String>,
+    
+    /// Run only the egress proxy; this is how the farm starts it
+    #[arg(long)]
+    egress_proxy_only: bool,
 }
 
 #[tokio::main]
@@ -41,13 +166,38 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Initialize logging
     init_logging(&args.log_level, &args.log_format)?;
     
+    if args.egress_proxy_only {
+        let policy = EgressPolicy {
+            allowed_hosts: args.egress_allowed_hosts,
+            ..Default::default()
+        };
+        let port = args.egress_proxy_port.unwrap_or(policy.proxy_port);
+        EgressProxy::new(policy).serve(port).await?;
+        return Ok(());
+    }
+    
     info!("Starting Lean Farm Job Runner");
     info!("Version: {}", env!("CARGO_PKG_VERSION"));
     info!("Build timestamp: {}", env!("VERGEN_BUILD_TIMESTAMP"));
     
     // Load configuration
-    let config = Config::from_file(&args.config).await?;
+    let mut config = Config::from_file(&args.config).await?;
     info!("Configuration loaded from {:?}", args.config);
+    if !args.egress_allowed_hosts.is_empty() {
+        config.security.egress.allowed_hosts = args.egress_allowed_hosts.clone();
+    }
+    if let Some(image) = &args.egress_proxy_image {
+        config.security.egress.proxy_image = image.clone();
+    }
+    if let Some(port) = args.egress_proxy_port {
+        config.security.egress.proxy_port = port;
+    }
+    if let Some(path) = &args.sandbox_seccomp_profile {
+        config.security.sandbox_profiles.seccomp_profile = Some(path.clone());
+    }
+    if let Some(name) = &args.sandbox_apparmor_profile {
+        config.security.sandbox_profiles.apparmor_profile = Some(name.clone());
+    }
     
     // Initialize security manager
     let security_manager = SecurityManager::new(&config.security)?;
@@ -57,30 +207,148 @@ async fn main() -> Result<(), Box<dyn Error>> {
     security_manager.validate_environment().await?;
     info!("Security validation passed");
     
+    // Initialize job runner
+    let mut job_runner = JobRunner::new(config, security_manager).await?;
+    if let Some(key_id) = &args.client_side_encryption_kms_key_id {
+        let aws_config = aws_config::load_from_env().await;
+        job_runner = job_runner.with_envelope_encryption(
+            envelope::EnvelopeEncryptor::new(aws_sdk_kms::Client::new(&aws_config), key_id),
+        );
+        info!("Client-side encryption enabled with KMS key {}", key_id);
+    }
+    if let Some(path) = &args.resource_policy {
+        job_runner = job_runner.with_resource_policy(ResourcePolicy::from_file(path)?);
+        info!("Resource policy loaded from {:?}", path);
+    }
+    if let Some(schedule) = &args.reverification_schedule {
+        job_runner = job_runner.with_reverification(ReverificationConfig {
+            schedule: CronSchedule::parse(schedule)?,
+            sample_rate: args.reverification_sample_rate,
+            max_theorems: args.reverification_max_theorems,
+        });
+        info!("Re-verification of proven theorems scheduled at {:?}", schedule);
+    }
+    if let Some(schedule) = &args.retention_schedule {
+        job_runner = job_runner.with_retention(RetentionConfig {
+            schedule: CronSchedule::parse(schedule)?,
+            policy: RetentionPolicy {
+                keep_latest_per_invariant: args.retention_keep_latest,
+                failed_attempt_max_age_days: args.retention_failed_attempt_days,
+            },
+            dry_run: args.retention_dry_run,
+        });
+        info!("Artifact retention scheduled at {:?}", schedule);
+    }
+    if args.container_pool_size > 0 {
+        let defaults = PoolConfig::default();
+        job_runner = job_runner.with_container_pool(PoolConfig {
+            size: args.container_pool_size,
+            image: args.sandbox_image.clone().unwrap_or(defaults.image.clone()),
+            max_jobs_per_sandbox: args.container_pool_max_jobs,
+            max_sandbox_age: Duration::from_secs(args.container_pool_max_age_minutes * 60),
+            ..defaults
+        });
+        info!("Container pool enabled with {} warm sandboxes", args.container_pool_size);
+    }
+    if let Some(path) = &args.mathlib_cache_path {
+        let defaults = MathlibCacheConfig::default();
+        job_runner = job_runner.with_mathlib_cache(MathlibCacheConfig {
+            volume_path: path.clone(),
+            warm_image: args.mathlib_cache_image.clone().unwrap_or(defaults.warm_image.clone()),
+            object_sync: args.mathlib_cache_sync,
+            ..defaults
+        });
+        info!("Mathlib cache enabled at {:?}", path);
+    }
+    if let Some(endpoint) = &args.badge_coverage_endpoint {
+        job_runner = job_runner.with_badge_endpoint(endpoint);
+        info!("Streaming batch coverage to {}", endpoint);
+    }
+    if args.warm_mathlib_cache_only {
+        match job_runner.warm_mathlib_cache().await? {
+            Some(manifest) => info!(
+                "Mathlib cache for {} at {} is ready ({})",
+                manifest.lean_toolchain, manifest.mathlib_commit, manifest.source
+            ),
+            None => warn!("No Mathlib cache to warm; set --mathlib-cache-path and MATHLIB_COMMIT"),
+        }
+        return Ok(());
+    }
+    // Fail closed: without enforced profiles sandboxes would run unconfined
+    job_runner.validate_sandbox_profiles().await?;
+    info!("Sandbox security profiles validated");
+    let job_runner = Arc::new(job_runner);
+    info!("Job runner initialized");
+    
     // Initialize metrics server
-    let metrics_server = MetricsServer::new(args.metrics_port, args.metrics_path);
+    let metrics_server = MetricsServer::new(args.metrics_port, args.metrics_path)
+        .with_registry(job_runner.metrics_registry());
     let metrics_handle = tokio::spawn(metrics_server.start());
     info!("Metrics server started on port {}", args.metrics_port);
     
-    // Initialize job runner
-    let job_runner = JobRunner::new(config, security_manager).await?;
-    info!("Job runner initialized");
+    // Pick up jobs left behind by replicas that shut down mid-proof
+    job_runner.restore_checkpoints().await?;
     
     // Start health check server
-    let health_handle = tokio::spawn(job_runner.start_health_server());
+    let health_handle = tokio::spawn({
+        let job_runner = job_runner.clone();
+        async move { job_runner.start_health_server().await }
+    });
     info!("Health check server started");
     
+    // Serve scaling hints for external autoscalers and take batch
+    // submissions
+    let grpc_addr = std::net::SocketAddr::from(([0, 0, 0, 0], args.grpc_port));
+    let grpc_handle = tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(ScalingService::server(job_runner.clone()))
+            .serve(grpc_addr)
+    );
+    info!("Lean Farm API listening on {}", grpc_addr);
+    
     // Start job processing
-    let job_handle = tokio::spawn(job_runner.start_processing());
+    let job_handle = tokio::spawn({
+        let job_runner = job_runner.clone();
+        async move { job_runner.start_processing().await }
+    });
     info!("Job processing started");
     
+    // Re-check proven theorems on schedule; a no-op when not configured
+    let reverification_handle = tokio::spawn({
+        let job_runner = job_runner.clone();
+        async move {
+            if let Err(e) = job_runner.start_reverification_scheduler().await {
+                error!("Re-verification scheduler stopped: {}", e);
+            }
+        }
+    });
+    
+    // Prune stored proofs and failed attempts on schedule; a no-op when not
+    // configured
+    let retention_handle = tokio::spawn({
+        let job_runner = job_runner.clone();
+        async move {
+            if let Err(e) = job_runner.start_retention_scheduler().await {
+                error!("Retention scheduler stopped: {}", e);
+            }
+        }
+    });
+    
     // Wait for shutdown signal
     wait_for_shutdown().await;
     
     info!("Shutting down Lean Farm Job Runner");
     
-    // Graceful shutdown
-    job_handle.abort();
+    // Graceful shutdown: finish or checkpoint in-flight jobs
+    job_runner.drain(Duration::from_secs(args.drain_grace_period_seconds)).await;
+    
+    // Results of jobs that finished during the drain are still being stored
+    if tokio::time::timeout(Duration::from_secs(10), job_handle).await.is_err() {
+        warn!("Timed out storing final job results");
+    }
+    reverification_handle.abort();
+    retention_handle.abort();
+    grpc_handle.abort();
     health_handle.abort();
     metrics_handle.abort();
     
@@ -0,0 +1,404 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::storage::StorageManager;
+use crate::toolchain::Toolchain;
+use crate::LeanFarmError;
+
+// Most proof time goes to rebuilding Mathlib oleans. A volume shared by the
+// farm and the docker host keeps one built Mathlib per toolchain; every
+// sandbox mounts the farm's read-only and finds the oleans through
+// LEAN_PATH. A toolchain's directory is filled once, when the pinned
+// Mathlib commit first shows up: from a copy another replica synced to
+// object storage if there is one, otherwise with `lake exe cache get`.
+
+/// Written into a cache directory once it is complete; a directory
+/// without it is never mounted
+pub const READY_MARKER: &str = ".lean-farm-cache.json";
+
+/// Where sandboxes see the cache
+pub const SANDBOX_MOUNT: &str = "/opt/mathlib";
+
+const MATHLIB_REPOSITORY: &str = "https://github.com/leanprover-community/mathlib4";
+
+/// A warming lock older than this was left by a replica that died
+const STALE_LOCK_AGE: Duration = Duration::from_secs(2 * 60 * 60);
+
+const LOCK_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MathlibCacheConfig {
+    /// Directory holding one cache per toolchain, at the same path in the
+    /// farm's container and on the docker host
+    pub volume_path: PathBuf,
+    /// Image that runs `lake exe cache get`; needs git and network access
+    pub warm_image: String,
+    pub repository: String,
+    /// Share warmed caches through object storage, so only one replica
+    /// downloads and builds each Mathlib version
+    pub object_sync: bool,
+}
+
+impl Default for MathlibCacheConfig {
+    fn default() -> Self {
+        Self {
+            volume_path: PathBuf::from("/var/lib/lean-farm/mathlib-cache"),
+            warm_image: crate::pool::default_image(),
+            repository: MATHLIB_REPOSITORY.to_string(),
+            object_sync: false,
+        }
+    }
+}
+
+/// Contents of the ready marker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheManifest {
+    pub lean_toolchain: String,
+    pub mathlib_commit: String,
+    /// "upstream" or "object_storage"
+    pub source: String,
+    pub warmed_at: DateTime<Utc>,
+    /// Olean directories relative to the cache root, in LEAN_PATH order
+    pub lib_dirs: Vec<String>,
+}
+
+impl CacheManifest {
+    pub fn matches(&self, toolchain: &Toolchain) -> bool {
+        self.lean_toolchain == toolchain.lean_toolchain && self.mathlib_commit == toolchain.mathlib_commit
+    }
+
+    /// `docker run` arguments mounting the cache into a sandbox
+    pub fn sandbox_args(&self, cache_dir: &Path) -> Vec<String> {
+        vec![
+            format!("--volume={}:{}:ro", cache_dir.display(), SANDBOX_MOUNT),
+            format!("--env=LEAN_PATH={}", lean_path(SANDBOX_MOUNT, &self.lib_dirs)),
+        ]
+    }
+}
+
+pub fn lean_path(mount: &str, lib_dirs: &[String]) -> String {
+    lib_dirs
+        .iter()
+        .map(|dir| format!("{}/{}", mount, dir))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Mathlib's own build output first, then each dependency's
+pub fn lib_dirs(root: &Path) -> Vec<String> {
+    let mut dirs = Vec::new();
+    if root.join(".lake/build/lib").is_dir() {
+        dirs.push(".lake/build/lib".to_string());
+    }
+    let mut packages: Vec<String> = std::fs::read_dir(root.join(".lake/packages"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| root.join(".lake/packages").join(name).join(".lake/build/lib").is_dir())
+        .collect();
+    packages.sort();
+    dirs.extend(packages.into_iter().map(|name| format!(".lake/packages/{}/.lake/build/lib", name)));
+    dirs
+}
+
+/// Shell script cloning Mathlib at `commit` into the current directory and
+/// downloading its prebuilt oleans
+pub fn warm_script(repository: &str, commit: &str) -> String {
+    format!(
+        "git init -q . && git remote add origin {repository} && git fetch -q --depth 1 origin {commit} \
+         && git checkout -q FETCH_HEAD && lake exe cache get",
+        repository = repository,
+        commit = commit
+    )
+}
+
+/// The Mathlib cache for the farm's toolchain
+#[derive(Debug, Clone)]
+pub struct MathlibCache {
+    config: MathlibCacheConfig,
+    toolchain: Toolchain,
+    /// `<minio key_prefix>/mathlib-cache/<toolchain key>`, when synced
+    object_prefix: Option<String>,
+}
+
+impl MathlibCache {
+    pub fn new(config: MathlibCacheConfig, toolchain: &Toolchain, artifact_prefix: &str) -> Self {
+        let object_prefix = config
+            .object_sync
+            .then(|| format!("{}/mathlib-cache/{}", artifact_prefix, toolchain.key()));
+        Self {
+            config,
+            toolchain: toolchain.clone(),
+            object_prefix,
+        }
+    }
+
+    /// Farms that don't pin Mathlib have nothing to cache
+    pub fn applies(&self) -> bool {
+        !self.toolchain.mathlib_commit.is_empty()
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        self.config.volume_path.join(self.toolchain.key())
+    }
+
+    /// The cache's manifest, if it is complete and for this toolchain
+    pub async fn manifest(&self) -> Option<CacheManifest> {
+        let bytes = tokio::fs::read(self.dir().join(READY_MARKER)).await.ok()?;
+        serde_json::from_slice::<CacheManifest>(&bytes)
+            .ok()
+            .filter(|manifest| manifest.matches(&self.toolchain))
+    }
+
+    /// Fills the cache unless it is already complete, returning its
+    /// manifest. Replicas sharing the volume take turns: one warms while
+    /// the others wait for its marker.
+    pub async fn ensure(&self, storage: &StorageManager) -> Result<CacheManifest, LeanFarmError> {
+        loop {
+            if let Some(manifest) = self.manifest().await {
+                return Ok(manifest);
+            }
+            if self.try_lock().await? {
+                let result = self.warm(storage).await;
+                let _ = tokio::fs::remove_file(self.lock_path()).await;
+                return result;
+            }
+            info!("Mathlib cache {} is being warmed elsewhere, waiting", self.toolchain.key());
+            tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn warm(&self, storage: &StorageManager) -> Result<CacheManifest, LeanFarmError> {
+        let dir = self.dir();
+        tokio::fs::create_dir_all(&dir).await.map_err(|e| io_error(&dir, e))?;
+
+        if let Some(manifest) = self.download(storage).await? {
+            info!("Mathlib cache {} synced from object storage", self.toolchain.key());
+            return Ok(manifest);
+        }
+
+        info!("Warming Mathlib cache {} with lake exe cache get", self.toolchain.key());
+        let output = tokio::process::Command::new("docker")
+            .args([
+                "run".to_string(),
+                "--rm".to_string(),
+                "--user=1000:1000".to_string(),
+                format!("--volume={}:/cache", dir.display()),
+                "--workdir=/cache".to_string(),
+                self.config.warm_image.clone(),
+                "sh".to_string(),
+                "-c".to_string(),
+                warm_script(&self.config.repository, &self.toolchain.mathlib_commit),
+            ])
+            .output()
+            .await
+            .map_err(|e| LeanFarmError::JobExecution(format!("failed to run docker: {}", e)))?;
+        if !output.status.success() {
+            return Err(LeanFarmError::JobExecution(format!(
+                "Mathlib cache warming failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let manifest = CacheManifest {
+            lean_toolchain: self.toolchain.lean_toolchain.clone(),
+            mathlib_commit: self.toolchain.mathlib_commit.clone(),
+            source: "upstream".to_string(),
+            warmed_at: Utc::now(),
+            lib_dirs: lib_dirs(&dir),
+        };
+        if let Err(e) = self.upload(storage, &manifest).await {
+            warn!("Failed to sync Mathlib cache {} to object storage: {}", self.toolchain.key(), e);
+        }
+        self.write_marker(&manifest).await?;
+        Ok(manifest)
+    }
+
+    // Copies another replica's cache, or returns None when none has been
+    // synced for this toolchain
+    async fn download(&self, storage: &StorageManager) -> Result<Option<CacheManifest>, LeanFarmError> {
+        let Some(prefix) = &self.object_prefix else {
+            return Ok(None);
+        };
+        let marker_key = format!("{}/{}", prefix, READY_MARKER);
+        let keys = storage.list_minio_keys(&format!("{}/", prefix)).await?;
+        if !keys.contains(&marker_key) {
+            return Ok(None);
+        }
+
+        let dir = self.dir();
+        for key in keys.iter().filter(|key| **key != marker_key) {
+            let relative = &key[prefix.len() + 1..];
+            let path = dir.join(relative);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await.map_err(|e| io_error(parent, e))?;
+            }
+            let (bytes, _) = storage.download_from_minio_with_metadata(key).await?;
+            tokio::fs::write(&path, bytes).await.map_err(|e| io_error(&path, e))?;
+        }
+
+        let (bytes, _) = storage.download_from_minio_with_metadata(&marker_key).await?;
+        let mut manifest: CacheManifest = serde_json::from_slice(&bytes)
+            .map_err(|e| LeanFarmError::Storage(format!("invalid Mathlib cache manifest {}: {}", marker_key, e)))?;
+        manifest.source = "object_storage".to_string();
+        self.write_marker(&manifest).await?;
+        Ok(Some(manifest))
+    }
+
+    // Uploads the cache with its marker last, so a partial upload is never
+    // picked up
+    async fn upload(&self, storage: &StorageManager, manifest: &CacheManifest) -> Result<(), LeanFarmError> {
+        let Some(prefix) = &self.object_prefix else {
+            return Ok(());
+        };
+        let dir = self.dir();
+        let files = tokio::task::spawn_blocking({
+            let dir = dir.clone();
+            move || cache_files(&dir)
+        })
+        .await
+        .map_err(|e| LeanFarmError::Storage(e.to_string()))?;
+
+        for relative in &files {
+            let path = dir.join(relative);
+            let bytes = tokio::fs::read(&path).await.map_err(|e| io_error(&path, e))?;
+            storage.upload_to_minio(&format!("{}/{}", prefix, relative), &bytes).await?;
+        }
+        let marker = serde_json::to_vec(manifest).map_err(|e| LeanFarmError::Storage(e.to_string()))?;
+        storage.upload_to_minio(&format!("{}/{}", prefix, READY_MARKER), &marker).await?;
+        info!("Synced {} Mathlib cache files to {}", files.len(), prefix);
+        Ok(())
+    }
+
+    async fn write_marker(&self, manifest: &CacheManifest) -> Result<(), LeanFarmError> {
+        let path = self.dir().join(READY_MARKER);
+        let bytes = serde_json::to_vec_pretty(manifest).map_err(|e| LeanFarmError::Storage(e.to_string()))?;
+        tokio::fs::write(&path, bytes).await.map_err(|e| io_error(&path, e))
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.config.volume_path.join(format!("{}.lock", self.toolchain.key()))
+    }
+
+    async fn try_lock(&self) -> Result<bool, LeanFarmError> {
+        let path = self.lock_path();
+        tokio::fs::create_dir_all(&self.config.volume_path)
+            .await
+            .map_err(|e| io_error(&self.config.volume_path, e))?;
+
+        let stale = tokio::fs::metadata(&path)
+            .await
+            .ok()
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > STALE_LOCK_AGE);
+        if stale {
+            warn!("Removing stale Mathlib cache lock {}", path.display());
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+
+        match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(io_error(&path, e)),
+        }
+    }
+}
+
+// Everything but git metadata and the marker, relative to the cache root
+fn cache_files(root: &Path) -> Vec<String> {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .flatten()
+        .filter(|entry| entry.file_type().is_file() && entry.file_name() != READY_MARKER)
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some(relative.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> LeanFarmError {
+    LeanFarmError::Storage(format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toolchain(mathlib_commit: &str) -> Toolchain {
+        Toolchain {
+            lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+            mathlib_commit: mathlib_commit.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_lib_dirs_and_sandbox_args() {
+        let root = std::env::temp_dir().join(format!("mathlib-cache-{}", uuid::Uuid::new_v4()));
+        for dir in [
+            ".lake/build/lib/Mathlib",
+            ".lake/packages/std/.lake/build/lib",
+            ".lake/packages/aesop/.lake/build/lib",
+            ".lake/packages/proofwidgets/src",
+            ".git/objects",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join(".lake/build/lib/Mathlib/Basic.olean"), b"olean").unwrap();
+        std::fs::write(root.join(".git/HEAD"), b"ref").unwrap();
+        std::fs::write(root.join(READY_MARKER), b"{}").unwrap();
+
+        let dirs = lib_dirs(&root);
+        assert_eq!(
+            dirs,
+            [
+                ".lake/build/lib",
+                ".lake/packages/aesop/.lake/build/lib",
+                ".lake/packages/std/.lake/build/lib",
+            ]
+        );
+        assert_eq!(cache_files(&root), [".lake/build/lib/Mathlib/Basic.olean"]);
+
+        let manifest = CacheManifest {
+            lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+            mathlib_commit: "3f1c2a9b4d5e6f70".to_string(),
+            source: "upstream".to_string(),
+            warmed_at: Utc::now(),
+            lib_dirs: dirs,
+        };
+        assert!(manifest.matches(&toolchain("3f1c2a9b4d5e6f70")));
+        assert!(!manifest.matches(&toolchain("0a1b2c3d4e5f6a7b")));
+        assert_eq!(
+            manifest.sandbox_args(Path::new("/var/lib/lean-farm/mathlib-cache/v4.7.0-mathlib-3f1c2a9b4d5e")),
+            [
+                "--volume=/var/lib/lean-farm/mathlib-cache/v4.7.0-mathlib-3f1c2a9b4d5e:/opt/mathlib:ro",
+                "--env=LEAN_PATH=/opt/mathlib/.lake/build/lib:/opt/mathlib/.lake/packages/aesop/.lake/build/lib:\
+                 /opt/mathlib/.lake/packages/std/.lake/build/lib",
+            ]
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_cache_is_per_toolchain() {
+        let cache = MathlibCache::new(
+            MathlibCacheConfig {
+                object_sync: true,
+                ..Default::default()
+            },
+            &toolchain("3f1c2a9b4d5e6f70"),
+            "farm",
+        );
+        assert!(cache.applies());
+        assert_eq!(cache.dir(), Path::new("/var/lib/lean-farm/mathlib-cache/v4.7.0-mathlib-3f1c2a9b4d5e"));
+        assert_eq!(cache.object_prefix.as_deref(), Some("farm/mathlib-cache/v4.7.0-mathlib-3f1c2a9b4d5e"));
+        assert!(!MathlibCache::new(MathlibCacheConfig::default(), &toolchain(""), "farm").applies());
+        assert!(warm_script(MATHLIB_REPOSITORY, "3f1c2a9b").contains("fetch -q --depth 1 origin 3f1c2a9b"));
+    }
+}
@@ -0,0 +1,262 @@
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use prometheus::{Encoder, Gauge, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+use serde::Serialize;
+use tracing::info;
+
+use crate::JobPriority;
+
+// Replicas each keep their own queue, so the scaling signals describe this
+// replica's load. `scaling_ratio` is the one to autoscale on: averaged over
+// pods with a target of 1, an HPA `Pods` metric sizes the farm so every
+// replica runs at its target concurrency.
+
+/// Weight of the latest job in the moving average of job durations
+const DURATION_SMOOTHING: f64 = 0.2;
+
+#[derive(Debug, Clone)]
+pub struct ScalingPolicy {
+    /// Fraction of workers meant to be busy at steady state, leaving
+    /// headroom for bursts
+    pub target_utilization: f64,
+    /// How quickly the queued backlog should be cleared
+    pub target_drain_time: Duration,
+}
+
+impl Default for ScalingPolicy {
+    fn default() -> Self {
+        Self {
+            target_utilization: 0.8,
+            target_drain_time: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Workers needed to keep running jobs going and clear `queue_depth`
+/// queued jobs within the policy's drain time at its target utilization.
+/// Until a job has finished there is no duration estimate, and every
+/// queued job counts as a worker.
+pub fn target_concurrency(
+    queue_depth: usize,
+    running_jobs: usize,
+    avg_job_duration: Option<Duration>,
+    policy: &ScalingPolicy,
+) -> usize {
+    let backlog_workers = match avg_job_duration {
+        Some(duration) if !policy.target_drain_time.is_zero() => {
+            queue_depth as f64 * duration.as_secs_f64() / policy.target_drain_time.as_secs_f64()
+        }
+        _ => queue_depth as f64,
+    };
+    let utilization = policy.target_utilization.clamp(0.01, 1.0);
+    ((running_jobs as f64 + backlog_workers) / utilization).ceil() as usize
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScalingHints {
+    pub queue_depth: usize,
+    /// Queued jobs by submitted priority, indexed by `JobPriority`
+    pub backlog_by_priority: [usize; 4],
+    pub running_jobs: usize,
+    pub worker_count: usize,
+    pub avg_job_duration_ms: Option<u64>,
+    pub worker_utilization: f64,
+    pub target_concurrency: usize,
+    /// Target concurrency over this replica's workers; above 1 the farm
+    /// needs more replicas, below 1 it can shrink
+    pub scaling_ratio: f64,
+}
+
+impl ScalingHints {
+    pub fn compute(
+        backlog_by_priority: [usize; 4],
+        running_jobs: usize,
+        worker_count: usize,
+        avg_job_duration: Option<Duration>,
+        policy: &ScalingPolicy,
+    ) -> Self {
+        let queue_depth = backlog_by_priority.iter().sum();
+        let target_concurrency = target_concurrency(queue_depth, running_jobs, avg_job_duration, policy);
+        let workers = worker_count.max(1) as f64;
+        Self {
+            queue_depth,
+            backlog_by_priority,
+            running_jobs,
+            worker_count,
+            avg_job_duration_ms: avg_job_duration.map(|duration| duration.as_millis() as u64),
+            worker_utilization: (running_jobs as f64 / workers).min(1.0),
+            target_concurrency,
+            scaling_ratio: target_concurrency as f64 / workers,
+        }
+    }
+
+    pub fn backlog(&self, priority: JobPriority) -> usize {
+        self.backlog_by_priority[priority as usize]
+    }
+}
+
+/// Prometheus gauges for the scaling signals, plus the moving average of
+/// job durations they are computed from
+#[derive(Debug)]
+pub struct ScalingMetrics {
+    registry: Registry,
+    queue_depth: IntGauge,
+    backlog: IntGaugeVec,
+    running_jobs: IntGauge,
+    avg_job_duration: Gauge,
+    worker_utilization: Gauge,
+    target_concurrency: IntGauge,
+    scaling_ratio: Gauge,
+    avg_duration_secs: Mutex<Option<f64>>,
+}
+
+impl ScalingMetrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+        let metrics = Self {
+            queue_depth: IntGauge::new("lean_farm_queue_size", "Jobs waiting in the queue")?,
+            backlog: IntGaugeVec::new(
+                Opts::new("lean_farm_queue_backlog", "Jobs waiting in the queue by priority"),
+                &["priority"],
+            )?,
+            running_jobs: IntGauge::new("lean_farm_active_workers", "Workers currently running a job")?,
+            avg_job_duration: Gauge::new(
+                "lean_farm_job_duration_avg_seconds",
+                "Moving average of job duration",
+            )?,
+            worker_utilization: Gauge::new("lean_farm_worker_utilization", "Fraction of workers busy")?,
+            target_concurrency: IntGauge::new(
+                "lean_farm_target_concurrency",
+                "Workers needed to run current jobs and clear the backlog in time",
+            )?,
+            scaling_ratio: Gauge::new(
+                "lean_farm_scaling_ratio",
+                "Target concurrency over worker count; autoscale to keep this at 1",
+            )?,
+            avg_duration_secs: Mutex::new(None),
+            registry,
+        };
+
+        metrics.registry.register(Box::new(metrics.queue_depth.clone()))?;
+        metrics.registry.register(Box::new(metrics.backlog.clone()))?;
+        metrics.registry.register(Box::new(metrics.running_jobs.clone()))?;
+        metrics.registry.register(Box::new(metrics.avg_job_duration.clone()))?;
+        metrics.registry.register(Box::new(metrics.worker_utilization.clone()))?;
+        metrics.registry.register(Box::new(metrics.target_concurrency.clone()))?;
+        metrics.registry.register(Box::new(metrics.scaling_ratio.clone()))?;
+        Ok(metrics)
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn observe_job_duration(&self, duration: Duration) {
+        let mut average = self.avg_duration_secs.lock().unwrap();
+        let secs = duration.as_secs_f64();
+        *average = Some(match *average {
+            Some(average) => average + DURATION_SMOOTHING * (secs - average),
+            None => secs,
+        });
+    }
+
+    pub fn average_job_duration(&self) -> Option<Duration> {
+        self.avg_duration_secs.lock().unwrap().map(Duration::from_secs_f64)
+    }
+
+    pub fn update(&self, hints: &ScalingHints) {
+        self.queue_depth.set(hints.queue_depth as i64);
+        for priority in JobPriority::ALL {
+            self.backlog
+                .with_label_values(&[priority.as_str()])
+                .set(hints.backlog(priority) as i64);
+        }
+        self.running_jobs.set(hints.running_jobs as i64);
+        self.avg_job_duration.set(hints.avg_job_duration_ms.unwrap_or(0) as f64 / 1000.0);
+        self.worker_utilization.set(hints.worker_utilization);
+        self.target_concurrency.set(hints.target_concurrency as i64);
+        self.scaling_ratio.set(hints.scaling_ratio);
+    }
+}
+
+/// Serves a Prometheus registry in the text exposition format
+#[derive(Debug, Clone)]
+pub struct MetricsServer {
+    port: u16,
+    path: String,
+    registry: Registry,
+}
+
+impl MetricsServer {
+    pub fn new(port: u16, path: String) -> Self {
+        Self {
+            port,
+            path,
+            registry: Registry::new(),
+        }
+    }
+
+    pub fn with_registry(mut self, registry: Registry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    pub async fn start(self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        use axum::{routing::get, Router};
+
+        let registry = self.registry;
+        let app = Router::new().route(&self.path, get(move || {
+            let registry = registry.clone();
+            async move {
+                let mut buffer = Vec::new();
+                TextEncoder::new()
+                    .encode(&registry.gather(), &mut buffer)
+                    .map(|_| String::from_utf8_lossy(&buffer).into_owned())
+                    .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            }
+        }));
+
+        let addr = SocketAddr::from(([0, 0, 0, 0], self.port));
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        info!("Metrics server listening on {}{}", addr, self.path);
+        axum::serve(listener, app).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_concurrency() {
+        let policy = ScalingPolicy::default();
+        assert_eq!(target_concurrency(0, 0, None, &policy), 0);
+        // 8 running at 80% utilization
+        assert_eq!(target_concurrency(0, 8, None, &policy), 10);
+        // 100 jobs of 60s each cleared in 300s need 20 workers, 25 at 80%
+        assert_eq!(target_concurrency(100, 0, Some(Duration::from_secs(60)), &policy), 25);
+        // No duration estimate yet
+        assert_eq!(target_concurrency(4, 0, None, &policy), 5);
+    }
+
+    #[test]
+    fn test_scaling_hints() {
+        let hints = ScalingHints::compute([10, 20, 0, 2], 5, 10, Some(Duration::from_secs(30)), &ScalingPolicy::default());
+        assert_eq!(hints.queue_depth, 32);
+        assert_eq!(hints.backlog(JobPriority::Normal), 20);
+        assert_eq!(hints.worker_utilization, 0.5);
+        // (5 + 32 * 30 / 300) / 0.8 = 10.25
+        assert_eq!(hints.target_concurrency, 11);
+        assert!((hints.scaling_ratio - 1.1).abs() < 1e-9);
+
+        let metrics = ScalingMetrics::new().unwrap();
+        metrics.observe_job_duration(Duration::from_secs(10));
+        metrics.observe_job_duration(Duration::from_secs(20));
+        assert_eq!(metrics.average_job_duration(), Some(Duration::from_secs(12)));
+        metrics.update(&hints);
+        assert_eq!(metrics.registry().gather().len(), 7);
+    }
+}
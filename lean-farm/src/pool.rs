@@ -0,0 +1,508 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use prometheus::{Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::resources::ResourceLimits;
+use crate::LeanFarmError;
+
+// Starting a Lean container per job costs tens of seconds before any proof
+// runs. The pool keeps warm sandboxes from an image with Mathlib already
+// built, leases one per job and, after a job that ran to completion,
+// empties its writable mounts and puts it back. Sandboxes whose job timed
+// out, errored or was preempted may still have processes running and are
+// never reused.
+
+/// The only writable paths in a sandbox; everything else is read-only
+pub const WRITABLE_MOUNTS: [&str; 2] = ["/tmp", "/var/lean-farm"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolConfig {
+    /// Idle sandboxes kept warm; 0 starts a fresh container for every job
+    pub size: usize,
+    pub image: String,
+    /// Jobs a sandbox runs before it is replaced
+    pub max_jobs_per_sandbox: u32,
+    /// Sandboxes older than this are replaced rather than leased
+    pub max_sandbox_age: Duration,
+    /// Run in each new sandbox before it joins the pool, to load the
+    /// elan toolchain and Mathlib oleans into the page cache
+    pub warm_command: Vec<String>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            size: 0,
+            image: default_image(),
+            max_jobs_per_sandbox: 50,
+            max_sandbox_age: Duration::from_secs(6 * 60 * 60),
+            warm_command: vec!["lake".to_string(), "env".to_string(), "lean".to_string(), "--version".to_string()],
+        }
+    }
+}
+
+pub fn default_image() -> String {
+    let lean_version = std::env::var("LEAN_VERSION").unwrap_or_else(|_| "4.7.0".to_string());
+    format!("leanprover/lean4:{}", lean_version)
+}
+
+/// `docker run` arguments for a sandbox: no network, a read-only root and
+/// small non-executable scratch mounts, plus `extra` arguments such as the
+/// security profiles and mounts
+pub fn create_args(image: &str, name: &str, extra: &[String]) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "run",
+        "--rm",
+        "--detach",
+        "--read-only",
+        "--tmpfs=/tmp:rw,noexec,nosuid,size=1g",
+        "--tmpfs=/var/lean-farm:rw,noexec,nosuid,size=2g",
+        "--user=1000:1000",
+        "--network=none",
+        "--label=lean-farm.pool=true",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    args.extend(extra.iter().cloned());
+    args.extend(["--name".to_string(), name.to_string()]);
+    args.extend([image.to_string(), "sleep".to_string(), "infinity".to_string()]);
+    args
+}
+
+/// `docker update` arguments giving a leased sandbox the job's limits. Swap
+/// is pinned to the memory limit, since docker refuses a memory limit
+/// above the container's current swap limit.
+pub fn update_args(container_id: &str, limits: &ResourceLimits) -> Vec<String> {
+    let mut args = vec!["update".to_string()];
+    args.extend(limits.docker_args());
+    args.push(format!("--memory-swap={}m", limits.memory_mb));
+    args.push(container_id.to_string());
+    args
+}
+
+fn reset_script() -> String {
+    let mounts = WRITABLE_MOUNTS.join(" ");
+    format!(
+        "find {mounts} -mindepth 1 -delete && [ -z \"$(find {mounts} -mindepth 1 | head -n 1)\" ]",
+        mounts = mounts
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sandbox {
+    pub container_id: String,
+    pub jobs_run: u32,
+    pub created_at: Instant,
+    /// Whether the sandbox came from the pool rather than being started for
+    /// the job
+    pub warm: bool,
+}
+
+/// How a leased sandbox's job ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobOutcome {
+    /// The job's commands all ran to completion, whether or not the proof
+    /// checked
+    Completed,
+    /// Timed out, preempted, or a docker command failed
+    Aborted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposal {
+    Recycle,
+    Discard(&'static str),
+}
+
+impl PoolConfig {
+    pub fn enabled(&self) -> bool {
+        self.size > 0
+    }
+
+    /// Whether a returned sandbox goes back to the pool. `idle` is how many
+    /// sandboxes are already waiting.
+    pub fn disposal(&self, sandbox: &Sandbox, outcome: JobOutcome, idle: usize, now: Instant) -> Disposal {
+        if outcome == JobOutcome::Aborted {
+            Disposal::Discard("aborted")
+        } else if !self.enabled() || idle >= self.size {
+            Disposal::Discard("pool_full")
+        } else if sandbox.jobs_run >= self.max_jobs_per_sandbox {
+            Disposal::Discard("max_jobs")
+        } else if self.expired(sandbox, now) {
+            Disposal::Discard("max_age")
+        } else {
+            Disposal::Recycle
+        }
+    }
+
+    fn expired(&self, sandbox: &Sandbox, now: Instant) -> bool {
+        now.saturating_duration_since(sandbox.created_at) >= self.max_sandbox_age
+    }
+}
+
+/// Pool gauges and counters, registered with the scaling registry
+#[derive(Debug, Clone)]
+pub struct PoolMetrics {
+    idle: IntGauge,
+    leased: IntGauge,
+    leases: IntCounterVec,
+    discarded: IntCounterVec,
+    recycled: IntCounterVec,
+    lease_seconds: Histogram,
+}
+
+impl PoolMetrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let metrics = Self {
+            idle: IntGauge::new("lean_farm_pool_idle_sandboxes", "Warm sandboxes waiting for a job")?,
+            leased: IntGauge::new("lean_farm_pool_leased_sandboxes", "Sandboxes currently running a job")?,
+            leases: IntCounterVec::new(
+                Opts::new("lean_farm_pool_leases_total", "Sandboxes leased to jobs, warm or started cold"),
+                &["source"],
+            )?,
+            discarded: IntCounterVec::new(
+                Opts::new("lean_farm_pool_discarded_total", "Sandboxes removed instead of reused, by reason"),
+                &["reason"],
+            )?,
+            recycled: IntCounterVec::new(
+                Opts::new("lean_farm_pool_recycled_total", "Sandboxes reset after a job, by result"),
+                &["result"],
+            )?,
+            lease_seconds: Histogram::with_opts(
+                HistogramOpts::new("lean_farm_pool_lease_seconds", "Time to hand a job a ready sandbox")
+                    .buckets(vec![0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 120.0]),
+            )?,
+        };
+        registry.register(Box::new(metrics.idle.clone()))?;
+        registry.register(Box::new(metrics.leased.clone()))?;
+        registry.register(Box::new(metrics.leases.clone()))?;
+        registry.register(Box::new(metrics.discarded.clone()))?;
+        registry.register(Box::new(metrics.recycled.clone()))?;
+        registry.register(Box::new(metrics.lease_seconds.clone()))?;
+        Ok(metrics)
+    }
+}
+
+/// Warm Lean sandboxes leased to jobs
+#[derive(Debug)]
+pub struct ContainerPool {
+    config: PoolConfig,
+    idle: Mutex<VecDeque<Sandbox>>,
+    /// Seccomp and AppArmor options every sandbox starts with
+    security_opts: Vec<String>,
+    /// `docker run` arguments added to every new sandbox, e.g. the Mathlib
+    /// cache mount
+    extra_args: Mutex<Vec<String>>,
+    metrics: PoolMetrics,
+}
+
+impl ContainerPool {
+    pub fn new(config: PoolConfig, metrics: PoolMetrics) -> Self {
+        Self {
+            config,
+            idle: Mutex::new(VecDeque::new()),
+            security_opts: Vec::new(),
+            extra_args: Mutex::new(Vec::new()),
+            metrics,
+        }
+    }
+
+    pub fn with_security_opts(mut self, security_opts: Vec<String>) -> Self {
+        self.security_opts = security_opts;
+        self
+    }
+
+    pub fn security_opts(&self) -> &[String] {
+        &self.security_opts
+    }
+
+    pub fn config(&self) -> &PoolConfig {
+        &self.config
+    }
+
+    pub fn metrics(&self) -> &PoolMetrics {
+        &self.metrics
+    }
+
+    pub async fn idle_count(&self) -> usize {
+        self.idle.lock().await.len()
+    }
+
+    /// Pulls the image so neither warming nor a cold lease waits on the
+    /// registry
+    pub async fn prepull(&self) -> Result<(), LeanFarmError> {
+        info!("Pulling sandbox image {}", self.config.image);
+        docker(&["pull".to_string(), self.config.image.clone()]).await?;
+        Ok(())
+    }
+
+    /// Starts sandboxes until the pool holds its configured number of idle
+    /// ones
+    pub async fn replenish(&self) -> Result<usize, LeanFarmError> {
+        let mut started = 0;
+        while self.idle_count().await < self.config.size {
+            let sandbox = self.start(true).await?;
+            let mut idle = self.idle.lock().await;
+            idle.push_back(sandbox);
+            self.metrics.idle.set(idle.len() as i64);
+            started += 1;
+        }
+        Ok(started)
+    }
+
+    /// A sandbox limited to the job's CPUs and memory: a warm one when the
+    /// pool has one, otherwise a new container
+    pub async fn lease(self: &Arc<Self>, limits: &ResourceLimits) -> Result<Lease, LeanFarmError> {
+        let started = Instant::now();
+        let mut sandbox = loop {
+            let next = {
+                let mut idle = self.idle.lock().await;
+                let next = idle.pop_front();
+                self.metrics.idle.set(idle.len() as i64);
+                next
+            };
+            match next {
+                Some(sandbox) if self.config.expired(&sandbox, Instant::now()) => {
+                    self.discard(&sandbox, "max_age").await;
+                }
+                Some(sandbox) => break sandbox,
+                None => break self.start(false).await?,
+            }
+        };
+
+        if let Err(e) = docker(&update_args(&sandbox.container_id, limits)).await {
+            self.discard(&sandbox, "update_failed").await;
+            return Err(e);
+        }
+        sandbox.jobs_run += 1;
+
+        let source = if sandbox.warm { "warm" } else { "cold" };
+        self.metrics.leases.with_label_values(&[source]).inc();
+        self.metrics.leased.inc();
+        self.metrics.lease_seconds.observe(started.elapsed().as_secs_f64());
+        Ok(Lease {
+            pool: self.clone(),
+            sandbox: Some(sandbox),
+        })
+    }
+
+    async fn release(&self, sandbox: Sandbox, outcome: JobOutcome) {
+        self.metrics.leased.dec();
+        let idle = self.idle_count().await;
+        match self.config.disposal(&sandbox, outcome, idle, Instant::now()) {
+            Disposal::Discard(reason) => self.discard(&sandbox, reason).await,
+            Disposal::Recycle => match self.reset(&sandbox).await {
+                Ok(()) => {
+                    self.metrics.recycled.with_label_values(&["reset"]).inc();
+                    let mut idle = self.idle.lock().await;
+                    idle.push_back(Sandbox { warm: true, ..sandbox });
+                    self.metrics.idle.set(idle.len() as i64);
+                }
+                Err(e) => {
+                    warn!("Failed to reset sandbox {}: {}", sandbox.container_id, e);
+                    self.metrics.recycled.with_label_values(&["reset_failed"]).inc();
+                    self.discard(&sandbox, "reset_failed").await;
+                }
+            },
+        }
+    }
+
+    /// Adds `args` to sandboxes started from now on. Idle sandboxes were
+    /// started without them, so they are removed.
+    pub async fn set_extra_args(&self, args: Vec<String>) {
+        {
+            let mut extra_args = self.extra_args.lock().await;
+            if *extra_args == args {
+                return;
+            }
+            *extra_args = args;
+        }
+        self.remove_idle("reconfigured").await;
+    }
+
+    /// Removes every idle sandbox, for shutdown
+    pub async fn clear(&self) -> usize {
+        self.remove_idle("shutdown").await
+    }
+
+    async fn remove_idle(&self, reason: &str) -> usize {
+        let sandboxes: Vec<Sandbox> = {
+            let mut idle = self.idle.lock().await;
+            self.metrics.idle.set(0);
+            idle.drain(..).collect()
+        };
+        for sandbox in &sandboxes {
+            self.discard(sandbox, reason).await;
+        }
+        sandboxes.len()
+    }
+
+    async fn start(&self, warm: bool) -> Result<Sandbox, LeanFarmError> {
+        let name = format!("lean-farm-{}", uuid::Uuid::new_v4());
+        let mut extra_args = self.security_opts.clone();
+        extra_args.extend(self.extra_args.lock().await.iter().cloned());
+        let output = docker(&create_args(&self.config.image, &name, &extra_args)).await?;
+        let sandbox = Sandbox {
+            container_id: output.trim().to_string(),
+            jobs_run: 0,
+            created_at: Instant::now(),
+            warm,
+        };
+
+        if warm && !self.config.warm_command.is_empty() {
+            let mut args = vec!["exec".to_string(), sandbox.container_id.clone()];
+            args.extend(self.config.warm_command.iter().cloned());
+            if let Err(e) = docker(&args).await {
+                self.discard(&sandbox, "warm_failed").await;
+                return Err(e);
+            }
+        }
+
+        info!("Started {} sandbox {}", if warm { "warm" } else { "cold" }, sandbox.container_id);
+        Ok(sandbox)
+    }
+
+    // Empties the writable mounts and checks nothing is left behind; the
+    // rest of the filesystem is read-only
+    async fn reset(&self, sandbox: &Sandbox) -> Result<(), LeanFarmError> {
+        docker(&[
+            "exec".to_string(),
+            sandbox.container_id.clone(),
+            "sh".to_string(),
+            "-c".to_string(),
+            reset_script(),
+        ])
+        .await?;
+        Ok(())
+    }
+
+    async fn discard(&self, sandbox: &Sandbox, reason: &str) {
+        self.metrics.discarded.with_label_values(&[reason]).inc();
+        // The container runs with --rm, so a failed removal means it is
+        // already gone
+        let _ = docker(&["rm".to_string(), "--force".to_string(), sandbox.container_id.clone()]).await;
+    }
+}
+
+/// A sandbox leased to one job. Dropping the lease without releasing it,
+/// as happens when the job times out or is preempted, removes the sandbox.
+#[derive(Debug)]
+pub struct Lease {
+    pool: Arc<ContainerPool>,
+    sandbox: Option<Sandbox>,
+}
+
+impl Lease {
+    pub fn container_id(&self) -> &str {
+        self.sandbox.as_ref().map(|sandbox| sandbox.container_id.as_str()).unwrap_or_default()
+    }
+
+    /// Hands the sandbox back, to be reset for the next job or removed
+    pub async fn release(mut self, outcome: JobOutcome) {
+        if let Some(sandbox) = self.sandbox.take() {
+            self.pool.release(sandbox, outcome).await;
+        }
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if let Some(sandbox) = self.sandbox.take() {
+            let pool = self.pool.clone();
+            tokio::spawn(async move { pool.release(sandbox, JobOutcome::Aborted).await });
+        }
+    }
+}
+
+async fn docker(args: &[String]) -> Result<String, LeanFarmError> {
+    let output = tokio::process::Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| LeanFarmError::JobExecution(format!("failed to run docker: {}", e)))?;
+    if !output.status.success() {
+        return Err(LeanFarmError::JobExecution(format!(
+            "docker {} failed: {}",
+            args.first().map(String::as_str).unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox(jobs_run: u32, age: Duration, now: Instant) -> Sandbox {
+        Sandbox {
+            container_id: "c1".to_string(),
+            jobs_run,
+            created_at: now - age,
+            warm: true,
+        }
+    }
+
+    #[test]
+    fn test_disposal() {
+        let config = PoolConfig {
+            size: 2,
+            max_jobs_per_sandbox: 10,
+            max_sandbox_age: Duration::from_secs(3600),
+            ..Default::default()
+        };
+        let now = Instant::now();
+        let fresh = sandbox(1, Duration::from_secs(60), now);
+
+        assert_eq!(config.disposal(&fresh, JobOutcome::Completed, 0, now), Disposal::Recycle);
+        assert_eq!(config.disposal(&fresh, JobOutcome::Aborted, 0, now), Disposal::Discard("aborted"));
+        assert_eq!(config.disposal(&fresh, JobOutcome::Completed, 2, now), Disposal::Discard("pool_full"));
+        assert_eq!(
+            config.disposal(&sandbox(10, Duration::from_secs(60), now), JobOutcome::Completed, 0, now),
+            Disposal::Discard("max_jobs")
+        );
+        assert_eq!(
+            config.disposal(&sandbox(1, Duration::from_secs(7200), now), JobOutcome::Completed, 0, now),
+            Disposal::Discard("max_age")
+        );
+
+        let disabled = PoolConfig::default();
+        assert_eq!(disabled.disposal(&fresh, JobOutcome::Completed, 0, now), Disposal::Discard("pool_full"));
+    }
+
+    #[test]
+    fn test_sandbox_arguments() {
+        let mount = "--volume=/var/lib/lean-farm/mathlib-cache/v4.7.0-no-mathlib:/opt/mathlib:ro".to_string();
+        let args = create_args("leanprover/lean4:4.7.0", "lean-farm-1", std::slice::from_ref(&mount));
+        assert!(args.contains(&mount));
+        assert!(args.contains(&"--network=none".to_string()));
+        assert!(args.contains(&"--read-only".to_string()));
+        assert_eq!(args[args.len() - 3..], ["leanprover/lean4:4.7.0", "sleep", "infinity"]);
+
+        let limits = ResourceLimits {
+            cpus: 4.0,
+            memory_mb: 8192,
+            ..Default::default()
+        };
+        assert_eq!(
+            update_args("c1", &limits),
+            ["update", "--cpus=4", "--memory=8192m", "--memory-swap=8192m", "c1"]
+        );
+        assert!(reset_script().starts_with("find /tmp /var/lean-farm -mindepth 1 -delete"));
+    }
+
+    #[test]
+    fn test_metrics_register() {
+        let registry = Registry::new();
+        let metrics = PoolMetrics::new(&registry).unwrap();
+        metrics.leases.with_label_values(&["warm"]).inc();
+        metrics.discarded.with_label_values(&["aborted"]).inc();
+        metrics.recycled.with_label_values(&["reset"]).inc();
+        metrics.lease_seconds.observe(0.2);
+        assert_eq!(registry.gather().len(), 6);
+    }
+}
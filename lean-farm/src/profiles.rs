@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::LeanFarmError;
+
+// Sandboxes run the job's Lean code, so they get a deny-by-default seccomp
+// profile (built in, or a replacement from config) and optionally an
+// AppArmor profile loaded on the docker host. The farm checks at startup
+// that the runtime enforces both by starting a probe container with them,
+// and refuses to run jobs otherwise.
+
+/// Deny-by-default seccomp profile allowing what Lean, lake, git and a
+/// shell need
+pub const BUILTIN_SECCOMP_PROFILE: &str = include_str!("../profiles/sandbox-seccomp.json");
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxProfileConfig {
+    /// Seccomp profile JSON for sandboxes; the built-in profile when unset
+    pub seccomp_profile: Option<PathBuf>,
+    /// AppArmor profile loaded on the docker host, e.g. "lean-farm-sandbox"
+    /// from `profiles/lean-farm-sandbox.apparmor`
+    pub apparmor_profile: Option<String>,
+}
+
+/// Checks a seccomp profile parses and denies what it doesn't list
+pub fn validate_seccomp(profile: &str) -> Result<(), LeanFarmError> {
+    let profile: serde_json::Value = serde_json::from_str(profile)
+        .map_err(|e| LeanFarmError::Security(format!("Invalid seccomp profile: {}", e)))?;
+    match profile["defaultAction"].as_str() {
+        Some("SCMP_ACT_ERRNO" | "SCMP_ACT_KILL" | "SCMP_ACT_KILL_PROCESS" | "SCMP_ACT_KILL_THREAD") => {}
+        other => {
+            return Err(LeanFarmError::Security(format!(
+                "Seccomp profile must deny unlisted syscalls, but its defaultAction is {:?}",
+                other.unwrap_or("missing")
+            )))
+        }
+    }
+    if !profile["syscalls"].is_array() {
+        return Err(LeanFarmError::Security("Seccomp profile lists no syscalls".to_string()));
+    }
+    Ok(())
+}
+
+/// Whether `docker info`'s security options include `feature`, e.g.
+/// "seccomp" in "name=seccomp,profile=builtin"
+pub fn runtime_supports(security_options: &[String], feature: &str) -> bool {
+    let name = format!("name={}", feature);
+    security_options
+        .iter()
+        .any(|option| option.split(',').next() == Some(name.as_str()))
+}
+
+/// The profiles applied to every sandbox
+#[derive(Debug, Clone)]
+pub struct SandboxProfiles {
+    seccomp_path: PathBuf,
+    apparmor_profile: Option<String>,
+}
+
+impl SandboxProfiles {
+    /// Checks the configured seccomp profile, or writes the built-in one
+    /// where the docker CLI can read it
+    pub fn load(config: &SandboxProfileConfig) -> Result<Self, LeanFarmError> {
+        let seccomp_path = match &config.seccomp_profile {
+            Some(path) => {
+                let profile = std::fs::read_to_string(path).map_err(|e| {
+                    LeanFarmError::Security(format!("Cannot read seccomp profile {}: {}", path.display(), e))
+                })?;
+                validate_seccomp(&profile)?;
+                path.clone()
+            }
+            None => {
+                let path = std::env::temp_dir().join("lean-farm-sandbox-seccomp.json");
+                std::fs::write(&path, BUILTIN_SECCOMP_PROFILE).map_err(|e| {
+                    LeanFarmError::Security(format!("Cannot write seccomp profile {}: {}", path.display(), e))
+                })?;
+                path
+            }
+        };
+        Ok(Self {
+            seccomp_path,
+            apparmor_profile: config.apparmor_profile.clone().filter(|name| !name.is_empty()),
+        })
+    }
+
+    /// `docker run` arguments applying the profiles
+    pub fn security_opts(&self) -> Vec<String> {
+        let mut opts = vec![format!("--security-opt=seccomp={}", self.seccomp_path.display())];
+        if let Some(name) = &self.apparmor_profile {
+            opts.push(format!("--security-opt=apparmor={}", name));
+        }
+        opts.push("--security-opt=no-new-privileges".to_string());
+        opts.push("--cap-drop=ALL".to_string());
+        opts
+    }
+
+    /// Fails unless the docker runtime enforces seccomp, and AppArmor when a
+    /// profile is configured, and can start `image` under the profiles
+    pub async fn validate_runtime(&self, image: &str) -> Result<(), LeanFarmError> {
+        let output = docker(&["info", "--format", "{{json .SecurityOptions}}"]).await?;
+        let security_options: Vec<String> = serde_json::from_str(output.trim()).map_err(|e| {
+            LeanFarmError::Security(format!("Cannot read docker security options {:?}: {}", output.trim(), e))
+        })?;
+        if !runtime_supports(&security_options, "seccomp") {
+            return Err(LeanFarmError::Security(
+                "The docker runtime does not support seccomp; sandboxes would run unconfined".to_string(),
+            ));
+        }
+        if let Some(name) = &self.apparmor_profile {
+            if !runtime_supports(&security_options, "apparmor") {
+                return Err(LeanFarmError::Security(format!(
+                    "AppArmor profile {} is configured but the docker runtime does not support AppArmor",
+                    name
+                )));
+            }
+        }
+
+        // Catches an AppArmor profile that isn't loaded on the host and a
+        // seccomp profile the runtime rejects
+        let mut args = vec!["run".to_string(), "--rm".to_string(), "--network=none".to_string()];
+        args.extend(self.security_opts());
+        args.extend([image.to_string(), "true".to_string()]);
+        docker(&args.iter().map(String::as_str).collect::<Vec<_>>())
+            .await
+            .map_err(|e| LeanFarmError::Security(format!("Sandbox security profiles cannot be applied: {}", e)))?;
+
+        info!(
+            "Sandboxes run with seccomp profile {}{}",
+            self.seccomp_path.display(),
+            self.apparmor_profile
+                .as_ref()
+                .map(|name| format!(" and AppArmor profile {}", name))
+                .unwrap_or_default()
+        );
+        Ok(())
+    }
+}
+
+async fn docker(args: &[&str]) -> Result<String, LeanFarmError> {
+    let output = tokio::process::Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| LeanFarmError::JobExecution(format!("failed to run docker: {}", e)))?;
+    if !output.status.success() {
+        return Err(LeanFarmError::JobExecution(format!(
+            "docker {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_seccomp_profile() {
+        assert!(validate_seccomp(BUILTIN_SECCOMP_PROFILE).is_ok());
+
+        let profile: serde_json::Value = serde_json::from_str(BUILTIN_SECCOMP_PROFILE).unwrap();
+        let allowed: Vec<&str> = profile["syscalls"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|rule| rule["action"] == "SCMP_ACT_ALLOW" && rule.get("args").is_none())
+            .flat_map(|rule| rule["names"].as_array().unwrap().iter().map(|name| name.as_str().unwrap()))
+            .collect();
+        assert!(allowed.contains(&"execve"));
+        for denied in ["ptrace", "mount", "unshare", "setns", "bpf", "keyctl", "perf_event_open", "clone3"] {
+            assert!(!allowed.contains(&denied), "{} is allowed", denied);
+        }
+
+        assert!(validate_seccomp(r#"{"defaultAction": "SCMP_ACT_ALLOW", "syscalls": []}"#).is_err());
+        assert!(validate_seccomp("unconfined").is_err());
+    }
+
+    #[test]
+    fn test_security_opts() {
+        let profiles = SandboxProfiles::load(&SandboxProfileConfig {
+            seccomp_profile: None,
+            apparmor_profile: Some("lean-farm-sandbox".to_string()),
+        })
+        .unwrap();
+        let opts = profiles.security_opts();
+        assert!(opts[0].starts_with("--security-opt=seccomp=") && opts[0].ends_with("lean-farm-sandbox-seccomp.json"));
+        assert_eq!(
+            opts[1..],
+            ["--security-opt=apparmor=lean-farm-sandbox", "--security-opt=no-new-privileges", "--cap-drop=ALL"]
+        );
+
+        let options = ["name=apparmor".to_string(), "name=seccomp,profile=builtin".to_string()];
+        assert!(runtime_supports(&options, "seccomp"));
+        assert!(runtime_supports(&options, "apparmor"));
+        assert!(!runtime_supports(&options[..1], "seccomp"));
+        assert!(!runtime_supports(&["name=rootless".to_string()], "selinux"));
+    }
+}
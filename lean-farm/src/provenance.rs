@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::ProofJob;
+use crate::proto::spec_to_proof::v1::{LeanTheorem, ProofArtifact};
+use crate::toolchain::Toolchain;
+use crate::worker::WorkerIdentity;
+
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+pub const SLSA_PROVENANCE_PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+pub const BUILD_TYPE: &str = "https://spec-to-proof.com/lean-farm/proof/v1";
+
+/// Theorem metadata key holding the SHA-256 of the spec document the
+/// theorem was derived from, set by the service submitting the job
+pub const SPEC_DOCUMENT_SHA256_METADATA_KEY: &str = "spec_document_sha256";
+
+/// Artifact metadata keys locating the provenance statement for a proof
+pub const PROVENANCE_KEY_METADATA_KEY: &str = "provenance_key";
+pub const PROVENANCE_SHA256_METADATA_KEY: &str = "provenance_sha256";
+
+/// Identifies this lean-farm release as the builder of a proof
+pub fn builder_id() -> String {
+    format!("https://github.com/spec-to-proof/lean-farm@v{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// in-toto statement carrying SLSA v1 provenance for one proof artifact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub statement_type: String,
+    pub subject: Vec<ResourceDescriptor>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    pub predicate: Provenance,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceDescriptor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub digest: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    pub build_definition: BuildDefinition,
+    pub run_details: RunDetails,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildDefinition {
+    pub build_type: String,
+    /// The proof options the job was submitted with
+    pub external_parameters: Value,
+    pub internal_parameters: Value,
+    pub resolved_dependencies: Vec<ResourceDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunDetails {
+    pub builder: Builder,
+    pub metadata: BuildMetadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Builder {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildMetadata {
+    pub invocation_id: String,
+    pub started_on: DateTime<Utc>,
+    pub finished_on: DateTime<Utc>,
+}
+
+impl Statement {
+    /// Provenance for `artifact`, proven from `theorem` by `job` on
+    /// `worker`. The spec document is only listed when the theorem records
+    /// its hash.
+    pub fn for_proof(
+        job: &ProofJob,
+        theorem: &LeanTheorem,
+        artifact: &ProofArtifact,
+        toolchain: &Toolchain,
+        worker: &WorkerIdentity,
+        started_on: DateTime<Utc>,
+        finished_on: DateTime<Utc>,
+    ) -> Self {
+        let mut resolved_dependencies = Vec::new();
+        if let Some(spec_sha256) = theorem.metadata.get(SPEC_DOCUMENT_SHA256_METADATA_KEY) {
+            resolved_dependencies.push(ResourceDescriptor {
+                name: Some("spec_document".to_string()),
+                uri: None,
+                digest: sha256_digest(spec_sha256),
+            });
+        }
+        resolved_dependencies.push(ResourceDescriptor {
+            name: Some(format!("theorem:{}", theorem.theorem_name)),
+            uri: None,
+            digest: sha256_digest(&theorem.content_sha256),
+        });
+        resolved_dependencies.push(ResourceDescriptor {
+            name: Some("lean_toolchain".to_string()),
+            uri: Some(toolchain.lean_toolchain.clone()),
+            digest: BTreeMap::new(),
+        });
+        if !toolchain.mathlib_commit.is_empty() {
+            resolved_dependencies.push(ResourceDescriptor {
+                name: Some("mathlib".to_string()),
+                uri: Some("git+https://github.com/leanprover-community/mathlib4".to_string()),
+                digest: BTreeMap::from([("gitCommit".to_string(), toolchain.mathlib_commit.clone())]),
+            });
+        }
+
+        Self {
+            statement_type: STATEMENT_TYPE.to_string(),
+            subject: vec![ResourceDescriptor {
+                name: Some(artifact.id.clone()),
+                uri: None,
+                digest: sha256_digest(&artifact.content_sha256),
+            }],
+            predicate_type: SLSA_PROVENANCE_PREDICATE_TYPE.to_string(),
+            predicate: Provenance {
+                build_definition: BuildDefinition {
+                    build_type: BUILD_TYPE.to_string(),
+                    external_parameters: json!({
+                        "theorem_id": theorem.id,
+                        "options": {
+                            "temperature": job.options.temperature,
+                            "max_tokens": job.options.max_tokens,
+                            "seed": job.options.seed,
+                            "max_attempts": job.options.max_attempts,
+                            "timeout_seconds": job.options.timeout_seconds,
+                            "proof_strategy": job.options.proof_strategy,
+                        },
+                    }),
+                    internal_parameters: json!({
+                        "priority": job.priority.as_str(),
+                        "worker": worker,
+                    }),
+                    resolved_dependencies,
+                },
+                run_details: RunDetails {
+                    builder: Builder { id: builder_id() },
+                    metadata: BuildMetadata {
+                        invocation_id: job.id.clone(),
+                        started_on,
+                        finished_on,
+                    },
+                },
+            },
+        }
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+}
+
+/// Stamps where a proof's statement is stored, and its hash, on the artifact
+pub fn record(metadata: &mut HashMap<String, String>, key: &str, statement: &[u8]) {
+    metadata.insert(PROVENANCE_KEY_METADATA_KEY.to_string(), key.to_string());
+    metadata.insert(PROVENANCE_SHA256_METADATA_KEY.to_string(), format!("{:x}", Sha256::digest(statement)));
+}
+
+fn sha256_digest(hash: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([("sha256".to_string(), hash.trim_start_matches("sha256:").to_string())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use crate::JobPriority;
+    use crate::proto::proof::v1::ProofOptions;
+
+    #[test]
+    fn test_statement_for_proof() {
+        let mut theorem = LeanTheorem {
+            id: "thm-1".to_string(),
+            theorem_name: "refund_bound".to_string(),
+            content_sha256: "sha256:abc".to_string(),
+            ..Default::default()
+        };
+        theorem.metadata.insert(SPEC_DOCUMENT_SHA256_METADATA_KEY.to_string(), "def".to_string());
+        let job = ProofJob {
+            id: "job-1".to_string(),
+            theorem: theorem.clone(),
+            options: ProofOptions {
+                seed: 7,
+                proof_strategy: "llm_guided".to_string(),
+                ..Default::default()
+            },
+            priority: JobPriority::High,
+            created_at: Instant::now(),
+            deadline: None,
+        };
+        let artifact = ProofArtifact {
+            id: "proof-1".to_string(),
+            content_sha256: "0123".to_string(),
+            ..Default::default()
+        };
+        let toolchain = Toolchain {
+            lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+            mathlib_commit: "3f1c2a9b4d5e".to_string(),
+        };
+        let worker = WorkerIdentity {
+            pod_name: "lean-farm-7d9c-abcde".to_string(),
+            node_name: "ip-10-0-1-17".to_string(),
+            image_digest: "sha256:9b2f".to_string(),
+            lean_toolchain: toolchain.lean_toolchain.clone(),
+            lean_farm_version: "1.0.0".to_string(),
+        };
+        let now = Utc::now();
+
+        let statement = Statement::for_proof(&job, &theorem, &artifact, &toolchain, &worker, now, now);
+        assert_eq!(statement.subject[0].digest["sha256"], "0123");
+
+        let build = &statement.predicate.build_definition;
+        let names: Vec<_> = build.resolved_dependencies.iter()
+            .map(|dependency| dependency.name.clone().unwrap())
+            .collect();
+        assert_eq!(names, vec!["spec_document", "theorem:refund_bound", "lean_toolchain", "mathlib"]);
+        assert_eq!(build.resolved_dependencies[1].digest["sha256"], "abc");
+        assert_eq!(build.external_parameters["options"]["seed"], 7);
+        assert_eq!(build.internal_parameters["worker"]["node_name"], "ip-10-0-1-17");
+
+        let value: Value = serde_json::from_slice(&statement.to_json().unwrap()).unwrap();
+        assert_eq!(value["_type"], STATEMENT_TYPE);
+        assert_eq!(value["predicate"]["runDetails"]["metadata"]["invocationId"], "job-1");
+        assert_eq!(value["predicate"]["runDetails"]["builder"]["id"], builder_id());
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::{JobPriority, LeanFarmError, ProofJob};
+use crate::proto::spec_to_proof::v1::LeanTheorem;
+
+/// Theorem metadata key holding the source invariant's taxonomy category
+pub const TAXONOMY_CATEGORY_METADATA_KEY: &str = "taxonomy_category";
+
+/// Theorem metadata key counting how often a job has been run
+pub const ATTEMPT_COUNT_METADATA_KEY: &str = "attempt_count";
+
+/// Artifact metadata keys recording the limits a proof ran under
+pub const APPLIED_TIMEOUT_METADATA_KEY: &str = "applied_timeout_seconds";
+pub const APPLIED_CPUS_METADATA_KEY: &str = "applied_cpus";
+pub const APPLIED_MEMORY_METADATA_KEY: &str = "applied_memory_mb";
+pub const APPLIED_MAX_ATTEMPTS_METADATA_KEY: &str = "applied_max_attempts";
+
+/// Limits a single proof job runs under
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    pub timeout_seconds: u64,
+    pub cpus: f64,
+    pub memory_mb: u64,
+    /// Runs of a failing job, including the first, before it is reported
+    /// as failed
+    pub max_attempts: u32,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 300,
+            cpus: 2.0,
+            memory_mb: 4096,
+            max_attempts: 1,
+        }
+    }
+}
+
+impl ResourceLimits {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_seconds)
+    }
+
+    /// `docker run` flags enforcing the CPU and memory limits
+    pub fn docker_args(&self) -> Vec<String> {
+        vec![
+            format!("--cpus={}", self.cpus),
+            format!("--memory={}m", self.memory_mb),
+        ]
+    }
+
+    pub fn record(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert(APPLIED_TIMEOUT_METADATA_KEY.to_string(), self.timeout_seconds.to_string());
+        metadata.insert(APPLIED_CPUS_METADATA_KEY.to_string(), self.cpus.to_string());
+        metadata.insert(APPLIED_MEMORY_METADATA_KEY.to_string(), self.memory_mb.to_string());
+        metadata.insert(APPLIED_MAX_ATTEMPTS_METADATA_KEY.to_string(), self.max_attempts.to_string());
+    }
+
+    fn apply(&mut self, overrides: &LimitOverrides) {
+        if let Some(timeout_seconds) = overrides.timeout_seconds {
+            self.timeout_seconds = timeout_seconds;
+        }
+        if let Some(cpus) = overrides.cpus {
+            self.cpus = cpus;
+        }
+        if let Some(memory_mb) = overrides.memory_mb {
+            self.memory_mb = memory_mb;
+        }
+        if let Some(max_attempts) = overrides.max_attempts {
+            self.max_attempts = max_attempts;
+        }
+    }
+}
+
+/// Limits to change from the ones a rule builds on; unset fields are kept
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LimitOverrides {
+    pub timeout_seconds: Option<u64>,
+    pub cpus: Option<f64>,
+    pub memory_mb: Option<u64>,
+    pub max_attempts: Option<u32>,
+}
+
+/// Maps job priority and invariant taxonomy category to resource limits.
+/// Priority overrides apply on top of the defaults, then category
+/// overrides on top of those.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourcePolicy {
+    #[serde(default)]
+    pub defaults: ResourceLimits,
+    /// Keyed by priority name ("low", "normal", "high", "critical")
+    #[serde(default)]
+    pub priorities: HashMap<String, LimitOverrides>,
+    /// Keyed by taxonomy category, e.g. "security"
+    #[serde(default)]
+    pub categories: HashMap<String, LimitOverrides>,
+}
+
+impl ResourcePolicy {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let policy: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn validate(&self) -> Result<(), LeanFarmError> {
+        for priority in self.priorities.keys() {
+            if !JobPriority::ALL.iter().any(|p| p.as_str() == priority) {
+                return Err(LeanFarmError::Config(format!("Unknown priority in resource policy: {}", priority)));
+            }
+        }
+
+        let mut rules = vec![("defaults", self.defaults.clone())];
+        rules.extend(self.priorities.iter().map(|(name, overrides)| {
+            let mut limits = self.defaults.clone();
+            limits.apply(overrides);
+            (name.as_str(), limits)
+        }));
+        for (name, limits) in rules {
+            if limits.timeout_seconds == 0 || limits.cpus <= 0.0 || limits.memory_mb == 0 || limits.max_attempts == 0 {
+                return Err(LeanFarmError::Config(format!(
+                    "Resource limits for {} must all be positive", name
+                )));
+            }
+        }
+        for (name, overrides) in &self.categories {
+            if overrides.timeout_seconds == Some(0)
+                || overrides.cpus.map_or(false, |cpus| cpus <= 0.0)
+                || overrides.memory_mb == Some(0)
+                || overrides.max_attempts == Some(0)
+            {
+                return Err(LeanFarmError::Config(format!(
+                    "Resource limits for category {} must all be positive", name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Limits for the priority the job was submitted with; aging only
+    /// changes when a job runs, not what it runs with
+    pub fn limits_for(&self, job: &ProofJob) -> ResourceLimits {
+        let mut limits = self.defaults.clone();
+        if let Some(overrides) = self.priorities.get(job.priority.as_str()) {
+            limits.apply(overrides);
+        }
+        if let Some(overrides) = taxonomy_category(&job.theorem).and_then(|category| self.categories.get(category)) {
+            limits.apply(overrides);
+        }
+        limits
+    }
+}
+
+pub fn taxonomy_category(theorem: &LeanTheorem) -> Option<&str> {
+    theorem.metadata.get(TAXONOMY_CATEGORY_METADATA_KEY).map(String::as_str)
+}
+
+pub fn attempt_count(theorem: &LeanTheorem) -> u32 {
+    theorem.metadata
+        .get(ATTEMPT_COUNT_METADATA_KEY)
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use crate::proto::proof::v1::ProofOptions;
+
+    fn job(priority: JobPriority, category: Option<&str>) -> ProofJob {
+        let mut theorem = LeanTheorem::default();
+        if let Some(category) = category {
+            theorem.metadata.insert(TAXONOMY_CATEGORY_METADATA_KEY.to_string(), category.to_string());
+        }
+        ProofJob {
+            id: "job".to_string(),
+            theorem,
+            options: ProofOptions::default(),
+            priority,
+            created_at: Instant::now(),
+            deadline: None,
+        }
+    }
+
+    fn policy() -> ResourcePolicy {
+        serde_json::from_value(serde_json::json!({
+            "priorities": {
+                "critical": {"timeout_seconds": 900, "cpus": 4.0, "max_attempts": 3},
+                "low": {"timeout_seconds": 120}
+            },
+            "categories": {
+                "security": {"memory_mb": 8192, "max_attempts": 2}
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_limits_fall_back_to_defaults() {
+        assert_eq!(policy().limits_for(&job(JobPriority::Normal, None)), ResourceLimits::default());
+        assert_eq!(policy().limits_for(&job(JobPriority::Normal, Some("liveness"))), ResourceLimits::default());
+    }
+
+    #[test]
+    fn test_category_overrides_apply_over_priority() {
+        let limits = policy().limits_for(&job(JobPriority::Critical, Some("security")));
+        assert_eq!(limits, ResourceLimits {
+            timeout_seconds: 900,
+            cpus: 4.0,
+            memory_mb: 8192,
+            max_attempts: 2,
+        });
+
+        let limits = policy().limits_for(&job(JobPriority::Low, None));
+        assert_eq!(limits.timeout(), Duration::from_secs(120));
+        assert_eq!(limits.docker_args(), vec!["--cpus=2".to_string(), "--memory=4096m".to_string()]);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_policies() {
+        assert!(policy().validate().is_ok());
+
+        let mut unknown = policy();
+        unknown.priorities.insert("urgent".to_string(), LimitOverrides::default());
+        assert!(unknown.validate().is_err());
+
+        let mut zero = policy();
+        zero.categories.insert("safety".to_string(), LimitOverrides { max_attempts: Some(0), ..Default::default() });
+        assert!(zero.validate().is_err());
+    }
+
+    #[test]
+    fn test_record_applied_limits() {
+        let mut metadata = HashMap::new();
+        policy().limits_for(&job(JobPriority::Critical, None)).record(&mut metadata);
+        assert_eq!(metadata[APPLIED_TIMEOUT_METADATA_KEY], "900");
+        assert_eq!(metadata[APPLIED_CPUS_METADATA_KEY], "4");
+        assert_eq!(metadata[APPLIED_MAX_ATTEMPTS_METADATA_KEY], "3");
+    }
+}
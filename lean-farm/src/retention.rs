@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::reverification::CronSchedule;
+use crate::worker::WorkerIdentity;
+
+// Proofs, their provenance and failed attempts pile up in MinIO. Retention
+// keeps the newest proofs of each invariant plus every proof a proven
+// record still names, since badges and coverage report those, and drops
+// failed-attempt transcripts once they are old enough to no longer help
+// with debugging.
+
+/// Suffix of the provenance statement stored next to each proof
+pub const PROVENANCE_SUFFIX: &str = ".intoto.json";
+
+/// What a retention run keeps
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    /// Newest proofs kept per invariant, across theorem versions and
+    /// toolchains
+    pub keep_latest_per_invariant: usize,
+    /// Failed-attempt transcripts older than this many days are deleted
+    pub failed_attempt_max_age_days: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_latest_per_invariant: 3,
+            failed_attempt_max_age_days: 30,
+        }
+    }
+}
+
+/// When retention runs, and whether it only reports what it would delete
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    pub schedule: CronSchedule,
+    pub policy: RetentionPolicy,
+    pub dry_run: bool,
+}
+
+/// A stored proof, as much of it as retention needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredProof {
+    pub key: String,
+    pub artifact_id: String,
+    pub invariant_id: String,
+    pub attempted_at: Option<DateTime<Utc>>,
+}
+
+/// The transcript of a job attempt that failed, kept for debugging until
+/// retention removes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedAttempt {
+    pub job_id: String,
+    pub attempt: u32,
+    pub theorem_id: String,
+    pub theorem_name: String,
+    pub invariant_id: String,
+    pub content_sha256: String,
+    pub toolchain: String,
+    /// Missing from attempts recorded before workers were identified
+    #[serde(default)]
+    pub worker: Option<WorkerIdentity>,
+    pub error_message: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Attempts are filed under the day they failed, so retention can age
+/// them by key without reading each one
+pub fn failed_attempt_key(prefix: &str, attempt: &FailedAttempt) -> String {
+    format!(
+        "{}/attempts/{}/{}/{}-{}.json",
+        prefix,
+        attempt.failed_at.format("%Y-%m-%d"),
+        attempt.content_sha256,
+        attempt.job_id,
+        attempt.attempt
+    )
+}
+
+fn failed_attempt_date(prefix: &str, key: &str) -> Option<NaiveDate> {
+    let day = key.strip_prefix(&format!("{}/attempts/", prefix))?.split('/').next()?;
+    NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()
+}
+
+/// What a retention run deleted, or would delete when dry-running
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub ran_at: DateTime<Utc>,
+    pub proofs_kept: usize,
+    /// Kept only because a proven record names them
+    pub proofs_protected: usize,
+    /// Proof keys; each proof's provenance statement goes with it
+    pub deleted_proofs: Vec<String>,
+    pub failed_attempts_kept: usize,
+    pub deleted_failed_attempts: Vec<String>,
+}
+
+/// Decides which proofs and failed attempts a run deletes. `protected`
+/// holds the artifact IDs named by proven records.
+pub fn plan(
+    policy: &RetentionPolicy,
+    prefix: &str,
+    proofs: &[StoredProof],
+    protected: &HashSet<String>,
+    failed_attempt_keys: &[String],
+    now: DateTime<Utc>,
+) -> RetentionReport {
+    let mut report = RetentionReport {
+        ran_at: now,
+        ..Default::default()
+    };
+
+    let mut by_invariant: HashMap<&str, Vec<&StoredProof>> = HashMap::new();
+    for proof in proofs {
+        by_invariant.entry(proof.invariant_id.as_str()).or_default().push(proof);
+    }
+    for mut group in by_invariant.into_values() {
+        // Newest first; the key breaks ties so runs are repeatable
+        group.sort_by(|a, b| b.attempted_at.cmp(&a.attempted_at).then_with(|| a.key.cmp(&b.key)));
+        for (index, proof) in group.into_iter().enumerate() {
+            if index < policy.keep_latest_per_invariant {
+                report.proofs_kept += 1;
+            } else if protected.contains(&proof.artifact_id) {
+                report.proofs_kept += 1;
+                report.proofs_protected += 1;
+            } else {
+                report.deleted_proofs.push(proof.key.clone());
+            }
+        }
+    }
+    report.deleted_proofs.sort();
+
+    let cutoff = (now - chrono::Duration::days(policy.failed_attempt_max_age_days as i64)).date_naive();
+    for key in failed_attempt_keys {
+        // Keys that don't carry a date are left for someone to look at
+        match failed_attempt_date(prefix, key) {
+            Some(day) if day < cutoff => report.deleted_failed_attempts.push(key.clone()),
+            _ => report.failed_attempts_kept += 1,
+        }
+    }
+    report.deleted_failed_attempts.sort();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn proof(key: &str, invariant_id: &str, day: u32) -> StoredProof {
+        StoredProof {
+            key: key.to_string(),
+            artifact_id: format!("artifact-{}", key),
+            invariant_id: invariant_id.to_string(),
+            attempted_at: Some(Utc.with_ymd_and_hms(2024, 5, day, 0, 0, 0).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_plan_keeps_latest_and_protected_proofs() {
+        let policy = RetentionPolicy {
+            keep_latest_per_invariant: 2,
+            failed_attempt_max_age_days: 30,
+        };
+        let proofs = vec![
+            proof("p1", "inv-1", 1),
+            proof("p2", "inv-1", 2),
+            proof("p3", "inv-1", 3),
+            proof("p4", "inv-1", 4),
+            proof("q1", "inv-2", 1),
+        ];
+        let protected = HashSet::from(["artifact-p1".to_string()]);
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+
+        let report = plan(&policy, "farm", &proofs, &protected, &[], now);
+
+        assert_eq!(report.deleted_proofs, ["p2"]);
+        assert_eq!(report.proofs_kept, 4);
+        assert_eq!(report.proofs_protected, 1);
+    }
+
+    #[test]
+    fn test_plan_ages_out_failed_attempts() {
+        let attempt = FailedAttempt {
+            job_id: "job-1".to_string(),
+            attempt: 2,
+            theorem_id: "thm-1".to_string(),
+            theorem_name: "refund_bound".to_string(),
+            invariant_id: "inv-1".to_string(),
+            content_sha256: "ab12".to_string(),
+            toolchain: "v4.7.0-mathlib-abc".to_string(),
+            worker: None,
+            error_message: "Job timeout".to_string(),
+            failed_at: Utc.with_ymd_and_hms(2024, 4, 1, 12, 0, 0).unwrap(),
+        };
+        let old = failed_attempt_key("farm", &attempt);
+        assert_eq!(old, "farm/attempts/2024-04-01/ab12/job-1-2.json");
+        let recent = "farm/attempts/2024-05-20/ab12/job-2-1.json".to_string();
+        let undated = "farm/attempts/misc/job-3-1.json".to_string();
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+
+        let report = plan(&RetentionPolicy::default(), "farm", &[], &HashSet::new(), &[old.clone(), recent, undated], now);
+
+        assert_eq!(report.deleted_failed_attempts, [old]);
+        assert_eq!(report.failed_attempts_kept, 2);
+    }
+}
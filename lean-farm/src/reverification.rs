@@ -0,0 +1,379 @@
+use std::time::{Duration, Instant};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use prometheus::{IntCounter, IntCounterVec, Opts, Registry};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{JobPriority, LeanFarmError, ProofJob};
+use crate::proto::proof::v1::ProofOptions;
+use crate::proto::spec_to_proof::v1::LeanTheorem;
+
+/// Theorem metadata key marking a job as a re-verification, holding the id
+/// of the run that scheduled it
+pub const REVERIFICATION_RUN_METADATA_KEY: &str = "reverification_run";
+
+/// Theorem metadata key holding the toolchain key the theorem was last
+/// proven with
+pub const PREVIOUS_TOOLCHAIN_METADATA_KEY: &str = "previous_toolchain";
+
+// Cron fields: minute, hour, day of month, month, day of week
+const FIELD_RANGES: [(u32, u32); 5] = [(0, 59), (0, 23), (1, 31), (1, 12), (0, 6)];
+
+// Longest gap between two matches of a valid expression: Feb 29 can be
+// eight years apart
+const MAX_SEARCH_MINUTES: i64 = 8 * 366 * 24 * 60;
+
+/// Standard five-field cron expression, evaluated in UTC. Supports `*`,
+/// values, ranges, steps and lists, plus `@hourly`, `@daily`, `@weekly`
+/// and `@monthly`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    expression: String,
+    fields: [Vec<bool>; 5],
+    // Cron matches either day field when both are restricted
+    day_of_month_any: bool,
+    day_of_week_any: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, LeanFarmError> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let parts: Vec<&str> = expanded.split_whitespace().collect();
+        if parts.len() != 5 {
+            return Err(LeanFarmError::Config(format!(
+                "Cron expression {:?} must have 5 fields", expression
+            )));
+        }
+
+        let mut fields: [Vec<bool>; 5] = Default::default();
+        for (index, part) in parts.iter().enumerate() {
+            fields[index] = parse_field(part, index)
+                .ok_or_else(|| LeanFarmError::Config(format!("Invalid cron field {:?} in {:?}", part, expression)))?;
+        }
+
+        Ok(Self {
+            expression: expression.trim().to_string(),
+            fields,
+            day_of_month_any: parts[2] == "*",
+            day_of_week_any: parts[4] == "*",
+        })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn matches(&self, time: &DateTime<Utc>) -> bool {
+        let day_of_month = self.fields[2][time.day() as usize];
+        let day_of_week = self.fields[4][time.weekday().num_days_from_sunday() as usize];
+        let day = match (self.day_of_month_any, self.day_of_week_any) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        };
+        day
+            && self.fields[0][time.minute() as usize]
+            && self.fields[1][time.hour() as usize]
+            && self.fields[3][time.month() as usize]
+    }
+
+    /// The first matching minute strictly after `time`
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = time.with_second(0)?.with_nanosecond(0)?;
+        (1..=MAX_SEARCH_MINUTES)
+            .map(|minutes| start + chrono::Duration::minutes(minutes))
+            .find(|candidate| self.matches(candidate))
+    }
+}
+
+// A lookup table indexed by value; 7 is accepted as Sunday
+fn parse_field(field: &str, index: usize) -> Option<Vec<bool>> {
+    let (min, max) = FIELD_RANGES[index];
+    let upper = if index == 4 { 7 } else { max };
+    let mut allowed = vec![false; max as usize + 1];
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&step| step > 0)?),
+            None => (item, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+                // "5/15" runs from 5 to the end of the range
+                None if item.contains('/') => (range.parse().ok()?, max),
+                None => {
+                    let value = range.parse().ok()?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > upper || start > end {
+            return None;
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed[if index == 4 { value % 7 } else { value } as usize] = true;
+        }
+    }
+    Some(allowed)
+}
+
+/// When proven theorems are re-checked against the farm's current
+/// toolchain, and how many of them
+#[derive(Debug, Clone)]
+pub struct ReverificationConfig {
+    pub schedule: CronSchedule,
+    /// Fraction of proven theorems re-checked per run; 1.0 re-checks all
+    pub sample_rate: f64,
+    /// Upper bound on theorems per run, keeping runs from crowding out
+    /// regular work
+    pub max_theorems: Option<usize>,
+}
+
+/// A proven theorem and the options it was proven with, kept so it can be
+/// re-checked later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenTheorem {
+    /// Base64 protobuf encoding of the theorem
+    pub theorem: String,
+    /// Base64 protobuf encoding of the proof options
+    pub options: String,
+    pub toolchain_key: String,
+    pub proof_artifact_id: String,
+    pub proven_at: DateTime<Utc>,
+}
+
+impl ProvenTheorem {
+    pub fn new(job: &ProofJob, theorem: &LeanTheorem, toolchain_key: &str, proof_artifact_id: &str) -> Self {
+        // Scheduling and retry bookkeeping belongs to the original job
+        let mut theorem = theorem.clone();
+        theorem.metadata.retain(|key, _| !is_job_bookkeeping(key));
+        Self {
+            theorem: STANDARD.encode(theorem.encode_to_vec()),
+            options: STANDARD.encode(job.options.encode_to_vec()),
+            toolchain_key: toolchain_key.to_string(),
+            proof_artifact_id: proof_artifact_id.to_string(),
+            proven_at: Utc::now(),
+        }
+    }
+
+    /// A low-priority job re-running the proof with whatever toolchain the
+    /// farm runs now
+    pub fn into_job(self, run_id: &str) -> Result<ProofJob, LeanFarmError> {
+        let decode = |field: &str, encoded: &str| {
+            STANDARD
+                .decode(encoded)
+                .map_err(|e| LeanFarmError::Storage(format!("proven theorem has invalid {}: {}", field, e)))
+        };
+        let mut theorem = LeanTheorem::decode(decode("theorem", &self.theorem)?.as_slice())
+            .map_err(|e| LeanFarmError::Storage(format!("proven theorem has invalid theorem: {}", e)))?;
+        let options = ProofOptions::decode(decode("options", &self.options)?.as_slice())
+            .map_err(|e| LeanFarmError::Storage(format!("proven theorem has invalid options: {}", e)))?;
+
+        theorem.lean_toolchain.clear();
+        theorem.mathlib_commit.clear();
+        theorem.metadata.insert(REVERIFICATION_RUN_METADATA_KEY.to_string(), run_id.to_string());
+        theorem.metadata.insert(PREVIOUS_TOOLCHAIN_METADATA_KEY.to_string(), self.toolchain_key);
+
+        Ok(ProofJob {
+            id: format!("reverify-{}-{}", run_id, theorem.content_sha256.get(..12).unwrap_or(&theorem.content_sha256)),
+            theorem,
+            options,
+            priority: JobPriority::Low,
+            created_at: Instant::now(),
+            deadline: None,
+        })
+    }
+}
+
+fn is_job_bookkeeping(key: &str) -> bool {
+    matches!(
+        key,
+        crate::scheduling::BATCH_ID_METADATA_KEY
+            | crate::scheduling::PREEMPTION_COUNT_METADATA_KEY
+            | crate::resources::ATTEMPT_COUNT_METADATA_KEY
+            | REVERIFICATION_RUN_METADATA_KEY
+            | PREVIOUS_TOOLCHAIN_METADATA_KEY
+    )
+}
+
+/// The re-verification run a theorem was scheduled by, if any
+pub fn reverification_run(theorem: &LeanTheorem) -> Option<&str> {
+    theorem.metadata.get(REVERIFICATION_RUN_METADATA_KEY).map(String::as_str)
+}
+
+/// Picks roughly `sample_rate` of the keys, a different sample for each
+/// seed so successive runs cover the whole set over time
+pub fn select_sample(keys: &[String], sample_rate: f64, max_theorems: Option<usize>, seed: &str) -> Vec<String> {
+    let threshold = (sample_rate.clamp(0.0, 1.0) * u64::MAX as f64) as u64;
+    let mut selected: Vec<(u64, &String)> = keys
+        .iter()
+        .map(|key| {
+            let digest = Sha256::digest(format!("{}:{}", seed, key).as_bytes());
+            (u64::from_be_bytes(digest[..8].try_into().unwrap()), key)
+        })
+        .filter(|(rank, _)| sample_rate >= 1.0 || *rank < threshold)
+        .collect();
+    selected.sort();
+    if let Some(max_theorems) = max_theorems {
+        selected.truncate(max_theorems);
+    }
+    selected.into_iter().map(|(_, key)| key.clone()).collect()
+}
+
+/// A previously proven theorem that no longer checks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Regression {
+    pub run_id: String,
+    pub theorem_id: String,
+    pub theorem_name: String,
+    pub invariant_id: String,
+    pub content_sha256: String,
+    pub previous_toolchain: String,
+    pub toolchain: String,
+    pub error_message: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Counters behind the proof regression alert
+#[derive(Debug, Clone)]
+pub struct ReverificationMetrics {
+    runs: IntCounter,
+    results: IntCounterVec,
+    regressions: IntCounter,
+}
+
+impl ReverificationMetrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let metrics = Self {
+            runs: IntCounter::new("lean_farm_reverification_runs_total", "Scheduled re-verification runs started")?,
+            results: IntCounterVec::new(
+                Opts::new("lean_farm_reverifications_total", "Re-verified theorems by outcome"),
+                &["outcome"],
+            )?,
+            regressions: IntCounter::new(
+                "lean_farm_proof_regressions_total",
+                "Previously proven theorems that failed re-verification",
+            )?,
+        };
+        registry.register(Box::new(metrics.runs.clone()))?;
+        registry.register(Box::new(metrics.results.clone()))?;
+        registry.register(Box::new(metrics.regressions.clone()))?;
+        Ok(metrics)
+    }
+
+    pub fn record_run(&self) {
+        self.runs.inc();
+    }
+
+    pub fn record_result(&self, success: bool) {
+        let outcome = if success { "passed" } else { "regressed" };
+        self.results.with_label_values(&[outcome]).inc();
+        if !success {
+            self.regressions.inc();
+        }
+    }
+}
+
+/// Time until the schedule next fires
+pub fn delay_until_next(schedule: &CronSchedule, now: DateTime<Utc>) -> Option<Duration> {
+    let next = schedule.next_after(now)?;
+    (next - now).to_std().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        let daily = CronSchedule::parse("30 3 * * *").unwrap();
+        assert_eq!(daily.next_after(at(2024, 5, 1, 2, 0)), Some(at(2024, 5, 1, 3, 30)));
+        assert_eq!(daily.next_after(at(2024, 5, 1, 3, 30)), Some(at(2024, 5, 2, 3, 30)));
+
+        // Sundays at 02:00; 2024-05-05 is a Sunday
+        let weekly = CronSchedule::parse("0 2 * * 7").unwrap();
+        assert_eq!(weekly.next_after(at(2024, 5, 1, 0, 0)), Some(at(2024, 5, 5, 2, 0)));
+
+        let every_six_hours = CronSchedule::parse("0 */6 * * 1-5").unwrap();
+        assert_eq!(every_six_hours.next_after(at(2024, 5, 3, 19, 0)), Some(at(2024, 5, 6, 0, 0)));
+
+        assert_eq!(CronSchedule::parse("@daily").unwrap().next_after(at(2024, 5, 1, 0, 0)), Some(at(2024, 5, 2, 0, 0)));
+    }
+
+    #[test]
+    fn test_restricted_day_fields_match_either() {
+        // The 1st of the month or any Monday
+        let schedule = CronSchedule::parse("0 0 1 * 1").unwrap();
+        assert!(schedule.matches(&at(2024, 5, 1, 0, 0)));
+        assert!(schedule.matches(&at(2024, 5, 6, 0, 0)));
+        assert!(!schedule.matches(&at(2024, 5, 7, 0, 0)));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert!(CronSchedule::parse("0 3 * *").is_err());
+        assert!(CronSchedule::parse("60 3 * * *").is_err());
+        assert!(CronSchedule::parse("0 3 * * 8").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 5-2 * * *").is_err());
+    }
+
+    #[test]
+    fn test_select_sample() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("proven/{:04}.json", i)).collect();
+
+        assert_eq!(select_sample(&keys, 1.0, None, "run-1").len(), 1000);
+        assert!(select_sample(&keys, 0.0, None, "run-1").is_empty());
+
+        let sample = select_sample(&keys, 0.1, None, "run-1");
+        assert!((50..150).contains(&sample.len()));
+        assert_eq!(sample, select_sample(&keys, 0.1, None, "run-1"));
+        assert_ne!(sample, select_sample(&keys, 0.1, None, "run-2"));
+
+        assert_eq!(select_sample(&keys, 1.0, Some(25), "run-1").len(), 25);
+    }
+
+    #[test]
+    fn test_proven_theorem_round_trip() {
+        let mut theorem = LeanTheorem {
+            content_sha256: "ab".repeat(32),
+            lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+            mathlib_commit: "abc".to_string(),
+            ..Default::default()
+        };
+        theorem.metadata.insert(crate::resources::ATTEMPT_COUNT_METADATA_KEY.to_string(), "2".to_string());
+        theorem.metadata.insert("source".to_string(), "nlp".to_string());
+        let job = ProofJob {
+            id: "job-1".to_string(),
+            theorem: theorem.clone(),
+            options: ProofOptions::default(),
+            priority: JobPriority::High,
+            created_at: Instant::now(),
+            deadline: None,
+        };
+
+        let job = ProvenTheorem::new(&job, &theorem, "v4.7.0-mathlib-abc", "proof-1").into_job("20240501").unwrap();
+
+        assert_eq!(job.priority, JobPriority::Low);
+        assert_eq!(reverification_run(&job.theorem), Some("20240501"));
+        assert_eq!(job.theorem.metadata[PREVIOUS_TOOLCHAIN_METADATA_KEY], "v4.7.0-mathlib-abc");
+        assert_eq!(job.theorem.metadata["source"], "nlp");
+        assert!(!job.theorem.metadata.contains_key(crate::resources::ATTEMPT_COUNT_METADATA_KEY));
+        // Checked against the farm's current toolchain, not the old one
+        assert!(job.theorem.lean_toolchain.is_empty());
+    }
+}
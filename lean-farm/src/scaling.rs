@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::time::Instant;
+use tonic::{Request, Response, Status};
+
+use crate::{JobPriority, ProofJob};
+use crate::job_runner::JobRunner;
+use crate::metrics::ScalingHints;
+use crate::proto::lean_farm::v1::{
+    lean_farm_service_server::{LeanFarmService, LeanFarmServiceServer},
+    GetScalingHintsRequest, GetScalingHintsResponse, PurgeToolchainRequest, PurgeToolchainResponse,
+    RunRetentionRequest, RunRetentionResponse, SubmitBatchRequest, SubmitBatchResponse,
+};
+
+/// gRPC view of the runner's scaling hints and proof storage
+/// administration, and its batch intake
+pub struct ScalingService {
+    job_runner: Arc<JobRunner>,
+}
+
+impl ScalingService {
+    pub fn server(job_runner: Arc<JobRunner>) -> LeanFarmServiceServer<Self> {
+        LeanFarmServiceServer::new(Self { job_runner })
+    }
+}
+
+#[tonic::async_trait]
+impl LeanFarmService for ScalingService {
+    async fn get_scaling_hints(
+        &self,
+        _request: Request<GetScalingHintsRequest>,
+    ) -> Result<Response<GetScalingHintsResponse>, Status> {
+        Ok(Response::new(to_response(&self.job_runner.scaling_hints().await)))
+    }
+
+    async fn purge_toolchain(
+        &self,
+        request: Request<PurgeToolchainRequest>,
+    ) -> Result<Response<PurgeToolchainResponse>, Status> {
+        let purged = self.job_runner
+            .purge_toolchain(&request.into_inner().toolchain_key)
+            .await
+            .map_err(|e| Status::failed_precondition(e.to_string()))?;
+        Ok(Response::new(PurgeToolchainResponse { purged_proofs: purged as u32 }))
+    }
+
+    async fn run_retention(
+        &self,
+        request: Request<RunRetentionRequest>,
+    ) -> Result<Response<RunRetentionResponse>, Status> {
+        let report = self.job_runner
+            .run_retention(request.into_inner().dry_run)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(RunRetentionResponse {
+            dry_run: report.dry_run,
+            proofs_kept: report.proofs_kept as u32,
+            proofs_protected: report.proofs_protected as u32,
+            deleted_proofs: report.deleted_proofs,
+            failed_attempts_kept: report.failed_attempts_kept as u32,
+            deleted_failed_attempts: report.deleted_failed_attempts,
+        }))
+    }
+
+    async fn submit_batch(
+        &self,
+        request: Request<SubmitBatchRequest>,
+    ) -> Result<Response<SubmitBatchResponse>, Status> {
+        let request = request.into_inner();
+        if request.theorems.is_empty() {
+            return Err(Status::invalid_argument("A batch needs at least one theorem"));
+        }
+
+        let batch_id = if request.batch_id.is_empty() {
+            uuid::Uuid::new_v4().to_string()
+        } else {
+            request.batch_id
+        };
+        let options = request.options.unwrap_or_default();
+        let priority = JobPriority::from(request.priority);
+        let now = Instant::now();
+        let jobs: Vec<ProofJob> = request.theorems
+            .into_iter()
+            .map(|theorem| ProofJob {
+                id: format!("{}-{}", batch_id, theorem.id),
+                theorem,
+                options: options.clone(),
+                priority: priority.clone(),
+                created_at: now,
+                deadline: None,
+            })
+            .collect();
+        let queued_jobs = jobs.len() as u32;
+
+        let updates = self.job_runner
+            .submit_batch(&batch_id, jobs)
+            .await
+            .map_err(|e| Status::resource_exhausted(e.to_string()))?;
+        self.job_runner.forward_coverage(updates, &batch_id, &request.repository_id, &request.commit_sha);
+
+        Ok(Response::new(SubmitBatchResponse { batch_id, queued_jobs }))
+    }
+}
+
+fn to_response(hints: &ScalingHints) -> GetScalingHintsResponse {
+    GetScalingHintsResponse {
+        queue_depth: hints.queue_depth as u32,
+        backlog_by_priority: JobPriority::ALL
+            .into_iter()
+            .map(|priority| (priority.as_str().to_string(), hints.backlog(priority) as u32))
+            .collect(),
+        running_jobs: hints.running_jobs as u32,
+        worker_count: hints.worker_count as u32,
+        avg_job_duration_ms: hints.avg_job_duration_ms.unwrap_or(0),
+        worker_utilization: hints.worker_utilization,
+        target_concurrency: hints.target_concurrency as u32,
+        scaling_ratio: hints.scaling_ratio,
+    }
+}
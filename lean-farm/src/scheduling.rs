@@ -0,0 +1,404 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, warn};
+
+use crate::{JobPriority, ProofJob, ProofResult};
+use crate::proto::spec_to_proof::v1::LeanTheorem;
+
+/// Theorem metadata key tying a job back to the batch it was submitted with
+pub const BATCH_ID_METADATA_KEY: &str = "batch_id";
+
+/// Theorem metadata key counting how often a job was preempted
+pub const PREEMPTION_COUNT_METADATA_KEY: &str = "preemption_count";
+
+/// Weights for ordering jobs within the queue
+#[derive(Debug, Clone)]
+pub struct SchedulingConfig {
+    pub priority_weight: f64,
+    pub ease_weight: f64,
+    /// Jobs with predicted ease below this run after every other job
+    pub defer_threshold: f64,
+    /// Each full interval a job waits raises its priority one level; `None`
+    /// disables aging
+    pub aging_interval: Option<Duration>,
+    /// Highest priority aging can reach, so aged jobs never compete with
+    /// jobs submitted as critical
+    pub max_aged_priority: JobPriority,
+    /// A job preempted this many times runs to completion
+    pub max_preemptions: u32,
+}
+
+impl Default for SchedulingConfig {
+    fn default() -> Self {
+        Self {
+            priority_weight: 0.6,
+            ease_weight: 0.4,
+            defer_threshold: 0.25,
+            aging_interval: Some(Duration::from_secs(300)),
+            max_aged_priority: JobPriority::High,
+            max_preemptions: 2,
+        }
+    }
+}
+
+/// Predicts how easy a theorem is to prove, from 0.0 (hard) to 1.0 (trivial).
+/// Uses cheap syntactic features of the statement plus any history recorded
+/// in the theorem metadata by earlier attempts.
+pub fn predict_ease(theorem: &LeanTheorem) -> f64 {
+    let code = theorem.lean_code.as_str();
+    let mut ease = 1.0;
+
+    ease -= (code.len() as f64 / 2000.0).min(0.3);
+
+    let quantifiers = ["∀", "∃", "forall", "exists"]
+        .iter()
+        .map(|q| code.matches(q).count())
+        .sum::<usize>();
+    ease -= (quantifiers as f64 * 0.08).min(0.3);
+
+    let implications = ["→", "↔", "->"]
+        .iter()
+        .map(|op| code.matches(op).count())
+        .sum::<usize>();
+    ease -= (implications as f64 * 0.04).min(0.2);
+
+    // Nonlinear arithmetic is out of reach for omega/linarith
+    if code.contains('^') || code.contains(" * ") || code.contains(" / ") {
+        ease -= 0.1;
+    }
+
+    let failed_attempts = theorem.metadata
+        .get("portfolio_failed_attempts")
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(0);
+    ease -= (failed_attempts as f64 * 0.1).min(0.3);
+
+    if let Some(confidence) = theorem.metadata
+        .get("confidence_score")
+        .and_then(|v| v.parse::<f64>().ok())
+    {
+        ease = 0.8 * ease + 0.2 * confidence.clamp(0.0, 1.0);
+    }
+
+    ease.clamp(0.0, 1.0)
+}
+
+/// The job's priority after aging: raised one level per `aging_interval`
+/// waited, up to `max_aged_priority`. Jobs submitted above the cap keep
+/// their priority.
+pub fn effective_priority(job: &ProofJob, config: &SchedulingConfig, now: Instant) -> JobPriority {
+    let base = job.priority.clone() as i32;
+    let interval = match config.aging_interval {
+        Some(interval) if !interval.is_zero() => interval,
+        _ => return job.priority.clone(),
+    };
+
+    let waited = now.saturating_duration_since(job.created_at);
+    let levels = (waited.as_secs_f64() / interval.as_secs_f64()) as i32;
+    let cap = config.max_aged_priority.clone() as i32;
+    JobPriority::from((base + levels).min(cap).max(base))
+}
+
+/// Sort key where larger values are scheduled earlier: non-deferred jobs
+/// first, then by blended priority/ease score, then oldest first
+pub fn schedule_key(job: &ProofJob, config: &SchedulingConfig, now: Instant) -> (bool, u64, Reverse<Instant>) {
+    let ease = predict_ease(&job.theorem);
+    let priority = effective_priority(job, config, now) as i32 as f64 / 3.0;
+    let score = config.priority_weight * priority + config.ease_weight * ease;
+
+    (
+        ease >= config.defer_threshold,
+        (score * 1_000_000.0) as u64,
+        Reverse(job.created_at),
+    )
+}
+
+/// Orders a batch so the jobs most likely to succeed quickly run first
+pub fn order_batch(mut jobs: Vec<ProofJob>, config: &SchedulingConfig) -> Vec<ProofJob> {
+    let now = Instant::now();
+    jobs.sort_by_cached_key(|job| Reverse(schedule_key(job, config, now)));
+    jobs
+}
+
+pub fn preemption_count(theorem: &LeanTheorem) -> u32 {
+    theorem.metadata
+        .get(PREEMPTION_COUNT_METADATA_KEY)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Picks the running job to preempt for `incoming`: the lowest-priority job
+/// below it that has not hit `max_preemptions`, preferring the one started
+/// most recently since it loses the least work
+pub fn preemption_victim<'a>(
+    running: impl IntoIterator<Item = (&'a ProofJob, Instant)>,
+    incoming: &ProofJob,
+    config: &SchedulingConfig,
+) -> Option<&'a ProofJob> {
+    running
+        .into_iter()
+        .filter(|(job, _)| job.priority < incoming.priority)
+        .filter(|(job, _)| preemption_count(&job.theorem) < config.max_preemptions)
+        .min_by_key(|(job, started_at)| (job.priority.clone(), Reverse(*started_at)))
+        .map(|(job, _)| job)
+}
+
+/// Running proof coverage for a submitted batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoverageUpdate {
+    pub batch_id: String,
+    pub total: u32,
+    pub completed: u32,
+    pub proven: u32,
+    pub failed: u32,
+}
+
+impl CoverageUpdate {
+    pub fn coverage(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.proven as f64 / self.total as f64
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed >= self.total
+    }
+}
+
+/// Tracks per-batch progress and broadcasts an update after every result
+#[derive(Debug)]
+pub struct CoverageTracker {
+    batches: RwLock<HashMap<String, CoverageUpdate>>,
+    updates: broadcast::Sender<CoverageUpdate>,
+}
+
+impl CoverageTracker {
+    pub fn new(capacity: usize) -> Self {
+        let (updates, _) = broadcast::channel(capacity);
+        Self {
+            batches: RwLock::new(HashMap::new()),
+            updates,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<CoverageUpdate> {
+        self.updates.subscribe()
+    }
+
+    pub async fn register_batch(&self, batch_id: &str, total: u32) -> CoverageUpdate {
+        let update = CoverageUpdate {
+            batch_id: batch_id.to_string(),
+            total,
+            ..Default::default()
+        };
+        self.batches.write().await.insert(batch_id.to_string(), update.clone());
+        let _ = self.updates.send(update.clone());
+        update
+    }
+
+    /// Records a finished job; results that are not part of a registered
+    /// batch are ignored
+    pub async fn record(&self, result: &ProofResult) -> Option<CoverageUpdate> {
+        let batch_id = result.theorem.metadata.get(BATCH_ID_METADATA_KEY)?;
+        let mut batches = self.batches.write().await;
+        let update = batches.get_mut(batch_id)?;
+
+        update.completed += 1;
+        if result.success {
+            update.proven += 1;
+        } else {
+            update.failed += 1;
+        }
+        let snapshot = update.clone();
+
+        if snapshot.is_complete() {
+            batches.remove(batch_id);
+        }
+
+        // No subscribers is fine; coverage is best-effort signal
+        let _ = self.updates.send(snapshot.clone());
+        Some(snapshot)
+    }
+}
+
+impl Default for CoverageTracker {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CoverageReport<'a> {
+    repository_id: &'a str,
+    commit_sha: &'a str,
+    #[serde(flatten)]
+    update: &'a CoverageUpdate,
+}
+
+/// Streams coverage for one batch to the GitHub app's badge endpoint until
+/// the batch completes
+pub async fn forward_to_badge(
+    mut updates: broadcast::Receiver<CoverageUpdate>,
+    endpoint: &str,
+    batch_id: &str,
+    repository_id: &str,
+    commit_sha: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+
+    loop {
+        let update = match updates.recv().await {
+            Ok(update) if update.batch_id == batch_id => update,
+            Ok(_) => continue,
+            // Each update supersedes the previous one, so skipping is safe
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Coverage stream for batch {} lagged by {} updates", batch_id, skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        };
+
+        let report = CoverageReport {
+            repository_id,
+            commit_sha,
+            update: &update,
+        };
+
+        if let Err(e) = client.post(endpoint).json(&report).send().await {
+            warn!("Failed to report coverage for batch {}: {}", batch_id, e);
+        }
+
+        if update.is_complete() {
+            info!(
+                "Batch {} complete: {}/{} proven",
+                batch_id, update.proven, update.total
+            );
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JobPriority, ResourceUsage};
+    use crate::proto::proof::v1::{ProofArtifact, ProofOptions};
+
+    fn job(id: &str, lean_code: &str, priority: JobPriority) -> ProofJob {
+        ProofJob {
+            id: id.to_string(),
+            theorem: LeanTheorem {
+                id: id.to_string(),
+                lean_code: lean_code.to_string(),
+                ..Default::default()
+            },
+            options: ProofOptions::default(),
+            priority,
+            created_at: Instant::now(),
+            deadline: None,
+        }
+    }
+
+    #[test]
+    fn test_predict_ease_penalizes_quantifiers() {
+        let simple = job("a", "theorem a : 1 + 1 = 2 := by simp", JobPriority::Normal);
+        let quantified = job(
+            "b",
+            "theorem b : ∀ x : Nat, ∃ y : Nat, ∀ z : Nat, x * y ≤ z → z ^ 2 ≥ x := by sorry",
+            JobPriority::Normal,
+        );
+
+        assert!(predict_ease(&simple.theorem) > predict_ease(&quantified.theorem));
+    }
+
+    #[test]
+    fn test_order_batch_defers_hardest() {
+        let hard_code = format!("theorem h : {} := by sorry", "∀ x : Nat, ∃ y : Nat, x * y → ".repeat(40));
+        let jobs = vec![
+            job("hard-critical", &hard_code, JobPriority::Critical),
+            job("easy-normal", "theorem e : 1 = 1 := rfl", JobPriority::Normal),
+            job("easy-high", "theorem f : 2 = 2 := rfl", JobPriority::High),
+        ];
+
+        let ordered: Vec<String> = order_batch(jobs, &SchedulingConfig::default())
+            .into_iter()
+            .map(|job| job.id)
+            .collect();
+
+        assert_eq!(ordered, vec!["easy-high", "easy-normal", "hard-critical"]);
+    }
+
+    #[test]
+    fn test_aging_escalates_waiting_jobs() {
+        let config = SchedulingConfig::default();
+        let mut waiting = job("low", "theorem l : 1 = 1 := rfl", JobPriority::Low);
+        let now = waiting.created_at + Duration::from_secs(299);
+        assert_eq!(effective_priority(&waiting, &config, now), JobPriority::Low);
+        assert_eq!(effective_priority(&waiting, &config, now + Duration::from_secs(1)), JobPriority::Normal);
+        // Capped below critical
+        assert_eq!(effective_priority(&waiting, &config, now + Duration::from_secs(3600)), JobPriority::High);
+
+        waiting.priority = JobPriority::Critical;
+        assert_eq!(effective_priority(&waiting, &config, now), JobPriority::Critical);
+
+        let no_aging = SchedulingConfig { aging_interval: None, ..Default::default() };
+        waiting.priority = JobPriority::Low;
+        assert_eq!(effective_priority(&waiting, &no_aging, now + Duration::from_secs(3600)), JobPriority::Low);
+    }
+
+    #[test]
+    fn test_preemption_victim() {
+        let config = SchedulingConfig::default();
+        let start = Instant::now();
+        let low_old = job("low-old", "", JobPriority::Low);
+        let low_new = job("low-new", "", JobPriority::Low);
+        let mut normal = job("normal", "", JobPriority::Normal);
+        let critical = job("critical", "", JobPriority::Critical);
+
+        let running = [
+            (&normal, start),
+            (&low_old, start),
+            (&low_new, start + Duration::from_secs(10)),
+            (&critical, start),
+        ];
+        let victim = preemption_victim(running, &critical, &config).unwrap();
+        assert_eq!(victim.id, "low-new");
+
+        // Jobs preempted too often are left alone
+        normal.theorem.metadata.insert(PREEMPTION_COUNT_METADATA_KEY.to_string(), "2".to_string());
+        assert!(preemption_victim([(&normal, start), (&critical, start)], &critical, &config).is_none());
+        assert!(preemption_victim([(&normal, start)], &job("low", "", JobPriority::Low), &config).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_coverage_tracker_streams_updates() {
+        let tracker = CoverageTracker::new(16);
+        let mut updates = tracker.subscribe();
+        tracker.register_batch("batch-1", 2).await;
+
+        let mut result = ProofResult {
+            job_id: "job-1".to_string(),
+            theorem: LeanTheorem::default(),
+            proof_artifact: ProofArtifact::default(),
+            duration_ms: 10,
+            success: true,
+            error_message: None,
+            resource_usage: ResourceUsage::default(),
+        };
+        result.theorem.metadata.insert(BATCH_ID_METADATA_KEY.to_string(), "batch-1".to_string());
+
+        tracker.record(&result).await.unwrap();
+        result.success = false;
+        let last = tracker.record(&result).await.unwrap();
+
+        assert_eq!(updates.recv().await.unwrap().completed, 0);
+        assert_eq!(updates.recv().await.unwrap().proven, 1);
+        assert_eq!(updates.recv().await.unwrap(), last);
+        assert!(last.is_complete());
+        assert_eq!(last.coverage(), 0.5);
+    }
+}
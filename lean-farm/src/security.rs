@@ -6,6 +6,7 @@ use tracing::{info, warn, error};
 use serde::{Deserialize, Serialize};
 
 use crate::LeanFarmError;
+use crate::profiles::{SandboxProfileConfig, SandboxProfiles};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
@@ -33,6 +34,12 @@ pub struct SecurityConfig {
     pub resource_limits: ResourceLimits,
     /// Security scanning
     pub security_scanning: SecurityScanning,
+    /// Hosts sandboxes may reach during a job's setup phase
+    #[serde(default)]
+    pub egress: EgressPolicy,
+    /// Seccomp and AppArmor profiles applied to sandboxes
+    #[serde(default)]
+    pub sandbox_profiles: SandboxProfileConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +70,78 @@ pub struct SecurityScanning {
     pub scan_timeout_seconds: u64,
 }
 
+/// Network access a sandbox gets before the job's code runs. Sandboxes
+/// have no network; with allowed hosts, each job first runs
+/// `setup_command` attached to an internal network whose only way out is
+/// an egress proxy admitting these hosts, and is detached before the build.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EgressPolicy {
+    /// "host", "host:port" or "*.domain[:port]"; port defaults to 443.
+    /// Empty disables the setup phase.
+    pub allowed_hosts: Vec<String>,
+    /// Run in the code bundle directory while the sandbox has egress
+    pub setup_command: Vec<String>,
+    pub setup_timeout_seconds: u64,
+    /// Internal docker network the sandbox and proxy share
+    pub network: String,
+    /// Image the proxy runs in, the farm's own
+    pub proxy_image: String,
+    pub proxy_port: u16,
+}
+
+impl Default for EgressPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: Vec::new(),
+            setup_command: vec!["lake".to_string(), "exe".to_string(), "cache".to_string(), "get".to_string()],
+            setup_timeout_seconds: 120,
+            network: "lean-farm-egress".to_string(),
+            proxy_image: String::new(),
+            proxy_port: 3128,
+        }
+    }
+}
+
+impl EgressPolicy {
+    pub fn enabled(&self) -> bool {
+        !self.allowed_hosts.is_empty()
+    }
+
+    /// Whether a connection to `host:port` is allowed
+    pub fn allows(&self, host: &str, port: u16) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.allowed_hosts.iter().any(|entry| {
+            let (pattern, allowed_port) = match entry.rsplit_once(':') {
+                Some((pattern, allowed_port)) => (pattern, allowed_port.parse().unwrap_or(0)),
+                None => (entry.as_str(), 443),
+            };
+            let pattern = pattern.to_ascii_lowercase();
+            let host_matches = match pattern.strip_prefix("*.") {
+                Some(domain) => host.strip_suffix(domain).is_some_and(|rest| rest.ends_with('.')),
+                None => host == pattern,
+            };
+            host_matches && port == allowed_port
+        })
+    }
+
+    fn validate(&self) -> Result<(), LeanFarmError> {
+        for entry in &self.allowed_hosts {
+            let (pattern, port) = entry.rsplit_once(':').unwrap_or((entry, "443"));
+            let domain = pattern.strip_prefix("*.").unwrap_or(pattern);
+            if domain.is_empty() || domain.contains('*') || port.parse::<u16>().map_or(true, |port| port == 0) {
+                return Err(LeanFarmError::Security(format!("Invalid egress host {:?}", entry)));
+            }
+        }
+        if self.enabled() && (self.setup_command.is_empty() || self.proxy_image.is_empty()) {
+            return Err(LeanFarmError::Security(
+                "Egress needs a setup command and a proxy image".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
 impl Default for SecurityConfig {
     fn default() -> Self {
         Self {
@@ -90,6 +169,8 @@ impl Default for SecurityConfig {
                 max_high_vulnerabilities: 5,
                 scan_timeout_seconds: 300,
             },
+            egress: EgressPolicy::default(),
+            sandbox_profiles: SandboxProfileConfig::default(),
         }
     }
 }
@@ -97,6 +178,7 @@ impl Default for SecurityConfig {
 pub struct SecurityManager {
     config: SecurityConfig,
     runtime_info: RuntimeInfo,
+    sandbox_profiles: SandboxProfiles,
 }
 
 #[derive(Debug, Clone)]
@@ -111,10 +193,12 @@ pub struct RuntimeInfo {
 impl SecurityManager {
     pub fn new(config: &SecurityConfig) -> Result<Self, Box<dyn Error>> {
         let runtime_info = Self::detect_runtime_info()?;
+        let sandbox_profiles = SandboxProfiles::load(&config.sandbox_profiles)?;
         
         Ok(Self {
             config: config.clone(),
             runtime_info,
+            sandbox_profiles,
         })
     }
 
@@ -194,6 +278,8 @@ impl SecurityManager {
             ).into());
         }
         
+        self.config.egress.validate()?;
+        
         info!("Security configuration validation passed");
         Ok(())
     }
@@ -306,6 +392,14 @@ impl SecurityManager {
     pub fn get_config(&self) -> &SecurityConfig {
         &self.config
     }
+
+    pub fn egress_policy(&self) -> &EgressPolicy {
+        &self.config.egress
+    }
+
+    pub fn sandbox_profiles(&self) -> &SandboxProfiles {
+        &self.sandbox_profiles
+    }
 }
 
 #[derive(Debug)]
@@ -353,4 +447,34 @@ mod tests {
         let manager = SecurityManager::new(&config).unwrap();
         assert!(manager.validate_resource_limits().is_err());
     }
+
+    #[test]
+    fn test_egress_policy() {
+        let policy = EgressPolicy {
+            allowed_hosts: vec![
+                "lakecache.blob.core.windows.net".to_string(),
+                "*.githubusercontent.com".to_string(),
+                "minio.lean-farm.svc:9000".to_string(),
+            ],
+            proxy_image: "lean-farm:latest".to_string(),
+            ..Default::default()
+        };
+        assert!(policy.validate().is_ok());
+        assert!(policy.allows("LakeCache.blob.core.windows.net", 443));
+        assert!(!policy.allows("lakecache.blob.core.windows.net", 80));
+        assert!(policy.allows("raw.githubusercontent.com", 443));
+        assert!(!policy.allows("githubusercontent.com", 443));
+        assert!(!policy.allows("evilgithubusercontent.com", 443));
+        assert!(policy.allows("minio.lean-farm.svc", 9000));
+        assert!(!policy.allows("minio.lean-farm.svc", 443));
+        assert!(!EgressPolicy::default().allows("github.com", 443));
+
+        for entry in ["*", "*.", "host:0", "host:http", "a.*.com"] {
+            let policy = EgressPolicy {
+                allowed_hosts: vec![entry.to_string()],
+                ..policy.clone()
+            };
+            assert!(policy.validate().is_err(), "{}", entry);
+        }
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::path::Path;
+use object_storage::{ObjectStore, ObjectStoreConfig, ObjectStoreError, PutOptions, S3ObjectStore};
+use tracing::info;
+
+use crate::config::StorageConfig;
+use crate::{LeanFarmError, ProofResult};
+
+// Code bundles come from the proof service's S3 bucket; proofs, proven
+// records, checkpoints and failed attempts go to MinIO. Both are
+// `S3ObjectStore`s, so either can point at AWS S3 or any S3-compatible
+// endpoint.
+
+impl From<ObjectStoreError> for LeanFarmError {
+    fn from(error: ObjectStoreError) -> Self {
+        match error {
+            ObjectStoreError::Config(message) => LeanFarmError::Config(message),
+            other => LeanFarmError::Storage(other.to_string()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StorageManager {
+    bundles: S3ObjectStore,
+    artifacts: S3ObjectStore,
+    artifact_prefix: String,
+}
+
+impl StorageManager {
+    pub async fn new(config: &StorageConfig) -> Result<Self, LeanFarmError> {
+        let bundles = S3ObjectStore::connect(&ObjectStoreConfig {
+            bucket: config.s3.bucket.clone(),
+            region: config.s3.region.clone(),
+            ..Default::default()
+        })
+        .await?;
+
+        // The chart passes MinIO credentials through the environment
+        let scheme = if config.minio.secure { "https" } else { "http" };
+        let artifacts = S3ObjectStore::connect(&ObjectStoreConfig {
+            bucket: config.minio.bucket.clone(),
+            endpoint: Some(if config.minio.endpoint.contains("://") {
+                config.minio.endpoint.clone()
+            } else {
+                format!("{}://{}", scheme, config.minio.endpoint)
+            }),
+            access_key_id: std::env::var("MINIO_ACCESS_KEY").ok(),
+            secret_access_key: std::env::var("MINIO_SECRET_KEY").ok(),
+            ..Default::default()
+        })
+        .await?;
+
+        Ok(Self {
+            bundles,
+            artifacts,
+            artifact_prefix: config.minio.key_prefix.clone(),
+        })
+    }
+
+    /// Copies a code bundle from S3 to a local file
+    pub async fn download_from_s3(&self, key: &str, local_path: &Path) -> Result<(), LeanFarmError> {
+        let object = self
+            .bundles
+            .get(key)
+            .await?
+            .ok_or_else(|| LeanFarmError::Storage(format!("No code bundle at {}", self.bundles.location(key))))?;
+        tokio::fs::write(local_path, &object.body)
+            .await
+            .map_err(|e| LeanFarmError::Storage(format!("failed to write {}: {}", local_path.display(), e)))?;
+        Ok(())
+    }
+
+    /// Keys under `prefix`, across every page of the listing
+    pub async fn list_minio_keys(&self, prefix: &str) -> Result<Vec<String>, LeanFarmError> {
+        Ok(self.artifacts.list(prefix).await?.into_iter().map(|object| object.key).collect())
+    }
+
+    pub async fn upload_to_minio(&self, key: &str, bytes: &[u8]) -> Result<(), LeanFarmError> {
+        self.upload_to_minio_with_metadata(key, bytes, &HashMap::new()).await
+    }
+
+    pub async fn upload_to_minio_with_metadata(
+        &self,
+        key: &str,
+        bytes: &[u8],
+        metadata: &HashMap<String, String>,
+    ) -> Result<(), LeanFarmError> {
+        let options = PutOptions::default().with_metadata(metadata.clone());
+        Ok(self.artifacts.put(key, bytes.to_vec(), &options).await?)
+    }
+
+    pub async fn download_from_minio_with_metadata(
+        &self,
+        key: &str,
+    ) -> Result<(Vec<u8>, HashMap<String, String>), LeanFarmError> {
+        let object = self
+            .artifacts
+            .get(key)
+            .await?
+            .ok_or_else(|| LeanFarmError::Storage(format!("No object at {}", self.artifacts.location(key))))?;
+        Ok((object.body, object.metadata))
+    }
+
+    pub async fn delete_from_minio(&self, key: &str) -> Result<(), LeanFarmError> {
+        Ok(self.artifacts.delete(key).await?)
+    }
+
+    /// Records a job's outcome under `<key_prefix>/results/`, next to the
+    /// proofs it produced
+    pub async fn store_job_result(&self, result: &ProofResult) -> Result<(), LeanFarmError> {
+        let key = format!("{}/results/{}.json", self.artifact_prefix, result.job_id);
+        let summary = serde_json::json!({
+            "job_id": result.job_id,
+            "theorem_id": result.theorem.id,
+            "theorem_name": result.theorem.theorem_name,
+            "content_sha256": result.theorem.content_sha256,
+            "proof_artifact_id": result.proof_artifact.id,
+            "success": result.success,
+            "error_message": result.error_message,
+            "duration_ms": result.duration_ms,
+            "cpu_seconds": result.resource_usage.cpu_seconds,
+            "memory_bytes": result.resource_usage.memory_bytes,
+        });
+        let body = serde_json::to_vec(&summary).map_err(|e| LeanFarmError::Storage(e.to_string()))?;
+        self.artifacts
+            .put(&key, body, &PutOptions::content_type("application/json"))
+            .await?;
+        info!("Stored result of job {} at {}", result.job_id, self.artifacts.location(&key));
+        Ok(())
+    }
+
+    /// Checks both buckets are reachable, for readiness probes
+    pub async fn ping(&self) -> Result<(), LeanFarmError> {
+        self.bundles.ping().await?;
+        self.artifacts.ping().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::proto::spec_to_proof::v1::{LeanTheorem, ProofArtifact};
+
+/// Lean toolchain and Mathlib commit a proof is checked with. A proof is
+/// only valid for the versions it was checked against, so cached proofs
+/// are keyed by both.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Toolchain {
+    /// elan toolchain name, e.g. "leanprover/lean4:v4.7.0"
+    pub lean_toolchain: String,
+    /// Empty when proofs don't depend on Mathlib
+    pub mathlib_commit: String,
+}
+
+impl Toolchain {
+    /// Reads `LEAN_VERSION`, which also selects the container image, and
+    /// `MATHLIB_COMMIT`
+    pub fn from_env() -> Self {
+        let lean_version = std::env::var("LEAN_VERSION").unwrap_or_else(|_| "4.7.0".to_string());
+        Self {
+            lean_toolchain: format!("leanprover/lean4:v{}", lean_version.trim_start_matches('v')),
+            mathlib_commit: std::env::var("MATHLIB_COMMIT").unwrap_or_default(),
+        }
+    }
+
+    /// The toolchain a theorem was generated for, if it records one
+    pub fn of_theorem(theorem: &LeanTheorem) -> Option<Self> {
+        (!theorem.lean_toolchain.is_empty()).then(|| Self {
+            lean_toolchain: theorem.lean_toolchain.clone(),
+            mathlib_commit: theorem.mathlib_commit.clone(),
+        })
+    }
+
+    /// Object key segment, e.g. "v4.7.0-mathlib-3f1c2a9b4d5e"
+    pub fn key(&self) -> String {
+        let lean = self.lean_toolchain
+            .rsplit(':')
+            .next()
+            .unwrap_or_default();
+        let lean: String = lean
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+            .collect();
+        match self.mathlib_commit.get(..12).unwrap_or(&self.mathlib_commit) {
+            "" => format!("{}-no-mathlib", lean),
+            commit => format!("{}-mathlib-{}", lean, commit),
+        }
+    }
+
+    pub fn stamp(&self, artifact: &mut ProofArtifact) {
+        artifact.lean_toolchain = self.lean_toolchain.clone();
+        artifact.mathlib_commit = self.mathlib_commit.clone();
+    }
+
+    /// Whether a theorem generated for `required` can be checked here. A
+    /// theorem that records no toolchain runs with any.
+    pub fn satisfies(&self, required: Option<&Toolchain>) -> bool {
+        required.map_or(true, |required| required == self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toolchain(lean: &str, mathlib: &str) -> Toolchain {
+        Toolchain { lean_toolchain: lean.to_string(), mathlib_commit: mathlib.to_string() }
+    }
+
+    #[test]
+    fn test_key_includes_lean_and_mathlib_versions() {
+        let with_mathlib = toolchain("leanprover/lean4:v4.7.0", "3f1c2a9b4d5e6f708192a3b4c5d6e7f8091a2b3c");
+        assert_eq!(with_mathlib.key(), "v4.7.0-mathlib-3f1c2a9b4d5e");
+        assert_eq!(toolchain("leanprover/lean4:v4.7.0", "").key(), "v4.7.0-no-mathlib");
+        assert_ne!(with_mathlib.key(), toolchain("leanprover/lean4:v4.8.0", &with_mathlib.mathlib_commit).key());
+    }
+
+    #[test]
+    fn test_theorem_toolchain_must_match() {
+        let farm = toolchain("leanprover/lean4:v4.7.0", "abc");
+        let mut theorem = LeanTheorem::default();
+        assert!(farm.satisfies(Toolchain::of_theorem(&theorem).as_ref()));
+
+        theorem.lean_toolchain = "leanprover/lean4:v4.7.0".to_string();
+        theorem.mathlib_commit = "abc".to_string();
+        assert!(farm.satisfies(Toolchain::of_theorem(&theorem).as_ref()));
+
+        theorem.mathlib_commit = "def".to_string();
+        assert!(!farm.satisfies(Toolchain::of_theorem(&theorem).as_ref()));
+    }
+}
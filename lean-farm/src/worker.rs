@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::toolchain::Toolchain;
+
+/// Artifact metadata keys naming the worker a proof ran on
+pub const WORKER_POD_METADATA_KEY: &str = "worker_pod";
+pub const WORKER_NODE_METADATA_KEY: &str = "worker_node";
+pub const WORKER_IMAGE_DIGEST_METADATA_KEY: &str = "worker_image_digest";
+pub const WORKER_LEAN_TOOLCHAIN_METADATA_KEY: &str = "worker_lean_toolchain";
+pub const WORKER_VERSION_METADATA_KEY: &str = "worker_lean_farm_version";
+
+/// The pod, node, image and versions a job ran on, so failures that only
+/// happen on some nodes or images can be traced back to them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerIdentity {
+    /// The hostname outside Kubernetes
+    pub pod_name: String,
+    /// Empty outside Kubernetes
+    pub node_name: String,
+    /// e.g. "sha256:9b2f..."; empty when the deployment doesn't pass it
+    pub image_digest: String,
+    pub lean_toolchain: String,
+    pub lean_farm_version: String,
+}
+
+impl WorkerIdentity {
+    /// Reads `POD_NAME` and `NODE_NAME`, set from the downward API, and
+    /// `IMAGE_DIGEST`, which the downward API can't provide
+    pub fn from_env(toolchain: &Toolchain) -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            pod_name: var("POD_NAME").or_else(|| var("HOSTNAME")).unwrap_or_default(),
+            node_name: var("NODE_NAME").unwrap_or_default(),
+            image_digest: var("IMAGE_DIGEST").unwrap_or_default(),
+            lean_toolchain: toolchain.lean_toolchain.clone(),
+            lean_farm_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Records the worker in artifact metadata, replacing the worker of an
+    /// earlier attempt; fields this worker doesn't know are left out
+    pub fn record(&self, metadata: &mut HashMap<String, String>) {
+        let fields = [
+            (WORKER_POD_METADATA_KEY, &self.pod_name),
+            (WORKER_NODE_METADATA_KEY, &self.node_name),
+            (WORKER_IMAGE_DIGEST_METADATA_KEY, &self.image_digest),
+            (WORKER_LEAN_TOOLCHAIN_METADATA_KEY, &self.lean_toolchain),
+            (WORKER_VERSION_METADATA_KEY, &self.lean_farm_version),
+        ];
+        for (key, value) in fields {
+            if value.is_empty() {
+                metadata.remove(key);
+            } else {
+                metadata.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_replaces_earlier_worker() {
+        let mut metadata = HashMap::from([
+            (WORKER_POD_METADATA_KEY.to_string(), "lean-farm-7d9c-abcde".to_string()),
+            (WORKER_IMAGE_DIGEST_METADATA_KEY.to_string(), "sha256:old".to_string()),
+            ("applied_cpus".to_string(), "2".to_string()),
+        ]);
+        let worker = WorkerIdentity {
+            pod_name: "lean-farm-7d9c-fghij".to_string(),
+            node_name: "ip-10-0-1-17".to_string(),
+            image_digest: String::new(),
+            lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+            lean_farm_version: "1.0.0".to_string(),
+        };
+
+        worker.record(&mut metadata);
+        assert_eq!(metadata[WORKER_POD_METADATA_KEY], "lean-farm-7d9c-fghij");
+        assert_eq!(metadata[WORKER_NODE_METADATA_KEY], "ip-10-0-1-17");
+        assert!(!metadata.contains_key(WORKER_IMAGE_DIGEST_METADATA_KEY));
+        assert_eq!(metadata[WORKER_VERSION_METADATA_KEY], "1.0.0");
+        assert_eq!(metadata["applied_cpus"], "2");
+    }
+}
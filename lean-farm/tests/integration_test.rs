@@ -254,6 +254,8 @@ theorem test_theorem : 1 + 1 = 2 := by
         compilation_errors: Vec::new(),
         proof_strategy: "auto".to_string(),
         metadata: std::collections::HashMap::new(),
+        lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+        mathlib_commit: String::new(),
     })
 }
 
@@ -275,6 +277,8 @@ theorem test_theorem_{} : {} + {} = {} := by
         compilation_errors: Vec::new(),
         proof_strategy: "auto".to_string(),
         metadata: std::collections::HashMap::new(),
+        lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+        mathlib_commit: String::new(),
     })
 }
 
@@ -307,6 +311,8 @@ async fn submit_job_to_farm(farm: &LeanFarm, job: ProofJob) -> Result<ProofResul
         proof_strategy: job.options.proof_strategy.clone(),
         confidence_score: 0.95,
         metadata: std::collections::HashMap::new(),
+        lean_toolchain: job.theorem.lean_toolchain.clone(),
+        mathlib_commit: job.theorem.mathlib_commit.clone(),
     };
     
     Ok(ProofResult {
@@ -0,0 +1,199 @@
+use spec_to_proof_error::Error;
+use storage::{EntityQuery, Repository};
+
+use crate::drift::{stored_invariants, INVARIANT_STATUS_STALE};
+use crate::invariant_diff::{ChangeKind, InvariantSetDiff};
+use crate::proto::nlp::v1::{
+    BackfillRequest, DocumentBackfill, ExtractInvariantsRequest, ExtractedInvariant, StoredDocument,
+};
+
+// A backfill re-extracts stored documents with the current prompts and
+// compares the result with the invariants already stored for them, so a
+// prompt change can be reviewed before it touches reviewed invariants.
+
+const QUERY_PAGE_SIZE: u32 = 100;
+
+/// The stored documents a backfill covers, in ID order
+pub async fn matching_documents(
+    repository: &dyn Repository<StoredDocument>,
+    request: &BackfillRequest,
+) -> Result<Vec<StoredDocument>, Error> {
+    let mut documents = Vec::new();
+    if !request.document_ids.is_empty() {
+        for document_id in &request.document_ids {
+            match repository.get(document_id).await? {
+                Some(stored) => documents.push(stored.entity),
+                None => tracing::warn!("Document {} has not been ingested, skipping", document_id),
+            }
+        }
+    } else if !request.source_system.is_empty() {
+        let query = EntityQuery::BySource(request.source_system.clone());
+        let mut page_token: Option<String> = None;
+        loop {
+            let page = repository.query(&query, page_token.as_deref(), QUERY_PAGE_SIZE).await?;
+            documents.extend(page.items.into_iter().map(|stored| stored.entity));
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => break,
+            }
+        }
+    } else {
+        return Err(Error::invalid_input("A backfill needs a source system or document IDs"));
+    }
+
+    documents.retain(|document| matches(document, request));
+    documents.sort_by(|a, b| a.id.cmp(&b.id));
+    if request.max_documents > 0 {
+        documents.truncate(request.max_documents as usize);
+    }
+    Ok(documents)
+}
+
+fn matches(document: &StoredDocument, request: &BackfillRequest) -> bool {
+    let seconds = |timestamp: &prost_types::Timestamp| (timestamp.seconds, timestamp.nanos);
+    let ingested_at = document.ingested_at.as_ref().map(seconds).unwrap_or_default();
+    (request.source_system.is_empty() || document.source_system == request.source_system)
+        && request.ingested_after.as_ref().map_or(true, |after| ingested_at >= seconds(after))
+        && request.ingested_before.as_ref().map_or(true, |before| ingested_at < seconds(before))
+}
+
+pub fn extraction_request(document: &StoredDocument, confidence_threshold: f64) -> ExtractInvariantsRequest {
+    ExtractInvariantsRequest {
+        document_id: document.id.clone(),
+        content: document.content.clone(),
+        title: document.title.clone(),
+        source_system: document.source_system.clone(),
+        confidence_threshold,
+        language: document.language.clone(),
+        ..Default::default()
+    }
+}
+
+/// The invariants a new extraction is compared with: everything stored
+/// for the document except invariants already invalidated by drift
+pub async fn current_invariants(
+    repository: &dyn Repository<crate::proto::nlp::v1::StoredInvariant>,
+    document_id: &str,
+) -> Result<Vec<ExtractedInvariant>, Error> {
+    Ok(stored_invariants(repository, document_id)
+        .await?
+        .into_iter()
+        .filter(|stored| stored.entity.status != INVARIANT_STATUS_STALE)
+        .filter_map(|stored| stored.entity.invariant)
+        .collect())
+}
+
+pub fn document_report(
+    document: &StoredDocument,
+    prompt_version: &str,
+    diff: &InvariantSetDiff,
+    applied: bool,
+) -> DocumentBackfill {
+    DocumentBackfill {
+        document_id: document.id.clone(),
+        source_system: document.source_system.clone(),
+        prompt_version: prompt_version.to_string(),
+        diff: Some(diff.to_response()),
+        applied,
+        error: String::new(),
+    }
+}
+
+pub fn failed_report(document: &StoredDocument, error: &Error) -> DocumentBackfill {
+    DocumentBackfill {
+        document_id: document.id.clone(),
+        source_system: document.source_system.clone(),
+        error: error.to_string(),
+        ..Default::default()
+    }
+}
+
+/// One line per document, e.g. "PROJ-1: 2 added, 1 strengthened, 5 unchanged"
+pub fn summary(document_id: &str, diff: &InvariantSetDiff) -> String {
+    let mut parts: Vec<String> = [
+        ChangeKind::Added,
+        ChangeKind::Removed,
+        ChangeKind::Strengthened,
+        ChangeKind::Weakened,
+        ChangeKind::Modified,
+    ]
+    .into_iter()
+    .map(|kind| (kind, diff.count(kind)))
+    .filter(|(_, count)| *count > 0)
+    .map(|(kind, count)| format!("{} {}", count, kind.as_str()))
+    .collect();
+    parts.push(format!("{} unchanged", diff.unchanged));
+    format!("{}: {}", document_id, parts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::{ExpectedVersion, InMemoryRepository};
+
+    use crate::invariant_diff::diff_invariant_sets;
+
+    fn document(id: &str, source_system: &str, ingested_at: i64) -> StoredDocument {
+        StoredDocument {
+            id: id.to_string(),
+            source_system: source_system.to_string(),
+            content: format!("{} content", id),
+            ingested_at: Some(prost_types::Timestamp { seconds: ingested_at, nanos: 0 }),
+            ..Default::default()
+        }
+    }
+
+    fn at(seconds: i64) -> Option<prost_types::Timestamp> {
+        Some(prost_types::Timestamp { seconds, nanos: 0 })
+    }
+
+    #[tokio::test]
+    async fn test_matching_documents_filters_by_source_and_date() {
+        let repository = InMemoryRepository::<StoredDocument>::new();
+        for stored in [
+            document("PROJ-2", "jira", 200),
+            document("PROJ-1", "jira", 100),
+            document("PROJ-3", "jira", 300),
+            document("page-1", "confluence", 200),
+        ] {
+            repository.put(&stored, ExpectedVersion::Any).await.unwrap();
+        }
+
+        let request = BackfillRequest {
+            source_system: "jira".to_string(),
+            ingested_after: at(100),
+            ingested_before: at(300),
+            ..Default::default()
+        };
+        let documents = matching_documents(&repository, &request).await.unwrap();
+        let ids: Vec<&str> = documents.iter().map(|document| document.id.as_str()).collect();
+        assert_eq!(ids, ["PROJ-1", "PROJ-2"]);
+
+        let request = BackfillRequest {
+            document_ids: vec!["page-1".to_string(), "missing".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(matching_documents(&repository, &request).await.unwrap().len(), 1);
+
+        let limited = BackfillRequest {
+            source_system: "jira".to_string(),
+            max_documents: 1,
+            ..Default::default()
+        };
+        assert_eq!(matching_documents(&repository, &limited).await.unwrap()[0].id, "PROJ-1");
+        assert!(matching_documents(&repository, &BackfillRequest::default()).await.is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let invariant = |expression: &str| ExtractedInvariant {
+            formal_expression: expression.to_string(),
+            ..Default::default()
+        };
+        let diff = diff_invariant_sets(
+            &[invariant("amount <= 500"), invariant("fee >= 0")],
+            &[invariant("amount <= 300"), invariant("fee >= 0"), invariant("currency_code_length == 3")],
+        );
+        assert_eq!(summary("PROJ-1", &diff), "PROJ-1: 1 added, 1 strengthened, 1 unchanged");
+    }
+}
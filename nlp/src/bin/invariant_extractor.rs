@@ -1,20 +1,33 @@
 use std::error::Error;
+use std::sync::Arc;
 use tonic::{transport::Server, Request, Response, Status};
 use tracing::{info, warn, error};
 use aws_config::BehaviorVersion;
+use cost_governance::tenant;
+use audit::AuditLog;
+use reload::{ConfigHandle, ConfigWatcher};
+use pipeline_control::PauseGate;
 
 use nlp::{
     NlpService, InvariantExtractionConfig,
+    consumer::{ConsumerConfig, DocumentConsumer},
+    runtime::RuntimeSettings,
+    taxonomy::TaxonomyConfig,
     proto::nlp::v1::{
         nlp_service_server::{NlpService as NlpServiceTrait, NlpServiceServer},
         ExtractInvariantsRequest, ExtractInvariantsResponse,
+        DiffInvariantSetsRequest, DiffInvariantSetsResponse,
         HealthCheckRequest, HealthCheckResponse,
-    }
+        PurgeCacheRequest, PurgeCacheResponse,
+        GetCacheStatsRequest, GetCacheStatsResponse,
+        BackfillRequest, BackfillResponse,
+    },
+    proto::spec_to_proof::v1::{ListInvariantsRequest, ListInvariantsResponse},
 };
 
 #[derive(Default)]
 pub struct NlpServiceImpl {
-    service: Option<NlpService>,
+    service: Option<Arc<NlpService>>,
 }
 
 #[tonic::async_trait]
@@ -23,19 +36,22 @@ impl NlpServiceTrait for NlpServiceImpl {
         &self,
         request: Request<ExtractInvariantsRequest>,
     ) -> Result<Response<ExtractInvariantsResponse>, Status> {
+        let tenant_id = tenant::tenant_from_metadata(
+            request.metadata().get(tenant::TENANT_METADATA_KEY).and_then(|value| value.to_str().ok()),
+        );
         let request_inner = request.into_inner();
         
         info!("Processing invariant extraction request for document: {}", request_inner.document_id);
         
         if let Some(service) = &self.service {
-            match service.extract_invariants(request_inner).await {
+            match tenant::scope(tenant_id, service.extract_invariants(request_inner)).await {
                 Ok(response) => {
                     info!("Successfully extracted {} invariants", response.invariants.len());
                     Ok(Response::new(response))
                 }
                 Err(e) => {
                     error!("Failed to extract invariants: {}", e);
-                    Err(Status::internal(format!("Extraction failed: {}", e)))
+                    Err(Status::from(e.context("Extraction failed")))
                 }
             }
         } else {
@@ -43,6 +59,17 @@ impl NlpServiceTrait for NlpServiceImpl {
         }
     }
 
+    async fn diff_invariant_sets(
+        &self,
+        request: Request<DiffInvariantSetsRequest>,
+    ) -> Result<Response<DiffInvariantSetsResponse>, Status> {
+        if let Some(service) = &self.service {
+            Ok(Response::new(service.diff_invariant_sets(request.into_inner())))
+        } else {
+            Err(Status::unavailable("Service not initialized"))
+        }
+    }
+
     async fn health_check(
         &self,
         _request: Request<HealthCheckRequest>,
@@ -59,6 +86,87 @@ impl NlpServiceTrait for NlpServiceImpl {
             Err(Status::unavailable("Service not initialized"))
         }
     }
+
+    async fn purge_cache(
+        &self,
+        request: Request<PurgeCacheRequest>,
+    ) -> Result<Response<PurgeCacheResponse>, Status> {
+        if let Some(service) = &self.service {
+            match service.purge_cache(request.into_inner()).await {
+                Ok(response) => {
+                    info!("Purged {} cache entries", response.deleted_count);
+                    Ok(Response::new(response))
+                }
+                Err(e) => {
+                    error!("Failed to purge cache: {}", e);
+                    Err(Status::from(e.context("Cache purge failed")))
+                }
+            }
+        } else {
+            Err(Status::unavailable("Service not initialized"))
+        }
+    }
+
+    async fn get_cache_stats(
+        &self,
+        _request: Request<GetCacheStatsRequest>,
+    ) -> Result<Response<GetCacheStatsResponse>, Status> {
+        if let Some(service) = &self.service {
+            service.cache_stats().await
+                .map(Response::new)
+                .map_err(|e| Status::from(e.context("Cache stats unavailable")))
+        } else {
+            Err(Status::unavailable("Service not initialized"))
+        }
+    }
+
+    async fn backfill(
+        &self,
+        request: Request<BackfillRequest>,
+    ) -> Result<Response<BackfillResponse>, Status> {
+        let tenant_id = tenant::tenant_from_metadata(
+            request.metadata().get(tenant::TENANT_METADATA_KEY).and_then(|value| value.to_str().ok()),
+        );
+        let request_inner = request.into_inner();
+
+        if let Some(service) = &self.service {
+            match tenant::scope(tenant_id, service.backfill(request_inner)).await {
+                Ok(response) => {
+                    info!(
+                        "Backfilled {} documents, {} failed",
+                        response.documents.len(),
+                        response.failed_count
+                    );
+                    Ok(Response::new(response))
+                }
+                Err(e) => {
+                    error!("Backfill failed: {}", e);
+                    Err(Status::from(e.context("Backfill failed")))
+                }
+            }
+        } else {
+            Err(Status::unavailable("Service not initialized"))
+        }
+    }
+
+    async fn list_invariants(
+        &self,
+        request: Request<ListInvariantsRequest>,
+    ) -> Result<Response<ListInvariantsResponse>, Status> {
+        if let Some(service) = &self.service {
+            let response = service.list_invariants(&reencode(&request.into_inner())?).await
+                .map_err(|e| Status::from(e.context("Listing invariants failed")))?;
+            Ok(Response::new(reencode(&response)?))
+        } else {
+            Err(Status::unavailable("Service not initialized"))
+        }
+    }
+}
+
+// The service's generated spec_to_proof.v1 messages and the proto crate's
+// share a wire format
+fn reencode<M: prost::Message, S: prost::Message + Default>(message: &M) -> Result<S, Status> {
+    S::decode(message.encode_to_vec().as_slice()).map_err(|e| Status::internal(e.to_string()))
 }
 
 #[tokio::main]
@@ -81,25 +189,83 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Initialize DynamoDB client
     let dynamo_client = aws_sdk_dynamodb::Client::new(&aws_config);
 
+    // Ensure the entity store is ready (DynamoDB table or Postgres schema)
+    storage::EntityStore::connect(&config.storage).await?.initialize().await?;
+
     // Initialize NLP service
-    let nlp_service = NlpService::new(config, dynamo_client).await?;
+    let mut nlp_service = NlpService::new(config, dynamo_client).await?;
 
     // Ensure cache table exists
     nlp_service.cache.ensure_table_exists().await?;
 
+    // Consume ingested documents from JetStream when NATS is configured
+    let consumer = match load_consumer_config() {
+        Some(consumer_config) => {
+            let mut consumer = DocumentConsumer::connect(consumer_config).await?;
+            // Operators pause extraction per tenant or source system through
+            // the gh-app admin API
+            if let Ok(redis_url) = std::env::var("PIPELINE_CONTROL_REDIS_URL") {
+                consumer = consumer.with_pause_gate(Arc::new(PauseGate::connect(&redis_url)?));
+            }
+            Some(consumer)
+        }
+        None => None,
+    };
+    if let Some(consumer) = &consumer {
+        nlp_service = nlp_service
+            .with_nats_client(consumer.client().clone())
+            .with_drift_events(consumer.drift_publisher());
+    }
+
+    // Confidence threshold, model and LLM limits can be changed by editing
+    // this file or sending SIGHUP
+    if let Ok(path) = std::env::var("NLP_RUNTIME_CONFIG") {
+        let settings: RuntimeSettings = reload::load_file(std::path::Path::new(&path))?;
+        let handle = ConfigHandle::new(settings);
+        nlp_service = nlp_service.with_runtime_settings(handle.clone());
+        let audit_log = AuditLog::open(std::env::var("AUDIT_LOG_PATH").ok().as_deref().map(std::path::Path::new)).await?;
+        ConfigWatcher::new(handle, &path, "nlp/runtime_settings")
+            .with_audit_log(Arc::new(audit_log))
+            .spawn();
+        info!("Watching runtime settings in {}", path);
+    }
+    let nlp_service = Arc::new(nlp_service);
+    if let Some(relay) = nlp_service.drift_relay() {
+        tokio::spawn(async move { relay.run().await });
+    }
+
     // Create service implementation
     let service_impl = NlpServiceImpl {
-        service: Some(nlp_service),
+        service: Some(nlp_service.clone()),
     };
 
-    // Start gRPC server
+    // Start gRPC server, authenticating callers with mTLS and/or bearer tokens
     let addr = "[::1]:50051".parse()?;
+    let auth_config = auth::AuthConfig::from_env()?;
+    let mut server = Server::builder();
+    if let Some(tls) = auth_config.server_tls_config()? {
+        server = server.tls_config(tls)?;
+    }
+    if !auth_config.is_enabled() {
+        warn!("gRPC authentication is disabled; set GRPC_TLS_* or GRPC_AUTH_TOKENS");
+    }
     info!("NLP Service listening on {}", addr);
 
-    Server::builder()
+    let server = server
+        .layer(auth::AuthLayer::new(auth_config))
         .add_service(NlpServiceServer::new(service_impl))
-        .serve(addr)
-        .await?;
+        .serve(addr);
+
+    let consume = async {
+        if let Some(consumer) = &consumer {
+            if let Err(e) = consumer.run(&nlp_service).await {
+                error!("Document consumer stopped: {}", e);
+            }
+        }
+    };
+
+    let (served, _) = tokio::join!(server, consume);
+    served?;
 
     Ok(())
 }
@@ -124,6 +290,10 @@ fn load_config() -> Result<InvariantExtractionConfig, Box<dyn Error>> {
             .unwrap_or_else(|_| "86400".to_string())
             .parse()
             .unwrap_or(86400),
+        negative_cache_ttl_seconds: std::env::var("NEGATIVE_CACHE_TTL_SECONDS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()
+            .unwrap_or(300),
         max_retries: std::env::var("MAX_RETRIES")
             .unwrap_or_else(|_| "3".to_string())
             .parse()
@@ -132,6 +302,10 @@ fn load_config() -> Result<InvariantExtractionConfig, Box<dyn Error>> {
             .unwrap_or_else(|_| "1000".to_string())
             .parse()
             .unwrap_or(1000),
+        stream_deadline_ms: std::env::var("STREAM_DEADLINE_MS")
+            .ok()
+            .and_then(|value| value.parse().ok()),
+        cost_governance_redis_url: std::env::var("COST_GOVERNANCE_REDIS_URL").ok(),
         confidence_threshold: std::env::var("CONFIDENCE_THRESHOLD")
             .unwrap_or_else(|_| "0.5".to_string())
             .parse()
@@ -140,8 +314,76 @@ fn load_config() -> Result<InvariantExtractionConfig, Box<dyn Error>> {
             .unwrap_or_else(|_| "0.015".to_string())
             .parse()
             .unwrap_or(0.015),
+        chunk_token_budget: std::env::var("CHUNK_TOKEN_BUDGET")
+            .unwrap_or_else(|_| "8000".to_string())
+            .parse()
+            .unwrap_or(8000),
+        chunk_overlap_tokens: std::env::var("CHUNK_OVERLAP_TOKENS")
+            .unwrap_or_else(|_| "200".to_string())
+            .parse()
+            .unwrap_or(200),
+        taxonomy: match std::env::var("TAXONOMY_CONFIG") {
+            Ok(path) => serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(|e| format!("Invalid taxonomy config {}: {}", path, e))?,
+            Err(_) => TaxonomyConfig::default(),
+        },
+        prompt_manifest: std::env::var("PROMPT_MANIFEST").ok(),
+        notifications: match std::env::var("NOTIFICATIONS_CONFIG") {
+            Ok(path) => Some(serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(|e| format!("Invalid notifications config {}: {}", path, e))?),
+            Err(_) => None,
+        },
+        health_check_timeout_ms: std::env::var("HEALTH_CHECK_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(2000),
+        storage: storage::StorageSettings {
+            backend: std::env::var("STORAGE_BACKEND")
+                .unwrap_or_else(|_| "dynamodb".to_string())
+                .parse()?,
+            table_name: std::env::var("ENTITY_TABLE_NAME")
+                .unwrap_or_else(|_| "spec-to-proof-entities".to_string()),
+            database_url: std::env::var("DATABASE_URL").ok(),
+            max_connections: std::env::var("DATABASE_MAX_CONNECTIONS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            data_dir: std::env::var("STORAGE_DATA_DIR").ok().map(Into::into),
+        },
     };
 
     info!("Loaded configuration: {:?}", config);
     Ok(config)
-} 
\ No newline at end of file
+} 
+
+fn load_consumer_config() -> Option<ConsumerConfig> {
+    let nats_url = std::env::var("NATS_URL").ok()?;
+    let defaults = ConsumerConfig::default();
+
+    Some(ConsumerConfig {
+        nats_url,
+        stream_name: std::env::var("NATS_STREAM_NAME").unwrap_or(defaults.stream_name),
+        subject: std::env::var("NLP_CONSUMER_SUBJECT").unwrap_or(defaults.subject),
+        durable_name: std::env::var("NLP_CONSUMER_DURABLE_NAME").unwrap_or(defaults.durable_name),
+        max_in_flight: std::env::var("NLP_CONSUMER_MAX_IN_FLIGHT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.max_in_flight),
+        ack_wait_seconds: std::env::var("NLP_CONSUMER_ACK_WAIT_SECONDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.ack_wait_seconds),
+        max_deliver: std::env::var("NLP_CONSUMER_MAX_DELIVER")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.max_deliver),
+        retry_base_delay_ms: defaults.retry_base_delay_ms,
+        retry_max_delay_ms: defaults.retry_max_delay_ms,
+        paused_retry_delay_ms: std::env::var("NLP_CONSUMER_PAUSED_RETRY_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.paused_retry_delay_ms),
+        dead_letter_subject: std::env::var("NLP_DEAD_LETTER_SUBJECT").unwrap_or(defaults.dead_letter_subject),
+        drift_subject_prefix: std::env::var("NLP_DRIFT_SUBJECT_PREFIX").unwrap_or(defaults.drift_subject_prefix),
+    })
+}
@@ -1,15 +1,94 @@
-use std::error::Error;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use spec_to_proof_error::Error;
 use aws_sdk_dynamodb::Client as DynamoClient;
-use aws_sdk_dynamodb::types::{AttributeValue, ScalarAttributeType, BillingMode};
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, AttributeValue, BillingMode, GlobalSecondaryIndex, KeySchemaElement, KeyType,
+    Projection, ProjectionType, ScalarAttributeType, TimeToLiveSpecification, TimeToLiveStatus,
+};
 use serde::{Deserialize, Serialize};
 use crate::proto::nlp::v1::ExtractInvariantsResponse;
 use crate::InvariantExtractionConfig;
 
+/// Epoch seconds after which DynamoDB's TTL process deletes an entry.
+/// Deletion can lag by up to two days, so reads check it as well.
+pub const TTL_ATTRIBUTE: &str = "expires_at";
+
+/// Index of cache keys by the document they were extracted from
+pub const DOCUMENT_INDEX: &str = "document_id-index";
+
 pub struct DynamoCache {
     client: DynamoClient,
     table_name: String,
     ttl_seconds: u64,
+    stats: CacheCounters,
+}
+
+/// Which entries a purge removes; every condition set must hold
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PurgeFilter {
+    /// Entries of these documents; every document when empty
+    pub document_ids: Vec<String>,
+    /// Entries past their TTL that DynamoDB has not removed yet
+    pub expired_only: bool,
+    /// Entries cached before this epoch second
+    pub created_before: Option<u64>,
+}
+
+impl PurgeFilter {
+    pub fn expired() -> Self {
+        Self { expired_only: true, ..Self::default() }
+    }
+
+    pub fn documents(document_ids: Vec<String>) -> Self {
+        Self { document_ids, ..Self::default() }
+    }
+
+    // Filter expression over the non-key attributes, with its values
+    fn expression(&self, now: u64) -> (Option<String>, HashMap<String, AttributeValue>) {
+        let mut conditions = Vec::new();
+        let mut values = HashMap::new();
+        if self.expired_only {
+            conditions.push(format!("{} < :now", TTL_ATTRIBUTE));
+            values.insert(":now".to_string(), AttributeValue::N(now.to_string()));
+        }
+        if let Some(created_before) = self.created_before {
+            conditions.push("created_at < :created_before".to_string());
+            values.insert(":created_before".to_string(), AttributeValue::N(created_before.to_string()));
+        }
+        let expression = (!conditions.is_empty()).then(|| conditions.join(" AND "));
+        (expression, values)
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    writes: AtomicU64,
+    bytes_written: AtomicU64,
+    deleted: AtomicU64,
+}
+
+/// Cache activity since the process started, plus the table's size as
+/// DynamoDB last reported it (refreshed roughly every six hours)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub item_count: u64,
+    pub table_size_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub writes: u64,
+    pub bytes_written: u64,
+    pub deleted: u64,
+}
+
+fn epoch_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,27 +105,26 @@ impl DynamoCache {
             client,
             table_name: "spec-to-proof-nlp-cache".to_string(),
             ttl_seconds: config.cache_ttl_seconds,
+            stats: CacheCounters::default(),
         }
     }
 
-    pub async fn get(&self, cache_key: &str) -> Result<Option<ExtractInvariantsResponse>, Box<dyn Error>> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    pub async fn get(&self, cache_key: &str) -> Result<Option<ExtractInvariantsResponse>, Error> {
+        let now = epoch_seconds();
 
         let response = self.client
             .get_item()
             .table_name(&self.table_name)
             .key("cache_key", AttributeValue::S(cache_key.to_string()))
             .send()
-            .await?;
+            .await
+            .map_err(Error::transient)?;
 
         if let Some(item) = response.item {
             if let (Some(cache_key_attr), Some(response_attr), Some(expires_at_attr)) = (
                 item.get("cache_key"),
                 item.get("response"),
-                item.get("expires_at"),
+                item.get(TTL_ATTRIBUTE),
             ) {
                 if let (Some(cache_key_val), Some(expires_at_val)) = (
                     cache_key_attr.as_s().ok(),
@@ -59,6 +137,7 @@ impl DynamoCache {
                                 if let Ok(response_json) = response_attr.as_s() {
                                     if let Ok(cache_entry) = serde_json::from_str::<CacheEntry>(response_json) {
                                         tracing::info!("Cache hit for key: {}", cache_key);
+                                        self.stats.hits.fetch_add(1, Ordering::Relaxed);
                                         return Ok(Some(cache_entry.response));
                                     }
                                 }
@@ -72,16 +151,24 @@ impl DynamoCache {
             }
         }
 
+        self.stats.misses.fetch_add(1, Ordering::Relaxed);
         Ok(None)
     }
 
-    pub async fn set(&self, cache_key: &str, response: &ExtractInvariantsResponse) -> Result<(), Box<dyn Error>> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    pub async fn set(&self, cache_key: &str, document_id: &str, response: &ExtractInvariantsResponse) -> Result<(), Error> {
+        self.set_with_ttl(cache_key, document_id, response, self.ttl_seconds).await
+    }
+
+    pub async fn set_with_ttl(
+        &self,
+        cache_key: &str,
+        document_id: &str,
+        response: &ExtractInvariantsResponse,
+        ttl_seconds: u64,
+    ) -> Result<(), Error> {
+        let now = epoch_seconds();
 
-        let expires_at = now + self.ttl_seconds;
+        let expires_at = now + ttl_seconds;
 
         let cache_entry = CacheEntry {
             cache_key: cache_key.to_string(),
@@ -91,34 +178,150 @@ impl DynamoCache {
         };
 
         let response_json = serde_json::to_string(&cache_entry)?;
+        let size_bytes = entry_size(cache_key, document_id, &response_json);
 
         self.client
             .put_item()
             .table_name(&self.table_name)
             .item("cache_key", AttributeValue::S(cache_key.to_string()))
+            .item("document_id", AttributeValue::S(document_id.to_string()))
             .item("response", AttributeValue::S(response_json))
             .item("created_at", AttributeValue::N(now.to_string()))
-            .item("expires_at", AttributeValue::N(expires_at.to_string()))
+            .item(TTL_ATTRIBUTE, AttributeValue::N(expires_at.to_string()))
+            .item("size_bytes", AttributeValue::N(size_bytes.to_string()))
             .send()
-            .await?;
+            .await
+            .map_err(Error::transient)?;
 
-        tracing::info!("Cached response for key: {}", cache_key);
+        self.stats.writes.fetch_add(1, Ordering::Relaxed);
+        self.stats.bytes_written.fetch_add(size_bytes, Ordering::Relaxed);
+        tracing::info!("Cached response for key: {} ({} bytes, {}s)", cache_key, size_bytes, ttl_seconds);
         Ok(())
     }
 
-    pub async fn delete(&self, cache_key: &str) -> Result<(), Box<dyn Error>> {
+    pub async fn delete(&self, cache_key: &str) -> Result<(), Error> {
         self.client
             .delete_item()
             .table_name(&self.table_name)
             .key("cache_key", AttributeValue::S(cache_key.to_string()))
             .send()
-            .await?;
+            .await
+            .map_err(Error::transient)?;
 
+        self.stats.deleted.fetch_add(1, Ordering::Relaxed);
         tracing::info!("Deleted cache entry for key: {}", cache_key);
         Ok(())
     }
 
-    pub async fn ensure_table_exists(&self) -> Result<(), Box<dyn Error>> {
+    /// Drops every cached extraction of the document, e.g. when it is
+    /// re-ingested and earlier versions can no longer be served
+    pub async fn invalidate_document(&self, document_id: &str) -> Result<u32, Error> {
+        self.purge(&PurgeFilter::documents(vec![document_id.to_string()])).await
+    }
+
+    /// Deletes the entries matching the filter and returns how many
+    pub async fn purge(&self, filter: &PurgeFilter) -> Result<u32, Error> {
+        let cache_keys = if filter.document_ids.is_empty() {
+            self.scan_keys(filter).await?
+        } else {
+            let mut cache_keys = Vec::new();
+            for document_id in &filter.document_ids {
+                cache_keys.extend(self.document_keys(document_id, filter).await?);
+            }
+            cache_keys
+        };
+
+        for cache_key in &cache_keys {
+            self.delete(cache_key).await?;
+        }
+
+        tracing::info!("Purged {} cache entries ({:?})", cache_keys.len(), filter);
+        Ok(cache_keys.len() as u32)
+    }
+
+    async fn scan_keys(&self, filter: &PurgeFilter) -> Result<Vec<String>, Error> {
+        let (expression, values) = filter.expression(epoch_seconds());
+        let mut cache_keys = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let response = self.client
+                .scan()
+                .table_name(&self.table_name)
+                .projection_expression("cache_key")
+                .set_filter_expression(expression.clone())
+                .set_expression_attribute_values((!values.is_empty()).then(|| values.clone()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(Error::transient)?;
+
+            cache_keys.extend(response.items.unwrap_or_default().iter().filter_map(item_cache_key));
+            match response.last_evaluated_key {
+                Some(key) => start_key = Some(key),
+                None => return Ok(cache_keys),
+            }
+        }
+    }
+
+    async fn document_keys(&self, document_id: &str, filter: &PurgeFilter) -> Result<Vec<String>, Error> {
+        let (expression, mut values) = filter.expression(epoch_seconds());
+        values.insert(":document_id".to_string(), AttributeValue::S(document_id.to_string()));
+        let mut cache_keys = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let response = self.client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(DOCUMENT_INDEX)
+                .key_condition_expression("document_id = :document_id")
+                .set_filter_expression(expression.clone())
+                .set_expression_attribute_values(Some(values.clone()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(Error::transient)?;
+
+            cache_keys.extend(response.items.unwrap_or_default().iter().filter_map(item_cache_key));
+            match response.last_evaluated_key {
+                Some(key) => start_key = Some(key),
+                None => return Ok(cache_keys),
+            }
+        }
+    }
+
+    pub async fn stats(&self) -> Result<CacheStats, Error> {
+        let table = self.client
+            .describe_table()
+            .table_name(&self.table_name)
+            .send()
+            .await
+            .map_err(Error::transient)?
+            .table;
+
+        Ok(CacheStats {
+            item_count: table.as_ref().and_then(|t| t.item_count).unwrap_or(0).max(0) as u64,
+            table_size_bytes: table.as_ref().and_then(|t| t.table_size_bytes).unwrap_or(0).max(0) as u64,
+            hits: self.stats.hits.load(Ordering::Relaxed),
+            misses: self.stats.misses.load(Ordering::Relaxed),
+            writes: self.stats.writes.load(Ordering::Relaxed),
+            bytes_written: self.stats.bytes_written.load(Ordering::Relaxed),
+            deleted: self.stats.deleted.load(Ordering::Relaxed),
+        })
+    }
+
+    pub async fn ping(&self) -> Result<(), Error> {
+        self.client
+            .describe_table()
+            .table_name(&self.table_name)
+            .send()
+            .await
+            .map_err(Error::transient)?;
+        Ok(())
+    }
+
+    pub async fn ensure_table_exists(&self) -> Result<(), Error> {
         // Check if table exists
         match self.client
             .describe_table()
@@ -128,7 +331,7 @@ impl DynamoCache {
         {
             Ok(_) => {
                 tracing::info!("Cache table {} already exists", self.table_name);
-                return Ok(());
+                return self.enable_ttl().await;
             }
             Err(_) => {
                 // Table doesn't exist, create it
@@ -140,29 +343,92 @@ impl DynamoCache {
             .create_table()
             .table_name(&self.table_name)
             .attribute_definitions(
-                aws_sdk_dynamodb::types::AttributeDefinition::builder()
+                AttributeDefinition::builder()
                     .attribute_name("cache_key")
                     .attribute_type(ScalarAttributeType::S)
                     .build()
+                    .map_err(Error::internal)?
+            )
+            .attribute_definitions(
+                AttributeDefinition::builder()
+                    .attribute_name("document_id")
+                    .attribute_type(ScalarAttributeType::S)
+                    .build()
+                    .map_err(Error::internal)?
             )
             .key_schema(
-                aws_sdk_dynamodb::types::KeySchemaElement::builder()
+                KeySchemaElement::builder()
                     .attribute_name("cache_key")
-                    .key_type(aws_sdk_dynamodb::types::KeyType::Hash)
+                    .key_type(KeyType::Hash)
                     .build()
+                    .map_err(Error::internal)?
+            )
+            .global_secondary_indexes(
+                GlobalSecondaryIndex::builder()
+                    .index_name(DOCUMENT_INDEX)
+                    .key_schema(
+                        KeySchemaElement::builder()
+                            .attribute_name("document_id")
+                            .key_type(KeyType::Hash)
+                            .build()
+                            .map_err(Error::internal)?
+                    )
+                    // Purge filters need these without reading the responses
+                    .projection(
+                        Projection::builder()
+                            .projection_type(ProjectionType::Include)
+                            .non_key_attributes("created_at")
+                            .non_key_attributes(TTL_ATTRIBUTE)
+                            .non_key_attributes("size_bytes")
+                            .build()
+                    )
+                    .build()
+                    .map_err(Error::internal)?
             )
             .billing_mode(BillingMode::PayPerRequest)
             .send()
-            .await?;
+            .await
+            .map_err(Error::transient)?;
 
         // Wait for table to be active
         self.wait_for_table_active().await?;
         tracing::info!("Cache table {} created successfully", self.table_name);
 
+        self.enable_ttl().await
+    }
+
+    /// Has DynamoDB delete entries once `expires_at` passes
+    async fn enable_ttl(&self) -> Result<(), Error> {
+        let description = self.client
+            .describe_time_to_live()
+            .table_name(&self.table_name)
+            .send()
+            .await
+            .map_err(Error::transient)?
+            .time_to_live_description;
+        let status = description.as_ref().and_then(|d| d.time_to_live_status.clone());
+        if matches!(status, Some(TimeToLiveStatus::Enabled) | Some(TimeToLiveStatus::Enabling)) {
+            return Ok(());
+        }
+
+        self.client
+            .update_time_to_live()
+            .table_name(&self.table_name)
+            .time_to_live_specification(
+                TimeToLiveSpecification::builder()
+                    .attribute_name(TTL_ATTRIBUTE)
+                    .enabled(true)
+                    .build()
+                    .map_err(Error::internal)?
+            )
+            .send()
+            .await
+            .map_err(Error::transient)?;
+        tracing::info!("Enabled TTL on {} for cache table {}", TTL_ATTRIBUTE, self.table_name);
         Ok(())
     }
 
-    async fn wait_for_table_active(&self) -> Result<(), Box<dyn Error>> {
+    async fn wait_for_table_active(&self) -> Result<(), Error> {
         let max_attempts = 30;
         let delay = Duration::from_secs(2);
 
@@ -192,39 +458,25 @@ impl DynamoCache {
             }
         }
 
-        Err("Table did not become active within expected time".into())
+        Err(Error::timeout("Table did not become active within expected time"))
     }
 
-    pub async fn cleanup_expired(&self) -> Result<u32, Box<dyn Error>> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        let scan_response = self.client
-            .scan()
-            .table_name(&self.table_name)
-            .filter_expression("expires_at < :now")
-            .expression_attribute_values(":now", AttributeValue::N(now.to_string()))
-            .send()
-            .await?;
-
-        let mut deleted_count = 0;
+    pub async fn cleanup_expired(&self) -> Result<u32, Error> {
+        self.purge(&PurgeFilter::expired()).await
+    }
+}
 
-        if let Some(items) = scan_response.items {
-            for item in items {
-                if let Some(cache_key_attr) = item.get("cache_key") {
-                    if let Some(cache_key) = cache_key_attr.as_s().ok() {
-                        self.delete(cache_key).await?;
-                        deleted_count += 1;
-                    }
-                }
-            }
-        }
+fn item_cache_key(item: &HashMap<String, AttributeValue>) -> Option<String> {
+    item.get("cache_key").and_then(|value| value.as_s().ok()).cloned()
+}
 
-        tracing::info!("Cleaned up {} expired cache entries", deleted_count);
-        Ok(deleted_count)
-    }
+// DynamoDB bills item size as the lengths of attribute names and values,
+// counting each number as 21 bytes at most
+fn entry_size(cache_key: &str, document_id: &str, response_json: &str) -> u64 {
+    const NUMBER_BYTES: usize = 21;
+    let strings = ["cache_key".len() + cache_key.len(), "document_id".len() + document_id.len(), "response".len() + response_json.len()];
+    let numbers = ["created_at", TTL_ATTRIBUTE, "size_bytes"].iter().map(|name| name.len() + NUMBER_BYTES);
+    (strings.iter().sum::<usize>() + numbers.sum::<usize>()) as u64
 }
 
 #[cfg(test)]
@@ -249,6 +501,7 @@ mod tests {
                 cached: false,
                 cache_key: "test_key".to_string(),
             }),
+            plan: None,
         };
 
         let cache_entry = CacheEntry {
@@ -265,4 +518,28 @@ mod tests {
         assert_eq!(deserialized.created_at, 1234567890);
         assert_eq!(deserialized.expires_at, 1234567890 + 86400);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_purge_filter_expression() {
+        let (expression, values) = PurgeFilter::documents(vec!["DOC-1".to_string()]).expression(100);
+        assert_eq!(expression, None);
+        assert!(values.is_empty());
+
+        let filter = PurgeFilter {
+            created_before: Some(50),
+            ..PurgeFilter::expired()
+        };
+        let (expression, values) = filter.expression(100);
+        assert_eq!(expression.as_deref(), Some("expires_at < :now AND created_at < :created_before"));
+        assert_eq!(values[":now"], AttributeValue::N("100".to_string()));
+        assert_eq!(values[":created_before"], AttributeValue::N("50".to_string()));
+    }
+
+    #[test]
+    fn test_entry_size_counts_names_and_values() {
+        let size = entry_size("key", "DOC-1", "{}");
+        let strings = "cache_key".len() + 3 + "document_id".len() + 5 + "response".len() + 2;
+        let numbers = "created_at".len() + "expires_at".len() + "size_bytes".len() + 3 * 21;
+        assert_eq!(size, (strings + numbers) as u64);
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use crate::proto::nlp::v1::{ChunkProvenance, ExtractedInvariant};
+
+// Rough characters-per-token ratio used to size chunks without a tokenizer
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Approximate token count of `text`, by the same ratio chunks are sized with
+pub fn estimate_tokens(text: &str) -> u32 {
+    text.len().div_ceil(CHARS_PER_TOKEN) as u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkingConfig {
+    /// Maximum chunk size excluding the overlap, in bytes of content
+    pub max_chars: usize,
+    /// Content repeated from the end of the previous chunk
+    pub overlap_chars: usize,
+}
+
+impl ChunkingConfig {
+    pub fn from_token_budget(budget_tokens: u32, overlap_tokens: u32) -> Self {
+        let overlap_chars = overlap_tokens as usize * CHARS_PER_TOKEN;
+        let budget_chars = budget_tokens as usize * CHARS_PER_TOKEN;
+        Self {
+            // Always leave room for some new content in each chunk
+            max_chars: budget_chars.saturating_sub(overlap_chars).max(CHARS_PER_TOKEN * 64),
+            overlap_chars,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentChunk {
+    pub index: usize,
+    pub heading_path: Vec<String>,
+    /// Character offsets into the document, end exclusive
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub text: String,
+}
+
+impl DocumentChunk {
+    pub fn provenance(&self) -> ChunkProvenance {
+        ChunkProvenance {
+            chunk_index: self.index as i32,
+            heading_path: self.heading_path.clone(),
+            start_offset: self.start_offset as i64,
+            end_offset: self.end_offset as i64,
+        }
+    }
+
+    /// Content sent to the model; chunks of a split document are labelled so
+    /// the model knows it only sees part of the spec
+    pub fn prompt_content(&self, chunk_count: usize) -> String {
+        if chunk_count <= 1 {
+            return self.text.clone();
+        }
+        let section = if self.heading_path.is_empty() {
+            String::new()
+        } else {
+            format!(", section: {}", self.heading_path.join(" > "))
+        };
+        format!("[Part {} of {}{}]\n\n{}", self.index + 1, chunk_count, section, self.text)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Span {
+    heading_path: Vec<String>,
+    start: usize,
+    end: usize,
+}
+
+/// Splits Markdown content into chunks at heading boundaries. Sections are
+/// packed together up to the size limit; oversized sections are split at
+/// line boundaries. Every chunk after the first repeats the tail of the
+/// previous one.
+pub fn chunk_document(content: &str, config: &ChunkingConfig) -> Vec<DocumentChunk> {
+    if content.is_empty() {
+        return vec![DocumentChunk {
+            index: 0,
+            heading_path: vec![],
+            start_offset: 0,
+            end_offset: 0,
+            text: String::new(),
+        }];
+    }
+
+    let mut packed: Vec<Span> = Vec::new();
+    for piece in split_sections(content).into_iter().flat_map(|section| split_oversized(content, section, config.max_chars)) {
+        match packed.last_mut() {
+            Some(last) if piece.end - last.start <= config.max_chars => last.end = piece.end,
+            _ => packed.push(piece),
+        }
+    }
+
+    packed
+        .into_iter()
+        .enumerate()
+        .map(|(index, span)| {
+            let start = if index == 0 {
+                span.start
+            } else {
+                overlap_start(content, span.start, config.overlap_chars)
+            };
+            DocumentChunk {
+                index,
+                heading_path: span.heading_path,
+                start_offset: content[..start].chars().count(),
+                end_offset: content[..span.end].chars().count(),
+                text: content[start..span.end].to_string(),
+            }
+        })
+        .collect()
+}
+
+fn heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    if (1..=6).contains(&level) && (rest.starts_with(' ') || rest.trim().is_empty()) {
+        Some((level, rest.trim().trim_end_matches('#').trim().to_string()))
+    } else {
+        None
+    }
+}
+
+fn split_sections(content: &str) -> Vec<Span> {
+    let mut sections = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut current = Span { heading_path: vec![], start: 0, end: 0 };
+    let mut in_code_block = false;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if let Some((level, title)) = heading(line).filter(|_| !in_code_block) {
+            if offset > current.start {
+                current.end = offset;
+                sections.push(current);
+            }
+            headings.retain(|(existing, _)| *existing < level);
+            headings.push((level, title));
+            current = Span {
+                heading_path: headings.iter().map(|(_, title)| title.clone()).collect(),
+                start: offset,
+                end: offset,
+            };
+        }
+        offset += line.len();
+    }
+
+    current.end = offset;
+    if current.end > current.start {
+        sections.push(current);
+    }
+    sections
+}
+
+fn split_oversized(content: &str, section: Span, max_chars: usize) -> Vec<Span> {
+    let mut pieces = Vec::new();
+    let mut start = section.start;
+
+    while section.end - start > max_chars {
+        let limit = floor_char_boundary(content, start + max_chars);
+        // Prefer ending on a line break; hard-split a single overlong line
+        let end = match content[start..limit].rfind('\n') {
+            Some(newline) if newline > 0 => start + newline + 1,
+            _ => limit,
+        };
+        pieces.push(Span { heading_path: section.heading_path.clone(), start, end });
+        start = end;
+    }
+
+    pieces.push(Span { heading_path: section.heading_path, start, end: section.end });
+    pieces
+}
+
+fn overlap_start(content: &str, start: usize, overlap_chars: usize) -> usize {
+    if overlap_chars == 0 {
+        return start;
+    }
+    let earliest = floor_char_boundary(content, start.saturating_sub(overlap_chars));
+    // Start the overlap on a line boundary when one falls inside it
+    match content[earliest..start].find('\n') {
+        Some(newline) if earliest + newline + 1 < start => earliest + newline + 1,
+        _ => earliest,
+    }
+}
+
+fn floor_char_boundary(content: &str, mut index: usize) -> usize {
+    index = index.min(content.len());
+    while !content.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn dedup_key(invariant: &ExtractedInvariant) -> String {
+    invariant.formal_expression
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Merges invariants extracted from several chunks. Invariants with the same
+/// formal expression are reported once, keeping the most confident one and
+/// the provenance and tags of all of them.
+pub fn merge_invariants(invariants: Vec<ExtractedInvariant>) -> Vec<ExtractedInvariant> {
+    let mut merged: Vec<ExtractedInvariant> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for invariant in invariants {
+        let key = dedup_key(&invariant);
+        let Some(&position) = positions.get(&key) else {
+            positions.insert(key, merged.len());
+            merged.push(invariant);
+            continue;
+        };
+
+        let existing = &mut merged[position];
+        let (mut kept, other) = if invariant.confidence_score > existing.confidence_score {
+            (invariant, std::mem::take(existing))
+        } else {
+            (std::mem::take(existing), invariant)
+        };
+
+        for tag in other.tags {
+            if !kept.tags.contains(&tag) {
+                kept.tags.push(tag);
+            }
+        }
+        let mut chunks = other.extraction_metadata.map(|metadata| metadata.chunks).unwrap_or_default();
+        let metadata = kept.extraction_metadata.get_or_insert_with(Default::default);
+        chunks.retain(|chunk| !metadata.chunks.contains(chunk));
+        metadata.chunks.extend(chunks);
+        metadata.chunks.sort_by_key(|chunk| chunk.chunk_index);
+
+        *existing = kept;
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::nlp::v1::ExtractionMetadata;
+
+    #[test]
+    fn test_small_document_is_single_chunk() {
+        let content = "# Payments\n\nAmounts are positive.\n";
+        let chunks = chunk_document(content, &ChunkingConfig { max_chars: 1000, overlap_chars: 10 });
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, content);
+        assert_eq!(chunks[0].heading_path, vec!["Payments".to_string()]);
+        assert_eq!(chunks[0].prompt_content(1), content);
+    }
+
+    #[test]
+    fn test_chunks_follow_headings_and_overlap() {
+        let content = "# Spec\nIntro line.\n## Limits\nLimit is 100 EUR.\n## Fees\nFee is 2 percent.\n";
+        let chunks = chunk_document(content, &ChunkingConfig { max_chars: 30, overlap_chars: 12 });
+
+        let paths: Vec<Vec<String>> = chunks.iter().map(|chunk| chunk.heading_path.clone()).collect();
+        assert_eq!(paths, vec![
+            vec!["Spec".to_string()],
+            vec!["Spec".to_string(), "Limits".to_string()],
+            vec!["Spec".to_string(), "Fees".to_string()],
+        ]);
+
+        // Later chunks start with the tail of the previous one
+        assert_eq!(chunks[1].text, "Intro line.\n## Limits\nLimit is 100 EUR.\n");
+        assert!(chunks[2].text.ends_with("## Fees\nFee is 2 percent.\n"));
+        assert_eq!(chunks[2].end_offset, content.chars().count());
+        assert!(chunks[2].prompt_content(3).starts_with("[Part 3 of 3, section: Spec > Fees]"));
+    }
+
+    #[test]
+    fn test_merge_invariants_keeps_provenance() {
+        let invariant = |expression: &str, confidence: f64, chunk_index: i32| ExtractedInvariant {
+            formal_expression: expression.to_string(),
+            confidence_score: confidence,
+            tags: vec![format!("chunk{}", chunk_index)],
+            extraction_metadata: Some(ExtractionMetadata {
+                chunks: vec![ChunkProvenance { chunk_index, ..Default::default() }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let merged = merge_invariants(vec![
+            invariant("amount > 0", 0.7, 0),
+            invariant("fee <= 2", 0.9, 0),
+            invariant("Amount >  0", 0.8, 1),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].formal_expression, "Amount >  0");
+        assert_eq!(merged[0].tags, vec!["chunk1".to_string(), "chunk0".to_string()]);
+        let chunk_indexes: Vec<i32> = merged[0].extraction_metadata.as_ref().unwrap().chunks
+            .iter()
+            .map(|chunk| chunk.chunk_index)
+            .collect();
+        assert_eq!(chunk_indexes, vec![0, 1]);
+    }
+}
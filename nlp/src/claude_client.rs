@@ -1,8 +1,18 @@
-use std::error::Error;
 use std::time::Duration;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
-use tokio::time::sleep;
+use std::sync::Arc;
+use spec_to_proof_error::Error;
+use circuit_breaker::CircuitBreaker;
+use cost_governance::LlmCallGovernor;
+use tokio::time::{sleep, Instant};
+
+use crate::streaming::{InvariantScanner, SseDecoder, StreamEvent};
+use crate::InvariantExtractionConfig;
+
+/// Name of the breaker every Claude client in the process shares
+pub const CLAUDE_CIRCUIT: &str = "claude";
 
 #[derive(Debug, Serialize)]
 struct ClaudeRequest {
@@ -10,6 +20,8 @@ struct ClaudeRequest {
     max_tokens: u32,
     temperature: f32,
     messages: Vec<ClaudeMessage>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -37,6 +49,24 @@ struct ClaudeUsage {
     output_tokens: u32,
 }
 
+/// The model behind invariant extraction. Implemented by `ClaudeClient`;
+/// evaluations and tests plug in recorded or scripted responses instead.
+#[async_trait]
+pub trait LanguageModel: Send + Sync {
+    /// Returns the response text and the input and output token counts
+    async fn generate_response(
+        &self,
+        prompt: &str,
+        max_retries: u32,
+        retry_delay_ms: u64,
+    ) -> Result<(String, u32, u32), Error>;
+
+    fn estimate_cost(&self, input_tokens: u32, output_tokens: u32, cost_per_1k_tokens: f64) -> f64 {
+        let total_tokens = input_tokens + output_tokens;
+        (total_tokens as f64 / 1000.0) * cost_per_1k_tokens
+    }
+}
+
 pub struct ClaudeClient {
     api_key: String,
     model: String,
@@ -44,6 +74,11 @@ pub struct ClaudeClient {
     temperature: f32,
     http_client: Client,
     base_url: String,
+    /// Streams responses when set, giving up on the rest of the output
+    /// once this much time has passed
+    stream_deadline: Option<Duration>,
+    governor: Option<Arc<LlmCallGovernor>>,
+    breaker: Arc<CircuitBreaker>,
 }
 
 impl ClaudeClient {
@@ -55,19 +90,48 @@ impl ClaudeClient {
             temperature: 0.0,
             http_client: Client::new(),
             base_url: "https://api.anthropic.com/v1/messages".to_string(),
+            stream_deadline: None,
+            governor: None,
+            breaker: CircuitBreaker::shared(CLAUDE_CIRCUIT),
+        }
+    }
+
+    pub fn from_config(config: &InvariantExtractionConfig) -> Self {
+        let client = Self::new(&config.claude_api_key, &config.claude_model);
+        match config.stream_deadline_ms {
+            Some(deadline_ms) => client.with_streaming(Duration::from_millis(deadline_ms)),
+            None => client,
         }
     }
 
-    pub async fn generate_response(
+    pub fn with_streaming(mut self, deadline: Duration) -> Self {
+        self.stream_deadline = Some(deadline);
+        self
+    }
+
+    /// Checks each request with cost governance and records its spend
+    /// against the tenant of the current request scope
+    pub fn with_cost_governance(mut self, governor: Arc<LlmCallGovernor>) -> Self {
+        self.governor = Some(governor);
+        self
+    }
+
+    /// Replaces the process-wide Claude breaker
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    async fn call_with_retries(
         &self,
         prompt: &str,
         max_retries: u32,
         retry_delay_ms: u64,
-    ) -> Result<(String, u32, u32), Box<dyn Error>> {
+    ) -> Result<(String, u32, u32), Error> {
         let mut last_error = None;
         
         for attempt in 0..=max_retries {
-            match self.make_request(prompt).await {
+            match self.governed_request(prompt).await {
                 Ok((response_text, input_tokens, output_tokens)) => {
                     tracing::info!(
                         "Claude API call successful: {} input tokens, {} output tokens",
@@ -77,16 +141,21 @@ impl ClaudeClient {
                     return Ok((response_text, input_tokens, output_tokens));
                 }
                 Err(e) => {
+                    // Retrying cannot fix a bad request or key
+                    if !e.is_retryable() {
+                        return Err(e);
+                    }
+                    let delay = e.retry_after().unwrap_or(Duration::from_millis(retry_delay_ms));
                     last_error = Some(e);
                     if attempt < max_retries {
                         tracing::warn!(
-                            "Claude API call failed (attempt {}/{}), retrying in {}ms: {}",
+                            "Claude API call failed (attempt {}/{}), retrying in {:?}: {}",
                             attempt + 1,
                             max_retries + 1,
-                            retry_delay_ms,
+                            delay,
                             last_error.as_ref().unwrap()
                         );
-                        sleep(Duration::from_millis(retry_delay_ms)).await;
+                        sleep(delay).await;
                     }
                 }
             }
@@ -95,8 +164,30 @@ impl ClaudeClient {
         Err(last_error.unwrap_or_else(|| "Unknown error".into()))
     }
 
-    async fn make_request(&self, prompt: &str) -> Result<(String, u32, u32), Box<dyn Error>> {
-        let request = ClaudeRequest {
+    async fn governed_request(&self, prompt: &str) -> Result<(String, u32, u32), Error> {
+        let tenant_id = match &self.governor {
+            Some(governor) => Some(governor.authorize((prompt.len() / 4) as u32).await?),
+            None => None,
+        };
+
+        let (response_text, input_tokens, output_tokens) = match self.stream_deadline {
+            Some(deadline) => self.make_streaming_request(prompt, deadline).await?,
+            None => self.make_request(prompt).await?,
+        };
+
+        if let (Some(governor), Some(tenant_id)) = (&self.governor, tenant_id) {
+            // The tokens are spent either way, so a failed write must not
+            // fail the call
+            if let Err(e) = governor.record(&tenant_id, &self.model, input_tokens, output_tokens).await {
+                tracing::warn!("Failed to record LLM cost for tenant {}: {}", tenant_id, e);
+            }
+        }
+
+        Ok((response_text, input_tokens, output_tokens))
+    }
+
+    fn build_request(&self, prompt: &str, stream: bool) -> ClaudeRequest {
+        ClaudeRequest {
             model: self.model.clone(),
             max_tokens: self.max_tokens,
             temperature: self.temperature,
@@ -106,26 +197,64 @@ impl ClaudeClient {
                     content: prompt.to_string(),
                 }
             ],
-        };
+            stream,
+        }
+    }
 
+    /// Lists the available models, which needs a valid key but spends no
+    /// tokens
+    pub async fn ping(&self) -> Result<(), Error> {
         let response = self.http_client
-            .post(&self.base_url)
+            .get(self.base_url.replace("/messages", "/models"))
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(&request)
             .send()
             .await?;
 
         if !response.status().is_success() {
+            return Err(Error::from_status(response.status().as_u16(), format!("Claude API returned {}", response.status())));
+        }
+        Ok(())
+    }
+
+    async fn send(&self, request: &ClaudeRequest) -> Result<reqwest::Response, Error> {
+        let post = self.http_client
+            .post(&self.base_url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(request);
+        let response = self.breaker.call_http(
+            async { post.send().await.map_err(Error::from) },
+            |response| response.status().as_u16(),
+        ).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs);
             let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(format!("Claude API error: {} - {}", response.status(), error_text).into());
+            let error = Error::from_status(status.as_u16(), format!("Claude API error: {} - {}", status, error_text));
+            return Err(match retry_after {
+                Some(delay) => error.with_retry_after(delay),
+                None => error,
+            });
         }
 
+        Ok(response)
+    }
+
+    async fn make_request(&self, prompt: &str) -> Result<(String, u32, u32), Error> {
+        let response = self.send(&self.build_request(prompt, false)).await?;
+
         let claude_response: ClaudeResponse = response.json().await?;
         
         if claude_response.content.is_empty() {
-            return Err("Empty response from Claude API".into());
+            return Err(Error::transient("Empty response from Claude API"));
         }
 
         let response_text = claude_response.content[0].text.clone();
@@ -135,9 +264,80 @@ impl ClaudeClient {
         Ok((response_text, input_tokens, output_tokens))
     }
 
-    pub fn estimate_cost(&self, input_tokens: u32, output_tokens: u32, cost_per_1k_tokens: f64) -> f64 {
-        let total_tokens = input_tokens + output_tokens;
-        (total_tokens as f64 / 1000.0) * cost_per_1k_tokens
+    /// Streams the completion, collecting invariants as their objects
+    /// complete. Stops reading once the full response has arrived; at the
+    /// deadline, returns whatever invariants were received if there are any.
+    async fn make_streaming_request(
+        &self,
+        prompt: &str,
+        deadline: Duration,
+    ) -> Result<(String, u32, u32), Error> {
+        let deadline_at = Instant::now() + deadline;
+        let mut response = self.send(&self.build_request(prompt, true)).await?;
+
+        let mut decoder = SseDecoder::default();
+        let mut scanner = InvariantScanner::default();
+        let (mut input_tokens, mut output_tokens) = (None, None);
+        let mut timed_out = false;
+
+        'stream: while !scanner.is_complete() {
+            let chunk = match tokio::time::timeout_at(deadline_at, response.chunk()).await {
+                Ok(chunk) => chunk?,
+                Err(_) => {
+                    timed_out = true;
+                    break;
+                }
+            };
+            let Some(chunk) = chunk else { break };
+
+            for event in decoder.push(&chunk) {
+                match event {
+                    StreamEvent::TextDelta(text) => scanner.push(&text),
+                    StreamEvent::Usage { input_tokens: input, output_tokens: output } => {
+                        input_tokens = input.or(input_tokens);
+                        output_tokens = output.or(output_tokens);
+                    }
+                    StreamEvent::Stop => break 'stream,
+                    StreamEvent::Error(message) => return Err(Error::transient(format!("Claude API stream error: {}", message))),
+                }
+            }
+        }
+
+        if scanner.is_complete() {
+            tracing::debug!("Response payload complete, closing the stream");
+        }
+        if timed_out {
+            if scanner.invariants().is_empty() {
+                return Err(Error::timeout(format!("Claude API stream produced no invariants within {:?}", deadline)));
+            }
+            tracing::warn!(
+                "Claude API stream hit its {:?} deadline; keeping {} invariants received so far",
+                deadline,
+                scanner.invariants().len()
+            );
+        }
+
+        // Usage after an early stop only reflects what was streamed, so
+        // fall back to an estimate from the text received
+        let output_tokens = output_tokens.unwrap_or(0).max((scanner.text().len() / 4) as u32);
+        if !scanner.is_complete() && scanner.invariants().is_empty() {
+            // Nothing recognisable; hand back the raw text so the parse
+            // error shows what the model said
+            return Ok((scanner.text().to_string(), input_tokens.unwrap_or(0), output_tokens));
+        }
+        Ok((scanner.into_response(), input_tokens.unwrap_or(0), output_tokens))
+    }
+}
+
+#[async_trait]
+impl LanguageModel for ClaudeClient {
+    async fn generate_response(
+        &self,
+        prompt: &str,
+        max_retries: u32,
+        retry_delay_ms: u64,
+    ) -> Result<(String, u32, u32), Error> {
+        self.call_with_retries(prompt, max_retries, retry_delay_ms).await
     }
 }
 
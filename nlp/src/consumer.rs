@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use spec_to_proof_error::Error;
+use async_nats::jetstream::{self, consumer::{pull, AckPolicy}, AckKind, Message};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use cost_governance::tenant;
+use pipeline_control::{PauseGate, Stage, DOCUMENT_TENANT_METADATA_KEY};
+
+use crate::drift::{DriftPublisher, DEFAULT_DRIFT_SUBJECT_PREFIX};
+use crate::proto::nlp::v1::ExtractInvariantsRequest;
+use crate::NlpService;
+
+/// Settings for the JetStream pull consumer that feeds ingested documents
+/// into `NlpService`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerConfig {
+    pub nats_url: String,
+    pub stream_name: String,
+    pub subject: String,
+    /// Durable name shared by every replica, so they form one consumer group
+    pub durable_name: String,
+    /// Upper bound on documents being extracted concurrently
+    pub max_in_flight: usize,
+    pub ack_wait_seconds: u64,
+    /// Deliveries before a document is routed to the dead letter subject
+    pub max_deliver: i64,
+    pub retry_base_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    /// How long a document waits before being redelivered while extraction
+    /// is paused for its tenant or source system
+    pub paused_retry_delay_ms: u64,
+    pub dead_letter_subject: String,
+    /// Subject prefix for spec drift events published on the same connection
+    pub drift_subject_prefix: String,
+}
+
+impl Default for ConsumerConfig {
+    fn default() -> Self {
+        Self {
+            nats_url: "nats://localhost:4222".to_string(),
+            stream_name: "SPEC_DOCUMENTS".to_string(),
+            subject: "spec-documents.>".to_string(),
+            durable_name: "nlp-invariant-extractor".to_string(),
+            max_in_flight: 8,
+            ack_wait_seconds: 300,
+            max_deliver: 5,
+            retry_base_delay_ms: 5_000,
+            retry_max_delay_ms: 300_000,
+            paused_retry_delay_ms: 60_000,
+            dead_letter_subject: "spec-documents-dlq.nlp".to_string(),
+            drift_subject_prefix: DEFAULT_DRIFT_SUBJECT_PREFIX.to_string(),
+        }
+    }
+}
+
+/// The fields of a published `SpecDocument` needed for extraction. Other
+/// fields in the message are ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DocumentMessage {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub source_system: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl DocumentMessage {
+    /// The tenant recorded at ingestion, or the default tenant
+    pub fn tenant_id(&self) -> String {
+        tenant::tenant_from_metadata(self.metadata.get(DOCUMENT_TENANT_METADATA_KEY).map(String::as_str))
+    }
+}
+
+impl From<DocumentMessage> for ExtractInvariantsRequest {
+    fn from(document: DocumentMessage) -> Self {
+        ExtractInvariantsRequest {
+            document_id: document.id,
+            content: document.content,
+            title: document.title,
+            source_system: document.source_system,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessingError {
+    /// The message can never succeed, e.g. it is not a document
+    Permanent(String),
+    /// Extraction failed but may succeed on redelivery
+    Transient(String),
+    /// Extraction is paused for the document's tenant or source system
+    Paused(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Disposition {
+    Ack,
+    Retry(Duration),
+    /// Ack and publish a fresh copy, so a long pause does not use up the
+    /// message's deliveries
+    Requeue,
+    DeadLetter(String),
+}
+
+pub fn retry_delay(delivered: i64, config: &ConsumerConfig) -> Duration {
+    let exponent = (delivered.max(1) - 1).min(16) as u32;
+    let delay_ms = config.retry_base_delay_ms.saturating_mul(2_u64.pow(exponent));
+    Duration::from_millis(delay_ms.min(config.retry_max_delay_ms))
+}
+
+pub fn disposition(result: &Result<(), ProcessingError>, delivered: i64, config: &ConsumerConfig) -> Disposition {
+    match result {
+        Ok(()) => Disposition::Ack,
+        Err(ProcessingError::Permanent(reason)) => Disposition::DeadLetter(reason.clone()),
+        Err(ProcessingError::Transient(reason)) if delivered >= config.max_deliver => {
+            Disposition::DeadLetter(format!("failed after {} deliveries: {}", delivered, reason))
+        }
+        Err(ProcessingError::Transient(_)) => Disposition::Retry(retry_delay(delivered, config)),
+        Err(ProcessingError::Paused(_)) if delivered >= config.max_deliver => Disposition::Requeue,
+        Err(ProcessingError::Paused(_)) => Disposition::Retry(Duration::from_millis(config.paused_retry_delay_ms)),
+    }
+}
+
+pub fn decode_document(payload: &[u8]) -> Result<ExtractInvariantsRequest, ProcessingError> {
+    decode_message(payload).map(Into::into)
+}
+
+pub fn decode_message(payload: &[u8]) -> Result<DocumentMessage, ProcessingError> {
+    let document: DocumentMessage = serde_json::from_slice(payload)
+        .map_err(|e| ProcessingError::Permanent(format!("invalid document payload: {}", e)))?;
+
+    if document.id.is_empty() {
+        return Err(ProcessingError::Permanent("document has no id".to_string()));
+    }
+
+    Ok(document)
+}
+
+/// Pulls documents from JetStream and runs extraction on each, with
+/// at-least-once delivery: a message is acked only after extraction and
+/// persistence succeed.
+pub struct DocumentConsumer {
+    config: ConsumerConfig,
+    client: async_nats::Client,
+    jetstream: jetstream::Context,
+    pause_gate: Arc<PauseGate>,
+}
+
+impl DocumentConsumer {
+    pub async fn connect(config: ConsumerConfig) -> Result<Self, Error> {
+        let client = async_nats::connect(&config.nats_url).await.map_err(Error::transient)?;
+        Ok(Self {
+            config,
+            jetstream: jetstream::new(client.clone()),
+            client,
+            pause_gate: Arc::new(PauseGate::disabled()),
+        })
+    }
+
+    // Documents are held back while extraction is paused for their tenant
+    // or source system
+    pub fn with_pause_gate(mut self, pause_gate: Arc<PauseGate>) -> Self {
+        self.pause_gate = pause_gate;
+        self
+    }
+
+    pub fn client(&self) -> &async_nats::Client {
+        &self.client
+    }
+
+    pub fn drift_publisher(&self) -> DriftPublisher {
+        DriftPublisher::new(self.client.clone(), &self.config.drift_subject_prefix)
+    }
+
+    async fn consumer(&self) -> Result<pull::Consumer, Error> {
+        let stream = self.jetstream
+            .get_or_create_stream(jetstream::stream::Config {
+                name: self.config.stream_name.clone(),
+                subjects: vec![self.config.subject.clone()],
+                ..Default::default()
+            })
+            .await
+            .map_err(Error::transient)?;
+
+        let consumer = stream
+            .get_or_create_consumer(&self.config.durable_name, pull::Config {
+                durable_name: Some(self.config.durable_name.clone()),
+                filter_subject: self.config.subject.clone(),
+                ack_policy: AckPolicy::Explicit,
+                ack_wait: Duration::from_secs(self.config.ack_wait_seconds),
+                // One spare delivery so a dead letter publish that failed
+                // on the last attempt gets retried
+                max_deliver: self.config.max_deliver + 1,
+                max_ack_pending: self.config.max_in_flight as i64,
+                ..Default::default()
+            })
+            .await
+            .map_err(Error::transient)?;
+
+        Ok(consumer)
+    }
+
+    pub async fn run(&self, service: &NlpService) -> Result<(), Error> {
+        let consumer = self.consumer().await?;
+        let messages = consumer.messages().await.map_err(Error::transient)?;
+
+        tracing::info!(
+            "Consuming {} as durable consumer {} (max in flight {})",
+            self.config.subject,
+            self.config.durable_name,
+            self.config.max_in_flight
+        );
+
+        messages
+            .for_each_concurrent(self.config.max_in_flight, |message| async move {
+                match message {
+                    Ok(message) => self.handle(service, message).await,
+                    Err(e) => tracing::error!("Failed to receive JetStream message: {}", e),
+                }
+            })
+            .await;
+
+        Ok(())
+    }
+
+    async fn handle(&self, service: &NlpService, message: Message) {
+        let delivered = message.info().map(|info| info.delivered).unwrap_or(1);
+
+        let result = match decode_message(&message.payload) {
+            Ok(document) => self.extract(service, document).await,
+            Err(e) => Err(e),
+        };
+
+        let outcome = match disposition(&result, delivered, &self.config) {
+            Disposition::Ack => message.ack().await,
+            Disposition::Retry(delay) if matches!(result, Err(ProcessingError::Paused(_))) => {
+                tracing::info!("Holding {} for {:?}: {:?}", message.subject, delay, result);
+                message.ack_with(AckKind::Nak(Some(delay))).await
+            }
+            Disposition::Retry(delay) => {
+                tracing::warn!(
+                    "Extraction failed on delivery {} of {}, retrying in {:?}: {:?}",
+                    delivered,
+                    message.subject,
+                    delay,
+                    result
+                );
+                message.ack_with(AckKind::Nak(Some(delay))).await
+            }
+            Disposition::Requeue => match self.requeue(&message).await {
+                Ok(()) => message.ack().await,
+                Err(e) => {
+                    tracing::error!("Failed to requeue paused message from {}: {}", message.subject, e);
+                    return;
+                }
+            },
+            Disposition::DeadLetter(reason) => match self.dead_letter(&message, &reason).await {
+                Ok(()) => message.ack_with(AckKind::Term).await,
+                Err(e) => {
+                    // Leave the message unacked so it is redelivered after ack_wait
+                    tracing::error!("Failed to route message to dead letter subject: {}", e);
+                    return;
+                }
+            },
+        };
+
+        if let Err(e) = outcome {
+            tracing::error!("Failed to acknowledge message on {}: {}", message.subject, e);
+        }
+    }
+
+    async fn extract(&self, service: &NlpService, document: DocumentMessage) -> Result<(), ProcessingError> {
+        let tenant_id = document.tenant_id();
+        if let Some(pause) = self.pause_gate
+            .check(Stage::Extraction, Some(&tenant_id), Some(&document.source_system))
+            .await
+        {
+            return Err(ProcessingError::Paused(format!(
+                "extraction paused by {}: {}",
+                pause.paused_by, pause.reason
+            )));
+        }
+
+        let request: ExtractInvariantsRequest = document.into();
+        let document_id = request.document_id.clone();
+        tenant::scope(tenant_id, service.extract_invariants(request))
+            .await
+            .map(|response| {
+                tracing::info!(
+                    "Extracted {} invariants from document {}",
+                    response.invariants.len(),
+                    document_id
+                );
+            })
+            .map_err(|e| match e {
+                // Redelivering the same document cannot fix it
+                Error::InvalidInput(reason) => ProcessingError::Permanent(reason),
+                e => ProcessingError::Transient(e.to_string()),
+            })
+    }
+
+    // The copy starts over with a fresh delivery count
+    async fn requeue(&self, message: &Message) -> Result<(), Error> {
+        tracing::info!("Requeueing paused document from {}", message.subject);
+        self.jetstream
+            .publish(message.subject.clone(), message.payload.clone())
+            .await
+            .map_err(Error::transient)?
+            .await
+            .map_err(Error::transient)?;
+        Ok(())
+    }
+
+    async fn dead_letter(&self, message: &Message, reason: &str) -> Result<(), Error> {
+        tracing::error!("Routing message from {} to {}: {}", message.subject, self.config.dead_letter_subject, reason);
+
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert("Spec-To-Proof-Original-Subject", message.subject.as_str());
+        headers.insert("Spec-To-Proof-Failure-Reason", reason);
+
+        self.jetstream
+            .publish_with_headers(self.config.dead_letter_subject.clone(), headers, message.payload.clone())
+            .await
+            .map_err(Error::transient)?
+            .await
+            .map_err(Error::transient)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_backs_off_and_caps() {
+        let config = ConsumerConfig {
+            retry_base_delay_ms: 1_000,
+            retry_max_delay_ms: 5_000,
+            ..Default::default()
+        };
+
+        assert_eq!(retry_delay(1, &config), Duration::from_secs(1));
+        assert_eq!(retry_delay(2, &config), Duration::from_secs(2));
+        assert_eq!(retry_delay(3, &config), Duration::from_secs(4));
+        assert_eq!(retry_delay(10, &config), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_disposition() {
+        let config = ConsumerConfig {
+            max_deliver: 3,
+            ..Default::default()
+        };
+        let transient = Err(ProcessingError::Transient("claude timeout".to_string()));
+
+        assert_eq!(disposition(&Ok(()), 1, &config), Disposition::Ack);
+        assert!(matches!(disposition(&transient, 1, &config), Disposition::Retry(_)));
+        assert!(matches!(disposition(&transient, 3, &config), Disposition::DeadLetter(_)));
+        assert!(matches!(
+            disposition(&Err(ProcessingError::Permanent("bad json".to_string())), 1, &config),
+            Disposition::DeadLetter(_)
+        ));
+    }
+
+    #[test]
+    fn test_paused_documents_are_held_without_dead_lettering() {
+        let config = ConsumerConfig {
+            max_deliver: 3,
+            paused_retry_delay_ms: 30_000,
+            ..Default::default()
+        };
+        let paused = Err(ProcessingError::Paused("extraction paused by oncall: incident".to_string()));
+
+        assert_eq!(disposition(&paused, 1, &config), Disposition::Retry(Duration::from_secs(30)));
+        assert_eq!(disposition(&paused, 3, &config), Disposition::Requeue);
+
+        let payload = br#"{"id":"PROJ-1-3","source_system":"jira","metadata":{"tenant_id":"acme"}}"#;
+        assert_eq!(decode_message(payload).unwrap().tenant_id(), "acme");
+        assert_eq!(decode_message(br#"{"id":"PROJ-1-3"}"#).unwrap().tenant_id(), tenant::DEFAULT_TENANT);
+    }
+
+    #[test]
+    fn test_decode_document() {
+        let payload = br#"{"id":"PROJ-1-3","title":"Payments","content":"Balance >= 0","source_system":"jira","version":3}"#;
+        let request = decode_document(payload).unwrap();
+        assert_eq!(request.document_id, "PROJ-1-3");
+        assert_eq!(request.source_system, "jira");
+
+        assert!(matches!(decode_document(b"not json"), Err(ProcessingError::Permanent(_))));
+        assert!(matches!(decode_document(br#"{"id":""}"#), Err(ProcessingError::Permanent(_))));
+    }
+}
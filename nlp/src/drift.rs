@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use spec_to_proof_error::Error;
+use serde::{Deserialize, Serialize};
+use storage::{idempotency_key, Entity, EntityQuery, ExpectedVersion, OutboxEvent, OutboxSink, Repository, Versioned};
+
+use crate::invariant_diff::{diff_invariant_sets, ChangeKind};
+use crate::persistence::invariant_id;
+use crate::proto::nlp::v1::{ExtractedInvariant, StoredInvariant};
+
+// spec_to_proof.v1.InvariantStatus values
+pub const INVARIANT_STATUS_PROVEN: i32 = 4;
+pub const INVARIANT_STATUS_STALE: i32 = 6;
+
+/// Drift events are published on `<prefix>.<document id>`
+pub const DEFAULT_DRIFT_SUBJECT_PREFIX: &str = "spec-drift";
+
+const QUERY_PAGE_SIZE: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftKind {
+    /// The new version tightens the invariant
+    Strengthened,
+    /// The new version relaxes the invariant
+    Weakened,
+    /// The new version states the invariant differently
+    Changed,
+    /// The new version no longer states the invariant
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriftedInvariant {
+    pub invariant_id: String,
+    pub description: String,
+    pub kind: DriftKind,
+    /// The invariant in the new version it most likely became
+    #[serde(default)]
+    pub replacement_id: Option<String>,
+    #[serde(default)]
+    pub replacement_description: Option<String>,
+}
+
+/// Proven invariants invalidated by a new version of their spec document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriftEvent {
+    pub document_id: String,
+    pub invariants: Vec<DriftedInvariant>,
+    pub summary: String,
+    /// Unix seconds
+    pub detected_at: u64,
+}
+
+/// Compares the invariants proven for a document with those extracted from
+/// its new version. Proven invariants restated in equivalent notation are
+/// not drift; the rest are classified by [`diff_invariant_sets`].
+pub fn detect_drift(
+    document_id: &str,
+    stored: &[StoredInvariant],
+    extracted: &[ExtractedInvariant],
+) -> Option<DriftEvent> {
+    let stored_ids: HashSet<&str> = stored.iter().map(|s| s.id.as_str()).collect();
+    let current_ids: HashSet<String> = extracted
+        .iter()
+        .map(|invariant| invariant_id(document_id, &invariant.formal_expression))
+        .collect();
+    let missing: Vec<ExtractedInvariant> = stored
+        .iter()
+        .filter(|s| s.status == INVARIANT_STATUS_PROVEN && !current_ids.contains(&s.id))
+        .filter_map(|s| s.invariant.clone())
+        .collect();
+    let added: Vec<ExtractedInvariant> = extracted
+        .iter()
+        .filter(|invariant| !stored_ids.contains(invariant_id(document_id, &invariant.formal_expression).as_str()))
+        .cloned()
+        .collect();
+
+    let invariants: Vec<DriftedInvariant> = diff_invariant_sets(&missing, &added)
+        .changes
+        .into_iter()
+        .filter_map(|change| {
+            let previous = change.before?;
+            Some(DriftedInvariant {
+                invariant_id: invariant_id(document_id, &previous.formal_expression),
+                description: label(&previous),
+                kind: match change.kind {
+                    ChangeKind::Strengthened => DriftKind::Strengthened,
+                    ChangeKind::Weakened => DriftKind::Weakened,
+                    ChangeKind::Removed => DriftKind::Removed,
+                    _ => DriftKind::Changed,
+                },
+                replacement_id: change.after.as_ref().map(|after| invariant_id(document_id, &after.formal_expression)),
+                replacement_description: change.after.as_ref().map(label),
+            })
+        })
+        .collect();
+
+    if invariants.is_empty() {
+        return None;
+    }
+
+    Some(DriftEvent {
+        document_id: document_id.to_string(),
+        summary: summarize(document_id, &invariants),
+        invariants,
+        detected_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+    })
+}
+
+fn label(invariant: &ExtractedInvariant) -> String {
+    if invariant.description.trim().is_empty() {
+        invariant.formal_expression.clone()
+    } else {
+        invariant.description.trim().to_string()
+    }
+}
+
+fn summarize(document_id: &str, invariants: &[DriftedInvariant]) -> String {
+    let removed = invariants.iter().filter(|i| i.kind == DriftKind::Removed).count();
+    let changed = invariants.len() - removed;
+
+    let mut summary = format!(
+        "Spec document {} changed after its invariants were proven: {} changed, {} removed",
+        document_id, changed, removed
+    );
+    for invariant in invariants {
+        let verb = match invariant.kind {
+            DriftKind::Strengthened => "strengthened",
+            DriftKind::Weakened => "weakened",
+            DriftKind::Changed => "changed",
+            DriftKind::Removed => "removed",
+        };
+        match &invariant.replacement_description {
+            Some(replacement) => {
+                summary.push_str(&format!("\n- {}: {} -> {}", verb, invariant.description, replacement));
+            }
+            None => summary.push_str(&format!("\n- {}: {}", verb, invariant.description)),
+        }
+    }
+    summary
+}
+
+/// Loads the invariants stored for a document and checks its new
+/// extraction against them, before the new invariants are persisted
+pub async fn detect_drift_event(
+    repository: &dyn Repository<StoredInvariant>,
+    document_id: &str,
+    extracted: &[ExtractedInvariant],
+) -> Result<Option<DriftEvent>, Error> {
+    let stored: Vec<StoredInvariant> = stored_invariants(repository, document_id)
+        .await?
+        .into_iter()
+        .map(|s| s.entity)
+        .collect();
+    Ok(detect_drift(document_id, &stored, extracted))
+}
+
+/// Marks the event's invariants stale so their proofs stop counting
+/// towards coverage. Invariants a reviewer has moved on since are left alone.
+pub async fn mark_stale(
+    repository: &dyn Repository<StoredInvariant>,
+    event: &DriftEvent,
+    outbox_events: &[OutboxEvent],
+) -> Result<usize, Error> {
+    // Written with the first transition, so the events go out if and only
+    // if some invariant actually went stale
+    let mut outbox_events = outbox_events;
+    let mut marked = 0;
+    for drifted in &event.invariants {
+        let Some(mut current) = repository.get(&drifted.invariant_id).await? else {
+            continue;
+        };
+        if current.entity.status != INVARIANT_STATUS_PROVEN {
+            continue;
+        }
+        current.entity.set_status(INVARIANT_STATUS_STALE);
+        repository
+            .put_with_events(&current.entity, ExpectedVersion::Exactly(current.version), outbox_events)
+            .await?;
+        outbox_events = &[];
+        marked += 1;
+    }
+    Ok(marked)
+}
+
+/// Every invariant stored for a document, whatever its status
+pub async fn stored_invariants(
+    repository: &dyn Repository<StoredInvariant>,
+    document_id: &str,
+) -> Result<Vec<Versioned<StoredInvariant>>, Error> {
+    let query = EntityQuery::BySource(document_id.to_string());
+    let mut items = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let page = repository.query(&query, page_token.as_deref(), QUERY_PAGE_SIZE).await?;
+        items.extend(page.items);
+        match page.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => return Ok(items),
+        }
+    }
+}
+
+/// Delivers drift events from the outbox for gh-app to flip affected badges
+#[derive(Debug, Clone)]
+pub struct DriftPublisher {
+    client: async_nats::Client,
+    subject_prefix: String,
+}
+
+impl DriftPublisher {
+    pub fn new(client: async_nats::Client, subject_prefix: &str) -> Self {
+        Self {
+            client,
+            subject_prefix: subject_prefix.to_string(),
+        }
+    }
+
+    /// The outbox event announcing `event`, for `mark_stale` to write
+    pub fn outbox_event(&self, event: &DriftEvent) -> Result<OutboxEvent, Error> {
+        let subject = drift_subject(&self.subject_prefix, &event.document_id);
+        let detected_at = event.detected_at.to_string();
+        let mut key_parts = vec![subject.as_str(), detected_at.as_str()];
+        key_parts.extend(event.invariants.iter().map(|drifted| drifted.invariant_id.as_str()));
+        Ok(OutboxEvent::new(&subject, &idempotency_key(&key_parts), serde_json::to_vec(event)?))
+    }
+}
+
+#[async_trait::async_trait]
+impl OutboxSink for DriftPublisher {
+    async fn deliver(&self, event: &OutboxEvent) -> Result<(), Error> {
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert("Nats-Msg-Id", event.idempotency_key.as_str());
+        self.client
+            .publish_with_headers(event.subject.clone(), headers, event.payload.clone().into())
+            .await
+            .map_err(Error::transient)?;
+        Ok(())
+    }
+}
+
+// Dots and whitespace would split or break the subject token
+pub fn drift_subject(prefix: &str, document_id: &str) -> String {
+    let token: String = document_id
+        .chars()
+        .map(|c| if c == '.' || c == '*' || c == '>' || c.is_whitespace() { '_' } else { c })
+        .collect();
+    format!("{}.{}", prefix, token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{persist_invariants, to_stored};
+    use crate::proto::nlp::v1::SourceSpan;
+    use storage::InMemoryRepository;
+
+    fn extracted(description: &str, expression: &str, quote: &str) -> ExtractedInvariant {
+        ExtractedInvariant {
+            description: description.to_string(),
+            formal_expression: expression.to_string(),
+            source_span: Some(SourceSpan {
+                quote: quote.to_string(),
+                verified: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn proven(document_id: &str, invariant: &ExtractedInvariant) -> StoredInvariant {
+        StoredInvariant {
+            status: INVARIANT_STATUS_PROVEN,
+            ..to_stored(document_id, invariant)
+        }
+    }
+
+    #[test]
+    fn test_detect_changed_and_removed() {
+        let balance = extracted("Balance is non-negative", "balance >= 0", "The balance must never be negative.");
+        let refunds = extracted("Refunds within 30 days", "refund_days <= 30", "Refunds are issued within 30 days.");
+        let stored = vec![proven("DOC-1", &balance), proven("DOC-1", &refunds)];
+
+        let new_balance = extracted("Balance is positive", "balance > 0", "The balance must never be negative.");
+        let event = detect_drift("DOC-1", &stored, &[new_balance]).unwrap();
+
+        assert_eq!(event.invariants.len(), 2);
+        assert_eq!(event.invariants[0].kind, DriftKind::Strengthened);
+        assert_eq!(event.invariants[0].replacement_id, Some(invariant_id("DOC-1", "balance > 0")));
+        assert_eq!(event.invariants[1].kind, DriftKind::Removed);
+        assert_eq!(
+            event.summary,
+            "Spec document DOC-1 changed after its invariants were proven: 1 changed, 1 removed\n\
+             - strengthened: Balance is non-negative -> Balance is positive\n\
+             - removed: Refunds within 30 days"
+        );
+    }
+
+    #[test]
+    fn test_no_drift_when_proven_invariants_remain() {
+        let balance = extracted("Balance is non-negative", "balance >= 0", "");
+        let pending = to_stored("DOC-1", &extracted("Unproven", "x > 1", ""));
+        let stored = vec![proven("DOC-1", &balance), pending];
+
+        // The unproven invariant disappearing is not drift
+        assert!(detect_drift("DOC-1", &stored, &[balance.clone()]).is_none());
+    }
+
+    #[test]
+    fn test_restated_invariant_is_not_drift() {
+        let balance = extracted("Balance is non-negative", "balance >= 0", "");
+        let stored = vec![proven("DOC-1", &balance)];
+
+        let restated = extracted("Balance is non-negative", "0 <= balance", "");
+        assert!(detect_drift("DOC-1", &stored, &[restated]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mark_stale() {
+        let repository = InMemoryRepository::<StoredInvariant>::new();
+        let outbox = repository.outbox();
+        let balance = extracted("Balance is non-negative", "balance >= 0", "");
+        persist_invariants(&repository, "DOC-1", &[balance]).await.unwrap();
+        let id = invariant_id("DOC-1", "balance >= 0");
+        repository.update_status(&id, INVARIANT_STATUS_PROVEN, 1).await.unwrap();
+
+        let event = detect_drift_event(&repository, "DOC-1", &[]).await.unwrap().unwrap();
+        assert_eq!(event.invariants[0].invariant_id, id);
+
+        let outbox_event = OutboxEvent::new("spec-drift.DOC-1", "key-1", serde_json::to_vec(&event).unwrap());
+        assert_eq!(mark_stale(&repository, &event, std::slice::from_ref(&outbox_event)).await.unwrap(), 1);
+        assert_eq!(repository.get(&id).await.unwrap().unwrap().entity.status, INVARIANT_STATUS_STALE);
+        assert_eq!(outbox.len().await, 1);
+        // Redelivery of the same version finds nothing left to drift
+        assert!(detect_drift_event(&repository, "DOC-1", &[]).await.unwrap().is_none());
+        let retried = OutboxEvent::new("spec-drift.DOC-1", "key-2", serde_json::to_vec(&event).unwrap());
+        assert_eq!(mark_stale(&repository, &event, &[retried]).await.unwrap(), 0);
+        assert_eq!(outbox.len().await, 1);
+    }
+
+    #[test]
+    fn test_drift_subject() {
+        assert_eq!(drift_subject("spec-drift", "PROJ-1.v2 draft"), "spec-drift.PROJ-1_v2_draft");
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::{BTreeMap, HashMap};
+use spec_to_proof_error::Error;
+use async_trait::async_trait;
+use prompt_registry::PromptTemplate;
+use serde::{Deserialize, Serialize};
+
+use crate::claude_client::LanguageModel;
+use crate::expression;
+use crate::pipeline::ExtractionPipeline;
+use crate::proto::nlp::v1::{ExtractInvariantsRequest, ExtractedInvariant};
+
+const UNCATEGORIZED: &str = "uncategorized";
+
+/// Documents with the invariants a correct extraction should find
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoldenDataset {
+    pub name: String,
+    pub documents: Vec<GoldenDocument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoldenDocument {
+    pub document_id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub source_system: String,
+    pub content: String,
+    pub expected: Vec<GoldenInvariant>,
+    /// Recorded model output (`{"invariants": [...]}`) replayed by
+    /// `ReplayLanguageModel`, so datasets can be evaluated offline
+    #[serde(default)]
+    pub model_response: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoldenInvariant {
+    pub formal_expression: String,
+    #[serde(default)]
+    pub description: String,
+    /// Taxonomy category the invariant belongs to
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+impl GoldenDataset {
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let raw = std::fs::read_to_string(path)?;
+        serde_json::from_str(&raw).map_err(|e| Error::invalid_input(format!("Invalid golden dataset {}: {}", path, e)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EvaluationConfig {
+    /// Minimum expression similarity for an extracted invariant to count as
+    /// finding a golden one
+    pub match_threshold: f64,
+}
+
+impl Default for EvaluationConfig {
+    fn default() -> Self {
+        Self { match_threshold: 0.8 }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scores {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+impl Scores {
+    pub fn from_counts(true_positives: usize, false_positives: usize, false_negatives: usize) -> Self {
+        let ratio = |numerator: usize, denominator: usize| {
+            // Nothing expected and nothing extracted is a perfect score
+            if denominator == 0 { 1.0 } else { numerator as f64 / denominator as f64 }
+        };
+        let precision = ratio(true_positives, true_positives + false_positives);
+        let recall = ratio(true_positives, true_positives + false_negatives);
+        let f1 = if precision + recall == 0.0 { 0.0 } else { 2.0 * precision * recall / (precision + recall) };
+
+        Self {
+            true_positives,
+            false_positives,
+            false_negatives,
+            precision,
+            recall,
+            f1,
+        }
+    }
+
+    fn add(&mut self, other: &Scores) {
+        *self = Scores::from_counts(
+            self.true_positives + other.true_positives,
+            self.false_positives + other.false_positives,
+            self.false_negatives + other.false_negatives,
+        );
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvariantMatch {
+    pub expected: String,
+    pub extracted: String,
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentReport {
+    pub document_id: String,
+    pub scores: Scores,
+    pub matches: Vec<InvariantMatch>,
+    /// Golden expressions nothing matched
+    pub missed: Vec<String>,
+    /// Extracted expressions that matched nothing
+    pub spurious: Vec<String>,
+    pub categories: BTreeMap<String, Scores>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationReport {
+    pub dataset: String,
+    pub prompt_name: String,
+    pub prompt_version: String,
+    pub prompt_sha256: String,
+    pub overall: Scores,
+    pub categories: BTreeMap<String, Scores>,
+    pub documents: Vec<DocumentReport>,
+}
+
+impl EvaluationReport {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Runs every golden document through the extraction pipeline and scores
+/// the result
+pub async fn evaluate(
+    pipeline: &ExtractionPipeline,
+    prompt_template: &PromptTemplate,
+    dataset: &GoldenDataset,
+    config: &EvaluationConfig,
+) -> Result<EvaluationReport, Error> {
+    let mut documents = Vec::with_capacity(dataset.documents.len());
+
+    for document in &dataset.documents {
+        let request = ExtractInvariantsRequest {
+            document_id: document.document_id.clone(),
+            content: document.content.clone(),
+            title: document.title.clone(),
+            source_system: document.source_system.clone(),
+            ..Default::default()
+        };
+        let output = pipeline.run(&request, prompt_template).await?;
+        documents.push(score_document(document, &output.invariants, config.match_threshold));
+    }
+
+    let mut overall = Scores::from_counts(0, 0, 0);
+    let mut categories: BTreeMap<String, Scores> = BTreeMap::new();
+    for report in &documents {
+        overall.add(&report.scores);
+        for (category, scores) in &report.categories {
+            categories.entry(category.clone()).or_insert_with(|| Scores::from_counts(0, 0, 0)).add(scores);
+        }
+    }
+
+    tracing::info!(
+        "Evaluated {} on {} documents: precision {:.3}, recall {:.3}, F1 {:.3}",
+        dataset.name,
+        documents.len(),
+        overall.precision,
+        overall.recall,
+        overall.f1
+    );
+
+    Ok(EvaluationReport {
+        dataset: dataset.name.clone(),
+        prompt_name: prompt_template.name.clone(),
+        prompt_version: prompt_template.version.clone(),
+        prompt_sha256: prompt_template.sha256.clone(),
+        overall,
+        categories,
+        documents,
+    })
+}
+
+/// Matches extracted invariants against the golden ones, best pairs first,
+/// each used at most once
+pub fn score_document(
+    document: &GoldenDocument,
+    extracted: &[ExtractedInvariant],
+    match_threshold: f64,
+) -> DocumentReport {
+    let mut candidates = Vec::new();
+    for (expected_index, expected) in document.expected.iter().enumerate() {
+        for (extracted_index, invariant) in extracted.iter().enumerate() {
+            let similarity = expression::similarity(&expected.formal_expression, &invariant.formal_expression);
+            if similarity >= match_threshold {
+                candidates.push((similarity, expected_index, extracted_index));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut expected_matched = vec![false; document.expected.len()];
+    let mut extracted_matched = vec![false; extracted.len()];
+    let mut matches = Vec::new();
+    let mut counts: HashMap<String, (usize, usize, usize)> = HashMap::new();
+
+    for (similarity, expected_index, extracted_index) in candidates {
+        if expected_matched[expected_index] || extracted_matched[extracted_index] {
+            continue;
+        }
+        expected_matched[expected_index] = true;
+        extracted_matched[extracted_index] = true;
+
+        let expected = &document.expected[expected_index];
+        let category = expected.category.clone()
+            .unwrap_or_else(|| extracted_category(&extracted[extracted_index]));
+        counts.entry(category).or_default().0 += 1;
+        matches.push(InvariantMatch {
+            expected: expected.formal_expression.clone(),
+            extracted: extracted[extracted_index].formal_expression.clone(),
+            similarity,
+        });
+    }
+
+    let mut spurious = Vec::new();
+    for (invariant, _) in extracted.iter().zip(&extracted_matched).filter(|(_, matched)| !**matched) {
+        counts.entry(extracted_category(invariant)).or_default().1 += 1;
+        spurious.push(invariant.formal_expression.clone());
+    }
+
+    let mut missed = Vec::new();
+    for (expected, _) in document.expected.iter().zip(&expected_matched).filter(|(_, matched)| !**matched) {
+        let category = expected.category.clone().unwrap_or_else(|| UNCATEGORIZED.to_string());
+        counts.entry(category).or_default().2 += 1;
+        missed.push(expected.formal_expression.clone());
+    }
+
+    DocumentReport {
+        document_id: document.document_id.clone(),
+        scores: Scores::from_counts(matches.len(), spurious.len(), missed.len()),
+        matches,
+        missed,
+        spurious,
+        categories: counts
+            .into_iter()
+            .map(|(category, (tp, fp, fn_))| (category, Scores::from_counts(tp, fp, fn_)))
+            .collect(),
+    }
+}
+
+fn extracted_category(invariant: &ExtractedInvariant) -> String {
+    invariant.classification
+        .as_ref()
+        .map(|classification| classification.category.clone())
+        .unwrap_or_else(|| UNCATEGORIZED.to_string())
+}
+
+/// Replays the responses recorded in a golden dataset, picking the document
+/// whose id appears in the prompt
+pub struct ReplayLanguageModel {
+    responses: Vec<(String, String)>,
+}
+
+impl ReplayLanguageModel {
+    pub fn from_dataset(dataset: &GoldenDataset) -> Self {
+        let mut responses: Vec<(String, String)> = dataset.documents
+            .iter()
+            .filter_map(|document| {
+                let response = document.model_response.as_ref()?;
+                Some((document.document_id.clone(), response.to_string()))
+            })
+            .collect();
+        // Longest ids first so "doc-10" is not taken for "doc-1"
+        responses.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        Self { responses }
+    }
+}
+
+#[async_trait]
+impl LanguageModel for ReplayLanguageModel {
+    async fn generate_response(
+        &self,
+        prompt: &str,
+        _max_retries: u32,
+        _retry_delay_ms: u64,
+    ) -> Result<(String, u32, u32), Error> {
+        let (_, response) = self.responses
+            .iter()
+            .find(|(document_id, _)| prompt.contains(document_id.as_str()))
+            .ok_or("No recorded response for prompt")?;
+        Ok((response.clone(), (prompt.len() / 4) as u32, (response.len() / 4) as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::nlp::v1::InvariantClassification;
+
+    fn extracted(formal_expression: &str, category: Option<&str>) -> ExtractedInvariant {
+        ExtractedInvariant {
+            formal_expression: formal_expression.to_string(),
+            classification: category.map(|category| InvariantClassification {
+                category: category.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn golden(formal_expression: &str, category: &str) -> GoldenInvariant {
+        GoldenInvariant {
+            formal_expression: formal_expression.to_string(),
+            description: String::new(),
+            category: Some(category.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_scores_from_counts() {
+        let scores = Scores::from_counts(8, 2, 1);
+        assert_eq!(scores.precision, 0.8);
+        assert!((scores.recall - 8.0 / 9.0).abs() < 1e-9);
+        assert!(scores.f1 > 0.84 && scores.f1 < 0.85);
+        assert_eq!(Scores::from_counts(0, 0, 0).f1, 1.0);
+    }
+
+    #[test]
+    fn test_score_document_per_category() {
+        let document = GoldenDocument {
+            document_id: "doc-1".to_string(),
+            title: String::new(),
+            source_system: String::new(),
+            content: String::new(),
+            expected: vec![
+                golden("latency_ms <= 100", "performance"),
+                golden("refund <= charge", "data_integrity"),
+            ],
+            model_response: None,
+        };
+        let invariants = vec![
+            extracted("100 >= latency_ms", Some("performance")),
+            extracted("retries < 3", Some("reliability")),
+        ];
+
+        let report = score_document(&document, &invariants, 0.8);
+        assert_eq!((report.scores.true_positives, report.scores.false_positives, report.scores.false_negatives), (1, 1, 1));
+        assert_eq!(report.missed, vec!["refund <= charge".to_string()]);
+        assert_eq!(report.spurious, vec!["retries < 3".to_string()]);
+        assert_eq!(report.categories["performance"].f1, 1.0);
+        assert_eq!(report.categories["data_integrity"].recall, 0.0);
+        assert_eq!(report.categories["reliability"].precision, 0.0);
+    }
+}
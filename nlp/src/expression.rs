@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+// Parses formal expressions as the model writes them ("x ≤ 10 ∧ y > 0",
+// "refund <= charge && charge > 0") into a canonical tree, so expressions
+// that differ only in notation, operand order of commutative operators or
+// comparison direction compare equal.
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Expr {
+    Atom(String),
+    Call(String, Vec<Expr>),
+    Unary(String, Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+    /// Commutative operator with its operands flattened and sorted
+    Commutative(String, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn render(&self) -> String {
+        match self {
+            Expr::Atom(atom) => atom.clone(),
+            Expr::Call(name, args) => {
+                format!("{}({})", name, args.iter().map(Expr::render).collect::<Vec<_>>().join(", "))
+            }
+            Expr::Unary(op, operand) => format!("({}{})", op, operand.render()),
+            Expr::Binary(op, left, right) => format!("({} {} {})", left.render(), op, right.render()),
+            Expr::Commutative(op, operands) => format!(
+                "({})",
+                operands.iter().map(Expr::render).collect::<Vec<_>>().join(&format!(" {} ", op))
+            ),
+        }
+    }
+
+    fn collect_subtrees(&self, subtrees: &mut Vec<String>) {
+        subtrees.push(self.render());
+        match self {
+            Expr::Atom(_) => {}
+            Expr::Call(_, operands) | Expr::Commutative(_, operands) => {
+                operands.iter().for_each(|operand| operand.collect_subtrees(subtrees));
+            }
+            Expr::Unary(_, operand) => operand.collect_subtrees(subtrees),
+            Expr::Binary(_, left, right) => {
+                left.collect_subtrees(subtrees);
+                right.collect_subtrees(subtrees);
+            }
+        }
+    }
+}
+
+/// Parses and canonicalizes an expression; None if it cannot be parsed
+pub fn canonicalize(expression: &str) -> Option<Expr> {
+    let tokens = tokenize(expression, false)?;
+    let mut parser = Parser { tokens, position: 0 };
+    let expr = parser.implication()?;
+    if parser.position != parser.tokens.len() {
+        return None;
+    }
+    Some(normalize(expr))
+}
+
+/// Similarity in [0, 1] of two expressions: the Dice coefficient over the
+/// subtrees of their canonical forms, which credits partially matching
+/// structure. Falls back to comparing token multisets when either side does
+/// not parse.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    match (canonicalize(a), canonicalize(b)) {
+        (Some(a), Some(b)) => {
+            if a == b {
+                return 1.0;
+            }
+            let (mut left, mut right) = (Vec::new(), Vec::new());
+            a.collect_subtrees(&mut left);
+            b.collect_subtrees(&mut right);
+            dice(&left, &right)
+        }
+        _ => {
+            let left = tokenize(a, true).unwrap_or_default();
+            let right = tokenize(b, true).unwrap_or_default();
+            dice(&left, &right)
+        }
+    }
+}
+
+fn dice(left: &[String], right: &[String]) -> f64 {
+    if left.is_empty() && right.is_empty() {
+        return 1.0;
+    }
+    let mut counts: HashMap<&str, i64> = HashMap::new();
+    for item in left {
+        *counts.entry(item).or_default() += 1;
+    }
+    let mut shared = 0;
+    for item in right {
+        if let Some(count) = counts.get_mut(item.as_str()) {
+            if *count > 0 {
+                *count -= 1;
+                shared += 1;
+            }
+        }
+    }
+    2.0 * shared as f64 / (left.len() + right.len()) as f64
+}
+
+// Multi-character operators first so "<=" is not read as "<" "="
+const OPERATORS: &[(&str, &str)] = &[
+    ("==>", "=>"), ("<=>", "<=>"), ("<->", "<=>"), ("->", "=>"), ("=>", "=>"),
+    ("<=", "<="), (">=", ">="), ("==", "=="), ("!=", "!="), ("&&", "&&"), ("||", "||"),
+    ("≤", "<="), ("≥", ">="), ("≠", "!="), ("∧", "&&"), ("∨", "||"), ("¬", "!"),
+    ("→", "=>"), ("⇒", "=>"), ("↔", "<=>"), ("⇔", "<=>"), ("×", "*"), ("·", "*"), ("÷", "/"),
+    ("<", "<"), (">", ">"), ("=", "=="), ("!", "!"), ("+", "+"), ("-", "-"), ("*", "*"),
+    ("/", "/"), ("%", "%"), ("^", "^"), ("(", "("), (")", ")"), (",", ","),
+];
+
+const KEYWORDS: &[(&str, &str)] = &[("and", "&&"), ("or", "||"), ("not", "!"), ("implies", "=>")];
+
+// Unknown symbols (quantifiers, set notation) fail tokenization unless
+// `lenient`, in which case they become tokens of their own
+fn tokenize(expression: &str, lenient: bool) -> Option<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim();
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = rest.trim_start();
+            continue;
+        }
+
+        if c.is_alphanumeric() || c == '_' || c == '.' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            let word = rest[..end].to_lowercase();
+            rest = &rest[end..];
+
+            let token = match KEYWORDS.iter().find(|(keyword, _)| *keyword == word) {
+                Some((_, op)) => op.to_string(),
+                None => match word.parse::<f64>() {
+                    // 1.0 and 1 are the same literal
+                    Ok(number) if word.starts_with(|c: char| c.is_ascii_digit()) => number.to_string(),
+                    _ => word,
+                },
+            };
+            tokens.push(token);
+            continue;
+        }
+
+        match OPERATORS.iter().find(|(symbol, _)| rest.starts_with(symbol)) {
+            Some((symbol, canonical)) => {
+                tokens.push(canonical.to_string());
+                rest = &rest[symbol.len()..];
+            }
+            None if lenient => {
+                tokens.push(c.to_string());
+                rest = &rest[c.len_utf8()..];
+            }
+            None => return None,
+        }
+    }
+
+    Some(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Right associative: a => b => c is a => (b => c)
+    fn implication(&mut self) -> Option<Expr> {
+        let left = self.binary_level(0)?;
+        for op in ["=>", "<=>"] {
+            if self.eat(op) {
+                let right = self.implication()?;
+                return Some(Expr::Binary(op.to_string(), Box::new(left), Box::new(right)));
+            }
+        }
+        Some(left)
+    }
+
+    fn binary_level(&mut self, level: usize) -> Option<Expr> {
+        const LEVELS: &[&[&str]] = &[
+            &["||"],
+            &["&&"],
+            &["==", "!=", "<", "<=", ">", ">="],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary_level(level + 1)?;
+        while let Some(op) = self.peek().filter(|token| LEVELS[level].contains(token)).map(str::to_string) {
+            self.position += 1;
+            let right = self.binary_level(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<Expr> {
+        for op in ["!", "-"] {
+            if self.eat(op) {
+                return Some(Expr::Unary(op.to_string(), Box::new(self.unary()?)));
+            }
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Option<Expr> {
+        let base = self.atom()?;
+        if self.eat("^") {
+            let exponent = self.unary()?;
+            return Some(Expr::Binary("^".to_string(), Box::new(base), Box::new(exponent)));
+        }
+        Some(base)
+    }
+
+    fn atom(&mut self) -> Option<Expr> {
+        if self.eat("(") {
+            let inner = self.implication()?;
+            return self.eat(")").then_some(inner);
+        }
+
+        let token = self.peek()?.to_string();
+        if !token.starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '.') {
+            return None;
+        }
+        self.position += 1;
+
+        if self.eat("(") {
+            let mut args = Vec::new();
+            if !self.eat(")") {
+                loop {
+                    args.push(self.implication()?);
+                    if self.eat(")") {
+                        break;
+                    }
+                    if !self.eat(",") {
+                        return None;
+                    }
+                }
+            }
+            return Some(Expr::Call(token, args));
+        }
+        Some(Expr::Atom(token))
+    }
+}
+
+fn normalize(expr: Expr) -> Expr {
+    match expr {
+        Expr::Atom(_) => expr,
+        Expr::Call(name, args) => Expr::Call(name, args.into_iter().map(normalize).collect()),
+        Expr::Unary(op, operand) => Expr::Unary(op, Box::new(normalize(*operand))),
+        Expr::Commutative(op, operands) => commutative(op, operands.into_iter().map(normalize).collect()),
+        Expr::Binary(op, left, right) => {
+            let (left, right) = (normalize(*left), normalize(*right));
+            match op.as_str() {
+                // Comparisons always point left: b >= a becomes a <= b
+                ">" => Expr::Binary("<".to_string(), Box::new(right), Box::new(left)),
+                ">=" => Expr::Binary("<=".to_string(), Box::new(right), Box::new(left)),
+                "&&" | "||" | "+" | "*" | "==" | "!=" | "<=>" => commutative(op, vec![left, right]),
+                _ => Expr::Binary(op, Box::new(left), Box::new(right)),
+            }
+        }
+    }
+}
+
+fn commutative(op: String, operands: Vec<Expr>) -> Expr {
+    let mut flattened = Vec::new();
+    for operand in operands {
+        match operand {
+            Expr::Commutative(inner, nested) if inner == op => flattened.extend(nested),
+            other => flattened.push(other),
+        }
+    }
+    flattened.sort();
+    Expr::Commutative(op, flattened)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notation_and_operand_order_are_canonical() {
+        let canonical = canonicalize("refund_amount <= charge_amount && charge_amount > 0").unwrap();
+        assert_eq!(canonicalize("0 < Charge_Amount ∧ charge_amount ≥ refund_amount").unwrap(), canonical);
+        assert_eq!(canonical.render(), "((0 < charge_amount) && (refund_amount <= charge_amount))");
+
+        assert_eq!(canonicalize("x = 1.0 implies y"), canonicalize("(x == 1) → y"));
+        assert!(canonicalize("x <= (1").is_none());
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("latency_ms < 100", "100 > latency_ms"), 1.0);
+
+        let partial = similarity("latency_ms < 100 && errors == 0", "latency_ms < 100");
+        assert!(partial > 0.4 && partial < 1.0, "{}", partial);
+
+        assert!(similarity("latency_ms < 100", "balance >= 0") < 0.2);
+        // Unparseable expressions are compared token by token
+        assert!((similarity("∀ x, x < 1", "∀ y, y < 1") - 2.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -1,10 +1,12 @@
-use std::error::Error;
+use std::sync::Arc;
+use spec_to_proof_error::Error;
 use serde::{Deserialize, Serialize};
 use crate::proto::nlp::v1::{
-    ExtractInvariantsRequest, ExtractedInvariant, Variable, Priority, TokenUsage
+    ExtractInvariantsRequest, ExtractedInvariant, ExtractionMetadata, Variable, Priority, SourceSpan, TokenUsage
 };
-use crate::claude_client::ClaudeClient;
-use crate::prompts::PromptTemplate;
+use crate::chunking::{self, ChunkingConfig};
+use crate::claude_client::{ClaudeClient, LanguageModel};
+use prompt_registry::PromptTemplate;
 
 #[derive(Debug, Deserialize)]
 struct ClaudeInvariantResponse {
@@ -21,6 +23,8 @@ struct RawExtractedInvariant {
     confidence_score: f64,
     tags: Vec<String>,
     priority: String,
+    #[serde(default)]
+    source_quote: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,49 +38,79 @@ struct RawVariable {
 }
 
 pub struct InvariantExtractor {
-    claude_client: ClaudeClient,
-    prompt_template: PromptTemplate,
+    language_model: Arc<dyn LanguageModel>,
     max_retries: u32,
     retry_delay_ms: u64,
     cost_per_1k_tokens: f64,
+    max_output_tokens: u32,
+    chunking: ChunkingConfig,
 }
 
 impl InvariantExtractor {
     pub fn new(config: &crate::InvariantExtractionConfig) -> Self {
         Self {
-            claude_client: ClaudeClient::new(&config.claude_api_key, &config.claude_model),
-            prompt_template: PromptTemplate::load("invariant_extraction.md"),
+            language_model: Arc::new(ClaudeClient::from_config(config)),
             max_retries: config.max_retries,
             retry_delay_ms: config.retry_delay_ms,
             cost_per_1k_tokens: config.cost_per_1k_tokens,
+            max_output_tokens: config.max_tokens,
+            chunking: ChunkingConfig::from_token_budget(config.chunk_token_budget, config.chunk_overlap_tokens),
         }
     }
 
+    pub fn with_language_model(mut self, language_model: Arc<dyn LanguageModel>) -> Self {
+        self.language_model = language_model;
+        self
+    }
+
     pub async fn extract_invariants(
         &self,
         request: &ExtractInvariantsRequest,
         redacted_content: &str,
-    ) -> Result<ExtractionResult, Box<dyn Error>> {
-        // Build the prompt from template
-        let prompt = self.build_prompt(request, redacted_content);
-        
-        // Call Claude API
-        let (response_text, input_tokens, output_tokens) = self.claude_client
-            .generate_response(&prompt, self.max_retries, self.retry_delay_ms)
-            .await?;
+        prompt_template: &PromptTemplate,
+    ) -> Result<ExtractionResult, Error> {
+        // Large documents are extracted chunk by chunk to stay within the
+        // model budget
+        let chunks = chunking::chunk_document(redacted_content, &self.chunking);
+        if chunks.len() > 1 {
+            tracing::info!("Extracting document {} in {} chunks", request.document_id, chunks.len());
+        }
 
-        // Parse the response
-        let claude_response: ClaudeInvariantResponse = serde_json::from_str(&response_text)
-            .map_err(|e| format!("Failed to parse Claude response: {}", e))?;
+        let mut invariants = Vec::new();
+        let mut input_tokens = 0;
+        let mut output_tokens = 0;
 
-        // Convert to protobuf format
-        let invariants: Vec<ExtractedInvariant> = claude_response.invariants
-            .into_iter()
-            .map(|raw_inv| self.convert_invariant(raw_inv))
-            .collect();
+        for chunk in &chunks {
+            // Build the prompt from template
+            let prompt = self.build_prompt(request, prompt_template, &chunk.prompt_content(chunks.len()))?;
+
+            // Call the model
+            let (response_text, chunk_input_tokens, chunk_output_tokens) = self.language_model
+                .generate_response(&prompt, self.max_retries, self.retry_delay_ms)
+                .await?;
+            input_tokens += chunk_input_tokens;
+            output_tokens += chunk_output_tokens;
+
+            // Parse the response
+            let claude_response: ClaudeInvariantResponse = serde_json::from_str(&response_text)
+                .map_err(|e| Error::transient(format!("Failed to parse Claude response for chunk {}: {}", chunk.index, e)))?;
+
+            // Convert to protobuf format, recording which chunk each came from
+            invariants.extend(claude_response.invariants.into_iter().map(|raw_inv| {
+                let mut invariant = self.convert_invariant(raw_inv);
+                invariant.extraction_metadata = Some(ExtractionMetadata {
+                    chunks: vec![chunk.provenance()],
+                    ..Default::default()
+                });
+                invariant
+            }));
+        }
+
+        // Overlapping chunks can yield the same invariant more than once
+        let invariants = chunking::merge_invariants(invariants);
 
         // Calculate cost
-        let estimated_cost = self.claude_client.estimate_cost(
+        let estimated_cost = self.language_model.estimate_cost(
             input_tokens,
             output_tokens,
             self.cost_per_1k_tokens
@@ -95,14 +129,44 @@ impl InvariantExtractor {
         })
     }
 
-    fn build_prompt(&self, request: &ExtractInvariantsRequest, redacted_content: &str) -> String {
-        let mut template_vars = std::collections::HashMap::new();
+    /// Builds the prompts `extract_invariants` would send and estimates
+    /// their usage without calling the model. Completions are assumed to use
+    /// the whole output budget.
+    pub fn estimate_usage(
+        &self,
+        request: &ExtractInvariantsRequest,
+        redacted_content: &str,
+        prompt_template: &PromptTemplate,
+    ) -> Result<(usize, TokenUsage), Error> {
+        let chunks = chunking::chunk_document(redacted_content, &self.chunking);
+        let mut input_tokens = 0;
+        for chunk in &chunks {
+            let prompt = self.build_prompt(request, prompt_template, &chunk.prompt_content(chunks.len()))?;
+            input_tokens += chunking::estimate_tokens(&prompt);
+        }
+        let output_tokens = self.max_output_tokens * chunks.len() as u32;
+
+        Ok((chunks.len(), TokenUsage {
+            input_tokens: input_tokens as i32,
+            output_tokens: output_tokens as i32,
+            total_tokens: (input_tokens + output_tokens) as i32,
+            estimated_cost_usd: self.language_model.estimate_cost(input_tokens, output_tokens, self.cost_per_1k_tokens),
+        }))
+    }
+
+    fn build_prompt(
+        &self,
+        request: &ExtractInvariantsRequest,
+        prompt_template: &PromptTemplate,
+        redacted_content: &str,
+    ) -> Result<String, Error> {
+        let mut template_vars: std::collections::HashMap<String, &str> = std::collections::HashMap::new();
         template_vars.insert("source_system".to_string(), &request.source_system);
         template_vars.insert("title".to_string(), &request.title);
         template_vars.insert("document_id".to_string(), &request.document_id);
         template_vars.insert("content".to_string(), redacted_content);
 
-        self.prompt_template.render(&template_vars)
+        Ok(prompt_template.render(&template_vars)?)
     }
 
     fn convert_invariant(&self, raw: RawExtractedInvariant) -> ExtractedInvariant {
@@ -134,7 +198,16 @@ impl InvariantExtractor {
             confidence_score: raw.confidence_score,
             tags: raw.tags,
             priority: priority as i32,
-            extraction_metadata: None, // Will be set by the service
+            extraction_metadata: None, // Will be set by the extractor and the service
+            validation_errors: vec![],
+            // Offsets are filled in once the quote is located in the document
+            source_span: Some(SourceSpan {
+                quote: raw.source_quote,
+                ..Default::default()
+            })
+            .filter(|span| !span.quote.trim().is_empty()),
+            classification: None, // Assigned by the taxonomy classifier
+            language: String::new(), // Set by the pipeline
         }
     }
 }
@@ -162,10 +235,12 @@ mod tests {
             confidence_score: 0.9,
             tags: vec![],
             priority: "HIGH".to_string(),
+            source_quote: "x must be positive.".to_string(),
         };
 
         let converted = extractor.convert_invariant(raw_inv);
         assert_eq!(converted.priority, Priority::PriorityHigh as i32);
+        assert_eq!(converted.source_span.unwrap().quote, "x must be positive.");
     }
 
     #[test]
@@ -179,12 +254,42 @@ mod tests {
             source_system: "jira".to_string(),
             invariant_types: vec![],
             confidence_threshold: 0.5,
+            dry_run: false,
+            language: String::new(),
         };
 
-        let prompt = extractor.build_prompt(&request, "Redacted content");
+        let registry = crate::prompts::builtin_registry();
+        let template = registry.active(crate::prompts::INVARIANT_EXTRACTION_PROMPT).unwrap();
+        let prompt = extractor.build_prompt(&request, &template, "Redacted content").unwrap();
         assert!(prompt.contains("jira"));
         assert!(prompt.contains("Test Document"));
         assert!(prompt.contains("test-123"));
         assert!(prompt.contains("Redacted content"));
     }
+
+    #[test]
+    fn test_usage_estimate_covers_every_chunk() {
+        let config = crate::InvariantExtractionConfig {
+            chunk_token_budget: 100,
+            chunk_overlap_tokens: 0,
+            ..Default::default()
+        };
+        let extractor = InvariantExtractor::new(&config);
+        let content = (0..40)
+            .map(|i| format!("## Section {}\n\nThe balance must stay non-negative after refund {}.\n", i, i))
+            .collect::<String>();
+        let request = ExtractInvariantsRequest {
+            document_id: "test-123".to_string(),
+            content: content.clone(),
+            ..Default::default()
+        };
+
+        let registry = crate::prompts::builtin_registry();
+        let template = registry.active(crate::prompts::INVARIANT_EXTRACTION_PROMPT).unwrap();
+        let (chunks, usage) = extractor.estimate_usage(&request, &content, &template).unwrap();
+        assert!(chunks > 1);
+        assert_eq!(usage.output_tokens, (config.max_tokens as usize * chunks) as i32);
+        assert!(usage.input_tokens as u32 > crate::chunking::estimate_tokens(&content));
+        assert!(usage.estimated_cost_usd > 0.0);
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use regex::Regex;
+
+use crate::pipeline::StructuredExtraction;
+use crate::proto::nlp::v1::{ExtractInvariantsRequest, ExtractedInvariant, Priority, SourceSpan, Variable};
+
+/// Confidence of invariants read from a `Then` step rather than inferred
+/// by the model
+pub const STRUCTURED_CONFIDENCE: f64 = 0.9;
+
+pub const GHERKIN_TAG: &str = "gherkin";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKeyword {
+    Given,
+    When,
+    Then,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub keyword: StepKeyword,
+    /// Text after the keyword, with outline placeholders filled in
+    pub text: String,
+    /// The line as written in the document, quoted as the source span
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Feature {
+    pub name: String,
+    pub tags: Vec<String>,
+    /// Steps every scenario starts with
+    pub background: Vec<Step>,
+    /// Scenario outlines appear once per row of their examples
+    pub scenarios: Vec<Scenario>,
+}
+
+/// Whether the request holds a `.feature` file rather than prose
+pub fn is_feature(request: &ExtractInvariantsRequest, content: &str) -> bool {
+    request.title.to_lowercase().ends_with(".feature")
+        || request.source_system.eq_ignore_ascii_case("gherkin")
+        || content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('@'))
+            .is_some_and(|line| line.starts_with("Feature:"))
+}
+
+enum Section {
+    Description,
+    Background,
+    Scenario,
+    Outline,
+    Examples,
+}
+
+/// Parses the feature, or returns `None` when the content has no
+/// `Feature:` line. Free-form description lines and step arguments (data
+/// tables and doc strings) are skipped.
+pub fn parse_feature(content: &str) -> Option<Feature> {
+    let mut feature: Option<Feature> = None;
+    let mut pending_tags = Vec::new();
+    let mut section = Section::Description;
+    let mut scenario = Scenario::default();
+    let mut outline: Option<(Scenario, Vec<String>)> = None;
+    let mut in_doc_string = false;
+    let mut last_keyword = StepKeyword::Given;
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with("\"\"\"") || line.starts_with("```") {
+            in_doc_string = !in_doc_string;
+            continue;
+        }
+        if in_doc_string || line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('@') {
+            pending_tags.extend(line.split_whitespace().map(|tag| tag.trim_start_matches('@').to_string()));
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix("Feature:") {
+            feature = Some(Feature {
+                name: name.trim().to_string(),
+                tags: std::mem::take(&mut pending_tags),
+                ..Default::default()
+            });
+            section = Section::Description;
+            continue;
+        }
+        let Some(current) = feature.as_mut() else {
+            continue;
+        };
+
+        if line.starts_with("Background:") {
+            finish_scenario(current, &mut scenario, &mut outline);
+            last_keyword = StepKeyword::Given;
+            section = Section::Background;
+        } else if let Some(name) = ["Scenario Outline:", "Scenario Template:"].iter().find_map(|k| line.strip_prefix(k)) {
+            finish_scenario(current, &mut scenario, &mut outline);
+            let tags = std::mem::take(&mut pending_tags);
+            outline = Some((Scenario { name: name.trim().to_string(), tags, steps: Vec::new() }, Vec::new()));
+            last_keyword = StepKeyword::Given;
+            section = Section::Outline;
+        } else if let Some(name) = ["Scenario:", "Example:"].iter().find_map(|k| line.strip_prefix(k)) {
+            finish_scenario(current, &mut scenario, &mut outline);
+            scenario = Scenario {
+                name: name.trim().to_string(),
+                tags: std::mem::take(&mut pending_tags),
+                steps: Vec::new(),
+            };
+            last_keyword = StepKeyword::Given;
+            section = Section::Scenario;
+        } else if line.starts_with("Examples:") || line.starts_with("Scenarios:") {
+            pending_tags.clear();
+            if let Some((_, header)) = outline.as_mut() {
+                header.clear();
+            }
+            section = Section::Examples;
+        } else if line.starts_with("Rule:") {
+            finish_scenario(current, &mut scenario, &mut outline);
+            section = Section::Description;
+        } else if line.starts_with('|') {
+            if let (Section::Examples, Some((template, header))) = (&section, outline.as_mut()) {
+                let cells: Vec<String> = line.trim_matches('|').split('|').map(|cell| cell.trim().to_string()).collect();
+                if header.is_empty() {
+                    *header = cells;
+                } else {
+                    current.scenarios.push(expand_outline(template, header, &cells));
+                }
+            }
+        } else if let Some((keyword, text)) = parse_step(line, last_keyword) {
+            last_keyword = keyword;
+            let step = Step { keyword, text: text.to_string(), line: line.to_string() };
+            match section {
+                Section::Background => current.background.push(step),
+                Section::Scenario => scenario.steps.push(step),
+                Section::Outline => {
+                    if let Some((template, _)) = outline.as_mut() {
+                        template.steps.push(step);
+                    }
+                }
+                Section::Description | Section::Examples => {}
+            }
+        }
+    }
+
+    let mut feature = feature?;
+    finish_scenario(&mut feature, &mut scenario, &mut outline);
+    Some(feature)
+}
+
+// `And`, `But` and `*` continue the previous step's keyword
+fn parse_step(line: &str, previous: StepKeyword) -> Option<(StepKeyword, &str)> {
+    let (keyword, text) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let keyword = match keyword {
+        "Given" => StepKeyword::Given,
+        "When" => StepKeyword::When,
+        "Then" => StepKeyword::Then,
+        "And" | "But" | "*" => previous,
+        _ => return None,
+    };
+    Some((keyword, text.trim()))
+}
+
+fn finish_scenario(feature: &mut Feature, scenario: &mut Scenario, outline: &mut Option<(Scenario, Vec<String>)>) {
+    let scenario = std::mem::take(scenario);
+    if !scenario.steps.is_empty() {
+        feature.scenarios.push(scenario);
+    }
+    // An outline without examples has nothing to run
+    outline.take();
+}
+
+fn expand_outline(template: &Scenario, header: &[String], row: &[String]) -> Scenario {
+    let fill = |text: &str| {
+        header.iter().zip(row).fold(text.to_string(), |text, (name, value)| text.replace(&format!("<{}>", name), value))
+    };
+    Scenario {
+        name: format!("{} ({})", template.name, row.join(", ")),
+        tags: template.tags.clone(),
+        steps: template
+            .steps
+            .iter()
+            .map(|step| Step { keyword: step.keyword, text: fill(&step.text), line: step.line.clone() })
+            .collect(),
+    }
+}
+
+/// Turns `Then` steps stating a numeric bound (e.g. "the balance should be
+/// at least 0") into invariants without calling the model. The remaining
+/// free-text `Then` steps are returned with their `Given` and `When`
+/// context, as feature content.
+pub struct GherkinExtractor {
+    constraint: Regex,
+    between: Regex,
+}
+
+impl Default for GherkinExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GherkinExtractor {
+    pub fn new() -> Self {
+        Self {
+            constraint: Regex::new(
+                r"(?i)^(?:the\s+)?(?P<subject>.+?)\s+(?:should|must|shall|will|is|are)\s+(?:be\s+)?(?P<comparison>at most|no more than|not more than|not exceed|less than or equal to|up to|under|below|less than|fewer than|at least|no less than|not less than|greater than or equal to|more than|greater than|above|over|exceed|exactly|equal to)?\s*(?P<value>-?\d+(?:[.,]\d+)?)\s*(?P<unit>[A-Za-zµ%]+)?\.?$",
+            ).unwrap(),
+            between: Regex::new(
+                r"(?i)^(?:the\s+)?(?P<subject>.+?)\s+(?:should|must|shall|will|is|are)\s+(?:be\s+)?between\s+(?P<low>-?\d+(?:[.,]\d+)?)\s+and\s+(?P<high>-?\d+(?:[.,]\d+)?)\s*(?P<unit>[A-Za-zµ%]+)?\.?$",
+            ).unwrap(),
+        }
+    }
+
+    pub fn extract(&self, feature: &Feature) -> StructuredExtraction {
+        let mut extraction = StructuredExtraction::default();
+        let mut free_text_scenarios = Vec::new();
+
+        for scenario in &feature.scenarios {
+            let context: Vec<&Step> = feature
+                .background
+                .iter()
+                .chain(&scenario.steps)
+                .filter(|step| step.keyword != StepKeyword::Then)
+                .collect();
+            let mut free_text_steps = Vec::new();
+
+            for step in scenario.steps.iter().filter(|step| step.keyword == StepKeyword::Then) {
+                match self.constraint_expression(&step.text) {
+                    Some((variable, expression, unit)) => {
+                        extraction.invariants.push(invariant(feature, scenario, &context, step, variable, expression, unit));
+                    }
+                    None => free_text_steps.push(step),
+                }
+            }
+
+            // Outline rows share their step lines, so the model sees each
+            // free-text scenario once
+            if !free_text_steps.is_empty() {
+                let lines: Vec<&str> = context.iter().chain(&free_text_steps).map(|step| step.line.as_str()).collect();
+                let rendered = format!("Scenario: {}\n  {}", scenario.name, lines.join("\n  "));
+                if !free_text_scenarios.iter().any(|(_, existing): &(String, Vec<&str>)| *existing == lines) {
+                    free_text_scenarios.push((rendered, lines));
+                }
+            }
+        }
+
+        if !free_text_scenarios.is_empty() {
+            let scenarios: Vec<String> = free_text_scenarios.into_iter().map(|(rendered, _)| rendered).collect();
+            extraction.free_text = format!("Feature: {}\n\n{}\n", feature.name, scenarios.join("\n\n"));
+        }
+        extraction
+    }
+
+    // Variable name, formal expression and unit of a bounded step
+    fn constraint_expression(&self, text: &str) -> Option<(String, String, Option<String>)> {
+        if let Some(caps) = self.between.captures(text) {
+            let variable = variable_name(&caps["subject"])?;
+            let unit = caps.name("unit").map(|unit| unit.as_str().to_string());
+            let suffix = unit.as_ref().map(|unit| format!(" {}", unit)).unwrap_or_default();
+            let expression = format!(
+                "{} >= {}{} && {} <= {}{}",
+                variable, &caps["low"], suffix, variable, &caps["high"], suffix
+            );
+            return Some((variable, expression, unit));
+        }
+
+        let caps = self.constraint.captures(text)?;
+        let variable = variable_name(&caps["subject"])?;
+        let operator = match caps.name("comparison").map(|c| c.as_str().to_lowercase()).as_deref() {
+            Some("at most" | "no more than" | "not more than" | "not exceed" | "less than or equal to" | "up to") => "<=",
+            Some("under" | "below" | "less than" | "fewer than") => "<",
+            Some("at least" | "no less than" | "not less than" | "greater than or equal to") => ">=",
+            Some("more than" | "greater than" | "above" | "over" | "exceed") => ">",
+            _ => "==",
+        };
+        let unit = caps.name("unit").map(|unit| unit.as_str().to_string());
+        let suffix = unit.as_ref().map(|unit| format!(" {}", unit)).unwrap_or_default();
+        let expression = format!("{} {} {}{}", variable, operator, &caps["value"], suffix);
+        Some((variable, expression, unit))
+    }
+}
+
+// snake_case name of a step's subject, e.g. `"response time"` to
+// `response_time`
+fn variable_name(subject: &str) -> Option<String> {
+    let words: Vec<String> = subject
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let words: Vec<&str> = words
+        .iter()
+        .map(String::as_str)
+        .skip_while(|word| matches!(*word, "the" | "a" | "an"))
+        .collect();
+    let name = words.join("_");
+    (!name.is_empty() && !name.starts_with(|c: char| c.is_ascii_digit())).then_some(name)
+}
+
+fn invariant(
+    feature: &Feature,
+    scenario: &Scenario,
+    context: &[&Step],
+    step: &Step,
+    variable: String,
+    formal_expression: String,
+    unit: Option<String>,
+) -> ExtractedInvariant {
+    let given_when: Vec<&str> = context.iter().map(|step| step.line.as_str()).collect();
+    let natural_language = if given_when.is_empty() {
+        step.text.clone()
+    } else {
+        format!("{}, then {}", given_when.join(", "), step.text)
+    };
+    let tags = std::iter::once(GHERKIN_TAG.to_string())
+        .chain(feature.tags.iter().cloned())
+        .chain(scenario.tags.iter().cloned())
+        .collect();
+    let mut units = HashMap::new();
+    if let Some(unit) = &unit {
+        units.insert(variable.clone(), unit.clone());
+    }
+
+    ExtractedInvariant {
+        description: format!("{}: {}", scenario.name, step.text),
+        formal_expression,
+        natural_language,
+        variables: vec![Variable {
+            name: variable,
+            type_: "number".to_string(),
+            description: String::new(),
+            unit: unit.unwrap_or_default(),
+            constraints: Vec::new(),
+        }],
+        units,
+        confidence_score: STRUCTURED_CONFIDENCE,
+        tags,
+        priority: Priority::PriorityUnspecified as i32,
+        source_span: Some(SourceSpan {
+            quote: step.line.clone(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEATURE: &str = r#"
+@payments
+Feature: Refunds
+  Refunds are paid back to the original card.
+
+  Background:
+    Given a settled payment of 100 USD
+
+  @critical
+  Scenario: Partial refund
+    When the merchant refunds 40 USD
+    Then the refunded amount should be at most 100 USD
+    And the customer is notified by email
+
+  Scenario Outline: Refund latency
+    When a refund is requested in <region>
+    Then the processing time must be less than <limit> ms
+
+    Examples:
+      | region | limit |
+      | eu     | 200   |
+      | us     | 300   |
+"#;
+
+    #[test]
+    fn test_parse_feature() {
+        let feature = parse_feature(FEATURE).unwrap();
+        assert_eq!(feature.name, "Refunds");
+        assert_eq!(feature.tags, ["payments"]);
+        assert_eq!(feature.background.len(), 1);
+        assert_eq!(feature.scenarios.len(), 3);
+        assert_eq!(feature.scenarios[0].tags, ["critical"]);
+        assert_eq!(feature.scenarios[0].steps[2].keyword, StepKeyword::Then);
+        assert_eq!(feature.scenarios[2].name, "Refund latency (us, 300)");
+        assert_eq!(feature.scenarios[2].steps[1].text, "the processing time must be less than 300 ms");
+
+        assert!(parse_feature("Refunds are paid back to the original card.").is_none());
+    }
+
+    #[test]
+    fn test_numeric_then_steps_become_invariants() {
+        let extraction = GherkinExtractor::new().extract(&parse_feature(FEATURE).unwrap());
+
+        let expressions: Vec<&str> = extraction.invariants.iter().map(|inv| inv.formal_expression.as_str()).collect();
+        assert_eq!(expressions, [
+            "refunded_amount <= 100 USD",
+            "processing_time < 200 ms",
+            "processing_time < 300 ms",
+        ]);
+        let refund = &extraction.invariants[0];
+        assert_eq!(refund.tags, ["gherkin", "payments", "critical"]);
+        assert_eq!(refund.source_span.as_ref().unwrap().quote, "Then the refunded amount should be at most 100 USD");
+        assert_eq!(
+            refund.natural_language,
+            "Given a settled payment of 100 USD, When the merchant refunds 40 USD, then the refunded amount should be at most 100 USD"
+        );
+
+        // Only the free-text step is left for the model
+        assert_eq!(
+            extraction.free_text,
+            "Feature: Refunds\n\nScenario: Partial refund\n  Given a settled payment of 100 USD\n  When the merchant refunds 40 USD\n  And the customer is notified by email\n"
+        );
+    }
+
+    #[test]
+    fn test_between_steps() {
+        let extractor = GherkinExtractor::new();
+        let (variable, expression, unit) = extractor
+            .constraint_expression("the retry count should be between 1 and 5")
+            .unwrap();
+        assert_eq!(variable, "retry_count");
+        assert_eq!(expression, "retry_count >= 1 && retry_count <= 5");
+        assert_eq!(unit, None);
+        assert!(extractor.constraint_expression("the customer is notified by email").is_none());
+    }
+}
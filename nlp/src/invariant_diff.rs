@@ -0,0 +1,379 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+
+use crate::expression::{self, canonicalize, Expr};
+use crate::proto::nlp::v1::{
+    DiffInvariantSetsResponse, ExtractedInvariant, InvariantChange as InvariantChangeMessage, InvariantChangeKind,
+};
+
+// Unequal invariants are paired as a change when they constrain mostly the
+// same variables or are structurally similar; otherwise they are reported
+// as a removal and an addition
+const MIN_VARIABLE_OVERLAP: f64 = 0.5;
+const MIN_SIMILARITY: f64 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// The new invariant implies the old one, e.g. `x <= 500` to `x <= 300`
+    Strengthened,
+    /// The old invariant implies the new one
+    Weakened,
+    /// Related but neither implies the other
+    Modified,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Strengthened => "strengthened",
+            ChangeKind::Weakened => "weakened",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvariantChange {
+    pub kind: ChangeKind,
+    /// Absent for additions
+    pub before: Option<ExtractedInvariant>,
+    /// Absent for removals
+    pub after: Option<ExtractedInvariant>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InvariantSetDiff {
+    /// Pairs and removals in base order, then additions in head order
+    pub changes: Vec<InvariantChange>,
+    /// Invariants present in both sets up to notation
+    pub unchanged: usize,
+}
+
+impl InvariantSetDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|change| change.kind == kind).count()
+    }
+
+    pub fn to_response(&self) -> DiffInvariantSetsResponse {
+        DiffInvariantSetsResponse {
+            changes: self
+                .changes
+                .iter()
+                .map(|change| InvariantChangeMessage {
+                    kind: match change.kind {
+                        ChangeKind::Added => InvariantChangeKind::Added,
+                        ChangeKind::Removed => InvariantChangeKind::Removed,
+                        ChangeKind::Strengthened => InvariantChangeKind::Strengthened,
+                        ChangeKind::Weakened => InvariantChangeKind::Weakened,
+                        ChangeKind::Modified => InvariantChangeKind::Modified,
+                    } as i32,
+                    before: change.before.clone(),
+                    after: change.after.clone(),
+                })
+                .collect(),
+            unchanged_count: self.unchanged as i32,
+        }
+    }
+}
+
+/// Compares two versions of an invariant set on the canonical expression
+/// trees of their formal expressions
+pub fn diff_invariant_sets(base: &[ExtractedInvariant], head: &[ExtractedInvariant]) -> InvariantSetDiff {
+    let base_exprs: Vec<Option<Expr>> = base.iter().map(|i| canonicalize(&i.formal_expression)).collect();
+    let head_exprs: Vec<Option<Expr>> = head.iter().map(|i| canonicalize(&i.formal_expression)).collect();
+    let mut base_matched = vec![false; base.len()];
+    let mut head_matched = vec![false; head.len()];
+    let mut diff = InvariantSetDiff::default();
+
+    for i in 0..base.len() {
+        let equivalent = (0..head.len()).find(|&j| {
+            !head_matched[j] && equivalent(&base[i], base_exprs[i].as_ref(), &head[j], head_exprs[j].as_ref())
+        });
+        if let Some(j) = equivalent {
+            base_matched[i] = true;
+            head_matched[j] = true;
+            diff.unchanged += 1;
+        }
+    }
+
+    // Best-scoring pairs first, each invariant in at most one pair
+    let mut candidates = Vec::new();
+    for i in (0..base.len()).filter(|&i| !base_matched[i]) {
+        for j in (0..head.len()).filter(|&j| !head_matched[j]) {
+            if let Some(score) = pair_score(&base[i], base_exprs[i].as_ref(), &head[j], head_exprs[j].as_ref()) {
+                candidates.push((score, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    let mut paired: Vec<(usize, InvariantChange)> = Vec::new();
+    for (_, i, j) in candidates {
+        if base_matched[i] || head_matched[j] {
+            continue;
+        }
+        base_matched[i] = true;
+        head_matched[j] = true;
+        paired.push((i, InvariantChange {
+            kind: classify(base_exprs[i].as_ref(), head_exprs[j].as_ref()),
+            before: Some(base[i].clone()),
+            after: Some(head[j].clone()),
+        }));
+    }
+    for i in (0..base.len()).filter(|&i| !base_matched[i]) {
+        paired.push((i, InvariantChange {
+            kind: ChangeKind::Removed,
+            before: Some(base[i].clone()),
+            after: None,
+        }));
+    }
+    paired.sort_by_key(|(i, _)| *i);
+    diff.changes.extend(paired.into_iter().map(|(_, change)| change));
+
+    diff.changes.extend((0..head.len()).filter(|&j| !head_matched[j]).map(|j| InvariantChange {
+        kind: ChangeKind::Added,
+        before: None,
+        after: Some(head[j].clone()),
+    }));
+
+    diff
+}
+
+fn equivalent(a: &ExtractedInvariant, a_expr: Option<&Expr>, b: &ExtractedInvariant, b_expr: Option<&Expr>) -> bool {
+    match (a_expr, b_expr) {
+        (Some(a), Some(b)) => a == b || (implies(a, b) && implies(b, a)),
+        (None, None) => a.formal_expression.trim() == b.formal_expression.trim(),
+        _ => false,
+    }
+}
+
+fn pair_score(a: &ExtractedInvariant, a_expr: Option<&Expr>, b: &ExtractedInvariant, b_expr: Option<&Expr>) -> Option<f64> {
+    let similarity = expression::similarity(&a.formal_expression, &b.formal_expression);
+    // Restating the same sentence or description is the strongest signal
+    if same_source(a, b) {
+        return Some(2.0 + similarity);
+    }
+
+    let overlap = match (a_expr, b_expr) {
+        (Some(a), Some(b)) => jaccard(&variables(a), &variables(b)),
+        _ => 0.0,
+    };
+    (overlap >= MIN_VARIABLE_OVERLAP || similarity >= MIN_SIMILARITY).then_some(overlap + similarity)
+}
+
+fn same_source(a: &ExtractedInvariant, b: &ExtractedInvariant) -> bool {
+    let quote = |invariant: &ExtractedInvariant| {
+        invariant.source_span.as_ref().filter(|span| span.verified).map(|span| span.quote.trim().to_string())
+    };
+    if let (Some(a), Some(b)) = (quote(a), quote(b)) {
+        if a == b {
+            return true;
+        }
+    }
+    !a.description.trim().is_empty() && a.description.trim().eq_ignore_ascii_case(b.description.trim())
+}
+
+fn classify(before: Option<&Expr>, after: Option<&Expr>) -> ChangeKind {
+    let (Some(before), Some(after)) = (before, after) else {
+        return ChangeKind::Modified;
+    };
+    match (implies(after, before), implies(before, after)) {
+        (true, false) => ChangeKind::Strengthened,
+        (false, true) => ChangeKind::Weakened,
+        _ => ChangeKind::Modified,
+    }
+}
+
+/// Whether `a` implies `b`, as far as conjunctions, disjunctions and
+/// numeric bounds on the same term can tell. False when unsure.
+pub fn implies(a: &Expr, b: &Expr) -> bool {
+    if a == b {
+        return true;
+    }
+    if let Expr::Commutative(op, operands) = b {
+        if op == "&&" {
+            return operands.iter().all(|operand| implies(a, operand));
+        }
+    }
+    if let Expr::Commutative(op, operands) = a {
+        if op == "||" {
+            return operands.iter().all(|operand| implies(operand, b));
+        }
+        if op == "&&" && operands.iter().any(|operand| implies(operand, b)) {
+            return true;
+        }
+    }
+    if let Expr::Commutative(op, operands) = b {
+        if op == "||" && operands.iter().any(|operand| implies(a, operand)) {
+            return true;
+        }
+    }
+    match (Bound::of(a), Bound::of(b)) {
+        (Some(a), Some(b)) => a.implies(&b),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Upper,
+    Lower,
+    Exact,
+}
+
+// A comparison of a term with a number. Canonical comparisons are always
+// `<` or `<=`, so the number's side tells upper from lower bounds.
+#[derive(Debug)]
+struct Bound<'a> {
+    term: &'a Expr,
+    side: Side,
+    value: f64,
+    strict: bool,
+}
+
+impl<'a> Bound<'a> {
+    fn of(expr: &'a Expr) -> Option<Self> {
+        match expr {
+            Expr::Binary(op, left, right) if op == "<" || op == "<=" => {
+                let strict = op == "<";
+                match (number(left), number(right)) {
+                    (None, Some(value)) => Some(Bound { term: left, side: Side::Upper, value, strict }),
+                    (Some(value), None) => Some(Bound { term: right, side: Side::Lower, value, strict }),
+                    _ => None,
+                }
+            }
+            Expr::Commutative(op, operands) if op == "==" && operands.len() == 2 => {
+                match (number(&operands[0]), number(&operands[1])) {
+                    (Some(value), None) => Some(Bound { term: &operands[1], side: Side::Exact, value, strict: false }),
+                    (None, Some(value)) => Some(Bound { term: &operands[0], side: Side::Exact, value, strict: false }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn implies(&self, other: &Bound) -> bool {
+        if self.term != other.term {
+            return false;
+        }
+        let tighter_or_equal = |tighter: bool| tighter || (self.value == other.value && (self.strict || !other.strict));
+        match (self.side, other.side) {
+            (Side::Exact, Side::Exact) => self.value == other.value,
+            (Side::Exact | Side::Upper, Side::Upper) => tighter_or_equal(self.value < other.value),
+            (Side::Exact | Side::Lower, Side::Lower) => tighter_or_equal(self.value > other.value),
+            _ => false,
+        }
+    }
+}
+
+fn number(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Atom(atom) if atom.starts_with(|c: char| c.is_ascii_digit()) => atom.parse().ok(),
+        Expr::Unary(op, operand) if op == "-" => number(operand).map(|value| -value),
+        _ => None,
+    }
+}
+
+fn variables(expr: &Expr) -> BTreeSet<&str> {
+    let mut variables = BTreeSet::new();
+    collect_variables(expr, &mut variables);
+    variables
+}
+
+fn collect_variables<'a>(expr: &'a Expr, variables: &mut BTreeSet<&'a str>) {
+    match expr {
+        Expr::Atom(atom) => {
+            if number(expr).is_none() {
+                variables.insert(atom.as_str());
+            }
+        }
+        Expr::Call(_, operands) | Expr::Commutative(_, operands) => {
+            operands.iter().for_each(|operand| collect_variables(operand, variables));
+        }
+        Expr::Unary(_, operand) => collect_variables(operand, variables),
+        Expr::Binary(_, left, right) => {
+            collect_variables(left, variables);
+            collect_variables(right, variables);
+        }
+    }
+}
+
+fn jaccard(a: &BTreeSet<&str>, b: &BTreeSet<&str>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invariant(expression: &str) -> ExtractedInvariant {
+        ExtractedInvariant {
+            formal_expression: expression.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn kinds(base: &[&str], head: &[&str]) -> Vec<ChangeKind> {
+        let base: Vec<_> = base.iter().map(|e| invariant(e)).collect();
+        let head: Vec<_> = head.iter().map(|e| invariant(e)).collect();
+        diff_invariant_sets(&base, &head).changes.iter().map(|c| c.kind).collect()
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(kinds(&["latency_ms <= 500"], &["latency_ms <= 300"]), vec![ChangeKind::Strengthened]);
+        assert_eq!(kinds(&["latency_ms <= 300"], &["latency_ms < 500"]), vec![ChangeKind::Weakened]);
+        assert_eq!(kinds(&["balance >= 0"], &["balance > 0"]), vec![ChangeKind::Strengthened]);
+        assert_eq!(kinds(&["retries <= 3"], &["retries == 2"]), vec![ChangeKind::Strengthened]);
+        assert_eq!(kinds(&["x <= 10"], &["x >= 5"]), vec![ChangeKind::Modified]);
+    }
+
+    #[test]
+    fn test_conjuncts_and_disjuncts() {
+        assert_eq!(kinds(&["x > 0"], &["x > 0 && y < 10"]), vec![ChangeKind::Strengthened]);
+        assert_eq!(kinds(&["x > 0 && x < 100"], &["x > 0"]), vec![ChangeKind::Weakened]);
+        assert_eq!(kinds(&["x > 0"], &["x > 0 || y > 0"]), vec![ChangeKind::Weakened]);
+    }
+
+    #[test]
+    fn test_added_removed_and_unchanged() {
+        let base = vec![invariant("balance >= 0"), invariant("latency_ms < 100")];
+        let head = vec![invariant("0 <= balance"), invariant("refund_days <= 30")];
+
+        let diff = diff_invariant_sets(&base, &head);
+
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.changes.len(), 2);
+        assert_eq!(diff.changes[0].kind, ChangeKind::Removed);
+        assert_eq!(diff.changes[0].before.as_ref().unwrap().formal_expression, "latency_ms < 100");
+        assert_eq!(diff.changes[1].kind, ChangeKind::Added);
+        assert_eq!(diff.count(ChangeKind::Added), 1);
+    }
+
+    #[test]
+    fn test_same_source_pairs_unrelated_expressions() {
+        let mut before = invariant("balance >= 0");
+        before.description = "Balance is never negative".to_string();
+        let mut after = invariant("ledger_total >= overdraft_limit");
+        after.description = "balance is never negative".to_string();
+
+        let diff = diff_invariant_sets(&[before], &[after]);
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].kind, ChangeKind::Modified);
+    }
+}
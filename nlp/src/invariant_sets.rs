@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::SystemTime;
+use spec_to_proof_error::Error;
+use sha2::{Digest, Sha256};
+use storage::{Entity, EntityQuery, ExpectedVersion, Repository};
+use cost_governance::tenant;
+use notifications::{Notification, NotificationKind, Notifier, Severity};
+
+use crate::proto::nlp::v1::{StoredInvariant, StoredInvariantSet};
+
+// spec_to_proof.v1.InvariantStatus.INVARIANT_STATUS_CONFIRMED
+pub const INVARIANT_STATUS_CONFIRMED: i32 = 2;
+
+// spec_to_proof.v1.InvariantSetStatus values
+pub const INVARIANT_SET_STATUS_DRAFT: i32 = 1;
+pub const INVARIANT_SET_STATUS_REVIEW: i32 = 2;
+pub const INVARIANT_SET_STATUS_APPROVED: i32 = 3;
+pub const INVARIANT_SET_STATUS_PROVEN: i32 = 4;
+pub const INVARIANT_SET_STATUS_FAILED: i32 = 5;
+
+/// Tags of the form `component:<name>` assign an invariant to a component
+pub const COMPONENT_TAG_PREFIX: &str = "component:";
+
+const QUERY_PAGE_SIZE: u32 = 100;
+
+impl Entity for StoredInvariantSet {
+    const KIND: &'static str = "INVARIANT_SET";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    // Only sets drawn from a single document can be found by it
+    fn source_id(&self) -> Option<String> {
+        match self.source_document_ids.as_slice() {
+            [document_id] => Some(document_id.clone()),
+            _ => None,
+        }
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Grouping {
+    /// One set per source document
+    SourceDocument,
+    /// One set per `component:` tag; an invariant with several component
+    /// tags joins each of their sets, one without any joins none
+    Component,
+    /// A single set of invariants picked by a user
+    Selection { name: String, invariant_ids: Vec<String> },
+}
+
+impl Grouping {
+    fn label(&self) -> &'static str {
+        match self {
+            Grouping::SourceDocument => "document",
+            Grouping::Component => "component",
+            Grouping::Selection { .. } => "selection",
+        }
+    }
+}
+
+/// Members of one set before it is stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetMembership {
+    pub key: String,
+    pub invariant_ids: Vec<String>,
+    pub source_document_ids: Vec<String>,
+}
+
+/// Groups the confirmed invariants; members and sets come out sorted so
+/// regrouping the same invariants gives the same result
+pub fn group_invariants(invariants: &[StoredInvariant], grouping: &Grouping) -> Result<Vec<SetMembership>, Error> {
+    let confirmed = invariants.iter().filter(|i| i.status == INVARIANT_STATUS_CONFIRMED);
+    let mut groups: BTreeMap<String, Vec<&StoredInvariant>> = BTreeMap::new();
+
+    match grouping {
+        Grouping::SourceDocument => {
+            for invariant in confirmed {
+                groups.entry(invariant.document_id.clone()).or_default().push(invariant);
+            }
+        }
+        Grouping::Component => {
+            for invariant in confirmed {
+                let tags = invariant.invariant.iter().flat_map(|i| i.tags.iter());
+                let components: BTreeSet<&str> = tags
+                    .filter_map(|tag| tag.strip_prefix(COMPONENT_TAG_PREFIX))
+                    .map(str::trim)
+                    .filter(|component| !component.is_empty())
+                    .collect();
+                for component in components {
+                    groups.entry(component.to_string()).or_default().push(invariant);
+                }
+            }
+        }
+        Grouping::Selection { name, invariant_ids } => {
+            if name.trim().is_empty() {
+                return Err(Error::invalid_input("A selected invariant set needs a name"));
+            }
+            let members: Vec<&StoredInvariant> = confirmed.filter(|i| invariant_ids.contains(&i.id)).collect();
+            if let Some(missing) = invariant_ids.iter().find(|id| !members.iter().any(|i| &i.id == *id)) {
+                return Err(Error::invalid_input(format!("Invariant {} is not a confirmed invariant", missing)));
+            }
+            groups.insert(name.trim().to_string(), members);
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .filter(|(_, members)| !members.is_empty())
+        .map(|(key, members)| {
+            let invariant_ids: BTreeSet<String> = members.iter().map(|i| i.id.clone()).collect();
+            let source_document_ids: BTreeSet<String> = members.iter().map(|i| i.document_id.clone()).collect();
+            SetMembership {
+                key,
+                invariant_ids: invariant_ids.into_iter().collect(),
+                source_document_ids: source_document_ids.into_iter().collect(),
+            }
+        })
+        .collect())
+}
+
+// The same grouping key always maps to the same set, so reassembling
+// updates sets in place
+pub fn invariant_set_id(grouping: &Grouping, key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(grouping.label().as_bytes());
+    hasher.update(b":");
+    hasher.update(key.as_bytes());
+    format!("set_{}", &hex::encode(hasher.finalize())[..16])
+}
+
+/// Hash over the member IDs, which are themselves derived from content
+pub fn membership_hash(invariant_ids: &[String]) -> String {
+    let mut sorted: Vec<&str> = invariant_ids.iter().map(String::as_str).collect();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut hasher = Sha256::new();
+    for id in sorted {
+        hasher.update(id.as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Draft → Review → Approved, with Review able to go back to Draft when
+/// changes are requested; Approved sets are settled by proving
+pub fn can_transition(from: i32, to: i32) -> bool {
+    matches!(
+        (from, to),
+        (INVARIANT_SET_STATUS_DRAFT, INVARIANT_SET_STATUS_REVIEW)
+            | (INVARIANT_SET_STATUS_REVIEW, INVARIANT_SET_STATUS_DRAFT)
+            | (INVARIANT_SET_STATUS_REVIEW, INVARIANT_SET_STATUS_APPROVED)
+            | (INVARIANT_SET_STATUS_APPROVED, INVARIANT_SET_STATUS_PROVEN)
+            | (INVARIANT_SET_STATUS_APPROVED, INVARIANT_SET_STATUS_FAILED)
+    )
+}
+
+fn set_name(grouping: &Grouping, key: &str) -> String {
+    match grouping {
+        Grouping::SourceDocument => format!("Invariants from {}", key),
+        Grouping::Component => format!("{} invariants", key),
+        Grouping::Selection { .. } => key.to_string(),
+    }
+}
+
+/// Builds and maintains invariant sets from the stored invariants
+pub struct InvariantSetAssembler {
+    invariants: Arc<dyn Repository<StoredInvariant>>,
+    sets: Arc<dyn Repository<StoredInvariantSet>>,
+    notifier: Option<Arc<Notifier>>,
+}
+
+impl std::fmt::Debug for InvariantSetAssembler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InvariantSetAssembler").finish_non_exhaustive()
+    }
+}
+
+impl InvariantSetAssembler {
+    pub fn new(
+        invariants: Arc<dyn Repository<StoredInvariant>>,
+        sets: Arc<dyn Repository<StoredInvariantSet>>,
+    ) -> Self {
+        Self { invariants, sets, notifier: None }
+    }
+
+    /// Asks reviewers to look at sets moved to Review
+    pub fn with_notifier(mut self, notifier: Arc<Notifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Groups the confirmed invariants of the documents and stores a set
+    /// per group. A set whose membership changed gets a new content hash
+    /// and goes back to Draft, since what was reviewed is no longer what it
+    /// holds; unchanged sets are left as they are.
+    pub async fn assemble(&self, document_ids: &[String], grouping: &Grouping) -> Result<Vec<StoredInvariantSet>, Error> {
+        let mut invariants = Vec::new();
+        for document_id in document_ids {
+            invariants.extend(self.document_invariants(document_id).await?);
+        }
+
+        let mut sets = Vec::new();
+        for membership in group_invariants(&invariants, grouping)? {
+            sets.push(self.store(grouping, membership).await?);
+        }
+        Ok(sets)
+    }
+
+    /// Moves a set along its review lifecycle
+    pub async fn transition(&self, set_id: &str, status: i32) -> Result<StoredInvariantSet, Error> {
+        let current = self
+            .sets
+            .get(set_id)
+            .await?
+            .ok_or_else(|| Error::invalid_input(format!("Invariant set {} not found", set_id)))?;
+        if !can_transition(current.entity.status, status) {
+            return Err(Error::invalid_input(format!(
+                "Invariant set {} cannot move from status {} to {}",
+                set_id, current.entity.status, status
+            )));
+        }
+
+        let mut set = current.entity;
+        set.status = status;
+        set.modified_at = Some(prost_types::Timestamp::from(SystemTime::now()));
+        self.sets.put(&set, ExpectedVersion::Exactly(current.version)).await?;
+
+        if status == INVARIANT_SET_STATUS_REVIEW {
+            if let Some(notifier) = &self.notifier {
+                let notification = Notification::new(NotificationKind::ReviewRequested, Severity::Info)
+                    .with_tenant(&tenant::current_tenant())
+                    .with_field("invariant_set_id", &set.id)
+                    .with_field("invariant_set_name", &set.name)
+                    .with_field("invariant_count", set.invariant_ids.len());
+                notifier.notify(&notification).await;
+            }
+        }
+        Ok(set)
+    }
+
+    async fn store(&self, grouping: &Grouping, membership: SetMembership) -> Result<StoredInvariantSet, Error> {
+        let id = invariant_set_id(grouping, &membership.key);
+        let content_sha256 = membership_hash(&membership.invariant_ids);
+        let now = Some(prost_types::Timestamp::from(SystemTime::now()));
+
+        let (set, expected) = match self.sets.get(&id).await? {
+            Some(current) if current.entity.content_sha256 == content_sha256 => return Ok(current.entity),
+            Some(current) => {
+                tracing::info!(
+                    "Invariant set {} membership changed ({} -> {} invariants), returning it to draft",
+                    id,
+                    current.entity.invariant_ids.len(),
+                    membership.invariant_ids.len()
+                );
+                let set = StoredInvariantSet {
+                    content_sha256,
+                    invariant_ids: membership.invariant_ids,
+                    source_document_ids: membership.source_document_ids,
+                    modified_at: now,
+                    status: INVARIANT_SET_STATUS_DRAFT,
+                    ..current.entity
+                };
+                (set, ExpectedVersion::Exactly(current.version))
+            }
+            None => {
+                let set = StoredInvariantSet {
+                    id,
+                    content_sha256,
+                    name: set_name(grouping, &membership.key),
+                    description: String::new(),
+                    invariant_ids: membership.invariant_ids,
+                    source_document_ids: membership.source_document_ids,
+                    created_at: now.clone(),
+                    modified_at: now,
+                    status: INVARIANT_SET_STATUS_DRAFT,
+                    grouping: grouping.label().to_string(),
+                };
+                (set, ExpectedVersion::Absent)
+            }
+        };
+
+        self.sets.put(&set, expected).await?;
+        Ok(set)
+    }
+
+    async fn document_invariants(&self, document_id: &str) -> Result<Vec<StoredInvariant>, Error> {
+        let query = EntityQuery::BySource(document_id.to_string());
+        let mut items = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let page = self.invariants.query(&query, page_token.as_deref(), QUERY_PAGE_SIZE).await?;
+            items.extend(page.items.into_iter().map(|v| v.entity));
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(items),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::to_stored;
+    use crate::proto::nlp::v1::ExtractedInvariant;
+    use storage::InMemoryRepository;
+
+    fn confirmed(document_id: &str, expression: &str, tags: &[&str]) -> StoredInvariant {
+        let invariant = ExtractedInvariant {
+            description: expression.to_string(),
+            formal_expression: expression.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+        StoredInvariant {
+            status: INVARIANT_STATUS_CONFIRMED,
+            ..to_stored(document_id, &invariant)
+        }
+    }
+
+    #[test]
+    fn test_group_by_document_skips_unconfirmed() {
+        let mut extracted = confirmed("DOC-2", "y > 0", &[]);
+        extracted.status = 1;
+        let invariants = vec![confirmed("DOC-2", "x > 0", &[]), confirmed("DOC-1", "z > 0", &[]), extracted];
+
+        let groups = group_invariants(&invariants, &Grouping::SourceDocument).unwrap();
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key, "DOC-1");
+        assert_eq!(groups[1].invariant_ids, vec![invariants[0].id.clone()]);
+    }
+
+    #[test]
+    fn test_group_by_component() {
+        let invariants = vec![
+            confirmed("DOC-1", "balance >= 0", &["component:ledger", "money"]),
+            confirmed("DOC-2", "latency_ms <= 50", &["component:api", "component:ledger"]),
+            confirmed("DOC-2", "untagged > 0", &[]),
+        ];
+
+        let groups = group_invariants(&invariants, &Grouping::Component).unwrap();
+
+        assert_eq!(groups.iter().map(|g| g.key.as_str()).collect::<Vec<_>>(), vec!["api", "ledger"]);
+        assert_eq!(groups[1].invariant_ids.len(), 2);
+        assert_eq!(groups[1].source_document_ids, vec!["DOC-1", "DOC-2"]);
+    }
+
+    #[test]
+    fn test_selection_requires_confirmed_members() {
+        let mut rejected = confirmed("DOC-1", "y > 0", &[]);
+        rejected.status = 3;
+        let invariants = vec![confirmed("DOC-1", "x > 0", &[]), rejected.clone()];
+
+        let selection = Grouping::Selection {
+            name: "Release 1.2".to_string(),
+            invariant_ids: vec![invariants[0].id.clone()],
+        };
+        let groups = group_invariants(&invariants, &selection).unwrap();
+        assert_eq!(groups[0].key, "Release 1.2");
+
+        let selection = Grouping::Selection {
+            name: "Release 1.2".to_string(),
+            invariant_ids: vec![invariants[0].id.clone(), rejected.id],
+        };
+        assert_eq!(group_invariants(&invariants, &selection).unwrap_err().kind(), "invalid_input");
+    }
+
+    #[test]
+    fn test_membership_hash_ignores_order() {
+        let a = membership_hash(&["inv_1".to_string(), "inv_2".to_string()]);
+        let b = membership_hash(&["inv_2".to_string(), "inv_1".to_string()]);
+        assert_eq!(a, b);
+        assert_ne!(a, membership_hash(&["inv_1".to_string()]));
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        assert!(can_transition(INVARIANT_SET_STATUS_DRAFT, INVARIANT_SET_STATUS_REVIEW));
+        assert!(can_transition(INVARIANT_SET_STATUS_REVIEW, INVARIANT_SET_STATUS_APPROVED));
+        assert!(can_transition(INVARIANT_SET_STATUS_REVIEW, INVARIANT_SET_STATUS_DRAFT));
+        assert!(!can_transition(INVARIANT_SET_STATUS_DRAFT, INVARIANT_SET_STATUS_APPROVED));
+        assert!(!can_transition(INVARIANT_SET_STATUS_APPROVED, INVARIANT_SET_STATUS_DRAFT));
+    }
+
+    #[tokio::test]
+    async fn test_membership_change_resets_review() {
+        let invariants = Arc::new(InMemoryRepository::<StoredInvariant>::new());
+        let sets = Arc::new(InMemoryRepository::<StoredInvariantSet>::new());
+        let assembler = InvariantSetAssembler::new(invariants.clone(), sets.clone());
+        let documents = vec!["DOC-1".to_string()];
+
+        invariants.put(&confirmed("DOC-1", "x > 0", &[]), ExpectedVersion::Absent).await.unwrap();
+        let first = assembler.assemble(&documents, &Grouping::SourceDocument).await.unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].status, INVARIANT_SET_STATUS_DRAFT);
+
+        let set_id = first[0].id.clone();
+        assembler.transition(&set_id, INVARIANT_SET_STATUS_REVIEW).await.unwrap();
+        assembler.transition(&set_id, INVARIANT_SET_STATUS_APPROVED).await.unwrap();
+        let err = assembler.transition(&set_id, INVARIANT_SET_STATUS_REVIEW).await.unwrap_err();
+        assert_eq!(err.kind(), "invalid_input");
+
+        // Same members: the approval stands
+        let again = assembler.assemble(&documents, &Grouping::SourceDocument).await.unwrap();
+        assert_eq!(again[0].status, INVARIANT_SET_STATUS_APPROVED);
+
+        invariants.put(&confirmed("DOC-1", "y > 0", &[]), ExpectedVersion::Absent).await.unwrap();
+        let changed = assembler.assemble(&documents, &Grouping::SourceDocument).await.unwrap();
+        assert_eq!(changed[0].id, set_id);
+        assert_eq!(changed[0].status, INVARIANT_SET_STATUS_DRAFT);
+        assert_eq!(changed[0].invariant_ids.len(), 2);
+        assert_ne!(changed[0].content_sha256, first[0].content_sha256);
+        assert_eq!(sets.get(&set_id).await.unwrap().unwrap().entity, changed[0]);
+    }
+}
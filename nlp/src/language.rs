@@ -0,0 +1,196 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::proto::nlp::v1::ExtractInvariantsRequest;
+
+/// Languages with their own extraction prompt and number conventions.
+/// Documents in other languages are treated as English.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    English,
+    German,
+    French,
+    Japanese,
+}
+
+impl Language {
+    pub const ALL: [Language; 4] = [Language::English, Language::German, Language::French, Language::Japanese];
+
+    /// ISO 639-1 code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Japanese => "ja",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|language| language.code().eq_ignore_ascii_case(code.trim()))
+    }
+
+    /// Whether `1.000,5` means one thousand and a half
+    pub fn uses_decimal_comma(&self) -> bool {
+        matches!(self, Language::German | Language::French)
+    }
+
+    fn stopwords(&self) -> &'static [&'static str] {
+        match self {
+            Language::English => &[
+                "the", "and", "must", "shall", "should", "is", "be", "not", "of", "to", "with", "for", "each",
+                "than", "when",
+            ],
+            Language::German => &[
+                "der", "die", "das", "und", "muss", "darf", "ist", "nicht", "mit", "ein", "eine", "für", "den",
+                "dem", "werden", "jede", "jeder", "sein",
+            ],
+            Language::French => &[
+                "le", "la", "les", "et", "doit", "est", "être", "pas", "ne", "des", "une", "un", "pour", "avec",
+                "du", "chaque", "dans",
+            ],
+            Language::Japanese => &[],
+        }
+    }
+}
+
+/// The language the request asks for, or the one its content is written in
+pub fn resolve_language(request: &ExtractInvariantsRequest) -> Language {
+    match Language::from_code(&request.language) {
+        Some(language) => language,
+        None => {
+            if !request.language.is_empty() {
+                tracing::warn!(
+                    "Unsupported language {} for document {}, detecting it instead",
+                    request.language,
+                    request.document_id
+                );
+            }
+            detect_language(&request.content)
+        }
+    }
+}
+
+/// Guesses the language from the script and the most common function words
+pub fn detect_language(text: &str) -> Language {
+    // Kana and kanji only occur in Japanese among the supported languages;
+    // a fifth of the letters allows for English identifiers and code
+    let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+    let japanese = text.chars().filter(|c| is_japanese(*c)).count();
+    if letters > 0 && japanese * 5 >= letters {
+        return Language::Japanese;
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let score = |language: Language| {
+        words.iter().filter(|word| language.stopwords().contains(&word.as_str())).count()
+    };
+
+    // English wins ties, including documents with no function words at all
+    [Language::German, Language::French]
+        .into_iter()
+        .map(|language| (language, score(language)))
+        .filter(|(_, count)| *count > score(Language::English))
+        .max_by_key(|(_, count)| *count)
+        .map(|(language, _)| language)
+        .unwrap_or(Language::English)
+}
+
+fn is_japanese(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{309F}'   // hiragana
+        | '\u{30A0}'..='\u{30FF}' // katakana
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+    )
+}
+
+/// Rewrites locale-specific numbers in formal expressions to the `1000.5`
+/// form the expression parser and unit checker understand. In decimal
+/// comma languages a comma between digits is always a decimal separator,
+/// so function arguments must be separated by `, `.
+pub struct NumberNormalizer {
+    point_grouped: Regex,
+    comma_grouped: Regex,
+    decimal_comma: Regex,
+}
+
+impl Default for NumberNormalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NumberNormalizer {
+    pub fn new() -> Self {
+        Self {
+            // 1,000,000.5
+            point_grouped: Regex::new(r"\b\d{1,3}(?:,\d{3})+(?:\.\d+)?\b").unwrap(),
+            // 1.000.000,5 or 1 000 000,5 with (narrow) no-break spaces
+            comma_grouped: Regex::new(r"\b\d{1,3}(?:[.\u{00A0}\u{202F}]\d{3})+(?:,\d+)?\b").unwrap(),
+            // 0,25
+            decimal_comma: Regex::new(r"\b(\d+),(\d+)\b").unwrap(),
+        }
+    }
+
+    pub fn normalize(&self, expression: &str, language: Language) -> String {
+        let expression = to_ascii_digits(expression);
+        if language.uses_decimal_comma() {
+            let ungrouped = self.comma_grouped.replace_all(&expression, |caps: &Captures| {
+                caps[0].replace(['.', '\u{00A0}', '\u{202F}'], "")
+            });
+            self.decimal_comma.replace_all(&ungrouped, "$1.$2").into_owned()
+        } else {
+            self.point_grouped
+                .replace_all(&expression, |caps: &Captures| caps[0].replace(',', ""))
+                .into_owned()
+        }
+    }
+}
+
+// Full-width digits and separators, common in Japanese text
+fn to_ascii_digits(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '０'..='９' => char::from(b'0' + (c as u32 - '０' as u32) as u8),
+            '．' => '.',
+            '，' => ',',
+            _ => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("The balance must never be negative."), Language::English);
+        assert_eq!(detect_language("Der Kontostand darf nicht negativ sein und muss geprüft werden."), Language::German);
+        assert_eq!(detect_language("Le solde doit être positif pour chaque compte."), Language::French);
+        assert_eq!(detect_language("残高は常に0以上でなければならない。max_balance"), Language::Japanese);
+        assert_eq!(detect_language("max_retries <= 3"), Language::English);
+    }
+
+    #[test]
+    fn test_normalize_numbers_per_locale() {
+        let normalizer = NumberNormalizer::new();
+        assert_eq!(normalizer.normalize("amount <= 1,000.5", Language::English), "amount <= 1000.5");
+        assert_eq!(normalizer.normalize("amount <= 1.000,5", Language::German), "amount <= 1000.5");
+        assert_eq!(normalizer.normalize("ratio <= 0,25", Language::French), "ratio <= 0.25");
+        assert_eq!(normalizer.normalize("amount <= 1\u{202F}000", Language::French), "amount <= 1000");
+        assert_eq!(normalizer.normalize("timeout <= ３０", Language::Japanese), "timeout <= 30");
+        assert_eq!(normalizer.normalize("max(a, 2) <= 1.5", Language::English), "max(a, 2) <= 1.5");
+    }
+
+    #[test]
+    fn test_language_codes() {
+        assert_eq!(Language::from_code("DE"), Some(Language::German));
+        assert_eq!(Language::from_code("es"), None);
+    }
+}
@@ -1,29 +1,65 @@
+pub mod backfill;
+pub mod chunking;
 pub mod claude_client;
 pub mod extractor;
 pub mod post_processor;
 pub mod cache;
+pub mod consumer;
+pub mod drift;
+pub mod evaluation;
+pub mod expression;
+pub mod gherkin;
+pub mod invariant_diff;
+pub mod invariant_sets;
+pub mod language;
+pub mod openapi;
+pub mod persistence;
 pub mod pii_redactor;
+pub mod pipeline;
 pub mod prompts;
 pub mod proto;
+pub mod proto_schema;
+pub mod runtime;
+pub mod single_flight;
+pub mod streaming;
+pub mod source_spans;
+pub mod taxonomy;
+pub mod units;
 
 use std::collections::HashMap;
-use std::error::Error;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use spec_to_proof_error::Error;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use aws_sdk_dynamodb::Client as DynamoClient;
 use regex::Regex;
+use storage::{EntityStore, OutboxRelay, OutboxStore, Repository, StorageSettings};
+use prompt_registry::{PromptRegistry, SelectedPrompt};
+use health::{HealthChecker, HealthReport};
+use circuit_breaker::CircuitBreaker;
+use reload::ConfigHandle;
+use cost_governance::{tenant, CostGovernanceConfig, CostGovernanceManager, LlmCallGovernor, ModelPricing, PricingTable};
+use notifications::{Notification, NotificationKind, NotificationSettings, Notifier, Severity};
+use spec_to_proof_proto::pagination;
+use spec_to_proof_proto::{ListInvariantsRequest, ListInvariantsResponse};
 
 use crate::proto::nlp::v1::{
     ExtractInvariantsRequest, ExtractInvariantsResponse, ExtractedInvariant,
-    Variable, Priority, TokenUsage, ProcessingMetadata, ExtractionMetadata,
-    HealthCheckRequest, HealthCheckResponse
+    DiffInvariantSetsRequest, DiffInvariantSetsResponse,
+    Variable, Priority, TokenUsage, ProcessingMetadata, ExtractionMetadata, ExtractionPlan,
+    HealthCheckRequest, HealthCheckResponse, HealthProbe, DependencyCheck, StoredInvariant,
+    PurgeCacheRequest, PurgeCacheResponse, GetCacheStatsResponse,
+    BackfillRequest, BackfillResponse, DocumentBackfill, StoredDocument,
 };
 
-use crate::claude_client::ClaudeClient;
-use crate::extractor::InvariantExtractor;
-use crate::cache::DynamoCache;
-use crate::pii_redactor::PiiRedactor;
+use crate::claude_client::{ClaudeClient, CLAUDE_CIRCUIT};
+use crate::cache::{DynamoCache, PurgeFilter};
+use crate::drift::DriftPublisher;
+use crate::pipeline::{ExtractionPipeline, PipelineOutput};
+use crate::runtime::RuntimeSettings;
+use crate::single_flight::SingleFlight;
+use crate::taxonomy::TaxonomyConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InvariantExtractionConfig {
@@ -32,10 +68,38 @@ pub struct InvariantExtractionConfig {
     pub max_tokens: u32,
     pub temperature: f32,
     pub cache_ttl_seconds: u64,
+    /// TTL for documents that produced no invariants, kept short so edits
+    /// that add requirements are picked up soon
+    #[serde(default = "default_negative_cache_ttl_seconds")]
+    pub negative_cache_ttl_seconds: u64,
     pub max_retries: u32,
     pub retry_delay_ms: u64,
+    /// Stream model responses, keeping the invariants received when this
+    /// many milliseconds pass; unset waits for the whole completion
+    #[serde(default)]
+    pub stream_deadline_ms: Option<u64>,
+    /// Redis for per-tenant LLM rate limits; cost governance is off when
+    /// unset
+    #[serde(default)]
+    pub cost_governance_redis_url: Option<String>,
     pub confidence_threshold: f64,
     pub cost_per_1k_tokens: f64,
+    pub chunk_token_budget: u32,
+    pub chunk_overlap_tokens: u32,
+    #[serde(default)]
+    pub taxonomy: TaxonomyConfig,
+    /// Prompt manifest overlaid on the built-in prompts: a file path or an
+    /// s3://bucket/key URI
+    #[serde(default)]
+    pub prompt_manifest: Option<String>,
+    pub storage: StorageSettings,
+    /// Slack and Teams channels told about completed extractions, spec
+    /// drift and tenant budget thresholds; none when unset
+    #[serde(default)]
+    pub notifications: Option<NotificationSettings>,
+    /// Per-dependency timeout for readiness checks
+    #[serde(default = "default_health_check_timeout_ms")]
+    pub health_check_timeout_ms: u64,
 }
 
 impl Default for InvariantExtractionConfig {
@@ -46,135 +110,525 @@ impl Default for InvariantExtractionConfig {
             max_tokens: 4000,
             temperature: 0.0,
             cache_ttl_seconds: 86400, // 24 hours
+            negative_cache_ttl_seconds: default_negative_cache_ttl_seconds(),
             max_retries: 3,
             retry_delay_ms: 1000,
+            stream_deadline_ms: None,
+            cost_governance_redis_url: None,
             confidence_threshold: 0.5,
             cost_per_1k_tokens: 0.015, // Claude 3 Opus pricing
+            chunk_token_budget: 8000,
+            chunk_overlap_tokens: 200,
+            taxonomy: TaxonomyConfig::default(),
+            prompt_manifest: None,
+            storage: StorageSettings::default(),
+            notifications: None,
+            health_check_timeout_ms: default_health_check_timeout_ms(),
         }
     }
 }
 
+fn default_negative_cache_ttl_seconds() -> u64 {
+    300
+}
+
+fn default_health_check_timeout_ms() -> u64 {
+    2000
+}
+
+// Claude and the invariant store are needed for every extraction; the
+// cache and the cost governor's Redis only make them cheaper or bounded
+fn build_health_checker(
+    config: &InvariantExtractionConfig,
+    claude_client: &Arc<ClaudeClient>,
+    cache: &Arc<DynamoCache>,
+    invariant_repository: &Arc<dyn Repository<StoredInvariant>>,
+    governor: Option<&Arc<LlmCallGovernor>>,
+) -> HealthChecker {
+    let claude_client = claude_client.clone();
+    let cache = cache.clone();
+    let invariant_repository = invariant_repository.clone();
+    let mut checker = HealthChecker::new(Duration::from_millis(config.health_check_timeout_ms))
+        .with_check("claude_api", true, move || {
+            let claude_client = claude_client.clone();
+            async move { claude_client.ping().await.map_err(|e| e.to_string()) }
+        })
+        // Open while recent Claude calls are failing; extractions fail fast
+        // until it half-opens
+        .with_check("claude_circuit", false, || {
+            let breaker = CircuitBreaker::shared(CLAUDE_CIRCUIT);
+            async move { breaker.health_check() }
+        })
+        .with_check("storage", true, move || {
+            let invariant_repository = invariant_repository.clone();
+            async move { invariant_repository.ping().await.map_err(|e| e.to_string()) }
+        })
+        .with_check("dynamodb_cache", false, move || {
+            let cache = cache.clone();
+            async move { cache.ping().await.map_err(|e| e.to_string()) }
+        });
+    if let Some(governor) = governor {
+        let governor = governor.clone();
+        checker = checker.with_check("redis", false, move || {
+            let governor = governor.clone();
+            async move { governor.ping().await.map_err(|e| e.to_string()) }
+        });
+    }
+    checker
+}
+
+fn build_pipeline(config: &InvariantExtractionConfig, governor: Option<&Arc<LlmCallGovernor>>) -> ExtractionPipeline {
+    let pipeline = ExtractionPipeline::new(config);
+    match governor {
+        Some(governor) => {
+            let language_model = ClaudeClient::from_config(config).with_cost_governance(governor.clone());
+            pipeline.with_language_model(Arc::new(language_model))
+        }
+        None => pipeline,
+    }
+}
+
+fn health_response(report: &HealthReport) -> HealthCheckResponse {
+    HealthCheckResponse {
+        status: report.status.as_str().to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+        dependencies: report
+            .checks
+            .iter()
+            .map(|check| DependencyCheck {
+                name: check.name.clone(),
+                status: check.status.as_str().to_string(),
+                critical: check.critical,
+                latency_ms: check.latency_ms,
+                message: check.message.clone().unwrap_or_default(),
+            })
+            .collect(),
+    }
+}
+
 pub struct NlpService {
     config: InvariantExtractionConfig,
-    claude_client: ClaudeClient,
-    extractor: InvariantExtractor,
-    cache: DynamoCache,
-    pii_redactor: PiiRedactor,
-    post_processor: post_processor::PostProcessor,
+    claude_client: Arc<ClaudeClient>,
+    runtime: ConfigHandle<RuntimeSettings>,
+    // Built from the runtime settings generation it is tagged with
+    pipeline: std::sync::RwLock<(u64, Arc<ExtractionPipeline>)>,
+    cache: Arc<DynamoCache>,
+    in_flight: SingleFlight<ExtractInvariantsResponse>,
+    prompts: PromptRegistry,
+    invariant_repository: Arc<dyn Repository<StoredInvariant>>,
+    document_repository: Arc<dyn Repository<StoredDocument>>,
+    governor: Option<Arc<LlmCallGovernor>>,
+    drift_publisher: Option<DriftPublisher>,
+    // Where mark_stale writes drift events, beside the invariants
+    outbox: Arc<dyn OutboxStore>,
+    notifier: Option<Arc<Notifier>>,
+    health: HealthChecker,
 }
 
 impl NlpService {
     pub async fn new(
         config: InvariantExtractionConfig,
         dynamo_client: DynamoClient,
-    ) -> Result<Self, Box<dyn Error>> {
-        let claude_client = ClaudeClient::new(&config.claude_api_key, &config.claude_model);
-        let extractor = InvariantExtractor::new(&config);
-        let cache = DynamoCache::new(dynamo_client, &config);
-        let pii_redactor = PiiRedactor::new();
-        let post_processor = post_processor::PostProcessor::new();
+    ) -> Result<Self, Error> {
+        let claude_client = Arc::new(ClaudeClient::new(&config.claude_api_key, &config.claude_model));
+        let notifier = match &config.notifications {
+            Some(settings) => Some(Arc::new(Notifier::connect(settings).await?)),
+            None => None,
+        };
+        let mut governor = None;
+        if let Some(redis_url) = &config.cost_governance_redis_url {
+            let mut manager = CostGovernanceManager::connect(redis_url, CostGovernanceConfig::default()).await?;
+            if let Some(notifier) = &notifier {
+                manager = manager.with_notifier(notifier.clone());
+            }
+            governor = Some(Arc::new(LlmCallGovernor::new(
+                Arc::new(manager),
+                PricingTable::new(ModelPricing::flat(config.cost_per_1k_tokens)),
+            )));
+        }
+        let pipeline = build_pipeline(&config, governor.as_ref());
+        let store = EntityStore::connect(&config.storage).await?;
+        let invariant_repository = store.repository::<StoredInvariant>();
+        let document_repository = store.repository::<StoredDocument>();
+        let cache = Arc::new(DynamoCache::new(dynamo_client, &config));
+        let health = build_health_checker(&config, &claude_client, &cache, &invariant_repository, governor.as_ref());
+        let mut prompts = prompts::builtin_registry();
+        if let Some(location) = &config.prompt_manifest {
+            prompts.apply_manifest(prompt_registry::load_manifest(location).await?)?;
+        }
 
         Ok(Self {
+            runtime: ConfigHandle::new(RuntimeSettings::from_config(&config)),
             config,
             claude_client,
-            extractor,
+            pipeline: std::sync::RwLock::new((0, Arc::new(pipeline))),
             cache,
-            pii_redactor,
-            post_processor,
+            in_flight: SingleFlight::new(),
+            prompts,
+            invariant_repository,
+            document_repository,
+            governor,
+            drift_publisher: None,
+            outbox: store.outbox(),
+            notifier,
+            health,
         })
     }
 
+    pub fn with_invariant_repository(mut self, repository: Arc<dyn Repository<StoredInvariant>>) -> Self {
+        self.invariant_repository = repository;
+        self.health = build_health_checker(
+            &self.config,
+            &self.claude_client,
+            &self.cache,
+            &self.invariant_repository,
+            self.governor.as_ref(),
+        );
+        self
+    }
+
+    pub fn with_document_repository(self, repository: Arc<dyn Repository<StoredDocument>>) -> Self {
+        Self {
+            document_repository: repository,
+            ..self
+        }
+    }
+
+    /// Reads confidence threshold, model and LLM call limits from `runtime`,
+    /// which a `reload::ConfigWatcher` may swap while the service runs
+    pub fn with_runtime_settings(self, runtime: ConfigHandle<RuntimeSettings>) -> Self {
+        let service = Self { runtime, ..self };
+        // Force a rebuild from the new settings
+        service.pipeline.write().unwrap().0 = u64::MAX;
+        service.current_pipeline();
+        service
+    }
+
+    pub fn runtime_settings(&self) -> ConfigHandle<RuntimeSettings> {
+        self.runtime.clone()
+    }
+
+    // Rebuilt after each reload of the runtime settings; in-flight
+    // extractions finish on the pipeline they started with
+    fn current_pipeline(&self) -> Arc<ExtractionPipeline> {
+        let generation = self.runtime.generation();
+        {
+            let (built_for, pipeline) = &*self.pipeline.read().unwrap();
+            if *built_for == generation {
+                return pipeline.clone();
+            }
+        }
+
+        let settings = self.runtime.current();
+        let pipeline = Arc::new(build_pipeline(&settings.apply(&self.config), self.governor.as_ref()));
+        if let (Some(governor), Some(limits)) = (&self.governor, &settings.llm_limits) {
+            governor.set_limits(limits.clone());
+        }
+        *self.pipeline.write().unwrap() = (generation, pipeline.clone());
+        pipeline
+    }
+
+    /// Adds the JetStream connection feeding the document consumer to the
+    /// readiness checks. Extraction over gRPC works without it, so a lost
+    /// connection only degrades the service.
+    pub fn with_nats_client(self, client: async_nats::Client) -> Self {
+        Self {
+            health: self.health.with_check("nats", false, move || {
+                let state = client.connection_state();
+                async move {
+                    match state {
+                        async_nats::connection::State::Connected => Ok(()),
+                        state => Err(format!("connection {:?}", state)),
+                    }
+                }
+            }),
+            ..self
+        }
+    }
+
+    /// Publishes an event when a new document version drifts from its
+    /// proven invariants; run `drift_relay` to deliver them
+    pub fn with_drift_events(self, publisher: DriftPublisher) -> Self {
+        Self {
+            drift_publisher: Some(publisher),
+            ..self
+        }
+    }
+
+    /// Delivers drift events from the outbox, when they are enabled
+    pub fn drift_relay(&self) -> Option<OutboxRelay> {
+        let publisher = self.drift_publisher.clone()?;
+        Some(OutboxRelay::new(self.outbox.clone(), Arc::new(publisher)))
+    }
+
+    // Attributed to the tenant the request is billed to
+    async fn notify(&self, notification: Notification) {
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(&notification.with_tenant(&tenant::current_tenant())).await;
+        }
+    }
+
     pub async fn extract_invariants(
         &self,
         request: ExtractInvariantsRequest,
-    ) -> Result<ExtractInvariantsResponse, Box<dyn Error>> {
+    ) -> Result<ExtractInvariantsResponse, Error> {
         let start_time = Instant::now();
+
+        // Routing by document keeps retries of a document on the same arm
+        let language = language::resolve_language(&request);
+        let prompt = prompts::select_extraction_prompt(&self.prompts, language, &request.document_id)?;
         
-        // Generate cache key from document content
-        let cache_key = self.generate_cache_key(&request);
+        // Generate cache key from document content and the prompt text
+        let cache_key = self.generate_cache_key(&request, &prompt);
         
         // Check cache first
-        if let Some(cached_response) = self.cache.get(&cache_key).await? {
+        if let Some(mut cached_response) = self.cache.get(&cache_key).await? {
             tracing::info!("Serving invariant extraction from cache for document {}", request.document_id);
+            if request.dry_run {
+                cached_response.plan = Some(ExtractionPlan {
+                    cache_hit: true,
+                    chunk_count: 0,
+                    estimated_token_usage: Some(TokenUsage::default()),
+                    model: self.runtime.current().claude_model.clone(),
+                    pii_detected: false,
+                });
+            }
             return Ok(self.add_metadata(cached_response, start_time, true, &cache_key));
         }
 
-        // Redact PII from content
-        let (redacted_content, pii_detected, redacted_fields) = 
-            self.pii_redactor.redact(&request.content);
+        // Dry runs stop before the model, drift detection and persistence
+        if request.dry_run {
+            let plan = self.current_pipeline().plan(&request, &prompt.template)?;
+            let response = ExtractInvariantsResponse {
+                plan: Some(plan),
+                ..Default::default()
+            };
+            return Ok(self.add_metadata(response, start_time, false, &cache_key));
+        }
 
-        // Extract invariants using Claude
-        let extraction_result = self.extractor
-            .extract_invariants(&request, &redacted_content)
+        // Concurrent requests for the same document wait for the first
+        // extraction instead of each calling the model
+        let flight = self.in_flight
+            .run(&cache_key, || self.extract_uncached(&request, &prompt, &cache_key))
             .await?;
+        if flight.shared {
+            tracing::info!("Coalesced invariant extraction for document {}", request.document_id);
+        }
 
-        // Post-process invariants
-        let processed_invariants = self.post_processor
-            .process_invariants(extraction_result.invariants)
-            .await?;
+        Ok(self.add_metadata(flight.value, start_time, flight.shared, &cache_key))
+    }
 
-        // Filter by confidence threshold
-        let filtered_invariants: Vec<ExtractedInvariant> = processed_invariants
-            .into_iter()
-            .filter(|inv| inv.confidence_score >= self.config.confidence_threshold)
-            .collect();
+    async fn extract_uncached(
+        &self,
+        request: &ExtractInvariantsRequest,
+        prompt: &SelectedPrompt,
+        cache_key: &str,
+    ) -> Result<ExtractInvariantsResponse, Error> {
+        // Kept so the document can be re-extracted by later backfills
+        persistence::persist_document(self.document_repository.as_ref(), request).await?;
+
+        // Redact, extract, verify quotes, post-process, classify and filter
+        let output = self.current_pipeline().run(request, &prompt.template).await?;
+        self.store_extraction(request, prompt, cache_key, output).await
+    }
+
+    async fn store_extraction(
+        &self,
+        request: &ExtractInvariantsRequest,
+        prompt: &SelectedPrompt,
+        cache_key: &str,
+        output: PipelineOutput,
+    ) -> Result<ExtractInvariantsResponse, Error> {
+        let (pii_detected, redacted_fields) = (output.pii_detected, output.redacted_fields);
+        let filtered_invariants = output.invariants;
+
+        // Compare with what was proven before the new version is stored.
+        // The event is written to the outbox with the stale transition, so
+        // it is delivered if and only if the invariants were marked.
+        if let Some(event) = drift::detect_drift_event(self.invariant_repository.as_ref(), &request.document_id, &filtered_invariants).await? {
+            tracing::warn!("{}", event.summary);
+            let outbox_events = match &self.drift_publisher {
+                Some(publisher) => vec![publisher.outbox_event(&event)?],
+                None => Vec::new(),
+            };
+            drift::mark_stale(self.invariant_repository.as_ref(), &event, &outbox_events).await?;
+            self.notify(
+                Notification::new(NotificationKind::DriftDetected, Severity::Warning)
+                    .with_field("document_id", &event.document_id)
+                    .with_field("drifted_count", event.invariants.len())
+                    .with_field("summary", &event.summary),
+            ).await;
+        }
+
+        let stored = persistence::persist_invariants(
+            self.invariant_repository.as_ref(),
+            &request.document_id,
+            &filtered_invariants,
+        ).await?;
+        tracing::info!("Stored {} new invariants for document {}", stored, request.document_id);
+        self.notify(
+            Notification::new(NotificationKind::ExtractionCompleted, Severity::Info)
+                .with_field("document_id", &request.document_id)
+                .with_field("invariant_count", filtered_invariants.len())
+                .with_field("stored_count", stored),
+        ).await;
 
         // Create response
-        let response = ExtractInvariantsResponse {
+        let mut response = ExtractInvariantsResponse {
             invariants: filtered_invariants,
-            token_usage: extraction_result.token_usage,
+            token_usage: output.token_usage,
             metadata: ProcessingMetadata::default(),
+            plan: None,
         };
 
-        // Cache the result
-        self.cache.set(&cache_key, &response).await?;
-
-        let final_response = self.add_metadata(
-            response, 
-            start_time, 
-            false, 
-            &cache_key
-        );
+        // Cache the result; empty results only briefly
+        let ttl_seconds = if response.invariants.is_empty() {
+            self.config.negative_cache_ttl_seconds
+        } else {
+            self.config.cache_ttl_seconds
+        };
+        // Earlier versions of the document must not be served again: a
+        // hit skips drift detection and persistence, so reverting to old
+        // content would leave the new version's invariants stored
+        let invalidated = self.cache.invalidate_document(&request.document_id).await?;
+        if invalidated > 0 {
+            tracing::info!("Invalidated {} cached extractions of document {}", invalidated, request.document_id);
+        }
+        self.cache.set_with_ttl(cache_key, &request.document_id, &response, ttl_seconds).await?;
 
         // Add extraction metadata
-        let mut response_with_metadata = final_response;
-        for invariant in &mut response_with_metadata.invariants {
+        for invariant in &mut response.invariants {
+            let chunks = invariant.extraction_metadata
+                .take()
+                .map(|metadata| metadata.chunks)
+                .unwrap_or_default();
             invariant.extraction_metadata = Some(ExtractionMetadata {
-                prompt_version: "1.0.0".to_string(),
+                prompt_version: prompt.template.version.clone(),
                 post_processing_rules: vec![
                     "variable_normalization".to_string(),
                     "unit_standardization".to_string(),
+                    "unit_consistency_check".to_string(),
+                    "taxonomy_classification".to_string(),
                     "confidence_filtering".to_string(),
                 ],
                 retry_count: 0,
                 pii_detected,
-                redacted_fields,
+                redacted_fields: redacted_fields.clone(),
+                chunks,
+                prompt_name: prompt.template.name.clone(),
+                prompt_sha256: prompt.template.sha256.clone(),
+                prompt_arm: prompt.arm.as_str().to_string(),
             });
         }
 
-        Ok(response_with_metadata)
+        Ok(response)
+    }
+
+    /// Re-extracts stored documents with the current prompts and reports
+    /// how the result differs from the invariants stored for each. Unless
+    /// the request applies the results, nothing is stored.
+    pub async fn backfill(&self, request: BackfillRequest) -> Result<BackfillResponse, Error> {
+        let documents = backfill::matching_documents(self.document_repository.as_ref(), &request).await?;
+        tracing::info!("Backfilling {} documents", documents.len());
+
+        let mut response = BackfillResponse::default();
+        for document in &documents {
+            match self.backfill_document(document, request.apply).await {
+                Ok(report) => response.documents.push(report),
+                Err(e) => {
+                    tracing::warn!("Backfill of document {} failed: {}", document.id, e);
+                    response.documents.push(backfill::failed_report(document, &e));
+                    response.failed_count += 1;
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    async fn backfill_document(&self, document: &StoredDocument, apply: bool) -> Result<DocumentBackfill, Error> {
+        let request = backfill::extraction_request(document, self.runtime.current().confidence_threshold);
+        let language = language::resolve_language(&request);
+        let prompt = prompts::select_extraction_prompt(&self.prompts, language, &request.document_id)?;
+        let current = backfill::current_invariants(self.invariant_repository.as_ref(), &document.id).await?;
+
+        let output = self.current_pipeline().run(&request, &prompt.template).await?;
+        let diff = invariant_diff::diff_invariant_sets(&current, &output.invariants);
+        tracing::info!("{}", backfill::summary(&document.id, &diff));
+
+        let applied = apply && !diff.is_empty();
+        if applied {
+            let cache_key = self.generate_cache_key(&request, &prompt);
+            self.store_extraction(&request, &prompt, &cache_key, output).await?;
+        }
+        Ok(backfill::document_report(document, &prompt.template.version, &diff, applied))
+    }
+
+    /// ListInvariants for review, paging through the stored invariants
+    pub async fn list_invariants(&self, request: &ListInvariantsRequest) -> Result<ListInvariantsResponse, Error> {
+        let response = pagination::list_invariants(
+            self.invariant_repository.as_ref(),
+            request,
+            persistence::to_invariant_model,
+        ).await?;
+        Ok(response)
+    }
+
+    pub fn diff_invariant_sets(&self, request: DiffInvariantSetsRequest) -> DiffInvariantSetsResponse {
+        invariant_diff::diff_invariant_sets(&request.base, &request.head).to_response()
+    }
+
+    pub async fn purge_cache(&self, request: PurgeCacheRequest) -> Result<PurgeCacheResponse, Error> {
+        let filter = PurgeFilter {
+            document_ids: request.document_ids,
+            expired_only: request.expired_only,
+            created_before: request.created_before.map(|timestamp| timestamp.seconds.max(0) as u64),
+        };
+        let deleted_count = self.cache.purge(&filter).await?;
+        Ok(PurgeCacheResponse { deleted_count })
+    }
+
+    pub async fn cache_stats(&self) -> Result<GetCacheStatsResponse, Error> {
+        let stats = self.cache.stats().await?;
+        Ok(GetCacheStatsResponse {
+            item_count: stats.item_count,
+            table_size_bytes: stats.table_size_bytes,
+            hits: stats.hits,
+            misses: stats.misses,
+            writes: stats.writes,
+            bytes_written: stats.bytes_written,
+            deleted: stats.deleted,
+        })
     }
 
     pub async fn health_check(
         &self,
-        _request: HealthCheckRequest,
-    ) -> Result<HealthCheckResponse, Box<dyn Error>> {
-        Ok(HealthCheckResponse {
-            status: "healthy".to_string(),
-            version: env!("CARGO_PKG_VERSION").to_string(),
-            timestamp: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
-        })
+        request: HealthCheckRequest,
+    ) -> Result<HealthCheckResponse, Error> {
+        let report = match request.probe() {
+            HealthProbe::Liveness => self.health.liveness(),
+            _ => self.health.readiness().await,
+        };
+        if !report.is_ready() {
+            tracing::warn!("Readiness check failed: {:?}", report.checks);
+        }
+        Ok(health_response(&report))
     }
 
-    fn generate_cache_key(&self, request: &ExtractInvariantsRequest) -> String {
+    fn generate_cache_key(&self, request: &ExtractInvariantsRequest, prompt: &SelectedPrompt) -> String {
         use sha2::{Sha256, Digest};
         
+        // Results depend on the model and threshold, which can change at runtime
+        let settings = self.runtime.current();
         let content = format!(
-            "{}:{}:{}:{}",
+            "{}:{}:{}:{}:{}:{}:{}",
             request.document_id,
             request.content,
             request.title,
-            request.source_system
+            request.source_system,
+            prompt.template.sha256,
+            settings.claude_model,
+            settings.confidence_threshold
         );
         
         let mut hasher = Sha256::new();
@@ -191,7 +645,7 @@ impl NlpService {
     ) -> ExtractInvariantsResponse {
         response.metadata = ProcessingMetadata {
             processed_at: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
-            model_used: self.config.claude_model.clone(),
+            model_used: self.runtime.current().claude_model.clone(),
             duration_ms: start_time.elapsed().as_millis() as i64,
             cached,
             cache_key: cache_key.to_string(),
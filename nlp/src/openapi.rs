@@ -0,0 +1,407 @@
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::proto::nlp::v1::{ExtractedInvariant, Priority, Variable};
+
+/// Confidence of invariants read from schema keywords; they state the
+/// constraint exactly, so only the naming is open to interpretation
+pub const STRUCTURED_CONFIDENCE: f64 = 0.95;
+
+pub const OPENAPI_TAG: &str = "openapi";
+
+const HTTP_METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// Parses an OpenAPI 3 or Swagger 2 document written as JSON or YAML.
+/// Returns `None` for anything else, including YAML without a top-level
+/// `openapi` or `swagger` field.
+pub fn parse_document(content: &str) -> Option<Value> {
+    let document: Value = serde_json::from_str(content)
+        .ok()
+        .or_else(|| serde_yaml::from_str(content).ok())?;
+    let is_openapi = document.get("openapi").is_some_and(Value::is_string)
+        || document.get("swagger").is_some_and(Value::is_string);
+    is_openapi.then_some(document)
+}
+
+/// Turns schema constraints (bounds, lengths, item counts, patterns,
+/// required properties) and documented rate limits into invariants without
+/// calling the model. `$ref`s are not followed: each referenced schema
+/// yields its own invariants.
+pub struct OpenApiExtractor {
+    rate_limit_text: Regex,
+}
+
+impl Default for OpenApiExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpenApiExtractor {
+    pub fn new() -> Self {
+        Self {
+            // "100 requests per minute", "100 req/min"
+            rate_limit_text: Regex::new(
+                r"(?i)\b(\d+)\s*(?:requests?|req|calls?)\s*(?:per|/|an?|every)\s*(s|sec|second|m|min|minute|h|hr|hour|d|day)\b",
+            ).unwrap(),
+        }
+    }
+
+    pub fn extract(&self, document: &Value) -> Vec<ExtractedInvariant> {
+        let mut invariants = Vec::new();
+
+        // components.schemas in OpenAPI 3, definitions in Swagger 2
+        let schemas = document
+            .pointer("/components/schemas")
+            .or_else(|| document.get("definitions"))
+            .and_then(Value::as_object);
+        for (name, schema) in schemas.into_iter().flatten() {
+            let pointer = if document.get("definitions").is_some() {
+                format!("#/definitions/{}", name)
+            } else {
+                format!("#/components/schemas/{}", name)
+            };
+            schema_invariants(&mut invariants, &snake_case(name), name, &pointer, schema);
+        }
+
+        if let Some(info) = document.get("info") {
+            self.rate_limit_invariants(&mut invariants, "api", "The API", info);
+        }
+        self.rate_limit_invariants(&mut invariants, "api", "The API", document);
+
+        for (path, item) in document.get("paths").and_then(Value::as_object).into_iter().flatten() {
+            let shared_parameters = item.get("parameters").and_then(Value::as_array);
+            for method in HTTP_METHODS {
+                let Some(operation) = item.get(method) else {
+                    continue;
+                };
+                let name = operation
+                    .get("operationId")
+                    .and_then(Value::as_str)
+                    .map(snake_case)
+                    .unwrap_or_else(|| snake_case(&format!("{} {}", method, path)));
+                let label = format!("{} {}", method.to_uppercase(), path);
+                let pointer = format!("#/paths/{}/{}", escape_pointer(path), method);
+
+                let parameters = shared_parameters
+                    .into_iter()
+                    .flatten()
+                    .chain(operation.get("parameters").and_then(Value::as_array).into_iter().flatten());
+                for parameter in parameters {
+                    parameter_invariants(&mut invariants, &name, &label, &pointer, parameter);
+                }
+
+                let body_schemas = operation
+                    .pointer("/requestBody/content")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(_, media)| media.get("schema"));
+                for schema in body_schemas {
+                    schema_invariants(
+                        &mut invariants,
+                        &format!("{}_body", name),
+                        &format!("{} body", label),
+                        &format!("{}/requestBody", pointer),
+                        schema,
+                    );
+                }
+
+                self.rate_limit_invariants(&mut invariants, &name, &label, operation);
+            }
+        }
+
+        invariants
+    }
+
+    // From x-rate-limit style extensions and "N requests per minute" in
+    // the description
+    fn rate_limit_invariants(&self, invariants: &mut Vec<ExtractedInvariant>, name: &str, label: &str, object: &Value) {
+        let Some(object) = object.as_object() else {
+            return;
+        };
+        let extension = ["x-rate-limit", "x-ratelimit", "x-rate-limit-limit", "x-ratelimit-limit"]
+            .iter()
+            .find_map(|key| object.get(*key));
+        let limit = match extension {
+            Some(Value::Object(fields)) => extension_rate_limit(fields),
+            Some(Value::String(text)) => self.text_rate_limit(text),
+            _ => None,
+        }
+        .or_else(|| object.get("description").and_then(Value::as_str).and_then(|text| self.text_rate_limit(text)));
+
+        if let Some((requests, period)) = limit {
+            let variable = format!("{}_requests_per_{}", name, period);
+            let description = format!("{} allows at most {} requests per {}", label, requests, period);
+            invariants.push(invariant(
+                description,
+                format!("{} <= {}", variable, requests),
+                variable,
+                format!("Requests to {} per {}", label, period),
+                "rate_limit",
+            ));
+        }
+    }
+
+    fn text_rate_limit(&self, text: &str) -> Option<(String, &'static str)> {
+        let caps = self.rate_limit_text.captures(text)?;
+        Some((caps[1].to_string(), period_name(&caps[2])?))
+    }
+}
+
+fn extension_rate_limit(fields: &Map<String, Value>) -> Option<(String, &'static str)> {
+    let requests = ["limit", "requests", "max"].iter().find_map(|key| fields.get(*key)).and_then(number)?;
+    let period = ["period", "window", "per", "interval"]
+        .iter()
+        .find_map(|key| fields.get(*key))
+        .and_then(Value::as_str)
+        .and_then(period_name)
+        .unwrap_or("minute");
+    Some((requests, period))
+}
+
+fn period_name(period: &str) -> Option<&'static str> {
+    match period.trim().trim_start_matches('1').to_lowercase().as_str() {
+        "s" | "sec" | "second" => Some("second"),
+        "m" | "min" | "minute" | "60s" => Some("minute"),
+        "h" | "hr" | "hour" | "60m" => Some("hour"),
+        "d" | "day" | "24h" => Some("day"),
+        _ => None,
+    }
+}
+
+fn parameter_invariants(invariants: &mut Vec<ExtractedInvariant>, operation: &str, label: &str, pointer: &str, parameter: &Value) {
+    let Some(name) = parameter.get("name").and_then(Value::as_str) else {
+        return;
+    };
+    let variable = format!("{}_{}", operation, snake_case(name));
+    let label = format!("{} parameter {}", label, name);
+    let pointer = format!("{}/parameters/{}", pointer, name);
+
+    if parameter.get("required").and_then(Value::as_bool) == Some(true) {
+        invariants.push(required_invariant(&variable, &label, &pointer));
+    }
+    // Swagger 2 puts the constraints on the parameter itself
+    let schema = parameter.get("schema").unwrap_or(parameter);
+    keyword_invariants(invariants, &variable, &label, &pointer, schema);
+}
+
+fn schema_invariants(invariants: &mut Vec<ExtractedInvariant>, variable: &str, label: &str, pointer: &str, schema: &Value) {
+    if schema.get("$ref").is_some() {
+        return;
+    }
+    keyword_invariants(invariants, variable, label, pointer, schema);
+
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    for (property, property_schema) in schema.get("properties").and_then(Value::as_object).into_iter().flatten() {
+        let property_variable = format!("{}_{}", variable, snake_case(property));
+        let property_label = format!("{}.{}", label, property);
+        let property_pointer = format!("{}/properties/{}", pointer, escape_pointer(property));
+        if required.contains(&property.as_str()) {
+            invariants.push(required_invariant(&property_variable, &property_label, &property_pointer));
+        }
+        schema_invariants(invariants, &property_variable, &property_label, &property_pointer, property_schema);
+    }
+
+    if let Some(items) = schema.get("items") {
+        schema_invariants(invariants, &format!("{}_item", variable), &format!("{}[]", label), &format!("{}/items", pointer), items);
+    }
+    for (index, member) in schema.get("allOf").and_then(Value::as_array).into_iter().flatten().enumerate() {
+        schema_invariants(invariants, variable, label, &format!("{}/allOf/{}", pointer, index), member);
+    }
+}
+
+// Bounds, lengths, item counts and patterns on one schema
+fn keyword_invariants(invariants: &mut Vec<ExtractedInvariant>, variable: &str, label: &str, pointer: &str, schema: &Value) {
+    let mut push = |expression: String, description: String| {
+        invariants.push(invariant(description, expression, variable.to_string(), format!("{} ({})", label, pointer), "data_integrity"));
+    };
+
+    // exclusiveMinimum is a flag in OpenAPI 3.0 and Swagger 2, and the
+    // bound itself in OpenAPI 3.1
+    let bounds = [
+        ("minimum", "exclusiveMinimum", ">=", ">", "at least", "greater than"),
+        ("maximum", "exclusiveMaximum", "<=", "<", "at most", "less than"),
+    ];
+    for (keyword, exclusive_keyword, inclusive, exclusive, inclusive_text, exclusive_text) in bounds {
+        let exclusive_flag = schema.get(exclusive_keyword).and_then(Value::as_bool) == Some(true);
+        if let Some(bound) = schema.get(keyword).and_then(number) {
+            let (operator, text) = if exclusive_flag { (exclusive, exclusive_text) } else { (inclusive, inclusive_text) };
+            push(format!("{} {} {}", variable, operator, bound), format!("{} is {} {}", label, text, bound));
+        }
+        if let Some(bound) = schema.get(exclusive_keyword).and_then(number) {
+            push(format!("{} {} {}", variable, exclusive, bound), format!("{} is {} {}", label, exclusive_text, bound));
+        }
+    }
+
+    let lengths = [
+        ("minLength", ">=", "at least", "characters"),
+        ("maxLength", "<=", "at most", "characters"),
+        ("minItems", ">=", "at least", "items"),
+        ("maxItems", "<=", "at most", "items"),
+    ];
+    for (keyword, operator, text, what) in lengths {
+        if let Some(length) = schema.get(keyword).and_then(number) {
+            push(
+                format!("len({}) {} {}", variable, operator, length),
+                format!("{} has {} {} {}", label, text, length, what),
+            );
+        }
+    }
+
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        push(
+            format!("matches({}, {})", variable, Value::String(pattern.to_string())),
+            format!("{} matches the pattern {}", label, pattern),
+        );
+    }
+}
+
+fn required_invariant(variable: &str, label: &str, pointer: &str) -> ExtractedInvariant {
+    invariant(
+        format!("{} is required", label),
+        format!("{} != null", variable),
+        variable.to_string(),
+        format!("{} ({})", label, pointer),
+        "data_integrity",
+    )
+}
+
+fn invariant(description: String, formal_expression: String, variable: String, variable_description: String, tag: &str) -> ExtractedInvariant {
+    ExtractedInvariant {
+        natural_language: description.clone(),
+        description,
+        formal_expression,
+        variables: vec![Variable {
+            name: variable,
+            type_: String::new(),
+            description: variable_description,
+            unit: String::new(),
+            constraints: Vec::new(),
+        }],
+        confidence_score: STRUCTURED_CONFIDENCE,
+        tags: vec![OPENAPI_TAG.to_string(), tag.to_string()],
+        priority: Priority::PriorityUnspecified as i32,
+        ..Default::default()
+    }
+}
+
+// Integers without a trailing `.0`
+fn number(value: &Value) -> Option<String> {
+    value.as_number().map(|number| number.to_string())
+}
+
+// `PaymentRequest`, `payment-request` and `POST /payments/{id}` to
+// `payment_request`, `payment_request` and `post_payments_id`
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            if c.is_uppercase() && previous_lower {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        } else {
+            if !snake.is_empty() && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            previous_lower = false;
+        }
+    }
+    snake.trim_end_matches('_').to_string()
+}
+
+fn escape_pointer(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r##"
+openapi: 3.0.3
+info:
+  title: Payments API
+  description: Clients may send 1000 requests per hour.
+paths:
+  /payments/{id}/refunds:
+    parameters:
+      - name: id
+        in: path
+        required: true
+        schema:
+          type: string
+          pattern: "^pay_[a-z0-9]+$"
+    post:
+      operationId: createRefund
+      x-rate-limit:
+        limit: 10
+        period: 1s
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/RefundRequest"
+components:
+  schemas:
+    RefundRequest:
+      type: object
+      required: [amount]
+      properties:
+        amount:
+          type: number
+          minimum: 0
+          exclusiveMinimum: true
+          maximum: 10000
+        reason:
+          type: string
+          maxLength: 500
+"##;
+
+    #[test]
+    fn test_parse_document_requires_openapi_field() {
+        assert!(parse_document(SPEC).is_some());
+        assert!(parse_document(r#"{"swagger": "2.0", "paths": {}}"#).is_some());
+        assert!(parse_document("title: Not an API\n").is_none());
+        assert!(parse_document("# Payments\n\nRefunds are capped.").is_none());
+    }
+
+    #[test]
+    fn test_extracts_schema_constraints_and_rate_limits() {
+        let invariants = OpenApiExtractor::new().extract(&parse_document(SPEC).unwrap());
+        let expressions: Vec<&str> = invariants.iter().map(|inv| inv.formal_expression.as_str()).collect();
+
+        assert_eq!(expressions, [
+            "refund_request_amount != null",
+            "refund_request_amount > 0",
+            "refund_request_amount <= 10000",
+            "len(refund_request_reason) <= 500",
+            "api_requests_per_hour <= 1000",
+            "create_refund_id != null",
+            "matches(create_refund_id, \"^pay_[a-z0-9]+$\")",
+            "create_refund_requests_per_second <= 10",
+        ]);
+        assert_eq!(invariants[1].description, "RefundRequest.amount is greater than 0");
+        assert_eq!(invariants[1].tags, ["openapi", "data_integrity"]);
+        assert_eq!(
+            invariants[1].variables[0].description,
+            "RefundRequest.amount (#/components/schemas/RefundRequest/properties/amount)"
+        );
+    }
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(snake_case("PaymentRequest"), "payment_request");
+        assert_eq!(snake_case("post /payments/{id}"), "post_payments_id");
+        assert_eq!(snake_case("max-amount"), "max_amount");
+    }
+}
@@ -0,0 +1,216 @@
+use spec_to_proof_error::Error;
+use sha2::{Digest, Sha256};
+use spec_to_proof_proto::pagination::PaginationError;
+use spec_to_proof_proto::{FromProto, InvariantModel};
+use storage::{Entity, ExpectedVersion, Repository, StorageError};
+
+use crate::proto::nlp::v1::{ExtractInvariantsRequest, ExtractedInvariant, StoredDocument, StoredInvariant};
+
+// spec_to_proof.v1.InvariantStatus.INVARIANT_STATUS_EXTRACTED
+pub const INVARIANT_STATUS_EXTRACTED: i32 = 1;
+
+impl Entity for StoredInvariant {
+    const KIND: &'static str = "INVARIANT";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        (!self.document_id.is_empty()).then(|| self.document_id.clone())
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+// Indexed by source system, so a source's documents can be listed for
+// backfills
+impl Entity for StoredDocument {
+    const KIND: &'static str = "DOCUMENT";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        (!self.source_system.is_empty()).then(|| self.source_system.clone())
+    }
+
+    fn status(&self) -> i32 {
+        0
+    }
+
+    fn set_status(&mut self, _status: i32) {}
+}
+
+// Re-extracting the same document yields the same IDs, so repeated runs
+// don't create duplicates
+pub fn invariant_id(document_id: &str, formal_expression: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(document_id.as_bytes());
+    hasher.update(b":");
+    hasher.update(formal_expression.as_bytes());
+    format!("inv_{}", &hex::encode(hasher.finalize())[..16])
+}
+
+pub fn to_stored(document_id: &str, invariant: &ExtractedInvariant) -> StoredInvariant {
+    StoredInvariant {
+        id: invariant_id(document_id, &invariant.formal_expression),
+        document_id: document_id.to_string(),
+        invariant: Some(invariant.clone()),
+        status: INVARIANT_STATUS_EXTRACTED,
+        extracted_at: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+    }
+}
+
+/// The shared model of a stored extraction, as listed for review. Status and
+/// priority share their enum values across the two protos, so they carry
+/// over as is.
+pub fn to_invariant_model(stored: StoredInvariant) -> Result<InvariantModel, PaginationError> {
+    let extracted = stored.invariant.unwrap_or_default();
+
+    let invariant = spec_to_proof_proto::Invariant {
+        id: stored.id,
+        content_sha256: hex::encode(Sha256::digest(extracted.formal_expression.as_bytes())),
+        description: extracted.description,
+        formal_expression: extracted.formal_expression,
+        natural_language: extracted.natural_language,
+        variables: extracted
+            .variables
+            .into_iter()
+            .map(|variable| spec_to_proof_proto::Variable {
+                name: variable.name,
+                var_type: variable.type_,
+                description: variable.description,
+                unit: variable.unit,
+                constraints: variable.constraints,
+            })
+            .collect(),
+        units: extracted.units,
+        confidence_score: extracted.confidence_score,
+        source_document_id: stored.document_id,
+        extracted_at: stored.extracted_at,
+        status: stored.status,
+        tags: extracted.tags,
+        priority: extracted.priority,
+        source_span: extracted.source_span.map(|span| spec_to_proof_proto::SourceSpan {
+            quote: span.quote,
+            start_offset: span.start_offset,
+            end_offset: span.end_offset,
+            verified: span.verified,
+        }),
+        classification: extracted.classification.map(|classification| spec_to_proof_proto::InvariantClassification {
+            category: classification.category,
+            secondary_categories: classification.secondary_categories,
+            proof_strategy: classification.proof_strategy,
+            confidence: classification.confidence,
+        }),
+    };
+    InvariantModel::from_proto(invariant).map_err(|e| PaginationError::Storage(StorageError::Decode(e.to_string())))
+}
+
+pub fn to_stored_document(request: &ExtractInvariantsRequest) -> StoredDocument {
+    StoredDocument {
+        id: request.document_id.clone(),
+        title: request.title.clone(),
+        content: request.content.clone(),
+        source_system: request.source_system.clone(),
+        language: request.language.clone(),
+        content_sha256: hex::encode(Sha256::digest(request.content.as_bytes())),
+        ingested_at: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+    }
+}
+
+/// Keeps the extracted version of a document, replacing earlier versions
+pub async fn persist_document(
+    repository: &dyn Repository<StoredDocument>,
+    request: &ExtractInvariantsRequest,
+) -> Result<(), Error> {
+    repository.put(&to_stored_document(request), ExpectedVersion::Any).await?;
+    Ok(())
+}
+
+/// Stores newly extracted invariants and returns how many were new.
+/// Invariants that already exist are left alone so review status set
+/// downstream is never reset by a re-extraction.
+pub async fn persist_invariants(
+    repository: &dyn Repository<StoredInvariant>,
+    document_id: &str,
+    invariants: &[ExtractedInvariant],
+) -> Result<usize, Error> {
+    let mut stored = 0;
+
+    for invariant in invariants {
+        let entity = to_stored(document_id, invariant);
+        match repository.put(&entity, ExpectedVersion::Absent).await {
+            Ok(_) => stored += 1,
+            Err(StorageError::VersionConflict { .. }) => {
+                tracing::debug!("Invariant {} already stored, skipping", entity.id);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spec_to_proof_proto::pagination::list_invariants;
+    use spec_to_proof_proto::ListInvariantsRequest;
+    use storage::{EntityQuery, InMemoryRepository};
+
+    fn extracted(expression: &str) -> ExtractedInvariant {
+        ExtractedInvariant {
+            formal_expression: expression.to_string(),
+            confidence_score: 0.9,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_persist_skips_existing_invariants() {
+        let repository = InMemoryRepository::<StoredInvariant>::new();
+        let invariants = vec![extracted("x > 0"), extracted("y <= 10")];
+
+        assert_eq!(persist_invariants(&repository, "doc-1", &invariants).await.unwrap(), 2);
+
+        // A reviewer confirms one invariant, then the document is re-extracted
+        let id = invariant_id("doc-1", "x > 0");
+        repository.update_status(&id, 2, 1).await.unwrap();
+        let rerun = vec![extracted("x > 0"), extracted("z == 1")];
+        assert_eq!(persist_invariants(&repository, "doc-1", &rerun).await.unwrap(), 1);
+
+        assert_eq!(repository.get(&id).await.unwrap().unwrap().entity.status, 2);
+        let page = repository
+            .query(&EntityQuery::BySource("doc-1".to_string()), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_list_invariants_for_review() {
+        let repository = InMemoryRepository::<StoredInvariant>::new();
+        persist_invariants(&repository, "doc-1", &[extracted("x > 0"), extracted("y <= 10")]).await.unwrap();
+        persist_invariants(&repository, "doc-2", &[extracted("z == 1")]).await.unwrap();
+        repository.update_status(&invariant_id("doc-1", "x > 0"), 2, 1).await.unwrap();
+
+        let request = ListInvariantsRequest {
+            filter: "source_document_id = doc-1 AND status = extracted".to_string(),
+            ..Default::default()
+        };
+        let response = list_invariants(&repository, &request, to_invariant_model).await.unwrap();
+        assert_eq!(response.invariants.len(), 1);
+        assert_eq!(response.invariants[0].formal_expression, "y <= 10");
+        assert_eq!(response.invariants[0].source_document_id, "doc-1");
+        assert_eq!(response.total_size, 1);
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::Arc;
+use spec_to_proof_error::Error;
+use prompt_registry::PromptTemplate;
+
+use crate::claude_client::LanguageModel;
+use crate::extractor::{ExtractionResult, InvariantExtractor};
+use crate::gherkin::{self, GherkinExtractor};
+use crate::language::{self, NumberNormalizer};
+use crate::openapi::{self, OpenApiExtractor};
+use crate::pii_redactor::PiiRedactor;
+use crate::post_processor::PostProcessor;
+use crate::proto::nlp::v1::{ExtractInvariantsRequest, ExtractedInvariant, ExtractionPlan, TokenUsage};
+use crate::proto_schema::{self, ProtoExtractor};
+use crate::source_spans;
+use crate::taxonomy::TaxonomyClassifier;
+use crate::InvariantExtractionConfig;
+
+/// The extraction stages between the cache and persistence: redaction,
+/// model extraction (Gherkin features and `.proto` files are parsed first,
+/// so only their free-text rules reach the model, and OpenAPI documents
+/// skip it), quote verification, number normalization, post-processing,
+/// classification and confidence filtering. Shared by `NlpService` and the
+/// evaluation harness so both measure the same behaviour.
+pub struct ExtractionPipeline {
+    extractor: InvariantExtractor,
+    gherkin: GherkinExtractor,
+    openapi: OpenApiExtractor,
+    proto: ProtoExtractor,
+    pii_redactor: PiiRedactor,
+    number_normalizer: NumberNormalizer,
+    post_processor: PostProcessor,
+    classifier: TaxonomyClassifier,
+    confidence_threshold: f64,
+    model: String,
+}
+
+/// Invariants read from a structured document, and the part of it the
+/// model still has to interpret
+#[derive(Debug, Clone, Default)]
+pub struct StructuredExtraction {
+    pub invariants: Vec<ExtractedInvariant>,
+    /// Empty when the whole document was structured
+    pub free_text: String,
+}
+
+pub struct PipelineOutput {
+    pub invariants: Vec<ExtractedInvariant>,
+    pub token_usage: Option<TokenUsage>,
+    pub pii_detected: bool,
+    pub redacted_fields: Vec<String>,
+}
+
+impl ExtractionPipeline {
+    pub fn new(config: &InvariantExtractionConfig) -> Self {
+        Self {
+            extractor: InvariantExtractor::new(config),
+            gherkin: GherkinExtractor::new(),
+            openapi: OpenApiExtractor::new(),
+            proto: ProtoExtractor::new(),
+            pii_redactor: PiiRedactor::new(),
+            number_normalizer: NumberNormalizer::new(),
+            post_processor: PostProcessor::new(),
+            classifier: TaxonomyClassifier::new(&config.taxonomy),
+            confidence_threshold: config.confidence_threshold,
+            model: config.claude_model.clone(),
+        }
+    }
+
+    pub fn with_language_model(mut self, language_model: Arc<dyn LanguageModel>) -> Self {
+        self.extractor = self.extractor.with_language_model(language_model);
+        self
+    }
+
+    /// What `run` would send to the model, without sending it
+    pub fn plan(
+        &self,
+        request: &ExtractInvariantsRequest,
+        prompt_template: &PromptTemplate,
+    ) -> Result<ExtractionPlan, Error> {
+        let (redacted_content, pii_detected, _) = self.pii_redactor.redact(&request.content);
+        let model_content = match self.structured(request, &redacted_content) {
+            Some(structured) => structured.free_text,
+            None => redacted_content,
+        };
+        let (chunk_count, usage) = if model_content.is_empty() {
+            (0, TokenUsage::default())
+        } else {
+            self.extractor.estimate_usage(request, &model_content, prompt_template)?
+        };
+
+        Ok(ExtractionPlan {
+            cache_hit: false,
+            chunk_count: chunk_count as i32,
+            estimated_token_usage: Some(usage),
+            model: self.model.clone(),
+            pii_detected,
+        })
+    }
+
+    pub async fn run(
+        &self,
+        request: &ExtractInvariantsRequest,
+        prompt_template: &PromptTemplate,
+    ) -> Result<PipelineOutput, Error> {
+        // Redact PII from content
+        let (redacted_content, pii_detected, redacted_fields) =
+            self.pii_redactor.redact(&request.content);
+
+        // Extract invariants using the model
+        let mut extraction_result = self.extract(request, &redacted_content, prompt_template).await?;
+
+        // Quotes come from the redacted content but are located in the
+        // original, so reviewers see the sentence as written
+        let verified_spans = source_spans::verify_source_spans(&mut extraction_result.invariants, &request.content);
+        tracing::debug!(
+            "Verified {} of {} source quotes for document {}",
+            verified_spans,
+            extraction_result.invariants.len(),
+            request.document_id
+        );
+
+        // Locale number formats such as 1.000,5 must be rewritten before
+        // post-processing parses the expressions
+        let language = language::resolve_language(request);
+        for invariant in &mut extraction_result.invariants {
+            invariant.language = language.code().to_string();
+            // Schema bounds are plain numbers already, and their patterns
+            // may hold quantifiers such as {1,3}
+            if invariant.tags.iter().any(|tag| tag == openapi::OPENAPI_TAG || tag == proto_schema::PROTOBUF_TAG) {
+                continue;
+            }
+            invariant.formal_expression = self.number_normalizer.normalize(&invariant.formal_expression, language);
+            for variable in &mut invariant.variables {
+                for constraint in &mut variable.constraints {
+                    *constraint = self.number_normalizer.normalize(constraint, language);
+                }
+            }
+        }
+
+        // Post-process invariants
+        let mut processed_invariants = self.post_processor
+            .process_invariants(extraction_result.invariants)
+            .await?;
+
+        // Classify into the taxonomy; this also fills in priorities the
+        // model left unspecified
+        self.classifier.classify_all(&mut processed_invariants);
+
+        // Filter by confidence threshold
+        let invariants = processed_invariants
+            .into_iter()
+            .filter(|inv| inv.confidence_score >= self.confidence_threshold)
+            .collect();
+
+        Ok(PipelineOutput {
+            invariants,
+            token_usage: extraction_result.token_usage,
+            pii_detected,
+            redacted_fields,
+        })
+    }
+
+    async fn extract(
+        &self,
+        request: &ExtractInvariantsRequest,
+        redacted_content: &str,
+        prompt_template: &PromptTemplate,
+    ) -> Result<ExtractionResult, Error> {
+        let Some(structured) = self.structured(request, redacted_content) else {
+            return self.extractor.extract_invariants(request, redacted_content, prompt_template).await;
+        };
+        tracing::info!(
+            "Read {} invariants from the structure of document {}",
+            structured.invariants.len(),
+            request.document_id
+        );
+        if structured.free_text.is_empty() {
+            return Ok(ExtractionResult {
+                invariants: structured.invariants,
+                token_usage: Some(TokenUsage::default()),
+            });
+        }
+
+        let mut result = self.extractor.extract_invariants(request, &structured.free_text, prompt_template).await?;
+        result.invariants.splice(0..0, structured.invariants);
+        Ok(result)
+    }
+
+    fn structured(&self, request: &ExtractInvariantsRequest, redacted_content: &str) -> Option<StructuredExtraction> {
+        if let Some(document) = openapi::parse_document(redacted_content) {
+            return Some(StructuredExtraction {
+                invariants: self.openapi.extract(&document),
+                free_text: String::new(),
+            });
+        }
+        if proto_schema::is_proto(request, redacted_content) {
+            if let Some(file) = proto_schema::parse_proto(redacted_content) {
+                return Some(self.proto.extract(&file));
+            }
+        }
+        if !gherkin::is_feature(request, redacted_content) {
+            return None;
+        }
+        let feature = gherkin::parse_feature(redacted_content)?;
+        Some(self.gherkin.extract(&feature))
+    }
+}
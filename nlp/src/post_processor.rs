@@ -1,11 +1,14 @@
 use std::collections::HashMap;
-use std::error::Error;
+use spec_to_proof_error::Error;
 use regex::Regex;
+use spec_to_proof_proto::constraint::Constraint;
 use crate::proto::nlp::v1::{ExtractedInvariant, Variable};
+use crate::units::UnitChecker;
 
 pub struct PostProcessor {
     variable_name_patterns: Vec<(Regex, String)>,
     unit_standardization: HashMap<String, String>,
+    unit_checker: UnitChecker,
 }
 
 impl PostProcessor {
@@ -80,13 +83,14 @@ impl PostProcessor {
         Self {
             variable_name_patterns,
             unit_standardization,
+            unit_checker: UnitChecker::new(),
         }
     }
 
     pub async fn process_invariants(
         &self,
         invariants: Vec<ExtractedInvariant>,
-    ) -> Result<Vec<ExtractedInvariant>, Box<dyn Error>> {
+    ) -> Result<Vec<ExtractedInvariant>, Error> {
         let mut processed_invariants = Vec::new();
 
         for mut invariant in invariants {
@@ -95,6 +99,37 @@ impl PostProcessor {
                 variable.name = self.normalize_variable_name(&variable.name);
             }
 
+            // Key units by the normalized variable names so they line up
+            // with the variables and the formal expression
+            invariant.units = invariant.units
+                .into_iter()
+                .map(|(var_name, unit)| (self.normalize_variable_name(&var_name), unit))
+                .collect();
+
+            // Keep only constraints in the constraint language, in canonical
+            // form; rejected ones are recorded so reviewers see what was lost
+            for variable in &mut invariant.variables {
+                let mut constraints = Vec::with_capacity(variable.constraints.len());
+                for constraint in &variable.constraints {
+                    match self.normalize_formal_expression(constraint).parse::<Constraint>() {
+                        Ok(parsed) => constraints.push(parsed.to_string()),
+                        Err(e) => invariant.validation_errors.push(format!("{}: {}", variable.name, e)),
+                    }
+                }
+                variable.constraints = constraints;
+            }
+
+            // Normalize formal expression
+            invariant.formal_expression = self.normalize_formal_expression(&invariant.formal_expression);
+
+            // Check dimensional consistency before standardization, which
+            // collapses distinctions such as % vs ratio
+            let report = self.unit_checker.check(&invariant);
+            if let Some(canonical_expression) = report.canonical_expression {
+                invariant.formal_expression = canonical_expression;
+            }
+            invariant.validation_errors.extend(report.errors);
+
             // Standardize units
             let mut normalized_units = HashMap::new();
             for (var_name, unit) in &invariant.units {
@@ -108,9 +143,6 @@ impl PostProcessor {
                 variable.unit = self.standardize_unit(&variable.unit);
             }
 
-            // Normalize formal expression
-            invariant.formal_expression = self.normalize_formal_expression(&invariant.formal_expression);
-
             processed_invariants.push(invariant);
         }
 
@@ -247,6 +279,10 @@ mod tests {
             tags: vec!["test".to_string()],
             priority: Priority::PriorityHigh as i32,
             extraction_metadata: None,
+            validation_errors: vec![],
+            source_span: None,
+            classification: None,
+            language: String::new(),
         };
 
         let processed = processor.process_invariants(vec![invariant]).await.unwrap();
@@ -256,5 +292,66 @@ mod tests {
         assert_eq!(processed_inv.variables[0].unit, "items");
         assert_eq!(processed_inv.units["user_id"], "items");
         assert_eq!(processed_inv.formal_expression, "user_id > 0");
+        assert!(processed_inv.validation_errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_constraints_are_canonicalized_or_rejected() {
+        let processor = PostProcessor::new();
+
+        let invariant = ExtractedInvariant {
+            formal_expression: "user_id > 0".to_string(),
+            variables: vec![Variable {
+                name: "User ID".to_string(),
+                type_: "integer".to_string(),
+                constraints: vec!["Non-Negative".to_string(), "User ID<=10".to_string(), "must be small".to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let processed = processor.process_invariants(vec![invariant]).await.unwrap();
+
+        assert_eq!(processed[0].variables[0].constraints, ["non_negative", "user_id <= 10"]);
+        assert_eq!(
+            processed[0].validation_errors,
+            ["user_id: invalid constraint \"must be small\": unknown constraint"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unit_consistency_checking() {
+        let processor = PostProcessor::new();
+
+        let invariant = |expression: &str, unit: &str| ExtractedInvariant {
+            description: "Latency bound".to_string(),
+            formal_expression: expression.to_string(),
+            natural_language: "Response time must stay under two seconds".to_string(),
+            variables: vec![],
+            units: {
+                let mut map = HashMap::new();
+                map.insert("Response Time".to_string(), unit.to_string());
+                map
+            },
+            confidence_score: 0.9,
+            tags: vec![],
+            priority: Priority::PriorityMedium as i32,
+            extraction_metadata: None,
+            validation_errors: vec![],
+            source_span: None,
+            classification: None,
+            language: String::new(),
+        };
+
+        let processed = processor.process_invariants(vec![
+            invariant("Response Time ≤ 2s", "ms"),
+            invariant("Response Time ≤ 2MB", "ms"),
+        ]).await.unwrap();
+
+        assert_eq!(processed[0].formal_expression, "response_time <= 2000");
+        assert!(processed[0].validation_errors.is_empty());
+
+        assert_eq!(processed[1].formal_expression, "response_time <= 2MB");
+        assert_eq!(processed[1].validation_errors.len(), 1);
     }
 } 
\ No newline at end of file
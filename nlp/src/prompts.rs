@@ -1,7 +1,67 @@
 use std::collections::HashMap;
-use std::error::Error;
 use std::fs;
 use std::path::Path;
+use prompt_registry::{PromptError, PromptRegistry, SelectedPrompt};
+
+use crate::language::Language;
+
+pub const INVARIANT_EXTRACTION_PROMPT: &str = "invariant_extraction";
+pub const INVARIANT_EXTRACTION_VERSION: &str = "1.0.0";
+
+/// Registry holding the bundled extraction prompts, one per language with
+/// notes under `prompts/languages/`; a manifest configured via
+/// `prompt_manifest` can add versions or route traffic to a candidate
+pub fn builtin_registry() -> PromptRegistry {
+    let template = PromptTemplate::load("invariant_extraction.md");
+    let mut registry = PromptRegistry::new().with_builtin(
+        INVARIANT_EXTRACTION_PROMPT,
+        INVARIANT_EXTRACTION_VERSION,
+        &template.template,
+        &["content"],
+    );
+
+    for language in Language::ALL.into_iter().filter(|language| *language != Language::English) {
+        let notes_path = format!("prompts/languages/{}.md", language.code());
+        if let Ok(notes) = fs::read_to_string(&notes_path) {
+            registry = registry.with_builtin(
+                &extraction_prompt_name(language),
+                INVARIANT_EXTRACTION_VERSION,
+                &with_language_notes(&template.template, &notes),
+                &["content"],
+            );
+        }
+    }
+    registry
+}
+
+pub fn extraction_prompt_name(language: Language) -> String {
+    match language {
+        Language::English => INVARIANT_EXTRACTION_PROMPT.to_string(),
+        _ => format!("{}_{}", INVARIANT_EXTRACTION_PROMPT, language.code()),
+    }
+}
+
+/// The language's extraction prompt, or the English one for languages
+/// without their own
+pub fn select_extraction_prompt(
+    registry: &PromptRegistry,
+    language: Language,
+    routing_key: &str,
+) -> Result<SelectedPrompt, PromptError> {
+    match registry.select(&extraction_prompt_name(language), routing_key) {
+        Err(PromptError::UnknownPrompt(_)) => registry.select(INVARIANT_EXTRACTION_PROMPT, routing_key),
+        selected => selected,
+    }
+}
+
+// The notes go right before the content so they are read last
+fn with_language_notes(template: &str, notes: &str) -> String {
+    const CONTENT_HEADING: &str = "## Content to Analyze";
+    match template.find(CONTENT_HEADING) {
+        Some(index) => format!("{}{}\n\n{}", &template[..index], notes.trim_end(), &template[index..]),
+        None => format!("{}\n\n{}", template, notes.trim_end()),
+    }
+}
 
 pub struct PromptTemplate {
     template: String,
@@ -43,6 +103,7 @@ impl PromptTemplate {
 4. **Identify units** for all variables where applicable
 5. **Assign confidence scores** based on clarity and completeness
 6. **Categorize by priority** (LOW, MEDIUM, HIGH, CRITICAL)
+7. **Quote the source sentence** each invariant was derived from, exactly as it appears in the content
 
 ## Output Format
 
@@ -69,12 +130,23 @@ Return a JSON array of invariants with the following structure:
       },
       "confidence_score": 0.95,
       "tags": ["safety", "data_integrity"],
-      "priority": "HIGH"
+      "priority": "HIGH",
+      "source_quote": "Sentence from the content the invariant was derived from, copied verbatim"
     }
   ]
 }
 ```
 
+## Variable Constraints
+
+Each entry in `constraints` must use one of these forms; anything else is discarded:
+
+- `positive`, `negative`, `non_negative`, `non_positive`, `non_zero`
+- `range(min, max)` for inclusive numeric bounds
+- `len >= n` (or `<=`, `==`, ...) for the length of a string or collection
+- `regex("pattern")` for values that must match a pattern
+- A comparison of two variables or a variable and a number, e.g. `retry_count <= max_retries`
+
 ## Content to Analyze
 
 ```
@@ -118,6 +190,25 @@ mod tests {
         assert_eq!(result, "Hello Alice, you are {{age}} years old.");
     }
 
+    #[test]
+    fn test_builtin_registry() {
+        let template = builtin_registry().active(INVARIANT_EXTRACTION_PROMPT).unwrap();
+        assert_eq!(template.version, INVARIANT_EXTRACTION_VERSION);
+        assert_eq!(template.sha256.len(), 64);
+    }
+
+    #[test]
+    fn test_language_prompts_fall_back_to_english() {
+        let registry = builtin_registry();
+        let german = select_extraction_prompt(&registry, Language::German, "DOC-1").unwrap();
+        assert_eq!(german.template.name, "invariant_extraction_de");
+        assert!(german.template.body.contains("written in German"));
+
+        let registry = PromptRegistry::new().with_builtin(INVARIANT_EXTRACTION_PROMPT, "1.0.0", "{{content}}", &["content"]);
+        let french = select_extraction_prompt(&registry, Language::French, "DOC-1").unwrap();
+        assert_eq!(french.template.name, INVARIANT_EXTRACTION_PROMPT);
+    }
+
     #[test]
     fn test_default_template_loading() {
         let template = PromptTemplate::load("nonexistent_template.md");
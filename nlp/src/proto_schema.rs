@@ -0,0 +1,793 @@
+use std::collections::{HashMap, HashSet};
+use regex::Regex;
+
+use crate::openapi::snake_case;
+use crate::pipeline::StructuredExtraction;
+use crate::proto::nlp::v1::{ExtractInvariantsRequest, ExtractedInvariant, Priority, SourceSpan, Variable};
+
+/// Confidence of invariants read from validation options such as
+/// `(validate.rules)` and `(buf.validate.field)`
+pub const ANNOTATION_CONFIDENCE: f64 = 0.95;
+
+/// Confidence of invariants read from a field comment ("at most 64
+/// characters"), which is prose and may be out of date
+pub const COMMENT_CONFIDENCE: f64 = 0.85;
+
+pub const PROTOBUF_TAG: &str = "protobuf";
+
+// Option prefixes of protoc-gen-validate and protovalidate field rules
+const RULE_PREFIXES: [&str; 3] = ["validate.rules.", "buf.validate.field.", "buf.validate.rules."];
+
+const FORMAT_RULES: [&str; 9] = ["email", "hostname", "ip", "ipv4", "ipv6", "uri", "uri_ref", "uuid", "address"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtoFile {
+    pub package: String,
+    /// Nested messages follow their parent
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// Fully qualified, e.g. `payments.v1.Refund.Item`
+    pub full_name: String,
+    pub comment: String,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    /// `int32`, `string`, `Money`, `map<string, int64>`
+    pub type_name: String,
+    pub number: String,
+    pub repeated: bool,
+    /// Leading and trailing comments
+    pub comment: String,
+    pub options: Vec<(String, OptionValue)>,
+    /// The declaration as written, for the source quote
+    pub declaration: String,
+}
+
+/// A field option value; options holding messages are flattened into
+/// dotted names such as `validate.rules.int32.gte`
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionValue {
+    Text(String),
+    Word(String),
+    List(Vec<OptionValue>),
+}
+
+impl OptionValue {
+    fn is_true(&self) -> bool {
+        matches!(self, OptionValue::Word(word) if word == "true")
+    }
+
+    // Strings quoted, everything else as written
+    fn literal(&self) -> String {
+        match self {
+            OptionValue::Text(text) => serde_json::Value::String(text.clone()).to_string(),
+            OptionValue::Word(word) => word.clone(),
+            OptionValue::List(values) => {
+                format!("[{}]", values.iter().map(OptionValue::literal).collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+}
+
+/// Whether the request holds a `.proto` file
+pub fn is_proto(request: &ExtractInvariantsRequest, content: &str) -> bool {
+    request.title.to_lowercase().ends_with(".proto")
+        || request.source_system.eq_ignore_ascii_case("protobuf")
+        || content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("//") && !line.starts_with("/*") && !line.starts_with('*'))
+            .is_some_and(|line| line.starts_with("syntax") || line.starts_with("edition"))
+}
+
+/// Parses the messages of a `.proto` file. Enums, services and extensions
+/// are skipped. Returns `None` if the file declares no messages.
+pub fn parse_proto(content: &str) -> Option<ProtoFile> {
+    let (tokens, comments) = lex(content);
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        comments: &comments,
+        lines: content.lines().collect(),
+    };
+    let mut file = ProtoFile { package: String::new(), messages: Vec::new() };
+
+    while let Some(lexeme) = parser.next() {
+        match &lexeme.token {
+            Token::Word(word) if word == "package" => {
+                if let Some(Token::Word(package)) = parser.peek() {
+                    file.package = package.clone();
+                }
+                parser.skip_statement();
+            }
+            Token::Word(word) if word == "message" => {
+                let line = lexeme.line;
+                let prefix = file.package.clone();
+                parser.message(&prefix, line, &mut file.messages);
+            }
+            Token::Word(word) if matches!(word.as_str(), "enum" | "service" | "extend") => parser.skip_declaration(),
+            Token::Symbol(';') => {}
+            _ => parser.skip_statement(),
+        }
+    }
+
+    (!file.messages.is_empty()).then_some(file)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Identifiers, numbers and dotted names
+    Word(String),
+    Text(String),
+    Symbol(char),
+}
+
+struct Lexeme {
+    token: Token,
+    line: usize,
+}
+
+struct Comment {
+    text: String,
+    /// Follows code on the same line
+    trailing: bool,
+}
+
+// Tokens with their line numbers, and comments keyed by the line they end on
+fn lex(content: &str) -> (Vec<Lexeme>, HashMap<usize, Comment>) {
+    let chars: Vec<char> = content.chars().collect();
+    let mut tokens = Vec::new();
+    let mut comments: HashMap<usize, Comment> = HashMap::new();
+    let mut line = 1;
+    let mut last_token_line = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            let start = i;
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            comments.insert(line, Comment {
+                text: text.trim_start_matches('/').trim().to_string(),
+                trailing: last_token_line == line,
+            });
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let start_line = line;
+            let start = i + 2;
+            i = start;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            let text: String = chars[start..i.min(chars.len())].iter().collect();
+            let text = text
+                .lines()
+                .map(|l| l.trim().trim_start_matches('*').trim())
+                .filter(|l| !l.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            comments.insert(line, Comment { text, trailing: last_token_line == start_line });
+            i += 2;
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    i += 1;
+                    text.push(match chars[i] {
+                        'n' => '\n',
+                        't' => '\t',
+                        escaped => escaped,
+                    });
+                } else {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    text.push(chars[i]);
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Lexeme { token: Token::Text(text), line });
+            last_token_line = line;
+        } else if c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '+') {
+            let start = i;
+            i += 1;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric()
+                    || matches!(chars[i], '_' | '.')
+                    // exponents such as 1e-5
+                    || (matches!(chars[i], '-' | '+') && matches!(chars[i - 1], 'e' | 'E') && chars[start].is_ascii_digit()))
+            {
+                i += 1;
+            }
+            tokens.push(Lexeme { token: Token::Word(chars[start..i].iter().collect()), line });
+            last_token_line = line;
+        } else {
+            tokens.push(Lexeme { token: Token::Symbol(c), line });
+            last_token_line = line;
+            i += 1;
+        }
+    }
+
+    (tokens, comments)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Lexeme],
+    position: usize,
+    comments: &'a HashMap<usize, Comment>,
+    lines: Vec<&'a str>,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<&'a Lexeme> {
+        let lexeme = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(lexeme)
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position).map(|lexeme| &lexeme.token)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn word(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                self.position += 1;
+                Some(word.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // Up to and including the next `;` or balanced block
+    fn skip_statement(&mut self) {
+        while let Some(lexeme) = self.next() {
+            match lexeme.token {
+                Token::Symbol(';') => return,
+                Token::Symbol('{') => {
+                    self.position -= 1;
+                    self.skip_block();
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // A named declaration with a body, such as `enum Status { ... }`
+    fn skip_declaration(&mut self) {
+        while self.peek().is_some_and(|token| *token != Token::Symbol('{')) {
+            self.position += 1;
+        }
+        self.skip_block();
+    }
+
+    fn skip_block(&mut self) {
+        let mut depth = 0;
+        while let Some(lexeme) = self.next() {
+            match lexeme.token {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Consecutive non-trailing comments directly above `line`, and the
+    // trailing comment on `end_line`
+    fn comment(&self, line: usize, end_line: usize) -> String {
+        let mut leading = Vec::new();
+        let mut above = line.saturating_sub(1);
+        while let Some(comment) = self.comments.get(&above).filter(|comment| !comment.trailing) {
+            leading.push(comment.text.as_str());
+            above -= 1;
+        }
+        leading.reverse();
+        if let Some(comment) = self.comments.get(&end_line).filter(|comment| comment.trailing) {
+            leading.push(&comment.text);
+        }
+        leading.join(" ")
+    }
+
+    // After the `message` keyword on `line`
+    fn message(&mut self, prefix: &str, line: usize, messages: &mut Vec<Message>) {
+        let Some(name) = self.word() else {
+            self.skip_statement();
+            return;
+        };
+        let full_name = if prefix.is_empty() { name } else { format!("{}.{}", prefix, name) };
+        if !self.eat('{') {
+            self.skip_statement();
+            return;
+        }
+
+        let index = messages.len();
+        messages.push(Message {
+            full_name: full_name.clone(),
+            comment: self.comment(line, line),
+            fields: Vec::new(),
+        });
+        let fields = self.body(&full_name, messages);
+        messages[index].fields = fields;
+    }
+
+    // Fields up to the closing brace, recursing into nested messages and
+    // oneofs
+    fn body(&mut self, full_name: &str, messages: &mut Vec<Message>) -> Vec<Field> {
+        let mut fields = Vec::new();
+        while let Some(lexeme) = self.tokens.get(self.position) {
+            match &lexeme.token {
+                Token::Symbol('}') => {
+                    self.position += 1;
+                    break;
+                }
+                Token::Symbol(';') => self.position += 1,
+                Token::Word(word) if word == "message" => {
+                    self.position += 1;
+                    self.message(full_name, lexeme.line, messages);
+                }
+                Token::Word(word) if matches!(word.as_str(), "enum" | "extend") => self.skip_declaration(),
+                Token::Word(word) if word == "oneof" => {
+                    self.position += 1;
+                    self.word();
+                    if self.eat('{') {
+                        fields.extend(self.body(full_name, messages));
+                    }
+                }
+                Token::Word(word) if matches!(word.as_str(), "option" | "reserved" | "extensions") => self.skip_statement(),
+                Token::Word(_) => match self.field() {
+                    Some(field) => fields.push(field),
+                    None => self.skip_statement(),
+                },
+                _ => self.skip_statement(),
+            }
+        }
+        fields
+    }
+
+    // `[repeated|optional|required] Type name = N [options];` or
+    // `map<K, V> name = N;`
+    fn field(&mut self) -> Option<Field> {
+        let start = self.position;
+        let line = self.tokens[start].line;
+        let mut repeated = false;
+        let mut type_name = self.word()?;
+        if matches!(type_name.as_str(), "repeated" | "optional" | "required") {
+            repeated = type_name == "repeated";
+            type_name = self.word()?;
+        }
+        if type_name == "map" && self.eat('<') {
+            let key = self.word()?;
+            self.eat(',');
+            let value = self.word()?;
+            self.eat('>').then_some(())?;
+            type_name = format!("map<{}, {}>", key, value);
+            repeated = true;
+        }
+        let name = self.word()?;
+        self.eat('=').then_some(())?;
+        let number = self.word()?;
+
+        let mut options = Vec::new();
+        if self.eat('[') {
+            self.options(&mut options)?;
+        }
+        let end_line = self.tokens.get(self.position).map_or(line, |lexeme| lexeme.line);
+        self.eat(';').then_some(())?;
+
+        Some(Field {
+            name,
+            type_name,
+            number,
+            repeated,
+            comment: self.comment(line, end_line),
+            options,
+            declaration: self.lines.get(line - 1).map(|l| l.trim().to_string()).unwrap_or_default(),
+        })
+    }
+
+    // Comma separated `name = value` up to `]`; names may be extensions
+    // such as `(validate.rules).string`
+    fn options(&mut self, options: &mut Vec<(String, OptionValue)>) -> Option<()> {
+        loop {
+            let mut name = String::new();
+            loop {
+                if self.eat('(') {
+                    name.push_str(&self.word()?);
+                    self.eat(')').then_some(())?;
+                } else if let Some(word) = self.word() {
+                    name.push_str(&word);
+                } else {
+                    break;
+                }
+            }
+            self.eat('=').then_some(())?;
+            self.value(&name, options)?;
+            if self.eat(']') {
+                return Some(());
+            }
+            self.eat(',').then_some(())?;
+        }
+    }
+
+    fn value(&mut self, path: &str, options: &mut Vec<(String, OptionValue)>) -> Option<()> {
+        if self.eat('{') {
+            // Text format: `key: value`, `key { ... }`, optionally separated
+            while !self.eat('}') {
+                let key = self.word()?;
+                self.eat(':');
+                self.value(&format!("{}.{}", path, key.trim_start_matches('.')), options)?;
+                if !self.eat(',') {
+                    self.eat(';');
+                }
+            }
+            return Some(());
+        }
+        let value = self.scalar_or_list()?;
+        options.push((path.to_string(), value));
+        Some(())
+    }
+
+    fn scalar_or_list(&mut self) -> Option<OptionValue> {
+        if self.eat('[') {
+            let mut values = Vec::new();
+            while !self.eat(']') {
+                values.push(self.scalar_or_list()?);
+                self.eat(',');
+            }
+            return Some(OptionValue::List(values));
+        }
+        match self.next().map(|lexeme| &lexeme.token)? {
+            Token::Word(word) => Some(OptionValue::Word(word.clone())),
+            Token::Text(text) => Some(OptionValue::Text(text.clone())),
+            Token::Symbol(_) => None,
+        }
+    }
+}
+
+/// Turns validation options and constraint comments on message fields into
+/// invariants without calling the model. Comments stating a rule the
+/// patterns do not cover ("must match the invoice currency") are returned
+/// as free text for the model.
+pub struct ProtoExtractor {
+    required: Regex,
+    bound: Regex,
+    between: Regex,
+    normative: Regex,
+}
+
+impl Default for ProtoExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProtoExtractor {
+    pub fn new() -> Self {
+        Self {
+            required: Regex::new(r"(?i)(?:^|[.;:]\s*)required\b|\b(?:is|are) required\b|\bmust (?:always )?be (?:set|present|provided)\b|\bmust not be empty\b").unwrap(),
+            bound: Regex::new(
+                r"(?i)\b(?P<comparison>at least|no less than|not less than|minimum(?: of)?|min|at most|no more than|not more than|up to|maximum(?: of)?|max|greater than|more than|less than|fewer than)\s*:?\s*(?P<value>-?\d+(?:\.\d+)?)\s*(?P<unit>characters?|chars?|bytes?|items?|elements?|entries|values)?\b",
+            ).unwrap(),
+            between: Regex::new(
+                r"(?i)\bbetween\s+(?P<low>-?\d+(?:\.\d+)?)\s+and\s+(?P<high>-?\d+(?:\.\d+)?)\s*(?P<unit>characters?|chars?|bytes?|items?|elements?|entries|values)?\b",
+            ).unwrap(),
+            normative: Regex::new(r"(?i)\b(?:must|shall|should|never|always|only|cannot|can't)\b").unwrap(),
+        }
+    }
+
+    pub fn extract(&self, file: &ProtoFile) -> StructuredExtraction {
+        let mut extraction = StructuredExtraction::default();
+        let mut free_text = Vec::new();
+
+        for message in &file.messages {
+            let short_name = message.full_name.strip_prefix(&format!("{}.", file.package)).unwrap_or(&message.full_name);
+            let message_variable = snake_case(short_name);
+            if self.normative.is_match(&message.comment) {
+                free_text.push(format!("{}: {}", message.full_name, message.comment));
+            }
+
+            for field in &message.fields {
+                let mut rules = Rules {
+                    invariants: Vec::new(),
+                    expressions: HashSet::new(),
+                    message,
+                    field,
+                    variable: format!("{}_{}", message_variable, snake_case(&field.name)),
+                    label: format!("{}.{}", message.full_name, field.name),
+                };
+                for (name, value) in &field.options {
+                    rules.option(name, value);
+                }
+                if !field.comment.is_empty() && !self.comment_rules(&mut rules) && self.normative.is_match(&field.comment) {
+                    free_text.push(format!("{} ({}): {}", rules.label, field.type_name, field.comment));
+                }
+                extraction.invariants.extend(rules.invariants);
+            }
+        }
+
+        if !free_text.is_empty() {
+            extraction.free_text = format!("Protobuf contract {}\n\n{}\n", file.package, free_text.join("\n"));
+        }
+        extraction
+    }
+
+    // Returns whether the comment stated a rule the patterns understood
+    fn comment_rules(&self, rules: &mut Rules) -> bool {
+        let comment = rules.field.comment.clone();
+        let before = rules.invariants.len();
+
+        if self.required.is_match(&comment) {
+            rules.required(COMMENT_CONFIDENCE);
+        }
+        for caps in self.between.captures_iter(&comment) {
+            let length = rules.is_length(caps.name("unit").map(|unit| unit.as_str()));
+            rules.bound(">=", &caps["low"], length, COMMENT_CONFIDENCE);
+            rules.bound("<=", &caps["high"], length, COMMENT_CONFIDENCE);
+        }
+        for caps in self.bound.captures_iter(&comment) {
+            let operator = match caps["comparison"].to_lowercase().as_str() {
+                "at least" | "no less than" | "not less than" | "minimum" | "minimum of" | "min" => ">=",
+                "at most" | "no more than" | "not more than" | "up to" | "maximum" | "maximum of" | "max" => "<=",
+                "greater than" | "more than" => ">",
+                _ => "<",
+            };
+            let length = rules.is_length(caps.name("unit").map(|unit| unit.as_str()));
+            rules.bound(operator, &caps["value"], length, COMMENT_CONFIDENCE);
+        }
+
+        rules.invariants.len() > before
+    }
+}
+
+// The invariants of one field, without duplicates when an option and a
+// comment state the same rule
+struct Rules<'a> {
+    invariants: Vec<ExtractedInvariant>,
+    expressions: HashSet<String>,
+    message: &'a Message,
+    field: &'a Field,
+    variable: String,
+    label: String,
+}
+
+impl Rules<'_> {
+    fn option(&mut self, name: &str, value: &OptionValue) {
+        if name == "google.api.field_behavior" {
+            if matches!(value, OptionValue::Word(word) if word == "REQUIRED") {
+                self.required(ANNOTATION_CONFIDENCE);
+            }
+            return;
+        }
+        let Some(mut rule) = RULE_PREFIXES.iter().find_map(|prefix| name.strip_prefix(prefix)) else {
+            return;
+        };
+        let variable = self.variable.clone();
+        if let Some(item_rule) = rule.strip_prefix("repeated.items.") {
+            self.variable = format!("{}_item", variable);
+            rule = item_rule;
+        }
+
+        let length = rule.starts_with("repeated.") || rule.starts_with("map.") || rule.starts_with("string.") || rule.starts_with("bytes.");
+        match rule.rsplit('.').next().unwrap_or(rule) {
+            "required" if value.is_true() => self.required(ANNOTATION_CONFIDENCE),
+            "gt" => self.bound(">", &value.literal(), false, ANNOTATION_CONFIDENCE),
+            "gte" => self.bound(">=", &value.literal(), false, ANNOTATION_CONFIDENCE),
+            "lt" => self.bound("<", &value.literal(), false, ANNOTATION_CONFIDENCE),
+            "lte" => self.bound("<=", &value.literal(), false, ANNOTATION_CONFIDENCE),
+            "const" => self.bound("==", &value.literal(), false, ANNOTATION_CONFIDENCE),
+            "min_len" | "min_bytes" | "min_items" | "min_pairs" => self.bound(">=", &value.literal(), length, ANNOTATION_CONFIDENCE),
+            "max_len" | "max_bytes" | "max_items" | "max_pairs" => self.bound("<=", &value.literal(), length, ANNOTATION_CONFIDENCE),
+            "len" | "len_bytes" => self.bound("==", &value.literal(), true, ANNOTATION_CONFIDENCE),
+            "pattern" => self.push(
+                format!("matches({}, {})", self.variable, value.literal()),
+                format!("{} matches the pattern {}", self.label, value.literal()),
+                ANNOTATION_CONFIDENCE,
+            ),
+            "in" | "not_in" => {
+                let values = match value {
+                    OptionValue::List(_) => value.literal(),
+                    _ => format!("[{}]", value.literal()),
+                };
+                let (operator, text) = if rule.ends_with("not_in") { ("not_in", "none of") } else { ("in", "one of") };
+                self.push(
+                    format!("{} {} {}", self.variable, operator, values),
+                    format!("{} is {} {}", self.label, text, values),
+                    ANNOTATION_CONFIDENCE,
+                );
+            }
+            format if FORMAT_RULES.contains(&format) && value.is_true() => self.push(
+                format!("is_{}({})", format, self.variable),
+                format!("{} is a valid {}", self.label, format.replace('_', " ")),
+                ANNOTATION_CONFIDENCE,
+            ),
+            _ => {}
+        }
+        self.variable = variable;
+    }
+
+    fn is_length(&self, unit: Option<&str>) -> bool {
+        unit.is_some_and(|unit| unit.to_lowercase() != "values")
+            || self.field.repeated
+            || matches!(self.field.type_name.as_str(), "string" | "bytes")
+    }
+
+    fn required(&mut self, confidence: f64) {
+        self.push(
+            format!("{} != null", self.variable),
+            format!("{} is required", self.label),
+            confidence,
+        );
+    }
+
+    fn bound(&mut self, operator: &str, value: &str, length: bool, confidence: f64) {
+        let text = match operator {
+            ">=" => "at least",
+            "<=" => "at most",
+            ">" => "greater than",
+            "<" => "less than",
+            _ => "exactly",
+        };
+        let (expression, description) = if length {
+            (format!("len({}) {} {}", self.variable, operator, value), format!("{} has {} {} elements", self.label, text, value))
+        } else {
+            (format!("{} {} {}", self.variable, operator, value), format!("{} is {} {}", self.label, text, value))
+        };
+        self.push(expression, description, confidence);
+    }
+
+    fn push(&mut self, formal_expression: String, description: String, confidence: f64) {
+        if !self.expressions.insert(formal_expression.clone()) {
+            return;
+        }
+        let type_name = if self.field.repeated && !self.field.type_name.starts_with("map<") {
+            format!("repeated {}", self.field.type_name)
+        } else {
+            self.field.type_name.clone()
+        };
+        self.invariants.push(ExtractedInvariant {
+            natural_language: description.clone(),
+            description,
+            formal_expression,
+            variables: vec![Variable {
+                name: self.variable.clone(),
+                type_: type_name,
+                description: format!("{} (field {})", self.label, self.field.number),
+                unit: String::new(),
+                constraints: Vec::new(),
+            }],
+            confidence_score: confidence,
+            tags: vec![PROTOBUF_TAG.to_string(), "data_integrity".to_string(), self.message.full_name.clone()],
+            priority: Priority::PriorityUnspecified as i32,
+            source_span: Some(SourceSpan {
+                quote: self.field.declaration.clone(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFUNDS: &str = r#"
+syntax = "proto3";
+
+package payments.v1;
+
+import "validate/validate.proto";
+
+// A refund of part of a payment. The refunded amount must never exceed
+// the captured amount.
+message RefundRequest {
+  string payment_id = 1 [(validate.rules).string = {min_len: 1, pattern: "^pay_[a-z0-9]+$"}];
+  int64 amount_cents = 2 [(buf.validate.field).int64.gt = 0];
+  // Free-form reason, at most 500 characters
+  string reason = 3;
+  repeated LineItem items = 4 [(validate.rules).repeated.min_items = 1];
+
+  message LineItem {
+    string sku = 1; // required
+    int32 quantity = 2; // between 1 and 99
+  }
+
+  enum Kind {
+    KIND_UNSPECIFIED = 0;
+  }
+
+  oneof target {
+    string card_id = 5 [(google.api.field_behavior) = REQUIRED];
+    // Must belong to the same customer as the payment
+    string wallet_id = 6;
+  }
+}
+"#;
+
+    #[test]
+    fn test_parse_proto() {
+        let file = parse_proto(REFUNDS).unwrap();
+        assert_eq!(file.package, "payments.v1");
+        let names: Vec<&str> = file.messages.iter().map(|message| message.full_name.as_str()).collect();
+        assert_eq!(names, ["payments.v1.RefundRequest", "payments.v1.RefundRequest.LineItem"]);
+        assert!(file.messages[0].comment.starts_with("A refund of part of a payment."));
+
+        let fields: Vec<&str> = file.messages[0].fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(fields, ["payment_id", "amount_cents", "reason", "items", "card_id", "wallet_id"]);
+        assert_eq!(file.messages[0].fields[0].options, [
+            ("validate.rules.string.min_len".to_string(), OptionValue::Word("1".to_string())),
+            ("validate.rules.string.pattern".to_string(), OptionValue::Text("^pay_[a-z0-9]+$".to_string())),
+        ]);
+        assert_eq!(file.messages[1].fields[0].comment, "required");
+
+        assert!(parse_proto("# Payments\n\nRefunds are capped.").is_none());
+    }
+
+    #[test]
+    fn test_extracts_rules_from_options_and_comments() {
+        let extraction = ProtoExtractor::new().extract(&parse_proto(REFUNDS).unwrap());
+        let expressions: Vec<&str> = extraction.invariants.iter().map(|inv| inv.formal_expression.as_str()).collect();
+
+        assert_eq!(expressions, [
+            "len(refund_request_payment_id) >= 1",
+            "matches(refund_request_payment_id, \"^pay_[a-z0-9]+$\")",
+            "refund_request_amount_cents > 0",
+            "len(refund_request_reason) <= 500",
+            "len(refund_request_items) >= 1",
+            "refund_request_card_id != null",
+            "refund_request_line_item_sku != null",
+            "refund_request_line_item_quantity >= 1",
+            "refund_request_line_item_quantity <= 99",
+        ]);
+
+        let amount = &extraction.invariants[2];
+        assert_eq!(amount.confidence_score, ANNOTATION_CONFIDENCE);
+        assert_eq!(amount.tags, ["protobuf", "data_integrity", "payments.v1.RefundRequest"]);
+        assert_eq!(amount.variables[0].description, "payments.v1.RefundRequest.amount_cents (field 2)");
+        assert_eq!(
+            amount.source_span.as_ref().unwrap().quote,
+            "int64 amount_cents = 2 [(buf.validate.field).int64.gt = 0];"
+        );
+        assert_eq!(extraction.invariants[3].confidence_score, COMMENT_CONFIDENCE);
+
+        assert_eq!(
+            extraction.free_text,
+            "Protobuf contract payments.v1\n\n\
+             payments.v1.RefundRequest: A refund of part of a payment. The refunded amount must never exceed the captured amount.\n\
+             payments.v1.RefundRequest.wallet_id (string): Must belong to the same customer as the payment\n"
+        );
+    }
+}
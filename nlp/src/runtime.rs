@@ -0,0 +1,76 @@
+use cost_governance::LlmCallLimits;
+use serde::{Deserialize, Serialize};
+
+use crate::InvariantExtractionConfig;
+
+/// Extraction settings operators can change without restarting the
+/// service, reloaded from the file in `NLP_RUNTIME_CONFIG`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeSettings {
+    pub confidence_threshold: f64,
+    pub claude_model: String,
+    /// Kill switch and rate limits for model calls; ignored unless cost
+    /// governance is configured
+    #[serde(default)]
+    pub llm_limits: Option<LlmCallLimits>,
+}
+
+impl RuntimeSettings {
+    pub fn from_config(config: &InvariantExtractionConfig) -> Self {
+        Self {
+            confidence_threshold: config.confidence_threshold,
+            claude_model: config.claude_model.clone(),
+            llm_limits: None,
+        }
+    }
+
+    /// `config` with these settings in place of its own
+    pub fn apply(&self, config: &InvariantExtractionConfig) -> InvariantExtractionConfig {
+        InvariantExtractionConfig {
+            confidence_threshold: self.confidence_threshold,
+            claude_model: self.claude_model.clone(),
+            ..config.clone()
+        }
+    }
+}
+
+impl reload::RuntimeSettings for RuntimeSettings {
+    fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.confidence_threshold) {
+            return Err("confidence_threshold must be between 0 and 1".to_string());
+        }
+        if self.claude_model.trim().is_empty() {
+            return Err("claude_model must not be empty".to_string());
+        }
+        match &self.llm_limits {
+            Some(limits) => limits.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reload::RuntimeSettings as _;
+
+    #[test]
+    fn test_apply_and_validate() {
+        let config = InvariantExtractionConfig::default();
+        let mut settings = RuntimeSettings::from_config(&config);
+        assert!(settings.validate().is_ok());
+
+        settings.confidence_threshold = 0.8;
+        settings.claude_model = "claude-3-haiku-20240307".to_string();
+        let applied = settings.apply(&config);
+        assert_eq!(applied.confidence_threshold, 0.8);
+        assert_eq!(applied.claude_model, "claude-3-haiku-20240307");
+        assert_eq!(applied.cache_ttl_seconds, config.cache_ttl_seconds);
+
+        settings.confidence_threshold = 1.5;
+        assert!(settings.validate().is_err());
+        settings.confidence_threshold = 0.8;
+        settings.claude_model = " ".to_string();
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use spec_to_proof_error::Error;
+use tokio::sync::watch;
+
+// Outcome shared with waiting callers, error kind included so they retry
+// the same way the leader would
+type Outcome<T> = Option<Result<T, Error>>;
+
+/// Coalesces concurrent calls for the same key: the first caller runs the
+/// work and everyone who arrives while it is in flight receives its result.
+pub struct SingleFlight<T> {
+    in_flight: Mutex<HashMap<String, watch::Receiver<Outcome<T>>>>,
+}
+
+/// A result, and whether it came from another caller's in-flight work
+pub struct Flight<T> {
+    pub value: T,
+    pub shared: bool,
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn run<F, Fut>(&self, key: &str, work: F) -> Result<Flight<T>, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        loop {
+            let role = {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                match in_flight.get(key) {
+                    Some(receiver) => Role::Follower(receiver.clone()),
+                    None => {
+                        let (sender, receiver) = watch::channel(None);
+                        in_flight.insert(key.to_string(), receiver);
+                        Role::Leader(sender)
+                    }
+                }
+            };
+
+            match role {
+                Role::Leader(sender) => {
+                    // Removes the key even if this future is dropped mid-flight
+                    let _guard = InFlightGuard { flights: self, key };
+                    let result = work().await;
+                    let _ = sender.send(Some(result.clone()));
+                    return result.map(|value| Flight { value, shared: false });
+                }
+                Role::Follower(mut receiver) => {
+                    let outcome = match receiver.wait_for(Option::is_some).await {
+                        Ok(outcome) => outcome.clone(),
+                        // The leader was cancelled before finishing; try to
+                        // take over the work
+                        Err(_) => continue,
+                    };
+                    return match outcome {
+                        Some(Ok(value)) => Ok(Flight { value, shared: true }),
+                        Some(Err(e)) => Err(e),
+                        None => continue,
+                    };
+                }
+            }
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+}
+
+enum Role<T> {
+    Leader(watch::Sender<Outcome<T>>),
+    Follower(watch::Receiver<Outcome<T>>),
+}
+
+struct InFlightGuard<'a, T> {
+    flights: &'a SingleFlight<T>,
+    key: &'a str,
+}
+
+impl<T> Drop for InFlightGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.flights.in_flight.lock() {
+            in_flight.remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_callers_share_one_execution() {
+        let flights = Arc::new(SingleFlight::<u32>::new());
+        let executions = Arc::new(AtomicUsize::new(0));
+
+        let calls = (0..5).map(|_| {
+            let flights = flights.clone();
+            let executions = executions.clone();
+            async move {
+                flights
+                    .run("doc-1", || async {
+                        executions.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok(7)
+                    })
+                    .await
+                    .unwrap()
+            }
+        });
+        let results = futures::future::join_all(calls).await;
+
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|flight| flight.value == 7));
+        assert_eq!(results.iter().filter(|flight| flight.shared).count(), 4);
+        assert_eq!(flights.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_shared_and_not_remembered() {
+        let flights = SingleFlight::<u32>::new();
+        let failed = flights.run("doc-1", || async { Err("model unavailable".into()) }).await;
+        assert_eq!(failed.err().unwrap().to_string(), "model unavailable");
+
+        let retried = flights.run("doc-1", || async { Ok(1) }).await.unwrap();
+        assert!(!retried.shared);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::proto::nlp::v1::ExtractedInvariant;
+
+pub const QUOTE_NOT_FOUND_ERROR: &str = "source quote not found in document";
+
+/// Locates each invariant's quoted sentence in the original (pre-redaction)
+/// content and records its character offsets. Matching ignores case and
+/// whitespace differences, since the model may reflow lines. Quotes that
+/// cannot be found, including ones that contain redaction placeholders, are
+/// kept but marked unverified. Returns the number of verified spans.
+pub fn verify_source_spans(invariants: &mut [ExtractedInvariant], content: &str) -> usize {
+    let (normalized, origins) = normalize(content);
+    let mut verified = 0;
+
+    for invariant in invariants.iter_mut() {
+        let Some(span) = invariant.source_span.as_mut() else {
+            continue;
+        };
+
+        let (quote, _) = normalize(span.quote.trim().trim_matches(|c| matches!(c, '"' | '“' | '”')));
+        let position = if quote.is_empty() || quote.len() > normalized.len() {
+            None
+        } else {
+            normalized.windows(quote.len()).position(|window| window == quote.as_slice())
+        };
+
+        match position {
+            Some(start) => {
+                span.start_offset = origins[start] as i64;
+                span.end_offset = origins[start + quote.len() - 1] as i64 + 1;
+                span.verified = true;
+                verified += 1;
+            }
+            None => {
+                span.start_offset = 0;
+                span.end_offset = 0;
+                span.verified = false;
+                if !invariant.validation_errors.iter().any(|e| e == QUOTE_NOT_FOUND_ERROR) {
+                    invariant.validation_errors.push(QUOTE_NOT_FOUND_ERROR.to_string());
+                }
+            }
+        }
+    }
+
+    verified
+}
+
+// Lowercases and collapses whitespace runs, keeping the original character
+// index of every normalized character
+fn normalize(text: &str) -> (Vec<char>, Vec<usize>) {
+    let mut chars = Vec::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+    let mut previous_space = true;
+
+    for (index, c) in text.chars().enumerate() {
+        if c.is_whitespace() {
+            if !previous_space {
+                chars.push(' ');
+                origins.push(index);
+            }
+            previous_space = true;
+            continue;
+        }
+        for lower in c.to_lowercase() {
+            chars.push(lower);
+            origins.push(index);
+        }
+        previous_space = false;
+    }
+
+    if chars.last() == Some(&' ') {
+        chars.pop();
+        origins.pop();
+    }
+    (chars, origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::nlp::v1::SourceSpan;
+
+    fn invariant(quote: &str) -> ExtractedInvariant {
+        ExtractedInvariant {
+            source_span: Some(SourceSpan {
+                quote: quote.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_quotes_are_located_in_original_content() {
+        let content = "# Limits\n\nRefunds never exceed\nthe charge. Contact ops@example.com on failure.";
+        let mut invariants = vec![
+            invariant("refunds never exceed the charge."),
+            invariant("Contact [REDACTED_EMAIL] on failure."),
+            ExtractedInvariant::default(),
+        ];
+
+        assert_eq!(verify_source_spans(&mut invariants, content), 1);
+
+        let span = invariants[0].source_span.as_ref().unwrap();
+        assert!(span.verified);
+        let quoted: String = content
+            .chars()
+            .skip(span.start_offset as usize)
+            .take((span.end_offset - span.start_offset) as usize)
+            .collect();
+        assert_eq!(quoted, "Refunds never exceed\nthe charge.");
+
+        assert!(!invariants[1].source_span.as_ref().unwrap().verified);
+        assert_eq!(invariants[1].validation_errors, vec![QUOTE_NOT_FOUND_ERROR.to_string()]);
+        assert!(invariants[2].source_span.is_none());
+    }
+}
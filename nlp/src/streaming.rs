@@ -0,0 +1,246 @@
+use serde_json::Value;
+
+// Support for the Messages API with `"stream": true`: decoding the
+// server-sent events and picking complete invariants out of the partial
+// output as it arrives.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    TextDelta(String),
+    /// Token counts; `message_start` carries the input count and
+    /// `message_delta` the running output count
+    Usage {
+        input_tokens: Option<u32>,
+        output_tokens: Option<u32>,
+    },
+    Stop,
+    Error(String),
+}
+
+/// Splits a byte stream into server-sent events. Chunks may end anywhere,
+/// including inside a UTF-8 sequence, so bytes are buffered until an event
+/// is complete.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<StreamEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = find_event_end(&self.buffer) {
+            let raw: Vec<u8> = self.buffer.drain(..end).collect();
+            let raw = String::from_utf8_lossy(&raw);
+            let data: Vec<&str> = raw
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(str::trim_start)
+                .collect();
+            if let Some(event) = parse_event(&data.join("\n")) {
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+// Index just past the blank line that terminates the first event
+fn find_event_end(buffer: &[u8]) -> Option<usize> {
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| i + 2);
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4);
+    match (lf, crlf) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn parse_event(data: &str) -> Option<StreamEvent> {
+    let event: Value = serde_json::from_str(data).ok()?;
+    let count = |value: &Value| value.as_u64().map(|n| n as u32);
+
+    match event["type"].as_str()? {
+        "content_block_delta" if event["delta"]["type"] == "text_delta" => {
+            Some(StreamEvent::TextDelta(event["delta"]["text"].as_str()?.to_string()))
+        }
+        "message_start" => Some(StreamEvent::Usage {
+            input_tokens: count(&event["message"]["usage"]["input_tokens"]),
+            output_tokens: count(&event["message"]["usage"]["output_tokens"]),
+        }),
+        "message_delta" => Some(StreamEvent::Usage {
+            input_tokens: None,
+            output_tokens: count(&event["usage"]["output_tokens"]),
+        }),
+        "message_stop" => Some(StreamEvent::Stop),
+        "error" => Some(StreamEvent::Error(
+            event["error"]["message"].as_str().unwrap_or("Unknown streaming error").to_string(),
+        )),
+        // ping, content_block_start/stop
+        _ => None,
+    }
+}
+
+/// Incrementally scans model output for invariant objects. Accepts the
+/// `{"invariants": [...]}` response the prompt asks for, a bare array, or
+/// one JSON object per line, with or without surrounding prose or fences.
+#[derive(Debug, Default)]
+pub struct InvariantScanner {
+    text: String,
+    position: usize,
+    stack: Vec<char>,
+    in_string: bool,
+    escaped: bool,
+    // Offsets of the innermost candidate object and of the top-level value
+    object_start: Option<usize>,
+    root_start: Option<usize>,
+    invariants: Vec<Value>,
+    complete: bool,
+}
+
+impl InvariantScanner {
+    pub fn push(&mut self, delta: &str) {
+        self.text.push_str(delta);
+
+        while let Some(c) = self.text[self.position..].chars().next() {
+            let offset = self.position;
+            self.position += c.len_utf8();
+
+            if self.in_string {
+                match c {
+                    _ if self.escaped => self.escaped = false,
+                    '\\' => self.escaped = true,
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match c {
+                '"' if !self.stack.is_empty() => self.in_string = true,
+                '{' | '[' => {
+                    // Elements of the invariants array, or of a bare array
+                    let element = self.stack == ['{', '['] || self.stack == ['['];
+                    if self.stack.is_empty() {
+                        self.root_start = Some(offset);
+                    } else if c == '{' && element {
+                        self.object_start = Some(offset);
+                    }
+                    self.stack.push(c);
+                }
+                '}' | ']' if !self.stack.is_empty() => {
+                    self.stack.pop();
+                    self.close(c, offset);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn close(&mut self, c: char, offset: usize) {
+        let element = self.stack == ['{', '['] || self.stack == ['['];
+        let top_level = self.stack.is_empty();
+
+        if c == ']' {
+            self.complete |= top_level;
+            return;
+        }
+
+        let start = match (top_level, element) {
+            (true, _) => self.root_start.take(),
+            (false, true) => self.object_start.take(),
+            (false, false) => return,
+        };
+        let parsed = start.and_then(|start| serde_json::from_str::<Value>(&self.text[start..=offset]).ok());
+        match parsed {
+            // A top-level object with an invariants array is the wrapper,
+            // whose elements were already collected
+            Some(value) if top_level && value.get("invariants").is_some() => self.complete = true,
+            Some(value) => self.invariants.push(value),
+            None => {}
+        }
+    }
+
+    /// Invariants whose objects have been fully received
+    pub fn invariants(&self) -> &[Value] {
+        &self.invariants
+    }
+
+    /// Whether the whole response payload has arrived, so the rest of the
+    /// stream can be abandoned. One-object-per-line output is never
+    /// complete before the stream ends.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The invariants received so far, in the shape the extractor parses
+    pub fn into_response(self) -> String {
+        serde_json::json!({ "invariants": self.invariants }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_decoder_handles_split_events() {
+        let stream = concat!(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n",
+            "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"x ≤ 1\"}}\n\n",
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        );
+
+        let mut decoder = SseDecoder::default();
+        let mut events = Vec::new();
+        // Split inside the multi-byte '≤'
+        let split = stream.find('≤').unwrap() + 1;
+        events.extend(decoder.push(&stream.as_bytes()[..split]));
+        events.extend(decoder.push(&stream.as_bytes()[split..]));
+
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Usage { input_tokens: Some(12), output_tokens: Some(1) },
+                StreamEvent::TextDelta("x ≤ 1".to_string()),
+                StreamEvent::Stop,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scanner_collects_invariants_as_they_arrive() {
+        let response = r#"```json
+{"invariants": [{"formal_expression": "a <= b", "tags": ["}"]}, {"formal_expression": "c > 0"}]}
+```"#;
+        let mut scanner = InvariantScanner::default();
+        let first_end = response.find("]},").unwrap() + 2;
+
+        scanner.push(&response[..first_end - 1]);
+        assert!(scanner.invariants().is_empty());
+        scanner.push(&response[first_end - 1..first_end]);
+        assert_eq!(scanner.invariants().len(), 1);
+        assert!(!scanner.is_complete());
+
+        scanner.push(&response[first_end..]);
+        assert_eq!(scanner.invariants().len(), 2);
+        assert!(scanner.is_complete());
+        assert_eq!(scanner.invariants()[1]["formal_expression"], "c > 0");
+    }
+
+    #[test]
+    fn test_scanner_accepts_json_lines() {
+        let mut scanner = InvariantScanner::default();
+        scanner.push("{\"formal_expression\": \"a <= b\"}\n{\"formal_expression\": \"c");
+        assert_eq!(scanner.invariants().len(), 1);
+        assert!(!scanner.is_complete());
+
+        let response: Value = serde_json::from_str(&scanner.into_response()).unwrap();
+        assert_eq!(response["invariants"].as_array().unwrap().len(), 1);
+    }
+}
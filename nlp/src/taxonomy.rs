@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::proto::nlp::v1::{ExtractedInvariant, InvariantClassification, Priority};
+
+// Maps invariants onto a controlled set of categories. Each category
+// carries a proof-strategy hint ("smt" or "lean") and an optional default
+// priority, which downstream routing keys off. The built-in taxonomy can be
+// extended or overridden from config.
+
+pub const PROOF_STRATEGY_SMT: &str = "smt";
+pub const PROOF_STRATEGY_LEAN: &str = "lean";
+
+// Tags are explicit categorization by the model, so they weigh the most
+const TAG_WEIGHT: u32 = 3;
+const UNIT_WEIGHT: u32 = 2;
+const TEXT_WEIGHT: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxonomyCategory {
+    pub name: String,
+    /// Words or phrases matched against tags, variable names and text; a
+    /// keyword also matches words it is a prefix of
+    pub keywords: Vec<String>,
+    /// Standardized units that indicate the category
+    #[serde(default)]
+    pub units: Vec<String>,
+    pub proof_strategy: String,
+    /// Applied when extraction did not assign a priority
+    #[serde(default)]
+    pub default_priority: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxonomyConfig {
+    /// Keep the built-in categories; configured categories with the same
+    /// name replace them
+    #[serde(default = "default_include_builtin")]
+    pub include_builtin: bool,
+    #[serde(default)]
+    pub categories: Vec<TaxonomyCategory>,
+}
+
+fn default_include_builtin() -> bool {
+    true
+}
+
+impl Default for TaxonomyConfig {
+    fn default() -> Self {
+        Self {
+            include_builtin: true,
+            categories: Vec::new(),
+        }
+    }
+}
+
+fn category(name: &str, keywords: &[&str], units: &[&str], proof_strategy: &str, default_priority: &str) -> TaxonomyCategory {
+    TaxonomyCategory {
+        name: name.to_string(),
+        keywords: keywords.iter().map(|k| k.to_string()).collect(),
+        units: units.iter().map(|u| u.to_string()).collect(),
+        proof_strategy: proof_strategy.to_string(),
+        default_priority: Some(default_priority.to_string()),
+    }
+}
+
+pub fn builtin_categories() -> Vec<TaxonomyCategory> {
+    vec![
+        category(
+            "performance",
+            &["performance", "latency", "response time", "throughput", "duration", "p99", "p95", "qps", "slow"],
+            &["milliseconds", "seconds"],
+            PROOF_STRATEGY_SMT,
+            "MEDIUM",
+        ),
+        category(
+            "security",
+            &["security", "auth", "permission", "password", "encrypt", "token", "secret", "role", "privilege", "pii"],
+            &[],
+            PROOF_STRATEGY_LEAN,
+            "HIGH",
+        ),
+        category(
+            "resource",
+            &["resource", "memory", "cpu", "disk", "quota", "capacity", "connection", "pool", "storage"],
+            &["bytes", "kilobytes", "megabytes", "gigabytes"],
+            PROOF_STRATEGY_SMT,
+            "MEDIUM",
+        ),
+        category(
+            "temporal",
+            &["temporal", "before", "after", "eventually", "always", "until", "order", "sequence", "expire", "deadline"],
+            &[],
+            PROOF_STRATEGY_LEAN,
+            "MEDIUM",
+        ),
+        category(
+            "data_integrity",
+            &["data integrity", "integrity", "consistent", "unique", "duplicate", "checksum", "balance", "total", "refund"],
+            &[],
+            PROOF_STRATEGY_LEAN,
+            "HIGH",
+        ),
+        category(
+            "reliability",
+            &["reliability", "retry", "error rate", "availability", "uptime", "failover", "fault", "recover"],
+            &["ratio"],
+            PROOF_STRATEGY_SMT,
+            "HIGH",
+        ),
+    ]
+}
+
+pub struct TaxonomyClassifier {
+    categories: Vec<(TaxonomyCategory, Vec<Vec<String>>)>,
+}
+
+impl TaxonomyClassifier {
+    pub fn new(config: &TaxonomyConfig) -> Self {
+        let mut categories = if config.include_builtin { builtin_categories() } else { Vec::new() };
+        for configured in &config.categories {
+            match categories.iter_mut().find(|c| c.name == configured.name) {
+                Some(existing) => *existing = configured.clone(),
+                None => categories.push(configured.clone()),
+            }
+        }
+
+        Self {
+            categories: categories
+                .into_iter()
+                .map(|category| {
+                    let keywords = category.keywords.iter().map(|k| words(k)).filter(|k| !k.is_empty()).collect();
+                    (category, keywords)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn category_names(&self) -> Vec<&str> {
+        self.categories.iter().map(|(category, _)| category.name.as_str()).collect()
+    }
+
+    /// Classifies the invariant, or returns None when no category matches
+    pub fn classify(&self, invariant: &ExtractedInvariant) -> Option<InvariantClassification> {
+        let tag_words: Vec<Vec<String>> = invariant.tags.iter().map(|tag| words(tag)).collect();
+        let variable_text: Vec<&str> = invariant.variables.iter().map(|v| v.name.as_str()).collect();
+        let text_words = words(&format!(
+            "{} {} {}",
+            invariant.description,
+            invariant.natural_language,
+            variable_text.join(" ")
+        ));
+        let units: Vec<&str> = invariant.units.values()
+            .chain(invariant.variables.iter().map(|v| &v.unit))
+            .map(|unit| unit.as_str())
+            .collect();
+
+        let mut scores: BTreeMap<u32, Vec<&TaxonomyCategory>> = BTreeMap::new();
+        let mut total = 0;
+        for (category, keywords) in &self.categories {
+            let mut score = 0;
+            for keyword in keywords {
+                if tag_words.iter().any(|tag| contains_phrase(tag, keyword)) {
+                    score += TAG_WEIGHT;
+                }
+                if contains_phrase(&text_words, keyword) {
+                    score += TEXT_WEIGHT;
+                }
+            }
+            if category.units.iter().any(|unit| units.contains(&unit.as_str())) {
+                score += UNIT_WEIGHT;
+            }
+            if score > 0 {
+                total += score;
+                scores.entry(score).or_default().push(category);
+            }
+        }
+
+        // Highest score wins; ties go to the category listed first
+        let mut ranked = scores.into_iter().rev().flat_map(|(score, categories)| {
+            categories.into_iter().map(move |category| (score, category))
+        });
+        let (score, primary) = ranked.next()?;
+
+        Some(InvariantClassification {
+            category: primary.name.clone(),
+            secondary_categories: ranked.map(|(_, category)| category.name.clone()).collect(),
+            proof_strategy: primary.proof_strategy.clone(),
+            confidence: score as f64 / total as f64,
+        })
+    }
+
+    /// Classifies each invariant and fills in the category's default
+    /// priority where extraction left it unspecified
+    pub fn classify_all(&self, invariants: &mut [ExtractedInvariant]) {
+        for invariant in invariants.iter_mut() {
+            invariant.classification = self.classify(invariant);
+
+            let Some(classification) = &invariant.classification else {
+                continue;
+            };
+            if invariant.priority != Priority::PriorityUnspecified as i32 {
+                continue;
+            }
+            let default_priority = self.categories
+                .iter()
+                .find(|(category, _)| category.name == classification.category)
+                .and_then(|(category, _)| category.default_priority.as_deref());
+            if let Some(priority) = default_priority.and_then(parse_priority) {
+                invariant.priority = priority as i32;
+            }
+        }
+    }
+}
+
+fn parse_priority(priority: &str) -> Option<Priority> {
+    match priority.to_uppercase().as_str() {
+        "CRITICAL" => Some(Priority::PriorityCritical),
+        "HIGH" => Some(Priority::PriorityHigh),
+        "MEDIUM" => Some(Priority::PriorityMedium),
+        "LOW" => Some(Priority::PriorityLow),
+        _ => None,
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+// Whether `phrase` occurs as consecutive words, each a prefix of the word
+// it is matched against ("encrypt" matches "encrypted")
+fn contains_phrase(words: &[String], phrase: &[String]) -> bool {
+    words.windows(phrase.len()).any(|window| {
+        window.iter().zip(phrase).all(|(word, keyword)| word.starts_with(keyword.as_str()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::nlp::v1::Variable;
+
+    fn invariant(description: &str, tags: &[&str], unit: &str) -> ExtractedInvariant {
+        ExtractedInvariant {
+            description: description.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            variables: vec![Variable {
+                name: "value".to_string(),
+                unit: unit.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_classify_builtin_categories() {
+        let classifier = TaxonomyClassifier::new(&TaxonomyConfig::default());
+
+        let latency = classifier.classify(&invariant("Response time stays under 100ms", &[], "milliseconds")).unwrap();
+        assert_eq!(latency.category, "performance");
+        assert_eq!(latency.proof_strategy, PROOF_STRATEGY_SMT);
+        assert_eq!(latency.confidence, 1.0);
+
+        let passwords = classifier.classify(&invariant("Passwords are stored encrypted", &["security"], "")).unwrap();
+        assert_eq!(passwords.category, "security");
+        assert_eq!(passwords.proof_strategy, PROOF_STRATEGY_LEAN);
+
+        assert!(classifier.classify(&invariant("Widgets are blue", &[], "")).is_none());
+    }
+
+    #[test]
+    fn test_configured_categories_extend_taxonomy() {
+        let config = TaxonomyConfig {
+            include_builtin: true,
+            categories: vec![TaxonomyCategory {
+                name: "compliance".to_string(),
+                keywords: vec!["gdpr".to_string(), "retention".to_string()],
+                units: vec![],
+                proof_strategy: PROOF_STRATEGY_LEAN.to_string(),
+                default_priority: Some("CRITICAL".to_string()),
+            }],
+        };
+        let classifier = TaxonomyClassifier::new(&config);
+        assert_eq!(classifier.category_names().len(), 7);
+
+        let mut invariants = vec![invariant("Data retention follows GDPR", &["compliance"], "")];
+        classifier.classify_all(&mut invariants);
+
+        let classification = invariants[0].classification.as_ref().unwrap();
+        assert_eq!(classification.category, "compliance");
+        assert_eq!(invariants[0].priority, Priority::PriorityCritical as i32);
+    }
+}
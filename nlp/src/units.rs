@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+use std::fmt;
+use regex::Regex;
+use crate::proto::nlp::v1::ExtractedInvariant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dimension {
+    Time,
+    DataSize,
+    Count,
+    Ratio,
+}
+
+impl Dimension {
+    pub fn canonical_unit(&self) -> Unit {
+        match self {
+            Dimension::Time => Unit { name: "milliseconds", dimension: Dimension::Time, factor: 1.0 },
+            Dimension::DataSize => Unit { name: "bytes", dimension: Dimension::DataSize, factor: 1.0 },
+            Dimension::Count => Unit { name: "items", dimension: Dimension::Count, factor: 1.0 },
+            Dimension::Ratio => Unit { name: "ratio", dimension: Dimension::Ratio, factor: 1.0 },
+        }
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Dimension::Time => "time",
+            Dimension::DataSize => "data size",
+            Dimension::Count => "count",
+            Dimension::Ratio => "ratio",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A unit of measure; `factor` converts a value in this unit to the
+// dimension's canonical unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+    pub name: &'static str,
+    pub dimension: Dimension,
+    pub factor: f64,
+}
+
+const KIB: f64 = 1024.0;
+
+// Size prefixes are binary (1 KB = 1024 bytes), matching how memory limits
+// are usually written in specs
+const UNIT_TABLE: &[(&[&str], &str, Dimension, f64)] = &[
+    (&["ns", "nanosecond", "nanoseconds"], "nanoseconds", Dimension::Time, 1e-6),
+    (&["us", "µs", "microsecond", "microseconds"], "microseconds", Dimension::Time, 1e-3),
+    (&["ms", "msec", "millisecond", "milliseconds"], "milliseconds", Dimension::Time, 1.0),
+    (&["s", "sec", "secs", "second", "seconds"], "seconds", Dimension::Time, 1_000.0),
+    (&["min", "mins", "minute", "minutes"], "minutes", Dimension::Time, 60_000.0),
+    (&["h", "hr", "hrs", "hour", "hours"], "hours", Dimension::Time, 3_600_000.0),
+    (&["d", "day", "days"], "days", Dimension::Time, 86_400_000.0),
+    (&["b", "byte", "bytes"], "bytes", Dimension::DataSize, 1.0),
+    (&["kb", "kib", "kilobyte", "kilobytes"], "kilobytes", Dimension::DataSize, KIB),
+    (&["mb", "mib", "megabyte", "megabytes"], "megabytes", Dimension::DataSize, KIB * KIB),
+    (&["gb", "gib", "gigabyte", "gigabytes"], "gigabytes", Dimension::DataSize, KIB * KIB * KIB),
+    (&["tb", "tib", "terabyte", "terabytes"], "terabytes", Dimension::DataSize, KIB * KIB * KIB * KIB),
+    (&["count", "item", "items", "request", "requests", "connection", "connections"], "items", Dimension::Count, 1.0),
+    (&["ratio", "fraction"], "ratio", Dimension::Ratio, 1.0),
+    (&["%", "percent", "percentage"], "percent", Dimension::Ratio, 0.01),
+];
+
+pub fn parse_unit(raw: &str) -> Option<Unit> {
+    let normalized = raw.trim().to_lowercase();
+    if normalized.is_empty() {
+        return None;
+    }
+
+    UNIT_TABLE
+        .iter()
+        .find(|(aliases, _, _, _)| aliases.contains(&normalized.as_str()))
+        .map(|(_, name, dimension, factor)| Unit {
+            name,
+            dimension: *dimension,
+            factor: *factor,
+        })
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UnitCheckReport {
+    pub errors: Vec<String>,
+    // Expression with unit-suffixed literals and mixed-unit variables
+    // rewritten into a single unit per comparison; None if nothing changed
+    // or the expression is inconsistent
+    pub canonical_expression: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Term<'a> {
+    Variable(&'a str, Option<Unit>),
+    Quantity(Unit),
+    Bare,
+    Opaque,
+}
+
+pub struct UnitChecker {
+    clause_separator: Regex,
+    comparison: Regex,
+    quantity_term: Regex,
+    quantity_literal: Regex,
+    identifier: Regex,
+}
+
+impl UnitChecker {
+    pub fn new() -> Self {
+        Self {
+            clause_separator: Regex::new(r"&&|\|\||==>|<->|->|[,;]").unwrap(),
+            comparison: Regex::new(r"<=|>=|==|!=|<|>|=").unwrap(),
+            quantity_term: Regex::new(r"^(\d+(?:\.\d+)?)\s*([A-Za-zµ%]+)$").unwrap(),
+            quantity_literal: Regex::new(r"(^|[^A-Za-z0-9_.])(\d+(?:\.\d+)?)\s*([A-Za-zµ%]+)").unwrap(),
+            identifier: Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap(),
+        }
+    }
+
+    pub fn check(&self, invariant: &ExtractedInvariant) -> UnitCheckReport {
+        let mut errors = Vec::new();
+        let mut declared: HashMap<String, String> = HashMap::new();
+
+        for variable in &invariant.variables {
+            if !variable.unit.trim().is_empty() {
+                declared.insert(variable.name.clone(), variable.unit.clone());
+            }
+        }
+
+        // The units map wins over per-variable units, but a dimension clash
+        // between the two is itself an inconsistency
+        for (name, unit) in &invariant.units {
+            if let Some(existing) = declared.get(name) {
+                if let (Some(a), Some(b)) = (parse_unit(unit), parse_unit(existing)) {
+                    if a.dimension != b.dimension {
+                        errors.push(format!(
+                            "variable `{}` is declared as {} ({}) in units but {} ({}) in its definition",
+                            name, unit, a.dimension, existing, b.dimension
+                        ));
+                    }
+                }
+            }
+            if !unit.trim().is_empty() {
+                declared.insert(name.clone(), unit.clone());
+            }
+        }
+
+        let mut report = self.check_expression(&invariant.formal_expression, &declared);
+        errors.append(&mut report.errors);
+        report.errors = errors;
+        if !report.errors.is_empty() {
+            report.canonical_expression = None;
+        }
+        report
+    }
+
+    pub fn check_expression(
+        &self,
+        expression: &str,
+        units: &HashMap<String, String>,
+    ) -> UnitCheckReport {
+        let mut errors = Vec::new();
+        let mut rewritten = String::with_capacity(expression.len());
+        let mut last = 0;
+
+        for separator in self.clause_separator.find_iter(expression) {
+            let clause = &expression[last..separator.start()];
+            rewritten.push_str(&self.check_clause(clause, units, &mut errors));
+            rewritten.push_str(separator.as_str());
+            last = separator.end();
+        }
+        rewritten.push_str(&self.check_clause(&expression[last..], units, &mut errors));
+
+        let canonical_expression = if errors.is_empty() && rewritten != expression {
+            Some(rewritten)
+        } else {
+            None
+        };
+
+        UnitCheckReport {
+            errors,
+            canonical_expression,
+        }
+    }
+
+    // Returns the clause rewritten into a common unit, or unchanged if it has
+    // no comparison or its dimension cannot be determined
+    fn check_clause(
+        &self,
+        clause: &str,
+        units: &HashMap<String, String>,
+        errors: &mut Vec<String>,
+    ) -> String {
+        let operands: Vec<&str> = self.comparison.split(clause).collect();
+        if operands.len() < 2 {
+            return clause.to_string();
+        }
+
+        let mut clause_dimension: Option<Dimension> = None;
+        let mut variable_units: HashMap<&str, Unit> = HashMap::new();
+        let mut consistent = true;
+
+        for operand in &operands {
+            let terms = self.parse_operand(operand, units);
+            if terms.iter().any(|term| matches!(term, Term::Opaque)) {
+                return clause.to_string();
+            }
+
+            let mut operand_dimension: Option<Dimension> = None;
+            for term in &terms {
+                let unit = match term {
+                    Term::Variable(name, Some(unit)) => {
+                        variable_units.insert(name, *unit);
+                        *unit
+                    }
+                    Term::Quantity(unit) => *unit,
+                    _ => continue,
+                };
+
+                match operand_dimension {
+                    Some(dimension) if dimension != unit.dimension => {
+                        errors.push(format!(
+                            "mixed dimensions in `{}`: {} and {}",
+                            operand.trim(),
+                            dimension,
+                            unit.dimension
+                        ));
+                        consistent = false;
+                    }
+                    _ => operand_dimension = Some(unit.dimension),
+                }
+            }
+
+            if let Some(dimension) = operand_dimension {
+                match clause_dimension {
+                    Some(expected) if expected != dimension => {
+                        errors.push(format!(
+                            "dimension mismatch in `{}`: {} compared with {}",
+                            clause.trim(),
+                            expected,
+                            dimension
+                        ));
+                        consistent = false;
+                    }
+                    _ => clause_dimension = Some(dimension),
+                }
+            }
+        }
+
+        let dimension = match clause_dimension {
+            Some(dimension) if consistent => dimension,
+            _ => return clause.to_string(),
+        };
+
+        // Keep the variables' own unit when they agree so the rewrite only
+        // touches literals; otherwise fall back to the canonical unit
+        let mut distinct = variable_units.values().map(|unit| unit.name).collect::<Vec<_>>();
+        distinct.sort_unstable();
+        distinct.dedup();
+        let target = match distinct.as_slice() {
+            [_] => *variable_units.values().next().unwrap(),
+            _ => dimension.canonical_unit(),
+        };
+
+        let with_literals = self.quantity_literal.replace_all(clause, |caps: &regex::Captures| {
+            let value: f64 = caps[2].parse().unwrap_or(0.0);
+            match parse_unit(&caps[3]) {
+                Some(unit) if unit.dimension == dimension => format!(
+                    "{}{}",
+                    &caps[1],
+                    format_quantity(value * unit.factor / target.factor)
+                ),
+                _ => caps[0].to_string(),
+            }
+        });
+
+        self.identifier
+            .replace_all(&with_literals, |caps: &regex::Captures| {
+                let name = &caps[0];
+                match variable_units.get(name) {
+                    Some(unit) if unit.name != target.name => {
+                        format!("({} * {})", name, format_quantity(unit.factor / target.factor))
+                    }
+                    _ => name.to_string(),
+                }
+            })
+            .to_string()
+    }
+
+    fn parse_operand<'a>(&self, operand: &'a str, units: &HashMap<String, String>) -> Vec<Term<'a>> {
+        if operand.contains(['*', '/', '^']) {
+            return vec![Term::Opaque];
+        }
+
+        operand
+            .split(['+', '-'])
+            .map(|term| term.trim().trim_matches(['(', ')']).trim())
+            .filter(|term| !term.is_empty())
+            .map(|term| self.parse_term(term, units))
+            .collect()
+    }
+
+    fn parse_term<'a>(&self, term: &'a str, units: &HashMap<String, String>) -> Term<'a> {
+        if term.parse::<f64>().is_ok() {
+            return Term::Bare;
+        }
+
+        if let Some(caps) = self.quantity_term.captures(term) {
+            return match parse_unit(&caps[2]) {
+                Some(unit) => Term::Quantity(unit),
+                None => Term::Bare,
+            };
+        }
+
+        if self.identifier.find(term).map(|m| m.as_str() == term).unwrap_or(false) {
+            let unit = units.get(term).and_then(|unit| parse_unit(unit));
+            return Term::Variable(term, unit);
+        }
+
+        Term::Opaque
+    }
+}
+
+impl Default for UnitChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn format_quantity(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        let formatted = format!("{:.6}", value);
+        formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_unit() {
+        assert_eq!(parse_unit("ms").unwrap().dimension, Dimension::Time);
+        assert_eq!(parse_unit("Seconds").unwrap().factor, 1_000.0);
+        assert_eq!(parse_unit("MB").unwrap().factor, 1024.0 * 1024.0);
+        assert_eq!(parse_unit("%").unwrap().dimension, Dimension::Ratio);
+        assert!(parse_unit("currency_units").is_none());
+        assert!(parse_unit("").is_none());
+    }
+
+    #[test]
+    fn test_converts_literals_to_variable_unit() {
+        let checker = UnitChecker::new();
+        let report = checker.check_expression(
+            "response_time <= 2s && memory_usage < 512 KB",
+            &units(&[("response_time", "ms"), ("memory_usage", "bytes")]),
+        );
+
+        assert!(report.errors.is_empty());
+        assert_eq!(
+            report.canonical_expression.as_deref(),
+            Some("response_time <= 2000 && memory_usage < 524288")
+        );
+    }
+
+    #[test]
+    fn test_scales_mixed_unit_variables_to_canonical() {
+        let checker = UnitChecker::new();
+        let report = checker.check_expression(
+            "timeout > latency",
+            &units(&[("timeout", "seconds"), ("latency", "milliseconds")]),
+        );
+
+        assert!(report.errors.is_empty());
+        assert_eq!(report.canonical_expression.as_deref(), Some("(timeout * 1000) > latency"));
+    }
+
+    #[test]
+    fn test_flags_dimension_mismatch() {
+        let checker = UnitChecker::new();
+        let report = checker.check_expression(
+            "response_time <= 5 MB",
+            &units(&[("response_time", "ms")]),
+        );
+
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].contains("time compared with data size"));
+        assert!(report.canonical_expression.is_none());
+    }
+
+    #[test]
+    fn test_flags_mixed_dimensions_in_sum() {
+        let checker = UnitChecker::new();
+        let report = checker.check_expression(
+            "queue_time + payload_size < 100",
+            &units(&[("queue_time", "ms"), ("payload_size", "bytes")]),
+        );
+
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].contains("mixed dimensions"));
+    }
+
+    #[test]
+    fn test_unitless_and_opaque_expressions_are_left_alone() {
+        let checker = UnitChecker::new();
+        let declared = units(&[("user_id", "count"), ("throughput", "requests")]);
+
+        let report = checker.check_expression("user_id > 0", &declared);
+        assert_eq!(report, UnitCheckReport::default());
+
+        let report = checker.check_expression("throughput / 2 >= 5s", &declared);
+        assert_eq!(report, UnitCheckReport::default());
+    }
+}
@@ -1,12 +1,16 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use nlp::{
     NlpService, InvariantExtractionConfig,
+    evaluation::{evaluate, EvaluationConfig, GoldenDataset, ReplayLanguageModel},
+    pipeline::ExtractionPipeline,
     proto::nlp::v1::{
         ExtractInvariantsRequest, ExtractedInvariant, Variable, Priority,
         TokenUsage, ProcessingMetadata, ExtractionMetadata
     }
 };
 use aws_sdk_dynamodb::Client as DynamoClient;
+use prompt_registry::PromptTemplate;
 
 // Golden invariants for synthetic test documents
 #[derive(Debug, Clone)]
@@ -403,6 +407,8 @@ async fn test_invariant_extraction_accuracy() {
             source_system: "test".to_string(),
             invariant_types: vec![],
             confidence_threshold: 0.5,
+            dry_run: false,
+            language: String::new(),
         };
 
         // Test PII redaction
@@ -437,6 +443,10 @@ async fn test_invariant_extraction_accuracy() {
                     _ => Priority::PriorityUnspecified as i32,
                 },
                 extraction_metadata: None,
+                validation_errors: vec![],
+                source_span: None,
+                classification: None,
+                language: String::new(),
             })
             .collect();
 
@@ -511,6 +521,7 @@ async fn test_cache_functionality() {
             cached: false,
             cache_key: test_key.to_string(),
         }),
+        plan: None,
     };
     
     // Test cache set/get (would need proper mocking)
@@ -524,16 +535,22 @@ async fn create_mock_dynamo_client() -> DynamoClient {
     DynamoClient::new(&config)
 }
 
-#[test]
-fn test_f1_score_calculation() {
-    // Test F1 score calculation for invariant extraction accuracy
-    let true_positives = 8;
-    let false_positives = 2;
-    let false_negatives = 1;
-    
-    let precision = true_positives as f64 / (true_positives + false_positives) as f64;
-    let recall = true_positives as f64 / (true_positives + false_negatives) as f64;
-    let f1_score = 2.0 * (precision * recall) / (precision + recall);
+#[tokio::test]
+async fn test_f1_score_calculation() {
+    // Run the golden dataset through the pipeline with the recorded model
+    // responses and score the result
+    let dataset: GoldenDataset =
+        serde_json::from_str(include_str!("testdata/golden_dataset.json")).unwrap();
+    let pipeline = ExtractionPipeline::new(&InvariantExtractionConfig::default())
+        .with_language_model(Arc::new(ReplayLanguageModel::from_dataset(&dataset)));
+    let template = PromptTemplate::new("golden", "test", "Document {{document_id}}\n\n{{content}}", &["content"]);
+
+    let report = evaluate(&pipeline, &template, &dataset, &EvaluationConfig::default()).await.unwrap();
+    let (f1_score, precision, recall) = (report.overall.f1, report.overall.precision, report.overall.recall);
+
+    assert_eq!(report.overall.true_positives, 5);
+    assert_eq!(report.documents[1].spurious, vec!["password_length >= 12".to_string()]);
+    assert_eq!(report.categories["performance"].f1, 1.0);
     
     // Should achieve ≥ 90% F1 score
     assert!(f1_score >= 0.9, "F1 score ({:.3}) is below 90% threshold", f1_score);
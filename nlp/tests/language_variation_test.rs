@@ -19,10 +19,20 @@ async fn test_language_variation_handling() {
         max_tokens: 4000,
         temperature: 0.0,
         cache_ttl_seconds: 86400,
+        negative_cache_ttl_seconds: 300,
         max_retries: 3,
         retry_delay_ms: 1000,
+        stream_deadline_ms: None,
+        cost_governance_redis_url: None,
         confidence_threshold: 0.5,
         cost_per_1k_tokens: 0.015,
+        chunk_token_budget: 8000,
+        chunk_overlap_tokens: 200,
+        taxonomy: Default::default(),
+        prompt_manifest: None,
+        storage: storage::StorageSettings::default(),
+        notifications: None,
+        health_check_timeout_ms: 2000,
     };
 
     // Test different phrasings of the same specification
@@ -120,6 +130,8 @@ async fn test_language_variation_handling() {
             source_system: "test_system".to_string(),
             invariant_types: vec![],
             confidence_threshold: 0.5,
+            dry_run: false,
+            language: String::new(),
         };
 
         // Create NLP service
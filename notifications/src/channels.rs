@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{Notification, NotificationError, Result, Severity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelProvider {
+    Slack,
+    Teams,
+}
+
+/// An incoming webhook messages are posted to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelSettings {
+    /// Name routing rules refer to the channel by
+    pub name: String,
+    pub provider: ChannelProvider,
+    pub webhook_url: String,
+}
+
+/// Somewhere rendered notifications can be delivered
+#[async_trait::async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn send(&self, message: &str, notification: &Notification) -> Result<()>;
+}
+
+/// Posts to a Slack or Microsoft Teams incoming webhook
+pub struct WebhookChannel {
+    settings: ChannelSettings,
+    http_client: reqwest::Client,
+}
+
+impl WebhookChannel {
+    pub fn new(settings: ChannelSettings) -> Self {
+        Self {
+            settings,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn payload(&self, message: &str, notification: &Notification) -> serde_json::Value {
+        match self.settings.provider {
+            ChannelProvider::Slack => json!({ "text": message }),
+            // Teams connectors still require the legacy MessageCard format
+            ChannelProvider::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": notification.kind.as_str(),
+                "themeColor": theme_color(notification.severity),
+                "text": message,
+            }),
+        }
+    }
+}
+
+fn theme_color(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "2EB67D",
+        Severity::Warning => "ECB22E",
+        Severity::Critical => "E01E5A",
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn name(&self) -> &str {
+        &self.settings.name
+    }
+
+    async fn send(&self, message: &str, notification: &Notification) -> Result<()> {
+        let response = self.http_client
+            .post(&self.settings.webhook_url)
+            .json(&self.payload(message, notification))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(NotificationError::Rejected {
+                channel: self.settings.name.clone(),
+                status: response.status().as_u16(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NotificationKind;
+
+    fn channel(provider: ChannelProvider) -> WebhookChannel {
+        WebhookChannel::new(ChannelSettings {
+            name: "proofs".to_string(),
+            provider,
+            webhook_url: "https://hooks.example.com/proofs".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_payload_per_provider() {
+        let notification = Notification::new(NotificationKind::ProofFailed, Severity::Critical);
+
+        assert_eq!(
+            channel(ChannelProvider::Slack).payload("Proof failed", &notification),
+            json!({ "text": "Proof failed" })
+        );
+
+        let teams = channel(ChannelProvider::Teams).payload("Proof failed", &notification);
+        assert_eq!(teams["@type"], "MessageCard");
+        assert_eq!(teams["summary"], "proof_failed");
+        assert_eq!(teams["themeColor"], "E01E5A");
+        assert_eq!(teams["text"], "Proof failed");
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use aws_sdk_ses::types::{Body, Content, Destination, Message};
+use aws_sdk_ses::Client as SesClient;
+use serde::{Deserialize, Serialize};
+
+use crate::template;
+use crate::{Notification, NotificationChannel, NotificationError, NotificationKind, Result};
+
+fn default_max_per_window() -> u32 {
+    20
+}
+
+fn default_window_secs() -> u64 {
+    3600
+}
+
+/// Emails sent through SES. Each tenant's notifications go to its own
+/// recipients when listed, otherwise to `default_recipients`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailSettings {
+    /// Name routing rules refer to the channel by
+    pub name: String,
+    pub ses_region: String,
+    pub sender: String,
+    #[serde(default)]
+    pub default_recipients: Vec<String>,
+    #[serde(default)]
+    pub tenant_recipients: HashMap<String, Vec<String>>,
+    /// Subjects overriding the built-in ones, with the same placeholders
+    /// as message templates
+    #[serde(default)]
+    pub subjects: HashMap<NotificationKind, String>,
+    /// Emails one recipient list may receive per window; the rest are
+    /// dropped so a burst of failures doesn't become a mail storm
+    #[serde(default = "default_max_per_window")]
+    pub max_per_window: u32,
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+}
+
+impl EmailSettings {
+    pub fn recipients(&self, tenant_id: Option<&str>) -> &[String] {
+        tenant_id
+            .and_then(|tenant_id| self.tenant_recipients.get(tenant_id))
+            .unwrap_or(&self.default_recipients)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers plain-text emails
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, sender: &str, message: &EmailMessage) -> Result<()>;
+}
+
+pub struct SesTransport {
+    client: SesClient,
+}
+
+impl SesTransport {
+    pub fn new(client: SesClient) -> Self {
+        Self { client }
+    }
+
+    pub async fn connect(region: &str) -> Self {
+        let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(aws_config::Region::new(region.to_string()))
+            .load()
+            .await;
+        Self::new(SesClient::new(&config))
+    }
+}
+
+fn ses_error(error: impl std::fmt::Display) -> NotificationError {
+    NotificationError::Email(error.to_string())
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SesTransport {
+    async fn send(&self, sender: &str, message: &EmailMessage) -> Result<()> {
+        let content = |data: &str| Content::builder().data(data).charset("UTF-8").build().map_err(ses_error);
+        let ses_message = Message::builder()
+            .subject(content(&message.subject)?)
+            .body(Body::builder().text(content(&message.body)?).build())
+            .build()
+            .map_err(ses_error)?;
+        let destination = Destination::builder()
+            .set_to_addresses(Some(message.to.clone()))
+            .build();
+
+        self.client
+            .send_email()
+            .source(sender)
+            .destination(destination)
+            .message(ses_message)
+            .send()
+            .await
+            .map_err(ses_error)?;
+        Ok(())
+    }
+}
+
+pub fn default_subject(kind: NotificationKind) -> &'static str {
+    match kind {
+        NotificationKind::ExtractionCompleted => "[Spec-to-Proof] Invariants extracted from {document_id}",
+        NotificationKind::ProofFailed => "[Spec-to-Proof] Proof failed for invariant {invariant_id}",
+        NotificationKind::BudgetThreshold => "[Spec-to-Proof] Tenant Budget Alert - {tenant_id} ({status})",
+        NotificationKind::DriftDetected => "[Spec-to-Proof] Spec drift in {document_id}",
+        NotificationKind::ReviewRequested => "[Spec-to-Proof] Review requested for {invariant_set_name}",
+    }
+}
+
+/// Emails rendered notifications to the tenant's recipients
+pub struct EmailChannel {
+    settings: EmailSettings,
+    transport: Arc<dyn EmailTransport>,
+    // Send times per recipient list within the current window
+    sent: Mutex<HashMap<Vec<String>, VecDeque<Instant>>>,
+}
+
+impl EmailChannel {
+    pub fn new(settings: EmailSettings, transport: Arc<dyn EmailTransport>) -> Self {
+        Self {
+            settings,
+            transport,
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn connect(settings: EmailSettings) -> Self {
+        let transport = SesTransport::connect(&settings.ses_region).await;
+        Self::new(settings, Arc::new(transport))
+    }
+
+    pub fn email(&self, message: &str, notification: &Notification) -> EmailMessage {
+        let subject = self.settings.subjects
+            .get(&notification.kind)
+            .map(String::as_str)
+            .unwrap_or_else(|| default_subject(notification.kind));
+        EmailMessage {
+            to: self.settings.recipients(notification.tenant_id.as_deref()).to_vec(),
+            subject: template::render(subject, notification),
+            body: message.to_string(),
+        }
+    }
+
+    // Counts the send against the recipients' window, refusing it once
+    // the window is full
+    fn admit(&self, recipients: &[String]) -> bool {
+        let window = Duration::from_secs(self.settings.window_secs);
+        let now = Instant::now();
+        let mut sent = self.sent.lock().unwrap();
+        sent.retain(|_, times| times.back().is_some_and(|last| now.duration_since(*last) < window));
+        let times = sent.entry(recipients.to_vec()).or_default();
+        while times.front().is_some_and(|first| now.duration_since(*first) >= window) {
+            times.pop_front();
+        }
+        if times.len() >= self.settings.max_per_window as usize {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &str {
+        &self.settings.name
+    }
+
+    async fn send(&self, message: &str, notification: &Notification) -> Result<()> {
+        let email = self.email(message, notification);
+        if email.to.is_empty() {
+            return Err(NotificationError::NoRecipients {
+                channel: self.settings.name.clone(),
+                tenant_id: notification.tenant_id.clone(),
+            });
+        }
+        if !self.admit(&email.to) {
+            return Err(NotificationError::RateLimited { channel: self.settings.name.clone() });
+        }
+        self.transport.send(&self.settings.sender, &email).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Severity;
+
+    #[derive(Default)]
+    struct RecordingTransport {
+        sent: Mutex<Vec<EmailMessage>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailTransport for RecordingTransport {
+        async fn send(&self, _sender: &str, message: &EmailMessage) -> Result<()> {
+            self.sent.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    fn settings(max_per_window: u32) -> EmailSettings {
+        serde_json::from_value(serde_json::json!({
+            "name": "email",
+            "ses_region": "us-east-1",
+            "sender": "noreply@spec-to-proof.com",
+            "default_recipients": ["alerts@spec-to-proof.com"],
+            "tenant_recipients": {"acme": ["oncall@acme.com", "specs@acme.com"]},
+            "max_per_window": max_per_window
+        })).unwrap()
+    }
+
+    fn drift(tenant_id: &str) -> Notification {
+        Notification::new(NotificationKind::DriftDetected, Severity::Warning)
+            .with_tenant(tenant_id)
+            .with_field("document_id", "DOC-1")
+    }
+
+    #[tokio::test]
+    async fn test_emails_tenant_recipients() {
+        let transport = Arc::new(RecordingTransport::default());
+        let channel = EmailChannel::new(settings(20), transport.clone());
+
+        channel.send("DOC-1 changed", &drift("acme")).await.unwrap();
+        channel.send("DOC-1 changed", &drift("globex")).await.unwrap();
+
+        let sent = transport.sent.lock().unwrap();
+        assert_eq!(sent[0], EmailMessage {
+            to: vec!["oncall@acme.com".to_string(), "specs@acme.com".to_string()],
+            subject: "[Spec-to-Proof] Spec drift in DOC-1".to_string(),
+            body: "DOC-1 changed".to_string(),
+        });
+        assert_eq!(sent[1].to, ["alerts@spec-to-proof.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limits_each_recipient_list() {
+        let transport = Arc::new(RecordingTransport::default());
+        let channel = EmailChannel::new(settings(2), transport.clone());
+
+        for _ in 0..2 {
+            channel.send("DOC-1 changed", &drift("acme")).await.unwrap();
+        }
+        assert!(matches!(
+            channel.send("DOC-1 changed", &drift("acme")).await,
+            Err(NotificationError::RateLimited { .. })
+        ));
+        // Other tenants have their own window
+        channel.send("DOC-1 changed", &drift("globex")).await.unwrap();
+        assert_eq!(transport.sent.lock().unwrap().len(), 3);
+    }
+}
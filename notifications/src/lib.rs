@@ -0,0 +1,351 @@
+pub mod channels;
+pub mod email;
+pub mod template;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+pub use channels::{ChannelProvider, ChannelSettings, NotificationChannel, WebhookChannel};
+pub use email::{EmailChannel, EmailMessage, EmailSettings, EmailTransport, SesTransport};
+
+#[derive(Debug, thiserror::Error)]
+pub enum NotificationError {
+    #[error("Invalid notification settings: {0}")]
+    InvalidSettings(String),
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Channel {channel} rejected the notification with status {status}")]
+    Rejected { channel: String, status: u16 },
+
+    #[error("Email error: {0}")]
+    Email(String),
+
+    #[error("Channel {channel} has no recipients for tenant {tenant_id:?}")]
+    NoRecipients { channel: String, tenant_id: Option<String> },
+
+    #[error("Channel {channel} has sent its limit of notifications for now")]
+    RateLimited { channel: String },
+}
+
+impl From<NotificationError> for spec_to_proof_error::Error {
+    fn from(error: NotificationError) -> Self {
+        use spec_to_proof_error::Error;
+        match error {
+            NotificationError::InvalidSettings(_) => Error::invalid_input(error),
+            NotificationError::Rejected { status, .. } => Error::from_status(status, error),
+            NotificationError::NoRecipients { .. } => Error::invalid_input(error),
+            NotificationError::RateLimited { .. } => Error::rate_limited(error, None),
+            NotificationError::Http(_) | NotificationError::Email(_) => Error::transient(error),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, NotificationError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    ExtractionCompleted,
+    /// Only raised for critical invariants
+    ProofFailed,
+    BudgetThreshold,
+    DriftDetected,
+    /// An invariant set is waiting for a reviewer
+    ReviewRequested,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::ExtractionCompleted => "extraction_completed",
+            NotificationKind::ProofFailed => "proof_failed",
+            NotificationKind::BudgetThreshold => "budget_threshold",
+            NotificationKind::DriftDetected => "drift_detected",
+            NotificationKind::ReviewRequested => "review_requested",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// Something worth telling a team about; `fields` fill the placeholders of
+/// the kind's message template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub severity: Severity,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Notification {
+    pub fn new(kind: NotificationKind, severity: Severity) -> Self {
+        Self {
+            kind,
+            severity,
+            tenant_id: None,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_tenant(mut self, tenant_id: &str) -> Self {
+        self.tenant_id = Some(tenant_id.to_string());
+        self
+    }
+
+    pub fn with_field(mut self, name: &str, value: impl ToString) -> Self {
+        self.fields.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Sends matching notifications to `channel`. Empty `kinds` and `tenants`
+/// match every kind and tenant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingRule {
+    pub channel: String,
+    #[serde(default)]
+    pub kinds: Vec<NotificationKind>,
+    #[serde(default)]
+    pub min_severity: Severity,
+    #[serde(default)]
+    pub tenants: Vec<String>,
+}
+
+impl RoutingRule {
+    pub fn matches(&self, notification: &Notification) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&notification.kind))
+            && notification.severity >= self.min_severity
+            && (self.tenants.is_empty()
+                || notification.tenant_id.as_ref().is_some_and(|tenant| self.tenants.contains(tenant)))
+    }
+}
+
+/// Channels, the rules routing notifications to them and templates
+/// overriding the built-in messages. Without rules every channel receives
+/// every notification.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    #[serde(default)]
+    pub channels: Vec<ChannelSettings>,
+    #[serde(default)]
+    pub email: Vec<EmailSettings>,
+    #[serde(default)]
+    pub routes: Vec<RoutingRule>,
+    #[serde(default)]
+    pub templates: HashMap<NotificationKind, String>,
+}
+
+/// Renders notifications and delivers them to the channels their routing
+/// rules select. Notifications report on work that has already happened,
+/// so delivery failures are logged rather than returned.
+#[derive(Default)]
+pub struct Notifier {
+    channels: Vec<Arc<dyn NotificationChannel>>,
+    routes: Vec<RoutingRule>,
+    templates: HashMap<NotificationKind, String>,
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier")
+            .field("channels", &self.channels.iter().map(|channel| channel.name()).collect::<Vec<_>>())
+            .field("routes", &self.routes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Notifier {
+    /// Builds the configured channels, connecting to SES for email ones
+    pub async fn connect(settings: &NotificationSettings) -> Result<Self> {
+        let mut names = HashSet::new();
+        for channel in &settings.channels {
+            if channel.webhook_url.is_empty() {
+                return Err(NotificationError::InvalidSettings(format!("channel {} has no webhook URL", channel.name)));
+            }
+            if !names.insert(channel.name.as_str()) {
+                return Err(NotificationError::InvalidSettings(format!("channel {} is defined twice", channel.name)));
+            }
+        }
+        for email in &settings.email {
+            if email.sender.is_empty() || email.max_per_window == 0 {
+                return Err(NotificationError::InvalidSettings(format!("email channel {} needs a sender and a send limit", email.name)));
+            }
+            if !names.insert(email.name.as_str()) {
+                return Err(NotificationError::InvalidSettings(format!("channel {} is defined twice", email.name)));
+            }
+        }
+        if let Some(route) = settings.routes.iter().find(|route| !names.contains(route.channel.as_str())) {
+            return Err(NotificationError::InvalidSettings(format!("route to unknown channel {}", route.channel)));
+        }
+
+        let mut notifier = Self {
+            routes: settings.routes.clone(),
+            templates: settings.templates.clone(),
+            ..Self::default()
+        };
+        for channel in &settings.channels {
+            notifier = notifier.with_channel(Arc::new(WebhookChannel::new(channel.clone())));
+        }
+        for email in &settings.email {
+            notifier = notifier.with_channel(Arc::new(EmailChannel::connect(email.clone()).await));
+        }
+        Ok(notifier)
+    }
+
+    pub fn with_channel(mut self, channel: Arc<dyn NotificationChannel>) -> Self {
+        self.channels.push(channel);
+        self
+    }
+
+    pub fn with_route(mut self, route: RoutingRule) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    pub fn render(&self, notification: &Notification) -> String {
+        let template = self.templates
+            .get(&notification.kind)
+            .map(String::as_str)
+            .unwrap_or_else(|| template::default_template(notification.kind));
+        template::render(template, notification)
+    }
+
+    /// Sends the notification to each selected channel once and returns
+    /// how many accepted it
+    pub async fn notify(&self, notification: &Notification) -> usize {
+        let message = self.render(notification);
+        let mut delivered = 0;
+        for channel in self.channels.iter().filter(|channel| self.selects(channel.name(), notification)) {
+            match channel.send(&message, notification).await {
+                Ok(()) => delivered += 1,
+                Err(e) => warn!("Failed to send {} notification to {}: {}", notification.kind.as_str(), channel.name(), e),
+            }
+        }
+        if delivered > 0 {
+            info!("Sent {} notification to {} channels", notification.kind.as_str(), delivered);
+        }
+        delivered
+    }
+
+    fn selects(&self, channel: &str, notification: &Notification) -> bool {
+        self.routes.is_empty()
+            || self.routes.iter().any(|route| route.channel == channel && route.matches(notification))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingChannel {
+        name: String,
+        messages: Mutex<Vec<String>>,
+    }
+
+    impl RecordingChannel {
+        fn named(name: &str) -> Arc<Self> {
+            Arc::new(Self { name: name.to_string(), ..Self::default() })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl NotificationChannel for RecordingChannel {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        async fn send(&self, message: &str, _notification: &Notification) -> Result<()> {
+            self.messages.lock().unwrap().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    fn proof_failed(severity: Severity, tenant_id: &str) -> Notification {
+        Notification::new(NotificationKind::ProofFailed, severity)
+            .with_tenant(tenant_id)
+            .with_field("priority", "critical")
+            .with_field("invariant_id", "inv_1")
+            .with_field("status", "timeout")
+            .with_field("description", "Balance is non-negative")
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_kind_severity_and_tenant() {
+        let oncall = RecordingChannel::named("oncall");
+        let acme = RecordingChannel::named("acme");
+        let notifier = Notifier::default()
+            .with_channel(oncall.clone())
+            .with_channel(acme.clone())
+            .with_route(RoutingRule {
+                channel: "oncall".to_string(),
+                kinds: vec![NotificationKind::ProofFailed],
+                min_severity: Severity::Critical,
+                tenants: vec![],
+            })
+            .with_route(RoutingRule {
+                channel: "acme".to_string(),
+                kinds: vec![],
+                min_severity: Severity::Info,
+                tenants: vec!["acme".to_string()],
+            });
+
+        assert_eq!(notifier.notify(&proof_failed(Severity::Critical, "acme")).await, 2);
+        assert_eq!(notifier.notify(&proof_failed(Severity::Warning, "globex")).await, 0);
+        assert_eq!(
+            oncall.messages.lock().unwrap().as_slice(),
+            ["Proof failed for critical invariant inv_1 (timeout): Balance is non-negative"]
+        );
+        assert_eq!(acme.messages.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_template_overrides() {
+        let channel = RecordingChannel::named("all");
+        let mut settings = NotificationSettings::default();
+        settings.templates.insert(NotificationKind::DriftDetected, ":warning: {document_id} drifted".to_string());
+        let notifier = Notifier::connect(&settings).await.unwrap().with_channel(channel.clone());
+
+        let drift = Notification::new(NotificationKind::DriftDetected, Severity::Warning).with_field("document_id", "DOC-1");
+        assert_eq!(notifier.notify(&drift).await, 1);
+        assert_eq!(channel.messages.lock().unwrap().as_slice(), [":warning: DOC-1 drifted"]);
+    }
+
+    #[tokio::test]
+    async fn test_settings_validation() {
+        let settings: NotificationSettings = serde_json::from_value(serde_json::json!({
+            "channels": [{"name": "proofs", "provider": "slack", "webhook_url": "https://hooks.slack.com/services/T/B/X"}],
+            "routes": [{"channel": "alerts", "kinds": ["budget_threshold"]}]
+        })).unwrap();
+        assert!(matches!(Notifier::connect(&settings).await, Err(NotificationError::InvalidSettings(_))));
+
+        let settings = NotificationSettings { routes: vec![], ..settings };
+        assert!(Notifier::connect(&settings).await.is_ok());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{Notification, NotificationKind};
+
+/// Built-in message for each kind, used unless the settings override it
+pub fn default_template(kind: NotificationKind) -> &'static str {
+    match kind {
+        NotificationKind::ExtractionCompleted => {
+            "Extracted {invariant_count} invariants from spec document {document_id}"
+        }
+        NotificationKind::ProofFailed => {
+            "Proof failed for {priority} invariant {invariant_id} ({status}): {description}"
+        }
+        NotificationKind::BudgetThreshold => {
+            "Tenant {tenant_id} is at {status}: {daily_spent_usd} USD today, {monthly_spent_usd} USD this month"
+        }
+        NotificationKind::DriftDetected => "{summary}",
+        NotificationKind::ReviewRequested => {
+            "Invariant set {invariant_set_name} ({invariant_count} invariants) is ready for review"
+        }
+    }
+}
+
+/// Replaces `{field}` placeholders with the notification's fields, its
+/// `kind`, `severity` and `tenant_id`. Unknown placeholders are left as
+/// written so a typo in a template shows up in the message.
+pub fn render(template: &str, notification: &Notification) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let placeholder = &rest[start..start + end + 1];
+        match lookup(notification, &placeholder[1..placeholder.len() - 1]) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(placeholder),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn lookup<'a>(notification: &'a Notification, name: &str) -> Option<&'a str> {
+    match name {
+        "kind" => Some(notification.kind.as_str()),
+        "severity" => Some(notification.severity.as_str()),
+        "tenant_id" => notification.tenant_id.as_deref(),
+        _ => notification.fields.get(name).map(String::as_str),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Severity;
+
+    #[test]
+    fn test_render_fills_fields_and_keeps_unknown_placeholders() {
+        let notification = Notification::new(NotificationKind::ExtractionCompleted, Severity::Info)
+            .with_tenant("acme")
+            .with_field("document_id", "DOC-1")
+            .with_field("invariant_count", 3);
+
+        assert_eq!(
+            render(default_template(NotificationKind::ExtractionCompleted), &notification),
+            "Extracted 3 invariants from spec document DOC-1"
+        );
+        assert_eq!(
+            render("[{severity}] {tenant_id}: {missing} {unclosed", &notification),
+            "[info] acme: {missing} {unclosed"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+pub mod memory;
+pub mod s3;
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+use async_trait::async_trait;
+
+pub use memory::InMemoryObjectStore;
+pub use s3::{ObjectStoreConfig, S3ObjectStore};
+
+// lean-farm keeps proofs in MinIO and the proof service keeps theorems and
+// transcripts in S3. Both go through `ObjectStore`, so listing, missing
+// objects, encryption and presigning behave the same whichever endpoint a
+// deployment points them at.
+
+/// SigV4 presigned URLs are valid for at most seven days
+pub const MAX_PRESIGN_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ObjectStoreError {
+    #[error("Invalid object location: {0}")]
+    InvalidLocation(String),
+
+    #[error("Object storage configuration error: {0}")]
+    Config(String),
+
+    #[error("Object storage request failed: {0}")]
+    Backend(String),
+}
+
+impl From<ObjectStoreError> for spec_to_proof_error::Error {
+    fn from(error: ObjectStoreError) -> Self {
+        use spec_to_proof_error::Error;
+        match error {
+            ObjectStoreError::InvalidLocation(_) => Error::invalid_input(error),
+            ObjectStoreError::Config(_) => Error::internal(error),
+            ObjectStoreError::Backend(_) => Error::transient(error),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ObjectStoreError>;
+
+/// Server-side encryption applied to written objects
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Encryption {
+    #[default]
+    None,
+    /// SSE-S3
+    Aes256,
+    /// SSE-KMS with this key. MinIO needs KES configured to accept it, and
+    /// GCS interoperability rejects it.
+    Kms { key_id: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PutOptions {
+    pub content_type: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+impl PutOptions {
+    pub fn content_type(content_type: &str) -> Self {
+        Self {
+            content_type: Some(content_type.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata.extend(metadata);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredObject {
+    pub body: Vec<u8>,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<SystemTime>,
+}
+
+/// A presigned request and the headers the caller must send with it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresignedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub expires_at: SystemTime,
+}
+
+/// One bucket of an object store
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    fn bucket(&self) -> &str;
+
+    /// Writes the object, replacing any earlier version, with the store's
+    /// server-side encryption
+    async fn put(&self, key: &str, body: Vec<u8>, options: &PutOptions) -> Result<()>;
+
+    /// The object and its user metadata, or None if there is no such key
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>>;
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>>;
+
+    /// Every object under `prefix`, across as many pages as it takes, in
+    /// key order
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>>;
+
+    /// Deleting a missing key succeeds
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// A URL reading the object without credentials until `expires_in`
+    /// has passed, capped at `MAX_PRESIGN_EXPIRY`
+    async fn presign_get(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest>;
+
+    /// A URL writing the object without credentials. The store's
+    /// encryption headers are part of the signature, so uploads must send
+    /// every returned header.
+    async fn presign_put(&self, key: &str, content_type: &str, expires_in: Duration) -> Result<PresignedRequest>;
+
+    /// Checks that the bucket is reachable with the store's credentials,
+    /// for readiness probes
+    async fn ping(&self) -> Result<()>;
+
+    fn location(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket(), key)
+    }
+}
+
+/// Splits `s3://bucket/key` into bucket and key
+pub fn parse_location(location: &str) -> Result<(String, String)> {
+    let invalid = || ObjectStoreError::InvalidLocation(location.to_string());
+    let path = location.strip_prefix("s3://").ok_or_else(invalid)?;
+    match path.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => Ok((bucket.to_string(), key.to_string())),
+        _ => Err(invalid()),
+    }
+}
+
+pub fn clamp_presign_expiry(expires_in: Duration) -> Duration {
+    expires_in.clamp(Duration::from_secs(1), MAX_PRESIGN_EXPIRY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            parse_location("s3://test-bucket/theorems/test.lean").unwrap(),
+            ("test-bucket".to_string(), "theorems/test.lean".to_string())
+        );
+        for invalid in ["invalid-location", "s3://bucket-only", "s3:///key", "gs://bucket/key"] {
+            assert!(parse_location(invalid).is_err(), "{:?} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_clamp_presign_expiry() {
+        assert_eq!(clamp_presign_expiry(Duration::ZERO), Duration::from_secs(1));
+        assert_eq!(clamp_presign_expiry(Duration::from_secs(900)), Duration::from_secs(900));
+        assert_eq!(clamp_presign_expiry(Duration::from_secs(30 * 24 * 60 * 60)), MAX_PRESIGN_EXPIRY);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime};
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use crate::{
+    clamp_presign_expiry, ObjectInfo, ObjectStore, PresignedRequest, PutOptions, Result, StoredObject,
+};
+
+/// Object store kept in process memory, for tests and local development.
+/// Presigned URLs use a `memory://` scheme and cannot be fetched.
+#[derive(Debug, Default)]
+pub struct InMemoryObjectStore {
+    bucket: String,
+    objects: RwLock<BTreeMap<String, (StoredObject, SystemTime)>>,
+}
+
+impl InMemoryObjectStore {
+    pub fn new(bucket: &str) -> Self {
+        Self {
+            bucket: bucket.to_string(),
+            objects: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub async fn len(&self) -> usize {
+        self.objects.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.objects.read().await.is_empty()
+    }
+
+    fn presign(&self, method: &str, key: &str, expires_in: Duration) -> PresignedRequest {
+        let expires_in = clamp_presign_expiry(expires_in);
+        PresignedRequest {
+            method: method.to_string(),
+            url: format!("memory://{}/{}?expires={}", self.bucket, key, expires_in.as_secs()),
+            headers: HashMap::new(),
+            expires_at: SystemTime::now() + expires_in,
+        }
+    }
+}
+
+fn info(key: &str, object: &StoredObject, modified: SystemTime) -> ObjectInfo {
+    ObjectInfo {
+        key: key.to_string(),
+        size: object.body.len() as u64,
+        last_modified: Some(modified),
+    }
+}
+
+#[async_trait]
+impl ObjectStore for InMemoryObjectStore {
+    fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>, options: &PutOptions) -> Result<()> {
+        let object = StoredObject {
+            body,
+            metadata: options.metadata.clone(),
+        };
+        self.objects.write().await.insert(key.to_string(), (object, SystemTime::now()));
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>> {
+        Ok(self.objects.read().await.get(key).map(|(object, _)| object.clone()))
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>> {
+        Ok(self.objects.read().await.get(key).map(|(object, modified)| info(key, object, *modified)))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        Ok(self
+            .objects
+            .read()
+            .await
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, (object, modified))| info(key, object, *modified))
+            .collect())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.objects.write().await.remove(key);
+        Ok(())
+    }
+
+    async fn presign_get(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
+        Ok(self.presign("GET", key, expires_in))
+    }
+
+    async fn presign_put(&self, key: &str, _content_type: &str, expires_in: Duration) -> Result<PresignedRequest> {
+        Ok(self.presign("PUT", key, expires_in))
+    }
+
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_get_list_delete() {
+        let store = InMemoryObjectStore::new("proofs");
+        let options = PutOptions::content_type("application/json")
+            .with_metadata(HashMap::from([("theorem_id".to_string(), "thm-1".to_string())]));
+        store.put("proofs/b", b"2".to_vec(), &options).await.unwrap();
+        store.put("proofs/a", b"1".to_vec(), &options).await.unwrap();
+        store.put("proven/a", b"3".to_vec(), &PutOptions::default()).await.unwrap();
+
+        let object = store.get("proofs/a").await.unwrap().unwrap();
+        assert_eq!(object.body, b"1");
+        assert_eq!(object.metadata["theorem_id"], "thm-1");
+        assert!(store.get("proofs/c").await.unwrap().is_none());
+
+        let keys: Vec<String> = store.list("proofs/").await.unwrap().into_iter().map(|info| info.key).collect();
+        assert_eq!(keys, ["proofs/a", "proofs/b"]);
+
+        store.delete("proofs/a").await.unwrap();
+        store.delete("proofs/a").await.unwrap();
+        assert!(store.head("proofs/a").await.unwrap().is_none());
+        assert_eq!(store.location("proofs/b"), "s3://proofs/proofs/b");
+    }
+}
@@ -0,0 +1,313 @@
+use std::time::{Duration, SystemTime};
+use async_trait::async_trait;
+use aws_sdk_s3::config::{Credentials, Region};
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::operation::put_object::builders::PutObjectFluentBuilder;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::{ByteStream, DateTime};
+use aws_sdk_s3::types::ServerSideEncryption;
+use aws_sdk_s3::Client as S3Client;
+
+use crate::{
+    clamp_presign_expiry, Encryption, ObjectInfo, ObjectStore, ObjectStoreError, PresignedRequest, PutOptions,
+    Result, StoredObject,
+};
+
+/// Where a store lives. Without an endpoint this is AWS S3 with the
+/// default credential chain; with one it is an S3-compatible service such
+/// as MinIO or GCS interoperability, addressed path-style.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectStoreConfig {
+    pub bucket: String,
+    pub region: String,
+    pub endpoint: Option<String>,
+    /// Static credentials, e.g. a MinIO access key or a GCS HMAC key. The
+    /// default credential chain is used when unset.
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub encryption: Encryption,
+}
+
+fn backend<E: std::error::Error>(error: E) -> ObjectStoreError {
+    ObjectStoreError::Backend(DisplayErrorContext(error).to_string())
+}
+
+fn system_time(time: &DateTime) -> Option<SystemTime> {
+    SystemTime::try_from(*time).ok()
+}
+
+/// One bucket on AWS S3 or an S3-compatible endpoint
+#[derive(Debug, Clone)]
+pub struct S3ObjectStore {
+    client: S3Client,
+    bucket: String,
+    encryption: Encryption,
+}
+
+impl S3ObjectStore {
+    pub fn new(client: S3Client, bucket: &str) -> Self {
+        Self {
+            client,
+            bucket: bucket.to_string(),
+            encryption: Encryption::None,
+        }
+    }
+
+    pub async fn connect(config: &ObjectStoreConfig) -> Result<Self> {
+        if config.bucket.is_empty() {
+            return Err(ObjectStoreError::Config("A bucket is required".to_string()));
+        }
+
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+        if !config.region.is_empty() {
+            loader = loader.region(Region::new(config.region.clone()));
+        }
+        let sdk_config = loader.load().await;
+
+        let mut builder = aws_sdk_s3::config::Builder::from(&sdk_config);
+        if let Some(endpoint) = &config.endpoint {
+            // MinIO and GCS don't serve virtual-hosted bucket names
+            builder = builder.endpoint_url(endpoint).force_path_style(true);
+            if sdk_config.region().is_none() {
+                builder = builder.region(Region::new("us-east-1"));
+            }
+        }
+        match (&config.access_key_id, &config.secret_access_key) {
+            (Some(access_key_id), Some(secret_access_key)) => {
+                builder = builder.credentials_provider(Credentials::new(
+                    access_key_id,
+                    secret_access_key,
+                    None,
+                    None,
+                    "object-storage-static",
+                ));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(ObjectStoreError::Config(
+                    "An access key ID and secret access key must be given together".to_string(),
+                ))
+            }
+        }
+
+        Ok(Self::new(S3Client::from_conf(builder.build()), &config.bucket).with_encryption(config.encryption.clone()))
+    }
+
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    /// The same client and encryption pointed at another bucket
+    pub fn for_bucket(&self, bucket: &str) -> Self {
+        Self {
+            bucket: bucket.to_string(),
+            ..self.clone()
+        }
+    }
+
+    pub fn client(&self) -> &S3Client {
+        &self.client
+    }
+
+    pub fn encryption(&self) -> &Encryption {
+        &self.encryption
+    }
+
+    pub async fn create_bucket_if_not_exists(&self, region: &str) -> Result<()> {
+        if self.ping().await.is_ok() {
+            tracing::info!("Bucket {} already exists", self.bucket);
+            return Ok(());
+        }
+
+        let mut request = self.client.create_bucket().bucket(&self.bucket);
+        // us-east-1 is the default and rejects an explicit constraint
+        if !region.is_empty() && region != "us-east-1" {
+            request = request.create_bucket_configuration(
+                aws_sdk_s3::types::CreateBucketConfiguration::builder()
+                    .location_constraint(aws_sdk_s3::types::BucketLocationConstraint::from(region))
+                    .build(),
+            );
+        }
+        request.send().await.map_err(backend)?;
+        tracing::info!("Created bucket {}", self.bucket);
+        Ok(())
+    }
+
+    pub async fn enable_versioning(&self) -> Result<()> {
+        self.client
+            .put_bucket_versioning()
+            .bucket(&self.bucket)
+            .versioning_configuration(
+                aws_sdk_s3::types::VersioningConfiguration::builder()
+                    .status(aws_sdk_s3::types::BucketVersioningStatus::Enabled)
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(backend)?;
+        tracing::info!("Enabled versioning for bucket {}", self.bucket);
+        Ok(())
+    }
+
+    fn encrypt(&self, request: PutObjectFluentBuilder) -> PutObjectFluentBuilder {
+        match &self.encryption {
+            Encryption::None => request,
+            Encryption::Aes256 => request.server_side_encryption(ServerSideEncryption::Aes256),
+            Encryption::Kms { key_id } => request
+                .server_side_encryption(ServerSideEncryption::AwsKms)
+                .ssekms_key_id(key_id),
+        }
+    }
+
+    fn presigned(presigned: &aws_sdk_s3::presigning::PresignedRequest, expires_in: Duration) -> PresignedRequest {
+        PresignedRequest {
+            method: presigned.method().to_string(),
+            url: presigned.uri().to_string(),
+            headers: presigned
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            expires_at: SystemTime::now() + expires_in,
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3ObjectStore {
+    fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>, options: &PutOptions) -> Result<()> {
+        let mut request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(body))
+            .set_content_type(options.content_type.clone());
+        if !options.metadata.is_empty() {
+            request = request.set_metadata(Some(options.metadata.clone()));
+        }
+        self.encrypt(request).send().await.map_err(backend)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>> {
+        let output = match self.client.get_object().bucket(&self.bucket).key(key).send().await {
+            Ok(output) => output,
+            Err(e) => {
+                let e = e.into_service_error();
+                if e.is_no_such_key() {
+                    return Ok(None);
+                }
+                return Err(backend(e));
+            }
+        };
+        let metadata = output.metadata().cloned().unwrap_or_default();
+        let body = output.body.collect().await.map_err(backend)?;
+        Ok(Some(StoredObject {
+            body: body.into_bytes().to_vec(),
+            metadata,
+        }))
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ObjectInfo>> {
+        match self.client.head_object().bucket(&self.bucket).key(key).send().await {
+            Ok(output) => Ok(Some(ObjectInfo {
+                key: key.to_string(),
+                size: output.content_length().unwrap_or_default().max(0) as u64,
+                last_modified: output.last_modified().and_then(system_time),
+            })),
+            Err(e) => {
+                let e = e.into_service_error();
+                if e.is_not_found() {
+                    return Ok(None);
+                }
+                Err(backend(e))
+            }
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let output = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(backend)?;
+
+            objects.extend(output.contents().iter().filter_map(|object| {
+                Some(ObjectInfo {
+                    key: object.key()?.to_string(),
+                    size: object.size().unwrap_or_default().max(0) as u64,
+                    last_modified: object.last_modified().and_then(system_time),
+                })
+            }));
+
+            continuation_token = output.next_continuation_token().map(str::to_string);
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        Ok(objects)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+
+    async fn presign_get(&self, key: &str, expires_in: Duration) -> Result<PresignedRequest> {
+        let expires_in = clamp_presign_expiry(expires_in);
+        let presigned = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(PresigningConfig::expires_in(expires_in).map_err(|e| ObjectStoreError::Config(e.to_string()))?)
+            .await
+            .map_err(backend)?;
+        Ok(Self::presigned(&presigned, expires_in))
+    }
+
+    async fn presign_put(&self, key: &str, content_type: &str, expires_in: Duration) -> Result<PresignedRequest> {
+        let expires_in = clamp_presign_expiry(expires_in);
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type);
+        let presigned = self
+            .encrypt(request)
+            .presigned(PresigningConfig::expires_in(expires_in).map_err(|e| ObjectStoreError::Config(e.to_string()))?)
+            .await
+            .map_err(backend)?;
+        Ok(Self::presigned(&presigned, expires_in))
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.client
+            .head_bucket()
+            .bucket(&self.bucket)
+            .send()
+            .await
+            .map_err(backend)?;
+        Ok(())
+    }
+}
+
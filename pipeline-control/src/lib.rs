@@ -0,0 +1,298 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+// Operators pause pipeline stages for a tenant or a source system during
+// incidents, without stopping the services. Pauses live in one Redis hash
+// written by the admin API; each consumer holds a `PauseGate` that rereads
+// the hash every few seconds and checks it before taking on work.
+
+/// Document metadata key naming the tenant an ingested document belongs to
+pub const DOCUMENT_TENANT_METADATA_KEY: &str = "tenant_id";
+
+const PAUSES_KEY: &str = "pipeline_pauses";
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineControlError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Unknown pipeline stage: {0}")]
+    UnknownStage(String),
+}
+
+impl From<PipelineControlError> for spec_to_proof_error::Error {
+    fn from(error: PipelineControlError) -> Self {
+        use spec_to_proof_error::Error;
+        match error {
+            PipelineControlError::Redis(_) => Error::transient(error),
+            PipelineControlError::Serialization(_) => Error::internal(error),
+            PipelineControlError::UnknownStage(_) => Error::invalid_input(error),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, PipelineControlError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Connectors polling source systems
+    Ingestion,
+    /// The NLP consumer extracting invariants from ingested documents
+    Extraction,
+    /// The proof service compiling invariants and dispatching proofs
+    Proving,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Ingestion => "ingestion",
+            Stage::Extraction => "extraction",
+            Stage::Proving => "proving",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Stage {
+    type Err = PipelineControlError;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text {
+            "ingestion" => Ok(Stage::Ingestion),
+            "extraction" => Ok(Stage::Extraction),
+            "proving" => Ok(Stage::Proving),
+            other => Err(PipelineControlError::UnknownStage(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseScope {
+    Tenant(String),
+    SourceSystem(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pause {
+    pub stage: Stage,
+    pub scope: PauseScope,
+    #[serde(default)]
+    pub reason: String,
+    pub paused_by: String,
+    pub paused_at: DateTime<Utc>,
+}
+
+impl Pause {
+    /// Whether this pause holds back work of `stage` for the given tenant or
+    /// source system; work that names neither is never paused
+    pub fn applies_to(&self, stage: Stage, tenant_id: Option<&str>, source_system: Option<&str>) -> bool {
+        self.stage == stage
+            && match &self.scope {
+                PauseScope::Tenant(id) => tenant_id == Some(id.as_str()),
+                PauseScope::SourceSystem(name) => source_system == Some(name.as_str()),
+            }
+    }
+}
+
+fn pause_field(stage: Stage, scope: &PauseScope) -> String {
+    match scope {
+        PauseScope::Tenant(id) => format!("{}:tenant:{}", stage, id),
+        PauseScope::SourceSystem(name) => format!("{}:source_system:{}", stage, name),
+    }
+}
+
+/// Active pauses in Redis, one hash field per stage and scope
+#[derive(Debug, Clone)]
+pub struct PauseStore {
+    redis_client: redis::Client,
+}
+
+impl PauseStore {
+    pub fn new(redis_client: redis::Client) -> Self {
+        Self { redis_client }
+    }
+
+    pub fn connect(redis_url: &str) -> Result<Self> {
+        Ok(Self::new(redis::Client::open(redis_url)?))
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        redis::cmd("PING").query_async::<_, String>(&mut conn).await?;
+        Ok(())
+    }
+
+    /// Pausing an already paused stage replaces its reason and author
+    pub async fn pause(&self, pause: &Pause) -> Result<()> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let _: () = conn
+            .hset(PAUSES_KEY, pause_field(pause.stage, &pause.scope), serde_json::to_string(pause)?)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the pause that was lifted, if the stage was paused
+    pub async fn resume(&self, stage: Stage, scope: &PauseScope) -> Result<Option<Pause>> {
+        let field = pause_field(stage, scope);
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let raw: Option<String> = conn.hget(PAUSES_KEY, &field).await?;
+        let _: () = conn.hdel(PAUSES_KEY, &field).await?;
+        Ok(raw.and_then(|raw| serde_json::from_str(&raw).ok()))
+    }
+
+    pub async fn list(&self) -> Result<Vec<Pause>> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let raw: Vec<String> = conn.hvals(PAUSES_KEY).await?;
+        // An unreadable entry is skipped rather than pausing everything
+        let mut pauses: Vec<Pause> = raw
+            .iter()
+            .filter_map(|raw| serde_json::from_str(raw).ok())
+            .collect();
+        pauses.sort_by_key(|pause| pause.paused_at);
+        Ok(pauses)
+    }
+}
+
+/// A consumer's view of the active pauses, reread from the store at most
+/// once per refresh interval. When Redis cannot be read the last known
+/// pauses stay in force, and until the first read nothing is paused.
+#[derive(Debug)]
+pub struct PauseGate {
+    store: Option<PauseStore>,
+    refresh_interval: Duration,
+    snapshot: Mutex<(Option<Instant>, Vec<Pause>)>,
+}
+
+impl PauseGate {
+    pub fn new(store: PauseStore) -> Self {
+        Self {
+            store: Some(store),
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            snapshot: Mutex::new((None, Vec::new())),
+        }
+    }
+
+    pub fn connect(redis_url: &str) -> Result<Self> {
+        Ok(Self::new(PauseStore::connect(redis_url)?))
+    }
+
+    /// A gate that never pauses, for deployments without pipeline control
+    pub fn disabled() -> Self {
+        Self::from_pauses(Vec::new())
+    }
+
+    /// A gate fixed to `pauses`
+    pub fn from_pauses(pauses: Vec<Pause>) -> Self {
+        Self {
+            store: None,
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            snapshot: Mutex::new((Some(Instant::now()), pauses)),
+        }
+    }
+
+    pub fn with_refresh_interval(mut self, refresh_interval: Duration) -> Self {
+        self.refresh_interval = refresh_interval;
+        self
+    }
+
+    /// The pause holding back `stage` for this tenant or source system, if
+    /// any
+    pub async fn check(&self, stage: Stage, tenant_id: Option<&str>, source_system: Option<&str>) -> Option<Pause> {
+        self.refresh().await;
+        let snapshot = self.snapshot.lock().unwrap();
+        snapshot
+            .1
+            .iter()
+            .find(|pause| pause.applies_to(stage, tenant_id, source_system))
+            .cloned()
+    }
+
+    async fn refresh(&self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let stale = match self.snapshot.lock().unwrap().0 {
+            Some(read_at) => read_at.elapsed() >= self.refresh_interval,
+            None => true,
+        };
+        if !stale {
+            return;
+        }
+
+        match store.list().await {
+            Ok(pauses) => *self.snapshot.lock().unwrap() = (Some(Instant::now()), pauses),
+            Err(e) => {
+                warn!("Failed to read pipeline pauses, keeping the last known set: {}", e);
+                // Wait a full interval before asking Redis again
+                self.snapshot.lock().unwrap().0 = Some(Instant::now());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pause(stage: Stage, scope: PauseScope) -> Pause {
+        Pause {
+            stage,
+            scope,
+            reason: "incident".to_string(),
+            paused_by: "oncall".to_string(),
+            paused_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_pause_applies_to_its_stage_and_scope() {
+        let tenant = pause(Stage::Proving, PauseScope::Tenant("acme".to_string()));
+        assert!(tenant.applies_to(Stage::Proving, Some("acme"), Some("jira")));
+        assert!(!tenant.applies_to(Stage::Extraction, Some("acme"), None));
+        assert!(!tenant.applies_to(Stage::Proving, Some("globex"), None));
+        assert!(!tenant.applies_to(Stage::Proving, None, Some("acme")));
+
+        let source = pause(Stage::Ingestion, PauseScope::SourceSystem("jira".to_string()));
+        assert!(source.applies_to(Stage::Ingestion, None, Some("jira")));
+        assert!(!source.applies_to(Stage::Ingestion, Some("jira"), Some("confluence")));
+        assert_eq!(pause_field(source.stage, &source.scope), "ingestion:source_system:jira");
+    }
+
+    #[test]
+    fn test_stage_round_trips_through_text() {
+        for stage in [Stage::Ingestion, Stage::Extraction, Stage::Proving] {
+            assert_eq!(stage.as_str().parse::<Stage>().unwrap(), stage);
+            assert_eq!(serde_json::to_string(&stage).unwrap(), format!("\"{}\"", stage));
+        }
+        assert!("compiling".parse::<Stage>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_gate_checks_its_pauses() {
+        let gate = PauseGate::from_pauses(vec![pause(Stage::Extraction, PauseScope::Tenant("acme".to_string()))]);
+        let found = gate.check(Stage::Extraction, Some("acme"), None).await.unwrap();
+        assert_eq!(found.reason, "incident");
+        assert!(gate.check(Stage::Proving, Some("acme"), None).await.is_none());
+        assert!(PauseGate::disabled().check(Stage::Extraction, Some("acme"), None).await.is_none());
+    }
+}
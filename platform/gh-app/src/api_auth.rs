@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use auth::constant_time_eq;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+use reload::ConfigHandle;
+
+use crate::config::GitHubAppConfig;
+use crate::runtime::RuntimeSettings;
+use crate::secrets::{AppSecrets, SecretHandle};
+
+// Callers present `Authorization: Bearer <credential>`, either a static API
+// key or a GitHub Actions OIDC token. Keys live with the other app secrets,
+// so rotating the secret rotates them; during a rotation both the old and
+// new key can be listed under the same id.
+
+pub const GITHUB_OIDC_ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+// Key id of the legacy `admin_api_token`
+const LEGACY_ADMIN_KEY_ID: &str = "admin";
+const JWKS_TTL: Duration = Duration::from_secs(3600);
+// Unknown key ids refetch the JWKS at most this often
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(60);
+const MAX_TRACKED_CALLERS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    ReadOnly,
+    Admin,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub key: String,
+    pub role: Role,
+    /// Requests per `rate_limit_window`; `rate_limit_requests` when unset
+    #[serde(default)]
+    pub rate_limit_requests: Option<u32>,
+}
+
+impl std::fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKey")
+            .field("id", &self.id)
+            .field("role", &self.role)
+            .field("rate_limit_requests", &self.rate_limit_requests)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OidcSettings {
+    #[serde(default = "default_oidc_issuer")]
+    pub issuer: String,
+    pub audience: String,
+    /// Roles granted to workflows by repository, as `owner/repo` or `owner/*`
+    pub repositories: HashMap<String, Role>,
+    #[serde(default)]
+    pub rate_limit_requests: Option<u32>,
+}
+
+fn default_oidc_issuer() -> String {
+    GITHUB_OIDC_ISSUER.to_string()
+}
+
+/// The authenticated caller, added to the request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    pub id: String,
+    pub role: Role,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiAuthError {
+    #[error("{0}")]
+    Unauthenticated(String),
+
+    #[error("{caller} lacks the {required:?} role")]
+    Forbidden { caller: String, required: Role },
+
+    #[error("Rate limit exceeded")]
+    RateLimited(Duration),
+
+    // Admin routes stay hidden until some credential is configured
+    #[error("Not found")]
+    NotConfigured,
+}
+
+impl IntoResponse for ApiAuthError {
+    fn into_response(self) -> Response {
+        let message = self.to_string();
+        match self {
+            ApiAuthError::Unauthenticated(_) => {
+                (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], message).into_response()
+            }
+            ApiAuthError::Forbidden { .. } => (StatusCode::FORBIDDEN, message).into_response(),
+            ApiAuthError::RateLimited(retry_after) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+                message,
+            )
+                .into_response(),
+            ApiAuthError::NotConfigured => (StatusCode::NOT_FOUND, message).into_response(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OidcClaims {
+    repository: String,
+}
+
+#[derive(Debug)]
+struct OidcVerifier {
+    settings: OidcSettings,
+    http: reqwest::Client,
+    jwks: RwLock<Option<(JwkSet, Instant)>>,
+}
+
+impl OidcVerifier {
+    fn new(settings: OidcSettings) -> Self {
+        Self {
+            settings,
+            http: reqwest::Client::new(),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    async fn verify(&self, token: &str) -> Result<(Caller, Option<u32>), ApiAuthError> {
+        let invalid = |e: jsonwebtoken::errors::Error| ApiAuthError::Unauthenticated(format!("Invalid OIDC token: {}", e));
+        let kid = decode_header(token).map_err(invalid)?.kid
+            .ok_or_else(|| ApiAuthError::Unauthenticated("OIDC token has no key id".to_string()))?;
+        let key = DecodingKey::from_jwk(&self.signing_key(&kid).await?).map_err(invalid)?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.settings.audience]);
+        validation.set_issuer(&[&self.settings.issuer]);
+        let claims = decode::<OidcClaims>(token, &key, &validation).map_err(invalid)?.claims;
+
+        let id = format!("oidc:{}", claims.repository);
+        match role_for_repository(&self.settings.repositories, &claims.repository) {
+            Some(role) => Ok((Caller { id, role }, self.settings.rate_limit_requests)),
+            None => Err(ApiAuthError::Unauthenticated(format!("Repository {} is not allowed", claims.repository))),
+        }
+    }
+
+    async fn signing_key(&self, kid: &str) -> Result<Jwk, ApiAuthError> {
+        if let Some((jwks, fetched_at)) = &*self.jwks.read().await {
+            let age = fetched_at.elapsed();
+            if age < JWKS_TTL {
+                if let Some(jwk) = jwks.find(kid) {
+                    return Ok(jwk.clone());
+                }
+            }
+            if age < JWKS_MIN_REFRESH {
+                return Err(ApiAuthError::Unauthenticated(format!("Unknown OIDC signing key {}", kid)));
+            }
+        }
+
+        // Not cached, or the issuer rotated its signing keys
+        let url = format!("{}/.well-known/jwks", self.settings.issuer.trim_end_matches('/'));
+        let jwks: JwkSet = self.http.get(&url).send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| ApiAuthError::Unauthenticated(format!("Failed to fetch OIDC keys: {}", e)))?
+            .json().await
+            .map_err(|e| ApiAuthError::Unauthenticated(format!("Failed to parse OIDC keys: {}", e)))?;
+        let jwk = jwks.find(kid).cloned();
+        *self.jwks.write().await = Some((jwks, Instant::now()));
+        jwk.ok_or_else(|| ApiAuthError::Unauthenticated(format!("Unknown OIDC signing key {}", kid)))
+    }
+}
+
+// Exact repositories take precedence over owner wildcards
+fn role_for_repository(repositories: &HashMap<String, Role>, repository: &str) -> Option<Role> {
+    repositories.get(repository).copied().or_else(|| {
+        let owner = repository.split_once('/')?.0;
+        repositories.get(&format!("{}/*", owner)).copied()
+    })
+}
+
+// The legacy admin token acts as an admin key
+fn authenticate_key(secrets: &AppSecrets, presented: &str) -> Option<(Caller, Option<u32>)> {
+    let legacy = (!secrets.admin_api_token.is_empty()).then(|| ApiKey {
+        id: LEGACY_ADMIN_KEY_ID.to_string(),
+        key: secrets.admin_api_token.clone(),
+        role: Role::Admin,
+        rate_limit_requests: None,
+    });
+
+    // Compare against every key so timing does not reveal which matched
+    let mut matched = None;
+    for key in secrets.api_keys.iter().chain(legacy.as_ref()) {
+        if constant_time_eq(presented.as_bytes(), key.key.as_bytes()) {
+            matched = Some(key);
+        }
+    }
+    matched.map(|key| (Caller { id: key.id.clone(), role: key.role }, key.rate_limit_requests))
+}
+
+// OIDC tokens are JWTs; API keys never contain dots
+fn looks_like_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+/// Fixed-window request counts per caller id
+#[derive(Debug)]
+pub struct CallerRateLimiter {
+    window: Duration,
+    callers: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl CallerRateLimiter {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            callers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a request, returning how long to wait if the caller is over `limit`
+    pub async fn check(&self, caller: &str, limit: u32) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut callers = self.callers.lock().await;
+
+        if callers.len() >= MAX_TRACKED_CALLERS {
+            let window = self.window;
+            callers.retain(|_, (started, _)| now.duration_since(*started) < window);
+        }
+
+        let (started, count) = callers.entry(caller.to_string()).or_insert((now, 0));
+        if now.duration_since(*started) >= self.window {
+            *started = now;
+            *count = 0;
+        }
+
+        if *count >= limit {
+            return Err(self.window.saturating_sub(now.duration_since(*started)));
+        }
+
+        *count += 1;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ApiAuthenticator {
+    secrets: SecretHandle,
+    oidc: Option<OidcVerifier>,
+    limiter: CallerRateLimiter,
+    default_rate_limit: u32,
+    runtime: Option<ConfigHandle<RuntimeSettings>>,
+}
+
+impl ApiAuthenticator {
+    pub fn new(config: &GitHubAppConfig, secrets: SecretHandle) -> Self {
+        Self {
+            secrets,
+            oidc: config.oidc.clone().map(OidcVerifier::new),
+            limiter: CallerRateLimiter::new(Duration::from_secs(config.rate_limit_window)),
+            default_rate_limit: config.rate_limit_requests,
+            runtime: None,
+        }
+    }
+
+    /// Takes the default per-caller limit from `runtime` on every request
+    pub fn with_runtime_settings(mut self, runtime: ConfigHandle<RuntimeSettings>) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    fn default_rate_limit(&self) -> u32 {
+        match &self.runtime {
+            Some(runtime) => runtime.current().rate_limit_requests,
+            None => self.default_rate_limit,
+        }
+    }
+
+    /// Whether any API key or OIDC trust is set up; until then read-only
+    /// routes are open and admin routes hidden
+    pub fn is_configured(&self) -> bool {
+        let secrets = self.secrets.current();
+        self.oidc.is_some() || !secrets.api_keys.is_empty() || !secrets.admin_api_token.is_empty()
+    }
+
+    pub async fn authorize(&self, headers: &HeaderMap, required: Role) -> Result<Caller, ApiAuthError> {
+        if !self.is_configured() {
+            return match required {
+                Role::ReadOnly => Ok(Caller { id: "anonymous".to_string(), role: Role::ReadOnly }),
+                Role::Admin => Err(ApiAuthError::NotConfigured),
+            };
+        }
+
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or_else(|| ApiAuthError::Unauthenticated("A bearer API key or OIDC token is required".to_string()))?;
+
+        let (caller, rate_limit) = match &self.oidc {
+            Some(oidc) if looks_like_jwt(presented) => oidc.verify(presented).await?,
+            _ => authenticate_key(&self.secrets.current(), presented)
+                .ok_or_else(|| ApiAuthError::Unauthenticated("Invalid API key".to_string()))?,
+        };
+
+        if caller.role < required {
+            return Err(ApiAuthError::Forbidden { caller: caller.id, required });
+        }
+
+        self.limiter.check(&caller.id, rate_limit.unwrap_or_else(|| self.default_rate_limit())).await
+            .map_err(ApiAuthError::RateLimited)?;
+        Ok(caller)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: &str, key: &str, role: Role, rate_limit_requests: Option<u32>) -> ApiKey {
+        ApiKey { id: id.to_string(), key: key.to_string(), role, rate_limit_requests }
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    fn authenticator(api_keys: Vec<ApiKey>, admin_api_token: &str) -> ApiAuthenticator {
+        let config = GitHubAppConfig {
+            api_keys,
+            admin_api_token: admin_api_token.to_string(),
+            rate_limit_requests: 100,
+            rate_limit_window: 60,
+            ..Default::default()
+        };
+        ApiAuthenticator::new(&config, SecretHandle::from_config(&config))
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured() {
+        let auth = authenticator(vec![], "");
+        assert!(auth.authorize(&HeaderMap::new(), Role::ReadOnly).await.is_ok());
+        assert!(matches!(
+            auth.authorize(&HeaderMap::new(), Role::Admin).await,
+            Err(ApiAuthError::NotConfigured)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_roles() {
+        let auth = authenticator(vec![key("dashboard", "read-key", Role::ReadOnly, None)], "s3cret");
+
+        assert!(matches!(
+            auth.authorize(&HeaderMap::new(), Role::ReadOnly).await,
+            Err(ApiAuthError::Unauthenticated(_))
+        ));
+        assert!(matches!(
+            auth.authorize(&bearer("wrong"), Role::ReadOnly).await,
+            Err(ApiAuthError::Unauthenticated(_))
+        ));
+
+        let dashboard = auth.authorize(&bearer("read-key"), Role::ReadOnly).await.unwrap();
+        assert_eq!(dashboard, Caller { id: "dashboard".to_string(), role: Role::ReadOnly });
+        assert!(matches!(
+            auth.authorize(&bearer("read-key"), Role::Admin).await,
+            Err(ApiAuthError::Forbidden { .. })
+        ));
+
+        // The legacy admin token can do everything
+        assert_eq!(auth.authorize(&bearer("s3cret"), Role::Admin).await.unwrap().id, "admin");
+        assert!(auth.authorize(&bearer("s3cret"), Role::ReadOnly).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rotation_and_rate_limits() {
+        let auth = authenticator(vec![key("ci", "old-key", Role::Admin, Some(2))], "");
+        assert!(auth.authorize(&bearer("old-key"), Role::Admin).await.is_ok());
+
+        let mut rotated = (*auth.secrets.current()).clone();
+        rotated.api_keys = vec![key("ci", "new-key", Role::Admin, Some(2))];
+        auth.secrets.replace(rotated);
+        assert!(auth.authorize(&bearer("old-key"), Role::Admin).await.is_err());
+
+        // The limit is per key id, so it carries across the rotation
+        assert!(auth.authorize(&bearer("new-key"), Role::Admin).await.is_ok());
+        assert!(matches!(
+            auth.authorize(&bearer("new-key"), Role::Admin).await,
+            Err(ApiAuthError::RateLimited(_))
+        ));
+    }
+
+    #[test]
+    fn test_role_for_repository() {
+        let repositories = HashMap::from([
+            ("fraware/*".to_string(), Role::ReadOnly),
+            ("fraware/spec-to-proof".to_string(), Role::Admin),
+        ]);
+        assert_eq!(role_for_repository(&repositories, "fraware/spec-to-proof"), Some(Role::Admin));
+        assert_eq!(role_for_repository(&repositories, "fraware/other"), Some(Role::ReadOnly));
+        assert_eq!(role_for_repository(&repositories, "someone/spec-to-proof"), None);
+        assert!(looks_like_jwt("a.b.c"));
+        assert!(!looks_like_jwt("plain-api-key"));
+    }
+
+    #[test]
+    fn test_api_key_debug_redacts_key() {
+        let formatted = format!("{:?}", key("ci", "super-secret", Role::Admin, None));
+        assert!(formatted.contains("ci"));
+        assert!(!formatted.contains("super-secret"));
+    }
+}
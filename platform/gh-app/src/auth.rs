@@ -8,10 +8,14 @@ use tracing::{info, warn, error};
 use uuid::Uuid;
 
 use crate::config::GitHubAppConfig;
+use crate::secrets::SecretHandle;
 
 #[derive(Debug, Clone)]
 pub struct JWTManager {
     config: GitHubAppConfig,
+    secrets: SecretHandle,
+    // Generation of the secrets the cached tokens were minted with
+    secrets_generation: u64,
     token_cache: HashMap<String, (String, Instant)>,
     public_key_cache: HashMap<String, (String, Instant)>,
 }
@@ -45,12 +49,35 @@ impl JWTManager {
     pub async fn new(config: &GitHubAppConfig) -> Result<Self> {
         Ok(Self {
             config: config.clone(),
+            secrets: SecretHandle::from_config(config),
+            secrets_generation: 0,
             token_cache: HashMap::new(),
             public_key_cache: HashMap::new(),
         })
     }
     
+    /// Signs and verifies with the shared secrets, picking up rotations
+    pub fn with_secrets(mut self, secrets: SecretHandle) -> Self {
+        self.secrets_generation = secrets.generation();
+        self.secrets = secrets;
+        self.token_cache.clear();
+        self.public_key_cache.clear();
+        self
+    }
+    
+    // Tokens minted before a rotation are signed with the old key
+    fn drop_stale_tokens(&mut self) {
+        let generation = self.secrets.generation();
+        if generation != self.secrets_generation {
+            info!("App secrets rotated, dropping {} cached tokens", self.token_cache.len());
+            self.token_cache.clear();
+            self.public_key_cache.clear();
+            self.secrets_generation = generation;
+        }
+    }
+    
     pub async fn create_app_jwt(&mut self) -> Result<String> {
+        self.drop_stale_tokens();
         let cache_key = "app_jwt".to_string();
         
         // Check cache first
@@ -73,7 +100,7 @@ impl JWTManager {
         let token = encode(
             &Header::new(Algorithm::RS256),
             &payload,
-            &EncodingKey::from_rsa_pem(self.config.private_key.as_bytes())?
+            &EncodingKey::from_rsa_pem(self.secrets.current().private_key.as_bytes())?
         )?;
         
         // Cache the token
@@ -85,6 +112,7 @@ impl JWTManager {
     }
     
     pub async fn create_installation_jwt(&mut self, installation_id: &str) -> Result<String> {
+        self.drop_stale_tokens();
         let cache_key = format!("installation_jwt_{}", installation_id);
         
         // Check cache first
@@ -108,7 +136,7 @@ impl JWTManager {
         let token = encode(
             &Header::new(Algorithm::RS256),
             &payload,
-            &EncodingKey::from_rsa_pem(self.config.private_key.as_bytes())?
+            &EncodingKey::from_rsa_pem(self.secrets.current().private_key.as_bytes())?
         )?;
         
         // Cache the token
@@ -124,7 +152,7 @@ impl JWTManager {
         // In a real implementation, you'd verify against the public key
         let token_data = decode::<JWTPayload>(
             token,
-            &DecodingKey::from_rsa_pem(self.config.private_key.as_bytes())?,
+            &DecodingKey::from_rsa_pem(self.secrets.current().private_key.as_bytes())?,
             &Validation::new(Algorithm::RS256)
         )?;
         
@@ -138,6 +166,7 @@ impl JWTManager {
     }
     
     pub async fn get_installation_token(&mut self, installation_id: &str) -> Result<String> {
+        self.drop_stale_tokens();
         let cache_key = format!("installation_token_{}", installation_id);
         
         // Check cache first
@@ -200,7 +229,7 @@ impl JWTManager {
         let signature_hash = &signature[7..];
         
         // Calculate expected signature
-        let mut mac = Hmac::<Sha256>::new_from_slice(self.config.webhook_secret.as_bytes())
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secrets.current().webhook_secret.as_bytes())
             .context("Failed to create HMAC")?;
         mac.update(payload.as_bytes());
         let expected_hash = hex::encode(mac.finalize().into_bytes());
@@ -218,6 +247,7 @@ impl JWTManager {
     }
     
     pub async fn get_public_key(&mut self) -> Result<String> {
+        self.drop_stale_tokens();
         let cache_key = "public_key".to_string();
         
         // Check cache first
@@ -291,7 +321,7 @@ impl JWTManager {
     
     pub async fn health_check(&self) -> Result<bool> {
         // Check if we can create a JWT token
-        let jwt_manager = JWTManager::new(&self.config).await?;
+        let jwt_manager = JWTManager::new(&self.config).await?.with_secrets(self.secrets.clone());
         let _token = jwt_manager.create_app_jwt().await?;
         
         Ok(true)
@@ -398,4 +428,21 @@ mod tests {
         let result = manager.validate_webhook_signature(payload, signature).await;
         assert!(result.is_ok());
     }
+    
+    #[tokio::test]
+    async fn test_rotation_drops_cached_tokens() {
+        let config = GitHubAppConfig::default();
+        let secrets = SecretHandle::from_config(&config);
+        let mut manager = JWTManager::new(&config).await.unwrap().with_secrets(secrets.clone());
+        manager.token_cache.insert("app_jwt".to_string(), ("old".to_string(), Instant::now()));
+        
+        manager.drop_stale_tokens();
+        assert_eq!(manager.token_cache.len(), 1);
+        
+        let mut rotated = (*secrets.current()).clone();
+        rotated.private_key = "rotated-key".to_string();
+        secrets.replace(rotated);
+        manager.drop_stale_tokens();
+        assert!(manager.token_cache.is_empty());
+    }
 } 
\ No newline at end of file
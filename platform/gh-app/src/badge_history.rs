@@ -0,0 +1,261 @@
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use storage::{repository_or_memory, Entity, EntityQuery, ExpectedVersion, InMemoryRepository, Repository, StorageSettings};
+
+use crate::coverage::CoverageReport;
+use crate::proto::gh_app::v1::*;
+
+const QUERY_PAGE_SIZE: u32 = 100;
+
+/// A change of a pull request's badge: the status it changed to and from,
+/// the coverage and artifacts behind it, and when
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BadgeTransition {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub provider: String,
+    #[prost(string, tag = "3")]
+    pub repository_id: String,
+    #[prost(string, tag = "4")]
+    pub pull_request_id: String,
+    #[prost(string, tag = "5")]
+    pub commit_sha: String,
+    #[prost(int32, tag = "6")]
+    pub status: i32,
+    /// Unspecified for a pull request's first badge
+    #[prost(int32, tag = "7")]
+    pub previous_status: i32,
+    #[prost(string, tag = "8")]
+    pub description: String,
+    #[prost(double, tag = "9")]
+    pub coverage_percentage: f64,
+    #[prost(uint32, tag = "10")]
+    pub invariants_proven: u32,
+    #[prost(uint32, tag = "11")]
+    pub total_invariants: u32,
+    #[prost(string, repeated, tag = "12")]
+    pub artifact_ids: Vec<String>,
+    #[prost(string, tag = "13")]
+    pub target_url: String,
+    #[prost(int64, tag = "14")]
+    pub recorded_at_ms: i64,
+}
+
+impl BadgeTransition {
+    pub fn new(request: &BadgeStatusRequest, response: &BadgeStatusResponse, coverage: Option<&CoverageReport>) -> Self {
+        let recorded_at = Utc::now();
+        Self {
+            id: format!("{}_{}", recorded_at.timestamp_millis(), uuid::Uuid::new_v4()),
+            provider: request.provider.as_str().to_string(),
+            repository_id: request.repository_id.clone(),
+            pull_request_id: request.pull_request_id.clone(),
+            commit_sha: request.commit_sha.clone(),
+            status: response.status.clone() as i32,
+            previous_status: BadgeStatus::Unspecified as i32,
+            description: response.description.clone(),
+            coverage_percentage: coverage.map(|report| report.overall.coverage_percentage).unwrap_or_default(),
+            invariants_proven: coverage.map(|report| report.overall.proven).unwrap_or_default(),
+            total_invariants: coverage.map(|report| report.overall.total).unwrap_or_default(),
+            artifact_ids: response.proof_artifacts.iter().map(|artifact| artifact.artifact_id.clone()).collect(),
+            target_url: response.target_url.clone(),
+            recorded_at_ms: recorded_at.timestamp_millis(),
+        }
+    }
+
+    fn pull_request_key(&self) -> String {
+        pull_request_key(&self.provider, &self.repository_id, &self.pull_request_id)
+    }
+
+    // Same badge for the same commit
+    fn repeats(&self, other: &BadgeTransition) -> bool {
+        self.commit_sha == other.commit_sha && self.status == other.status && self.description == other.description
+    }
+}
+
+impl Entity for BadgeTransition {
+    const KIND: &'static str = "BADGE_TRANSITION";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    // Indexed so a pull request's history is one query
+    fn source_id(&self) -> Option<String> {
+        Some(self.pull_request_key())
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+fn pull_request_key(provider: &str, repository_id: &str, pull_request_id: &str) -> String {
+    format!("{}/{}/{}", provider, repository_id, pull_request_id)
+}
+
+pub(crate) fn status_name(status: i32) -> &'static str {
+    match status {
+        1 => "pending",
+        2 => "success",
+        3 => "failure",
+        4 => "error",
+        _ => "unspecified",
+    }
+}
+
+/// A transition as served by the history endpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BadgeHistoryEntry {
+    pub commit_sha: String,
+    pub status: &'static str,
+    pub previous_status: Option<&'static str>,
+    pub description: String,
+    pub coverage_percentage: f64,
+    pub invariants_proven: u32,
+    pub total_invariants: u32,
+    pub artifact_ids: Vec<String>,
+    pub target_url: String,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl From<BadgeTransition> for BadgeHistoryEntry {
+    fn from(transition: BadgeTransition) -> Self {
+        Self {
+            status: status_name(transition.status),
+            previous_status: (transition.previous_status != BadgeStatus::Unspecified as i32)
+                .then(|| status_name(transition.previous_status)),
+            commit_sha: transition.commit_sha,
+            description: transition.description,
+            coverage_percentage: transition.coverage_percentage,
+            invariants_proven: transition.invariants_proven,
+            total_invariants: transition.total_invariants,
+            artifact_ids: transition.artifact_ids,
+            target_url: transition.target_url,
+            recorded_at: Utc.timestamp_millis_opt(transition.recorded_at_ms).single().unwrap_or_default(),
+        }
+    }
+}
+
+/// Every badge a pull request has shown, so teams can audit when and why
+/// it flipped
+pub struct BadgeHistory {
+    repository: Arc<dyn Repository<BadgeTransition>>,
+}
+
+impl std::fmt::Debug for BadgeHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BadgeHistory").finish_non_exhaustive()
+    }
+}
+
+impl BadgeHistory {
+    pub fn new(repository: Arc<dyn Repository<BadgeTransition>>) -> Self {
+        Self { repository }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(Arc::new(InMemoryRepository::new()))
+    }
+
+    /// Without `badge_history_storage` a pull request's history starts over
+    /// whenever the app restarts
+    pub async fn from_settings(settings: Option<&StorageSettings>) -> Result<Self> {
+        Ok(Self::new(repository_or_memory(settings).await?))
+    }
+
+    /// Stores `transition` after the pull request's latest one, unless it
+    /// repeats that badge for the same commit. Returns whether it was stored.
+    pub async fn record(&self, mut transition: BadgeTransition) -> Result<bool> {
+        let history = self.load(&transition.pull_request_key()).await?;
+        if let Some(latest) = history.last() {
+            if transition.repeats(latest) {
+                return Ok(false);
+            }
+            transition.previous_status = latest.status;
+        }
+        self.repository.put(&transition, ExpectedVersion::Absent).await?;
+        Ok(true)
+    }
+
+    /// A pull request's transitions, oldest first, optionally only those
+    /// for one commit
+    pub async fn for_pull_request(
+        &self,
+        provider: ScmKind,
+        repository_id: &str,
+        pull_request_id: &str,
+        commit_sha: Option<&str>,
+    ) -> Result<Vec<BadgeTransition>> {
+        let mut history = self.load(&pull_request_key(provider.as_str(), repository_id, pull_request_id)).await?;
+        if let Some(commit_sha) = commit_sha {
+            history.retain(|transition| transition.commit_sha == commit_sha);
+        }
+        Ok(history)
+    }
+
+    async fn load(&self, key: &str) -> Result<Vec<BadgeTransition>> {
+        let query = EntityQuery::BySource(key.to_string());
+        let mut history = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let page = self.repository.query(&query, page_token.as_deref(), QUERY_PAGE_SIZE).await?;
+            history.extend(page.items.into_iter().map(|stored| stored.entity));
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => break,
+            }
+        }
+        history.sort_by(|a, b| (a.recorded_at_ms, &a.id).cmp(&(b.recorded_at_ms, &b.id)));
+        Ok(history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transition(commit_sha: &str, status: BadgeStatus, description: &str, recorded_at_ms: i64) -> BadgeTransition {
+        BadgeTransition {
+            id: format!("{}_{}", recorded_at_ms, commit_sha),
+            provider: "github".to_string(),
+            repository_id: "acme/payments".to_string(),
+            pull_request_id: "42".to_string(),
+            commit_sha: commit_sha.to_string(),
+            status: status as i32,
+            description: description.to_string(),
+            recorded_at_ms,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_only_transitions() {
+        let history = BadgeHistory::in_memory();
+        assert!(history.record(transition("abc", BadgeStatus::Pending, "2/4 proven", 1)).await.unwrap());
+        assert!(!history.record(transition("abc", BadgeStatus::Pending, "2/4 proven", 2)).await.unwrap());
+        assert!(history.record(transition("abc", BadgeStatus::Failure, "3/4 proven, 1 failed", 3)).await.unwrap());
+        assert!(history.record(transition("def", BadgeStatus::Success, "4/4 proven", 4)).await.unwrap());
+
+        let all = history.for_pull_request(ScmKind::GitHub, "acme/payments", "42", None).await.unwrap();
+        let statuses: Vec<_> = all.iter().map(|t| (t.previous_status, t.status)).collect();
+        assert_eq!(statuses, vec![
+            (BadgeStatus::Unspecified as i32, BadgeStatus::Pending as i32),
+            (BadgeStatus::Pending as i32, BadgeStatus::Failure as i32),
+            (BadgeStatus::Failure as i32, BadgeStatus::Success as i32),
+        ]);
+
+        let for_commit = history.for_pull_request(ScmKind::GitHub, "acme/payments", "42", Some("abc")).await.unwrap();
+        assert_eq!(for_commit.len(), 2);
+        let entry = BadgeHistoryEntry::from(for_commit[1].clone());
+        assert_eq!((entry.previous_status, entry.status), (Some("pending"), "failure"));
+
+        assert!(history.for_pull_request(ScmKind::GitLab, "acme/payments", "42", None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{info, warn, error};
+
+use crate::badge::BadgeManager;
+use crate::badge_history::BadgeHistory;
+use crate::proof_status::ProofStatusStore;
+use crate::config::GitHubAppConfig;
+use crate::coverage::CoverageService;
+use crate::events::{PipelineEvent, PipelineEvents};
+use crate::installations::InstallationRegistry;
+use crate::pr_comment::PrCommentReporter;
+use crate::provenance::ProvenanceAttestor;
+use crate::repo_config::RepoConfigStore;
+use crate::secrets::SecretHandle;
+use crate::proto::gh_app::v1::{BadgeStatusRequest, BadgeStatusResponse, ScmKind};
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+// Finished jobs stay queryable for this long before being pruned
+const FINISHED_JOB_RETENTION: chrono::Duration = chrono::Duration::hours(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BadgeJobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl BadgeJobState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, BadgeJobState::Succeeded | BadgeJobState::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BadgeJob {
+    pub id: String,
+    pub state: BadgeJobState,
+    pub request: BadgeStatusRequest,
+    pub attempts: u32,
+    pub result: Option<BadgeStatusResponse>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Returned to callers of the badge endpoints once a job is queued
+#[derive(Debug, Clone, Serialize)]
+pub struct BadgeJobAccepted {
+    pub job_id: String,
+    pub status_url: String,
+}
+
+impl BadgeJobAccepted {
+    pub fn new(job_id: String) -> Self {
+        let status_url = format!("/badge/jobs/{}", job_id);
+        Self { job_id, status_url }
+    }
+}
+
+/// Performs the actual badge update for a job. Each worker owns its own
+/// updater, so implementations are free to keep per-worker caches.
+#[async_trait::async_trait]
+pub trait BadgeUpdater: Send {
+    async fn update(&mut self, request: &BadgeStatusRequest) -> Result<BadgeStatusResponse>;
+}
+
+#[async_trait::async_trait]
+impl BadgeUpdater for BadgeManager {
+    async fn update(&mut self, request: &BadgeStatusRequest) -> Result<BadgeStatusResponse> {
+        self.update_badge_status(request.clone()).await
+    }
+}
+
+fn request_key(provider: ScmKind, repository_id: &str, pull_request_id: &str) -> String {
+    format!("{}_{}_{}", provider.as_str(), repository_id, pull_request_id)
+}
+
+pub fn retry_delay(attempt: u32, base: Duration) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    base.saturating_mul(2_u32.pow(exponent)).min(MAX_RETRY_DELAY)
+}
+
+/// In-process queue of badge update jobs. Webhook and HTTP handlers enqueue
+/// jobs and return immediately; a pool of workers performs Sigstore
+/// verification and GitHub status updates with retries.
+pub struct BadgeJobQueue {
+    jobs: RwLock<HashMap<String, BadgeJob>>,
+    // Latest request per pull request, outliving job retention so spec
+    // drift can re-verify badges long after they were set
+    latest_requests: RwLock<HashMap<String, BadgeStatusRequest>>,
+    sender: mpsc::Sender<String>,
+    receiver: Mutex<mpsc::Receiver<String>>,
+    max_attempts: u32,
+    retry_base_delay: Duration,
+    job_timeout: Duration,
+    events: PipelineEvents,
+    metrics: Arc<RwLock<HashMap<String, u64>>>,
+}
+
+impl std::fmt::Debug for BadgeJobQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BadgeJobQueue")
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_base_delay", &self.retry_base_delay)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BadgeJobQueue {
+    pub fn new(config: &GitHubAppConfig, metrics: Arc<RwLock<HashMap<String, u64>>>) -> Self {
+        let (sender, receiver) = mpsc::channel(config.badge_queue_capacity.max(1));
+
+        Self {
+            jobs: RwLock::new(HashMap::new()),
+            latest_requests: RwLock::new(HashMap::new()),
+            sender,
+            receiver: Mutex::new(receiver),
+            max_attempts: config.badge_job_max_attempts.max(1),
+            retry_base_delay: Duration::from_millis(config.badge_retry_base_delay_ms),
+            job_timeout: Duration::from_secs(config.badge_timeout),
+            events: PipelineEvents::new(),
+            metrics,
+        }
+    }
+
+    /// Publishes a `badge_updated` event on `events` for each finished update
+    pub fn with_events(mut self, events: PipelineEvents) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Creates the queue and starts `badge_worker_count` workers, each with
+    /// its own `BadgeManager` sharing the installation registry, secrets,
+    /// coverage, repository configs and provenance attestations
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start(
+        config: &GitHubAppConfig,
+        installations: Arc<InstallationRegistry>,
+        secrets: SecretHandle,
+        coverage: Arc<CoverageService>,
+        provenance: Option<Arc<ProvenanceAttestor>>,
+        pr_comments: Option<Arc<PrCommentReporter>>,
+        history: Arc<BadgeHistory>,
+        proof_statuses: Arc<ProofStatusStore>,
+        repo_configs: Arc<RepoConfigStore>,
+        events: PipelineEvents,
+        metrics: Arc<RwLock<HashMap<String, u64>>>,
+    ) -> Result<Arc<Self>> {
+        let queue = Arc::new(Self::new(config, metrics).with_events(events));
+
+        let mut managers = Vec::with_capacity(config.badge_worker_count);
+        for _ in 0..config.badge_worker_count {
+            let mut manager = BadgeManager::new(config).await?
+                .with_installations(installations.clone())
+                .with_secrets(secrets.clone())
+                .with_coverage(coverage.clone())
+                .with_history(history.clone())
+                .with_proof_statuses(proof_statuses.clone())
+                .with_repo_configs(repo_configs.clone());
+            if let Some(provenance) = &provenance {
+                manager = manager.with_provenance(provenance.clone());
+            }
+            if let Some(pr_comments) = &pr_comments {
+                manager = manager.with_pr_comments(pr_comments.clone());
+            }
+            managers.push(manager);
+        }
+        queue.spawn_workers(managers);
+
+        info!("Started {} badge update workers", config.badge_worker_count);
+        Ok(queue)
+    }
+
+    pub fn spawn_workers<U: BadgeUpdater + 'static>(self: &Arc<Self>, updaters: Vec<U>) -> Vec<JoinHandle<()>> {
+        updaters
+            .into_iter()
+            .map(|mut updater| {
+                let queue = Arc::clone(self);
+                tokio::spawn(async move {
+                    while let Some(job_id) = queue.next_job().await {
+                        queue.run_job(&mut updater, &job_id).await;
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Adds a job and returns its ID. Fails when the queue is full so
+    /// callers can shed load instead of blocking the request path.
+    pub async fn enqueue(&self, request: BadgeStatusRequest) -> Result<String> {
+        let now = Utc::now();
+        let key = request_key(request.provider, &request.repository_id, &request.pull_request_id);
+        self.latest_requests.write().await.insert(key, BadgeStatusRequest { refresh: false, ..request.clone() });
+        let job = BadgeJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            state: BadgeJobState::Queued,
+            request,
+            attempts: 0,
+            result: None,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let job_id = job.id.clone();
+
+        {
+            let mut jobs = self.jobs.write().await;
+            jobs.retain(|_, job| !job.state.is_finished() || now - job.updated_at < FINISHED_JOB_RETENTION);
+            jobs.insert(job_id.clone(), job);
+        }
+
+        if let Err(e) = self.sender.try_send(job_id.clone()) {
+            self.jobs.write().await.remove(&job_id);
+            return Err(anyhow::anyhow!("Badge job queue rejected job: {}", e));
+        }
+
+        self.increment_metric("badge_jobs_enqueued_total").await;
+        Ok(job_id)
+    }
+
+    /// The latest badge request of each pull request referencing the spec
+    /// document
+    pub async fn requests_for_document(&self, spec_document_id: &str) -> Vec<BadgeStatusRequest> {
+        self.latest_requests.read().await
+            .values()
+            .filter(|request| request.spec_document_ids.iter().any(|id| id == spec_document_id))
+            .cloned()
+            .collect()
+    }
+
+    /// The latest badge request of a pull request, e.g. to verify it again
+    /// when checks are re-run from the GitHub UI
+    pub async fn latest_request(&self, provider: ScmKind, repository_id: &str, pull_request_id: &str) -> Option<BadgeStatusRequest> {
+        self.latest_requests.read().await.get(&request_key(provider, repository_id, pull_request_id)).cloned()
+    }
+
+    pub async fn get(&self, job_id: &str) -> Option<BadgeJob> {
+        self.jobs.read().await.get(job_id).cloned()
+    }
+
+    async fn next_job(&self) -> Option<String> {
+        self.receiver.lock().await.recv().await
+    }
+
+    async fn run_job<U: BadgeUpdater>(&self, updater: &mut U, job_id: &str) {
+        let (request, attempt) = {
+            let mut jobs = self.jobs.write().await;
+            let Some(job) = jobs.get_mut(job_id) else {
+                warn!("Badge job {} disappeared before it ran", job_id);
+                return;
+            };
+            job.state = BadgeJobState::Running;
+            job.attempts += 1;
+            job.updated_at = Utc::now();
+            (job.request.clone(), job.attempts)
+        };
+
+        let result = match tokio::time::timeout(self.job_timeout, updater.update(&request)).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::anyhow!("Badge update timed out after {:?}", self.job_timeout)),
+        };
+
+        match result {
+            Ok(response) => {
+                self.increment_metric(&format!("badge_{:?}", response.status)).await;
+                self.increment_metric("badge_total").await;
+                self.events.publish(PipelineEvent::badge_updated(&request, &response));
+                self.finish(job_id, BadgeJobState::Succeeded, Some(response), None).await;
+            }
+            Err(e) if attempt < self.max_attempts => {
+                let delay = retry_delay(attempt, self.retry_base_delay);
+                warn!("Badge job {} failed on attempt {}, retrying in {:?}: {}", job_id, attempt, delay, e);
+                self.finish(job_id, BadgeJobState::Queued, None, Some(e.to_string())).await;
+                self.schedule_retry(job_id, delay);
+            }
+            Err(e) => {
+                error!("Badge job {} failed after {} attempts: {}", job_id, attempt, e);
+                self.increment_metric("badge_jobs_failed_total").await;
+                self.finish(job_id, BadgeJobState::Failed, None, Some(e.to_string())).await;
+            }
+        }
+    }
+
+    async fn finish(
+        &self,
+        job_id: &str,
+        state: BadgeJobState,
+        result: Option<BadgeStatusResponse>,
+        error: Option<String>,
+    ) {
+        if let Some(job) = self.jobs.write().await.get_mut(job_id) {
+            job.state = state;
+            job.result = result;
+            job.last_error = error;
+            job.updated_at = Utc::now();
+        }
+    }
+
+    fn schedule_retry(&self, job_id: &str, delay: Duration) {
+        let sender = self.sender.clone();
+        let job_id = job_id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Err(e) = sender.send(job_id).await {
+                error!("Failed to requeue badge job: {}", e);
+            }
+        });
+    }
+
+    async fn increment_metric(&self, name: &str) {
+        let mut metrics = self.metrics.write().await;
+        *metrics.entry(name.to_string()).or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::gh_app::v1::{BadgeStatus, ScmKind};
+
+    struct FlakyUpdater {
+        failures_left: u32,
+    }
+
+    #[async_trait::async_trait]
+    impl BadgeUpdater for FlakyUpdater {
+        async fn update(&mut self, _request: &BadgeStatusRequest) -> Result<BadgeStatusResponse> {
+            if self.failures_left > 0 {
+                self.failures_left -= 1;
+                return Err(anyhow::anyhow!("GitHub API unavailable"));
+            }
+            Ok(BadgeStatusResponse {
+                status: BadgeStatus::Success,
+                message: "Verified".to_string(),
+                target_url: String::new(),
+                description: String::new(),
+                context: String::new(),
+                proof_artifacts: vec![],
+                sigstore_entries: vec![],
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+        }
+    }
+
+    fn request() -> BadgeStatusRequest {
+        BadgeStatusRequest {
+            repository_id: "123".to_string(),
+            pull_request_id: "456".to_string(),
+            commit_sha: "abc123".to_string(),
+            spec_document_ids: vec!["SPEC-1".to_string()],
+            installation_id: "789".to_string(),
+            app_id: "1".to_string(),
+            provider: ScmKind::GitHub,
+            refresh: false,
+        }
+    }
+
+    fn queue(max_attempts: u32) -> Arc<BadgeJobQueue> {
+        let config = GitHubAppConfig {
+            badge_job_max_attempts: max_attempts,
+            badge_retry_base_delay_ms: 1,
+            ..Default::default()
+        };
+        Arc::new(BadgeJobQueue::new(&config, Arc::new(RwLock::new(HashMap::new()))))
+    }
+
+    async fn wait_until_finished(queue: &BadgeJobQueue, job_id: &str) -> BadgeJob {
+        for _ in 0..200 {
+            let job = queue.get(job_id).await.unwrap();
+            if job.state.is_finished() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("badge job {} did not finish", job_id);
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_and_caps() {
+        let base = Duration::from_secs(1);
+        assert_eq!(retry_delay(1, base), Duration::from_secs(1));
+        assert_eq!(retry_delay(3, base), Duration::from_secs(4));
+        assert_eq!(retry_delay(20, base), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_job_succeeds_after_retries() {
+        let queue = queue(3);
+        queue.spawn_workers(vec![FlakyUpdater { failures_left: 2 }]);
+
+        let job_id = queue.enqueue(request()).await.unwrap();
+        let job = wait_until_finished(&queue, &job_id).await;
+
+        assert_eq!(job.state, BadgeJobState::Succeeded);
+        assert_eq!(job.attempts, 3);
+        assert!(job.result.is_some());
+        assert!(job.last_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_job_fails_after_max_attempts() {
+        let queue = queue(2);
+        queue.spawn_workers(vec![FlakyUpdater { failures_left: 5 }]);
+
+        let job_id = queue.enqueue(request()).await.unwrap();
+        let job = wait_until_finished(&queue, &job_id).await;
+
+        assert_eq!(job.state, BadgeJobState::Failed);
+        assert_eq!(job.attempts, 2);
+        assert_eq!(job.last_error.as_deref(), Some("GitHub API unavailable"));
+    }
+}
@@ -0,0 +1,257 @@
+use std::time::Duration;
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::info;
+
+use crate::config::GitHubAppConfig;
+use crate::proto::gh_app::v1::{BadgeStatus, BadgeStatusRequest};
+use crate::scm::{self, ScmKind, ScmProvider};
+
+/// Header Bitbucket sends the payload's HMAC-SHA256 in, as `sha256=<hex>`
+pub const BITBUCKET_SIGNATURE_HEADER: &str = "X-Hub-Signature";
+pub const BITBUCKET_EVENT_HEADER: &str = "X-Event-Key";
+pub const PULL_REQUEST_CREATED: &str = "pullrequest:created";
+pub const PULL_REQUEST_UPDATED: &str = "pullrequest:updated";
+
+fn default_api_url() -> String {
+    "https://api.bitbucket.org".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitbucketSettings {
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    /// Repository or workspace access token with the `repository` scope
+    pub access_token: String,
+    /// Secret configured on the repository's webhook
+    pub webhook_secret: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct BitbucketClient {
+    http_client: Client,
+    api_url: String,
+    access_token: String,
+    // Bitbucket rejects build statuses without a link
+    default_target_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BuildStatusRequest<'a> {
+    key: &'a str,
+    state: &'a str,
+    name: &'a str,
+    description: &'a str,
+    url: &'a str,
+}
+
+impl BitbucketClient {
+    pub fn new(settings: &BitbucketSettings, config: &GitHubAppConfig) -> Result<Self> {
+        let http_client = Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout))
+            .user_agent("spec-to-proof-gh-app")
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            http_client,
+            api_url: settings.api_url.trim_end_matches('/').to_string(),
+            access_token: settings.access_token.clone(),
+            default_target_url: config.badge_target_url.clone(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ScmProvider for BitbucketClient {
+    fn kind(&self) -> ScmKind {
+        ScmKind::Bitbucket
+    }
+
+    async fn update_commit_status(
+        &mut self,
+        repository: &str,
+        sha: &str,
+        status: BadgeStatus,
+        context: &str,
+        description: &str,
+        target_url: Option<&str>,
+    ) -> Result<()> {
+        let url = format!("{}/2.0/repositories/{}/commit/{}/statuses/build", self.api_url, repository, sha);
+        // Posting again with the same key replaces the status
+        let request = BuildStatusRequest {
+            key: context,
+            state: build_state(status),
+            name: context,
+            description,
+            url: target_url.unwrap_or(&self.default_target_url),
+        };
+
+        let response = self.http_client
+            .post(&url)
+            .bearer_auth(&self.access_token)
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to update Bitbucket build status")?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to update Bitbucket build status: {}", error_text));
+        }
+
+        info!("Updated Bitbucket build status for {}@{}: {:?}", repository, sha, status);
+        Ok(())
+    }
+}
+
+fn build_state(status: BadgeStatus) -> &'static str {
+    match status {
+        BadgeStatus::BadgeStatusPending => "INPROGRESS",
+        BadgeStatus::BadgeStatusSuccess => "SUCCESSFUL",
+        _ => "FAILED",
+    }
+}
+
+/// Checks the `X-Hub-Signature` header against the raw request body
+pub fn verify_signature(payload: &[u8], signature: &str, secret: &str) -> bool {
+    if secret.is_empty() {
+        return false;
+    }
+    let Some(signature) = signature.strip_prefix("sha256=").and_then(|hash| hex::decode(hash).ok()) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(payload);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullRequestEvent {
+    pub pullrequest: BitbucketPullRequest,
+    pub repository: BitbucketRepository,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BitbucketRepository {
+    /// "workspace/repo_slug"
+    pub full_name: String,
+    #[serde(default)]
+    pub uuid: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BitbucketPullRequest {
+    pub id: u64,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub state: String,
+    pub source: BitbucketEndpoint,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BitbucketEndpoint {
+    pub commit: BitbucketCommit,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BitbucketCommit {
+    pub hash: String,
+}
+
+impl PullRequestEvent {
+    /// Spec references in the title and description
+    pub fn spec_references(&self) -> Vec<String> {
+        let mut references = scm::extract_spec_references(&self.pullrequest.title);
+        references.extend(scm::extract_spec_references(&self.pullrequest.description));
+
+        let mut seen = std::collections::HashSet::new();
+        references.retain(|reference| seen.insert(reference.clone()));
+        references
+    }
+
+    /// The badge update for this pull request, if it needs one. Bitbucket
+    /// sends `pullrequest:updated` for any edit, not only new commits;
+    /// the badge cache keeps repeats for the same head cheap.
+    pub fn badge_request(&self, event_key: &str, config: &GitHubAppConfig) -> Option<BadgeStatusRequest> {
+        if event_key != PULL_REQUEST_CREATED && event_key != PULL_REQUEST_UPDATED {
+            return None;
+        }
+        if self.pullrequest.state != "OPEN" {
+            return None;
+        }
+        let spec_document_ids = self.spec_references();
+        if spec_document_ids.is_empty() {
+            return None;
+        }
+        Some(BadgeStatusRequest {
+            repository_id: self.repository.full_name.clone(),
+            pull_request_id: self.pullrequest.id.to_string(),
+            commit_sha: self.pullrequest.source.commit.hash.clone(),
+            spec_document_ids,
+            installation_id: String::new(),
+            app_id: config.app_id.clone(),
+            provider: ScmKind::Bitbucket,
+            refresh: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(state: &str) -> PullRequestEvent {
+        serde_json::from_value(serde_json::json!({
+            "pullrequest": {
+                "id": 44,
+                "title": "Refund window for SPEC-7",
+                "description": "spec: refund-policy\nAlso SPEC-7",
+                "state": state,
+                "source": {"branch": {"name": "refunds"}, "commit": {"hash": "9f8e7d6c"}}
+            },
+            "repository": {"full_name": "acme/payments", "uuid": "{1b2c}"}
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_badge_request_from_pull_request() {
+        let config = GitHubAppConfig::default();
+        let request = event("OPEN").badge_request(PULL_REQUEST_CREATED, &config).unwrap();
+        assert_eq!(request.repository_id, "acme/payments");
+        assert_eq!(request.pull_request_id, "44");
+        assert_eq!(request.commit_sha, "9f8e7d6c");
+        assert_eq!(request.provider, ScmKind::Bitbucket);
+        assert_eq!(request.spec_document_ids, vec!["7", "refund-policy"]);
+    }
+
+    #[test]
+    fn test_only_open_pull_requests_need_verification() {
+        let config = GitHubAppConfig::default();
+        assert!(event("OPEN").badge_request(PULL_REQUEST_UPDATED, &config).is_some());
+        assert!(event("MERGED").badge_request(PULL_REQUEST_UPDATED, &config).is_none());
+        assert!(event("OPEN").badge_request("pullrequest:comment_created", &config).is_none());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let payload = br#"{"pullrequest":{}}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(payload);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_signature(payload, &signature, "s3cret"));
+        assert!(!verify_signature(payload, &signature, "other"));
+        assert!(!verify_signature(b"{}", &signature, "s3cret"));
+        assert!(!verify_signature(payload, signature.trim_start_matches("sha256="), "s3cret"));
+        assert!(!verify_signature(payload, &signature, ""));
+    }
+}
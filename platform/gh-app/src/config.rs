@@ -1,16 +1,28 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use anyhow::{Result, Context};
-use config::{Config, ConfigError, Environment, File};
+use config::{Config, File, FileFormat};
 use tracing::{info, warn};
 
+use crate::api_auth::{ApiKey, OidcSettings};
+use crate::exports::AuditExportSettings;
+use crate::bitbucket::BitbucketSettings;
+use crate::drift::DriftSettings;
+use crate::events::EventStreamSettings;
+use crate::gitlab::GitLabSettings;
+use crate::pr_comment::PrCommentSettings;
+use crate::provenance::ProvenanceSettings;
+use crate::sigstore_bundle::SigstoreTrustRootSettings;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitHubAppConfig {
     // GitHub App settings
     pub app_id: String,
     pub private_key: String,
     pub webhook_secret: String,
+    // Default installation; further installations register via webhooks
     pub installation_id: String,
     pub client_id: String,
     pub client_secret: String,
@@ -33,11 +45,34 @@ pub struct GitHubAppConfig {
     pub badge_context: String,
     pub badge_description: String,
     pub badge_target_url: String,
+    // Per-invariant results table commented on pull requests; off when unset
+    #[serde(default)]
+    pub pr_comments: Option<PrCommentSettings>,
+    // Spec drift events from the nlp service; badges are not re-verified
+    // on drift when unset
+    #[serde(default)]
+    pub drift: Option<DriftSettings>,
+    // Pipeline progress streamed to the UI; only badge updates are
+    // streamed when unset
+    #[serde(default)]
+    pub pipeline_events: Option<EventStreamSettings>,
+    
+    // GitLab merge request integration; the GitLab webhook is disabled
+    // when unset
+    #[serde(default)]
+    pub gitlab: Option<GitLabSettings>,
+    // Bitbucket Cloud pull request integration, likewise
+    #[serde(default)]
+    pub bitbucket: Option<BitbucketSettings>,
     
     // Sigstore settings
     pub sigstore_rekor_url: String,
     pub sigstore_fulcio_url: String,
     pub sigstore_oidc_issuer: String,
+    // Keys of the Rekor and Fulcio at the URLs above, for verifying offline
+    // bundles; required when pointing at a private Sigstore deployment
+    #[serde(default)]
+    pub sigstore_trust_root: Option<SigstoreTrustRootSettings>,
     
     // AWS settings
     pub aws_region: String,
@@ -57,10 +92,92 @@ pub struct GitHubAppConfig {
     pub rate_limit_requests: u32,
     pub rate_limit_window: u64,
     
+    // Public verification widget
+    pub widget_invariant_sets: Vec<String>,
+    pub widget_cache_max_age: u64,
+    pub widget_rate_limit_requests: u32,
+    pub widget_rate_limit_window: u64,
+    pub widget_trust_forwarded_for: bool,
+    
+    // Webhook delivery log; kept in memory when no backend is configured
+    #[serde(default)]
+    pub webhook_delivery_storage: Option<storage::StorageSettings>,
+    
+    // Entity store holding persisted invariants and proof artifacts, used
+    // to compute coverage; coverage is reported from memory when unset
+    #[serde(default)]
+    pub coverage_storage: Option<storage::StorageSettings>,
+    
+    // Badge status transitions per pull request, served as badge history;
+    // kept in memory when no backend is configured
+    #[serde(default)]
+    pub badge_history_storage: Option<storage::StorageSettings>,
+    
+    // Latest proof result per commit, served to external CI; kept in
+    // memory when no backend is configured
+    #[serde(default)]
+    pub proof_status_storage: Option<storage::StorageSettings>,
+    
+    // Admin API; disabled while the token is empty
+    #[serde(default)]
+    pub admin_api_token: String,
+    
+    // Bearer credentials for the metrics, badge and admin APIs; keys may
+    // also come from the secret, which rotates them
+    #[serde(default)]
+    pub api_keys: Vec<ApiKey>,
+    #[serde(default)]
+    pub oidc: Option<OidcSettings>,
+    
+    // Append-only audit trail of admin actions; kept in memory when unset
+    #[serde(default)]
+    pub audit_log_path: Option<PathBuf>,
+    
+    // Signed audit bundles per pull request; the export API is disabled
+    // when unset
+    #[serde(default)]
+    pub audit_export: Option<AuditExportSettings>,
+    
+    // KMS key signing SLSA provenance for proof artifacts; the provenance
+    // API is disabled when unset
+    #[serde(default)]
+    pub provenance: Option<ProvenanceSettings>,
+    
+    // Redis holding tenant budgets and spend, shared with the LLM services;
+    // the tenant usage API is disabled when unset
+    #[serde(default)]
+    pub cost_governance_redis_url: Option<String>,
+    
+    // Redis holding pipeline stage pauses, read by the ingestion, nlp and
+    // proof consumers; the pause API is disabled when unset
+    #[serde(default)]
+    pub pipeline_control_redis_url: Option<String>,
+    
+    // Background badge update workers
+    pub badge_worker_count: usize,
+    pub badge_queue_capacity: usize,
+    pub badge_job_max_attempts: u32,
+    pub badge_retry_base_delay_ms: u64,
+    
     // Timeouts
     pub request_timeout: u64,
     pub webhook_timeout: u64,
     pub badge_timeout: u64,
+    // Per-dependency timeout for readiness checks
+    #[serde(default = "default_health_check_timeout_ms")]
+    pub health_check_timeout_ms: u64,
+    // How often the Secrets Manager secret is checked for a rotated version;
+    // 0 disables hot reload
+    #[serde(default = "default_secrets_rotation_interval_secs")]
+    pub secrets_rotation_interval_secs: u64,
+}
+
+fn default_health_check_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_secrets_rotation_interval_secs() -> u64 {
+    300
 }
 
 impl Default for GitHubAppConfig {
@@ -77,11 +194,15 @@ impl Default for GitHubAppConfig {
                 "statuses".to_string(),
                 "contents".to_string(),
                 "metadata".to_string(),
+                "checks".to_string(),
+                "actions".to_string(),
             ],
             events: vec![
                 "pull_request".to_string(),
                 "push".to_string(),
                 "status".to_string(),
+                "check_suite".to_string(),
+                "workflow_run".to_string(),
             ],
             base_url: "https://api.github.com".to_string(),
             upload_url: "https://uploads.github.com".to_string(),
@@ -92,9 +213,15 @@ impl Default for GitHubAppConfig {
             badge_context: "spec-to-proof/verification".to_string(),
             badge_description: "Spec-to-Proof verification status".to_string(),
             badge_target_url: "https://spec-to-proof.com/verification".to_string(),
+            pr_comments: None,
+            drift: None,
+            pipeline_events: None,
+            gitlab: None,
+            bitbucket: None,
             sigstore_rekor_url: "https://rekor.sigstore.dev".to_string(),
             sigstore_fulcio_url: "https://fulcio.sigstore.dev".to_string(),
             sigstore_oidc_issuer: "https://oauth2.sigstore.dev/auth".to_string(),
+            sigstore_trust_root: None,
             aws_region: "us-east-1".to_string(),
             aws_secrets_arn: "".to_string(),
             aws_s3_bucket: "spec-to-proof-artifacts".to_string(),
@@ -105,36 +232,233 @@ impl Default for GitHubAppConfig {
             enable_sigstore_verification: true,
             rate_limit_requests: 1000,
             rate_limit_window: 3600,
+            widget_invariant_sets: vec![],
+            widget_cache_max_age: 300,
+            widget_rate_limit_requests: 60,
+            widget_rate_limit_window: 60,
+            widget_trust_forwarded_for: false,
+            webhook_delivery_storage: None,
+            coverage_storage: None,
+            badge_history_storage: None,
+            proof_status_storage: None,
+            admin_api_token: "".to_string(),
+            api_keys: Vec::new(),
+            oidc: None,
+            audit_log_path: None,
+            audit_export: None,
+            provenance: None,
+            cost_governance_redis_url: None,
+            pipeline_control_redis_url: None,
+            badge_worker_count: 4,
+            badge_queue_capacity: 1000,
+            badge_job_max_attempts: 5,
+            badge_retry_base_delay_ms: 1000,
             request_timeout: 30,
             webhook_timeout: 10,
             badge_timeout: 5,
+            health_check_timeout_ms: default_health_check_timeout_ms(),
+            secrets_rotation_interval_secs: default_secrets_rotation_interval_secs(),
         }
     }
 }
 
-impl GitHubAppConfig {
-    pub fn load() -> Result<Self> {
-        let config_path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config".to_string());
-        let environment = env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string());
-        
-        info!("Loading configuration from {} with environment {}", config_path, environment);
-        
-        let config = Config::builder()
-            // Start with default settings
-            .add_source(File::from(PathBuf::from(&config_path).join("default.yaml")).required(false))
-            // Add environment-specific settings
-            .add_source(File::from(PathBuf::from(&config_path).join(format!("{}.yaml", environment))).required(false))
-            // Add local settings
-            .add_source(File::from(PathBuf::from(&config_path).join("local.yaml")).required(false))
-            // Add environment variables with prefix
-            .add_source(Environment::with_prefix("GH_APP").separator("__"))
-            // Add secrets from AWS Secrets Manager
-            .add_source(Environment::with_prefix("GH_APP_SECRETS").separator("__"))
+// Environment variables override any setting as GH_APP__<KEY>, with `__`
+// separating nested keys, e.g. GH_APP__GITLAB__BASE_URL
+const ENV_PREFIX: &str = "GH_APP__";
+const ENV_KEY_SEPARATOR: &str = "__";
+
+// Single-underscore variables that predate GH_APP__<KEY>
+const LEGACY_ENV_KEYS: &[(&str, &str)] = &[
+    ("GH_APP_ID", "app_id"),
+    ("GH_APP_PRIVATE_KEY", "private_key"),
+    ("GH_APP_WEBHOOK_SECRET", "webhook_secret"),
+    ("GH_APP_INSTALLATION_ID", "installation_id"),
+    ("GH_APP_HOST", "host"),
+    ("GH_APP_PORT", "port"),
+    ("GH_APP_LOG_LEVEL", "log_level"),
+];
+
+/// Command line flags, the last layer applied over the configuration file
+/// and environment
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub log_level: Option<String>,
+    pub log_format: Option<String>,
+}
+
+/// The merged settings as JSON, remembering which layer last set each
+/// top-level key so errors can name it
+struct ConfigLayers {
+    defaults: Value,
+    merged: Value,
+    origins: Vec<(String, String)>,
+}
+
+impl ConfigLayers {
+    fn new(defaults: Value) -> Self {
+        Self {
+            merged: defaults.clone(),
+            defaults,
+            origins: Vec::new(),
+        }
+    }
+
+    fn set(&mut self, path: &[String], value: Value, origin: &str) -> Result<()> {
+        let mut target = &mut self.merged;
+        let mut schema = Some(&self.defaults);
+        for (depth, key) in path.iter().enumerate() {
+            schema = child_schema(schema, key)
+                .map_err(|_| unknown_key(&path[..=depth], origin))?;
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+            target = target
+                .as_object_mut()
+                .expect("target was just made an object")
+                .entry(key.clone())
+                .or_insert(Value::Null);
+        }
+        merge(target, schema, path.to_vec(), value, origin)?;
+
+        let top = path[0].clone();
+        self.origins.retain(|(key, _)| *key != top);
+        self.origins.push((top, origin.to_string()));
+        Ok(())
+    }
+
+    fn apply_file(&mut self, path: &Path) -> Result<()> {
+        let origin = path.display().to_string();
+        let contents = Config::builder()
+            .add_source(File::from(path).format(FileFormat::Yaml))
             .build()
-            .context("Failed to build configuration")?;
-        
-        let mut app_config: GitHubAppConfig = config.try_deserialize()
-            .context("Failed to deserialize configuration")?;
+            .and_then(|file| file.try_deserialize::<Value>())
+            .with_context(|| format!("Failed to read configuration file {}", origin))?;
+
+        match contents {
+            Value::Object(settings) => {
+                for (key, value) in settings {
+                    self.set(&[key], value, &origin)?;
+                }
+                Ok(())
+            }
+            Value::Null => Ok(()),
+            _ => Err(anyhow::anyhow!("{} must contain a mapping of settings", origin)),
+        }
+    }
+
+    fn apply_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+        let mut vars: Vec<(String, String)> = vars.into_iter().collect();
+        vars.sort();
+        for (name, raw) in vars {
+            let path: Vec<String> = if let Some(key) = name.strip_prefix(ENV_PREFIX) {
+                key.split(ENV_KEY_SEPARATOR).map(str::to_lowercase).collect()
+            } else if let Some((_, key)) = LEGACY_ENV_KEYS.iter().find(|(legacy, _)| *legacy == name) {
+                vec![key.to_string()]
+            } else {
+                continue;
+            };
+            let value = env_value(&raw, schema_at(&self.defaults, &path));
+            self.set(&path, value, &format!("environment variable {}", name))?;
+        }
+        Ok(())
+    }
+
+    fn apply_overrides(&mut self, overrides: &ConfigOverrides) -> Result<()> {
+        let flags = [
+            ("host", "--host", overrides.host.clone().map(Value::from)),
+            ("port", "--port", overrides.port.map(Value::from)),
+            ("log_level", "--log-level", overrides.log_level.clone().map(Value::from)),
+            ("log_format", "--log-format", overrides.log_format.clone().map(Value::from)),
+        ];
+        for (key, flag, value) in flags {
+            if let Some(value) = value {
+                self.set(&[key.to_string()], value, flag)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn into_config(self) -> Result<GitHubAppConfig> {
+        if let Ok(config) = serde_json::from_value(self.merged.clone()) {
+            return Ok(config);
+        }
+
+        // Find the offending key by applying each overridden key to the
+        // defaults on its own
+        for (key, origin) in &self.origins {
+            let mut candidate = self.defaults.clone();
+            candidate[key.as_str()] = self.merged[key.as_str()].clone();
+            if let Err(e) = serde_json::from_value::<GitHubAppConfig>(candidate) {
+                return Err(anyhow::anyhow!("Invalid value for `{}` (from {}): {}", key, origin, e));
+            }
+        }
+        serde_json::from_value(self.merged).context("Invalid configuration")
+    }
+}
+
+// The default value below `key`, or an error when the defaults are a
+// mapping without it; `None` once below a setting with no default shape
+fn child_schema<'a>(schema: Option<&'a Value>, key: &str) -> Result<Option<&'a Value>, ()> {
+    match schema {
+        Some(Value::Object(fields)) => fields.get(key).map(Some).ok_or(()),
+        _ => Ok(None),
+    }
+}
+
+fn schema_at<'a>(defaults: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(defaults, |schema, key| schema.get(key))
+}
+
+fn unknown_key(path: &[String], origin: &str) -> anyhow::Error {
+    anyhow::anyhow!("Unknown configuration key `{}` (from {})", path.join("."), origin)
+}
+
+fn merge(target: &mut Value, schema: Option<&Value>, path: Vec<String>, value: Value, origin: &str) -> Result<()> {
+    let Value::Object(fields) = value else {
+        *target = value;
+        return Ok(());
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    for (key, value) in fields {
+        let mut child_path = path.clone();
+        child_path.push(key.clone());
+        let schema = child_schema(schema, &key).map_err(|_| unknown_key(&child_path, origin))?;
+        let child = target
+            .as_object_mut()
+            .expect("target was just made an object")
+            .entry(key)
+            .or_insert(Value::Null);
+        merge(child, schema, child_path, value, origin)?;
+    }
+    Ok(())
+}
+
+// Environment values are strings; read them as the type of the setting's
+// default, and as JSON where the setting has none
+fn env_value(raw: &str, default: Option<&Value>) -> Value {
+    match default {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        Some(Value::Array(_)) if !raw.trim_start().starts_with('[') => raw
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(Value::from)
+            .collect(),
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    }
+}
+
+impl GitHubAppConfig {
+    /// Loads the configuration in layers, each overriding the last: the
+    /// built-in defaults, the YAML file at `path`, `GH_APP__*` environment
+    /// variables and command line flags. Secrets Manager values are applied
+    /// on top when `aws_secrets_arn` is set.
+    pub async fn load(path: Option<&Path>, overrides: &ConfigOverrides) -> Result<Self> {
+        let mut app_config = Self::from_layers(path, env::vars(), overrides)?;
         
         // Load secrets from AWS Secrets Manager if configured
         if !app_config.aws_secrets_arn.is_empty() {
@@ -148,6 +472,23 @@ impl GitHubAppConfig {
         Ok(app_config)
     }
     
+    /// The file, environment and flag layers without secrets or validation
+    pub fn from_layers(
+        path: Option<&Path>,
+        env_vars: impl IntoIterator<Item = (String, String)>,
+        overrides: &ConfigOverrides,
+    ) -> Result<Self> {
+        let defaults = serde_json::to_value(Self::default()).context("Failed to serialize default configuration")?;
+        let mut layers = ConfigLayers::new(defaults);
+        if let Some(path) = path {
+            info!("Loading configuration from {}", path.display());
+            layers.apply_file(path)?;
+        }
+        layers.apply_env(env_vars)?;
+        layers.apply_overrides(overrides)?;
+        layers.into_config()
+    }
+    
     async fn load_secrets_from_aws(&mut self) -> Result<()> {
         use aws_sdk_secretsmanager::Client as SecretsClient;
         use aws_config::BehaviorVersion;
@@ -183,6 +524,13 @@ impl GitHubAppConfig {
             if let Some(client_secret) = secrets.get("client_secret").and_then(|v| v.as_str()) {
                 self.client_secret = client_secret.to_string();
             }
+            if let Some(admin_api_token) = secrets.get("admin_api_token").and_then(|v| v.as_str()) {
+                self.admin_api_token = admin_api_token.to_string();
+            }
+            if let Some(api_keys) = secrets.get("api_keys") {
+                self.api_keys = serde_json::from_value(api_keys.clone())
+                    .context("Failed to parse api_keys")?;
+            }
         }
         
         Ok(())
@@ -200,7 +548,7 @@ impl GitHubAppConfig {
             return Err(anyhow::anyhow!("webhook_secret is required"));
         }
         if self.installation_id.is_empty() {
-            return Err(anyhow::anyhow!("installation_id is required"));
+            info!("No default installation_id configured; installations will be registered from webhooks");
         }
         
         // Validate URLs
@@ -235,6 +583,22 @@ impl GitHubAppConfig {
             return Err(anyhow::anyhow!("badge_timeout must be greater than 0"));
         }
         
+        // Validate badge worker settings
+        if self.badge_worker_count == 0 {
+            return Err(anyhow::anyhow!("badge_worker_count must be greater than 0"));
+        }
+        if self.badge_queue_capacity == 0 {
+            return Err(anyhow::anyhow!("badge_queue_capacity must be greater than 0"));
+        }
+        if self.badge_job_max_attempts == 0 {
+            return Err(anyhow::anyhow!("badge_job_max_attempts must be greater than 0"));
+        }
+        
+        // Validate widget settings
+        if self.widget_rate_limit_window == 0 {
+            return Err(anyhow::anyhow!("widget_rate_limit_window must be greater than 0"));
+        }
+        
         info!("Configuration validation passed");
         Ok(())
     }
@@ -285,6 +649,78 @@ mod tests {
         assert!(config.validate().is_ok());
     }
     
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+    
+    fn yaml_file(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("gh-app-config-{}-{}.yaml", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+    
+    #[test]
+    fn test_layers_override_in_order() {
+        let path = yaml_file("layers", "port: 9000\nbadge_context: acme/proofs\nwidget_cache_max_age: 60\n");
+        let config = GitHubAppConfig::from_layers(
+            Some(&path),
+            env(&[
+                ("GH_APP_ID", "12345"),
+                ("GH_APP__PORT", "9100"),
+                ("GH_APP__WIDGET_INVARIANT_SETS", "payments, refunds"),
+                ("GH_APP_WEBHOOK_URL", "https://example.com/not-a-setting"),
+            ]),
+            &ConfigOverrides {
+                log_level: Some("debug".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(config.app_id, "12345");
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.badge_context, "acme/proofs");
+        assert_eq!(config.widget_cache_max_age, 60);
+        assert_eq!(config.widget_invariant_sets, vec!["payments", "refunds"]);
+        assert_eq!(config.log_level, "debug");
+        // Untouched settings keep their defaults
+        assert_eq!(config.badge_worker_count, GitHubAppConfig::default().badge_worker_count);
+        
+        let flagged = GitHubAppConfig::from_layers(
+            None,
+            env(&[("GH_APP__PORT", "9100")]),
+            &ConfigOverrides {
+                port: Some(7000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(flagged.port, 7000);
+    }
+    
+    #[test]
+    fn test_layer_errors_name_the_offending_key() {
+        let path = yaml_file("unknown", "badge_contxt: acme/proofs\n");
+        let error = GitHubAppConfig::from_layers(Some(&path), env(&[]), &ConfigOverrides::default()).unwrap_err();
+        assert!(error.to_string().contains("`badge_contxt`"), "{}", error);
+        assert!(error.to_string().contains(&path.display().to_string()), "{}", error);
+        std::fs::remove_file(&path).unwrap();
+        
+        let path = yaml_file("type", "badge_worker_count: many\n");
+        let error = GitHubAppConfig::from_layers(Some(&path), env(&[]), &ConfigOverrides::default()).unwrap_err();
+        assert!(error.to_string().contains("`badge_worker_count`"), "{}", error);
+        std::fs::remove_file(&path).unwrap();
+        
+        let error = GitHubAppConfig::from_layers(None, env(&[("GH_APP__PORT", "http")]), &ConfigOverrides::default())
+            .unwrap_err();
+        assert!(error.to_string().contains("`port` (from environment variable GH_APP__PORT)"), "{}", error);
+        
+        let error = GitHubAppConfig::from_layers(None, env(&[("GH_APP__BADGE__TEXT", "x")]), &ConfigOverrides::default())
+            .unwrap_err();
+        assert!(error.to_string().contains("`badge`"), "{}", error);
+    }
+    
     #[test]
     fn test_url_generation() {
         let config = GitHubAppConfig::default();
@@ -0,0 +1,592 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use storage::{Entity, EntityQuery, EntityStore, InMemoryRepository, Repository, StorageSettings};
+use spec_to_proof_proto::BadgeStatusModel;
+
+use crate::proto::gh_app::v1::BadgeStatus;
+
+// spec_to_proof.v1.InvariantStatus values
+const INVARIANT_STATUS_REJECTED: i32 = 3;
+const INVARIANT_STATUS_PROVEN: i32 = 4;
+const INVARIANT_STATUS_STALE: i32 = 6;
+
+// spec_to_proof.v1.ProofStatus values
+const PROOF_STATUS_SUCCESS: i32 = 3;
+const PROOF_STATUS_FAILED: i32 = 4;
+const PROOF_STATUS_TIMEOUT: i32 = 5;
+const PROOF_STATUS_ERROR: i32 = 6;
+
+const QUERY_PAGE_SIZE: u32 = 100;
+
+/// The fields of the nlp service's `StoredInvariant` that coverage needs.
+/// Tags match nlp.proto so persisted entities decode directly; other fields
+/// are skipped.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InvariantRecord {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub document_id: String,
+    #[prost(message, optional, tag = "3")]
+    pub invariant: Option<InvariantFields>,
+    #[prost(int32, tag = "4")]
+    pub status: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InvariantFields {
+    #[prost(string, tag = "1")]
+    pub description: String,
+    #[prost(double, tag = "6")]
+    pub confidence_score: f64,
+    #[prost(string, repeated, tag = "7")]
+    pub tags: Vec<String>,
+    #[prost(int32, tag = "8")]
+    pub priority: i32,
+}
+
+impl InvariantRecord {
+    pub fn priority(&self) -> PriorityTier {
+        PriorityTier::from_i32(self.invariant.as_ref().map(|i| i.priority).unwrap_or_default())
+    }
+}
+
+impl Entity for InvariantRecord {
+    const KIND: &'static str = "INVARIANT";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        (!self.document_id.is_empty()).then(|| self.document_id.clone())
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+/// The fields of `spec_to_proof.v1.ProofArtifact` that coverage needs
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProofArtifactRecord {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "3")]
+    pub theorem_id: String,
+    #[prost(string, tag = "4")]
+    pub invariant_id: String,
+    #[prost(int32, tag = "5")]
+    pub status: i32,
+    #[prost(int64, tag = "7")]
+    pub duration_ms: i64,
+}
+
+impl Entity for ProofArtifactRecord {
+    const KIND: &'static str = "PROOF_ARTIFACT";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        (!self.invariant_id.is_empty()).then(|| self.invariant_id.clone())
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityTier {
+    Critical,
+    High,
+    Medium,
+    Low,
+    #[default]
+    Unspecified,
+}
+
+impl PriorityTier {
+    pub fn from_i32(value: i32) -> Self {
+        match value {
+            4 => PriorityTier::Critical,
+            3 => PriorityTier::High,
+            2 => PriorityTier::Medium,
+            1 => PriorityTier::Low,
+            _ => PriorityTier::Unspecified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoverageCounts {
+    pub total: u32,
+    pub proven: u32,
+    pub failed: u32,
+    pub pending: u32,
+    pub coverage_percentage: f64,
+}
+
+impl CoverageCounts {
+    fn add(&mut self, outcome: InvariantOutcome) {
+        self.total += 1;
+        match outcome {
+            InvariantOutcome::Proven => self.proven += 1,
+            InvariantOutcome::Failed => self.failed += 1,
+            InvariantOutcome::Pending => self.pending += 1,
+        }
+        self.coverage_percentage = percentage(self.proven, self.total);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvariantOutcome {
+    Proven,
+    Failed,
+    Pending,
+}
+
+/// Where one invariant stands, for per-invariant reporting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvariantResult {
+    pub invariant_id: String,
+    pub document_id: String,
+    pub description: String,
+    #[serde(default)]
+    pub priority: PriorityTier,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub outcome: InvariantOutcome,
+    /// Extraction confidence, 0.0 to 1.0
+    pub confidence_score: f64,
+    /// The attempt that decided the outcome, if any
+    pub artifact_id: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub spec_document_ids: Vec<String>,
+    #[serde(flatten)]
+    pub overall: CoverageCounts,
+    /// Only tiers with at least one invariant are present
+    pub tiers: BTreeMap<PriorityTier, CoverageCounts>,
+    /// Invariants excluded because a reviewer rejected them
+    pub rejected: u32,
+    /// Invariants whose spec document changed since they were proven;
+    /// counted as pending until a reviewer confirms or rejects them
+    #[serde(default)]
+    pub stale: u32,
+}
+
+impl CoverageReport {
+    pub fn badge_status(&self) -> BadgeStatus {
+        if self.overall.failed > 0 {
+            BadgeStatus::Failure
+        } else if self.overall.total > 0 && self.overall.proven == self.overall.total {
+            BadgeStatus::Success
+        } else {
+            BadgeStatus::Pending
+        }
+    }
+
+    pub fn description(&self) -> String {
+        if self.overall.total == 0 {
+            return "Spec-to-Proof: no invariants to verify".to_string();
+        }
+
+        let mut description = format!(
+            "Spec-to-Proof: {}/{} invariants proven ({:.1}%)",
+            self.overall.proven, self.overall.total, self.overall.coverage_percentage
+        );
+        if let Some(critical) = self.tiers.get(&PriorityTier::Critical) {
+            description.push_str(&format!(", critical {}/{}", critical.proven, critical.total));
+        }
+        if self.stale > 0 {
+            description.push_str(&format!(", {} stale", self.stale));
+        }
+        description
+    }
+
+    pub fn apply_to(&self, badge: &mut BadgeStatusModel) {
+        badge.coverage_percentage = self.overall.coverage_percentage;
+        badge.invariants_proven = self.overall.proven as i32;
+        badge.total_invariants = self.overall.total as i32;
+    }
+}
+
+fn percentage(proven: u32, total: u32) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        f64::from(proven) * 100.0 / f64::from(total)
+    }
+}
+
+// A successful proof wins over any number of failed attempts; an invariant
+// with only failed attempts counts as failed until a retry succeeds
+fn outcome(invariant: &InvariantRecord, artifacts: &[&ProofArtifactRecord]) -> InvariantOutcome {
+    // Proofs of a stale invariant were for an earlier version of the spec
+    if invariant.status == INVARIANT_STATUS_STALE {
+        return InvariantOutcome::Pending;
+    }
+    if invariant.status == INVARIANT_STATUS_PROVEN
+        || artifacts.iter().any(|a| a.status == PROOF_STATUS_SUCCESS)
+    {
+        InvariantOutcome::Proven
+    } else if artifacts
+        .iter()
+        .any(|a| matches!(a.status, PROOF_STATUS_FAILED | PROOF_STATUS_TIMEOUT | PROOF_STATUS_ERROR))
+    {
+        InvariantOutcome::Failed
+    } else {
+        InvariantOutcome::Pending
+    }
+}
+
+fn group_by_invariant(artifacts: &[ProofArtifactRecord]) -> HashMap<&str, Vec<&ProofArtifactRecord>> {
+    let mut artifacts_by_invariant: HashMap<&str, Vec<&ProofArtifactRecord>> = HashMap::new();
+    for artifact in artifacts {
+        artifacts_by_invariant.entry(artifact.invariant_id.as_str()).or_default().push(artifact);
+    }
+    artifacts_by_invariant
+}
+
+pub fn compute_coverage(
+    spec_document_ids: &[String],
+    invariants: &[InvariantRecord],
+    artifacts: &[ProofArtifactRecord],
+) -> CoverageReport {
+    let artifacts_by_invariant = group_by_invariant(artifacts);
+
+    let mut report = CoverageReport {
+        spec_document_ids: spec_document_ids.to_vec(),
+        ..Default::default()
+    };
+
+    for invariant in invariants {
+        if invariant.status == INVARIANT_STATUS_REJECTED {
+            report.rejected += 1;
+            continue;
+        }
+
+        if invariant.status == INVARIANT_STATUS_STALE {
+            report.stale += 1;
+        }
+
+        let attempts = artifacts_by_invariant.get(invariant.id.as_str()).map(Vec::as_slice).unwrap_or(&[]);
+        let outcome = outcome(invariant, attempts);
+        report.overall.add(outcome);
+        report.tiers.entry(invariant.priority()).or_default().add(outcome);
+    }
+
+    report
+}
+
+/// One result per non-rejected invariant, in input order
+pub fn invariant_results(invariants: &[InvariantRecord], artifacts: &[ProofArtifactRecord]) -> Vec<InvariantResult> {
+    let artifacts_by_invariant = group_by_invariant(artifacts);
+
+    invariants
+        .iter()
+        .filter(|invariant| invariant.status != INVARIANT_STATUS_REJECTED)
+        .map(|invariant| {
+            let attempts = artifacts_by_invariant.get(invariant.id.as_str()).map(Vec::as_slice).unwrap_or(&[]);
+            let outcome = outcome(invariant, attempts);
+            // The successful attempt when there is one, else the latest
+            let decisive = attempts
+                .iter()
+                .find(|a| a.status == PROOF_STATUS_SUCCESS)
+                .or_else(|| attempts.last());
+            let fields = invariant.invariant.clone().unwrap_or_default();
+
+            InvariantResult {
+                invariant_id: invariant.id.clone(),
+                document_id: invariant.document_id.clone(),
+                description: fields.description,
+                priority: invariant.priority(),
+                tags: fields.tags,
+                outcome,
+                confidence_score: fields.confidence_score,
+                artifact_id: decisive.map(|a| a.id.clone()),
+                duration_ms: decisive.map(|a| a.duration_ms).filter(|ms| *ms > 0),
+            }
+        })
+        .collect()
+}
+
+/// Computes proof coverage for spec documents from the invariants and proof
+/// artifacts persisted by the nlp and proof services
+pub struct CoverageService {
+    invariants: Arc<dyn Repository<InvariantRecord>>,
+    artifacts: Arc<dyn Repository<ProofArtifactRecord>>,
+}
+
+impl std::fmt::Debug for CoverageService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CoverageService").finish_non_exhaustive()
+    }
+}
+
+impl CoverageService {
+    pub fn new(
+        invariants: Arc<dyn Repository<InvariantRecord>>,
+        artifacts: Arc<dyn Repository<ProofArtifactRecord>>,
+    ) -> Self {
+        Self { invariants, artifacts }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(Arc::new(InMemoryRepository::new()), Arc::new(InMemoryRepository::new()))
+    }
+
+    /// Readiness check of the store the nlp and proof services write to;
+    /// badges can't reflect new proofs while it is down
+    pub async fn ping(&self) -> Result<()> {
+        self.invariants.ping().await?;
+        Ok(())
+    }
+
+    /// Reads the shared entity store, or process memory when none is set
+    pub async fn from_settings(settings: Option<&StorageSettings>) -> Result<Self> {
+        match settings {
+            Some(settings) => {
+                let store = EntityStore::connect(settings).await?;
+                Ok(Self::new(store.repository(), store.repository()))
+            }
+            None => Ok(Self::in_memory()),
+        }
+    }
+
+    pub async fn compute(&self, spec_document_ids: &[String]) -> Result<CoverageReport> {
+        let (invariants, artifacts) = self.load(spec_document_ids).await?;
+        Ok(compute_coverage(spec_document_ids, &invariants, &artifacts))
+    }
+
+    /// Coverage along with the per-invariant results it was computed from
+    pub async fn compute_detailed(&self, spec_document_ids: &[String]) -> Result<(CoverageReport, Vec<InvariantResult>)> {
+        let (invariants, artifacts) = self.load(spec_document_ids).await?;
+        Ok((
+            compute_coverage(spec_document_ids, &invariants, &artifacts),
+            invariant_results(&invariants, &artifacts),
+        ))
+    }
+
+    async fn load(&self, spec_document_ids: &[String]) -> Result<(Vec<InvariantRecord>, Vec<ProofArtifactRecord>)> {
+        let mut invariants = Vec::new();
+        for document_id in spec_document_ids {
+            invariants.extend(query_all(self.invariants.as_ref(), &EntityQuery::BySource(document_id.clone())).await?);
+        }
+
+        let mut artifacts = Vec::new();
+        for invariant in &invariants {
+            if invariant.status == INVARIANT_STATUS_REJECTED {
+                continue;
+            }
+            artifacts.extend(query_all(self.artifacts.as_ref(), &EntityQuery::BySource(invariant.id.clone())).await?);
+        }
+
+        Ok((invariants, artifacts))
+    }
+}
+
+async fn query_all<E: Entity>(repository: &dyn Repository<E>, query: &EntityQuery) -> Result<Vec<E>> {
+    let mut items = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let page = repository.query(query, page_token.as_deref(), QUERY_PAGE_SIZE).await?;
+        items.extend(page.items.into_iter().map(|stored| stored.entity));
+
+        match page.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => return Ok(items),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use storage::ExpectedVersion;
+
+    fn invariant(id: &str, document_id: &str, priority: i32, status: i32) -> InvariantRecord {
+        InvariantRecord {
+            id: id.to_string(),
+            document_id: document_id.to_string(),
+            invariant: Some(InvariantFields {
+                description: format!("invariant {}", id),
+                confidence_score: 0.9,
+                tags: Vec::new(),
+                priority,
+            }),
+            status,
+        }
+    }
+
+    fn artifact(id: &str, invariant_id: &str, status: i32) -> ProofArtifactRecord {
+        ProofArtifactRecord {
+            id: id.to_string(),
+            theorem_id: format!("thm_{}", invariant_id),
+            invariant_id: invariant_id.to_string(),
+            status,
+            duration_ms: 1500,
+        }
+    }
+
+    #[test]
+    fn test_no_invariants() {
+        let report = compute_coverage(&["DOC-1".to_string()], &[], &[]);
+
+        assert_eq!(report.overall, CoverageCounts::default());
+        assert!(report.tiers.is_empty());
+        assert_eq!(report.badge_status() as i32, BadgeStatus::Pending as i32);
+        assert_eq!(report.description(), "Spec-to-Proof: no invariants to verify");
+    }
+
+    #[test]
+    fn test_rejected_invariants_are_excluded() {
+        let invariants = vec![
+            invariant("inv_1", "DOC-1", 4, 2),
+            invariant("inv_2", "DOC-1", 4, INVARIANT_STATUS_REJECTED),
+        ];
+        let artifacts = vec![
+            artifact("a1", "inv_1", PROOF_STATUS_SUCCESS),
+            artifact("a2", "inv_2", PROOF_STATUS_FAILED),
+        ];
+
+        let report = compute_coverage(&["DOC-1".to_string()], &invariants, &artifacts);
+
+        assert_eq!(report.rejected, 1);
+        assert_eq!(report.overall.total, 1);
+        assert_eq!(report.overall.proven, 1);
+        assert_eq!(report.overall.coverage_percentage, 100.0);
+        assert_eq!(report.badge_status() as i32, BadgeStatus::Success as i32);
+    }
+
+    #[test]
+    fn test_coverage_per_priority_tier() {
+        let invariants = vec![
+            invariant("inv_1", "DOC-1", 4, 2),
+            invariant("inv_2", "DOC-1", 4, 2),
+            invariant("inv_3", "DOC-1", 1, 2),
+            invariant("inv_4", "DOC-1", 0, 2),
+        ];
+        let artifacts = vec![
+            // A retry that succeeded outweighs the earlier timeout
+            artifact("a1", "inv_1", PROOF_STATUS_TIMEOUT),
+            artifact("a2", "inv_1", PROOF_STATUS_SUCCESS),
+            artifact("a3", "inv_2", PROOF_STATUS_FAILED),
+            artifact("a4", "inv_3", PROOF_STATUS_SUCCESS),
+        ];
+
+        let report = compute_coverage(&["DOC-1".to_string()], &invariants, &artifacts);
+
+        assert_eq!(report.overall.total, 4);
+        assert_eq!(report.overall.proven, 2);
+        assert_eq!(report.overall.failed, 1);
+        assert_eq!(report.overall.pending, 1);
+        assert_eq!(report.overall.coverage_percentage, 50.0);
+
+        let critical = &report.tiers[&PriorityTier::Critical];
+        assert_eq!((critical.proven, critical.total), (1, 2));
+        assert_eq!(report.tiers[&PriorityTier::Low].coverage_percentage, 100.0);
+        assert_eq!(report.tiers[&PriorityTier::Unspecified].pending, 1);
+        assert!(!report.tiers.contains_key(&PriorityTier::High));
+
+        assert_eq!(report.badge_status() as i32, BadgeStatus::Failure as i32);
+        assert_eq!(report.description(), "Spec-to-Proof: 2/4 invariants proven (50.0%), critical 1/2");
+    }
+
+    #[test]
+    fn test_stale_invariants_are_pending() {
+        let invariants = vec![
+            invariant("inv_1", "DOC-1", 4, INVARIANT_STATUS_STALE),
+            invariant("inv_2", "DOC-1", 4, 2),
+        ];
+        let artifacts = vec![
+            artifact("a1", "inv_1", PROOF_STATUS_SUCCESS),
+            artifact("a2", "inv_2", PROOF_STATUS_SUCCESS),
+        ];
+
+        let report = compute_coverage(&["DOC-1".to_string()], &invariants, &artifacts);
+
+        assert_eq!(report.stale, 1);
+        assert_eq!((report.overall.proven, report.overall.pending), (1, 1));
+        assert_eq!(report.badge_status() as i32, BadgeStatus::Pending as i32);
+        assert_eq!(report.description(), "Spec-to-Proof: 1/2 invariants proven (50.0%), critical 1/2, 1 stale");
+    }
+
+    #[test]
+    fn test_invariant_results() {
+        let invariants = vec![
+            invariant("inv_1", "DOC-1", 4, 2),
+            invariant("inv_2", "DOC-1", 4, INVARIANT_STATUS_REJECTED),
+            invariant("inv_3", "DOC-1", 1, 2),
+        ];
+        let artifacts = vec![
+            artifact("a1", "inv_1", PROOF_STATUS_SUCCESS),
+            artifact("a2", "inv_1", PROOF_STATUS_TIMEOUT),
+        ];
+
+        let results = invariant_results(&invariants, &artifacts);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].outcome, InvariantOutcome::Proven);
+        assert_eq!(results[0].artifact_id.as_deref(), Some("a1"));
+        assert_eq!(results[0].duration_ms, Some(1500));
+        assert_eq!(results[1].outcome, InvariantOutcome::Pending);
+        assert_eq!(results[1].artifact_id, None);
+    }
+
+    proptest! {
+        #[test]
+        fn test_apply_to_overwrites_counts(mut badge: BadgeStatusModel, proven in 0..50u32, pending in 0..50u32) {
+            let invariants: Vec<_> = (0..proven + pending)
+                .map(|i| invariant(&format!("inv_{}", i), "DOC-1", 3, 2))
+                .collect();
+            let artifacts: Vec<_> = (0..proven)
+                .map(|i| artifact(&format!("a{}", i), &format!("inv_{}", i), PROOF_STATUS_SUCCESS))
+                .collect();
+
+            let report = compute_coverage(&["DOC-1".to_string()], &invariants, &artifacts);
+            let (repo_owner, commit_sha) = (badge.repo_owner.clone(), badge.commit_sha.clone());
+            report.apply_to(&mut badge);
+
+            prop_assert_eq!(badge.invariants_proven, proven as i32);
+            prop_assert_eq!(badge.total_invariants, (proven + pending) as i32);
+            prop_assert!((0.0..=100.0).contains(&badge.coverage_percentage));
+            prop_assert_eq!((badge.repo_owner, badge.commit_sha), (repo_owner, commit_sha));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_service_reads_persisted_entities() {
+        let invariants = Arc::new(InMemoryRepository::<InvariantRecord>::new());
+        let artifacts = Arc::new(InMemoryRepository::<ProofArtifactRecord>::new());
+        invariants.put(&invariant("inv_1", "DOC-1", 3, 2), ExpectedVersion::Absent).await.unwrap();
+        invariants.put(&invariant("inv_2", "DOC-2", 3, 2), ExpectedVersion::Absent).await.unwrap();
+        artifacts.put(&artifact("a1", "inv_1", PROOF_STATUS_SUCCESS), ExpectedVersion::Absent).await.unwrap();
+
+        let service = CoverageService::new(invariants, artifacts);
+        let report = service.compute(&["DOC-1".to_string()]).await.unwrap();
+
+        assert_eq!(report.overall.total, 1);
+        assert_eq!(report.overall.proven, 1);
+    }
+}
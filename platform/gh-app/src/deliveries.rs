@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use anyhow::Result;
+use axum::http::HeaderMap;
+use chrono::Utc;
+use storage::{
+    repository_or_memory, Entity, EntityQuery, ExpectedVersion, InMemoryRepository, Repository, StorageError, StorageSettings,
+};
+
+// Headers worth keeping for replay; anything else GitHub sends is dropped
+const PERSISTED_HEADERS: &[&str] = &[
+    "x-github-event",
+    "x-github-delivery",
+    "x-github-hook-id",
+    "x-github-hook-installation-target-id",
+    "x-github-hook-installation-target-type",
+    "x-hub-signature-256",
+    "content-type",
+    "user-agent",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum DeliveryStatus {
+    Received = 1,
+    Processed = 2,
+    Failed = 3,
+}
+
+impl DeliveryStatus {
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            1 => Some(DeliveryStatus::Received),
+            2 => Some(DeliveryStatus::Processed),
+            3 => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A raw webhook delivery as received from GitHub, kept so it can be
+/// replayed if processing fails
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WebhookDelivery {
+    #[prost(string, tag = "1")]
+    pub delivery_id: String,
+    #[prost(string, tag = "2")]
+    pub event_type: String,
+    #[prost(map = "string, string", tag = "3")]
+    pub headers: HashMap<String, String>,
+    #[prost(string, tag = "4")]
+    pub payload: String,
+    #[prost(int32, tag = "5")]
+    pub status: i32,
+    #[prost(uint32, tag = "6")]
+    pub attempts: u32,
+    #[prost(string, tag = "7")]
+    pub last_error: String,
+    #[prost(int64, tag = "8")]
+    pub received_at: i64,
+}
+
+impl WebhookDelivery {
+    pub fn new(delivery_id: &str, event_type: &str, headers: &HeaderMap, payload: &str) -> Self {
+        let headers = PERSISTED_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = headers.get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+
+        Self {
+            delivery_id: delivery_id.to_string(),
+            event_type: event_type.to_string(),
+            headers,
+            payload: payload.to_string(),
+            status: DeliveryStatus::Received as i32,
+            attempts: 0,
+            last_error: String::new(),
+            received_at: Utc::now().timestamp(),
+        }
+    }
+
+    pub fn delivery_status(&self) -> Option<DeliveryStatus> {
+        DeliveryStatus::from_i32(self.status)
+    }
+
+    pub fn signature(&self) -> &str {
+        self.headers.get("x-hub-signature-256").map(String::as_str).unwrap_or("")
+    }
+}
+
+impl Entity for WebhookDelivery {
+    const KIND: &'static str = "WEBHOOK_DELIVERY";
+
+    fn entity_id(&self) -> String {
+        self.delivery_id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        None
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordOutcome {
+    /// First time this delivery was seen
+    New,
+    /// Seen before but never processed successfully; process it again
+    Retry,
+    /// Already processed; GitHub redelivered it
+    AlreadyProcessed,
+}
+
+/// Persistent log of webhook deliveries keyed by GitHub delivery ID
+pub struct DeliveryLog {
+    repository: Arc<dyn Repository<WebhookDelivery>>,
+}
+
+impl std::fmt::Debug for DeliveryLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeliveryLog").finish_non_exhaustive()
+    }
+}
+
+impl DeliveryLog {
+    pub fn new(repository: Arc<dyn Repository<WebhookDelivery>>) -> Self {
+        Self { repository }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(Arc::new(InMemoryRepository::new()))
+    }
+
+    /// Readiness check; webhooks can't be deduplicated or replayed while
+    /// the log is unreachable
+    pub async fn ping(&self) -> Result<()> {
+        self.repository.ping().await?;
+        Ok(())
+    }
+
+    /// Without `webhook_delivery_storage` deliveries are only deduplicated
+    /// and replayable until the process restarts
+    pub async fn from_settings(settings: Option<&StorageSettings>) -> Result<Self> {
+        Ok(Self::new(repository_or_memory(settings).await?))
+    }
+
+    pub async fn record(&self, delivery: &WebhookDelivery) -> Result<RecordOutcome> {
+        match self.repository.put(delivery, ExpectedVersion::Absent).await {
+            Ok(_) => Ok(RecordOutcome::New),
+            Err(StorageError::VersionConflict { .. }) => {
+                let existing = self.get(&delivery.delivery_id).await?;
+                match existing.and_then(|d| d.delivery_status()) {
+                    Some(DeliveryStatus::Processed) => Ok(RecordOutcome::AlreadyProcessed),
+                    _ => Ok(RecordOutcome::Retry),
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get(&self, delivery_id: &str) -> Result<Option<WebhookDelivery>> {
+        Ok(self.repository.get(delivery_id).await?.map(|stored| stored.entity))
+    }
+
+    pub async fn mark_processed(&self, delivery_id: &str) -> Result<()> {
+        self.finish(delivery_id, DeliveryStatus::Processed, None).await
+    }
+
+    pub async fn mark_failed(&self, delivery_id: &str, error: &str) -> Result<()> {
+        self.finish(delivery_id, DeliveryStatus::Failed, Some(error)).await
+    }
+
+    pub async fn failed(&self, page_token: Option<&str>, limit: u32) -> Result<(Vec<WebhookDelivery>, Option<String>)> {
+        let page = self.repository
+            .query(&EntityQuery::ByStatus(DeliveryStatus::Failed as i32), page_token, limit)
+            .await?;
+        Ok((page.items.into_iter().map(|stored| stored.entity).collect(), page.next_page_token))
+    }
+
+    async fn finish(&self, delivery_id: &str, status: DeliveryStatus, error: Option<&str>) -> Result<()> {
+        let stored = self.repository
+            .get(delivery_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Webhook delivery {} not found", delivery_id))?;
+
+        let mut delivery = stored.entity;
+        delivery.status = status as i32;
+        delivery.attempts += 1;
+        delivery.last_error = error.unwrap_or_default().to_string();
+
+        self.repository.put(&delivery, ExpectedVersion::Exactly(stored.version)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn delivery(id: &str) -> WebhookDelivery {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-GitHub-Event", HeaderValue::from_static("pull_request"));
+        headers.insert("X-Hub-Signature-256", HeaderValue::from_static("sha256=abc"));
+        headers.insert("Authorization", HeaderValue::from_static("Bearer secret"));
+        WebhookDelivery::new(id, "pull_request", &headers, r#"{"action":"opened"}"#)
+    }
+
+    #[test]
+    fn test_only_github_headers_are_persisted() {
+        let delivery = delivery("d-1");
+        assert_eq!(delivery.signature(), "sha256=abc");
+        assert_eq!(delivery.headers.get("x-github-event").map(String::as_str), Some("pull_request"));
+        assert!(!delivery.headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn test_record_is_idempotent_on_delivery_id() {
+        let log = DeliveryLog::in_memory();
+        let d = delivery("d-1");
+
+        assert_eq!(log.record(&d).await.unwrap(), RecordOutcome::New);
+        log.mark_failed("d-1", "GitHub API timeout").await.unwrap();
+        assert_eq!(log.record(&d).await.unwrap(), RecordOutcome::Retry);
+
+        let (failed, _) = log.failed(None, 10).await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].last_error, "GitHub API timeout");
+
+        log.mark_processed("d-1").await.unwrap();
+        assert_eq!(log.record(&d).await.unwrap(), RecordOutcome::AlreadyProcessed);
+        assert_eq!(log.get("d-1").await.unwrap().unwrap().attempts, 2);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::badge_queue::BadgeJobQueue;
+use audit::{actions, AuditEvent, AuditLog};
+
+fn default_subject() -> String {
+    "spec-drift.>".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftSettings {
+    pub nats_url: String,
+    /// Subject the nlp service publishes drift events on
+    #[serde(default = "default_subject")]
+    pub subject: String,
+}
+
+/// The nlp service's drift event: proven invariants of a spec document that
+/// changed or disappeared in a new version of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftEvent {
+    pub document_id: String,
+    #[serde(default)]
+    pub invariants: Vec<DriftedInvariant>,
+    #[serde(default)]
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftedInvariant {
+    pub invariant_id: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: String,
+}
+
+/// Re-verifies the badges of pull requests referencing a drifted spec
+/// document. Their stale invariants count as pending, so the badges move
+/// off green until a reviewer confirms or rejects them.
+pub struct DriftListener {
+    badge_queue: Arc<BadgeJobQueue>,
+    audit_log: Arc<AuditLog>,
+    metrics: Arc<RwLock<HashMap<String, u64>>>,
+}
+
+impl DriftListener {
+    pub fn new(
+        badge_queue: Arc<BadgeJobQueue>,
+        audit_log: Arc<AuditLog>,
+        metrics: Arc<RwLock<HashMap<String, u64>>>,
+    ) -> Self {
+        Self { badge_queue, audit_log, metrics }
+    }
+
+    pub async fn spawn(self, settings: &DriftSettings) -> Result<JoinHandle<()>> {
+        let client = async_nats::connect(&settings.nats_url).await
+            .context("Failed to connect to NATS for drift events")?;
+        let mut subscription = client.subscribe(settings.subject.clone()).await
+            .context("Failed to subscribe to drift events")?;
+        info!("Listening for spec drift events on {}", settings.subject);
+
+        Ok(tokio::spawn(async move {
+            while let Some(message) = subscription.next().await {
+                match serde_json::from_slice::<DriftEvent>(&message.payload) {
+                    Ok(event) => self.handle(&event).await,
+                    Err(e) => warn!("Ignoring malformed drift event on {}: {}", message.subject, e),
+                }
+            }
+            error!("Drift event subscription closed");
+        }))
+    }
+
+    /// Queues a refreshed badge update for every pull request referencing
+    /// the document and returns how many were queued
+    pub async fn handle(&self, event: &DriftEvent) -> usize {
+        info!("{}", event.summary);
+        {
+            let mut metrics = self.metrics.write().await;
+            *metrics.entry("spec_drift_events_total".to_string()).or_insert(0) += 1;
+        }
+
+        let mut queued = 0;
+        for mut request in self.badge_queue.requests_for_document(&event.document_id).await {
+            request.refresh = true;
+            match self.badge_queue.enqueue(request).await {
+                Ok(_) => queued += 1,
+                Err(e) => warn!("Badge re-verification after drift in {} not queued: {}", event.document_id, e),
+            }
+        }
+
+        let audit = AuditEvent::new("nlp", actions::SPEC_DRIFT_DETECTED, &format!("spec_document/{}", event.document_id))
+            .with_after(&serde_json::json!({
+                "invariants": event.invariants,
+                "summary": event.summary,
+                "badges_requeued": queued,
+            }));
+        if let Err(e) = self.audit_log.record(audit).await {
+            error!("Failed to write audit record: {}", e);
+        }
+
+        queued
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GitHubAppConfig;
+    use crate::proto::gh_app::v1::{BadgeStatusRequest, ScmKind};
+
+    fn request(pull_request_id: &str, spec_document_ids: &[&str]) -> BadgeStatusRequest {
+        BadgeStatusRequest {
+            repository_id: "acme/payments".to_string(),
+            pull_request_id: pull_request_id.to_string(),
+            commit_sha: "abc123".to_string(),
+            spec_document_ids: spec_document_ids.iter().map(|id| id.to_string()).collect(),
+            installation_id: "1".to_string(),
+            app_id: "42".to_string(),
+            provider: ScmKind::GitHub,
+            refresh: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drift_requeues_referencing_pull_requests() {
+        let metrics = Arc::new(RwLock::new(HashMap::new()));
+        let queue = Arc::new(BadgeJobQueue::new(&GitHubAppConfig::default(), metrics.clone()));
+        queue.enqueue(request("7", &["DOC-1", "DOC-2"])).await.unwrap();
+        queue.enqueue(request("8", &["DOC-3"])).await.unwrap();
+
+        let listener = DriftListener::new(queue.clone(), Arc::new(AuditLog::open(None).await.unwrap()), metrics.clone());
+        let event: DriftEvent = serde_json::from_value(serde_json::json!({
+            "document_id": "DOC-2",
+            "invariants": [{"invariant_id": "inv_1", "description": "Balance is non-negative", "kind": "removed"}],
+            "summary": "Spec document DOC-2 changed after its invariants were proven: 0 changed, 1 removed",
+            "detected_at": 1700000000
+        })).unwrap();
+
+        assert_eq!(listener.handle(&event).await, 1);
+        assert_eq!(metrics.read().await["spec_drift_events_total"], 1);
+        let requeued = queue.requests_for_document("DOC-2").await;
+        assert_eq!(requeued.len(), 1);
+        assert!(requeued[0].refresh);
+    }
+}
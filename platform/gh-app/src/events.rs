@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::AppState;
+use crate::proto::gh_app::v1::{BadgeStatusRequest, BadgeStatusResponse};
+
+// Events a subscriber may fall behind by before it skips ahead
+const EVENT_BUFFER: usize = 1024;
+
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+fn default_subject() -> String {
+    "pipeline.events.>".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventStreamSettings {
+    pub nats_url: String,
+    /// Subject of the JetStream stream nlp and lean-farm publish pipeline
+    /// progress on
+    #[serde(default = "default_subject")]
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineEventKind {
+    InvariantExtracted,
+    TheoremCompiling,
+    ProofSucceeded,
+    ProofFailed,
+    BadgeUpdated,
+}
+
+impl PipelineEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PipelineEventKind::InvariantExtracted => "invariant_extracted",
+            PipelineEventKind::TheoremCompiling => "theorem_compiling",
+            PipelineEventKind::ProofSucceeded => "proof_succeeded",
+            PipelineEventKind::ProofFailed => "proof_failed",
+            PipelineEventKind::BadgeUpdated => "badge_updated",
+        }
+    }
+}
+
+/// Progress of a spec document through extraction, proving and badging.
+/// Extraction and proof events carry the document; badge events carry the
+/// pull request and every document it references.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineEvent {
+    pub kind: PipelineEventKind,
+    #[serde(default)]
+    pub document_ids: Vec<String>,
+    #[serde(default)]
+    pub repository_id: Option<String>,
+    #[serde(default)]
+    pub pull_request_id: Option<String>,
+    #[serde(default)]
+    pub invariant_id: Option<String>,
+    #[serde(default)]
+    pub message: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl PipelineEvent {
+    pub fn badge_updated(request: &BadgeStatusRequest, response: &BadgeStatusResponse) -> Self {
+        Self {
+            kind: PipelineEventKind::BadgeUpdated,
+            document_ids: request.spec_document_ids.clone(),
+            repository_id: Some(request.repository_id.clone()),
+            pull_request_id: Some(request.pull_request_id.clone()),
+            invariant_id: None,
+            message: response.message.clone(),
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// What a client streams events for: a spec document, or a pull request
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventFilter {
+    pub document_id: Option<String>,
+    pub repository_id: Option<String>,
+    pub pull_request_id: Option<String>,
+}
+
+impl EventFilter {
+    pub fn is_valid(&self) -> bool {
+        self.document_id.is_some() || (self.repository_id.is_some() && self.pull_request_id.is_some())
+    }
+
+    pub fn matches(&self, event: &PipelineEvent) -> bool {
+        if let Some(document_id) = &self.document_id {
+            if !event.document_ids.contains(document_id) {
+                return false;
+            }
+        }
+        if let Some(repository_id) = &self.repository_id {
+            if event.repository_id.as_ref() != Some(repository_id) {
+                return false;
+            }
+        }
+        if let Some(pull_request_id) = &self.pull_request_id {
+            if event.pull_request_id.as_ref() != Some(pull_request_id) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Fans pipeline events out to every connected client. Clients that fall
+/// more than `EVENT_BUFFER` events behind skip the ones they missed.
+#[derive(Debug, Clone)]
+pub struct PipelineEvents {
+    sender: broadcast::Sender<PipelineEvent>,
+}
+
+impl Default for PipelineEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipelineEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+
+    /// Returns how many clients received the event
+    pub fn publish(&self, event: PipelineEvent) -> usize {
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    pub fn subscribe(&self, filter: EventFilter) -> impl Stream<Item = PipelineEvent> {
+        futures::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Pipeline event subscriber lagged, skipped {} events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .filter(move |event| futures::future::ready(filter.matches(event)))
+    }
+
+    /// Forwards events published on the JetStream stream's subject
+    pub async fn spawn_listener(
+        &self,
+        settings: &EventStreamSettings,
+        metrics: Arc<RwLock<HashMap<String, u64>>>,
+    ) -> Result<JoinHandle<()>> {
+        let client = async_nats::connect(&settings.nats_url).await
+            .context("Failed to connect to NATS for pipeline events")?;
+        let mut subscription = client.subscribe(settings.subject.clone()).await
+            .context("Failed to subscribe to pipeline events")?;
+        info!("Streaming pipeline events from {}", settings.subject);
+
+        let events = self.clone();
+        Ok(tokio::spawn(async move {
+            while let Some(message) = subscription.next().await {
+                match serde_json::from_slice::<PipelineEvent>(&message.payload) {
+                    Ok(event) => {
+                        let mut metrics = metrics.write().await;
+                        *metrics.entry(format!("pipeline_events_{}_total", event.kind.as_str())).or_insert(0) += 1;
+                        drop(metrics);
+                        events.publish(event);
+                    }
+                    Err(e) => warn!("Ignoring malformed pipeline event on {}: {}", message.subject, e),
+                }
+            }
+            error!("Pipeline event subscription closed");
+        }))
+    }
+}
+
+/// Server-sent events for one document or pull request, named by kind
+pub async fn stream_events(
+    State(state): State<Arc<AppState>>,
+    Query(filter): Query<EventFilter>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    if !filter.is_valid() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Either document_id or both repository_id and pull_request_id are required".to_string(),
+        ));
+    }
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("pipeline_event_streams_total".to_string()).or_insert(0) += 1;
+    }
+
+    let stream = state.pipeline_events.subscribe(filter).map(|event| {
+        let sse = Event::default()
+            .event(event.kind.as_str())
+            .json_data(&event)
+            .unwrap_or_else(|e| Event::default().comment(format!("unserializable event: {}", e)));
+        Ok(sse)
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: PipelineEventKind, document_id: &str, pull_request_id: Option<&str>) -> PipelineEvent {
+        PipelineEvent {
+            kind,
+            document_ids: vec![document_id.to_string()],
+            repository_id: pull_request_id.map(|_| "acme/payments".to_string()),
+            pull_request_id: pull_request_id.map(str::to_string),
+            invariant_id: None,
+            message: String::new(),
+            occurred_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_filter_requires_document_or_pull_request() {
+        assert!(!EventFilter::default().is_valid());
+        assert!(!EventFilter { repository_id: Some("acme/payments".to_string()), ..Default::default() }.is_valid());
+        assert!(EventFilter { document_id: Some("DOC-1".to_string()), ..Default::default() }.is_valid());
+        assert!(EventFilter {
+            repository_id: Some("acme/payments".to_string()),
+            pull_request_id: Some("7".to_string()),
+            ..Default::default()
+        }.is_valid());
+    }
+
+    #[test]
+    fn test_event_parses_producer_payload() {
+        let event: PipelineEvent = serde_json::from_value(serde_json::json!({
+            "kind": "proof_failed",
+            "document_ids": ["DOC-1"],
+            "invariant_id": "inv_1",
+            "message": "Lean compilation failed",
+            "occurred_at": "2026-01-01T00:00:00Z"
+        })).unwrap();
+        assert_eq!(event.kind, PipelineEventKind::ProofFailed);
+        assert_eq!(event.pull_request_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_only_receive_matching_events() {
+        let events = PipelineEvents::new();
+        let mut stream = Box::pin(events.subscribe(EventFilter {
+            repository_id: Some("acme/payments".to_string()),
+            pull_request_id: Some("7".to_string()),
+            ..Default::default()
+        }));
+
+        events.publish(event(PipelineEventKind::ProofSucceeded, "DOC-1", None));
+        events.publish(event(PipelineEventKind::BadgeUpdated, "DOC-1", Some("8")));
+        events.publish(event(PipelineEventKind::BadgeUpdated, "DOC-1", Some("7")));
+
+        let received = stream.next().await.unwrap();
+        assert_eq!(received.kind, PipelineEventKind::BadgeUpdated);
+        assert_eq!(received.pull_request_id.as_deref(), Some("7"));
+    }
+}
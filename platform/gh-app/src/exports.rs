@@ -0,0 +1,158 @@
+use std::time::Duration;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use export::{BundleBuilder, BundleStore, EntryKind, KmsManifestSigner, PullRequestRef, UploadedBundle};
+use spec_to_proof_proto::{InvariantModel, LeanTheoremModel, ProofArtifactModel, SpecDocumentModel};
+
+use crate::proto::gh_app::v1::SigstoreEntry;
+use crate::sigstore::SigstoreClient;
+
+fn default_key_prefix() -> String {
+    "audit-exports/".to_string()
+}
+
+fn default_url_expiry_secs() -> u64 {
+    24 * 60 * 60
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditExportSettings {
+    pub bucket: String,
+    #[serde(default = "default_key_prefix")]
+    pub key_prefix: String,
+    /// Asymmetric ECC_NIST_P256 KMS key the manifests are signed with
+    pub signing_key_id: String,
+    /// KMS key for server-side encryption of the stored bundles
+    #[serde(default)]
+    pub encryption_key_id: Option<String>,
+    #[serde(default = "default_url_expiry_secs")]
+    pub url_expiry_secs: u64,
+}
+
+/// Everything to package for one pull request. Sigstore entries are
+/// fetched from Rekor by id.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditExportRequest {
+    /// "owner/name"
+    pub repository: String,
+    pub pull_request: u64,
+    pub head_sha: String,
+    #[serde(default)]
+    pub spec_documents: Vec<SpecDocumentModel>,
+    #[serde(default)]
+    pub invariants: Vec<InvariantModel>,
+    #[serde(default)]
+    pub lean_theorems: Vec<LeanTheoremModel>,
+    #[serde(default)]
+    pub proof_artifacts: Vec<ProofArtifactModel>,
+    #[serde(default)]
+    pub sigstore_entry_ids: Vec<String>,
+}
+
+impl AuditExportRequest {
+    pub fn is_empty(&self) -> bool {
+        self.spec_documents.is_empty()
+            && self.invariants.is_empty()
+            && self.lean_theorems.is_empty()
+            && self.proof_artifacts.is_empty()
+            && self.sigstore_entry_ids.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditExporter {
+    signer: KmsManifestSigner,
+    store: BundleStore,
+}
+
+impl AuditExporter {
+    pub async fn from_settings(settings: &AuditExportSettings, aws_region: &str) -> Self {
+        let aws_config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(aws_sdk_sts::Region::new(aws_region.to_string()))
+            .load()
+            .await;
+        let mut store = BundleStore::new(aws_sdk_s3::Client::new(&aws_config), &settings.bucket, &settings.key_prefix)
+            .with_url_expiry(Duration::from_secs(settings.url_expiry_secs));
+        if let Some(key_id) = &settings.encryption_key_id {
+            store = store.with_kms_key(key_id);
+        }
+        Self {
+            signer: KmsManifestSigner::new(aws_sdk_kms::Client::new(&aws_config), &settings.signing_key_id),
+            store,
+        }
+    }
+
+    /// Builds, signs and uploads the bundle, returning where to download it
+    pub async fn export(&self, request: &AuditExportRequest, sigstore: &SigstoreClient) -> Result<UploadedBundle> {
+        let mut sigstore_entries = Vec::new();
+        for entry_id in &request.sigstore_entry_ids {
+            sigstore_entries.push(
+                sigstore.fetch_entry(entry_id).await
+                    .with_context(|| format!("Failed to fetch Sigstore entry {}", entry_id))?,
+            );
+        }
+
+        let bundle = build_bundle(request, &sigstore_entries)?
+            .finish(&self.signer, chrono::Utc::now())
+            .await
+            .context("Failed to sign audit bundle")?;
+        self.store.upload(&bundle).await.context("Failed to upload audit bundle")
+    }
+}
+
+pub fn build_bundle(request: &AuditExportRequest, sigstore_entries: &[SigstoreEntry]) -> Result<BundleBuilder> {
+    let mut builder = BundleBuilder::new(PullRequestRef {
+        repository: request.repository.clone(),
+        number: request.pull_request,
+        head_sha: request.head_sha.clone(),
+    });
+    for document in &request.spec_documents {
+        builder.add_json(EntryKind::SpecSnapshot, &format!("{}.json", document.id), document)?;
+    }
+    for invariant in &request.invariants {
+        builder.add_json(EntryKind::Invariants, &format!("{}.json", invariant.id), invariant)?;
+    }
+    for theorem in &request.lean_theorems {
+        builder.add_bytes(EntryKind::LeanSource, &format!("{}.lean", theorem.id), theorem.lean_code.as_str())?;
+    }
+    for artifact in &request.proof_artifacts {
+        builder.add_json(EntryKind::ProofOutput, &format!("{}.json", artifact.id), artifact)?;
+    }
+    for entry in sigstore_entries {
+        builder.add_json(EntryKind::SigstoreEntry, &format!("{}.json", entry.entry_id), entry)?;
+    }
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_bundle_layout() {
+        let request: AuditExportRequest = serde_json::from_value(serde_json::json!({
+            "repository": "acme/payments",
+            "pull_request": 42,
+            "head_sha": "0123abcd",
+            "sigstore_entry_ids": ["24296fb24b8ad77a"]
+        })).unwrap();
+        assert!(!request.is_empty());
+
+        let entry = SigstoreEntry {
+            entry_id: "24296fb24b8ad77a".to_string(),
+            log_index: "1".to_string(),
+            integrated_time: "1700000000".to_string(),
+            log_id: "c0d23d6a".to_string(),
+            rekor_entry_url: String::new(),
+            fulcio_certificate_url: String::new(),
+            oidc_issuer: String::new(),
+            oidc_identity: String::new(),
+            signature: String::new(),
+            public_key: String::new(),
+            artifact_hash: "abc".to_string(),
+            artifact_type: "proof".to_string(),
+        };
+        let builder = build_bundle(&request, &[entry]).unwrap();
+        assert_eq!(builder.paths(), vec!["sigstore/24296fb24b8ad77a.json"]);
+    }
+}
@@ -1,14 +1,18 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use reqwest::{Client, header::{HeaderMap, HeaderValue, AUTHORIZATION, USER_AGENT, ACCEPT}};
+use reqwest::{Client, RequestBuilder, Response, header::{HeaderMap, HeaderValue, AUTHORIZATION, USER_AGENT, ACCEPT}};
 use serde::{Deserialize, Serialize};
 use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey, Algorithm};
-use chrono::{Utc, Duration as ChronoDuration};
+use chrono::{DateTime, Utc, Duration as ChronoDuration};
 use anyhow::{Result, Context};
+use circuit_breaker::CircuitBreaker;
 use tracing::{info, warn, error};
 use uuid::Uuid;
 
 use crate::config::GitHubAppConfig;
+use crate::installations::InstallationRegistry;
+use crate::secrets::SecretHandle;
 use crate::proto::gh_app::v1::*;
 
 #[derive(Debug, Clone)]
@@ -16,9 +20,15 @@ pub struct GitHubClient {
     config: GitHubAppConfig,
     http_client: Client,
     jwt_cache: HashMap<String, (String, Instant)>,
-    installation_token_cache: HashMap<String, (String, Instant)>,
+    installations: Arc<InstallationRegistry>,
+    secrets: SecretHandle,
+    installation_id: String,
+    breaker: Arc<CircuitBreaker>,
 }
 
+/// Name of the breaker shared by every GitHub client in the process
+pub const GITHUB_CIRCUIT: &str = "github";
+
 #[derive(Debug, Serialize, Deserialize)]
 struct JWTPayload {
     iss: String,  // App ID
@@ -80,6 +90,13 @@ struct Repository {
     default_branch: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueComment {
+    pub id: u64,
+    #[serde(default)]
+    pub body: String,
+}
+
 impl GitHubClient {
     pub async fn new(config: &GitHubAppConfig) -> Result<Self> {
         let mut headers = HeaderMap::new();
@@ -96,11 +113,40 @@ impl GitHubClient {
             config: config.clone(),
             http_client,
             jwt_cache: HashMap::new(),
-            installation_token_cache: HashMap::new(),
+            installations: Arc::new(InstallationRegistry::from_config(config)),
+            secrets: SecretHandle::from_config(config),
+            installation_id: config.installation_id.clone(),
+            breaker: CircuitBreaker::shared(GITHUB_CIRCUIT),
         })
     }
     
+    /// Shares installation tokens with every other client using the registry
+    pub fn with_installations(mut self, installations: Arc<InstallationRegistry>) -> Self {
+        self.installations = installations;
+        self
+    }
+    
+    /// Signs with the shared secrets, picking up rotations
+    pub fn with_secrets(mut self, secrets: SecretHandle) -> Self {
+        self.secrets = secrets;
+        self
+    }
+    
+    /// Scopes subsequent API calls to the given installation
+    pub fn set_installation(&mut self, installation_id: &str) {
+        self.installation_id = installation_id.to_string();
+    }
+    
     pub async fn create_jwt(&mut self) -> Result<String> {
+        let token = self.app_jwt()?;
+        
+        // Cache the JWT
+        self.jwt_cache.insert("jwt".to_string(), (token.clone(), Instant::now()));
+        
+        Ok(token)
+    }
+    
+    fn app_jwt(&self) -> Result<String> {
         let now = Utc::now();
         let exp = now + ChronoDuration::minutes(10); // JWT expires in 10 minutes
         
@@ -113,21 +159,40 @@ impl GitHubClient {
         let token = encode(
             &Header::new(Algorithm::RS256),
             &payload,
-            &EncodingKey::from_rsa_pem(self.config.private_key.as_bytes())?
+            &EncodingKey::from_rsa_pem(self.secrets.current().private_key.as_bytes())?
         )?;
-        
-        // Cache the JWT
-        self.jwt_cache.insert("jwt".to_string(), (token.clone(), Instant::now()));
-        
         Ok(token)
     }
     
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        self.breaker.call_http(
+            async { request.send().await.map_err(anyhow::Error::from) },
+            |response| response.status().as_u16(),
+        ).await
+    }
+    
+    /// Authenticates as the app against `GET /app`, which fails if the app
+    /// ID or private key is wrong or GitHub is unreachable
+    pub async fn verify_app_credentials(&self) -> Result<()> {
+        let jwt = self.app_jwt().context("Failed to sign app JWT")?;
+        let response = self.http_client
+            .get(format!("{}/app", self.config.base_url))
+            .header(AUTHORIZATION, format!("Bearer {}", jwt))
+            .header("X-GitHub-Api-Version", &self.config.api_version)
+            .send()
+            .await
+            .context("GitHub API unreachable")?;
+        
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("GitHub API rejected app credentials: {}", response.status()));
+        }
+        Ok(())
+    }
+    
     pub async fn get_installation_token(&mut self, installation_id: &str) -> Result<String> {
         // Check cache first
-        if let Some((token, created_at)) = self.installation_token_cache.get(installation_id) {
-            if created_at.elapsed() < Duration::from_secs(3600) { // 1 hour cache
-                return Ok(token.clone());
-            }
+        if let Some(token) = self.installations.cached_token(installation_id).await {
+            return Ok(token);
         }
         
         // Get JWT for authentication
@@ -136,10 +201,9 @@ impl GitHubClient {
         let url = format!("{}/app/installations/{}/access_tokens", 
             self.config.base_url, installation_id);
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .post(&url)
-            .header(AUTHORIZATION, format!("Bearer {}", jwt))
-            .send()
+            .header(AUTHORIZATION, format!("Bearer {}", jwt)))
             .await
             .context("Failed to get installation token")?;
         
@@ -151,11 +215,11 @@ impl GitHubClient {
         let token_response: InstallationTokenResponse = response.json().await
             .context("Failed to parse installation token response")?;
         
-        // Cache the token
-        self.installation_token_cache.insert(
-            installation_id.to_string(), 
-            (token_response.token.clone(), Instant::now())
-        );
+        // Cache the token until shortly before GitHub expires it
+        let expires_at = DateTime::parse_from_rfc3339(&token_response.expires_at)
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now() + ChronoDuration::hours(1));
+        self.installations.store_token(installation_id, &token_response.token, expires_at).await;
         
         Ok(token_response.token)
     }
@@ -169,8 +233,8 @@ impl GitHubClient {
         description: &str,
         target_url: Option<&str>,
     ) -> Result<()> {
-        let installation_id = &self.config.installation_id;
-        let token = self.get_installation_token(installation_id).await?;
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
         
         let state = match status {
             BadgeStatus::BadgeStatusPending => "pending",
@@ -190,11 +254,10 @@ impl GitHubClient {
         let url = format!("{}/repos/{}/statuses/{}", 
             self.config.base_url, repo, sha);
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .post(&url)
             .header(AUTHORIZATION, format!("token {}", token))
-            .json(&status_request)
-            .send()
+            .json(&status_request))
             .await
             .context("Failed to update commit status")?;
         
@@ -208,16 +271,15 @@ impl GitHubClient {
     }
     
     pub async fn get_pull_request(&mut self, repo: &str, pr_number: &str) -> Result<PullRequest> {
-        let installation_id = &self.config.installation_id;
-        let token = self.get_installation_token(installation_id).await?;
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
         
         let url = format!("{}/repos/{}/pulls/{}", 
             self.config.base_url, repo, pr_number);
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .get(&url)
-            .header(AUTHORIZATION, format!("token {}", token))
-            .send()
+            .header(AUTHORIZATION, format!("token {}", token)))
             .await
             .context("Failed to get pull request")?;
         
@@ -233,15 +295,14 @@ impl GitHubClient {
     }
     
     pub async fn get_repository(&mut self, repo: &str) -> Result<Repository> {
-        let installation_id = &self.config.installation_id;
-        let token = self.get_installation_token(installation_id).await?;
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
         
         let url = format!("{}/repos/{}", self.config.base_url, repo);
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .get(&url)
-            .header(AUTHORIZATION, format!("token {}", token))
-            .send()
+            .header(AUTHORIZATION, format!("token {}", token)))
             .await
             .context("Failed to get repository")?;
         
@@ -257,16 +318,15 @@ impl GitHubClient {
     }
     
     pub async fn get_commit(&mut self, repo: &str, sha: &str) -> Result<serde_json::Value> {
-        let installation_id = &self.config.installation_id;
-        let token = self.get_installation_token(installation_id).await?;
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
         
         let url = format!("{}/repos/{}/commits/{}", 
             self.config.base_url, repo, sha);
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .get(&url)
-            .header(AUTHORIZATION, format!("token {}", token))
-            .send()
+            .header(AUTHORIZATION, format!("token {}", token)))
             .await
             .context("Failed to get commit")?;
         
@@ -282,16 +342,15 @@ impl GitHubClient {
     }
     
     pub async fn get_changed_files(&mut self, repo: &str, pr_number: &str) -> Result<Vec<String>> {
-        let installation_id = &self.config.installation_id;
-        let token = self.get_installation_token(installation_id).await?;
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
         
         let url = format!("{}/repos/{}/pulls/{}/files", 
             self.config.base_url, repo, pr_number);
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .get(&url)
-            .header(AUTHORIZATION, format!("token {}", token))
-            .send()
+            .header(AUTHORIZATION, format!("token {}", token)))
             .await
             .context("Failed to get changed files")?;
         
@@ -311,6 +370,118 @@ impl GitHubClient {
         Ok(file_names)
     }
     
+    /// A file's contents at `git_ref`, or `None` when the repository has no
+    /// such file
+    pub async fn get_file_contents(&mut self, repo: &str, path: &str, git_ref: &str) -> Result<Option<String>> {
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
+        
+        let url = format!("{}/repos/{}/contents/{}", 
+            self.config.base_url, repo, path);
+        
+        let response = self.send(self.http_client
+            .get(&url)
+            .query(&[("ref", git_ref)])
+            .header(AUTHORIZATION, format!("token {}", token))
+            .header(ACCEPT, "application/vnd.github.raw"))
+            .await
+            .context("Failed to get file contents")?;
+        
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to get file contents: {}", error_text));
+        }
+        
+        let contents = response.text().await
+            .context("Failed to read file contents")?;
+        
+        Ok(Some(contents))
+    }
+    
+    pub async fn list_issue_comments(&mut self, repo: &str, issue_number: &str) -> Result<Vec<IssueComment>> {
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
+        
+        let mut comments = Vec::new();
+        for page in 1.. {
+            let url = format!("{}/repos/{}/issues/{}/comments?per_page=100&page={}", 
+                self.config.base_url, repo, issue_number, page);
+            
+            let response = self.send(self.http_client
+                .get(&url)
+                .header(AUTHORIZATION, format!("token {}", token)))
+                .await
+                .context("Failed to list issue comments")?;
+            
+            if !response.status().is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                return Err(anyhow::anyhow!("Failed to list issue comments: {}", error_text));
+            }
+            
+            let page_comments: Vec<IssueComment> = response.json().await
+                .context("Failed to parse issue comments response")?;
+            let last_page = page_comments.len() < 100;
+            comments.extend(page_comments);
+            if last_page {
+                break;
+            }
+        }
+        
+        Ok(comments)
+    }
+    
+    pub async fn create_issue_comment(&mut self, repo: &str, issue_number: &str, body: &str) -> Result<IssueComment> {
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
+        
+        let url = format!("{}/repos/{}/issues/{}/comments", 
+            self.config.base_url, repo, issue_number);
+        
+        let response = self.send(self.http_client
+            .post(&url)
+            .header(AUTHORIZATION, format!("token {}", token))
+            .json(&serde_json::json!({ "body": body })))
+            .await
+            .context("Failed to create issue comment")?;
+        
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to create issue comment: {}", error_text));
+        }
+        
+        let comment: IssueComment = response.json().await
+            .context("Failed to parse issue comment response")?;
+        
+        info!("Created comment {} on {}#{}", comment.id, repo, issue_number);
+        Ok(comment)
+    }
+    
+    pub async fn update_issue_comment(&mut self, repo: &str, comment_id: u64, body: &str) -> Result<()> {
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
+        
+        let url = format!("{}/repos/{}/issues/comments/{}", 
+            self.config.base_url, repo, comment_id);
+        
+        let response = self.send(self.http_client
+            .patch(&url)
+            .header(AUTHORIZATION, format!("token {}", token))
+            .json(&serde_json::json!({ "body": body })))
+            .await
+            .context("Failed to update issue comment")?;
+        
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to update issue comment: {}", error_text));
+        }
+        
+        info!("Updated comment {} on {}", comment_id, repo);
+        Ok(())
+    }
+    
     pub async fn create_check_run(
         &mut self,
         repo: &str,
@@ -320,8 +491,8 @@ impl GitHubClient {
         conclusion: Option<&str>,
         output: Option<serde_json::Value>,
     ) -> Result<()> {
-        let installation_id = &self.config.installation_id;
-        let token = self.get_installation_token(installation_id).await?;
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
         
         let mut check_run = serde_json::json!({
             "name": name,
@@ -340,11 +511,10 @@ impl GitHubClient {
         let url = format!("{}/repos/{}/check-runs", 
             self.config.base_url, repo);
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .post(&url)
             .header(AUTHORIZATION, format!("token {}", token))
-            .json(&check_run)
-            .send()
+            .json(&check_run))
             .await
             .context("Failed to create check run")?;
         
@@ -365,8 +535,8 @@ impl GitHubClient {
         conclusion: Option<&str>,
         output: Option<serde_json::Value>,
     ) -> Result<()> {
-        let installation_id = &self.config.installation_id;
-        let token = self.get_installation_token(installation_id).await?;
+        let installation_id = self.installation_id.clone();
+        let token = self.get_installation_token(&installation_id).await?;
         
         let mut check_run = serde_json::json!({
             "status": status,
@@ -383,11 +553,10 @@ impl GitHubClient {
         let url = format!("{}/repos/{}/check-runs/{}", 
             self.config.base_url, repo, check_run_id);
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .patch(&url)
             .header(AUTHORIZATION, format!("token {}", token))
-            .json(&check_run)
-            .send()
+            .json(&check_run))
             .await
             .context("Failed to update check run")?;
         
@@ -0,0 +1,262 @@
+use std::time::Duration;
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::GitHubAppConfig;
+use crate::proto::gh_app::v1::{BadgeStatus, BadgeStatusRequest};
+use crate::scm::{self, ScmKind, ScmProvider};
+
+/// Header GitLab sends the webhook secret token in
+pub const GITLAB_TOKEN_HEADER: &str = "X-Gitlab-Token";
+pub const GITLAB_EVENT_HEADER: &str = "X-Gitlab-Event";
+pub const MERGE_REQUEST_HOOK: &str = "Merge Request Hook";
+
+fn default_base_url() -> String {
+    "https://gitlab.com".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitLabSettings {
+    /// Instance URL, for self-managed GitLab
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    /// Project or group access token with the `api` scope
+    pub access_token: String,
+    /// Secret token configured on the project's webhook
+    pub webhook_secret: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitLabClient {
+    http_client: Client,
+    base_url: String,
+    access_token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CommitStatusRequest<'a> {
+    state: &'a str,
+    name: &'a str,
+    description: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_url: Option<&'a str>,
+}
+
+impl GitLabClient {
+    pub fn new(settings: &GitLabSettings, config: &GitHubAppConfig) -> Result<Self> {
+        let http_client = Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout))
+            .user_agent("spec-to-proof-gh-app")
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            http_client,
+            base_url: settings.base_url.trim_end_matches('/').to_string(),
+            access_token: settings.access_token.clone(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ScmProvider for GitLabClient {
+    fn kind(&self) -> ScmKind {
+        ScmKind::GitLab
+    }
+
+    // Statuses on the merge request's head commit show up as an external
+    // job in its latest pipeline
+    async fn update_commit_status(
+        &mut self,
+        repository: &str,
+        sha: &str,
+        status: BadgeStatus,
+        context: &str,
+        description: &str,
+        target_url: Option<&str>,
+    ) -> Result<()> {
+        let url = format!("{}/api/v4/projects/{}/statuses/{}", self.base_url, project_path(repository), sha);
+        let request = CommitStatusRequest {
+            state: commit_state(status),
+            name: context,
+            description,
+            target_url,
+        };
+
+        let response = self.http_client
+            .post(&url)
+            .header("PRIVATE-TOKEN", &self.access_token)
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to update GitLab commit status")?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to update GitLab commit status: {}", error_text));
+        }
+
+        info!("Updated GitLab commit status for {}@{}: {:?}", repository, sha, status);
+        Ok(())
+    }
+}
+
+fn commit_state(status: BadgeStatus) -> &'static str {
+    match status {
+        BadgeStatus::BadgeStatusPending => "running",
+        BadgeStatus::BadgeStatusSuccess => "success",
+        _ => "failed",
+    }
+}
+
+// Projects can be addressed by id or by URL-encoded path
+fn project_path(repository: &str) -> String {
+    repository.replace('/', "%2F")
+}
+
+/// Compares the webhook's secret token with the configured one without
+/// leaking where they differ
+pub fn verify_token(received: &str, expected: &str) -> bool {
+    !expected.is_empty() && auth::constant_time_eq(received.as_bytes(), expected.as_bytes())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeRequestEvent {
+    pub object_kind: String,
+    pub project: GitLabProject,
+    pub object_attributes: MergeRequestAttributes,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitLabProject {
+    pub id: u64,
+    pub path_with_namespace: String,
+    #[serde(default)]
+    pub web_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeRequestAttributes {
+    pub id: u64,
+    pub iid: u64,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub action: Option<String>,
+    /// Previous head, only present when the update pushed new commits
+    #[serde(default)]
+    pub oldrev: Option<String>,
+    pub last_commit: GitLabCommit,
+    #[serde(default)]
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitLabCommit {
+    pub id: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl MergeRequestEvent {
+    /// Whether the event changed the code under review
+    pub fn needs_verification(&self) -> bool {
+        match self.object_attributes.action.as_deref() {
+            Some("open") | Some("reopen") => true,
+            Some("update") => self.object_attributes.oldrev.is_some(),
+            _ => false,
+        }
+    }
+
+    /// Spec references in the title, description and head commit message
+    pub fn spec_references(&self) -> Vec<String> {
+        let attributes = &self.object_attributes;
+        let mut references = scm::extract_spec_references(&attributes.title);
+        if let Some(description) = &attributes.description {
+            references.extend(scm::extract_spec_references(description));
+        }
+        references.extend(scm::extract_spec_references(&attributes.last_commit.message));
+
+        let mut seen = std::collections::HashSet::new();
+        references.retain(|reference| seen.insert(reference.clone()));
+        references
+    }
+
+    /// The badge update for this merge request, if it needs one
+    pub fn badge_request(&self, config: &GitHubAppConfig) -> Option<BadgeStatusRequest> {
+        if self.object_kind != "merge_request" || !self.needs_verification() {
+            return None;
+        }
+        let spec_document_ids = self.spec_references();
+        if spec_document_ids.is_empty() {
+            return None;
+        }
+        Some(BadgeStatusRequest {
+            repository_id: self.project.id.to_string(),
+            pull_request_id: self.object_attributes.iid.to_string(),
+            commit_sha: self.object_attributes.last_commit.id.clone(),
+            spec_document_ids,
+            installation_id: String::new(),
+            app_id: config.app_id.clone(),
+            provider: ScmKind::GitLab,
+            refresh: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(action: &str, oldrev: Option<&str>) -> MergeRequestEvent {
+        serde_json::from_value(serde_json::json!({
+            "object_kind": "merge_request",
+            "project": {"id": 17, "path_with_namespace": "acme/payments"},
+            "object_attributes": {
+                "id": 901,
+                "iid": 12,
+                "title": "Refund window for SPEC-7",
+                "description": "spec: refund-policy\nAlso SPEC-7",
+                "action": action,
+                "oldrev": oldrev,
+                "last_commit": {"id": "0123abcd", "message": "Implement DOC-3"}
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_badge_request_from_merge_request() {
+        let config = GitHubAppConfig::default();
+        let request = event("open", None).badge_request(&config).unwrap();
+        assert_eq!(request.repository_id, "17");
+        assert_eq!(request.pull_request_id, "12");
+        assert_eq!(request.commit_sha, "0123abcd");
+        assert_eq!(request.provider, ScmKind::GitLab);
+        assert_eq!(request.spec_document_ids, vec!["7", "refund-policy", "3"]);
+    }
+
+    #[test]
+    fn test_only_code_changes_need_verification() {
+        let config = GitHubAppConfig::default();
+        assert!(event("update", None).badge_request(&config).is_none());
+        assert!(event("update", Some("ffff0000")).badge_request(&config).is_some());
+        assert!(event("merge", None).badge_request(&config).is_none());
+    }
+
+    #[test]
+    fn test_verify_token() {
+        assert!(verify_token("s3cret", "s3cret"));
+        assert!(!verify_token("s3cre", "s3cret"));
+        assert!(!verify_token("", ""));
+    }
+
+    #[test]
+    fn test_project_path() {
+        assert_eq!(project_path("17"), "17");
+        assert_eq!(project_path("acme/payments"), "acme%2Fpayments");
+    }
+}
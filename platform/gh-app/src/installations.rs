@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::config::GitHubAppConfig;
+
+// Installation tokens are refreshed this long before GitHub expires them
+const TOKEN_REFRESH_MARGIN: ChronoDuration = ChronoDuration::minutes(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Installation {
+    pub id: String,
+    pub account_login: String,
+    pub account_type: String,
+    pub repository_selection: String,
+    pub suspended: bool,
+    pub registered_at: DateTime<Utc>,
+}
+
+impl Installation {
+    pub fn new(id: &str, account_login: &str, account_type: &str, repository_selection: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            account_login: account_login.to_string(),
+            account_type: account_type.to_string(),
+            repository_selection: repository_selection.to_string(),
+            suspended: false,
+            registered_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl InstallationToken {
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now + TOKEN_REFRESH_MARGIN < self.expires_at
+    }
+}
+
+/// Installations (tenants) of the app, keyed by installation ID, together
+/// with their cached access tokens. Installations are added and removed at
+/// runtime from `installation` webhook events.
+#[derive(Debug, Default)]
+pub struct InstallationRegistry {
+    installations: RwLock<HashMap<String, Installation>>,
+    tokens: RwLock<HashMap<String, InstallationToken>>,
+    default_installation_id: Option<String>,
+}
+
+impl InstallationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seeds the registry with the installation from the config, which also
+    /// serves requests that do not name an installation
+    pub fn from_config(config: &GitHubAppConfig) -> Self {
+        if config.installation_id.is_empty() {
+            return Self::new();
+        }
+
+        let installation = Installation::new(&config.installation_id, "", "", "");
+        Self {
+            installations: RwLock::new(HashMap::from([(installation.id.clone(), installation)])),
+            tokens: RwLock::new(HashMap::new()),
+            default_installation_id: Some(config.installation_id.clone()),
+        }
+    }
+
+    pub async fn add(&self, installation: Installation) {
+        info!("Registered installation {} ({})", installation.id, installation.account_login);
+        self.installations.write().await.insert(installation.id.clone(), installation);
+    }
+
+    pub async fn remove(&self, installation_id: &str) -> Option<Installation> {
+        self.tokens.write().await.remove(installation_id);
+        let removed = self.installations.write().await.remove(installation_id);
+        if removed.is_some() {
+            info!("Removed installation {}", installation_id);
+        }
+        removed
+    }
+
+    pub async fn set_suspended(&self, installation_id: &str, suspended: bool) -> bool {
+        if suspended {
+            self.tokens.write().await.remove(installation_id);
+        }
+        match self.installations.write().await.get_mut(installation_id) {
+            Some(installation) => {
+                installation.suspended = suspended;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Registers an installation seen on a verified webhook that the
+    /// registry does not know yet, e.g. one added before a restart
+    pub async fn ensure_registered(&self, installation: Installation) {
+        let mut installations = self.installations.write().await;
+        if !installations.contains_key(&installation.id) {
+            warn!("Registering previously unknown installation {} from webhook", installation.id);
+            installations.insert(installation.id.clone(), installation);
+        }
+    }
+
+    pub async fn get(&self, installation_id: &str) -> Option<Installation> {
+        self.installations.read().await.get(installation_id).cloned()
+    }
+
+    pub async fn list(&self) -> Vec<Installation> {
+        let mut installations: Vec<Installation> = self.installations.read().await.values().cloned().collect();
+        installations.sort_by(|a, b| a.id.cmp(&b.id));
+        installations
+    }
+
+    /// Picks the installation a request acts on. An empty ID falls back to
+    /// the configured default, or the only registered installation.
+    pub async fn resolve(&self, installation_id: &str) -> Result<String> {
+        let installations = self.installations.read().await;
+
+        let id = if !installation_id.is_empty() {
+            installation_id.to_string()
+        } else if let Some(default_id) = &self.default_installation_id {
+            default_id.clone()
+        } else if installations.len() == 1 {
+            installations.keys().next().cloned().unwrap_or_default()
+        } else {
+            return Err(anyhow::anyhow!("Request does not name an installation and no default is configured"));
+        };
+
+        match installations.get(&id) {
+            Some(installation) if installation.suspended => {
+                Err(anyhow::anyhow!("Installation {} is suspended", id))
+            }
+            Some(_) => Ok(id),
+            None => Err(anyhow::anyhow!("Unknown installation {}", id)),
+        }
+    }
+
+    pub async fn cached_token(&self, installation_id: &str) -> Option<String> {
+        let tokens = self.tokens.read().await;
+        tokens
+            .get(installation_id)
+            .filter(|token| token.is_fresh(Utc::now()))
+            .map(|token| token.token.clone())
+    }
+
+    /// Drops every cached token, e.g. after the app's private key rotated
+    pub async fn invalidate_tokens(&self) {
+        self.tokens.write().await.clear();
+    }
+
+    pub async fn store_token(&self, installation_id: &str, token: &str, expires_at: DateTime<Utc>) {
+        self.tokens.write().await.insert(
+            installation_id.to_string(),
+            InstallationToken {
+                token: token.to_string(),
+                expires_at,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve_scopes_to_registered_installations() {
+        let config = GitHubAppConfig {
+            installation_id: "100".to_string(),
+            ..Default::default()
+        };
+        let registry = InstallationRegistry::from_config(&config);
+        registry.add(Installation::new("200", "acme", "Organization", "selected")).await;
+
+        assert_eq!(registry.resolve("").await.unwrap(), "100");
+        assert_eq!(registry.resolve("200").await.unwrap(), "200");
+        assert!(registry.resolve("300").await.is_err());
+
+        registry.set_suspended("200", true).await;
+        assert!(registry.resolve("200").await.is_err());
+
+        registry.remove("200").await;
+        assert!(registry.get("200").await.is_none());
+        assert_eq!(registry.list().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_expire_before_github_deadline() {
+        let registry = InstallationRegistry::new();
+        registry.add(Installation::new("100", "acme", "Organization", "all")).await;
+
+        registry.store_token("100", "ghs_fresh", Utc::now() + ChronoDuration::hours(1)).await;
+        assert_eq!(registry.cached_token("100").await.as_deref(), Some("ghs_fresh"));
+
+        registry.store_token("100", "ghs_stale", Utc::now() + ChronoDuration::minutes(2)).await;
+        assert!(registry.cached_token("100").await.is_none());
+
+        registry.store_token("100", "ghs_fresh", Utc::now() + ChronoDuration::hours(1)).await;
+        registry.remove("100").await;
+        assert!(registry.cached_token("100").await.is_none());
+    }
+}
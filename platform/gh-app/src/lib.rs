@@ -2,35 +2,89 @@ pub mod config;
 pub mod github;
 pub mod webhook;
 pub mod badge;
+pub mod badge_queue;
+pub mod badge_history;
+pub mod coverage;
 pub mod sigstore;
+pub mod sigstore_bundle;
 pub mod auth;
 pub mod proto;
 pub mod error;
 pub mod server;
 pub mod workflows;
 pub mod webhook_handlers;
+pub mod widget;
+pub mod deliveries;
+pub mod installations;
+pub mod secrets;
+pub mod api_auth;
+pub mod exports;
+pub mod gitlab;
+pub mod bitbucket;
+pub mod scm;
+pub mod provenance;
+pub mod pr_comment;
+pub mod policy;
+pub mod proof_status;
+pub mod repo_config;
+pub mod drift;
+pub mod events;
+pub mod runtime;
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use axum::{
-    routing::{post, get},
+    routing::{post, get, put},
     Router,
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     Json,
-    extract::{State, Path},
+    extract::{State, Path, Query, Request},
+    Extension,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use tracing::{info, error, warn};
 use anyhow::Result;
 
 use crate::config::GitHubAppConfig;
-use crate::github::GitHubClient;
+use crate::github::{GitHubClient, GITHUB_CIRCUIT};
 use crate::webhook::WebhookProcessor;
-use crate::badge::BadgeManager;
-use crate::sigstore::SigstoreClient;
+use crate::badge::{BadgeManager, CoverageReportRequest};
+use crate::badge_history::{BadgeHistory, BadgeHistoryEntry};
+use crate::coverage::{CoverageReport, CoverageService};
+use crate::badge_queue::{BadgeJob, BadgeJobAccepted, BadgeJobQueue};
+use crate::sigstore::{SigstoreClient, SIGSTORE_CIRCUIT};
 use crate::auth::JWTManager;
+use crate::widget::{WidgetRateLimiter, WidgetStore};
+use crate::installations::{Installation, InstallationRegistry};
+use crate::secrets::{SecretHandle, SecretsRotationWatcher};
+use crate::api_auth::{ApiAuthenticator, Caller, Role};
+use crate::deliveries::{DeliveryLog, DeliveryStatus, RecordOutcome, WebhookDelivery};
+use crate::exports::{AuditExportRequest, AuditExporter};
+use crate::bitbucket::{PullRequestEvent, BITBUCKET_EVENT_HEADER, BITBUCKET_SIGNATURE_HEADER};
+use crate::gitlab::{MergeRequestEvent, GITLAB_EVENT_HEADER, GITLAB_TOKEN_HEADER, MERGE_REQUEST_HOOK};
+use crate::pr_comment::PrCommentReporter;
+use crate::proof_status::{etag_matches, ProofStatusResponse, ProofStatusStore};
+use crate::repo_config::{ConfigIssue, RepoConfig, RepoConfigStore};
+use crate::drift::DriftListener;
+use crate::events::PipelineEvents;
+use crate::runtime::RuntimeSettings;
+use crate::provenance::{self, ProvenanceAttestation, ProvenanceAttestor, ProvenanceRequest};
 use crate::proto::gh_app::v1::*;
+use audit::{actions, AuditEvent, AuditLog};
+use reload::{ConfigHandle, ConfigWatcher};
+use health::{HealthChecker, HealthReport};
+use circuit_breaker::{CircuitBreaker, CircuitState};
+use cost_governance::{BudgetStore, TenantBudget, TenantUsage};
+use pipeline_control::{Pause, PauseScope, PauseStore, Stage};
+use export::UploadedBundle;
+use spec_to_proof_proto::preview::{build_document_preview, DocumentPreview};
+use spec_to_proof_proto::validation;
+use spec_to_proof_proto::{InvariantModel, SpecDocumentModel};
 
 #[derive(Debug, Clone)]
 pub struct AppState {
@@ -38,39 +92,311 @@ pub struct AppState {
     pub github_client: Arc<GitHubClient>,
     pub webhook_processor: Arc<WebhookProcessor>,
     pub badge_manager: Arc<BadgeManager>,
+    pub badge_queue: Arc<BadgeJobQueue>,
+    pub pipeline_events: PipelineEvents,
+    pub installations: Arc<InstallationRegistry>,
+    pub secrets: SecretHandle,
+    pub repo_configs: Arc<RepoConfigStore>,
+    pub proof_statuses: Arc<ProofStatusStore>,
+    pub coverage: Arc<CoverageService>,
     pub sigstore_client: Arc<SigstoreClient>,
     pub jwt_manager: Arc<JWTManager>,
+    pub api_auth: Arc<ApiAuthenticator>,
+    pub widget_store: Arc<WidgetStore>,
+    pub widget_rate_limiter: Arc<WidgetRateLimiter>,
+    /// Settings reloaded from the config file while the server runs
+    pub runtime: ConfigHandle<RuntimeSettings>,
+    pub webhook_deliveries: Arc<DeliveryLog>,
+    pub audit_log: Arc<AuditLog>,
+    pub audit_exporter: Option<Arc<AuditExporter>>,
+    pub provenance_attestor: Option<Arc<ProvenanceAttestor>>,
+    pub tenant_budgets: Option<Arc<BudgetStore>>,
+    pub pipeline_pauses: Option<Arc<PauseStore>>,
+    pub health: Arc<HealthChecker>,
+    pub started_at: Instant,
     pub metrics: Arc<RwLock<HashMap<String, u64>>>,
 }
 
 impl AppState {
     pub async fn new(config: GitHubAppConfig) -> Result<Self> {
-        let github_client = Arc::new(GitHubClient::new(&config).await?);
-        let webhook_processor = Arc::new(WebhookProcessor::new(&config).await?);
-        let badge_manager = Arc::new(BadgeManager::new(&config).await?);
-        let sigstore_client = Arc::new(SigstoreClient::new(&config).await?);
-        let jwt_manager = Arc::new(JWTManager::new(&config).await?);
         let metrics = Arc::new(RwLock::new(HashMap::new()));
+        let installations = Arc::new(InstallationRegistry::from_config(&config));
+        let secrets = SecretHandle::from_config(&config);
+        let github_client = Arc::new(
+            GitHubClient::new(&config).await?
+                .with_installations(installations.clone())
+                .with_secrets(secrets.clone())
+        );
+        let coverage = Arc::new(CoverageService::from_settings(config.coverage_storage.as_ref()).await?);
+        let provenance_attestor = match &config.provenance {
+            Some(settings) => Some(Arc::new(ProvenanceAttestor::from_settings(settings, &config.aws_region).await)),
+            None => None,
+        };
+        // Shared by all badge workers so each pull request's comment is
+        // tracked in one place
+        let pr_comments = config.pr_comments.as_ref()
+            .map(|settings| Arc::new(PrCommentReporter::new(settings, &config.badge_target_url)));
+        let badge_history = Arc::new(BadgeHistory::from_settings(config.badge_history_storage.as_ref()).await?);
+        let proof_statuses = Arc::new(ProofStatusStore::from_settings(config.proof_status_storage.as_ref()).await?);
+        // One cache of `.spec-to-proof.yml` per commit for the webhook
+        // handlers and badge workers
+        let repo_configs = Arc::new(RepoConfigStore::new(
+            GitHubClient::new(&config).await?
+                .with_installations(installations.clone())
+                .with_secrets(secrets.clone()),
+            installations.clone(),
+        ));
+        let pipeline_events = PipelineEvents::new();
+        let badge_queue = BadgeJobQueue::start(
+            &config,
+            installations.clone(),
+            secrets.clone(),
+            coverage.clone(),
+            provenance_attestor.clone(),
+            pr_comments.clone(),
+            badge_history.clone(),
+            proof_statuses.clone(),
+            repo_configs.clone(),
+            pipeline_events.clone(),
+            metrics.clone(),
+        ).await?;
+        let webhook_processor = Arc::new(
+            WebhookProcessor::with_dependencies(&config, badge_queue.clone(), installations.clone(), repo_configs.clone()).await?
+        );
+        let mut badge_manager = BadgeManager::new(&config).await?
+            .with_installations(installations.clone())
+            .with_secrets(secrets.clone())
+            .with_coverage(coverage.clone())
+            .with_history(badge_history)
+            .with_proof_statuses(proof_statuses.clone())
+            .with_repo_configs(repo_configs.clone());
+        if let Some(provenance) = &provenance_attestor {
+            badge_manager = badge_manager.with_provenance(provenance.clone());
+        }
+        if let Some(pr_comments) = &pr_comments {
+            badge_manager = badge_manager.with_pr_comments(pr_comments.clone());
+        }
+        let badge_manager = Arc::new(badge_manager);
+        let sigstore_client = Arc::new(SigstoreClient::new(&config).await?);
+        let jwt_manager = Arc::new(JWTManager::new(&config).await?.with_secrets(secrets.clone()));
+        let runtime = ConfigHandle::new(RuntimeSettings::from_config(&config));
+        let api_auth = Arc::new(ApiAuthenticator::new(&config, secrets.clone()).with_runtime_settings(runtime.clone()));
+        if !api_auth.is_configured() {
+            warn!("No API keys or OIDC trust configured; metrics and badge routes are unauthenticated");
+        }
+        let widget_store = Arc::new(WidgetStore::new());
+        let widget_rate_limiter = Arc::new(WidgetRateLimiter::from_config(&config).with_runtime_settings(runtime.clone()));
+        let webhook_deliveries = Arc::new(DeliveryLog::from_settings(config.webhook_delivery_storage.as_ref()).await?);
+        let audit_log = Arc::new(AuditLog::open(config.audit_log_path.as_deref()).await?);
+        let audit_exporter = match &config.audit_export {
+            Some(settings) => Some(Arc::new(AuditExporter::from_settings(settings, &config.aws_region).await)),
+            None => None,
+        };
+        let tenant_budgets = config.cost_governance_redis_url.as_deref()
+            .map(BudgetStore::connect)
+            .transpose()?
+            .map(Arc::new);
+        let pipeline_pauses = config.pipeline_control_redis_url.as_deref()
+            .map(PauseStore::connect)
+            .transpose()?
+            .map(Arc::new);
+        let health = Arc::new(build_health_checker(
+            &config,
+            &github_client,
+            &webhook_deliveries,
+            &coverage,
+            tenant_budgets.as_ref(),
+        ));
 
         Ok(Self {
             config,
             github_client,
             webhook_processor,
             badge_manager,
+            badge_queue,
+            pipeline_events,
+            installations,
+            secrets,
+            repo_configs,
+            proof_statuses,
+            coverage,
             sigstore_client,
             jwt_manager,
+            api_auth,
+            widget_store,
+            widget_rate_limiter,
+            runtime,
+            webhook_deliveries,
+            audit_log,
+            audit_exporter,
+            provenance_attestor,
+            tenant_budgets,
+            pipeline_pauses,
+            health,
+            started_at: Instant::now(),
             metrics,
         })
     }
+
+    /// Polls the app's Secrets Manager secret for rotations when secrets
+    /// come from AWS and a rotation interval is set
+    pub async fn spawn_secrets_rotation(&self) -> Option<JoinHandle<()>> {
+        if self.config.aws_secrets_arn.is_empty() || self.config.secrets_rotation_interval_secs == 0 {
+            return None;
+        }
+
+        let aws_config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(aws_sdk_sts::Region::new(self.config.aws_region.clone()))
+            .load()
+            .await;
+        let watcher = SecretsRotationWatcher::new(
+            aws_sdk_secretsmanager::Client::new(&aws_config),
+            &self.config.aws_secrets_arn,
+            Duration::from_secs(self.config.secrets_rotation_interval_secs),
+        );
+        info!("Watching {} for secret rotations every {}s", self.config.aws_secrets_arn, self.config.secrets_rotation_interval_secs);
+        Some(watcher.spawn(self.secrets.clone(), self.installations.clone()))
+    }
+
+    /// Re-reads rate limits and the widget cache lifetime from `config_path`
+    /// when it changes or on SIGHUP, auditing each change
+    pub fn spawn_config_watcher(&self, config_path: &std::path::Path) -> JoinHandle<()> {
+        info!("Watching {} for runtime setting changes", config_path.display());
+        ConfigWatcher::new(self.runtime.clone(), config_path, "gh-app/runtime_settings")
+            .with_loader(RuntimeSettings::load)
+            .with_audit_log(self.audit_log.clone())
+            .spawn()
+    }
+
+    /// Re-verifies badges when the nlp service reports spec drift, if a
+    /// NATS connection for drift events is configured
+    pub async fn spawn_drift_listener(&self) -> Result<Option<JoinHandle<()>>> {
+        let Some(settings) = &self.config.drift else {
+            return Ok(None);
+        };
+        let listener = DriftListener::new(self.badge_queue.clone(), self.audit_log.clone(), self.metrics.clone());
+        Ok(Some(listener.spawn(settings).await?))
+    }
+
+    /// Streams extraction and proof progress from NATS to event stream
+    /// clients, if a NATS connection for pipeline events is configured
+    pub async fn spawn_event_listener(&self) -> Result<Option<JoinHandle<()>>> {
+        let Some(settings) = &self.config.pipeline_events else {
+            return Ok(None);
+        };
+        Ok(Some(self.pipeline_events.spawn_listener(settings, self.metrics.clone()).await?))
+    }
+}
+
+// Webhooks cannot be accepted without GitHub credentials or the delivery
+// log; coverage and tenant budgets only back some endpoints
+fn build_health_checker(
+    config: &GitHubAppConfig,
+    github_client: &Arc<GitHubClient>,
+    webhook_deliveries: &Arc<DeliveryLog>,
+    coverage: &Arc<CoverageService>,
+    tenant_budgets: Option<&Arc<BudgetStore>>,
+) -> HealthChecker {
+    let github_client = github_client.clone();
+    let webhook_deliveries = webhook_deliveries.clone();
+    let coverage = coverage.clone();
+    let mut checker = HealthChecker::new(Duration::from_millis(config.health_check_timeout_ms))
+        .with_check("github_api", true, move || {
+            let github_client = github_client.clone();
+            async move { github_client.verify_app_credentials().await.map_err(|e| format!("{:#}", e)) }
+        })
+        .with_check("webhook_delivery_storage", true, move || {
+            let webhook_deliveries = webhook_deliveries.clone();
+            async move { webhook_deliveries.ping().await.map_err(|e| e.to_string()) }
+        })
+        .with_check("coverage_storage", false, move || {
+            let coverage = coverage.clone();
+            async move { coverage.ping().await.map_err(|e| e.to_string()) }
+        })
+        // Open breakers degrade the service rather than take it down; calls
+        // fail fast until the cooldown lets a probe through
+        .with_check("github_circuit", false, || {
+            let breaker = CircuitBreaker::shared(GITHUB_CIRCUIT);
+            async move { breaker.health_check() }
+        })
+        .with_check("sigstore_circuit", false, || {
+            let breaker = CircuitBreaker::shared(SIGSTORE_CIRCUIT);
+            async move { breaker.health_check() }
+        });
+    if let Some(tenant_budgets) = tenant_budgets {
+        let tenant_budgets = tenant_budgets.clone();
+        checker = checker.with_check("redis", false, move || {
+            let tenant_budgets = tenant_budgets.clone();
+            async move { tenant_budgets.ping().await.map_err(|e| e.to_string()) }
+        });
+    }
+    checker
 }
 
 pub async fn create_app(state: AppState) -> Router {
+    let state = Arc::new(state);
+
+    let read_only = Router::new()
+        .route("/badge/jobs/:id", get(get_badge_job))
+        .route("/badge/:repo/:pr/history", get(get_badge_history))
+        .route("/api/v1/proof-status", get(get_proof_status))
+        .route("/events", get(events::stream_events))
+        .route("/metrics", get(get_metrics))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_read_only));
+
+    let admin = Router::new()
+        .route("/admin/webhooks/:delivery_id/replay", post(replay_webhook))
+        .route("/admin/installations", get(list_installations))
+        .route("/admin/tenants/:id/usage", get(get_tenant_usage))
+        .route("/admin/tenants/:id/budget", put(set_tenant_budget))
+        .route("/admin/pauses", get(list_pipeline_pauses))
+        .route("/admin/tenants/:id/pauses/:stage", put(pause_tenant_stage).delete(resume_tenant_stage))
+        .route("/admin/sources/:system/pauses/:stage", put(pause_source_stage).delete(resume_source_stage))
+        .route("/admin/exports", post(export_audit_bundle))
+        .route("/admin/provenance", post(attest_provenance))
+        .route("/badge/:repo/:pr", post(update_badge))
+        .route("/badge/coverage", post(report_coverage))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin));
+
     Router::new()
         .route("/webhook", post(handle_webhook))
-        .route("/badge/:repo/:pr", post(update_badge))
+        .route("/gitlab/webhook", post(handle_gitlab_webhook))
+        .route("/bitbucket/webhook", post(handle_bitbucket_webhook))
+        .route("/coverage", post(compute_coverage))
+        .route("/documents/preview", post(preview_document))
+        .route("/validate-config", post(validate_repo_config))
         .route("/health", get(health_check))
-        .route("/metrics", get(get_metrics))
-        .with_state(Arc::new(state))
+        .route("/ready", get(readiness_check))
+        .merge(read_only)
+        .merge(admin)
+        .merge(widget::router())
+        .with_state(state)
+}
+
+async fn require_read_only(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    authorize_request(&state, Role::ReadOnly, request, next).await
+}
+
+async fn require_admin(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    authorize_request(&state, Role::Admin, request, next).await
+}
+
+// The caller is added to the request extensions for handlers to read
+async fn authorize_request(state: &AppState, role: Role, mut request: Request, next: Next) -> Response {
+    match state.api_auth.authorize(request.headers(), role).await {
+        Ok(caller) => {
+            request.extensions_mut().insert(caller);
+            next.run(request).await
+        }
+        Err(e) => {
+            warn!("Rejected {} {}: {}", request.method(), request.uri().path(), e);
+            {
+                let mut metrics = state.metrics.write().await;
+                *metrics.entry("api_auth_rejected_total".to_string()).or_insert(0) += 1;
+            }
+            e.into_response()
+        }
+    }
 }
 
 async fn handle_webhook(
@@ -99,7 +425,7 @@ async fn handle_webhook(
     let verification_request = WebhookVerificationRequest {
         payload: body.clone(),
         signature: signature.to_string(),
-        webhook_secret: state.config.webhook_secret.clone(),
+        webhook_secret: state.secrets.current().webhook_secret.clone(),
     };
 
     let verification_response = state.webhook_processor.verify_webhook(verification_request).await
@@ -109,68 +435,708 @@ async fn handle_webhook(
         return Err((StatusCode::UNAUTHORIZED, "Invalid webhook signature".to_string()));
     }
 
-    // Process webhook
+    // Persist the raw delivery before processing so it can be replayed
+    let delivery_id = if delivery_id.is_empty() {
+        uuid::Uuid::new_v4().to_string()
+    } else {
+        delivery_id.to_string()
+    };
+    let delivery = WebhookDelivery::new(&delivery_id, event_type, &headers, &body);
+
+    let outcome = state.webhook_deliveries.record(&delivery).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to persist webhook delivery: {}", e)))?;
+
+    if outcome == RecordOutcome::AlreadyProcessed {
+        info!("Skipping already processed webhook delivery {}", delivery_id);
+        return Ok(Json(ProcessWebhookResponse {
+            success: true,
+            message: format!("Delivery {} already processed", delivery_id),
+            badge_updates: vec![],
+            processed_events: vec![],
+        }));
+    }
+
+    let response = process_delivery(&state, &delivery).await?;
+    Ok(Json(response))
+}
+
+// GitLab authenticates deliveries with a shared token rather than a signature
+async fn handle_gitlab_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<ProcessWebhookResponse>, (StatusCode, String)> {
+    let settings = state.config.gitlab.as_ref()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "GitLab is not configured".to_string()))?;
+
+    let token = headers
+        .get(GITLAB_TOKEN_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("");
+    if !gitlab::verify_token(token, &settings.webhook_secret) {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid GitLab webhook token".to_string()));
+    }
+
+    let event_type = headers
+        .get(GITLAB_EVENT_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("unknown");
+    info!("Received GitLab webhook: event={}", event_type);
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("gitlab_webhooks_total".to_string()).or_insert(0) += 1;
+    }
+
+    if event_type != MERGE_REQUEST_HOOK {
+        return Ok(Json(ProcessWebhookResponse {
+            success: true,
+            message: format!("Ignoring GitLab event {}", event_type),
+            badge_updates: vec![],
+            processed_events: vec![],
+        }));
+    }
+
+    let event: MergeRequestEvent = serde_json::from_str(&body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid merge request event: {}", e)))?;
+    let action = event.object_attributes.action.clone().unwrap_or_else(|| "unknown".to_string());
+
+    let message = match event.badge_request(&state.config) {
+        Some(request) => {
+            let job_id = state.badge_queue.enqueue(request).await
+                .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Badge update not queued: {}", e)))?;
+            format!("Verifying spec documents... (badge job {})", job_id)
+        }
+        None => "No spec documents to verify".to_string(),
+    };
+
+    Ok(Json(ProcessWebhookResponse {
+        success: true,
+        message,
+        badge_updates: vec![],
+        processed_events: vec![format!("merge_request_{}", action)],
+    }))
+}
+
+async fn handle_bitbucket_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<ProcessWebhookResponse>, (StatusCode, String)> {
+    let settings = state.config.bitbucket.as_ref()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Bitbucket is not configured".to_string()))?;
+
+    let signature = headers
+        .get(BITBUCKET_SIGNATURE_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("");
+    if !bitbucket::verify_signature(body.as_bytes(), signature, &settings.webhook_secret) {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid Bitbucket webhook signature".to_string()));
+    }
+
+    let event_key = headers
+        .get(BITBUCKET_EVENT_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("unknown");
+    info!("Received Bitbucket webhook: event={}", event_key);
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("bitbucket_webhooks_total".to_string()).or_insert(0) += 1;
+    }
+
+    if !event_key.starts_with("pullrequest:") {
+        return Ok(Json(ProcessWebhookResponse {
+            success: true,
+            message: format!("Ignoring Bitbucket event {}", event_key),
+            badge_updates: vec![],
+            processed_events: vec![],
+        }));
+    }
+
+    let event: PullRequestEvent = serde_json::from_str(&body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid pull request event: {}", e)))?;
+
+    let message = match event.badge_request(event_key, &state.config) {
+        Some(request) => {
+            let job_id = state.badge_queue.enqueue(request).await
+                .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Badge update not queued: {}", e)))?;
+            format!("Verifying spec documents... (badge job {})", job_id)
+        }
+        None => "No spec documents to verify".to_string(),
+    };
+
+    Ok(Json(ProcessWebhookResponse {
+        success: true,
+        message,
+        badge_updates: vec![],
+        processed_events: vec![event_key.replace(':', "_")],
+    }))
+}
+
+async fn process_delivery(
+    state: &AppState,
+    delivery: &WebhookDelivery,
+) -> Result<ProcessWebhookResponse, (StatusCode, String)> {
     let request = ProcessWebhookRequest {
-        payload: body,
-        signature: signature.to_string(),
-        event_type: event_type.to_string(),
-        delivery_id: delivery_id.to_string(),
+        payload: delivery.payload.clone(),
+        signature: delivery.signature().to_string(),
+        event_type: delivery.event_type.clone(),
+        delivery_id: delivery.delivery_id.clone(),
         installation_id: "".to_string(), // Will be extracted from payload
     };
 
-    let response = state.webhook_processor.process_webhook(request).await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Webhook processing failed: {}", e)))?;
+    let response = match state.webhook_processor.process_webhook(request).await {
+        Ok(response) => {
+            if let Err(e) = state.webhook_deliveries.mark_processed(&delivery.delivery_id).await {
+                warn!("Failed to mark webhook delivery {} processed: {}", delivery.delivery_id, e);
+            }
+            response
+        }
+        Err(e) => {
+            if let Err(log_error) = state.webhook_deliveries.mark_failed(&delivery.delivery_id, &e.to_string()).await {
+                error!("Failed to mark webhook delivery {} failed: {}", delivery.delivery_id, log_error);
+            }
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Webhook processing failed: {}", e)));
+        }
+    };
 
     // Update metrics
     {
         let mut metrics = state.metrics.write().await;
-        *metrics.entry(format!("webhook_{}", event_type)).or_insert(0) += 1;
+        *metrics.entry(format!("webhook_{}", delivery.event_type)).or_insert(0) += 1;
         *metrics.entry("webhook_total".to_string()).or_insert(0) += 1;
     }
 
-    Ok(Json(response))
+    Ok(response)
+}
+
+// Admin actions have already taken effect, so a failed write is only logged
+async fn record_audit(state: &AppState, event: AuditEvent) {
+    if let Err(e) = state.audit_log.record(event).await {
+        error!("Failed to write audit record: {}", e);
+    }
+}
+
+async fn replay_webhook(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path(delivery_id): Path<String>,
+) -> Result<Json<ProcessWebhookResponse>, (StatusCode, String)> {
+    let delivery = state.webhook_deliveries.get(&delivery_id).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load webhook delivery: {}", e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Webhook delivery {} not found", delivery_id)))?;
+
+    if delivery.delivery_status() == Some(DeliveryStatus::Processed) {
+        return Err((StatusCode::CONFLICT, format!("Webhook delivery {} was already processed", delivery_id)));
+    }
+
+    info!("Replaying webhook delivery {} (event={}, attempts={})", delivery_id, delivery.event_type, delivery.attempts);
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("webhook_replays_total".to_string()).or_insert(0) += 1;
+    }
+
+    let result = process_delivery(&state, &delivery).await;
+    let outcome = match &result {
+        Ok(response) => serde_json::json!({"success": response.success, "message": response.message}),
+        Err((_, message)) => serde_json::json!({"success": false, "message": message}),
+    };
+    record_audit(
+        &state,
+        AuditEvent::new(&caller.id, actions::WEBHOOK_REPLAYED, &format!("webhook_delivery/{}", delivery_id))
+            .with_before(&serde_json::json!({
+                "status": format!("{:?}", delivery.delivery_status()),
+                "attempts": delivery.attempts,
+            }))
+            .with_after(&outcome),
+    ).await;
+
+    Ok(Json(result?))
+}
+
+async fn list_installations(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Installation>>, (StatusCode, String)> {
+    Ok(Json(state.installations.list().await))
+}
+
+fn tenant_budgets(state: &AppState) -> Result<&BudgetStore, (StatusCode, String)> {
+    state.tenant_budgets.as_deref()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Cost governance is not configured".to_string()))
+}
+
+async fn get_tenant_usage(
+    State(state): State<Arc<AppState>>,
+    Path(tenant_id): Path<String>,
+) -> Result<Json<TenantUsage>, (StatusCode, String)> {
+    let usage = tenant_budgets(&state)?.usage(&tenant_id, chrono::Utc::now()).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load tenant usage: {}", e)))?;
+    Ok(Json(usage))
+}
+
+async fn set_tenant_budget(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path(tenant_id): Path<String>,
+    Json(budget): Json<TenantBudget>,
+) -> Result<Json<TenantUsage>, (StatusCode, String)> {
+    let invalid = |limit: Option<f64>| limit.map_or(false, |limit| !(limit >= 0.0));
+    if invalid(budget.daily_usd) || invalid(budget.monthly_usd)
+        || !(0.0..=100.0).contains(&budget.soft_threshold_percent) {
+        return Err((StatusCode::BAD_REQUEST, "Budget limits must be non-negative and the soft threshold a percentage".to_string()));
+    }
+
+    let budgets = tenant_budgets(&state)?;
+    let store_error = |e: cost_governance::CostGovernanceError| {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update tenant budget: {}", e))
+    };
+    let before = budgets.usage(&tenant_id, chrono::Utc::now()).await.map_err(store_error)?.budget;
+    budgets.set_budget(&tenant_id, &budget).await.map_err(store_error)?;
+    info!("Updated budget for tenant {} by {}: {:?}", tenant_id, caller.id, budget);
+    record_audit(
+        &state,
+        AuditEvent::new(&caller.id, actions::TENANT_BUDGET_UPDATED, &format!("tenant/{}", tenant_id))
+            .with_before(&before)
+            .with_after(&budget),
+    ).await;
+    let usage = budgets.usage(&tenant_id, chrono::Utc::now()).await.map_err(store_error)?;
+    Ok(Json(usage))
+}
+
+#[derive(Debug, Deserialize)]
+struct PauseRequest {
+    #[serde(default)]
+    reason: String,
+}
+
+fn pipeline_pauses(state: &AppState) -> Result<&PauseStore, (StatusCode, String)> {
+    state.pipeline_pauses.as_deref()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Pipeline control is not configured".to_string()))
+}
+
+fn parse_stage(stage: &str) -> Result<Stage, (StatusCode, String)> {
+    stage.parse().map_err(|e: pipeline_control::PipelineControlError| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+fn pause_resource(stage: Stage, scope: &PauseScope) -> String {
+    match scope {
+        PauseScope::Tenant(id) => format!("tenant/{}/{}", id, stage),
+        PauseScope::SourceSystem(name) => format!("source_system/{}/{}", name, stage),
+    }
+}
+
+async fn list_pipeline_pauses(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Pause>>, (StatusCode, String)> {
+    let pauses = pipeline_pauses(&state)?.list().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load pipeline pauses: {}", e)))?;
+    Ok(Json(pauses))
+}
+
+async fn pause_tenant_stage(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path((tenant_id, stage)): Path<(String, String)>,
+    Json(request): Json<PauseRequest>,
+) -> Result<Json<Pause>, (StatusCode, String)> {
+    pause_stage(&state, &caller, parse_stage(&stage)?, PauseScope::Tenant(tenant_id), request.reason).await
+}
+
+async fn resume_tenant_stage(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path((tenant_id, stage)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    resume_stage(&state, &caller, parse_stage(&stage)?, PauseScope::Tenant(tenant_id)).await
+}
+
+async fn pause_source_stage(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path((source_system, stage)): Path<(String, String)>,
+    Json(request): Json<PauseRequest>,
+) -> Result<Json<Pause>, (StatusCode, String)> {
+    pause_stage(&state, &caller, parse_stage(&stage)?, PauseScope::SourceSystem(source_system), request.reason).await
+}
+
+async fn resume_source_stage(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Path((source_system, stage)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    resume_stage(&state, &caller, parse_stage(&stage)?, PauseScope::SourceSystem(source_system)).await
+}
+
+async fn pause_stage(
+    state: &AppState,
+    caller: &Caller,
+    stage: Stage,
+    scope: PauseScope,
+    reason: String,
+) -> Result<Json<Pause>, (StatusCode, String)> {
+    let resource = pause_resource(stage, &scope);
+    let pause = Pause {
+        stage,
+        scope,
+        reason,
+        paused_by: caller.id.clone(),
+        paused_at: chrono::Utc::now(),
+    };
+    pipeline_pauses(state)?.pause(&pause).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to pause {}: {}", resource, e)))?;
+    warn!("Paused {} by {}: {}", resource, caller.id, pause.reason);
+    record_audit(
+        state,
+        AuditEvent::new(&caller.id, actions::PIPELINE_PAUSED, &resource).with_after(&pause),
+    ).await;
+    Ok(Json(pause))
+}
+
+async fn resume_stage(
+    state: &AppState,
+    caller: &Caller,
+    stage: Stage,
+    scope: PauseScope,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let resource = pause_resource(stage, &scope);
+    let lifted = pipeline_pauses(state)?.resume(stage, &scope).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to resume {}: {}", resource, e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("{} is not paused", resource)))?;
+    info!("Resumed {} by {}", resource, caller.id);
+    record_audit(
+        state,
+        AuditEvent::new(&caller.id, actions::PIPELINE_RESUMED, &resource).with_before(&lifted),
+    ).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn export_audit_bundle(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<AuditExportRequest>,
+) -> Result<Json<UploadedBundle>, (StatusCode, String)> {
+    let exporter = state.audit_exporter.as_deref()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Audit export is not configured".to_string()))?;
+    if request.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Nothing to export".to_string()));
+    }
+
+    let uploaded = exporter.export(&request, &state.sigstore_client).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Audit export failed: {:#}", e)))?;
+    info!("Exported audit bundle {} for {}#{} by {}", uploaded.bundle_id, request.repository, request.pull_request, caller.id);
+    record_audit(
+        &state,
+        AuditEvent::new(
+            &caller.id,
+            actions::AUDIT_BUNDLE_EXPORTED,
+            &format!("pull_request/{}/{}", request.repository, request.pull_request),
+        )
+        .with_after(&serde_json::json!({
+            "bundle_id": uploaded.bundle_id,
+            "key": uploaded.key,
+            "archive_sha256": uploaded.archive_sha256,
+        })),
+    ).await;
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("audit_exports_total".to_string()).or_insert(0) += 1;
+    }
+
+    Ok(Json(uploaded))
+}
+
+async fn attest_provenance(
+    State(state): State<Arc<AppState>>,
+    Extension(caller): Extension<Caller>,
+    Json(request): Json<ProvenanceRequest>,
+) -> Result<Json<ProvenanceAttestation>, (StatusCode, String)> {
+    let attestor = state.provenance_attestor.as_deref()
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Provenance signing is not configured".to_string()))?;
+    provenance::subject_sha256(&request.statement)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid provenance statement: {}", e)))?;
+
+    let attestation = attestor.attest(&request, &state.sigstore_client).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Provenance attestation failed: {:#}", e)))?;
+    info!("Attested provenance for artifact {} as {} by {}", attestation.artifact_id, attestation.entry_id, caller.id);
+    record_audit(
+        &state,
+        AuditEvent::new(&caller.id, actions::PROVENANCE_ATTESTED, &format!("proof_artifact/{}", attestation.artifact_id))
+            .with_after(&attestation),
+    ).await;
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("provenance_attestations_total".to_string()).or_insert(0) += 1;
+    }
+
+    Ok(Json(attestation))
 }
 
 async fn update_badge(
     State(state): State<Arc<AppState>>,
     Path((repo, pr)): Path<(String, String)>,
     Json(request): Json<BadgeStatusRequest>,
-) -> Result<Json<BadgeStatusResponse>, (StatusCode, String)> {
-    info!("Updating badge for repo={}, pr={}", repo, pr);
+) -> Result<(StatusCode, Json<BadgeJobAccepted>), (StatusCode, String)> {
+    info!("Queueing badge update for repo={}, pr={}", repo, pr);
 
-    let response = state.badge_manager.update_badge_status(request).await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Badge update failed: {}", e)))?;
+    let job_id = state.badge_queue.enqueue(request).await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, format!("Badge update not queued: {}", e)))?;
+
+    Ok((StatusCode::ACCEPTED, Json(BadgeJobAccepted::new(job_id))))
+}
+
+async fn get_badge_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<BadgeJob>, (StatusCode, String)> {
+    state.badge_queue.get(&id).await
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Badge job {} not found", id)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BadgeHistoryQuery {
+    provider: ScmKind,
+    /// Only the badges shown on this commit
+    commit_sha: Option<String>,
+}
+
+async fn get_badge_history(
+    State(state): State<Arc<AppState>>,
+    Path((repo, pr)): Path<(String, String)>,
+    Query(query): Query<BadgeHistoryQuery>,
+) -> Result<Json<Vec<BadgeHistoryEntry>>, (StatusCode, String)> {
+    let history = state.badge_manager
+        .get_badge_history(query.provider, &repo, &pr, query.commit_sha.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load badge history: {}", e)))?;
+    Ok(Json(history))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ProofStatusQuery {
+    provider: ScmKind,
+    repo: String,
+    commit: String,
+}
+
+// The commit's latest proof result for CI systems that can't read commit
+// statuses. Pollers send the ETag back in `If-None-Match` and get a bodiless
+// 304 until the result changes.
+async fn get_proof_status(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ProofStatusQuery>,
+) -> Response {
+    if query.repo.is_empty() || query.commit.is_empty() {
+        return (StatusCode::BAD_REQUEST, "repo and commit are required".to_string()).into_response();
+    }
 
-    // Update metrics
     {
         let mut metrics = state.metrics.write().await;
-        *metrics.entry(format!("badge_{:?}", response.status)).or_insert(0) += 1;
-        *metrics.entry("badge_total".to_string()).or_insert(0) += 1;
+        *metrics.entry("proof_status_requests_total".to_string()).or_insert(0) += 1;
     }
 
-    Ok(Json(response))
+    let status = match state.proof_statuses.get(query.provider, &query.repo, &query.commit).await {
+        Ok(Some(status)) => status,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("No proof status for {}@{}", query.repo, query.commit),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to load proof status: {}", e)).into_response();
+        }
+    };
+
+    let etag = status.etag();
+    let not_modified = headers.get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|if_none_match| etag_matches(if_none_match, &etag));
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        Json(ProofStatusResponse::from(status)).into_response()
+    };
+    let response_headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        response_headers.insert(header::ETAG, etag);
+    }
+    // Caches must revalidate, so a re-run proof shows up on the next poll
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("private, no-cache"));
+    response
+}
+
+async fn report_coverage(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CoverageReportRequest>,
+) -> Result<Json<BadgeStatus>, (StatusCode, String)> {
+    if request.completed > request.total || request.proven + request.failed != request.completed {
+        return Err((StatusCode::BAD_REQUEST, "Inconsistent coverage counts".to_string()));
+    }
+
+    let status = state.badge_manager.report_coverage(&request).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Coverage report failed: {}", e)))?;
+
+    state.widget_store.record(&request, &state.config).await;
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("badge_coverage_reports_total".to_string()).or_insert(0) += 1;
+    }
+
+    Ok(Json(status))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoverageRequest {
+    pub spec_document_ids: Vec<String>,
+}
+
+async fn compute_coverage(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CoverageRequest>,
+) -> Result<Json<CoverageReport>, (StatusCode, String)> {
+    if request.spec_document_ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "spec_document_ids must not be empty".to_string()));
+    }
+
+    let report = state.coverage.compute(&request.spec_document_ids).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Coverage computation failed: {}", e)))?;
+
+    Ok(Json(report))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentPreviewRequest {
+    pub document: SpecDocumentModel,
+    pub invariants: Vec<InvariantModel>,
 }
 
+// Payloads are checked against the published schema first, so a malformed
+// document is rejected with the paths of every offending field
+async fn preview_document(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Json<DocumentPreview>, (StatusCode, String)> {
+    validation::validate_spec_document(&body["document"])
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    for invariant in body["invariants"].as_array().into_iter().flatten() {
+        validation::validate_invariant(invariant)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    }
+    let request: DocumentPreviewRequest = serde_json::from_value(body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid preview request: {}", e)))?;
+
+    if let Some(invariant) = request.invariants.iter().find(|i| i.source_document_id != request.document.id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invariant {} does not belong to document {}", invariant.id, request.document.id),
+        ));
+    }
+
+    let preview = build_document_preview(&request.document, &request.invariants);
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("document_preview_total".to_string()).or_insert(0) += 1;
+    }
+
+    Ok(Json(preview))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidateConfigResponse {
+    pub valid: bool,
+    pub errors: Vec<ConfigIssue>,
+    /// The config as the app reads it, with defaults filled in
+    pub config: Option<RepoConfig>,
+}
+
+// Checks a `.spec-to-proof.yml` posted as the raw request body, so CI can
+// reject a broken config before it merges
+async fn validate_repo_config(
+    State(state): State<Arc<AppState>>,
+    body: String,
+) -> (StatusCode, Json<ValidateConfigResponse>) {
+    let response = match RepoConfig::validate(&body) {
+        Ok(config) => ValidateConfigResponse { valid: true, errors: Vec::new(), config: Some(config) },
+        Err(errors) => ValidateConfigResponse { valid: false, errors, config: None },
+    };
+
+    {
+        let mut metrics = state.metrics.write().await;
+        let outcome = if response.valid { "valid" } else { "invalid" };
+        *metrics.entry(format!("repo_config_validations_{}_total", outcome)).or_insert(0) += 1;
+    }
+
+    let status = if response.valid { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+    (status, Json(response))
+}
+
+// Liveness: the process is up and serving requests
 async fn health_check(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<HealthCheckResponse>, (StatusCode, String)> {
-    let checks = HashMap::new(); // TODO: Implement actual health checks
+    Ok(Json(health_response(&state, &state.health.liveness())))
+}
 
-    let response = HealthCheckResponse {
-        status: "healthy".to_string(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime: "0s".to_string(), // TODO: Track actual uptime
-        checks: checks.into_iter().map(|(k, v)| (k, v.to_string())).collect(),
-    };
+// Readiness: every dependency check, with 503 while a critical one fails
+async fn readiness_check(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<HealthCheckResponse>) {
+    let report = state.health.readiness().await;
+    if !report.is_ready() {
+        warn!("Readiness check failed: {:?}", report.checks);
+    }
+    let status = if report.is_ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(health_response(&state, &report)))
+}
 
-    Ok(Json(response))
+pub fn health_response(state: &AppState, report: &HealthReport) -> HealthCheckResponse {
+    let checks = report
+        .checks
+        .iter()
+        .map(|check| {
+            let mut summary = format!("{} ({}ms)", check.status.as_str(), check.latency_ms);
+            if let Some(message) = &check.message {
+                summary.push_str(": ");
+                summary.push_str(message);
+            }
+            (check.name.clone(), summary)
+        })
+        .collect();
+
+    HealthCheckResponse {
+        status: report.status.as_str().to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime: format!("{}s", state.started_at.elapsed().as_secs()),
+        checks,
+    }
 }
 
 async fn get_metrics(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<HashMap<String, u64>>, (StatusCode, String)> {
-    let metrics = state.metrics.read().await;
-    Ok(Json(metrics.clone()))
+    let mut metrics = state.metrics.read().await.clone();
+    for snapshot in circuit_breaker::snapshots() {
+        let state = match snapshot.state {
+            CircuitState::Closed => 0,
+            CircuitState::HalfOpen => 1,
+            CircuitState::Open => 2,
+        };
+        metrics.insert(format!("circuit_breaker_{}_state", snapshot.name), state);
+        metrics.insert(format!("circuit_breaker_{}_rejected_total", snapshot.name), snapshot.rejected_total);
+        metrics.insert(format!("circuit_breaker_{}_opened_total", snapshot.name), snapshot.opened_total);
+    }
+    Ok(Json(metrics))
 }
 
 #[cfg(test)]
@@ -191,5 +1157,20 @@ mod tests {
         let state = AppState::new(config).await.unwrap();
         let response = health_check(State(Arc::new(state))).await;
         assert!(response.is_ok());
+        assert_eq!(response.unwrap().status, "healthy");
+    }
+
+    #[tokio::test]
+    async fn test_health_response_summarises_checks() {
+        let state = AppState::new(GitHubAppConfig::default()).await.unwrap();
+        let report = HealthChecker::new(Duration::from_millis(100))
+            .with_check("github_api", true, || async { Err("bad credentials".to_string()) })
+            .readiness()
+            .await;
+
+        let response = health_response(&state, &report);
+        assert_eq!(response.status, "unhealthy");
+        assert!(response.checks["github_api"].starts_with("unhealthy ("));
+        assert!(response.checks["github_api"].ends_with("ms): bad credentials"));
     }
 } 
\ No newline at end of file
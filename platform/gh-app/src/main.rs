@@ -1,3 +1,4 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
@@ -6,57 +7,81 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use clap::Parser;
 use anyhow::Result;
 
-use crate::config::GitHubAppConfig;
+use crate::config::{ConfigOverrides, GitHubAppConfig};
 use crate::server::Server;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Configuration file path
-    #[arg(short, long, default_value = "config/default.yaml")]
-    config: String,
+    /// Configuration file path; config/default.yaml is read when present
+    #[arg(short, long, env = "GH_APP_CONFIG")]
+    config: Option<PathBuf>,
     
-    /// Log level
-    #[arg(long, default_value = "info")]
-    log_level: String,
+    /// Log level, overriding the configuration
+    #[arg(long)]
+    log_level: Option<String>,
     
-    /// Log format (json, text)
-    #[arg(long, default_value = "json")]
-    log_format: String,
+    /// Log format (json, text), overriding the configuration
+    #[arg(long)]
+    log_format: Option<String>,
     
-    /// Host to bind to
-    #[arg(long, default_value = "0.0.0.0")]
-    host: String,
+    /// Host to bind to, overriding the configuration
+    #[arg(long)]
+    host: Option<String>,
     
-    /// Port to bind to
-    #[arg(long, default_value = "8080")]
-    port: u16,
+    /// Port to bind to, overriding the configuration
+    #[arg(long)]
+    port: Option<u16>,
     
     /// Enable debug mode
     #[arg(long)]
     debug: bool,
 }
 
+const DEFAULT_CONFIG_PATH: &str = "config/default.yaml";
+
+impl Args {
+    fn config_path(&self) -> Option<PathBuf> {
+        self.config.clone().or_else(|| {
+            let default = Path::new(DEFAULT_CONFIG_PATH);
+            default.exists().then(|| default.to_path_buf())
+        })
+    }
+    
+    fn overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            host: self.host.clone(),
+            port: self.port,
+            log_level: self.log_level.clone(),
+            log_format: self.log_format.clone(),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse command line arguments
     let args = Args::parse();
     
+    // Load configuration; logging follows its settings, so load errors are
+    // reported on exit instead of logged
+    let config_path = args.config_path();
+    let config = GitHubAppConfig::load(config_path.as_deref(), &args.overrides()).await?;
+    
     // Initialize logging
-    init_logging(&args.log_level, &args.log_format, args.debug)?;
+    init_logging(&config.log_level, &config.log_format, args.debug)?;
     
     info!("Starting Spec-to-Proof GitHub App");
     info!("Version: {}", env!("CARGO_PKG_VERSION"));
-    info!("Config path: {}", args.config);
-    info!("Log level: {}", args.log_level);
-    info!("Log format: {}", args.log_format);
-    
-    // Load configuration
-    let config = load_config(&args.config).await?;
-    info!("Configuration loaded successfully");
+    match &config_path {
+        Some(path) => info!("Config path: {}", path.display()),
+        None => info!("No config file; using defaults and environment"),
+    }
+    info!("Log level: {}", config.log_level);
+    info!("Log format: {}", config.log_format);
     
     // Create and start server
-    let server = Server::new(config).await?;
+    let server = Server::with_config_file(config, config_path.as_deref()).await?;
     info!("Server created successfully");
     
     // Set up graceful shutdown
@@ -137,79 +162,19 @@ fn init_logging(level: &str, format: &str, debug: bool) -> Result<()> {
     Ok(())
 }
 
-async fn load_config(config_path: &str) -> Result<GitHubAppConfig> {
-    // For now, create a default config
-    // TODO: Implement actual config loading from file
-    let mut config = GitHubAppConfig::default();
-    
-    // Override with environment variables if present
-    if let Ok(app_id) = std::env::var("GH_APP_ID") {
-        config.app_id = app_id;
-    }
-    
-    if let Ok(private_key) = std::env::var("GH_APP_PRIVATE_KEY") {
-        config.private_key = private_key;
-    }
-    
-    if let Ok(webhook_secret) = std::env::var("GH_APP_WEBHOOK_SECRET") {
-        config.webhook_secret = webhook_secret;
-    }
-    
-    if let Ok(installation_id) = std::env::var("GH_APP_INSTALLATION_ID") {
-        config.installation_id = installation_id;
-    }
-    
-    if let Ok(host) = std::env::var("GH_APP_HOST") {
-        config.host = host;
-    }
-    
-    if let Ok(port) = std::env::var("GH_APP_PORT") {
-        if let Ok(port_num) = port.parse::<u16>() {
-            config.port = port_num;
-        }
-    }
-    
-    // Validate configuration
-    config.validate()?;
-    
-    info!("Configuration loaded from environment variables");
-    Ok(config)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     
     #[test]
     fn test_args_parsing() {
-        let args = Args::parse_from(&["gh-app", "--config", "test.yaml"]);
-        assert_eq!(args.config, "test.yaml");
-        assert_eq!(args.log_level, "info");
-        assert_eq!(args.log_format, "json");
-    }
-    
-    #[tokio::test]
-    async fn test_config_loading() {
-        // Set environment variables for testing
-        std::env::set_var("GH_APP_ID", "12345");
-        std::env::set_var("GH_APP_PRIVATE_KEY", "test-key");
-        std::env::set_var("GH_APP_WEBHOOK_SECRET", "test-secret");
-        std::env::set_var("GH_APP_INSTALLATION_ID", "67890");
-        
-        let config = load_config("test.yaml").await;
-        assert!(config.is_ok());
-        
-        let config = config.unwrap();
-        assert_eq!(config.app_id, "12345");
-        assert_eq!(config.private_key, "test-key");
-        assert_eq!(config.webhook_secret, "test-secret");
-        assert_eq!(config.installation_id, "67890");
+        let args = Args::parse_from(["gh-app", "--config", "test.yaml", "--port", "9090"]);
+        assert_eq!(args.config_path(), Some(PathBuf::from("test.yaml")));
         
-        // Clean up environment variables
-        std::env::remove_var("GH_APP_ID");
-        std::env::remove_var("GH_APP_PRIVATE_KEY");
-        std::env::remove_var("GH_APP_WEBHOOK_SECRET");
-        std::env::remove_var("GH_APP_INSTALLATION_ID");
+        let overrides = args.overrides();
+        assert_eq!(overrides.port, Some(9090));
+        assert_eq!(overrides.host, None);
+        assert_eq!(overrides.log_level, None);
     }
     
     #[test]
@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+
+use crate::coverage::{CoverageReport, InvariantOutcome, InvariantResult, PriorityTier};
+use crate::proto::gh_app::v1::BadgeStatus;
+
+/// What has to be proven before a pull request may merge, declared in the
+/// `policy` section of a repository's `.spec-to-proof.yml`. A repository
+/// without a policy keeps the all-or-nothing rule of
+/// `CoverageReport::badge_status`.
+///
+/// ```yaml
+/// policy:
+///   required_documents: [DOC-123]
+///   required_tags: [security]
+///   min_coverage:
+///     critical: 100
+///     high: 80
+///   exempt_paths: ["docs/**", "*.md"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MergePolicy {
+    /// Spec documents whose invariants must all be proven
+    pub required_documents: Vec<String>,
+    /// Invariant tags whose invariants must all be proven
+    pub required_tags: Vec<String>,
+    /// Minimum coverage percentage per priority tier; tiers without
+    /// invariants are met
+    pub min_coverage: BTreeMap<PriorityTier, f64>,
+    /// Globs (`*`, `?`, `**`) of paths that need no proofs; a pull request
+    /// changing only exempt paths passes
+    pub exempt_paths: Vec<String>,
+}
+
+/// The badge a policy computes for a pull request
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyDecision {
+    pub status: BadgeStatus,
+    /// Requirements not met, failed ones before pending ones
+    pub unmet: Vec<String>,
+    /// Every changed path is exempt
+    pub exempt: bool,
+}
+
+impl MergePolicy {
+    pub fn has_exempt_paths(&self) -> bool {
+        !self.exempt_paths.is_empty()
+    }
+
+    pub fn is_exempt(&self, path: &str) -> bool {
+        self.exempt_paths.iter().any(|pattern| glob_matches(pattern, path))
+    }
+
+    /// Decides the badge from the required invariants and tier thresholds.
+    /// Invariants the policy doesn't require don't block the merge.
+    pub fn evaluate(&self, report: &CoverageReport, results: &[InvariantResult], changed_files: &[String]) -> PolicyDecision {
+        if !changed_files.is_empty() && changed_files.iter().all(|path| self.is_exempt(path)) {
+            return PolicyDecision { status: BadgeStatus::Success, unmet: Vec::new(), exempt: true };
+        }
+
+        let mut failed = Vec::new();
+        let mut pending = Vec::new();
+
+        // Required documents with no invariants yet can't be met
+        let covered: BTreeSet<&str> = results.iter().map(|r| r.document_id.as_str()).collect();
+        for document_id in &self.required_documents {
+            if !covered.contains(document_id.as_str()) {
+                pending.push(format!("{} has no invariants", document_id));
+            }
+        }
+
+        let mut required_failed = 0;
+        let mut required_pending = 0;
+        for result in results.iter().filter(|r| self.requires(r)) {
+            match result.outcome {
+                InvariantOutcome::Proven => {}
+                InvariantOutcome::Failed => required_failed += 1,
+                InvariantOutcome::Pending => required_pending += 1,
+            }
+        }
+        if required_failed > 0 {
+            failed.push(format!("{} required invariant(s) failed", required_failed));
+        }
+        if required_pending > 0 {
+            pending.push(format!("{} required invariant(s) pending", required_pending));
+        }
+
+        for (tier, threshold) in &self.min_coverage {
+            let Some(counts) = report.tiers.get(tier) else {
+                continue;
+            };
+            if counts.coverage_percentage >= *threshold {
+                continue;
+            }
+            let unmet = format!(
+                "{} coverage {:.1}% < {}%",
+                tier_name(*tier), counts.coverage_percentage, threshold
+            );
+            // Still reachable if enough pending invariants get proven
+            let reachable = f64::from(counts.proven + counts.pending) * 100.0 >= threshold * f64::from(counts.total);
+            if reachable {
+                pending.push(unmet);
+            } else {
+                failed.push(unmet);
+            }
+        }
+
+        let status = if !failed.is_empty() {
+            BadgeStatus::Failure
+        } else if !pending.is_empty() {
+            BadgeStatus::Pending
+        } else {
+            BadgeStatus::Success
+        };
+        failed.extend(pending);
+
+        PolicyDecision { status, unmet: failed, exempt: false }
+    }
+
+    fn requires(&self, result: &InvariantResult) -> bool {
+        self.required_documents.contains(&result.document_id)
+            || result.tags.iter().any(|tag| self.required_tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+    }
+}
+
+impl PolicyDecision {
+    /// Commit status description; GitHub cuts these at 140 characters, so
+    /// only the first unmet requirement is spelled out
+    pub fn description(&self, report: &CoverageReport) -> String {
+        if self.exempt {
+            return "Spec-to-Proof: all changed paths are exempt by policy".to_string();
+        }
+        match self.unmet.split_first() {
+            None => format!("{}; policy met", report.description()),
+            Some((first, [])) => format!("{}; policy: {}", report.description(), first),
+            Some((first, rest)) => format!("{}; policy: {} (+{} more)", report.description(), first, rest.len()),
+        }
+    }
+}
+
+fn tier_name(tier: PriorityTier) -> &'static str {
+    match tier {
+        PriorityTier::Critical => "critical",
+        PriorityTier::High => "high",
+        PriorityTier::Medium => "medium",
+        PriorityTier::Low => "low",
+        PriorityTier::Unspecified => "unspecified",
+    }
+}
+
+/// Matches a repository path against a glob: `*` and `?` stay within a
+/// path segment, `**` spans any number of segments, and a trailing `/`
+/// matches everything below a directory
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_start_matches("./");
+    let pattern = match pattern.strip_suffix('/') {
+        Some(directory) => format!("{}/**", directory),
+        None => pattern.to_string(),
+    };
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    // A pattern without a slash matches the file name in any directory
+    if pattern.len() == 1 && pattern[0] != "**" {
+        return path.last().is_some_and(|name| segment_matches(pattern[0].as_bytes(), name.as_bytes()));
+    }
+    segments_match(&pattern, &path)
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path)) => segment_matches(segment.as_bytes(), name.as_bytes()) && segments_match(rest, path),
+            None => false,
+        },
+    }
+}
+
+fn segment_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| segment_matches(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && segment_matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && segment_matches(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coverage::CoverageCounts;
+
+    fn result(document_id: &str, tags: &[&str], outcome: InvariantOutcome) -> InvariantResult {
+        InvariantResult {
+            invariant_id: format!("inv_{}", tags.join("_")),
+            document_id: document_id.to_string(),
+            description: String::new(),
+            priority: PriorityTier::High,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            outcome,
+            confidence_score: 0.9,
+            artifact_id: None,
+            duration_ms: None,
+        }
+    }
+
+    fn counts(total: u32, proven: u32, failed: u32) -> CoverageCounts {
+        CoverageCounts {
+            total,
+            proven,
+            failed,
+            pending: total - proven - failed,
+            coverage_percentage: f64::from(proven) * 100.0 / f64::from(total),
+        }
+    }
+
+    #[test]
+    fn test_only_required_invariants_block() {
+        let policy = MergePolicy {
+            required_tags: vec!["security".to_string()],
+            ..Default::default()
+        };
+        let report = CoverageReport::default();
+        let mut results = vec![
+            result("DOC-1", &["security"], InvariantOutcome::Proven),
+            result("DOC-1", &["ux"], InvariantOutcome::Failed),
+        ];
+
+        let decision = policy.evaluate(&report, &results, &[]);
+        assert_eq!(decision.status.clone() as i32, BadgeStatus::Success as i32);
+
+        results.push(result("DOC-2", &["Security"], InvariantOutcome::Pending));
+        let decision = policy.evaluate(&report, &results, &[]);
+        assert_eq!(decision.status.clone() as i32, BadgeStatus::Pending as i32);
+        assert_eq!(decision.unmet, vec!["1 required invariant(s) pending".to_string()]);
+    }
+
+    #[test]
+    fn test_min_coverage_per_tier() {
+        let policy = MergePolicy {
+            min_coverage: BTreeMap::from([(PriorityTier::Critical, 100.0), (PriorityTier::High, 50.0)]),
+            ..Default::default()
+        };
+        let mut report = CoverageReport {
+            overall: counts(6, 3, 1),
+            tiers: BTreeMap::from([(PriorityTier::Critical, counts(2, 1, 0)), (PriorityTier::High, counts(4, 2, 1))]),
+            ..Default::default()
+        };
+
+        let decision = policy.evaluate(&report, &[], &[]);
+        assert_eq!(decision.status.clone() as i32, BadgeStatus::Pending as i32);
+        assert_eq!(
+            decision.description(&report),
+            "Spec-to-Proof: 3/6 invariants proven (50.0%), critical 1/2; policy: critical coverage 50.0% < 100%"
+        );
+
+        // A failed critical invariant makes 100% unreachable
+        report.tiers.insert(PriorityTier::Critical, counts(2, 1, 1));
+        assert_eq!(policy.evaluate(&report, &[], &[]).status as i32, BadgeStatus::Failure as i32);
+
+        report.tiers.insert(PriorityTier::Critical, counts(2, 2, 0));
+        let decision = policy.evaluate(&report, &[], &[]);
+        assert_eq!(decision.status.clone() as i32, BadgeStatus::Success as i32);
+        assert!(decision.description(&report).ends_with("; policy met"));
+    }
+
+    #[test]
+    fn test_exempt_paths() {
+        let policy = MergePolicy {
+            required_documents: vec!["DOC-1".to_string()],
+            exempt_paths: vec!["docs/".to_string(), "*.md".to_string(), ".github/**/*.yml".to_string()],
+            ..Default::default()
+        };
+        let report = CoverageReport::default();
+
+        let exempt = policy.evaluate(&report, &[], &["docs/guide/intro.txt".to_string(), "src/README.md".to_string()]);
+        assert!(exempt.exempt);
+        assert_eq!(exempt.status.clone() as i32, BadgeStatus::Success as i32);
+
+        let decision = policy.evaluate(&report, &[], &["docs/intro.txt".to_string(), "src/lib.rs".to_string()]);
+        assert_eq!(decision.unmet, vec!["DOC-1 has no invariants".to_string()]);
+
+        assert!(glob_matches(".github/**/*.yml", ".github/workflows/ci.yml"));
+        assert!(glob_matches(".github/**/*.yml", ".github/dependabot.yml"));
+        assert!(!glob_matches("docs/*", "docs/guide/intro.txt"));
+        assert!(glob_matches("src/?ib.rs", "src/lib.rs"));
+    }
+}
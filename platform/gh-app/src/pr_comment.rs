@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::coverage::{CoverageReport, InvariantOutcome, InvariantResult};
+use crate::github::GitHubClient;
+
+/// Hidden marker identifying the app's comment, so it is edited in place
+pub const COMMENT_MARKER: &str = "<!-- spec-to-proof:proof-table -->";
+
+// Keeps the comment well under GitHub's 65536 character limit
+const MAX_ROWS: usize = 200;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrCommentSettings {
+    /// Repositories ("owner/name") that get the comment; "*" enables it
+    /// everywhere
+    #[serde(default)]
+    pub repositories: Vec<String>,
+}
+
+impl PrCommentSettings {
+    pub fn enabled_for(&self, repository: &str) -> bool {
+        self.repositories.iter().any(|r| r == "*" || r.eq_ignore_ascii_case(repository))
+    }
+}
+
+/// Posts a table of per-invariant proof results on pull requests and keeps
+/// it up to date as results change
+#[derive(Debug)]
+pub struct PrCommentReporter {
+    settings: PrCommentSettings,
+    artifact_base_url: String,
+    // Comment id and last body per pull request, so unchanged results
+    // don't cost an API call
+    posted: RwLock<HashMap<String, (u64, String)>>,
+}
+
+impl PrCommentReporter {
+    pub fn new(settings: &PrCommentSettings, artifact_base_url: &str) -> Self {
+        Self {
+            settings: settings.clone(),
+            artifact_base_url: artifact_base_url.to_string(),
+            posted: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn enabled_for(&self, repository: &str) -> bool {
+        self.settings.enabled_for(repository)
+    }
+
+    /// Creates or updates the comment; returns whether anything was posted
+    pub async fn report(
+        &self,
+        github: &mut GitHubClient,
+        repo: &str,
+        pr_number: &str,
+        commit_sha: &str,
+        report: &CoverageReport,
+        results: &[InvariantResult],
+    ) -> Result<bool> {
+        let body = render_comment(report, results, commit_sha, &self.artifact_base_url);
+        let key = format!("{}#{}", repo, pr_number);
+
+        let known = self.posted.read().await.get(&key).cloned();
+        let comment_id = match known {
+            Some((_, previous)) if previous == body => return Ok(false),
+            Some((comment_id, _)) => Some(comment_id),
+            // Not posted by this process; look for one left by an earlier run
+            None => github.list_issue_comments(repo, pr_number).await?
+                .into_iter()
+                .find(|comment| comment.body.starts_with(COMMENT_MARKER))
+                .map(|comment| comment.id),
+        };
+
+        let comment_id = match comment_id {
+            Some(comment_id) => {
+                github.update_issue_comment(repo, comment_id, &body).await?;
+                comment_id
+            }
+            None => github.create_issue_comment(repo, pr_number, &body).await?.id,
+        };
+
+        info!("Posted proof results comment on {}", key);
+        self.posted.write().await.insert(key, (comment_id, body));
+        Ok(true)
+    }
+}
+
+/// Markdown body of the comment, starting with [`COMMENT_MARKER`]
+pub fn render_comment(
+    report: &CoverageReport,
+    results: &[InvariantResult],
+    commit_sha: &str,
+    artifact_base_url: &str,
+) -> String {
+    let mut body = format!("{}\n### Spec-to-Proof verification\n\n", COMMENT_MARKER);
+    body.push_str(&format!("{} at `{}`\n", report.description(), short_sha(commit_sha)));
+
+    if results.is_empty() {
+        return body;
+    }
+
+    body.push_str("\n| Invariant | Status | Proof time | Confidence | Artifact |\n");
+    body.push_str("|---|---|---|---|---|\n");
+    for result in results.iter().take(MAX_ROWS) {
+        let artifact = match &result.artifact_id {
+            Some(id) => format!("[{}]({}?artifacts={})", short_sha(id), artifact_base_url, id),
+            None => "—".to_string(),
+        };
+        body.push_str(&format!(
+            "| {} | {} | {} | {:.0}% | {} |\n",
+            table_cell(&result.description),
+            outcome_label(result.outcome),
+            result.duration_ms.map(format_duration).unwrap_or_else(|| "—".to_string()),
+            result.confidence_score * 100.0,
+            artifact,
+        ));
+    }
+    if results.len() > MAX_ROWS {
+        body.push_str(&format!("\n…and {} more invariants\n", results.len() - MAX_ROWS));
+    }
+
+    body
+}
+
+fn outcome_label(outcome: InvariantOutcome) -> &'static str {
+    match outcome {
+        InvariantOutcome::Proven => "✅ Proven",
+        InvariantOutcome::Failed => "❌ Failed",
+        InvariantOutcome::Pending => "⏳ Pending",
+    }
+}
+
+fn format_duration(ms: i64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format!("{}m {}s", ms / 60_000, (ms % 60_000) / 1000)
+    }
+}
+
+fn short_sha(id: &str) -> &str {
+    id.get(..7).unwrap_or(id)
+}
+
+// Pipes and newlines would break the table row
+fn table_cell(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coverage::CoverageCounts;
+
+    fn result(description: &str, outcome: InvariantOutcome, artifact_id: Option<&str>, duration_ms: Option<i64>) -> InvariantResult {
+        InvariantResult {
+            invariant_id: "inv_1".to_string(),
+            document_id: "DOC-1".to_string(),
+            description: description.to_string(),
+            priority: Default::default(),
+            tags: Vec::new(),
+            outcome,
+            confidence_score: 0.87,
+            artifact_id: artifact_id.map(str::to_string),
+            duration_ms,
+        }
+    }
+
+    #[test]
+    fn test_render_comment() {
+        let report = CoverageReport {
+            spec_document_ids: vec!["DOC-1".to_string()],
+            overall: CoverageCounts { total: 2, proven: 1, failed: 0, pending: 1, coverage_percentage: 50.0 },
+            ..Default::default()
+        };
+        let results = vec![
+            result("balance | never\nnegative", InvariantOutcome::Proven, Some("abcdef123456"), Some(1500)),
+            result("refunds within 30 days", InvariantOutcome::Pending, None, None),
+        ];
+
+        let body = render_comment(&report, &results, "0123456789abcdef", "https://example.com/v");
+
+        assert!(body.starts_with(COMMENT_MARKER));
+        assert!(body.contains("Spec-to-Proof: 1/2 invariants proven (50.0%) at `0123456`"));
+        assert!(body.contains("| balance \\| never negative | ✅ Proven | 1.5s | 87% | [abcdef1](https://example.com/v?artifacts=abcdef123456) |"));
+        assert!(body.contains("| refunds within 30 days | ⏳ Pending | — | 87% | — |"));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(250), "250ms");
+        assert_eq!(format_duration(12_345), "12.3s");
+        assert_eq!(format_duration(125_000), "2m 5s");
+    }
+
+    #[test]
+    fn test_enabled_per_repository() {
+        let settings = PrCommentSettings { repositories: vec!["acme/payments".to_string()] };
+        assert!(settings.enabled_for("Acme/Payments"));
+        assert!(!settings.enabled_for("acme/ledger"));
+        assert!(PrCommentSettings { repositories: vec!["*".to_string()] }.enabled_for("acme/ledger"));
+        assert!(!PrCommentSettings::default().enabled_for("acme/payments"));
+    }
+}
@@ -0,0 +1,291 @@
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use prost::Message;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use storage::{repository_or_memory, Entity, ExpectedVersion, InMemoryRepository, Repository, StorageSettings};
+
+use crate::badge_history::status_name;
+use crate::coverage::CoverageReport;
+use crate::proto::gh_app::v1::*;
+
+/// The latest proof result for one commit, kept so CI systems other than
+/// the commit's host can gate on it
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CommitProofStatus {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub provider: String,
+    #[prost(string, tag = "3")]
+    pub repository_id: String,
+    #[prost(string, tag = "4")]
+    pub commit_sha: String,
+    /// Empty for commits verified from a push
+    #[prost(string, tag = "5")]
+    pub pull_request_id: String,
+    #[prost(int32, tag = "6")]
+    pub status: i32,
+    #[prost(string, tag = "7")]
+    pub description: String,
+    #[prost(double, tag = "8")]
+    pub coverage_percentage: f64,
+    #[prost(uint32, tag = "9")]
+    pub invariants_proven: u32,
+    #[prost(uint32, tag = "10")]
+    pub invariants_failed: u32,
+    #[prost(uint32, tag = "11")]
+    pub total_invariants: u32,
+    #[prost(message, repeated, tag = "12")]
+    pub artifacts: Vec<ProofArtifactRecord>,
+    #[prost(string, tag = "13")]
+    pub target_url: String,
+    #[prost(int64, tag = "14")]
+    pub updated_at_ms: i64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProofArtifactRecord {
+    #[prost(string, tag = "1")]
+    pub artifact_id: String,
+    #[prost(string, tag = "2")]
+    pub spec_document_id: String,
+    #[prost(string, tag = "3")]
+    pub status: String,
+    #[prost(string, tag = "4")]
+    pub content_hash: String,
+    #[prost(string, tag = "5")]
+    pub proof_hash: String,
+    #[prost(string, tag = "6")]
+    pub rekor_entry_id: String,
+    #[prost(string, tag = "7")]
+    pub provenance_entry_id: String,
+}
+
+impl CommitProofStatus {
+    pub fn new(request: &BadgeStatusRequest, response: &BadgeStatusResponse, coverage: Option<&CoverageReport>) -> Self {
+        Self {
+            id: commit_key(request.provider.as_str(), &request.repository_id, &request.commit_sha),
+            provider: request.provider.as_str().to_string(),
+            repository_id: request.repository_id.clone(),
+            commit_sha: request.commit_sha.clone(),
+            pull_request_id: request.pull_request_id.clone(),
+            status: response.status.clone() as i32,
+            description: response.description.clone(),
+            coverage_percentage: coverage.map(|report| report.overall.coverage_percentage).unwrap_or_default(),
+            invariants_proven: coverage.map(|report| report.overall.proven).unwrap_or_default(),
+            invariants_failed: coverage.map(|report| report.overall.failed).unwrap_or_default(),
+            total_invariants: coverage.map(|report| report.overall.total).unwrap_or_default(),
+            artifacts: response.proof_artifacts.iter().map(|artifact| ProofArtifactRecord {
+                artifact_id: artifact.artifact_id.clone(),
+                spec_document_id: artifact.spec_document_id.clone(),
+                status: artifact.status.clone(),
+                content_hash: artifact.content_hash.clone(),
+                proof_hash: artifact.proof_hash.clone(),
+                rekor_entry_id: artifact.rekor_entry_id.clone(),
+                provenance_entry_id: artifact.provenance_entry_id.clone(),
+            }).collect(),
+            target_url: response.target_url.clone(),
+            updated_at_ms: Utc::now().timestamp_millis(),
+        }
+    }
+
+    /// Strong validator of the result, quoted for the `ETag` header; it
+    /// only changes when the result does
+    pub fn etag(&self) -> String {
+        let digest = Sha256::digest(self.encode_to_vec());
+        format!("\"{}\"", hex::encode(&digest[..16]))
+    }
+
+    // Same result, whenever it was computed
+    fn repeats(&self, other: &CommitProofStatus) -> bool {
+        let mut other = other.clone();
+        other.updated_at_ms = self.updated_at_ms;
+        *self == other
+    }
+}
+
+impl Entity for CommitProofStatus {
+    const KIND: &'static str = "COMMIT_PROOF_STATUS";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        None
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+fn commit_key(provider: &str, repository_id: &str, commit_sha: &str) -> String {
+    format!("{}/{}@{}", provider, repository_id, commit_sha)
+}
+
+/// Whether an `If-None-Match` header value lists `etag`
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',')
+        .map(|candidate| candidate.trim())
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// A commit's proof status as served to external CI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProofStatusResponse {
+    pub provider: String,
+    pub repository: String,
+    pub commit_sha: String,
+    pub pull_request: Option<String>,
+    pub status: &'static str,
+    /// Every required invariant is proven; what deployments gate on
+    pub proven: bool,
+    pub description: String,
+    pub coverage_percentage: f64,
+    pub invariants_proven: u32,
+    pub invariants_failed: u32,
+    pub total_invariants: u32,
+    pub artifacts: Vec<ProofArtifactStatus>,
+    pub target_url: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProofArtifactStatus {
+    pub artifact_id: String,
+    pub spec_document_id: String,
+    pub status: String,
+    pub content_hash: String,
+    pub proof_hash: String,
+    pub rekor_entry_id: Option<String>,
+    pub provenance_entry_id: Option<String>,
+}
+
+impl From<CommitProofStatus> for ProofStatusResponse {
+    fn from(status: CommitProofStatus) -> Self {
+        Self {
+            status: status_name(status.status),
+            proven: status.status == BadgeStatus::Success as i32,
+            pull_request: Some(status.pull_request_id).filter(|id| !id.is_empty()),
+            provider: status.provider,
+            repository: status.repository_id,
+            commit_sha: status.commit_sha,
+            description: status.description,
+            coverage_percentage: status.coverage_percentage,
+            invariants_proven: status.invariants_proven,
+            invariants_failed: status.invariants_failed,
+            total_invariants: status.total_invariants,
+            artifacts: status.artifacts.into_iter().map(|artifact| ProofArtifactStatus {
+                artifact_id: artifact.artifact_id,
+                spec_document_id: artifact.spec_document_id,
+                status: artifact.status,
+                content_hash: artifact.content_hash,
+                proof_hash: artifact.proof_hash,
+                rekor_entry_id: Some(artifact.rekor_entry_id).filter(|id| !id.is_empty()),
+                provenance_entry_id: Some(artifact.provenance_entry_id).filter(|id| !id.is_empty()),
+            }).collect(),
+            target_url: status.target_url,
+            updated_at: Utc.timestamp_millis_opt(status.updated_at_ms).single().unwrap_or_default(),
+        }
+    }
+}
+
+/// The latest proof status of every verified commit
+pub struct ProofStatusStore {
+    repository: Arc<dyn Repository<CommitProofStatus>>,
+}
+
+impl std::fmt::Debug for ProofStatusStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProofStatusStore").finish_non_exhaustive()
+    }
+}
+
+impl ProofStatusStore {
+    pub fn new(repository: Arc<dyn Repository<CommitProofStatus>>) -> Self {
+        Self { repository }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(Arc::new(InMemoryRepository::new()))
+    }
+
+    /// Without `proof_status_storage`, commits verified before the app
+    /// restarted answer 404 until they are verified again
+    pub async fn from_settings(settings: Option<&StorageSettings>) -> Result<Self> {
+        Ok(Self::new(repository_or_memory(settings).await?))
+    }
+
+    /// Replaces the commit's status unless it repeats the stored result, so
+    /// the ETag stays the same. Returns whether it was stored.
+    pub async fn record(&self, status: CommitProofStatus) -> Result<bool> {
+        if let Some(stored) = self.repository.get(&status.id).await? {
+            if status.repeats(&stored.entity) {
+                return Ok(false);
+            }
+        }
+        self.repository.put(&status, ExpectedVersion::Any).await?;
+        Ok(true)
+    }
+
+    pub async fn get(&self, provider: ScmKind, repository_id: &str, commit_sha: &str) -> Result<Option<CommitProofStatus>> {
+        let stored = self.repository.get(&commit_key(provider.as_str(), repository_id, commit_sha)).await?;
+        Ok(stored.map(|stored| stored.entity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(status: BadgeStatus, proven: u32, updated_at_ms: i64) -> CommitProofStatus {
+        CommitProofStatus {
+            id: commit_key("github", "acme/payments", "abc"),
+            provider: "github".to_string(),
+            repository_id: "acme/payments".to_string(),
+            commit_sha: "abc".to_string(),
+            status: status as i32,
+            invariants_proven: proven,
+            total_invariants: 4,
+            artifacts: vec![ProofArtifactRecord {
+                artifact_id: "artifact_1".to_string(),
+                rekor_entry_id: "24296fb2".to_string(),
+                ..Default::default()
+            }],
+            updated_at_ms,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_etag_follows_the_result() {
+        let store = ProofStatusStore::in_memory();
+        assert!(store.record(status(BadgeStatus::Pending, 2, 1)).await.unwrap());
+        let etag = store.get(ScmKind::GitHub, "acme/payments", "abc").await.unwrap().unwrap().etag();
+
+        // Recomputing the same result keeps the ETag
+        assert!(!store.record(status(BadgeStatus::Pending, 2, 2)).await.unwrap());
+        let stored = store.get(ScmKind::GitHub, "acme/payments", "abc").await.unwrap().unwrap();
+        assert_eq!(stored.etag(), etag);
+
+        assert!(store.record(status(BadgeStatus::Success, 4, 3)).await.unwrap());
+        let stored = store.get(ScmKind::GitHub, "acme/payments", "abc").await.unwrap().unwrap();
+        assert_ne!(stored.etag(), etag);
+        assert!(etag_matches(&format!("W/{}, \"other\"", stored.etag()), &stored.etag()));
+        assert!(!etag_matches(&etag, &stored.etag()));
+
+        let response = ProofStatusResponse::from(stored);
+        assert_eq!((response.status, response.proven, response.pull_request), ("success", true, None));
+        assert_eq!(response.artifacts[0].rekor_entry_id.as_deref(), Some("24296fb2"));
+        assert_eq!(response.artifacts[0].provenance_entry_id, None);
+
+        assert!(store.get(ScmKind::GitLab, "acme/payments", "abc").await.unwrap().is_none());
+    }
+}
@@ -10,6 +10,8 @@ pub mod gh_app {
         use serde::{Deserialize, Serialize};
         use chrono::{DateTime, Utc};
         
+        pub use crate::scm::ScmKind;
+        
         #[derive(Debug, Clone, Serialize, Deserialize)]
         pub enum WebhookEventType {
             Unspecified = 0,
@@ -147,6 +149,13 @@ pub mod gh_app {
             pub spec_document_ids: Vec<String>,
             pub installation_id: String,
             pub app_id: String,
+            /// Host the status is published to
+            #[serde(default)]
+            pub provider: ScmKind,
+            /// Recompute even if a badge for this commit is cached, e.g.
+            /// after the referenced spec documents drifted
+            #[serde(default)]
+            pub refresh: bool,
         }
         
         #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -173,6 +182,12 @@ pub mod gh_app {
             pub proven_at: DateTime<Utc>,
             pub status: String,
             pub error_message: String,
+            /// Rekor entry of the artifact's signed SLSA provenance, if any
+            #[serde(default)]
+            pub provenance_entry_id: String,
+            /// Sigstore bundle JSON for the proof, verified without calling Rekor
+            #[serde(default)]
+            pub sigstore_bundle: String,
         }
         
         #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use export::{KmsManifestSigner, ManifestSigner};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use crate::proto::gh_app::v1::SigstoreEntry;
+use crate::sigstore::SigstoreClient;
+
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+pub const IN_TOTO_STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+pub const SLSA_PROVENANCE_PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+
+/// Sigstore artifact type of provenance entries
+pub const PROVENANCE_ARTIFACT_TYPE: &str = "slsa-provenance";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenanceSettings {
+    /// Asymmetric ECC_NIST_P256 KMS key the DSSE envelopes are signed with
+    pub signing_key_id: String,
+}
+
+/// A provenance statement written by lean-farm next to a proof artifact
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProvenanceRequest {
+    pub spec_document_id: String,
+    pub artifact_id: String,
+    pub statement: serde_json::Value,
+}
+
+/// Reference to a signed statement, attached to the badge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceAttestation {
+    pub spec_document_id: String,
+    pub artifact_id: String,
+    /// SHA-256 of the proof artifact the statement is about
+    pub subject_sha256: String,
+    pub statement_sha256: String,
+    pub entry_id: String,
+    pub rekor_entry_url: String,
+    pub attested_at: DateTime<Utc>,
+}
+
+impl ProvenanceAttestation {
+    fn new(request: &ProvenanceRequest, subject_sha256: String, statement: &[u8], entry: &SigstoreEntry) -> Self {
+        Self {
+            spec_document_id: request.spec_document_id.clone(),
+            artifact_id: request.artifact_id.clone(),
+            subject_sha256,
+            statement_sha256: hex::encode(Sha256::digest(statement)),
+            entry_id: entry.entry_id.clone(),
+            rekor_entry_url: entry.rekor_entry_url.clone(),
+            attested_at: Utc::now(),
+        }
+    }
+}
+
+/// Signs provenance statements as DSSE envelopes and records them in Rekor,
+/// keeping the latest attestation per spec document for the badge
+#[derive(Debug)]
+pub struct ProvenanceAttestor {
+    signer: KmsManifestSigner,
+    attestations: RwLock<HashMap<String, ProvenanceAttestation>>,
+}
+
+impl ProvenanceAttestor {
+    pub async fn from_settings(settings: &ProvenanceSettings, aws_region: &str) -> Self {
+        let aws_config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(aws_sdk_sts::Region::new(aws_region.to_string()))
+            .load()
+            .await;
+        Self {
+            signer: KmsManifestSigner::new(aws_sdk_kms::Client::new(&aws_config), &settings.signing_key_id),
+            attestations: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn attest(&self, request: &ProvenanceRequest, sigstore: &SigstoreClient) -> Result<ProvenanceAttestation> {
+        let subject_sha256 = subject_sha256(&request.statement)?;
+        let statement = serde_json::to_vec(&request.statement)?;
+
+        let signature = self.signer.sign(&pae(IN_TOTO_PAYLOAD_TYPE, &statement)).await
+            .context("Failed to sign provenance statement")?;
+        let public_key = self.signer.public_key_pem().await
+            .context("Failed to read provenance signing key")?;
+        let entry = sigstore.create_signed_entry(
+            &format!("sha256:{}", subject_sha256),
+            PROVENANCE_ARTIFACT_TYPE,
+            &general_purpose::STANDARD.encode(signature),
+            &public_key,
+        ).await?;
+
+        let attestation = ProvenanceAttestation::new(request, subject_sha256, &statement, &entry);
+        self.attestations.write().await.insert(request.spec_document_id.clone(), attestation.clone());
+        Ok(attestation)
+    }
+
+    pub async fn get(&self, spec_document_id: &str) -> Option<ProvenanceAttestation> {
+        self.attestations.read().await.get(spec_document_id).cloned()
+    }
+}
+
+/// The digest of the single proof artifact a statement is about
+pub fn subject_sha256(statement: &serde_json::Value) -> Result<String> {
+    if statement["_type"] != IN_TOTO_STATEMENT_TYPE {
+        return Err(anyhow!("Not an in-toto v1 statement"));
+    }
+    if statement["predicateType"] != SLSA_PROVENANCE_PREDICATE_TYPE {
+        return Err(anyhow!("Predicate is not SLSA v1 provenance"));
+    }
+    let subjects = statement["subject"].as_array()
+        .ok_or_else(|| anyhow!("Statement has no subject"))?;
+    let [subject] = subjects.as_slice() else {
+        return Err(anyhow!("Expected one subject, found {}", subjects.len()));
+    };
+    subject["digest"]["sha256"].as_str()
+        .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|digest| digest.to_ascii_lowercase())
+        .ok_or_else(|| anyhow!("Subject has no SHA-256 digest"))
+}
+
+/// DSSE pre-authentication encoding, the bytes an envelope signature covers
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = format!("DSSEv1 {} {} {} ", payload_type.len(), payload_type, payload.len()).into_bytes();
+    encoded.extend_from_slice(payload);
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pae() {
+        assert_eq!(
+            pae(IN_TOTO_PAYLOAD_TYPE, b"{}"),
+            b"DSSEv1 28 application/vnd.in-toto+json 2 {}".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_subject_sha256() {
+        let digest = "a".repeat(64);
+        let mut statement = json!({
+            "_type": IN_TOTO_STATEMENT_TYPE,
+            "predicateType": SLSA_PROVENANCE_PREDICATE_TYPE,
+            "subject": [{"name": "proof-1", "digest": {"sha256": digest}}],
+            "predicate": {},
+        });
+        assert_eq!(subject_sha256(&statement).unwrap(), digest);
+
+        statement["subject"] = json!([]);
+        assert!(subject_sha256(&statement).is_err());
+
+        statement["predicateType"] = json!("https://slsa.dev/provenance/v0.2");
+        assert!(subject_sha256(&statement).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use anyhow::Result;
+use config::{Config, File, FileFormat};
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::github::GitHubClient;
+use crate::installations::InstallationRegistry;
+use crate::policy::MergePolicy;
+
+/// Per-repository settings file, read at the commit being verified
+pub const REPO_CONFIG_FILE: &str = ".spec-to-proof.yml";
+
+// Commits never change, so entries only leave to bound memory
+const MAX_CACHED_CONFIGS: usize = 1000;
+
+const KNOWN_KEYS: &[&str] = &["spec_sources", "document_id_patterns", "policy", "notifications"];
+
+/// A repository's `.spec-to-proof.yml`
+///
+/// ```yaml
+/// spec_sources:
+///   - system: jira
+///     project: PAY
+/// document_id_patterns: ["PAY-[0-9]+"]
+/// policy:
+///   min_coverage:
+///     critical: 100
+/// notifications:
+///   - provider: slack
+///     target: https://hooks.slack.com/services/T000/B000/XXXX
+///     kinds: [proof_failed]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepoConfig {
+    /// Where the repository's spec documents live
+    pub spec_sources: Vec<SpecSource>,
+    /// Regexes finding spec document references in pull requests and
+    /// commit messages; the first capture group, if any, is the document
+    /// id. Empty uses the built-in `spec:` / `DOC-` style references.
+    pub document_id_patterns: Vec<String>,
+    /// Merge gate; without one every invariant has to be proven
+    pub policy: Option<MergePolicy>,
+    pub notifications: Vec<NotificationTarget>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "system", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SpecSource {
+    Confluence { space: String },
+    Jira { project: String },
+    GoogleDocs { folder_id: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationProvider {
+    Slack,
+    Teams,
+    Email,
+}
+
+/// Same names as the notifications service's kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    ExtractionCompleted,
+    ProofFailed,
+    DriftDetected,
+    ReviewRequested,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationTarget {
+    pub provider: NotificationProvider,
+    /// Incoming webhook URL, or an email address
+    pub target: String,
+    /// Empty notifies of every kind
+    #[serde(default)]
+    pub kinds: Vec<NotificationKind>,
+}
+
+/// A problem with one setting, located by its path, e.g.
+/// `policy.min_coverage.critical` or `spec_sources[1]`; empty for the file
+/// as a whole
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { path: path.into(), message: message.into() }
+    }
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl RepoConfig {
+    /// Parses and checks the file, reporting every problem rather than
+    /// the first
+    pub fn validate(contents: &str) -> std::result::Result<Self, Vec<ConfigIssue>> {
+        let value = Config::builder()
+            .add_source(File::from_str(contents, FileFormat::Yaml))
+            .build()
+            .and_then(|file| file.try_deserialize::<Value>())
+            .map_err(|e| vec![ConfigIssue::new("", format!("Invalid YAML: {}", e))])?;
+        let settings = match value {
+            Value::Object(settings) => settings,
+            Value::Null => return Ok(Self::default()),
+            _ => return Err(vec![ConfigIssue::new("", "Must contain a mapping of settings")]),
+        };
+
+        let mut config = Self::default();
+        let mut issues = Vec::new();
+        for (key, value) in settings {
+            match key.as_str() {
+                "spec_sources" => config.spec_sources = list(&key, value, &mut issues),
+                "document_id_patterns" => {
+                    config.document_id_patterns = list(&key, value, &mut issues);
+                    for (index, pattern) in config.document_id_patterns.iter().enumerate() {
+                        if let Err(e) = Regex::new(pattern) {
+                            issues.push(ConfigIssue::new(format!("{}[{}]", key, index), format!("Invalid regex: {}", e)));
+                        }
+                    }
+                }
+                "policy" => match serde_json::from_value::<Option<MergePolicy>>(value) {
+                    Ok(policy) => {
+                        if let Some(policy) = &policy {
+                            check_policy(policy, &mut issues);
+                        }
+                        config.policy = policy;
+                    }
+                    Err(e) => issues.push(ConfigIssue::new(key, e.to_string())),
+                },
+                "notifications" => {
+                    config.notifications = list(&key, value, &mut issues);
+                    for (index, target) in config.notifications.iter().enumerate() {
+                        if let Some(message) = target.problem() {
+                            issues.push(ConfigIssue::new(format!("{}[{}].target", key, index), message));
+                        }
+                    }
+                }
+                _ => issues.push(ConfigIssue::new(
+                    key,
+                    format!("Unknown key, expected one of {}", KNOWN_KEYS.join(", ")),
+                )),
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(config)
+        } else {
+            Err(issues)
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        Self::validate(contents).map_err(|issues| {
+            let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+            anyhow::anyhow!("Invalid {}: {}", REPO_CONFIG_FILE, issues.join("; "))
+        })
+    }
+
+    /// Spec document references in `text`, using the configured patterns
+    /// if there are any
+    pub fn spec_references(&self, text: &str) -> Vec<String> {
+        if self.document_id_patterns.is_empty() {
+            return crate::scm::extract_spec_references(text);
+        }
+        self.document_id_patterns
+            .iter()
+            .filter_map(|pattern| Regex::new(pattern).ok())
+            .flat_map(|regex| {
+                regex.captures_iter(text)
+                    .filter_map(|captures| captures.get(1).or_else(|| captures.get(0)))
+                    .map(|reference| reference.as_str().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+impl NotificationTarget {
+    fn problem(&self) -> Option<&'static str> {
+        match self.provider {
+            NotificationProvider::Slack | NotificationProvider::Teams if !self.target.starts_with("https://") => {
+                Some("Must be an https:// incoming webhook URL")
+            }
+            NotificationProvider::Email if !self.target.contains('@') => Some("Must be an email address"),
+            _ => None,
+        }
+    }
+}
+
+// Deserializes each item on its own so every bad one is reported at its index
+fn list<T: DeserializeOwned>(key: &str, value: Value, issues: &mut Vec<ConfigIssue>) -> Vec<T> {
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Null => return Vec::new(),
+        _ => {
+            issues.push(ConfigIssue::new(key, "Must be a list"));
+            return Vec::new();
+        }
+    };
+    items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, item)| match serde_json::from_value(item) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                issues.push(ConfigIssue::new(format!("{}[{}]", key, index), e.to_string()));
+                None
+            }
+        })
+        .collect()
+}
+
+fn check_policy(policy: &MergePolicy, issues: &mut Vec<ConfigIssue>) {
+    for (tier, threshold) in &policy.min_coverage {
+        if !(0.0..=100.0).contains(threshold) {
+            let tier = serde_json::to_value(tier).ok().and_then(|t| t.as_str().map(str::to_string)).unwrap_or_default();
+            issues.push(ConfigIssue::new(
+                format!("policy.min_coverage.{}", tier),
+                format!("Must be a percentage between 0 and 100, got {}", threshold),
+            ));
+        }
+    }
+    for (index, pattern) in policy.exempt_paths.iter().enumerate() {
+        if pattern.trim().is_empty() {
+            issues.push(ConfigIssue::new(format!("policy.exempt_paths[{}]", index), "Must not be empty"));
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedRepoConfig {
+    // `None` when the repository has no valid file at the commit
+    config: Option<Arc<RepoConfig>>,
+    cached_at: Instant,
+}
+
+/// Repository configs fetched through the contents API, cached per
+/// repository and commit
+#[derive(Debug)]
+pub struct RepoConfigStore {
+    github: GitHubClient,
+    installations: Arc<InstallationRegistry>,
+    cache: RwLock<HashMap<String, CachedRepoConfig>>,
+}
+
+impl RepoConfigStore {
+    pub fn new(github: GitHubClient, installations: Arc<InstallationRegistry>) -> Self {
+        Self {
+            github,
+            installations,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The config of `repo` ("owner/name") at `commit_sha`, or `None` when
+    /// there is no file or it is invalid. Only failed fetches are errors;
+    /// an invalid file is logged and cached like a missing one.
+    pub async fn get(&self, installation_id: &str, repo: &str, commit_sha: &str) -> Result<Option<Arc<RepoConfig>>> {
+        let key = format!("{}@{}", repo, commit_sha);
+        if let Some(cached) = self.cache.read().await.get(&key) {
+            return Ok(cached.config.clone());
+        }
+
+        // Installation tokens are cached in the shared registry, so the
+        // clone doesn't cost a token exchange
+        let mut github = self.github.clone();
+        github.set_installation(&self.installations.resolve(installation_id).await?);
+        let config = match github.get_file_contents(repo, REPO_CONFIG_FILE, commit_sha).await? {
+            Some(contents) => match RepoConfig::parse(&contents) {
+                Ok(config) => {
+                    info!("Loaded {} of {}@{}", REPO_CONFIG_FILE, repo, commit_sha);
+                    Some(Arc::new(config))
+                }
+                Err(e) => {
+                    warn!("Ignoring {} of {}@{}: {}", REPO_CONFIG_FILE, repo, commit_sha, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        self.insert(key, config.clone()).await;
+        Ok(config)
+    }
+
+    async fn insert(&self, key: String, config: Option<Arc<RepoConfig>>) {
+        let mut cache = self.cache.write().await;
+        if cache.len() >= MAX_CACHED_CONFIGS {
+            if let Some(oldest) = cache.iter().min_by_key(|(_, cached)| cached.cached_at).map(|(key, _)| key.clone()) {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, CachedRepoConfig { config, cached_at: Instant::now() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coverage::PriorityTier;
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = RepoConfig::parse(
+            r#"
+spec_sources:
+  - system: jira
+    project: PAY
+  - system: google_docs
+    folder_id: 1AbC
+document_id_patterns: ["(PAY-[0-9]+)"]
+policy:
+  required_tags: [security]
+  min_coverage:
+    critical: 100
+notifications:
+  - provider: email
+    target: payments@example.com
+    kinds: [proof_failed, drift_detected]
+"#,
+        ).unwrap();
+
+        assert_eq!(config.spec_sources[0], SpecSource::Jira { project: "PAY".to_string() });
+        assert_eq!(config.spec_sources[1], SpecSource::GoogleDocs { folder_id: "1AbC".to_string() });
+        let policy = config.policy.as_ref().unwrap();
+        assert_eq!(policy.min_coverage[&PriorityTier::Critical], 100.0);
+        assert_eq!(config.notifications[0].kinds, vec![NotificationKind::ProofFailed, NotificationKind::DriftDetected]);
+        assert_eq!(config.spec_references("Implements PAY-12 and PAY-7, not DOC-1"), vec!["PAY-12", "PAY-7"]);
+
+        assert_eq!(RepoConfig::parse("").unwrap(), RepoConfig::default());
+        assert_eq!(RepoConfig::default().spec_references("Fixes DOC-42"), vec!["42"]);
+    }
+
+    #[test]
+    fn test_validation_reports_every_issue() {
+        let issues = RepoConfig::validate(
+            r#"
+spec_sources:
+  - system: jira
+    project: PAY
+  - system: notion
+    page: x
+document_id_patterns: ["PAY-(["]
+policy:
+  required_tag: [security]
+notifications:
+  - provider: slack
+    target: http://hooks.example.com
+channels: []
+"#,
+        ).unwrap_err();
+        let paths: Vec<&str> = issues.iter().map(|issue| issue.path.as_str()).collect();
+
+        assert_eq!(paths, vec!["channels", "document_id_patterns[0]", "notifications[0].target", "policy", "spec_sources[1]"]);
+        assert!(issues[4].message.contains("unknown variant `notion`"));
+        assert!(issues[3].message.contains("unknown field `required_tag`"));
+
+        let issues = RepoConfig::validate("policy:\n  min_coverage:\n    high: 120\n").unwrap_err();
+        assert_eq!(issues, vec![ConfigIssue::new("policy.min_coverage.high", "Must be a percentage between 0 and 100, got 120")]);
+
+        assert!(RepoConfig::validate("policy: [unclosed\n").unwrap_err()[0].message.starts_with("Invalid YAML"));
+    }
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::path::Path;
+
+use reload::ReloadError;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ConfigOverrides, GitHubAppConfig};
+
+/// Rate limits and cache lifetimes re-read from the config file while the
+/// server runs; every other setting still needs a restart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeSettings {
+    pub rate_limit_requests: u32,
+    pub widget_cache_max_age: u64,
+    pub widget_rate_limit_requests: u32,
+    pub widget_rate_limit_window: u64,
+}
+
+impl RuntimeSettings {
+    pub fn from_config(config: &GitHubAppConfig) -> Self {
+        Self {
+            rate_limit_requests: config.rate_limit_requests,
+            widget_cache_max_age: config.widget_cache_max_age,
+            widget_rate_limit_requests: config.widget_rate_limit_requests,
+            widget_rate_limit_window: config.widget_rate_limit_window,
+        }
+    }
+
+    /// Re-layers the config file and environment the way startup does, so
+    /// an environment override keeps winning over the edited file
+    pub fn load(path: &Path) -> reload::Result<Self> {
+        let config = GitHubAppConfig::from_layers(Some(path), env::vars(), &ConfigOverrides::default())
+            .map_err(|e| ReloadError::Read { path: path.to_path_buf(), message: format!("{:#}", e) })?;
+        let settings = Self::from_config(&config);
+        reload::RuntimeSettings::validate(&settings)
+            .map_err(|message| ReloadError::Invalid { path: path.to_path_buf(), message })?;
+        Ok(settings)
+    }
+}
+
+impl reload::RuntimeSettings for RuntimeSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.widget_rate_limit_window == 0 {
+            return Err("widget_rate_limit_window must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml_file(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = env::temp_dir().join(format!("gh-app-runtime-{}-{}.yaml", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_from_config_file() {
+        let path = yaml_file("valid", "widget_cache_max_age: 60\nwidget_rate_limit_requests: 10\n");
+        let settings = RuntimeSettings::load(&path).unwrap();
+        assert_eq!(settings.widget_cache_max_age, 60);
+        assert_eq!(settings.widget_rate_limit_requests, 10);
+
+        let path = yaml_file("invalid", "widget_rate_limit_window: 0\n");
+        assert!(matches!(RuntimeSettings::load(&path), Err(ReloadError::Invalid { .. })));
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::github::GitHubClient;
+use crate::proto::gh_app::v1::BadgeStatus;
+
+/// Source control host a repository lives on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScmKind {
+    #[default]
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl ScmKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScmKind::GitHub => "github",
+            ScmKind::GitLab => "gitlab",
+            ScmKind::Bitbucket => "bitbucket",
+        }
+    }
+}
+
+/// Publishes verification results back to the host of a change request.
+/// The badge manager and coverage reporting only talk to this.
+#[async_trait::async_trait]
+pub trait ScmProvider: Send + Sync {
+    fn kind(&self) -> ScmKind;
+
+    /// Sets the status shown on `sha`. `repository` is the host's
+    /// identifier: "owner/name" on GitHub, the project id or path on GitLab,
+    /// "workspace/repo_slug" on Bitbucket.
+    async fn update_commit_status(
+        &mut self,
+        repository: &str,
+        sha: &str,
+        status: BadgeStatus,
+        context: &str,
+        description: &str,
+        target_url: Option<&str>,
+    ) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl ScmProvider for GitHubClient {
+    fn kind(&self) -> ScmKind {
+        ScmKind::GitHub
+    }
+
+    async fn update_commit_status(
+        &mut self,
+        repository: &str,
+        sha: &str,
+        status: BadgeStatus,
+        context: &str,
+        description: &str,
+        target_url: Option<&str>,
+    ) -> Result<()> {
+        GitHubClient::update_commit_status(self, repository, sha, status, context, description, target_url).await
+    }
+}
+
+/// Spec document references in a change request description or commit
+/// message, in order of appearance
+pub fn extract_spec_references(text: &str) -> Vec<String> {
+    let mut references = Vec::new();
+
+    // Look for spec document references in various formats
+    let patterns = vec![
+        r"spec:\s*([a-zA-Z0-9_-]+)",           // spec: DOC-123
+        r"document:\s*([a-zA-Z0-9_-]+)",       // document: DOC-123
+        r"#([a-zA-Z0-9_-]+)",                  // #DOC-123
+        r"DOC-([0-9]+)",                       // DOC-123
+        r"SPEC-([0-9]+)",                      // SPEC-123
+    ];
+
+    for pattern in patterns {
+        if let Ok(regex) = regex::Regex::new(pattern) {
+            for cap in regex.captures_iter(text) {
+                if let Some(reference) = cap.get(1) {
+                    references.push(reference.as_str().to_string());
+                }
+            }
+        }
+    }
+
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scm_kind_defaults_to_github() {
+        #[derive(Deserialize)]
+        struct Request {
+            #[serde(default)]
+            provider: ScmKind,
+        }
+        let request: Request = serde_json::from_str("{}").unwrap();
+        assert_eq!(request.provider, ScmKind::GitHub);
+        let request: Request = serde_json::from_str(r#"{"provider": "gitlab"}"#).unwrap();
+        assert_eq!(request.provider, ScmKind::GitLab);
+        let request: Request = serde_json::from_str(r#"{"provider": "bitbucket"}"#).unwrap();
+        assert_eq!(request.provider, ScmKind::Bitbucket);
+    }
+
+    #[test]
+    fn test_extract_spec_references() {
+        assert_eq!(extract_spec_references("Implements spec: refund-policy"), vec!["refund-policy"]);
+        assert_eq!(extract_spec_references("Fixes DOC-42"), vec!["42"]);
+    }
+}
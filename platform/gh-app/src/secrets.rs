@@ -0,0 +1,222 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use aws_sdk_secretsmanager::Client as SecretsClient;
+use jsonwebtoken::EncodingKey;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::api_auth::ApiKey;
+use crate::config::GitHubAppConfig;
+use crate::installations::InstallationRegistry;
+
+/// Credentials that can rotate while the app is running
+#[derive(Clone, PartialEq, Eq)]
+pub struct AppSecrets {
+    pub private_key: String,
+    pub webhook_secret: String,
+    pub client_secret: String,
+    pub admin_api_token: String,
+    pub api_keys: Vec<ApiKey>,
+}
+
+impl std::fmt::Debug for AppSecrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AppSecrets").finish_non_exhaustive()
+    }
+}
+
+impl AppSecrets {
+    pub fn from_config(config: &GitHubAppConfig) -> Self {
+        Self {
+            private_key: config.private_key.clone(),
+            webhook_secret: config.webhook_secret.clone(),
+            client_secret: config.client_secret.clone(),
+            admin_api_token: config.admin_api_token.clone(),
+            api_keys: config.api_keys.clone(),
+        }
+    }
+
+    /// Overlays the fields present in a Secrets Manager JSON secret, the
+    /// same layout `GitHubAppConfig::load` reads at startup
+    pub fn apply_secret_json(&mut self, secret_string: &str) -> Result<()> {
+        let secrets: serde_json::Value = serde_json::from_str(secret_string)
+            .context("Failed to parse secrets JSON")?;
+
+        for (name, field) in [
+            ("private_key", &mut self.private_key),
+            ("webhook_secret", &mut self.webhook_secret),
+            ("client_secret", &mut self.client_secret),
+            ("admin_api_token", &mut self.admin_api_token),
+        ] {
+            if let Some(value) = secrets.get(name).and_then(|v| v.as_str()) {
+                *field = value.to_string();
+            }
+        }
+        if let Some(api_keys) = secrets.get("api_keys") {
+            self.api_keys = serde_json::from_value(api_keys.clone())
+                .context("Failed to parse api_keys")?;
+        }
+        Ok(())
+    }
+}
+
+/// The current secrets, shared by every component that signs or verifies
+/// with them. A rotation swaps the whole set at once, so readers never see
+/// a new private key alongside an old webhook secret.
+#[derive(Debug, Clone)]
+pub struct SecretHandle {
+    current: Arc<RwLock<Arc<AppSecrets>>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl SecretHandle {
+    pub fn new(secrets: AppSecrets) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(secrets))),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn from_config(config: &GitHubAppConfig) -> Self {
+        Self::new(AppSecrets::from_config(config))
+    }
+
+    pub fn current(&self) -> Arc<AppSecrets> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Incremented on every rotation; anything derived from the secrets
+    /// under an older generation is stale
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Swaps in a new set, returning false if nothing changed
+    pub fn replace(&self, secrets: AppSecrets) -> bool {
+        let mut current = self.current.write().unwrap();
+        if **current == secrets {
+            return false;
+        }
+        *current = Arc::new(secrets);
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        true
+    }
+}
+
+/// Polls the app's Secrets Manager secret for a new `AWSCURRENT` version
+/// and swaps the rotated credentials into the running process
+#[derive(Debug)]
+pub struct SecretsRotationWatcher {
+    client: SecretsClient,
+    secret_arn: String,
+    interval: Duration,
+    current_version: Option<String>,
+}
+
+impl SecretsRotationWatcher {
+    pub fn new(client: SecretsClient, secret_arn: &str, interval: Duration) -> Self {
+        Self {
+            client,
+            secret_arn: secret_arn.to_string(),
+            interval,
+            current_version: None,
+        }
+    }
+
+    /// Checks for a rotation once, returning whether the secrets changed.
+    /// Installation tokens minted with the old private key are dropped so
+    /// the next request mints one with the new key.
+    pub async fn poll(&mut self, secrets: &SecretHandle, installations: &InstallationRegistry) -> Result<bool> {
+        let description = self.client
+            .describe_secret()
+            .secret_id(&self.secret_arn)
+            .send()
+            .await
+            .context("Failed to describe app secret")?;
+        let version = description.version_ids_to_stages()
+            .and_then(|versions| {
+                versions.iter()
+                    .find(|(_, stages)| stages.iter().any(|stage| stage == "AWSCURRENT"))
+                    .map(|(version, _)| version.clone())
+            });
+        if version.is_none() || version == self.current_version {
+            return Ok(false);
+        }
+
+        let secret = self.client
+            .get_secret_value()
+            .secret_id(&self.secret_arn)
+            .set_version_id(version.clone())
+            .send()
+            .await
+            .context("Failed to retrieve rotated app secret")?;
+        let mut rotated = (*secrets.current()).clone();
+        rotated.apply_secret_json(secret.secret_string().unwrap_or("{}"))?;
+        // A broken key would fail every GitHub call; keep the old one
+        EncodingKey::from_rsa_pem(rotated.private_key.as_bytes())
+            .context("Rotated private key is not a valid RSA PEM")?;
+
+        // The first poll usually just learns the version loaded at startup
+        self.current_version = version;
+        if !secrets.replace(rotated) {
+            return Ok(false);
+        }
+        installations.invalidate_tokens().await;
+        info!("Rotated GitHub App secrets to version {}", self.current_version.as_deref().unwrap_or(""));
+        Ok(true)
+    }
+
+    pub fn spawn(mut self, secrets: SecretHandle, installations: Arc<InstallationRegistry>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.poll(&secrets, &installations).await {
+                    error!("Secrets rotation check failed: {:#}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_secret_json() {
+        let mut secrets = AppSecrets::from_config(&GitHubAppConfig {
+            webhook_secret: "old-webhook".to_string(),
+            admin_api_token: "old-admin".to_string(),
+            ..Default::default()
+        });
+        secrets.apply_secret_json(r#"{"webhook_secret": "new-webhook", "app_id": "1"}"#).unwrap();
+        assert_eq!(secrets.webhook_secret, "new-webhook");
+        assert_eq!(secrets.admin_api_token, "old-admin");
+        assert!(secrets.api_keys.is_empty());
+
+        secrets.apply_secret_json(r#"{"api_keys": [{"id": "ci", "key": "k1", "role": "admin"}]}"#).unwrap();
+        assert_eq!(secrets.api_keys[0].role, crate::api_auth::Role::Admin);
+        assert!(secrets.apply_secret_json("not json").is_err());
+        assert!(!format!("{:?}", secrets).contains("new-webhook"));
+    }
+
+    #[test]
+    fn test_secret_handle_replace() {
+        let handle = SecretHandle::from_config(&GitHubAppConfig::default());
+        let reader = handle.clone();
+        assert_eq!(reader.generation(), 0);
+
+        let unchanged = (*handle.current()).clone();
+        assert!(!handle.replace(unchanged));
+        assert_eq!(reader.generation(), 0);
+
+        let mut rotated = (*handle.current()).clone();
+        rotated.webhook_secret = "rotated".to_string();
+        assert!(handle.replace(rotated));
+        assert_eq!(reader.generation(), 1);
+        assert_eq!(reader.current().webhook_secret, "rotated");
+    }
+}
@@ -17,8 +17,9 @@ use tower_http::{
 use tracing::{info, warn, error};
 use anyhow::Result;
 
+use crate::badge_queue::BadgeJobAccepted;
 use crate::config::GitHubAppConfig;
-use crate::lib::{AppState, create_app};
+use crate::lib::{AppState, create_app, health_response};
 use crate::proto::gh_app::v1::*;
 use crate::error::{GitHubAppError, ErrorResponse};
 
@@ -29,7 +30,20 @@ pub struct Server {
 
 impl Server {
     pub async fn new(config: GitHubAppConfig) -> Result<Self> {
+        Self::with_config_file(config, None).await
+    }
+
+    /// Like `new`, also reloading runtime settings from the file `config`
+    /// was loaded from
+    pub async fn with_config_file(config: GitHubAppConfig, config_path: Option<&std::path::Path>) -> Result<Self> {
         let state = AppState::new(config.clone()).await?;
+        // Runs for the life of the process
+        let _ = state.spawn_secrets_rotation().await;
+        let _ = state.spawn_drift_listener().await?;
+        let _ = state.spawn_event_listener().await?;
+        if let Some(path) = config_path {
+            let _ = state.spawn_config_watcher(path);
+        }
         let app = create_app(state).await;
         
         // Add middleware
@@ -52,7 +66,11 @@ impl Server {
         let listener = tokio::net::TcpListener::bind(addr).await?;
         info!("Listening on {}", addr);
         
-        axum::serve(listener, self.app.clone()).await?;
+        // Peer addresses are needed by the public widget's rate limiter
+        axum::serve(
+            listener,
+            self.app.clone().into_make_service_with_connect_info::<SocketAddr>(),
+        ).await?;
         
         Ok(())
     }
@@ -80,16 +98,7 @@ pub async fn handle_error(err: axum::BoxError) -> impl IntoResponse {
 pub async fn health_check_handler(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<HealthCheckResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let checks = HashMap::new(); // TODO: Implement actual health checks
-    
-    let response = HealthCheckResponse {
-        status: "healthy".to_string(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        uptime: "0s".to_string(), // TODO: Track actual uptime
-        checks: checks.into_iter().map(|(k, v)| (k, v.to_string())).collect(),
-    };
-    
-    Ok(Json(response))
+    Ok(Json(health_response(&state, &state.health.liveness())))
 }
 
 // Metrics endpoint
@@ -127,7 +136,7 @@ pub async fn webhook_handler(
     let verification_request = WebhookVerificationRequest {
         payload: body.clone(),
         signature: signature.to_string(),
-        webhook_secret: state.config.webhook_secret.clone(),
+        webhook_secret: state.secrets.current().webhook_secret.clone(),
     };
 
     let verification_response = state.webhook_processor.verify_webhook(verification_request).await
@@ -183,27 +192,20 @@ pub async fn badge_handler(
     State(state): State<Arc<AppState>>,
     Path((repo, pr)): Path<(String, String)>,
     Json(request): Json<BadgeStatusRequest>,
-) -> Result<Json<BadgeStatusResponse>, (StatusCode, Json<ErrorResponse>)> {
-    info!("Updating badge for repo={}, pr={}", repo, pr);
+) -> Result<(StatusCode, Json<BadgeJobAccepted>), (StatusCode, Json<ErrorResponse>)> {
+    info!("Queueing badge update for repo={}, pr={}", repo, pr);
 
-    let response = state.badge_manager.update_badge_status(request).await
+    let job_id = state.badge_queue.enqueue(request).await
         .map_err(|e| {
             let error_response = ErrorResponse::new(
                 "BADGE_ERROR",
-                &format!("Badge update failed: {}", e),
+                &format!("Badge update not queued: {}", e),
                 "BADGE_001"
             );
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
+            (StatusCode::SERVICE_UNAVAILABLE, Json(error_response))
         })?;
 
-    // Update metrics
-    {
-        let mut metrics = state.metrics.write().await;
-        *metrics.entry(format!("badge_{:?}", response.status)).or_insert(0) += 1;
-        *metrics.entry("badge_total".to_string()).or_insert(0) += 1;
-    }
-
-    Ok(Json(response))
+    Ok((StatusCode::ACCEPTED, Json(BadgeJobAccepted::new(job_id))))
 }
 
 // Sigstore verification handler
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
-use reqwest::{Client, header::{HeaderMap, HeaderValue, CONTENT_TYPE, ACCEPT}};
+use reqwest::{Client, RequestBuilder, Response, header::{HeaderMap, HeaderValue, CONTENT_TYPE, ACCEPT}};
 use anyhow::{Result, Context};
+use circuit_breaker::CircuitBreaker;
 use tracing::{info, warn, error};
 use chrono::{DateTime, Utc};
 use base64::{Engine as _, engine::general_purpose};
@@ -11,6 +13,7 @@ use hex;
 
 use crate::config::GitHubAppConfig;
 use crate::proto::gh_app::v1::*;
+use crate::sigstore_bundle::{SigstoreBundle, TrustRoot};
 
 #[derive(Debug, Clone)]
 pub struct SigstoreClient {
@@ -19,9 +22,14 @@ pub struct SigstoreClient {
     rekor_url: String,
     fulcio_url: String,
     oidc_issuer: String,
+    trust_root: Option<TrustRoot>,
     entry_cache: HashMap<String, (SigstoreEntry, Instant)>,
+    breaker: Arc<CircuitBreaker>,
 }
 
+/// Name of the breaker shared by every Sigstore client in the process
+pub const SIGSTORE_CIRCUIT: &str = "sigstore";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RekorEntry {
     pub uuid: String,
@@ -77,13 +85,20 @@ impl SigstoreClient {
             .build()
             .context("Failed to create HTTP client")?;
         
+        let trust_root = match &config.sigstore_trust_root {
+            Some(settings) => Some(TrustRoot::load(settings).await.context("Failed to load Sigstore trust root")?),
+            None => None,
+        };
+        
         Ok(Self {
             config: config.clone(),
             http_client,
             rekor_url: config.sigstore_rekor_url.clone(),
             fulcio_url: config.sigstore_fulcio_url.clone(),
             oidc_issuer: config.sigstore_oidc_issuer.clone(),
+            trust_root,
             entry_cache: HashMap::new(),
+            breaker: CircuitBreaker::shared(SIGSTORE_CIRCUIT),
         })
     }
     
@@ -95,11 +110,7 @@ impl SigstoreClient {
             }
         }
         
-        // Fetch entry from Rekor
-        let rekor_entry = self.fetch_rekor_entry(entry_id).await?;
-        
-        // Convert to SigstoreEntry
-        let entry = self.convert_rekor_entry(rekor_entry).await?;
+        let entry = self.fetch_entry(entry_id).await?;
         
         // Cache the entry
         self.entry_cache.insert(entry_id.to_string(), (entry.clone(), Instant::now()));
@@ -107,12 +118,35 @@ impl SigstoreClient {
         Ok(entry)
     }
     
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        self.breaker.call_http(
+            async { request.send().await.map_err(anyhow::Error::from) },
+            |response| response.status().as_u16(),
+        ).await
+    }
+    
+    /// Fetches an entry from Rekor, bypassing the cache
+    pub async fn fetch_entry(&self, entry_id: &str) -> Result<SigstoreEntry> {
+        let rekor_entry = self.fetch_rekor_entry(entry_id).await?;
+        self.convert_rekor_entry(rekor_entry).await
+    }
+    
+    /// Verifies a Sigstore bundle against the configured trust root without
+    /// calling Rekor
+    pub fn verify_bundle(&self, bundle_json: &str, artifact_type: &str) -> Result<SigstoreEntry> {
+        let trust_root = self.trust_root.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No Sigstore trust root configured for offline verification"))?;
+        let mut entry = SigstoreBundle::from_json(bundle_json)?.verify(trust_root, artifact_type)?;
+        entry.rekor_entry_url = format!("{}/api/v1/log/entries/{}", self.rekor_url, entry.entry_id);
+        entry.fulcio_certificate_url = format!("{}/api/v1/signingCert", self.fulcio_url);
+        Ok(entry)
+    }
+    
     async fn fetch_rekor_entry(&self, entry_id: &str) -> Result<RekorEntry> {
         let url = format!("{}/api/v1/log/entries/{}", self.rekor_url, entry_id);
         
-        let response = self.http_client
-            .get(&url)
-            .send()
+        let response = self.send(self.http_client
+            .get(&url))
             .await
             .context("Failed to fetch Rekor entry")?;
         
@@ -260,9 +294,8 @@ impl SigstoreClient {
     pub async fn get_log_info(&self) -> Result<LogInfo> {
         let url = format!("{}/api/v1/log", self.rekor_url);
         
-        let response = self.http_client
-            .get(&url)
-            .send()
+        let response = self.send(self.http_client
+            .get(&url))
             .await
             .context("Failed to get log info")?;
         
@@ -280,9 +313,8 @@ impl SigstoreClient {
     pub async fn get_public_key(&self) -> Result<String> {
         let url = format!("{}/api/v1/log/publicKey", self.rekor_url);
         
-        let response = self.http_client
-            .get(&url)
-            .send()
+        let response = self.send(self.http_client
+            .get(&url))
             .await
             .context("Failed to get public key")?;
         
@@ -318,10 +350,9 @@ impl SigstoreClient {
             "logIndex": null
         });
         
-        let response = self.http_client
+        let response = self.send(self.http_client
             .post(&url)
-            .json(&request_body)
-            .send()
+            .json(&request_body))
             .await
             .context("Failed to retrieve entry by artifact hash")?;
         
@@ -0,0 +1,491 @@
+use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use base64::{Engine as _, engine::general_purpose};
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use p256::pkcs8::DecodePublicKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384};
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::name::GeneralName;
+use x509_cert::ext::pkix::SubjectAltName;
+use x509_cert::Certificate;
+
+use crate::proto::gh_app::v1::SigstoreEntry;
+
+const ECDSA_WITH_SHA256: &str = "1.2.840.10045.4.3.2";
+const ECDSA_WITH_SHA384: &str = "1.2.840.10045.4.3.3";
+const SUBJECT_ALT_NAME: &str = "2.5.29.17";
+// Fulcio's OIDC issuer extensions: the original raw string, and its
+// DER-encoded replacement
+const FULCIO_ISSUER_V1: &str = "1.3.6.1.4.1.57264.1.1";
+const FULCIO_ISSUER_V2: &str = "1.3.6.1.4.1.57264.1.8";
+
+/// Keys of a Rekor and Fulcio deployment, so bundles from a private
+/// Sigstore can be verified. The URLs are `sigstore_rekor_url` and
+/// `sigstore_fulcio_url`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigstoreTrustRootSettings {
+    /// PEM public key the Rekor log signs entry timestamps with
+    pub rekor_public_key_path: PathBuf,
+    /// PEM root certificates, and any intermediates, Fulcio issues from
+    pub fulcio_certificates_path: PathBuf,
+}
+
+/// Rekor's public key and the Fulcio certificates bundles are checked against
+#[derive(Debug, Clone)]
+pub struct TrustRoot {
+    rekor_key: p256::ecdsa::VerifyingKey,
+    rekor_log_id: String,
+    fulcio_certificates: Vec<Certificate>,
+}
+
+impl TrustRoot {
+    pub async fn load(settings: &SigstoreTrustRootSettings) -> Result<Self> {
+        let rekor_pem = tokio::fs::read_to_string(&settings.rekor_public_key_path).await
+            .with_context(|| format!("Failed to read {}", settings.rekor_public_key_path.display()))?;
+        let fulcio_pem = tokio::fs::read_to_string(&settings.fulcio_certificates_path).await
+            .with_context(|| format!("Failed to read {}", settings.fulcio_certificates_path.display()))?;
+        Self::from_pem(&rekor_pem, &fulcio_pem)
+    }
+
+    pub fn from_pem(rekor_public_key: &str, fulcio_certificates: &str) -> Result<Self> {
+        let rekor_der = pem_blocks(rekor_public_key, "PUBLIC KEY")?.into_iter().next()
+            .ok_or_else(|| anyhow!("No Rekor public key found"))?;
+        let rekor_key = p256::ecdsa::VerifyingKey::from_public_key_der(&rekor_der)
+            .map_err(|e| anyhow!("Rekor public key is not a P-256 key: {}", e))?;
+        let fulcio_certificates = pem_blocks(fulcio_certificates, "CERTIFICATE")?
+            .iter()
+            .map(|der| Certificate::from_der(der).context("Invalid Fulcio certificate"))
+            .collect::<Result<Vec<_>>>()?;
+        if fulcio_certificates.is_empty() {
+            return Err(anyhow!("No Fulcio certificates found"));
+        }
+        Ok(Self {
+            rekor_key,
+            rekor_log_id: hex::encode(Sha256::digest(&rekor_der)),
+            fulcio_certificates,
+        })
+    }
+}
+
+/// A Sigstore bundle: a signature with its signing certificate and the
+/// Rekor entry that logged it, verifiable without contacting Rekor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigstoreBundle {
+    #[serde(default)]
+    pub media_type: String,
+    pub verification_material: VerificationMaterial,
+    pub message_signature: MessageSignature,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMaterial {
+    pub x509_certificate_chain: CertificateChain,
+    pub tlog_entries: Vec<TlogEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateChain {
+    pub certificates: Vec<RawCertificate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawCertificate {
+    /// Base64 DER
+    pub raw_bytes: String,
+}
+
+/// Integers are strings, as in the protobuf JSON encoding bundles use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlogEntry {
+    pub log_index: String,
+    pub log_id: LogId,
+    pub integrated_time: String,
+    pub inclusion_promise: InclusionPromise,
+    #[serde(default)]
+    pub inclusion_proof: Option<InclusionProof>,
+    /// Base64 of the entry body as Rekor stored it
+    pub canonicalized_body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogId {
+    /// Base64 SHA-256 of the log's public key
+    pub key_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionPromise {
+    /// Base64 signed entry timestamp
+    pub signed_entry_timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionProof {
+    pub log_index: String,
+    /// Base64 hashes, leaf to root
+    pub hashes: Vec<String>,
+    pub root_hash: String,
+    pub tree_size: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSignature {
+    pub message_digest: MessageDigest,
+    /// Base64 DER ECDSA signature
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageDigest {
+    pub algorithm: String,
+    /// Base64
+    pub digest: String,
+}
+
+impl SigstoreBundle {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Invalid Sigstore bundle")
+    }
+
+    /// Checks the signing certificate chains to Fulcio, the entry timestamp
+    /// is Rekor's, the entry was logged while the certificate was valid and
+    /// records this signature, and the signature covers the digest
+    pub fn verify(&self, trust_root: &TrustRoot, artifact_type: &str) -> Result<SigstoreEntry> {
+        let chain = self.verification_material.x509_certificate_chain.certificates.iter()
+            .map(|certificate| {
+                let der = general_purpose::STANDARD.decode(&certificate.raw_bytes)?;
+                Ok(Certificate::from_der(&der)?)
+            })
+            .collect::<Result<Vec<_>>>()
+            .context("Invalid certificate in bundle")?;
+        let leaf = chain.first().ok_or_else(|| anyhow!("Bundle has no signing certificate"))?;
+        verify_chain(&chain, &trust_root.fulcio_certificates)?;
+
+        let [entry] = self.verification_material.tlog_entries.as_slice() else {
+            return Err(anyhow!("Expected one transparency log entry, found {}", self.verification_material.tlog_entries.len()));
+        };
+        let log_index: u64 = entry.log_index.parse().context("Invalid log index")?;
+        let integrated_time: i64 = entry.integrated_time.parse().context("Invalid integrated time")?;
+        let log_id = hex::encode(general_purpose::STANDARD.decode(&entry.log_id.key_id).context("Invalid log id")?);
+        if log_id != trust_root.rekor_log_id {
+            return Err(anyhow!("Entry was logged by an untrusted Rekor log {}", log_id));
+        }
+
+        let set = general_purpose::STANDARD.decode(&entry.inclusion_promise.signed_entry_timestamp)
+            .context("Invalid signed entry timestamp")?;
+        let set = p256::ecdsa::Signature::from_der(&set).context("Invalid signed entry timestamp")?;
+        let payload = set_payload(&entry.canonicalized_body, integrated_time, &log_id, log_index);
+        trust_root.rekor_key.verify_prehash(&Sha256::digest(&payload), &set)
+            .map_err(|_| anyhow!("Signed entry timestamp does not verify"))?;
+
+        let validity = &leaf.tbs_certificate.validity;
+        let not_before = validity.not_before.to_unix_duration().as_secs() as i64;
+        let not_after = validity.not_after.to_unix_duration().as_secs() as i64;
+        if integrated_time < not_before || integrated_time > not_after {
+            return Err(anyhow!("Entry was logged outside the certificate's validity"));
+        }
+
+        let body = general_purpose::STANDARD.decode(&entry.canonicalized_body).context("Invalid entry body")?;
+        if let Some(proof) = &entry.inclusion_proof {
+            verify_inclusion_proof(proof, log_index, &body)?;
+        }
+
+        let digest = general_purpose::STANDARD.decode(&self.message_signature.message_digest.digest)
+            .context("Invalid message digest")?;
+        let leaf_pem = pem_encode(&leaf.to_der()?, "CERTIFICATE");
+        check_body(&body, &hex::encode(&digest), &self.message_signature.signature, &leaf_pem)?;
+
+        let signature = general_purpose::STANDARD.decode(&self.message_signature.signature)
+            .context("Invalid message signature")?;
+        verify_signature(leaf, &digest, &signature)
+            .context("Message signature does not verify")?;
+
+        let (oidc_issuer, oidc_identity) = signer_identity(leaf);
+        let entry_id = hex::encode(leaf_hash(&body));
+        Ok(SigstoreEntry {
+            entry_id: entry_id.clone(),
+            log_index: log_index.to_string(),
+            integrated_time: integrated_time.to_string(),
+            log_id,
+            rekor_entry_url: String::new(),
+            fulcio_certificate_url: String::new(),
+            oidc_issuer,
+            oidc_identity,
+            signature: self.message_signature.signature.clone(),
+            public_key: general_purpose::STANDARD.encode(leaf_pem),
+            artifact_hash: hex::encode(&digest),
+            artifact_type: artifact_type.to_string(),
+        })
+    }
+}
+
+// Each certificate must be issued by the next one, and the last by, or be,
+// a trusted Fulcio certificate
+fn verify_chain(chain: &[Certificate], trusted: &[Certificate]) -> Result<()> {
+    for pair in chain.windows(2) {
+        verify_issued_by(&pair[0], &pair[1])?;
+    }
+    let last = chain.last().ok_or_else(|| anyhow!("Empty certificate chain"))?;
+    let anchored = trusted.iter().any(|root| root == last || verify_issued_by(last, root).is_ok());
+    if anchored {
+        Ok(())
+    } else {
+        Err(anyhow!("Signing certificate does not chain to a trusted Fulcio certificate"))
+    }
+}
+
+fn verify_issued_by(certificate: &Certificate, issuer: &Certificate) -> Result<()> {
+    if certificate.tbs_certificate.issuer != issuer.tbs_certificate.subject {
+        return Err(anyhow!("Certificate issuer does not match"));
+    }
+    let message = certificate.tbs_certificate.to_der()?;
+    let signature = certificate.signature.as_bytes()
+        .ok_or_else(|| anyhow!("Certificate signature is not byte aligned"))?;
+    let digest = match certificate.signature_algorithm.oid.to_string().as_str() {
+        ECDSA_WITH_SHA256 => Sha256::digest(&message).to_vec(),
+        ECDSA_WITH_SHA384 => Sha384::digest(&message).to_vec(),
+        other => return Err(anyhow!("Unsupported certificate signature algorithm {}", other)),
+    };
+    verify_signature(issuer, &digest, signature)
+}
+
+// Fulcio issues from P-384 roots to P-256 leaves, so both curves are accepted
+fn verify_signature(signer: &Certificate, prehash: &[u8], signature: &[u8]) -> Result<()> {
+    let key = signer.tbs_certificate.subject_public_key_info.subject_public_key.raw_bytes();
+    if let Ok(key) = p256::ecdsa::VerifyingKey::from_sec1_bytes(key) {
+        let signature = p256::ecdsa::Signature::from_der(signature)?;
+        return key.verify_prehash(prehash, &signature).map_err(|_| anyhow!("Invalid signature"));
+    }
+    if let Ok(key) = p384::ecdsa::VerifyingKey::from_sec1_bytes(key) {
+        let signature = p384::ecdsa::Signature::from_der(signature)?;
+        return key.verify_prehash(prehash, &signature).map_err(|_| anyhow!("Invalid signature"));
+    }
+    Err(anyhow!("Unsupported public key; expected P-256 or P-384"))
+}
+
+/// The canonical JSON Rekor signs to produce an entry timestamp
+pub fn set_payload(body: &str, integrated_time: i64, log_id: &str, log_index: u64) -> Vec<u8> {
+    // serde_json keeps object keys sorted, which canonical JSON requires
+    serde_json::to_vec(&serde_json::json!({
+        "body": body,
+        "integratedTime": integrated_time,
+        "logID": log_id,
+        "logIndex": log_index,
+    })).unwrap_or_default()
+}
+
+// The logged hashedrekord entry must be for this digest, signature and
+// certificate, or the timestamp vouches for a different signature
+fn check_body(body: &[u8], digest_hex: &str, signature: &str, certificate_pem: &str) -> Result<()> {
+    let body: serde_json::Value = serde_json::from_slice(body).context("Entry body is not JSON")?;
+    if body["kind"] != "hashedrekord" {
+        return Err(anyhow!("Unsupported entry kind {}", body["kind"]));
+    }
+    let spec = &body["spec"];
+    if spec["data"]["hash"]["value"].as_str() != Some(digest_hex) {
+        return Err(anyhow!("Entry is for a different digest"));
+    }
+    if spec["signature"]["content"].as_str() != Some(signature) {
+        return Err(anyhow!("Entry is for a different signature"));
+    }
+    let logged_certificate = spec["signature"]["publicKey"]["content"].as_str()
+        .and_then(|content| general_purpose::STANDARD.decode(content).ok());
+    if logged_certificate.as_deref() != Some(certificate_pem.as_bytes()) {
+        return Err(anyhow!("Entry is for a different certificate"));
+    }
+    Ok(())
+}
+
+fn verify_inclusion_proof(proof: &InclusionProof, log_index: u64, body: &[u8]) -> Result<()> {
+    let index: u64 = proof.log_index.parse().context("Invalid inclusion proof index")?;
+    let tree_size: u64 = proof.tree_size.parse().context("Invalid inclusion proof tree size")?;
+    // Sharded logs report the entry's global index outside the proof
+    if index > log_index {
+        return Err(anyhow!("Inclusion proof is for another entry"));
+    }
+    let hashes = proof.hashes.iter()
+        .map(|hash| decode_hash(hash))
+        .collect::<Result<Vec<_>>>()?;
+    let root = decode_hash(&proof.root_hash)?;
+    if verify_inclusion(index, tree_size, leaf_hash(body), &hashes, root) {
+        Ok(())
+    } else {
+        Err(anyhow!("Inclusion proof does not verify"))
+    }
+}
+
+// Bundles carry hashes as base64; Rekor's API as hex
+fn decode_hash(hash: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(hash).or_else(|_| general_purpose::STANDARD.decode(hash))
+        .context("Invalid hash in inclusion proof")?;
+    bytes.try_into().map_err(|_| anyhow!("Inclusion proof hash is not SHA-256"))
+}
+
+fn leaf_hash(body: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(body);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// RFC 9162 inclusion proof verification
+pub fn verify_inclusion(index: u64, tree_size: u64, leaf: [u8; 32], proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+    if index >= tree_size {
+        return false;
+    }
+    let inner = (64 - (index ^ (tree_size - 1)).leading_zeros()) as usize;
+    let border = (index >> inner).count_ones() as usize;
+    if proof.len() != inner + border {
+        return false;
+    }
+
+    let mut hash = leaf;
+    for (level, sibling) in proof[..inner].iter().enumerate() {
+        hash = if (index >> level) & 1 == 0 {
+            node_hash(&hash, sibling)
+        } else {
+            node_hash(sibling, &hash)
+        };
+    }
+    for sibling in &proof[inner..] {
+        hash = node_hash(sibling, &hash);
+    }
+    hash == root
+}
+
+fn signer_identity(certificate: &Certificate) -> (String, String) {
+    let mut issuer = String::new();
+    let mut identity = String::new();
+    for extension in certificate.tbs_certificate.extensions.iter().flatten() {
+        let value = extension.extn_value.as_bytes();
+        match extension.extn_id.to_string().as_str() {
+            FULCIO_ISSUER_V1 if issuer.is_empty() => issuer = String::from_utf8_lossy(value).to_string(),
+            FULCIO_ISSUER_V2 => {
+                if let Ok(value) = x509_cert::der::asn1::Utf8StringRef::from_der(value) {
+                    issuer = value.as_str().to_string();
+                }
+            }
+            SUBJECT_ALT_NAME => {
+                let names = SubjectAltName::from_der(value).map(|names| names.0).unwrap_or_default();
+                identity = names.iter()
+                    .find_map(|name| match name {
+                        GeneralName::Rfc822Name(email) => Some(email.to_string()),
+                        GeneralName::UniformResourceIdentifier(uri) => Some(uri.to_string()),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+            }
+            _ => {}
+        }
+    }
+    (issuer, identity)
+}
+
+fn pem_blocks(text: &str, label: &str) -> Result<Vec<Vec<u8>>> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let mut blocks = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(&begin) {
+        let body = &rest[start + begin.len()..];
+        let stop = body.find(&end).ok_or_else(|| anyhow!("Unterminated PEM block"))?;
+        let encoded: String = body[..stop].chars().filter(|c| !c.is_whitespace()).collect();
+        blocks.push(general_purpose::STANDARD.decode(encoded).context("Invalid PEM block")?);
+        rest = &body[stop + end.len()..];
+    }
+    Ok(blocks)
+}
+
+fn pem_encode(der: &[u8], label: &str) -> String {
+    let encoded = general_purpose::STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(leaves: &[[u8; 32]]) -> [u8; 32] {
+        match leaves.len() {
+            1 => leaves[0],
+            n => {
+                let split = n.next_power_of_two() / 2;
+                node_hash(&tree(&leaves[..split]), &tree(&leaves[split..]))
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_inclusion() {
+        let leaves: Vec<_> = (0u8..7).map(|i| leaf_hash(&[i])).collect();
+        let root = tree(&leaves);
+
+        // Leaf 4 of 7: siblings are leaf 5, then leaf 6, then the left subtree
+        let proof = [leaves[5], leaves[6], tree(&leaves[..4])];
+        assert!(verify_inclusion(4, 7, leaves[4], &proof, root));
+        assert!(!verify_inclusion(4, 7, leaves[3], &proof, root));
+        assert!(!verify_inclusion(4, 7, leaves[4], &proof[..2], root));
+        assert!(!verify_inclusion(7, 7, leaves[4], &proof, root));
+    }
+
+    #[test]
+    fn test_set_payload_is_canonical() {
+        let payload = set_payload("eyJ9", 1700000000, "c0d23d6a", 42);
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            r#"{"body":"eyJ9","integratedTime":1700000000,"logID":"c0d23d6a","logIndex":42}"#
+        );
+    }
+
+    #[test]
+    fn test_check_body() {
+        let certificate = pem_encode(b"certificate", "CERTIFICATE");
+        let body = serde_json::json!({
+            "apiVersion": "0.0.1",
+            "kind": "hashedrekord",
+            "spec": {
+                "data": {"hash": {"algorithm": "sha256", "value": "abcd"}},
+                "signature": {
+                    "content": "c2lnbmF0dXJl",
+                    "publicKey": {"content": general_purpose::STANDARD.encode(&certificate)},
+                },
+            },
+        });
+        let body = serde_json::to_vec(&body).unwrap();
+
+        assert!(check_body(&body, "abcd", "c2lnbmF0dXJl", &certificate).is_ok());
+        assert!(check_body(&body, "ef01", "c2lnbmF0dXJl", &certificate).is_err());
+        assert!(check_body(&body, "abcd", "b3RoZXI=", &certificate).is_err());
+    }
+
+    #[test]
+    fn test_pem_round_trip() {
+        let der = vec![7u8; 100];
+        let pem = pem_encode(&der, "CERTIFICATE");
+        assert_eq!(pem_blocks(&format!("{}{}", pem, pem), "CERTIFICATE").unwrap(), vec![der.clone(), der]);
+        assert!(pem_blocks(&pem, "PUBLIC KEY").unwrap().is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -9,7 +10,10 @@ use anyhow::{Result, Context};
 use tracing::{info, warn, error};
 use chrono::{DateTime, Utc};
 
+use crate::badge_queue::BadgeJobQueue;
 use crate::config::GitHubAppConfig;
+use crate::installations::{Installation, InstallationRegistry};
+use crate::repo_config::{RepoConfig, RepoConfigStore};
 use crate::proto::gh_app::v1::*;
 
 #[derive(Debug, Clone)]
@@ -17,6 +21,9 @@ pub struct WebhookProcessor {
     config: GitHubAppConfig,
     event_handlers: HashMap<String, Box<dyn EventHandler + Send + Sync>>,
     signature_cache: HashMap<String, Instant>,
+    badge_queue: Option<Arc<BadgeJobQueue>>,
+    installations: Arc<InstallationRegistry>,
+    repo_configs: Option<Arc<RepoConfigStore>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,6 +43,48 @@ pub struct WebhookPayload {
     pub forced: Option<bool>,
     pub base_ref: Option<String>,
     pub compare: Option<String>,
+    pub check_suite: Option<CheckSuitePayload>,
+    pub workflow_run: Option<WorkflowRunPayload>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckSuitePayload {
+    pub id: u64,
+    pub head_sha: String,
+    pub head_branch: Option<String>,
+    pub status: Option<String>,
+    pub conclusion: Option<String>,
+    #[serde(default)]
+    pub pull_requests: Vec<CheckPullRequestPayload>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkflowRunPayload {
+    pub id: u64,
+    pub name: Option<String>,
+    pub head_sha: String,
+    pub head_branch: Option<String>,
+    /// Event that triggered the workflow, e.g. "pull_request"
+    pub event: String,
+    pub status: Option<String>,
+    pub conclusion: Option<String>,
+    /// 1 for the first run, incremented by every re-run
+    #[serde(default = "default_run_attempt")]
+    pub run_attempt: u64,
+    #[serde(default)]
+    pub pull_requests: Vec<CheckPullRequestPayload>,
+}
+
+fn default_run_attempt() -> u64 {
+    1
+}
+
+/// A pull request as listed in check suite and workflow run payloads,
+/// which carry no title or body
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckPullRequestPayload {
+    pub id: u64,
+    pub number: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -141,10 +190,34 @@ pub trait EventHandler: Send + Sync {
 
 impl WebhookProcessor {
     pub async fn new(config: &GitHubAppConfig) -> Result<Self> {
+        Self::build(config, None, Arc::new(InstallationRegistry::from_config(config)), None).await
+    }
+    
+    /// Processor whose handlers enqueue badge update jobs instead of only
+    /// reporting a pending badge, keep the installation registry in sync,
+    /// and find spec references with each repository's configured patterns
+    pub async fn with_dependencies(
+        config: &GitHubAppConfig,
+        badge_queue: Arc<BadgeJobQueue>,
+        installations: Arc<InstallationRegistry>,
+        repo_configs: Arc<RepoConfigStore>,
+    ) -> Result<Self> {
+        Self::build(config, Some(badge_queue), installations, Some(repo_configs)).await
+    }
+    
+    async fn build(
+        config: &GitHubAppConfig,
+        badge_queue: Option<Arc<BadgeJobQueue>>,
+        installations: Arc<InstallationRegistry>,
+        repo_configs: Option<Arc<RepoConfigStore>>,
+    ) -> Result<Self> {
         let mut processor = Self {
             config: config.clone(),
             event_handlers: HashMap::new(),
             signature_cache: HashMap::new(),
+            badge_queue,
+            installations,
+            repo_configs,
         };
         
         // Register event handlers
@@ -157,13 +230,13 @@ impl WebhookProcessor {
         // Register pull request handler
         self.event_handlers.insert(
             "pull_request".to_string(),
-            Box::new(PullRequestHandler::new(&self.config).await?),
+            Box::new(PullRequestHandler::new(&self.config, self.badge_queue.clone(), self.repo_configs.clone()).await?),
         );
         
         // Register push handler
         self.event_handlers.insert(
             "push".to_string(),
-            Box::new(PushHandler::new(&self.config).await?),
+            Box::new(PushHandler::new(&self.config, self.badge_queue.clone(), self.repo_configs.clone()).await?),
         );
         
         // Register status handler
@@ -172,6 +245,23 @@ impl WebhookProcessor {
             Box::new(StatusHandler::new(&self.config).await?),
         );
         
+        // Register re-run handlers, so "Re-run checks" in the GitHub UI
+        // verifies the pull request's spec documents again
+        self.event_handlers.insert(
+            "check_suite".to_string(),
+            Box::new(CheckSuiteHandler::new(&self.config, self.badge_queue.clone())),
+        );
+        self.event_handlers.insert(
+            "workflow_run".to_string(),
+            Box::new(WorkflowRunHandler::new(&self.config, self.badge_queue.clone())),
+        );
+        
+        // Register installation handler
+        self.event_handlers.insert(
+            "installation".to_string(),
+            Box::new(InstallationHandler::new(self.installations.clone())),
+        );
+        
         info!("Registered {} event handlers", self.event_handlers.len());
         Ok(())
     }
@@ -236,6 +326,16 @@ impl WebhookProcessor {
         let webhook_payload: WebhookPayload = serde_json::from_str(&payload)
             .context("Failed to parse webhook payload")?;
         
+        // Scope the event to its installation. GitHub only signs deliveries
+        // for live installations, so unknown ones (e.g. added before a
+        // restart) are registered here.
+        if let Some(installation) = &webhook_payload.installation {
+            if event_type != "installation" {
+                self.installations.ensure_registered(installation_from_payload(installation)).await;
+                self.installations.resolve(&installation.id.to_string()).await?;
+            }
+        }
+        
         // Find handler for event type
         let handler = self.event_handlers.get(&event_type)
             .ok_or_else(|| anyhow::anyhow!("No handler found for event type: {}", event_type))?;
@@ -274,40 +374,259 @@ impl WebhookProcessor {
     }
     
     fn extract_spec_references(&self, text: &str) -> Vec<String> {
-        let mut references = Vec::new();
-        
-        // Look for spec document references in various formats
-        let patterns = vec![
-            r"spec:\s*([a-zA-Z0-9_-]+)",           // spec: DOC-123
-            r"document:\s*([a-zA-Z0-9_-]+)",       // document: DOC-123
-            r"#([a-zA-Z0-9_-]+)",                  // #DOC-123
-            r"DOC-([0-9]+)",                       // DOC-123
-            r"SPEC-([0-9]+)",                      // SPEC-123
-        ];
-        
-        for pattern in patterns {
-            if let Ok(regex) = regex::Regex::new(pattern) {
-                for cap in regex.captures_iter(text) {
-                    if let Some(reference) = cap.get(1) {
-                        references.push(reference.as_str().to_string());
-                    }
+        crate::scm::extract_spec_references(text)
+    }
+}
+
+fn installation_from_payload(payload: &InstallationPayload) -> Installation {
+    Installation::new(
+        &payload.id.to_string(),
+        &payload.account.login,
+        &payload.account.user_type,
+        &payload.repository_selection,
+    )
+}
+
+// Installation Event Handler
+pub struct InstallationHandler {
+    installations: Arc<InstallationRegistry>,
+}
+
+impl InstallationHandler {
+    pub fn new(installations: Arc<InstallationRegistry>) -> Self {
+        Self { installations }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for InstallationHandler {
+    async fn handle(&self, payload: &WebhookPayload) -> Result<ProcessWebhookResponse> {
+        let installation = payload.installation.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Installation event without installation"))?;
+        let action = payload.action.as_deref().unwrap_or("unknown");
+        let installation_id = installation.id.to_string();
+        
+        info!("Handling installation event: action={}, installation={}", action, installation_id);
+        
+        match action {
+            "created" => self.installations.add(installation_from_payload(installation)).await,
+            "deleted" => {
+                self.installations.remove(&installation_id).await;
+            }
+            "suspend" | "unsuspend" => {
+                let suspended = action == "suspend";
+                if !self.installations.set_suspended(&installation_id, suspended).await {
+                    let mut registered = installation_from_payload(installation);
+                    registered.suspended = suspended;
+                    self.installations.add(registered).await;
                 }
             }
+            _ => {
+                warn!("Unhandled installation action: {}", action);
+            }
         }
         
-        references
+        Ok(ProcessWebhookResponse {
+            success: true,
+            message: format!("Installation {} {}", installation_id, action),
+            badge_updates: Vec::new(),
+            processed_events: vec![format!("installation_{}", action)],
+        })
+    }
+}
+
+// Hands the badge update to the background workers when a queue is wired in
+async fn pending_badge_message(badge_queue: Option<&BadgeJobQueue>, request: BadgeStatusRequest) -> Result<String> {
+    match badge_queue {
+        Some(queue) => {
+            let job_id = queue.enqueue(request).await?;
+            Ok(format!("Verifying spec documents... (badge job {})", job_id))
+        }
+        None => Ok("Verifying spec documents...".to_string()),
+    }
+}
+
+// The repository's config at `commit_sha`. Webhooks are still processed,
+// with the built-in reference patterns, when it can't be fetched.
+async fn repo_config_at(
+    repo_configs: Option<&RepoConfigStore>,
+    payload: &WebhookPayload,
+    commit_sha: &str,
+) -> Option<Arc<RepoConfig>> {
+    let repo_configs = repo_configs?;
+    let repository = payload.repository.as_ref()?;
+    let installation_id = payload.installation.as_ref()
+        .map(|i| i.id.to_string())
+        .unwrap_or_default();
+    match repo_configs.get(&installation_id, &repository.full_name, commit_sha).await {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Failed to fetch config of {}@{}: {}", repository.full_name, commit_sha, e);
+            None
+        }
+    }
+}
+
+// Queues a refreshed badge update at `head_sha` for each pull request,
+// reusing the spec documents of its latest badge request since check suite
+// and workflow run payloads don't carry the pull request body
+async fn reverify_pull_requests(
+    config: &GitHubAppConfig,
+    badge_queue: Option<&BadgeJobQueue>,
+    payload: &WebhookPayload,
+    pull_requests: &[CheckPullRequestPayload],
+    head_sha: &str,
+) -> Result<Vec<BadgeStatusResponse>> {
+    let Some(queue) = badge_queue else {
+        warn!("No badge queue wired in, re-run not verified");
+        return Ok(Vec::new());
+    };
+    let repository_id = payload.repository.as_ref()
+        .map(|r| r.id.to_string())
+        .unwrap_or_default();
+
+    let mut badge_updates = Vec::new();
+    for pr in pull_requests {
+        let Some(latest) = queue.latest_request(ScmKind::GitHub, &repository_id, &pr.id.to_string()).await else {
+            warn!("No earlier badge for pull request #{} in {}, nothing to re-verify", pr.number, repository_id);
+            continue;
+        };
+        let badge_request = BadgeStatusRequest {
+            commit_sha: head_sha.to_string(),
+            refresh: true,
+            ..latest
+        };
+
+        let message = pending_badge_message(Some(queue), badge_request).await?;
+        badge_updates.push(BadgeStatusResponse {
+            status: BadgeStatus::BadgeStatusPending,
+            message,
+            target_url: config.badge_target_url.clone(),
+            description: config.badge_description.clone(),
+            context: config.badge_context.clone(),
+            proof_artifacts: Vec::new(),
+            sigstore_entries: Vec::new(),
+            created_at: Some(chrono::Utc::now().into()),
+            updated_at: Some(chrono::Utc::now().into()),
+        });
+    }
+
+    Ok(badge_updates)
+}
+
+// Check Suite Event Handler
+pub struct CheckSuiteHandler {
+    config: GitHubAppConfig,
+    badge_queue: Option<Arc<BadgeJobQueue>>,
+}
+
+impl CheckSuiteHandler {
+    pub fn new(config: &GitHubAppConfig, badge_queue: Option<Arc<BadgeJobQueue>>) -> Self {
+        Self {
+            config: config.clone(),
+            badge_queue,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for CheckSuiteHandler {
+    async fn handle(&self, payload: &WebhookPayload) -> Result<ProcessWebhookResponse> {
+        let check_suite = payload.check_suite.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Check suite event without check_suite"))?;
+        let action = payload.action.as_deref().unwrap_or("unknown");
+        
+        info!("Handling check suite event: action={}, check_suite={}", action, check_suite.id);
+        
+        // "rerequested" is sent when a user re-runs the app's checks
+        let badge_updates = if action == "rerequested" {
+            reverify_pull_requests(
+                &self.config,
+                self.badge_queue.as_deref(),
+                payload,
+                &check_suite.pull_requests,
+                &check_suite.head_sha,
+            ).await?
+        } else {
+            Vec::new()
+        };
+        
+        Ok(ProcessWebhookResponse {
+            success: true,
+            message: format!("Check suite {} {}, {} badge(s) re-verified", check_suite.id, action, badge_updates.len()),
+            badge_updates,
+            processed_events: vec![format!("check_suite_{}", action)],
+        })
+    }
+}
+
+// Workflow Run Event Handler
+pub struct WorkflowRunHandler {
+    config: GitHubAppConfig,
+    badge_queue: Option<Arc<BadgeJobQueue>>,
+}
+
+impl WorkflowRunHandler {
+    pub fn new(config: &GitHubAppConfig, badge_queue: Option<Arc<BadgeJobQueue>>) -> Self {
+        Self {
+            config: config.clone(),
+            badge_queue,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for WorkflowRunHandler {
+    async fn handle(&self, payload: &WebhookPayload) -> Result<ProcessWebhookResponse> {
+        let workflow_run = payload.workflow_run.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Workflow run event without workflow_run"))?;
+        let action = payload.action.as_deref().unwrap_or("unknown");
+        
+        info!(
+            "Handling workflow run event: action={}, workflow_run={}, attempt={}",
+            action, workflow_run.id, workflow_run.run_attempt
+        );
+        
+        // First runs are covered by the pull_request event that triggered
+        // them; only re-runs verify again
+        let badge_updates = if action == "requested" && workflow_run.run_attempt > 1 {
+            reverify_pull_requests(
+                &self.config,
+                self.badge_queue.as_deref(),
+                payload,
+                &workflow_run.pull_requests,
+                &workflow_run.head_sha,
+            ).await?
+        } else {
+            Vec::new()
+        };
+        
+        Ok(ProcessWebhookResponse {
+            success: true,
+            message: format!("Workflow run {} {}, {} badge(s) re-verified", workflow_run.id, action, badge_updates.len()),
+            badge_updates,
+            processed_events: vec![format!("workflow_run_{}", action)],
+        })
     }
 }
 
 // Pull Request Event Handler
 pub struct PullRequestHandler {
     config: GitHubAppConfig,
+    badge_queue: Option<Arc<BadgeJobQueue>>,
+    repo_configs: Option<Arc<RepoConfigStore>>,
 }
 
 impl PullRequestHandler {
-    pub async fn new(config: &GitHubAppConfig) -> Result<Self> {
+    pub async fn new(
+        config: &GitHubAppConfig,
+        badge_queue: Option<Arc<BadgeJobQueue>>,
+        repo_configs: Option<Arc<RepoConfigStore>>,
+    ) -> Result<Self> {
         Ok(Self {
             config: config.clone(),
+            badge_queue,
+            repo_configs,
         })
     }
 }
@@ -324,8 +643,14 @@ impl EventHandler for PullRequestHandler {
             if let Some(action) = &payload.action {
                 match action.as_str() {
                     "opened" | "synchronize" | "reopened" => {
-                        // Extract spec documents
-                        let spec_documents = self.extract_spec_documents(payload).await?;
+                        // Extract spec documents, with the repository's own
+                        // document id patterns if it configures any
+                        let spec_documents = match repo_config_at(self.repo_configs.as_deref(), payload, &pr.head.sha).await {
+                            Some(repo_config) => pr.body.as_deref()
+                                .map(|body| repo_config.spec_references(body))
+                                .unwrap_or_default(),
+                            None => self.extract_spec_documents(payload).await?,
+                        };
                         
                         if !spec_documents.is_empty() {
                             // Create badge status request
@@ -338,12 +663,16 @@ impl EventHandler for PullRequestHandler {
                                     .map(|i| i.id.to_string())
                                     .unwrap_or_default(),
                                 app_id: self.config.app_id.clone(),
+                                provider: ScmKind::GitHub,
+                                refresh: false,
                             };
                             
+                            let message = pending_badge_message(self.badge_queue.as_deref(), badge_request).await?;
+                            
                             // Create badge response
                             let badge_response = BadgeStatusResponse {
                                 status: BadgeStatus::BadgeStatusPending,
-                                message: "Verifying spec documents...".to_string(),
+                                message,
                                 target_url: self.config.badge_target_url.clone(),
                                 description: self.config.badge_description.clone(),
                                 context: self.config.badge_context.clone(),
@@ -380,12 +709,20 @@ impl EventHandler for PullRequestHandler {
 // Push Event Handler
 pub struct PushHandler {
     config: GitHubAppConfig,
+    badge_queue: Option<Arc<BadgeJobQueue>>,
+    repo_configs: Option<Arc<RepoConfigStore>>,
 }
 
 impl PushHandler {
-    pub async fn new(config: &GitHubAppConfig) -> Result<Self> {
+    pub async fn new(
+        config: &GitHubAppConfig,
+        badge_queue: Option<Arc<BadgeJobQueue>>,
+        repo_configs: Option<Arc<RepoConfigStore>>,
+    ) -> Result<Self> {
         Ok(Self {
             config: config.clone(),
+            badge_queue,
+            repo_configs,
         })
     }
 }
@@ -398,8 +735,19 @@ impl EventHandler for PushHandler {
         let mut badge_updates = Vec::new();
         let mut processed_events = Vec::new();
         
-        // Extract spec documents from commit messages
-        let spec_documents = self.extract_spec_documents(payload).await?;
+        // Extract spec documents from commit messages, with the
+        // repository's own document id patterns if it configures any
+        let repo_config = match &payload.head_commit {
+            Some(head_commit) => repo_config_at(self.repo_configs.as_deref(), payload, &head_commit.id).await,
+            None => None,
+        };
+        let spec_documents = match repo_config {
+            Some(repo_config) => payload.commits.iter().flatten()
+                .chain(payload.head_commit.as_ref())
+                .flat_map(|commit| repo_config.spec_references(&commit.message))
+                .collect(),
+            None => self.extract_spec_documents(payload).await?,
+        };
         
         if !spec_documents.is_empty() {
             // Create badge status request for the head commit
@@ -415,12 +763,16 @@ impl EventHandler for PushHandler {
                         .map(|i| i.id.to_string())
                         .unwrap_or_default(),
                     app_id: self.config.app_id.clone(),
+                    provider: ScmKind::GitHub,
+                    refresh: false,
                 };
                 
+                let message = pending_badge_message(self.badge_queue.as_deref(), badge_request).await?;
+                
                 // Create badge response
                 let badge_response = BadgeStatusResponse {
                     status: BadgeStatus::BadgeStatusPending,
-                    message: "Verifying spec documents...".to_string(),
+                    message,
                     target_url: self.config.badge_target_url.clone(),
                     description: self.config.badge_description.clone(),
                     context: self.config.badge_context.clone(),
@@ -529,55 +881,13 @@ trait SpecReferenceExtractor {
 
 impl SpecReferenceExtractor for PullRequestHandler {
     fn extract_spec_references(&self, text: &str) -> Vec<String> {
-        let mut references = Vec::new();
-        
-        // Look for spec document references in various formats
-        let patterns = vec![
-            r"spec:\s*([a-zA-Z0-9_-]+)",           // spec: DOC-123
-            r"document:\s*([a-zA-Z0-9_-]+)",       // document: DOC-123
-            r"#([a-zA-Z0-9_-]+)",                  // #DOC-123
-            r"DOC-([0-9]+)",                       // DOC-123
-            r"SPEC-([0-9]+)",                      // SPEC-123
-        ];
-        
-        for pattern in patterns {
-            if let Ok(regex) = regex::Regex::new(pattern) {
-                for cap in regex.captures_iter(text) {
-                    if let Some(reference) = cap.get(1) {
-                        references.push(reference.as_str().to_string());
-                    }
-                }
-            }
-        }
-        
-        references
+        crate::scm::extract_spec_references(text)
     }
 }
 
 impl SpecReferenceExtractor for PushHandler {
     fn extract_spec_references(&self, text: &str) -> Vec<String> {
-        let mut references = Vec::new();
-        
-        // Look for spec document references in various formats
-        let patterns = vec![
-            r"spec:\s*([a-zA-Z0-9_-]+)",           // spec: DOC-123
-            r"document:\s*([a-zA-Z0-9_-]+)",       // document: DOC-123
-            r"#([a-zA-Z0-9_-]+)",                  // #DOC-123
-            r"DOC-([0-9]+)",                       // DOC-123
-            r"SPEC-([0-9]+)",                      // SPEC-123
-        ];
-        
-        for pattern in patterns {
-            if let Ok(regex) = regex::Regex::new(pattern) {
-                for cap in regex.captures_iter(text) {
-                    if let Some(reference) = cap.get(1) {
-                        references.push(reference.as_str().to_string());
-                    }
-                }
-            }
-        }
-        
-        references
+        crate::scm::extract_spec_references(text)
     }
 }
 
@@ -598,10 +908,76 @@ mod tests {
         assert!(processor.is_ok());
     }
     
+    #[tokio::test]
+    async fn test_installation_events_update_registry() {
+        let installations = Arc::new(InstallationRegistry::new());
+        let processor = WebhookProcessor::build(&GitHubAppConfig::default(), None, installations.clone(), None).await.unwrap();
+        let event = |action: &str| ProcessWebhookRequest {
+            payload: format!(
+                r#"{{"action":"{}","installation":{{"id":4242,"account":{{"id":1,"login":"acme","avatar_url":"","type":"Organization","site_admin":false}},"repository_selection":"all","permissions":{{}},"events":[]}}}}"#,
+                action
+            ),
+            signature: String::new(),
+            event_type: "installation".to_string(),
+            delivery_id: "d-1".to_string(),
+            installation_id: String::new(),
+        };
+        
+        processor.process_webhook(event("created")).await.unwrap();
+        assert_eq!(installations.get("4242").await.unwrap().account_login, "acme");
+        assert_eq!(installations.resolve("4242").await.unwrap(), "4242");
+        
+        processor.process_webhook(event("suspend")).await.unwrap();
+        assert!(installations.resolve("4242").await.is_err());
+        
+        processor.process_webhook(event("deleted")).await.unwrap();
+        assert!(installations.get("4242").await.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_check_suite_rerequest_reverifies_pull_request() {
+        let config = GitHubAppConfig::default();
+        let queue = Arc::new(BadgeJobQueue::new(&config, Arc::new(tokio::sync::RwLock::new(HashMap::new()))));
+        queue.enqueue(BadgeStatusRequest {
+            repository_id: "99".to_string(),
+            pull_request_id: "123".to_string(),
+            commit_sha: "abc".to_string(),
+            spec_document_ids: vec!["DOC-1".to_string()],
+            installation_id: "4242".to_string(),
+            app_id: "42".to_string(),
+            provider: ScmKind::GitHub,
+            refresh: false,
+        }).await.unwrap();
+        
+        let processor = WebhookProcessor::build(&config, Some(queue.clone()), Arc::new(InstallationRegistry::new()), None).await.unwrap();
+        let check_suite = |action: &str, pull_request_id: u64| ProcessWebhookRequest {
+            payload: format!(
+                r#"{{"action":"{}","check_suite":{{"id":7,"head_sha":"def","pull_requests":[{{"id":{},"number":5}}]}},"repository":{{"id":99,"name":"payments","full_name":"acme/payments","owner":{{"id":1,"login":"acme","avatar_url":"","type":"Organization","site_admin":false}},"private":false,"default_branch":"main","html_url":"","clone_url":"","ssh_url":""}}}}"#,
+                action, pull_request_id
+            ),
+            signature: String::new(),
+            event_type: "check_suite".to_string(),
+            delivery_id: format!("d-{}-{}", action, pull_request_id),
+            installation_id: String::new(),
+        };
+        
+        let response = processor.process_webhook(check_suite("rerequested", 123)).await.unwrap();
+        assert_eq!(response.badge_updates.len(), 1);
+        let latest = queue.latest_request(ScmKind::GitHub, "99", "123").await.unwrap();
+        assert_eq!(latest.commit_sha, "def");
+        assert_eq!(latest.spec_document_ids, vec!["DOC-1".to_string()]);
+        
+        // Completed suites and pull requests without an earlier badge are left alone
+        assert!(processor.process_webhook(check_suite("completed", 123)).await.unwrap().badge_updates.is_empty());
+        assert!(processor.process_webhook(check_suite("rerequested", 456)).await.unwrap().badge_updates.is_empty());
+    }
+    
     #[test]
     fn test_spec_reference_extraction() {
         let handler = PullRequestHandler {
             config: GitHubAppConfig::default(),
+            badge_queue: None,
+            repo_configs: None,
         };
         
         let text = "This PR addresses spec: DOC-123 and document: SPEC-456. Also see #DOC-789.";
@@ -619,6 +995,9 @@ mod tests {
             config,
             event_handlers: HashMap::new(),
             signature_cache: HashMap::new(),
+            badge_queue: None,
+            installations: Arc::new(InstallationRegistry::new()),
+            repo_configs: None,
         };
         
         // Test with valid signature
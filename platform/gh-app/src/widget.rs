@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use axum::{
+    routing::get,
+    Router,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+    extract::{ConnectInfo, Path, State},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+use tower_http::cors::{Any, CorsLayer};
+use tracing::{info, warn};
+use reload::ConfigHandle;
+
+use crate::AppState;
+use crate::badge::CoverageReportRequest;
+use crate::config::GitHubAppConfig;
+use crate::runtime::RuntimeSettings;
+
+// Upper bound on tracked clients before expired windows are swept
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Public summary served to the embeddable "formally verified" widget
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationWidget {
+    pub invariant_set_id: String,
+    pub proven: u32,
+    pub total: u32,
+    pub coverage: f64,
+    pub last_verified_at: DateTime<Utc>,
+    pub sigstore_url: Option<String>,
+}
+
+/// Latest completed verification per invariant set, fed by coverage reports
+#[derive(Debug, Default)]
+pub struct WidgetStore {
+    summaries: RwLock<HashMap<String, VerificationWidget>>,
+}
+
+impl WidgetStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a finished batch; partial reports and batches without an
+    /// invariant set are ignored so the widget only shows settled results
+    pub async fn record(&self, report: &CoverageReportRequest, config: &GitHubAppConfig) -> Option<VerificationWidget> {
+        let invariant_set_id = report.invariant_set_id.as_ref()?;
+        if report.completed < report.total {
+            return None;
+        }
+
+        let coverage = if report.total == 0 {
+            0.0
+        } else {
+            report.proven as f64 / report.total as f64
+        };
+
+        let widget = VerificationWidget {
+            invariant_set_id: invariant_set_id.clone(),
+            proven: report.proven,
+            total: report.total,
+            coverage,
+            last_verified_at: Utc::now(),
+            sigstore_url: report.rekor_entry_uuid.as_ref().map(|uuid| {
+                format!("{}/api/v1/log/entries/{}", config.sigstore_rekor_url.trim_end_matches('/'), uuid)
+            }),
+        };
+
+        self.summaries.write().await.insert(invariant_set_id.clone(), widget.clone());
+        Some(widget)
+    }
+
+    pub async fn get(&self, invariant_set_id: &str) -> Option<VerificationWidget> {
+        self.summaries.read().await.get(invariant_set_id).cloned()
+    }
+}
+
+/// Fixed-window limiter keyed by client address. IPv6 clients are grouped
+/// by /64, since a single host can usually rotate through its whole prefix.
+#[derive(Debug)]
+pub struct WidgetRateLimiter {
+    limit: u32,
+    window: Duration,
+    runtime: Option<ConfigHandle<RuntimeSettings>>,
+    clients: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl WidgetRateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            runtime: None,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes the limit and window from `runtime` on every request instead
+    pub fn with_runtime_settings(mut self, runtime: ConfigHandle<RuntimeSettings>) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    fn limits(&self) -> (u32, Duration) {
+        match &self.runtime {
+            Some(runtime) => {
+                let settings = runtime.current();
+                (settings.widget_rate_limit_requests, Duration::from_secs(settings.widget_rate_limit_window))
+            }
+            None => (self.limit, self.window),
+        }
+    }
+
+    pub fn from_config(config: &GitHubAppConfig) -> Self {
+        Self::new(
+            config.widget_rate_limit_requests,
+            Duration::from_secs(config.widget_rate_limit_window),
+        )
+    }
+
+    /// Counts a request, returning how long to wait if the client is over its limit
+    pub async fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        let key = rate_limit_key(client);
+        let (limit, window) = self.limits();
+        let now = Instant::now();
+        let mut clients = self.clients.lock().await;
+
+        if clients.len() >= MAX_TRACKED_CLIENTS {
+            clients.retain(|_, (started, _)| now.duration_since(*started) < window);
+        }
+
+        let (started, count) = clients.entry(key).or_insert((now, 0));
+        if now.duration_since(*started) >= window {
+            *started = now;
+            *count = 0;
+        }
+
+        if *count >= limit {
+            return Err(window.saturating_sub(now.duration_since(*started)));
+        }
+
+        *count += 1;
+        Ok(())
+    }
+}
+
+fn rate_limit_key(client: IpAddr) -> IpAddr {
+    match client {
+        IpAddr::V4(_) => client,
+        IpAddr::V6(v6) => {
+            let mut segments = v6.segments();
+            segments[4..].fill(0);
+            IpAddr::V6(segments.into())
+        }
+    }
+}
+
+// Only the rightmost X-Forwarded-For entry is added by our own proxy; the
+// rest are client-controlled and would let callers dodge the limiter
+fn client_ip(config: &GitHubAppConfig, headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+    if config.widget_trust_forwarded_for {
+        let forwarded = headers
+            .get("X-Forwarded-For")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    peer.ip()
+}
+
+/// Public, read-only routes; any origin may embed the widget, but only
+/// simple GET requests without credentials are allowed
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/widget/invariant-sets/:id", get(get_widget))
+        .layer(
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods([Method::GET])
+                .max_age(Duration::from_secs(86400))
+        )
+}
+
+async fn get_widget(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(invariant_set_id): Path<String>,
+) -> Response {
+    let client = client_ip(&state.config, &headers, peer);
+
+    if let Err(retry_after) = state.widget_rate_limiter.check(client).await {
+        warn!("Widget rate limit exceeded for {}", client);
+        {
+            let mut metrics = state.metrics.write().await;
+            *metrics.entry("widget_rate_limited_total".to_string()).or_insert(0) += 1;
+        }
+        let retry_after = retry_after.as_secs().max(1).to_string();
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after)],
+            "Rate limit exceeded".to_string(),
+        )
+            .into_response();
+    }
+
+    {
+        let mut metrics = state.metrics.write().await;
+        *metrics.entry("widget_requests_total".to_string()).or_insert(0) += 1;
+    }
+
+    // Sets that are not allowlisted look exactly like unknown ones
+    let widget = if state.config.widget_invariant_sets.contains(&invariant_set_id) {
+        state.widget_store.get(&invariant_set_id).await
+    } else {
+        None
+    };
+
+    let Some(widget) = widget else {
+        return (StatusCode::NOT_FOUND, "Unknown invariant set".to_string()).into_response();
+    };
+
+    info!("Serving verification widget for {}", invariant_set_id);
+
+    let mut response = Json(widget).into_response();
+    let response_headers = response.headers_mut();
+    if let Ok(cache_control) = HeaderValue::from_str(&format!("public, max-age={}", state.runtime.current().widget_cache_max_age)) {
+        response_headers.insert(header::CACHE_CONTROL, cache_control);
+    }
+    response_headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use crate::scm::ScmKind;
+
+    fn report(invariant_set_id: Option<&str>, completed: u32) -> CoverageReportRequest {
+        CoverageReportRequest {
+            repository_id: "org/repo".to_string(),
+            commit_sha: "abc123".to_string(),
+            batch_id: "batch-1".to_string(),
+            total: 4,
+            completed,
+            proven: completed.min(3),
+            failed: completed.saturating_sub(3),
+            invariant_set_id: invariant_set_id.map(str::to_string),
+            rekor_entry_uuid: Some("24296fb24b8ad77a".to_string()),
+            installation_id: String::new(),
+            provider: ScmKind::GitHub,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_widget_store_records_completed_batches() {
+        let store = WidgetStore::new();
+        let config = GitHubAppConfig::default();
+
+        assert!(store.record(&report(Some("set-1"), 2), &config).await.is_none());
+        assert!(store.record(&report(None, 4), &config).await.is_none());
+
+        let widget = store.record(&report(Some("set-1"), 4), &config).await.unwrap();
+        assert_eq!(widget.proven, 3);
+        assert_eq!(widget.coverage, 0.75);
+        assert_eq!(
+            widget.sigstore_url.as_deref(),
+            Some("https://rekor.sigstore.dev/api/v1/log/entries/24296fb24b8ad77a")
+        );
+        assert_eq!(store.get("set-1").await, Some(widget));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_groups_ipv6_prefix() {
+        let limiter = WidgetRateLimiter::new(2, Duration::from_secs(60));
+        let a: IpAddr = "2001:db8::1".parse::<Ipv6Addr>().unwrap().into();
+        let b: IpAddr = "2001:db8::ffff:2".parse::<Ipv6Addr>().unwrap().into();
+        let other = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+        assert!(limiter.check(a).await.is_ok());
+        assert!(limiter.check(b).await.is_ok());
+        assert!(limiter.check(a).await.is_err());
+        assert!(limiter.check(other).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_follows_runtime_settings() {
+        let config = GitHubAppConfig::default();
+        let runtime = ConfigHandle::new(RuntimeSettings { widget_rate_limit_requests: 1, ..RuntimeSettings::from_config(&config) });
+        let limiter = WidgetRateLimiter::new(2, Duration::from_secs(60)).with_runtime_settings(runtime.clone());
+        let client = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+        assert!(limiter.check(client).await.is_ok());
+        assert!(limiter.check(client).await.is_err());
+
+        runtime.replace(RuntimeSettings { widget_rate_limit_requests: 3, ..RuntimeSettings::from_config(&config) });
+        assert!(limiter.check(client).await.is_ok());
+    }
+
+    #[test]
+    fn test_client_ip_ignores_spoofed_forwarded_entries() {
+        let mut config = GitHubAppConfig::default();
+        let peer: SocketAddr = "10.0.0.5:443".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", HeaderValue::from_static("1.1.1.1, 203.0.113.9"));
+
+        assert_eq!(client_ip(&config, &headers, peer), peer.ip());
+
+        config.widget_trust_forwarded_for = true;
+        assert_eq!(client_ip(&config, &headers, peer), "203.0.113.9".parse::<IpAddr>().unwrap());
+    }
+}
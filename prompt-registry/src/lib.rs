@@ -0,0 +1,299 @@
+pub mod source;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub use source::{load_manifest, PromptManifest, PromptManifestEntry, PromptVersionSource};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PromptError {
+    #[error("Unknown prompt: {0}")]
+    UnknownPrompt(String),
+
+    #[error("Prompt {prompt} has no version {version}")]
+    UnknownVersion { prompt: String, version: String },
+
+    #[error("Prompt {prompt}@{version} is missing variables: {}", missing.join(", "))]
+    MissingVariables {
+        prompt: String,
+        version: String,
+        missing: Vec<String>,
+    },
+
+    #[error("Invalid prompt manifest: {0}")]
+    InvalidManifest(String),
+
+    #[error("Failed to load prompts from {location}: {message}")]
+    Load { location: String, message: String },
+}
+
+impl From<PromptError> for spec_to_proof_error::Error {
+    fn from(error: PromptError) -> Self {
+        use spec_to_proof_error::Error;
+        match error {
+            PromptError::Load { .. } => Error::transient(error),
+            PromptError::InvalidManifest(_) => Error::internal(error),
+            _ => Error::invalid_input(error),
+        }
+    }
+}
+
+/// One immutable version of a prompt. Placeholders are written `{{name}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub version: String,
+    pub body: String,
+    /// Variables that must be supplied when rendering
+    pub variables: Vec<String>,
+    /// Hex SHA-256 of the body, recorded in extraction/compilation metadata
+    /// so results can be traced to the exact text sent to the model
+    pub sha256: String,
+}
+
+impl PromptTemplate {
+    pub fn new(name: &str, version: &str, body: &str, variables: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            body: body.to_string(),
+            variables: variables.iter().map(|v| v.to_string()).collect(),
+            sha256: hex::encode(Sha256::digest(body.as_bytes())),
+        }
+    }
+
+    /// Substitutes the given variables. Placeholders without a value are
+    /// left in place, but every declared variable must be supplied.
+    pub fn render(&self, variables: &HashMap<String, &str>) -> Result<String, PromptError> {
+        let missing: Vec<String> = self.variables
+            .iter()
+            .filter(|name| !variables.contains_key(name.as_str()))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(PromptError::MissingVariables {
+                prompt: self.name.clone(),
+                version: self.version.clone(),
+                missing,
+            });
+        }
+
+        let mut result = self.body.clone();
+        for (key, value) in variables {
+            result = result.replace(&format!("{{{{{}}}}}", key), value);
+        }
+        Ok(result)
+    }
+}
+
+/// Which side of an A/B split served a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptArm {
+    Active,
+    Candidate,
+}
+
+impl PromptArm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromptArm::Active => "active",
+            PromptArm::Candidate => "candidate",
+        }
+    }
+}
+
+/// Routes a share of traffic to a candidate version for evaluation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateRouting {
+    pub version: String,
+    /// 0-100
+    pub traffic_percent: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectedPrompt {
+    pub template: Arc<PromptTemplate>,
+    pub arm: PromptArm,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PromptVersions {
+    versions: BTreeMap<String, Arc<PromptTemplate>>,
+    active: String,
+    candidate: Option<CandidateRouting>,
+}
+
+/// Named, versioned prompts. Services register their built-in templates and
+/// may overlay a manifest loaded from a file or S3, which can add versions,
+/// change the active one or split traffic to a candidate.
+#[derive(Debug, Clone, Default)]
+pub struct PromptRegistry {
+    prompts: BTreeMap<String, PromptVersions>,
+}
+
+impl PromptRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a version; the first version registered for a name is active
+    pub fn register(&mut self, template: PromptTemplate) {
+        let entry = self.prompts.entry(template.name.clone()).or_default();
+        if entry.active.is_empty() {
+            entry.active = template.version.clone();
+        }
+        entry.versions.insert(template.version.clone(), Arc::new(template));
+    }
+
+    pub fn with_builtin(mut self, name: &str, version: &str, body: &str, variables: &[&str]) -> Self {
+        self.register(PromptTemplate::new(name, version, body, variables));
+        self
+    }
+
+    pub fn apply_manifest(&mut self, manifest: PromptManifest) -> Result<(), PromptError> {
+        for (name, entry) in manifest.prompts {
+            for (version, source) in entry.versions {
+                let body = source.template.ok_or_else(|| {
+                    PromptError::InvalidManifest(format!("{}@{} has no template body", name, version))
+                })?;
+                let variables: Vec<&str> = source.variables.iter().map(String::as_str).collect();
+                self.register(PromptTemplate::new(&name, &version, &body, &variables));
+            }
+
+            let prompt = self.prompts.get_mut(&name).ok_or_else(|| PromptError::UnknownPrompt(name.clone()))?;
+            if let Some(active) = entry.active {
+                prompt.active = active;
+            }
+            prompt.candidate = entry.candidate;
+            self.validate(&name)?;
+        }
+        Ok(())
+    }
+
+    pub fn with_manifest(mut self, manifest: PromptManifest) -> Result<Self, PromptError> {
+        self.apply_manifest(manifest)?;
+        Ok(self)
+    }
+
+    fn validate(&self, name: &str) -> Result<(), PromptError> {
+        let prompt = &self.prompts[name];
+        let unknown = |version: &str| PromptError::UnknownVersion {
+            prompt: name.to_string(),
+            version: version.to_string(),
+        };
+        if !prompt.versions.contains_key(&prompt.active) {
+            return Err(unknown(&prompt.active));
+        }
+        if let Some(candidate) = &prompt.candidate {
+            if !prompt.versions.contains_key(&candidate.version) {
+                return Err(unknown(&candidate.version));
+            }
+            if candidate.traffic_percent > 100 {
+                return Err(PromptError::InvalidManifest(format!(
+                    "{} candidate traffic_percent {} exceeds 100",
+                    name, candidate.traffic_percent
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str, version: &str) -> Result<Arc<PromptTemplate>, PromptError> {
+        let prompt = self.prompts.get(name).ok_or_else(|| PromptError::UnknownPrompt(name.to_string()))?;
+        prompt.versions.get(version).cloned().ok_or_else(|| PromptError::UnknownVersion {
+            prompt: name.to_string(),
+            version: version.to_string(),
+        })
+    }
+
+    pub fn active(&self, name: &str) -> Result<Arc<PromptTemplate>, PromptError> {
+        let prompt = self.prompts.get(name).ok_or_else(|| PromptError::UnknownPrompt(name.to_string()))?;
+        self.get(name, &prompt.active)
+    }
+
+    /// Picks the version to use for a request. The split is deterministic in
+    /// `routing_key` (e.g. a document or invariant id), so retries and cache
+    /// lookups for the same item stay on the same arm.
+    pub fn select(&self, name: &str, routing_key: &str) -> Result<SelectedPrompt, PromptError> {
+        let prompt = self.prompts.get(name).ok_or_else(|| PromptError::UnknownPrompt(name.to_string()))?;
+
+        if let Some(candidate) = &prompt.candidate {
+            if traffic_bucket(name, routing_key) < candidate.traffic_percent as u64 {
+                return Ok(SelectedPrompt {
+                    template: self.get(name, &candidate.version)?,
+                    arm: PromptArm::Candidate,
+                });
+            }
+        }
+
+        Ok(SelectedPrompt {
+            template: self.get(name, &prompt.active)?,
+            arm: PromptArm::Active,
+        })
+    }
+}
+
+// Stable bucket in 0..100 for a routing key
+fn traffic_bucket(name: &str, routing_key: &str) -> u64 {
+    let digest = Sha256::digest(format!("{}:{}", name, routing_key).as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes) % 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> PromptRegistry {
+        PromptRegistry::new()
+            .with_builtin("greeting", "1.0.0", "Hello {{name}}", &["name"])
+            .with_builtin("greeting", "1.1.0", "Hi {{name}}!", &["name"])
+    }
+
+    #[test]
+    fn test_render_requires_declared_variables() {
+        let template = registry().active("greeting").unwrap();
+        assert_eq!(template.version, "1.0.0");
+
+        let mut variables = HashMap::new();
+        assert!(matches!(template.render(&variables), Err(PromptError::MissingVariables { .. })));
+
+        variables.insert("name".to_string(), "Ada");
+        assert_eq!(template.render(&variables).unwrap(), "Hello Ada");
+        assert_eq!(template.sha256, hex::encode(Sha256::digest(b"Hello {{name}}")));
+    }
+
+    #[test]
+    fn test_candidate_routing_splits_traffic() {
+        let manifest: PromptManifest = serde_json::from_str(
+            r#"{"prompts": {"greeting": {"candidate": {"version": "1.1.0", "traffic_percent": 25}}}}"#,
+        )
+        .unwrap();
+        let registry = registry().with_manifest(manifest).unwrap();
+
+        let candidates = (0..1000)
+            .map(|i| registry.select("greeting", &format!("doc-{}", i)).unwrap())
+            .filter(|selected| selected.arm == PromptArm::Candidate)
+            .inspect(|selected| assert_eq!(selected.template.version, "1.1.0"))
+            .count();
+        assert!((150..350).contains(&candidates), "{} candidates", candidates);
+
+        // The same key always lands on the same arm
+        let first = registry.select("greeting", "doc-7").unwrap();
+        assert_eq!(registry.select("greeting", "doc-7").unwrap().arm, first.arm);
+    }
+
+    #[test]
+    fn test_manifest_rejects_unknown_versions() {
+        let manifest: PromptManifest =
+            serde_json::from_str(r#"{"prompts": {"greeting": {"active": "2.0.0"}}}"#).unwrap();
+        assert!(matches!(
+            registry().with_manifest(manifest),
+            Err(PromptError::UnknownVersion { .. })
+        ));
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::{CandidateRouting, PromptError};
+
+/// Prompt configuration overlaid on a service's built-in templates, e.g.
+///
+/// ```json
+/// {"prompts": {"invariant_extraction": {
+///     "active": "1.0.0",
+///     "versions": {"1.1.0": {"file": "invariant_extraction_v1_1.md", "variables": ["content"]}},
+///     "candidate": {"version": "1.1.0", "traffic_percent": 10}}}}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromptManifest {
+    #[serde(default)]
+    pub prompts: BTreeMap<String, PromptManifestEntry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromptManifestEntry {
+    /// Keeps the current active version when unset
+    #[serde(default)]
+    pub active: Option<String>,
+    #[serde(default)]
+    pub versions: BTreeMap<String, PromptVersionSource>,
+    #[serde(default)]
+    pub candidate: Option<CandidateRouting>,
+}
+
+/// A template body given inline or as a file next to the manifest
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromptVersionSource {
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub variables: Vec<String>,
+}
+
+/// Loads a manifest from a local path or an `s3://bucket/key` URI and reads
+/// the template files it references, which are resolved relative to the
+/// manifest's directory or key prefix
+pub async fn load_manifest(location: &str) -> Result<PromptManifest, PromptError> {
+    let location_error = |message: String| PromptError::Load {
+        location: location.to_string(),
+        message,
+    };
+
+    let (raw, files) = match location.strip_prefix("s3://") {
+        Some(path) => {
+            let (bucket, key) = path
+                .split_once('/')
+                .ok_or_else(|| location_error("expected s3://bucket/key".to_string()))?;
+            let aws_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+            let client = aws_sdk_s3::Client::new(&aws_config);
+            let prefix = key.rsplit_once('/').map(|(prefix, _)| format!("{}/", prefix)).unwrap_or_default();
+            let raw = read_s3_object(&client, bucket, key).await.map_err(location_error)?;
+            (raw, TemplateFiles::S3 { client, bucket: bucket.to_string(), prefix })
+        }
+        None => {
+            let raw = std::fs::read_to_string(location).map_err(|e| location_error(e.to_string()))?;
+            let directory = Path::new(location).parent().unwrap_or(Path::new(".")).to_path_buf();
+            (raw, TemplateFiles::Local(directory))
+        }
+    };
+
+    let mut manifest: PromptManifest = serde_json::from_str(&raw)
+        .map_err(|e| PromptError::InvalidManifest(format!("{}: {}", location, e)))?;
+
+    for (name, entry) in manifest.prompts.iter_mut() {
+        for (version, source) in entry.versions.iter_mut() {
+            if source.template.is_some() {
+                continue;
+            }
+            let file = source.file.as_deref().ok_or_else(|| {
+                PromptError::InvalidManifest(format!("{}@{} needs a template or file", name, version))
+            })?;
+            source.template = Some(files.read(file).await.map_err(|message| PromptError::Load {
+                location: file.to_string(),
+                message,
+            })?);
+        }
+    }
+
+    tracing::info!("Loaded {} prompt definitions from {}", manifest.prompts.len(), location);
+    Ok(manifest)
+}
+
+enum TemplateFiles {
+    Local(std::path::PathBuf),
+    S3 {
+        client: aws_sdk_s3::Client,
+        bucket: String,
+        prefix: String,
+    },
+}
+
+impl TemplateFiles {
+    async fn read(&self, file: &str) -> Result<String, String> {
+        match self {
+            TemplateFiles::Local(directory) => {
+                std::fs::read_to_string(directory.join(file)).map_err(|e| e.to_string())
+            }
+            TemplateFiles::S3 { client, bucket, prefix } => {
+                read_s3_object(client, bucket, &format!("{}{}", prefix, file)).await
+            }
+        }
+    }
+}
+
+async fn read_s3_object(client: &aws_sdk_s3::Client, bucket: &str, key: &str) -> Result<String, String> {
+    let object = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let bytes = object.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+    String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_manifest_reads_template_files() {
+        let directory = tempfile::tempdir().unwrap();
+        std::fs::write(directory.path().join("greeting_v2.md"), "Hey {{name}}").unwrap();
+        let manifest_path = directory.path().join("prompts.json");
+        std::fs::write(
+            &manifest_path,
+            r#"{"prompts": {"greeting": {"versions": {"2.0.0": {"file": "greeting_v2.md", "variables": ["name"]}}}}}"#,
+        )
+        .unwrap();
+
+        let manifest = load_manifest(manifest_path.to_str().unwrap()).await.unwrap();
+        let source = &manifest.prompts["greeting"].versions["2.0.0"];
+        assert_eq!(source.template.as_deref(), Some("Hey {{name}}"));
+        assert_eq!(source.variables, vec!["name".to_string()]);
+    }
+}
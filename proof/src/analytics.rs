@@ -0,0 +1,424 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+use prometheus::{GaugeVec, Opts, Registry};
+use spec_to_proof_error::Error;
+use storage::{Entity, EntityQuery, Repository};
+
+use crate::proto::proof::v1::*;
+use crate::proto::spec_to_proof::v1::{Priority, ProofArtifact, ProofStatus};
+
+// Success rates, durations, token cost and retries of the persisted proof
+// artifacts, grouped by proof strategy, taxonomy category, priority and
+// model. Each theorem keeps its latest successful and latest failed
+// artifact, so the figures cover every theorem proven or attempted rather
+// than every call. Category and priority come from the invariants the nlp
+// service stored.
+
+const QUERY_PAGE_SIZE: u32 = 100;
+
+// Statuses of finished proofs; pending and running artifacts are skipped
+const FINISHED_STATUSES: [ProofStatus; 4] =
+    [ProofStatus::Success, ProofStatus::Failed, ProofStatus::Timeout, ProofStatus::Error];
+
+/// The fields of the nlp service's `StoredInvariant` analytics groups by.
+/// Tags match nlp.proto so persisted entities decode directly; other fields
+/// are skipped.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InvariantRecord {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub document_id: String,
+    #[prost(message, optional, tag = "3")]
+    pub invariant: Option<InvariantFields>,
+    #[prost(int32, tag = "4")]
+    pub status: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InvariantFields {
+    #[prost(int32, tag = "8")]
+    pub priority: i32,
+    #[prost(message, optional, tag = "12")]
+    pub classification: Option<ClassificationFields>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ClassificationFields {
+    #[prost(string, tag = "1")]
+    pub category: String,
+}
+
+impl Entity for InvariantRecord {
+    const KIND: &'static str = "INVARIANT";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        (!self.document_id.is_empty()).then(|| self.document_id.clone())
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+impl InvariantRecord {
+    fn category(&self) -> Option<&str> {
+        self.invariant
+            .as_ref()
+            .and_then(|invariant| invariant.classification.as_ref())
+            .map(|classification| classification.category.as_str())
+            .filter(|category| !category.is_empty())
+    }
+
+    fn priority(&self) -> Priority {
+        self.invariant
+            .as_ref()
+            .and_then(|invariant| Priority::try_from(invariant.priority).ok())
+            .unwrap_or(Priority::Unspecified)
+    }
+}
+
+pub fn dimension_name(dimension: AnalyticsDimension) -> &'static str {
+    match dimension {
+        AnalyticsDimension::Unspecified => "all",
+        AnalyticsDimension::Strategy => "strategy",
+        AnalyticsDimension::Category => "category",
+        AnalyticsDimension::Priority => "priority",
+        AnalyticsDimension::Model => "model",
+    }
+}
+
+const DIMENSIONS: [AnalyticsDimension; 4] = [
+    AnalyticsDimension::Strategy,
+    AnalyticsDimension::Category,
+    AnalyticsDimension::Priority,
+    AnalyticsDimension::Model,
+];
+
+// What one artifact contributes
+#[derive(Debug, Clone, Copy, Default)]
+struct Sample {
+    succeeded: bool,
+    duration_ms: u64,
+    tokens: u64,
+    cost_usd: f64,
+    retries: u32,
+}
+
+fn metadata_number<T: std::str::FromStr + Default>(artifact: &ProofArtifact, key: &str) -> T {
+    artifact.metadata.get(key).and_then(|value| value.parse().ok()).unwrap_or_default()
+}
+
+fn sample(artifact: &ProofArtifact) -> Sample {
+    // Successful Lean proofs record input and output tokens; failures only
+    // the total over their attempts
+    let tokens = match metadata_number::<u64>(artifact, "proof_tokens") {
+        0 => metadata_number::<u64>(artifact, "proof_input_tokens") + metadata_number::<u64>(artifact, "proof_output_tokens"),
+        tokens => tokens,
+    };
+    let attempts: u32 = metadata_number(artifact, "attempts");
+    Sample {
+        succeeded: artifact.status == ProofStatus::Success as i32,
+        duration_ms: artifact.duration_ms,
+        tokens,
+        cost_usd: metadata_number(artifact, "proof_estimated_cost_usd"),
+        retries: attempts.saturating_sub(1) + metadata_number::<u32>(artifact, "repair_attempts"),
+    }
+}
+
+fn group_value(dimension: AnalyticsDimension, artifact: &ProofArtifact, invariant: Option<&InvariantRecord>) -> String {
+    let value = match dimension {
+        AnalyticsDimension::Unspecified => Some("all".to_string()),
+        AnalyticsDimension::Strategy => Some(artifact.proof_strategy.clone()),
+        AnalyticsDimension::Category => invariant.and_then(|invariant| invariant.category()).map(str::to_string),
+        AnalyticsDimension::Priority => invariant.map(|invariant| {
+            invariant.priority().as_str_name().trim_start_matches("PRIORITY_").to_lowercase()
+        }),
+        // SMT artifacts name their solver; evaluation uses neither
+        AnalyticsDimension::Model => artifact.metadata.get("model").or_else(|| artifact.metadata.get("solver")).cloned(),
+    };
+    value.filter(|value| !value.is_empty()).unwrap_or_else(|| "unknown".to_string())
+}
+
+// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], percentile: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (percentile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn stats(dimension: AnalyticsDimension, value: String, samples: &[Sample]) -> ProofStats {
+    let mut durations: Vec<u64> = samples.iter().map(|sample| sample.duration_ms).collect();
+    durations.sort_unstable();
+    let succeeded = samples.iter().filter(|sample| sample.succeeded).count() as u32;
+    ProofStats {
+        dimension: dimension as i32,
+        value,
+        proofs: samples.len() as u32,
+        succeeded,
+        success_rate: if samples.is_empty() { 0.0 } else { succeeded as f64 / samples.len() as f64 },
+        median_duration_ms: percentile(&durations, 0.5),
+        p95_duration_ms: percentile(&durations, 0.95),
+        tokens: samples.iter().map(|sample| sample.tokens).sum(),
+        estimated_cost_usd: samples.iter().map(|sample| sample.cost_usd).sum(),
+        retries: samples.iter().map(|sample| sample.retries).sum(),
+    }
+}
+
+/// Aggregates `artifacts` overall and per value of each of `dimensions`,
+/// all of them when empty. Groups are ordered by dimension, then value.
+pub fn summarize(
+    artifacts: &[ProofArtifact],
+    invariants: &HashMap<String, InvariantRecord>,
+    dimensions: &[AnalyticsDimension],
+) -> ProofAnalytics {
+    let dimensions = match dimensions {
+        [] => &DIMENSIONS[..],
+        dimensions => dimensions,
+    };
+
+    let samples: Vec<Sample> = artifacts.iter().map(sample).collect();
+    let mut groups = Vec::new();
+    for dimension in dimensions.iter().filter(|dimension| **dimension != AnalyticsDimension::Unspecified) {
+        let mut by_value: BTreeMap<String, Vec<Sample>> = BTreeMap::new();
+        for (artifact, sample) in artifacts.iter().zip(&samples) {
+            let value = group_value(*dimension, artifact, invariants.get(&artifact.invariant_id));
+            by_value.entry(value).or_default().push(*sample);
+        }
+        groups.extend(by_value.into_iter().map(|(value, samples)| stats(*dimension, value, &samples)));
+    }
+
+    ProofAnalytics {
+        overall: Some(stats(AnalyticsDimension::Unspecified, "all".to_string(), &samples)),
+        groups,
+        computed_at: Some(prost_types::Timestamp::from(SystemTime::now())),
+    }
+}
+
+/// Reads finished artifacts and the invariants they prove from the entity
+/// store
+pub struct AnalyticsReader<'a> {
+    artifact_repository: &'a dyn Repository<ProofArtifact>,
+    invariant_repository: &'a dyn Repository<InvariantRecord>,
+}
+
+impl<'a> AnalyticsReader<'a> {
+    pub fn new(
+        artifact_repository: &'a dyn Repository<ProofArtifact>,
+        invariant_repository: &'a dyn Repository<InvariantRecord>,
+    ) -> Self {
+        Self {
+            artifact_repository,
+            invariant_repository,
+        }
+    }
+
+    /// Analytics over artifacts attempted at or after `since`, or all of
+    /// them
+    pub async fn analytics(
+        &self,
+        since: Option<SystemTime>,
+        dimensions: &[AnalyticsDimension],
+    ) -> Result<ProofAnalytics, Error> {
+        let since = since.map(prost_types::Timestamp::from);
+        let mut artifacts = Vec::new();
+        for status in FINISHED_STATUSES {
+            let query = EntityQuery::ByStatus(status as i32);
+            let mut page_token: Option<String> = None;
+            loop {
+                let page = self.artifact_repository.query(&query, page_token.as_deref(), QUERY_PAGE_SIZE).await?;
+                artifacts.extend(page.items.into_iter().map(|stored| stored.entity).filter(|artifact| {
+                    match (&since, &artifact.attempted_at) {
+                        (Some(since), Some(attempted_at)) => {
+                            (attempted_at.seconds, attempted_at.nanos) >= (since.seconds, since.nanos)
+                        }
+                        (Some(_), None) => false,
+                        (None, _) => true,
+                    }
+                }));
+                match page.next_page_token {
+                    Some(token) => page_token = Some(token),
+                    None => break,
+                }
+            }
+        }
+
+        let mut invariants = HashMap::new();
+        for artifact in &artifacts {
+            if artifact.invariant_id.is_empty() || invariants.contains_key(&artifact.invariant_id) {
+                continue;
+            }
+            if let Some(stored) = self.invariant_repository.get(&artifact.invariant_id).await? {
+                invariants.insert(artifact.invariant_id.clone(), stored.entity);
+            }
+        }
+
+        Ok(summarize(&artifacts, &invariants, dimensions))
+    }
+}
+
+/// Prometheus gauges mirroring the latest analytics, labelled by dimension
+/// and value; the overall figures carry `dimension="all"`
+pub struct AnalyticsGauges {
+    proofs: GaugeVec,
+    success_rate: GaugeVec,
+    duration_ms: GaugeVec,
+    tokens: GaugeVec,
+    cost_usd: GaugeVec,
+    retries: GaugeVec,
+}
+
+impl AnalyticsGauges {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let labels = ["dimension", "value"];
+        let gauge = |name: &str, help: &str, labels: &[&str]| -> Result<GaugeVec, prometheus::Error> {
+            let gauge = GaugeVec::new(Opts::new(name, help), labels)?;
+            registry.register(Box::new(gauge.clone()))?;
+            Ok(gauge)
+        };
+        Ok(Self {
+            proofs: gauge("proof_analytics_proofs", "Finished proofs", &labels)?,
+            success_rate: gauge("proof_analytics_success_rate", "Share of finished proofs that succeeded", &labels)?,
+            duration_ms: gauge(
+                "proof_analytics_duration_ms",
+                "Proof duration percentiles in milliseconds",
+                &["dimension", "value", "quantile"],
+            )?,
+            tokens: gauge("proof_analytics_tokens", "Model tokens spent on proofs", &labels)?,
+            cost_usd: gauge("proof_analytics_cost_usd", "Estimated model cost of proofs in USD", &labels)?,
+            retries: gauge("proof_analytics_retries", "Proof attempts beyond the first, counting repairs", &labels)?,
+        })
+    }
+
+    /// Replaces every series with those of `analytics`, dropping groups
+    /// that no longer exist
+    pub fn update(&self, analytics: &ProofAnalytics) {
+        for gauge in [&self.proofs, &self.success_rate, &self.duration_ms, &self.tokens, &self.cost_usd, &self.retries] {
+            gauge.reset();
+        }
+        for stats in analytics.overall.iter().chain(&analytics.groups) {
+            let dimension = dimension_name(AnalyticsDimension::try_from(stats.dimension).unwrap_or(AnalyticsDimension::Unspecified));
+            let labels = [dimension, stats.value.as_str()];
+            self.proofs.with_label_values(&labels).set(stats.proofs as f64);
+            self.success_rate.with_label_values(&labels).set(stats.success_rate);
+            self.duration_ms.with_label_values(&[dimension, &stats.value, "0.5"]).set(stats.median_duration_ms as f64);
+            self.duration_ms.with_label_values(&[dimension, &stats.value, "0.95"]).set(stats.p95_duration_ms as f64);
+            self.tokens.with_label_values(&labels).set(stats.tokens as f64);
+            self.cost_usd.with_label_values(&labels).set(stats.estimated_cost_usd);
+            self.retries.with_label_values(&labels).set(stats.retries as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact(invariant_id: &str, strategy: &str, status: ProofStatus, duration_ms: u64, metadata: &[(&str, &str)]) -> ProofArtifact {
+        ProofArtifact {
+            id: format!("proof_theorem_{}", invariant_id),
+            invariant_id: invariant_id.to_string(),
+            proof_strategy: strategy.to_string(),
+            status: status as i32,
+            duration_ms,
+            metadata: metadata.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn invariant(id: &str, category: &str, priority: Priority) -> (String, InvariantRecord) {
+        let record = InvariantRecord {
+            id: id.to_string(),
+            invariant: Some(InvariantFields {
+                priority: priority as i32,
+                classification: Some(ClassificationFields { category: category.to_string() }),
+            }),
+            ..Default::default()
+        };
+        (id.to_string(), record)
+    }
+
+    #[test]
+    fn test_summarize() {
+        let opus = "claude-3-opus-20240229";
+        let artifacts = vec![
+            artifact("inv1", "auto", ProofStatus::Success, 1000, &[
+                ("model", opus),
+                ("attempts", "2"),
+                ("proof_input_tokens", "900"),
+                ("proof_output_tokens", "100"),
+                ("proof_estimated_cost_usd", "0.03"),
+            ]),
+            artifact("inv2", "auto", ProofStatus::Failed, 3000, &[
+                ("model", opus),
+                ("attempts", "3"),
+                ("repair_attempts", "1"),
+                ("proof_tokens", "5000"),
+                ("proof_estimated_cost_usd", "0.12"),
+            ]),
+            artifact("inv3", "smt", ProofStatus::Success, 20, &[("solver", "z3")]),
+            artifact("inv4", "evaluation", ProofStatus::Success, 5, &[]),
+        ];
+        let invariants: HashMap<_, _> = [
+            invariant("inv1", "performance", Priority::High),
+            invariant("inv2", "performance", Priority::Critical),
+            invariant("inv3", "security", Priority::High),
+        ]
+        .into_iter()
+        .collect();
+
+        let analytics = summarize(&artifacts, &invariants, &[]);
+        let overall = analytics.overall.unwrap();
+        assert_eq!((overall.proofs, overall.succeeded), (4, 3));
+        assert_eq!(overall.success_rate, 0.75);
+        assert_eq!((overall.median_duration_ms, overall.p95_duration_ms), (20, 3000));
+        assert_eq!(overall.tokens, 6000);
+        assert!((overall.estimated_cost_usd - 0.15).abs() < 1e-9);
+        assert_eq!(overall.retries, 4);
+
+        let group = |dimension: AnalyticsDimension, value: &str| {
+            analytics
+                .groups
+                .iter()
+                .find(|stats| stats.dimension == dimension as i32 && stats.value == value)
+                .unwrap_or_else(|| panic!("no {:?} group {}", dimension, value))
+        };
+        assert_eq!(group(AnalyticsDimension::Strategy, "auto").success_rate, 0.5);
+        assert_eq!(group(AnalyticsDimension::Category, "performance").proofs, 2);
+        assert_eq!(group(AnalyticsDimension::Category, "unknown").proofs, 1);
+        assert_eq!(group(AnalyticsDimension::Priority, "high").succeeded, 2);
+        assert_eq!(group(AnalyticsDimension::Priority, "critical").retries, 3);
+        assert_eq!(group(AnalyticsDimension::Model, "z3").proofs, 1);
+        assert_eq!(group(AnalyticsDimension::Model, opus).median_duration_ms, 1000);
+        assert_eq!(analytics.groups.len(), 3 + 3 + 3 + 3);
+
+        let by_model = summarize(&artifacts, &invariants, &[AnalyticsDimension::Model]);
+        assert!(by_model.groups.iter().all(|stats| stats.dimension == AnalyticsDimension::Model as i32));
+    }
+
+    #[test]
+    fn test_gauges_follow_the_latest_analytics() {
+        let registry = Registry::new();
+        let gauges = AnalyticsGauges::new(&registry).unwrap();
+        let artifacts = vec![artifact("inv1", "auto", ProofStatus::Success, 10, &[])];
+        gauges.update(&summarize(&artifacts, &HashMap::new(), &[AnalyticsDimension::Strategy]));
+        assert_eq!(gauges.success_rate.with_label_values(&["strategy", "auto"]).get(), 1.0);
+
+        let artifacts = vec![artifact("inv1", "smt", ProofStatus::Failed, 10, &[])];
+        gauges.update(&summarize(&artifacts, &HashMap::new(), &[AnalyticsDimension::Strategy]));
+        let families = registry.gather();
+        let success_rate = families.iter().find(|family| family.get_name() == "proof_analytics_success_rate").unwrap();
+        // The all group and the smt group; auto is gone
+        assert_eq!(success_rate.get_metric().len(), 2);
+    }
+}
@@ -0,0 +1,108 @@
+use spec_to_proof_error::Error;
+use export::{BundleBuilder, EntryKind, PullRequestRef};
+use serde_json::{json, Value};
+
+use crate::proto::spec_to_proof::v1::*;
+
+/// Key prefix, within the service bucket, audit bundles are stored under
+pub const AUDIT_BUNDLE_KEY_PREFIX: &str = "audit-exports/";
+
+/// Collects what the proof service holds for an invariant set: the
+/// invariants, each theorem's Lean source and every proof attempt
+pub fn build_bundle(
+    pull_request: PullRequestRef,
+    invariant_set: &InvariantSet,
+    theorems: &[LeanTheorem],
+    artifacts: &[ProofArtifact],
+) -> Result<BundleBuilder, Error> {
+    let mut builder = BundleBuilder::new(pull_request);
+    builder.add_json(EntryKind::Invariants, &format!("{}.json", invariant_set.id), &json!({
+        "id": invariant_set.id,
+        "content_sha256": invariant_set.content_sha256,
+        "name": invariant_set.name,
+        "description": invariant_set.description,
+        "invariants": invariant_set.invariants.iter().map(invariant_json).collect::<Vec<_>>(),
+    }))?;
+    for theorem in theorems {
+        builder.add_bytes(EntryKind::LeanSource, &format!("{}.lean", theorem.id), theorem.lean_code.as_str())?;
+    }
+    for artifact in artifacts {
+        builder.add_json(EntryKind::ProofOutput, &format!("{}.json", artifact.id), &artifact_json(artifact))?;
+    }
+    Ok(builder)
+}
+
+fn invariant_json(invariant: &Invariant) -> Value {
+    json!({
+        "id": invariant.id,
+        "content_sha256": invariant.content_sha256,
+        "description": invariant.description,
+        "formal_expression": invariant.formal_expression,
+        "natural_language": invariant.natural_language,
+        "variables": invariant.variables.iter().map(|variable| json!({
+            "name": variable.name,
+            "type": variable.var_type,
+            "description": variable.description,
+            "unit": variable.unit,
+            "constraints": variable.constraints,
+        })).collect::<Vec<_>>(),
+        "units": invariant.units,
+        "source_document_id": invariant.source_document_id,
+        "tags": invariant.tags,
+    })
+}
+
+/// The JSON form of a proof artifact in bundles and CLI output
+pub fn artifact_json(artifact: &ProofArtifact) -> Value {
+    json!({
+        "id": artifact.id,
+        "content_sha256": artifact.content_sha256,
+        "theorem_id": artifact.theorem_id,
+        "invariant_id": artifact.invariant_id,
+        "status": ProofStatus::try_from(artifact.status)
+            .map(|status| status.as_str_name())
+            .unwrap_or("PROOF_STATUS_UNSPECIFIED"),
+        "attempted_at": artifact.attempted_at.as_ref().map(|attempted_at| attempted_at.to_string()),
+        "duration_ms": artifact.duration_ms,
+        "output": artifact.output,
+        "logs": artifact.logs,
+        "proof_strategy": artifact.proof_strategy,
+        "confidence_score": artifact.confidence_score,
+        "metadata": artifact.metadata,
+        "lean_toolchain": artifact.lean_toolchain,
+        "mathlib_commit": artifact.mathlib_commit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_bundle_layout() {
+        let invariant_set = InvariantSet {
+            id: "set-1".to_string(),
+            invariants: vec![Invariant { id: "inv-1".to_string(), ..Default::default() }],
+            ..Default::default()
+        };
+        let theorems = vec![LeanTheorem {
+            id: "thm-1".to_string(),
+            lean_code: "theorem refund_bound : True := trivial".to_string(),
+            ..Default::default()
+        }];
+        let artifacts = vec![ProofArtifact {
+            id: "proof-1".to_string(),
+            status: ProofStatus::Success as i32,
+            ..Default::default()
+        }];
+        let pull_request = PullRequestRef {
+            repository: "acme/payments".to_string(),
+            number: 42,
+            head_sha: "0123abcd".to_string(),
+        };
+
+        let builder = build_bundle(pull_request, &invariant_set, &theorems, &artifacts).unwrap();
+        assert_eq!(builder.paths(), vec!["invariants/set-1.json", "lean/thm-1.lean", "proofs/proof-1.json"]);
+        assert_eq!(artifact_json(&artifacts[0])["status"], "PROOF_STATUS_SUCCESS");
+    }
+}
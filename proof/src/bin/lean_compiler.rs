@@ -1,8 +1,18 @@
 use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{Encoder, Registry, TextEncoder};
 use tonic::transport::Server;
-use tracing::{info, error};
+use tracing::{info, warn, error};
 
+use audit::AuditLog;
+use reload::{ConfigHandle, ConfigWatcher};
+use proof::analytics::AnalyticsGauges;
 use proof::lib::{ProofServiceImpl, ProofConfig};
+use proof::runtime::RuntimeSettings;
 use proof::proto::proof::v1::proof_service_server::ProofServiceServer;
 
 #[tokio::main]
@@ -17,17 +27,55 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Load configuration
     let config = load_config()?;
     
+    // Ensure the entity store is ready (DynamoDB table or Postgres schema)
+    storage::EntityStore::connect(&config.storage).await?.initialize().await?;
+
     // Create the proof service
-    let proof_service = ProofServiceImpl::new(config).await?;
+    let mut proof_service = ProofServiceImpl::new(config).await?;
+
+    // Model selection and LLM limits can be changed by editing this file or
+    // sending SIGHUP
+    if let Ok(path) = std::env::var("PROOF_RUNTIME_CONFIG") {
+        let settings: RuntimeSettings = reload::load_file(std::path::Path::new(&path))?;
+        let handle = ConfigHandle::new(settings);
+        proof_service = proof_service.with_runtime_settings(handle.clone());
+        let audit_log = AuditLog::open(std::env::var("AUDIT_LOG_PATH").ok().as_deref().map(std::path::Path::new)).await?;
+        ConfigWatcher::new(handle, &path, "proof/runtime_settings")
+            .with_audit_log(Arc::new(audit_log))
+            .spawn();
+        info!("Watching runtime settings in {}", path);
+    }
+    
+    let proof_service = Arc::new(proof_service);
+
+    // Proof analytics as Prometheus gauges, refreshed from the entity store
+    if let Ok(metrics_addr) = std::env::var("METRICS_ADDR") {
+        let refresh_seconds = std::env::var("ANALYTICS_REFRESH_SECONDS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()
+            .unwrap_or(300);
+        serve_analytics_metrics(proof_service.clone(), &metrics_addr, Duration::from_secs(refresh_seconds)).await?;
+    }
     
     // Create gRPC server
     let addr = "[::1]:50051".parse()?;
-    let svc = ProofServiceServer::new(proof_service);
+    let svc = ProofServiceServer::from_arc(proof_service);
+    
+    // Authenticate callers with mTLS and/or bearer tokens
+    let auth_config = auth::AuthConfig::from_env()?;
+    let mut server = Server::builder();
+    if let Some(tls) = auth_config.server_tls_config()? {
+        server = server.tls_config(tls)?;
+    }
+    if !auth_config.is_enabled() {
+        warn!("gRPC authentication is disabled; set GRPC_TLS_* or GRPC_AUTH_TOKENS");
+    }
     
     info!("Proof service listening on {}", addr);
     
     // Run the server
-    Server::builder()
+    server
+        .layer(auth::AuthLayer::new(auth_config))
         .add_service(svc)
         .serve(addr)
         .await?;
@@ -35,12 +83,52 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+async fn serve_analytics_metrics(
+    proof_service: Arc<ProofServiceImpl>,
+    addr: &str,
+    refresh: Duration,
+) -> Result<(), Box<dyn Error>> {
+    let registry = Registry::new();
+    let gauges = AnalyticsGauges::new(&registry)?;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(refresh);
+        loop {
+            interval.tick().await;
+            match proof_service.proof_analytics(None, &[]).await {
+                Ok(analytics) => gauges.update(&analytics),
+                Err(e) => warn!("Failed to refresh proof analytics: {}", e),
+            }
+        }
+    });
+
+    let app = Router::new().route("/metrics", get(move || async move {
+        let mut buffer = Vec::new();
+        match TextEncoder::new().encode(&registry.gather(), &mut buffer) {
+            Ok(()) => (StatusCode::OK, String::from_utf8(buffer).unwrap_or_default()),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("Proof analytics metrics on {}/metrics, refreshed every {}s", addr, refresh.as_secs());
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Metrics server failed: {}", e);
+        }
+    });
+    Ok(())
+}
+
 fn load_config() -> Result<ProofConfig, Box<dyn Error>> {
     let config = ProofConfig {
         claude_api_key: std::env::var("CLAUDE_API_KEY")
             .unwrap_or_else(|_| "".to_string()),
         claude_model: std::env::var("CLAUDE_MODEL")
             .unwrap_or_else(|_| "claude-3-opus-20240229".to_string()),
+        simple_claude_model: std::env::var("CLAUDE_SIMPLE_MODEL").ok(),
+        routing_max_simple_operators: std::env::var("ROUTING_MAX_SIMPLE_OPERATORS")
+            .unwrap_or_else(|_| "4".to_string())
+            .parse()
+            .unwrap_or(4),
         max_tokens: std::env::var("MAX_TOKENS")
             .unwrap_or_else(|_| "8000".to_string())
             .parse()
@@ -57,17 +145,97 @@ fn load_config() -> Result<ProofConfig, Box<dyn Error>> {
             .unwrap_or_else(|_| "1000".to_string())
             .parse()
             .unwrap_or(1000),
+        repair_lean_project: std::env::var("REPAIR_LEAN_PROJECT").ok().map(Into::into),
+        repair_max_attempts: std::env::var("REPAIR_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse()
+            .unwrap_or(3),
+        repair_token_budget: std::env::var("REPAIR_TOKEN_BUDGET")
+            .unwrap_or_else(|_| "32000".to_string())
+            .parse()
+            .unwrap_or(32_000),
+        repair_check_timeout_ms: std::env::var("REPAIR_CHECK_TIMEOUT_MS")
+            .unwrap_or_else(|_| "120000".to_string())
+            .parse()
+            .unwrap_or(120_000),
+        stream_deadline_ms: std::env::var("STREAM_DEADLINE_MS")
+            .ok()
+            .and_then(|value| value.parse().ok()),
+        cost_governance_redis_url: std::env::var("COST_GOVERNANCE_REDIS_URL").ok(),
+        pipeline_control_redis_url: std::env::var("PIPELINE_CONTROL_REDIS_URL").ok(),
         cost_per_1k_tokens: std::env::var("COST_PER_1K_TOKENS")
             .unwrap_or_else(|_| "0.015".to_string())
             .parse()
             .unwrap_or(0.015),
+        model_pricing: cost_governance::parse_model_pricing(
+            &std::env::var("MODEL_PRICING").unwrap_or_default(),
+        )?,
+        lean_toolchain: proof::toolchain::lean_toolchain_for_version(
+            &std::env::var("LEAN_VERSION").unwrap_or_else(|_| "4.7.0".to_string()),
+        ),
+        mathlib_commit: std::env::var("MATHLIB_COMMIT").unwrap_or_default(),
+        shared_definitions: std::env::var("SHARED_DEFINITIONS")
+            .map(|value| value == "true")
+            .unwrap_or(false),
         s3_bucket: std::env::var("S3_BUCKET")
             .unwrap_or_else(|_| "spec-to-proof-lean".to_string()),
         s3_region: std::env::var("S3_REGION")
             .unwrap_or_else(|_| "us-east-1".to_string()),
+        s3_endpoint: std::env::var("S3_ENDPOINT").ok(),
         s3_key_prefix: std::env::var("S3_KEY_PREFIX")
             .unwrap_or_else(|_| "theorems/".to_string()),
         kms_key_id: std::env::var("KMS_KEY_ID").ok(),
+        client_side_encryption: std::env::var("CLIENT_SIDE_ENCRYPTION")
+            .map(|value| value == "true")
+            .unwrap_or(false),
+        transcript_key_prefix: std::env::var("TRANSCRIPT_KEY_PREFIX")
+            .unwrap_or_else(|_| "transcripts/".to_string()),
+        presigned_url_expiry_seconds: std::env::var("PRESIGNED_URL_EXPIRY_SECONDS")
+            .unwrap_or_else(|_| "900".to_string())
+            .parse()
+            .unwrap_or(900),
+        audit_signing_key_id: std::env::var("AUDIT_SIGNING_KEY_ID").ok(),
+        z3_path: std::env::var("Z3_PATH")
+            .unwrap_or_else(|_| "z3".to_string()),
+        smt_timeout_ms: std::env::var("SMT_TIMEOUT_MS")
+            .unwrap_or_else(|_| "5000".to_string())
+            .parse()
+            .unwrap_or(5000),
+        evaluation_exhaustive_limit: std::env::var("EVALUATION_EXHAUSTIVE_LIMIT")
+            .unwrap_or_else(|_| "100000".to_string())
+            .parse()
+            .unwrap_or(100_000),
+        evaluation_sample_size: std::env::var("EVALUATION_SAMPLE_SIZE")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse()
+            .unwrap_or(10_000),
+        evaluation_timeout_ms: std::env::var("EVALUATION_TIMEOUT_MS")
+            .unwrap_or_else(|_| "250".to_string())
+            .parse()
+            .unwrap_or(250),
+        prompt_manifest: std::env::var("PROMPT_MANIFEST").ok(),
+        notifications: match std::env::var("NOTIFICATIONS_CONFIG") {
+            Ok(path) => Some(serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(|e| format!("Invalid notifications config {}: {}", path, e))?),
+            Err(_) => None,
+        },
+        health_check_timeout_ms: std::env::var("HEALTH_CHECK_TIMEOUT_MS")
+            .unwrap_or_else(|_| "2000".to_string())
+            .parse()
+            .unwrap_or(2000),
+        storage: storage::StorageSettings {
+            backend: std::env::var("STORAGE_BACKEND")
+                .unwrap_or_else(|_| "dynamodb".to_string())
+                .parse()?,
+            table_name: std::env::var("ENTITY_TABLE_NAME")
+                .unwrap_or_else(|_| "spec-to-proof-entities".to_string()),
+            database_url: std::env::var("DATABASE_URL").ok(),
+            max_connections: std::env::var("DATABASE_MAX_CONNECTIONS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            data_dir: std::env::var("STORAGE_DATA_DIR").ok().map(Into::into),
+        },
     };
 
     // Validate required configuration
@@ -77,6 +245,9 @@ fn load_config() -> Result<ProofConfig, Box<dyn Error>> {
 
     info!("Configuration loaded successfully");
     info!("Claude Model: {}", config.claude_model);
+    if let Some(simple_model) = &config.simple_claude_model {
+        info!("Simple invariants routed to: {}", simple_model);
+    }
     info!("S3 Bucket: {}", config.s3_bucket);
     info!("S3 Region: {}", config.s3_region);
     info!("Temperature: {}", config.temperature);
@@ -1,8 +1,18 @@
 use std::collections::HashMap;
-use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use spec_to_proof_error::Error;
+use circuit_breaker::CircuitBreaker;
+use cost_governance::LlmCallGovernor;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::streaming::{LeanCodeScanner, SseDecoder, StreamEvent};
+
+/// Name of the breaker every Claude client in the process shares
+pub const CLAUDE_CIRCUIT: &str = "claude";
 
 #[derive(Debug, Serialize)]
 struct ClaudeRequest {
@@ -13,6 +23,8 @@ struct ClaudeRequest {
     tools: Option<Vec<ClaudeTool>>,
     tool_choice: Option<String>,
     seed: Option<u64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -69,6 +81,7 @@ struct ClaudeUsage {
     output_tokens: u32,
 }
 
+#[derive(Clone)]
 pub struct ClaudeClient {
     api_key: String,
     model: String,
@@ -76,6 +89,11 @@ pub struct ClaudeClient {
     temperature: f32,
     http_client: Client,
     base_url: String,
+    /// Streams responses when set, failing if the code has not arrived
+    /// within this time
+    stream_deadline: Option<Duration>,
+    governor: Option<Arc<LlmCallGovernor>>,
+    breaker: Arc<CircuitBreaker>,
 }
 
 impl ClaudeClient {
@@ -87,17 +105,40 @@ impl ClaudeClient {
             temperature: 0.0,
             http_client: Client::new(),
             base_url: "https://api.anthropic.com/v1/messages".to_string(),
+            stream_deadline: None,
+            governor: None,
+            breaker: CircuitBreaker::shared(CLAUDE_CIRCUIT),
         }
     }
 
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    pub fn with_streaming(mut self, deadline: Duration) -> Self {
+        self.stream_deadline = Some(deadline);
+        self
+    }
+
+    /// Checks each request with cost governance and records its spend
+    /// against the tenant of the current request scope
+    pub fn with_cost_governance(mut self, governor: Arc<LlmCallGovernor>) -> Self {
+        self.governor = Some(governor);
+        self
+    }
+
+    /// Replaces the process-wide Claude breaker
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
     pub async fn generate_lean_theorem(
         &self,
-        invariant: &str,
-        proof_strategy: &str,
+        prompt: String,
         seed: u64,
-    ) -> Result<(String, u32, u32), Box<dyn Error>> {
-        let prompt = self.build_lean_prompt(invariant, proof_strategy);
-        
+    ) -> Result<(String, u32, u32), Error> {
         let tools = vec![
             ClaudeTool {
                 tool_type: "function".to_string(),
@@ -147,20 +188,18 @@ impl ClaudeClient {
             tools: Some(tools),
             tool_choice: Some("auto".to_string()),
             seed: Some(seed),
+            stream: self.stream_deadline.is_some(),
         };
 
-        let response = self.make_request(&request).await?;
+        let response = self.make_request(&request, "generate_lean_theorem", "lean_code").await?;
         Ok((response.0, response.1, response.2))
     }
 
     pub async fn generate_proof(
         &self,
-        theorem_code: &str,
-        proof_strategy: &str,
+        prompt: String,
         seed: u64,
-    ) -> Result<(String, u32, u32), Box<dyn Error>> {
-        let prompt = self.build_proof_prompt(theorem_code, proof_strategy);
-        
+    ) -> Result<(String, u32, u32), Error> {
         let tools = vec![
             ClaudeTool {
                 tool_type: "function".to_string(),
@@ -206,74 +245,48 @@ impl ClaudeClient {
             tools: Some(tools),
             tool_choice: Some("auto".to_string()),
             seed: Some(seed),
+            stream: self.stream_deadline.is_some(),
         };
 
-        let response = self.make_request(&request).await?;
+        let response = self.make_request(&request, "complete_proof", "proof_code").await?;
         Ok((response.0, response.1, response.2))
     }
 
-    fn build_lean_prompt(&self, invariant: &str, proof_strategy: &str) -> String {
-        format!(
-            r#"You are an expert Lean 4 theorem prover. Convert the following invariant specification into a Lean 4 theorem.
-
-Invariant Specification:
-{}
-
-Proof Strategy: {}
-
-Requirements:
-1. Generate a complete, compilable Lean 4 theorem
-2. Include all necessary imports
-3. Use proper Lean 4 syntax and conventions
-4. Make the theorem name descriptive and follow Lean naming conventions
-5. Include type annotations where helpful
-6. Use the specified proof strategy
-
-Generate the theorem using the generate_lean_theorem function."#,
-            invariant, proof_strategy
-        )
-    }
-
-    fn build_proof_prompt(&self, theorem_code: &str, proof_strategy: &str) -> String {
-        format!(
-            r#"You are an expert Lean 4 theorem prover. Complete the proof for the following Lean theorem.
-
-Theorem Code:
-{}
+    async fn make_request(
+        &self,
+        request: &ClaudeRequest,
+        tool_name: &str,
+        code_argument: &str,
+    ) -> Result<(String, u32, u32), Error> {
+        let tenant_id = match &self.governor {
+            Some(governor) => {
+                let prompt_len: usize = request.messages.iter().map(|message| message.content.len()).sum();
+                Some(governor.authorize((prompt_len / 4) as u32).await?)
+            }
+            None => None,
+        };
 
-Proof Strategy: {}
+        let (lean_code, input_tokens, output_tokens) = match self.stream_deadline {
+            Some(deadline) => self.make_streaming_request(request, tool_name, code_argument, deadline).await?,
+            None => self.make_complete_request(request).await?,
+        };
 
-Requirements:
-1. Complete the proof using Lean 4 tactics
-2. Follow the specified proof strategy
-3. Make the proof clear and readable
-4. Use appropriate tactics for the theorem type
-5. Ensure the proof compiles and runs successfully
+        if let (Some(governor), Some(tenant_id)) = (&self.governor, tenant_id) {
+            // The tokens are spent either way, so a failed write must not
+            // fail the call
+            if let Err(e) = governor.record(&tenant_id, &request.model, input_tokens, output_tokens).await {
+                tracing::warn!("Failed to record LLM cost for tenant {}: {}", tenant_id, e);
+            }
+        }
 
-Complete the proof using the complete_proof function."#,
-            theorem_code, proof_strategy
-        )
+        Ok((lean_code, input_tokens, output_tokens))
     }
 
-    async fn make_request(
+    async fn make_complete_request(
         &self,
         request: &ClaudeRequest,
-    ) -> Result<(String, u32, u32), Box<dyn Error>> {
-        let response = self
-            .http_client
-            .post(&self.base_url)
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(request)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(format!("Claude API error: {}", error_text).into());
-        }
-
+    ) -> Result<(String, u32, u32), Error> {
+        let response = self.send(request).await?;
         let claude_response: ClaudeResponse = response.json().await?;
         
         // Extract the tool call response
@@ -283,7 +296,7 @@ Complete the proof using the complete_proof function."#,
                 for tool_call in tool_calls {
                     if tool_call.function.name == "generate_lean_theorem" || 
                        tool_call.function.name == "complete_proof" {
-                        let args: Value = serde_json::from_str(&tool_call.function.arguments)?;
+                        let args: Value = serde_json::from_str(&tool_call.function.arguments).map_err(Error::transient)?;
                         
                         if tool_call.function.name == "generate_lean_theorem" {
                             lean_code = args["lean_code"].as_str().unwrap_or("").to_string();
@@ -297,7 +310,7 @@ Complete the proof using the complete_proof function."#,
         }
 
         if lean_code.is_empty() {
-            return Err("No valid tool call response received".into());
+            return Err(Error::transient("No valid tool call response received"));
         }
 
         Ok((
@@ -307,6 +320,97 @@ Complete the proof using the complete_proof function."#,
         ))
     }
 
+    /// Lists the available models, which needs a valid key but spends no
+    /// tokens
+    pub async fn ping(&self) -> Result<(), Error> {
+        let response = self.http_client
+            .get(self.base_url.replace("/messages", "/models"))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(Error::from_status(response.status().as_u16(), format!("Claude API returned {}", response.status())));
+        }
+        Ok(())
+    }
+
+    async fn send(&self, request: &ClaudeRequest) -> Result<reqwest::Response, Error> {
+        let post = self
+            .http_client
+            .post(&self.base_url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(request);
+        let response = self
+            .breaker
+            .call_http(async { post.send().await.map_err(Error::from) }, |response| response.status().as_u16())
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs);
+            let error_text = response.text().await?;
+            let error = Error::from_status(status, format!("Claude API error: {}", error_text));
+            return Err(match retry_after {
+                Some(delay) => error.with_retry_after(delay),
+                None => error,
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// Streams the completion and stops reading as soon as the requested
+    /// code is complete, rather than waiting for the model to finish
+    async fn make_streaming_request(
+        &self,
+        request: &ClaudeRequest,
+        tool_name: &str,
+        code_argument: &str,
+        deadline: Duration,
+    ) -> Result<(String, u32, u32), Error> {
+        let deadline_at = Instant::now() + deadline;
+        let mut response = self.send(request).await?;
+
+        let mut decoder = SseDecoder::default();
+        let mut scanner = LeanCodeScanner::new(tool_name, code_argument);
+        let (mut input_tokens, mut output_tokens) = (None, None);
+
+        'stream: while scanner.code().is_none() {
+            let chunk = tokio::time::timeout_at(deadline_at, response.chunk())
+                .await
+                .map_err(|_| Error::timeout(format!("Claude API stream did not produce Lean code within {:?}", deadline)))??;
+            let Some(chunk) = chunk else { break };
+
+            for event in decoder.push(&chunk) {
+                match &event {
+                    StreamEvent::Usage { input_tokens: input, output_tokens: output } => {
+                        input_tokens = input.or(input_tokens);
+                        output_tokens = output.or(output_tokens);
+                    }
+                    StreamEvent::Stop => break 'stream,
+                    StreamEvent::Error(message) => return Err(Error::transient(format!("Claude API stream error: {}", message))),
+                    _ => scanner.push(&event),
+                }
+            }
+        }
+
+        // Usage after an early stop only reflects what was streamed, so
+        // fall back to an estimate from the text received
+        let output_tokens = output_tokens.unwrap_or(0).max((scanner.received_len() / 4) as u32);
+        let lean_code = scanner.into_code().ok_or_else(|| Error::transient("No valid tool call response received"))?;
+
+        Ok((lean_code, input_tokens.unwrap_or(0), output_tokens))
+    }
+
     pub fn estimate_cost(&self, input_tokens: u32, output_tokens: u32, cost_per_1k_tokens: f64) -> f64 {
         let total_tokens = input_tokens + output_tokens;
         (total_tokens as f64 / 1000.0) * cost_per_1k_tokens
@@ -330,16 +434,4 @@ mod tests {
         let cost = client.estimate_cost(1000, 500, 0.015);
         assert_eq!(cost, 0.0225); // (1500 / 1000) * 0.015
     }
-
-    #[test]
-    fn test_prompt_building() {
-        let client = ClaudeClient::new("test-key", "claude-3-opus-20240229");
-        let lean_prompt = client.build_lean_prompt("test invariant", "induction");
-        assert!(lean_prompt.contains("test invariant"));
-        assert!(lean_prompt.contains("induction"));
-        
-        let proof_prompt = client.build_proof_prompt("test theorem", "tactics");
-        assert!(proof_prompt.contains("test theorem"));
-        assert!(proof_prompt.contains("tactics"));
-    }
 } 
\ No newline at end of file
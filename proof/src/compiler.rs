@@ -1,41 +1,176 @@
 use std::collections::HashMap;
-use std::error::Error;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use spec_to_proof_error::Error;
+use futures::future::select_ok;
+use cost_governance::LlmCallGovernor;
+use prompt_registry::{PromptRegistry, SelectedPrompt};
 use serde_json::Value;
 use sha2::{Sha256, Digest};
 
 use crate::claude_client::ClaudeClient;
+use crate::definitions;
+use crate::model_router::{ModelRouter, ModelTier};
+use crate::prompts;
+use crate::repair::{CheckOutcome, FailureClass, LeanChecker, RepairBudget, REPAIR_STRATEGY_PREFIX};
+use crate::transcripts::{AttemptTranscript, ProofTranscript, TranscriptRecorder};
 use crate::proto::proof::v1::*;
 use crate::proto::spec_to_proof::v1::*;
 
+// Strategies tried by the portfolio runner when none are given explicitly.
+// "llm_guided" leaves tactic selection to the model.
+pub const DEFAULT_PORTFOLIO_STRATEGIES: &[&str] = &[
+    "simp",
+    "aesop",
+    "omega",
+    "nlinarith",
+    "decide",
+    "llm_guided",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortfolioMode {
+    Sequential,
+    Parallel,
+}
+
+/// Theorem metadata holding the content hash of the invariant it was
+/// compiled from, so stale theorems can be told apart from current ones
+pub const INVARIANT_SHA256_METADATA_KEY: &str = "invariant_sha256";
+
+// Rough characters-per-token ratio for estimating prompts without a tokenizer
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens and cost of one model call, estimated without making it.
+/// Completions are assumed to use the whole output budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEstimate {
+    pub model: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PortfolioOptions {
+    pub strategies: Vec<String>,
+    pub mode: PortfolioMode,
+    pub per_strategy_timeout: Duration,
+}
+
+impl Default for PortfolioOptions {
+    fn default() -> Self {
+        Self {
+            strategies: DEFAULT_PORTFOLIO_STRATEGIES.iter().map(|s| s.to_string()).collect(),
+            mode: PortfolioMode::Sequential,
+            per_strategy_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl PortfolioOptions {
+    /// The default portfolio with the request's `timeout_seconds` split across
+    /// its attempts and, when they run one after another, its strategies.
+    /// Requests without a timeout keep the default per-strategy timeout.
+    pub fn for_request(options: &ProofOptions) -> Self {
+        let mut portfolio = Self::default();
+        if options.timeout_seconds > 0 {
+            let strategies = match portfolio.mode {
+                PortfolioMode::Sequential => portfolio.strategies.len() as u32,
+                PortfolioMode::Parallel => 1,
+            };
+            let shares = options.max_attempts.max(1) * strategies;
+            portfolio.per_strategy_timeout = Duration::from_secs(options.timeout_seconds as u64) / shares;
+        }
+        portfolio
+    }
+}
+
 pub struct LeanCompiler {
     claude_client: ClaudeClient,
+    // Client for the cheaper model simple invariants are routed to
+    simple_claude_client: Option<ClaudeClient>,
+    router: ModelRouter,
+    prompts: Arc<PromptRegistry>,
+    // Checks generated proofs so failing ones can be repaired
+    checker: Option<LeanChecker>,
     config: ProofConfig,
 }
 
 impl LeanCompiler {
     pub fn new(config: &ProofConfig) -> Self {
-        let claude_client = ClaudeClient::new(&config.claude_api_key, &config.claude_model);
+        let mut claude_client = ClaudeClient::new(&config.claude_api_key, &config.claude_model);
+        if let Some(deadline_ms) = config.stream_deadline_ms {
+            claude_client = claude_client.with_streaming(Duration::from_millis(deadline_ms));
+        }
+        let simple_claude_client = config.simple_claude_model
+            .as_ref()
+            .map(|model| claude_client.clone().with_model(model));
         
         Self {
             claude_client,
+            simple_claude_client,
+            router: ModelRouter::new(config),
+            prompts: Arc::new(prompts::builtin_registry()),
+            checker: config.repair_lean_project.as_ref().map(|project| {
+                LeanChecker::new(project, Duration::from_millis(config.repair_check_timeout_ms))
+            }),
             config: config.clone(),
         }
     }
 
+    pub fn with_prompt_registry(mut self, prompts: Arc<PromptRegistry>) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    pub fn with_cost_governance(self, governor: Arc<LlmCallGovernor>) -> Self {
+        Self {
+            claude_client: self.claude_client.with_cost_governance(governor.clone()),
+            simple_claude_client: self.simple_claude_client.map(|client| client.with_cost_governance(governor)),
+            ..self
+        }
+    }
+
+    /// Whether generated proofs are checked with Lean, which the portfolio
+    /// needs to tell a working strategy from one that merely answered
+    pub fn has_checker(&self) -> bool {
+        self.checker.is_some()
+    }
+
+    fn client_for(&self, model: &str) -> &ClaudeClient {
+        match &self.simple_claude_client {
+            Some(client) if self.router.is_simple_model(model) => client,
+            _ => &self.claude_client,
+        }
+    }
+
     pub async fn compile_invariant_to_theorem(
         &self,
         invariant: &Invariant,
         options: &CompilationOptions,
-    ) -> Result<LeanTheorem, Box<dyn Error>> {
+    ) -> Result<LeanTheorem, Error> {
+        self.compile_invariant_in_set(invariant, None, options).await
+    }
+
+    /// Compiles an invariant against its set's shared definitions, given as
+    /// the module name and its Lean source, so the theorem uses the shared
+    /// types and bounds instead of declaring the variables itself
+    pub async fn compile_invariant_in_set(
+        &self,
+        invariant: &Invariant,
+        definitions: Option<(&str, &str)>,
+        options: &CompilationOptions,
+    ) -> Result<LeanTheorem, Error> {
         let start_time = Instant::now();
-        
-        // Convert invariant to string representation
-        let invariant_str = self.invariant_to_string(invariant);
+        let (prompt, rendered) = self.theorem_prompt(invariant, definitions, options)?;
+
+        let route = self.router.route(invariant);
+        tracing::debug!("Routing invariant {} to {} ({})", invariant.id, route.model, route.reason);
         
         // Generate Lean theorem using Claude
-        let (lean_code, input_tokens, output_tokens) = self.claude_client
-            .generate_lean_theorem(&invariant_str, &options.proof_strategy, options.seed)
+        let (lean_code, input_tokens, output_tokens) = self.client_for(&route.model)
+            .generate_lean_theorem(rendered, options.seed)
             .await?;
 
         // Parse the response to extract theorem name and imports
@@ -43,16 +178,26 @@ impl LeanCompiler {
         
         // Generate theorem ID and content hash
         let theorem_id = self.generate_theorem_id(invariant);
-        let content_hash = self.compute_content_hash(&lean_code);
+        let content_hash = compute_content_hash(&lean_code);
         
         // Create metadata
         let mut metadata = HashMap::new();
         metadata.insert("input_tokens".to_string(), input_tokens.to_string());
         metadata.insert("output_tokens".to_string(), output_tokens.to_string());
+        metadata.insert(
+            "estimated_cost_usd".to_string(),
+            self.router.estimate_cost(&route.model, input_tokens, output_tokens).to_string(),
+        );
         metadata.insert("compilation_time_ms".to_string(), start_time.elapsed().as_millis().to_string());
+        metadata.insert(INVARIANT_SHA256_METADATA_KEY.to_string(), invariant.content_sha256.clone());
         metadata.insert("proof_strategy".to_string(), options.proof_strategy.clone());
         metadata.insert("temperature".to_string(), options.temperature.to_string());
         metadata.insert("seed".to_string(), options.seed.to_string());
+        record_prompt(&mut metadata, &prompt);
+        route.record(&mut metadata);
+        if let Some((module, _)) = definitions {
+            metadata.insert(definitions::SHARED_DEFINITIONS_METADATA_KEY.to_string(), module.to_string());
+        }
         
         if let Some(imports) = parsed_response.get("imports") {
             metadata.insert("imports".to_string(), serde_json::to_string(imports)?);
@@ -76,29 +221,276 @@ impl LeanCompiler {
             compilation_errors: Vec::new(),
             proof_strategy: options.proof_strategy.clone(),
             metadata,
+            lean_toolchain: self.config.lean_toolchain.clone(),
+            mathlib_commit: self.config.mathlib_commit.clone(),
         };
 
         Ok(theorem)
     }
 
+    /// Estimates compiling `invariant` without calling the model
+    pub fn estimate_compilation(
+        &self,
+        invariant: &Invariant,
+        definitions: Option<(&str, &str)>,
+        options: &CompilationOptions,
+    ) -> Result<CallEstimate, Error> {
+        let (_, rendered) = self.theorem_prompt(invariant, definitions, options)?;
+        let model = self.router.route(invariant).model;
+        Ok(self.estimate_call(model, &rendered, options.max_tokens))
+    }
+
+    /// Estimates one proof attempt for `theorem` without calling the model.
+    /// For a theorem that is not compiled yet, pass a stub carrying the id,
+    /// and the statement's estimated output tokens as `pending_statement_tokens`.
+    pub fn estimate_proof(
+        &self,
+        theorem: &LeanTheorem,
+        pending_statement_tokens: u32,
+        options: &ProofOptions,
+    ) -> Result<CallEstimate, Error> {
+        let (_, rendered) = self.proof_prompt(theorem, options)?;
+        let mut estimate = self.estimate_call(self.router.model_for_theorem(theorem), &rendered, options.max_tokens);
+        estimate.input_tokens += pending_statement_tokens;
+        estimate.cost_usd = self.router.estimate_cost(&estimate.model, estimate.input_tokens, estimate.output_tokens);
+        Ok(estimate)
+    }
+
+    fn estimate_call(&self, model: String, prompt: &str, max_tokens: u32) -> CallEstimate {
+        let input_tokens = prompt.len().div_ceil(CHARS_PER_TOKEN) as u32;
+        let output_tokens = if max_tokens > 0 { max_tokens } else { self.config.max_tokens };
+        CallEstimate {
+            cost_usd: self.router.estimate_cost(&model, input_tokens, output_tokens),
+            model,
+            input_tokens,
+            output_tokens,
+        }
+    }
+
+    fn theorem_prompt(
+        &self,
+        invariant: &Invariant,
+        definitions: Option<(&str, &str)>,
+        options: &CompilationOptions,
+    ) -> Result<(SelectedPrompt, String), Error> {
+        // Convert invariant to string representation
+        let mut invariant_str = self.invariant_to_string(invariant);
+        if let Some((module, lean)) = definitions {
+            invariant_str.push_str(&format!(
+                "\n\nShared Definitions (module {}, imported and opened; use these instead of declaring the variables):\n{}",
+                module, lean
+            ));
+        }
+
+        let prompt = self.prompts.select(prompts::THEOREM_GENERATION_PROMPT, &invariant.id)?;
+        let mut variables = HashMap::new();
+        variables.insert("invariant".to_string(), invariant_str.as_str());
+        variables.insert("proof_strategy".to_string(), options.proof_strategy.as_str());
+        let rendered = prompt.template.render(&variables)?;
+        Ok((prompt, rendered))
+    }
+
+    fn proof_prompt(&self, theorem: &LeanTheorem, options: &ProofOptions) -> Result<(SelectedPrompt, String), Error> {
+        let prompt = self.prompts.select(prompts::PROOF_GENERATION_PROMPT, &theorem.id)?;
+        let mut variables = HashMap::new();
+        variables.insert("theorem_code".to_string(), theorem.lean_code.as_str());
+        variables.insert("proof_strategy".to_string(), options.proof_strategy.as_str());
+        let rendered = prompt.template.render(&variables)?;
+        Ok((prompt, rendered))
+    }
+
+    fn repair_prompt(
+        &self,
+        theorem: &LeanTheorem,
+        proof_code: &str,
+        outcome: &CheckOutcome,
+        class: FailureClass,
+    ) -> Result<(SelectedPrompt, String), Error> {
+        let prompt = self.prompts.select(prompts::PROOF_REPAIR_PROMPT, &theorem.id)?;
+        let diagnostics = outcome.error_lines().join("\n");
+        let mut variables = HashMap::new();
+        variables.insert("theorem_code".to_string(), theorem.lean_code.as_str());
+        variables.insert("proof_code".to_string(), proof_code);
+        variables.insert("diagnostics".to_string(), diagnostics.as_str());
+        variables.insert("repair_instruction".to_string(), class.instruction());
+        let rendered = prompt.template.render(&variables)?;
+        Ok((prompt, rendered))
+    }
+
     pub async fn generate_proof(
         &self,
         theorem: &LeanTheorem,
         options: &ProofOptions,
-    ) -> Result<(LeanTheorem, ProofArtifact), Box<dyn Error>> {
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
+        self.generate_proof_recorded(theorem, options, &TranscriptRecorder::default()).await
+    }
+
+    /// Like `generate_proof`, recording the attempt in `transcript` whether
+    /// or not it succeeds. With a checker configured, a proof that fails to
+    /// compile is repaired, each repair recorded as its own attempt.
+    pub async fn generate_proof_recorded(
+        &self,
+        theorem: &LeanTheorem,
+        options: &ProofOptions,
+        transcript: &TranscriptRecorder,
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
+        let start_time = Instant::now();
+        let mut attempt = AttemptTranscript {
+            strategy: options.proof_strategy.clone(),
+            diagnostics: theorem.compilation_errors.clone(),
+            ..Default::default()
+        };
+
+        let result = self.attempt_proof(theorem, options, &mut attempt).await;
+        let checker = match (&result, &self.checker) {
+            (Ok(_), Some(checker)) => checker,
+            _ => {
+                record_attempt(transcript, attempt, start_time, &result);
+                return result;
+            }
+        };
+        let (proven_theorem, proof_artifact) = result?;
+
+        let outcome = match checker.check(&proven_theorem.lean_code).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                let result = Err(e);
+                record_attempt(transcript, attempt, start_time, &result);
+                return result;
+            }
+        };
+        if outcome.success() {
+            let result = Ok((proven_theorem, proof_artifact));
+            record_attempt(transcript, attempt, start_time, &result);
+            return result;
+        }
+
+        let repair_of = record_failed_check(transcript, attempt, start_time, &outcome);
+        self.repair_proof(theorem, options, proof_artifact.output, outcome, repair_of, transcript).await
+    }
+
+    // Asks the model to fix the errors in `proof_code` until its proof
+    // checks or the repair budget runs out
+    async fn repair_proof(
+        &self,
+        theorem: &LeanTheorem,
+        options: &ProofOptions,
+        mut proof_code: String,
+        mut outcome: CheckOutcome,
+        mut repair_of: u32,
+        transcript: &TranscriptRecorder,
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
+        let Some(checker) = &self.checker else {
+            return Err(Error::internal("Proof repair needs a checker"));
+        };
+        let budget = RepairBudget::for_options(options, &self.config);
+        let model = self.router.model_for_theorem(theorem);
+        let mut repaired_classes = Vec::new();
+        let mut tokens_used = 0;
+
+        while budget.allows(repaired_classes.len() as u32, tokens_used) {
+            let start_time = Instant::now();
+            let class = FailureClass::of_diagnostics(&outcome.diagnostics).unwrap_or(FailureClass::Other);
+            repaired_classes.push(class.as_str());
+            let (prompt, rendered) = self.repair_prompt(theorem, &proof_code, &outcome, class)?;
+            let mut attempt = AttemptTranscript {
+                strategy: format!("{}{}", REPAIR_STRATEGY_PREFIX, class.as_str()),
+                model: model.clone(),
+                prompt: rendered,
+                repair_of: Some(repair_of),
+                ..Default::default()
+            };
+
+            let result = self.complete_proof(theorem, options, &prompt, &mut attempt, start_time).await;
+            tokens_used += attempt.tokens;
+            let (mut proven_theorem, mut proof_artifact) = match result {
+                Ok(proof) => proof,
+                Err(e) => {
+                    let result = Err(e);
+                    record_attempt(transcript, attempt, start_time, &result);
+                    return result;
+                }
+            };
+
+            outcome = match checker.check(&proven_theorem.lean_code).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    let result = Err(e);
+                    record_attempt(transcript, attempt, start_time, &result);
+                    return result;
+                }
+            };
+            if outcome.success() {
+                for metadata in [&mut proven_theorem.metadata, &mut proof_artifact.metadata] {
+                    metadata.insert("repair_attempts".to_string(), repaired_classes.len().to_string());
+                    metadata.insert("repaired_failure_classes".to_string(), repaired_classes.join(","));
+                }
+                let result = Ok((proven_theorem, proof_artifact));
+                record_attempt(transcript, attempt, start_time, &result);
+                return result;
+            }
+
+            proof_code = proof_artifact.output;
+            repair_of = record_failed_check(transcript, attempt, start_time, &outcome);
+        }
+
+        let class = FailureClass::of_diagnostics(&outcome.diagnostics).unwrap_or(FailureClass::Other);
+        Err(Error::transient(format!(
+            "Proof of {} still fails to compile after {} repairs ({} tokens): {}",
+            theorem.theorem_name,
+            repaired_classes.len(),
+            tokens_used,
+            outcome.error_lines().first().map(String::as_str).unwrap_or(class.as_str())
+        )))
+    }
+
+    async fn attempt_proof(
+        &self,
+        theorem: &LeanTheorem,
+        options: &ProofOptions,
+        attempt: &mut AttemptTranscript,
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
         let start_time = Instant::now();
         
+        let (prompt, rendered) = self.proof_prompt(theorem, options)?;
+
+        attempt.model = self.router.model_for_theorem(theorem);
+        attempt.prompt = rendered;
+        self.complete_proof(theorem, options, &prompt, attempt, start_time).await
+    }
+
+    // Sends `attempt.prompt` to `attempt.model` and builds the proven
+    // theorem and artifact from the completion
+    async fn complete_proof(
+        &self,
+        theorem: &LeanTheorem,
+        options: &ProofOptions,
+        prompt: &SelectedPrompt,
+        attempt: &mut AttemptTranscript,
+        start_time: Instant,
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
+        let model = attempt.model.clone();
+
         // Generate proof using Claude
-        let (proof_code, input_tokens, output_tokens) = self.claude_client
-            .generate_proof(&theorem.lean_code, &options.proof_strategy, options.seed)
+        let (proof_code, input_tokens, output_tokens) = self.client_for(&model)
+            .generate_proof(attempt.prompt.clone(), options.seed)
             .await?;
+        attempt.completion = proof_code.clone();
+        attempt.tokens = input_tokens + output_tokens;
+        attempt.cost_usd = self.router.estimate_cost(&model, input_tokens, output_tokens);
 
         // Parse the proof response
-        let parsed_proof = self.parse_proof_response(&proof_code)?;
+        let parsed_proof = match self.parse_proof_response(&proof_code) {
+            Ok(parsed_proof) => parsed_proof,
+            Err(e) => {
+                attempt.diagnostics.push(e.to_string());
+                return Err(e);
+            }
+        };
         
         // Combine original theorem with proof
         let complete_lean_code = format!("{}\n\n{}", theorem.lean_code, proof_code);
-        let new_content_hash = self.compute_content_hash(&complete_lean_code);
+        let new_content_hash = compute_content_hash(&complete_lean_code);
         
         // Create proven theorem
         let mut proven_theorem = theorem.clone();
@@ -110,9 +502,17 @@ impl LeanCompiler {
         let mut metadata = HashMap::new();
         metadata.insert("proof_input_tokens".to_string(), input_tokens.to_string());
         metadata.insert("proof_output_tokens".to_string(), output_tokens.to_string());
+        metadata.insert(
+            "proof_estimated_cost_usd".to_string(),
+            attempt.cost_usd.to_string(),
+        );
+        let tier = if self.router.is_simple_model(&model) { ModelTier::Simple } else { ModelTier::Complex };
+        metadata.insert("model".to_string(), model.clone());
+        metadata.insert("model_tier".to_string(), tier.as_str().to_string());
         metadata.insert("proof_generation_time_ms".to_string(), start_time.elapsed().as_millis().to_string());
         metadata.insert("proof_strategy".to_string(), options.proof_strategy.clone());
         metadata.insert("attempts".to_string(), "1".to_string());
+        record_prompt(&mut metadata, prompt);
         
         if let Some(tactics) = parsed_proof.get("tactics_used") {
             metadata.insert("tactics_used".to_string(), serde_json::to_string(tactics)?);
@@ -122,12 +522,12 @@ impl LeanCompiler {
             metadata.insert("difficulty".to_string(), difficulty.as_str().unwrap_or("unknown").to_string());
         }
         
-        proven_theorem.metadata = metadata;
+        proven_theorem.metadata = metadata.clone();
 
         // Create proof artifact
         let proof_artifact = ProofArtifact {
-            id: self.generate_proof_artifact_id(theorem),
-            content_sha256: self.compute_content_hash(&proof_code),
+            id: proof_artifact_id(theorem),
+            content_sha256: compute_content_hash(&proof_code),
             theorem_id: theorem.id.clone(),
             invariant_id: theorem.source_invariant_id.clone(),
             status: ProofStatus::Success as i32,
@@ -143,12 +543,159 @@ impl LeanCompiler {
             }),
             proof_strategy: options.proof_strategy.clone(),
             confidence_score: 1.0, // TODO: Implement confidence scoring
-            metadata: HashMap::new(),
+            metadata,
+            lean_toolchain: theorem.lean_toolchain.clone(),
+            mathlib_commit: theorem.mathlib_commit.clone(),
+        };
+
+        Ok((proven_theorem, proof_artifact))
+    }
+
+    pub async fn generate_proof_portfolio(
+        &self,
+        theorem: &LeanTheorem,
+        options: &ProofOptions,
+        portfolio: &PortfolioOptions,
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
+        self.generate_proof_portfolio_recorded(theorem, options, portfolio, &TranscriptRecorder::default()).await
+    }
+
+    /// Like `generate_proof_portfolio`, recording each strategy's attempt in
+    /// `transcript`. Parallel attempts still running when one succeeds are
+    /// cancelled and not recorded.
+    pub async fn generate_proof_portfolio_recorded(
+        &self,
+        theorem: &LeanTheorem,
+        options: &ProofOptions,
+        portfolio: &PortfolioOptions,
+        transcript: &TranscriptRecorder,
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
+        if portfolio.strategies.is_empty() {
+            return Err(Error::invalid_input("Proof portfolio has no strategies"));
+        }
+        if self.checker.is_none() {
+            return Err(Error::invalid_input("Proof portfolio needs a Lean checker; set repair_lean_project"));
+        }
+
+        let start_time = Instant::now();
+
+        let (winner, mut proven_theorem, mut proof_artifact, failures) = match portfolio.mode {
+            PortfolioMode::Sequential => {
+                let mut failures = Vec::new();
+                let mut winner = None;
+                for strategy in &portfolio.strategies {
+                    match self.attempt_strategy(theorem, options, strategy, portfolio.per_strategy_timeout, transcript).await {
+                        Ok((proven_theorem, proof_artifact)) => {
+                            winner = Some((strategy.clone(), proven_theorem, proof_artifact));
+                            break;
+                        }
+                        Err(e) => failures.push(e),
+                    }
+                }
+                match winner {
+                    Some((strategy, proven_theorem, proof_artifact)) => (strategy, proven_theorem, proof_artifact, failures),
+                    None => return Err(format!("All portfolio strategies failed: {}", failures.join("; ")).into()),
+                }
+            }
+            PortfolioMode::Parallel => {
+                let attempts = portfolio.strategies.iter().map(|strategy| {
+                    Box::pin(async move {
+                        self.attempt_strategy(theorem, options, strategy, portfolio.per_strategy_timeout, transcript)
+                            .await
+                            .map(|(proven_theorem, proof_artifact)| (strategy.clone(), proven_theorem, proof_artifact))
+                    })
+                });
+                match select_ok(attempts).await {
+                    Ok(((strategy, proven_theorem, proof_artifact), _remaining)) => (strategy, proven_theorem, proof_artifact, Vec::new()),
+                    Err(e) => return Err(format!("All portfolio strategies failed, last error: {}", e).into()),
+                }
+            }
         };
 
+        tracing::info!("Portfolio proof of {} succeeded with strategy {}", theorem.theorem_name, winner);
+
+        let mode = match portfolio.mode {
+            PortfolioMode::Sequential => "sequential",
+            PortfolioMode::Parallel => "parallel",
+        };
+
+        for metadata in [&mut proven_theorem.metadata, &mut proof_artifact.metadata] {
+            metadata.insert("portfolio_winner".to_string(), winner.clone());
+            metadata.insert("portfolio_mode".to_string(), mode.to_string());
+            metadata.insert("portfolio_strategies".to_string(), portfolio.strategies.join(","));
+            metadata.insert("portfolio_failed_attempts".to_string(), failures.len().to_string());
+            metadata.insert("portfolio_time_ms".to_string(), start_time.elapsed().as_millis().to_string());
+        }
+        proof_artifact.proof_strategy = winner;
+
         Ok((proven_theorem, proof_artifact))
     }
 
+    // Generates one proof and checks it without repairing it, so a strategy
+    // whose proof does not compile fails and the portfolio moves on
+    async fn generate_checked_proof(
+        &self,
+        theorem: &LeanTheorem,
+        options: &ProofOptions,
+        transcript: &TranscriptRecorder,
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
+        let Some(checker) = &self.checker else {
+            return Err(Error::internal("Checking a proof needs a checker"));
+        };
+        let start_time = Instant::now();
+        let mut attempt = AttemptTranscript {
+            strategy: options.proof_strategy.clone(),
+            diagnostics: theorem.compilation_errors.clone(),
+            ..Default::default()
+        };
+
+        let result = match self.attempt_proof(theorem, options, &mut attempt).await {
+            Ok((proven_theorem, proof_artifact)) => match checker.check(&proven_theorem.lean_code).await {
+                Ok(outcome) if outcome.success() => Ok((proven_theorem, proof_artifact)),
+                Ok(outcome) => {
+                    let class = FailureClass::of_diagnostics(&outcome.diagnostics).unwrap_or(FailureClass::Other);
+                    record_failed_check(transcript, attempt, start_time, &outcome);
+                    return Err(Error::transient(format!(
+                        "Proof does not compile: {}",
+                        outcome.error_lines().first().map(String::as_str).unwrap_or(class.as_str())
+                    )));
+                }
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        record_attempt(transcript, attempt, start_time, &result);
+        result
+    }
+
+    async fn attempt_strategy(
+        &self,
+        theorem: &LeanTheorem,
+        options: &ProofOptions,
+        strategy: &str,
+        per_strategy_timeout: Duration,
+        transcript: &TranscriptRecorder,
+    ) -> Result<(LeanTheorem, ProofArtifact), String> {
+        let mut strategy_options = options.clone();
+        strategy_options.proof_strategy = strategy.to_string();
+
+        let attempt = self.generate_checked_proof(theorem, &strategy_options, transcript);
+        match tokio::time::timeout(per_strategy_timeout, attempt).await {
+            Ok(Ok((proven_theorem, proof_artifact))) => Ok((proven_theorem, proof_artifact)),
+            Ok(Err(e)) => Err(format!("{}: {}", strategy, e)),
+            Err(_) => {
+                let error = format!("{}: timed out after {}ms", strategy, per_strategy_timeout.as_millis());
+                transcript.record(AttemptTranscript {
+                    strategy: strategy.to_string(),
+                    error: Some(error.clone()),
+                    duration_ms: per_strategy_timeout.as_millis() as u64,
+                    ..Default::default()
+                });
+                Err(error)
+            }
+        }
+    }
+
     fn invariant_to_string(&self, invariant: &Invariant) -> String {
         let mut parts = Vec::new();
         
@@ -190,7 +737,7 @@ impl LeanCompiler {
         parts.join("\n\n")
     }
 
-    fn parse_lean_response(&self, lean_code: &str) -> Result<Value, Box<dyn Error>> {
+    fn parse_lean_response(&self, lean_code: &str) -> Result<Value, Error> {
         // In a real implementation, this would parse the Lean code more intelligently
         // For now, we'll extract basic information
         
@@ -220,7 +767,7 @@ impl LeanCompiler {
         Ok(Value::Object(result))
     }
 
-    fn parse_proof_response(&self, proof_code: &str) -> Result<Value, Box<dyn Error>> {
+    fn parse_proof_response(&self, proof_code: &str) -> Result<Value, Error> {
         // In a real implementation, this would parse the proof code more intelligently
         // For now, we'll extract basic information
         
@@ -302,20 +849,118 @@ impl LeanCompiler {
     }
 
     fn generate_theorem_id(&self, invariant: &Invariant) -> String {
-        format!("theorem_{}", invariant.id)
+        theorem_id(&invariant.id)
     }
+}
 
-    fn generate_proof_artifact_id(&self, theorem: &LeanTheorem) -> String {
-        format!("proof_{}", theorem.id)
+/// Hex SHA-256 of `content`, as stored in `content_sha256` fields
+pub(crate) fn compute_content_hash(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Id of the theorem compiled from an invariant; recompiling replaces it
+pub fn theorem_id(invariant_id: &str) -> String {
+    format!("theorem_{}", invariant_id)
+}
+
+/// Id of the artifact recording a proof of `theorem`, and the key its
+/// transcript is stored under
+pub fn proof_artifact_id(theorem: &LeanTheorem) -> String {
+    artifact_id_for_theorem(&theorem.id)
+}
+
+pub fn artifact_id_for_theorem(theorem_id: &str) -> String {
+    format!("proof_{}", theorem_id)
+}
+
+/// Id of the artifact recording the latest failed proof of `theorem`, kept
+/// apart from the artifact of its latest successful proof
+pub fn failed_proof_artifact_id(theorem: &LeanTheorem) -> String {
+    format!("{}_failed", proof_artifact_id(theorem))
+}
+
+/// Artifact recording that `attempts` tries at proving `theorem` failed
+/// with `error`, with the model calls in `transcript` they made
+pub fn failed_proof_artifact(
+    theorem: &LeanTheorem,
+    options: &ProofOptions,
+    transcript: &ProofTranscript,
+    error: &Error,
+    attempts: u32,
+    duration_ms: u64,
+) -> ProofArtifact {
+    let status = match error {
+        Error::Timeout(_) => ProofStatus::Timeout,
+        _ => ProofStatus::Failed,
+    };
+    let mut metadata = HashMap::new();
+    if let Some(model) = transcript.attempts.iter().rev().map(|attempt| &attempt.model).find(|model| !model.is_empty()) {
+        metadata.insert("model".to_string(), model.clone());
     }
+    let tokens: u64 = transcript.attempts.iter().map(|attempt| attempt.tokens as u64).sum();
+    let cost_usd: f64 = transcript.attempts.iter().map(|attempt| attempt.cost_usd).sum();
+    let repairs = transcript.attempts.iter().filter(|attempt| attempt.repair_of.is_some()).count();
+    metadata.insert("proof_tokens".to_string(), tokens.to_string());
+    metadata.insert("proof_estimated_cost_usd".to_string(), cost_usd.to_string());
+    metadata.insert("proof_strategy".to_string(), options.proof_strategy.clone());
+    metadata.insert("attempts".to_string(), attempts.to_string());
+    metadata.insert("repair_attempts".to_string(), repairs.to_string());
 
-    fn compute_content_hash(&self, content: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(content.as_bytes());
-        format!("{:x}", hasher.finalize())
+    ProofArtifact {
+        id: failed_proof_artifact_id(theorem),
+        theorem_id: theorem.id.clone(),
+        invariant_id: theorem.source_invariant_id.clone(),
+        status: status as i32,
+        attempted_at: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+        duration_ms,
+        logs: vec![error.to_string()],
+        proof_strategy: options.proof_strategy.clone(),
+        metadata,
+        lean_toolchain: theorem.lean_toolchain.clone(),
+        mathlib_commit: theorem.mathlib_commit.clone(),
+        ..Default::default()
     }
 }
 
+// Records an attempt with its duration and any error
+fn record_attempt(
+    transcript: &TranscriptRecorder,
+    mut attempt: AttemptTranscript,
+    start_time: Instant,
+    result: &Result<(LeanTheorem, ProofArtifact), Error>,
+) -> u32 {
+    attempt.duration_ms = start_time.elapsed().as_millis() as u64;
+    if let Err(e) = result {
+        attempt.error = Some(e.to_string());
+    }
+    transcript.record(attempt)
+}
+
+// Records an attempt whose proof was generated but does not compile
+fn record_failed_check(
+    transcript: &TranscriptRecorder,
+    mut attempt: AttemptTranscript,
+    start_time: Instant,
+    outcome: &CheckOutcome,
+) -> u32 {
+    let class = FailureClass::of_diagnostics(&outcome.diagnostics).unwrap_or(FailureClass::Other);
+    attempt.diagnostics.extend(outcome.error_lines());
+    attempt.failure_class = Some(class.as_str().to_string());
+    attempt.error = Some(format!("Proof does not compile: {}", class.as_str()));
+    attempt.duration_ms = start_time.elapsed().as_millis() as u64;
+    transcript.record(attempt)
+}
+
+// Ties generated theorems and proofs to the exact prompt text and A/B arm
+fn record_prompt(metadata: &mut HashMap<String, String>, prompt: &SelectedPrompt) {
+    metadata.insert("prompt_name".to_string(), prompt.template.name.clone());
+    metadata.insert("prompt_version".to_string(), prompt.template.version.clone());
+    metadata.insert("prompt_sha256".to_string(), prompt.template.sha256.clone());
+    metadata.insert("prompt_arm".to_string(), prompt.arm.as_str().to_string());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -347,6 +992,8 @@ mod tests {
             status: InvariantStatus::Extracted as i32,
             tags: vec!["test".to_string()],
             priority: Priority::Medium as i32,
+            source_span: None,
+            classification: None,
         };
         
         let result = compiler.invariant_to_string(&invariant);
@@ -425,12 +1072,61 @@ mod tests {
     }
 
     #[test]
-    fn test_compute_content_hash() {
+    fn test_default_portfolio_options() {
+        let portfolio = PortfolioOptions::default();
+        assert_eq!(portfolio.mode, PortfolioMode::Sequential);
+        assert_eq!(portfolio.strategies.len(), DEFAULT_PORTFOLIO_STRATEGIES.len());
+        assert!(portfolio.strategies.contains(&"omega".to_string()));
+        assert!(portfolio.strategies.contains(&"llm_guided".to_string()));
+    }
+
+    #[test]
+    fn test_request_portfolio_stays_within_timeout() {
+        let options = ProofOptions {
+            max_attempts: 2,
+            timeout_seconds: 120,
+            ..Default::default()
+        };
+        let portfolio = PortfolioOptions::for_request(&options);
+        let total = portfolio.per_strategy_timeout * (portfolio.strategies.len() as u32 * options.max_attempts);
+        assert!(total <= Duration::from_secs(120));
+
+        let unbounded = PortfolioOptions::for_request(&ProofOptions::default());
+        assert_eq!(unbounded.per_strategy_timeout, PortfolioOptions::default().per_strategy_timeout);
+    }
+
+    #[tokio::test]
+    async fn test_portfolio_requires_strategies() {
         let config = ProofConfig::default();
         let compiler = LeanCompiler::new(&config);
-        
+
+        let portfolio = PortfolioOptions {
+            strategies: vec![],
+            ..Default::default()
+        };
+
+        let result = compiler
+            .generate_proof_portfolio(&LeanTheorem::default(), &ProofOptions::default(), &portfolio)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_portfolio_requires_checker() {
+        // Without a checker every strategy's answer would count as a proof
+        let compiler = LeanCompiler::new(&ProofConfig::default());
+        assert!(!compiler.has_checker());
+
+        let result = compiler
+            .generate_proof_portfolio(&LeanTheorem::default(), &ProofOptions::default(), &PortfolioOptions::default())
+            .await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_compute_content_hash() {
         let content = "test content";
-        let hash = compiler.compute_content_hash(content);
+        let hash = compute_content_hash(content);
         
         // SHA256 hash of "test content"
         let expected = "a8fdc205a9f19cc1c9daea1b32e1342f441f42b602cf69d3c0c5ceb7cc1e49d7";
@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, BTreeSet};
+use spec_to_proof_error::Error;
+use spec_to_proof_proto::constraint::{Comparison, Constraint, Operand};
+
+use crate::proto::spec_to_proof::v1::{InvariantSet, Variable};
+use crate::workspace::upper_camel;
+
+/// Theorem metadata key naming the definitions module a theorem was
+/// generated against
+pub const SHARED_DEFINITIONS_METADATA_KEY: &str = "shared_definitions";
+
+/// Name of the structure bundling every variable with its constraints
+pub const VARIABLES_STRUCTURE: &str = "Variables";
+
+/// The variables of an invariant set, merged across invariants, from which
+/// the set's `Definitions.lean` is generated: a type per variable, a bounds
+/// predicate per variable and a structure carrying every constraint as a
+/// hypothesis, so all theorems in the set declare them the same way
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SharedDefinitions {
+    variables: BTreeMap<String, Variable>,
+}
+
+impl SharedDefinitions {
+    pub fn for_set(invariant_set: &InvariantSet) -> Result<Self, Error> {
+        Self::from_variables(invariant_set.invariants.iter().flat_map(|invariant| invariant.variables.iter()))
+    }
+
+    /// Merges variables declared by several invariants, keeping the first
+    /// description and unit and the union of the constraints. Declarations
+    /// that disagree on the type are an error.
+    pub fn from_variables<'a>(variables: impl IntoIterator<Item = &'a Variable>) -> Result<Self, Error> {
+        let mut merged: BTreeMap<String, Variable> = BTreeMap::new();
+        for variable in variables {
+            let name = lean_ident(&variable.name);
+            if name.is_empty() {
+                continue;
+            }
+            match merged.get_mut(&name) {
+                Some(existing) => {
+                    if lean_type(&existing.var_type) != lean_type(&variable.var_type) {
+                        return Err(Error::invalid_input(format!(
+                            "Variable {} is declared as both {} and {}",
+                            variable.name, existing.var_type, variable.var_type
+                        )));
+                    }
+                    for constraint in &variable.constraints {
+                        if !existing.constraints.contains(constraint) {
+                            existing.constraints.push(constraint.clone());
+                        }
+                    }
+                }
+                None => {
+                    merged.insert(name, variable.clone());
+                }
+            }
+        }
+        Ok(Self { variables: merged })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    /// Lean source of the definitions module, declared in `namespace`
+    pub fn to_lean(&self, namespace: &str) -> String {
+        let names: BTreeSet<&str> = self.variables.keys().map(String::as_str).collect();
+        let mut imports = BTreeSet::new();
+        let mut types = String::new();
+        let mut bounds = String::new();
+        let mut fields = String::new();
+        let mut hypotheses = String::new();
+        let mut untranslated = Vec::new();
+
+        for (name, variable) in &self.variables {
+            let lean_type = lean_type(&variable.var_type);
+            if lean_type == "ℝ" {
+                imports.insert("Mathlib.Data.Real.Basic");
+            }
+            let alias = type_alias(name);
+            let mut doc = variable.description.trim().to_string();
+            if !variable.unit.is_empty() {
+                doc = format!("{} ({})", doc, variable.unit).trim().to_string();
+            }
+            if !doc.is_empty() {
+                types.push_str(&format!("/-- {} -/\n", doc));
+            }
+            types.push_str(&format!("abbrev {} := {}\n\n", alias, lean_type));
+            fields.push_str(&format!("  {} : {}\n", name, alias));
+
+            let mut own = Vec::new();
+            for (index, constraint) in variable.constraints.iter().enumerate() {
+                match constraint_to_lean(constraint, name, lean_type, &names) {
+                    Some((proposition, mentioned)) => {
+                        hypotheses.push_str(&format!("  h_{}_{} : {}\n", name, index + 1, proposition));
+                        if mentioned.iter().all(|other| other == name) {
+                            own.push(proposition);
+                        }
+                    }
+                    None => untranslated.push(format!("{}: {}", name, constraint)),
+                }
+            }
+            if !own.is_empty() {
+                let body = if own.len() == 1 {
+                    own.remove(0)
+                } else {
+                    own.iter().map(|proposition| format!("({})", proposition)).collect::<Vec<_>>().join(" ∧ ")
+                };
+                bounds.push_str(&format!(
+                    "/-- Constraints on `{}` alone -/\ndef {}_bounds ({} : {}) : Prop :=\n  {}\n\n",
+                    name, name, name, alias, body
+                ));
+            }
+        }
+
+        let mut module: String = imports.iter().map(|import| format!("import {}\n", import)).collect();
+        if !imports.is_empty() {
+            module.push('\n');
+        }
+        module.push_str(&format!("namespace {}\n\n", namespace));
+        module.push_str(&types);
+        module.push_str(&bounds);
+        if !self.variables.is_empty() {
+            module.push_str(&format!(
+                "/-- Values of every variable in the set that satisfy the declared constraints -/\nstructure {} where\n{}{}\n",
+                VARIABLES_STRUCTURE, fields, hypotheses
+            ));
+        }
+        for constraint in untranslated {
+            module.push_str(&format!("-- Not translated: {}\n", constraint));
+        }
+        module.push_str(&format!("end {}\n", namespace));
+        module
+    }
+}
+
+/// Lean type for a variable's declared type
+pub fn lean_type(var_type: &str) -> &'static str {
+    match var_type.to_lowercase().as_str() {
+        "nat" | "natural" | "u32" | "u64" | "usize" | "count" | "ℕ" => "ℕ",
+        "int" | "integer" | "i32" | "i64" | "ℤ" => "ℤ",
+        "bool" | "boolean" => "Bool",
+        "string" | "str" => "String",
+        _ => "ℝ",
+    }
+}
+
+/// Type abbreviation declared for a variable, e.g. "ResponseTime" for
+/// "response_time"
+pub fn type_alias(variable_name: &str) -> String {
+    let alias = upper_camel(variable_name);
+    // A structure field and its type can't share a name
+    if alias.is_empty() || alias == VARIABLES_STRUCTURE || alias == variable_name {
+        format!("{}Type", alias)
+    } else {
+        alias
+    }
+}
+
+fn lean_ident(name: &str) -> String {
+    let ident: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    match ident.chars().next() {
+        Some(first) if first.is_ascii_digit() => format!("_{}", ident),
+        _ => ident,
+    }
+}
+
+// Translates a constraint into a Lean proposition, returning the variables
+// it mentions. Constraints outside the constraint language, comparisons with
+// anything other than the set's variables and numbers, patterns and lengths
+// of non-string variables are not translated.
+fn constraint_to_lean(
+    constraint: &str,
+    variable: &str,
+    lean_type: &str,
+    variables: &BTreeSet<&str>,
+) -> Option<(String, Vec<String>)> {
+    let proposition = match constraint.parse::<Constraint>().ok()? {
+        Constraint::Positive => format!("0 < {}", variable),
+        Constraint::Negative => format!("{} < 0", variable),
+        Constraint::NonNegative => format!("0 ≤ {}", variable),
+        Constraint::NonPositive => format!("{} ≤ 0", variable),
+        Constraint::NonZero => format!("{} ≠ 0", variable),
+        Constraint::Range { min, max } => format!("{} ≤ {} ∧ {} ≤ {}", min, variable, variable, max),
+        Constraint::Length { comparison, value } if lean_type == "String" => {
+            format!("{}.length {} {}", variable, lean_comparison(comparison), value)
+        }
+        Constraint::Length { .. } | Constraint::Regex(_) => return None,
+        Constraint::Compare { left, comparison, right } => {
+            let mut mentioned = Vec::new();
+            let mut operand = |operand: &Operand| match operand {
+                Operand::Number(number) => Some(number.clone()),
+                Operand::Variable(name) => {
+                    let ident = lean_ident(name);
+                    variables.contains(ident.as_str()).then(|| {
+                        mentioned.push(ident.clone());
+                        ident
+                    })
+                }
+            };
+            let (left, right) = (operand(&left)?, operand(&right)?);
+            return Some((format!("{} {} {}", left, lean_comparison(comparison), right), mentioned));
+        }
+    };
+    Some((proposition, vec![variable.to_string()]))
+}
+
+fn lean_comparison(comparison: Comparison) -> &'static str {
+    match comparison {
+        Comparison::Lt => "<",
+        Comparison::Le => "≤",
+        Comparison::Gt => ">",
+        Comparison::Ge => "≥",
+        Comparison::Eq => "=",
+        Comparison::Ne => "≠",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::spec_to_proof::v1::Invariant;
+
+    fn variable(name: &str, var_type: &str, constraints: &[&str]) -> Variable {
+        Variable {
+            name: name.to_string(),
+            var_type: var_type.to_string(),
+            constraints: constraints.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn invariant_set(variables: Vec<Vec<Variable>>) -> InvariantSet {
+        InvariantSet {
+            invariants: variables
+                .into_iter()
+                .map(|variables| Invariant { variables, ..Default::default() })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_definitions_declare_types_bounds_and_structure() {
+        let mut response_time = variable("response_time", "Nat", &["response_time <= 500"]);
+        response_time.description = "Time to first byte".to_string();
+        response_time.unit = "ms".to_string();
+        let set = invariant_set(vec![
+            vec![response_time, variable("error_rate", "Real", &["non_negative", "error_rate <= 1"])],
+            vec![variable("timeout", "Nat", &["positive", "response_time < timeout"])],
+        ]);
+        let lean = SharedDefinitions::for_set(&set).unwrap().to_lean("Slo");
+
+        assert!(lean.starts_with("import Mathlib.Data.Real.Basic\n\nnamespace Slo\n\n"));
+        assert!(lean.contains("/-- Time to first byte (ms) -/\nabbrev ResponseTime := ℕ\n"));
+        assert!(lean.contains("abbrev ErrorRate := ℝ\n"));
+        assert!(lean.contains("def error_rate_bounds (error_rate : ErrorRate) : Prop :=\n  (0 ≤ error_rate) ∧ (error_rate ≤ 1)\n"));
+        assert!(lean.contains("def response_time_bounds (response_time : ResponseTime) : Prop :=\n  response_time ≤ 500\n"));
+        assert!(lean.contains("def timeout_bounds (timeout : Timeout) : Prop :=\n  0 < timeout\n"));
+        assert!(lean.contains(
+            "structure Variables where\n  error_rate : ErrorRate\n  response_time : ResponseTime\n  timeout : Timeout\n"
+        ));
+        assert!(lean.contains("  h_timeout_2 : response_time < timeout\n"));
+        assert!(lean.ends_with("end Slo\n"));
+    }
+
+    #[test]
+    fn test_merges_constraints_across_invariants() {
+        let set = invariant_set(vec![
+            vec![variable("refund_amount", "Nat", &["refund_amount <= 1000"])],
+            vec![variable("refund_amount", "u64", &["refund_amount <= 1000", "refund_amount > 0"])],
+        ]);
+        let lean = SharedDefinitions::for_set(&set).unwrap().to_lean("Refunds");
+
+        assert_eq!(lean.matches("abbrev RefundAmount").count(), 1);
+        assert!(lean.contains("  h_refund_amount_1 : refund_amount ≤ 1000\n  h_refund_amount_2 : refund_amount > 0\n"));
+        assert!(!lean.contains("import"));
+    }
+
+    #[test]
+    fn test_conflicting_types_are_rejected() {
+        let set = invariant_set(vec![
+            vec![variable("amount", "Nat", &[])],
+            vec![variable("amount", "Real", &[])],
+        ]);
+        assert!(SharedDefinitions::for_set(&set).is_err());
+    }
+
+    #[test]
+    fn test_untranslatable_constraints_are_noted() {
+        let set = invariant_set(vec![vec![variable("latency", "Nat", &["latency < sla_limit", "must be fast"])]]);
+        let lean = SharedDefinitions::for_set(&set).unwrap().to_lean("Slo");
+
+        assert!(!lean.contains("latency_bounds"));
+        assert!(lean.contains("-- Not translated: latency: latency < sla_limit\n"));
+        assert!(lean.contains("-- Not translated: latency: must be fast\n"));
+    }
+
+    #[test]
+    fn test_constraint_language_forms() {
+        let set = invariant_set(vec![vec![
+            variable("discount", "Real", &["range(0, 0.5)"]),
+            variable("currency", "String", &["len == 3", "regex(\"^[A-Z]+$\")"]),
+            variable("retries", "Nat", &["len <= 3", "non_zero"]),
+        ]]);
+        let lean = SharedDefinitions::for_set(&set).unwrap().to_lean("Checkout");
+
+        assert!(lean.contains("def discount_bounds (discount : Discount) : Prop :=\n  0 ≤ discount ∧ discount ≤ 0.5\n"));
+        assert!(lean.contains("def currency_bounds (currency : Currency) : Prop :=\n  currency.length = 3\n"));
+        assert!(lean.contains("  h_retries_2 : retries ≠ 0\n"));
+        assert!(lean.contains("-- Not translated: currency: regex(\"^[A-Z]+$\")\n"));
+        assert!(lean.contains("-- Not translated: retries: len <= 3\n"));
+    }
+
+    #[test]
+    fn test_type_alias_avoids_clashes() {
+        assert_eq!(type_alias("response_time"), "ResponseTime");
+        assert_eq!(type_alias("Amount"), "AmountType");
+        assert_eq!(type_alias("variables"), "VariablesType");
+    }
+}
@@ -0,0 +1,564 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::ProofConfig;
+use crate::compiler::compute_content_hash;
+use crate::smt::{binary_precedence, is_natural, smt_sort, tokenize, Token};
+use crate::proto::spec_to_proof::v1::*;
+
+// Native evaluation of invariants whose variables all range over small finite
+// domains. Runs entirely in-process on a parsed expression tree with a time
+// budget, so it never executes anything the spec author wrote.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvaluationOutcome {
+    // Holds for every assignment in the finite domain
+    Exhaustive { assignments: u64 },
+    // No counterexample among the checked assignments, which did not cover
+    // the whole domain
+    Sampled { assignments: u64 },
+    Counterexample(String),
+    NotApplicable(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Int(i64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Domain {
+    Bool,
+    Range(i64, i64),
+}
+
+impl Domain {
+    fn size(&self) -> u64 {
+        match self {
+            Domain::Bool => 2,
+            Domain::Range(lo, hi) => (*hi as i128 - *lo as i128 + 1).clamp(0, u64::MAX as i128) as u64,
+        }
+    }
+
+    fn nth(&self, index: u64) -> Value {
+        match self {
+            Domain::Bool => Value::Bool(index == 1),
+            Domain::Range(lo, _) => Value::Int(lo.wrapping_add(index as i64)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    Int(i64),
+    Bool(bool),
+    Var(String),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+pub struct InvariantEvaluator {
+    exhaustive_limit: u64,
+    sample_size: u64,
+    timeout: Duration,
+}
+
+impl InvariantEvaluator {
+    pub fn new(config: &ProofConfig) -> Self {
+        Self {
+            exhaustive_limit: config.evaluation_exhaustive_limit,
+            sample_size: config.evaluation_sample_size,
+            timeout: Duration::from_millis(config.evaluation_timeout_ms),
+        }
+    }
+
+    pub fn check(&self, invariant: &Invariant) -> (EvaluationOutcome, ProofArtifact) {
+        let start_time = Instant::now();
+        let outcome = self.evaluate(invariant);
+        let duration_ms = start_time.elapsed().as_millis() as u64;
+
+        let status = match outcome {
+            EvaluationOutcome::Exhaustive { .. } => ProofStatus::Success,
+            EvaluationOutcome::Counterexample(_) => ProofStatus::Failed,
+            _ => ProofStatus::Unspecified,
+        };
+
+        let mut metadata = HashMap::new();
+        record_evidence(&outcome, &mut metadata);
+
+        let artifact = ProofArtifact {
+            id: format!("proof_eval_{}", invariant.id),
+            content_sha256: compute_content_hash(&invariant.formal_expression),
+            theorem_id: String::new(),
+            invariant_id: invariant.id.clone(),
+            status: status as i32,
+            attempted_at: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+            duration_ms,
+            output: describe(&outcome),
+            logs: vec![],
+            resource_usage: Some(ResourceUsage {
+                cpu_seconds: duration_ms as f64 / 1000.0,
+                memory_bytes: 0,
+                disk_bytes: 0,
+                network_bytes: 0,
+            }),
+            proof_strategy: "evaluation".to_string(),
+            confidence_score: if status == ProofStatus::Success { 1.0 } else { 0.0 },
+            metadata,
+            // Not checked by Lean, so valid under any toolchain
+            lean_toolchain: String::new(),
+            mathlib_commit: String::new(),
+        };
+
+        (outcome, artifact)
+    }
+
+    pub fn evaluate(&self, invariant: &Invariant) -> EvaluationOutcome {
+        let goal = match parse(&invariant.formal_expression) {
+            Ok(goal) => goal,
+            Err(e) => return EvaluationOutcome::NotApplicable(e),
+        };
+
+        let (domains, preconditions) = match infer_domains(invariant) {
+            Ok(result) => result,
+            Err(e) => return EvaluationOutcome::NotApplicable(e),
+        };
+
+        let mut referenced = Vec::new();
+        collect_vars(&goal, &mut referenced);
+        if let Some(unknown) = referenced.iter().find(|name| !domains.iter().any(|(n, _)| n == *name)) {
+            return EvaluationOutcome::NotApplicable(format!("variable {} is not declared", unknown));
+        }
+
+        let total = domains
+            .iter()
+            .fold(1u64, |acc, (_, domain)| acc.saturating_mul(domain.size()));
+        if total == 0 {
+            return EvaluationOutcome::NotApplicable("constraints leave an empty domain".to_string());
+        }
+        let deadline = Instant::now() + self.timeout;
+        let names: Vec<&str> = domains.iter().map(|(name, _)| name.as_str()).collect();
+        let mut assignment: HashMap<&str, Value> = HashMap::new();
+        let mut checked = 0u64;
+
+        let exhaustive = total <= self.exhaustive_limit;
+        let iterations = if exhaustive { total } else { self.sample_size };
+        let mut rng = StdRng::seed_from_u64(seed_for(invariant));
+
+        for i in 0..iterations {
+            if i % 1024 == 0 && Instant::now() > deadline {
+                return EvaluationOutcome::Sampled { assignments: checked };
+            }
+
+            // Mixed-radix decode for exhaustive runs, independent draws otherwise
+            let mut index = i;
+            for (name, domain) in &domains {
+                let position = if exhaustive {
+                    let position = index % domain.size();
+                    index /= domain.size();
+                    position
+                } else {
+                    rng.gen_range(0..domain.size())
+                };
+                assignment.insert(name.as_str(), domain.nth(position));
+            }
+
+            // Assignments outside the declared constraints are not part of
+            // the domain the invariant talks about
+            let admissible = preconditions
+                .iter()
+                .all(|p| matches!(eval(p, &assignment), Ok(Value::Bool(true))));
+            if !admissible {
+                continue;
+            }
+
+            match eval(&goal, &assignment) {
+                Ok(Value::Bool(true)) => checked += 1,
+                Ok(Value::Bool(false)) => {
+                    let witness: Vec<String> = names
+                        .iter()
+                        .map(|name| format!("{} = {}", name, format_value(assignment[name])))
+                        .collect();
+                    return EvaluationOutcome::Counterexample(witness.join(", "));
+                }
+                Ok(Value::Int(_)) => {
+                    return EvaluationOutcome::NotApplicable("expression is not boolean".to_string());
+                }
+                Err(e) => return EvaluationOutcome::NotApplicable(e),
+            }
+        }
+
+        if exhaustive {
+            EvaluationOutcome::Exhaustive { assignments: checked }
+        } else {
+            EvaluationOutcome::Sampled { assignments: checked }
+        }
+    }
+}
+
+// Records a pre-proof check on the artifact that ends up carrying the proof
+pub fn record_evidence(outcome: &EvaluationOutcome, metadata: &mut HashMap<String, String>) {
+    let (kind, detail) = match outcome {
+        EvaluationOutcome::Exhaustive { assignments } => ("exhaustive", assignments.to_string()),
+        EvaluationOutcome::Sampled { assignments } => ("sampled", assignments.to_string()),
+        EvaluationOutcome::Counterexample(witness) => ("counterexample", witness.clone()),
+        EvaluationOutcome::NotApplicable(_) => return,
+    };
+    metadata.insert("evaluation_check".to_string(), kind.to_string());
+    metadata.insert("evaluation_detail".to_string(), detail);
+}
+
+fn describe(outcome: &EvaluationOutcome) -> String {
+    match outcome {
+        EvaluationOutcome::Exhaustive { assignments } => {
+            format!("Holds for all {} admissible assignments", assignments)
+        }
+        EvaluationOutcome::Sampled { assignments } => {
+            format!("No counterexample in {} sampled assignments", assignments)
+        }
+        EvaluationOutcome::Counterexample(witness) => format!("Counterexample: {}", witness),
+        EvaluationOutcome::NotApplicable(reason) => format!("Not finitely checkable: {}", reason),
+    }
+}
+
+type Domains = Vec<(String, Domain)>;
+
+fn infer_domains(invariant: &Invariant) -> Result<(Domains, Vec<Expr>), String> {
+    let mut domains = Vec::new();
+    let mut preconditions = Vec::new();
+
+    for variable in &invariant.variables {
+        let mut constraints = Vec::new();
+        for constraint in &variable.constraints {
+            constraints.push(parse(constraint)?);
+        }
+
+        let domain = match smt_sort(&variable.var_type) {
+            "Bool" => Domain::Bool,
+            "Int" => {
+                let mut lo = if is_natural(&variable.var_type) { Some(0) } else { None };
+                let mut hi = None;
+                for constraint in &constraints {
+                    tighten_bounds(constraint, &variable.name, &mut lo, &mut hi);
+                }
+                match (lo, hi) {
+                    (Some(lo), Some(hi)) => Domain::Range(lo, hi),
+                    _ => return Err(format!("variable {} has no finite bounds", variable.name)),
+                }
+            }
+            _ => return Err(format!("variable {} is not integer or boolean", variable.name)),
+        };
+
+        domains.push((variable.name.clone(), domain));
+        preconditions.extend(constraints);
+    }
+
+    Ok((domains, preconditions))
+}
+
+// Narrows [lo, hi] using conjuncts of the form `name op literal` or
+// `literal op name`; anything else only acts as a filter
+pub(crate) fn tighten_bounds(expr: &Expr, name: &str, lo: &mut Option<i64>, hi: &mut Option<i64>) {
+    let (op, lhs, rhs) = match expr {
+        Expr::Binary("and", a, b) => {
+            tighten_bounds(a, name, lo, hi);
+            tighten_bounds(b, name, lo, hi);
+            return;
+        }
+        Expr::Binary(op, lhs, rhs) => (*op, lhs.as_ref(), rhs.as_ref()),
+        _ => return,
+    };
+
+    // Normalize to `name op value`
+    let (op, value) = match (lhs, rhs) {
+        (Expr::Var(v), Expr::Int(n)) if v == name => (op, *n),
+        (Expr::Int(n), Expr::Var(v)) if v == name => (flip(op), *n),
+        _ => return,
+    };
+
+    let raise = |bound: &mut Option<i64>, v: i64| *bound = Some(bound.map_or(v, |b| b.max(v)));
+    let lower = |bound: &mut Option<i64>, v: i64| *bound = Some(bound.map_or(v, |b| b.min(v)));
+
+    match op {
+        "<=" => lower(hi, value),
+        "<" => lower(hi, value.saturating_sub(1)),
+        ">=" => raise(lo, value),
+        ">" => raise(lo, value.saturating_add(1)),
+        "=" => {
+            raise(lo, value);
+            lower(hi, value);
+        }
+        _ => {}
+    }
+}
+
+fn flip(op: &'static str) -> &'static str {
+    match op {
+        "<" => ">",
+        "<=" => ">=",
+        ">" => "<",
+        ">=" => "<=",
+        other => other,
+    }
+}
+
+pub(crate) fn parse(expression: &str) -> Result<Expr, String> {
+    let tokens = tokenize(expression).map_err(|e| e.to_string())?;
+    let mut pos = 0;
+    let expr = parse_expression(&tokens, &mut pos, 0)?;
+    if pos < tokens.len() {
+        return Err(format!("trailing tokens in expression: {}", expression));
+    }
+    Ok(expr)
+}
+
+fn parse_expression(tokens: &[Token], pos: &mut usize, min_precedence: u8) -> Result<Expr, String> {
+    let mut lhs = parse_unary(tokens, pos)?;
+
+    while let Some(Token::Op(op)) = tokens.get(*pos) {
+        let precedence = match binary_precedence(op) {
+            Some(p) if p >= min_precedence => p,
+            _ => break,
+        };
+        *pos += 1;
+        let next_min = if *op == "=>" { precedence } else { precedence + 1 };
+        let rhs = parse_expression(tokens, pos, next_min)?;
+        lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+fn parse_unary(tokens: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    let token = tokens.get(*pos).cloned();
+    *pos += 1;
+
+    match token {
+        Some(Token::Op("not")) => Ok(Expr::Not(Box::new(parse_unary(tokens, pos)?))),
+        Some(Token::Op("-")) => Ok(Expr::Neg(Box::new(parse_unary(tokens, pos)?))),
+        Some(Token::Number(n)) => n
+            .parse::<i64>()
+            .map(Expr::Int)
+            .map_err(|_| format!("non-integer literal {}", n)),
+        Some(Token::Ident(name)) => Ok(match name.as_str() {
+            "true" | "True" => Expr::Bool(true),
+            "false" | "False" => Expr::Bool(false),
+            _ => Expr::Var(name),
+        }),
+        Some(Token::LParen) => {
+            let inner = parse_expression(tokens, pos, 0)?;
+            match tokens.get(*pos) {
+                Some(Token::RParen) => {
+                    *pos += 1;
+                    Ok(inner)
+                }
+                _ => Err("unbalanced parentheses in expression".to_string()),
+            }
+        }
+        Some(token) => Err(format!("unexpected token {:?} in expression", token)),
+        None => Err("unexpected end of expression".to_string()),
+    }
+}
+
+fn collect_vars(expr: &Expr, vars: &mut Vec<String>) {
+    match expr {
+        Expr::Var(name) => {
+            if !vars.contains(name) {
+                vars.push(name.clone());
+            }
+        }
+        Expr::Not(inner) | Expr::Neg(inner) => collect_vars(inner, vars),
+        Expr::Binary(_, lhs, rhs) => {
+            collect_vars(lhs, vars);
+            collect_vars(rhs, vars);
+        }
+        Expr::Int(_) | Expr::Bool(_) => {}
+    }
+}
+
+fn eval(expr: &Expr, assignment: &HashMap<&str, Value>) -> Result<Value, String> {
+    match expr {
+        Expr::Int(n) => Ok(Value::Int(*n)),
+        Expr::Bool(b) => Ok(Value::Bool(*b)),
+        Expr::Var(name) => assignment
+            .get(name.as_str())
+            .copied()
+            .ok_or_else(|| format!("unbound variable {}", name)),
+        Expr::Not(inner) => Ok(Value::Bool(!as_bool(eval(inner, assignment)?)?)),
+        Expr::Neg(inner) => as_int(eval(inner, assignment)?)?
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or_else(|| "integer overflow".to_string()),
+        Expr::Binary(op, lhs, rhs) => {
+            // Short-circuit so guards like `y != 0 => x / y > 0` are safe
+            match *op {
+                "and" => {
+                    return Ok(Value::Bool(
+                        as_bool(eval(lhs, assignment)?)? && as_bool(eval(rhs, assignment)?)?,
+                    ))
+                }
+                "or" => {
+                    return Ok(Value::Bool(
+                        as_bool(eval(lhs, assignment)?)? || as_bool(eval(rhs, assignment)?)?,
+                    ))
+                }
+                "=>" => {
+                    return Ok(Value::Bool(
+                        !as_bool(eval(lhs, assignment)?)? || as_bool(eval(rhs, assignment)?)?,
+                    ))
+                }
+                _ => {}
+            }
+
+            let a = eval(lhs, assignment)?;
+            let b = eval(rhs, assignment)?;
+            match *op {
+                "=" => Ok(Value::Bool(a == b)),
+                "distinct" => Ok(Value::Bool(a != b)),
+                "<" => Ok(Value::Bool(as_int(a)? < as_int(b)?)),
+                "<=" => Ok(Value::Bool(as_int(a)? <= as_int(b)?)),
+                ">" => Ok(Value::Bool(as_int(a)? > as_int(b)?)),
+                ">=" => Ok(Value::Bool(as_int(a)? >= as_int(b)?)),
+                "+" | "-" | "*" | "/" | "mod" => {
+                    let (a, b) = (as_int(a)?, as_int(b)?);
+                    let result = match *op {
+                        "+" => a.checked_add(b),
+                        "-" => a.checked_sub(b),
+                        "*" => a.checked_mul(b),
+                        "/" => a.checked_div_euclid(b),
+                        _ => a.checked_rem_euclid(b),
+                    };
+                    result
+                        .map(Value::Int)
+                        .ok_or_else(|| format!("arithmetic error evaluating {} {} {}", a, op, b))
+                }
+                other => Err(format!("unsupported operator {}", other)),
+            }
+        }
+    }
+}
+
+fn as_bool(value: Value) -> Result<bool, String> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        Value::Int(n) => Err(format!("expected boolean, found {}", n)),
+    }
+}
+
+fn as_int(value: Value) -> Result<i64, String> {
+    match value {
+        Value::Int(n) => Ok(n),
+        Value::Bool(b) => Err(format!("expected integer, found {}", b)),
+    }
+}
+
+fn format_value(value: Value) -> String {
+    match value {
+        Value::Int(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+    }
+}
+
+// Deterministic per invariant so sampled counterexamples are reproducible
+fn seed_for(invariant: &Invariant) -> u64 {
+    let hash = compute_content_hash(&format!("{}:{}", invariant.id, invariant.formal_expression));
+    u64::from_str_radix(&hash[..16], 16).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, var_type: &str, constraints: &[&str]) -> Variable {
+        Variable {
+            name: name.to_string(),
+            var_type: var_type.to_string(),
+            constraints: constraints.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn invariant(formal_expression: &str, variables: Vec<Variable>) -> Invariant {
+        Invariant {
+            id: "inv1".to_string(),
+            formal_expression: formal_expression.to_string(),
+            variables,
+            ..Default::default()
+        }
+    }
+
+    fn evaluator() -> InvariantEvaluator {
+        InvariantEvaluator::new(&ProofConfig::default())
+    }
+
+    #[test]
+    fn test_exhaustive_check_holds() {
+        let inv = invariant(
+            "retries * delay_ms <= 5000",
+            vec![
+                variable("retries", "Nat", &["retries <= 5"]),
+                variable("delay_ms", "Nat", &["delay_ms <= 1000"]),
+            ],
+        );
+
+        assert_eq!(evaluator().evaluate(&inv), EvaluationOutcome::Exhaustive { assignments: 6 * 1001 });
+    }
+
+    #[test]
+    fn test_counterexample_is_reported() {
+        let inv = invariant(
+            "enabled → count < 10",
+            vec![
+                variable("enabled", "Bool", &[]),
+                variable("count", "Nat", &["count <= 10"]),
+            ],
+        );
+
+        let (outcome, artifact) = evaluator().check(&inv);
+        assert_eq!(outcome, EvaluationOutcome::Counterexample("enabled = true, count = 10".to_string()));
+        assert_eq!(artifact.status, ProofStatus::Failed as i32);
+        assert_eq!(artifact.proof_strategy, "evaluation");
+    }
+
+    #[test]
+    fn test_constraints_filter_assignments() {
+        let inv = invariant(
+            "x / y <= x",
+            vec![
+                variable("x", "Nat", &["x < 20"]),
+                variable("y", "Nat", &["1 <= y && y <= 4"]),
+            ],
+        );
+
+        assert_eq!(evaluator().evaluate(&inv), EvaluationOutcome::Exhaustive { assignments: 20 * 4 });
+    }
+
+    #[test]
+    fn test_large_domains_are_sampled() {
+        let inv = invariant(
+            "a + b >= a",
+            vec![
+                variable("a", "Nat", &["a <= 100000"]),
+                variable("b", "Nat", &["b <= 100000"]),
+            ],
+        );
+
+        match evaluator().evaluate(&inv) {
+            EvaluationOutcome::Sampled { assignments } => assert!(assignments > 0),
+            other => panic!("expected sampled outcome, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unbounded_or_real_variables_are_not_applicable() {
+        let unbounded = invariant("n + 0 = n", vec![variable("n", "Nat", &[])]);
+        let real = invariant("x <= 1", vec![variable("x", "Real", &["x <= 1"])]);
+
+        assert!(matches!(evaluator().evaluate(&unbounded), EvaluationOutcome::NotApplicable(_)));
+        assert!(matches!(evaluator().evaluate(&real), EvaluationOutcome::NotApplicable(_)));
+    }
+}
@@ -1,31 +1,123 @@
+pub mod analytics;
+pub mod audit_bundle;
 pub mod claude_client;
 pub mod compiler;
+pub mod definitions;
+pub mod evaluator;
+pub mod model_router;
+pub mod persistence;
+pub mod plan;
 pub mod s3_storage;
 pub mod prompts;
+pub mod repair;
+pub mod runtime;
+pub mod smt;
+pub mod streaming;
+pub mod testgen;
+pub mod toolchain;
+pub mod transcripts;
+pub mod workspace;
 pub mod proto;
 
 use std::collections::HashMap;
-use std::error::Error;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use spec_to_proof_error::Error;
 use tokio::sync::RwLock;
 use tonic::{Request, Response, Status};
+use storage::{EntityStore, ExpectedVersion, Repository, StorageSettings};
+use cost_governance::{tenant, CostGovernanceConfig, CostGovernanceManager, LlmCallGovernor, ModelPricing};
+use notifications::{Notification, NotificationKind, NotificationSettings, Notifier, Severity};
+use health::{HealthChecker, HealthReport, HealthStatus};
+use circuit_breaker::CircuitBreaker;
+use export::{KmsManifestSigner, PullRequestRef, UploadedBundle};
+use prompt_registry::PromptRegistry;
+use reload::ConfigHandle;
+use pipeline_control::{PauseGate, Stage};
+use spec_to_proof_proto::pagination;
 
 use crate::proto::proof::v1::proof_service_server::ProofService as ProofServiceTrait;
 use crate::proto::proof::v1::*;
 use crate::proto::spec_to_proof::v1::*;
+use crate::runtime::RuntimeSettings;
 
+#[derive(Clone)]
 pub struct ProofConfig {
     pub claude_api_key: String,
     pub claude_model: String,
+    /// Cheaper model for invariants the router judges simple; every
+    /// invariant goes to `claude_model` when unset
+    pub simple_claude_model: Option<String>,
+    /// Most operators an invariant may have and still count as simple
+    pub routing_max_simple_operators: usize,
     pub max_tokens: u32,
     pub temperature: f32,
     pub max_retries: u32,
     pub retry_delay_ms: u64,
+    /// Lake project with `lean_toolchain` and Mathlib built, where generated
+    /// proofs are checked and, when they fail, repaired from the compiler
+    /// errors; proofs are accepted unchecked when unset
+    pub repair_lean_project: Option<std::path::PathBuf>,
+    /// Repair prompts sent per proof unless the request sets its own
+    pub repair_max_attempts: u32,
+    /// Tokens all repairs of one proof may use unless the request sets its
+    /// own
+    pub repair_token_budget: u32,
+    pub repair_check_timeout_ms: u64,
+    /// Stream model responses, failing a generation that has not produced
+    /// its Lean code after this many milliseconds; unset waits for the
+    /// whole completion
+    pub stream_deadline_ms: Option<u64>,
+    /// Redis for per-tenant LLM rate limits; cost governance is off when
+    /// unset
+    pub cost_governance_redis_url: Option<String>,
+    /// Redis holding the pipeline pauses set through the admin API; proving
+    /// is never paused when unset
+    pub pipeline_control_redis_url: Option<String>,
+    /// Fallback price for models without an entry in `model_pricing`
     pub cost_per_1k_tokens: f64,
+    /// Per-model prices overriding the built-in Claude price list
+    pub model_pricing: HashMap<String, ModelPricing>,
+    /// Lean toolchain theorems are generated for, e.g.
+    /// "leanprover/lean4:v4.7.0"
+    pub lean_toolchain: String,
+    /// Mathlib commit theorems are generated against; empty when unpinned
+    pub mathlib_commit: String,
+    /// Generate each set's theorems against a shared definitions module
+    /// instead of standalone; such theorems only check inside the set's
+    /// Lake workspace
+    pub shared_definitions: bool,
     pub s3_bucket: String,
     pub s3_region: String,
+    /// S3-compatible endpoint such as MinIO holding `s3_bucket`; AWS S3
+    /// when unset
+    pub s3_endpoint: Option<String>,
     pub s3_key_prefix: String,
     pub kms_key_id: Option<String>,
+    /// Encrypt theorems and transcripts with a per-object data key from
+    /// `kms_key_id` before they reach S3, rather than relying on SSE alone
+    pub client_side_encryption: bool,
+    /// Key prefix for proof attempt transcripts in `s3_bucket`
+    pub transcript_key_prefix: String,
+    /// Default lifetime of presigned URLs, capped at seven days
+    pub presigned_url_expiry_seconds: u64,
+    /// Asymmetric KMS key signing audit bundle manifests; audit export is
+    /// disabled when unset
+    pub audit_signing_key_id: Option<String>,
+    pub z3_path: String,
+    pub smt_timeout_ms: u64,
+    pub evaluation_exhaustive_limit: u64,
+    pub evaluation_sample_size: u64,
+    pub evaluation_timeout_ms: u64,
+    /// Prompt manifest overlaid on the built-in prompts: a file path or an
+    /// s3://bucket/key URI
+    pub prompt_manifest: Option<String>,
+    pub storage: StorageSettings,
+    /// Slack and Teams channels told about failed proofs of critical
+    /// invariants and tenant budget thresholds; none when unset
+    pub notifications: Option<NotificationSettings>,
+    /// Per-dependency timeout for readiness checks
+    pub health_check_timeout_ms: u64,
 }
 
 impl Default for ProofConfig {
@@ -33,110 +125,358 @@ impl Default for ProofConfig {
         Self {
             claude_api_key: String::new(),
             claude_model: "claude-3-opus-20240229".to_string(),
+            simple_claude_model: None,
+            routing_max_simple_operators: 4,
             max_tokens: 8000,
             temperature: 0.0, // Deterministic generation
             max_retries: 3,
             retry_delay_ms: 1000,
+            repair_lean_project: None,
+            repair_max_attempts: 3,
+            repair_token_budget: 32_000,
+            repair_check_timeout_ms: 120_000,
+            stream_deadline_ms: None,
+            cost_governance_redis_url: None,
+            pipeline_control_redis_url: None,
             cost_per_1k_tokens: 0.015, // Claude 3 Opus pricing
+            model_pricing: HashMap::new(),
+            lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+            mathlib_commit: String::new(),
+            shared_definitions: false,
             s3_bucket: "spec-to-proof-lean".to_string(),
             s3_region: "us-east-1".to_string(),
+            s3_endpoint: None,
             s3_key_prefix: "theorems/".to_string(),
             kms_key_id: None,
+            client_side_encryption: false,
+            transcript_key_prefix: "transcripts/".to_string(),
+            presigned_url_expiry_seconds: 900,
+            audit_signing_key_id: None,
+            z3_path: "z3".to_string(),
+            smt_timeout_ms: 5000,
+            evaluation_exhaustive_limit: 100_000,
+            evaluation_sample_size: 10_000,
+            evaluation_timeout_ms: 250,
+            prompt_manifest: None,
+            storage: StorageSettings::default(),
+            notifications: None,
+            health_check_timeout_ms: 2000,
         }
     }
 }
 
 pub struct ProofServiceImpl {
     config: ProofConfig,
-    claude_client: claude_client::ClaudeClient,
-    compiler: compiler::LeanCompiler,
-    s3_storage: s3_storage::S3Storage,
+    claude_client: Arc<claude_client::ClaudeClient>,
+    prompts: Arc<PromptRegistry>,
+    runtime: ConfigHandle<RuntimeSettings>,
+    // Built from the runtime settings generation it is tagged with
+    compiler: std::sync::RwLock<(u64, Arc<compiler::LeanCompiler>)>,
+    smt_solver: smt::SmtSolver,
+    evaluator: evaluator::InvariantEvaluator,
+    s3_storage: Arc<s3_storage::S3Storage>,
+    audit_signer: Option<KmsManifestSigner>,
+    governor: Option<Arc<LlmCallGovernor>>,
+    pause_gate: PauseGate,
+    theorem_repository: Arc<dyn Repository<LeanTheorem>>,
+    artifact_repository: Arc<dyn Repository<ProofArtifact>>,
+    invariant_repository: Arc<dyn Repository<analytics::InvariantRecord>>,
+    notifier: Option<Arc<Notifier>>,
+    health: HealthChecker,
     start_time: Instant,
 }
 
 impl ProofServiceImpl {
-    pub async fn new(config: ProofConfig) -> Result<Self, Box<dyn Error>> {
-        let claude_client = claude_client::ClaudeClient::new(&config.claude_api_key, &config.claude_model);
-        let compiler = compiler::LeanCompiler::new(&config);
-        let s3_storage = s3_storage::S3Storage::new(&config).await?;
+    pub async fn new(config: ProofConfig) -> Result<Self, Error> {
+        let claude_client = Arc::new(claude_client::ClaudeClient::new(&config.claude_api_key, &config.claude_model));
+        let mut prompts = prompts::builtin_registry();
+        if let Some(location) = &config.prompt_manifest {
+            prompts.apply_manifest(prompt_registry::load_manifest(location).await?)?;
+        }
+        let prompts = Arc::new(prompts);
+        let notifier = match &config.notifications {
+            Some(settings) => Some(Arc::new(Notifier::connect(settings).await?)),
+            None => None,
+        };
+        let mut governor = None;
+        if let Some(redis_url) = &config.cost_governance_redis_url {
+            let mut manager = CostGovernanceManager::connect(redis_url, CostGovernanceConfig::default()).await?;
+            if let Some(notifier) = &notifier {
+                manager = manager.with_notifier(notifier.clone());
+            }
+            governor = Some(Arc::new(LlmCallGovernor::new(
+                Arc::new(manager),
+                model_router::pricing_table(&config),
+            )));
+        }
+        let pause_gate = match &config.pipeline_control_redis_url {
+            Some(redis_url) => PauseGate::connect(redis_url)?,
+            None => PauseGate::disabled(),
+        };
+        let compiler = build_compiler(&config, &prompts, governor.as_ref());
+        let smt_solver = smt::SmtSolver::new(&config);
+        let evaluator = evaluator::InvariantEvaluator::new(&config);
+        let s3_storage = Arc::new(s3_storage::S3Storage::new(&config).await?);
+        let audit_signer = match &config.audit_signing_key_id {
+            Some(key_id) => {
+                let aws_config = aws_config::load_default_config(aws_config::BehaviorVersion::latest()).await;
+                Some(KmsManifestSigner::new(aws_sdk_kms::Client::new(&aws_config), key_id))
+            }
+            None => None,
+        };
+
+        let entity_store = EntityStore::connect(&config.storage).await?;
+        let theorem_repository = entity_store.repository::<LeanTheorem>();
+        let artifact_repository = entity_store.repository::<ProofArtifact>();
+        let invariant_repository = entity_store.repository::<analytics::InvariantRecord>();
+
+        let health = build_health_checker(
+            &config,
+            &claude_client,
+            &s3_storage,
+            &theorem_repository,
+            governor.as_ref(),
+        );
 
         Ok(Self {
+            runtime: ConfigHandle::new(RuntimeSettings::from_config(&config)),
             config,
             claude_client,
-            compiler,
+            prompts,
+            compiler: std::sync::RwLock::new((0, Arc::new(compiler))),
+            smt_solver,
+            evaluator,
             s3_storage,
+            audit_signer,
+            governor,
+            pause_gate,
+            theorem_repository,
+            artifact_repository,
+            invariant_repository,
+            notifier,
+            health,
             start_time: Instant::now(),
         })
     }
 
+    pub fn with_repositories(
+        mut self,
+        theorem_repository: Arc<dyn Repository<LeanTheorem>>,
+        artifact_repository: Arc<dyn Repository<ProofArtifact>>,
+    ) -> Self {
+        self.theorem_repository = theorem_repository;
+        self.artifact_repository = artifact_repository;
+        self.health = build_health_checker(
+            &self.config,
+            &self.claude_client,
+            &self.s3_storage,
+            &self.theorem_repository,
+            self.governor.as_ref(),
+        );
+        self
+    }
+
+    /// Reads model selection and LLM call limits from `runtime`, which a
+    /// `reload::ConfigWatcher` may swap while the service runs
+    pub fn with_runtime_settings(self, runtime: ConfigHandle<RuntimeSettings>) -> Self {
+        let service = Self { runtime, ..self };
+        // Force a rebuild from the new settings
+        service.compiler.write().unwrap().0 = u64::MAX;
+        service.compiler();
+        service
+    }
+
+    pub fn runtime_settings(&self) -> ConfigHandle<RuntimeSettings> {
+        self.runtime.clone()
+    }
+
+    // Rebuilt after each reload of the runtime settings; in-flight proofs
+    // finish on the compiler they started with
+    fn compiler(&self) -> Arc<compiler::LeanCompiler> {
+        let generation = self.runtime.generation();
+        {
+            let (built_for, compiler) = &*self.compiler.read().unwrap();
+            if *built_for == generation {
+                return compiler.clone();
+            }
+        }
+
+        let settings = self.runtime.current();
+        let compiler = Arc::new(build_compiler(&settings.apply(&self.config), &self.prompts, self.governor.as_ref()));
+        if let (Some(governor), Some(limits)) = (&self.governor, &settings.llm_limits) {
+            governor.set_limits(limits.clone());
+        }
+        *self.compiler.write().unwrap() = (generation, compiler.clone());
+        compiler
+    }
+
+    /// Refuses proof work for a tenant while proving is paused for it
+    pub async fn ensure_proving_allowed(&self, tenant_id: &str) -> Result<(), Status> {
+        match self.pause_gate.check(Stage::Proving, Some(tenant_id), None).await {
+            Some(pause) => Err(Status::unavailable(format!(
+                "Proving is paused for tenant {} by {}: {}",
+                tenant_id, pause.paused_by, pause.reason
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn check_health(&self, probe: HealthProbe) -> HealthReport {
+        match probe {
+            HealthProbe::Liveness => self.health.liveness(),
+            _ => self.health.readiness().await,
+        }
+    }
+
     pub async fn compile_invariant_set(
         &self,
         invariant_set: &InvariantSet,
         options: &CompilationOptions,
-    ) -> Result<Vec<LeanTheorem>, Box<dyn Error>> {
+    ) -> Result<Vec<LeanTheorem>, Error> {
         let start_time = Instant::now();
         
         tracing::info!("Compiling invariant set {} with {} invariants", 
             invariant_set.id, invariant_set.invariants.len());
 
+        let definitions = self.set_definitions(invariant_set)?;
+
         let mut theorems = Vec::new();
-        let mut total_input_tokens = 0;
-        let mut total_output_tokens = 0;
+        let mut estimated_cost = 0.0;
 
         for invariant in &invariant_set.invariants {
-            let theorem = self.compiler.compile_invariant_to_theorem(invariant, options).await?;
+            let theorem = self.compiler()
+                .compile_invariant_in_set(
+                    invariant,
+                    definitions.as_ref().map(|(module, lean)| (module.as_str(), lean.as_str())),
+                    options,
+                )
+                .await?;
             
-            // Track token usage
-            if let Some(token_usage) = &theorem.metadata.get("token_usage") {
-                if let Ok(usage) = serde_json::from_str::<HashMap<String, u32>>(token_usage) {
-                    total_input_tokens += usage.get("input_tokens").unwrap_or(&0);
-                    total_output_tokens += usage.get("output_tokens").unwrap_or(&0);
-                }
-            }
+            // Each theorem is priced at the model it was routed to
+            estimated_cost += theorem.metadata.get("estimated_cost_usd")
+                .and_then(|cost| cost.parse::<f64>().ok())
+                .unwrap_or(0.0);
             
             theorems.push(theorem);
         }
 
         let duration_ms = start_time.elapsed().as_millis() as u64;
-        let estimated_cost = self.estimate_cost(total_input_tokens, total_output_tokens);
 
         tracing::info!("Compiled {} theorems in {}ms, cost: ${:.4}", 
             theorems.len(), duration_ms, estimated_cost);
 
+        persistence::persist_theorems(self.theorem_repository.as_ref(), &theorems).await?;
+
         Ok(theorems)
     }
 
+    /// Plans a proof run over the set without calling the model or starting
+    /// Lean jobs
+    pub async fn plan_proof_run(
+        &self,
+        invariant_set: &InvariantSet,
+        compilation_options: &CompilationOptions,
+        proof_options: &ProofOptions,
+    ) -> Result<ProofRunPlan, Error> {
+        let definitions = self.set_definitions(invariant_set)?;
+        let compiler = self.compiler();
+        let planner = plan::ProofPlanner::new(
+            &compiler,
+            &self.config,
+            self.theorem_repository.as_ref(),
+            self.artifact_repository.as_ref(),
+        );
+
+        planner
+            .plan(
+                invariant_set,
+                definitions.as_ref().map(|(module, lean)| (module.as_str(), lean.as_str())),
+                compilation_options,
+                proof_options,
+            )
+            .await
+    }
+
+    /// Analytics over proofs attempted at or after `since`, grouped by each
+    /// of `dimensions` or by all of them when empty
+    pub async fn proof_analytics(
+        &self,
+        since: Option<std::time::SystemTime>,
+        dimensions: &[AnalyticsDimension],
+    ) -> Result<ProofAnalytics, Error> {
+        analytics::AnalyticsReader::new(self.artifact_repository.as_ref(), self.invariant_repository.as_ref())
+            .analytics(since, dimensions)
+            .await
+    }
+
+    // The shared definitions module and its Lean source, when theorems in a
+    // set import common definitions
+    fn set_definitions(&self, invariant_set: &InvariantSet) -> Result<Option<(String, String)>, Error> {
+        if !self.config.shared_definitions {
+            return Ok(None);
+        }
+        let package = workspace::package_name(&invariant_set.name);
+        Ok(Some((
+            format!("{}.{}", package, workspace::DEFINITIONS_MODULE),
+            definitions::SharedDefinitions::for_set(invariant_set)?.to_lean(&package),
+        )))
+    }
+
     pub async fn generate_proof(
         &self,
         theorem: &LeanTheorem,
         options: &ProofOptions,
-    ) -> Result<(LeanTheorem, ProofArtifact), Box<dyn Error>> {
+    ) -> Result<(LeanTheorem, ProofArtifact), Error> {
         let start_time = Instant::now();
         
         tracing::info!("Generating proof for theorem {}", theorem.theorem_name);
 
         let mut attempts = 0;
         let mut last_error = None;
+        let transcript = transcripts::TranscriptRecorder::default();
+        let portfolio = compiler::PortfolioOptions::for_request(options);
 
         while attempts < options.max_attempts {
             attempts += 1;
             
-            match self.compiler.generate_proof(theorem, options).await {
-                Ok((proven_theorem, proof_artifact)) => {
+            // A request naming no strategy races the portfolio's strategies,
+            // which needs a checker to tell which of them worked
+            let compiler = self.compiler();
+            let generation = if options.proof_strategy.is_empty() && compiler.has_checker() {
+                compiler.generate_proof_portfolio_recorded(theorem, options, &portfolio, &transcript).await
+            } else {
+                compiler.generate_proof_recorded(theorem, options, &transcript).await
+            };
+            match generation {
+                Ok((mut proven_theorem, mut proof_artifact)) => {
                     let duration_ms = start_time.elapsed().as_millis() as u64;
                     
                     tracing::info!("Proof generated successfully in {}ms after {} attempts", 
                         duration_ms, attempts);
+
+                    for metadata in [&mut proven_theorem.metadata, &mut proof_artifact.metadata] {
+                        metadata.insert("attempts".to_string(), attempts.to_string());
+                    }
+                    let transcript = transcript.into_transcript(&proof_artifact.id, &theorem.id);
+                    if let Some(location) = self.store_transcript(&transcript).await {
+                        transcripts::record_location(&mut proof_artifact.metadata, &location, transcript.attempts.len());
+                    }
                     
                     return Ok((proven_theorem, proof_artifact));
                 }
                 Err(e) => {
+                    // Another attempt cannot fix bad input or credentials
+                    let retryable = e.is_retryable();
+                    let retry_after = e.retry_after();
                     last_error = Some(e);
+                    if !retryable {
+                        break;
+                    }
                     
                     if attempts < options.max_attempts {
-                        let delay = Duration::from_millis(
+                        let delay = retry_after.unwrap_or_else(|| Duration::from_millis(
                             (self.config.retry_delay_ms * (2_u64.pow(attempts as u32 - 1))) as u64
-                        );
+                        ));
                         tracing::warn!("Proof attempt {} failed, retrying in {:?}: {}", 
                             attempts, delay, last_error.as_ref().unwrap());
                         tokio::time::sleep(delay).await;
@@ -145,7 +485,282 @@ impl ProofServiceImpl {
             }
         }
 
-        Err(last_error.unwrap_or_else(|| "All proof attempts failed".into()))
+        let last_error = last_error.unwrap_or_else(|| "All proof attempts failed".into());
+        let transcript = transcript.into_transcript(&compiler::failed_proof_artifact_id(theorem), &theorem.id);
+        let mut artifact = compiler::failed_proof_artifact(
+            theorem,
+            options,
+            &transcript,
+            &last_error,
+            attempts,
+            start_time.elapsed().as_millis() as u64,
+        );
+        let location = self.store_transcript(&transcript).await;
+        if let Some(location) = &location {
+            transcripts::record_location(&mut artifact.metadata, location, transcript.attempts.len());
+        }
+        // Failed proofs count towards proof analytics; losing one only skews
+        // them, so it does not hide the proof's own error
+        if let Err(e) = self.artifact_repository.put(&artifact, ExpectedVersion::Any).await {
+            tracing::warn!("Failed to store failed proof artifact {}: {}", artifact.id, e);
+        }
+        if location.is_some() {
+            return Err(last_error.context(format!("Transcript stored for artifact {}", artifact.id)));
+        }
+        Err(last_error)
+    }
+
+    /// ListLeanTheorems, paging through the stored theorems
+    pub async fn list_lean_theorems(
+        &self,
+        request: &spec_to_proof_proto::ListLeanTheoremsRequest,
+    ) -> Result<spec_to_proof_proto::ListLeanTheoremsResponse, Error> {
+        let response = pagination::list_lean_theorems(
+            self.theorem_repository.as_ref(),
+            request,
+            persistence::to_theorem_model,
+        ).await?;
+        Ok(response)
+    }
+
+    /// ListProofArtifacts, paging through the stored artifacts
+    pub async fn list_proof_artifacts(
+        &self,
+        request: &spec_to_proof_proto::ListProofArtifactsRequest,
+    ) -> Result<spec_to_proof_proto::ListProofArtifactsResponse, Error> {
+        let response = pagination::list_proof_artifacts(
+            self.artifact_repository.as_ref(),
+            request,
+            persistence::to_artifact_model,
+        ).await?;
+        Ok(response)
+    }
+
+    // Transcripts are diagnostic, so failing to store one never fails the
+    // proof it records
+    async fn store_transcript(&self, transcript: &transcripts::ProofTranscript) -> Option<String> {
+        if transcript.attempts.is_empty() {
+            return None;
+        }
+        match self.s3_storage.upload_transcript(transcript).await {
+            Ok(location) => Some(location),
+            Err(e) => {
+                tracing::warn!("Failed to store transcript for artifact {}: {}", transcript.artifact_id, e);
+                None
+            }
+        }
+    }
+
+    /// The transcript recorded for a proof artifact, either inline or as a
+    /// presigned S3 URL. None if no transcript was stored.
+    pub async fn get_proof_transcript(
+        &self,
+        artifact_id: &str,
+        presign: bool,
+        url_expiry: Option<Duration>,
+    ) -> Result<Option<get_proof_transcript_response::Transcript>, Error> {
+        if presign {
+            let presigned = self.s3_storage.presign_transcript(artifact_id, self.presign_expiry(url_expiry)).await?;
+            return Ok(presigned.map(|presigned| get_proof_transcript_response::Transcript::PresignedUrl(presigned.into())));
+        }
+
+        let transcript = self.s3_storage.download_transcript(artifact_id).await?;
+        Ok(transcript.map(|transcript| get_proof_transcript_response::Transcript::Content(transcript.into())))
+    }
+
+    /// A presigned URL for a theorem's Lean file or, for an artifact, its
+    /// stored transcript, with the object's s3:// location. None if the
+    /// theorem or artifact is unknown, or nothing of it is in S3.
+    pub async fn get_presigned_url(
+        &self,
+        target: &get_presigned_url_request::Target,
+        operation: PresignedOperation,
+        expiry: Option<Duration>,
+    ) -> Result<Option<(String, s3_storage::PresignedRequest)>, Error> {
+        let expires_in = self.presign_expiry(expiry);
+        match (target, operation) {
+            (get_presigned_url_request::Target::TheoremId(theorem_id), PresignedOperation::Put) => {
+                let Some(theorem) = self.theorem_repository.get(theorem_id).await? else {
+                    return Ok(None);
+                };
+                let location = self.s3_storage.theorem_upload_location(&theorem.entity);
+                let presigned = self.s3_storage.generate_presigned_put(&location, "text/plain", expires_in).await?;
+                Ok(Some((location, presigned)))
+            }
+            (get_presigned_url_request::Target::TheoremId(theorem_id), _) => {
+                let Some(theorem) = self.theorem_repository.get(theorem_id).await? else {
+                    return Ok(None);
+                };
+                let Some(location) = self.s3_storage.latest_theorem_location(&theorem.entity).await? else {
+                    return Ok(None);
+                };
+                let presigned = self.s3_storage.generate_presigned_get(&location, expires_in).await?;
+                Ok(Some((location, presigned)))
+            }
+            (get_presigned_url_request::Target::ArtifactId(_), PresignedOperation::Put) => {
+                Err(Error::invalid_input("Artifacts are written by the service and cannot be uploaded"))
+            }
+            (get_presigned_url_request::Target::ArtifactId(artifact_id), _) => {
+                let Some(artifact) = self.artifact_repository.get(artifact_id).await? else {
+                    return Ok(None);
+                };
+                let Some(location) = artifact.entity.metadata.get(transcripts::TRANSCRIPT_LOCATION_METADATA_KEY) else {
+                    return Ok(None);
+                };
+                let presigned = self.s3_storage.generate_presigned_get(location, expires_in).await?;
+                Ok(Some((location.clone(), presigned)))
+            }
+        }
+    }
+
+    /// Deletes the stored theorems of a toolchain no longer in use. The
+    /// toolchain theorems are currently generated for cannot be purged.
+    pub async fn purge_toolchain(&self, toolchain_key: &str) -> Result<usize, Error> {
+        if toolchain_key.is_empty() || toolchain_key.contains('/') {
+            return Err(Error::invalid_input(format!("Invalid toolchain key: {:?}", toolchain_key)));
+        }
+        if toolchain_key == toolchain::toolchain_key(&self.config.lean_toolchain, &self.config.mathlib_commit) {
+            return Err(Error::invalid_input(format!("Toolchain {} is in use and cannot be purged", toolchain_key)));
+        }
+        self.s3_storage.purge_toolchain(toolchain_key).await
+    }
+
+    /// Signs and uploads a bundle of the set's invariants, Lean sources
+    /// and stored proof attempts for auditors of a pull request
+    pub async fn export_audit_bundle(
+        &self,
+        pull_request: PullRequestRef,
+        invariant_set: &InvariantSet,
+    ) -> Result<UploadedBundle, Error> {
+        let signer = self.audit_signer.as_ref().ok_or("Audit export requires an audit signing key")?;
+
+        let mut theorems = Vec::new();
+        let mut artifacts = Vec::new();
+        for invariant in &invariant_set.invariants {
+            theorems.extend(persistence::load_for_invariant(self.theorem_repository.as_ref(), &invariant.id).await?);
+            artifacts.extend(persistence::load_for_invariant(self.artifact_repository.as_ref(), &invariant.id).await?);
+        }
+
+        let bundle = audit_bundle::build_bundle(pull_request, invariant_set, &theorems, &artifacts)?
+            .finish(signer, chrono::Utc::now())
+            .await?;
+        tracing::info!(
+            "Exporting audit bundle {} with {} entries",
+            bundle.manifest.bundle_id,
+            bundle.manifest.entries.len()
+        );
+        Ok(self.s3_storage.audit_bundle_store().upload(&bundle).await?)
+    }
+
+    fn presign_expiry(&self, requested: Option<Duration>) -> Duration {
+        requested.unwrap_or_else(|| Duration::from_secs(self.config.presigned_url_expiry_seconds))
+    }
+
+    // Proves a single invariant end to end. Invariants over small finite
+    // domains are first evaluated natively, which can fail fast with a
+    // counterexample. Invariants tagged as simple arithmetic constraints go
+    // to the SMT backend next; anything the solver cannot settle falls back
+    // to Lean compilation and proof search. The returned theorem is None when
+    // evaluation or SMT settled the invariant.
+    pub async fn prove_invariant(
+        &self,
+        invariant: &Invariant,
+        compilation_options: &CompilationOptions,
+        proof_options: &ProofOptions,
+    ) -> Result<(Option<LeanTheorem>, ProofArtifact), Error> {
+        let (evaluation, evaluation_artifact) = self.evaluator.check(invariant);
+        match &evaluation {
+            evaluator::EvaluationOutcome::Counterexample(witness) => {
+                tracing::warn!("Evaluation found a counterexample for invariant {}: {}", invariant.id, witness);
+                persistence::persist_proof_result(
+                    self.theorem_repository.as_ref(),
+                    self.artifact_repository.as_ref(),
+                    None,
+                    &evaluation_artifact,
+                ).await?;
+                self.notify_proof_failure(invariant, "counterexample", witness).await;
+                return Ok((None, evaluation_artifact));
+            }
+            evaluator::EvaluationOutcome::NotApplicable(reason) => {
+                tracing::debug!("Invariant {} is not finitely checkable: {}", invariant.id, reason);
+            }
+            _ => {
+                tracing::info!("Invariant {} passed pre-proof evaluation: {}", invariant.id, evaluation_artifact.output);
+            }
+        }
+
+        let (theorem, mut artifact) = match self.prove_with_backends(invariant, compilation_options, proof_options).await {
+            Ok(result) => result,
+            Err(e) => {
+                self.notify_proof_failure(invariant, "error", &e.to_string()).await;
+                return Err(e);
+            }
+        };
+        evaluator::record_evidence(&evaluation, &mut artifact.metadata);
+        if let Some(status) = failed_status(artifact.status) {
+            self.notify_proof_failure(invariant, status, &artifact.output).await;
+        }
+
+        persistence::persist_proof_result(
+            self.theorem_repository.as_ref(),
+            self.artifact_repository.as_ref(),
+            theorem.as_ref(),
+            &artifact,
+        ).await?;
+
+        Ok((theorem, artifact))
+    }
+
+    // Only critical invariants page anyone; other failures show up on the
+    // badge and in coverage
+    async fn notify_proof_failure(&self, invariant: &Invariant, status: &str, reason: &str) {
+        let Some(notifier) = &self.notifier else {
+            return;
+        };
+        if invariant.priority != Priority::Critical as i32 {
+            return;
+        }
+        let notification = Notification::new(NotificationKind::ProofFailed, Severity::Critical)
+            .with_tenant(&tenant::current_tenant())
+            .with_field("priority", "critical")
+            .with_field("invariant_id", &invariant.id)
+            .with_field("document_id", &invariant.source_document_id)
+            .with_field("description", &invariant.description)
+            .with_field("status", status)
+            .with_field("reason", reason);
+        notifier.notify(&notification).await;
+    }
+
+    async fn prove_with_backends(
+        &self,
+        invariant: &Invariant,
+        compilation_options: &CompilationOptions,
+        proof_options: &ProofOptions,
+    ) -> Result<(Option<LeanTheorem>, ProofArtifact), Error> {
+        if smt::SmtSolver::is_candidate(invariant) {
+            match self.smt_solver.prove(invariant).await {
+                Ok((smt::SmtOutcome::Proven, artifact)) => {
+                    tracing::info!("Invariant {} proven by SMT in {}ms", invariant.id, artifact.duration_ms);
+                    return Ok((None, artifact));
+                }
+                Ok((smt::SmtOutcome::Counterexample(model), artifact)) => {
+                    // A counterexample is a definitive answer; Lean won't do better
+                    tracing::warn!("SMT found a counterexample for invariant {}: {}", invariant.id, model);
+                    return Ok((None, artifact));
+                }
+                Ok((smt::SmtOutcome::Unknown(reason), _)) => {
+                    tracing::info!("SMT inconclusive for invariant {} ({}), falling back to Lean", invariant.id, reason);
+                }
+                Err(e) => {
+                    tracing::warn!("SMT backend failed for invariant {}, falling back to Lean: {}", invariant.id, e);
+                }
+            }
+        }
+
+        let theorem = self.compiler().compile_invariant_to_theorem(invariant, compilation_options).await?;
+        let (proven_theorem, proof_artifact) = self.generate_proof(&theorem, proof_options).await?;
+
+        Ok((Some(proven_theorem), proof_artifact))
     }
 
     pub async fn stream_lean_code(
@@ -153,7 +768,7 @@ impl ProofServiceImpl {
         theorem: &LeanTheorem,
         s3_config: &S3Config,
         versioning: &VersioningOptions,
-    ) -> Result<StreamLeanCodeResponse, Box<dyn Error>> {
+    ) -> Result<StreamLeanCodeResponse, Error> {
         let start_time = Instant::now();
         
         tracing::info!("Streaming Lean code for theorem {} to S3", theorem.theorem_name);
@@ -199,26 +814,118 @@ impl ProofServiceImpl {
         })
     }
 
-    fn estimate_cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
-        let total_tokens = input_tokens + output_tokens;
-        (total_tokens as f64 / 1000.0) * self.config.cost_per_1k_tokens
-    }
-
     fn get_uptime_seconds(&self) -> u64 {
         self.start_time.elapsed().as_secs()
     }
 }
 
+// Readiness checks: the Claude API, the theorem bucket and the entity store
+// are required to prove anything; Redis only backs cost governance
+fn build_compiler(
+    config: &ProofConfig,
+    prompts: &Arc<PromptRegistry>,
+    governor: Option<&Arc<LlmCallGovernor>>,
+) -> compiler::LeanCompiler {
+    let compiler = compiler::LeanCompiler::new(config).with_prompt_registry(prompts.clone());
+    match governor {
+        Some(governor) => compiler.with_cost_governance(governor.clone()),
+        None => compiler,
+    }
+}
+
+fn build_health_checker(
+    config: &ProofConfig,
+    claude_client: &Arc<claude_client::ClaudeClient>,
+    s3_storage: &Arc<s3_storage::S3Storage>,
+    theorem_repository: &Arc<dyn Repository<LeanTheorem>>,
+    governor: Option<&Arc<LlmCallGovernor>>,
+) -> HealthChecker {
+    let claude_client = claude_client.clone();
+    let s3_storage = s3_storage.clone();
+    let theorem_repository = theorem_repository.clone();
+    let mut checker = HealthChecker::new(Duration::from_millis(config.health_check_timeout_ms))
+        .with_check("claude_api", true, move || {
+            let claude_client = claude_client.clone();
+            async move { claude_client.ping().await.map_err(|e| e.to_string()) }
+        })
+        // Open while recent Claude calls are failing; extractions fail fast
+        // until it half-opens
+        .with_check("claude_circuit", false, || {
+            let breaker = CircuitBreaker::shared(claude_client::CLAUDE_CIRCUIT);
+            async move { breaker.health_check() }
+        })
+        .with_check("s3", true, move || {
+            let s3_storage = s3_storage.clone();
+            async move { s3_storage.check_bucket().await.map_err(|e| e.to_string()) }
+        })
+        .with_check("storage", true, move || {
+            let theorem_repository = theorem_repository.clone();
+            async move { theorem_repository.ping().await.map_err(|e| e.to_string()) }
+        });
+    if let Some(governor) = governor {
+        let governor = governor.clone();
+        checker = checker.with_check("redis", false, move || {
+            let governor = governor.clone();
+            async move { governor.ping().await.map_err(|e| e.to_string()) }
+        });
+    }
+    checker
+}
+
+fn service_status(status: HealthStatus) -> ServiceStatus {
+    match status {
+        HealthStatus::Healthy => ServiceStatus::Healthy,
+        HealthStatus::Degraded => ServiceStatus::Degraded,
+        HealthStatus::Unhealthy => ServiceStatus::Unhealthy,
+    }
+}
+
+// LLM spend is billed to the tenant named in the request metadata
+fn failed_status(status: i32) -> Option<&'static str> {
+    match ProofStatus::try_from(status) {
+        Ok(ProofStatus::Failed) => Some("failed"),
+        Ok(ProofStatus::Timeout) => Some("timeout"),
+        Ok(ProofStatus::Error) => Some("error"),
+        _ => None,
+    }
+}
+
+fn request_tenant<T>(request: &Request<T>) -> String {
+    tenant::tenant_from_metadata(
+        request.metadata().get(tenant::TENANT_METADATA_KEY).and_then(|value| value.to_str().ok()),
+    )
+}
+
 #[tonic::async_trait]
 impl ProofServiceTrait for ProofServiceImpl {
     async fn compile_invariant_set(
         &self,
         request: Request<CompileInvariantSetRequest>,
     ) -> Result<Response<CompileInvariantSetResponse>, Status> {
+        let tenant_id = request_tenant(&request);
         let req = request.into_inner();
         let start_time = Instant::now();
+        let invariant_set = req.invariant_set.unwrap_or_default();
+        let options = req.options.unwrap_or_default();
+
+        if options.dry_run {
+            let proof_options = ProofOptions {
+                max_attempts: self.config.max_retries,
+                ..Default::default()
+            };
+            let plan = self.plan_proof_run(&invariant_set, &options, &proof_options).await
+                .map_err(|e| Status::from(e.context("Planning failed")))?;
+            return Ok(Response::new(CompileInvariantSetResponse {
+                theorems: Vec::new(),
+                metadata: None,
+                errors: Vec::new(),
+                plan: Some(plan),
+            }));
+        }
 
-        match self.compile_invariant_set(&req.invariant_set.unwrap(), &req.options.unwrap()).await {
+        self.ensure_proving_allowed(&tenant_id).await?;
+        let compilation = self.compile_invariant_set(&invariant_set, &options);
+        match tenant::scope(tenant_id, compilation).await {
             Ok(theorems) => {
                 let duration_ms = start_time.elapsed().as_millis() as u64;
                 
@@ -233,13 +940,14 @@ impl ProofServiceTrait for ProofServiceImpl {
                     theorems,
                     metadata: Some(metadata),
                     errors: Vec::new(),
+                    plan: None,
                 };
 
                 Ok(Response::new(response))
             }
             Err(e) => {
                 tracing::error!("Failed to compile invariant set: {}", e);
-                Err(Status::internal(format!("Compilation failed: {}", e)))
+                Err(Status::from(e.context("Compilation failed")))
             }
         }
     }
@@ -248,10 +956,26 @@ impl ProofServiceTrait for ProofServiceImpl {
         &self,
         request: Request<GenerateProofRequest>,
     ) -> Result<Response<GenerateProofResponse>, Status> {
+        let tenant_id = request_tenant(&request);
         let req = request.into_inner();
         let start_time = Instant::now();
 
-        match self.generate_proof(&req.theorem.unwrap(), &req.options.unwrap()).await {
+        self.ensure_proving_allowed(&tenant_id).await?;
+        let options = req.options.unwrap_or_default();
+        let generation = async {
+            match (req.invariant, req.theorem) {
+                (Some(invariant), _) => {
+                    let compilation_options = req.compilation_options.unwrap_or_default();
+                    self.prove_invariant(&invariant, &compilation_options, &options).await
+                }
+                (None, Some(theorem)) => self
+                    .generate_proof(&theorem, &options)
+                    .await
+                    .map(|(theorem, proof_artifact)| (Some(theorem), proof_artifact)),
+                (None, None) => Err(Error::invalid_input("GenerateProof needs a theorem or an invariant")),
+            }
+        };
+        match tenant::scope(tenant_id, generation).await {
             Ok((theorem, proof_artifact)) => {
                 let duration_ms = start_time.elapsed().as_millis() as u64;
                 
@@ -264,7 +988,7 @@ impl ProofServiceTrait for ProofServiceImpl {
                 };
 
                 let response = GenerateProofResponse {
-                    theorem: Some(theorem),
+                    theorem,
                     proof_artifact: Some(proof_artifact),
                     metadata: Some(metadata),
                 };
@@ -273,7 +997,7 @@ impl ProofServiceTrait for ProofServiceImpl {
             }
             Err(e) => {
                 tracing::error!("Failed to generate proof: {}", e);
-                Err(Status::internal(format!("Proof generation failed: {}", e)))
+                Err(Status::from(e.context("Proof generation failed")))
             }
         }
     }
@@ -293,20 +1017,232 @@ impl ProofServiceTrait for ProofServiceImpl {
             }
             Err(e) => {
                 tracing::error!("Failed to stream Lean code: {}", e);
-                Err(Status::internal(format!("Streaming failed: {}", e)))
+                Err(Status::from(e.context("Streaming failed")))
             }
         }
     }
 
+    async fn get_proof_transcript(
+        &self,
+        request: Request<GetProofTranscriptRequest>,
+    ) -> Result<Response<GetProofTranscriptResponse>, Status> {
+        let req = request.into_inner();
+        if req.artifact_id.is_empty() {
+            return Err(Status::invalid_argument("artifact_id is required"));
+        }
+
+        if req.presign && self.s3_storage.client_side_encrypted() {
+            return Err(Status::failed_precondition("Transcripts are encrypted client-side; fetch the content instead"));
+        }
+
+        let url_expiry = (req.url_expiry_seconds > 0).then(|| Duration::from_secs(req.url_expiry_seconds as u64));
+        match self.get_proof_transcript(&req.artifact_id, req.presign, url_expiry).await {
+            Ok(Some(transcript)) => Ok(Response::new(GetProofTranscriptResponse {
+                artifact_id: req.artifact_id,
+                transcript: Some(transcript),
+            })),
+            Ok(None) => Err(Status::not_found(format!("No transcript for artifact {}", req.artifact_id))),
+            Err(e) => {
+                tracing::error!("Failed to fetch transcript for artifact {}: {}", req.artifact_id, e);
+                Err(Status::from(e.context("Transcript retrieval failed")))
+            }
+        }
+    }
+
+    async fn get_presigned_url(
+        &self,
+        request: Request<GetPresignedUrlRequest>,
+    ) -> Result<Response<GetPresignedUrlResponse>, Status> {
+        let req = request.into_inner();
+        let operation = req.operation();
+        let target = match req.target {
+            Some(get_presigned_url_request::Target::TheoremId(id)) if id.is_empty() => None,
+            Some(get_presigned_url_request::Target::ArtifactId(id)) if id.is_empty() => None,
+            target => target,
+        };
+        let Some(target) = target else {
+            return Err(Status::invalid_argument("theorem_id or artifact_id is required"));
+        };
+        if matches!(target, get_presigned_url_request::Target::ArtifactId(_)) && operation == PresignedOperation::Put {
+            return Err(Status::invalid_argument("Artifacts can only be presigned for download"));
+        }
+
+        if self.s3_storage.client_side_encrypted() {
+            return Err(Status::failed_precondition("Presigned URLs are unavailable with client-side encryption"));
+        }
+
+        let expiry = (req.expiry_seconds > 0).then(|| Duration::from_secs(req.expiry_seconds as u64));
+        match self.get_presigned_url(&target, operation, expiry).await {
+            Ok(Some((s3_location, presigned))) => Ok(Response::new(GetPresignedUrlResponse {
+                s3_location,
+                url: Some(presigned.into()),
+            })),
+            Ok(None) => Err(Status::not_found(format!("No stored object for {:?}", target))),
+            Err(e) => {
+                tracing::error!("Failed to presign {:?}: {}", target, e);
+                Err(Status::from(e.context("Presigning failed")))
+            }
+        }
+    }
+
+    async fn purge_toolchain(
+        &self,
+        request: Request<PurgeToolchainRequest>,
+    ) -> Result<Response<PurgeToolchainResponse>, Status> {
+        let toolchain_key = request.into_inner().toolchain_key;
+        match self.purge_toolchain(&toolchain_key).await {
+            Ok(purged) => Ok(Response::new(PurgeToolchainResponse { purged_objects: purged as u32 })),
+            Err(e) => {
+                tracing::error!("Failed to purge toolchain {}: {}", toolchain_key, e);
+                Err(Status::from(e))
+            }
+        }
+    }
+
+    async fn export_audit_bundle(
+        &self,
+        request: Request<ExportAuditBundleRequest>,
+    ) -> Result<Response<ExportAuditBundleResponse>, Status> {
+        let req = request.into_inner();
+        let invariant_set = req.invariant_set
+            .ok_or_else(|| Status::invalid_argument("Missing invariant_set"))?;
+        if req.repository.is_empty() || req.pull_request == 0 {
+            return Err(Status::invalid_argument("Missing repository or pull request"));
+        }
+        let pull_request = PullRequestRef {
+            repository: req.repository,
+            number: req.pull_request,
+            head_sha: req.head_sha,
+        };
+
+        match self.export_audit_bundle(pull_request, &invariant_set).await {
+            Ok(uploaded) => Ok(Response::new(ExportAuditBundleResponse {
+                s3_location: format!("s3://{}/{}", uploaded.bucket, uploaded.key),
+                bundle_id: uploaded.bundle_id,
+                archive_sha256: uploaded.archive_sha256,
+                url: Some(PresignedUrl {
+                    url: uploaded.download_url,
+                    expires_at: Some(prost_types::Timestamp::from(std::time::SystemTime::from(uploaded.expires_at))),
+                    method: "GET".to_string(),
+                    headers: HashMap::new(),
+                }),
+            })),
+            Err(e) => {
+                tracing::error!("Failed to export audit bundle for invariant set {}: {}", invariant_set.id, e);
+                Err(Status::from(e))
+            }
+        }
+    }
+
+    async fn plan_proof_run(
+        &self,
+        request: Request<PlanProofRunRequest>,
+    ) -> Result<Response<PlanProofRunResponse>, Status> {
+        let req = request.into_inner();
+        let invariant_set = req.invariant_set.unwrap_or_default();
+        let compilation_options = req.compilation_options.unwrap_or_default();
+        let proof_options = req.proof_options.unwrap_or_default();
+
+        match self.plan_proof_run(&invariant_set, &compilation_options, &proof_options).await {
+            Ok(plan) => Ok(Response::new(PlanProofRunResponse { plan: Some(plan) })),
+            Err(e) => {
+                tracing::error!("Failed to plan proof run for invariant set {}: {}", invariant_set.id, e);
+                Err(Status::from(e.context("Planning failed")))
+            }
+        }
+    }
+
+    async fn generate_test_scaffolds(
+        &self,
+        request: Request<GenerateTestScaffoldsRequest>,
+    ) -> Result<Response<GenerateTestScaffoldsResponse>, Status> {
+        let req = request.into_inner();
+        let invariant_set = req.invariant_set
+            .ok_or_else(|| Status::invalid_argument("Missing invariant_set"))?;
+        let frameworks = req.frameworks
+            .iter()
+            .map(|framework| {
+                TestFramework::try_from(*framework)
+                    .map_err(|_| Status::invalid_argument(format!("Unknown test framework {}", framework)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let scaffolds = testgen::generate_scaffolds(&invariant_set, &frameworks, req.cases);
+        tracing::info!(
+            "Generated {} test scaffolds for invariant set {}",
+            scaffolds.len(),
+            invariant_set.id
+        );
+        Ok(Response::new(GenerateTestScaffoldsResponse {
+            patch: testgen::suggestion_patch(&scaffolds),
+            scaffolds,
+        }))
+    }
+
+    async fn get_proof_analytics(
+        &self,
+        request: Request<GetProofAnalyticsRequest>,
+    ) -> Result<Response<GetProofAnalyticsResponse>, Status> {
+        let req = request.into_inner();
+        let since = req.since
+            .map(std::time::SystemTime::try_from)
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("Invalid since: {}", e)))?;
+        let dimensions = req.dimensions
+            .iter()
+            .map(|dimension| {
+                AnalyticsDimension::try_from(*dimension)
+                    .map_err(|_| Status::invalid_argument(format!("Unknown analytics dimension {}", dimension)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let analytics = self.proof_analytics(since, &dimensions)
+            .await
+            .map_err(|e| Status::from(e.context("Failed to compute proof analytics")))?;
+        Ok(Response::new(GetProofAnalyticsResponse { analytics: Some(analytics) }))
+    }
+
+    async fn list_lean_theorems(
+        &self,
+        request: Request<ListLeanTheoremsRequest>,
+    ) -> Result<Response<ListLeanTheoremsResponse>, Status> {
+        let request = persistence::to_shared(&request.into_inner()).map_err(Error::from)?;
+        let response = self.list_lean_theorems(&request).await
+            .map_err(|e| Status::from(e.context("Listing theorems failed")))?;
+        Ok(Response::new(persistence::to_shared(&response).map_err(Error::from)?))
+    }
+
+    async fn list_proof_artifacts(
+        &self,
+        request: Request<ListProofArtifactsRequest>,
+    ) -> Result<Response<ListProofArtifactsResponse>, Status> {
+        let request = persistence::to_shared(&request.into_inner()).map_err(Error::from)?;
+        let response = self.list_proof_artifacts(&request).await
+            .map_err(|e| Status::from(e.context("Listing proof artifacts failed")))?;
+        Ok(Response::new(persistence::to_shared(&response).map_err(Error::from)?))
+    }
+
     async fn health_check(
         &self,
-        _request: Request<HealthCheckRequest>,
+        request: Request<HealthCheckRequest>,
     ) -> Result<Response<HealthCheckResponse>, Status> {
+        let report = self.check_health(request.into_inner().probe()).await;
+        if !report.is_ready() {
+            tracing::warn!("Health check failed: {:?}", report.checks);
+        }
+
         let response = HealthCheckResponse {
-            status: ServiceStatus::Healthy as i32,
+            status: service_status(report.status) as i32,
             version: env!("CARGO_PKG_VERSION").to_string(),
             uptime_seconds: self.get_uptime_seconds(),
             checked_at: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+            dependencies: report.checks.into_iter().map(|check| DependencyCheck {
+                name: check.name,
+                status: service_status(check.status) as i32,
+                critical: check.critical,
+                latency_ms: check.latency_ms,
+                message: check.message.unwrap_or_default(),
+            }).collect(),
         };
 
         Ok(Response::new(response))
@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+
+use cost_governance::PricingTable;
+
+use crate::evaluator::{self, Expr};
+use crate::ProofConfig;
+use crate::proto::spec_to_proof::v1::*;
+
+// Routes each invariant to a model by how hard its formal expression looks.
+// Quantifier-free linear arithmetic with a handful of operators rarely needs
+// the strongest model, so it goes to the cheaper one when one is configured;
+// anything the evaluator's parser cannot read (quantifiers, set notation,
+// function symbols) or that is nonlinear stays on the default model.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelTier {
+    Simple,
+    Complex,
+}
+
+impl ModelTier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelTier::Simple => "simple",
+            ModelTier::Complex => "complex",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComplexityFeatures {
+    pub expression_length: usize,
+    /// Whether the expression is in the quantifier-free arithmetic and
+    /// boolean fragment the evaluator parses
+    pub parsed: bool,
+    pub operators: usize,
+    pub depth: usize,
+    pub variables: usize,
+    pub nonlinear: bool,
+}
+
+impl ComplexityFeatures {
+    pub fn of(expression: &str) -> Self {
+        let mut features = Self {
+            expression_length: expression.chars().count(),
+            ..Default::default()
+        };
+        if let Ok(expr) = evaluator::parse(expression) {
+            let mut variables = HashSet::new();
+            features.parsed = true;
+            features.depth = walk(&expr, &mut features, &mut variables);
+            features.variables = variables.len();
+        }
+        features
+    }
+}
+
+// Counts operators and variables and returns the depth of the tree
+fn walk<'a>(expr: &'a Expr, features: &mut ComplexityFeatures, variables: &mut HashSet<&'a str>) -> usize {
+    match expr {
+        Expr::Int(_) | Expr::Bool(_) => 1,
+        Expr::Var(name) => {
+            variables.insert(name);
+            1
+        }
+        Expr::Not(inner) | Expr::Neg(inner) => {
+            features.operators += 1;
+            1 + walk(inner, features, variables)
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            features.operators += 1;
+            if matches!(*op, "*" | "/" | "mod") && mentions_variable(lhs) && mentions_variable(rhs) {
+                features.nonlinear = true;
+            }
+            1 + walk(lhs, features, variables).max(walk(rhs, features, variables))
+        }
+    }
+}
+
+fn mentions_variable(expr: &Expr) -> bool {
+    match expr {
+        Expr::Var(_) => true,
+        Expr::Int(_) | Expr::Bool(_) => false,
+        Expr::Not(inner) | Expr::Neg(inner) => mentions_variable(inner),
+        Expr::Binary(_, lhs, rhs) => mentions_variable(lhs) || mentions_variable(rhs),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingDecision {
+    pub model: String,
+    pub tier: ModelTier,
+    pub reason: String,
+    pub features: ComplexityFeatures,
+}
+
+impl RoutingDecision {
+    /// Records the decision and the features behind it, so routing can be
+    /// audited against proof outcomes
+    pub fn record(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert("model".to_string(), self.model.clone());
+        metadata.insert("model_tier".to_string(), self.tier.as_str().to_string());
+        metadata.insert("routing_reason".to_string(), self.reason.clone());
+        metadata.insert("complexity_operators".to_string(), self.features.operators.to_string());
+        metadata.insert("complexity_depth".to_string(), self.features.depth.to_string());
+        metadata.insert("complexity_variables".to_string(), self.features.variables.to_string());
+    }
+}
+
+pub struct ModelRouter {
+    complex_model: String,
+    simple_model: Option<String>,
+    max_simple_operators: usize,
+    pricing: PricingTable,
+}
+
+impl ModelRouter {
+    pub fn new(config: &ProofConfig) -> Self {
+        Self {
+            complex_model: config.claude_model.clone(),
+            simple_model: config.simple_claude_model.clone(),
+            max_simple_operators: config.routing_max_simple_operators,
+            pricing: pricing_table(config),
+        }
+    }
+
+    pub fn route(&self, invariant: &Invariant) -> RoutingDecision {
+        let features = ComplexityFeatures::of(&invariant.formal_expression);
+        let (tier, reason) = if !features.parsed {
+            (ModelTier::Complex, "quantifiers or syntax outside linear arithmetic".to_string())
+        } else if features.nonlinear {
+            (ModelTier::Complex, "nonlinear arithmetic".to_string())
+        } else if features.operators > self.max_simple_operators {
+            (
+                ModelTier::Complex,
+                format!("{} operators exceeds {}", features.operators, self.max_simple_operators),
+            )
+        } else {
+            (ModelTier::Simple, format!("linear arithmetic with {} operators", features.operators))
+        };
+
+        match (tier, &self.simple_model) {
+            (ModelTier::Simple, Some(simple_model)) => RoutingDecision {
+                model: simple_model.clone(),
+                tier,
+                reason,
+                features,
+            },
+            // Without a cheaper model everything runs on the default one
+            _ => RoutingDecision {
+                model: self.complex_model.clone(),
+                tier: ModelTier::Complex,
+                reason,
+                features,
+            },
+        }
+    }
+
+    /// The model for a theorem's proof: the one its statement was generated
+    /// with, or the default model for theorems from elsewhere
+    pub fn model_for_theorem(&self, theorem: &LeanTheorem) -> String {
+        theorem
+            .metadata
+            .get("model")
+            .filter(|model| Some(*model) == self.simple_model.as_ref())
+            .cloned()
+            .unwrap_or_else(|| self.complex_model.clone())
+    }
+
+    pub fn is_simple_model(&self, model: &str) -> bool {
+        self.simple_model.as_deref() == Some(model)
+    }
+
+    pub fn estimate_cost(&self, model: &str, input_tokens: u32, output_tokens: u32) -> f64 {
+        self.pricing.cost(model, input_tokens, output_tokens)
+    }
+}
+
+/// Built-in Claude prices overlaid with the configured ones, falling back to
+/// `cost_per_1k_tokens` for unknown models
+pub fn pricing_table(config: &ProofConfig) -> PricingTable {
+    let mut pricing = PricingTable::builtin(config.cost_per_1k_tokens);
+    for (model, model_pricing) in &config.model_pricing {
+        pricing.set(model, *model_pricing);
+    }
+    pricing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invariant(formal_expression: &str) -> Invariant {
+        Invariant {
+            id: "inv".to_string(),
+            formal_expression: formal_expression.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn router() -> ModelRouter {
+        ModelRouter::new(&ProofConfig {
+            simple_claude_model: Some("claude-3-haiku-20240307".to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_complexity_features() {
+        let features = ComplexityFeatures::of("refund <= charge && charge > 0");
+        assert!(features.parsed);
+        assert_eq!(features.operators, 3);
+        assert_eq!(features.depth, 3);
+        assert_eq!(features.variables, 2);
+        assert!(!features.nonlinear);
+
+        assert!(ComplexityFeatures::of("2 * x + 1 < 10").parsed);
+        assert!(!ComplexityFeatures::of("2 * x + 1 < 10").nonlinear);
+        assert!(ComplexityFeatures::of("x * y < 10").nonlinear);
+        assert!(!ComplexityFeatures::of("∀ x, P(x)").parsed);
+    }
+
+    #[test]
+    fn test_routing() {
+        let router = router();
+
+        let simple = router.route(&invariant("balance >= 0"));
+        assert_eq!(simple.tier, ModelTier::Simple);
+        assert_eq!(simple.model, "claude-3-haiku-20240307");
+
+        for expression in ["∀ x, x < limit", "x * y <= z", "a + b + c + d + e < f"] {
+            let complex = router.route(&invariant(expression));
+            assert_eq!(complex.tier, ModelTier::Complex, "{}", expression);
+            assert_eq!(complex.model, "claude-3-opus-20240229");
+        }
+
+        // No cheaper model configured
+        let decision = ModelRouter::new(&ProofConfig::default()).route(&invariant("balance >= 0"));
+        assert_eq!(decision.tier, ModelTier::Complex);
+    }
+
+    #[test]
+    fn test_proofs_stay_on_the_theorem_model() {
+        let router = router();
+        let mut theorem = LeanTheorem::default();
+        assert_eq!(router.model_for_theorem(&theorem), "claude-3-opus-20240229");
+
+        router.route(&invariant("balance >= 0")).record(&mut theorem.metadata);
+        assert_eq!(router.model_for_theorem(&theorem), "claude-3-haiku-20240307");
+
+        // A model that is no longer configured falls back to the default
+        theorem.metadata.insert("model".to_string(), "retired-model".to_string());
+        assert_eq!(router.model_for_theorem(&theorem), "claude-3-opus-20240229");
+    }
+}
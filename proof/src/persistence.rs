@@ -0,0 +1,189 @@
+use spec_to_proof_error::Error;
+use spec_to_proof_proto::pagination::PaginationError;
+use spec_to_proof_proto::{FromProto, LeanTheoremModel, ProofArtifactModel};
+use storage::{Entity, EntityQuery, ExpectedVersion, Repository, StorageError};
+
+use crate::proto::spec_to_proof::v1::{LeanTheorem, ProofArtifact};
+
+impl Entity for LeanTheorem {
+    const KIND: &'static str = "THEOREM";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        (!self.source_invariant_id.is_empty()).then(|| self.source_invariant_id.clone())
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+impl Entity for ProofArtifact {
+    const KIND: &'static str = "PROOF_ARTIFACT";
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn source_id(&self) -> Option<String> {
+        (!self.invariant_id.is_empty()).then(|| self.invariant_id.clone())
+    }
+
+    fn status(&self) -> i32 {
+        self.status
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.status = status;
+    }
+}
+
+/// Stores the latest compiled form of each theorem. Theorems are
+/// regenerated on every compilation, so writes are unconditional.
+pub async fn persist_theorems(
+    repository: &dyn Repository<LeanTheorem>,
+    theorems: &[LeanTheorem],
+) -> Result<(), Error> {
+    for theorem in theorems {
+        repository.put(theorem, ExpectedVersion::Any).await?;
+    }
+    Ok(())
+}
+
+pub async fn persist_proof_result(
+    theorem_repository: &dyn Repository<LeanTheorem>,
+    artifact_repository: &dyn Repository<ProofArtifact>,
+    theorem: Option<&LeanTheorem>,
+    artifact: &ProofArtifact,
+) -> Result<(), Error> {
+    if let Some(theorem) = theorem {
+        theorem_repository.put(theorem, ExpectedVersion::Any).await?;
+    }
+    artifact_repository.put(artifact, ExpectedVersion::Any).await?;
+    Ok(())
+}
+
+const QUERY_PAGE_SIZE: u32 = 100;
+
+/// Every stored theorem or artifact generated from an invariant
+pub async fn load_for_invariant<E: Entity>(
+    repository: &dyn Repository<E>,
+    invariant_id: &str,
+) -> Result<Vec<E>, Error> {
+    let query = EntityQuery::BySource(invariant_id.to_string());
+    let mut items = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let page = repository.query(&query, page_token.as_deref(), QUERY_PAGE_SIZE).await?;
+        items.extend(page.items.into_iter().map(|stored| stored.entity));
+        match page.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => return Ok(items),
+        }
+    }
+}
+
+// The service generates its own copy of the spec_to_proof.v1 messages; the
+// shared models are reached through the wire format both copies share
+pub(crate) fn to_shared<M: prost::Message, S: prost::Message + Default>(message: &M) -> Result<S, PaginationError> {
+    S::decode(message.encode_to_vec().as_slice()).map_err(|e| PaginationError::Storage(e.into()))
+}
+
+fn decode_error(error: Box<dyn std::error::Error>) -> PaginationError {
+    PaginationError::Storage(StorageError::Decode(error.to_string()))
+}
+
+/// The shared model of a stored theorem, as listed by ListLeanTheorems
+pub fn to_theorem_model(theorem: LeanTheorem) -> Result<LeanTheoremModel, PaginationError> {
+    LeanTheoremModel::from_proto(to_shared(&theorem)?).map_err(decode_error)
+}
+
+/// The shared model of a stored artifact, as listed by ListProofArtifacts
+pub fn to_artifact_model(artifact: ProofArtifact) -> Result<ProofArtifactModel, PaginationError> {
+    ProofArtifactModel::from_proto(to_shared(&artifact)?).map_err(decode_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::spec_to_proof::v1::{ProofStatus, TheoremStatus};
+    use spec_to_proof_proto::pagination::list_lean_theorems;
+    use storage::InMemoryRepository;
+
+    #[tokio::test]
+    async fn test_persist_proof_result_indexes_by_invariant() {
+        let theorems = InMemoryRepository::<LeanTheorem>::new();
+        let artifacts = InMemoryRepository::<ProofArtifact>::new();
+
+        let theorem = LeanTheorem {
+            id: "thm-1".to_string(),
+            source_invariant_id: "inv-1".to_string(),
+            status: TheoremStatus::Proven as i32,
+            ..Default::default()
+        };
+        let artifact = ProofArtifact {
+            id: "proof-1".to_string(),
+            theorem_id: "thm-1".to_string(),
+            invariant_id: "inv-1".to_string(),
+            status: ProofStatus::Success as i32,
+            ..Default::default()
+        };
+
+        persist_proof_result(&theorems, &artifacts, Some(&theorem), &artifact).await.unwrap();
+        persist_proof_result(&theorems, &artifacts, Some(&theorem), &artifact).await.unwrap();
+
+        assert_eq!(theorems.get("thm-1").await.unwrap().unwrap().version, 2);
+        let by_invariant = artifacts
+            .query(&EntityQuery::BySource("inv-1".to_string()), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(by_invariant.items.len(), 1);
+        assert_eq!(load_for_invariant(&artifacts, "inv-1").await.unwrap().len(), 1);
+
+        let succeeded = artifacts
+            .query(&EntityQuery::ByStatus(ProofStatus::Success as i32), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(succeeded.items[0].entity.id, "proof-1");
+    }
+
+    #[tokio::test]
+    async fn test_list_theorems_by_invariant() {
+        let theorems = InMemoryRepository::<LeanTheorem>::new();
+        for (id, invariant_id, status) in [
+            ("thm-1", "inv-1", TheoremStatus::Proven),
+            ("thm-2", "inv-1", TheoremStatus::Failed),
+            ("thm-3", "inv-2", TheoremStatus::Proven),
+        ] {
+            let theorem = LeanTheorem {
+                id: id.to_string(),
+                source_invariant_id: invariant_id.to_string(),
+                status: status as i32,
+                generated_at: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+                ..Default::default()
+            };
+            theorems.put(&theorem, ExpectedVersion::Any).await.unwrap();
+        }
+
+        let request = spec_to_proof_proto::ListLeanTheoremsRequest {
+            page_size: 1,
+            filter: "status = proven".to_string(),
+            ..Default::default()
+        };
+        let first = list_lean_theorems(&theorems, &request, to_theorem_model).await.unwrap();
+        assert_eq!(first.theorems[0].id, "thm-1");
+        assert_eq!(first.total_size, -1);
+
+        let request = spec_to_proof_proto::ListLeanTheoremsRequest { page_token: first.next_page_token, ..request };
+        let second = list_lean_theorems(&theorems, &request, to_theorem_model).await.unwrap();
+        assert_eq!(second.theorems[0].id, "thm-3");
+        assert!(second.next_page_token.is_empty());
+    }
+}
@@ -0,0 +1,316 @@
+use spec_to_proof_error::Error;
+use storage::Repository;
+
+use crate::compiler::{self, CallEstimate, LeanCompiler, INVARIANT_SHA256_METADATA_KEY};
+use crate::proto::proof::v1::*;
+use crate::proto::spec_to_proof::v1::*;
+use crate::smt::SmtSolver;
+use crate::ProofConfig;
+
+/// Works out what proving an invariant set would take from the stored
+/// theorems and artifacts: which invariants need a proof, which are already
+/// proven against the current toolchain, and the model and Lean work the
+/// rest would cost. Nothing is compiled, solved or built.
+pub struct ProofPlanner<'a> {
+    compiler: &'a LeanCompiler,
+    config: &'a ProofConfig,
+    theorem_repository: &'a dyn Repository<LeanTheorem>,
+    artifact_repository: &'a dyn Repository<ProofArtifact>,
+}
+
+impl<'a> ProofPlanner<'a> {
+    pub fn new(
+        compiler: &'a LeanCompiler,
+        config: &'a ProofConfig,
+        theorem_repository: &'a dyn Repository<LeanTheorem>,
+        artifact_repository: &'a dyn Repository<ProofArtifact>,
+    ) -> Self {
+        Self {
+            compiler,
+            config,
+            theorem_repository,
+            artifact_repository,
+        }
+    }
+
+    pub async fn plan(
+        &self,
+        invariant_set: &InvariantSet,
+        definitions: Option<(&str, &str)>,
+        compilation_options: &CompilationOptions,
+        proof_options: &ProofOptions,
+    ) -> Result<ProofRunPlan, Error> {
+        let config = self.config;
+        let max_attempts = proof_options.max_attempts.max(1);
+        let mut plan = ProofRunPlan {
+            estimated_token_usage: Some(TokenUsage::default()),
+            ..Default::default()
+        };
+        let mut lean_theorems = 0u32;
+
+        for invariant in &invariant_set.invariants {
+            let theorem_id = compiler::theorem_id(&invariant.id);
+            let theorem = self.theorem_repository
+                .get(&theorem_id)
+                .await?
+                .map(|stored| stored.entity)
+                .filter(|theorem| theorem_is_current(theorem, invariant, config));
+            let artifact = self.artifact_repository
+                .get(&compiler::artifact_id_for_theorem(&theorem_id))
+                .await?
+                .map(|stored| stored.entity);
+
+            let (action, reason) = decide(invariant, theorem.as_ref(), artifact.as_ref(), config);
+            let mut invariant_plan = InvariantPlan {
+                invariant_id: invariant.id.clone(),
+                action: action as i32,
+                reason,
+                theorem_cached: theorem.is_some(),
+                ..Default::default()
+            };
+            if action == PlannedAction::Skip {
+                plan.invariants.push(invariant_plan);
+                continue;
+            }
+
+            // A cached theorem only needs its proof; otherwise the proof prompt
+            // will also carry the statement the compilation produces
+            let (compilation, proof) = match &theorem {
+                Some(theorem) => (None, self.compiler.estimate_proof(theorem, 0, proof_options)?),
+                None => {
+                    let compilation = self.compiler.estimate_compilation(invariant, definitions, compilation_options)?;
+                    let stub = LeanTheorem {
+                        id: theorem_id.clone(),
+                        ..Default::default()
+                    };
+                    let proof = self.compiler.estimate_proof(&stub, compilation.output_tokens, proof_options)?;
+                    (Some(compilation), proof)
+                }
+            };
+            invariant_plan.model = compilation.as_ref().map_or(&proof.model, |c| &c.model).clone();
+
+            let lean_usage = total_usage(compilation.iter().chain([&proof]));
+            let lean_cost = compilation.as_ref().map_or(0.0, |c| c.cost_usd) + proof.cost_usd;
+            let max_cost = lean_cost + proof.cost_usd * (max_attempts - 1) as f64;
+            lean_theorems += 1;
+
+            if SmtSolver::is_candidate(invariant) {
+                invariant_plan.backend = "smt".to_string();
+                invariant_plan.reason.push_str("; falls back to Lean if the solver is inconclusive");
+                invariant_plan.estimated_token_usage = Some(TokenUsage::default());
+            } else {
+                invariant_plan.backend = "lean".to_string();
+                invariant_plan.estimated_cost_usd = lean_cost;
+                add_usage(plan.estimated_token_usage.as_mut(), &lean_usage);
+                plan.estimated_cost_usd += lean_cost;
+                invariant_plan.estimated_token_usage = Some(lean_usage);
+            }
+            plan.max_cost_usd += max_cost;
+            plan.invariants.push(invariant_plan);
+        }
+
+        plan.lean_resources = Some(LeanResources {
+            lean_toolchain: config.lean_toolchain.clone(),
+            mathlib_commit: config.mathlib_commit.clone(),
+            theorem_count: lean_theorems,
+            max_attempts,
+            max_duration_seconds: lean_theorems as u64 * max_attempts as u64 * proof_options.timeout_seconds as u64,
+        });
+        Ok(plan)
+    }
+}
+
+// Theorems are only reused when compiled from this exact invariant for the
+// configured toolchain
+fn theorem_is_current(theorem: &LeanTheorem, invariant: &Invariant, config: &ProofConfig) -> bool {
+    theorem.metadata.get(INVARIANT_SHA256_METADATA_KEY) == Some(&invariant.content_sha256)
+        && theorem.lean_toolchain == config.lean_toolchain
+        && theorem.mathlib_commit == config.mathlib_commit
+}
+
+fn decide(
+    invariant: &Invariant,
+    theorem: Option<&LeanTheorem>,
+    artifact: Option<&ProofArtifact>,
+    config: &ProofConfig,
+) -> (PlannedAction, String) {
+    let Some(artifact) = artifact else {
+        return (PlannedAction::Prove, "not proven yet".to_string());
+    };
+    if invariant.status == InvariantStatus::Stale as i32 {
+        return (PlannedAction::Reprove, "spec changed since the last proof".to_string());
+    }
+    if artifact.status != ProofStatus::Success as i32 {
+        let status = ProofStatus::try_from(artifact.status)
+            .map(|status| status.as_str_name())
+            .unwrap_or("PROOF_STATUS_UNSPECIFIED");
+        return (PlannedAction::Reprove, format!("last attempt ended {}", status));
+    }
+    if artifact.lean_toolchain != config.lean_toolchain || artifact.mathlib_commit != config.mathlib_commit {
+        return (
+            PlannedAction::Reprove,
+            format!(
+                "proven with {} (Mathlib {:?}), now {} (Mathlib {:?})",
+                artifact.lean_toolchain, artifact.mathlib_commit, config.lean_toolchain, config.mathlib_commit
+            ),
+        );
+    }
+    if theorem.is_none() {
+        return (PlannedAction::Reprove, "invariant changed since its proof".to_string());
+    }
+    (PlannedAction::Skip, format!("proven with {}", config.lean_toolchain))
+}
+
+fn total_usage<'a>(estimates: impl Iterator<Item = &'a CallEstimate>) -> TokenUsage {
+    let mut usage = TokenUsage::default();
+    for estimate in estimates {
+        usage.input_tokens += estimate.input_tokens;
+        usage.output_tokens += estimate.output_tokens;
+    }
+    usage.total_tokens = usage.input_tokens + usage.output_tokens;
+    usage
+}
+
+fn add_usage(total: Option<&mut TokenUsage>, usage: &TokenUsage) {
+    if let Some(total) = total {
+        total.input_tokens += usage.input_tokens;
+        total.output_tokens += usage.output_tokens;
+        total.total_tokens += usage.total_tokens;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use storage::{ExpectedVersion, InMemoryRepository};
+
+    fn invariant(id: &str, status: InvariantStatus) -> Invariant {
+        Invariant {
+            id: id.to_string(),
+            content_sha256: format!("sha-{}", id),
+            description: "Refunds never exceed the charge".to_string(),
+            formal_expression: "refund <= charge".to_string(),
+            status: status as i32,
+            ..Default::default()
+        }
+    }
+
+    fn theorem(invariant: &Invariant, config: &ProofConfig) -> LeanTheorem {
+        LeanTheorem {
+            id: compiler::theorem_id(&invariant.id),
+            lean_code: "theorem refund_bound (refund charge : Nat) (h : refund ≤ charge) : refund ≤ charge := by".to_string(),
+            source_invariant_id: invariant.id.clone(),
+            metadata: HashMap::from([(INVARIANT_SHA256_METADATA_KEY.to_string(), invariant.content_sha256.clone())]),
+            lean_toolchain: config.lean_toolchain.clone(),
+            mathlib_commit: config.mathlib_commit.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn artifact(theorem: &LeanTheorem, status: ProofStatus, toolchain: &str) -> ProofArtifact {
+        ProofArtifact {
+            id: compiler::proof_artifact_id(theorem),
+            theorem_id: theorem.id.clone(),
+            invariant_id: theorem.source_invariant_id.clone(),
+            status: status as i32,
+            lean_toolchain: toolchain.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_classifies_invariants_against_stored_proofs() {
+        let config = ProofConfig::default();
+        let compiler = LeanCompiler::new(&config);
+        let theorems = InMemoryRepository::<LeanTheorem>::new();
+        let artifacts = InMemoryRepository::<ProofArtifact>::new();
+
+        let proven = invariant("inv_proven", InvariantStatus::Proven);
+        let old_toolchain = invariant("inv_old_toolchain", InvariantStatus::Proven);
+        let failed = invariant("inv_failed", InvariantStatus::Failed);
+        let fresh = invariant("inv_new", InvariantStatus::Extracted);
+        for (invariant, status, toolchain) in [
+            (&proven, ProofStatus::Success, config.lean_toolchain.as_str()),
+            (&old_toolchain, ProofStatus::Success, "leanprover/lean4:v4.6.0"),
+            (&failed, ProofStatus::Failed, config.lean_toolchain.as_str()),
+        ] {
+            let theorem = theorem(invariant, &config);
+            theorems.put(&theorem, ExpectedVersion::Any).await.unwrap();
+            artifacts.put(&artifact(&theorem, status, toolchain), ExpectedVersion::Any).await.unwrap();
+        }
+
+        let set = InvariantSet {
+            id: "set-1".to_string(),
+            invariants: vec![proven.clone(), old_toolchain.clone(), failed.clone(), fresh.clone()],
+            ..Default::default()
+        };
+        let proof_options = ProofOptions {
+            max_tokens: 1000,
+            max_attempts: 3,
+            timeout_seconds: 60,
+            ..Default::default()
+        };
+        let compilation_options = CompilationOptions {
+            max_tokens: 500,
+            ..Default::default()
+        };
+        let planner = ProofPlanner::new(&compiler, &config, &theorems, &artifacts);
+        let plan = planner.plan(&set, None, &compilation_options, &proof_options).await.unwrap();
+
+        let actions: Vec<(i32, bool)> = plan.invariants.iter().map(|p| (p.action, p.theorem_cached)).collect();
+        assert_eq!(actions, vec![
+            (PlannedAction::Skip as i32, true),
+            (PlannedAction::Reprove as i32, true),
+            (PlannedAction::Reprove as i32, true),
+            (PlannedAction::Prove as i32, false),
+        ]);
+        assert!(plan.invariants[1].reason.contains("v4.6.0"));
+
+        // Only the new invariant is compiled; its proof prompt carries the
+        // statement's output budget on top of the template
+        let fresh_usage = plan.invariants[3].estimated_token_usage.clone().unwrap();
+        let cached_usage = plan.invariants[2].estimated_token_usage.clone().unwrap();
+        assert_eq!(fresh_usage.output_tokens, 1500);
+        assert_eq!(cached_usage.output_tokens, 1000);
+
+        let resources = plan.lean_resources.unwrap();
+        assert_eq!(resources.theorem_count, 3);
+        assert_eq!(resources.max_duration_seconds, 3 * 3 * 60);
+        assert!(plan.estimated_cost_usd > 0.0);
+        assert!(plan.max_cost_usd > plan.estimated_cost_usd);
+    }
+
+    #[tokio::test]
+    async fn test_edited_invariant_is_recompiled() {
+        let config = ProofConfig::default();
+        let compiler = LeanCompiler::new(&config);
+        let theorems = InMemoryRepository::<LeanTheorem>::new();
+        let artifacts = InMemoryRepository::<ProofArtifact>::new();
+
+        let original = invariant("inv_1", InvariantStatus::Proven);
+        let theorem = theorem(&original, &config);
+        theorems.put(&theorem, ExpectedVersion::Any).await.unwrap();
+        artifacts
+            .put(&artifact(&theorem, ProofStatus::Success, &config.lean_toolchain), ExpectedVersion::Any)
+            .await
+            .unwrap();
+
+        let edited = Invariant {
+            content_sha256: "sha-edited".to_string(),
+            ..original
+        };
+        let set = InvariantSet {
+            invariants: vec![edited],
+            ..Default::default()
+        };
+        let planner = ProofPlanner::new(&compiler, &config, &theorems, &artifacts);
+        let plan = planner
+            .plan(&set, None, &CompilationOptions::default(), &ProofOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(plan.invariants[0].action, PlannedAction::Reprove as i32);
+        assert!(!plan.invariants[0].theorem_cached);
+    }
+}
@@ -1,4 +1,78 @@
 use std::collections::HashMap;
+use spec_to_proof_error::Error;
+use prompt_registry::PromptRegistry;
+
+pub const THEOREM_GENERATION_PROMPT: &str = "theorem_generation";
+pub const PROOF_GENERATION_PROMPT: &str = "proof_generation";
+pub const PROOF_REPAIR_PROMPT: &str = "proof_repair";
+
+const THEOREM_GENERATION_TEMPLATE: &str = r#"You are an expert Lean 4 theorem prover. Convert the following invariant specification into a Lean 4 theorem.
+
+Invariant Specification:
+{{invariant}}
+
+Proof Strategy: {{proof_strategy}}
+
+Requirements:
+1. Generate a complete, compilable Lean 4 theorem
+2. Include all necessary imports
+3. Use proper Lean 4 syntax and conventions
+4. Make the theorem name descriptive and follow Lean naming conventions
+5. Include type annotations where helpful
+6. Use the specified proof strategy
+
+Generate the theorem using the generate_lean_theorem function."#;
+
+const PROOF_GENERATION_TEMPLATE: &str = r#"You are an expert Lean 4 theorem prover. Complete the proof for the following Lean theorem.
+
+Theorem Code:
+{{theorem_code}}
+
+Proof Strategy: {{proof_strategy}}
+
+Requirements:
+1. Complete the proof using Lean 4 tactics
+2. Follow the specified proof strategy
+3. Make the proof clear and readable
+4. Use appropriate tactics for the theorem type
+5. Ensure the proof compiles and runs successfully
+
+Complete the proof using the complete_proof function."#;
+
+const PROOF_REPAIR_TEMPLATE: &str = r#"You are an expert Lean 4 theorem prover. Your proof of the following Lean theorem does not compile.
+
+Theorem Code:
+{{theorem_code}}
+
+Your Proof:
+{{proof_code}}
+
+Compiler Errors (line:column, counted in the theorem code followed by your proof):
+{{diagnostics}}
+
+{{repair_instruction}}
+
+Requirements:
+1. Change only what the errors require; keep the parts of the proof that work
+2. Do not use sorry or admit
+3. Return the complete corrected proof, not just the changed lines
+
+Complete the proof using the complete_proof function."#;
+
+/// Registry holding the prompts the compiler sends to Claude; a manifest
+/// configured via `prompt_manifest` can add versions or route traffic to a
+/// candidate
+pub fn builtin_registry() -> PromptRegistry {
+    PromptRegistry::new()
+        .with_builtin(THEOREM_GENERATION_PROMPT, "1.0.0", THEOREM_GENERATION_TEMPLATE, &["invariant", "proof_strategy"])
+        .with_builtin(PROOF_GENERATION_PROMPT, "1.0.0", PROOF_GENERATION_TEMPLATE, &["theorem_code", "proof_strategy"])
+        .with_builtin(
+            PROOF_REPAIR_PROMPT,
+            "1.0.0",
+            PROOF_REPAIR_TEMPLATE,
+            &["theorem_code", "proof_code", "diagnostics", "repair_instruction"],
+        )
+}
 
 pub struct PromptTemplate {
     template: String,
@@ -217,36 +291,36 @@ impl GuardedPrompt {
         }
     }
 
-    pub fn render(&self, variables: &HashMap<String, &str>) -> Result<String, Box<dyn std::error::Error>> {
+    pub fn render(&self, variables: &HashMap<String, &str>) -> Result<String, Error> {
         let mut result = self.template.render(variables);
         
         // Check for injection patterns
         for pattern in &self.injection_patterns {
             if result.to_lowercase().contains(&pattern.to_lowercase()) {
-                return Err(format!("Potential prompt injection detected: {}", pattern).into());
+                return Err(Error::invalid_input(format!("Potential prompt injection detected: {}", pattern)));
             }
         }
         
         // Check for escape sequences
         for escape in &self.escape_sequences {
             if result.contains(escape) {
-                return Err(format!("Suspicious escape sequence detected: {}", escape).into());
+                return Err(Error::invalid_input(format!("Suspicious escape sequence detected: {}", escape)));
             }
         }
         
         // Additional safety checks
         if result.len() > 10000 {
-            return Err("Prompt too long (max 10KB)".into());
+            return Err(Error::invalid_input("Prompt too long (max 10KB)"));
         }
         
         if result.contains('\0') {
-            return Err("Null bytes not allowed".into());
+            return Err(Error::invalid_input("Null bytes not allowed"));
         }
         
         Ok(result)
     }
 
-    pub fn validate_input(&self, input: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn validate_input(&self, input: &str) -> Result<(), Error> {
         // Check for common injection attempts
         let suspicious_patterns = [
             "ignore", "disregard", "forget", "new instructions", "system prompt",
@@ -256,18 +330,18 @@ impl GuardedPrompt {
         let lower_input = input.to_lowercase();
         for pattern in &suspicious_patterns {
             if lower_input.contains(pattern) {
-                return Err(format!("Suspicious input pattern detected: {}", pattern).into());
+                return Err(Error::invalid_input(format!("Suspicious input pattern detected: {}", pattern)));
             }
         }
         
         // Check for excessive length
         if input.len() > 5000 {
-            return Err("Input too long (max 5KB)".into());
+            return Err(Error::invalid_input("Input too long (max 5KB)"));
         }
         
         // Check for null bytes
         if input.contains('\0') {
-            return Err("Null bytes not allowed in input".into());
+            return Err(Error::invalid_input("Null bytes not allowed in input"));
         }
         
         Ok(())
@@ -290,6 +364,31 @@ mod tests {
         assert!(result.contains("induction"));
     }
 
+    #[test]
+    fn test_builtin_registry_rendering() {
+        let registry = builtin_registry();
+
+        let mut variables = HashMap::new();
+        variables.insert("invariant".to_string(), "test invariant");
+        variables.insert("proof_strategy".to_string(), "induction");
+        let lean_prompt = registry.active(THEOREM_GENERATION_PROMPT).unwrap().render(&variables).unwrap();
+        assert!(lean_prompt.contains("test invariant"));
+        assert!(lean_prompt.contains("induction"));
+
+        variables.insert("theorem_code".to_string(), "test theorem");
+        let proof_prompt = registry.active(PROOF_GENERATION_PROMPT).unwrap().render(&variables).unwrap();
+        assert!(proof_prompt.contains("test theorem"));
+        assert!(proof_prompt.contains("induction"));
+
+        variables.insert("proof_code".to_string(), "by simp");
+        variables.insert("diagnostics".to_string(), "3:2: error: unsolved goals");
+        variables.insert("repair_instruction".to_string(), "Close each remaining goal.");
+        let repair_prompt = registry.active(PROOF_REPAIR_PROMPT).unwrap().render(&variables).unwrap();
+        assert!(repair_prompt.contains("by simp"));
+        assert!(repair_prompt.contains("3:2: error: unsolved goals"));
+        assert!(repair_prompt.contains("Close each remaining goal."));
+    }
+
     #[test]
     fn test_guarded_prompt_safety() {
         let guarded = GuardedPrompt::new("lean_theorem_generation");
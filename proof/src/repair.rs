@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use spec_to_proof_error::Error;
+use sha2::{Digest, Sha256};
+
+use crate::proto::proof::v1::ProofOptions;
+use crate::transcripts::ProofTranscript;
+use crate::ProofConfig;
+
+// A generated proof is checked with `lake env lean` in a project pinned to
+// the service's toolchain. When it fails, the first error is classified and
+// the model is asked to fix that error specifically, with the diagnostics
+// and its previous proof in front of it, until the proof checks or the
+// attempt or token budget runs out. Each repair is recorded in the
+// transcript with its failure class, so success rates per class can be
+// measured.
+
+/// Transcript strategy prefix of repair attempts, e.g. "repair:unknown_identifier"
+pub const REPAIR_STRATEGY_PREFIX: &str = "repair:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// One message from the Lean compiler
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeanDiagnostic {
+    pub line: u32,
+    pub column: u32,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl std::fmt::Display for LeanDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        write!(f, "{}:{}: {}: {}", self.line, self.column, severity, self.message)
+    }
+}
+
+/// Parses `lean` output: `<file>:<line>:<col>: <severity>: <message>`,
+/// where the message continues over the following lines until the next
+/// header
+pub fn parse_diagnostics(output: &str) -> Vec<LeanDiagnostic> {
+    let mut diagnostics: Vec<LeanDiagnostic> = Vec::new();
+    for line in output.lines() {
+        match parse_header(line) {
+            Some(diagnostic) => diagnostics.push(diagnostic),
+            None => {
+                if let Some(last) = diagnostics.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+            }
+        }
+    }
+    for diagnostic in &mut diagnostics {
+        diagnostic.message = diagnostic.message.trim_end().to_string();
+    }
+    diagnostics
+}
+
+fn parse_header(line: &str) -> Option<LeanDiagnostic> {
+    let (severity, split) = [(Severity::Error, ": error: "), (Severity::Warning, ": warning: "), (Severity::Info, ": info: ")]
+        .into_iter()
+        .find_map(|(severity, marker)| line.split_once(marker).map(|split| (severity, split)))?;
+    let (location, message) = split;
+    let mut parts = location.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    parts.next()?;
+    Some(LeanDiagnostic {
+        line,
+        column,
+        severity,
+        message: message.to_string(),
+    })
+}
+
+/// What went wrong with a proof, from its first error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FailureClass {
+    UnknownIdentifier,
+    UnsolvedGoals,
+    TypeMismatch,
+    TacticFailed,
+    Syntax,
+    Timeout,
+    Other,
+}
+
+impl FailureClass {
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        if message.contains("unknown identifier") || message.contains("unknown constant") || message.contains("unknown namespace") {
+            FailureClass::UnknownIdentifier
+        } else if message.contains("unsolved goals") {
+            FailureClass::UnsolvedGoals
+        } else if message.contains("type mismatch") || message.contains("application type mismatch") {
+            FailureClass::TypeMismatch
+        } else if message.contains("timeout") || message.contains("maximum recursion depth") {
+            FailureClass::Timeout
+        } else if message.starts_with("unexpected token") || message.starts_with("unexpected end of input") || message.starts_with("expected ") {
+            FailureClass::Syntax
+        } else if message.contains("failed") {
+            FailureClass::TacticFailed
+        } else {
+            FailureClass::Other
+        }
+    }
+
+    /// The class of the first error, if there is one
+    pub fn of_diagnostics(diagnostics: &[LeanDiagnostic]) -> Option<Self> {
+        diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| Self::classify(&diagnostic.message))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureClass::UnknownIdentifier => "unknown_identifier",
+            FailureClass::UnsolvedGoals => "unsolved_goals",
+            FailureClass::TypeMismatch => "type_mismatch",
+            FailureClass::TacticFailed => "tactic_failed",
+            FailureClass::Syntax => "syntax",
+            FailureClass::Timeout => "timeout",
+            FailureClass::Other => "other",
+        }
+    }
+
+    /// What the repair prompt asks the model to do
+    pub fn instruction(&self) -> &'static str {
+        match self {
+            FailureClass::UnknownIdentifier => {
+                "The proof refers to a name that does not exist. Replace it with the correct Mathlib or core \
+                 lemma name, or add the missing import or `open`; do not invent lemmas."
+            }
+            FailureClass::UnsolvedGoals => {
+                "The proof leaves goals open. Close each remaining goal shown in the diagnostics with \
+                 appropriate tactics."
+            }
+            FailureClass::TypeMismatch => {
+                "A term has the wrong type. Adjust the term, add a coercion or rewrite so the types shown \
+                 in the diagnostics agree."
+            }
+            FailureClass::TacticFailed => {
+                "A tactic failed on its goal. Replace it with a tactic that applies to that goal, or \
+                 prepare the goal first."
+            }
+            FailureClass::Syntax => "The proof does not parse. Fix the Lean 4 syntax at the reported position.",
+            FailureClass::Timeout => {
+                "The proof times out. Replace expensive automation such as `simp` over large sets or \
+                 `decide` with more targeted steps."
+            }
+            FailureClass::Other => "Fix the errors reported by the compiler.",
+        }
+    }
+}
+
+/// How much repairing a single proof may cost
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RepairBudget {
+    /// Repair prompts sent after the first failed check; 0 disables repair
+    pub max_attempts: u32,
+    /// Input and output tokens all repairs of the proof may use together
+    pub max_tokens: u32,
+}
+
+impl RepairBudget {
+    /// The request's budget, falling back to the service's where it sets none
+    pub fn for_options(options: &ProofOptions, config: &ProofConfig) -> Self {
+        Self {
+            max_attempts: match options.max_repair_attempts {
+                0 => config.repair_max_attempts,
+                attempts => attempts,
+            },
+            max_tokens: match options.repair_token_budget {
+                0 => config.repair_token_budget,
+                tokens => tokens,
+            },
+        }
+    }
+
+    /// Whether another repair may start after `attempts` repairs that used
+    /// `tokens_used` tokens
+    pub fn allows(&self, attempts: u32, tokens_used: u32) -> bool {
+        attempts < self.max_attempts && tokens_used < self.max_tokens
+    }
+}
+
+/// Result of checking a proof
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckOutcome {
+    pub diagnostics: Vec<LeanDiagnostic>,
+}
+
+impl CheckOutcome {
+    pub fn errors(&self) -> impl Iterator<Item = &LeanDiagnostic> {
+        self.diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn success(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Errors as sent to the model and recorded in the transcript
+    pub fn error_lines(&self) -> Vec<String> {
+        self.errors().map(ToString::to_string).collect()
+    }
+}
+
+/// Checks Lean files with `lake env lean` in a Lake project that has the
+/// service's toolchain and Mathlib built
+#[derive(Debug, Clone)]
+pub struct LeanChecker {
+    project_dir: PathBuf,
+    timeout: Duration,
+}
+
+impl LeanChecker {
+    pub fn new(project_dir: impl Into<PathBuf>, timeout: Duration) -> Self {
+        Self {
+            project_dir: project_dir.into(),
+            timeout,
+        }
+    }
+
+    pub async fn check(&self, lean_code: &str) -> Result<CheckOutcome, Error> {
+        let digest = Sha256::digest(lean_code.as_bytes());
+        let file_name = format!("SpecToProofRepair{:x}.lean", digest);
+        let path = self.project_dir.join(&file_name);
+        tokio::fs::write(&path, lean_code).await?;
+
+        let output = tokio::time::timeout(
+            self.timeout,
+            tokio::process::Command::new("lake")
+                .args(["env", "lean", &file_name])
+                .current_dir(&self.project_dir)
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await;
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let diagnostics = match output {
+            Ok(output) => {
+                let output = output?;
+                let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                text.push_str(&String::from_utf8_lossy(&output.stderr));
+                let mut diagnostics = parse_diagnostics(&text);
+                if !output.status.success() && !diagnostics.iter().any(|d| d.severity == Severity::Error) {
+                    diagnostics.push(LeanDiagnostic {
+                        line: 0,
+                        column: 0,
+                        severity: Severity::Error,
+                        message: format!("lean exited with {}: {}", output.status, text.trim()),
+                    });
+                }
+                diagnostics
+            }
+            Err(_) => vec![LeanDiagnostic {
+                line: 0,
+                column: 0,
+                severity: Severity::Error,
+                message: format!("(deterministic) timeout: checking took over {}ms", self.timeout.as_millis()),
+            }],
+        };
+        Ok(CheckOutcome { diagnostics })
+    }
+}
+
+/// Repair attempts and how many of them produced a proof that checks
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClassStats {
+    pub attempts: u32,
+    pub repaired: u32,
+}
+
+impl ClassStats {
+    pub fn success_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.repaired as f64 / self.attempts as f64
+        }
+    }
+}
+
+/// Repair outcomes per failure class across stored transcripts
+pub fn repair_stats<'a>(transcripts: impl IntoIterator<Item = &'a ProofTranscript>) -> BTreeMap<String, ClassStats> {
+    let mut stats: BTreeMap<String, ClassStats> = BTreeMap::new();
+    for attempt in transcripts.into_iter().flat_map(|transcript| &transcript.attempts) {
+        let Some(class) = attempt.strategy.strip_prefix(REPAIR_STRATEGY_PREFIX) else {
+            continue;
+        };
+        let entry = stats.entry(class.to_string()).or_default();
+        entry.attempts += 1;
+        if attempt.error.is_none() {
+            entry.repaired += 1;
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcripts::AttemptTranscript;
+
+    const LEAN_OUTPUT: &str = "\
+SpecToProofRepair1.lean:4:2: error: unknown identifier 'Nat.add_zero_right'
+SpecToProofRepair1.lean:6:0: warning: declaration uses 'sorry'
+SpecToProofRepair1.lean:9:38: error: unsolved goals
+n : ℕ
+⊢ n + 0 = n
+";
+
+    #[test]
+    fn test_parse_and_classify_diagnostics() {
+        let diagnostics = parse_diagnostics(LEAN_OUTPUT);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].line, 4);
+        assert_eq!(diagnostics[0].column, 2);
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[2].message, "unsolved goals\nn : ℕ\n⊢ n + 0 = n");
+        assert_eq!(diagnostics[0].to_string(), "4:2: error: unknown identifier 'Nat.add_zero_right'");
+
+        assert_eq!(FailureClass::of_diagnostics(&diagnostics), Some(FailureClass::UnknownIdentifier));
+        assert_eq!(FailureClass::of_diagnostics(&diagnostics[1..]), Some(FailureClass::UnsolvedGoals));
+        assert_eq!(FailureClass::of_diagnostics(&diagnostics[1..2]), None);
+
+        for (message, class) in [
+            ("type mismatch\n  h\nhas type", FailureClass::TypeMismatch),
+            ("linarith failed to find a contradiction", FailureClass::TacticFailed),
+            ("unexpected token 'at'; expected term", FailureClass::Syntax),
+            ("(deterministic) timeout at whnf", FailureClass::Timeout),
+            ("invalid field notation", FailureClass::Other),
+        ] {
+            assert_eq!(FailureClass::classify(message), class, "{}", message);
+        }
+
+        let outcome = CheckOutcome { diagnostics };
+        assert!(!outcome.success());
+        assert_eq!(outcome.error_lines().len(), 2);
+        assert!(CheckOutcome { diagnostics: Vec::new() }.success());
+    }
+
+    #[test]
+    fn test_repair_budget() {
+        let config = ProofConfig::default();
+        let budget = RepairBudget::for_options(&ProofOptions::default(), &config);
+        assert_eq!(budget.max_attempts, config.repair_max_attempts);
+
+        let budget = RepairBudget::for_options(
+            &ProofOptions { max_repair_attempts: 2, repair_token_budget: 1000, ..Default::default() },
+            &config,
+        );
+        assert!(budget.allows(1, 999));
+        assert!(!budget.allows(2, 0));
+        assert!(!budget.allows(0, 1000));
+    }
+
+    #[test]
+    fn test_repair_stats() {
+        let repair = |class: &str, error: Option<&str>| AttemptTranscript {
+            strategy: format!("{}{}", REPAIR_STRATEGY_PREFIX, class),
+            error: error.map(str::to_string),
+            ..Default::default()
+        };
+        let transcripts = [
+            ProofTranscript {
+                attempts: vec![
+                    AttemptTranscript { strategy: "simp".to_string(), ..Default::default() },
+                    repair("unknown_identifier", Some("does not compile: unsolved_goals")),
+                    repair("unsolved_goals", None),
+                ],
+                ..Default::default()
+            },
+            ProofTranscript {
+                attempts: vec![repair("unknown_identifier", None)],
+                ..Default::default()
+            },
+        ];
+
+        let stats = repair_stats(&transcripts);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["unknown_identifier"], ClassStats { attempts: 2, repaired: 1 });
+        assert_eq!(stats["unsolved_goals"].success_rate(), 1.0);
+    }
+}
@@ -0,0 +1,77 @@
+use cost_governance::LlmCallLimits;
+use serde::{Deserialize, Serialize};
+
+use crate::ProofConfig;
+
+/// Model selection and LLM call limits operators can change without
+/// restarting the service, reloaded from the file in `PROOF_RUNTIME_CONFIG`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeSettings {
+    pub claude_model: String,
+    #[serde(default)]
+    pub simple_claude_model: Option<String>,
+    pub routing_max_simple_operators: usize,
+    /// Kill switch and rate limits for model calls; ignored unless cost
+    /// governance is configured
+    #[serde(default)]
+    pub llm_limits: Option<LlmCallLimits>,
+}
+
+impl RuntimeSettings {
+    pub fn from_config(config: &ProofConfig) -> Self {
+        Self {
+            claude_model: config.claude_model.clone(),
+            simple_claude_model: config.simple_claude_model.clone(),
+            routing_max_simple_operators: config.routing_max_simple_operators,
+            llm_limits: None,
+        }
+    }
+
+    /// `config` with these settings in place of its own
+    pub fn apply(&self, config: &ProofConfig) -> ProofConfig {
+        ProofConfig {
+            claude_model: self.claude_model.clone(),
+            simple_claude_model: self.simple_claude_model.clone(),
+            routing_max_simple_operators: self.routing_max_simple_operators,
+            ..config.clone()
+        }
+    }
+}
+
+impl reload::RuntimeSettings for RuntimeSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.claude_model.trim().is_empty() {
+            return Err("claude_model must not be empty".to_string());
+        }
+        if self.simple_claude_model.as_deref().is_some_and(|model| model.trim().is_empty()) {
+            return Err("simple_claude_model must not be empty when set".to_string());
+        }
+        match &self.llm_limits {
+            Some(limits) => limits.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reload::RuntimeSettings as _;
+
+    #[test]
+    fn test_apply_and_validate() {
+        let config = ProofConfig::default();
+        let mut settings = RuntimeSettings::from_config(&config);
+        assert!(settings.validate().is_ok());
+
+        settings.simple_claude_model = Some("claude-3-haiku-20240307".to_string());
+        settings.routing_max_simple_operators = 2;
+        let applied = settings.apply(&config);
+        assert_eq!(applied.simple_claude_model.as_deref(), Some("claude-3-haiku-20240307"));
+        assert_eq!(applied.routing_max_simple_operators, 2);
+        assert_eq!(applied.lean_toolchain, config.lean_toolchain);
+
+        settings.simple_claude_model = Some(String::new());
+        assert!(settings.validate().is_err());
+    }
+}
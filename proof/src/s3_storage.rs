@@ -1,33 +1,75 @@
 use std::collections::HashMap;
-use std::error::Error;
-use aws_sdk_s3::Client as S3Client;
-use aws_sdk_s3::types::{ServerSideEncryption, SseCustomerAlgorithm};
-use aws_sdk_s3::primitives::ByteStream;
+use std::time::Duration;
+use spec_to_proof_error::Error;
 use aws_sdk_kms::Client as KmsClient;
+use envelope::EnvelopeEncryptor;
+use export::BundleStore;
+use object_storage::{Encryption, ObjectStore, ObjectStoreConfig, PutOptions, S3ObjectStore};
 
+use crate::audit_bundle::AUDIT_BUNDLE_KEY_PREFIX;
 use crate::proto::proof::v1::*;
 use crate::proto::spec_to_proof::v1::*;
+use crate::toolchain;
+use crate::transcripts::{self, ProofTranscript};
+
+pub use object_storage::{PresignedRequest, MAX_PRESIGN_EXPIRY};
+
+impl From<PresignedRequest> for PresignedUrl {
+    fn from(presigned: PresignedRequest) -> Self {
+        Self {
+            url: presigned.url,
+            expires_at: Some(prost_types::Timestamp::from(presigned.expires_at)),
+            method: presigned.method,
+            headers: presigned.headers,
+        }
+    }
+}
 
 pub struct S3Storage {
-    s3_client: S3Client,
+    // The theorem bucket, with SSE-KMS when a KMS key is configured
+    store: S3ObjectStore,
     kms_client: Option<KmsClient>,
+    // Set when objects are encrypted client-side before upload
+    envelope: Option<EnvelopeEncryptor>,
     config: ProofConfig,
 }
 
 impl S3Storage {
-    pub async fn new(config: &ProofConfig) -> Result<Self, Box<dyn Error>> {
+    pub async fn new(config: &ProofConfig) -> Result<Self, Error> {
         let aws_config = aws_config::load_default_config(aws_config::BehaviorVersion::latest()).await;
         
-        let s3_client = S3Client::new(&aws_config);
+        let store = S3ObjectStore::connect(&ObjectStoreConfig {
+            bucket: config.s3_bucket.clone(),
+            region: config.s3_region.clone(),
+            endpoint: config.s3_endpoint.clone(),
+            // Prompts and completions in transcripts quote the specs, so
+            // they get the same protection as theorems
+            encryption: match &config.kms_key_id {
+                Some(key_id) => Encryption::Kms { key_id: key_id.clone() },
+                None => Encryption::None,
+            },
+            ..Default::default()
+        })
+        .await?;
         let kms_client = if config.kms_key_id.is_some() {
             Some(KmsClient::new(&aws_config))
         } else {
             None
         };
+        let envelope = match (&kms_client, &config.kms_key_id) {
+            (Some(kms_client), Some(key_id)) if config.client_side_encryption => {
+                Some(EnvelopeEncryptor::new(kms_client.clone(), key_id))
+            }
+            _ if config.client_side_encryption => {
+                return Err("Client-side encryption requires a KMS key ID".into());
+            }
+            _ => None,
+        };
 
         Ok(Self {
-            s3_client,
+            store,
             kms_client,
+            envelope,
             config: config.clone(),
         })
     }
@@ -37,52 +79,35 @@ impl S3Storage {
         theorem: &LeanTheorem,
         version: &str,
         s3_config: &S3Config,
-    ) -> Result<String, Box<dyn Error>> {
+    ) -> Result<String, Error> {
         let key = self.generate_s3_key(theorem, version, s3_config);
         
-        // Prepare encryption settings
-        let encryption_config = self.build_encryption_config(s3_config).await?;
+        // The request's bucket and encryption settings override the service's
+        let store = self.store
+            .for_bucket(&s3_config.bucket_name)
+            .with_encryption(self.build_encryption_config(s3_config).await?);
         
         // Upload the theorem code
-        let body = ByteStream::from(theorem.lean_code.as_bytes());
-        
-        let mut upload_request = self.s3_client
-            .put_object()
-            .bucket(&s3_config.bucket_name)
-            .key(&key)
-            .body(body)
-            .content_type("text/plain");
-
-        // Apply encryption if configured
-        if let Some(encryption) = encryption_config {
-            upload_request = upload_request.set_server_side_encryption(Some(encryption));
-        }
+        let (body, mut metadata) = self.seal(&key, theorem.lean_code.as_bytes().to_vec()).await?;
 
         // Add metadata
-        let mut metadata = HashMap::new();
         metadata.insert("theorem_id".to_string(), theorem.id.clone());
         metadata.insert("theorem_name".to_string(), theorem.theorem_name.clone());
         metadata.insert("source_invariant_id".to_string(), theorem.source_invariant_id.clone());
         metadata.insert("content_hash".to_string(), theorem.content_sha256.clone());
         metadata.insert("version".to_string(), version.to_string());
         metadata.insert("proof_strategy".to_string(), theorem.proof_strategy.clone());
+        metadata.insert("lean_toolchain".to_string(), theorem.lean_toolchain.clone());
+        metadata.insert("mathlib_commit".to_string(), theorem.mathlib_commit.clone());
         metadata.insert("uploaded_at".to_string(), std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs()
             .to_string());
 
-        upload_request = upload_request.set_metadata(Some(metadata));
-
-        // Execute upload
-        let result = upload_request.send().await?;
+        store.put(&key, body, &PutOptions::content_type("text/plain").with_metadata(metadata)).await?;
         
-        // Generate S3 location URL
-        let s3_location = format!(
-            "s3://{}/{}",
-            s3_config.bucket_name,
-            key
-        );
+        let s3_location = store.location(&key);
 
         tracing::info!("Successfully uploaded theorem {} to {}", theorem.theorem_name, s3_location);
 
@@ -92,24 +117,22 @@ impl S3Storage {
     pub async fn download_theorem(
         &self,
         s3_location: &str,
-    ) -> Result<LeanTheorem, Box<dyn Error>> {
+    ) -> Result<LeanTheorem, Error> {
         // Parse S3 location to extract bucket and key
         let (bucket, key) = self.parse_s3_location(s3_location)?;
         
         // Download the object
-        let result = self.s3_client
-            .get_object()
-            .bucket(bucket)
-            .key(key)
-            .send()
-            .await?;
-
-        // Read the content
-        let body = result.body.collect().await?;
-        let lean_code = String::from_utf8(body.into_bytes())?;
+        let object = self.store
+            .for_bucket(&bucket)
+            .get(&key)
+            .await?
+            .ok_or_else(|| Error::invalid_input(format!("No theorem stored at {}", s3_location)))?;
 
         // Extract metadata
-        let metadata = result.metadata().unwrap_or(&HashMap::new());
+        let metadata = object.metadata;
+
+        // Read the content
+        let lean_code = String::from_utf8(self.open(&key, object.body, &metadata).await?).map_err(Error::internal)?;
         
         // Reconstruct LeanTheorem (simplified - in real implementation, you'd store full proto)
         let theorem = LeanTheorem {
@@ -123,6 +146,8 @@ impl S3Storage {
             compilation_errors: Vec::new(),
             proof_strategy: metadata.get("proof_strategy").unwrap_or(&"".to_string()).clone(),
             metadata: HashMap::new(),
+            lean_toolchain: metadata.get("lean_toolchain").cloned().unwrap_or_default(),
+            mathlib_commit: metadata.get("mathlib_commit").cloned().unwrap_or_default(),
         };
 
         Ok(theorem)
@@ -131,18 +156,12 @@ impl S3Storage {
     pub async fn list_theorems(
         &self,
         prefix: &str,
-    ) -> Result<Vec<String>, Box<dyn Error>> {
-        let result = self.s3_client
-            .list_objects_v2()
-            .bucket(&self.config.s3_bucket)
-            .prefix(prefix)
-            .send()
-            .await?;
-
-        let keys: Vec<String> = result.contents()
-            .unwrap_or(&[])
-            .iter()
-            .map(|obj| obj.key().unwrap_or("").to_string())
+    ) -> Result<Vec<String>, Error> {
+        let keys: Vec<String> = self.store
+            .list(prefix)
+            .await?
+            .into_iter()
+            .map(|object| object.key)
             .collect();
 
         Ok(keys)
@@ -151,21 +170,206 @@ impl S3Storage {
     pub async fn delete_theorem(
         &self,
         s3_location: &str,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), Error> {
         let (bucket, key) = self.parse_s3_location(s3_location)?;
         
-        self.s3_client
-            .delete_object()
-            .bucket(bucket)
-            .key(key)
-            .send()
-            .await?;
+        self.store.for_bucket(&bucket).delete(&key).await?;
 
         tracing::info!("Successfully deleted theorem from {}", s3_location);
 
         Ok(())
     }
 
+    /// Deletes every theorem stored for a deprecated toolchain, identified
+    /// by `toolchain::toolchain_key`. Returns how many objects were removed.
+    pub async fn purge_toolchain(&self, toolchain_key: &str) -> Result<usize, Error> {
+        let prefix = format!("{}{}/", self.config.s3_key_prefix, toolchain_key);
+        let mut purged = 0;
+
+        for object in self.store.list(&prefix).await? {
+            self.store.delete(&object.key).await?;
+            purged += 1;
+        }
+
+        tracing::info!("Purged {} theorem objects for toolchain {}", purged, toolchain_key);
+        Ok(purged)
+    }
+
+    /// Stores a proof transcript as JSON in the theorem bucket, returning
+    /// its s3:// location
+    pub async fn upload_transcript(
+        &self,
+        transcript: &ProofTranscript,
+    ) -> Result<String, Error> {
+        let key = transcripts::transcript_key(&self.config.transcript_key_prefix, &transcript.artifact_id);
+
+        let (body, metadata) = self.seal(&key, serde_json::to_vec(transcript)?).await?;
+
+        self.store
+            .put(&key, body, &PutOptions::content_type("application/json").with_metadata(metadata))
+            .await?;
+
+        Ok(self.store.location(&key))
+    }
+
+    /// Fetches the transcript stored for an artifact, or None if there is
+    /// none
+    pub async fn download_transcript(
+        &self,
+        artifact_id: &str,
+    ) -> Result<Option<ProofTranscript>, Error> {
+        let key = transcripts::transcript_key(&self.config.transcript_key_prefix, artifact_id);
+
+        let Some(object) = self.store.get(&key).await? else {
+            return Ok(None);
+        };
+
+        let body = self.open(&key, object.body, &object.metadata).await?;
+        Ok(Some(serde_json::from_slice(&body)?))
+    }
+
+    /// A URL that downloads an artifact's transcript without AWS
+    /// credentials until `expires_in` has passed, or None if there is no
+    /// transcript
+    pub async fn presign_transcript(
+        &self,
+        artifact_id: &str,
+        expires_in: Duration,
+    ) -> Result<Option<PresignedRequest>, Error> {
+        let key = transcripts::transcript_key(&self.config.transcript_key_prefix, artifact_id);
+
+        // Presigning never fails for a missing object, so check first
+        if self.store.head(&key).await?.is_none() {
+            return Ok(None);
+        }
+
+        let location = self.store.location(&key);
+        Ok(Some(self.generate_presigned_get(&location, expires_in).await?))
+    }
+
+    /// The most recently uploaded version of a theorem's Lean file, if any
+    pub async fn latest_theorem_location(
+        &self,
+        theorem: &LeanTheorem,
+    ) -> Result<Option<String>, Error> {
+        let prefix = format!(
+            "{}{}/{}/",
+            self.config.s3_key_prefix,
+            toolchain::theorem_toolchain_key(theorem),
+            theorem_hash_prefix(theorem)
+        );
+        let suffix = format!("/{}.lean", theorem.theorem_name);
+
+        let latest = self.store
+            .list(&prefix)
+            .await?
+            .into_iter()
+            .filter(|object| object.key.ends_with(&suffix))
+            .max_by_key(|object| object.last_modified);
+
+        Ok(latest.map(|object| self.store.location(&object.key)))
+    }
+
+    /// Where a presigned upload of the theorem's current content goes,
+    /// versioned by content hash like `upload_theorem`
+    pub fn theorem_upload_location(&self, theorem: &LeanTheorem) -> String {
+        format!(
+            "s3://{}/{}{}/{}/{}/{}.lean",
+            self.config.s3_bucket,
+            self.config.s3_key_prefix,
+            toolchain::theorem_toolchain_key(theorem),
+            theorem_hash_prefix(theorem),
+            theorem.content_sha256,
+            theorem.theorem_name
+        )
+    }
+
+    /// A URL that reads the object at `s3_location` without AWS credentials
+    /// until `expires_in` has passed. Reading SSE-KMS objects needs no
+    /// extra headers, since the URL is signed with SigV4.
+    pub async fn generate_presigned_get(
+        &self,
+        s3_location: &str,
+        expires_in: Duration,
+    ) -> Result<PresignedRequest, Error> {
+        self.check_presignable()?;
+        let (bucket, key) = self.parse_s3_location(s3_location)?;
+
+        Ok(self.store.for_bucket(&bucket).presign_get(&key, expires_in).await?)
+    }
+
+    /// A URL that writes the object at `s3_location` without AWS
+    /// credentials until `expires_in` has passed. With a KMS key
+    /// configured the encryption headers are part of the signature, so
+    /// uploads must send every returned header and land encrypted with
+    /// that key.
+    pub async fn generate_presigned_put(
+        &self,
+        s3_location: &str,
+        content_type: &str,
+        expires_in: Duration,
+    ) -> Result<PresignedRequest, Error> {
+        self.check_presignable()?;
+        let (bucket, key) = self.parse_s3_location(s3_location)?;
+
+        Ok(self.store.for_bucket(&bucket).presign_put(&key, content_type, expires_in).await?)
+    }
+
+    /// Audit bundles go to the service bucket with SSE only, even when
+    /// client-side encryption is on, since auditors download them directly
+    pub fn audit_bundle_store(&self) -> BundleStore {
+        let store = BundleStore::new(self.store.client().clone(), &self.config.s3_bucket, AUDIT_BUNDLE_KEY_PREFIX)
+            .with_url_expiry(Duration::from_secs(self.config.presigned_url_expiry_seconds));
+        match &self.config.kms_key_id {
+            Some(key_id) => store.with_kms_key(key_id),
+            None => store,
+        }
+    }
+
+    /// Whether objects are encrypted client-side, leaving presigned URLs
+    /// unusable
+    pub fn client_side_encrypted(&self) -> bool {
+        self.envelope.is_some()
+    }
+
+    // A presigned GET would hand out ciphertext, and a presigned PUT would
+    // store plaintext
+    fn check_presignable(&self) -> Result<(), Error> {
+        if self.client_side_encrypted() {
+            return Err("Presigned URLs are unavailable with client-side encryption".into());
+        }
+        Ok(())
+    }
+
+    async fn seal(
+        &self,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<(Vec<u8>, HashMap<String, String>), Error> {
+        match &self.envelope {
+            Some(envelope) => {
+                let sealed = envelope.seal(key, &body).await?;
+                Ok((sealed.ciphertext, sealed.metadata))
+            }
+            None => Ok((body, HashMap::new())),
+        }
+    }
+
+    async fn open(
+        &self,
+        key: &str,
+        body: Vec<u8>,
+        metadata: &HashMap<String, String>,
+    ) -> Result<Vec<u8>, Error> {
+        match &self.envelope {
+            Some(envelope) => Ok(envelope.open(key, body, metadata).await?),
+            None if envelope::is_sealed(metadata) => {
+                Err(format!("Object {} is encrypted client-side but no KMS key is configured", key).into())
+            }
+            None => Ok(body),
+        }
+    }
+
     fn generate_s3_key(
         &self,
         theorem: &LeanTheorem,
@@ -173,12 +377,11 @@ impl S3Storage {
         s3_config: &S3Config,
     ) -> String {
         let prefix = s3_config.key_prefix.as_deref().unwrap_or("theorems/");
-        let invariant_hash = &theorem.content_sha256[..8]; // Use first 8 chars for readability
-        
         format!(
-            "{}{}/{}/{}.lean",
+            "{}{}/{}/{}/{}.lean",
             prefix,
-            invariant_hash,
+            toolchain::theorem_toolchain_key(theorem),
+            theorem_hash_prefix(theorem),
             version,
             theorem.theorem_name
         )
@@ -187,11 +390,11 @@ impl S3Storage {
     async fn build_encryption_config(
         &self,
         s3_config: &S3Config,
-    ) -> Result<Option<ServerSideEncryption>, Box<dyn Error>> {
+    ) -> Result<Encryption, Error> {
         if let Some(encryption) = &s3_config.encryption {
             match encryption.sse_algorithm.as_str() {
                 "AES256" => {
-                    Ok(Some(ServerSideEncryption::Aes256))
+                    Ok(Encryption::Aes256)
                 }
                 "aws:kms" => {
                     if let Some(key_id) = &encryption.kms_key_id {
@@ -201,10 +404,10 @@ impl S3Storage {
                                 .describe_key()
                                 .key_id(key_id)
                                 .send()
-                                .await?;
+                                .await.map_err(Error::transient)?;
                         }
                         
-                        Ok(Some(ServerSideEncryption::AwsKms))
+                        Ok(Encryption::Kms { key_id: key_id.clone() })
                     } else {
                         Err("KMS key ID required for aws:kms encryption".into())
                     }
@@ -214,88 +417,48 @@ impl S3Storage {
                 }
             }
         } else {
-            Ok(None)
+            Ok(Encryption::None)
         }
     }
 
-    fn parse_s3_location(&self, s3_location: &str) -> Result<(String, String), Box<dyn Error>> {
-        if !s3_location.starts_with("s3://") {
-            return Err("Invalid S3 location format".into());
-        }
-        
-        let path = &s3_location[5..]; // Remove "s3://"
-        let parts: Vec<&str> = path.splitn(2, '/').collect();
-        
-        if parts.len() != 2 {
-            return Err("Invalid S3 location format".into());
-        }
-        
-        Ok((parts[0].to_string(), parts[1].to_string()))
+    fn parse_s3_location(&self, s3_location: &str) -> Result<(String, String), Error> {
+        Ok(object_storage::parse_location(s3_location)?)
     }
 
-    pub async fn create_bucket_if_not_exists(&self) -> Result<(), Box<dyn Error>> {
-        // Check if bucket exists
-        match self.s3_client
-            .head_bucket()
-            .bucket(&self.config.s3_bucket)
-            .send()
-            .await
-        {
-            Ok(_) => {
-                tracing::info!("S3 bucket {} already exists", self.config.s3_bucket);
-                Ok(())
-            }
-            Err(_) => {
-                // Create bucket
-                let mut create_request = self.s3_client
-                    .create_bucket()
-                    .bucket(&self.config.s3_bucket);
-
-                // Set region if specified
-                if !self.config.s3_region.is_empty() {
-                    create_request = create_request.create_bucket_configuration(
-                        aws_sdk_s3::types::CreateBucketConfiguration::builder()
-                            .location_constraint(aws_sdk_s3::types::BucketLocationConstraint::from(
-                                self.config.s3_region.as_str()
-                            ))
-                            .build()
-                    );
-                }
-
-                create_request.send().await?;
-                tracing::info!("Created S3 bucket {}", self.config.s3_bucket);
-                Ok(())
-            }
-        }
+    /// HeadBucket on the theorem bucket: reachable, and readable with the
+    /// service's credentials
+    pub async fn check_bucket(&self) -> Result<(), Error> {
+        Ok(self.store.ping().await?)
     }
 
-    pub async fn enable_versioning(&self) -> Result<(), Box<dyn Error>> {
-        self.s3_client
-            .put_bucket_versioning()
-            .bucket(&self.config.s3_bucket)
-            .versioning_configuration(
-                aws_sdk_s3::types::VersioningConfiguration::builder()
-                    .status(aws_sdk_s3::types::BucketVersioningStatus::Enabled)
-                    .build()
-            )
-            .send()
-            .await?;
+    pub async fn create_bucket_if_not_exists(&self) -> Result<(), Error> {
+        Ok(self.store.create_bucket_if_not_exists(&self.config.s3_region).await?)
+    }
 
-        tracing::info!("Enabled versioning for S3 bucket {}", self.config.s3_bucket);
-        Ok(())
+    pub async fn enable_versioning(&self) -> Result<(), Error> {
+        Ok(self.store.enable_versioning().await?)
     }
 }
 
+// Theorem keys are grouped by toolchain, so a deprecated toolchain's
+// theorems share a prefix, then by the first 8 chars of the content hash
+// for readability
+fn theorem_hash_prefix(theorem: &LeanTheorem) -> &str {
+    &theorem.content_sha256[..8.min(theorem.content_sha256.len())]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aws_sdk_s3::Client as S3Client;
 
     #[test]
     fn test_generate_s3_key() {
         let config = ProofConfig::default();
         let storage = S3Storage {
-            s3_client: S3Client::new(&aws_config::SdkConfig::builder().build()),
+            store: S3ObjectStore::new(S3Client::new(&aws_config::SdkConfig::builder().build()), "spec-to-proof-lean"),
             kms_client: None,
+            envelope: None,
             config,
         };
 
@@ -310,6 +473,8 @@ mod tests {
             compilation_errors: Vec::new(),
             proof_strategy: "induction".to_string(),
             metadata: HashMap::new(),
+            lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+            mathlib_commit: String::new(),
         };
 
         let s3_config = S3Config {
@@ -320,15 +485,16 @@ mod tests {
         };
 
         let key = storage.generate_s3_key(&theorem, "v1", &s3_config);
-        assert!(key.starts_with("theorems/a1b2c3d4/v1/test_theorem.lean"));
+        assert!(key.starts_with("theorems/v4.7.0-no-mathlib/a1b2c3d4/v1/test_theorem.lean"));
     }
 
     #[test]
     fn test_parse_s3_location() {
         let config = ProofConfig::default();
         let storage = S3Storage {
-            s3_client: S3Client::new(&aws_config::SdkConfig::builder().build()),
+            store: S3ObjectStore::new(S3Client::new(&aws_config::SdkConfig::builder().build()), "spec-to-proof-lean"),
             kms_client: None,
+            envelope: None,
             config,
         };
 
@@ -343,8 +509,9 @@ mod tests {
     fn test_parse_s3_location_invalid() {
         let config = ProofConfig::default();
         let storage = S3Storage {
-            s3_client: S3Client::new(&aws_config::SdkConfig::builder().build()),
+            store: S3ObjectStore::new(S3Client::new(&aws_config::SdkConfig::builder().build()), "spec-to-proof-lean"),
             kms_client: None,
+            envelope: None,
             config,
         };
 
@@ -352,4 +519,26 @@ mod tests {
         let result = storage.parse_s3_location(invalid_location);
         assert!(result.is_err());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_theorem_upload_location() {
+        let storage = S3Storage {
+            store: S3ObjectStore::new(S3Client::new(&aws_config::SdkConfig::builder().build()), "spec-to-proof-lean"),
+            kms_client: None,
+            envelope: None,
+            config: ProofConfig::default(),
+        };
+
+        let theorem = LeanTheorem {
+            content_sha256: "a1b2c3d4e5f6".to_string(),
+            theorem_name: "test_theorem".to_string(),
+            lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+            mathlib_commit: "3f1c2a9b4d5e6f70".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            storage.theorem_upload_location(&theorem),
+            "s3://spec-to-proof-lean/theorems/v4.7.0-mathlib-3f1c2a9b4d5e/a1b2c3d4/a1b2c3d4e5f6/test_theorem.lean"
+        );
+    }
+}
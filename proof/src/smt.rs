@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use spec_to_proof_error::Error;
+use tokio::io::AsyncWriteExt;
+
+use crate::ProofConfig;
+use crate::compiler::compute_content_hash;
+use crate::proto::spec_to_proof::v1::*;
+
+// Tags that mark an invariant as a plain arithmetic/boolean constraint that
+// an SMT solver can discharge without going through Lean
+pub const SMT_TAGS: &[&str] = &["smt", "arithmetic", "linear", "bounds"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmtOutcome {
+    // The negated goal is unsatisfiable, i.e. the invariant holds
+    Proven,
+    // The solver found an assignment violating the invariant
+    Counterexample(String),
+    Unknown(String),
+}
+
+pub struct SmtSolver {
+    z3_path: String,
+    timeout: Duration,
+}
+
+impl SmtSolver {
+    pub fn new(config: &ProofConfig) -> Self {
+        Self {
+            z3_path: config.z3_path.clone(),
+            timeout: Duration::from_millis(config.smt_timeout_ms),
+        }
+    }
+
+    pub fn is_candidate(invariant: &Invariant) -> bool {
+        // The taxonomy's proof-strategy hint routes whole categories (e.g.
+        // performance bounds) to the solver
+        let hinted = invariant.classification
+            .as_ref()
+            .is_some_and(|classification| classification.proof_strategy == "smt");
+        hinted || invariant.tags.iter().any(|tag| SMT_TAGS.contains(&tag.to_lowercase().as_str()))
+    }
+
+    pub async fn prove(&self, invariant: &Invariant) -> Result<(SmtOutcome, ProofArtifact), Error> {
+        let start_time = Instant::now();
+
+        let script = to_smtlib(invariant)?;
+        let output = self.run_z3(&script).await?;
+        let outcome = parse_z3_output(&output);
+
+        let duration_ms = start_time.elapsed().as_millis() as u64;
+
+        let status = match outcome {
+            SmtOutcome::Proven => ProofStatus::Success,
+            SmtOutcome::Counterexample(_) => ProofStatus::Failed,
+            SmtOutcome::Unknown(_) => ProofStatus::Timeout,
+        };
+
+        let mut metadata = HashMap::new();
+        metadata.insert("solver".to_string(), "z3".to_string());
+        metadata.insert("smt_timeout_ms".to_string(), self.timeout.as_millis().to_string());
+        if let SmtOutcome::Counterexample(model) = &outcome {
+            metadata.insert("counterexample".to_string(), model.clone());
+        }
+
+        let artifact = ProofArtifact {
+            id: format!("proof_smt_{}", invariant.id),
+            content_sha256: compute_content_hash(&script),
+            theorem_id: String::new(),
+            invariant_id: invariant.id.clone(),
+            status: status as i32,
+            attempted_at: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+            duration_ms,
+            output: script,
+            logs: vec![output],
+            resource_usage: Some(ResourceUsage {
+                cpu_seconds: duration_ms as f64 / 1000.0,
+                memory_bytes: 0,
+                disk_bytes: 0,
+                network_bytes: 0,
+            }),
+            proof_strategy: "smt".to_string(),
+            confidence_score: if outcome == SmtOutcome::Proven { 1.0 } else { 0.0 },
+            metadata,
+            // Not checked by Lean, so valid under any toolchain
+            lean_toolchain: String::new(),
+            mathlib_commit: String::new(),
+        };
+
+        Ok((outcome, artifact))
+    }
+
+    async fn run_z3(&self, script: &str) -> Result<String, Error> {
+        let mut child = tokio::process::Command::new(&self.z3_path)
+            .arg("-in")
+            .arg(format!("-t:{}", self.timeout.as_millis()))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(script.as_bytes()).await?;
+        }
+
+        // Give the process a little longer than the solver's own timeout
+        let output = tokio::time::timeout(self.timeout + Duration::from_secs(1), child.wait_with_output())
+            .await
+            .map_err(|_| Error::timeout("z3 did not finish within the timeout"))??;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+pub fn parse_z3_output(output: &str) -> SmtOutcome {
+    let mut lines = output.lines();
+    match lines.next().map(str::trim) {
+        Some("unsat") => SmtOutcome::Proven,
+        Some("sat") => SmtOutcome::Counterexample(lines.collect::<Vec<_>>().join("\n")),
+        Some(other) => SmtOutcome::Unknown(other.to_string()),
+        None => SmtOutcome::Unknown("no output from solver".to_string()),
+    }
+}
+
+// Builds an SMT-LIB script that asserts the variable constraints and the
+// negation of the formal expression; `unsat` means the invariant holds
+pub fn to_smtlib(invariant: &Invariant) -> Result<String, Error> {
+    let mut script = String::new();
+    script.push_str("(set-logic ALL)\n");
+
+    let sorts: HashMap<String, &'static str> = invariant.variables
+        .iter()
+        .map(|variable| (variable.name.clone(), smt_sort(&variable.var_type)))
+        .collect();
+
+    for variable in &invariant.variables {
+        let symbol = smt_symbol(&variable.name)?;
+        script.push_str(&format!("(declare-const {} {})\n", symbol, sorts[&variable.name]));
+
+        if is_natural(&variable.var_type) {
+            script.push_str(&format!("(assert (>= {} 0))\n", symbol));
+        }
+
+        for constraint in &variable.constraints {
+            script.push_str(&format!("(assert {})\n", translate_expression(constraint, &sorts)?));
+        }
+    }
+
+    let goal = translate_expression(&invariant.formal_expression, &sorts)?;
+    script.push_str(&format!("(assert (not {}))\n", goal));
+    script.push_str("(check-sat)\n(get-model)\n");
+
+    Ok(script)
+}
+
+// Variable names come from extracted specs, so every one is written as a
+// quoted symbol; `|` and `\` cannot appear inside one
+pub(crate) fn smt_symbol(name: &str) -> Result<String, Error> {
+    if name.is_empty() || name.contains(['|', '\\']) {
+        return Err(Error::invalid_input(format!("Variable name '{}' is not a valid SMT-LIB symbol", name)));
+    }
+    Ok(format!("|{}|", name))
+}
+
+pub(crate) fn smt_sort(var_type: &str) -> &'static str {
+    match var_type.to_lowercase().as_str() {
+        "int" | "integer" | "nat" | "natural" | "i32" | "i64" | "u32" | "u64" | "usize" | "count" => "Int",
+        "bool" | "boolean" => "Bool",
+        _ => "Real",
+    }
+}
+
+pub(crate) fn is_natural(var_type: &str) -> bool {
+    matches!(var_type.to_lowercase().as_str(), "nat" | "natural" | "u32" | "u64" | "usize" | "count")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    Number(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+pub(crate) fn tokenize(expression: &str) -> Result<Vec<Token>, Error> {
+    // Unicode and C-style operators map onto SMT-LIB function names
+    const OPERATORS: &[(&str, &str)] = &[
+        ("<==>", "="), ("<=>", "="), ("==>", "=>"), ("->", "=>"), ("→", "=>"), ("⇒", "=>"),
+        ("<=", "<="), (">=", ">="), ("==", "="), ("!=", "distinct"), ("&&", "and"), ("||", "or"),
+        ("≤", "<="), ("≥", ">="), ("≠", "distinct"), ("∧", "and"), ("∨", "or"), ("¬", "not"),
+        ("<", "<"), (">", ">"), ("=", "="), ("!", "not"),
+        ("+", "+"), ("-", "-"), ("*", "*"), ("/", "/"), ("%", "mod"),
+    ];
+
+    let mut tokens = Vec::new();
+    let mut rest = expression;
+
+    'outer: while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        if c == '(' {
+            tokens.push(Token::LParen);
+            rest = &rest[1..];
+            continue;
+        }
+        if c == ')' {
+            tokens.push(Token::RParen);
+            rest = &rest[1..];
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let end = rest.find(|ch: char| !(ch.is_ascii_digit() || ch == '.')).unwrap_or(rest.len());
+            tokens.push(Token::Number(rest[..end].to_string()));
+            rest = &rest[end..];
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == '.')).unwrap_or(rest.len());
+            let word = &rest[..end];
+            tokens.push(match word {
+                "and" => Token::Op("and"),
+                "or" => Token::Op("or"),
+                "not" => Token::Op("not"),
+                _ => Token::Ident(word.to_string()),
+            });
+            rest = &rest[end..];
+            continue;
+        }
+        for (symbol, op) in OPERATORS {
+            if rest.starts_with(symbol) {
+                tokens.push(Token::Op(*op));
+                rest = &rest[symbol.len()..];
+                continue 'outer;
+            }
+        }
+        return Err(Error::invalid_input(format!("Unsupported character '{}' in expression for SMT translation", c)));
+    }
+
+    Ok(tokens)
+}
+
+pub(crate) fn binary_precedence(op: &str) -> Option<u8> {
+    match op {
+        "=>" => Some(1),
+        "or" => Some(2),
+        "and" => Some(3),
+        "=" | "distinct" | "<" | "<=" | ">" | ">=" => Some(4),
+        "+" | "-" => Some(5),
+        "*" | "/" | "mod" => Some(6),
+        _ => None,
+    }
+}
+
+// A translated term and its SMT sort, which picks between integer and real
+// division
+struct Term {
+    text: String,
+    sort: &'static str,
+}
+
+impl Term {
+    fn new(text: String, sort: &'static str) -> Self {
+        Self { text, sort }
+    }
+}
+
+fn numeric_sort(lhs: &Term, rhs: &Term) -> &'static str {
+    if lhs.sort == "Int" && rhs.sort == "Int" { "Int" } else { "Real" }
+}
+
+// Precedence climbing over the infix token stream
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    sorts: &'a HashMap<String, &'static str>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_expression(&mut self, min_precedence: u8) -> Result<Term, Error> {
+        let mut lhs = self.parse_unary()?;
+
+        while let Some(Token::Op(op)) = self.peek().cloned() {
+            let precedence = match binary_precedence(op) {
+                Some(p) if p >= min_precedence => p,
+                _ => break,
+            };
+            self.next();
+            // Implication is right-associative, everything else left
+            let next_min = if op == "=>" { precedence } else { precedence + 1 };
+            let rhs = self.parse_expression(next_min)?;
+            // `/` is real division in SMT-LIB; integers divide with `div`
+            let (op, sort) = match op {
+                "+" | "-" | "*" => (op, numeric_sort(&lhs, &rhs)),
+                "/" if numeric_sort(&lhs, &rhs) == "Int" => ("div", "Int"),
+                "/" => ("/", "Real"),
+                "mod" => ("mod", "Int"),
+                _ => (op, "Bool"),
+            };
+            lhs = Term::new(format!("({} {} {})", op, lhs.text, rhs.text), sort);
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Term, Error> {
+        match self.next() {
+            Some(Token::Op("not")) => Ok(Term::new(format!("(not {})", self.parse_unary()?.text), "Bool")),
+            Some(Token::Op("-")) => {
+                let operand = self.parse_unary()?;
+                Ok(Term::new(format!("(- {})", operand.text), operand.sort))
+            }
+            Some(Token::Number(n)) => {
+                let sort = if n.contains('.') { "Real" } else { "Int" };
+                Ok(Term::new(n, sort))
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" | "True" => Ok(Term::new("true".to_string(), "Bool")),
+                "false" | "False" => Ok(Term::new("false".to_string(), "Bool")),
+                _ => {
+                    let sort = self.sorts.get(&name).copied().unwrap_or("Real");
+                    Ok(Term::new(smt_symbol(&name)?, sort))
+                }
+            },
+            Some(Token::LParen) => {
+                let inner = self.parse_expression(0)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err(Error::invalid_input("Unbalanced parentheses in expression")),
+                }
+            }
+            Some(token) => Err(Error::invalid_input(format!("Unexpected token {:?} in expression", token))),
+            None => Err(Error::invalid_input("Unexpected end of expression")),
+        }
+    }
+}
+
+// `sorts` maps variable names to their SMT sorts; undeclared names are
+// treated as reals
+pub fn translate_expression(expression: &str, sorts: &HashMap<String, &'static str>) -> Result<String, Error> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens, pos: 0, sorts };
+    let term = parser.parse_expression(0)?;
+
+    if parser.pos < parser.tokens.len() {
+        return Err(Error::invalid_input(format!("Trailing tokens in expression: {}", expression)));
+    }
+
+    Ok(term.text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_invariant(formal_expression: &str, tags: Vec<&str>) -> Invariant {
+        Invariant {
+            id: "inv1".to_string(),
+            formal_expression: formal_expression.to_string(),
+            variables: vec![
+                Variable {
+                    name: "latency_ms".to_string(),
+                    var_type: "Nat".to_string(),
+                    description: "Request latency".to_string(),
+                    unit: "ms".to_string(),
+                    constraints: vec!["latency_ms <= 50".to_string()],
+                }
+            ],
+            tags: tags.into_iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_translate_expression_precedence() {
+        let sorts = HashMap::new();
+        assert_eq!(translate_expression("x + 2 * y <= 10", &sorts).unwrap(), "(<= (+ |x| (* 2 |y|)) 10)");
+        assert_eq!(translate_expression("a > 0 && b ≥ 1", &sorts).unwrap(), "(and (> |a| 0) (>= |b| 1))");
+        assert_eq!(translate_expression("!(x == y)", &sorts).unwrap(), "(not (= |x| |y|))");
+        assert_eq!(translate_expression("p → q → r", &sorts).unwrap(), "(=> |p| (=> |q| |r|))");
+    }
+
+    #[test]
+    fn test_translate_expression_rejects_quantifiers() {
+        let sorts = HashMap::new();
+        assert!(translate_expression("∀x, P(x)", &sorts).is_err());
+        assert!(translate_expression("(x + 1", &sorts).is_err());
+    }
+
+    #[test]
+    fn test_translate_expression_integer_division() {
+        let sorts = HashMap::from([("n".to_string(), "Int"), ("r".to_string(), "Real")]);
+        assert_eq!(translate_expression("n / 2 <= n", &sorts).unwrap(), "(<= (div |n| 2) |n|)");
+        assert_eq!(translate_expression("(n + 1) / 2 > 0", &sorts).unwrap(), "(> (div (+ |n| 1) 2) 0)");
+        assert_eq!(translate_expression("r / 2 <= r", &sorts).unwrap(), "(<= (/ |r| 2) |r|)");
+        assert_eq!(translate_expression("n / 2.5 <= r", &sorts).unwrap(), "(<= (/ |n| 2.5) |r|)");
+    }
+
+    #[test]
+    fn test_to_smtlib() {
+        let script = to_smtlib(&test_invariant("latency_ms < 100", vec!["smt"])).unwrap();
+        assert!(script.contains("(declare-const |latency_ms| Int)"));
+        assert!(script.contains("(assert (>= |latency_ms| 0))"));
+        assert!(script.contains("(assert (<= |latency_ms| 50))"));
+        assert!(script.contains("(assert (not (< |latency_ms| 100)))"));
+        assert!(script.ends_with("(check-sat)\n(get-model)\n"));
+    }
+
+    #[test]
+    fn test_to_smtlib_rejects_unquotable_names() {
+        let mut invariant = test_invariant("latency_ms < 100", vec!["smt"]);
+        invariant.variables[0].name = "x|) (assert false) (|y".to_string();
+        assert!(to_smtlib(&invariant).is_err());
+    }
+
+    #[test]
+    fn test_is_candidate() {
+        assert!(SmtSolver::is_candidate(&test_invariant("x < 1", vec!["Arithmetic"])));
+        assert!(!SmtSolver::is_candidate(&test_invariant("x < 1", vec!["security"])));
+
+        let mut classified = test_invariant("latency_ms < 100", vec!["latency"]);
+        classified.classification = Some(InvariantClassification {
+            category: "performance".to_string(),
+            proof_strategy: "smt".to_string(),
+            ..Default::default()
+        });
+        assert!(SmtSolver::is_candidate(&classified));
+    }
+
+    #[test]
+    fn test_parse_z3_output() {
+        assert_eq!(parse_z3_output("unsat\n"), SmtOutcome::Proven);
+        assert_eq!(parse_z3_output("unknown\n"), SmtOutcome::Unknown("unknown".to_string()));
+        match parse_z3_output("sat\n(model (define-fun x () Int 7))\n") {
+            SmtOutcome::Counterexample(model) => assert!(model.contains("define-fun x")),
+            other => panic!("expected counterexample, got {:?}", other),
+        }
+    }
+}
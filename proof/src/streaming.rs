@@ -0,0 +1,202 @@
+use serde_json::Value;
+
+// Support for the Messages API with `"stream": true`: decoding the
+// server-sent events and recognising when the Lean code the request asked
+// for has fully arrived.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    TextDelta(String),
+    ToolUseStart(String),
+    /// A fragment of a tool call's JSON arguments
+    ToolInputDelta(String),
+    BlockStop,
+    /// Token counts; `message_start` carries the input count and
+    /// `message_delta` the running output count
+    Usage {
+        input_tokens: Option<u32>,
+        output_tokens: Option<u32>,
+    },
+    Stop,
+    Error(String),
+}
+
+/// Splits a byte stream into server-sent events. Chunks may end anywhere,
+/// including inside a UTF-8 sequence, so bytes are buffered until an event
+/// is complete.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<StreamEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = find_event_end(&self.buffer) {
+            let raw: Vec<u8> = self.buffer.drain(..end).collect();
+            let raw = String::from_utf8_lossy(&raw);
+            let data: Vec<&str> = raw
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(str::trim_start)
+                .collect();
+            if let Some(event) = parse_event(&data.join("\n")) {
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+// Index just past the blank line that terminates the first event
+fn find_event_end(buffer: &[u8]) -> Option<usize> {
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| i + 2);
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4);
+    match (lf, crlf) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn parse_event(data: &str) -> Option<StreamEvent> {
+    let event: Value = serde_json::from_str(data).ok()?;
+    let count = |value: &Value| value.as_u64().map(|n| n as u32);
+
+    match event["type"].as_str()? {
+        "content_block_start" if event["content_block"]["type"] == "tool_use" => {
+            Some(StreamEvent::ToolUseStart(event["content_block"]["name"].as_str()?.to_string()))
+        }
+        "content_block_delta" => match event["delta"]["type"].as_str()? {
+            "text_delta" => Some(StreamEvent::TextDelta(event["delta"]["text"].as_str()?.to_string())),
+            "input_json_delta" => Some(StreamEvent::ToolInputDelta(
+                event["delta"]["partial_json"].as_str()?.to_string(),
+            )),
+            _ => None,
+        },
+        "content_block_stop" => Some(StreamEvent::BlockStop),
+        "message_start" => Some(StreamEvent::Usage {
+            input_tokens: count(&event["message"]["usage"]["input_tokens"]),
+            output_tokens: count(&event["message"]["usage"]["output_tokens"]),
+        }),
+        "message_delta" => Some(StreamEvent::Usage {
+            input_tokens: None,
+            output_tokens: count(&event["usage"]["output_tokens"]),
+        }),
+        "message_stop" => Some(StreamEvent::Stop),
+        "error" => Some(StreamEvent::Error(
+            event["error"]["message"].as_str().unwrap_or("Unknown streaming error").to_string(),
+        )),
+        _ => None,
+    }
+}
+
+/// Watches streamed output for the Lean code: the code argument of the
+/// expected tool call, or failing that a closed ```lean fence in the text
+#[derive(Debug)]
+pub struct LeanCodeScanner {
+    tool_name: String,
+    code_argument: String,
+    text: String,
+    tool_input: Option<String>,
+    code: Option<String>,
+}
+
+impl LeanCodeScanner {
+    pub fn new(tool_name: &str, code_argument: &str) -> Self {
+        Self {
+            tool_name: tool_name.to_string(),
+            code_argument: code_argument.to_string(),
+            text: String::new(),
+            tool_input: None,
+            code: None,
+        }
+    }
+
+    pub fn push(&mut self, event: &StreamEvent) {
+        if self.code.is_some() {
+            return;
+        }
+
+        match event {
+            StreamEvent::ToolUseStart(name) if *name == self.tool_name => self.tool_input = Some(String::new()),
+            StreamEvent::ToolInputDelta(fragment) => {
+                if let Some(input) = &mut self.tool_input {
+                    input.push_str(fragment);
+                }
+            }
+            StreamEvent::BlockStop => {
+                if let Some(input) = self.tool_input.take() {
+                    self.code = serde_json::from_str::<Value>(&input)
+                        .ok()
+                        .and_then(|args| args[self.code_argument.as_str()].as_str().map(str::to_string))
+                        .filter(|code| !code.trim().is_empty());
+                }
+            }
+            StreamEvent::TextDelta(text) => {
+                self.text.push_str(text);
+                self.code = fenced_lean_code(&self.text);
+            }
+            _ => {}
+        }
+    }
+
+    /// The complete Lean code, once it has arrived
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    pub fn into_code(self) -> Option<String> {
+        self.code
+    }
+
+    /// Characters received so far, for estimating usage after an early stop
+    pub fn received_len(&self) -> usize {
+        self.text.len() + self.tool_input.as_ref().map_or(0, String::len)
+    }
+}
+
+fn fenced_lean_code(text: &str) -> Option<String> {
+    let start = text.find("```lean")? + "```lean".len();
+    let body = &text[start..];
+    let end = body.find("```")?;
+    Some(body[..end].trim().to_string()).filter(|code| !code.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(stream: &str) -> Vec<StreamEvent> {
+        SseDecoder::default().push(stream.as_bytes())
+    }
+
+    #[test]
+    fn test_tool_call_code_is_complete_at_block_stop() {
+        let events = decode(concat!(
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"tool_use\",\"name\":\"complete_proof\"}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"proof_code\\\": \\\"by \"}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"simp\\\"}\"}}\n\n",
+            "data: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+        ));
+        assert_eq!(events.len(), 4);
+
+        let mut scanner = LeanCodeScanner::new("complete_proof", "proof_code");
+        for event in &events[..3] {
+            scanner.push(event);
+        }
+        assert_eq!(scanner.code(), None);
+        scanner.push(&events[3]);
+        assert_eq!(scanner.code(), Some("by simp"));
+    }
+
+    #[test]
+    fn test_fenced_code_in_text() {
+        let mut scanner = LeanCodeScanner::new("generate_lean_theorem", "lean_code");
+        scanner.push(&StreamEvent::TextDelta("Here it is:\n```lean\ntheorem t : 1 = 1 := ".to_string()));
+        assert_eq!(scanner.code(), None);
+        scanner.push(&StreamEvent::TextDelta("rfl\n```\nDone.".to_string()));
+        assert_eq!(scanner.code(), Some("theorem t : 1 = 1 := rfl"));
+    }
+}
@@ -0,0 +1,716 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::evaluator;
+use crate::proto::proof::v1::*;
+use crate::proto::spec_to_proof::v1::*;
+use crate::smt::{binary_precedence, is_natural, smt_sort, tokenize, Token};
+
+// Property-based test scaffolds for an invariant set: a Rust proptest file
+// and a Python hypothesis file, each with one test per invariant. A test
+// draws the invariant's variables from strategies narrowed by their
+// constraints, assumes the constraints and asserts the formal expression.
+// Tests name the invariant they check, so a failure traces back to the
+// spec; invariants that cannot be translated still get a skipped test
+// saying why.
+
+/// Property cases per test when the request doesn't say
+pub const DEFAULT_CASES: u32 = 256;
+
+/// Bound for integers their constraints leave unbounded
+const DEFAULT_INT_BOUND: i64 = 1_000_000;
+
+const KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "const", "continue", "crate", "def", "del", "dyn",
+    "elif", "else", "enum", "except", "extern", "fn", "finally", "for", "from", "global", "if", "impl", "import",
+    "in", "is", "lambda", "let", "loop", "match", "mod", "move", "mut", "nonlocal", "not", "or", "pass", "pub",
+    "raise", "ref", "return", "self", "static", "struct", "super", "trait", "try", "type", "unsafe", "use",
+    "where", "while", "with", "yield",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Int,
+    Bool,
+    Real,
+}
+
+fn kind(var_type: &str) -> Option<Kind> {
+    match smt_sort(var_type) {
+        "Int" => Some(Kind::Int),
+        "Bool" => Some(Kind::Bool),
+        // Anything else sorts as Real for the solver, but only numbers can
+        // be generated as floats
+        _ => matches!(
+            var_type.to_lowercase().as_str(),
+            "float" | "double" | "real" | "decimal" | "number" | "f32" | "f64"
+        )
+        .then_some(Kind::Real),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Strategy {
+    Int(i64, i64),
+    Bool,
+    // Inclusive bounds
+    Real(i64, i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    // As written, e.g. `0.5`
+    Number(String),
+    Bool(bool),
+    Var(String),
+    Not(Box<Term>),
+    Neg(Box<Term>),
+    Binary(&'static str, Box<Term>, Box<Term>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Language {
+    Rust,
+    Python,
+}
+
+// An invariant translated into the pieces of a property test
+#[derive(Debug)]
+struct Property {
+    // Source name to identifier and kind
+    names: HashMap<String, (String, Kind)>,
+    variables: Vec<(String, Strategy)>,
+    assumptions: Vec<Term>,
+    goal: Term,
+    // Arithmetic is over floats: some variable is real or some literal
+    // has a fraction
+    real: bool,
+}
+
+impl Property {
+    fn translate(invariant: &Invariant) -> Result<Self, String> {
+        let goal = parse(&invariant.formal_expression)?;
+        let mut names = HashMap::new();
+        let mut variables = Vec::new();
+        let mut assumptions = Vec::new();
+
+        for variable in &invariant.variables {
+            let kind = kind(&variable.var_type)
+                .ok_or_else(|| format!("variable {} has unsupported type {}", variable.name, variable.var_type))?;
+            let mut lo = is_natural(&variable.var_type).then_some(0);
+            let mut hi = None;
+            for constraint in &variable.constraints {
+                assumptions.push(parse(constraint)?);
+                if kind != Kind::Bool {
+                    if let Ok(expr) = evaluator::parse(constraint) {
+                        evaluator::tighten_bounds(&expr, &variable.name, &mut lo, &mut hi);
+                    }
+                }
+            }
+
+            // Integer bounds narrow reals too; fractional ones are only assumed
+            let strategy = match kind {
+                Kind::Bool => Strategy::Bool,
+                Kind::Int | Kind::Real => {
+                    // A one-sided bound keeps the default width on the other side
+                    let min = lo.unwrap_or_else(|| {
+                        hi.unwrap_or(DEFAULT_INT_BOUND).saturating_sub(2 * DEFAULT_INT_BOUND).min(-DEFAULT_INT_BOUND)
+                    });
+                    let max = hi.unwrap_or_else(|| {
+                        lo.unwrap_or(-DEFAULT_INT_BOUND).saturating_add(2 * DEFAULT_INT_BOUND).max(DEFAULT_INT_BOUND)
+                    });
+                    if min > max {
+                        return Err(format!("constraints on {} admit no values", variable.name));
+                    }
+                    if kind == Kind::Int {
+                        Strategy::Int(min, max)
+                    } else {
+                        Strategy::Real(min, max)
+                    }
+                }
+            };
+            let identifier = identifier(&variable.name);
+            names.insert(variable.name.clone(), (identifier.clone(), kind));
+            variables.push((identifier, strategy));
+        }
+
+        let mut referenced = Vec::new();
+        for term in assumptions.iter().chain([&goal]) {
+            collect_vars(term, &mut referenced);
+        }
+        if let Some(undeclared) = referenced.iter().find(|name| !names.contains_key(*name)) {
+            return Err(format!("{} is not a declared variable", undeclared));
+        }
+
+        let real = names.values().any(|(_, kind)| *kind == Kind::Real)
+            || assumptions.iter().chain([&goal]).any(has_fraction);
+        Ok(Self {
+            names,
+            variables,
+            assumptions,
+            goal,
+            real,
+        })
+    }
+
+    // A whole assumption or assertion, without the parentheses `render`
+    // puts around a binary operation
+    fn condition(&self, term: &Term, language: Language) -> String {
+        let rendered = self.render(term, language);
+        match term {
+            Term::Binary(..) if rendered.starts_with('(') => rendered[1..rendered.len() - 1].to_string(),
+            _ => rendered,
+        }
+    }
+
+    fn render(&self, term: &Term, language: Language) -> String {
+        let rust = language == Language::Rust;
+        match term {
+            Term::Number(n) if self.real && !n.contains('.') => format!("{}.0", n),
+            Term::Number(n) => n.clone(),
+            Term::Bool(b) if rust => b.to_string(),
+            Term::Bool(true) => "True".to_string(),
+            Term::Bool(false) => "False".to_string(),
+            Term::Var(name) => {
+                let (identifier, kind) = &self.names[name];
+                if rust && self.real && *kind == Kind::Int {
+                    format!("({} as f64)", identifier)
+                } else {
+                    identifier.clone()
+                }
+            }
+            Term::Not(inner) if rust => format!("!{}", self.render(inner, language)),
+            Term::Not(inner) => format!("(not {})", self.render(inner, language)),
+            Term::Neg(inner) => format!("(-{})", self.render(inner, language)),
+            Term::Binary(op, lhs, rhs) => {
+                let (a, b) = (self.render(lhs, language), self.render(rhs, language));
+                // Integer division and remainder follow SMT-LIB (Euclidean);
+                // Python's floor versions only differ for negative divisors
+                match (*op, language) {
+                    ("=>", Language::Rust) => format!("(!{} || {})", a, b),
+                    ("=>", Language::Python) => format!("((not {}) or {})", a, b),
+                    ("/", Language::Rust) if !self.real => format!("i64::div_euclid({}, {})", a, b),
+                    ("mod", Language::Rust) if !self.real => format!("i64::rem_euclid({}, {})", a, b),
+                    ("/", Language::Python) if !self.real => format!("({} // {})", a, b),
+                    _ => format!("({} {} {})", a, operator(op, language), b),
+                }
+            }
+        }
+    }
+}
+
+fn operator(op: &str, language: Language) -> &str {
+    match (op, language) {
+        ("=", _) => "==",
+        ("distinct", _) => "!=",
+        ("mod", _) => "%",
+        ("and", Language::Rust) => "&&",
+        ("or", Language::Rust) => "||",
+        (op, _) => op,
+    }
+}
+
+fn parse(expression: &str) -> Result<Term, String> {
+    let tokens = tokenize(expression).map_err(|e| e.to_string())?;
+    let mut pos = 0;
+    let term = parse_expression(&tokens, &mut pos, 0)?;
+    if pos < tokens.len() {
+        return Err(format!("trailing tokens in expression: {}", expression));
+    }
+    Ok(term)
+}
+
+fn parse_expression(tokens: &[Token], pos: &mut usize, min_precedence: u8) -> Result<Term, String> {
+    let mut lhs = parse_unary(tokens, pos)?;
+
+    while let Some(Token::Op(op)) = tokens.get(*pos) {
+        let precedence = match binary_precedence(op) {
+            Some(p) if p >= min_precedence => p,
+            _ => break,
+        };
+        *pos += 1;
+        let next_min = if *op == "=>" { precedence } else { precedence + 1 };
+        let rhs = parse_expression(tokens, pos, next_min)?;
+        lhs = Term::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+fn parse_unary(tokens: &[Token], pos: &mut usize) -> Result<Term, String> {
+    let token = tokens.get(*pos).cloned();
+    *pos += 1;
+
+    match token {
+        Some(Token::Op("not")) => Ok(Term::Not(Box::new(parse_unary(tokens, pos)?))),
+        Some(Token::Op("-")) => Ok(Term::Neg(Box::new(parse_unary(tokens, pos)?))),
+        Some(Token::Number(n)) => match n.parse::<f64>() {
+            Ok(_) => Ok(Term::Number(n)),
+            Err(_) => Err(format!("invalid number {}", n)),
+        },
+        Some(Token::Ident(name)) => Ok(match name.as_str() {
+            "true" | "True" => Term::Bool(true),
+            "false" | "False" => Term::Bool(false),
+            _ => Term::Var(name),
+        }),
+        Some(Token::LParen) => {
+            let inner = parse_expression(tokens, pos, 0)?;
+            match tokens.get(*pos) {
+                Some(Token::RParen) => {
+                    *pos += 1;
+                    Ok(inner)
+                }
+                _ => Err("unbalanced parentheses in expression".to_string()),
+            }
+        }
+        Some(token) => Err(format!("unexpected token {:?} in expression", token)),
+        None => Err("unexpected end of expression".to_string()),
+    }
+}
+
+fn collect_vars(term: &Term, vars: &mut Vec<String>) {
+    match term {
+        Term::Var(name) => {
+            if !vars.contains(name) {
+                vars.push(name.clone());
+            }
+        }
+        Term::Not(inner) | Term::Neg(inner) => collect_vars(inner, vars),
+        Term::Binary(_, lhs, rhs) => {
+            collect_vars(lhs, vars);
+            collect_vars(rhs, vars);
+        }
+        Term::Number(_) | Term::Bool(_) => {}
+    }
+}
+
+fn has_fraction(term: &Term) -> bool {
+    match term {
+        Term::Number(n) => n.contains('.'),
+        Term::Not(inner) | Term::Neg(inner) => has_fraction(inner),
+        Term::Binary(_, lhs, rhs) => has_fraction(lhs) || has_fraction(rhs),
+        Term::Bool(_) | Term::Var(_) => false,
+    }
+}
+
+// Lowercase snake case, safe as a Rust or Python identifier
+fn identifier(name: &str) -> String {
+    let mut identifier = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            identifier.push(c);
+        } else if !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    let identifier = identifier.trim_matches('_');
+    match identifier.chars().next() {
+        None => "value".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("v_{}", identifier),
+        _ if KEYWORDS.contains(&identifier) => format!("{}_", identifier),
+        _ => identifier.to_string(),
+    }
+}
+
+// One line, for comments and docstrings
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// A JSON string literal is also a valid Rust and Python one
+fn string_literal(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+struct PlannedTest<'a> {
+    name: String,
+    invariant: &'a Invariant,
+    property: Result<Property, String>,
+}
+
+fn plan_tests(invariant_set: &InvariantSet) -> Vec<PlannedTest<'_>> {
+    let mut taken = HashSet::new();
+    invariant_set
+        .invariants
+        .iter()
+        .map(|invariant| {
+            let base = format!("invariant_{}", identifier(&invariant.id.to_lowercase()));
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !taken.insert(name.clone()) {
+                name = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            PlannedTest {
+                name,
+                invariant,
+                property: Property::translate(invariant),
+            }
+        })
+        .collect()
+}
+
+fn scaffolded(test: &PlannedTest, name: &str) -> ScaffoldedTest {
+    ScaffoldedTest {
+        name: name.to_string(),
+        invariant_id: test.invariant.id.clone(),
+        skip_reason: test.property.as_ref().err().cloned().unwrap_or_default(),
+    }
+}
+
+fn header(comment: &str, invariant_set: &InvariantSet) -> String {
+    let mut header = format!(
+        "{c} Property tests generated by Spec-to-Proof from invariant set {} ({}",
+        one_line(&invariant_set.name),
+        invariant_set.id,
+        c = comment
+    );
+    if !invariant_set.content_sha256.is_empty() {
+        header.push_str(&format!(", sha256 {}", invariant_set.content_sha256));
+    }
+    header.push_str(&format!(
+        ").\n{c} Each test checks the invariant it names; adapt the strategies to your own types.\n",
+        c = comment
+    ));
+    header
+}
+
+fn rust_scaffold(invariant_set: &InvariantSet, tests: &[PlannedTest], path: String, cases: u32) -> TestScaffold {
+    let mut properties = String::new();
+    let mut plain = String::new();
+
+    for test in tests {
+        let invariant = test.invariant;
+        let mut doc = String::new();
+        if !invariant.description.is_empty() {
+            doc.push_str(&format!("    /// {}\n", one_line(&invariant.description)));
+        }
+        doc.push_str(&format!("    /// Invariant: {}\n", invariant.id));
+        doc.push_str(&format!("    /// Formal: {}\n", one_line(&invariant.formal_expression)));
+        let message = string_literal(&format!("invariant {} violated", invariant.id));
+
+        match &test.property {
+            Ok(property) if !property.variables.is_empty() => {
+                let args: Vec<String> = property
+                    .variables
+                    .iter()
+                    .map(|(identifier, strategy)| {
+                        let strategy = match strategy {
+                            Strategy::Int(min, max) => format!("{}i64..={}i64", min, max),
+                            Strategy::Bool => "any::<bool>()".to_string(),
+                            Strategy::Real(min, max) => format!("{}.0f64..={}.0f64", min, max),
+                        };
+                        format!("{} in {}", identifier, strategy)
+                    })
+                    .collect();
+                properties.push('\n');
+                properties.push_str(&doc);
+                properties.push_str(&format!("    #[test]\n    fn {}({}) {{\n", test.name, args.join(", ")));
+                for assumption in &property.assumptions {
+                    properties.push_str(&format!(
+                        "        prop_assume!({});\n",
+                        property.condition(assumption, Language::Rust)
+                    ));
+                }
+                properties.push_str(&format!(
+                    "        prop_assert!({}, {});\n    }}\n",
+                    property.condition(&property.goal, Language::Rust),
+                    message
+                ));
+            }
+            // Nothing to generate, so a plain test
+            Ok(property) => {
+                plain.push('\n');
+                plain.push_str(&doc.replace("    ///", "///"));
+                plain.push_str(&format!(
+                    "#[test]\nfn {}() {{\n    assert!({}, {});\n}}\n",
+                    test.name,
+                    property.condition(&property.goal, Language::Rust),
+                    message
+                ));
+            }
+            Err(reason) => {
+                plain.push('\n');
+                plain.push_str(&doc.replace("    ///", "///"));
+                plain.push_str(&format!(
+                    "#[test]\n#[ignore = {}]\nfn {}() {{\n    todo!({});\n}}\n",
+                    string_literal(&format!("not translated: {}", reason)),
+                    test.name,
+                    string_literal(&format!("check invariant {}", invariant.id))
+                ));
+            }
+        }
+    }
+
+    let mut content = header("//", invariant_set);
+    content.push_str("use proptest::prelude::*;\n");
+    if !properties.is_empty() {
+        content.push_str(&format!(
+            "\nproptest! {{\n    #![proptest_config(ProptestConfig::with_cases({}))]\n{}}}\n",
+            cases, properties
+        ));
+    }
+    content.push_str(&plain);
+
+    TestScaffold {
+        framework: TestFramework::Proptest as i32,
+        path,
+        content,
+        tests: tests.iter().map(|test| scaffolded(test, &test.name)).collect(),
+    }
+}
+
+fn python_scaffold(invariant_set: &InvariantSet, tests: &[PlannedTest], path: String, cases: u32) -> TestScaffold {
+    let mut body = String::new();
+
+    for test in tests {
+        let invariant = test.invariant;
+        let name = format!("test_{}", test.name);
+        let mut docstring = format!(
+            "Invariant: {}\n    Formal: {}",
+            invariant.id,
+            one_line(&invariant.formal_expression)
+        );
+        if !invariant.description.is_empty() {
+            docstring = format!("{}\n\n    {}", one_line(&invariant.description), docstring);
+        }
+        let docstring = docstring.replace('\\', "\\\\").replace('"', "\\\"");
+        let message = string_literal(&format!("invariant {} violated", invariant.id));
+
+        body.push_str("\n\n");
+        match &test.property {
+            Ok(property) => {
+                let identifiers: Vec<&str> = property.variables.iter().map(|(identifier, _)| identifier.as_str()).collect();
+                if !property.variables.is_empty() {
+                    let strategies: Vec<String> = property
+                        .variables
+                        .iter()
+                        .map(|(identifier, strategy)| {
+                            let strategy = match strategy {
+                                Strategy::Int(min, max) => format!("st.integers(min_value={}, max_value={})", min, max),
+                                Strategy::Bool => "st.booleans()".to_string(),
+                                Strategy::Real(min, max) => {
+                                    format!("st.floats(min_value={}, max_value={}, allow_nan=False)", min, max)
+                                }
+                            };
+                            format!("{}={}", identifier, strategy)
+                        })
+                        .collect();
+                    body.push_str(&format!("@settings(max_examples={})\n@given({})\n", cases, strategies.join(", ")));
+                }
+                body.push_str(&format!(
+                    "def {}({}):\n    \"\"\"{}\"\"\"\n",
+                    name,
+                    identifiers.join(", "),
+                    docstring
+                ));
+                for assumption in &property.assumptions {
+                    body.push_str(&format!("    assume({})\n", property.condition(assumption, Language::Python)));
+                }
+                body.push_str(&format!(
+                    "    assert {}, {}\n",
+                    property.condition(&property.goal, Language::Python),
+                    message
+                ));
+            }
+            Err(reason) => {
+                body.push_str(&format!(
+                    "@pytest.mark.skip(reason={})\ndef {}():\n    \"\"\"{}\"\"\"\n",
+                    string_literal(&format!("not translated: {}", reason)),
+                    name,
+                    docstring
+                ));
+            }
+        }
+    }
+
+    let mut content = header("#", invariant_set);
+    if tests.iter().any(|test| test.property.is_err()) {
+        content.push_str("import pytest\n");
+    }
+    content.push_str("from hypothesis import assume, given, settings, strategies as st\n");
+    content.push_str(&body);
+
+    TestScaffold {
+        framework: TestFramework::Hypothesis as i32,
+        path,
+        content,
+        tests: tests.iter().map(|test| scaffolded(test, &format!("test_{}", test.name))).collect(),
+    }
+}
+
+/// One scaffold per framework, both when `frameworks` is empty. `cases`
+/// of 0 means `DEFAULT_CASES`.
+pub fn generate_scaffolds(invariant_set: &InvariantSet, frameworks: &[TestFramework], cases: u32) -> Vec<TestScaffold> {
+    let frameworks = match frameworks {
+        [] => &[TestFramework::Proptest, TestFramework::Hypothesis][..],
+        frameworks => frameworks,
+    };
+    let cases = if cases == 0 { DEFAULT_CASES } else { cases };
+    let name = if invariant_set.name.is_empty() { &invariant_set.id } else { &invariant_set.name };
+    let stem = identifier(&name.to_lowercase());
+    let tests = plan_tests(invariant_set);
+
+    frameworks
+        .iter()
+        .filter_map(|framework| match framework {
+            TestFramework::Proptest => {
+                Some(rust_scaffold(invariant_set, &tests, format!("tests/{}_invariants.rs", stem), cases))
+            }
+            TestFramework::Hypothesis => {
+                Some(python_scaffold(invariant_set, &tests, format!("tests/test_{}_invariants.py", stem), cases))
+            }
+            TestFramework::Unspecified => None,
+        })
+        .collect()
+}
+
+/// A patch adding the scaffolds, for `git apply` or a pull request
+/// suggestion
+pub fn suggestion_patch(scaffolds: &[TestScaffold]) -> String {
+    let mut patch = String::new();
+    for scaffold in scaffolds {
+        let lines: Vec<&str> = scaffold.content.lines().collect();
+        patch.push_str(&format!(
+            "diff --git a/{path} b/{path}\nnew file mode 100644\n--- /dev/null\n+++ b/{path}\n@@ -0,0 +1,{} @@\n",
+            lines.len(),
+            path = scaffold.path
+        ));
+        for line in lines {
+            patch.push('+');
+            patch.push_str(line);
+            patch.push('\n');
+        }
+    }
+    patch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, var_type: &str, constraints: &[&str]) -> Variable {
+        Variable {
+            name: name.to_string(),
+            var_type: var_type.to_string(),
+            constraints: constraints.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn invariant_set() -> InvariantSet {
+        InvariantSet {
+            id: "set1".to_string(),
+            name: "Payments API".to_string(),
+            invariants: vec![
+                Invariant {
+                    id: "inv-latency".to_string(),
+                    description: "Retries stay within the latency budget".to_string(),
+                    formal_expression: "enabled → retries * delay_ms <= 5000".to_string(),
+                    variables: vec![
+                        variable("enabled", "bool", &[]),
+                        variable("retries", "Nat", &["retries <= 5"]),
+                        variable("delay_ms", "Nat", &["delay_ms <= 1000"]),
+                    ],
+                    ..Default::default()
+                },
+                Invariant {
+                    id: "inv-ratio".to_string(),
+                    formal_expression: "ratio * total <= total".to_string(),
+                    variables: vec![
+                        variable("ratio", "float", &["ratio >= 0", "ratio <= 1"]),
+                        variable("total", "int", &["total >= 0"]),
+                    ],
+                    ..Default::default()
+                },
+                Invariant {
+                    id: "inv-currency".to_string(),
+                    formal_expression: "currency = currency".to_string(),
+                    variables: vec![variable("currency", "string", &[])],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rust_scaffold() {
+        let scaffolds = generate_scaffolds(&invariant_set(), &[TestFramework::Proptest], 0);
+        assert_eq!(scaffolds.len(), 1);
+        let scaffold = &scaffolds[0];
+        assert_eq!(scaffold.path, "tests/payments_api_invariants.rs");
+
+        let content = &scaffold.content;
+        assert!(content.contains("ProptestConfig::with_cases(256)"));
+        assert!(content.contains(
+            "fn invariant_inv_latency(enabled in any::<bool>(), retries in 0i64..=5i64, delay_ms in 0i64..=1000i64)"
+        ));
+        assert!(content.contains("prop_assert!(!enabled || ((retries * delay_ms) <= 5000), \"invariant inv-latency violated\");"));
+        // Mixed arithmetic is over floats
+        assert!(content.contains("prop_assume!((total as f64) >= 0.0);"));
+        assert!(content.contains("fn invariant_inv_ratio(ratio in 0.0f64..=1.0f64, total in 0i64..=2000000i64)"));
+        assert!(content.contains("prop_assert!((ratio * (total as f64)) <= (total as f64)"));
+        assert!(content.contains("#[ignore = \"not translated: variable currency has unsupported type string\"]"));
+
+        let traced: Vec<(&str, bool)> = scaffold
+            .tests
+            .iter()
+            .map(|test| (test.invariant_id.as_str(), test.skip_reason.is_empty()))
+            .collect();
+        assert_eq!(traced, vec![("inv-latency", true), ("inv-ratio", true), ("inv-currency", false)]);
+    }
+
+    #[test]
+    fn test_python_scaffold_and_patch() {
+        let scaffolds = generate_scaffolds(&invariant_set(), &[], 50);
+        assert_eq!(scaffolds.len(), 2);
+        let scaffold = &scaffolds[1];
+        assert_eq!(scaffold.path, "tests/test_payments_api_invariants.py");
+
+        let content = &scaffold.content;
+        assert!(content.contains("import pytest\n"));
+        assert!(content.contains(
+            "@settings(max_examples=50)\n@given(enabled=st.booleans(), retries=st.integers(min_value=0, max_value=5), delay_ms=st.integers(min_value=0, max_value=1000))\ndef test_invariant_inv_latency(enabled, retries, delay_ms):"
+        ));
+        assert!(content.contains("    assert (not enabled) or ((retries * delay_ms) <= 5000), \"invariant inv-latency violated\"\n"));
+        assert!(content.contains("    assume(ratio <= 1.0)\n"));
+        assert!(content.contains("@pytest.mark.skip(reason=\"not translated: variable currency has unsupported type string\")"));
+        assert_eq!(scaffold.tests[0].name, "test_invariant_inv_latency");
+
+        let patch = suggestion_patch(&scaffolds);
+        assert!(patch.contains("+++ b/tests/payments_api_invariants.rs\n"));
+        assert!(patch.contains("+++ b/tests/test_payments_api_invariants.py\n"));
+        assert_eq!(
+            patch.lines().filter(|line| line.starts_with('+') && !line.starts_with("+++")).count(),
+            scaffolds.iter().map(|scaffold| scaffold.content.lines().count()).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_bounds_and_undeclared_variables() {
+        let property = Property::translate(&Invariant {
+            formal_expression: "x / 2 < limit".to_string(),
+            variables: vec![variable("x", "int", &["x >= 10"]), variable("limit", "int", &["limit <= 5"])],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(property.variables[0].1, Strategy::Int(10, 2_000_010));
+        assert_eq!(property.variables[1].1, Strategy::Int(-1_999_995, 5));
+        assert_eq!(property.condition(&property.goal, Language::Rust), "i64::div_euclid(x, 2) < limit");
+        assert_eq!(property.condition(&property.goal, Language::Python), "(x // 2) < limit");
+
+        let undeclared = Property::translate(&Invariant {
+            formal_expression: "x < y".to_string(),
+            variables: vec![variable("x", "int", &[])],
+            ..Default::default()
+        });
+        assert_eq!(undeclared.unwrap_err(), "y is not a declared variable");
+
+        let empty = Property::translate(&Invariant {
+            formal_expression: "x < 0".to_string(),
+            variables: vec![variable("x", "u32", &["x >= 5", "x <= 3"])],
+            ..Default::default()
+        });
+        assert_eq!(empty.unwrap_err(), "constraints on x admit no values");
+    }
+}
@@ -0,0 +1,42 @@
+use crate::proto::spec_to_proof::v1::LeanTheorem;
+
+/// Object key segment for a toolchain, e.g. "v4.7.0-mathlib-3f1c2a9b4d5e".
+/// Matches the keys lean-farm stores proofs under, so one key purges both.
+pub fn toolchain_key(lean_toolchain: &str, mathlib_commit: &str) -> String {
+    let lean: String = lean_toolchain
+        .rsplit(':')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    let lean = if lean.is_empty() { "unversioned".to_string() } else { lean };
+    match mathlib_commit.get(..12).unwrap_or(mathlib_commit) {
+        "" => format!("{}-no-mathlib", lean),
+        commit => format!("{}-mathlib-{}", lean, commit),
+    }
+}
+
+pub fn theorem_toolchain_key(theorem: &LeanTheorem) -> String {
+    toolchain_key(&theorem.lean_toolchain, &theorem.mathlib_commit)
+}
+
+/// Lean toolchain name for a `LEAN_VERSION` such as "4.7.0"
+pub fn lean_toolchain_for_version(version: &str) -> String {
+    format!("leanprover/lean4:v{}", version.trim_start_matches('v'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toolchain_key() {
+        assert_eq!(
+            toolchain_key("leanprover/lean4:v4.7.0", "3f1c2a9b4d5e6f708192a3b4c5d6e7f8091a2b3c"),
+            "v4.7.0-mathlib-3f1c2a9b4d5e"
+        );
+        assert_eq!(toolchain_key(&lean_toolchain_for_version("4.7.0"), ""), "v4.7.0-no-mathlib");
+        assert_eq!(theorem_toolchain_key(&LeanTheorem::default()), "unversioned-no-mathlib");
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::proto::proof::v1 as proof_proto;
+
+/// Artifact metadata key holding the s3:// location of the proof's transcript
+pub const TRANSCRIPT_LOCATION_METADATA_KEY: &str = "transcript_location";
+/// Artifact metadata key holding the number of attempts in the transcript
+pub const TRANSCRIPT_ATTEMPTS_METADATA_KEY: &str = "transcript_attempts";
+
+/// What was sent to and received from the model in one proof attempt, and
+/// why the attempt failed if it did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttemptTranscript {
+    pub attempt: u32,
+    pub strategy: String,
+    pub model: String,
+    pub prompt: String,
+    pub completion: String,
+    /// Compiler errors on the theorem and problems parsing the completion
+    pub diagnostics: Vec<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Class of the first compiler error in this attempt's proof
+    #[serde(default)]
+    pub failure_class: Option<String>,
+    /// Attempt whose proof this one repairs
+    #[serde(default)]
+    pub repair_of: Option<u32>,
+    /// Input plus output tokens of the model call
+    #[serde(default)]
+    pub tokens: u32,
+    /// Estimated cost of the model call in USD
+    #[serde(default)]
+    pub cost_usd: f64,
+}
+
+/// Every attempt made at proving a theorem, stored under the artifact id
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProofTranscript {
+    pub artifact_id: String,
+    pub theorem_id: String,
+    pub attempts: Vec<AttemptTranscript>,
+}
+
+/// Collects attempts across retries and portfolio strategies, which may run
+/// concurrently
+#[derive(Debug, Default)]
+pub struct TranscriptRecorder {
+    attempts: Mutex<Vec<AttemptTranscript>>,
+}
+
+impl TranscriptRecorder {
+    /// Appends an attempt, numbering it in recording order, and returns its
+    /// number
+    pub fn record(&self, mut attempt: AttemptTranscript) -> u32 {
+        let mut attempts = self.attempts.lock().unwrap();
+        attempt.attempt = attempts.len() as u32 + 1;
+        let number = attempt.attempt;
+        attempts.push(attempt);
+        number
+    }
+
+    pub fn len(&self) -> usize {
+        self.attempts.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn into_transcript(self, artifact_id: &str, theorem_id: &str) -> ProofTranscript {
+        ProofTranscript {
+            artifact_id: artifact_id.to_string(),
+            theorem_id: theorem_id.to_string(),
+            attempts: self.attempts.into_inner().unwrap(),
+        }
+    }
+}
+
+pub fn transcript_key(prefix: &str, artifact_id: &str) -> String {
+    format!("{}{}/transcript.json", prefix, artifact_id)
+}
+
+pub fn record_location(metadata: &mut HashMap<String, String>, location: &str, attempts: usize) {
+    metadata.insert(TRANSCRIPT_LOCATION_METADATA_KEY.to_string(), location.to_string());
+    metadata.insert(TRANSCRIPT_ATTEMPTS_METADATA_KEY.to_string(), attempts.to_string());
+}
+
+impl From<ProofTranscript> for proof_proto::ProofTranscript {
+    fn from(transcript: ProofTranscript) -> Self {
+        Self {
+            theorem_id: transcript.theorem_id,
+            attempts: transcript.attempts.into_iter().map(|attempt| proof_proto::AttemptTranscript {
+                attempt: attempt.attempt,
+                strategy: attempt.strategy,
+                model: attempt.model,
+                prompt: attempt.prompt,
+                completion: attempt.completion,
+                diagnostics: attempt.diagnostics,
+                error: attempt.error.unwrap_or_default(),
+                duration_ms: attempt.duration_ms,
+                failure_class: attempt.failure_class.unwrap_or_default(),
+                repair_of: attempt.repair_of.unwrap_or_default(),
+                tokens: attempt.tokens,
+            }).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_numbers_attempts() {
+        let recorder = TranscriptRecorder::default();
+        assert!(recorder.is_empty());
+        recorder.record(AttemptTranscript {
+            strategy: "simp".to_string(),
+            error: Some("simp: timed out after 60000ms".to_string()),
+            ..Default::default()
+        });
+        recorder.record(AttemptTranscript {
+            strategy: "omega".to_string(),
+            completion: "by omega".to_string(),
+            ..Default::default()
+        });
+
+        let transcript = recorder.into_transcript("proof_t1", "t1");
+        assert_eq!(transcript.attempts.len(), 2);
+        assert_eq!(transcript.attempts[0].attempt, 1);
+        assert_eq!(transcript.attempts[1].attempt, 2);
+        assert_eq!(transcript.attempts[1].strategy, "omega");
+
+        let json = serde_json::to_string(&transcript).unwrap();
+        assert_eq!(serde_json::from_str::<ProofTranscript>(&json).unwrap(), transcript);
+
+        let proto = proof_proto::ProofTranscript::from(transcript);
+        assert_eq!(proto.attempts[0].error, "simp: timed out after 60000ms");
+        assert_eq!(proto.attempts[1].error, "");
+    }
+
+    #[test]
+    fn test_transcript_location() {
+        assert_eq!(transcript_key("transcripts/", "proof_t1"), "transcripts/proof_t1/transcript.json");
+
+        let mut metadata = HashMap::new();
+        record_location(&mut metadata, "s3://bucket/transcripts/proof_t1/transcript.json", 3);
+        assert_eq!(metadata[TRANSCRIPT_ATTEMPTS_METADATA_KEY], "3");
+    }
+}
@@ -0,0 +1,392 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use spec_to_proof_error::Error;
+
+use crate::definitions::SharedDefinitions;
+use crate::proto::spec_to_proof::v1::{InvariantSet, LeanTheorem};
+use crate::ProofConfig;
+
+const MATHLIB_GIT_URL: &str = "https://github.com/leanprover-community/mathlib4.git";
+
+/// Module every theorem module imports, holding what the theorems share
+pub const DEFINITIONS_MODULE: &str = "Definitions";
+
+/// A complete Lake project for an invariant set: one module per theorem
+/// plus a shared definitions module, so `lake build` compiles the whole set
+/// at once and theorems can use each other as lemmas
+#[derive(Debug, Clone, PartialEq)]
+pub struct LakeWorkspace {
+    /// Lake package and root library name
+    pub package: String,
+    /// File contents keyed by path relative to the project root
+    pub files: BTreeMap<PathBuf, String>,
+    /// Module each theorem was placed in, keyed by theorem id
+    pub theorem_modules: BTreeMap<String, String>,
+}
+
+impl LakeWorkspace {
+    pub async fn write_to(&self, root: &Path) -> Result<(), Error> {
+        for (path, contents) in &self.files {
+            let path = root.join(path);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, contents).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the project to `root` and runs `lake build` there
+    pub async fn build_in(&self, root: &Path) -> Result<LakeBuildOutput, Error> {
+        self.write_to(root).await?;
+        let output = tokio::process::Command::new("lake")
+            .arg("build")
+            .current_dir(root)
+            .output()
+            .await?;
+        Ok(LakeBuildOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LakeBuildOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Assembles a `LakeWorkspace` from an invariant set and its compiled
+/// theorems
+pub struct WorkspaceBuilder<'a> {
+    invariant_set: &'a InvariantSet,
+    theorems: Vec<&'a LeanTheorem>,
+    lean_toolchain: String,
+    mathlib_commit: String,
+}
+
+impl<'a> WorkspaceBuilder<'a> {
+    pub fn new(invariant_set: &'a InvariantSet, config: &ProofConfig) -> Self {
+        Self {
+            invariant_set,
+            theorems: Vec::new(),
+            lean_toolchain: config.lean_toolchain.clone(),
+            mathlib_commit: config.mathlib_commit.clone(),
+        }
+    }
+
+    pub fn theorems(mut self, theorems: impl IntoIterator<Item = &'a LeanTheorem>) -> Self {
+        self.theorems.extend(theorems);
+        self
+    }
+
+    pub fn build(self) -> Result<LakeWorkspace, Error> {
+        if self.theorems.is_empty() {
+            return Err(Error::invalid_input(format!("Invariant set {} has no theorems to build", self.invariant_set.id)));
+        }
+        // Every module is compiled by the one toolchain the workspace pins
+        for theorem in &self.theorems {
+            if !theorem.lean_toolchain.is_empty()
+                && (theorem.lean_toolchain != self.lean_toolchain || theorem.mathlib_commit != self.mathlib_commit)
+            {
+                return Err(Error::invalid_input(format!(
+                    "Theorem {} was generated for {} (Mathlib {:?}), not {} (Mathlib {:?})",
+                    theorem.theorem_name, theorem.lean_toolchain, theorem.mathlib_commit,
+                    self.lean_toolchain, self.mathlib_commit
+                )));
+            }
+        }
+
+        let package = package_name(&self.invariant_set.name);
+        let mut taken = BTreeSet::from([DEFINITIONS_MODULE.to_string()]);
+        let modules: Vec<String> = self.theorems
+            .iter()
+            .map(|theorem| {
+                let base = module_name(&theorem.theorem_name);
+                let mut name = base.clone();
+                let mut suffix = 2;
+                while !taken.insert(name.clone()) {
+                    name = format!("{}{}", base, suffix);
+                    suffix += 1;
+                }
+                name
+            })
+            .collect();
+        let references = lemma_references(&self.theorems);
+
+        let mut files = BTreeMap::new();
+        files.insert(PathBuf::from("lean-toolchain"), format!("{}\n", self.lean_toolchain));
+        files.insert(PathBuf::from("lakefile.lean"), self.lakefile(&package));
+        files.insert(
+            PathBuf::from(&package).join(format!("{}.lean", DEFINITIONS_MODULE)),
+            SharedDefinitions::for_set(self.invariant_set)?.to_lean(&package),
+        );
+        for (index, theorem) in self.theorems.iter().enumerate() {
+            let lemmas: Vec<&str> = references[index].iter().map(|&i| modules[i].as_str()).collect();
+            files.insert(
+                PathBuf::from(&package).join(format!("{}.lean", modules[index])),
+                theorem_module(&package, &modules[index], theorem, &lemmas),
+            );
+        }
+
+        let mut root = format!("import {}.{}\n", package, DEFINITIONS_MODULE);
+        for module in &modules {
+            root.push_str(&format!("import {}.{}\n", package, module));
+        }
+        files.insert(PathBuf::from(format!("{}.lean", package)), root);
+
+        Ok(LakeWorkspace {
+            theorem_modules: self.theorems
+                .iter()
+                .zip(&modules)
+                .map(|(theorem, module)| (theorem.id.clone(), format!("{}.{}", package, module)))
+                .collect(),
+            package,
+            files,
+        })
+    }
+
+    fn lakefile(&self, package: &str) -> String {
+        let mathlib_rev = if self.mathlib_commit.is_empty() {
+            String::new()
+        } else {
+            format!(" @ \"{}\"", self.mathlib_commit)
+        };
+        format!(
+            "import Lake\nopen Lake DSL\n\npackage «{package}» where\n\nrequire mathlib from git\n  \"{url}\"{rev}\n\n@[default_target]\nlean_lib «{package}» where\n",
+            package = package,
+            url = MATHLIB_GIT_URL,
+            rev = mathlib_rev,
+        )
+    }
+}
+
+/// Package name for an invariant set, e.g. "Refund policy (v2)" becomes
+/// "RefundPolicyV2"
+pub fn package_name(set_name: &str) -> String {
+    let name = upper_camel(set_name);
+    if name.is_empty() { "InvariantSet".to_string() } else { name }
+}
+
+fn module_name(theorem_name: &str) -> String {
+    let name = upper_camel(theorem_name);
+    if name.is_empty() { "Theorem".to_string() } else { name }
+}
+
+// Lean module names must start with a letter
+pub(crate) fn upper_camel(name: &str) -> String {
+    let camel: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+        })
+        .collect();
+    match camel.chars().next() {
+        Some(first) if first.is_ascii_digit() => format!("T{}", camel),
+        _ => camel,
+    }
+}
+
+// Imports stay at the top of the module; the rest of the theorem goes in
+// its own namespace so theorem names can repeat across modules
+fn theorem_module(package: &str, module: &str, theorem: &LeanTheorem, lemmas: &[&str]) -> String {
+    let mut imports = BTreeSet::from([format!("{}.{}", package, DEFINITIONS_MODULE)]);
+    imports.extend(lemmas.iter().map(|lemma| format!("{}.{}", package, lemma)));
+    let mut body = Vec::new();
+    for line in theorem.lean_code.lines() {
+        match line.trim().strip_prefix("import ") {
+            Some(import) => {
+                imports.insert(import.trim().to_string());
+            }
+            None => body.push(line),
+        }
+    }
+
+    let mut contents: String = imports.iter().map(|import| format!("import {}\n", import)).collect();
+    contents.push_str(&format!("\nnamespace {}.{}\n\nopen {}\n", package, module, package));
+    for lemma in lemmas {
+        contents.push_str(&format!("open {}.{}\n", package, lemma));
+    }
+    contents.push('\n');
+    contents.push_str(body.join("\n").trim());
+    contents.push_str(&format!("\n\nend {}.{}\n", package, module));
+    contents
+}
+
+// For each theorem, the theorems its code mentions by name. A reference
+// that would create an import cycle is dropped, keeping the first-listed
+// theorem's reference.
+fn lemma_references(theorems: &[&LeanTheorem]) -> Vec<Vec<usize>> {
+    let names: HashMap<&str, usize> = theorems
+        .iter()
+        .enumerate()
+        .map(|(index, theorem)| (theorem.theorem_name.as_str(), index))
+        .collect();
+
+    let mut references: Vec<Vec<usize>> = vec![Vec::new(); theorems.len()];
+    for (index, theorem) in theorems.iter().enumerate() {
+        let mentioned: BTreeSet<usize> = theorem.lean_code
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+            .filter(|&word| word != theorem.theorem_name)
+            .filter_map(|word| names.get(word).copied())
+            .collect();
+        for other in mentioned {
+            if !reaches(&references, other, index) {
+                references[index].push(other);
+            }
+        }
+    }
+    references
+}
+
+fn reaches(references: &[Vec<usize>], from: usize, to: usize) -> bool {
+    let mut stack = vec![from];
+    let mut seen = BTreeSet::new();
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+        if seen.insert(node) {
+            stack.extend(&references[node]);
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::spec_to_proof::v1::{Invariant, Variable};
+
+    fn variable(name: &str, var_type: &str) -> Variable {
+        Variable {
+            name: name.to_string(),
+            var_type: var_type.to_string(),
+            description: format!("The {}", name),
+            ..Default::default()
+        }
+    }
+
+    fn theorem(id: &str, name: &str, lean_code: &str) -> LeanTheorem {
+        LeanTheorem {
+            id: id.to_string(),
+            theorem_name: name.to_string(),
+            lean_code: lean_code.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn invariant_set() -> InvariantSet {
+        InvariantSet {
+            id: "set-1".to_string(),
+            name: "Refund policy (v2)".to_string(),
+            invariants: vec![
+                Invariant {
+                    variables: vec![variable("refund_amount", "Nat"), variable("charge_amount", "Nat")],
+                    ..Default::default()
+                },
+                Invariant {
+                    variables: vec![variable("refund_amount", "Nat"), variable("fee_rate", "Real")],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_workspace_layout() {
+        let set = invariant_set();
+        let theorems = vec![
+            theorem("t1", "refund_bound", "import Mathlib.Data.Nat.Basic\n\ntheorem refund_bound : True := trivial"),
+            theorem("t2", "refund_bound", "theorem refund_bound : True := trivial"),
+        ];
+        let workspace = WorkspaceBuilder::new(&set, &ProofConfig::default())
+            .theorems(&theorems)
+            .build()
+            .unwrap();
+
+        assert_eq!(workspace.package, "RefundPolicyV2");
+        let paths: Vec<&str> = workspace.files.keys().map(|path| path.to_str().unwrap()).collect();
+        assert_eq!(paths, vec![
+            "RefundPolicyV2/Definitions.lean",
+            "RefundPolicyV2/RefundBound.lean",
+            "RefundPolicyV2/RefundBound2.lean",
+            "RefundPolicyV2.lean",
+            "lakefile.lean",
+            "lean-toolchain",
+        ]);
+        assert_eq!(workspace.files[Path::new("lean-toolchain")], "leanprover/lean4:v4.7.0\n");
+        assert_eq!(workspace.theorem_modules["t2"], "RefundPolicyV2.RefundBound2");
+
+        let module = &workspace.files[Path::new("RefundPolicyV2/RefundBound.lean")];
+        assert!(module.starts_with("import Mathlib.Data.Nat.Basic\nimport RefundPolicyV2.Definitions\n\nnamespace RefundPolicyV2.RefundBound\n"));
+        assert!(module.ends_with("theorem refund_bound : True := trivial\n\nend RefundPolicyV2.RefundBound\n"));
+    }
+
+    #[test]
+    fn test_definitions_module_declares_variables() {
+        let set = invariant_set();
+        let theorems = vec![theorem("t1", "refund_bound", "theorem refund_bound : True := trivial")];
+        let workspace = WorkspaceBuilder::new(&set, &ProofConfig::default())
+            .theorems(&theorems)
+            .build()
+            .unwrap();
+
+        let definitions = &workspace.files[Path::new("RefundPolicyV2/Definitions.lean")];
+        assert!(definitions.contains("namespace RefundPolicyV2\n"));
+        assert!(definitions.contains("/-- The refund_amount -/\nabbrev RefundAmount := ℕ\n"));
+        assert!(definitions.contains("abbrev ChargeAmount := ℕ\n"));
+        assert!(definitions.contains("import Mathlib.Data.Real.Basic\n"));
+    }
+
+    #[test]
+    fn test_theorems_import_referenced_lemmas() {
+        let set = invariant_set();
+        let theorems = vec![
+            theorem("t1", "fee_le_charge", "theorem fee_le_charge : True := refund_le_charge"),
+            theorem("t2", "refund_le_charge", "theorem refund_le_charge : True := fee_le_charge"),
+        ];
+        let workspace = WorkspaceBuilder::new(&set, &ProofConfig::default())
+            .theorems(&theorems)
+            .build()
+            .unwrap();
+
+        let first = &workspace.files[Path::new("RefundPolicyV2/FeeLeCharge.lean")];
+        assert!(first.contains("import RefundPolicyV2.RefundLeCharge\n"));
+        assert!(first.contains("open RefundPolicyV2.RefundLeCharge\n"));
+        // The reverse reference would be an import cycle
+        let second = &workspace.files[Path::new("RefundPolicyV2/RefundLeCharge.lean")];
+        assert!(!second.contains("import RefundPolicyV2.FeeLeCharge"));
+    }
+
+    #[test]
+    fn test_lakefile_pins_mathlib() {
+        let set = invariant_set();
+        let theorems = vec![theorem("t1", "refund_bound", "theorem refund_bound : True := trivial")];
+        let config = ProofConfig {
+            mathlib_commit: "3f1c2a9b".to_string(),
+            ..Default::default()
+        };
+        let workspace = WorkspaceBuilder::new(&set, &config).theorems(&theorems).build().unwrap();
+
+        let lakefile = &workspace.files[Path::new("lakefile.lean")];
+        assert!(lakefile.contains("package «RefundPolicyV2» where"));
+        assert!(lakefile.contains(&format!("\"{}\" @ \"3f1c2a9b\"", MATHLIB_GIT_URL)));
+    }
+
+    #[test]
+    fn test_rejects_theorems_for_other_toolchains() {
+        let set = invariant_set();
+        let mut stale = theorem("t1", "refund_bound", "theorem refund_bound : True := trivial");
+        stale.lean_toolchain = "leanprover/lean4:v4.3.0".to_string();
+        let theorems = vec![stale];
+        assert!(WorkspaceBuilder::new(&set, &ProofConfig::default()).theorems(&theorems).build().is_err());
+        assert!(WorkspaceBuilder::new(&set, &ProofConfig::default()).build().is_err());
+    }
+}
@@ -139,6 +139,8 @@ async fn test_trivial_invariant_compilation() {
         status: InvariantStatus::Extracted as i32,
         tags: vec!["trivial".to_string(), "arithmetic".to_string()],
         priority: Priority::Low as i32,
+        source_span: None,
+        classification: None,
     };
 
     let options = CompilationOptions {
@@ -147,6 +149,7 @@ async fn test_trivial_invariant_compilation() {
         seed: 42,
         proof_strategy: "simp".to_string(),
         include_dependencies: true,
+        dry_run: false,
     };
 
     // Test compilation time
@@ -199,6 +202,8 @@ async fn test_resnet_invariant_compilation() {
         status: InvariantStatus::Extracted as i32,
         tags: vec!["resnet".to_string(), "neural_network".to_string(), "linear_algebra".to_string()],
         priority: Priority::High as i32,
+        source_span: None,
+        classification: None,
     };
 
     let options = CompilationOptions {
@@ -207,6 +212,7 @@ async fn test_resnet_invariant_compilation() {
         seed: 42,
         proof_strategy: "linear_algebra".to_string(),
         include_dependencies: true,
+        dry_run: false,
     };
 
     // Test that ResNet invariants have appropriate complexity
@@ -238,6 +244,8 @@ theorem test_theorem (n : Nat) : n + 0 = n := by
         compilation_errors: Vec::new(),
         proof_strategy: "simp".to_string(),
         metadata: HashMap::new(),
+        lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+        mathlib_commit: String::new(),
     };
 
     let options = ProofOptions {
@@ -246,6 +254,8 @@ theorem test_theorem (n : Nat) : n + 0 = n := by
         seed: 42,
         max_attempts: 3,
         timeout_seconds: 30,
+        proof_strategy: "simp".to_string(),
+        ..Default::default()
     };
 
     // Test that proof generation respects retry limits
@@ -281,6 +291,8 @@ theorem test_theorem (n : Nat) : n + 0 = n := by
         compilation_errors: Vec::new(),
         proof_strategy: "simp".to_string(),
         metadata: HashMap::new(),
+        lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+        mathlib_commit: String::new(),
     };
 
     let s3_config = S3Config {
@@ -390,6 +402,8 @@ async fn test_benchmark_resnet_example() {
             status: InvariantStatus::Extracted as i32,
             tags: vec!["resnet".to_string(), "neural_network".to_string()],
             priority: Priority::High as i32,
+            source_span: None,
+            classification: None,
         };
         
         // Simulate processing
@@ -510,6 +524,8 @@ async fn test_error_handling() {
         status: InvariantStatus::Unspecified as i32,
         tags: vec![],
         priority: Priority::Unspecified as i32,
+        source_span: None,
+        classification: None,
     };
     
     // Verify that invalid invariants are handled gracefully
@@ -551,6 +567,8 @@ async fn test_concurrent_compilation() {
                 status: InvariantStatus::Extracted as i32,
                 tags: vec!["concurrent".to_string()],
                 priority: Priority::Medium as i32,
+                source_span: None,
+                classification: None,
             };
             
             // Simulate processing time
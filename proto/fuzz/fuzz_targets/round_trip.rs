@@ -62,6 +62,8 @@ fuzz_target!(|data: &[u8]| {
         status: spec_to_proof_proto::InvariantStatus::Extracted,
         tags: vec!["test".to_string()],
         priority: spec_to_proof_proto::Priority::Medium,
+        source_span: None,
+        classification: None,
     };
 
     // Test round-trip for Invariant
@@ -97,6 +99,8 @@ fuzz_target!(|data: &[u8]| {
         compilation_errors: vec![],
         proof_strategy: "simp".to_string(),
         metadata: HashMap::new(),
+        lean_toolchain: "leanprover/lean4:v4.7.0".to_string(),
+        mathlib_commit: String::new(),
     };
 
     // Create test ProofArtifact
@@ -119,6 +123,8 @@ fuzz_target!(|data: &[u8]| {
         proof_strategy: "simp".to_string(),
         confidence_score: 1.0,
         metadata: HashMap::new(),
+        lean_toolchain: lean_theorem.lean_toolchain.clone(),
+        mathlib_commit: lean_theorem.mathlib_commit.clone(),
     };
 
     // Create test BadgeStatus
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use proptest::prelude::*;
+
+use crate::{
+    BadgeState, BadgeStatusModel, DocumentStatus, InvariantClassificationModel, InvariantModel,
+    InvariantStatus, LeanTheoremModel, Priority, ProofArtifactModel, ProofStatus,
+    ResourceUsageModel, SourceSpanModel, SpecDocumentModel, TheoremStatus, VariableModel,
+};
+
+// proptest strategies for the domain models, compiled into the library with
+// the `test-util` feature so other crates can use them in their own property
+// tests: `proptest! { fn my_test(invariant: InvariantModel) { ... } }`.
+
+/// Whole-second timestamps between the epoch and the end of year 9999
+pub fn datetime() -> BoxedStrategy<DateTime<Utc>> {
+    (0..253402300799i64)
+        .prop_map(|timestamp| DateTime::from_timestamp(timestamp, 0).unwrap_or_else(Utc::now))
+        .boxed()
+}
+
+impl Arbitrary for SpecDocumentModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            datetime(),
+            datetime(),
+            any::<HashMap<String, String>>(),
+            any::<i32>(),
+            any::<DocumentStatus>(),
+        )
+            .prop_map(|(id, content_sha256, source_system, source_id, title, content, url, author, created_at, modified_at, metadata, version, status)| {
+                SpecDocumentModel {
+                    id,
+                    content_sha256,
+                    source_system,
+                    source_id,
+                    title,
+                    content,
+                    url,
+                    author,
+                    created_at,
+                    modified_at,
+                    metadata,
+                    version,
+                    status,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for InvariantModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<Vec<VariableModel>>(),
+            any::<HashMap<String, String>>(),
+            any::<f64>(),
+            any::<String>(),
+            datetime(),
+            any::<InvariantStatus>(),
+            any::<Vec<String>>(),
+            (
+                any::<Priority>(),
+                any::<Option<SourceSpanModel>>(),
+                any::<Option<InvariantClassificationModel>>(),
+            ),
+        )
+            .prop_map(|(id, content_sha256, description, formal_expression, natural_language, variables, units, confidence_score, source_document_id, extracted_at, status, tags, (priority, source_span, classification))| {
+                InvariantModel {
+                    id,
+                    content_sha256,
+                    description,
+                    formal_expression,
+                    natural_language,
+                    variables,
+                    units,
+                    confidence_score,
+                    source_document_id,
+                    extracted_at,
+                    status,
+                    tags,
+                    priority,
+                    source_span,
+                    classification,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for InvariantClassificationModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<String>(),
+            any::<Vec<String>>(),
+            prop_oneof![Just("smt".to_string()), Just("lean".to_string())],
+            0.0..=1.0f64,
+        )
+            .prop_map(|(category, secondary_categories, proof_strategy, confidence)| InvariantClassificationModel {
+                category,
+                secondary_categories,
+                proof_strategy,
+                confidence,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for SourceSpanModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (any::<String>(), 0..10_000usize, 0..1_000usize, any::<bool>())
+            .prop_map(|(quote, start_offset, length, verified)| SourceSpanModel {
+                quote,
+                start_offset,
+                end_offset: start_offset + length,
+                verified,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for VariableModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<Vec<String>>(),
+        )
+            .prop_map(|(name, var_type, description, unit, constraints)| {
+                VariableModel {
+                    name,
+                    var_type,
+                    description,
+                    unit,
+                    constraints,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for LeanTheoremModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            datetime(),
+            any::<TheoremStatus>(),
+            any::<Vec<String>>(),
+            prop_oneof![Just("smt".to_string()), Just("lean".to_string())],
+            any::<HashMap<String, String>>(),
+            any::<String>(),
+            any::<String>(),
+        )
+            .prop_map(|(id, content_sha256, theorem_name, lean_code, source_invariant_id, generated_at, status, compilation_errors, proof_strategy, metadata, lean_toolchain, mathlib_commit)| {
+                LeanTheoremModel {
+                    id,
+                    content_sha256,
+                    theorem_name,
+                    lean_code,
+                    source_invariant_id,
+                    generated_at,
+                    status,
+                    compilation_errors,
+                    proof_strategy,
+                    metadata,
+                    lean_toolchain,
+                    mathlib_commit,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for ProofArtifactModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<ProofStatus>(),
+            datetime(),
+            0..3_600_000i64,
+            any::<String>(),
+            any::<Vec<String>>(),
+            any::<ResourceUsageModel>(),
+            prop_oneof![Just("smt".to_string()), Just("lean".to_string())],
+            0.0..=1.0f64,
+            (
+                any::<HashMap<String, String>>(),
+                any::<String>(),
+                any::<String>(),
+            ),
+        )
+            .prop_map(|(id, content_sha256, theorem_id, invariant_id, status, attempted_at, duration_ms, output, logs, resource_usage, proof_strategy, confidence_score, (metadata, lean_toolchain, mathlib_commit))| {
+                ProofArtifactModel {
+                    id,
+                    content_sha256,
+                    theorem_id,
+                    invariant_id,
+                    status,
+                    attempted_at,
+                    duration_ms,
+                    output,
+                    logs,
+                    resource_usage,
+                    proof_strategy,
+                    confidence_score,
+                    metadata,
+                    lean_toolchain,
+                    mathlib_commit,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for ResourceUsageModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (0.0..86_400.0f64, 0..i64::MAX, 0..i64::MAX, 0..i64::MAX)
+            .prop_map(|(cpu_seconds, memory_bytes, disk_bytes, network_bytes)| ResourceUsageModel {
+                cpu_seconds,
+                memory_bytes,
+                disk_bytes,
+                network_bytes,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for BadgeStatusModel {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            1..100_000i32,
+            "[0-9a-f]{40}",
+            any::<BadgeState>(),
+            any::<String>(),
+            any::<String>(),
+            datetime(),
+            datetime(),
+            any::<Vec<String>>(),
+            (0..=1_000i32, 0..=1_000i32),
+        )
+            .prop_map(|(id, content_sha256, repo_owner, repo_name, pr_number, commit_sha, state, description, target_url, created_at, updated_at, proof_artifact_ids, (a, b))| {
+                let (invariants_proven, total_invariants) = (a.min(b), a.max(b));
+                let coverage_percentage = if total_invariants == 0 {
+                    0.0
+                } else {
+                    invariants_proven as f64 * 100.0 / total_invariants as f64
+                };
+                BadgeStatusModel {
+                    id,
+                    content_sha256,
+                    repo_owner,
+                    repo_name,
+                    pr_number,
+                    commit_sha,
+                    state,
+                    description,
+                    target_url,
+                    created_at,
+                    updated_at,
+                    proof_artifact_ids,
+                    coverage_percentage,
+                    invariants_proven,
+                    total_invariants,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for DocumentStatus {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(DocumentStatus::Unspecified),
+            Just(DocumentStatus::Draft),
+            Just(DocumentStatus::Published),
+            Just(DocumentStatus::Archived),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for InvariantStatus {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(InvariantStatus::Unspecified),
+            Just(InvariantStatus::Extracted),
+            Just(InvariantStatus::Confirmed),
+            Just(InvariantStatus::Rejected),
+            Just(InvariantStatus::Proven),
+            Just(InvariantStatus::Failed),
+            Just(InvariantStatus::Stale),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for TheoremStatus {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(TheoremStatus::Unspecified),
+            Just(TheoremStatus::Generated),
+            Just(TheoremStatus::Compiling),
+            Just(TheoremStatus::Compiled),
+            Just(TheoremStatus::Proving),
+            Just(TheoremStatus::Proven),
+            Just(TheoremStatus::Failed),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for ProofStatus {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(ProofStatus::Unspecified),
+            Just(ProofStatus::Pending),
+            Just(ProofStatus::Running),
+            Just(ProofStatus::Success),
+            Just(ProofStatus::Failed),
+            Just(ProofStatus::Timeout),
+            Just(ProofStatus::Error),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for BadgeState {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(BadgeState::Unspecified),
+            Just(BadgeState::Pending),
+            Just(BadgeState::Success),
+            Just(BadgeState::Failure),
+            Just(BadgeState::Error),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Priority {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(Priority::Unspecified),
+            Just(Priority::Low),
+            Just(Priority::Medium),
+            Just(Priority::High),
+            Just(Priority::Critical),
+        ]
+        .boxed()
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use crate::constraint::validate_constraints;
+use crate::{
+    calculate_sha256, generate_id, DocumentStatus, InvariantClassificationModel, InvariantModel,
+    InvariantStatus, LeanTheoremModel, Priority, ProofArtifactModel, ProofStatus,
+    ResourceUsageModel, SourceSpanModel, SpecDocumentModel, TheoremStatus, VariableModel,
+};
+
+// Builders for the domain models. Required fields are arguments of
+// `builder(...)`, so forgetting one is a compile error; everything else has a
+// setter. `build()` fills in `id`, timestamps and `content_sha256` when they
+// were not set explicitly, then validates the result.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl BuildError {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+macro_rules! setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: impl Into<$ty>) -> Self {
+                self.model.$field = $field.into();
+                self
+            }
+        )*
+    };
+}
+
+fn require(field: &'static str, value: &str) -> Result<(), BuildError> {
+    if value.trim().is_empty() {
+        return Err(BuildError::new(field, "is required"));
+    }
+    Ok(())
+}
+
+fn validate_sha256(field: &'static str, value: &str) -> Result<(), BuildError> {
+    if value.len() != 64 || !value.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+        return Err(BuildError::new(field, "must be 64 lowercase hex characters"));
+    }
+    Ok(())
+}
+
+fn validate_unit_interval(field: &'static str, value: f64) -> Result<(), BuildError> {
+    if !(0.0..=1.0).contains(&value) {
+        return Err(BuildError::new(field, format!("must be between 0.0 and 1.0, got {}", value)));
+    }
+    Ok(())
+}
+
+fn fill_identity(id: &mut String, content_sha256: &mut String, content: &str) {
+    if id.is_empty() {
+        *id = generate_id();
+    }
+    if content_sha256.is_empty() {
+        *content_sha256 = calculate_sha256(content);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpecDocumentBuilder {
+    model: SpecDocumentModel,
+}
+
+impl SpecDocumentModel {
+    pub fn builder(
+        source_system: impl Into<String>,
+        source_id: impl Into<String>,
+        content: impl Into<String>,
+    ) -> SpecDocumentBuilder {
+        let now = Utc::now();
+        SpecDocumentBuilder {
+            model: SpecDocumentModel {
+                id: String::new(),
+                content_sha256: String::new(),
+                source_system: source_system.into(),
+                source_id: source_id.into(),
+                title: String::new(),
+                content: content.into(),
+                url: String::new(),
+                author: String::new(),
+                created_at: now,
+                modified_at: now,
+                metadata: HashMap::new(),
+                version: 1,
+                status: DocumentStatus::Draft,
+            },
+        }
+    }
+}
+
+impl SpecDocumentBuilder {
+    setters! {
+        id: String,
+        content_sha256: String,
+        title: String,
+        url: String,
+        author: String,
+        created_at: DateTime<Utc>,
+        modified_at: DateTime<Utc>,
+        metadata: HashMap<String, String>,
+        version: i32,
+        status: DocumentStatus,
+    }
+
+    pub fn metadata_entry(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.model.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(mut self) -> Result<SpecDocumentModel, BuildError> {
+        let model = &mut self.model;
+        fill_identity(&mut model.id, &mut model.content_sha256, &model.content);
+
+        require("source_system", &model.source_system)?;
+        require("source_id", &model.source_id)?;
+        validate_sha256("content_sha256", &model.content_sha256)?;
+        if model.version < 1 {
+            return Err(BuildError::new("version", "must be at least 1"));
+        }
+        if model.modified_at < model.created_at {
+            return Err(BuildError::new("modified_at", "is before created_at"));
+        }
+        Ok(self.model)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvariantBuilder {
+    model: InvariantModel,
+}
+
+impl InvariantModel {
+    pub fn builder(
+        source_document_id: impl Into<String>,
+        description: impl Into<String>,
+        formal_expression: impl Into<String>,
+    ) -> InvariantBuilder {
+        InvariantBuilder {
+            model: InvariantModel {
+                id: String::new(),
+                content_sha256: String::new(),
+                description: description.into(),
+                formal_expression: formal_expression.into(),
+                natural_language: String::new(),
+                variables: Vec::new(),
+                units: HashMap::new(),
+                confidence_score: 1.0,
+                source_document_id: source_document_id.into(),
+                extracted_at: Utc::now(),
+                status: InvariantStatus::Extracted,
+                tags: Vec::new(),
+                priority: Priority::Medium,
+                source_span: None,
+                classification: None,
+            },
+        }
+    }
+}
+
+impl InvariantBuilder {
+    setters! {
+        id: String,
+        content_sha256: String,
+        natural_language: String,
+        variables: Vec<VariableModel>,
+        units: HashMap<String, String>,
+        confidence_score: f64,
+        extracted_at: DateTime<Utc>,
+        status: InvariantStatus,
+        tags: Vec<String>,
+        priority: Priority,
+    }
+
+    pub fn variable(mut self, variable: VariableModel) -> Self {
+        self.model.variables.push(variable);
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.model.tags.push(tag.into());
+        self
+    }
+
+    pub fn source_span(mut self, span: SourceSpanModel) -> Self {
+        self.model.source_span = Some(span);
+        self
+    }
+
+    pub fn classification(mut self, classification: InvariantClassificationModel) -> Self {
+        self.model.classification = Some(classification);
+        self
+    }
+
+    pub fn build(mut self) -> Result<InvariantModel, BuildError> {
+        let model = &mut self.model;
+        fill_identity(&mut model.id, &mut model.content_sha256, &model.formal_expression);
+
+        require("source_document_id", &model.source_document_id)?;
+        require("description", &model.description)?;
+        require("formal_expression", &model.formal_expression)?;
+        validate_sha256("content_sha256", &model.content_sha256)?;
+        validate_unit_interval("confidence_score", model.confidence_score)?;
+        if let Some(span) = &model.source_span {
+            if span.end_offset < span.start_offset {
+                return Err(BuildError::new("source_span", "ends before it starts"));
+            }
+        }
+        if let Some(classification) = &model.classification {
+            validate_unit_interval("classification.confidence", classification.confidence)?;
+        }
+        for variable in &model.variables {
+            require("variables.name", &variable.name)?;
+            validate_constraints(&variable.constraints)
+                .map_err(|e| BuildError::new("variables.constraints", e.to_string()))?;
+        }
+        Ok(self.model)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LeanTheoremBuilder {
+    model: LeanTheoremModel,
+}
+
+impl LeanTheoremModel {
+    pub fn builder(
+        source_invariant_id: impl Into<String>,
+        theorem_name: impl Into<String>,
+        lean_code: impl Into<String>,
+    ) -> LeanTheoremBuilder {
+        LeanTheoremBuilder {
+            model: LeanTheoremModel {
+                id: String::new(),
+                content_sha256: String::new(),
+                theorem_name: theorem_name.into(),
+                lean_code: lean_code.into(),
+                source_invariant_id: source_invariant_id.into(),
+                generated_at: Utc::now(),
+                status: TheoremStatus::Generated,
+                compilation_errors: Vec::new(),
+                proof_strategy: String::new(),
+                metadata: HashMap::new(),
+                lean_toolchain: String::new(),
+                mathlib_commit: String::new(),
+            },
+        }
+    }
+}
+
+impl LeanTheoremBuilder {
+    setters! {
+        id: String,
+        content_sha256: String,
+        generated_at: DateTime<Utc>,
+        status: TheoremStatus,
+        compilation_errors: Vec<String>,
+        proof_strategy: String,
+        metadata: HashMap<String, String>,
+        lean_toolchain: String,
+        mathlib_commit: String,
+    }
+
+    pub fn metadata_entry(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.model.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(mut self) -> Result<LeanTheoremModel, BuildError> {
+        let model = &mut self.model;
+        fill_identity(&mut model.id, &mut model.content_sha256, &model.lean_code);
+
+        require("source_invariant_id", &model.source_invariant_id)?;
+        require("theorem_name", &model.theorem_name)?;
+        require("lean_code", &model.lean_code)?;
+        validate_sha256("content_sha256", &model.content_sha256)?;
+        Ok(self.model)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProofArtifactBuilder {
+    model: ProofArtifactModel,
+}
+
+impl ProofArtifactModel {
+    pub fn builder(theorem_id: impl Into<String>, invariant_id: impl Into<String>) -> ProofArtifactBuilder {
+        ProofArtifactBuilder {
+            model: ProofArtifactModel {
+                id: String::new(),
+                content_sha256: String::new(),
+                theorem_id: theorem_id.into(),
+                invariant_id: invariant_id.into(),
+                status: ProofStatus::Pending,
+                attempted_at: Utc::now(),
+                duration_ms: 0,
+                output: String::new(),
+                logs: Vec::new(),
+                resource_usage: ResourceUsageModel {
+                    cpu_seconds: 0.0,
+                    memory_bytes: 0,
+                    disk_bytes: 0,
+                    network_bytes: 0,
+                },
+                proof_strategy: String::new(),
+                confidence_score: 0.0,
+                metadata: HashMap::new(),
+                lean_toolchain: String::new(),
+                mathlib_commit: String::new(),
+            },
+        }
+    }
+}
+
+impl ProofArtifactBuilder {
+    setters! {
+        id: String,
+        content_sha256: String,
+        status: ProofStatus,
+        attempted_at: DateTime<Utc>,
+        duration_ms: i64,
+        output: String,
+        logs: Vec<String>,
+        resource_usage: ResourceUsageModel,
+        proof_strategy: String,
+        confidence_score: f64,
+        metadata: HashMap<String, String>,
+        lean_toolchain: String,
+        mathlib_commit: String,
+    }
+
+    pub fn build(mut self) -> Result<ProofArtifactModel, BuildError> {
+        let model = &mut self.model;
+        // The same output means different things for different theorems
+        let content = format!("{}\n{}", model.theorem_id, model.output);
+        fill_identity(&mut model.id, &mut model.content_sha256, &content);
+
+        require("theorem_id", &model.theorem_id)?;
+        require("invariant_id", &model.invariant_id)?;
+        validate_sha256("content_sha256", &model.content_sha256)?;
+        validate_unit_interval("confidence_score", model.confidence_score)?;
+        if model.duration_ms < 0 {
+            return Err(BuildError::new("duration_ms", "must not be negative"));
+        }
+        let usage = &model.resource_usage;
+        if usage.cpu_seconds.is_nan() || usage.cpu_seconds < 0.0 || usage.memory_bytes < 0 || usage.disk_bytes < 0 || usage.network_bytes < 0 {
+            return Err(BuildError::new("resource_usage", "must not be negative"));
+        }
+        Ok(self.model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fills_identity_from_content() {
+        let document = SpecDocumentModel::builder("jira", "PROJ-1", "The balance must never be negative")
+            .title("Payments")
+            .build()
+            .unwrap();
+
+        assert!(!document.id.is_empty());
+        assert_eq!(document.content_sha256, calculate_sha256("The balance must never be negative"));
+        assert_eq!(document.created_at, document.modified_at);
+        assert_eq!(document.status, DocumentStatus::Draft);
+
+        let invariant = InvariantModel::builder(&document.id, "Balance is non-negative", "balance >= 0")
+            .priority(Priority::High)
+            .tag("payments")
+            .confidence_score(0.8)
+            .build()
+            .unwrap();
+        assert_eq!(invariant.content_sha256, calculate_sha256("balance >= 0"));
+        assert_eq!(invariant.source_document_id, document.id);
+        assert_eq!(invariant.tags, vec!["payments"]);
+    }
+
+    #[test]
+    fn test_explicit_values_are_kept() {
+        let theorem = LeanTheoremModel::builder("inv-1", "balance_nonneg", "theorem balance_nonneg : True := trivial")
+            .id("thm-1")
+            .content_sha256("a".repeat(64))
+            .status(TheoremStatus::Compiled)
+            .build()
+            .unwrap();
+
+        assert_eq!(theorem.id, "thm-1");
+        assert_eq!(theorem.content_sha256, "a".repeat(64));
+        assert_eq!(theorem.status, TheoremStatus::Compiled);
+    }
+
+    #[test]
+    fn test_validation_errors_name_the_field() {
+        let error = InvariantModel::builder("doc-1", "x", "x > 0").confidence_score(1.5).build().unwrap_err();
+        assert_eq!(error.field, "confidence_score");
+
+        let error = InvariantModel::builder("doc-1", "x", "x > 0").confidence_score(f64::NAN).build().unwrap_err();
+        assert_eq!(error.field, "confidence_score");
+
+        let error = SpecDocumentModel::builder("jira", "PROJ-1", "content")
+            .content_sha256("not-a-hash")
+            .build()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "content_sha256: must be 64 lowercase hex characters");
+
+        let error = ProofArtifactModel::builder("", "inv-1").build().unwrap_err();
+        assert_eq!(error.field, "theorem_id");
+
+        let error = InvariantModel::builder("doc-1", "x", "x > 0")
+            .variable(VariableModel {
+                name: "x".to_string(),
+                var_type: "Nat".to_string(),
+                description: String::new(),
+                unit: String::new(),
+                constraints: vec!["must be small".to_string()],
+            })
+            .build()
+            .unwrap_err();
+        assert_eq!(error.field, "variables.constraints");
+
+        let error = ProofArtifactModel::builder("thm-1", "inv-1").duration_ms(-5).build().unwrap_err();
+        assert_eq!(error.field, "duration_ms");
+    }
+
+    #[test]
+    fn test_artifact_hash_depends_on_theorem() {
+        let first = ProofArtifactModel::builder("thm-1", "inv-1").output("ok").build().unwrap();
+        let second = ProofArtifactModel::builder("thm-2", "inv-1").output("ok").build().unwrap();
+        assert_ne!(first.content_sha256, second.content_sha256);
+        assert_ne!(first.id, second.id);
+    }
+}
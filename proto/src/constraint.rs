@@ -0,0 +1,298 @@
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+
+// The language of variable constraints. A constraint restricts the variable
+// it is declared on, or compares two operands:
+//
+//   positive | negative | non_negative | non_positive | non_zero
+//   range(min, max)            inclusive bounds
+//   len >= n                   length of a string or collection
+//   regex("pattern")           the whole value matches the pattern
+//   latency_ms <= 50           comparison of variables and numbers
+//
+// Shorthands accept `-` and spaces in place of `_`. Parsing then printing
+// a constraint yields its canonical form.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+        }
+    }
+
+    // Longest operators first, so `<=` is not read as `<`
+    fn split(text: &str) -> Option<(&str, Comparison, &str)> {
+        const OPERATORS: [(&str, Comparison); 7] = [
+            ("<=", Comparison::Le),
+            (">=", Comparison::Ge),
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            ("<", Comparison::Lt),
+            (">", Comparison::Gt),
+            ("=", Comparison::Eq),
+        ];
+        OPERATORS.iter().find_map(|(operator, comparison)| {
+            text.find(operator)
+                .map(|index| (&text[..index], *comparison, &text[index + operator.len()..]))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Variable(String),
+    /// As written, e.g. `0.5`
+    Number(String),
+}
+
+impl Operand {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if is_number(text) {
+            Some(Operand::Number(text.to_string()))
+        } else if is_identifier(text) {
+            Some(Operand::Variable(text.to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Variable(name) | Operand::Number(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    Positive,
+    Negative,
+    NonNegative,
+    NonPositive,
+    NonZero,
+    Range { min: String, max: String },
+    Length { comparison: Comparison, value: u64 },
+    Regex(String),
+    Compare { left: Operand, comparison: Comparison, right: Operand },
+}
+
+impl Constraint {
+    /// Variables named by a comparison; the other forms only restrict the
+    /// variable they are declared on
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Constraint::Compare { left, right, .. } => [left, right]
+                .into_iter()
+                .filter_map(|operand| match operand {
+                    Operand::Variable(name) => Some(name.as_str()),
+                    Operand::Number(_) => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintError {
+    pub constraint: String,
+    pub message: String,
+}
+
+impl ConstraintError {
+    fn new(constraint: &str, message: impl Into<String>) -> Self {
+        Self {
+            constraint: constraint.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid constraint {:?}: {}", self.constraint, self.message)
+    }
+}
+
+impl std::error::Error for ConstraintError {}
+
+impl FromStr for Constraint {
+    type Err = ConstraintError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            return Err(ConstraintError::new(text, "is empty"));
+        }
+
+        let shorthand = trimmed.to_lowercase().replace(['-', ' '], "_");
+        match shorthand.as_str() {
+            "positive" => return Ok(Constraint::Positive),
+            "negative" => return Ok(Constraint::Negative),
+            "non_negative" | "nonnegative" => return Ok(Constraint::NonNegative),
+            "non_positive" | "nonpositive" => return Ok(Constraint::NonPositive),
+            "non_zero" | "nonzero" => return Ok(Constraint::NonZero),
+            _ => {}
+        }
+
+        if let Some(arguments) = call_arguments(trimmed, "range") {
+            let bounds: Vec<&str> = arguments.split(',').map(str::trim).collect();
+            let [min, max] = bounds.as_slice() else {
+                return Err(ConstraintError::new(text, "range takes a minimum and a maximum"));
+            };
+            if !is_number(min) || !is_number(max) {
+                return Err(ConstraintError::new(text, "range bounds must be numbers"));
+            }
+            if min.parse::<f64>().unwrap_or(f64::NAN) > max.parse::<f64>().unwrap_or(f64::NAN) {
+                return Err(ConstraintError::new(text, "range minimum exceeds its maximum"));
+            }
+            return Ok(Constraint::Range { min: min.to_string(), max: max.to_string() });
+        }
+
+        if let Some(arguments) = call_arguments(trimmed, "regex") {
+            let pattern = unquote(arguments.trim());
+            if pattern.is_empty() {
+                return Err(ConstraintError::new(text, "regex pattern is empty"));
+            }
+            Regex::new(&pattern).map_err(|e| ConstraintError::new(text, format!("invalid regex: {}", e)))?;
+            return Ok(Constraint::Regex(pattern));
+        }
+
+        let Some((left, comparison, right)) = Comparison::split(trimmed) else {
+            return Err(ConstraintError::new(text, "unknown constraint"));
+        };
+        let left = left.trim();
+        if left.eq_ignore_ascii_case("len") || left.eq_ignore_ascii_case("length") {
+            let value = right
+                .trim()
+                .parse()
+                .map_err(|_| ConstraintError::new(text, "length must be compared with a whole number"))?;
+            return Ok(Constraint::Length { comparison, value });
+        }
+
+        match (Operand::parse(left), Operand::parse(right)) {
+            (Some(left), Some(right)) => Ok(Constraint::Compare { left, comparison, right }),
+            _ => Err(ConstraintError::new(text, "comparisons take a variable or a number on each side")),
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::Positive => write!(f, "positive"),
+            Constraint::Negative => write!(f, "negative"),
+            Constraint::NonNegative => write!(f, "non_negative"),
+            Constraint::NonPositive => write!(f, "non_positive"),
+            Constraint::NonZero => write!(f, "non_zero"),
+            Constraint::Range { min, max } => write!(f, "range({}, {})", min, max),
+            Constraint::Length { comparison, value } => write!(f, "len {} {}", comparison.as_str(), value),
+            Constraint::Regex(pattern) => write!(f, "regex({})", serde_json::Value::String(pattern.clone())),
+            Constraint::Compare { left, comparison, right } => {
+                write!(f, "{} {} {}", left, comparison.as_str(), right)
+            }
+        }
+    }
+}
+
+/// Parses every constraint, returning them in canonical form or the first
+/// that is not part of the language
+pub fn validate_constraints(constraints: &[String]) -> Result<Vec<String>, ConstraintError> {
+    constraints
+        .iter()
+        .map(|constraint| constraint.parse::<Constraint>().map(|parsed| parsed.to_string()))
+        .collect()
+}
+
+// `name(arguments)`, case-insensitively on the name
+fn call_arguments<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    let open = text.find('(')?;
+    if !text[..open].trim().eq_ignore_ascii_case(name) || !text.ends_with(')') {
+        return None;
+    }
+    Some(&text[open + 1..text.len() - 1])
+}
+
+// Double-quoted patterns use JSON escapes; bare ones are taken as written
+fn unquote(text: &str) -> String {
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        serde_json::from_str(text).unwrap_or_else(|_| text[1..text.len() - 1].to_string())
+    } else {
+        text.to_string()
+    }
+}
+
+fn is_number(text: &str) -> bool {
+    !text.is_empty()
+        && text.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'))
+        && text.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(constraint: &str) -> String {
+        constraint.parse::<Constraint>().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parses_each_form_to_canonical_text() {
+        assert_eq!(canonical("Non-Negative"), "non_negative");
+        assert_eq!(canonical("nonzero"), "non_zero");
+        assert_eq!(canonical("RANGE( 0 ,100.5 )"), "range(0, 100.5)");
+        assert_eq!(canonical("len>=1"), "len >= 1");
+        assert_eq!(canonical("length = 3"), "len == 3");
+        assert_eq!(canonical(r#"regex("^[A-Z]{3}$")"#), r#"regex("^[A-Z]{3}$")"#);
+        assert_eq!(canonical("regex(^pay_[a-z0-9]+$)"), r#"regex("^pay_[a-z0-9]+$")"#);
+        assert_eq!(canonical("latency_ms<=50"), "latency_ms <= 50");
+        assert_eq!(canonical("response_time < timeout"), "response_time < timeout");
+        assert_eq!(canonical("-5 <= balance"), "-5 <= balance");
+    }
+
+    #[test]
+    fn test_rejects_unknown_and_malformed_constraints() {
+        for constraint in ["must be fast", "", "range(1)", "range(10, 1)", "range(a, 5)", "len >= 1.5", "regex([)", "a + b <= c"] {
+            assert!(constraint.parse::<Constraint>().is_err(), "{:?} should be rejected", constraint);
+        }
+        let error = "strictly increasing".parse::<Constraint>().unwrap_err();
+        assert_eq!(error.to_string(), "invalid constraint \"strictly increasing\": unknown constraint");
+    }
+
+    #[test]
+    fn test_validate_constraints() {
+        let constraints = vec!["positive".to_string(), "x<10".to_string()];
+        assert_eq!(validate_constraints(&constraints).unwrap(), ["positive", "x < 10"]);
+        assert!(validate_constraints(&["positive".to_string(), "sometimes".to_string()]).is_err());
+        assert_eq!(
+            "amount <= limit".parse::<Constraint>().unwrap().variables(),
+            ["amount", "limit"]
+        );
+    }
+}
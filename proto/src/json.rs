@@ -0,0 +1,909 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    BadgeState, BadgeStatusModel, DocumentStatus, InvariantClassificationModel, InvariantModel,
+    InvariantSetModel, InvariantSetStatus, InvariantStatus, LeanTheoremModel, Priority,
+    ProofArtifactModel, ProofStatus, ResourceUsageModel, SourceSpanModel, SpecDocumentModel,
+    TheoremStatus, VariableModel,
+};
+
+// Canonical proto3 JSON mapping for the domain models: lowerCamelCase field
+// names, enums as value names, int64 as strings, RFC 3339 timestamps and
+// default-valued scalar fields omitted. Parsing also accepts the original
+// proto field names and numeric enum values, as the mapping requires.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtoJsonError {
+    /// Location of the offending value, e.g. `$.invariants[0].priority`
+    pub path: String,
+    pub message: String,
+}
+
+impl ProtoJsonError {
+    fn new(path: &str, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ProtoJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for ProtoJsonError {}
+
+pub trait ProtoJson: Sized {
+    fn to_json(&self) -> Value;
+
+    /// Parses a value found at `path`; used directly for nested messages
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError>;
+
+    fn from_json(value: &Value) -> Result<Self, ProtoJsonError> {
+        Self::from_json_at(value, "$")
+    }
+
+    fn to_json_string(&self) -> String {
+        self.to_json().to_string()
+    }
+
+    fn from_json_str(json: &str) -> Result<Self, ProtoJsonError> {
+        let value: Value = serde_json::from_str(json).map_err(|e| ProtoJsonError::new("$", e.to_string()))?;
+        Self::from_json(&value)
+    }
+}
+
+// Enum values in proto number order, paired with their proto value names
+trait JsonEnum: Clone + PartialEq + Sized + 'static {
+    const VALUES: &'static [(Self, &'static str)];
+}
+
+impl JsonEnum for DocumentStatus {
+    const VALUES: &'static [(Self, &'static str)] = &[
+        (DocumentStatus::Unspecified, "DOCUMENT_STATUS_UNSPECIFIED"),
+        (DocumentStatus::Draft, "DOCUMENT_STATUS_DRAFT"),
+        (DocumentStatus::Published, "DOCUMENT_STATUS_PUBLISHED"),
+        (DocumentStatus::Archived, "DOCUMENT_STATUS_ARCHIVED"),
+    ];
+}
+
+impl JsonEnum for InvariantStatus {
+    const VALUES: &'static [(Self, &'static str)] = &[
+        (InvariantStatus::Unspecified, "INVARIANT_STATUS_UNSPECIFIED"),
+        (InvariantStatus::Extracted, "INVARIANT_STATUS_EXTRACTED"),
+        (InvariantStatus::Confirmed, "INVARIANT_STATUS_CONFIRMED"),
+        (InvariantStatus::Rejected, "INVARIANT_STATUS_REJECTED"),
+        (InvariantStatus::Proven, "INVARIANT_STATUS_PROVEN"),
+        (InvariantStatus::Failed, "INVARIANT_STATUS_FAILED"),
+        (InvariantStatus::Stale, "INVARIANT_STATUS_STALE"),
+    ];
+}
+
+impl JsonEnum for InvariantSetStatus {
+    const VALUES: &'static [(Self, &'static str)] = &[
+        (InvariantSetStatus::Unspecified, "INVARIANT_SET_STATUS_UNSPECIFIED"),
+        (InvariantSetStatus::Draft, "INVARIANT_SET_STATUS_DRAFT"),
+        (InvariantSetStatus::Review, "INVARIANT_SET_STATUS_REVIEW"),
+        (InvariantSetStatus::Approved, "INVARIANT_SET_STATUS_APPROVED"),
+        (InvariantSetStatus::Proven, "INVARIANT_SET_STATUS_PROVEN"),
+        (InvariantSetStatus::Failed, "INVARIANT_SET_STATUS_FAILED"),
+    ];
+}
+
+impl JsonEnum for TheoremStatus {
+    const VALUES: &'static [(Self, &'static str)] = &[
+        (TheoremStatus::Unspecified, "THEOREM_STATUS_UNSPECIFIED"),
+        (TheoremStatus::Generated, "THEOREM_STATUS_GENERATED"),
+        (TheoremStatus::Compiling, "THEOREM_STATUS_COMPILING"),
+        (TheoremStatus::Compiled, "THEOREM_STATUS_COMPILED"),
+        (TheoremStatus::Proving, "THEOREM_STATUS_PROVING"),
+        (TheoremStatus::Proven, "THEOREM_STATUS_PROVEN"),
+        (TheoremStatus::Failed, "THEOREM_STATUS_FAILED"),
+    ];
+}
+
+impl JsonEnum for ProofStatus {
+    const VALUES: &'static [(Self, &'static str)] = &[
+        (ProofStatus::Unspecified, "PROOF_STATUS_UNSPECIFIED"),
+        (ProofStatus::Pending, "PROOF_STATUS_PENDING"),
+        (ProofStatus::Running, "PROOF_STATUS_RUNNING"),
+        (ProofStatus::Success, "PROOF_STATUS_SUCCESS"),
+        (ProofStatus::Failed, "PROOF_STATUS_FAILED"),
+        (ProofStatus::Timeout, "PROOF_STATUS_TIMEOUT"),
+        (ProofStatus::Error, "PROOF_STATUS_ERROR"),
+    ];
+}
+
+impl JsonEnum for BadgeState {
+    const VALUES: &'static [(Self, &'static str)] = &[
+        (BadgeState::Unspecified, "BADGE_STATE_UNSPECIFIED"),
+        (BadgeState::Pending, "BADGE_STATE_PENDING"),
+        (BadgeState::Success, "BADGE_STATE_SUCCESS"),
+        (BadgeState::Failure, "BADGE_STATE_FAILURE"),
+        (BadgeState::Error, "BADGE_STATE_ERROR"),
+    ];
+}
+
+impl JsonEnum for Priority {
+    const VALUES: &'static [(Self, &'static str)] = &[
+        (Priority::Unspecified, "PRIORITY_UNSPECIFIED"),
+        (Priority::Low, "PRIORITY_LOW"),
+        (Priority::Medium, "PRIORITY_MEDIUM"),
+        (Priority::High, "PRIORITY_HIGH"),
+        (Priority::Critical, "PRIORITY_CRITICAL"),
+    ];
+}
+
+fn lower_camel_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut upper_next = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper_next = true;
+        } else if upper_next {
+            result.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn format_timestamp(value: &DateTime<Utc>) -> String {
+    // AutoSi emits 0, 3, 6 or 9 fractional digits, as proto3 JSON requires
+    value.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+// Builds a message object, skipping fields that hold their default value
+#[derive(Default)]
+struct JsonWriter(Map<String, Value>);
+
+impl JsonWriter {
+    fn put(mut self, name: &str, value: Value) -> Self {
+        self.0.insert(lower_camel_case(name), value);
+        self
+    }
+
+    fn string(self, name: &str, value: &str) -> Self {
+        if value.is_empty() {
+            return self;
+        }
+        self.put(name, Value::String(value.to_string()))
+    }
+
+    fn int32(self, name: &str, value: i32) -> Self {
+        if value == 0 {
+            return self;
+        }
+        self.put(name, Value::from(value))
+    }
+
+    fn int64(self, name: &str, value: i64) -> Self {
+        if value == 0 {
+            return self;
+        }
+        self.put(name, Value::String(value.to_string()))
+    }
+
+    fn double(self, name: &str, value: f64) -> Self {
+        if value == 0.0 {
+            return self;
+        }
+        let json = match Number::from_f64(value) {
+            Some(number) => Value::Number(number),
+            None if value.is_nan() => Value::String("NaN".to_string()),
+            None if value > 0.0 => Value::String("Infinity".to_string()),
+            None => Value::String("-Infinity".to_string()),
+        };
+        self.put(name, json)
+    }
+
+    fn boolean(self, name: &str, value: bool) -> Self {
+        if !value {
+            return self;
+        }
+        self.put(name, Value::Bool(value))
+    }
+
+    fn timestamp(self, name: &str, value: &DateTime<Utc>) -> Self {
+        self.put(name, Value::String(format_timestamp(value)))
+    }
+
+    fn strings(self, name: &str, values: &[String]) -> Self {
+        if values.is_empty() {
+            return self;
+        }
+        self.put(name, Value::from(values.to_vec()))
+    }
+
+    fn map(self, name: &str, values: &HashMap<String, String>) -> Self {
+        if values.is_empty() {
+            return self;
+        }
+        let object: Map<String, Value> = values
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        self.put(name, Value::Object(object))
+    }
+
+    fn enumeration<E: JsonEnum>(self, name: &str, value: &E) -> Self {
+        match E::VALUES.iter().position(|(v, _)| v == value) {
+            Some(0) | None => self,
+            Some(index) => self.put(name, Value::String(E::VALUES[index].1.to_string())),
+        }
+    }
+
+    fn message<T: ProtoJson>(self, name: &str, value: &T) -> Self {
+        self.put(name, value.to_json())
+    }
+
+    fn optional_message<T: ProtoJson>(self, name: &str, value: &Option<T>) -> Self {
+        match value {
+            Some(value) => self.message(name, value),
+            None => self,
+        }
+    }
+
+    fn messages<T: ProtoJson>(self, name: &str, values: &[T]) -> Self {
+        if values.is_empty() {
+            return self;
+        }
+        self.put(name, Value::Array(values.iter().map(ProtoJson::to_json).collect()))
+    }
+
+    fn build(self) -> Value {
+        Value::Object(self.0)
+    }
+}
+
+// Reads message fields, accepting either the JSON name or the proto field
+// name, and rejects unknown fields once all known ones have been read
+struct JsonReader<'a> {
+    object: &'a Map<String, Value>,
+    path: String,
+    consumed: Vec<&'a str>,
+}
+
+impl<'a> JsonReader<'a> {
+    fn new(value: &'a Value, path: &str) -> Result<Self, ProtoJsonError> {
+        match value {
+            Value::Object(object) => Ok(Self {
+                object,
+                path: path.to_string(),
+                consumed: Vec::new(),
+            }),
+            _ => Err(ProtoJsonError::new(path, "expected a JSON object")),
+        }
+    }
+
+    fn field_path(&self, name: &str) -> String {
+        format!("{}.{}", self.path, lower_camel_case(name))
+    }
+
+    // Returns None for absent fields and explicit nulls alike
+    fn field(&mut self, name: &str) -> Option<&'a Value> {
+        let json_name = lower_camel_case(name);
+        let mut found = None;
+        for key in [json_name.as_str(), name] {
+            if let Some((key, value)) = self.object.get_key_value(key) {
+                self.consumed.push(key.as_str());
+                found = found.or(Some(value));
+            }
+        }
+        found.filter(|value| !value.is_null())
+    }
+
+    fn string(&mut self, name: &str) -> Result<String, ProtoJsonError> {
+        match self.field(name) {
+            None => Ok(String::new()),
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(_) => Err(ProtoJsonError::new(&self.field_path(name), "expected a string")),
+        }
+    }
+
+    fn integer(&mut self, name: &str) -> Result<i64, ProtoJsonError> {
+        let path = self.field_path(name);
+        match self.field(name) {
+            None => Ok(0),
+            Some(Value::Number(n)) => n
+                .as_i64()
+                .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0 && f.abs() < 9.2e18).map(|f| f as i64))
+                .ok_or_else(|| ProtoJsonError::new(&path, "expected an integer")),
+            Some(Value::String(s)) => s
+                .parse::<i64>()
+                .map_err(|_| ProtoJsonError::new(&path, "expected an integer")),
+            Some(_) => Err(ProtoJsonError::new(&path, "expected an integer")),
+        }
+    }
+
+    fn int32(&mut self, name: &str) -> Result<i32, ProtoJsonError> {
+        let value = self.integer(name)?;
+        i32::try_from(value).map_err(|_| ProtoJsonError::new(&self.field_path(name), "int32 out of range"))
+    }
+
+    fn int64(&mut self, name: &str) -> Result<i64, ProtoJsonError> {
+        self.integer(name)
+    }
+
+    // int64 fields holding character offsets
+    fn offset(&mut self, name: &str) -> Result<usize, ProtoJsonError> {
+        let value = self.integer(name)?;
+        usize::try_from(value).map_err(|_| ProtoJsonError::new(&self.field_path(name), "offset must not be negative"))
+    }
+
+    fn double(&mut self, name: &str) -> Result<f64, ProtoJsonError> {
+        let path = self.field_path(name);
+        match self.field(name) {
+            None => Ok(0.0),
+            Some(Value::Number(n)) => n.as_f64().ok_or_else(|| ProtoJsonError::new(&path, "expected a number")),
+            Some(Value::String(s)) => match s.as_str() {
+                "NaN" => Ok(f64::NAN),
+                "Infinity" => Ok(f64::INFINITY),
+                "-Infinity" => Ok(f64::NEG_INFINITY),
+                other => other.parse::<f64>().map_err(|_| ProtoJsonError::new(&path, "expected a number")),
+            },
+            Some(_) => Err(ProtoJsonError::new(&path, "expected a number")),
+        }
+    }
+
+    fn boolean(&mut self, name: &str) -> Result<bool, ProtoJsonError> {
+        match self.field(name) {
+            None => Ok(false),
+            Some(Value::Bool(b)) => Ok(*b),
+            Some(_) => Err(ProtoJsonError::new(&self.field_path(name), "expected a boolean")),
+        }
+    }
+
+    fn timestamp(&mut self, name: &str) -> Result<DateTime<Utc>, ProtoJsonError> {
+        let path = self.field_path(name);
+        match self.field(name) {
+            None => Ok(DateTime::<Utc>::default()),
+            Some(Value::String(s)) => DateTime::parse_from_rfc3339(s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|e| ProtoJsonError::new(&path, format!("invalid RFC 3339 timestamp: {}", e))),
+            Some(_) => Err(ProtoJsonError::new(&path, "expected an RFC 3339 timestamp string")),
+        }
+    }
+
+    fn strings(&mut self, name: &str) -> Result<Vec<String>, ProtoJsonError> {
+        let path = self.field_path(name);
+        match self.field(name) {
+            None => Ok(Vec::new()),
+            Some(Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| match item {
+                    Value::String(s) => Ok(s.clone()),
+                    _ => Err(ProtoJsonError::new(&format!("{}[{}]", path, i), "expected a string")),
+                })
+                .collect(),
+            Some(_) => Err(ProtoJsonError::new(&path, "expected an array")),
+        }
+    }
+
+    fn map(&mut self, name: &str) -> Result<HashMap<String, String>, ProtoJsonError> {
+        let path = self.field_path(name);
+        match self.field(name) {
+            None => Ok(HashMap::new()),
+            Some(Value::Object(entries)) => entries
+                .iter()
+                .map(|(k, v)| match v {
+                    Value::String(s) => Ok((k.clone(), s.clone())),
+                    _ => Err(ProtoJsonError::new(&format!("{}.{}", path, k), "expected a string")),
+                })
+                .collect(),
+            Some(_) => Err(ProtoJsonError::new(&path, "expected an object")),
+        }
+    }
+
+    fn enumeration<E: JsonEnum>(&mut self, name: &str) -> Result<E, ProtoJsonError> {
+        let path = self.field_path(name);
+        let unspecified = E::VALUES[0].0.clone();
+        match self.field(name) {
+            None => Ok(unspecified),
+            Some(Value::String(s)) => E::VALUES
+                .iter()
+                .find(|(_, value_name)| value_name == s)
+                .map(|(value, _)| value.clone())
+                .ok_or_else(|| ProtoJsonError::new(&path, format!("unknown enum value {}", s))),
+            // Unknown numbers have no model representation; treat them like
+            // the FromProto conversions do
+            Some(Value::Number(n)) => Ok(n
+                .as_u64()
+                .and_then(|i| E::VALUES.get(i as usize))
+                .map(|(value, _)| value.clone())
+                .unwrap_or(unspecified)),
+            Some(_) => Err(ProtoJsonError::new(&path, "expected an enum name or number")),
+        }
+    }
+
+    fn message<T: ProtoJson>(&mut self, name: &str) -> Result<T, ProtoJsonError> {
+        let path = self.field_path(name);
+        match self.field(name) {
+            Some(value) => T::from_json_at(value, &path),
+            None => T::from_json_at(&Value::Object(Map::new()), &path),
+        }
+    }
+
+    fn optional_message<T: ProtoJson>(&mut self, name: &str) -> Result<Option<T>, ProtoJsonError> {
+        let path = self.field_path(name);
+        self.field(name).map(|value| T::from_json_at(value, &path)).transpose()
+    }
+
+    fn messages<T: ProtoJson>(&mut self, name: &str) -> Result<Vec<T>, ProtoJsonError> {
+        let path = self.field_path(name);
+        match self.field(name) {
+            None => Ok(Vec::new()),
+            Some(Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| T::from_json_at(item, &format!("{}[{}]", path, i)))
+                .collect(),
+            Some(_) => Err(ProtoJsonError::new(&path, "expected an array")),
+        }
+    }
+
+    fn finish(self) -> Result<(), ProtoJsonError> {
+        match self.object.keys().find(|key| !self.consumed.contains(&key.as_str())) {
+            Some(unknown) => Err(ProtoJsonError::new(&format!("{}.{}", self.path, unknown), "unknown field")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ProtoJson for SpecDocumentModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("id", &self.id)
+            .string("content_sha256", &self.content_sha256)
+            .string("source_system", &self.source_system)
+            .string("source_id", &self.source_id)
+            .string("title", &self.title)
+            .string("content", &self.content)
+            .string("url", &self.url)
+            .string("author", &self.author)
+            .timestamp("created_at", &self.created_at)
+            .timestamp("modified_at", &self.modified_at)
+            .map("metadata", &self.metadata)
+            .int32("version", self.version)
+            .enumeration("status", &self.status)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = SpecDocumentModel {
+            id: reader.string("id")?,
+            content_sha256: reader.string("content_sha256")?,
+            source_system: reader.string("source_system")?,
+            source_id: reader.string("source_id")?,
+            title: reader.string("title")?,
+            content: reader.string("content")?,
+            url: reader.string("url")?,
+            author: reader.string("author")?,
+            created_at: reader.timestamp("created_at")?,
+            modified_at: reader.timestamp("modified_at")?,
+            metadata: reader.map("metadata")?,
+            version: reader.int32("version")?,
+            status: reader.enumeration("status")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for InvariantModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("id", &self.id)
+            .string("content_sha256", &self.content_sha256)
+            .string("description", &self.description)
+            .string("formal_expression", &self.formal_expression)
+            .string("natural_language", &self.natural_language)
+            .messages("variables", &self.variables)
+            .map("units", &self.units)
+            .double("confidence_score", self.confidence_score)
+            .string("source_document_id", &self.source_document_id)
+            .timestamp("extracted_at", &self.extracted_at)
+            .enumeration("status", &self.status)
+            .strings("tags", &self.tags)
+            .enumeration("priority", &self.priority)
+            .optional_message("source_span", &self.source_span)
+            .optional_message("classification", &self.classification)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = InvariantModel {
+            id: reader.string("id")?,
+            content_sha256: reader.string("content_sha256")?,
+            description: reader.string("description")?,
+            formal_expression: reader.string("formal_expression")?,
+            natural_language: reader.string("natural_language")?,
+            variables: reader.messages("variables")?,
+            units: reader.map("units")?,
+            confidence_score: reader.double("confidence_score")?,
+            source_document_id: reader.string("source_document_id")?,
+            extracted_at: reader.timestamp("extracted_at")?,
+            status: reader.enumeration("status")?,
+            tags: reader.strings("tags")?,
+            priority: reader.enumeration("priority")?,
+            source_span: reader.optional_message("source_span")?,
+            classification: reader.optional_message("classification")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for SourceSpanModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("quote", &self.quote)
+            .int64("start_offset", self.start_offset as i64)
+            .int64("end_offset", self.end_offset as i64)
+            .boolean("verified", self.verified)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = SourceSpanModel {
+            quote: reader.string("quote")?,
+            start_offset: reader.offset("start_offset")?,
+            end_offset: reader.offset("end_offset")?,
+            verified: reader.boolean("verified")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for InvariantClassificationModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("category", &self.category)
+            .strings("secondary_categories", &self.secondary_categories)
+            .string("proof_strategy", &self.proof_strategy)
+            .double("confidence", self.confidence)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = InvariantClassificationModel {
+            category: reader.string("category")?,
+            secondary_categories: reader.strings("secondary_categories")?,
+            proof_strategy: reader.string("proof_strategy")?,
+            confidence: reader.double("confidence")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for VariableModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("name", &self.name)
+            .string("type", &self.var_type)
+            .string("description", &self.description)
+            .string("unit", &self.unit)
+            .strings("constraints", &self.constraints)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = VariableModel {
+            name: reader.string("name")?,
+            var_type: reader.string("type")?,
+            description: reader.string("description")?,
+            unit: reader.string("unit")?,
+            constraints: reader.strings("constraints")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for InvariantSetModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("id", &self.id)
+            .string("content_sha256", &self.content_sha256)
+            .string("name", &self.name)
+            .string("description", &self.description)
+            .messages("invariants", &self.invariants)
+            .strings("source_document_ids", &self.source_document_ids)
+            .timestamp("created_at", &self.created_at)
+            .timestamp("modified_at", &self.modified_at)
+            .enumeration("status", &self.status)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = InvariantSetModel {
+            id: reader.string("id")?,
+            content_sha256: reader.string("content_sha256")?,
+            name: reader.string("name")?,
+            description: reader.string("description")?,
+            invariants: reader.messages("invariants")?,
+            source_document_ids: reader.strings("source_document_ids")?,
+            created_at: reader.timestamp("created_at")?,
+            modified_at: reader.timestamp("modified_at")?,
+            status: reader.enumeration("status")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for LeanTheoremModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("id", &self.id)
+            .string("content_sha256", &self.content_sha256)
+            .string("theorem_name", &self.theorem_name)
+            .string("lean_code", &self.lean_code)
+            .string("source_invariant_id", &self.source_invariant_id)
+            .timestamp("generated_at", &self.generated_at)
+            .enumeration("status", &self.status)
+            .strings("compilation_errors", &self.compilation_errors)
+            .string("proof_strategy", &self.proof_strategy)
+            .map("metadata", &self.metadata)
+            .string("lean_toolchain", &self.lean_toolchain)
+            .string("mathlib_commit", &self.mathlib_commit)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = LeanTheoremModel {
+            id: reader.string("id")?,
+            content_sha256: reader.string("content_sha256")?,
+            theorem_name: reader.string("theorem_name")?,
+            lean_code: reader.string("lean_code")?,
+            source_invariant_id: reader.string("source_invariant_id")?,
+            generated_at: reader.timestamp("generated_at")?,
+            status: reader.enumeration("status")?,
+            compilation_errors: reader.strings("compilation_errors")?,
+            proof_strategy: reader.string("proof_strategy")?,
+            metadata: reader.map("metadata")?,
+            lean_toolchain: reader.string("lean_toolchain")?,
+            mathlib_commit: reader.string("mathlib_commit")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for ProofArtifactModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("id", &self.id)
+            .string("content_sha256", &self.content_sha256)
+            .string("theorem_id", &self.theorem_id)
+            .string("invariant_id", &self.invariant_id)
+            .enumeration("status", &self.status)
+            .timestamp("attempted_at", &self.attempted_at)
+            .int64("duration_ms", self.duration_ms)
+            .string("output", &self.output)
+            .strings("logs", &self.logs)
+            .message("resource_usage", &self.resource_usage)
+            .string("proof_strategy", &self.proof_strategy)
+            .double("confidence_score", self.confidence_score)
+            .map("metadata", &self.metadata)
+            .string("lean_toolchain", &self.lean_toolchain)
+            .string("mathlib_commit", &self.mathlib_commit)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = ProofArtifactModel {
+            id: reader.string("id")?,
+            content_sha256: reader.string("content_sha256")?,
+            theorem_id: reader.string("theorem_id")?,
+            invariant_id: reader.string("invariant_id")?,
+            status: reader.enumeration("status")?,
+            attempted_at: reader.timestamp("attempted_at")?,
+            duration_ms: reader.int64("duration_ms")?,
+            output: reader.string("output")?,
+            logs: reader.strings("logs")?,
+            resource_usage: reader.message("resource_usage")?,
+            proof_strategy: reader.string("proof_strategy")?,
+            confidence_score: reader.double("confidence_score")?,
+            metadata: reader.map("metadata")?,
+            lean_toolchain: reader.string("lean_toolchain")?,
+            mathlib_commit: reader.string("mathlib_commit")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for ResourceUsageModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .double("cpu_seconds", self.cpu_seconds)
+            .int64("memory_bytes", self.memory_bytes)
+            .int64("disk_bytes", self.disk_bytes)
+            .int64("network_bytes", self.network_bytes)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = ResourceUsageModel {
+            cpu_seconds: reader.double("cpu_seconds")?,
+            memory_bytes: reader.int64("memory_bytes")?,
+            disk_bytes: reader.int64("disk_bytes")?,
+            network_bytes: reader.int64("network_bytes")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+impl ProtoJson for BadgeStatusModel {
+    fn to_json(&self) -> Value {
+        JsonWriter::default()
+            .string("id", &self.id)
+            .string("content_sha256", &self.content_sha256)
+            .string("repo_owner", &self.repo_owner)
+            .string("repo_name", &self.repo_name)
+            .int32("pr_number", self.pr_number)
+            .string("commit_sha", &self.commit_sha)
+            .enumeration("state", &self.state)
+            .string("description", &self.description)
+            .string("target_url", &self.target_url)
+            .timestamp("created_at", &self.created_at)
+            .timestamp("updated_at", &self.updated_at)
+            .strings("proof_artifact_ids", &self.proof_artifact_ids)
+            .double("coverage_percentage", self.coverage_percentage)
+            .int32("invariants_proven", self.invariants_proven)
+            .int32("total_invariants", self.total_invariants)
+            .build()
+    }
+
+    fn from_json_at(value: &Value, path: &str) -> Result<Self, ProtoJsonError> {
+        let mut reader = JsonReader::new(value, path)?;
+        let model = BadgeStatusModel {
+            id: reader.string("id")?,
+            content_sha256: reader.string("content_sha256")?,
+            repo_owner: reader.string("repo_owner")?,
+            repo_name: reader.string("repo_name")?,
+            pr_number: reader.int32("pr_number")?,
+            commit_sha: reader.string("commit_sha")?,
+            state: reader.enumeration("state")?,
+            description: reader.string("description")?,
+            target_url: reader.string("target_url")?,
+            created_at: reader.timestamp("created_at")?,
+            updated_at: reader.timestamp("updated_at")?,
+            proof_artifact_ids: reader.strings("proof_artifact_ids")?,
+            coverage_percentage: reader.double("coverage_percentage")?,
+            invariants_proven: reader.int32("invariants_proven")?,
+            total_invariants: reader.int32("total_invariants")?,
+        };
+        reader.finish()?;
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Golden files hold the canonical encoding; parsing and re-encoding them
+    // must be lossless
+    fn assert_golden_round_trip<T: ProtoJson>(golden: &str) -> T {
+        let expected: Value = serde_json::from_str(golden).unwrap();
+        let model = T::from_json(&expected).unwrap();
+        assert_eq!(model.to_json(), expected);
+        model
+    }
+
+    #[test]
+    fn test_spec_document_golden() {
+        let document: SpecDocumentModel =
+            assert_golden_round_trip(include_str!("../testdata/json/spec_document.json"));
+        assert_eq!(document.status, DocumentStatus::Published);
+        assert_eq!(document.version, 3);
+    }
+
+    #[test]
+    fn test_invariant_set_golden() {
+        let set: InvariantSetModel =
+            assert_golden_round_trip(include_str!("../testdata/json/invariant_set.json"));
+        assert_eq!(set.invariants.len(), 2);
+        assert_eq!(set.invariants[0].variables[0].var_type, "Nat");
+        assert_eq!(set.invariants[1].priority, Priority::Critical);
+        let span = set.invariants[0].source_span.as_ref().unwrap();
+        assert_eq!((span.start_offset, span.end_offset, span.verified), (120, 174, true));
+        assert!(set.invariants[1].source_span.is_none());
+        let classification = set.invariants[0].classification.as_ref().unwrap();
+        assert_eq!(classification.category, "data_integrity");
+        assert_eq!(classification.proof_strategy, "lean");
+    }
+
+    #[test]
+    fn test_lean_theorem_golden() {
+        let theorem: LeanTheoremModel =
+            assert_golden_round_trip(include_str!("../testdata/json/lean_theorem.json"));
+        assert_eq!(theorem.status, TheoremStatus::Proven);
+    }
+
+    #[test]
+    fn test_proof_artifact_golden() {
+        let artifact: ProofArtifactModel =
+            assert_golden_round_trip(include_str!("../testdata/json/proof_artifact.json"));
+        assert_eq!(artifact.duration_ms, 1532);
+        assert_eq!(artifact.resource_usage.memory_bytes, 268435456);
+    }
+
+    #[test]
+    fn test_badge_status_golden() {
+        let badge: BadgeStatusModel =
+            assert_golden_round_trip(include_str!("../testdata/json/badge_status.json"));
+        assert_eq!(badge.state, BadgeState::Success);
+    }
+
+    #[test]
+    fn test_accepts_proto_field_names_and_enum_numbers() {
+        let variable = VariableModel::from_json(&json!({
+            "name": "latency_ms",
+            "type": "Nat",
+            "constraints": ["latency_ms <= 50"]
+        }))
+        .unwrap();
+        assert_eq!(variable.var_type, "Nat");
+
+        let usage = ResourceUsageModel::from_json(&json!({
+            "cpu_seconds": 1.5,
+            "memory_bytes": 1024
+        }))
+        .unwrap();
+        assert_eq!(usage.memory_bytes, 1024);
+
+        let artifact = ProofArtifactModel::from_json(&json!({"status": 3, "durationMs": "10"})).unwrap();
+        assert_eq!(artifact.status, ProofStatus::Success);
+        assert_eq!(artifact.duration_ms, 10);
+    }
+
+    #[test]
+    fn test_defaults_are_omitted() {
+        let usage = ResourceUsageModel {
+            cpu_seconds: 0.0,
+            memory_bytes: 4096,
+            disk_bytes: 0,
+            network_bytes: 0,
+        };
+        assert_eq!(usage.to_json(), json!({"memoryBytes": "4096"}));
+    }
+
+    #[test]
+    fn test_errors_carry_paths() {
+        let error = InvariantSetModel::from_json(&json!({
+            "invariants": [{"id": "inv-1"}, {"priority": "PRIORITY_URGENT"}]
+        }))
+        .unwrap_err();
+        assert_eq!(error.path, "$.invariants[1].priority");
+
+        let error = VariableModel::from_json(&json!({"name": "x", "kind": "Nat"})).unwrap_err();
+        assert_eq!(error.path, "$.kind");
+        assert_eq!(error.message, "unknown field");
+    }
+}
@@ -2,6 +2,15 @@ pub mod spec_to_proof {
     tonic::include_proto!("spec_to_proof.v1");
 }
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod arbitrary;
+pub mod builder;
+pub mod constraint;
+pub mod json;
+pub mod pagination;
+pub mod preview;
+pub mod validation;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -57,11 +66,33 @@ pub struct InvariantModel {
     pub status: InvariantStatus,
     pub tags: Vec<String>,
     pub priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_span: Option<SourceSpanModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classification: Option<InvariantClassificationModel>,
+}
+
+// Offsets are character offsets into the original document content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceSpanModel {
+    pub quote: String,
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub verified: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvariantClassificationModel {
+    pub category: String,
+    pub secondary_categories: Vec<String>,
+    pub proof_strategy: String,
+    pub confidence: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VariableModel {
     pub name: String,
+    #[serde(rename = "type", alias = "var_type")]
     pub var_type: String,
     pub description: String,
     pub unit: String,
@@ -93,6 +124,10 @@ pub struct LeanTheoremModel {
     pub compilation_errors: Vec<String>,
     pub proof_strategy: String,
     pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub lean_toolchain: String,
+    #[serde(default)]
+    pub mathlib_commit: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -110,6 +145,10 @@ pub struct ProofArtifactModel {
     pub proof_strategy: String,
     pub confidence_score: f64,
     pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub lean_toolchain: String,
+    #[serde(default)]
+    pub mathlib_commit: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -141,6 +180,7 @@ pub struct BadgeStatusModel {
 
 // Rust enums for better type safety
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum DocumentStatus {
     Unspecified,
     Draft,
@@ -149,6 +189,7 @@ pub enum DocumentStatus {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum InvariantStatus {
     Unspecified,
     Extracted,
@@ -156,9 +197,11 @@ pub enum InvariantStatus {
     Rejected,
     Proven,
     Failed,
+    Stale,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum InvariantSetStatus {
     Unspecified,
     Draft,
@@ -169,6 +212,7 @@ pub enum InvariantSetStatus {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum TheoremStatus {
     Unspecified,
     Generated,
@@ -180,6 +224,7 @@ pub enum TheoremStatus {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum ProofStatus {
     Unspecified,
     Pending,
@@ -191,6 +236,7 @@ pub enum ProofStatus {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum BadgeState {
     Unspecified,
     Pending,
@@ -200,6 +246,7 @@ pub enum BadgeState {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum Priority {
     Unspecified,
     Low,
@@ -284,6 +331,8 @@ impl ToProto for InvariantModel {
             status: self.status.to_proto() as i32,
             tags: self.tags.clone(),
             priority: self.priority.to_proto() as i32,
+            source_span: self.source_span.as_ref().map(|span| span.to_proto()),
+            classification: self.classification.as_ref().map(|classification| classification.to_proto()),
         }
     }
 }
@@ -310,6 +359,63 @@ impl FromProto for InvariantModel {
             status: InvariantStatus::from_proto(proto.status),
             tags: proto.tags,
             priority: Priority::from_proto(proto.priority),
+            source_span: proto.source_span.map(SourceSpanModel::from_proto).transpose()?,
+            classification: proto.classification.map(InvariantClassificationModel::from_proto).transpose()?,
+        })
+    }
+}
+
+impl ToProto for SourceSpanModel {
+    type ProtoType = SourceSpan;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        SourceSpan {
+            quote: self.quote.clone(),
+            start_offset: self.start_offset as i64,
+            end_offset: self.end_offset as i64,
+            verified: self.verified,
+        }
+    }
+}
+
+impl FromProto for SourceSpanModel {
+    type ProtoType = SourceSpan;
+
+    fn from_proto(proto: Self::ProtoType) -> Result<Self, Box<dyn std::error::Error>> {
+        if proto.start_offset < 0 || proto.end_offset < proto.start_offset {
+            return Err(format!("Invalid source span offsets {}..{}", proto.start_offset, proto.end_offset).into());
+        }
+        Ok(SourceSpanModel {
+            quote: proto.quote,
+            start_offset: proto.start_offset as usize,
+            end_offset: proto.end_offset as usize,
+            verified: proto.verified,
+        })
+    }
+}
+
+impl ToProto for InvariantClassificationModel {
+    type ProtoType = InvariantClassification;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        InvariantClassification {
+            category: self.category.clone(),
+            secondary_categories: self.secondary_categories.clone(),
+            proof_strategy: self.proof_strategy.clone(),
+            confidence: self.confidence,
+        }
+    }
+}
+
+impl FromProto for InvariantClassificationModel {
+    type ProtoType = InvariantClassification;
+
+    fn from_proto(proto: Self::ProtoType) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(InvariantClassificationModel {
+            category: proto.category,
+            secondary_categories: proto.secondary_categories,
+            proof_strategy: proto.proof_strategy,
+            confidence: proto.confidence,
         })
     }
 }
@@ -342,6 +448,210 @@ impl FromProto for VariableModel {
     }
 }
 
+impl ToProto for InvariantSetModel {
+    type ProtoType = InvariantSet;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        InvariantSet {
+            id: self.id.clone(),
+            content_sha256: self.content_sha256.clone(),
+            name: self.name.clone(),
+            description: self.description.clone(),
+            invariants: self.invariants.iter().map(|i| i.to_proto()).collect(),
+            source_document_ids: self.source_document_ids.clone(),
+            created_at: Some(self.created_at.into()),
+            modified_at: Some(self.modified_at.into()),
+            status: self.status.to_proto() as i32,
+        }
+    }
+}
+
+impl FromProto for InvariantSetModel {
+    type ProtoType = InvariantSet;
+
+    fn from_proto(proto: Self::ProtoType) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(InvariantSetModel {
+            id: proto.id,
+            content_sha256: proto.content_sha256,
+            name: proto.name,
+            description: proto.description,
+            invariants: proto
+                .invariants
+                .into_iter()
+                .map(InvariantModel::from_proto)
+                .collect::<Result<Vec<_>, _>>()?,
+            source_document_ids: proto.source_document_ids,
+            created_at: proto.created_at.unwrap_or_default().try_into()?,
+            modified_at: proto.modified_at.unwrap_or_default().try_into()?,
+            status: InvariantSetStatus::from_proto(proto.status),
+        })
+    }
+}
+
+impl ToProto for LeanTheoremModel {
+    type ProtoType = LeanTheorem;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        LeanTheorem {
+            id: self.id.clone(),
+            content_sha256: self.content_sha256.clone(),
+            theorem_name: self.theorem_name.clone(),
+            lean_code: self.lean_code.clone(),
+            source_invariant_id: self.source_invariant_id.clone(),
+            generated_at: Some(self.generated_at.into()),
+            status: self.status.to_proto() as i32,
+            compilation_errors: self.compilation_errors.clone(),
+            proof_strategy: self.proof_strategy.clone(),
+            metadata: self.metadata.clone(),
+            lean_toolchain: self.lean_toolchain.clone(),
+            mathlib_commit: self.mathlib_commit.clone(),
+        }
+    }
+}
+
+impl FromProto for LeanTheoremModel {
+    type ProtoType = LeanTheorem;
+
+    fn from_proto(proto: Self::ProtoType) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(LeanTheoremModel {
+            id: proto.id,
+            content_sha256: proto.content_sha256,
+            theorem_name: proto.theorem_name,
+            lean_code: proto.lean_code,
+            source_invariant_id: proto.source_invariant_id,
+            generated_at: proto.generated_at.unwrap_or_default().try_into()?,
+            status: TheoremStatus::from_proto(proto.status),
+            compilation_errors: proto.compilation_errors,
+            proof_strategy: proto.proof_strategy,
+            metadata: proto.metadata,
+            lean_toolchain: proto.lean_toolchain,
+            mathlib_commit: proto.mathlib_commit,
+        })
+    }
+}
+
+impl ToProto for ProofArtifactModel {
+    type ProtoType = ProofArtifact;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        ProofArtifact {
+            id: self.id.clone(),
+            content_sha256: self.content_sha256.clone(),
+            theorem_id: self.theorem_id.clone(),
+            invariant_id: self.invariant_id.clone(),
+            status: self.status.to_proto() as i32,
+            attempted_at: Some(self.attempted_at.into()),
+            duration_ms: self.duration_ms,
+            output: self.output.clone(),
+            logs: self.logs.clone(),
+            resource_usage: Some(self.resource_usage.to_proto()),
+            proof_strategy: self.proof_strategy.clone(),
+            confidence_score: self.confidence_score,
+            metadata: self.metadata.clone(),
+            lean_toolchain: self.lean_toolchain.clone(),
+            mathlib_commit: self.mathlib_commit.clone(),
+        }
+    }
+}
+
+impl FromProto for ProofArtifactModel {
+    type ProtoType = ProofArtifact;
+
+    fn from_proto(proto: Self::ProtoType) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(ProofArtifactModel {
+            id: proto.id,
+            content_sha256: proto.content_sha256,
+            theorem_id: proto.theorem_id,
+            invariant_id: proto.invariant_id,
+            status: ProofStatus::from_proto(proto.status),
+            attempted_at: proto.attempted_at.unwrap_or_default().try_into()?,
+            duration_ms: proto.duration_ms,
+            output: proto.output,
+            logs: proto.logs,
+            resource_usage: ResourceUsageModel::from_proto(proto.resource_usage.unwrap_or_default())?,
+            proof_strategy: proto.proof_strategy,
+            confidence_score: proto.confidence_score,
+            metadata: proto.metadata,
+            lean_toolchain: proto.lean_toolchain,
+            mathlib_commit: proto.mathlib_commit,
+        })
+    }
+}
+
+impl ToProto for ResourceUsageModel {
+    type ProtoType = ResourceUsage;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        ResourceUsage {
+            cpu_seconds: self.cpu_seconds,
+            memory_bytes: self.memory_bytes,
+            disk_bytes: self.disk_bytes,
+            network_bytes: self.network_bytes,
+        }
+    }
+}
+
+impl FromProto for ResourceUsageModel {
+    type ProtoType = ResourceUsage;
+
+    fn from_proto(proto: Self::ProtoType) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(ResourceUsageModel {
+            cpu_seconds: proto.cpu_seconds,
+            memory_bytes: proto.memory_bytes,
+            disk_bytes: proto.disk_bytes,
+            network_bytes: proto.network_bytes,
+        })
+    }
+}
+
+impl ToProto for BadgeStatusModel {
+    type ProtoType = BadgeStatus;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        BadgeStatus {
+            id: self.id.clone(),
+            content_sha256: self.content_sha256.clone(),
+            repo_owner: self.repo_owner.clone(),
+            repo_name: self.repo_name.clone(),
+            pr_number: self.pr_number,
+            commit_sha: self.commit_sha.clone(),
+            state: self.state.to_proto() as i32,
+            description: self.description.clone(),
+            target_url: self.target_url.clone(),
+            created_at: Some(self.created_at.into()),
+            updated_at: Some(self.updated_at.into()),
+            proof_artifact_ids: self.proof_artifact_ids.clone(),
+            coverage_percentage: self.coverage_percentage,
+            invariants_proven: self.invariants_proven,
+            total_invariants: self.total_invariants,
+        }
+    }
+}
+
+impl FromProto for BadgeStatusModel {
+    type ProtoType = BadgeStatus;
+
+    fn from_proto(proto: Self::ProtoType) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(BadgeStatusModel {
+            id: proto.id,
+            content_sha256: proto.content_sha256,
+            repo_owner: proto.repo_owner,
+            repo_name: proto.repo_name,
+            pr_number: proto.pr_number,
+            commit_sha: proto.commit_sha,
+            state: BadgeState::from_proto(proto.state),
+            description: proto.description,
+            target_url: proto.target_url,
+            created_at: proto.created_at.unwrap_or_default().try_into()?,
+            updated_at: proto.updated_at.unwrap_or_default().try_into()?,
+            proof_artifact_ids: proto.proof_artifact_ids,
+            coverage_percentage: proto.coverage_percentage,
+            invariants_proven: proto.invariants_proven,
+            total_invariants: proto.total_invariants,
+        })
+    }
+}
+
 // Enum conversion implementations
 impl ToProto for DocumentStatus {
     type ProtoType = i32;
@@ -381,6 +691,7 @@ impl ToProto for InvariantStatus {
             InvariantStatus::Rejected => 3,
             InvariantStatus::Proven => 4,
             InvariantStatus::Failed => 5,
+            InvariantStatus::Stale => 6,
         }
     }
 }
@@ -396,6 +707,7 @@ impl FromProto for InvariantStatus {
             3 => InvariantStatus::Rejected,
             4 => InvariantStatus::Proven,
             5 => InvariantStatus::Failed,
+            6 => InvariantStatus::Stale,
             _ => InvariantStatus::Unspecified,
         }
     }
@@ -430,6 +742,132 @@ impl FromProto for Priority {
     }
 }
 
+impl ToProto for InvariantSetStatus {
+    type ProtoType = i32;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        match self {
+            InvariantSetStatus::Unspecified => 0,
+            InvariantSetStatus::Draft => 1,
+            InvariantSetStatus::Review => 2,
+            InvariantSetStatus::Approved => 3,
+            InvariantSetStatus::Proven => 4,
+            InvariantSetStatus::Failed => 5,
+        }
+    }
+}
+
+impl FromProto for InvariantSetStatus {
+    type ProtoType = i32;
+
+    fn from_proto(proto: Self::ProtoType) -> Self {
+        match proto {
+            0 => InvariantSetStatus::Unspecified,
+            1 => InvariantSetStatus::Draft,
+            2 => InvariantSetStatus::Review,
+            3 => InvariantSetStatus::Approved,
+            4 => InvariantSetStatus::Proven,
+            5 => InvariantSetStatus::Failed,
+            _ => InvariantSetStatus::Unspecified,
+        }
+    }
+}
+
+impl ToProto for TheoremStatus {
+    type ProtoType = i32;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        match self {
+            TheoremStatus::Unspecified => 0,
+            TheoremStatus::Generated => 1,
+            TheoremStatus::Compiling => 2,
+            TheoremStatus::Compiled => 3,
+            TheoremStatus::Proving => 4,
+            TheoremStatus::Proven => 5,
+            TheoremStatus::Failed => 6,
+        }
+    }
+}
+
+impl FromProto for TheoremStatus {
+    type ProtoType = i32;
+
+    fn from_proto(proto: Self::ProtoType) -> Self {
+        match proto {
+            0 => TheoremStatus::Unspecified,
+            1 => TheoremStatus::Generated,
+            2 => TheoremStatus::Compiling,
+            3 => TheoremStatus::Compiled,
+            4 => TheoremStatus::Proving,
+            5 => TheoremStatus::Proven,
+            6 => TheoremStatus::Failed,
+            _ => TheoremStatus::Unspecified,
+        }
+    }
+}
+
+impl ToProto for ProofStatus {
+    type ProtoType = i32;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        match self {
+            ProofStatus::Unspecified => 0,
+            ProofStatus::Pending => 1,
+            ProofStatus::Running => 2,
+            ProofStatus::Success => 3,
+            ProofStatus::Failed => 4,
+            ProofStatus::Timeout => 5,
+            ProofStatus::Error => 6,
+        }
+    }
+}
+
+impl FromProto for ProofStatus {
+    type ProtoType = i32;
+
+    fn from_proto(proto: Self::ProtoType) -> Self {
+        match proto {
+            0 => ProofStatus::Unspecified,
+            1 => ProofStatus::Pending,
+            2 => ProofStatus::Running,
+            3 => ProofStatus::Success,
+            4 => ProofStatus::Failed,
+            5 => ProofStatus::Timeout,
+            6 => ProofStatus::Error,
+            _ => ProofStatus::Unspecified,
+        }
+    }
+}
+
+impl ToProto for BadgeState {
+    type ProtoType = i32;
+
+    fn to_proto(&self) -> Self::ProtoType {
+        match self {
+            BadgeState::Unspecified => 0,
+            BadgeState::Pending => 1,
+            BadgeState::Success => 2,
+            BadgeState::Failure => 3,
+            BadgeState::Error => 4,
+        }
+    }
+}
+
+impl FromProto for BadgeState {
+    type ProtoType = i32;
+
+    fn from_proto(proto: Self::ProtoType) -> Self {
+        match proto {
+            0 => BadgeState::Unspecified,
+            1 => BadgeState::Pending,
+            2 => BadgeState::Success,
+            3 => BadgeState::Failure,
+            4 => BadgeState::Error,
+            _ => BadgeState::Unspecified,
+        }
+    }
+}
+
 // Utility functions for SHA256 hashing
 pub fn calculate_sha256(content: &str) -> String {
     let mut hasher = Sha256::new();
@@ -478,7 +916,7 @@ pub fn generate_json_schema() -> serde_json::Value {
                     "source_id": {"type": "string"},
                     "title": {"type": "string"},
                     "content": {"type": "string"},
-                    "url": {"type": "string", "format": "uri"},
+                    "url": {"type": "string", "anyOf": [{"const": ""}, {"format": "uri"}]},
                     "author": {"type": "string"},
                     "created_at": {"type": "string", "format": "date-time"},
                     "modified_at": {"type": "string", "format": "date-time"},
@@ -507,7 +945,29 @@ pub fn generate_json_schema() -> serde_json::Value {
                     "extracted_at": {"type": "string", "format": "date-time"},
                     "status": {"$ref": "#/definitions/InvariantStatus"},
                     "tags": {"type": "array", "items": {"type": "string"}},
-                    "priority": {"$ref": "#/definitions/Priority"}
+                    "priority": {"$ref": "#/definitions/Priority"},
+                    "source_span": {"$ref": "#/definitions/SourceSpan"},
+                    "classification": {"$ref": "#/definitions/InvariantClassification"}
+                }
+            },
+            "InvariantClassification": {
+                "type": "object",
+                "required": ["category"],
+                "properties": {
+                    "category": {"type": "string"},
+                    "secondary_categories": {"type": "array", "items": {"type": "string"}},
+                    "proof_strategy": {"type": "string", "enum": ["smt", "lean"]},
+                    "confidence": {"type": "number", "minimum": 0.0, "maximum": 1.0}
+                }
+            },
+            "SourceSpan": {
+                "type": "object",
+                "required": ["quote"],
+                "properties": {
+                    "quote": {"type": "string"},
+                    "start_offset": {"type": "integer", "minimum": 0},
+                    "end_offset": {"type": "integer", "minimum": 0},
+                    "verified": {"type": "boolean"}
                 }
             },
             "Variable": {
@@ -523,11 +983,111 @@ pub fn generate_json_schema() -> serde_json::Value {
             },
             "InvariantStatus": {
                 "type": "string",
-                "enum": ["unspecified", "extracted", "confirmed", "rejected", "proven", "failed"]
+                "enum": ["unspecified", "extracted", "confirmed", "rejected", "proven", "failed", "stale"]
             },
             "Priority": {
                 "type": "string",
                 "enum": ["unspecified", "low", "medium", "high", "critical"]
+            },
+            "InvariantSet": {
+                "type": "object",
+                "required": ["id", "content_sha256", "name", "invariants"],
+                "properties": {
+                    "id": {"type": "string"},
+                    "content_sha256": {"type": "string", "pattern": "^[a-fA-F0-9]{64}$"},
+                    "name": {"type": "string", "minLength": 1},
+                    "description": {"type": "string"},
+                    "invariants": {"type": "array", "items": {"$ref": "#/definitions/Invariant"}},
+                    "source_document_ids": {"type": "array", "items": {"type": "string"}},
+                    "created_at": {"type": "string", "format": "date-time"},
+                    "modified_at": {"type": "string", "format": "date-time"},
+                    "status": {"$ref": "#/definitions/InvariantSetStatus"}
+                }
+            },
+            "InvariantSetStatus": {
+                "type": "string",
+                "enum": ["unspecified", "draft", "review", "approved", "proven", "failed"]
+            },
+            "LeanTheorem": {
+                "type": "object",
+                "required": ["id", "content_sha256", "theorem_name", "lean_code", "source_invariant_id"],
+                "properties": {
+                    "id": {"type": "string"},
+                    "content_sha256": {"type": "string", "pattern": "^[a-fA-F0-9]{64}$"},
+                    "theorem_name": {"type": "string", "minLength": 1},
+                    "lean_code": {"type": "string", "minLength": 1},
+                    "source_invariant_id": {"type": "string"},
+                    "generated_at": {"type": "string", "format": "date-time"},
+                    "status": {"$ref": "#/definitions/TheoremStatus"},
+                    "compilation_errors": {"type": "array", "items": {"type": "string"}},
+                    "proof_strategy": {"type": "string"},
+                    "metadata": {"type": "object", "additionalProperties": {"type": "string"}},
+                    "lean_toolchain": {"type": "string"},
+                    "mathlib_commit": {"type": "string"}
+                }
+            },
+            "TheoremStatus": {
+                "type": "string",
+                "enum": ["unspecified", "generated", "compiling", "compiled", "proving", "proven", "failed"]
+            },
+            "ProofArtifact": {
+                "type": "object",
+                "required": ["id", "content_sha256", "theorem_id", "invariant_id", "status"],
+                "properties": {
+                    "id": {"type": "string"},
+                    "content_sha256": {"type": "string", "pattern": "^[a-fA-F0-9]{64}$"},
+                    "theorem_id": {"type": "string"},
+                    "invariant_id": {"type": "string"},
+                    "status": {"$ref": "#/definitions/ProofStatus"},
+                    "attempted_at": {"type": "string", "format": "date-time"},
+                    "duration_ms": {"type": "integer", "minimum": 0},
+                    "output": {"type": "string"},
+                    "logs": {"type": "array", "items": {"type": "string"}},
+                    "resource_usage": {"$ref": "#/definitions/ResourceUsage"},
+                    "proof_strategy": {"type": "string"},
+                    "confidence_score": {"type": "number", "minimum": 0.0, "maximum": 1.0},
+                    "metadata": {"type": "object", "additionalProperties": {"type": "string"}},
+                    "lean_toolchain": {"type": "string"},
+                    "mathlib_commit": {"type": "string"}
+                }
+            },
+            "ProofStatus": {
+                "type": "string",
+                "enum": ["unspecified", "pending", "running", "success", "failed", "timeout", "error"]
+            },
+            "ResourceUsage": {
+                "type": "object",
+                "properties": {
+                    "cpu_seconds": {"type": "number", "minimum": 0.0},
+                    "memory_bytes": {"type": "integer", "minimum": 0},
+                    "disk_bytes": {"type": "integer", "minimum": 0},
+                    "network_bytes": {"type": "integer", "minimum": 0}
+                }
+            },
+            "BadgeStatus": {
+                "type": "object",
+                "required": ["id", "repo_owner", "repo_name", "commit_sha", "state"],
+                "properties": {
+                    "id": {"type": "string"},
+                    "content_sha256": {"type": "string", "pattern": "^[a-fA-F0-9]{64}$"},
+                    "repo_owner": {"type": "string", "minLength": 1},
+                    "repo_name": {"type": "string", "minLength": 1},
+                    "pr_number": {"type": "integer", "minimum": 0},
+                    "commit_sha": {"type": "string", "pattern": "^[a-fA-F0-9]{40}$"},
+                    "state": {"$ref": "#/definitions/BadgeState"},
+                    "description": {"type": "string"},
+                    "target_url": {"type": "string"},
+                    "created_at": {"type": "string", "format": "date-time"},
+                    "updated_at": {"type": "string", "format": "date-time"},
+                    "proof_artifact_ids": {"type": "array", "items": {"type": "string"}},
+                    "coverage_percentage": {"type": "number", "minimum": 0.0, "maximum": 100.0},
+                    "invariants_proven": {"type": "integer", "minimum": 0},
+                    "total_invariants": {"type": "integer", "minimum": 0}
+                }
+            },
+            "BadgeState": {
+                "type": "string",
+                "enum": ["unspecified", "pending", "success", "failure", "error"]
             }
         }
     })
@@ -536,6 +1096,7 @@ pub fn generate_json_schema() -> serde_json::Value {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::json::ProtoJson;
     use proptest::prelude::*;
 
     proptest! {
@@ -555,6 +1116,54 @@ mod tests {
             assert_eq!(invariant.id, round_trip.id);
             assert_eq!(invariant.content_sha256, round_trip.content_sha256);
             assert_eq!(invariant.description, round_trip.description);
+            assert_eq!(invariant.source_span, round_trip.source_span);
+            assert_eq!(invariant.classification, round_trip.classification);
+        }
+
+        #[test]
+        fn test_lean_theorem_round_trip(theorem: LeanTheoremModel) {
+            let proto = theorem.to_proto();
+            let round_trip = LeanTheoremModel::from_proto(proto.clone()).unwrap();
+            prop_assert_eq!(round_trip.to_proto(), proto);
+            prop_assert_eq!(round_trip.status, theorem.status);
+        }
+
+        #[test]
+        fn test_proof_artifact_round_trip(artifact: ProofArtifactModel) {
+            let proto = artifact.to_proto();
+            let round_trip = ProofArtifactModel::from_proto(proto.clone()).unwrap();
+            prop_assert_eq!(round_trip.to_proto(), proto);
+            prop_assert_eq!(round_trip.status, artifact.status);
+        }
+
+        #[test]
+        fn test_badge_status_round_trip(badge: BadgeStatusModel) {
+            let proto = badge.to_proto();
+            let round_trip = BadgeStatusModel::from_proto(proto.clone()).unwrap();
+            prop_assert_eq!(round_trip.to_proto(), proto);
+            prop_assert_eq!(round_trip.created_at, badge.created_at);
+        }
+
+        #[test]
+        fn test_lean_theorem_json_round_trip(theorem: LeanTheoremModel) {
+            let json = theorem.to_json();
+            let round_trip = LeanTheoremModel::from_json(&json).unwrap();
+            prop_assert_eq!(round_trip.to_json(), json);
+        }
+
+        #[test]
+        fn test_proof_artifact_json_round_trip(artifact: ProofArtifactModel) {
+            let json = artifact.to_json();
+            let round_trip = ProofArtifactModel::from_json(&json).unwrap();
+            prop_assert_eq!(round_trip.to_json(), json);
+        }
+
+        #[test]
+        fn test_badge_status_json_round_trip(badge: BadgeStatusModel) {
+            let json = badge.to_json();
+            let round_trip = BadgeStatusModel::from_json(&json).unwrap();
+            prop_assert_eq!(round_trip.to_json(), json);
+            prop_assert!(badge.invariants_proven <= badge.total_invariants);
         }
 
         #[test]
@@ -566,172 +1175,58 @@ mod tests {
         }
     }
 
-    // Arbitrary implementations for property-based testing
-    impl Arbitrary for SpecDocumentModel {
-        type Parameters = ();
-        type Strategy = BoxedStrategy<Self>;
-
-        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-            (
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<DateTime<Utc>>(),
-                any::<DateTime<Utc>>(),
-                any::<HashMap<String, String>>(),
-                any::<i32>(),
-                any::<DocumentStatus>(),
-            )
-                .prop_map(|(id, content_sha256, source_system, source_id, title, content, url, author, created_at, modified_at, metadata, version, status)| {
-                    SpecDocumentModel {
-                        id,
-                        content_sha256,
-                        source_system,
-                        source_id,
-                        title,
-                        content,
-                        url,
-                        author,
-                        created_at,
-                        modified_at,
-                        metadata,
-                        version,
-                        status,
-                    }
-                })
-                .boxed()
-        }
-    }
-
-    impl Arbitrary for InvariantModel {
-        type Parameters = ();
-        type Strategy = BoxedStrategy<Self>;
-
-        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-            (
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<Vec<VariableModel>>(),
-                any::<HashMap<String, String>>(),
-                any::<f64>(),
-                any::<String>(),
-                any::<DateTime<Utc>>(),
-                any::<InvariantStatus>(),
-                any::<Vec<String>>(),
-                any::<Priority>(),
-            )
-                .prop_map(|(id, content_sha256, description, formal_expression, natural_language, variables, units, confidence_score, source_document_id, extracted_at, status, tags, priority)| {
-                    InvariantModel {
-                        id,
-                        content_sha256,
-                        description,
-                        formal_expression,
-                        natural_language,
-                        variables,
-                        units,
-                        confidence_score,
-                        source_document_id,
-                        extracted_at,
-                        status,
-                        tags,
-                        priority,
-                    }
-                })
-                .boxed()
-        }
-    }
-
-    impl Arbitrary for VariableModel {
-        type Parameters = ();
-        type Strategy = BoxedStrategy<Self>;
-
-        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-            (
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<String>(),
-                any::<Vec<String>>(),
-            )
-                .prop_map(|(name, var_type, description, unit, constraints)| {
-                    VariableModel {
-                        name,
-                        var_type,
-                        description,
-                        unit,
-                        constraints,
-                    }
-                })
-                .boxed()
-        }
-    }
-
-    impl Arbitrary for DocumentStatus {
-        type Parameters = ();
-        type Strategy = BoxedStrategy<Self>;
-
-        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-            prop_oneof![
-                Just(DocumentStatus::Unspecified),
-                Just(DocumentStatus::Draft),
-                Just(DocumentStatus::Published),
-                Just(DocumentStatus::Archived),
-            ]
-            .boxed()
-        }
-    }
-
-    impl Arbitrary for InvariantStatus {
-        type Parameters = ();
-        type Strategy = BoxedStrategy<Self>;
-
-        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-            prop_oneof![
-                Just(InvariantStatus::Unspecified),
-                Just(InvariantStatus::Extracted),
-                Just(InvariantStatus::Confirmed),
-                Just(InvariantStatus::Rejected),
-                Just(InvariantStatus::Proven),
-                Just(InvariantStatus::Failed),
-            ]
-            .boxed()
-        }
-    }
-
-    impl Arbitrary for Priority {
-        type Parameters = ();
-        type Strategy = BoxedStrategy<Self>;
-
-        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-            prop_oneof![
-                Just(Priority::Unspecified),
-                Just(Priority::Low),
-                Just(Priority::Medium),
-                Just(Priority::High),
-                Just(Priority::Critical),
-            ]
-            .boxed()
-        }
-    }
-
-    impl Arbitrary for DateTime<Utc> {
-        type Parameters = ();
-        type Strategy = BoxedStrategy<Self>;
-
-        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
-            (0..253402300799i64)
-                .prop_map(|timestamp| {
-                    DateTime::from_timestamp(timestamp, 0).unwrap_or_else(|| Utc::now())
-                })
-                .boxed()
-        }
-    }
-} 
\ No newline at end of file
+    #[test]
+    fn test_invariant_set_round_trip() {
+        let invariant = InvariantModel {
+            id: "inv-1".to_string(),
+            content_sha256: calculate_sha256("balance >= 0"),
+            description: "Balance never goes negative".to_string(),
+            formal_expression: "balance >= 0".to_string(),
+            natural_language: "The account balance must never be negative".to_string(),
+            variables: vec![],
+            units: HashMap::new(),
+            confidence_score: 0.9,
+            source_document_id: "doc-1".to_string(),
+            extracted_at: Utc::now(),
+            status: InvariantStatus::Confirmed,
+            tags: vec![],
+            priority: Priority::High,
+            source_span: None,
+            classification: None,
+        };
+        let set = InvariantSetModel {
+            id: "set-1".to_string(),
+            content_sha256: calculate_sha256("set"),
+            name: "Payments".to_string(),
+            description: String::new(),
+            invariants: vec![invariant],
+            source_document_ids: vec!["doc-1".to_string()],
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            modified_at: DateTime::from_timestamp(1_700_000_500, 0).unwrap(),
+            status: InvariantSetStatus::Approved,
+        };
+
+        let proto = set.to_proto();
+        assert_eq!(proto.status, 3);
+        assert_eq!(proto.invariants[0].priority, 3);
+
+        let round_trip = InvariantSetModel::from_proto(proto.clone()).unwrap();
+        assert_eq!(round_trip.status, InvariantSetStatus::Approved);
+        assert_eq!(round_trip.invariants[0].status, InvariantStatus::Confirmed);
+        assert_eq!(round_trip.modified_at, set.modified_at);
+        assert_eq!(round_trip.to_proto(), proto);
+    }
+
+    #[test]
+    fn test_missing_resource_usage_defaults_to_zero() {
+        let proto = ProofArtifact {
+            status: 5,
+            ..Default::default()
+        };
+        let artifact = ProofArtifactModel::from_proto(proto).unwrap();
+        assert_eq!(artifact.status, ProofStatus::Timeout);
+        assert_eq!(artifact.resource_usage.memory_bytes, 0);
+        assert_eq!(BadgeState::from_proto(42), BadgeState::Unspecified);
+        assert_eq!(TheoremStatus::from_proto(TheoremStatus::Proving.to_proto()), TheoremStatus::Proving);
+    }
+}
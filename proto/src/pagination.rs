@@ -0,0 +1,942 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use storage::{Entity, EntityQuery, Repository, StorageError};
+
+use crate::{
+    InvariantModel, InvariantStatus, Invariant, LeanTheorem, LeanTheoremModel,
+    ListInvariantsRequest, ListInvariantsResponse, ListLeanTheoremsRequest, ListLeanTheoremsResponse,
+    ListProofArtifactsRequest, ListProofArtifactsResponse, Priority, ProofArtifact, ProofArtifactModel,
+    ProofStatus, TheoremStatus, ToProto,
+};
+
+// Shared paging for list APIs, reading storage one page at a time through
+// `Repository::query`. Filters use a small subset of AIP-160: `field op
+// value` terms joined by AND, with ops = != < <= > >= and `:` (contains /
+// has). An `=` term on the status or source field picks the storage index
+// to read; the other terms are applied to each page as it is read. Without
+// order_by items come in storage order. With it, every match of the filter
+// is read and sorted, tie-broken by id, and tokens hold an offset into the
+// sorted matches.
+
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageRequest {
+    /// Zero means DEFAULT_PAGE_SIZE; larger values are capped at MAX_PAGE_SIZE
+    #[serde(default)]
+    pub page_size: u32,
+    #[serde(default)]
+    pub page_token: Option<String>,
+    #[serde(default)]
+    pub order_by: Option<String>,
+    #[serde(default)]
+    pub filter: Option<String>,
+}
+
+impl PageRequest {
+    pub fn effective_page_size(&self) -> usize {
+        match self.page_size {
+            0 => DEFAULT_PAGE_SIZE as usize,
+            size => size.min(MAX_PAGE_SIZE) as usize,
+        }
+    }
+
+    // Tokens are only valid for the query that produced them
+    fn query_fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.filter.as_deref().unwrap_or("").as_bytes());
+        hasher.update(b"\n");
+        hasher.update(self.order_by.as_deref().unwrap_or("").as_bytes());
+        hex::encode(&hasher.finalize()[..8])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_page_token: Option<String>,
+    /// Number of items matching the filter across all pages; only known
+    /// when they all fit on the first page or order_by is set
+    pub total_size: Option<usize>,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_page_token: self.next_page_token,
+            total_size: self.total_size,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PaginationError {
+    InvalidPageToken(String),
+    InvalidFilter(String),
+    InvalidOrderBy(String),
+    Storage(StorageError),
+}
+
+impl fmt::Display for PaginationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaginationError::InvalidPageToken(msg) => write!(f, "invalid page token: {}", msg),
+            PaginationError::InvalidFilter(msg) => write!(f, "invalid filter: {}", msg),
+            PaginationError::InvalidOrderBy(msg) => write!(f, "invalid order_by: {}", msg),
+            PaginationError::Storage(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for PaginationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PaginationError::Storage(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<StorageError> for PaginationError {
+    fn from(error: StorageError) -> Self {
+        PaginationError::Storage(error)
+    }
+}
+
+impl From<PaginationError> for spec_to_proof_error::Error {
+    fn from(error: PaginationError) -> Self {
+        match error {
+            PaginationError::Storage(error) => error.into(),
+            error => spec_to_proof_error::Error::invalid_input(error),
+        }
+    }
+}
+
+/// A field value exposed to filtering and ordering
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    Time(DateTime<Utc>),
+    List(Vec<String>),
+    /// Ordered enum; `names` are the lowercase value names in rank order
+    Enum { rank: usize, names: &'static [&'static str] },
+}
+
+const INVARIANT_STATUS_NAMES: &[&str] = &["unspecified", "extracted", "confirmed", "rejected", "proven", "failed", "stale"];
+const PRIORITY_NAMES: &[&str] = &["unspecified", "low", "medium", "high", "critical"];
+const THEOREM_STATUS_NAMES: &[&str] = &["unspecified", "generated", "compiling", "compiled", "proving", "proven", "failed"];
+const PROOF_STATUS_NAMES: &[&str] = &["unspecified", "pending", "running", "success", "failed", "timeout", "error"];
+
+/// Types that can be listed through `paginate`
+pub trait Pageable {
+    /// Field holding the ID the stored entity is indexed by as its source
+    const SOURCE_FIELD: &'static str;
+
+    /// Lowercase names of the `status` values, in proto enum order
+    const STATUS_NAMES: &'static [&'static str];
+
+    /// Returns None for fields that do not exist on the type
+    fn field_value(&self, field: &str) -> Option<FieldValue>;
+}
+
+impl Pageable for InvariantModel {
+    const SOURCE_FIELD: &'static str = "source_document_id";
+    const STATUS_NAMES: &'static [&'static str] = INVARIANT_STATUS_NAMES;
+
+    fn field_value(&self, field: &str) -> Option<FieldValue> {
+        Some(match field {
+            "id" => FieldValue::Text(self.id.clone()),
+            "description" => FieldValue::Text(self.description.clone()),
+            "formal_expression" => FieldValue::Text(self.formal_expression.clone()),
+            "natural_language" => FieldValue::Text(self.natural_language.clone()),
+            "source_document_id" => FieldValue::Text(self.source_document_id.clone()),
+            "confidence_score" => FieldValue::Number(self.confidence_score),
+            "extracted_at" => FieldValue::Time(self.extracted_at),
+            "tags" => FieldValue::List(self.tags.clone()),
+            "variables" => FieldValue::List(self.variables.iter().map(|v| v.name.clone()).collect()),
+            "status" => FieldValue::Enum {
+                rank: match self.status {
+                    InvariantStatus::Unspecified => 0,
+                    InvariantStatus::Extracted => 1,
+                    InvariantStatus::Confirmed => 2,
+                    InvariantStatus::Rejected => 3,
+                    InvariantStatus::Proven => 4,
+                    InvariantStatus::Failed => 5,
+                    InvariantStatus::Stale => 6,
+                },
+                names: INVARIANT_STATUS_NAMES,
+            },
+            "priority" => FieldValue::Enum {
+                rank: match self.priority {
+                    Priority::Unspecified => 0,
+                    Priority::Low => 1,
+                    Priority::Medium => 2,
+                    Priority::High => 3,
+                    Priority::Critical => 4,
+                },
+                names: PRIORITY_NAMES,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl Pageable for LeanTheoremModel {
+    const SOURCE_FIELD: &'static str = "source_invariant_id";
+    const STATUS_NAMES: &'static [&'static str] = THEOREM_STATUS_NAMES;
+
+    fn field_value(&self, field: &str) -> Option<FieldValue> {
+        Some(match field {
+            "id" => FieldValue::Text(self.id.clone()),
+            "theorem_name" => FieldValue::Text(self.theorem_name.clone()),
+            "source_invariant_id" => FieldValue::Text(self.source_invariant_id.clone()),
+            "proof_strategy" => FieldValue::Text(self.proof_strategy.clone()),
+            "generated_at" => FieldValue::Time(self.generated_at),
+            "compilation_errors" => FieldValue::List(self.compilation_errors.clone()),
+            "status" => FieldValue::Enum {
+                rank: match self.status {
+                    TheoremStatus::Unspecified => 0,
+                    TheoremStatus::Generated => 1,
+                    TheoremStatus::Compiling => 2,
+                    TheoremStatus::Compiled => 3,
+                    TheoremStatus::Proving => 4,
+                    TheoremStatus::Proven => 5,
+                    TheoremStatus::Failed => 6,
+                },
+                names: THEOREM_STATUS_NAMES,
+            },
+            _ => return None,
+        })
+    }
+}
+
+impl Pageable for ProofArtifactModel {
+    const SOURCE_FIELD: &'static str = "invariant_id";
+    const STATUS_NAMES: &'static [&'static str] = PROOF_STATUS_NAMES;
+
+    fn field_value(&self, field: &str) -> Option<FieldValue> {
+        Some(match field {
+            "id" => FieldValue::Text(self.id.clone()),
+            "theorem_id" => FieldValue::Text(self.theorem_id.clone()),
+            "invariant_id" => FieldValue::Text(self.invariant_id.clone()),
+            "proof_strategy" => FieldValue::Text(self.proof_strategy.clone()),
+            "attempted_at" => FieldValue::Time(self.attempted_at),
+            "duration_ms" => FieldValue::Number(self.duration_ms as f64),
+            "confidence_score" => FieldValue::Number(self.confidence_score),
+            "status" => FieldValue::Enum {
+                rank: match self.status {
+                    ProofStatus::Unspecified => 0,
+                    ProofStatus::Pending => 1,
+                    ProofStatus::Running => 2,
+                    ProofStatus::Success => 3,
+                    ProofStatus::Failed => 4,
+                    ProofStatus::Timeout => 5,
+                    ProofStatus::Error => 6,
+                },
+                names: PROOF_STATUS_NAMES,
+            },
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Has,
+}
+
+#[derive(Debug, Clone)]
+struct FilterTerm {
+    field: String,
+    op: FilterOp,
+    value: String,
+}
+
+// Whitespace-separated tokens, keeping double-quoted strings intact
+fn tokenize_filter(filter: &str) -> Result<Vec<String>, PaginationError> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in filter.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(PaginationError::InvalidFilter("unterminated string".to_string()));
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn parse_term(term: &str) -> Result<FilterTerm, PaginationError> {
+    // Two-character operators must be tried before their prefixes
+    const OPERATORS: &[(&str, FilterOp)] = &[
+        (">=", FilterOp::Ge),
+        ("<=", FilterOp::Le),
+        ("!=", FilterOp::Ne),
+        ("=", FilterOp::Eq),
+        ("<", FilterOp::Lt),
+        (">", FilterOp::Gt),
+        (":", FilterOp::Has),
+    ];
+
+    let field_end = term
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .ok_or_else(|| PaginationError::InvalidFilter(format!("missing operator in '{}'", term)))?;
+    let (field, rest) = term.split_at(field_end);
+    if field.is_empty() {
+        return Err(PaginationError::InvalidFilter(format!("missing field name in '{}'", term)));
+    }
+
+    let rest = rest.trim_start();
+    let (symbol, op) = OPERATORS
+        .iter()
+        .find(|(symbol, _)| rest.starts_with(symbol))
+        .ok_or_else(|| PaginationError::InvalidFilter(format!("missing operator in '{}'", term)))?;
+
+    let value = rest[symbol.len()..].trim();
+    let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    };
+    if value.is_empty() {
+        return Err(PaginationError::InvalidFilter(format!("missing value in '{}'", term)));
+    }
+
+    Ok(FilterTerm {
+        field: field.to_string(),
+        op: *op,
+        value: value.to_string(),
+    })
+}
+
+fn parse_filter(filter: &str) -> Result<Vec<FilterTerm>, PaginationError> {
+    let mut terms = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+
+    for token in tokenize_filter(filter)? {
+        if token == "AND" {
+            if current.is_empty() {
+                return Err(PaginationError::InvalidFilter("AND without a preceding term".to_string()));
+            }
+            terms.push(parse_term(&current.join(" "))?);
+            current.clear();
+        } else if token == "OR" || token == "NOT" {
+            return Err(PaginationError::InvalidFilter(format!("{} is not supported", token)));
+        } else {
+            current.push(token);
+        }
+    }
+
+    if current.is_empty() {
+        if !terms.is_empty() {
+            return Err(PaginationError::InvalidFilter("AND without a following term".to_string()));
+        }
+    } else {
+        terms.push(parse_term(&current.join(" "))?);
+    }
+    Ok(terms)
+}
+
+fn compare_ordered<T: PartialOrd>(op: FilterOp, actual: &T, expected: &T) -> bool {
+    match op {
+        FilterOp::Eq | FilterOp::Has => actual == expected,
+        FilterOp::Ne => actual != expected,
+        FilterOp::Lt => actual < expected,
+        FilterOp::Le => actual <= expected,
+        FilterOp::Gt => actual > expected,
+        FilterOp::Ge => actual >= expected,
+    }
+}
+
+fn term_matches(term: &FilterTerm, value: &FieldValue) -> Result<bool, PaginationError> {
+    match value {
+        FieldValue::Text(actual) => Ok(match term.op {
+            FilterOp::Has => actual.to_lowercase().contains(&term.value.to_lowercase()),
+            op => compare_ordered(op, actual, &term.value),
+        }),
+        FieldValue::Number(actual) => {
+            let expected: f64 = term.value.parse().map_err(|_| {
+                PaginationError::InvalidFilter(format!("{} expects a number, got '{}'", term.field, term.value))
+            })?;
+            Ok(compare_ordered(term.op, actual, &expected))
+        }
+        FieldValue::Time(actual) => {
+            let expected = DateTime::parse_from_rfc3339(&term.value)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|_| {
+                    PaginationError::InvalidFilter(format!("{} expects an RFC 3339 timestamp, got '{}'", term.field, term.value))
+                })?;
+            Ok(compare_ordered(term.op, actual, &expected))
+        }
+        FieldValue::List(items) => match term.op {
+            FilterOp::Has | FilterOp::Eq => Ok(items.iter().any(|item| item == &term.value)),
+            FilterOp::Ne => Ok(items.iter().all(|item| item != &term.value)),
+            _ => Err(PaginationError::InvalidFilter(format!("{} is a list and only supports ':', '=' and '!='", term.field))),
+        },
+        FieldValue::Enum { rank, names } => Ok(compare_ordered(term.op, rank, &enum_rank(term, names)?)),
+    }
+}
+
+// Accepts the value's name or its number
+fn enum_rank(term: &FilterTerm, names: &[&str]) -> Result<usize, PaginationError> {
+    let wanted = term.value.to_lowercase();
+    names
+        .iter()
+        .position(|name| *name == wanted)
+        .or_else(|| term.value.parse::<usize>().ok().filter(|r| *r < names.len()))
+        .ok_or_else(|| {
+            PaginationError::InvalidFilter(format!(
+                "{} must be one of {}, got '{}'",
+                term.field,
+                names.join(", "),
+                term.value
+            ))
+        })
+}
+
+fn item_matches<T: Pageable>(item: &T, terms: &[FilterTerm]) -> Result<bool, PaginationError> {
+    for term in terms {
+        let value = item
+            .field_value(&term.field)
+            .ok_or_else(|| PaginationError::InvalidFilter(format!("unknown field '{}'", term.field)))?;
+        if !term_matches(term, &value)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn parse_order_by(order_by: &str) -> Result<Vec<(String, bool)>, PaginationError> {
+    order_by
+        .split(',')
+        .map(str::trim)
+        .filter(|clause| !clause.is_empty())
+        .map(|clause| {
+            let mut parts = clause.split_whitespace();
+            let field = parts.next().unwrap_or_default().to_string();
+            let descending = match parts.next().map(|d| d.to_lowercase()).as_deref() {
+                None | Some("asc") => false,
+                Some("desc") => true,
+                Some(other) => {
+                    return Err(PaginationError::InvalidOrderBy(format!("unknown direction '{}'", other)))
+                }
+            };
+            if parts.next().is_some() {
+                return Err(PaginationError::InvalidOrderBy(format!("unexpected tokens in '{}'", clause)));
+            }
+            Ok((field, descending))
+        })
+        .collect()
+}
+
+fn compare_values(a: &FieldValue, b: &FieldValue) -> Ordering {
+    match (a, b) {
+        (FieldValue::Text(a), FieldValue::Text(b)) => a.cmp(b),
+        (FieldValue::Number(a), FieldValue::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (FieldValue::Time(a), FieldValue::Time(b)) => a.cmp(b),
+        (FieldValue::Enum { rank: a, .. }, FieldValue::Enum { rank: b, .. }) => a.cmp(b),
+        _ => Ordering::Equal,
+    }
+}
+
+fn order_key<T: Pageable>(item: &T, ordering: &[(String, bool)]) -> Result<Vec<FieldValue>, PaginationError> {
+    ordering
+        .iter()
+        .map(|(field, _)| match item.field_value(field) {
+            Some(FieldValue::List(_)) => Err(PaginationError::InvalidOrderBy(format!("cannot order by list field '{}'", field))),
+            Some(value) => Ok(value),
+            None => Err(PaginationError::InvalidOrderBy(format!("unknown field '{}'", field))),
+        })
+        .collect()
+}
+
+// The storage index that answers the filter, preferring the more selective
+// source index; the terms are still checked against every item read
+fn storage_query<T: Pageable>(terms: &[FilterTerm]) -> Result<EntityQuery, PaginationError> {
+    let pinned = |field: &str| terms.iter().find(|term| term.op == FilterOp::Eq && term.field == field);
+    if let Some(term) = pinned(T::SOURCE_FIELD) {
+        return Ok(EntityQuery::BySource(term.value.clone()));
+    }
+    if let Some(term) = pinned("status") {
+        return Ok(EntityQuery::ByStatus(enum_rank(term, T::STATUS_NAMES)? as i32));
+    }
+    Ok(EntityQuery::All)
+}
+
+// Tokens resume inside a storage page: `skip` items of the page read with
+// `storage_token` were already returned or filtered out
+#[derive(Serialize, Deserialize)]
+struct PageToken {
+    storage_token: Option<String>,
+    skip: usize,
+    query: String,
+}
+
+fn encode_page_token(storage_token: Option<String>, skip: usize, request: &PageRequest) -> String {
+    let token = PageToken {
+        storage_token,
+        skip,
+        query: request.query_fingerprint(),
+    };
+    hex::encode(serde_json::to_vec(&token).unwrap_or_default())
+}
+
+fn decode_page_token(request: &PageRequest) -> Result<(Option<String>, usize), PaginationError> {
+    let raw = match request.page_token.as_deref() {
+        None | Some("") => return Ok((None, 0)),
+        Some(raw) => raw,
+    };
+
+    let bytes = hex::decode(raw).map_err(|_| PaginationError::InvalidPageToken("malformed token".to_string()))?;
+    let token: PageToken = serde_json::from_slice(&bytes)
+        .map_err(|_| PaginationError::InvalidPageToken("malformed token".to_string()))?;
+
+    if token.query != request.query_fingerprint() {
+        return Err(PaginationError::InvalidPageToken(
+            "token was issued for a different filter or order_by".to_string(),
+        ));
+    }
+    Ok((token.storage_token, token.skip))
+}
+
+/// Lists the entities in `repository` matching `request`, converting each
+/// with `to_item` before filtering it. Storage is read a page at a time
+/// until the page is full, so a selective filter without an index to
+/// answer it may read many storage pages.
+pub async fn paginate<E: Entity, T: Pageable>(
+    repository: &dyn Repository<E>,
+    request: &PageRequest,
+    to_item: impl Fn(E) -> Result<T, PaginationError>,
+) -> Result<Page<T>, PaginationError> {
+    let terms = parse_filter(request.filter.as_deref().unwrap_or(""))?;
+    let query = storage_query::<T>(&terms)?;
+    let ordering = parse_order_by(request.order_by.as_deref().unwrap_or(""))?;
+    if !ordering.is_empty() {
+        return paginate_ordered(repository, request, &query, &terms, ordering, to_item).await;
+    }
+    let (mut storage_token, mut skip) = decode_page_token(request)?;
+    let first_page = storage_token.is_none() && skip == 0;
+    let page_size = request.effective_page_size();
+
+    let mut items = Vec::new();
+    loop {
+        let page = repository.query(&query, storage_token.as_deref(), page_size as u32).await?;
+        for (index, stored) in page.items.into_iter().enumerate().skip(skip) {
+            if items.len() == page_size {
+                return Ok(Page {
+                    items,
+                    next_page_token: Some(encode_page_token(storage_token, index, request)),
+                    total_size: None,
+                });
+            }
+            let item = to_item(stored.entity)?;
+            if item_matches(&item, &terms)? {
+                items.push(item);
+            }
+        }
+
+        skip = 0;
+        match page.next_page_token {
+            Some(token) => storage_token = Some(token),
+            None => {
+                let total_size = first_page.then_some(items.len());
+                return Ok(Page { items, next_page_token: None, total_size });
+            }
+        }
+    }
+}
+
+// Sorting needs every match, so storage is read to the end on each call and
+// the token's `skip` is an offset into the sorted matches
+async fn paginate_ordered<E: Entity, T: Pageable>(
+    repository: &dyn Repository<E>,
+    request: &PageRequest,
+    query: &EntityQuery,
+    terms: &[FilterTerm],
+    mut ordering: Vec<(String, bool)>,
+    to_item: impl Fn(E) -> Result<T, PaginationError>,
+) -> Result<Page<T>, PaginationError> {
+    ordering.push(("id".to_string(), false));
+    let (_, offset) = decode_page_token(request)?;
+
+    let mut keyed = Vec::new();
+    let mut storage_token = None;
+    loop {
+        let page = repository.query(query, storage_token.as_deref(), MAX_PAGE_SIZE).await?;
+        for stored in page.items {
+            let item = to_item(stored.entity)?;
+            if item_matches(&item, terms)? {
+                keyed.push((order_key(&item, &ordering)?, item));
+            }
+        }
+        match page.next_page_token {
+            Some(token) => storage_token = Some(token),
+            None => break,
+        }
+    }
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        a.iter()
+            .zip(b.iter())
+            .zip(ordering.iter())
+            .map(|((a, b), (_, descending))| {
+                let ord = compare_values(a, b);
+                if *descending { ord.reverse() } else { ord }
+            })
+            .find(|ord| *ord != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let total_size = keyed.len();
+    let page_size = request.effective_page_size();
+    let end = offset.saturating_add(page_size).min(total_size);
+    let items = keyed.into_iter().skip(offset).take(page_size).map(|(_, item)| item).collect();
+
+    Ok(Page {
+        items,
+        next_page_token: (end < total_size).then(|| encode_page_token(None, end, request)),
+        total_size: Some(total_size),
+    })
+}
+
+// Adapters for the gRPC list messages
+
+fn page_request(page_size: i32, page_token: &str, order_by: &str, filter: &str) -> PageRequest {
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    PageRequest {
+        page_size: page_size.max(0) as u32,
+        page_token: non_empty(page_token),
+        order_by: non_empty(order_by),
+        filter: non_empty(filter),
+    }
+}
+
+impl From<&ListInvariantsRequest> for PageRequest {
+    fn from(request: &ListInvariantsRequest) -> Self {
+        page_request(request.page_size, &request.page_token, &request.order_by, &request.filter)
+    }
+}
+
+impl From<&ListLeanTheoremsRequest> for PageRequest {
+    fn from(request: &ListLeanTheoremsRequest) -> Self {
+        page_request(request.page_size, &request.page_token, &request.order_by, &request.filter)
+    }
+}
+
+impl From<&ListProofArtifactsRequest> for PageRequest {
+    fn from(request: &ListProofArtifactsRequest) -> Self {
+        page_request(request.page_size, &request.page_token, &request.order_by, &request.filter)
+    }
+}
+
+/// ListInvariants over the stored invariants in `repository`
+pub async fn list_invariants<E: Entity>(
+    repository: &dyn Repository<E>,
+    request: &ListInvariantsRequest,
+    to_model: impl Fn(E) -> Result<InvariantModel, PaginationError>,
+) -> Result<ListInvariantsResponse, PaginationError> {
+    let page = paginate(repository, &PageRequest::from(request), to_model).await?;
+    Ok(page.map(|invariant| invariant.to_proto()).into())
+}
+
+/// ListLeanTheorems over the stored theorems in `repository`
+pub async fn list_lean_theorems<E: Entity>(
+    repository: &dyn Repository<E>,
+    request: &ListLeanTheoremsRequest,
+    to_model: impl Fn(E) -> Result<LeanTheoremModel, PaginationError>,
+) -> Result<ListLeanTheoremsResponse, PaginationError> {
+    let page = paginate(repository, &PageRequest::from(request), to_model).await?;
+    Ok(page.map(|theorem| theorem.to_proto()).into())
+}
+
+/// ListProofArtifacts over the stored artifacts in `repository`
+pub async fn list_proof_artifacts<E: Entity>(
+    repository: &dyn Repository<E>,
+    request: &ListProofArtifactsRequest,
+    to_model: impl Fn(E) -> Result<ProofArtifactModel, PaginationError>,
+) -> Result<ListProofArtifactsResponse, PaginationError> {
+    let page = paginate(repository, &PageRequest::from(request), to_model).await?;
+    Ok(page.map(|artifact| artifact.to_proto()).into())
+}
+
+// -1 when the total was not counted
+fn response_total_size(total_size: Option<usize>) -> i32 {
+    total_size.map_or(-1, |total_size| total_size as i32)
+}
+
+impl From<Page<Invariant>> for ListInvariantsResponse {
+    fn from(page: Page<Invariant>) -> Self {
+        ListInvariantsResponse {
+            invariants: page.items,
+            next_page_token: page.next_page_token.unwrap_or_default(),
+            total_size: response_total_size(page.total_size),
+        }
+    }
+}
+
+impl From<Page<LeanTheorem>> for ListLeanTheoremsResponse {
+    fn from(page: Page<LeanTheorem>) -> Self {
+        ListLeanTheoremsResponse {
+            theorems: page.items,
+            next_page_token: page.next_page_token.unwrap_or_default(),
+            total_size: response_total_size(page.total_size),
+        }
+    }
+}
+
+impl From<Page<ProofArtifact>> for ListProofArtifactsResponse {
+    fn from(page: Page<ProofArtifact>) -> Self {
+        ListProofArtifactsResponse {
+            artifacts: page.items,
+            next_page_token: page.next_page_token.unwrap_or_default(),
+            total_size: response_total_size(page.total_size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use storage::{ExpectedVersion, InMemoryRepository};
+
+    use crate::FromProto;
+
+    impl Entity for Invariant {
+        const KIND: &'static str = "INVARIANT";
+
+        fn entity_id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn source_id(&self) -> Option<String> {
+            Some(self.source_document_id.clone())
+        }
+
+        fn status(&self) -> i32 {
+            self.status
+        }
+
+        fn set_status(&mut self, status: i32) {
+            self.status = status;
+        }
+    }
+
+    fn invariant(id: &str, confidence: f64, priority: Priority, tags: &[&str]) -> InvariantModel {
+        InvariantModel {
+            id: id.to_string(),
+            content_sha256: String::new(),
+            description: format!("Invariant {}", id),
+            formal_expression: "x > 0".to_string(),
+            natural_language: String::new(),
+            variables: vec![],
+            units: HashMap::new(),
+            confidence_score: confidence,
+            source_document_id: "doc-1".to_string(),
+            extracted_at: Utc::now(),
+            status: InvariantStatus::Extracted,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            priority,
+            source_span: None,
+            classification: None,
+        }
+    }
+
+    async fn invariants() -> InMemoryRepository<Invariant> {
+        let repository = InMemoryRepository::new();
+        for model in [
+            invariant("inv-1", 0.9, Priority::High, &["payments"]),
+            invariant("inv-2", 0.4, Priority::Low, &["payments", "refunds"]),
+            invariant("inv-3", 0.8, Priority::Critical, &["auth"]),
+            invariant("inv-4", 0.7, Priority::Medium, &["payments"]),
+            InvariantModel {
+                source_document_id: "doc-2".to_string(),
+                status: InvariantStatus::Confirmed,
+                ..invariant("inv-5", 0.9, Priority::High, &["payments"])
+            },
+        ] {
+            repository.put(&model.to_proto(), ExpectedVersion::Any).await.unwrap();
+        }
+        repository
+    }
+
+    fn to_model(invariant: Invariant) -> Result<InvariantModel, PaginationError> {
+        InvariantModel::from_proto(invariant).map_err(|e| PaginationError::Storage(StorageError::Decode(e.to_string())))
+    }
+
+    fn ids(page: &Page<InvariantModel>) -> Vec<&str> {
+        page.items.iter().map(|i| i.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_pages_through_all_items() {
+        let repository = invariants().await;
+        let mut request = PageRequest {
+            page_size: 3,
+            ..Default::default()
+        };
+
+        let first = paginate(&repository, &request, to_model).await.unwrap();
+        assert_eq!(ids(&first), vec!["inv-1", "inv-2", "inv-3"]);
+        assert_eq!(first.total_size, None);
+
+        request.page_token = first.next_page_token.clone();
+        let second = paginate(&repository, &request, to_model).await.unwrap();
+        assert_eq!(ids(&second), vec!["inv-4", "inv-5"]);
+        assert!(second.next_page_token.is_none());
+        assert_eq!(second.total_size, None);
+    }
+
+    #[tokio::test]
+    async fn test_filter_resumes_inside_storage_pages() {
+        let repository = invariants().await;
+        let mut request = PageRequest {
+            page_size: 2,
+            filter: Some("tags:payments AND priority >= medium AND confidence_score > 0.5".to_string()),
+            ..Default::default()
+        };
+
+        let first = paginate(&repository, &request, to_model).await.unwrap();
+        assert_eq!(ids(&first), vec!["inv-1", "inv-4"]);
+
+        request.page_token = first.next_page_token.clone();
+        let second = paginate(&repository, &request, to_model).await.unwrap();
+        assert_eq!(ids(&second), vec!["inv-5"]);
+        assert!(second.next_page_token.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_indexed_filters() {
+        let repository = invariants().await;
+        let by_status = PageRequest {
+            filter: Some("status = confirmed".to_string()),
+            ..Default::default()
+        };
+        let page = paginate(&repository, &by_status, to_model).await.unwrap();
+        assert_eq!(ids(&page), vec!["inv-5"]);
+        assert_eq!(page.total_size, Some(1));
+
+        let by_source = PageRequest {
+            filter: Some("source_document_id = doc-1 AND priority = high".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&paginate(&repository, &by_source, to_model).await.unwrap()), vec!["inv-1"]);
+
+        let terms = parse_filter("status = 2 AND source_document_id = doc-1").unwrap();
+        assert_eq!(storage_query::<InvariantModel>(&terms).unwrap(), EntityQuery::BySource("doc-1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_token_for_other_query() {
+        let repository = invariants().await;
+        let request = PageRequest {
+            page_size: 1,
+            ..Default::default()
+        };
+        let token = paginate(&repository, &request, to_model).await.unwrap().next_page_token;
+
+        let changed = PageRequest {
+            page_size: 1,
+            page_token: token,
+            filter: Some("priority = high".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            paginate(&repository, &changed, to_model).await,
+            Err(PaginationError::InvalidPageToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_filters() {
+        let repository = invariants().await;
+        for filter in ["nope = 1", "priority = urgent", "status = urgent", "confidence_score > high", "tags > a", "id = a OR id = b", "id = a AND"] {
+            let request = PageRequest {
+                filter: Some(filter.to_string()),
+                ..Default::default()
+            };
+            assert!(
+                matches!(paginate(&repository, &request, to_model).await, Err(PaginationError::InvalidFilter(_))),
+                "{}",
+                filter
+            );
+        }
+
+        for order_by in ["tags", "nope desc", "confidence_score sideways", "id asc extra"] {
+            let request = PageRequest {
+                order_by: Some(order_by.to_string()),
+                ..Default::default()
+            };
+            assert!(
+                matches!(paginate(&repository, &request, to_model).await, Err(PaginationError::InvalidOrderBy(_))),
+                "{}",
+                order_by
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_order_by_pages_through_sorted_matches() {
+        let repository = invariants().await;
+        let mut request = PageRequest {
+            page_size: 2,
+            filter: Some("tags:payments".to_string()),
+            order_by: Some("confidence_score desc".to_string()),
+            ..Default::default()
+        };
+
+        let first = paginate(&repository, &request, to_model).await.unwrap();
+        assert_eq!(ids(&first), vec!["inv-1", "inv-5"]);
+        assert_eq!(first.total_size, Some(4));
+
+        request.page_token = first.next_page_token.clone();
+        let second = paginate(&repository, &request, to_model).await.unwrap();
+        assert_eq!(ids(&second), vec!["inv-4", "inv-2"]);
+        assert!(second.next_page_token.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_invariants_response() {
+        let repository = invariants().await;
+        let request = ListInvariantsRequest {
+            page_size: 10,
+            filter: "tags:refunds".to_string(),
+            ..Default::default()
+        };
+
+        let response = list_invariants(&repository, &request, to_model).await.unwrap();
+        assert_eq!(response.invariants.len(), 1);
+        assert_eq!(response.invariants[0].id, "inv-2");
+        assert_eq!(response.total_size, 1);
+        assert!(response.next_page_token.is_empty());
+    }
+}
@@ -0,0 +1,419 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{calculate_sha256, InvariantModel, SpecDocumentModel};
+
+// Document preview for the UI: normalized content split into renderable
+// blocks with stable IDs, plus the span each invariant was extracted from.
+// All offsets are character (not byte) offsets into `normalized_content`.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentPreview {
+    pub document_id: String,
+    pub content_sha256: String,
+    pub normalized_content: String,
+    pub blocks: Vec<PreviewBlock>,
+    pub anchors: Vec<InvariantAnchor>,
+    pub unanchored_invariant_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    Heading,
+    Paragraph,
+    ListItem,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewBlock {
+    pub id: String,
+    pub kind: BlockKind,
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorMatch {
+    SourceQuote,
+    Exact,
+    Fuzzy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvariantAnchor {
+    pub invariant_id: String,
+    pub block_id: String,
+    pub start: usize,
+    pub end: usize,
+    pub match_kind: AnchorMatch,
+}
+
+// Minimum token overlap for a sentence to count as an invariant's source
+const FUZZY_MATCH_THRESHOLD: f64 = 0.5;
+
+pub fn build_document_preview(
+    document: &SpecDocumentModel,
+    invariants: &[InvariantModel],
+) -> DocumentPreview {
+    let normalized_content = normalize_content(&document.content);
+    let blocks = split_blocks(&document.id, &normalized_content);
+
+    let mut anchors = Vec::new();
+    let mut unanchored_invariant_ids = Vec::new();
+
+    for invariant in invariants {
+        match anchor_invariant(invariant, &normalized_content, &blocks) {
+            Some(anchor) => anchors.push(anchor),
+            None => unanchored_invariant_ids.push(invariant.id.clone()),
+        }
+    }
+
+    DocumentPreview {
+        document_id: document.id.clone(),
+        content_sha256: calculate_sha256(&normalized_content),
+        normalized_content,
+        blocks,
+        anchors,
+        unanchored_invariant_ids,
+    }
+}
+
+// Unifies line endings, strips trailing whitespace and collapses runs of
+// blank lines so offsets don't depend on the source system's formatting
+pub fn normalize_content(content: &str) -> String {
+    let unified = content.replace("\r\n", "\n").replace('\r', "\n").replace('\t', "    ");
+
+    let mut lines: Vec<&str> = Vec::new();
+    let mut previous_blank = true;
+    for line in unified.lines() {
+        let line = line.trim_end();
+        let blank = line.is_empty();
+        if blank && previous_blank {
+            continue;
+        }
+        lines.push(line);
+        previous_blank = blank;
+    }
+
+    while lines.last().map_or(false, |line| line.is_empty()) {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+fn block_kind(line: &str) -> BlockKind {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') {
+        BlockKind::Heading
+    } else if trimmed.starts_with("- ")
+        || trimmed.starts_with("* ")
+        || trimmed.split_once(". ").map_or(false, |(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    {
+        BlockKind::ListItem
+    } else {
+        BlockKind::Paragraph
+    }
+}
+
+// Headings and list items are blocks of their own; consecutive prose lines
+// form a paragraph. Block IDs hash the document ID, the block text and the
+// number of earlier identical blocks, so editing one block leaves the IDs
+// of the others unchanged.
+pub fn split_blocks(document_id: &str, normalized_content: &str) -> Vec<PreviewBlock> {
+    let mut blocks = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    let mut push_block = |kind: BlockKind, start: usize, text: &str, blocks: &mut Vec<PreviewBlock>| {
+        let occurrence = seen.entry(text.to_string()).or_insert(0);
+        let id = format!(
+            "blk-{}",
+            &calculate_sha256(&format!("{}\u{0}{}\u{0}{}", document_id, text, occurrence))[..12]
+        );
+        *occurrence += 1;
+        blocks.push(PreviewBlock {
+            id,
+            kind,
+            text: text.to_string(),
+            start,
+            end: start + text.chars().count(),
+        });
+    };
+
+    let mut paragraph: Option<(usize, String)> = None;
+    let mut offset = 0;
+
+    for line in normalized_content.split('\n') {
+        let line_len = line.chars().count();
+        let kind = block_kind(line);
+
+        if line.is_empty() || kind != BlockKind::Paragraph {
+            if let Some((start, text)) = paragraph.take() {
+                push_block(BlockKind::Paragraph, start, &text, &mut blocks);
+            }
+        }
+
+        if !line.is_empty() {
+            match kind {
+                BlockKind::Paragraph => match paragraph.as_mut() {
+                    Some((_, text)) => {
+                        text.push('\n');
+                        text.push_str(line);
+                    }
+                    None => paragraph = Some((offset, line.to_string())),
+                },
+                _ => push_block(kind, offset, line, &mut blocks),
+            }
+        }
+
+        // +1 for the newline separator
+        offset += line_len + 1;
+    }
+
+    if let Some((start, text)) = paragraph.take() {
+        push_block(BlockKind::Paragraph, start, &text, &mut blocks);
+    }
+
+    blocks
+}
+
+fn anchor_invariant(
+    invariant: &InvariantModel,
+    normalized_content: &str,
+    blocks: &[PreviewBlock],
+) -> Option<InvariantAnchor> {
+    let haystack = normalized_content.to_ascii_lowercase();
+    let anchor = |(block, start, end): (&PreviewBlock, usize, usize), match_kind: AnchorMatch| InvariantAnchor {
+        invariant_id: invariant.id.clone(),
+        block_id: block.id.clone(),
+        start,
+        end,
+        match_kind,
+    };
+
+    // The quote recorded at extraction, once verified against the document
+    if let Some(span) = invariant.source_span.as_ref().filter(|span| span.verified) {
+        if let Some(found) = find_exact(&span.quote, normalized_content, &haystack, blocks) {
+            return Some(anchor(found, AnchorMatch::SourceQuote));
+        }
+    }
+
+    // Exact (ASCII case-insensitive) match of the source sentence next
+    let candidates = [&invariant.natural_language, &invariant.description];
+    for candidate in candidates {
+        if let Some(found) = find_exact(candidate, normalized_content, &haystack, blocks) {
+            return Some(anchor(found, AnchorMatch::Exact));
+        }
+    }
+
+    // Otherwise the sentence with the highest token overlap
+    let mut best: Option<(f64, &PreviewBlock, usize, usize)> = None;
+    for block in blocks {
+        for (start, end, sentence) in sentences(block) {
+            let score = candidates
+                .iter()
+                .map(|candidate| token_overlap(candidate, &sentence))
+                .fold(0.0, f64::max);
+            if score >= FUZZY_MATCH_THRESHOLD && best.map_or(true, |(best_score, ..)| score > best_score) {
+                best = Some((score, block, start, end));
+            }
+        }
+    }
+
+    best.map(|(_, block, start, end)| anchor((block, start, end), AnchorMatch::Fuzzy))
+}
+
+// `haystack` is the ASCII-lowercased content, so byte offsets line up
+fn find_exact<'a>(
+    text: &str,
+    normalized_content: &str,
+    haystack: &str,
+    blocks: &'a [PreviewBlock],
+) -> Option<(&'a PreviewBlock, usize, usize)> {
+    let needle = collapse_whitespace(text).to_ascii_lowercase();
+    if needle.is_empty() {
+        return None;
+    }
+    let byte_start = haystack.find(&needle)?;
+    let start = normalized_content[..byte_start].chars().count();
+    let end = start + needle.chars().count();
+    containing_block(blocks, start, end).map(|block| (block, start, end))
+}
+
+fn containing_block(blocks: &[PreviewBlock], start: usize, end: usize) -> Option<&PreviewBlock> {
+    blocks.iter().find(|block| block.start <= start && end <= block.end)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Splits a block into sentences, returning document-level char offsets
+fn sentences(block: &PreviewBlock) -> Vec<(usize, usize, String)> {
+    let chars: Vec<char> = block.text.chars().collect();
+    let mut result = Vec::new();
+    let mut sentence_start = 0;
+
+    for i in 0..chars.len() {
+        let at_boundary = matches!(chars[i], '.' | '!' | '?')
+            && chars.get(i + 1).map_or(true, |next| next.is_whitespace());
+        if at_boundary || i + 1 == chars.len() {
+            let text: String = chars[sentence_start..=i].iter().collect();
+            let leading = text.chars().take_while(|c| c.is_whitespace()).count();
+            let trimmed = text.trim();
+            if !trimmed.is_empty() {
+                let start = block.start + sentence_start + leading;
+                result.push((start, start + trimmed.chars().count(), trimmed.to_string()));
+            }
+            sentence_start = i + 1;
+        }
+    }
+
+    result
+}
+
+fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.len() > 2)
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+// Fraction of the candidate's tokens that appear in the sentence
+fn token_overlap(candidate: &str, sentence: &str) -> f64 {
+    let candidate_tokens = tokens(candidate);
+    if candidate_tokens.is_empty() {
+        return 0.0;
+    }
+    let sentence_tokens = tokens(sentence);
+    let shared = candidate_tokens
+        .iter()
+        .filter(|token| sentence_tokens.contains(token))
+        .count();
+    shared as f64 / candidate_tokens.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DocumentStatus, InvariantStatus, Priority, SourceSpanModel};
+    use chrono::Utc;
+
+    fn document(content: &str) -> SpecDocumentModel {
+        SpecDocumentModel {
+            id: "doc-1".to_string(),
+            content_sha256: String::new(),
+            source_system: "confluence".to_string(),
+            source_id: "123".to_string(),
+            title: "API spec".to_string(),
+            content: content.to_string(),
+            url: String::new(),
+            author: String::new(),
+            created_at: Utc::now(),
+            modified_at: Utc::now(),
+            metadata: HashMap::new(),
+            version: 1,
+            status: DocumentStatus::Published,
+        }
+    }
+
+    fn invariant(id: &str, natural_language: &str) -> InvariantModel {
+        InvariantModel {
+            id: id.to_string(),
+            content_sha256: String::new(),
+            description: String::new(),
+            formal_expression: String::new(),
+            natural_language: natural_language.to_string(),
+            variables: Vec::new(),
+            units: HashMap::new(),
+            confidence_score: 0.9,
+            source_document_id: "doc-1".to_string(),
+            extracted_at: Utc::now(),
+            status: InvariantStatus::Extracted,
+            tags: Vec::new(),
+            priority: Priority::Medium,
+            source_span: None,
+            classification: None,
+        }
+    }
+
+    #[test]
+    fn test_normalize_content() {
+        let normalized = normalize_content("# Title  \r\n\r\n\r\nFirst line\t\r\nSecond\n\n\n");
+        assert_eq!(normalized, "# Title\n\nFirst line\nSecond");
+    }
+
+    #[test]
+    fn test_split_blocks() {
+        let content = normalize_content("# Limits\n\nLatency stays low.\nAlways.\n\n- Item one\n- Item two");
+        let blocks = split_blocks("doc-1", &content);
+
+        let kinds: Vec<BlockKind> = blocks.iter().map(|b| b.kind.clone()).collect();
+        assert_eq!(kinds, vec![BlockKind::Heading, BlockKind::Paragraph, BlockKind::ListItem, BlockKind::ListItem]);
+
+        for block in &blocks {
+            let text: String = content.chars().skip(block.start).take(block.end - block.start).collect();
+            assert_eq!(text, block.text);
+        }
+    }
+
+    #[test]
+    fn test_block_ids_are_stable_across_edits() {
+        let before = split_blocks("doc-1", "# Limits\n\nLatency stays low.");
+        let after = split_blocks("doc-1", "# Limits\n\nLatency stays very low.");
+
+        assert_eq!(before[0].id, after[0].id);
+        assert_ne!(before[1].id, after[1].id);
+    }
+
+    #[test]
+    fn test_anchors() {
+        let doc = document("# Limits\n\nThe service must respond quickly. Response time must not exceed 100ms for any request.");
+        let invariants = vec![
+            invariant("exact", "response time must not exceed 100ms"),
+            invariant("fuzzy", "Any request response time should never exceed 100ms"),
+            invariant("missing", "Passwords are hashed with bcrypt"),
+        ];
+
+        let preview = build_document_preview(&doc, &invariants);
+
+        let exact = preview.anchors.iter().find(|a| a.invariant_id == "exact").unwrap();
+        assert_eq!(exact.match_kind, AnchorMatch::Exact);
+        let text: String = preview.normalized_content.chars().skip(exact.start).take(exact.end - exact.start).collect();
+        assert_eq!(text, "Response time must not exceed 100ms");
+
+        let fuzzy = preview.anchors.iter().find(|a| a.invariant_id == "fuzzy").unwrap();
+        assert_eq!(fuzzy.match_kind, AnchorMatch::Fuzzy);
+        assert_eq!(fuzzy.block_id, exact.block_id);
+
+        assert_eq!(preview.unanchored_invariant_ids, vec!["missing".to_string()]);
+    }
+
+    #[test]
+    fn test_verified_source_quote_takes_precedence() {
+        let doc = document("# Limits\n\nThe service must respond quickly. Response time must not exceed 100ms.");
+        let mut quoted = invariant("quoted", "The service must respond quickly");
+        quoted.source_span = Some(SourceSpanModel {
+            quote: "Response time must not exceed 100ms.".to_string(),
+            start_offset: 44,
+            end_offset: 80,
+            verified: true,
+        });
+        let mut unverified = quoted.clone();
+        unverified.id = "unverified".to_string();
+        unverified.source_span.as_mut().unwrap().verified = false;
+
+        let preview = build_document_preview(&doc, &[quoted, unverified]);
+
+        let quoted = preview.anchors.iter().find(|a| a.invariant_id == "quoted").unwrap();
+        assert_eq!(quoted.match_kind, AnchorMatch::SourceQuote);
+        let text: String = preview.normalized_content.chars().skip(quoted.start).take(quoted.end - quoted.start).collect();
+        assert_eq!(text, "Response time must not exceed 100ms.");
+
+        let unverified = preview.anchors.iter().find(|a| a.invariant_id == "unverified").unwrap();
+        assert_eq!(unverified.match_kind, AnchorMatch::Exact);
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use jsonschema::JSONSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::generate_json_schema;
+
+// Definitions in generate_json_schema() that instances can be validated against
+const VALIDATED_DEFINITIONS: &[&str] = &[
+    "SpecDocument",
+    "Invariant",
+    "Variable",
+    "InvariantSet",
+    "LeanTheorem",
+    "ProofArtifact",
+    "BadgeStatus",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value, empty for the document root
+    pub instance_path: String,
+    /// JSON pointer to the schema keyword that rejected it
+    pub schema_path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaValidationError {
+    pub definition: String,
+    pub violations: Vec<SchemaViolation>,
+}
+
+impl fmt::Display for SchemaValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed schema validation: ", self.definition)?;
+        let details: Vec<String> = self
+            .violations
+            .iter()
+            .map(|v| {
+                let path = if v.instance_path.is_empty() { "/" } else { &v.instance_path };
+                format!("{}: {}", path, v.message)
+            })
+            .collect();
+        write!(f, "{}", details.join("; "))
+    }
+}
+
+impl std::error::Error for SchemaValidationError {}
+
+// Each definition is compiled once as a standalone schema that shares the
+// root definitions, so nested $refs keep resolving
+fn compiled_schemas() -> &'static HashMap<&'static str, JSONSchema> {
+    static SCHEMAS: OnceLock<HashMap<&'static str, JSONSchema>> = OnceLock::new();
+
+    SCHEMAS.get_or_init(|| {
+        let root = generate_json_schema();
+
+        VALIDATED_DEFINITIONS
+            .iter()
+            .map(|definition| {
+                let schema = serde_json::json!({
+                    "$schema": root["$schema"],
+                    "$ref": format!("#/definitions/{}", definition),
+                    "definitions": root["definitions"],
+                });
+                let compiled = JSONSchema::compile(&schema)
+                    .unwrap_or_else(|e| panic!("invalid built-in schema for {}: {}", definition, e));
+                (*definition, compiled)
+            })
+            .collect()
+    })
+}
+
+pub fn validate_against(definition: &str, instance: &Value) -> Result<(), SchemaValidationError> {
+    let schema = compiled_schemas().get(definition).ok_or_else(|| SchemaValidationError {
+        definition: definition.to_string(),
+        violations: vec![SchemaViolation {
+            instance_path: String::new(),
+            schema_path: String::new(),
+            message: format!("unknown schema definition: {}", definition),
+        }],
+    })?;
+
+    schema.validate(instance).map_err(|errors| SchemaValidationError {
+        definition: definition.to_string(),
+        violations: errors
+            .map(|error| SchemaViolation {
+                instance_path: error.instance_path.to_string(),
+                schema_path: error.schema_path.to_string(),
+                message: error.to_string(),
+            })
+            .collect(),
+    })
+}
+
+pub fn validate_spec_document(instance: &Value) -> Result<(), SchemaValidationError> {
+    validate_against("SpecDocument", instance)
+}
+
+pub fn validate_invariant(instance: &Value) -> Result<(), SchemaValidationError> {
+    validate_against("Invariant", instance)
+}
+
+pub fn validate_variable(instance: &Value) -> Result<(), SchemaValidationError> {
+    validate_against("Variable", instance)
+}
+
+pub fn validate_invariant_set(instance: &Value) -> Result<(), SchemaValidationError> {
+    validate_against("InvariantSet", instance)
+}
+
+pub fn validate_lean_theorem(instance: &Value) -> Result<(), SchemaValidationError> {
+    validate_against("LeanTheorem", instance)
+}
+
+pub fn validate_proof_artifact(instance: &Value) -> Result<(), SchemaValidationError> {
+    validate_against("ProofArtifact", instance)
+}
+
+pub fn validate_badge_status(instance: &Value) -> Result<(), SchemaValidationError> {
+    validate_against("BadgeStatus", instance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::{
+        InvariantModel, InvariantSetModel, InvariantSetStatus, LeanTheoremModel, ProofArtifactModel,
+        SpecDocumentModel, VariableModel,
+    };
+
+    fn spec_document() -> Value {
+        json!({
+            "id": "doc-1",
+            "content_sha256": "a".repeat(64),
+            "source_system": "jira",
+            "title": "Payments",
+            "content": "Refunds must not exceed the original charge.",
+            "version": 1,
+            "status": "published"
+        })
+    }
+
+    #[test]
+    fn test_valid_spec_document() {
+        assert!(validate_spec_document(&spec_document()).is_ok());
+    }
+
+    #[test]
+    fn test_spec_document_violations_carry_paths() {
+        let mut document = spec_document();
+        document.as_object_mut().unwrap().remove("title");
+        document["content_sha256"] = json!("not-a-hash");
+        document["version"] = json!(0);
+
+        let error = validate_spec_document(&document).unwrap_err();
+        let mut paths: Vec<&str> = error.violations.iter().map(|v| v.instance_path.as_str()).collect();
+        paths.sort();
+
+        assert_eq!(error.definition, "SpecDocument");
+        assert_eq!(paths, vec!["", "/content_sha256", "/version"]);
+    }
+
+    #[test]
+    fn test_invariant_nested_variable_errors() {
+        let invariant = json!({
+            "id": "inv-1",
+            "content_sha256": "b".repeat(64),
+            "description": "Refund bound",
+            "formal_expression": "refund <= charge",
+            "confidence_score": 1.5,
+            "variables": [{"name": "refund"}],
+            "priority": "urgent"
+        });
+
+        let error = validate_invariant(&invariant).unwrap_err();
+        let paths: Vec<&str> = error.violations.iter().map(|v| v.instance_path.as_str()).collect();
+
+        assert!(paths.contains(&"/confidence_score"));
+        assert!(paths.contains(&"/variables/0"));
+        assert!(paths.contains(&"/priority"));
+    }
+
+    #[test]
+    fn test_models_serialize_to_valid_instances() {
+        let document = SpecDocumentModel::builder("jira", "PROJ-1", "Refunds must not exceed the original charge.")
+            .title("Payments")
+            .build()
+            .unwrap();
+        let invariant = InvariantModel::builder(&document.id, "Refund bound", "refund <= charge")
+            .variable(VariableModel {
+                name: "refund".to_string(),
+                var_type: "Nat".to_string(),
+                description: String::new(),
+                unit: String::new(),
+                constraints: Vec::new(),
+            })
+            .build()
+            .unwrap();
+        let theorem = LeanTheoremModel::builder(&invariant.id, "refund_bound", "theorem refund_bound : True := trivial")
+            .build()
+            .unwrap();
+        let artifact = ProofArtifactModel::builder(&theorem.id, &invariant.id).build().unwrap();
+        let invariant_set = InvariantSetModel {
+            id: "set-1".to_string(),
+            content_sha256: "c".repeat(64),
+            name: "Payments".to_string(),
+            description: String::new(),
+            invariants: vec![invariant.clone()],
+            source_document_ids: vec![document.id.clone()],
+            created_at: document.created_at,
+            modified_at: document.modified_at,
+            status: InvariantSetStatus::Review,
+        };
+
+        validate_spec_document(&serde_json::to_value(&document).unwrap()).unwrap();
+        validate_invariant(&serde_json::to_value(&invariant).unwrap()).unwrap();
+        validate_invariant_set(&serde_json::to_value(&invariant_set).unwrap()).unwrap();
+        validate_lean_theorem(&serde_json::to_value(&theorem).unwrap()).unwrap();
+        validate_proof_artifact(&serde_json::to_value(&artifact).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_badge_status_violations() {
+        let badge = json!({
+            "id": "badge-1",
+            "repo_owner": "fraware",
+            "repo_name": "",
+            "commit_sha": "abc123",
+            "state": "green",
+            "coverage_percentage": 120.0
+        });
+
+        let error = validate_badge_status(&badge).unwrap_err();
+        let mut paths: Vec<&str> = error.violations.iter().map(|v| v.instance_path.as_str()).collect();
+        paths.sort();
+
+        assert_eq!(paths, vec!["/commit_sha", "/coverage_percentage", "/repo_name", "/state"]);
+    }
+
+    #[test]
+    fn test_unknown_definition() {
+        let error = validate_against("Nope", &json!({})).unwrap_err();
+        assert_eq!(error.violations.len(), 1);
+    }
+}
@@ -0,0 +1,340 @@
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use audit::{actions, AuditEvent, AuditLog};
+use config::{Config, File};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+// Settings are re-read whenever the file's modification time changes or
+// the process receives SIGHUP. A file that fails to parse or validate is
+// logged and ignored; the snapshot before it stays in effect.
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReloadError {
+    #[error("Failed to read settings from {path}: {message}")]
+    Read { path: PathBuf, message: String },
+
+    #[error("Invalid settings in {path}: {message}")]
+    Invalid { path: PathBuf, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, ReloadError>;
+
+/// Settings a service lets operators change without a restart
+pub trait RuntimeSettings: Serialize + DeserializeOwned + PartialEq + Debug + Send + Sync + 'static {
+    /// Checked before a reloaded snapshot replaces the current one
+    fn validate(&self) -> std::result::Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The current settings, shared by every component that reads them. A
+/// reload swaps the whole snapshot at once, so readers never see half of
+/// an update.
+#[derive(Debug)]
+pub struct ConfigHandle<T> {
+    current: Arc<RwLock<Arc<T>>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl<T> Clone for ConfigHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            current: self.current.clone(),
+            generation: self.generation.clone(),
+        }
+    }
+}
+
+impl<T: PartialEq> ConfigHandle<T> {
+    pub fn new(settings: T) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(settings))),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn current(&self) -> Arc<T> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Incremented on every reload; anything built from an older
+    /// generation is stale
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Swaps in new settings, returning false if nothing changed
+    pub fn replace(&self, settings: T) -> bool {
+        let mut current = self.current.write().unwrap();
+        if **current == settings {
+            return false;
+        }
+        *current = Arc::new(settings);
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        true
+    }
+}
+
+/// Reads and validates settings from a YAML, JSON or TOML file, the format
+/// chosen by its extension
+pub fn load_file<T: RuntimeSettings>(path: &Path) -> Result<T> {
+    let settings: T = Config::builder()
+        .add_source(File::from(path))
+        .build()
+        .and_then(|config| config.try_deserialize())
+        .map_err(|e| ReloadError::Read { path: path.to_path_buf(), message: e.to_string() })?;
+    settings
+        .validate()
+        .map_err(|message| ReloadError::Invalid { path: path.to_path_buf(), message })?;
+    Ok(settings)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReloadTrigger {
+    FileChanged,
+    Signal,
+}
+
+impl ReloadTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReloadTrigger::FileChanged => "file_changed",
+            ReloadTrigger::Signal => "sighup",
+        }
+    }
+}
+
+type Loader<T> = Box<dyn Fn(&Path) -> Result<T> + Send + Sync>;
+
+/// Re-reads a settings file into a `ConfigHandle` when it changes or on
+/// SIGHUP, recording each change in the audit trail
+pub struct ConfigWatcher<T> {
+    handle: ConfigHandle<T>,
+    path: PathBuf,
+    resource: String,
+    loader: Loader<T>,
+    audit_log: Option<Arc<AuditLog>>,
+    poll_interval: Duration,
+    modified: Option<SystemTime>,
+}
+
+impl<T: RuntimeSettings> ConfigWatcher<T> {
+    /// `resource` names the settings in logs and audit records, e.g.
+    /// "nlp/runtime_settings". The file as it is now is taken to be what
+    /// `handle` was loaded from.
+    pub fn new(handle: ConfigHandle<T>, path: impl AsRef<Path>, resource: &str) -> Self {
+        let path = path.as_ref().to_path_buf();
+        Self {
+            modified: modified_time(&path),
+            handle,
+            path,
+            resource: resource.to_string(),
+            loader: Box::new(|path| load_file(path)),
+            audit_log: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Replaces `load_file`, for settings derived from a larger config file
+    pub fn with_loader(mut self, loader: impl Fn(&Path) -> Result<T> + Send + Sync + 'static) -> Self {
+        self.loader = Box::new(loader);
+        self
+    }
+
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Reloads if the file was modified since the last check
+    pub async fn check(&mut self) -> Result<bool> {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return Ok(false);
+        }
+        // Remembered even if the reload fails, so a broken file is reported
+        // once rather than on every poll
+        self.modified = modified;
+        self.reload(ReloadTrigger::FileChanged).await
+    }
+
+    /// Loads and validates the file and swaps it in, returning whether any
+    /// setting changed
+    pub async fn reload(&mut self, trigger: ReloadTrigger) -> Result<bool> {
+        let loaded = (self.loader)(&self.path)?;
+        let before = self.handle.current();
+        if !self.handle.replace(loaded) {
+            return Ok(false);
+        }
+
+        let (before, after) = changed_settings(before.as_ref(), self.handle.current().as_ref());
+        let keys: Vec<&String> = after.keys().collect();
+        info!("Reloaded {} from {} on {}: {:?} changed", self.resource, self.path.display(), trigger.as_str(), keys);
+
+        // The new settings are already in effect, so a failed write is only logged
+        if let Some(audit_log) = &self.audit_log {
+            let event = AuditEvent::new(&format!("config-watcher:{}", trigger.as_str()), actions::CONFIG_RELOADED, &self.resource)
+                .with_before(&before)
+                .with_after(&after);
+            if let Err(e) = audit_log.record(event).await {
+                error!("Failed to write audit record for {} reload: {}", self.resource, e);
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => Some(hangup),
+                Err(e) => {
+                    warn!("SIGHUP reloads of {} are unavailable: {}", self.resource, e);
+                    None
+                }
+            };
+            let mut interval = tokio::time::interval(self.poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+            loop {
+                let result = tokio::select! {
+                    _ = interval.tick() => self.check().await,
+                    Some(()) = next_hangup(&mut hangup) => self.reload(ReloadTrigger::Signal).await,
+                };
+                if let Err(e) = result {
+                    error!("Keeping current {}: {}", self.resource, e);
+                }
+            }
+        })
+    }
+}
+
+async fn next_hangup(hangup: &mut Option<Signal>) -> Option<()> {
+    match hangup {
+        Some(hangup) => hangup.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// The top-level settings that differ, as they were and as they are now
+fn changed_settings<T: Serialize>(before: &T, after: &T) -> (Map<String, Value>, Map<String, Value>) {
+    let as_map = |settings: &T| match serde_json::to_value(settings) {
+        Ok(Value::Object(map)) => map,
+        Ok(value) => Map::from_iter([("value".to_string(), value)]),
+        Err(e) => Map::from_iter([("error".to_string(), Value::String(e.to_string()))]),
+    };
+    let (before, after) = (as_map(before), as_map(after));
+
+    let mut changed_before = Map::new();
+    let mut changed_after = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if before.get(key) != after.get(key) && !changed_after.contains_key(key) {
+            changed_before.insert(key.clone(), before.get(key).cloned().unwrap_or(Value::Null));
+            changed_after.insert(key.clone(), after.get(key).cloned().unwrap_or(Value::Null));
+        }
+    }
+    (changed_before, changed_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Tunables {
+        confidence_threshold: f64,
+        model: String,
+    }
+
+    impl RuntimeSettings for Tunables {
+        fn validate(&self) -> std::result::Result<(), String> {
+            if !(0.0..=1.0).contains(&self.confidence_threshold) {
+                return Err("confidence_threshold must be between 0 and 1".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    fn tunables(confidence_threshold: f64, model: &str) -> Tunables {
+        Tunables { confidence_threshold, model: model.to_string() }
+    }
+
+    fn settings_file(name: &str, settings: &Value) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("reload-{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, settings.to_string()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_handle_replace() {
+        let handle = ConfigHandle::new(tunables(0.5, "opus"));
+        let reader = handle.clone();
+
+        assert!(!handle.replace(tunables(0.5, "opus")));
+        assert_eq!(reader.generation(), 0);
+        assert!(handle.replace(tunables(0.7, "opus")));
+        assert_eq!(reader.generation(), 1);
+        assert_eq!(reader.current().confidence_threshold, 0.7);
+    }
+
+    #[tokio::test]
+    async fn test_reload_swaps_and_audits_changed_keys() {
+        let path = settings_file("swap", &serde_json::json!({"confidence_threshold": 0.5, "model": "opus"}));
+        let handle = ConfigHandle::new(load_file::<Tunables>(&path).unwrap());
+        let audit_log = Arc::new(AuditLog::in_memory().await.unwrap());
+        let mut watcher = ConfigWatcher::new(handle.clone(), &path, "nlp/runtime_settings")
+            .with_audit_log(audit_log.clone());
+
+        assert!(!watcher.check().await.unwrap());
+        assert!(!watcher.reload(ReloadTrigger::Signal).await.unwrap());
+
+        std::fs::write(&path, serde_json::json!({"confidence_threshold": 0.8, "model": "opus"}).to_string()).unwrap();
+        assert!(watcher.reload(ReloadTrigger::Signal).await.unwrap());
+        assert_eq!(*handle.current(), tunables(0.8, "opus"));
+
+        let records = audit_log.records().await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].event.action, actions::CONFIG_RELOADED);
+        assert_eq!(records[0].event.actor, "config-watcher:sighup");
+        assert_eq!(records[0].event.before, Some(serde_json::json!({"confidence_threshold": 0.5})));
+        assert_eq!(records[0].event.after, Some(serde_json::json!({"confidence_threshold": 0.8})));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_settings_keep_the_current_snapshot() {
+        let path = settings_file("invalid", &serde_json::json!({"confidence_threshold": 0.5, "model": "opus"}));
+        let handle = ConfigHandle::new(tunables(0.5, "opus"));
+        let mut watcher = ConfigWatcher::new(handle.clone(), &path, "nlp/runtime_settings");
+
+        std::fs::write(&path, serde_json::json!({"confidence_threshold": 3.0, "model": "opus"}).to_string()).unwrap();
+        assert!(matches!(watcher.reload(ReloadTrigger::Signal).await, Err(ReloadError::Invalid { .. })));
+        std::fs::write(&path, "{not json").unwrap();
+        assert!(matches!(watcher.reload(ReloadTrigger::Signal).await, Err(ReloadError::Read { .. })));
+
+        assert_eq!(*handle.current(), tunables(0.5, "opus"));
+        assert_eq!(handle.generation(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
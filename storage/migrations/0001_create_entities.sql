@@ -0,0 +1,15 @@
+-- Single table for every entity kind, mirroring the DynamoDB layout:
+-- entities are stored as encoded protobuf alongside their index columns
+CREATE TABLE IF NOT EXISTS entities (
+    kind        TEXT        NOT NULL,
+    id          TEXT        NOT NULL,
+    source_id   TEXT,
+    status      INTEGER     NOT NULL,
+    payload     BYTEA       NOT NULL,
+    version     BIGINT      NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (kind, id)
+);
+
+CREATE INDEX IF NOT EXISTS entities_source_idx ON entities (kind, source_id, id) WHERE source_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS entities_status_idx ON entities (kind, status, id);
@@ -0,0 +1,15 @@
+-- Transactional outbox: events are inserted in the same transaction as the
+-- entity write they announce and deleted by the relay once delivered and
+-- past retention
+CREATE TABLE IF NOT EXISTS outbox (
+    idempotency_key TEXT        PRIMARY KEY,
+    subject         TEXT        NOT NULL,
+    payload         BYTEA       NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL,
+    published_at    TIMESTAMPTZ,
+    attempts        INTEGER     NOT NULL DEFAULT 0,
+    last_error      TEXT
+);
+
+CREATE INDEX IF NOT EXISTS outbox_pending_idx ON outbox (created_at) WHERE published_at IS NULL;
+CREATE INDEX IF NOT EXISTS outbox_published_idx ON outbox (published_at) WHERE published_at IS NOT NULL;
@@ -0,0 +1,757 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::time::Duration;
+use async_trait::async_trait;
+use aws_sdk_dynamodb::Client as DynamoClient;
+use aws_sdk_dynamodb::error::SdkError;
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::operation::update_item::UpdateItemError;
+use aws_sdk_dynamodb::primitives::Blob;
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, AttributeValue, BillingMode, GlobalSecondaryIndex, KeySchemaElement, KeyType,
+    Projection, ProjectionType, ReturnValue, ScalarAttributeType, TableStatus, TransactWriteItem, Update,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{
+    partition_key, source_index_key, status_index_key, Entity, EntityQuery, ExpectedVersion, OutboxEvent,
+    OutboxStore, QueryPage, Repository, StorageError, Versioned, ENTITY_SORT_KEY, EVENT_SORT_KEY, OUTBOX_KIND,
+};
+
+pub const DEFAULT_TABLE_NAME: &str = "spec-to-proof-entities";
+
+const SOURCE_INDEX: &str = "gsi1";
+const STATUS_INDEX: &str = "gsi2";
+
+// A transaction holds at most 100 items, one of which is the entity
+const MAX_TRANSACTION_EVENTS: usize = 99;
+
+// Retries of an unconditional put_with_events that lost a race with
+// another writer
+const MAX_UNCONDITIONAL_ATTEMPTS: usize = 3;
+
+// Key attributes; everything that names a key must round-trip through page tokens
+const KEY_ATTRIBUTES: &[&str] = &["pk", "sk", "gsi1pk", "gsi1sk", "gsi2pk", "gsi2sk"];
+
+/// DynamoDB repository using a single table for every entity kind. Entities
+/// are stored as encoded protobuf alongside the key and index attributes.
+pub struct DynamoRepository<E> {
+    client: DynamoClient,
+    table_name: String,
+    _entity: PhantomData<fn() -> E>,
+}
+
+impl<E: Entity> DynamoRepository<E> {
+    pub fn new(client: DynamoClient, table_name: &str) -> Self {
+        Self {
+            client,
+            table_name: table_name.to_string(),
+            _entity: PhantomData,
+        }
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    pub async fn ensure_table_exists(&self) -> Result<(), StorageError> {
+        ensure_entity_table(&self.client, &self.table_name).await
+    }
+
+    // The limit applies before the kind filter, so a page may hold fewer
+    // than `limit` entities, or none, and still have a next page
+    async fn scan_kind(&self, page_token: Option<&str>, limit: u32) -> Result<QueryPage<E>, StorageError> {
+        let mut request = self.client
+            .scan()
+            .table_name(&self.table_name)
+            .limit(limit.max(1) as i32)
+            .filter_expression("begins_with(pk, :kind) AND sk = :sk")
+            .expression_attribute_values(":kind", AttributeValue::S(format!("{}#", E::KIND)))
+            .expression_attribute_values(":sk", AttributeValue::S(ENTITY_SORT_KEY.to_string()));
+
+        if let Some(token) = page_token {
+            request = request.set_exclusive_start_key(Some(decode_page_token(token)?));
+        }
+
+        let response = request.send().await.map_err(backend_error)?;
+        decode_page(response.items.as_deref(), response.last_evaluated_key.as_ref())
+    }
+}
+
+/// Creates the entity table and its indexes if it does not exist yet
+pub async fn ensure_entity_table(client: &DynamoClient, table_name: &str) -> Result<(), StorageError> {
+    if client.describe_table().table_name(table_name).send().await.is_ok() {
+        tracing::info!("Entity table {} already exists", table_name);
+        return Ok(());
+    }
+
+    tracing::info!("Creating entity table: {}", table_name);
+
+    let mut request = client
+        .create_table()
+        .table_name(table_name)
+        .billing_mode(BillingMode::PayPerRequest)
+        .key_schema(key_element("pk", KeyType::Hash)?)
+        .key_schema(key_element("sk", KeyType::Range)?);
+
+    for attribute in KEY_ATTRIBUTES {
+        request = request.attribute_definitions(
+            AttributeDefinition::builder()
+                .attribute_name(*attribute)
+                .attribute_type(ScalarAttributeType::S)
+                .build()
+                .map_err(backend_error)?,
+        );
+    }
+
+    for (index, hash, range) in [(SOURCE_INDEX, "gsi1pk", "gsi1sk"), (STATUS_INDEX, "gsi2pk", "gsi2sk")] {
+        request = request.global_secondary_indexes(
+            GlobalSecondaryIndex::builder()
+                .index_name(index)
+                .key_schema(key_element(hash, KeyType::Hash)?)
+                .key_schema(key_element(range, KeyType::Range)?)
+                .projection(Projection::builder().projection_type(ProjectionType::All).build())
+                .build()
+                .map_err(backend_error)?,
+        );
+    }
+
+    request.send().await.map_err(backend_error)?;
+    wait_for_table_active(client, table_name).await?;
+    tracing::info!("Entity table {} created successfully", table_name);
+
+    Ok(())
+}
+
+/// Fails unless the table exists and is active
+pub async fn describe_active_table(client: &DynamoClient, table_name: &str) -> Result<(), StorageError> {
+    let response = client.describe_table().table_name(table_name).send().await.map_err(backend_error)?;
+    match response.table.and_then(|table| table.table_status) {
+        Some(TableStatus::Active) => Ok(()),
+        status => Err(StorageError::Backend(format!("Table {} is not active: {:?}", table_name, status))),
+    }
+}
+
+async fn wait_for_table_active(client: &DynamoClient, table_name: &str) -> Result<(), StorageError> {
+    let max_attempts = 30;
+    let delay = Duration::from_secs(2);
+
+    for attempt in 1..=max_attempts {
+        match client.describe_table().table_name(table_name).send().await {
+            Ok(response) => {
+                let status = response.table.and_then(|table| table.table_status);
+                if status == Some(TableStatus::Active) {
+                    return Ok(());
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to describe table (attempt {}): {}", attempt, e);
+            }
+        }
+
+        if attempt < max_attempts {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    Err(StorageError::Backend("Table did not become active within expected time".to_string()))
+}
+
+fn key_element(name: &str, key_type: KeyType) -> Result<KeySchemaElement, StorageError> {
+    KeySchemaElement::builder()
+        .attribute_name(name)
+        .key_type(key_type)
+        .build()
+        .map_err(backend_error)
+}
+
+fn backend_error(error: impl std::fmt::Display) -> StorageError {
+    StorageError::Backend(error.to_string())
+}
+
+fn decode_item<E: Entity>(item: &HashMap<String, AttributeValue>) -> Result<Versioned<E>, StorageError> {
+    let payload = item
+        .get("payload")
+        .and_then(|v| v.as_b().ok())
+        .ok_or_else(|| StorageError::Decode("missing payload".to_string()))?;
+    let version = item
+        .get("version")
+        .and_then(|v| v.as_n().ok())
+        .and_then(|n| n.parse::<u64>().ok())
+        .ok_or_else(|| StorageError::Decode("missing version".to_string()))?;
+    let updated_at = item
+        .get("updated_at")
+        .and_then(|v| v.as_s().ok())
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_default();
+
+    Ok(Versioned {
+        entity: E::decode(payload.as_ref())?,
+        version,
+        updated_at,
+    })
+}
+
+// Page tokens carry DynamoDB's LastEvaluatedKey, which only ever holds
+// string key attributes in this table
+fn encode_page_token(key: &HashMap<String, AttributeValue>) -> Option<String> {
+    let strings: serde_json::Map<String, serde_json::Value> = key
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), serde_json::Value::String(value.as_s().ok()?.clone()))))
+        .collect();
+    serde_json::to_vec(&strings).ok().map(hex::encode)
+}
+
+fn decode_page<E: Entity>(
+    items: Option<&[HashMap<String, AttributeValue>]>,
+    last_evaluated_key: Option<&HashMap<String, AttributeValue>>,
+) -> Result<QueryPage<E>, StorageError> {
+    Ok(QueryPage {
+        items: items.unwrap_or_default().iter().map(decode_item).collect::<Result<Vec<_>, _>>()?,
+        next_page_token: last_evaluated_key.and_then(encode_page_token),
+    })
+}
+
+fn decode_page_token(token: &str) -> Result<HashMap<String, AttributeValue>, StorageError> {
+    let bytes = hex::decode(token).map_err(|e| StorageError::InvalidPageToken(e.to_string()))?;
+    let strings: HashMap<String, String> =
+        serde_json::from_slice(&bytes).map_err(|e| StorageError::InvalidPageToken(e.to_string()))?;
+
+    if let Some(unknown) = strings.keys().find(|name| !KEY_ATTRIBUTES.contains(&name.as_str())) {
+        return Err(StorageError::InvalidPageToken(format!("unexpected attribute {}", unknown)));
+    }
+
+    Ok(strings.into_iter().map(|(name, value)| (name, AttributeValue::S(value))).collect())
+}
+
+// One UpdateItem, sent on its own or as part of a transaction
+#[derive(Default)]
+struct ItemUpdate {
+    key: HashMap<String, AttributeValue>,
+    expression: String,
+    condition: Option<String>,
+    names: HashMap<String, String>,
+    values: HashMap<String, AttributeValue>,
+}
+
+impl ItemUpdate {
+    fn name(&mut self, placeholder: &str, attribute: &str) {
+        self.names.insert(placeholder.to_string(), attribute.to_string());
+    }
+
+    fn value(&mut self, placeholder: &str, value: AttributeValue) {
+        self.values.insert(placeholder.to_string(), value);
+    }
+
+    fn into_transact_item(self, table_name: &str) -> Result<TransactWriteItem, StorageError> {
+        let update = Update::builder()
+            .table_name(table_name)
+            .set_key(Some(self.key))
+            .update_expression(self.expression)
+            .set_condition_expression(self.condition)
+            .set_expression_attribute_names(Some(self.names))
+            .set_expression_attribute_values(Some(self.values))
+            .build()
+            .map_err(backend_error)?;
+        Ok(TransactWriteItem::builder().update(update).build())
+    }
+}
+
+fn entity_update<E: Entity>(entity: &E, expected: ExpectedVersion) -> ItemUpdate {
+    let id = entity.entity_id();
+    let mut update = ItemUpdate {
+        key: HashMap::from([
+            ("pk".to_string(), AttributeValue::S(partition_key(E::KIND, &id))),
+            ("sk".to_string(), AttributeValue::S(ENTITY_SORT_KEY.to_string())),
+        ]),
+        ..Default::default()
+    };
+
+    let mut set_clauses = vec![
+        "#kind = :kind",
+        "#entity_id = :entity_id",
+        "#payload = :payload",
+        "#updated_at = :updated_at",
+        "#gsi2pk = :gsi2pk",
+        "#gsi2sk = :updated_at",
+        "#version = if_not_exists(#version, :zero) + :one",
+    ];
+    for (placeholder, attribute) in [
+        ("#kind", "kind"),
+        ("#entity_id", "entity_id"),
+        ("#payload", "payload"),
+        ("#updated_at", "updated_at"),
+        ("#gsi1pk", "gsi1pk"),
+        ("#gsi1sk", "gsi1sk"),
+        ("#gsi2pk", "gsi2pk"),
+        ("#gsi2sk", "gsi2sk"),
+        ("#version", "version"),
+    ] {
+        update.name(placeholder, attribute);
+    }
+    update.value(":kind", AttributeValue::S(E::KIND.to_string()));
+    update.value(":entity_id", AttributeValue::S(id.clone()));
+    update.value(":payload", AttributeValue::B(Blob::new(entity.encode_to_vec())));
+    update.value(":updated_at", AttributeValue::S(Utc::now().to_rfc3339()));
+    update.value(":gsi2pk", AttributeValue::S(status_index_key(E::KIND, entity.status())));
+    update.value(":zero", AttributeValue::N("0".to_string()));
+    update.value(":one", AttributeValue::N("1".to_string()));
+
+    let remove_clause = match entity.source_id() {
+        Some(source_id) => {
+            set_clauses.push("#gsi1pk = :gsi1pk");
+            set_clauses.push("#gsi1sk = :gsi1sk");
+            update.value(":gsi1pk", AttributeValue::S(source_index_key(&source_id)));
+            update.value(":gsi1sk", AttributeValue::S(partition_key(E::KIND, &id)));
+            ""
+        }
+        // Entities without a source drop out of the source index
+        None => " REMOVE #gsi1pk, #gsi1sk",
+    };
+    update.expression = format!("SET {}{}", set_clauses.join(", "), remove_clause);
+
+    match expected {
+        ExpectedVersion::Any => {}
+        ExpectedVersion::Absent => update.condition = Some("attribute_not_exists(pk)".to_string()),
+        ExpectedVersion::Exactly(version) => {
+            update.condition = Some("#version = :expected".to_string());
+            update.value(":expected", AttributeValue::N(version.to_string()));
+        }
+    }
+    update
+}
+
+fn event_key(idempotency_key: &str) -> HashMap<String, AttributeValue> {
+    HashMap::from([
+        ("pk".to_string(), AttributeValue::S(partition_key(OUTBOX_KIND, idempotency_key))),
+        ("sk".to_string(), AttributeValue::S(EVENT_SORT_KEY.to_string())),
+    ])
+}
+
+fn outbox_index_key(published: bool) -> String {
+    format!("{}#{}", OUTBOX_KIND, if published { "PUBLISHED" } else { "PENDING" })
+}
+
+// Fixed-width UTC, so index sort keys order chronologically
+fn sort_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+// Inserts the event unless its idempotency key is already in the outbox.
+// Every attribute is set with if_not_exists rather than guarded by a
+// condition, so a duplicate doesn't cancel the transaction it is part of.
+fn event_update(event: &OutboxEvent) -> ItemUpdate {
+    let mut update = ItemUpdate {
+        key: event_key(&event.idempotency_key),
+        ..Default::default()
+    };
+
+    let mut set_clauses = Vec::new();
+    for (attribute, placeholder, value) in [
+        ("idempotency_key", ":idempotency_key", AttributeValue::S(event.idempotency_key.clone())),
+        ("subject", ":subject", AttributeValue::S(event.subject.clone())),
+        ("payload", ":payload", AttributeValue::B(Blob::new(event.payload.clone()))),
+        ("created_at", ":created_at", AttributeValue::S(sort_timestamp(event.created_at))),
+        ("attempts", ":attempts", AttributeValue::N(event.attempts.to_string())),
+        ("gsi2pk", ":gsi2pk", AttributeValue::S(outbox_index_key(false))),
+        ("gsi2sk", ":created_at", AttributeValue::S(sort_timestamp(event.created_at))),
+    ] {
+        set_clauses.push(format!("#{0} = if_not_exists(#{0}, {1})", attribute, placeholder));
+        update.name(&format!("#{}", attribute), attribute);
+        update.value(placeholder, value);
+    }
+    update.expression = format!("SET {}", set_clauses.join(", "));
+    update
+}
+
+fn decode_event(item: &HashMap<String, AttributeValue>) -> Result<OutboxEvent, StorageError> {
+    let string = |name: &str| item.get(name).and_then(|v| v.as_s().ok()).cloned();
+    let time = |name: &str| {
+        string(name)
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc))
+    };
+
+    Ok(OutboxEvent {
+        idempotency_key: string("idempotency_key")
+            .ok_or_else(|| StorageError::Decode("missing idempotency_key".to_string()))?,
+        subject: string("subject").ok_or_else(|| StorageError::Decode("missing subject".to_string()))?,
+        payload: item
+            .get("payload")
+            .and_then(|v| v.as_b().ok())
+            .map(|blob| blob.as_ref().to_vec())
+            .ok_or_else(|| StorageError::Decode("missing payload".to_string()))?,
+        created_at: time("created_at").unwrap_or_default(),
+        published_at: time("published_at"),
+        attempts: item
+            .get("attempts")
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse().ok())
+            .unwrap_or_default(),
+        last_error: string("last_error"),
+    })
+}
+
+fn is_condition_failure(error: &SdkError<TransactWriteItemsError, aws_sdk_dynamodb::config::http::HttpResponse>) -> bool {
+    match error.as_service_error() {
+        Some(TransactWriteItemsError::TransactionCanceledException(canceled)) => canceled
+            .cancellation_reasons()
+            .iter()
+            .any(|reason| reason.code() == Some("ConditionalCheckFailed")),
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl<E: Entity> Repository<E> for DynamoRepository<E> {
+    async fn put(&self, entity: &E, expected: ExpectedVersion) -> Result<u64, StorageError> {
+        let update = entity_update(entity, expected);
+        let response = self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .set_key(Some(update.key))
+            .update_expression(update.expression)
+            .set_condition_expression(update.condition)
+            .set_expression_attribute_names(Some(update.names))
+            .set_expression_attribute_values(Some(update.values))
+            .return_values(ReturnValue::UpdatedNew)
+            .send()
+            .await
+            .map_err(|e| {
+                if matches!(e.as_service_error(), Some(UpdateItemError::ConditionalCheckFailedException(_))) {
+                    StorageError::VersionConflict {
+                        kind: E::KIND,
+                        id: entity.entity_id(),
+                        expected,
+                    }
+                } else {
+                    backend_error(e)
+                }
+            })?;
+
+        response
+            .attributes
+            .as_ref()
+            .and_then(|attributes| attributes.get("version"))
+            .and_then(|v| v.as_n().ok())
+            .and_then(|n| n.parse::<u64>().ok())
+            .ok_or_else(|| StorageError::Decode("update did not return a version".to_string()))
+    }
+
+    // TransactWriteItems returns no attributes, so the new version is
+    // derived from the expected one; unconditional writes are pinned to
+    // the version read just before
+    async fn put_with_events(
+        &self,
+        entity: &E,
+        expected: ExpectedVersion,
+        events: &[OutboxEvent],
+    ) -> Result<u64, StorageError> {
+        if events.len() > MAX_TRANSACTION_EVENTS {
+            return Err(StorageError::Backend(format!(
+                "{} outbox events exceed the {} a transaction can hold",
+                events.len(),
+                MAX_TRANSACTION_EVENTS
+            )));
+        }
+
+        let mut attempt = 1;
+        loop {
+            let pinned = match expected {
+                ExpectedVersion::Any => match self.get(&entity.entity_id()).await? {
+                    Some(current) => ExpectedVersion::Exactly(current.version),
+                    None => ExpectedVersion::Absent,
+                },
+                other => other,
+            };
+
+            let mut items = vec![entity_update(entity, pinned).into_transact_item(&self.table_name)?];
+            for event in events {
+                items.push(event_update(event).into_transact_item(&self.table_name)?);
+            }
+
+            match self.client.transact_write_items().set_transact_items(Some(items)).send().await {
+                Ok(_) => {
+                    return Ok(match pinned {
+                        ExpectedVersion::Exactly(version) => version + 1,
+                        _ => 1,
+                    })
+                }
+                Err(e) if is_condition_failure(&e) => {
+                    if expected == ExpectedVersion::Any && attempt < MAX_UNCONDITIONAL_ATTEMPTS {
+                        attempt += 1;
+                        continue;
+                    }
+                    return Err(StorageError::VersionConflict {
+                        kind: E::KIND,
+                        id: entity.entity_id(),
+                        expected,
+                    });
+                }
+                Err(e) => return Err(backend_error(e)),
+            }
+        }
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Versioned<E>>, StorageError> {
+        let response = self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("pk", AttributeValue::S(partition_key(E::KIND, id)))
+            .key("sk", AttributeValue::S(ENTITY_SORT_KEY.to_string()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(backend_error)?;
+
+        response.item.as_ref().map(decode_item).transpose()
+    }
+
+    async fn query(
+        &self,
+        query: &EntityQuery,
+        page_token: Option<&str>,
+        limit: u32,
+    ) -> Result<QueryPage<E>, StorageError> {
+        let mut request = self.client
+            .query()
+            .table_name(&self.table_name)
+            .limit(limit.max(1) as i32);
+
+        request = match query {
+            EntityQuery::BySource(source_id) => request
+                .index_name(SOURCE_INDEX)
+                .key_condition_expression("gsi1pk = :pk AND begins_with(gsi1sk, :kind)")
+                .expression_attribute_values(":pk", AttributeValue::S(source_index_key(source_id)))
+                .expression_attribute_values(":kind", AttributeValue::S(format!("{}#", E::KIND))),
+            EntityQuery::ByStatus(status) => request
+                .index_name(STATUS_INDEX)
+                .key_condition_expression("gsi2pk = :pk")
+                .expression_attribute_values(":pk", AttributeValue::S(status_index_key(E::KIND, *status))),
+            EntityQuery::All => return self.scan_kind(page_token, limit).await,
+        };
+
+        if let Some(token) = page_token {
+            request = request.set_exclusive_start_key(Some(decode_page_token(token)?));
+        }
+
+        let response = request.send().await.map_err(backend_error)?;
+        decode_page(response.items.as_deref(), response.last_evaluated_key.as_ref())
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        describe_active_table(&self.client, &self.table_name).await
+    }
+}
+
+/// Outbox items in the entity table, written by
+/// `DynamoRepository::put_with_events`
+#[derive(Debug, Clone)]
+pub struct DynamoOutbox {
+    client: DynamoClient,
+    table_name: String,
+}
+
+impl DynamoOutbox {
+    pub fn new(client: DynamoClient, table_name: &str) -> Self {
+        Self {
+            client,
+            table_name: table_name.to_string(),
+        }
+    }
+
+    // Updates an event that may have been pruned meanwhile; those are
+    // left alone rather than recreated without their payload
+    async fn update_existing(&self, idempotency_key: &str, update: ItemUpdate) -> Result<(), StorageError> {
+        let result = self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .set_key(Some(event_key(idempotency_key)))
+            .update_expression(update.expression)
+            .condition_expression("attribute_exists(pk)")
+            .set_expression_attribute_names(Some(update.names))
+            .set_expression_attribute_values(Some(update.values))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(e.as_service_error(), Some(UpdateItemError::ConditionalCheckFailedException(_))) => Ok(()),
+            Err(e) => Err(backend_error(e)),
+        }
+    }
+}
+
+#[async_trait]
+impl OutboxStore for DynamoOutbox {
+    async fn append(&self, events: &[OutboxEvent]) -> Result<(), StorageError> {
+        for event in events {
+            let update = event_update(event);
+            self.client
+                .update_item()
+                .table_name(&self.table_name)
+                .set_key(Some(update.key))
+                .update_expression(update.expression)
+                .set_expression_attribute_names(Some(update.names))
+                .set_expression_attribute_values(Some(update.values))
+                .send()
+                .await
+                .map_err(backend_error)?;
+        }
+        Ok(())
+    }
+
+    async fn fetch_pending(&self, limit: usize) -> Result<Vec<OutboxEvent>, StorageError> {
+        let response = self.client
+            .query()
+            .table_name(&self.table_name)
+            .index_name(STATUS_INDEX)
+            .key_condition_expression("gsi2pk = :pk")
+            .expression_attribute_values(":pk", AttributeValue::S(outbox_index_key(false)))
+            .limit(limit.clamp(1, i32::MAX as usize) as i32)
+            .send()
+            .await
+            .map_err(backend_error)?;
+
+        response.items.as_deref().unwrap_or_default().iter().map(decode_event).collect()
+    }
+
+    async fn mark_published(&self, idempotency_key: &str) -> Result<(), StorageError> {
+        let mut update = ItemUpdate {
+            expression: "SET #published_at = :now, #gsi2pk = :gsi2pk, #gsi2sk = :now REMOVE #last_error".to_string(),
+            ..Default::default()
+        };
+        for attribute in ["published_at", "gsi2pk", "gsi2sk", "last_error"] {
+            update.name(&format!("#{}", attribute), attribute);
+        }
+        update.value(":now", AttributeValue::S(sort_timestamp(Utc::now())));
+        update.value(":gsi2pk", AttributeValue::S(outbox_index_key(true)));
+        self.update_existing(idempotency_key, update).await
+    }
+
+    async fn record_failure(&self, idempotency_key: &str, error: &str) -> Result<(), StorageError> {
+        let mut update = ItemUpdate {
+            expression: "SET #attempts = #attempts + :one, #last_error = :error".to_string(),
+            ..Default::default()
+        };
+        update.name("#attempts", "attempts");
+        update.name("#last_error", "last_error");
+        update.value(":one", AttributeValue::N("1".to_string()));
+        update.value(":error", AttributeValue::S(error.to_string()));
+        self.update_existing(idempotency_key, update).await
+    }
+
+    async fn prune_published(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        let mut pruned = 0;
+        let mut start_key = None;
+        loop {
+            let response = self.client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(STATUS_INDEX)
+                .key_condition_expression("gsi2pk = :pk AND gsi2sk < :cutoff")
+                .expression_attribute_values(":pk", AttributeValue::S(outbox_index_key(true)))
+                .expression_attribute_values(":cutoff", AttributeValue::S(sort_timestamp(cutoff)))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(backend_error)?;
+
+            for item in response.items.as_deref().unwrap_or_default() {
+                let key: HashMap<String, AttributeValue> = ["pk", "sk"]
+                    .into_iter()
+                    .filter_map(|name| Some((name.to_string(), item.get(name)?.clone())))
+                    .collect();
+                self.client
+                    .delete_item()
+                    .table_name(&self.table_name)
+                    .set_key(Some(key))
+                    .send()
+                    .await
+                    .map_err(backend_error)?;
+                pruned += 1;
+            }
+
+            start_key = response.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(pruned);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_entity::{widget, Widget};
+    use prost::Message;
+
+    #[test]
+    fn test_page_token_round_trip() {
+        let key: HashMap<String, AttributeValue> = [
+            ("pk", "WIDGET#w-1"),
+            ("sk", "ENTITY"),
+            ("gsi1pk", "SOURCE#doc-1"),
+            ("gsi1sk", "WIDGET#w-1"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), AttributeValue::S(v.to_string())))
+        .collect();
+
+        let token = encode_page_token(&key).unwrap();
+        assert_eq!(decode_page_token(&token).unwrap(), key);
+
+        let forged = hex::encode(br#"{"payload":"x"}"#);
+        assert!(matches!(decode_page_token(&forged), Err(StorageError::InvalidPageToken(_))));
+    }
+
+    #[test]
+    fn test_decode_item() {
+        let entity = widget("w-1", "doc-1", 2);
+        let item: HashMap<String, AttributeValue> = [
+            ("payload".to_string(), AttributeValue::B(Blob::new(entity.encode_to_vec()))),
+            ("version".to_string(), AttributeValue::N("7".to_string())),
+            ("updated_at".to_string(), AttributeValue::S("2024-01-15T10:30:00+00:00".to_string())),
+        ]
+        .into_iter()
+        .collect();
+
+        let stored: Versioned<Widget> = decode_item(&item).unwrap();
+        assert_eq!(stored.entity, entity);
+        assert_eq!(stored.version, 7);
+    }
+
+    #[test]
+    fn test_event_update_never_cancels_its_transaction() {
+        let event = OutboxEvent::new("spec-drift.DOC-1", "key-1", b"{}".to_vec());
+        let update = event_update(&event);
+        assert!(update.condition.is_none());
+        assert_eq!(update.key["pk"], AttributeValue::S("OUTBOX#key-1".to_string()));
+        assert_eq!(update.expression.matches("if_not_exists").count(), update.names.len());
+        assert_eq!(update.values[":gsi2pk"], AttributeValue::S("OUTBOX#PENDING".to_string()));
+    }
+
+    #[test]
+    fn test_decode_event() {
+        let created_at = DateTime::parse_from_rfc3339("2024-01-15T10:30:00.123456Z").unwrap().with_timezone(&Utc);
+        let item: HashMap<String, AttributeValue> = [
+            ("idempotency_key", AttributeValue::S("key-1".to_string())),
+            ("subject", AttributeValue::S("spec-drift.DOC-1".to_string())),
+            ("payload", AttributeValue::B(Blob::new(b"{}".to_vec()))),
+            ("created_at", AttributeValue::S(sort_timestamp(created_at))),
+            ("attempts", AttributeValue::N("2".to_string())),
+            ("last_error", AttributeValue::S("timeout".to_string())),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+
+        let event = decode_event(&item).unwrap();
+        assert_eq!(event.idempotency_key, "key-1");
+        assert_eq!(event.payload, b"{}");
+        assert_eq!(event.created_at, created_at);
+        assert_eq!((event.attempts, event.last_error.as_deref()), (2, Some("timeout")));
+        assert!(!event.is_published());
+    }
+}
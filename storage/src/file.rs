@@ -0,0 +1,403 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    decode_id_token, encode_id_token, Entity, EntityQuery, ExpectedVersion, OutboxEvent, OutboxStore, QueryPage,
+    Repository, StorageError, Versioned, OUTBOX_KIND,
+};
+
+/// Repository keeping one JSON file per entity under `<root>/<KIND>/`, for
+/// the CLI and single-machine runs. File names are the hex-encoded entity
+/// ID, so directory order matches entity ID order.
+#[derive(Debug)]
+pub struct FileRepository<E> {
+    dir: PathBuf,
+    // Serializes read-check-write cycles within the process
+    write_lock: Mutex<()>,
+    outbox: FileOutbox,
+    _entity: PhantomData<fn() -> E>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredRecord {
+    version: u64,
+    updated_at: DateTime<Utc>,
+    /// Hex of the protobuf encoding
+    entity: String,
+}
+
+impl<E: Entity> FileRepository<E> {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            dir: root.as_ref().join(E::KIND),
+            write_lock: Mutex::new(()),
+            outbox: FileOutbox::new(root),
+            _entity: PhantomData,
+        }
+    }
+
+    fn path_for(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", encode_id_token(id)))
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Versioned<E>>, StorageError> {
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(path, e)),
+        };
+        let record: StoredRecord =
+            serde_json::from_slice(&contents).map_err(|e| StorageError::Decode(e.to_string()))?;
+        let bytes = hex::decode(&record.entity).map_err(|e| StorageError::Decode(e.to_string()))?;
+
+        Ok(Some(Versioned {
+            entity: E::decode(bytes.as_slice())?,
+            version: record.version,
+            updated_at: record.updated_at,
+        }))
+    }
+
+    /// Hex-encoded IDs of every stored entity, in entity ID order
+    fn stored_tokens(&self) -> Result<Vec<String>, StorageError> {
+        stored_tokens(&self.dir)
+    }
+
+    // Checks `expected` against the stored entity and returns the version
+    // to write; the caller holds write_lock until the write
+    fn next_version(&self, entity: &E, expected: ExpectedVersion) -> Result<u64, StorageError> {
+        let id = entity.entity_id();
+        let current = self.read(&self.path_for(&id))?.map(|v| v.version);
+
+        let allowed = match expected {
+            ExpectedVersion::Any => true,
+            ExpectedVersion::Absent => current.is_none(),
+            ExpectedVersion::Exactly(version) => current == Some(version),
+        };
+        if !allowed {
+            return Err(StorageError::VersionConflict {
+                kind: E::KIND,
+                id,
+                expected,
+            });
+        }
+        Ok(current.unwrap_or(0) + 1)
+    }
+
+    fn write_entity(&self, entity: &E, version: u64) -> Result<u64, StorageError> {
+        write_json(
+            &self.path_for(&entity.entity_id()),
+            &StoredRecord {
+                version,
+                updated_at: Utc::now(),
+                entity: hex::encode(entity.encode_to_vec()),
+            },
+        )?;
+        Ok(version)
+    }
+}
+
+// Writes to a sibling temp file and renames it so readers never see a
+// partially written file
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), StorageError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+    }
+    let contents = serde_json::to_vec_pretty(value).map_err(|e| StorageError::Backend(e.to_string()))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, contents).map_err(|e| io_error(&tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| io_error(path, e))
+}
+
+/// Hex-encoded names of the JSON files in `dir`, sorted
+fn stored_tokens(dir: &Path) -> Result<Vec<String>, StorageError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error(dir, e)),
+    };
+
+    let mut tokens = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| io_error(dir, e))?;
+        let name = entry.file_name();
+        if let Some(token) = name.to_str().and_then(|n| n.strip_suffix(".json")) {
+            tokens.push(token.to_string());
+        }
+    }
+    tokens.sort();
+    Ok(tokens)
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> StorageError {
+    StorageError::Backend(format!("{}: {}", path.display(), error))
+}
+
+fn query_matches<E: Entity>(entity: &E, query: &EntityQuery) -> bool {
+    match query {
+        EntityQuery::BySource(source_id) => entity.source_id().as_deref() == Some(source_id.as_str()),
+        EntityQuery::ByStatus(status) => entity.status() == *status,
+        EntityQuery::All => true,
+    }
+}
+
+#[async_trait]
+impl<E: Entity> Repository<E> for FileRepository<E> {
+    async fn put(&self, entity: &E, expected: ExpectedVersion) -> Result<u64, StorageError> {
+        let _guard = self.write_lock.lock().await;
+        let version = self.next_version(entity, expected)?;
+        self.write_entity(entity, version)
+    }
+
+    // Files can't be written atomically together. Events go first, so a
+    // crash in between leaves an event for a write that didn't happen,
+    // which consumers already tolerate from redeliveries, rather than
+    // losing the event of one that did.
+    async fn put_with_events(
+        &self,
+        entity: &E,
+        expected: ExpectedVersion,
+        events: &[OutboxEvent],
+    ) -> Result<u64, StorageError> {
+        let _guard = self.write_lock.lock().await;
+        let version = self.next_version(entity, expected)?;
+        self.outbox.append(events).await?;
+        self.write_entity(entity, version)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Versioned<E>>, StorageError> {
+        self.read(&self.path_for(id))
+    }
+
+    async fn query(
+        &self,
+        query: &EntityQuery,
+        page_token: Option<&str>,
+        limit: u32,
+    ) -> Result<QueryPage<E>, StorageError> {
+        // Validate the token even though the comparison below is on its hex form
+        let after = match page_token {
+            Some(token) => Some(encode_id_token(&decode_id_token(token)?)),
+            None => None,
+        };
+
+        let mut items: Vec<Versioned<E>> = Vec::new();
+        let mut next_page_token = None;
+        for token in self.stored_tokens()? {
+            if after.as_ref().is_some_and(|after| token <= *after) {
+                continue;
+            }
+            let Some(stored) = self.read(&self.dir.join(format!("{}.json", token)))? else {
+                continue;
+            };
+            if !query_matches(&stored.entity, query) {
+                continue;
+            }
+            if items.len() == limit as usize {
+                next_page_token = items.last().map(|last| encode_id_token(&last.entity.entity_id()));
+                break;
+            }
+            items.push(stored);
+        }
+
+        Ok(QueryPage { items, next_page_token })
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        match fs::metadata(&self.dir) {
+            Ok(meta) if meta.is_dir() => Ok(()),
+            Ok(_) => Err(StorageError::Backend(format!("{} is not a directory", self.dir.display()))),
+            // Kinds get their directory on first write
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(io_error(&self.dir, e)),
+        }
+    }
+}
+
+/// Outbox keeping one JSON file per event under `<root>/OUTBOX/`, written
+/// by `FileRepository::put_with_events`
+#[derive(Debug)]
+pub struct FileOutbox {
+    dir: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl FileOutbox {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            dir: root.as_ref().join(OUTBOX_KIND),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    fn path_for(&self, idempotency_key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", hex::encode(idempotency_key)))
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<OutboxEvent>, StorageError> {
+        match fs::read(path) {
+            Ok(contents) => serde_json::from_slice(&contents).map(Some).map_err(|e| StorageError::Decode(e.to_string())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(path, e)),
+        }
+    }
+
+    fn events(&self) -> Result<Vec<(PathBuf, OutboxEvent)>, StorageError> {
+        let mut events = Vec::new();
+        for token in stored_tokens(&self.dir)? {
+            let path = self.dir.join(format!("{}.json", token));
+            if let Some(event) = self.read(&path)? {
+                events.push((path, event));
+            }
+        }
+        Ok(events)
+    }
+
+    async fn update(&self, idempotency_key: &str, apply: impl FnOnce(&mut OutboxEvent)) -> Result<(), StorageError> {
+        let _guard = self.write_lock.lock().await;
+        let path = self.path_for(idempotency_key);
+        if let Some(mut event) = self.read(&path)? {
+            apply(&mut event);
+            write_json(&path, &event)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OutboxStore for FileOutbox {
+    async fn append(&self, events: &[OutboxEvent]) -> Result<(), StorageError> {
+        let _guard = self.write_lock.lock().await;
+        for event in events {
+            let path = self.path_for(&event.idempotency_key);
+            if self.read(&path)?.is_none() {
+                write_json(&path, event)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn fetch_pending(&self, limit: usize) -> Result<Vec<OutboxEvent>, StorageError> {
+        let mut pending: Vec<OutboxEvent> = self.events()?
+            .into_iter()
+            .map(|(_, event)| event)
+            .filter(|event| !event.is_published())
+            .collect();
+        pending.sort_by_key(|event| event.created_at);
+        pending.truncate(limit);
+        Ok(pending)
+    }
+
+    async fn mark_published(&self, idempotency_key: &str) -> Result<(), StorageError> {
+        self.update(idempotency_key, |event| {
+            event.published_at = Some(Utc::now());
+            event.last_error = None;
+        }).await
+    }
+
+    async fn record_failure(&self, idempotency_key: &str, error: &str) -> Result<(), StorageError> {
+        self.update(idempotency_key, |event| {
+            event.attempts += 1;
+            event.last_error = Some(error.to_string());
+        }).await
+    }
+
+    async fn prune_published(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        let _guard = self.write_lock.lock().await;
+        let mut pruned = 0;
+        for (path, event) in self.events()? {
+            if event.published_at.is_some_and(|published_at| published_at < cutoff) {
+                fs::remove_file(&path).map_err(|e| io_error(&path, e))?;
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_entity::{widget, Widget};
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("storage-file-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    #[tokio::test]
+    async fn test_put_get_round_trip_and_versions() {
+        let root = temp_root("round-trip");
+        let repo = FileRepository::<Widget>::new(&root);
+        let w = widget("THEOREM#w/1", "doc-1", 1);
+
+        assert_eq!(repo.put(&w, ExpectedVersion::Absent).await.unwrap(), 1);
+        assert!(matches!(
+            repo.put(&w, ExpectedVersion::Absent).await,
+            Err(StorageError::VersionConflict { .. })
+        ));
+        assert_eq!(repo.put(&w, ExpectedVersion::Exactly(1)).await.unwrap(), 2);
+
+        // A fresh handle over the same directory sees the persisted state
+        let reopened = FileRepository::<Widget>::new(&root);
+        let stored = reopened.get("THEOREM#w/1").await.unwrap().unwrap();
+        assert_eq!(stored.entity, w);
+        assert_eq!(stored.version, 2);
+        assert!(reopened.get("missing").await.unwrap().is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_events_persist_beside_entities() {
+        let root = temp_root("outbox");
+        let repo = FileRepository::<Widget>::new(&root);
+        let event = OutboxEvent::new("widgets", "key-1", b"{}".to_vec());
+
+        repo.put_with_events(&widget("w-1", "doc-1", 1), ExpectedVersion::Absent, std::slice::from_ref(&event)).await.unwrap();
+        assert!(repo
+            .put_with_events(&widget("w-1", "doc-1", 2), ExpectedVersion::Absent, &[OutboxEvent::new("widgets", "key-2", b"{}".to_vec())])
+            .await
+            .is_err());
+
+        let outbox = FileOutbox::new(&root);
+        assert_eq!(outbox.fetch_pending(10).await.unwrap(), vec![event]);
+        outbox.mark_published("key-1").await.unwrap();
+        assert!(outbox.fetch_pending(10).await.unwrap().is_empty());
+        assert_eq!(outbox.prune_published(Utc::now()).await.unwrap(), 1);
+        assert_eq!(repo.get("w-1").await.unwrap().unwrap().version, 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_pages_in_id_order() {
+        let root = temp_root("query");
+        let repo = FileRepository::<Widget>::new(&root);
+        for (id, doc, status) in [("w-4", "doc-1", 1), ("w-2", "doc-2", 1), ("w-3", "doc-1", 2), ("w-1", "doc-1", 1)] {
+            repo.put(&widget(id, doc, status), ExpectedVersion::Any).await.unwrap();
+        }
+
+        let by_source = EntityQuery::BySource("doc-1".to_string());
+        let first = repo.query(&by_source, None, 2).await.unwrap();
+        assert_eq!(first.items.iter().map(|v| v.entity.id.as_str()).collect::<Vec<_>>(), vec!["w-1", "w-3"]);
+
+        let second = repo.query(&by_source, first.next_page_token.as_deref(), 2).await.unwrap();
+        assert_eq!(second.items.iter().map(|v| v.entity.id.as_str()).collect::<Vec<_>>(), vec!["w-4"]);
+        assert!(second.next_page_token.is_none());
+
+        assert_eq!(repo.query(&EntityQuery::ByStatus(1), None, 10).await.unwrap().items.len(), 3);
+        assert!(matches!(
+            repo.query(&by_source, Some("not-hex"), 2).await,
+            Err(StorageError::InvalidPageToken(_))
+        ));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
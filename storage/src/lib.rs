@@ -0,0 +1,237 @@
+pub mod dynamo;
+pub mod file;
+pub mod memory;
+pub mod outbox;
+pub mod postgres;
+pub mod store;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+pub use dynamo::{DynamoOutbox, DynamoRepository};
+pub use file::{FileOutbox, FileRepository};
+pub use memory::InMemoryRepository;
+pub use outbox::{idempotency_key, InMemoryOutbox, OutboxEvent, OutboxRelay, OutboxSink, OutboxStore};
+pub use postgres::{PostgresOutbox, PostgresRepository};
+pub use store::{repository_or_memory, EntityStore, StorageBackend, StorageSettings};
+
+/// A protobuf message that can be persisted through a `Repository`.
+/// Implemented by each service for its own generated types.
+pub trait Entity: prost::Message + Default + Clone + Send + Sync + 'static {
+    /// Type tag used in keys, e.g. "THEOREM"; must not contain '#'
+    const KIND: &'static str;
+
+    fn entity_id(&self) -> String;
+
+    /// What the entity was derived from (the source document of an
+    /// invariant, the invariant of a theorem, ...), indexed for `BySource`
+    fn source_id(&self) -> Option<String>;
+
+    /// Proto enum value of the entity's status, indexed for `ByStatus`
+    fn status(&self) -> i32;
+
+    fn set_status(&mut self, status: i32);
+}
+
+/// Precondition on the stored version for a write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedVersion {
+    /// Unconditional upsert
+    Any,
+    /// The entity must not exist yet
+    Absent,
+    /// The stored entity must be at exactly this version
+    Exactly(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Versioned<E> {
+    pub entity: E,
+    /// Starts at 1 and increments on every write
+    pub version: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntityQuery {
+    BySource(String),
+    ByStatus(i32),
+    /// Every entity of the kind; DynamoDB answers it with a table scan
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryPage<E> {
+    pub items: Vec<Versioned<E>>,
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("{kind} {id} not found")]
+    NotFound { kind: &'static str, id: String },
+
+    #[error("Version conflict on {kind} {id}: expected {expected:?}")]
+    VersionConflict {
+        kind: &'static str,
+        id: String,
+        expected: ExpectedVersion,
+    },
+
+    #[error("Invalid page token: {0}")]
+    InvalidPageToken(String),
+
+    #[error("Failed to decode stored entity: {0}")]
+    Decode(String),
+
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+}
+
+impl From<prost::DecodeError> for StorageError {
+    fn from(error: prost::DecodeError) -> Self {
+        StorageError::Decode(error.to_string())
+    }
+}
+
+impl From<StorageError> for spec_to_proof_error::Error {
+    fn from(error: StorageError) -> Self {
+        use spec_to_proof_error::Error;
+        match error {
+            StorageError::NotFound { .. } | StorageError::InvalidPageToken(_) => Error::invalid_input(error),
+            // Another writer got there first; re-reading and retrying resolves it
+            StorageError::VersionConflict { .. } | StorageError::Backend(_) => Error::transient(error),
+            StorageError::Decode(_) => Error::internal(error),
+        }
+    }
+}
+
+#[async_trait]
+pub trait Repository<E: Entity>: Send + Sync {
+    /// Writes the entity if `expected` holds and returns its new version
+    async fn put(&self, entity: &E, expected: ExpectedVersion) -> Result<u64, StorageError>;
+
+    /// Like `put`, and appends `events` to the backend's outbox in the same
+    /// transaction, so they are delivered if and only if the write happened.
+    /// Events whose idempotency key is already in the outbox are ignored.
+    async fn put_with_events(
+        &self,
+        entity: &E,
+        expected: ExpectedVersion,
+        events: &[OutboxEvent],
+    ) -> Result<u64, StorageError>;
+
+    async fn get(&self, id: &str) -> Result<Option<Versioned<E>>, StorageError>;
+
+    /// Returns up to `limit` matches; pass the previous page's
+    /// `next_page_token` to continue
+    async fn query(
+        &self,
+        query: &EntityQuery,
+        page_token: Option<&str>,
+        limit: u32,
+    ) -> Result<QueryPage<E>, StorageError>;
+
+    /// Sets the status of the entity stored at `expected_version`,
+    /// returning the new version
+    async fn update_status(&self, id: &str, status: i32, expected_version: u64) -> Result<u64, StorageError> {
+        let mut current = self.get(id).await?.ok_or_else(|| StorageError::NotFound {
+            kind: E::KIND,
+            id: id.to_string(),
+        })?;
+
+        if current.version != expected_version {
+            return Err(StorageError::VersionConflict {
+                kind: E::KIND,
+                id: id.to_string(),
+                expected: ExpectedVersion::Exactly(expected_version),
+            });
+        }
+
+        current.entity.set_status(status);
+        self.put(&current.entity, ExpectedVersion::Exactly(expected_version)).await
+    }
+
+    /// Checks that the backend is reachable and the table or schema usable,
+    /// for readiness probes
+    async fn ping(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+// Single-table key layout shared by the backends:
+//   pk     = KIND#id                  sk     = ENTITY
+//   gsi1pk = SOURCE#source_id         gsi1sk = KIND#id
+//   gsi2pk = KIND#STATUS#status       gsi2sk = updated_at
+// Outbox events live beside them, indexed by delivery state:
+//   pk     = OUTBOX#idempotency_key   sk     = EVENT
+//   gsi2pk = OUTBOX#PENDING           gsi2sk = created_at
+//   gsi2pk = OUTBOX#PUBLISHED         gsi2sk = published_at
+pub(crate) const ENTITY_SORT_KEY: &str = "ENTITY";
+pub(crate) const OUTBOX_KIND: &str = "OUTBOX";
+pub(crate) const EVENT_SORT_KEY: &str = "EVENT";
+
+pub(crate) fn partition_key(kind: &str, id: &str) -> String {
+    format!("{}#{}", kind, id)
+}
+
+pub(crate) fn source_index_key(source_id: &str) -> String {
+    format!("SOURCE#{}", source_id)
+}
+
+pub(crate) fn status_index_key(kind: &str, status: i32) -> String {
+    format!("{}#STATUS#{}", kind, status)
+}
+
+// Backends that page in entity ID order use the last returned ID as the token
+pub(crate) fn encode_id_token(id: &str) -> String {
+    hex::encode(id)
+}
+
+pub(crate) fn decode_id_token(token: &str) -> Result<String, StorageError> {
+    let bytes = hex::decode(token).map_err(|e| StorageError::InvalidPageToken(e.to_string()))?;
+    String::from_utf8(bytes).map_err(|e| StorageError::InvalidPageToken(e.to_string()))
+}
+
+#[cfg(test)]
+pub(crate) mod test_entity {
+    use super::Entity;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Widget {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(string, tag = "2")]
+        pub document_id: String,
+        #[prost(int32, tag = "3")]
+        pub status: i32,
+    }
+
+    impl Entity for Widget {
+        const KIND: &'static str = "WIDGET";
+
+        fn entity_id(&self) -> String {
+            self.id.clone()
+        }
+
+        fn source_id(&self) -> Option<String> {
+            (!self.document_id.is_empty()).then(|| self.document_id.clone())
+        }
+
+        fn status(&self) -> i32 {
+            self.status
+        }
+
+        fn set_status(&mut self, status: i32) {
+            self.status = status;
+        }
+    }
+
+    pub fn widget(id: &str, document_id: &str, status: i32) -> Widget {
+        Widget {
+            id: id.to_string(),
+            document_id: document_id.to_string(),
+            status,
+        }
+    }
+}
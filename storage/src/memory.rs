@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::Utc;
+use tokio::sync::RwLock;
+
+use crate::{
+    decode_id_token, encode_id_token, Entity, EntityQuery, ExpectedVersion, InMemoryOutbox, OutboxEvent,
+    OutboxStore, QueryPage, Repository, StorageError, Versioned,
+};
+
+/// Repository kept in process memory, for tests and local development.
+/// Query results are ordered by entity ID.
+#[derive(Debug, Default)]
+pub struct InMemoryRepository<E> {
+    entries: RwLock<BTreeMap<String, Versioned<E>>>,
+    outbox: Arc<InMemoryOutbox>,
+}
+
+impl<E: Entity> InMemoryRepository<E> {
+    pub fn new() -> Self {
+        Self {
+            entries: RwLock::new(BTreeMap::new()),
+            outbox: Arc::new(InMemoryOutbox::new()),
+        }
+    }
+
+    /// Appends events from `put_with_events` to `outbox` rather than one of
+    /// the repository's own, so repositories can share it with a relay
+    pub fn with_outbox(mut self, outbox: Arc<InMemoryOutbox>) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    pub fn outbox(&self) -> Arc<InMemoryOutbox> {
+        self.outbox.clone()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.entries.read().await.is_empty()
+    }
+}
+
+fn query_matches<E: Entity>(entity: &E, query: &EntityQuery) -> bool {
+    match query {
+        EntityQuery::BySource(source_id) => entity.source_id().as_deref() == Some(source_id.as_str()),
+        EntityQuery::ByStatus(status) => entity.status() == *status,
+        EntityQuery::All => true,
+    }
+}
+
+fn write<E: Entity>(
+    entries: &mut BTreeMap<String, Versioned<E>>,
+    entity: &E,
+    expected: ExpectedVersion,
+) -> Result<u64, StorageError> {
+    let id = entity.entity_id();
+    let current = entries.get(&id).map(|v| v.version);
+
+    let allowed = match expected {
+        ExpectedVersion::Any => true,
+        ExpectedVersion::Absent => current.is_none(),
+        ExpectedVersion::Exactly(version) => current == Some(version),
+    };
+    if !allowed {
+        return Err(StorageError::VersionConflict {
+            kind: E::KIND,
+            id,
+            expected,
+        });
+    }
+
+    let version = current.unwrap_or(0) + 1;
+    entries.insert(
+        id,
+        Versioned {
+            entity: entity.clone(),
+            version,
+            updated_at: Utc::now(),
+        },
+    );
+    Ok(version)
+}
+
+#[async_trait]
+impl<E: Entity> Repository<E> for InMemoryRepository<E> {
+    async fn put(&self, entity: &E, expected: ExpectedVersion) -> Result<u64, StorageError> {
+        write(&mut *self.entries.write().await, entity, expected)
+    }
+
+    async fn put_with_events(
+        &self,
+        entity: &E,
+        expected: ExpectedVersion,
+        events: &[OutboxEvent],
+    ) -> Result<u64, StorageError> {
+        // Holding the entries lock keeps readers from seeing the entity
+        // before its events
+        let mut entries = self.entries.write().await;
+        let version = write(&mut entries, entity, expected)?;
+        self.outbox.append(events).await?;
+        Ok(version)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Versioned<E>>, StorageError> {
+        Ok(self.entries.read().await.get(id).cloned())
+    }
+
+    async fn query(
+        &self,
+        query: &EntityQuery,
+        page_token: Option<&str>,
+        limit: u32,
+    ) -> Result<QueryPage<E>, StorageError> {
+        let start = match page_token {
+            Some(token) => Bound::Excluded(decode_id_token(token)?),
+            None => Bound::Unbounded,
+        };
+
+        let entries = self.entries.read().await;
+        let mut matching = entries
+            .range((start, Bound::Unbounded))
+            .filter(|(_, stored)| query_matches(&stored.entity, query));
+
+        let items: Vec<Versioned<E>> = matching
+            .by_ref()
+            .take(limit as usize)
+            .map(|(_, stored)| stored.clone())
+            .collect();
+
+        let next_page_token = match (items.last(), matching.next()) {
+            (Some(last), Some(_)) => Some(encode_id_token(&last.entity.entity_id())),
+            _ => None,
+        };
+
+        Ok(QueryPage { items, next_page_token })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_entity::{widget, Widget};
+
+    #[tokio::test]
+    async fn test_put_enforces_expected_version() {
+        let repo = InMemoryRepository::<Widget>::new();
+        let w = widget("w-1", "doc-1", 1);
+
+        assert_eq!(repo.put(&w, ExpectedVersion::Absent).await.unwrap(), 1);
+        assert!(matches!(
+            repo.put(&w, ExpectedVersion::Absent).await,
+            Err(StorageError::VersionConflict { .. })
+        ));
+        assert_eq!(repo.put(&w, ExpectedVersion::Exactly(1)).await.unwrap(), 2);
+        assert!(repo.put(&w, ExpectedVersion::Exactly(1)).await.is_err());
+        assert_eq!(repo.put(&w, ExpectedVersion::Any).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_events_are_written_only_with_the_entity() {
+        let outbox = Arc::new(InMemoryOutbox::new());
+        let repo = InMemoryRepository::<Widget>::new().with_outbox(outbox.clone());
+        let w = widget("w-1", "doc-1", 1);
+        let event = OutboxEvent::new("widgets", "key-1", b"{}".to_vec());
+
+        assert_eq!(repo.put_with_events(&w, ExpectedVersion::Absent, std::slice::from_ref(&event)).await.unwrap(), 1);
+        assert!(matches!(
+            repo.put_with_events(&w, ExpectedVersion::Absent, &[OutboxEvent::new("widgets", "key-2", b"{}".to_vec())]).await,
+            Err(StorageError::VersionConflict { .. })
+        ));
+        assert_eq!(outbox.fetch_pending(10).await.unwrap(), vec![event]);
+    }
+
+    #[tokio::test]
+    async fn test_update_status() {
+        let repo = InMemoryRepository::<Widget>::new();
+        repo.put(&widget("w-1", "doc-1", 1), ExpectedVersion::Absent).await.unwrap();
+
+        assert_eq!(repo.update_status("w-1", 4, 1).await.unwrap(), 2);
+        let stored = repo.get("w-1").await.unwrap().unwrap();
+        assert_eq!(stored.entity.status, 4);
+        assert_eq!(stored.version, 2);
+
+        assert!(matches!(repo.update_status("w-1", 5, 1).await, Err(StorageError::VersionConflict { .. })));
+        assert!(matches!(repo.update_status("w-9", 5, 1).await, Err(StorageError::NotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_query_pages_by_source_and_status() {
+        let repo = InMemoryRepository::<Widget>::new();
+        for (id, doc, status) in [("w-1", "doc-1", 1), ("w-2", "doc-2", 1), ("w-3", "doc-1", 2), ("w-4", "doc-1", 1)] {
+            repo.put(&widget(id, doc, status), ExpectedVersion::Any).await.unwrap();
+        }
+
+        let by_source = EntityQuery::BySource("doc-1".to_string());
+        let first = repo.query(&by_source, None, 2).await.unwrap();
+        assert_eq!(first.items.iter().map(|v| v.entity.id.as_str()).collect::<Vec<_>>(), vec!["w-1", "w-3"]);
+
+        let second = repo.query(&by_source, first.next_page_token.as_deref(), 2).await.unwrap();
+        assert_eq!(second.items.iter().map(|v| v.entity.id.as_str()).collect::<Vec<_>>(), vec!["w-4"]);
+        assert!(second.next_page_token.is_none());
+
+        let by_status = repo.query(&EntityQuery::ByStatus(1), None, 10).await.unwrap();
+        assert_eq!(by_status.items.len(), 3);
+
+        let all = repo.query(&EntityQuery::All, None, 10).await.unwrap();
+        assert_eq!(all.items.len(), 4);
+    }
+}
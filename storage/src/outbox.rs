@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+use crate::StorageError;
+
+// Transactional outbox. Producers write events with
+// `Repository::put_with_events`, in the same transaction as the entity they
+// announce, and an OutboxRelay delivers them afterwards. Events are keyed
+// by their idempotency key, which the sink passes on as the message ID so
+// the broker drops duplicates when the relay retries after a crash between
+// delivery and mark_published.
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxEvent {
+    pub idempotency_key: String,
+    pub subject: String,
+    pub payload: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+impl OutboxEvent {
+    pub fn new(subject: &str, idempotency_key: &str, payload: Vec<u8>) -> Self {
+        Self {
+            idempotency_key: idempotency_key.to_string(),
+            subject: subject.to_string(),
+            payload,
+            created_at: Utc::now(),
+            published_at: None,
+            attempts: 0,
+            last_error: None,
+        }
+    }
+
+    pub fn is_published(&self) -> bool {
+        self.published_at.is_some()
+    }
+}
+
+/// Idempotency key derived from the logical event, so re-enqueueing the
+/// same document version yields the same key
+pub fn idempotency_key(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update(b"\0");
+    }
+    hex::encode(hasher.finalize())
+}
+
+#[async_trait]
+pub trait OutboxStore: Send + Sync + std::fmt::Debug {
+    /// Appends events outside any entity write, for producers with nothing
+    /// of their own to store; events whose idempotency key is already
+    /// present are ignored
+    async fn append(&self, events: &[OutboxEvent]) -> Result<(), StorageError>;
+
+    /// Undelivered events, oldest first
+    async fn fetch_pending(&self, limit: usize) -> Result<Vec<OutboxEvent>, StorageError>;
+
+    async fn mark_published(&self, idempotency_key: &str) -> Result<(), StorageError>;
+
+    async fn record_failure(&self, idempotency_key: &str, error: &str) -> Result<(), StorageError>;
+
+    /// Deletes events delivered before `cutoff`, returning how many
+    async fn prune_published(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError>;
+}
+
+/// Outbox kept in process memory, shared with the `InMemoryRepository`
+/// instances that write to it
+#[derive(Debug, Default)]
+pub struct InMemoryOutbox {
+    events: RwLock<BTreeMap<String, OutboxEvent>>,
+}
+
+impl InMemoryOutbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.events.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.events.read().await.is_empty()
+    }
+}
+
+#[async_trait]
+impl OutboxStore for InMemoryOutbox {
+    async fn append(&self, events: &[OutboxEvent]) -> Result<(), StorageError> {
+        let mut stored = self.events.write().await;
+        for event in events {
+            stored.entry(event.idempotency_key.clone()).or_insert_with(|| event.clone());
+        }
+        Ok(())
+    }
+
+    async fn fetch_pending(&self, limit: usize) -> Result<Vec<OutboxEvent>, StorageError> {
+        let stored = self.events.read().await;
+        let mut pending: Vec<OutboxEvent> = stored.values().filter(|event| !event.is_published()).cloned().collect();
+        pending.sort_by_key(|event| event.created_at);
+        pending.truncate(limit);
+        Ok(pending)
+    }
+
+    async fn mark_published(&self, idempotency_key: &str) -> Result<(), StorageError> {
+        if let Some(event) = self.events.write().await.get_mut(idempotency_key) {
+            event.published_at = Some(Utc::now());
+            event.last_error = None;
+        }
+        Ok(())
+    }
+
+    async fn record_failure(&self, idempotency_key: &str, error: &str) -> Result<(), StorageError> {
+        if let Some(event) = self.events.write().await.get_mut(idempotency_key) {
+            event.attempts += 1;
+            event.last_error = Some(error.to_string());
+        }
+        Ok(())
+    }
+
+    async fn prune_published(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        let mut stored = self.events.write().await;
+        let before = stored.len();
+        stored.retain(|_, event| event.published_at.is_none_or(|published_at| published_at >= cutoff));
+        Ok(before - stored.len())
+    }
+}
+
+/// Where the relay delivers events: a message bus, a NATS subject, ...
+#[async_trait]
+pub trait OutboxSink: Send + Sync {
+    /// Must pass the event's idempotency key on as the message ID
+    async fn deliver(&self, event: &OutboxEvent) -> Result<(), spec_to_proof_error::Error>;
+}
+
+/// Delivers pending outbox events and prunes delivered ones once they are
+/// older than the retention period
+pub struct OutboxRelay {
+    store: Arc<dyn OutboxStore>,
+    sink: Arc<dyn OutboxSink>,
+    batch_size: usize,
+    poll_interval: Duration,
+    retention: Duration,
+}
+
+impl OutboxRelay {
+    pub fn new(store: Arc<dyn OutboxStore>, sink: Arc<dyn OutboxSink>) -> Self {
+        Self {
+            store,
+            sink,
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            retention: Duration::from_secs(24 * 3600),
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// How long delivered events are kept; while kept, re-appending the
+    /// same idempotency key is a no-op
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.poll_interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.relay_pending().await {
+                tracing::error!("Outbox relay pass failed: {}", e);
+            }
+            if let Err(e) = self.prune().await {
+                tracing::error!("Outbox prune failed: {}", e);
+            }
+        }
+    }
+
+    /// Delivers one batch of pending events, returning how many were
+    /// delivered
+    pub async fn relay_pending(&self) -> Result<usize, StorageError> {
+        let pending = self.store.fetch_pending(self.batch_size).await?;
+        let mut published = 0;
+
+        for event in pending {
+            match self.sink.deliver(&event).await {
+                Ok(()) => {
+                    self.store.mark_published(&event.idempotency_key).await?;
+                    published += 1;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to relay outbox event {} to {}: {}",
+                        event.idempotency_key,
+                        event.subject,
+                        e
+                    );
+                    self.store.record_failure(&event.idempotency_key, &e.to_string()).await?;
+                }
+            }
+        }
+
+        Ok(published)
+    }
+
+    /// Deletes events delivered longer ago than the retention period,
+    /// returning how many
+    pub async fn prune(&self) -> Result<usize, StorageError> {
+        let retention = chrono::Duration::from_std(self.retention).unwrap_or(chrono::Duration::MAX);
+        let cutoff = Utc::now().checked_sub_signed(retention).unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let pruned = self.store.prune_published(cutoff).await?;
+        if pruned > 0 {
+            tracing::info!("Pruned {} delivered outbox events", pruned);
+        }
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        delivered: Mutex<Vec<String>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl OutboxSink for RecordingSink {
+        async fn deliver(&self, event: &OutboxEvent) -> Result<(), spec_to_proof_error::Error> {
+            if self.fail {
+                return Err(spec_to_proof_error::Error::transient("connection refused"));
+            }
+            self.delivered.lock().unwrap().push(event.idempotency_key.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_idempotency_key_is_stable() {
+        let key1 = idempotency_key(&["spec-documents.jira", "PROJ-1-3", "abc"]);
+        let key2 = idempotency_key(&["spec-documents.jira", "PROJ-1-3", "abc"]);
+        let key3 = idempotency_key(&["spec-documents.jira", "PROJ-1-4", "abc"]);
+
+        assert_eq!(key1, key2);
+        assert_ne!(key1, key3);
+    }
+
+    #[tokio::test]
+    async fn test_append_deduplicates_by_idempotency_key() {
+        let outbox = InMemoryOutbox::new();
+        let event = OutboxEvent::new("spec-documents.jira", "key-1", b"{}".to_vec());
+
+        outbox.append(std::slice::from_ref(&event)).await.unwrap();
+        outbox.append(&[event]).await.unwrap();
+
+        assert_eq!(outbox.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_marks_and_prunes_delivered_events() {
+        let outbox = Arc::new(InMemoryOutbox::new());
+        let sink = Arc::new(RecordingSink::default());
+        outbox.append(&[
+            OutboxEvent::new("spec-documents.jira", "key-1", b"{}".to_vec()),
+            OutboxEvent::new("spec-documents.jira", "key-2", b"{}".to_vec()),
+        ]).await.unwrap();
+
+        let relay = OutboxRelay::new(outbox.clone(), sink.clone());
+        assert_eq!(relay.relay_pending().await.unwrap(), 2);
+        assert_eq!(relay.relay_pending().await.unwrap(), 0);
+        assert_eq!(*sink.delivered.lock().unwrap(), vec!["key-1", "key-2"]);
+
+        // Kept for the retention period, then deleted
+        assert_eq!(relay.prune().await.unwrap(), 0);
+        assert_eq!(outbox.len().await, 2);
+        let relay = relay.with_retention(Duration::ZERO);
+        assert_eq!(relay.prune().await.unwrap(), 2);
+        assert!(outbox.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_stays_pending() {
+        let outbox = Arc::new(InMemoryOutbox::new());
+        outbox.append(&[OutboxEvent::new("spec-documents.jira", "key-1", b"{}".to_vec())]).await.unwrap();
+
+        let relay = OutboxRelay::new(outbox.clone(), Arc::new(RecordingSink { fail: true, ..Default::default() }))
+            .with_retention(Duration::ZERO);
+        assert_eq!(relay.relay_pending().await.unwrap(), 0);
+        assert_eq!(relay.prune().await.unwrap(), 0);
+
+        let pending = outbox.fetch_pending(10).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].attempts, 1);
+        assert!(pending[0].last_error.as_deref().unwrap().contains("connection refused"));
+    }
+}
@@ -0,0 +1,317 @@
+use std::marker::PhantomData;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnection, PgPool, PgPoolOptions, PgRow};
+use sqlx::Row;
+
+use crate::{
+    decode_id_token, encode_id_token, Entity, EntityQuery, ExpectedVersion, OutboxEvent, OutboxStore, QueryPage,
+    Repository, StorageError, Versioned,
+};
+
+/// Schema migrations for the `entities` and `outbox` tables, embedded at
+/// build time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+const UPSERT: &str = "INSERT INTO entities (kind, id, source_id, status, payload, version, updated_at) \
+     VALUES ($1, $2, $3, $4, $5, 1, now()) \
+     ON CONFLICT (kind, id) DO UPDATE SET \
+     source_id = EXCLUDED.source_id, status = EXCLUDED.status, payload = EXCLUDED.payload, \
+     version = entities.version + 1, updated_at = now() \
+     RETURNING version";
+
+const INSERT_IF_ABSENT: &str = "INSERT INTO entities (kind, id, source_id, status, payload, version, updated_at) \
+     VALUES ($1, $2, $3, $4, $5, 1, now()) \
+     ON CONFLICT (kind, id) DO NOTHING \
+     RETURNING version";
+
+const UPDATE_AT_VERSION: &str = "UPDATE entities SET \
+     source_id = $3, status = $4, payload = $5, version = version + 1, updated_at = now() \
+     WHERE kind = $1 AND id = $2 AND version = $6 \
+     RETURNING version";
+
+const INSERT_EVENT: &str = "INSERT INTO outbox (idempotency_key, subject, payload, created_at) \
+     VALUES ($1, $2, $3, $4) \
+     ON CONFLICT (idempotency_key) DO NOTHING";
+
+const FETCH_PENDING: &str = "SELECT idempotency_key, subject, payload, created_at, published_at, attempts, last_error \
+     FROM outbox WHERE published_at IS NULL ORDER BY created_at LIMIT $1";
+
+const MARK_PUBLISHED: &str = "UPDATE outbox SET published_at = now(), last_error = NULL WHERE idempotency_key = $1";
+
+const RECORD_FAILURE: &str = "UPDATE outbox SET attempts = attempts + 1, last_error = $2 WHERE idempotency_key = $1";
+
+const PRUNE_PUBLISHED: &str = "DELETE FROM outbox WHERE published_at < $1";
+
+const QUERY_BY_SOURCE: &str = "SELECT id, payload, version, updated_at FROM entities \
+     WHERE kind = $1 AND source_id = $2 AND ($3::TEXT IS NULL OR id > $3) \
+     ORDER BY id LIMIT $4";
+
+const QUERY_BY_STATUS: &str = "SELECT id, payload, version, updated_at FROM entities \
+     WHERE kind = $1 AND status = $2 AND ($3::TEXT IS NULL OR id > $3) \
+     ORDER BY id LIMIT $4";
+
+const QUERY_ALL: &str = "SELECT id, payload, version, updated_at FROM entities \
+     WHERE kind = $1 AND ($2::TEXT IS NULL OR id > $2) \
+     ORDER BY id LIMIT $3";
+
+pub async fn connect(database_url: &str, max_connections: u32) -> Result<PgPool, StorageError> {
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .connect(database_url)
+        .await
+        .map_err(backend_error)
+}
+
+pub async fn ping(pool: &PgPool) -> Result<(), StorageError> {
+    sqlx::query("SELECT 1").execute(pool).await.map_err(backend_error)?;
+    Ok(())
+}
+
+pub async fn run_migrations(pool: &PgPool) -> Result<(), StorageError> {
+    MIGRATOR.run(pool).await.map_err(backend_error)?;
+    tracing::info!("Entity schema migrations applied");
+    Ok(())
+}
+
+/// PostgreSQL repository sharing one `entities` table across entity kinds,
+/// with the same key layout and versioning semantics as the DynamoDB backend.
+/// Query results are ordered by entity ID.
+pub struct PostgresRepository<E> {
+    pool: PgPool,
+    _entity: PhantomData<fn() -> E>,
+}
+
+impl<E: Entity> PostgresRepository<E> {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            _entity: PhantomData,
+        }
+    }
+}
+
+fn backend_error(error: impl std::fmt::Display) -> StorageError {
+    StorageError::Backend(error.to_string())
+}
+
+fn decode_row<E: Entity>(row: &PgRow) -> Result<Versioned<E>, StorageError> {
+    let payload: Vec<u8> = row.try_get("payload").map_err(|e| StorageError::Decode(e.to_string()))?;
+    let version: i64 = row.try_get("version").map_err(|e| StorageError::Decode(e.to_string()))?;
+    let updated_at: DateTime<Utc> = row.try_get("updated_at").map_err(|e| StorageError::Decode(e.to_string()))?;
+
+    Ok(Versioned {
+        entity: E::decode(payload.as_slice())?,
+        version: version as u64,
+        updated_at,
+    })
+}
+
+async fn write_entity<E: Entity>(
+    connection: &mut PgConnection,
+    entity: &E,
+    expected: ExpectedVersion,
+) -> Result<u64, StorageError> {
+    let id = entity.entity_id();
+    let statement = match expected {
+        ExpectedVersion::Any => UPSERT,
+        ExpectedVersion::Absent => INSERT_IF_ABSENT,
+        ExpectedVersion::Exactly(_) => UPDATE_AT_VERSION,
+    };
+
+    let mut query = sqlx::query_scalar::<_, i64>(statement)
+        .bind(E::KIND)
+        .bind(&id)
+        .bind(entity.source_id())
+        .bind(entity.status())
+        .bind(entity.encode_to_vec());
+    if let ExpectedVersion::Exactly(version) = expected {
+        query = query.bind(version as i64);
+    }
+
+    // No row comes back when the conflict clause or version check
+    // rejected the write
+    match query.fetch_optional(connection).await.map_err(backend_error)? {
+        Some(version) => Ok(version as u64),
+        None => Err(StorageError::VersionConflict {
+            kind: E::KIND,
+            id,
+            expected,
+        }),
+    }
+}
+
+async fn insert_events(connection: &mut PgConnection, events: &[OutboxEvent]) -> Result<(), StorageError> {
+    for event in events {
+        sqlx::query(INSERT_EVENT)
+            .bind(&event.idempotency_key)
+            .bind(&event.subject)
+            .bind(&event.payload)
+            .bind(event.created_at)
+            .execute(&mut *connection)
+            .await
+            .map_err(backend_error)?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl<E: Entity> Repository<E> for PostgresRepository<E> {
+    async fn put(&self, entity: &E, expected: ExpectedVersion) -> Result<u64, StorageError> {
+        let mut connection = self.pool.acquire().await.map_err(backend_error)?;
+        write_entity(&mut connection, entity, expected).await
+    }
+
+    async fn put_with_events(
+        &self,
+        entity: &E,
+        expected: ExpectedVersion,
+        events: &[OutboxEvent],
+    ) -> Result<u64, StorageError> {
+        // Dropping the transaction on an early return rolls it back
+        let mut transaction = self.pool.begin().await.map_err(backend_error)?;
+        let version = write_entity(&mut transaction, entity, expected).await?;
+        insert_events(&mut transaction, events).await?;
+        transaction.commit().await.map_err(backend_error)?;
+        Ok(version)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Versioned<E>>, StorageError> {
+        let row = sqlx::query("SELECT payload, version, updated_at FROM entities WHERE kind = $1 AND id = $2")
+            .bind(E::KIND)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(backend_error)?;
+
+        row.as_ref().map(decode_row).transpose()
+    }
+
+    async fn query(
+        &self,
+        query: &EntityQuery,
+        page_token: Option<&str>,
+        limit: u32,
+    ) -> Result<QueryPage<E>, StorageError> {
+        let after = page_token.map(decode_id_token).transpose()?;
+        let limit = limit.max(1) as i64;
+
+        let statement = match query {
+            EntityQuery::BySource(source_id) => sqlx::query(QUERY_BY_SOURCE).bind(E::KIND).bind(source_id.as_str()),
+            EntityQuery::ByStatus(status) => sqlx::query(QUERY_BY_STATUS).bind(E::KIND).bind(*status),
+            EntityQuery::All => sqlx::query(QUERY_ALL).bind(E::KIND),
+        };
+
+        // Fetch one extra row to learn whether another page exists
+        let rows = statement
+            .bind(after)
+            .bind(limit + 1)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(backend_error)?;
+
+        let has_more = rows.len() as i64 > limit;
+        let items = rows
+            .iter()
+            .take(limit as usize)
+            .map(decode_row)
+            .collect::<Result<Vec<Versioned<E>>, _>>()?;
+
+        let next_page_token = match items.last() {
+            Some(last) if has_more => Some(encode_id_token(&last.entity.entity_id())),
+            _ => None,
+        };
+
+        Ok(QueryPage { items, next_page_token })
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        ping(&self.pool).await
+    }
+}
+
+/// Outbox in the `outbox` table, written by `PostgresRepository::put_with_events`
+#[derive(Debug, Clone)]
+pub struct PostgresOutbox {
+    pool: PgPool,
+}
+
+impl PostgresOutbox {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn decode_event(row: &PgRow) -> Result<OutboxEvent, StorageError> {
+    let decode = |e: sqlx::Error| StorageError::Decode(e.to_string());
+    let attempts: i32 = row.try_get("attempts").map_err(decode)?;
+    Ok(OutboxEvent {
+        idempotency_key: row.try_get("idempotency_key").map_err(decode)?,
+        subject: row.try_get("subject").map_err(decode)?,
+        payload: row.try_get("payload").map_err(decode)?,
+        created_at: row.try_get("created_at").map_err(decode)?,
+        published_at: row.try_get("published_at").map_err(decode)?,
+        attempts: attempts.max(0) as u32,
+        last_error: row.try_get("last_error").map_err(decode)?,
+    })
+}
+
+#[async_trait]
+impl OutboxStore for PostgresOutbox {
+    async fn append(&self, events: &[OutboxEvent]) -> Result<(), StorageError> {
+        let mut transaction = self.pool.begin().await.map_err(backend_error)?;
+        insert_events(&mut transaction, events).await?;
+        transaction.commit().await.map_err(backend_error)
+    }
+
+    async fn fetch_pending(&self, limit: usize) -> Result<Vec<OutboxEvent>, StorageError> {
+        let rows = sqlx::query(FETCH_PENDING)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(backend_error)?;
+        rows.iter().map(decode_event).collect()
+    }
+
+    async fn mark_published(&self, idempotency_key: &str) -> Result<(), StorageError> {
+        sqlx::query(MARK_PUBLISHED).bind(idempotency_key).execute(&self.pool).await.map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn record_failure(&self, idempotency_key: &str, error: &str) -> Result<(), StorageError> {
+        sqlx::query(RECORD_FAILURE)
+            .bind(idempotency_key)
+            .bind(error)
+            .execute(&self.pool)
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn prune_published(&self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        let result = sqlx::query(PRUNE_PUBLISHED).bind(cutoff).execute(&self.pool).await.map_err(backend_error)?;
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_embedded() {
+        let versions: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+        assert_eq!(versions, vec![1, 2]);
+        let sql: Vec<&str> = MIGRATOR.iter().map(|migration| migration.sql.as_ref()).collect();
+        assert!(sql[0].contains("CREATE TABLE IF NOT EXISTS entities"));
+        assert!(sql[1].contains("CREATE TABLE IF NOT EXISTS outbox"));
+    }
+
+    #[test]
+    fn test_page_token_round_trip() {
+        let token = encode_id_token("thm-42");
+        assert_eq!(decode_id_token(&token).unwrap(), "thm-42");
+        assert!(matches!(decode_id_token("not-hex"), Err(StorageError::InvalidPageToken(_))));
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use aws_sdk_dynamodb::Client as DynamoClient;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+
+use crate::{
+    dynamo, postgres, DynamoOutbox, DynamoRepository, Entity, FileOutbox, FileRepository, InMemoryRepository,
+    OutboxStore, PostgresOutbox, PostgresRepository, Repository, StorageError,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    DynamoDb,
+    Postgres,
+    /// One JSON file per entity under `data_dir`, for the CLI
+    Local,
+}
+
+impl FromStr for StorageBackend {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dynamodb" | "dynamo" => Ok(StorageBackend::DynamoDb),
+            "postgres" | "postgresql" => Ok(StorageBackend::Postgres),
+            "local" | "file" => Ok(StorageBackend::Local),
+            other => Err(StorageError::Backend(format!("Unknown storage backend: {}", other))),
+        }
+    }
+}
+
+/// Backend selection shared by every service that persists entities
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StorageSettings {
+    pub backend: StorageBackend,
+    /// DynamoDB table holding all entity kinds
+    pub table_name: String,
+    /// Postgres connection string; required for the Postgres backend
+    pub database_url: Option<String>,
+    pub max_connections: u32,
+    /// Root directory; required for the local backend
+    #[serde(default)]
+    pub data_dir: Option<PathBuf>,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::DynamoDb,
+            table_name: dynamo::DEFAULT_TABLE_NAME.to_string(),
+            database_url: None,
+            max_connections: 10,
+            data_dir: None,
+        }
+    }
+}
+
+// Connection strings usually embed credentials, and service configs are logged
+impl fmt::Debug for StorageSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StorageSettings")
+            .field("backend", &self.backend)
+            .field("table_name", &self.table_name)
+            .field("database_url", &self.database_url.as_ref().map(|_| "<redacted>"))
+            .field("max_connections", &self.max_connections)
+            .field("data_dir", &self.data_dir)
+            .finish()
+    }
+}
+
+/// A connection to the configured backend, handing out repositories for
+/// any entity kind over the same table or pool
+#[derive(Clone)]
+pub enum EntityStore {
+    DynamoDb { client: DynamoClient, table_name: String },
+    Postgres(PgPool),
+    Local(PathBuf),
+}
+
+impl EntityStore {
+    pub async fn connect(settings: &StorageSettings) -> Result<Self, StorageError> {
+        match settings.backend {
+            StorageBackend::DynamoDb => {
+                let aws_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Ok(EntityStore::DynamoDb {
+                    client: DynamoClient::new(&aws_config),
+                    table_name: settings.table_name.clone(),
+                })
+            }
+            StorageBackend::Postgres => {
+                let database_url = settings.database_url.as_deref().ok_or_else(|| {
+                    StorageError::Backend("database_url is required for the postgres backend".to_string())
+                })?;
+                Ok(EntityStore::Postgres(postgres::connect(database_url, settings.max_connections).await?))
+            }
+            StorageBackend::Local => {
+                let data_dir = settings.data_dir.clone().ok_or_else(|| {
+                    StorageError::Backend("data_dir is required for the local backend".to_string())
+                })?;
+                Ok(EntityStore::Local(data_dir))
+            }
+        }
+    }
+
+    /// Creates the DynamoDB table, applies pending Postgres migrations or
+    /// creates the local data directory
+    pub async fn initialize(&self) -> Result<(), StorageError> {
+        match self {
+            EntityStore::DynamoDb { client, table_name } => dynamo::ensure_entity_table(client, table_name).await,
+            EntityStore::Postgres(pool) => postgres::run_migrations(pool).await,
+            EntityStore::Local(data_dir) => std::fs::create_dir_all(data_dir)
+                .map_err(|e| StorageError::Backend(format!("{}: {}", data_dir.display(), e))),
+        }
+    }
+
+    /// DescribeTable on DynamoDB, `SELECT 1` on Postgres, a directory check
+    /// for the local backend
+    pub async fn ping(&self) -> Result<(), StorageError> {
+        match self {
+            EntityStore::DynamoDb { client, table_name } => dynamo::describe_active_table(client, table_name).await,
+            EntityStore::Postgres(pool) => postgres::ping(pool).await,
+            EntityStore::Local(data_dir) if data_dir.is_dir() => Ok(()),
+            EntityStore::Local(data_dir) => Err(StorageError::Backend(format!(
+                "{} is not a directory",
+                data_dir.display()
+            ))),
+        }
+    }
+
+    pub fn repository<E: Entity>(&self) -> Arc<dyn Repository<E>> {
+        match self {
+            EntityStore::DynamoDb { client, table_name } => Arc::new(DynamoRepository::new(client.clone(), table_name)),
+            EntityStore::Postgres(pool) => Arc::new(PostgresRepository::new(pool.clone())),
+            EntityStore::Local(data_dir) => Arc::new(FileRepository::new(data_dir)),
+        }
+    }
+
+    /// The outbox that this store's repositories append to in
+    /// `put_with_events`, for the relay to drain
+    pub fn outbox(&self) -> Arc<dyn OutboxStore> {
+        match self {
+            EntityStore::DynamoDb { client, table_name } => Arc::new(DynamoOutbox::new(client.clone(), table_name)),
+            EntityStore::Postgres(pool) => Arc::new(PostgresOutbox::new(pool.clone())),
+            EntityStore::Local(data_dir) => Arc::new(FileOutbox::new(data_dir)),
+        }
+    }
+}
+
+/// A repository on the configured backend for services whose persistence
+/// is optional; without settings, entities live in process memory and are
+/// lost on restart
+pub async fn repository_or_memory<E: Entity>(
+    settings: Option<&StorageSettings>,
+) -> Result<Arc<dyn Repository<E>>, StorageError> {
+    match settings {
+        Some(settings) => Ok(EntityStore::connect(settings).await?.repository()),
+        None => Ok(Arc::new(InMemoryRepository::new())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!("dynamodb".parse::<StorageBackend>().unwrap(), StorageBackend::DynamoDb);
+        assert_eq!("Postgres".parse::<StorageBackend>().unwrap(), StorageBackend::Postgres);
+        assert_eq!("local".parse::<StorageBackend>().unwrap(), StorageBackend::Local);
+        assert!("mysql".parse::<StorageBackend>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_postgres_requires_database_url() {
+        let settings = StorageSettings {
+            backend: StorageBackend::Postgres,
+            database_url: None,
+            ..Default::default()
+        };
+        assert!(matches!(EntityStore::connect(&settings).await, Err(StorageError::Backend(_))));
+
+        let redacted = format!("{:?}", StorageSettings {
+            database_url: Some("postgres://user:secret@db/spec_to_proof".to_string()),
+            ..settings
+        });
+        assert!(!redacted.contains("secret"));
+    }
+}